@@ -8,7 +8,7 @@ use std::collections::HashMap;
 use std::process::Stdio;
 use std::sync::Arc;
 use tokio::process::Command;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, mpsc};
 use tokio::time::{Duration, interval};
 use tracing::{debug, error, info, warn};
 
@@ -49,6 +49,16 @@ pub struct CiRun {
     pub url: String,
 }
 
+impl CiRun {
+    /// 失敗またはタイムアウトで完了したか
+    pub fn failed(&self) -> bool {
+        matches!(
+            self.conclusion,
+            Some(CiRunConclusion::Failure | CiRunConclusion::TimedOut)
+        )
+    }
+}
+
 /// CI監視マネージャー
 #[derive(Clone)]
 pub struct CiMonitor {
@@ -58,6 +68,8 @@ pub struct CiMonitor {
     repo_path: Option<String>,
     /// ポーリング間隔（秒）
     poll_interval: u64,
+    /// 監視中に失敗した実行の送り先
+    failures: Option<mpsc::UnboundedSender<CiRun>>,
 }
 
 impl CiMonitor {
@@ -67,9 +79,16 @@ impl CiMonitor {
             runs: Arc::new(RwLock::new(HashMap::new())),
             repo_path,
             poll_interval: poll_interval.unwrap_or(30),
+            failures: None,
         }
     }
 
+    /// 監視中に失敗で完了した実行を `sender` に送る
+    pub fn with_failure_sink(mut self, sender: mpsc::UnboundedSender<CiRun>) -> Self {
+        self.failures = Some(sender);
+        self
+    }
+
     /// 最新のCI実行を取得
     pub async fn get_latest_runs(&self, limit: usize) -> Result<Vec<CiRun>, String> {
         let mut cmd = Command::new("gh");
@@ -262,6 +281,7 @@ impl CiMonitor {
         let runs = self.runs.clone();
         let poll_interval = self.poll_interval;
         let repo_path = self.repo_path.clone();
+        let failures = self.failures.clone();
 
         tokio::spawn(async move {
             let monitor = CiMonitor::new(repo_path, Some(poll_interval));
@@ -290,6 +310,11 @@ impl CiMonitor {
                                         "CI run {} completed with conclusion: {:?}",
                                         run.id, run.conclusion
                                     );
+                                    if run.failed()
+                                        && let Some(failures) = &failures
+                                    {
+                                        let _ = failures.send(run.clone());
+                                    }
                                 }
                             }
 
@@ -311,6 +336,14 @@ impl CiMonitor {
         let runs = self.runs.read().await;
         runs.values().cloned().collect()
     }
+
+    /// キャッシュされたCI実行を消去し、消去した件数を返す
+    pub async fn clear_cache(&self) -> usize {
+        let mut runs = self.runs.write().await;
+        let count = runs.len();
+        runs.clear();
+        count
+    }
 }
 
 #[cfg(test)]
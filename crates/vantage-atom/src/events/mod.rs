@@ -1,11 +1,18 @@
+use crate::settings::SettingChange;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 use tracing::debug;
+use vantage_persistence::TemplateChange;
 
 // Event system for process lifecycle management
 
+/// 保持するイベント履歴の件数（デバッグバンドル用）
+pub const EVENT_HISTORY_CAPACITY: usize = 1000;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum EventType {
@@ -15,6 +22,36 @@ pub enum EventType {
     ProcessRecovered,
     ProcessCreated,
     ProcessRemoved,
+    ProcessIdle,
+    /// ヘルスチェックのしきい値（エラー率・stderrの割合）の超過
+    ProcessUnhealthy,
+    /// SIGSTOPによる一時停止
+    ProcessPaused,
+    /// 一時停止からの再開
+    ProcessResumed,
+    CrashLoopDetected,
+    /// 再起動ポリシーによる再起動の予定
+    RestartScheduled,
+    /// 再起動ポリシーによる再起動
+    ProcessRestarted,
+    /// 再起動ポリシーの回数の上限に達した
+    RestartLimitReached,
+    /// 実体と一致しなくなった状態の訂正（PIDの消失や再利用）
+    ProcessStateCorrected,
+    /// カオステストによる障害注入
+    ChaosInjected,
+    /// メンテナンスウィンドウの開始
+    MaintenanceStarted,
+    /// メンテナンスウィンドウの終了
+    MaintenanceEnded,
+    /// 起動のために機密の環境変数を復号した
+    SensitiveEnvAccessed,
+    /// 外部で起動したプロセスを引き取った
+    ProcessAdopted,
+    /// 読み込めないスナップショットの代わりに前の世代（`.bak`）から復元した
+    SnapshotRecovered,
+    /// 定期実行の時刻になった（起動しなかった場合も含む）
+    ScheduledRun,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +83,11 @@ impl ProcessEvent {
 #[derive(Clone)]
 pub struct EventSystem {
     sender: broadcast::Sender<ProcessEvent>,
+    history: Arc<Mutex<VecDeque<ProcessEvent>>>,
+    /// データベース上のテンプレートの変更（`live-query` フィーチャーで購読）
+    template_changes: broadcast::Sender<TemplateChange>,
+    /// 設定の変更（変わったキーごと）
+    setting_changes: broadcast::Sender<SettingChange>,
 }
 
 impl Default for EventSystem {
@@ -57,13 +99,27 @@ impl Default for EventSystem {
 impl EventSystem {
     pub fn new() -> Self {
         let (sender, _) = broadcast::channel(100);
-        Self { sender }
+        let (template_changes, _) = broadcast::channel(100);
+        let (setting_changes, _) = broadcast::channel(100);
+        Self {
+            sender,
+            history: Arc::new(Mutex::new(VecDeque::with_capacity(EVENT_HISTORY_CAPACITY))),
+            template_changes,
+            setting_changes,
+        }
     }
 
     pub async fn emit(&self, event: ProcessEvent) -> Result<()> {
         debug!("Emitting event: {:?}", event.event_type);
 
         // メモリ内でのみイベントを保持（データベース記録は削除）
+        {
+            let mut history = self.history.lock().unwrap();
+            if history.len() >= EVENT_HISTORY_CAPACITY {
+                history.pop_front();
+            }
+            history.push_back(event.clone());
+        }
 
         // ブロードキャスト（リスナーがいなくてもエラーにしない）
         let _ = self.sender.send(event);
@@ -75,6 +131,38 @@ impl EventSystem {
         self.sender.subscribe()
     }
 
+    /// テンプレートの変更を送る側（購読タスクに渡す）
+    pub fn template_change_sender(&self) -> broadcast::Sender<TemplateChange> {
+        self.template_changes.clone()
+    }
+
+    pub fn subscribe_template_changes(&self) -> broadcast::Receiver<TemplateChange> {
+        self.template_changes.subscribe()
+    }
+
+    /// 設定の変更を送る（リスナーがいなくてもエラーにしない）
+    pub fn emit_setting_changes(&self, changes: Vec<SettingChange>) {
+        for change in changes {
+            debug!("Setting changed: {}", change.key);
+            let _ = self.setting_changes.send(change);
+        }
+    }
+
+    pub fn subscribe_setting_changes(&self) -> broadcast::Receiver<SettingChange> {
+        self.setting_changes.subscribe()
+    }
+
+    /// 最近のイベント（古い順、`since` 以降のみ）
+    pub fn recent_events(&self, since: Option<DateTime<Utc>>) -> Vec<ProcessEvent> {
+        self.history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| since.is_none_or(|since| event.timestamp >= since))
+            .cloned()
+            .collect()
+    }
+
     pub async fn emit_process_started(&self, process_id: String, pid: Option<u32>) -> Result<()> {
         let mut context = serde_json::Map::new();
         if let Some(pid) = pid {
@@ -90,10 +178,15 @@ impl EventSystem {
         .await
     }
 
+    /// `stop_requested` は停止要求による終了か（正常終了時の連鎖の判定に使う）
+    ///
+    /// 異常終了の場合は `failure` にstderrの末尾などをまとめたレポートを含める。
     pub async fn emit_process_stopped(
         &self,
         process_id: String,
         exit_code: Option<i32>,
+        stop_requested: bool,
+        failure: Option<&crate::process::FailureReport>,
     ) -> Result<()> {
         let mut context = serde_json::Map::new();
         if let Some(code) = exit_code {
@@ -102,6 +195,13 @@ impl EventSystem {
                 serde_json::Value::Number(code.into()),
             );
         }
+        context.insert(
+            "stop_requested".to_string(),
+            serde_json::Value::Bool(stop_requested),
+        );
+        if let Some(failure) = failure {
+            context.insert("failure".to_string(), serde_json::json!(failure));
+        }
 
         self.emit(ProcessEvent::new(
             EventType::ProcessStopped,
@@ -124,4 +224,281 @@ impl EventSystem {
         ))
         .await
     }
+
+    pub async fn emit_process_idle(
+        &self,
+        process_id: String,
+        idle_seconds: u64,
+        action: crate::process::IdleAction,
+    ) -> Result<()> {
+        let context = serde_json::json!({
+            "idle_seconds": idle_seconds,
+            "action": action,
+        });
+
+        self.emit(ProcessEvent::new(
+            EventType::ProcessIdle,
+            process_id,
+            Some(context),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_process_unhealthy(
+        &self,
+        process_id: String,
+        failures: Vec<String>,
+        action: crate::process::HealthAction,
+    ) -> Result<()> {
+        let context = serde_json::json!({
+            "failures": failures,
+            "action": action,
+        });
+
+        self.emit(ProcessEvent::new(
+            EventType::ProcessUnhealthy,
+            process_id,
+            Some(context),
+            None,
+        ))
+        .await
+    }
+
+    /// ヘルスチェックの異常からの回復
+    pub async fn emit_process_recovered(
+        &self,
+        process_id: String,
+        unhealthy_seconds: u64,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::ProcessRecovered,
+            process_id,
+            Some(serde_json::json!({ "unhealthy_seconds": unhealthy_seconds })),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_process_paused(&self, process_id: String, pid: u32) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::ProcessPaused,
+            process_id,
+            Some(serde_json::json!({ "pid": pid })),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_process_resumed(
+        &self,
+        process_id: String,
+        pid: u32,
+        paused_seconds: u64,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::ProcessResumed,
+            process_id,
+            Some(serde_json::json!({ "pid": pid, "paused_seconds": paused_seconds })),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_process_state_corrected(
+        &self,
+        correction: &crate::process::StateCorrection,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::ProcessStateCorrected,
+            correction.process_id.clone(),
+            Some(serde_json::json!({
+                "pid": correction.pid,
+                "previous_state": correction.previous_state,
+                "status": correction.status,
+            })),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_chaos_action(&self, record: &crate::process::ChaosRecord) -> Result<()> {
+        let context = serde_json::json!({
+            "chaos": true,
+            "action": record.action,
+            "schedule_id": record.schedule_id,
+        });
+
+        self.emit(ProcessEvent::new(
+            EventType::ChaosInjected,
+            record.process_id.clone(),
+            Some(context),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_maintenance_started(
+        &self,
+        process_id: String,
+        maintenance: &crate::process::ActiveMaintenance,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::MaintenanceStarted,
+            process_id,
+            Some(serde_json::json!(maintenance)),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_maintenance_ended(&self, process_id: String, window: String) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::MaintenanceEnded,
+            process_id,
+            Some(serde_json::json!({ "name": window })),
+            None,
+        ))
+        .await
+    }
+
+    /// 復号した変数の名前だけを記録する（値は含めない）
+    pub async fn emit_sensitive_env_accessed(
+        &self,
+        process_id: String,
+        names: Vec<String>,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::SensitiveEnvAccessed,
+            process_id,
+            Some(serde_json::json!({ "names": names })),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_process_adopted(
+        &self,
+        external: &crate::process::ExternalProcess,
+        auto: bool,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::ProcessAdopted,
+            external.process_id.clone(),
+            Some(serde_json::json!({
+                "pid": external.pid,
+                "command_line": external.command_line,
+                "auto": auto,
+            })),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_scheduled_run(
+        &self,
+        process_id: String,
+        schedule_id: &str,
+        outcome: &crate::process::ScheduleOutcome,
+    ) -> Result<()> {
+        let mut context = serde_json::json!(outcome);
+        context["schedule_id"] = serde_json::json!(schedule_id);
+        self.emit(ProcessEvent::new(
+            EventType::ScheduledRun,
+            process_id,
+            Some(context),
+            None,
+        ))
+        .await
+    }
+
+    /// プロセスに紐付かないため `process_id` は空
+    pub async fn emit_snapshot_recovered(
+        &self,
+        fallback: &vantage_persistence::SnapshotFallback,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::SnapshotRecovered,
+            String::new(),
+            Some(serde_json::json!({
+                "path": fallback.path,
+                "backup": fallback.backup,
+                "error": fallback.error,
+            })),
+            None,
+        ))
+        .await
+    }
+
+    /// 再起動ポリシーによる再起動を予定した（`attempt` は1から）
+    pub async fn emit_restart_scheduled(
+        &self,
+        process_id: String,
+        attempt: u32,
+        max_retries: Option<u32>,
+        delay: std::time::Duration,
+        exit_code: Option<i32>,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::RestartScheduled,
+            process_id,
+            Some(serde_json::json!({
+                "attempt": attempt,
+                "max_retries": max_retries,
+                "delay_ms": delay.as_millis() as u64,
+                "exit_code": exit_code,
+            })),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_process_restarted(
+        &self,
+        process_id: String,
+        attempt: u32,
+        pid: u32,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::ProcessRestarted,
+            process_id,
+            Some(serde_json::json!({ "attempt": attempt, "pid": pid })),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_restart_limit_reached(
+        &self,
+        process_id: String,
+        attempts: u32,
+        exit_code: Option<i32>,
+    ) -> Result<()> {
+        self.emit(ProcessEvent::new(
+            EventType::RestartLimitReached,
+            process_id,
+            Some(serde_json::json!({ "attempts": attempts, "exit_code": exit_code })),
+            None,
+        ))
+        .await
+    }
+
+    pub async fn emit_crash_loop_detected(
+        &self,
+        process_id: String,
+        info: &crate::process::CrashLoopInfo,
+    ) -> Result<()> {
+        let context = serde_json::json!({
+            "recent_exits": info.recent_exits,
+            "suggestion": info.suggestion,
+        });
+
+        self.emit(ProcessEvent::new(
+            EventType::CrashLoopDetected,
+            process_id,
+            Some(context),
+            None,
+        ))
+        .await
+    }
 }
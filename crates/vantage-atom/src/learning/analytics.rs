@@ -0,0 +1,371 @@
+//! アシスタントの操作の効果の集計
+//!
+//! MCPツールの呼び出しと、適用・却下した提案を記録し、その後の対象プロセスの結果と突き合わせます。
+//! 操作から `window` の間に対象のプロセスが異常終了・クラッシュループ・ヘルスチェックの異常に
+//! なれば失敗、何も起きずに `window` が過ぎれば安定とみなします。
+//! 失敗の判定には通知の受信箱の記録を使うため、停止の要求やメンテナンスウィンドウ中の終了は
+//! 失敗に数えません。記録はサーバーのメモリ上にあり、古いものから `MAX_RECORDS` 件を超えた分を捨てます。
+
+use super::Suggestion;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use vantage_persistence::{Notification, NotificationKind};
+
+/// 残しておく記録の件数（ツールの呼び出しと提案のそれぞれ）
+pub const MAX_RECORDS: usize = 5000;
+/// 結果を判定する期間の既定値（分）
+pub const DEFAULT_WINDOW_MINUTES: i64 = 10;
+
+/// ツールの呼び出しの記録
+#[derive(Debug, Clone)]
+struct ToolCall {
+    tool: String,
+    process_id: Option<String>,
+    at: DateTime<Utc>,
+    succeeded: bool,
+    /// プロセスを変更するツールか（結果の判定の対象）
+    mutating: bool,
+}
+
+/// 提案の適用・却下の記録
+#[derive(Debug, Clone)]
+struct SuggestionRecord {
+    provider: String,
+    action: &'static str,
+    process_id: Option<String>,
+    at: DateTime<Utc>,
+    applied: bool,
+}
+
+/// 操作の後の対象プロセスの結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Stable,
+    Failed,
+    Pending,
+}
+
+/// 結果の件数と成功率
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OutcomeCounts {
+    /// 期間内に失敗しなかった操作
+    pub stable: u32,
+    /// 期間内に対象のプロセスが失敗した操作
+    pub failed: u32,
+    /// 期間がまだ過ぎていない操作
+    pub pending: u32,
+    /// 判定済みの操作のうち安定だった割合（判定済みのものが無ければNone）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success_rate: Option<f64>,
+}
+
+impl OutcomeCounts {
+    fn add(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Stable => self.stable += 1,
+            Outcome::Failed => self.failed += 1,
+            Outcome::Pending => self.pending += 1,
+        }
+        let decided = self.stable + self.failed;
+        self.success_rate = (decided > 0).then(|| self.stable as f64 / decided as f64);
+    }
+}
+
+/// ツールごとの集計
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolEffectiveness {
+    pub tool: String,
+    pub calls: u32,
+    /// エラーを返した呼び出し
+    pub errors: u32,
+    /// プロセスを対象にした、プロセスを変更する呼び出しの結果
+    pub outcomes: OutcomeCounts,
+}
+
+/// 提案のプロバイダーとアクションの種類ごとの集計
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestionEffectiveness {
+    pub provider: String,
+    pub action: String,
+    pub applied: u32,
+    pub dismissed: u32,
+    /// 適用した提案の結果（プロセスを対象にしたもののみ）
+    pub outcomes: OutcomeCounts,
+}
+
+/// 集計の結果
+#[derive(Debug, Clone, Serialize)]
+pub struct AssistantAnalytics {
+    pub window_minutes: i64,
+    /// 最も古い記録の時刻
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    /// 呼び出しの多い順
+    pub tools: Vec<ToolEffectiveness>,
+    /// 適用・却下の多い順
+    pub suggestions: Vec<SuggestionEffectiveness>,
+}
+
+/// ツールの呼び出しと提案の記録
+#[derive(Debug, Clone, Default)]
+pub struct AnalyticsRecorder {
+    tool_calls: Arc<RwLock<VecDeque<ToolCall>>>,
+    suggestions: Arc<RwLock<VecDeque<SuggestionRecord>>>,
+}
+
+fn push_capped<T>(records: &mut VecDeque<T>, record: T) {
+    if records.len() >= MAX_RECORDS {
+        records.pop_front();
+    }
+    records.push_back(record);
+}
+
+impl AnalyticsRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ツールの呼び出しを記録する
+    pub async fn record_tool_call(
+        &self,
+        tool: &str,
+        process_id: Option<String>,
+        succeeded: bool,
+        mutating: bool,
+    ) {
+        let call = ToolCall {
+            tool: tool.to_string(),
+            process_id,
+            at: Utc::now(),
+            succeeded,
+            mutating,
+        };
+        push_capped(&mut *self.tool_calls.write().await, call);
+    }
+
+    /// 提案の適用・却下を記録する
+    pub async fn record_suggestion(&self, suggestion: &Suggestion, applied: bool) {
+        let record = SuggestionRecord {
+            provider: suggestion.provider.clone(),
+            action: suggestion.action.kind(),
+            process_id: suggestion.action.process_id().map(str::to_string),
+            at: Utc::now(),
+            applied,
+        };
+        push_capped(&mut *self.suggestions.write().await, record);
+    }
+
+    /// 記録を失敗の通知と突き合わせて集計する
+    pub async fn summarize(
+        &self,
+        failures: &[Notification],
+        window: Duration,
+        now: DateTime<Utc>,
+    ) -> AssistantAnalytics {
+        let tool_calls = self.tool_calls.read().await;
+        let suggestions = self.suggestions.read().await;
+        summarize(tool_calls.iter(), suggestions.iter(), failures, window, now)
+    }
+}
+
+/// 対象のプロセスが失敗したことを示す通知か
+fn is_failure(notification: &Notification) -> bool {
+    matches!(
+        notification.kind,
+        NotificationKind::ProcessFailed
+            | NotificationKind::CrashLoop
+            | NotificationKind::ProcessUnhealthy
+    )
+}
+
+fn outcome(
+    process_id: &str,
+    at: DateTime<Utc>,
+    failures: &[Notification],
+    window: Duration,
+    now: DateTime<Utc>,
+) -> Outcome {
+    let until = at + window;
+    let failed = failures.iter().any(|n| {
+        is_failure(n)
+            && n.process_id.as_deref() == Some(process_id)
+            && n.created_at > at
+            && n.created_at <= until
+    });
+    if failed {
+        Outcome::Failed
+    } else if now >= until {
+        Outcome::Stable
+    } else {
+        Outcome::Pending
+    }
+}
+
+fn summarize<'a>(
+    tool_calls: impl Iterator<Item = &'a ToolCall>,
+    suggestions: impl Iterator<Item = &'a SuggestionRecord>,
+    failures: &[Notification],
+    window: Duration,
+    now: DateTime<Utc>,
+) -> AssistantAnalytics {
+    let mut since: Option<DateTime<Utc>> = None;
+    let mut oldest = |at: DateTime<Utc>| {
+        since = Some(since.map_or(at, |since| since.min(at)));
+    };
+
+    let mut tools: BTreeMap<&str, ToolEffectiveness> = BTreeMap::new();
+    for call in tool_calls {
+        oldest(call.at);
+        let entry = tools
+            .entry(call.tool.as_str())
+            .or_insert_with(|| ToolEffectiveness {
+                tool: call.tool.clone(),
+                calls: 0,
+                errors: 0,
+                outcomes: OutcomeCounts::default(),
+            });
+        entry.calls += 1;
+        if !call.succeeded {
+            entry.errors += 1;
+            continue;
+        }
+        if let (true, Some(process_id)) = (call.mutating, &call.process_id) {
+            entry
+                .outcomes
+                .add(outcome(process_id, call.at, failures, window, now));
+        }
+    }
+
+    let mut by_action: BTreeMap<(&str, &str), SuggestionEffectiveness> = BTreeMap::new();
+    for record in suggestions {
+        oldest(record.at);
+        let entry = by_action
+            .entry((record.provider.as_str(), record.action))
+            .or_insert_with(|| SuggestionEffectiveness {
+                provider: record.provider.clone(),
+                action: record.action.to_string(),
+                applied: 0,
+                dismissed: 0,
+                outcomes: OutcomeCounts::default(),
+            });
+        if !record.applied {
+            entry.dismissed += 1;
+            continue;
+        }
+        entry.applied += 1;
+        if let Some(process_id) = &record.process_id {
+            entry
+                .outcomes
+                .add(outcome(process_id, record.at, failures, window, now));
+        }
+    }
+
+    let mut tools: Vec<ToolEffectiveness> = tools.into_values().collect();
+    tools.sort_by_key(|tool| std::cmp::Reverse(tool.calls));
+    let mut suggestions: Vec<SuggestionEffectiveness> = by_action.into_values().collect();
+    suggestions.sort_by_key(|s| std::cmp::Reverse(s.applied + s.dismissed));
+
+    AssistantAnalytics {
+        window_minutes: window.num_minutes(),
+        since,
+        tools,
+        suggestions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(tool: &str, process_id: &str, at: DateTime<Utc>) -> ToolCall {
+        ToolCall {
+            tool: tool.to_string(),
+            process_id: Some(process_id.to_string()),
+            at,
+            succeeded: true,
+            mutating: true,
+        }
+    }
+
+    fn failure(process_id: &str, at: DateTime<Utc>) -> Notification {
+        Notification {
+            notification_id: process_id.to_string(),
+            kind: NotificationKind::ProcessFailed,
+            title: String::new(),
+            message: String::new(),
+            process_id: Some(process_id.to_string()),
+            link: None,
+            created_at: at,
+            read: false,
+            read_at: None,
+            route: None,
+        }
+    }
+
+    #[test]
+    fn test_outcomes_follow_failures_within_the_window() {
+        let now = Utc::now();
+        let window = Duration::minutes(10);
+        let calls = [
+            // 5分後に失敗した
+            call("restart_process", "api", now - Duration::minutes(30)),
+            // 失敗は期間の後なので安定
+            call("restart_process", "worker", now - Duration::minutes(30)),
+            // 期間がまだ過ぎていない
+            call("restart_process", "web", now - Duration::minutes(2)),
+            ToolCall {
+                succeeded: false,
+                ..call("restart_process", "db", now - Duration::minutes(30))
+            },
+            ToolCall {
+                mutating: false,
+                ..call("get_process_status", "api", now - Duration::minutes(40))
+            },
+        ];
+        let suggestions = [
+            SuggestionRecord {
+                provider: "learning".to_string(),
+                action: "start_process",
+                process_id: Some("worker".to_string()),
+                at: now - Duration::minutes(30),
+                applied: true,
+            },
+            SuggestionRecord {
+                provider: "learning".to_string(),
+                action: "start_process",
+                process_id: Some("web".to_string()),
+                at: now - Duration::minutes(30),
+                applied: false,
+            },
+        ];
+        let failures = [
+            failure("api", now - Duration::minutes(25)),
+            failure("worker", now - Duration::minutes(5)),
+        ];
+
+        let analytics = summarize(calls.iter(), suggestions.iter(), &failures, window, now);
+        assert_eq!(analytics.window_minutes, 10);
+        assert_eq!(analytics.since, Some(now - Duration::minutes(40)));
+
+        let restart = &analytics.tools[0];
+        assert_eq!(restart.tool, "restart_process");
+        assert_eq!((restart.calls, restart.errors), (4, 1));
+        assert_eq!(
+            (
+                restart.outcomes.stable,
+                restart.outcomes.failed,
+                restart.outcomes.pending
+            ),
+            (1, 1, 1)
+        );
+        assert_eq!(restart.outcomes.success_rate, Some(0.5));
+        assert_eq!(analytics.tools[1].outcomes.success_rate, None);
+
+        let start = &analytics.suggestions[0];
+        assert_eq!((start.applied, start.dismissed), (1, 1));
+        assert_eq!(start.outcomes.success_rate, Some(1.0));
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use chrono::{DateTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -7,6 +8,18 @@ use tracing::{debug, error, info};
 
 // Learning engine for process behavior patterns
 use crate::events::{EventSystem, EventType, ProcessEvent};
+use crate::process::CrashLoopInfo;
+
+pub mod analytics;
+pub mod providers;
+pub mod watch;
+
+pub use analytics::{AnalyticsRecorder, AssistantAnalytics};
+pub use providers::{SuggestionContext, SuggestionPipeline, SuggestionProvider};
+pub use watch::{SuggestionNotification, SuggestionWatch, SuggestionWatcher};
+
+/// 直前に起動したプロセスとの連続起動とみなす間隔（秒）
+const SEQUENCE_WINDOW_SECS: i64 = 300;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessPattern {
@@ -30,6 +43,12 @@ pub struct Suggestion {
     pub confidence: f64,
     pub action: SuggestedAction,
     pub reason: String,
+    /// 提案元のプロバイダー名
+    #[serde(default)]
+    pub provider: String,
+    /// プロバイダーの重みを反映したスコア（並び順に使用）
+    #[serde(default)]
+    pub score: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,12 +58,143 @@ pub enum SuggestedAction {
     StopProcess { process_id: String },
     RestartProcess { process_id: String },
     CreateProcess { command: String, args: Vec<String> },
+    InvestigateProcess { process_id: String },
+    InvestigateCiRun { run_id: u64 },
+}
+
+/// 提案パターンごとの採用・却下の記録
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SuggestionFeedback {
+    pub accepted: u32,
+    pub dismissed: u32,
 }
 
+impl SuggestionFeedback {
+    /// 信頼度に掛ける係数（0.5〜1.5）
+    ///
+    /// 記録がない場合は1.0で、採用率（ラプラス補正あり）に応じて増減する。
+    pub fn confidence_factor(&self) -> f64 {
+        let rate = (self.accepted as f64 + 1.0) / ((self.accepted + self.dismissed) as f64 + 2.0);
+        0.5 + rate
+    }
+}
+
+impl SuggestedAction {
+    /// フィードバックの集計に使うキー
+    pub fn key(&self) -> String {
+        match self {
+            Self::StartProcess { process_id } => format!("start_process:{process_id}"),
+            Self::StopProcess { process_id } => format!("stop_process:{process_id}"),
+            Self::RestartProcess { process_id } => format!("restart_process:{process_id}"),
+            Self::CreateProcess { command, args } => {
+                format!("create_process:{} {}", command, args.join(" "))
+            }
+            Self::InvestigateProcess { process_id } => {
+                format!("investigate_process:{process_id}")
+            }
+            Self::InvestigateCiRun { run_id } => format!("investigate_ci_run:{run_id}"),
+        }
+    }
+
+    /// アクションの種類（集計に使う）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::StartProcess { .. } => "start_process",
+            Self::StopProcess { .. } => "stop_process",
+            Self::RestartProcess { .. } => "restart_process",
+            Self::CreateProcess { .. } => "create_process",
+            Self::InvestigateProcess { .. } => "investigate_process",
+            Self::InvestigateCiRun { .. } => "investigate_ci_run",
+        }
+    }
+
+    /// 対象のプロセス（プロセスを対象にしないアクションはNone）
+    pub fn process_id(&self) -> Option<&str> {
+        match self {
+            Self::StartProcess { process_id }
+            | Self::StopProcess { process_id }
+            | Self::RestartProcess { process_id }
+            | Self::InvestigateProcess { process_id } => Some(process_id),
+            Self::CreateProcess { .. } | Self::InvestigateCiRun { .. } => None,
+        }
+    }
+
+    /// 実行内容の説明（確認用）
+    pub fn describe(&self) -> String {
+        match self {
+            Self::StartProcess { process_id } => format!("Start process '{process_id}'"),
+            Self::StopProcess { process_id } => format!("Stop process '{process_id}'"),
+            Self::RestartProcess { process_id } => format!("Restart process '{process_id}'"),
+            Self::CreateProcess { command, args } => {
+                format!(
+                    "Register a process running '{} {}'",
+                    command,
+                    args.join(" ")
+                )
+            }
+            Self::InvestigateProcess { process_id } => {
+                format!("Show recent output of process '{process_id}'")
+            }
+            Self::InvestigateCiRun { run_id } => format!("Show failed logs of CI run {run_id}"),
+        }
+    }
+}
+
+impl Suggestion {
+    /// フィードバックを集計する提案パターンのキー（プロバイダーとアクション）
+    pub fn feedback_key(&self) -> String {
+        format!("{}:{}", self.provider, self.action.key())
+    }
+
+    pub fn new(message: String, confidence: f64, action: SuggestedAction, reason: String) -> Self {
+        Self {
+            message,
+            confidence,
+            action,
+            reason,
+            provider: String::new(),
+            score: confidence,
+        }
+    }
+
+    /// クラッシュループ中のプロセスに対する調査の提案
+    pub fn investigate_crash_loop(process_id: &str, info: &CrashLoopInfo) -> Self {
+        let exit_codes: Vec<String> = info
+            .recent_exits
+            .iter()
+            .map(|e| match (e.exit_code, &e.error) {
+                (Some(code), _) => code.to_string(),
+                (None, Some(error)) => error.clone(),
+                (None, None) => "signal".to_string(),
+            })
+            .collect();
+
+        Self::new(
+            format!(
+                "「{process_id}」がクラッシュループ中です。再起動する前にログを確認してください。"
+            ),
+            1.0,
+            SuggestedAction::InvestigateProcess {
+                process_id: process_id.to_string(),
+            },
+            format!("直近の終了コード: {}", exit_codes.join(", ")),
+        )
+    }
+}
+
+/// 直前に起動したプロセスとその起動時刻
+type LastStart = Option<(String, DateTime<Utc>)>;
+
 #[derive(Clone)]
 pub struct LearningEngine {
     event_system: Arc<EventSystem>,
     patterns: Arc<RwLock<HashMap<String, ProcessPattern>>>,
+    /// プロセスごとの時間帯別（0-23時）起動回数
+    start_hours: Arc<RwLock<HashMap<String, [u32; 24]>>>,
+    /// 直前に起動したプロセス
+    last_start: Arc<RwLock<LastStart>>,
+    /// 提案パターンごとのフィードバック
+    feedback: Arc<RwLock<HashMap<String, SuggestionFeedback>>>,
 }
 
 impl LearningEngine {
@@ -52,7 +202,50 @@ impl LearningEngine {
         Self {
             event_system,
             patterns: Arc::new(RwLock::new(HashMap::new())),
+            start_hours: Arc::new(RwLock::new(HashMap::new())),
+            last_start: Arc::new(RwLock::new(None)),
+            feedback: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 提案の採用・却下を記録
+    pub async fn record_feedback(&self, suggestion: &Suggestion, accepted: bool) {
+        let key = suggestion.feedback_key();
+        let mut feedback = self.feedback.write().await;
+        let entry = feedback.entry(key.clone()).or_default();
+        if accepted {
+            entry.accepted += 1;
+        } else {
+            entry.dismissed += 1;
         }
+        debug!(
+            "Recorded suggestion feedback for '{}': {} accepted, {} dismissed",
+            key, entry.accepted, entry.dismissed
+        );
+    }
+
+    /// フィードバックを反映した信頼度
+    pub async fn adjusted_confidence(&self, suggestion: &Suggestion) -> f64 {
+        let factor = self
+            .feedback
+            .read()
+            .await
+            .get(&suggestion.feedback_key())
+            .map(SuggestionFeedback::confidence_factor)
+            .unwrap_or(1.0);
+        (suggestion.confidence * factor).clamp(0.0, 1.0)
+    }
+
+    /// 指定した時間帯に `min_count` 回以上起動されたプロセスと回数
+    pub async fn frequent_starts_at_hour(&self, hour: u32, min_count: u32) -> Vec<(String, u32)> {
+        let start_hours = self.start_hours.read().await;
+        let mut result: Vec<(String, u32)> = start_hours
+            .iter()
+            .map(|(id, hours)| (id.clone(), hours[(hour % 24) as usize]))
+            .filter(|(_, count)| *count >= min_count)
+            .collect();
+        result.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        result
     }
 
     pub async fn start_learning(&self) -> Result<()> {
@@ -128,10 +321,14 @@ impl LearningEngine {
             EventType::ProcessStopped => {
                 self.learn_process_stop(&event.process_id).await?;
             }
-            EventType::ProcessError => {
+            EventType::ProcessError | EventType::RestartLimitReached => {
                 self.learn_process_error(&event.process_id, event.context)
                     .await?;
             }
+            EventType::ProcessRestarted => {
+                self.learn_process_restart(&event.process_id, event.context)
+                    .await?;
+            }
             _ => {}
         }
 
@@ -152,6 +349,32 @@ impl LearningEngine {
             });
 
         pattern.confidence = (pattern.confidence + 0.05).min(1.0);
+        drop(patterns);
+
+        // 時間帯別の起動回数
+        let now = Utc::now();
+        let hour = now.with_timezone(&chrono::Local).hour() as usize;
+        self.start_hours
+            .write()
+            .await
+            .entry(process_id.to_string())
+            .or_insert([0; 24])[hour] += 1;
+
+        // 直前のプロセスに続けて起動されたものを次のプロセスとして記録
+        let previous = self
+            .last_start
+            .write()
+            .await
+            .replace((process_id.to_string(), now));
+        if let Some((previous_id, started_at)) = previous
+            && previous_id != process_id
+            && (now - started_at).num_seconds() <= SEQUENCE_WINDOW_SECS
+            && let Some(pattern) = self.patterns.write().await.get_mut(&previous_id)
+            && !pattern.next_processes.iter().any(|p| p == process_id)
+        {
+            pattern.next_processes.push(process_id.to_string());
+        }
+
         Ok(())
     }
 
@@ -184,6 +407,32 @@ impl LearningEngine {
         Ok(())
     }
 
+    async fn learn_process_restart(
+        &self,
+        process_id: &str,
+        context: Option<serde_json::Value>,
+    ) -> Result<()> {
+        // 再起動ポリシーによる再起動の回数と最後の再起動を記録（メモリ内で管理）
+        let mut patterns = self.patterns.write().await;
+
+        if let Some(pattern) = patterns.get_mut(process_id) {
+            pattern.confidence = (pattern.confidence - 0.05).max(0.0);
+            let count = pattern
+                .context
+                .get("restart_count")
+                .and_then(|count| count.as_u64())
+                .unwrap_or(0);
+            pattern
+                .context
+                .insert("restart_count".to_string(), (count + 1).into());
+            if let Some(ctx) = context {
+                pattern.context.insert("last_restart".to_string(), ctx);
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn get_suggestions(&self, current_process: Option<&str>) -> Result<Vec<Suggestion>> {
         let mut suggestions = Vec::new();
 
@@ -195,26 +444,23 @@ impl LearningEngine {
                 && pattern.confidence > 0.6
             {
                 for next_process in &pattern.next_processes {
-                    suggestions.push(Suggestion {
-                        message: format!(
+                    suggestions.push(Suggestion::new(
+                        format!(
                             "「{process_id}」が起動しました。通常は「{next_process}」も必要です。"
                         ),
-                        confidence: pattern.confidence,
-                        action: SuggestedAction::StartProcess {
+                        pattern.confidence,
+                        SuggestedAction::StartProcess {
                             process_id: next_process.clone(),
                         },
-                        reason: format!(
+                        format!(
                             "過去のパターンから学習（信頼度: {:.0}%）",
                             pattern.confidence * 100.0
                         ),
-                    });
+                    ));
                 }
             }
         }
 
-        // 時間帯に基づく提案（簡易実装）
-        // TODO: 実際の時間パターン学習を実装
-
         Ok(suggestions)
     }
 }
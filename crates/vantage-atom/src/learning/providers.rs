@@ -0,0 +1,397 @@
+//! 提案プロバイダーとスコアリングパイプライン
+//!
+//! 各プロバイダーが共通トレイトを通して提案を返し、パイプラインが設定に従って
+//! 有効・無効の判定と重み付けを行った上でスコア順に並べます。
+
+use super::{LearningEngine, SuggestedAction, Suggestion};
+use crate::ci::{CiMonitor, CiRunConclusion, CiRunStatus};
+use crate::process::{ProcessManager, ProcessState};
+use anyhow::Result;
+use chrono::{Local, Timelike};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+use vantage_persistence::SuggestionProviderSettings;
+
+/// 時間帯パターンとみなす最小起動回数
+const TIME_PATTERN_MIN_STARTS: u32 = 3;
+/// CI状態の取得タイムアウト
+const CI_FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 提案生成時のコンテキスト
+#[derive(Debug, Clone, Default)]
+pub struct SuggestionContext {
+    pub current_process: Option<String>,
+}
+
+/// 提案プロバイダーが返すFuture
+pub type SuggestFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<Suggestion>>> + Send + 'a>>;
+
+/// 提案プロバイダー
+pub trait SuggestionProvider: Send + Sync {
+    /// 設定や提案の出所として使われる名前
+    fn name(&self) -> &'static str;
+
+    fn suggest<'a>(&'a self, context: &'a SuggestionContext) -> SuggestFuture<'a>;
+}
+
+/// 提案パイプライン
+#[derive(Default)]
+pub struct SuggestionPipeline {
+    providers: Vec<Box<dyn SuggestionProvider>>,
+    /// 採用・却下の記録を信頼度に反映する学習エンジン
+    feedback: Option<Arc<LearningEngine>>,
+}
+
+impl SuggestionPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 標準のプロバイダー構成
+    pub fn with_default_providers(
+        process_manager: ProcessManager,
+        learning_engine: Arc<LearningEngine>,
+        ci_monitor: Arc<CiMonitor>,
+    ) -> Self {
+        Self::new()
+            .with_feedback(learning_engine.clone())
+            .with_provider(LearningProvider {
+                engine: learning_engine.clone(),
+            })
+            .with_provider(TimePatternProvider {
+                engine: learning_engine,
+                process_manager: process_manager.clone(),
+            })
+            .with_provider(CiStatusProvider { ci_monitor })
+            .with_provider(DiagnosisProvider {
+                process_manager: process_manager.clone(),
+            })
+            .with_provider(IdleProvider { process_manager })
+    }
+
+    pub fn with_provider(mut self, provider: impl SuggestionProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    pub fn with_feedback(mut self, engine: Arc<LearningEngine>) -> Self {
+        self.feedback = Some(engine);
+        self
+    }
+
+    pub fn provider_names(&self) -> Vec<&'static str> {
+        self.providers.iter().map(|p| p.name()).collect()
+    }
+
+    /// 全プロバイダーを実行し、重み付けしたスコアの降順で返す
+    ///
+    /// 信頼度にはフィードバックによる補正を適用する。
+    /// 失敗したプロバイダーは警告を出して読み飛ばす。
+    pub async fn run(
+        &self,
+        context: &SuggestionContext,
+        settings: &HashMap<String, SuggestionProviderSettings>,
+    ) -> Vec<Suggestion> {
+        let mut suggestions = Vec::new();
+        for provider in &self.providers {
+            let config = settings.get(provider.name()).cloned().unwrap_or_default();
+            if !config.enabled {
+                continue;
+            }
+            match provider.suggest(context).await {
+                Ok(provided) => {
+                    for mut suggestion in provided {
+                        suggestion.provider = provider.name().to_string();
+                        if let Some(engine) = &self.feedback {
+                            suggestion.confidence = engine.adjusted_confidence(&suggestion).await;
+                        }
+                        suggestion.score = suggestion.confidence * config.weight;
+                        suggestions.push(suggestion);
+                    }
+                }
+                Err(e) => warn!("Suggestion provider '{}' failed: {}", provider.name(), e),
+            }
+        }
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        suggestions
+    }
+}
+
+/// 起動順序の学習結果に基づく提案
+pub struct LearningProvider {
+    engine: Arc<LearningEngine>,
+}
+
+impl SuggestionProvider for LearningProvider {
+    fn name(&self) -> &'static str {
+        "learning"
+    }
+
+    fn suggest<'a>(&'a self, context: &'a SuggestionContext) -> SuggestFuture<'a> {
+        Box::pin(async move {
+            self.engine
+                .get_suggestions(context.current_process.as_deref())
+                .await
+        })
+    }
+}
+
+/// 現在の時間帯によく起動されるプロセスの提案
+pub struct TimePatternProvider {
+    engine: Arc<LearningEngine>,
+    process_manager: ProcessManager,
+}
+
+impl SuggestionProvider for TimePatternProvider {
+    fn name(&self) -> &'static str {
+        "time_patterns"
+    }
+
+    fn suggest<'a>(&'a self, _context: &'a SuggestionContext) -> SuggestFuture<'a> {
+        Box::pin(async move {
+            let hour = Local::now().hour();
+            let frequent = self
+                .engine
+                .frequent_starts_at_hour(hour, TIME_PATTERN_MIN_STARTS)
+                .await;
+            if frequent.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let running: Vec<String> = self
+                .process_manager
+                .list_processes(None)
+                .await
+                .into_iter()
+                .filter(|p| matches!(p.state, ProcessState::Running { .. }))
+                .map(|p| p.id)
+                .collect();
+
+            Ok(frequent
+                .into_iter()
+                .filter(|(id, _)| !running.contains(id))
+                .map(|(id, count)| {
+                    Suggestion::new(
+                        format!("この時間帯は「{id}」をよく起動しています。"),
+                        (0.4 + count as f64 * 0.05).min(0.8),
+                        SuggestedAction::StartProcess {
+                            process_id: id.clone(),
+                        },
+                        format!("{hour}時台に{count}回起動"),
+                    )
+                })
+                .collect())
+        })
+    }
+}
+
+/// 失敗したCI実行の調査の提案
+pub struct CiStatusProvider {
+    ci_monitor: Arc<CiMonitor>,
+}
+
+impl SuggestionProvider for CiStatusProvider {
+    fn name(&self) -> &'static str {
+        "ci_status"
+    }
+
+    fn suggest<'a>(&'a self, _context: &'a SuggestionContext) -> SuggestFuture<'a> {
+        Box::pin(async move {
+            let mut runs = self.ci_monitor.get_cached_runs().await;
+            if runs.is_empty() {
+                runs = tokio::time::timeout(CI_FETCH_TIMEOUT, self.ci_monitor.get_latest_runs(5))
+                    .await
+                    .map_err(|_| anyhow::anyhow!("timed out fetching CI runs"))?
+                    .map_err(|e| anyhow::anyhow!(e))?;
+            }
+
+            // ブランチごとに最新の完了済み実行のみを見る
+            runs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+            let mut seen_branches = Vec::new();
+            let mut suggestions = Vec::new();
+            for run in runs {
+                if run.status != CiRunStatus::Completed || seen_branches.contains(&run.branch) {
+                    continue;
+                }
+                seen_branches.push(run.branch.clone());
+                if matches!(
+                    run.conclusion,
+                    Some(CiRunConclusion::Failure | CiRunConclusion::TimedOut)
+                ) {
+                    suggestions.push(Suggestion::new(
+                        format!(
+                            "ブランチ「{}」のCI「{}」が失敗しています。",
+                            run.branch, run.workflow_name
+                        ),
+                        0.7,
+                        SuggestedAction::InvestigateCiRun { run_id: run.id },
+                        format!("{}: {:?}", run.url, run.conclusion),
+                    ));
+                }
+            }
+            Ok(suggestions)
+        })
+    }
+}
+
+/// クラッシュループや異常終了したプロセスの調査の提案
+pub struct DiagnosisProvider {
+    process_manager: ProcessManager,
+}
+
+impl SuggestionProvider for DiagnosisProvider {
+    fn name(&self) -> &'static str {
+        "diagnosis"
+    }
+
+    fn suggest<'a>(&'a self, _context: &'a SuggestionContext) -> SuggestFuture<'a> {
+        Box::pin(async move {
+            let crash_loops = self.process_manager.crash_loops().await;
+            let mut suggestions: Vec<Suggestion> = crash_loops
+                .iter()
+                .map(|(id, info)| Suggestion::investigate_crash_loop(id, info))
+                .collect();
+
+            for info in self.process_manager.list_processes(None).await {
+                if crash_loops.iter().any(|(id, _)| *id == info.id) {
+                    continue;
+                }
+                let reason = match &info.state {
+                    ProcessState::Failed { error, .. } => error.clone(),
+                    ProcessState::Stopped {
+                        exit_code: Some(code),
+                        ..
+                    } if *code != 0 => format!("終了コード {code}"),
+                    _ => continue,
+                };
+                suggestions.push(Suggestion::new(
+                    format!(
+                        "「{}」が異常終了しました。ログを確認してください。",
+                        info.id
+                    ),
+                    0.6,
+                    SuggestedAction::InvestigateProcess {
+                        process_id: info.id.clone(),
+                    },
+                    reason,
+                ));
+            }
+            Ok(suggestions)
+        })
+    }
+}
+
+/// アイドル状態のプロセスの停止の提案
+pub struct IdleProvider {
+    process_manager: ProcessManager,
+}
+
+impl SuggestionProvider for IdleProvider {
+    fn name(&self) -> &'static str {
+        "idle"
+    }
+
+    fn suggest<'a>(&'a self, _context: &'a SuggestionContext) -> SuggestFuture<'a> {
+        Box::pin(async move {
+            Ok(self
+                .process_manager
+                .idle_processes()
+                .await
+                .into_iter()
+                .map(|(id, idle_since)| {
+                    Suggestion::new(
+                        format!("「{id}」はアイドル状態です。不要であれば停止できます。"),
+                        0.5,
+                        SuggestedAction::StopProcess {
+                            process_id: id.clone(),
+                        },
+                        format!("{} からアイドル", idle_since.to_rfc3339()),
+                    )
+                })
+                .collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedProvider(&'static str, f64);
+
+    impl SuggestionProvider for FixedProvider {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn suggest<'a>(&'a self, _context: &'a SuggestionContext) -> SuggestFuture<'a> {
+            Box::pin(async move {
+                Ok(vec![Suggestion::new(
+                    self.0.to_string(),
+                    self.1,
+                    SuggestedAction::StartProcess {
+                        process_id: self.0.to_string(),
+                    },
+                    String::new(),
+                )])
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_applies_weights_and_provenance() {
+        let pipeline = SuggestionPipeline::new()
+            .with_provider(FixedProvider("a", 0.9))
+            .with_provider(FixedProvider("b", 0.6))
+            .with_provider(FixedProvider("c", 1.0));
+
+        let mut settings = HashMap::new();
+        settings.insert(
+            "a".to_string(),
+            SuggestionProviderSettings {
+                enabled: true,
+                weight: 0.5,
+            },
+        );
+        settings.insert(
+            "c".to_string(),
+            SuggestionProviderSettings {
+                enabled: false,
+                weight: 1.0,
+            },
+        );
+
+        let suggestions = pipeline.run(&SuggestionContext::default(), &settings).await;
+        let providers: Vec<&str> = suggestions.iter().map(|s| s.provider.as_str()).collect();
+        assert_eq!(providers, vec!["b", "a"]);
+        assert!((suggestions[1].score - 0.45).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_feedback_adjusts_confidence() {
+        let engine = Arc::new(LearningEngine::new(Arc::new(
+            crate::events::EventSystem::new(),
+        )));
+        let pipeline = SuggestionPipeline::new()
+            .with_feedback(engine.clone())
+            .with_provider(FixedProvider("a", 0.6))
+            .with_provider(FixedProvider("b", 0.6));
+        let context = SuggestionContext::default();
+
+        let suggestions = pipeline.run(&context, &HashMap::new()).await;
+        let a = suggestions.iter().find(|s| s.provider == "a").unwrap();
+        let b = suggestions.iter().find(|s| s.provider == "b").unwrap();
+        engine.record_feedback(a, true).await;
+        engine.record_feedback(b, false).await;
+        engine.record_feedback(b, false).await;
+
+        let suggestions = pipeline.run(&context, &HashMap::new()).await;
+        assert_eq!(suggestions[0].provider, "a");
+        assert!(suggestions[0].confidence > 0.6);
+        assert!(suggestions[1].confidence < 0.6);
+    }
+}
@@ -0,0 +1,244 @@
+//! 提案の監視と通知
+//!
+//! エージェントが `get_suggestions` を繰り返し呼ばなくても済むように、プロセスの失敗や
+//! クラッシュループなどのイベントのたび、また一定間隔（学習した時間帯の提案のため）で
+//! パイプラインを実行し、新しく現れた提案を購読者に送ります。購読ごとに信頼度の下限と
+//! プロバイダーを指定でき、MCPクライアントにはログ通知（`notifications/message`）で届けます。
+
+use super::{Suggestion, SuggestionContext, SuggestionPipeline};
+use crate::events::EventType;
+use crate::process::ProcessManager;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Once};
+use std::time::Duration;
+use tokio::sync::{Mutex, broadcast};
+use tokio::task::AbortHandle;
+use tracing::{debug, info};
+
+/// 時間帯の提案などを拾うための定期実行の間隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(60);
+/// イベントの後、状態が落ち着くまで待つ時間
+const EVENT_SETTLE: Duration = Duration::from_millis(500);
+
+/// 新しく現れた提案
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestionNotification {
+    pub suggestion: Suggestion,
+    /// 実行のきっかけ（イベント名、または `interval`）
+    pub trigger: String,
+    pub emitted_at: DateTime<Utc>,
+}
+
+/// 提案の購読
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestionWatch {
+    pub watch_id: String,
+    /// この信頼度（0.0〜1.0）以上の提案だけを送る
+    pub min_confidence: f64,
+    /// 送る提案のプロバイダー（`None` はすべて）
+    pub providers: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SuggestionWatch {
+    pub fn accepts(&self, suggestion: &Suggestion) -> bool {
+        suggestion.confidence >= self.min_confidence
+            && self
+                .providers
+                .as_ref()
+                .is_none_or(|providers| providers.contains(&suggestion.provider))
+    }
+}
+
+/// 前回の実行になかった提案と、今回の提案のキー
+pub fn fresh_suggestions(
+    previous: &HashSet<String>,
+    suggestions: Vec<Suggestion>,
+) -> (Vec<Suggestion>, HashSet<String>) {
+    let current = suggestions.iter().map(|s| s.feedback_key()).collect();
+    let fresh = suggestions
+        .into_iter()
+        .filter(|s| !previous.contains(&s.feedback_key()))
+        .collect();
+    (fresh, current)
+}
+
+/// 提案の監視
+pub struct SuggestionWatcher {
+    pipeline: Arc<SuggestionPipeline>,
+    process_manager: ProcessManager,
+    sender: broadcast::Sender<SuggestionNotification>,
+    started: Once,
+    watches: Mutex<HashMap<String, (SuggestionWatch, AbortHandle)>>,
+}
+
+impl SuggestionWatcher {
+    pub fn new(pipeline: Arc<SuggestionPipeline>, process_manager: ProcessManager) -> Self {
+        let (sender, _) = broadcast::channel(100);
+        Self {
+            pipeline,
+            process_manager,
+            sender,
+            started: Once::new(),
+            watches: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 新しい提案を受け取る（最初の購読で監視を始める）
+    pub fn subscribe(self: &Arc<Self>) -> broadcast::Receiver<SuggestionNotification> {
+        let receiver = self.sender.subscribe();
+        self.started.call_once(|| {
+            let watcher = self.clone();
+            tokio::spawn(async move { watcher.run().await });
+        });
+        receiver
+    }
+
+    /// 購読を登録する（`task` は通知を送るタスク。解除すると止める）
+    pub async fn register(&self, watch: SuggestionWatch, task: AbortHandle) {
+        info!(
+            "Watching suggestions with confidence >= {} ({})",
+            watch.min_confidence, watch.watch_id
+        );
+        self.watches
+            .lock()
+            .await
+            .insert(watch.watch_id.clone(), (watch, task));
+    }
+
+    /// 購読を解除する
+    pub async fn unregister(&self, watch_id: &str) -> Result<(), String> {
+        let (_, task) = self
+            .watches
+            .lock()
+            .await
+            .remove(watch_id)
+            .ok_or_else(|| format!("Suggestion watch '{watch_id}' not found"))?;
+        task.abort();
+        Ok(())
+    }
+
+    /// 購読の一覧（送り先が切断された購読は取り除く）
+    pub async fn list(&self) -> Vec<SuggestionWatch> {
+        let mut watches = self.watches.lock().await;
+        watches.retain(|_, (_, task)| !task.is_finished());
+        let mut list: Vec<SuggestionWatch> = watches.values().map(|(w, _)| w.clone()).collect();
+        list.sort_by_key(|w| w.created_at);
+        list
+    }
+
+    async fn run(self: Arc<Self>) {
+        let mut events = self.process_manager.event_system().subscribe();
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        // 購読を始めた時点の提案は「新しい提案」として扱わない
+        let mut previous = self
+            .evaluate()
+            .await
+            .into_iter()
+            .map(|s| s.feedback_key())
+            .collect();
+        interval.reset();
+
+        loop {
+            let trigger = tokio::select! {
+                _ = interval.tick() => "interval".to_string(),
+                event = events.recv() => match event {
+                    Ok(event) => match event.event_type {
+                        EventType::ProcessError
+                        | EventType::ProcessStopped
+                        | EventType::ProcessIdle
+                        | EventType::ProcessUnhealthy
+                        | EventType::CrashLoopDetected
+                        | EventType::RestartLimitReached
+                        | EventType::ProcessStateCorrected => {
+                            tokio::time::sleep(EVENT_SETTLE).await;
+                            serde_json::to_value(&event.event_type)
+                                .ok()
+                                .and_then(|v| v.as_str().map(str::to_string))
+                                .unwrap_or_default()
+                        }
+                        _ => continue,
+                    },
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+            };
+
+            let (fresh, current) = fresh_suggestions(&previous, self.evaluate().await);
+            previous = current;
+            for suggestion in fresh {
+                debug!("New suggestion ({}): {}", trigger, suggestion.message);
+                let _ = self.sender.send(SuggestionNotification {
+                    suggestion,
+                    trigger: trigger.clone(),
+                    emitted_at: Utc::now(),
+                });
+            }
+        }
+    }
+
+    async fn evaluate(&self) -> Vec<Suggestion> {
+        let settings = self
+            .process_manager
+            .get_settings()
+            .await
+            .unwrap_or_default();
+        self.pipeline
+            .run(
+                &SuggestionContext::default(),
+                &settings.suggestion_providers,
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::learning::SuggestedAction;
+
+    fn suggestion(provider: &str, process_id: &str, confidence: f64) -> Suggestion {
+        let mut suggestion = Suggestion::new(
+            format!("Investigate {process_id}"),
+            confidence,
+            SuggestedAction::InvestigateProcess {
+                process_id: process_id.to_string(),
+            },
+            String::new(),
+        );
+        suggestion.provider = provider.to_string();
+        suggestion
+    }
+
+    #[test]
+    fn test_only_new_suggestions_above_threshold_are_sent() {
+        let (fresh, previous) =
+            fresh_suggestions(&HashSet::new(), vec![suggestion("diagnosis", "api", 0.9)]);
+        assert_eq!(fresh.len(), 1);
+
+        let (fresh, _) = fresh_suggestions(
+            &previous,
+            vec![
+                suggestion("diagnosis", "api", 0.9),
+                suggestion("diagnosis", "worker", 0.5),
+            ],
+        );
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(
+            fresh[0].action.key(),
+            "investigate_process:worker".to_string()
+        );
+
+        let watch = SuggestionWatch {
+            watch_id: "w".to_string(),
+            min_confidence: 0.8,
+            providers: Some(vec!["diagnosis".to_string()]),
+            created_at: Utc::now(),
+        };
+        assert!(!watch.accepts(&fresh[0]));
+        assert!(watch.accepts(&suggestion("diagnosis", "api", 0.9)));
+        assert!(!watch.accepts(&suggestion("time_pattern", "api", 0.9)));
+    }
+}
@@ -1,28 +1,50 @@
 use rmcp::{
-    ErrorData as McpError, ServerHandler,
-    handler::server::{tool::ToolRouter, wrapper::Parameters},
+    ErrorData as McpError, Peer, RoleServer, ServerHandler,
+    handler::server::{
+        tool::{ToolCallContext, ToolRouter},
+        wrapper::Parameters,
+    },
     model::*,
-    tool, tool_handler, tool_router,
+    service::RequestContext,
+    tool, tool_router,
 };
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
 
 pub mod ci;
 pub mod error;
 pub mod events;
 pub mod learning;
+pub mod mcp_session;
 pub mod messages;
 pub mod process;
+pub mod response_budget;
 pub mod security;
+pub mod server_admin;
+pub mod server_startup;
+pub mod settings;
 pub mod web;
 
 pub use error::{VantageError, VantageResult};
 
 use ci::CiMonitor;
 use events::EventSystem;
-use learning::LearningEngine;
+use learning::{
+    AnalyticsRecorder, LearningEngine, SuggestionContext, SuggestionPipeline, SuggestionWatch,
+    SuggestionWatcher,
+};
 use messages::*;
-use process::ProcessManager;
+use process::TimeWindow;
+use process::template_render::RenderOverrides;
+use process::{
+    ApprovalOperation, ChaosTarget, ConfigFormat, OutputSampling, ProcessManager, ProcessOptions,
+    RedactionOverride, RollingRestartOptions, Runbook, WorkspaceImportOptions,
+};
+use security::permissions::{self, check_secret_env, mask_secrets};
+use vantage_persistence::{
+    Access, Capability, ChangeTarget, LazyStartConfig, MaintenanceWindow, Permissions,
+};
 
 #[derive(Clone)]
 pub struct VantageServer {
@@ -33,8 +55,160 @@ pub struct VantageServer {
     learning_engine: Arc<LearningEngine>,
     #[allow(dead_code)]
     ci_monitor: Arc<CiMonitor>,
+    suggestion_pipeline: Arc<SuggestionPipeline>,
+    /// 新しい提案の監視（watch_suggestionsの購読に通知を送る）
+    suggestion_watcher: Arc<SuggestionWatcher>,
+    /// 直近のget_suggestionsの結果（apply/dismissで番号指定に使う）
+    last_suggestions: Arc<Mutex<Vec<learning::Suggestion>>>,
+    /// ツールの呼び出しと提案の適用・却下の記録（get_assistant_analyticsで集計する）
+    analytics: AnalyticsRecorder,
     tool_router: ToolRouter<VantageServer>,
     db_connection: Option<Arc<vantage_persistence::DbConnection>>,
+    /// データベース上のテンプレートの変更の購読（サーバーが持っている間だけ続く）
+    #[cfg(feature = "live-query")]
+    _live_templates: Option<Arc<vantage_persistence::LiveTemplatesGuard>>,
+    /// MCPクライアントのアクセス権（設定ディレクトリの auth.yaml の `mcp_permissions`）
+    permissions: Permissions,
+}
+
+/// テンプレートの変更の購読を始め、イベントシステム経由でWebクライアントに伝える
+#[cfg(feature = "live-query")]
+fn spawn_live_templates(
+    db_connection: Option<&vantage_persistence::DbConnection>,
+    event_system: &EventSystem,
+) -> Option<Arc<vantage_persistence::LiveTemplatesGuard>> {
+    let config = db_connection?.config().clone();
+    Some(Arc::new(vantage_persistence::LiveTemplatesGuard::spawn(
+        config,
+        event_system.template_change_sender(),
+    )))
+}
+
+/// テンプレート用データベースのレコードIDのキー（`template:` を除いたもの）
+fn template_key(template: &vantage_persistence::Template) -> Option<String> {
+    let id = template.id.as_ref()?.to_string();
+    Some(id.trim_start_matches("template:").to_string())
+}
+
+/// RFC 3339の期間を読み取る
+fn parse_time_window(window: OutputWindowRequest) -> std::result::Result<TimeWindow, String> {
+    let parse = |value: Option<String>| {
+        value
+            .map(|v| {
+                chrono::DateTime::parse_from_rfc3339(&v)
+                    .map(|t| t.with_timezone(&chrono::Utc))
+                    .map_err(|e| format!("Invalid timestamp '{v}': {e}"))
+            })
+            .transpose()
+    };
+    let window = TimeWindow {
+        from: parse(window.from)?,
+        to: parse(window.to)?,
+    };
+    if let (Some(from), Some(to)) = (window.from, window.to)
+        && from >= to
+    {
+        return Err("Window 'from' must be before 'to'".to_string());
+    }
+    Ok(window)
+}
+
+/// プロセスの手順書のリソースURI
+fn runbook_uri(process_id: &str) -> String {
+    format!("vantage://processes/{process_id}/runbook")
+}
+
+/// ツールの引数から対象のプロセスのIDを取り出す（`id` または `process_id`）
+fn target_process(arguments: Option<&rmcp::model::JsonObject>) -> Option<String> {
+    let arguments = arguments?;
+    ["process_id", "id"]
+        .iter()
+        .find_map(|key| arguments.get(*key)?.as_str())
+        .map(str::to_string)
+}
+
+/// SurrealDBに接続してスキーマを適用する（接続できなければテンプレート機能なしで続ける）
+async fn connect_database() -> Option<Arc<vantage_persistence::DbConnection>> {
+    let started = Instant::now();
+    match vantage_persistence::DbConnection::new_from_env().await {
+        Ok(conn) => {
+            server_startup::record("db_connect", started, None);
+            tracing::info!("SurrealDBに接続しました");
+
+            // スキーマを確認し、足りない定義だけを適用する（作成済みなら読み取りの権限だけを使う）
+            let schema_manager = vantage_persistence::SchemaManager::new(conn.db());
+            let started = Instant::now();
+            let checked = schema_manager.ensure().await;
+            server_startup::record(
+                "schema_check",
+                started,
+                match &checked {
+                    Ok(report) if !report.is_ok() => {
+                        Some(format!("{} definition(s) missing", report.failures.len()))
+                    }
+                    Ok(report) if report.applied > 0 => {
+                        Some(format!("applied {} definition(s)", report.applied))
+                    }
+                    Ok(_) => None,
+                    Err(e) => Some(format!("failed: {e}")),
+                },
+            );
+            match checked {
+                Ok(report) if report.is_ok() => {
+                    if report.applied > 0 {
+                        tracing::info!(
+                            "データベーススキーマの足りない{}件の定義を適用しました",
+                            report.applied
+                        );
+                    }
+                }
+                Ok(report) => {
+                    tracing::warn!(
+                        "データベーススキーマの{}件の定義を作成できませんでした。テンプレート機能が正常に動作しない可能性があります。\
+                         権限のあるユーザーで `vantagemcp init-db` を実行してください:\n{}",
+                        report.failures.len(),
+                        report.diagnostics()
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "データベーススキーマを確認できませんでした: {:#}. テンプレート機能が正常に動作しない可能性があります。\
+                         `vantagemcp init-db --check` で足りない定義と必要な権限を確認できます。",
+                        e
+                    );
+                }
+            }
+
+            Some(Arc::new(conn))
+        }
+        Err(e) => {
+            server_startup::record("db_connect", started, Some(format!("failed: {e}")));
+            tracing::warn!(
+                "SurrealDBへの接続に失敗しました: {}. テンプレート機能は利用できません。\
+                 SurrealDBが起動しており、アクセス可能であることを確認してください。\
+                 接続設定は以下の環境変数で設定できます: \
+                 VANTAGE_DB_ENDPOINT, VANTAGE_DB_NAMESPACE, VANTAGE_DB_DATABASE, \
+                 VANTAGE_DB_USERNAME, VANTAGE_DB_PASSWORD",
+                e
+            );
+            None
+        }
+    }
+}
+
+/// MCPクライアントのアクセス権を読み込む（設定を読めなければすべて許可）
+async fn load_mcp_permissions(process_manager: &ProcessManager) -> Permissions {
+    match process_manager
+        .persistence_manager()
+        .load_auth_config()
+        .await
+    {
+        Ok(config) => config.mcp_permissions(),
+        Err(e) => {
+            tracing::warn!("Failed to load MCP permissions, allowing all: {}", e);
+            Permissions::all(Access::Write)
+        }
+    }
 }
 
 #[tool_router]
@@ -46,9 +220,8 @@ impl VantageServer {
         tracing::debug!("Initializing process manager");
         let process_manager = ProcessManager::new().await;
 
-        // イベントシステムを初期化（Database依存を削除）
-        tracing::debug!("Initializing event system");
-        let event_system = Arc::new(EventSystem::new());
+        // イベントシステムはProcessManagerと共有する
+        let event_system = process_manager.event_system();
 
         // 学習エンジンを初期化（Database依存を削除）
         tracing::debug!("Initializing learning engine");
@@ -59,53 +232,49 @@ impl VantageServer {
         let ci_monitor = Arc::new(CiMonitor::new(None, Some(30)));
 
         // DB接続を初期化（オプショナル）
-        let db_connection = match vantage_persistence::DbConnection::new_default().await {
-            Ok(conn) => {
-                tracing::info!("SurrealDBに接続しました");
-
-                // スキーマを自動適用
-                let schema_manager = vantage_persistence::SchemaManager::new(conn.db());
-                match schema_manager.apply_all().await {
-                    Ok(_) => {
-                        tracing::info!("データベーススキーマを適用しました");
-                    }
-                    Err(e) => {
-                        tracing::error!(
-                            "データベーススキーマの適用に失敗しました: {}. テンプレート機能が正常に動作しない可能性があります。\
-                             SurrealDBの権限を確認し、データベースにアクセス可能であることを確認してください。",
-                            e
-                        );
-                    }
-                }
+        let db_connection = connect_database().await;
 
-                Some(Arc::new(conn))
-            }
-            Err(e) => {
-                tracing::warn!(
-                    "SurrealDBへの接続に失敗しました: {}. テンプレート機能は利用できません。\
-                     SurrealDBが起動しており、アクセス可能であることを確認してください。\
-                     接続設定は以下の環境変数で設定できます: \
-                     VANTAGE_DB_ENDPOINT, VANTAGE_DB_NAMESPACE, VANTAGE_DB_DATABASE, \
-                     VANTAGE_DB_USERNAME, VANTAGE_DB_PASSWORD",
-                    e
-                );
-                None
-            }
-        };
+        let suggestion_pipeline = Arc::new(SuggestionPipeline::with_default_providers(
+            process_manager.clone(),
+            learning_engine.clone(),
+            ci_monitor.clone(),
+        ));
+        let suggestion_watcher = Arc::new(SuggestionWatcher::new(
+            suggestion_pipeline.clone(),
+            process_manager.clone(),
+        ));
+
+        let permissions = load_mcp_permissions(&process_manager).await;
 
         tracing::info!("VantageServer initialization complete");
         Ok(Self {
             start_time: Arc::new(Mutex::new(chrono::Utc::now())),
             process_manager,
+            #[cfg(feature = "live-query")]
+            _live_templates: spawn_live_templates(db_connection.as_deref(), &event_system),
             event_system,
             learning_engine,
             ci_monitor,
+            suggestion_pipeline,
+            suggestion_watcher,
+            last_suggestions: Arc::new(Mutex::new(Vec::new())),
+            analytics: AnalyticsRecorder::new(),
             tool_router: Self::tool_router(),
             db_connection,
+            permissions,
         })
     }
 
     pub fn set_process_manager(&mut self, manager: ProcessManager) {
+        self.suggestion_pipeline = Arc::new(SuggestionPipeline::with_default_providers(
+            manager.clone(),
+            self.learning_engine.clone(),
+            self.ci_monitor.clone(),
+        ));
+        self.suggestion_watcher = Arc::new(SuggestionWatcher::new(
+            self.suggestion_pipeline.clone(),
+            manager.clone(),
+        ));
         self.process_manager = manager;
     }
 
@@ -113,8 +282,8 @@ impl VantageServer {
     pub async fn with_process_manager(process_manager: ProcessManager) -> anyhow::Result<Self> {
         tracing::info!("Initializing VantageServer with existing ProcessManager");
 
-        // Initialize event system
-        let event_system = Arc::new(EventSystem::new());
+        // Share the process manager's event system
+        let event_system = process_manager.event_system();
 
         // Initialize learning engine
         let learning_engine = Arc::new(LearningEngine::new(event_system.clone()));
@@ -131,50 +300,37 @@ impl VantageServer {
         let ci_monitor_2 = Arc::new(CiMonitor::new(None, Some(30)));
 
         // DB接続を初期化（オプショナル）
-        let db_connection = match vantage_persistence::DbConnection::new_default().await {
-            Ok(conn) => {
-                tracing::info!("SurrealDBに接続しました");
-
-                // スキーマを自動適用
-                let schema_manager = vantage_persistence::SchemaManager::new(conn.db());
-                match schema_manager.apply_all().await {
-                    Ok(_) => {
-                        tracing::info!("データベーススキーマを適用しました");
-                    }
-                    Err(e) => {
-                        tracing::error!(
-                            "データベーススキーマの適用に失敗しました: {}. テンプレート機能が正常に動作しない可能性があります。\
-                             SurrealDBの権限を確認し、データベースにアクセス可能であることを確認してください。",
-                            e
-                        );
-                    }
-                }
+        let db_connection = connect_database().await;
 
-                Some(Arc::new(conn))
-            }
-            Err(e) => {
-                tracing::warn!(
-                    "SurrealDBへの接続に失敗しました: {}. テンプレート機能は利用できません。\
-                     SurrealDBが起動しており、アクセス可能であることを確認してください。\
-                     接続設定は以下の環境変数で設定できます: \
-                     VANTAGE_DB_ENDPOINT, VANTAGE_DB_NAMESPACE, VANTAGE_DB_DATABASE, \
-                     VANTAGE_DB_USERNAME, VANTAGE_DB_PASSWORD",
-                    e
-                );
-                None
-            }
-        };
+        let suggestion_pipeline = Arc::new(SuggestionPipeline::with_default_providers(
+            process_manager.clone(),
+            learning_engine.clone(),
+            ci_monitor_2.clone(),
+        ));
+        let suggestion_watcher = Arc::new(SuggestionWatcher::new(
+            suggestion_pipeline.clone(),
+            process_manager.clone(),
+        ));
+
+        let permissions = load_mcp_permissions(&process_manager).await;
 
         tracing::info!("VantageServer initialization complete");
 
         Ok(Self {
             start_time: Arc::new(Mutex::new(chrono::Utc::now())),
             process_manager,
+            #[cfg(feature = "live-query")]
+            _live_templates: spawn_live_templates(db_connection.as_deref(), &event_system),
             event_system,
             learning_engine,
             ci_monitor: ci_monitor_2,
+            suggestion_pipeline,
+            suggestion_watcher,
+            last_suggestions: Arc::new(Mutex::new(Vec::new())),
+            analytics: AnalyticsRecorder::new(),
             tool_router: Self::tool_router(),
             db_connection,
+            permissions,
         })
     }
 
@@ -192,6 +348,168 @@ impl VantageServer {
         Ok(())
     }
 
+    /// 承認が必要な操作なら承認待ちとして登録し、ツールの結果としてその旨を返す
+    ///
+    /// 確認が必要なツールは、Webコンソールで判断されるまで進捗通知を送りながら待ち、
+    /// 承認されて実行された結果（または却下・タイムアウト）を返す。
+    async fn pending_approval(
+        &self,
+        operation: ApprovalOperation,
+        context: &RequestContext<RoleServer>,
+    ) -> std::result::Result<Option<CallToolResult>, McpError> {
+        let Some(approval) = self
+            .process_manager
+            .request_approval(operation)
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?
+        else {
+            return Ok(None);
+        };
+
+        if approval.waiting {
+            return self.await_confirmation(approval, context).await.map(Some);
+        }
+
+        let response = serde_json::json!({
+            "status": "pending_approval",
+            "approval_id": approval.approval_id,
+            "summary": approval.summary,
+            "message": "This operation requires human approval and has not been executed. \
+                Ask the user to approve it in the web console (Approvals), then check the \
+                outcome with get_approval.",
+        });
+        Ok(Some(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )])))
+    }
+
+    /// 確認が必要な操作の判断を待ち、結果をツールの結果にする
+    async fn await_confirmation(
+        &self,
+        approval: vantage_persistence::ApprovalRequest,
+        context: &RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        // 待つ間も待ち合わせを持っていると、承認された操作を実行するWebのタスクが排他を取れない
+        process::fence::leave_scope();
+        let progress_token = context.meta.get_progress_token();
+        let total = approval
+            .expires_at
+            .map(|expires_at| (expires_at - approval.requested_at).num_seconds() as f64);
+        let mut notified_secs = None;
+        let peer = context.peer.clone();
+        let decided = self
+            .process_manager
+            .wait_for_approval(
+                &approval.approval_id,
+                context.ct.clone().cancelled_owned(),
+                |request, waited| {
+                    let Some(token) = progress_token.clone() else {
+                        return;
+                    };
+                    // 5秒ごとに送る
+                    let secs = waited.as_secs() / process::approval::CONFIRM_PROGRESS_SECS
+                        * process::approval::CONFIRM_PROGRESS_SECS;
+                    if notified_secs == Some(secs) {
+                        return;
+                    }
+                    notified_secs = Some(secs);
+                    let message = match request.status {
+                        vantage_persistence::ApprovalStatus::Approved => {
+                            format!("Approved; running: {}", request.summary)
+                        }
+                        _ => format!(
+                            "Waiting for confirmation in the web console (Approvals): {}",
+                            request.summary
+                        ),
+                    };
+                    let peer = peer.clone();
+                    tokio::spawn(async move {
+                        let _ = peer
+                            .notify_progress(ProgressNotificationParam {
+                                progress_token: token,
+                                progress: secs as f64,
+                                total,
+                                message: Some(message),
+                            })
+                            .await;
+                    });
+                },
+            )
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+
+        let outcome = serde_json::json!({
+            "status": decided.status,
+            "approval_id": decided.approval_id,
+            "summary": decided.summary,
+            "decided_by": decided.decided_by,
+            "reason": decided.reason,
+            "result": decided.result,
+        });
+        let text = serde_json::to_string_pretty(&outcome).unwrap();
+        match decided.status {
+            vantage_persistence::ApprovalStatus::Executed => {
+                Ok(CallToolResult::success(vec![Content::text(text)]))
+            }
+            _ => Ok(CallToolResult::error(vec![Content::text(text)])),
+        }
+    }
+
+    /// MCPサンプリングでクライアントのLLMに未知のエラーを分類させ、確認待ちのルール候補として保存する
+    async fn sample_diagnosis(
+        &self,
+        peer: &Peer<RoleServer>,
+        report: &process::DiagnosisReport,
+    ) -> std::result::Result<vantage_persistence::DiagnosisRule, String> {
+        let supports_sampling = peer
+            .peer_info()
+            .is_some_and(|info| info.capabilities.sampling.is_some());
+        if !supports_sampling {
+            return Err("The client does not support MCP sampling".to_string());
+        }
+
+        let command = self
+            .process_manager
+            .get_process_status(report.process_id.clone())
+            .await
+            .ok()
+            .map(|status| status.info.command);
+        let prompt = process::diagnosis::sampling_prompt(
+            &report.process_id,
+            command.as_deref(),
+            &report.excerpt,
+        );
+        let result = peer
+            .create_message(CreateMessageRequestParam {
+                messages: vec![SamplingMessage {
+                    role: Role::User,
+                    content: Content::text(prompt),
+                }],
+                model_preferences: None,
+                system_prompt: Some(process::diagnosis::SAMPLING_SYSTEM_PROMPT.to_string()),
+                include_context: Some(ContextInclusion::None),
+                temperature: Some(0.0),
+                max_tokens: 800,
+                stop_sequences: None,
+                metadata: None,
+            })
+            .await
+            .map_err(|e| format!("Sampling request failed: {e}"))?;
+        let reply = result
+            .message
+            .content
+            .as_text()
+            .map(|text| text.text.clone())
+            .ok_or_else(|| "The sampled reply was not text".to_string())?;
+
+        let candidate =
+            process::diagnosis::candidate_from_reply(&reply, &report.excerpt, Some(result.model))?;
+        self.process_manager
+            .add_diagnosis_candidate(&candidate)
+            .await?;
+        Ok(candidate)
+    }
+
     #[tool(description = "Echo the input message back")]
     fn echo(
         &self,
@@ -212,179 +530,2644 @@ impl VantageServer {
         let start_time = self.start_time.lock().await;
         let uptime = chrono::Utc::now() - *start_time;
 
-        Ok(CallToolResult::success(vec![Content::text(format!(
+        let mut status = format!(
             "Status: running\nVersion: 0.1.0\nUptime: {} seconds\nTools: echo, ping, get_status, create_process, start_process, stop_process, get_process_status, get_process_output, list_processes, remove_process",
             uptime.num_seconds()
-        ))]))
+        );
+        if let Some(safe_mode) = self.process_manager.safe_mode_status().await {
+            status.push_str(&format!(
+                "\nSafe mode: on since {} (restore and auto-start skipped; {} process(es) pending, use safe_mode_restore)",
+                safe_mode.since.to_rfc3339(),
+                safe_mode.pending.len()
+            ));
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(status)]))
     }
 
-    #[tool(description = "Create and register a new process")]
-    async fn create_process(
+    #[tool(
+        description = "Restore processes that a safe-mode startup (--safe-mode or VANTAGE_SAFE_MODE=1) skipped. Reads the snapshot that was not restored and registers the given process_ids (all when empty) without touching processes that already exist; with start=true also starts those marked auto_start_on_restore. Reports restored, started, skipped, not found, failed and still pending processes"
+    )]
+    async fn safe_mode_restore(
         &self,
-        Parameters(CreateProcessRequest {
-            id,
-            command,
-            args,
-            env,
-            cwd,
-            auto_start_on_restore,
-        }): Parameters<CreateProcessRequest>,
+        Parameters(SafeModeRestoreRequest { process_ids, start }): Parameters<
+            SafeModeRestoreRequest,
+        >,
     ) -> std::result::Result<CallToolResult, McpError> {
-        let cwd_path = cwd.map(std::path::PathBuf::from);
+        let report = self
+            .process_manager
+            .safe_mode_restore(process_ids, start)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
 
-        // Create the process
-        self.process_manager
-            .create_process(
-                id.clone(),
-                command,
-                args,
-                env,
-                cwd_path,
-                auto_start_on_restore,
-            )
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Get server diagnostics including log disk usage against the configured quota, per-workspace quota usage, log sink health and output buffer memory per process"
+    )]
+    async fn get_diagnostics(&self) -> std::result::Result<CallToolResult, McpError> {
+        let diagnostics = self
+            .process_manager
+            .diagnostics()
             .await
-            .map_err(|e| McpError {
-                message: e.into(),
-                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
-                data: None,
-            })?;
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let json = serde_json::to_string_pretty(&diagnostics)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-        Ok(CallToolResult::success(vec![Content::text(format!(
-            "Process '{id}' created successfully"
-        ))]))
+        Ok(CallToolResult::success(vec![Content::text(json)]))
     }
 
-    #[tool(description = "Start a registered process")]
-    async fn start_process(
+    #[tool(
+        description = "Show how long the server took to start, phase by phase (data directory, process manager, snapshot restore, auto-start, web server, SurrealDB connect and schema apply), plus the total of recent startups per version to spot cold-start regressions"
+    )]
+    async fn get_startup_report(&self) -> std::result::Result<CallToolResult, McpError> {
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&server_startup::report()).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Show where the server keeps its config, data and state files (auth, snapshots, contexts, logs, TLS, debug bundles) and which rule chose them: --data-dir flag > VANTAGE_DATA_DIR > workspace .vantage > XDG base directories"
+    )]
+    async fn show_paths(&self) -> std::result::Result<CallToolResult, McpError> {
+        let paths = vantage_persistence::paths::get();
+        let locations: serde_json::Map<String, serde_json::Value> = paths
+            .locations()
+            .into_iter()
+            .map(|(name, path)| {
+                (
+                    name.to_string(),
+                    serde_json::json!({ "path": path, "exists": path.exists() }),
+                )
+            })
+            .collect();
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&serde_json::json!({
+                "source": paths.source,
+                "precedence": ["flag", "env", "workspace", "user"],
+                "locations": locations,
+            }))
+            .unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Diagnose a process from its redacted stderr excerpt using the active diagnosis rules (category, cause and fix). When no rule matches and use_sampling is true, asks the client's LLM via MCP sampling to classify the error and stores the answer as a candidate rule pending confirm_diagnosis_rule"
+    )]
+    async fn diagnose_process(
         &self,
-        Parameters(StartProcessRequest { id }): Parameters<StartProcessRequest>,
+        Parameters(DiagnoseProcessRequest { id, use_sampling }): Parameters<DiagnoseProcessRequest>,
+        peer: Peer<RoleServer>,
     ) -> std::result::Result<CallToolResult, McpError> {
-        let pid = self
+        let mut report = self
             .process_manager
-            .start_process(id.clone())
+            .diagnose_process(&id)
             .await
-            .map_err(|e| McpError {
-                message: e.into(),
-                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
-                data: None,
-            })?;
+            .map_err(|e| McpError::invalid_params(e, None))?;
 
-        Ok(CallToolResult::success(vec![Content::text(format!(
-            "Process '{id}' started with PID {pid}"
-        ))]))
+        if report.unknown_signature && use_sampling.unwrap_or(false) {
+            match self.sample_diagnosis(&peer, &report).await {
+                Ok(candidate) => report.candidate = Some(candidate),
+                Err(e) => report.sampling_note = Some(e),
+            }
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Stop a running process")]
-    async fn stop_process(
-        &self,
-        Parameters(McpStopProcessRequest {
-            id,
-            grace_period_ms,
-        }): Parameters<McpStopProcessRequest>,
-    ) -> std::result::Result<CallToolResult, McpError> {
-        self.process_manager
-            .stop_process(id.clone(), grace_period_ms)
+    #[tool(
+        description = "List diagnosis rules: built-in rules, rules suggested via MCP sampling (pending until confirmed) and confirmed or rejected ones"
+    )]
+    async fn list_diagnosis_rules(&self) -> std::result::Result<CallToolResult, McpError> {
+        let rules = self
+            .process_manager
+            .list_diagnosis_rules()
             .await
-            .map_err(|e| McpError {
-                message: e.into(),
-                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
-                data: None,
-            })?;
+            .map_err(|e| McpError::internal_error(e, None))?;
 
-        Ok(CallToolResult::success(vec![Content::text(format!(
-            "Process '{id}' stopped successfully"
-        ))]))
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&rules).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Get process status and metrics")]
-    async fn get_process_status(
+    #[tool(
+        description = "Accept or reject a diagnosis rule suggested via MCP sampling. Accepted rules are used by diagnose_process; pending and rejected ones are not"
+    )]
+    async fn confirm_diagnosis_rule(
         &self,
-        Parameters(GetProcessStatusRequest { id }): Parameters<GetProcessStatusRequest>,
+        Parameters(ConfirmDiagnosisRuleRequest { rule_id, accept }): Parameters<
+            ConfirmDiagnosisRuleRequest,
+        >,
     ) -> std::result::Result<CallToolResult, McpError> {
-        let status = self
+        let rule = self
             .process_manager
-            .get_process_status(id)
+            .confirm_diagnosis_rule(&rule_id, accept)
             .await
-            .map_err(|e| McpError {
-                message: e.into(),
-                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
-                data: None,
-            })?;
+            .map_err(|e| McpError::invalid_params(e, None))?;
 
-        let json = serde_json::to_string_pretty(&status).map_err(|e| McpError {
-            message: format!("Failed to serialize status: {e}").into(),
-            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
-            data: None,
-        })?;
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&rule).unwrap(),
+        )]))
+    }
 
-        Ok(CallToolResult::success(vec![Content::text(json)]))
+    #[tool(
+        description = "Show the snapshot, restore, import or export currently holding the operation fence, with its step and progress. While it runs, tools that change processes wait for it to finish (up to 30s) and are then rejected"
+    )]
+    async fn get_operation_status(&self) -> std::result::Result<CallToolResult, McpError> {
+        let text = match self.process_manager.operation_status() {
+            Some(operation) => serde_json::to_string_pretty(&operation).unwrap(),
+            None => "No fenced operation is running".to_string(),
+        };
+        Ok(CallToolResult::success(vec![Content::text(text)]))
     }
 
-    #[tool(description = "Get process output (stdout/stderr)")]
-    async fn get_process_output(
+    #[tool(
+        description = "Show cumulative CPU-seconds per process since the server started (across restarts) with estimated energy (Wh) and electricity cost, sorted by usage, plus totals and the coefficients used"
+    )]
+    async fn get_resource_overview(&self) -> std::result::Result<CallToolResult, McpError> {
+        let overview = self
+            .process_manager
+            .get_resource_overview()
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&overview).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Set the coefficients used to estimate energy and cost from CPU time: watts per fully busy core, price per kWh and currency"
+    )]
+    async fn set_energy_cost(
         &self,
-        Parameters(GetProcessOutputRequest { id, stream, lines }): Parameters<
-            GetProcessOutputRequest,
-        >,
+        Parameters(SetEnergyCostRequest {
+            watts_per_core,
+            price_per_kwh,
+            currency,
+        }): Parameters<SetEnergyCostRequest>,
     ) -> std::result::Result<CallToolResult, McpError> {
-        let output = self
+        let mut settings = self
             .process_manager
-            .get_process_output(id, stream, lines)
+            .get_settings()
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let energy_cost = &mut settings.energy_cost;
+        energy_cost.watts_per_core = watts_per_core.unwrap_or(energy_cost.watts_per_core);
+        energy_cost.price_per_kwh = price_per_kwh.unwrap_or(energy_cost.price_per_kwh);
+        if let Some(currency) = currency {
+            energy_cost.currency = currency;
+        }
+        let energy_cost = energy_cost.clone();
+        self.process_manager
+            .save_settings(settings)
             .await
             .map_err(|e| McpError {
                 message: e.into(),
-                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
                 data: None,
             })?;
 
         Ok(CallToolResult::success(vec![Content::text(
-            output.join("\n"),
+            serde_json::to_string_pretty(&energy_cost).unwrap(),
         )]))
     }
 
-    #[tool(description = "List all managed processes")]
-    async fn list_processes(
+    #[tool(
+        description = "Find processes started outside Vantage (e.g. npm run dev in a terminal) whose command line and working directory match a process definition that is not running. With adopt=true the matches are adopted: Vantage tracks them by PID for metrics, ports, health, exit detection and stop, but cannot capture their stdout/stderr. Only one match per definition is adopted"
+    )]
+    async fn scan_external_processes(
         &self,
-        Parameters(ListProcessesRequest { filter }): Parameters<ListProcessesRequest>,
+        Parameters(ScanExternalProcessesRequest { process_ids, adopt }): Parameters<
+            ScanExternalProcessesRequest,
+        >,
     ) -> std::result::Result<CallToolResult, McpError> {
-        let processes = self.process_manager.list_processes(filter).await;
-
-        let json = serde_json::to_string_pretty(&processes).map_err(|e| McpError {
-            message: format!("Failed to serialize processes: {e}").into(),
-            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
-            data: None,
-        })?;
+        let report = self
+            .process_manager
+            .scan_external_processes(process_ids, adopt)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
 
-        Ok(CallToolResult::success(vec![Content::text(json)]))
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Remove a process from management")]
-    async fn remove_process(
+    #[tool(
+        description = "Turn automatic adoption on or off for a workspace directory. When on, processes matching a definition and running inside the workspace are adopted on the periodic check (every 30 seconds) without asking"
+    )]
+    async fn set_auto_adopt(
         &self,
-        Parameters(RemoveProcessRequest { id }): Parameters<RemoveProcessRequest>,
+        Parameters(SetAutoAdoptRequest { workspace, enabled }): Parameters<SetAutoAdoptRequest>,
     ) -> std::result::Result<CallToolResult, McpError> {
+        let mut settings = self
+            .process_manager
+            .get_settings()
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let workspaces = &mut settings.auto_adopt_workspaces;
+        workspaces.retain(|w| w != &workspace);
+        if enabled {
+            workspaces.push(workspace);
+        }
+        let workspaces = workspaces.clone();
         self.process_manager
-            .remove_process(id.clone())
+            .save_settings(settings)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&serde_json::json!({
+                "auto_adopt_workspaces": workspaces
+            }))
+            .unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "List every setting key with its type (boolean, integer, number, string, enum, array, object), whether it may be null, allowed range or values, default and description"
+    )]
+    async fn get_settings_schema(&self) -> std::result::Result<CallToolResult, McpError> {
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&settings::schema()).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Get the current value of one setting by its dotted key (e.g. mdns.enabled), or of every key when key is omitted. Secret-looking values are masked"
+    )]
+    async fn get_setting(
+        &self,
+        Parameters(GetSettingRequest { key }): Parameters<GetSettingRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let current = self
+            .process_manager
+            .get_settings()
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let mut value = match key {
+            Some(key) => serde_json::to_value(
+                settings::get_with_default(&current, &key)
+                    .map_err(|e| McpError::invalid_params(e, None))?,
+            )
+            .unwrap(),
+            None => serde_json::Value::Object(settings::get_all(&current)),
+        };
+        process::debug_bundle::redact_json(&mut value);
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&value).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Change one setting by its dotted key (e.g. mdns.enabled, approvals.confirm_timeout_secs). The value is checked against the key's type, range and allowed values before saving; subsystems and the web console are notified of the change immediately. Returns the old and new value"
+    )]
+    async fn set_setting(
+        &self,
+        Parameters(SetSettingRequest { key, value }): Parameters<SetSettingRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let mut change = self
+            .process_manager
+            .set_setting(&key, value)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        process::debug_bundle::redact_json(&mut change.old_value);
+        process::debug_bundle::redact_json(&mut change.new_value);
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&change).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Restart the web console server with the same address and options, re-reading auth.yaml (login, webhooks) and TLS certificates. Open browser connections may need a reload"
+    )]
+    async fn restart_web_server(&self) -> std::result::Result<CallToolResult, McpError> {
+        let restart = server_admin::restart_web_server()
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&restart).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Rotate the server's own log file (--log-file): the current file is renamed with a timestamp suffix, logging continues in a fresh file, and only the newest 5 rotated files are kept"
+    )]
+    async fn rotate_server_log(&self) -> std::result::Result<CallToolResult, McpError> {
+        let rotation =
+            server_admin::rotate_log_file().map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&rotation).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Clear the server's caches: the last get_suggestions result (suggestion numbers must be fetched again) and the cached CI runs"
+    )]
+    async fn clear_server_caches(&self) -> std::result::Result<CallToolResult, McpError> {
+        let cleared = server_admin::ClearedCaches {
+            suggestions: std::mem::take(&mut *self.last_suggestions.lock().await).len(),
+            ci_runs: self.ci_monitor.clear_cache().await,
+        };
+        server_admin::record_cleared_caches(&cleared);
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&cleared).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Restart the Vantage server itself in place (same PID and arguments) without stopping managed processes: running processes are handed over and adopted by the new server, which resumes capturing their output. All definitions are written to the snapshot first. The MCP session ends, so reconnect the client afterwards. Not available in safe mode"
+    )]
+    async fn restart_server(&self) -> std::result::Result<CallToolResult, McpError> {
+        let restart = server_admin::restart_server(&self.process_manager)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&restart).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Create a support bundle (tar.gz) with the server version, redacted settings, process list with states, diagnostics, event history, server logs and process output, optionally limited to the last N minutes"
+    )]
+    async fn create_debug_bundle(
+        &self,
+        Parameters(CreateDebugBundleRequest {
+            since_minutes,
+            output_path,
+        }): Parameters<CreateDebugBundleRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let bundle = self
+            .process_manager
+            .create_debug_bundle(since_minutes, output_path.map(std::path::PathBuf::from))
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let json = serde_json::to_string_pretty(&bundle)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        description = "Get a process's timeline ordered by start time: runs with outcome and duration, restarts with the downtime before them, periods of failed health checks, pauses and maintenance windows, errors, crash loops and chaos injections, definition changes, and CI failures that happened during a run. Includes totals for runs, restarts, failed runs, running and unhealthy time. Only what the server retains in memory is included"
+    )]
+    async fn get_process_timeline(
+        &self,
+        Parameters(GetProcessTimelineRequest { id, from, to }): Parameters<
+            GetProcessTimelineRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let window = parse_time_window(OutputWindowRequest { from, to })
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let timeline = self
+            .process_manager
+            .get_process_timeline(&id, window)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&timeline).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Export run history for analysis in notebooks: runs (start, end, duration, exit code, outcome), events and 10-second output line/error counts, limited to a time range, as CSV files or Parquet (when built with the parquet-export feature). Only what the server retains in memory is exported"
+    )]
+    async fn export_history(
+        &self,
+        Parameters(ExportHistoryRequest {
+            from,
+            to,
+            format,
+            output_dir,
+        }): Parameters<ExportHistoryRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let window = parse_time_window(OutputWindowRequest { from, to })
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let export = self
+            .process_manager
+            .export_history(window, format, output_dir.map(std::path::PathBuf::from))
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&export).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Create and register a new process")]
+    async fn create_process(
+        &self,
+        Parameters(CreateProcessRequest {
+            id,
+            command,
+            args,
+            env,
+            sensitive_env,
+            cwd,
+            auto_start_on_restore,
+            tags,
+            idle_detection,
+            health_check,
+            tmux,
+            repl,
+            log_sources,
+            log_sinks,
+            redaction,
+            metadata,
+            runbook,
+            startup,
+            env_loader,
+            output_recording,
+            assertions,
+            restart_policy,
+            group,
+            depends_on,
+            parameters,
+            keep_stdin_open,
+            run_as,
+            resource_limits,
+            container,
+        }): Parameters<CreateProcessRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        check_secret_env(&self.permissions, &env, &sensitive_env)
+            .map_err(|e| McpError::invalid_request(e, None))?;
+        let cwd_path = cwd.map(std::path::PathBuf::from);
+
+        // Create the process
+        self.process_manager
+            .create_process_with_options(
+                id.clone(),
+                command,
+                args,
+                env,
+                cwd_path,
+                ProcessOptions {
+                    auto_start_on_restore,
+                    tags,
+                    idle_detection,
+                    health_check,
+                    tmux,
+                    repl,
+                    log_sources,
+                    log_sinks,
+                    redaction,
+                    metadata,
+                    runbook,
+                    startup,
+                    env_loader,
+                    output_recording,
+                    assertions,
+                    restart_policy,
+                    group,
+                    depends_on,
+                    parameters,
+                    keep_stdin_open,
+                    run_as,
+                    resource_limits,
+                    container,
+                    sensitive_env,
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Process '{id}' created successfully"
+        ))]))
+    }
+
+    #[tool(description = "Start a registered process")]
+    async fn start_process(
+        &self,
+        Parameters(StartProcessRequest { id }): Parameters<StartProcessRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let crash_loop = self
+            .process_manager
+            .get_process_status(id.clone())
+            .await
+            .ok()
+            .and_then(|status| status.crash_loop);
+
+        let pid = self
+            .process_manager
+            .start_process(id.clone())
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        let mut message = format!("Process '{id}' started with PID {pid}");
+        if let Some(info) = crash_loop {
+            message.push_str(&format!(
+                "\nWarning: crash loop detected. {}",
+                info.suggestion
+            ));
+        }
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(
+        description = "Zero-downtime restart of a service listening on a port: starts a second instance (the process or its `<id>-green` copy, whichever is not live) with the port environment variable set to another port, waits until it accepts connections, switches the reverse proxy in front of the service to it, then stops the old instance after drain_secs. The first call puts the proxy on proxy_port in front of the running process; clients should connect to that port. If the new instance does not become ready it is stopped and traffic stays on the old one. get_process_status shows the proxy route"
+    )]
+    async fn rolling_restart(
+        &self,
+        Parameters(RollingRestartRequest {
+            id,
+            proxy_port,
+            proxy_host,
+            port_env,
+            standby_port,
+            ready_timeout_secs,
+            drain_secs,
+        }): Parameters<RollingRestartRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let options = RollingRestartOptions {
+            proxy_port,
+            proxy_host,
+            port_env,
+            standby_port,
+            ready_timeout_secs,
+            drain_secs,
+        };
+        let report = self
+            .process_manager
+            .rolling_restart(id, options)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Start a rarely-used service on demand: Vantage listens on listen_port in place of the process and, on the first incoming connection, starts it, waits until it accepts connections on backend_port (passed through port_env), then proxies the connection. The URL stays valid while the process is stopped. With idle_stop_secs the process is stopped again once it has had no connections for that long (only when lazy start started it). Omit listen_port to turn lazy start off. get_process_status shows the listener, connection counts and the last activation error"
+    )]
+    async fn set_lazy_start(
+        &self,
+        Parameters(SetLazyStartRequest {
+            id,
+            listen_port,
+            host,
+            backend_port,
+            port_env,
+            ready_timeout_secs,
+            idle_stop_secs,
+        }): Parameters<SetLazyStartRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let config = listen_port.map(|listen_port| LazyStartConfig {
+            listen_port,
+            host,
+            backend_port,
+            port_env,
+            ready_timeout_secs,
+            idle_stop_secs,
+        });
+        let status = self
+            .process_manager
+            .set_lazy_start(id.clone(), config)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        let message = match status {
+            Some(status) => serde_json::to_string_pretty(&status).unwrap(),
+            None => format!("Lazy start turned off for process '{id}'"),
+        };
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(
+        description = "Start a process with values for the parameters declared in its definition (e.g. filter for `cargo test {{filter}}`). The values are substituted into the args for this run only and recorded in its run history"
+    )]
+    async fn start_process_with_params(
+        &self,
+        Parameters(StartProcessWithParamsRequest { id, params }): Parameters<
+            StartProcessWithParamsRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let pid = self
+            .process_manager
+            .start_process_with_params(id.clone(), params.clone())
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        let mut message = format!("Process '{id}' started with PID {pid}");
+        if !params.is_empty() {
+            let mut values: Vec<String> = params
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect();
+            values.sort();
+            message.push_str(&format!(" ({})", values.join(", ")));
+        }
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(description = "Stop a running process")]
+    async fn stop_process(
+        &self,
+        Parameters(McpStopProcessRequest {
+            id,
+            grace_period_ms,
+        }): Parameters<McpStopProcessRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .stop_process(id.clone(), grace_period_ms)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Process '{id}' stopped successfully"
+        ))]))
+    }
+
+    #[tool(
+        description = "Restart a process in one step: stop it (SIGTERM, then SIGKILL after grace_period_ms, default 5000), wait until it has fully exited, and start it again. Automatic restarts are held off meanwhile, so monitors cannot start it first. A stopped process is simply started. Returns the new PID"
+    )]
+    async fn restart_process(
+        &self,
+        Parameters(RestartProcessRequest {
+            id,
+            grace_period_ms,
+        }): Parameters<RestartProcessRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let pid = self
+            .process_manager
+            .restart_process(id.clone(), grace_period_ms)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Process '{id}' restarted with PID {pid}"
+        ))]))
+    }
+
+    #[tool(
+        description = "Temporarily freeze a running process with SIGSTOP (containers are paused) without losing its state. Paused processes are not treated as idle or failed. Not available on Windows; use stop_process there"
+    )]
+    async fn pause_process(
+        &self,
+        Parameters(PauseProcessRequest { id }): Parameters<PauseProcessRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .pause_process(id.clone())
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Process '{id}' paused; use resume_process to continue it"
+        ))]))
+    }
+
+    #[tool(description = "Resume a process paused with pause_process (SIGCONT)")]
+    async fn resume_process(
+        &self,
+        Parameters(ResumeProcessRequest { id }): Parameters<ResumeProcessRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .resume_process(id.clone())
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Process '{id}' resumed"
+        ))]))
+    }
+
+    #[tool(
+        description = "Get process status and metrics, including a report of the last failure (stderr tail, exit code or signal, resource usage)"
+    )]
+    async fn get_process_status(
+        &self,
+        Parameters(GetProcessStatusRequest { id }): Parameters<GetProcessStatusRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .get_process_status(id.clone())
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        let mut status = mask_secrets(Some(&self.permissions), &status);
+        // 手順書はファイルの内容まで返す（読めなければ理由を返す）
+        if let Some(runbook) = self.process_manager.get_runbook(&id).await.transpose() {
+            status["runbook"] = match runbook {
+                Ok(content) => serde_json::to_value(content).unwrap_or_default(),
+                Err(e) => serde_json::json!({ "error": e }),
+            };
+        }
+        if let Some(route) = self.process_manager.proxy_route(&id).await {
+            status["proxy_route"] = serde_json::to_value(route).unwrap_or_default();
+        }
+        let json = serde_json::to_string_pretty(&status).map_err(|e| McpError {
+            message: format!("Failed to serialize status: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        description = "Get output rates of a process: stdout/stderr lines and error lines (ERROR, panic, Traceback...) per minute, the stderr share, and whether they are rising or falling compared with the previous window of the same length. Includes the health check verdict when the process has a health_check configured"
+    )]
+    async fn get_process_metrics(
+        &self,
+        Parameters(GetProcessMetricsRequest { id, window_secs }): Parameters<
+            GetProcessMetricsRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let metrics = self
+            .process_manager
+            .get_process_metrics(&id, window_secs)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let json = serde_json::to_string_pretty(&metrics).map_err(|e| McpError {
+            message: format!("Failed to serialize metrics: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        description = "Get process output (stdout/stderr). With from_bookmark and/or to_bookmark, returns only the output after, before or between bookmarks set with add_output_bookmark, as JSON with the bookmarks and whether lines were cut off. With follow (or cursor/since), returns JSON with the lines after a cursor or time, oldest first, and a cursor to pass back to poll for new lines without re-reading them. With structured, lines are JSON objects with seq, timestamp and stream (stdout/stderr); stream Both always interleaves stdout and stderr in the order they were written"
+    )]
+    async fn get_process_output(
+        &self,
+        Parameters(GetProcessOutputRequest {
+            id,
+            stream,
+            lines,
+            timestamps,
+            from_bookmark,
+            to_bookmark,
+            follow,
+            cursor,
+            since,
+            structured,
+        }): Parameters<GetProcessOutputRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if follow || cursor.is_some() || since.is_some() {
+            let since = since
+                .map(|since| {
+                    chrono::DateTime::parse_from_rfc3339(&since)
+                        .map(|since| since.with_timezone(&chrono::Utc))
+                        .map_err(|e| {
+                            McpError::invalid_params(
+                                format!("since must be an RFC 3339 time: {e}"),
+                                None,
+                            )
+                        })
+                })
+                .transpose()?;
+            let output = if structured {
+                self.process_manager
+                    .follow_process_lines(&id, stream, cursor, since, lines)
+                    .await
+                    .map(|output| serde_json::to_string_pretty(&output).unwrap())
+            } else {
+                self.process_manager
+                    .follow_process_output(&id, stream, cursor, since, lines, timestamps)
+                    .await
+                    .map(|output| serde_json::to_string_pretty(&output).unwrap())
+            }
+            .map_err(|e| McpError::invalid_params(e, None))?;
+            return Ok(CallToolResult::success(vec![Content::text(output)]));
+        }
+        if from_bookmark.is_some() || to_bookmark.is_some() {
+            let output = self
+                .process_manager
+                .get_output_between_bookmarks(
+                    &id,
+                    stream,
+                    from_bookmark.as_deref(),
+                    to_bookmark.as_deref(),
+                    lines,
+                    timestamps,
+                )
+                .await
+                .map_err(|e| McpError {
+                    message: e.into(),
+                    code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                    data: None,
+                })?;
+            return Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&output).unwrap(),
+            )]));
+        }
+
+        if structured {
+            let output = self
+                .process_manager
+                .get_process_output_lines(id, stream, lines)
+                .await
+                .map_err(|e| McpError::invalid_params(e, None))?;
+            return Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&output).unwrap(),
+            )]));
+        }
+
+        let output = if timestamps {
+            self.process_manager
+                .get_process_output_lines(id, stream, lines)
+                .await
+                .map(|lines| lines.iter().map(|l| l.with_timestamp()).collect())
+        } else {
+            self.process_manager
+                .get_process_output(id, stream, lines)
+                .await
+        }
+        .map_err(|e| McpError {
+            message: e.into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            output.join("\n"),
+        )]))
+    }
+
+    #[tool(
+        description = "Bookmark the current end of a process's output with a name (e.g. \"before migration\"), so output after, before or between bookmarks can be fetched later with get_process_output"
+    )]
+    async fn add_output_bookmark(
+        &self,
+        Parameters(AddOutputBookmarkRequest { id, name, note }): Parameters<
+            AddOutputBookmarkRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let bookmark = self
+            .process_manager
+            .add_output_bookmark(&id, name, note)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&bookmark).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "List the output bookmarks of a process, oldest first")]
+    async fn list_output_bookmarks(
+        &self,
+        Parameters(ListOutputBookmarksRequest { id }): Parameters<ListOutputBookmarksRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let bookmarks = self
+            .process_manager
+            .list_output_bookmarks(&id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&bookmarks).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Remove an output bookmark from a process")]
+    async fn remove_output_bookmark(
+        &self,
+        Parameters(RemoveOutputBookmarkRequest { id, name }): Parameters<
+            RemoveOutputBookmarkRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .remove_output_bookmark(&id, &name)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Removed bookmark '{name}' from process '{id}'"
+        ))]))
+    }
+
+    #[tool(
+        description = "Run code in a REPL process (created with `repl`) and return only the output of that execution"
+    )]
+    async fn execute_in_repl(
+        &self,
+        Parameters(ExecuteInReplRequest {
+            id,
+            code,
+            timeout_ms,
+        }): Parameters<ExecuteInReplRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let timeout = std::time::Duration::from_millis(timeout_ms.unwrap_or(30_000));
+        let execution = self
+            .process_manager
+            .execute_in_repl(id, code, timeout)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&execution).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Write input to the stdin of a running process created with keep_stdin_open, e.g. to answer a prompt. Text is sent as UTF-8 with a trailing newline; use encoding `base64` for raw bytes and `close` to send EOF. Read the response with get_process_output"
+    )]
+    async fn send_process_input(
+        &self,
+        Parameters(SendProcessInputRequest {
+            id,
+            input,
+            encoding,
+            newline,
+            close,
+        }): Parameters<SendProcessInputRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let bytes = process::stdin::encode_input(&input, encoding, newline)
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let sent = self
+            .process_manager
+            .send_process_input(id.clone(), bytes, close)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        let mut message = format!("Sent {sent} bytes to the stdin of '{id}'");
+        if close {
+            message.push_str(" and closed it");
+        }
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(
+        description = "Sample the output of a very chatty process: keep every Nth line plus all lines matching error patterns. Takes effect immediately; omit every_n to turn it off"
+    )]
+    async fn set_output_sampling(
+        &self,
+        Parameters(SetOutputSamplingRequest {
+            id,
+            every_n,
+            keep_patterns,
+        }): Parameters<SetOutputSamplingRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let sampling = every_n.map(|every_n| OutputSampling {
+            every_n,
+            keep_patterns,
+        });
+        let status = self
+            .process_manager
+            .set_output_sampling(id.clone(), sampling)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        let message = match status {
+            Some(status) => format!(
+                "Output sampling enabled for process '{id}': keeping 1 of every {} lines plus lines matching {} pattern(s)",
+                status.config.every_n,
+                if status.config.keep_patterns.is_empty() {
+                    "the default error".to_string()
+                } else {
+                    status.config.keep_patterns.len().to_string()
+                }
+            ),
+            None => format!("Output sampling disabled for process '{id}'"),
+        };
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(
+        description = "Forward the output of every process to syslog (UDP host:port), the Loki push API or a JSONL file, labeled with process_id, stream and workspace. Applies to processes started afterwards; sink health is shown in get_diagnostics"
+    )]
+    async fn set_log_sinks(
+        &self,
+        Parameters(SetLogSinksRequest { sinks }): Parameters<SetLogSinksRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let mut settings = self
+            .process_manager
+            .get_settings()
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let names: Vec<String> = sinks.iter().map(|sink| sink.name.clone()).collect();
+        settings.log_sinks = sinks;
+        self.process_manager
+            .save_settings(settings)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        let message = if names.is_empty() {
+            "Global log forwarding disabled".to_string()
+        } else {
+            format!(
+                "Forwarding the output of processes started from now on to {} sink(s): {}",
+                names.len(),
+                names.join(", ")
+            )
+        };
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(
+        description = "Mask secrets in captured output: each rule's regex matches are replaced by its mask (default [REDACTED], `$1` keeps capture groups) before lines are stored, forwarded to log sinks or returned by tools. Without id the global rules are replaced; with id the process's override (extra rules plus disabled global rule names) is replaced. Takes effect immediately; get_process_status counts redactions"
+    )]
+    async fn set_redaction_rules(
+        &self,
+        Parameters(SetRedactionRulesRequest { id, rules, disable }): Parameters<
+            SetRedactionRulesRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let invalid = |e: String| McpError {
+            message: e.into(),
+            code: rmcp::model::ErrorCode::INVALID_PARAMS,
+            data: None,
+        };
+        let count = rules.len();
+        let message = match id {
+            None => {
+                if !disable.is_empty() {
+                    return Err(invalid(
+                        "disable can only be used together with id".to_string(),
+                    ));
+                }
+                self.process_manager
+                    .set_redaction_rules(rules)
+                    .await
+                    .map_err(invalid)?;
+                format!("Applying {count} global redaction rule(s) to the output of every process")
+            }
+            Some(id) => {
+                let redaction = (!rules.is_empty() || !disable.is_empty())
+                    .then_some(RedactionOverride { disable, rules });
+                self.process_manager
+                    .set_process_redaction(id.clone(), redaction)
+                    .await
+                    .map_err(invalid)?;
+                format!("Applying {count} redaction rule(s) of its own to process '{id}'")
+            }
+        };
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(
+        description = "Attach recovery steps (a runbook) to a process: a Markdown file path (read on each view, relative to the process's cwd) or inline Markdown. Omit both to remove it. The runbook is returned by get_process_status, exposed as the MCP resource vantage://processes/<id>/runbook and shown in the web console"
+    )]
+    async fn set_runbook(
+        &self,
+        Parameters(SetRunbookRequest { id, path, text }): Parameters<SetRunbookRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let runbook = match (path, text) {
+            (Some(_), Some(_)) => {
+                return Err(McpError::invalid_params(
+                    "Specify either path or text, not both",
+                    None,
+                ));
+            }
+            (Some(path), None) => Some(Runbook::Path(path)),
+            (None, Some(text)) => Some(Runbook::Inline(text)),
+            (None, None) => None,
+        };
+        let message = match &runbook {
+            Some(Runbook::Path(path)) => format!("Runbook of process '{id}' set to {path}"),
+            Some(Runbook::Inline(_)) => format!("Runbook of process '{id}' set"),
+            None => format!("Runbook of process '{id}' removed"),
+        };
+        self.process_manager
+            .set_runbook(id, runbook)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(
+        description = "Spawn a process through its project's environment loader so it sees the same PATH and tool versions as your shell: `auto` detects mise.toml, .envrc or .tool-versions from the process's cwd upwards and uses whichever of mise, direnv or asdf is installed. The environment is loaded on every start (variables in the process's env take precedence) and the resolved toolchain versions are recorded in the run history shown by explain_run_environment. The environment is loaded once now to check it works; omit loader to turn it off"
+    )]
+    async fn set_env_loader(
+        &self,
+        Parameters(SetEnvLoaderRequest { id, loader }): Parameters<SetEnvLoaderRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let toolchain = self
+            .process_manager
+            .set_env_loader(id.clone(), loader)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let message = match (loader, toolchain) {
+            (None, _) => format!("Process '{id}' no longer loads a project environment"),
+            (Some(_), Some(toolchain)) => serde_json::to_string_pretty(&toolchain).unwrap(),
+            (Some(_), None) => format!(
+                "No mise, direnv or asdf configuration found for process '{id}'; it will be loaded once one is added to its working directory"
+            ),
+        };
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(
+        description = "Record every run of a process into a replayable file: each stdout/stderr line (after redaction, before sampling) with its time since start, plus code sent to a REPL when include_stdin is set. Applies from the next start; the newest `keep` recordings (default 10) are kept. List them with list_output_recordings and play them back with replay_output. Omit recording to stop recording"
+    )]
+    async fn set_output_recording(
+        &self,
+        Parameters(SetOutputRecordingRequest { id, recording }): Parameters<
+            SetOutputRecordingRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let enabled = recording.is_some();
+        self.process_manager
+            .set_output_recording(id.clone(), recording)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let message = if enabled {
+            format!("Process '{id}' will record its output from the next start")
+        } else {
+            format!("Process '{id}' no longer records its output")
+        };
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(description = "List the output recordings of a process, newest first")]
+    async fn list_output_recordings(
+        &self,
+        Parameters(ListOutputRecordingsRequest { id }): Parameters<ListOutputRecordingsRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let recordings = self
+            .process_manager
+            .list_output_recordings(&id)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&recordings).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Play back an output recording (the latest unless recording is given) with its original timing: speed 1 keeps the pauses, 10 is ten times faster, 0 returns it at once. While playing, each line is sent as a progress notification when the client passes a progress token; the whole replay is returned at the end, each line prefixed with its time since start and stream"
+    )]
+    async fn replay_output(
+        &self,
+        Parameters(ReplayOutputRequest {
+            id,
+            recording,
+            speed,
+            streams,
+        }): Parameters<ReplayOutputRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let recorded = self
+            .process_manager
+            .load_output_recording(&id, recording.as_deref())
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let lines: Vec<_> = recorded
+            .lines
+            .into_iter()
+            .filter(|line| streams.is_empty() || streams.contains(&line.stream))
+            .collect();
+        let progress_token = context.meta.get_progress_token();
+        let total = lines.len() as f64;
+        let mut sent = 0;
+        let mut replayed = Vec::with_capacity(lines.len());
+        let playback = process::recording::replay(&lines, speed.unwrap_or(1.0), |line| {
+            let text = process::recording::format_line(line);
+            sent += 1;
+            if let Some(token) = progress_token.clone() {
+                let peer = context.peer.clone();
+                let message = text.clone();
+                let progress = sent as f64;
+                tokio::spawn(async move {
+                    let _ = peer
+                        .notify_progress(ProgressNotificationParam {
+                            progress_token: token,
+                            progress,
+                            total: Some(total),
+                            message: Some(message),
+                        })
+                        .await;
+                });
+            }
+            replayed.push(text);
+        });
+        let cancelled = tokio::select! {
+            _ = playback => false,
+            _ = context.ct.cancelled() => true,
+        };
+
+        let header = &recorded.header;
+        let mut text = format!(
+            "Recording '{}' of '{}' (run {}, started {}): {} {}\n",
+            recorded.name,
+            header.process_id,
+            header.run,
+            header.started_at.to_rfc3339(),
+            header.command,
+            header.args.join(" ")
+        );
+        text.push_str(&replayed.join("\n"));
+        if cancelled {
+            text.push_str(&format!(
+                "\n(replay cancelled after {} of {} lines)",
+                replayed.len(),
+                lines.len()
+            ));
+        }
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        description = "Set the expected behavior of a process for run_checks: ready_within (a port accepts connections or a pattern appears within N seconds), no_output_match (a pattern does not appear within N seconds, stderr only if stderr_only) and exit_code (the process exits with the code, optionally within N seconds). Pass an empty list to remove them"
+    )]
+    async fn set_process_assertions(
+        &self,
+        Parameters(SetProcessAssertionsRequest { id, assertions }): Parameters<
+            SetProcessAssertionsRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let count = assertions.len();
+        self.process_manager
+            .set_process_assertions(id.clone(), assertions)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let message = if count == 0 {
+            format!("Removed the assertions of process '{id}'")
+        } else {
+            format!("Process '{id}' now has {count} assertion(s)")
+        };
+        Ok(CallToolResult::success(vec![Content::text(message)]))
+    }
+
+    #[tool(
+        description = "Run local smoke checks: restart each process with assertions (all of them, or process_ids) in parallel, watch its output, ports and exit until every assertion is decided or timeout_secs passes, then stop it unless keep_running. Returns a pass/fail report per assertion with the reason and when it was decided"
+    )]
+    async fn run_checks(
+        &self,
+        Parameters(RunChecksRequest {
+            process_ids,
+            timeout_secs,
+            keep_running,
+        }): Parameters<RunChecksRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let report = self
+            .process_manager
+            .run_checks(process_ids, timeout_secs, keep_running)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Chaos testing: SIGKILL a running process (random among a tag when no id), optionally restarting it later. Requires chaos mode in the settings"
+    )]
+    async fn chaos_kill(
+        &self,
+        Parameters(ChaosKillRequest {
+            id,
+            tag,
+            restart_after_secs,
+        }): Parameters<ChaosKillRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let target = ChaosTarget {
+            process_id: id,
+            tag,
+        };
+        let record = self
+            .process_manager
+            .chaos_kill(
+                target,
+                restart_after_secs.map(std::time::Duration::from_secs),
+            )
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&record).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Chaos testing: pause a running process with SIGSTOP and resume it with SIGCONT after the duration. Requires chaos mode in the settings"
+    )]
+    async fn chaos_pause(
+        &self,
+        Parameters(ChaosPauseRequest {
+            id,
+            tag,
+            duration_secs,
+        }): Parameters<ChaosPauseRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let target = ChaosTarget {
+            process_id: id,
+            tag,
+        };
+        let record = self
+            .process_manager
+            .chaos_pause(target, std::time::Duration::from_secs(duration_secs))
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&record).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Chaos testing: pause a random matching process on a fixed interval until the schedule is cancelled. Requires chaos mode in the settings"
+    )]
+    async fn chaos_schedule_pauses(
+        &self,
+        Parameters(ChaosSchedulePausesRequest {
+            id,
+            tag,
+            interval_secs,
+            pause_secs,
+        }): Parameters<ChaosSchedulePausesRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let target = ChaosTarget {
+            process_id: id,
+            tag,
+        };
+        let schedule = self
+            .process_manager
+            .chaos_schedule_pauses(
+                target,
+                std::time::Duration::from_secs(interval_secs),
+                std::time::Duration::from_secs(pause_secs),
+            )
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&schedule).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Cancel a chaos pause schedule")]
+    async fn chaos_cancel_schedule(
+        &self,
+        Parameters(ChaosCancelScheduleRequest { schedule_id }): Parameters<
+            ChaosCancelScheduleRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .chaos_cancel_schedule(&schedule_id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Cancelled chaos schedule '{schedule_id}'"
+        ))]))
+    }
+
+    #[tool(
+        description = "Show whether chaos mode is enabled, active pause schedules and recent chaos actions"
+    )]
+    async fn chaos_status(&self) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .chaos_status()
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Create a tunnel (ssh -L, ssh -R or a cloudflared quick tunnel) managed like a process. Link it to a process to show its state and public URL in that process's status"
+    )]
+    async fn create_tunnel(
+        &self,
+        Parameters(CreateTunnelRequest {
+            tunnel_id,
+            provider,
+            local_port,
+            ssh_host,
+            remote,
+            process_id,
+            public_url,
+            start,
+        }): Parameters<CreateTunnelRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let config = vantage_persistence::TunnelConfig {
+            tunnel_id,
+            provider,
+            local_port,
+            ssh_host,
+            remote,
+            process_id,
+            public_url,
+        };
+        let status = self
+            .process_manager
+            .create_tunnel(config, start.unwrap_or(true))
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Start a stopped or failed tunnel")]
+    async fn start_tunnel(
+        &self,
+        Parameters(TunnelIdRequest { tunnel_id }): Parameters<TunnelIdRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .start_tunnel(&tunnel_id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Stop a running tunnel")]
+    async fn stop_tunnel(
+        &self,
+        Parameters(TunnelIdRequest { tunnel_id }): Parameters<TunnelIdRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .stop_tunnel(&tunnel_id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Remove a tunnel, stopping it first if it is running")]
+    async fn remove_tunnel(
+        &self,
+        Parameters(TunnelIdRequest { tunnel_id }): Parameters<TunnelIdRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .remove_tunnel(&tunnel_id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Tunnel '{tunnel_id}' removed"
+        ))]))
+    }
+
+    #[tool(description = "List tunnels with their state, health and public URL")]
+    async fn list_tunnels(
+        &self,
+        Parameters(ListTunnelsRequest { process_id }): Parameters<ListTunnelsRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let tunnels = self
+            .process_manager
+            .list_tunnels(process_id.as_deref())
+            .await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&tunnels).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Register a Kubernetes pod or deployment from a dev cluster as a read-only external process: its pod status is polled, its logs are captured so get_process_output works with the workload ID, and local ports can be forwarded to a ready pod. Requires a build with the `kubernetes` feature"
+    )]
+    async fn register_kubernetes_workload(
+        &self,
+        Parameters(RegisterKubernetesWorkloadRequest {
+            workload_id,
+            kind,
+            name,
+            context,
+            namespace,
+            container,
+            port_forwards,
+            tags,
+        }): Parameters<RegisterKubernetesWorkloadRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let config = vantage_persistence::KubernetesWorkload {
+            workload_id,
+            context,
+            namespace,
+            kind,
+            name,
+            container,
+            port_forwards: port_forwards.unwrap_or_default(),
+            tags: tags.unwrap_or_default(),
+        };
+        let status = self
+            .process_manager
+            .register_kubernetes_workload(config)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Stop tracking a Kubernetes workload and close its port forwards. The cluster resources are left untouched"
+    )]
+    async fn remove_kubernetes_workload(
+        &self,
+        Parameters(KubernetesWorkloadIdRequest { workload_id }): Parameters<
+            KubernetesWorkloadIdRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .remove_kubernetes_workload(&workload_id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Kubernetes workload '{workload_id}' removed"
+        ))]))
+    }
+
+    #[tool(
+        description = "List registered Kubernetes workloads with their pods, readiness, restarts, log following and port-forward state"
+    )]
+    async fn list_kubernetes_workloads(&self) -> std::result::Result<CallToolResult, McpError> {
+        let workloads = self.process_manager.list_kubernetes_workloads().await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&workloads).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Chain processes: when `after` exits successfully (exit code 0, not stopped by request), start another process or create and start one from a template. Chains that would loop are rejected, and a chain that fires too often is suppressed for a while"
+    )]
+    async fn create_chain(
+        &self,
+        Parameters(CreateChainRequest {
+            chain_id,
+            after,
+            action,
+            enabled,
+        }): Parameters<CreateChainRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .create_chain(chain_id, after, action, enabled.unwrap_or(true))
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "List process chains with their last run and run count")]
+    async fn list_chains(
+        &self,
+        Parameters(ListChainsRequest { process_id }): Parameters<ListChainsRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let chains = self
+            .process_manager
+            .list_chains(process_id.as_deref())
+            .await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&chains).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Enable or disable a process chain without removing it")]
+    async fn set_chain_enabled(
+        &self,
+        Parameters(SetChainEnabledRequest { chain_id, enabled }): Parameters<
+            SetChainEnabledRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .set_chain_enabled(&chain_id, enabled)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Remove a process chain")]
+    async fn remove_chain(
+        &self,
+        Parameters(ChainIdRequest { chain_id }): Parameters<ChainIdRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .remove_chain(&chain_id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Chain '{chain_id}' removed"
+        ))]))
+    }
+
+    #[tool(
+        description = "Start a process on a cron schedule (minute hour day month weekday, server local time), e.g. \"0 * * * *\" to run a backup script every hour. A run is skipped while the process is still running, and each run is recorded as a scheduled_run event"
+    )]
+    async fn create_schedule(
+        &self,
+        Parameters(CreateScheduleRequest {
+            schedule_id,
+            process_id,
+            cron,
+            enabled,
+        }): Parameters<CreateScheduleRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .create_schedule(schedule_id, process_id, cron, enabled.unwrap_or(true))
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "List process schedules with their next run, run count and last outcome")]
+    async fn list_schedules(
+        &self,
+        Parameters(ListSchedulesRequest { process_id }): Parameters<ListSchedulesRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let schedules = self
+            .process_manager
+            .list_schedules(process_id.as_deref())
+            .await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&schedules).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Change a schedule's cron expression or turn it off or on")]
+    async fn update_schedule(
+        &self,
+        Parameters(UpdateScheduleRequest {
+            schedule_id,
+            cron,
+            enabled,
+        }): Parameters<UpdateScheduleRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .update_schedule(&schedule_id, cron, enabled)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Remove a process schedule")]
+    async fn remove_schedule(
+        &self,
+        Parameters(ScheduleIdRequest { schedule_id }): Parameters<ScheduleIdRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .remove_schedule(&schedule_id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Schedule '{schedule_id}' removed"
+        ))]))
+    }
+
+    #[tool(
+        description = "Create or replace a maintenance window. While it is active, covered processes (all processes when no processes, tags or workspaces are given) are not restarted automatically, idle detection takes no action, exits do not count towards crash loop detection and image rebuilds are deferred. Start and end are reported as maintenance_started / maintenance_ended events"
+    )]
+    async fn create_maintenance_window(
+        &self,
+        Parameters(CreateMaintenanceWindowRequest {
+            name,
+            schedule,
+            processes,
+            tags,
+            workspaces,
+            reason,
+        }): Parameters<CreateMaintenanceWindowRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .set_maintenance_window(MaintenanceWindow {
+                name,
+                schedule,
+                processes,
+                tags,
+                workspaces,
+                reason,
+            })
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "List maintenance windows with the current period, the next start and the processes they cover"
+    )]
+    async fn list_maintenance_windows(&self) -> std::result::Result<CallToolResult, McpError> {
+        let windows = self
+            .process_manager
+            .list_maintenance_windows()
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&windows).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Remove a maintenance window")]
+    async fn remove_maintenance_window(
+        &self,
+        Parameters(MaintenanceWindowNameRequest { name }): Parameters<MaintenanceWindowNameRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.process_manager
+            .remove_maintenance_window(&name)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Maintenance window '{name}' removed"
+        ))]))
+    }
+
+    #[tool(
+        description = "Compute durations between output lines matching marker patterns (e.g. build -> ready)"
+    )]
+    async fn get_process_phases(
+        &self,
+        Parameters(GetProcessPhasesRequest { id, markers }): Parameters<GetProcessPhasesRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let phases = self
+            .process_manager
+            .get_process_phases(id, markers)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        let json = serde_json::to_string_pretty(&phases).map_err(|e| McpError {
+            message: format!("Failed to serialize phases: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        description = "List network connections of a running process and its children, with an optional I/O throughput estimate"
+    )]
+    async fn get_process_network(
+        &self,
+        Parameters(GetProcessNetworkRequest { id, sample_ms }): Parameters<
+            GetProcessNetworkRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let sample_ms = sample_ms.map(|ms| ms.min(5000));
+        let network = self
+            .process_manager
+            .get_process_network(id, sample_ms)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        let json = serde_json::to_string_pretty(&network).map_err(|e| McpError {
+            message: format!("Failed to serialize network info: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        description = "Compare a process's stored environment variables with the ones its running child was started with, listing added/removed/changed variables and whether a restart is needed"
+    )]
+    async fn diff_process_env(
+        &self,
+        Parameters(DiffProcessEnvRequest { id }): Parameters<DiffProcessEnvRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let diff = self
+            .process_manager
+            .diff_process_env(id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        let diff = mask_secrets(Some(&self.permissions), &diff);
+        let json = serde_json::to_string_pretty(&diff).map_err(|e| McpError {
+            message: format!("Failed to serialize environment diff: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        description = "Explain the environment a process run was started with: resolved command, working directory, OS/kernel/host/user, locale, PATH, tool versions (node, python, cargo, ...) and all environment variables with secrets masked. Also lists what changed since another run (default: the previous one), to track down 'works on my machine' differences"
+    )]
+    async fn explain_run_environment(
+        &self,
+        Parameters(ExplainRunEnvironmentRequest {
+            id,
+            run,
+            compare_with,
+            include_env,
+        }): Parameters<ExplainRunEnvironmentRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let text = self
+            .process_manager
+            .explain_run_environment(id, run, compare_with, include_env.unwrap_or(true))
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        let text = if self.permissions.allows(Capability::Secrets, Access::Read) {
+            text
+        } else {
+            process::debug_bundle::redact_text(&text)
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        description = "Compare a process's output between two time windows, or before and after a restart, summarizing new and resolved error messages and messages that appeared or disappeared instead of returning full logs. Numbers, IDs and timestamps are ignored when matching lines"
+    )]
+    async fn diff_output_windows(
+        &self,
+        Parameters(DiffOutputWindowsRequest {
+            id,
+            before,
+            after,
+            restart,
+            limit,
+        }): Parameters<DiffOutputWindowsRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let invalid = |message: String| McpError {
+            message: message.into(),
+            code: rmcp::model::ErrorCode::INVALID_PARAMS,
+            data: None,
+        };
+        let windows = match (before, after) {
+            (Some(before), Some(after)) => Some((
+                parse_time_window(before).map_err(invalid)?,
+                parse_time_window(after).map_err(invalid)?,
+            )),
+            (None, None) => None,
+            _ => {
+                return Err(invalid(
+                    "Specify both 'before' and 'after' windows, or neither to compare around a restart"
+                        .to_string(),
+                ));
+            }
+        };
+
+        let diff = self
+            .process_manager
+            .diff_output_windows(id, windows, restart, limit)
+            .await
+            .map_err(invalid)?;
+        let diff = mask_secrets(Some(&self.permissions), &diff);
+
+        let json = serde_json::to_string_pretty(&diff).map_err(|e| McpError {
+            message: format!("Failed to serialize output diff: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        description = "List all managed processes, optionally narrowed by a filter expression such as `state == running && tag in [\"backend\"] && started_at > now()-2h` (operators: == != < <= > >= in contains ~ !~, combined with && || ! and parentheses)"
+    )]
+    async fn list_processes(
+        &self,
+        Parameters(ListProcessesRequest { filter, expression }): Parameters<ListProcessesRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let processes = self
+            .process_manager
+            .list_processes_matching(filter, expression.as_deref())
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+        let processes = mask_secrets(Some(&self.permissions), &processes);
+
+        let json = serde_json::to_string_pretty(&processes).map_err(|e| McpError {
+            message: format!("Failed to serialize processes: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        description = "Get recent process lifecycle events (newest first), optionally narrowed by a filter expression such as `type in [process_error, crash_loop_detected] && timestamp > now()-1h`"
+    )]
+    async fn get_events(
+        &self,
+        Parameters(GetEventsRequest { expression, limit }): Parameters<GetEventsRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let events = self
+            .process_manager
+            .get_events(expression.as_deref(), limit.unwrap_or(100))
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+        let events = mask_secrets(Some(&self.permissions), &events);
+
+        let json = serde_json::to_string_pretty(&events).map_err(|e| McpError {
+            message: format!("Failed to serialize events: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        description = "Start every stopped process matching a filter expression (same syntax as list_processes). Use dry_run to see which processes match first"
+    )]
+    async fn start_processes(
+        &self,
+        Parameters(StartProcessesRequest {
+            expression,
+            dry_run,
+        }): Parameters<StartProcessesRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let report = self
+            .process_manager
+            .start_matching_processes(&expression, dry_run)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Stop every running process matching a filter expression (same syntax as list_processes). Use dry_run to see which processes match first"
+    )]
+    async fn stop_processes(
+        &self,
+        Parameters(StopProcessesRequest {
+            expression,
+            grace_period_ms,
+            dry_run,
+        }): Parameters<StopProcessesRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let report = self
+            .process_manager
+            .stop_matching_processes(&expression, grace_period_ms, dry_run)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Start every stopped process in a group (set with `group` on create_process) in startup order: lower `startup.priority` first, waiting for each batch to be ready. Use dry_run to list the members first"
+    )]
+    async fn start_group(
+        &self,
+        Parameters(StartGroupRequest { group, dry_run }): Parameters<StartGroupRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let report = self
+            .process_manager
+            .start_group(&group, dry_run)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Stop every running process in a group in reverse startup order (the processes started last are stopped first). Use dry_run to list the members first"
+    )]
+    async fn stop_group(
+        &self,
+        Parameters(StopGroupRequest {
+            group,
+            grace_period_ms,
+            dry_run,
+        }): Parameters<StopGroupRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let report = self
+            .process_manager
+            .stop_group(&group, grace_period_ms, dry_run)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Get the state, PID and health of every process in a group in startup order, and whether the whole group is up"
+    )]
+    async fn get_group_status(
+        &self,
+        Parameters(GetGroupStatusRequest { group }): Parameters<GetGroupStatusRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let status = self
+            .process_manager
+            .get_group_status(&group)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&status).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Get the status of an operation waiting for human approval, including who decided and the execution result"
+    )]
+    async fn get_approval(
+        &self,
+        Parameters(GetApprovalRequest { approval_id }): Parameters<GetApprovalRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let approval = self
+            .process_manager
+            .get_approval(&approval_id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&approval).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "List the notifications inbox, newest first: process failures, crash loops, failed health checks, failed CI runs and operations waiting for approval. Unlike events, notifications stay until they are marked as read with mark_read"
+    )]
+    async fn list_notifications(
+        &self,
+        Parameters(ListNotificationsRequest {
+            unread_only,
+            kind,
+            limit,
+        }): Parameters<ListNotificationsRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let notifications = self
+            .process_manager
+            .list_notifications(unread_only, kind, Some(limit.unwrap_or(50)))
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let unread = self
+            .process_manager
+            .unread_notification_count()
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let response = serde_json::json!({
+            "unread": unread,
+            "notifications": notifications,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Show which notification routing rule a sample notification would hit and where it would be delivered (Slack, email, desktop or inbox only). Rules are evaluated in order and the first match wins; each evaluated rule lists the conditions that did not match. Pass routes to try rules before saving them"
+    )]
+    async fn test_notification_route(
+        &self,
+        Parameters(TestNotificationRouteRequest {
+            kind,
+            severity,
+            process_id,
+            tags,
+            workspace,
+            routes,
+        }): Parameters<TestNotificationRouteRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let result = self
+            .process_manager
+            .test_notification_route(kind, severity, process_id, tags, workspace, routes)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Mark notifications in the inbox as read, by ID or all at once")]
+    async fn mark_read(
+        &self,
+        Parameters(MarkReadRequest { ids, all }): Parameters<MarkReadRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if !all && ids.is_empty() {
+            return Err(McpError::invalid_params(
+                "Specify notification ids or set all to true",
+                None,
+            ));
+        }
+        let marked = self
+            .process_manager
+            .mark_notifications_read((!all).then_some(ids.as_slice()))
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let unread = self
+            .process_manager
+            .unread_notification_count()
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&serde_json::json!({
+                "marked": marked,
+                "unread": unread,
+            }))
+            .unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Remove a process from management")]
+    async fn remove_process(
+        &self,
+        Parameters(RemoveProcessRequest { id }): Parameters<RemoveProcessRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if let Some(pending) = self
+            .pending_approval(
+                ApprovalOperation::RemoveProcess { id: id.clone() },
+                &context,
+            )
+            .await?
+        {
+            return Ok(pending);
+        }
+        self.process_manager
+            .remove_process(id.clone())
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Process '{id}' removed successfully"
+        ))]))
+    }
+
+    #[tool(description = "Export all processes to a JSON file for backup/persistence")]
+    async fn export_processes(
+        &self,
+        Parameters(ExportProcessesRequest { file_path }): Parameters<ExportProcessesRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let path = self
+            .process_manager
+            .export_processes(file_path)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Processes exported successfully to {path}"
+        ))]))
+    }
+
+    #[tool(description = "Import processes from a JSON file")]
+    async fn import_processes(
+        &self,
+        Parameters(ImportProcessesRequest { file_path }): Parameters<ImportProcessesRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if let Some(pending) = self
+            .pending_approval(
+                ApprovalOperation::ImportProcesses {
+                    file_path: file_path.clone(),
+                },
+                &context,
+            )
+            .await?
+        {
+            return Ok(pending);
+        }
+        self.process_manager
+            .import_processes(&file_path)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Processes imported successfully from {file_path}"
+        ))]))
+    }
+
+    #[tool(
+        description = "Create a snapshot of the entire database (processes, templates, clipboard)"
+    )]
+    async fn create_snapshot(&self) -> std::result::Result<CallToolResult, McpError> {
+        let path = self
+            .process_manager
+            .create_snapshot()
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Snapshot created successfully at {path}"
+        ))]))
+    }
+
+    #[tool(
+        description = "Remove every process matching a filter expression (same syntax as list_processes), stopping running ones first. A pre-op snapshot is taken automatically so the removal can be reverted with undo_last_operation. Use dry_run to see which processes match first"
+    )]
+    async fn remove_processes(
+        &self,
+        Parameters(RemoveProcessesRequest {
+            expression,
+            dry_run,
+        }): Parameters<RemoveProcessesRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        // 一致するものを確かめるだけなら承認はいらない
+        if !dry_run
+            && let Some(pending) = self
+                .pending_approval(
+                    ApprovalOperation::RemoveProcesses {
+                        expression: expression.clone(),
+                    },
+                    &context,
+                )
+                .await?
+        {
+            return Ok(pending);
+        }
+        let report = self
+            .process_manager
+            .remove_matching_processes(&expression, dry_run)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Tidy up stopped processes by moving them to the recycle bin instead of deleting them. Filters: older_than (time since they stopped, e.g. \"7d\"), id_pattern (glob), tag, include_failed and include_auto_start (both off by default). Use dry_run to preview the matches; the result reports matched, recycled and failed counts. Recycled processes can be brought back with restore_from_recycle_bin"
+    )]
+    async fn cleanup_processes(
+        &self,
+        Parameters(CleanupProcessesRequest {
+            older_than,
+            id_pattern,
+            tag,
+            include_failed,
+            include_auto_start,
+            dry_run,
+        }): Parameters<CleanupProcessesRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let filter = process::recycle_bin::CleanupFilter {
+            include_failed,
+            older_than,
+            id_pattern,
+            tag,
+            include_auto_start,
+        };
+        let report = self
+            .process_manager
+            .cleanup_processes(&filter, dry_run)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "List process definitions in the recycle bin (moved there by cleanup_processes), newest first. The last 100 are kept"
+    )]
+    async fn list_recycle_bin(&self) -> std::result::Result<CallToolResult, McpError> {
+        let items = self
+            .process_manager
+            .list_recycle_bin()
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+        let items = mask_secrets(Some(&self.permissions), &items);
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&items).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Restore a process from the recycle bin. It is recreated in the stopped state; fails if a process with the same id exists"
+    )]
+    async fn restore_from_recycle_bin(
+        &self,
+        Parameters(RestoreFromRecycleBinRequest { id }): Parameters<RestoreFromRecycleBinRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let item = self
+            .process_manager
+            .restore_from_recycle_bin(&id)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+        let item = mask_secrets(Some(&self.permissions), &item);
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&item).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Permanently delete process definitions from the recycle bin: all of them, or only those of one process id"
+    )]
+    async fn empty_recycle_bin(
+        &self,
+        Parameters(EmptyRecycleBinRequest { id }): Parameters<EmptyRecycleBinRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if let Some(pending) = self
+            .pending_approval(
+                ApprovalOperation::EmptyRecycleBin { id: id.clone() },
+                &context,
+            )
+            .await?
+        {
+            return Ok(pending);
+        }
+        let purged = self
+            .process_manager
+            .empty_recycle_bin(id.as_deref())
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::json!({ "purged": purged }).to_string(),
+        )]))
+    }
+
+    #[tool(
+        description = "List the safety snapshots taken automatically before risky operations (import_yaml, import_processes, import_vscode_tasks, restore_snapshot, remove_processes), newest first"
+    )]
+    async fn list_pre_op_snapshots(&self) -> std::result::Result<CallToolResult, McpError> {
+        let snapshots = self
+            .process_manager
+            .list_pre_op_snapshots()
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&snapshots).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Roll back the most recent risky operation by restoring process definitions from its pre-op snapshot. Processes added by the operation are removed; changed or removed ones are recreated in the stopped state"
+    )]
+    async fn undo_last_operation(&self) -> std::result::Result<CallToolResult, McpError> {
+        let report = self
+            .process_manager
+            .undo_last_operation()
             .await
             .map_err(|e| McpError {
                 message: e.into(),
-                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
                 data: None,
             })?;
 
-        Ok(CallToolResult::success(vec![Content::text(format!(
-            "Process '{id}' removed successfully"
-        ))]))
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Export all processes to a JSON file for backup/persistence")]
-    async fn export_processes(
-        &self,
-        Parameters(ExportProcessesRequest { file_path }): Parameters<ExportProcessesRequest>,
-    ) -> std::result::Result<CallToolResult, McpError> {
-        let path = self
+    #[tool(
+        description = "List recorded definition changes (process create/update/remove and template edits) that can be undone, newest first, and the undone changes that can be redone"
+    )]
+    async fn list_changes(&self) -> std::result::Result<CallToolResult, McpError> {
+        let history = self
             .process_manager
-            .export_processes(file_path)
+            .list_changes()
             .await
             .map_err(|e| McpError {
                 message: e.into(),
@@ -392,51 +3175,66 @@ impl VantageServer {
                 data: None,
             })?;
 
-        Ok(CallToolResult::success(vec![Content::text(format!(
-            "Processes exported successfully to {path}"
-        ))]))
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&history).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Import processes from a JSON file")]
-    async fn import_processes(
-        &self,
-        Parameters(ImportProcessesRequest { file_path }): Parameters<ImportProcessesRequest>,
-    ) -> std::result::Result<CallToolResult, McpError> {
-        self.process_manager
-            .import_processes(&file_path)
+    #[tool(
+        description = "Undo the most recent definition change (process create/update/remove or template edit). Running processes keep running; a restored definition applies on the next start"
+    )]
+    async fn undo_last_change(&self) -> std::result::Result<CallToolResult, McpError> {
+        let repo = self
+            .db_connection
+            .as_ref()
+            .map(|db| vantage_persistence::TemplateRepository::new(db.db()));
+        let change = self
+            .process_manager
+            .undo_last_change(repo.as_ref())
             .await
             .map_err(|e| McpError {
                 message: e.into(),
-                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
                 data: None,
             })?;
 
-        Ok(CallToolResult::success(vec![Content::text(format!(
-            "Processes imported successfully from {file_path}"
-        ))]))
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&change).unwrap(),
+        )]))
     }
 
-    #[tool(
-        description = "Create a snapshot of the entire database (processes, templates, clipboard)"
-    )]
-    async fn create_snapshot(&self) -> std::result::Result<CallToolResult, McpError> {
-        let path = self
+    #[tool(description = "Redo the most recently undone definition change")]
+    async fn redo_change(&self) -> std::result::Result<CallToolResult, McpError> {
+        let repo = self
+            .db_connection
+            .as_ref()
+            .map(|db| vantage_persistence::TemplateRepository::new(db.db()));
+        let change = self
             .process_manager
-            .create_snapshot()
+            .redo_change(repo.as_ref())
             .await
             .map_err(|e| McpError {
                 message: e.into(),
-                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                code: rmcp::model::ErrorCode::INVALID_REQUEST,
                 data: None,
             })?;
 
-        Ok(CallToolResult::success(vec![Content::text(format!(
-            "Snapshot created successfully at {path}"
-        ))]))
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&change).unwrap(),
+        )]))
     }
 
     #[tool(description = "Restore the database from the latest snapshot")]
-    async fn restore_snapshot(&self) -> std::result::Result<CallToolResult, McpError> {
+    async fn restore_snapshot(
+        &self,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if let Some(pending) = self
+            .pending_approval(ApprovalOperation::RestoreSnapshot {}, &context)
+            .await?
+        {
+            return Ok(pending);
+        }
         self.process_manager
             .restore_snapshot()
             .await
@@ -451,17 +3249,68 @@ impl VantageServer {
         )]))
     }
 
-    #[tool(description = "Export processes to YAML format")]
+    #[tool(
+        description = "Export the whole workspace (process definitions, templates, chains, session contexts, settings and runbooks) as one versioned YAML bundle to share with another machine. Secret-looking env values, arguments and log sink credentials are replaced with <secret> and listed in the result; working directories under root are written relative to it"
+    )]
+    async fn export_workspace(
+        &self,
+        Parameters(ExportWorkspaceRequest { file_path, root }): Parameters<ExportWorkspaceRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let report = self
+            .process_manager
+            .export_workspace(file_path, root.map(std::path::PathBuf::from))
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Import a workspace bundle written by export_workspace. Relative working directories are resolved against root. Existing definitions with the same id fail the import unless on_conflict is skip or overwrite; settings are replaced only with import_settings=true. Returns what was created, replaced and skipped plus a validation report (missing commands or directories, secrets to fill in, chains or contexts pointing at unknown processes). dry_run=true reports without changing anything"
+    )]
+    async fn import_workspace(
+        &self,
+        Parameters(ImportWorkspaceRequest {
+            file_path,
+            on_conflict,
+            import_settings,
+            root,
+            dry_run,
+        }): Parameters<ImportWorkspaceRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let options = WorkspaceImportOptions {
+            on_conflict,
+            import_settings,
+            root: root.map(std::path::PathBuf::from),
+            dry_run,
+        };
+        let report = self
+            .process_manager
+            .import_workspace(&file_path, options)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Export processes to YAML format, or KDL with format=kdl or a .kdl file path"
+    )]
     async fn export_yaml(
         &self,
         Parameters(ExportYamlRequest {
             file_path,
             only_auto_start,
+            format,
         }): Parameters<ExportYamlRequest>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let path = self
             .process_manager
-            .export_yaml(file_path, only_auto_start)
+            .export_yaml(file_path, only_auto_start, format)
             .await
             .map_err(|e| McpError {
                 message: e.into(),
@@ -469,22 +3318,38 @@ impl VantageServer {
                 data: None,
             })?;
 
+        let format = format.unwrap_or_else(|| ConfigFormat::from_path(std::path::Path::new(&path)));
         let message = if only_auto_start {
-            format!("Auto-start processes exported to YAML at {path}")
+            format!("Auto-start processes exported to {format:?} at {path}")
         } else {
-            format!("All processes exported to YAML at {path}")
+            format!("All processes exported to {format:?} at {path}")
         };
 
         Ok(CallToolResult::success(vec![Content::text(message)]))
     }
 
-    #[tool(description = "Import processes from YAML format")]
+    #[tool(
+        description = "Import processes from YAML format, or KDL with format=kdl or a .kdl file path"
+    )]
     async fn import_yaml(
         &self,
-        Parameters(ImportYamlRequest { file_path }): Parameters<ImportYamlRequest>,
+        Parameters(ImportYamlRequest { file_path, format }): Parameters<ImportYamlRequest>,
+        context: RequestContext<RoleServer>,
     ) -> std::result::Result<CallToolResult, McpError> {
+        if let Some(pending) = self
+            .pending_approval(
+                ApprovalOperation::ImportYaml {
+                    file_path: file_path.clone(),
+                    format,
+                },
+                &context,
+            )
+            .await?
+        {
+            return Ok(pending);
+        }
         self.process_manager
-            .import_yaml(&file_path)
+            .import_yaml(&file_path, format)
             .await
             .map_err(|e| McpError {
                 message: e.into(),
@@ -493,11 +3358,11 @@ impl VantageServer {
             })?;
 
         Ok(CallToolResult::success(vec![Content::text(format!(
-            "Processes imported successfully from YAML file {file_path}"
+            "Processes imported successfully from {file_path}"
         ))]))
     }
 
-    #[tool(description = "Create a snapshot in specified format (yaml or surql)")]
+    #[tool(description = "Create a snapshot in specified format (yaml, kdl or surql)")]
     async fn create_formatted_snapshot(
         &self,
         Parameters(CreateSnapshotRequest { file_path, format }): Parameters<CreateSnapshotRequest>,
@@ -505,7 +3370,12 @@ impl VantageServer {
         let path = match format {
             SnapshotFormat::Yaml => {
                 self.process_manager
-                    .export_yaml(file_path, true) // Only auto-start for snapshots
+                    .export_yaml(file_path, true, Some(ConfigFormat::Yaml)) // Only auto-start for snapshots
+                    .await
+            }
+            SnapshotFormat::Kdl => {
+                self.process_manager
+                    .export_yaml(file_path, true, Some(ConfigFormat::Kdl))
                     .await
             }
             SnapshotFormat::Surql => self.process_manager.export_processes(file_path).await,
@@ -521,16 +3391,31 @@ impl VantageServer {
         ))]))
     }
 
-    #[tool(description = "Update process configuration (auto_start flags)")]
+    #[tool(
+        description = "Update process configuration: auto_start_on_restore, the startup order (priority, parallel group, ready_port) used by auto-start on restore, start_processes and start_group, and the group used by start_group/stop_group"
+    )]
     async fn update_process_config(
         &self,
         Parameters(UpdateProcessConfigRequest {
             id,
             auto_start_on_restore,
+            startup,
+            group,
         }): Parameters<UpdateProcessConfigRequest>,
     ) -> std::result::Result<CallToolResult, McpError> {
+        let startup_message = startup.as_ref().map(|order| {
+            format!(
+                " - startup priority {}{}",
+                order.priority,
+                order
+                    .group
+                    .as_ref()
+                    .map(|group| format!(" in group '{group}'"))
+                    .unwrap_or_default()
+            )
+        });
         self.process_manager
-            .update_process_config(id.clone(), auto_start_on_restore)
+            .update_process_config(id.clone(), auto_start_on_restore, startup, group.clone())
             .await
             .map_err(|e| McpError {
                 message: e.into(),
@@ -542,12 +3427,20 @@ impl VantageServer {
         if let Some(value) = auto_start_on_restore {
             message.push_str(&format!(" - auto_start_on_restore set to {value}"));
         }
+        if let Some(startup_message) = startup_message {
+            message.push_str(&startup_message);
+        }
+        match group.as_deref() {
+            Some("") => message.push_str(" - removed from its group"),
+            Some(group) => message.push_str(&format!(" - group set to '{group}'")),
+            None => {}
+        }
 
         Ok(CallToolResult::success(vec![Content::text(message)]))
     }
 
     #[tool(
-        description = "Update process attributes (command, args, env, cwd, and auto_start flags)"
+        description = "Update process attributes (command, args, env, cwd, auto_start flags and metadata)"
     )]
     async fn update_process(
         &self,
@@ -556,10 +3449,30 @@ impl VantageServer {
             command,
             args,
             env,
+            sensitive_env,
             cwd,
             auto_start_on_restore,
+            metadata,
         }): Parameters<UpdateProcessRequest>,
     ) -> std::result::Result<CallToolResult, McpError> {
+        check_secret_env(
+            &self.permissions,
+            env.as_ref().unwrap_or(&Default::default()),
+            sensitive_env.as_deref().unwrap_or_default(),
+        )
+        .map_err(|e| McpError::invalid_request(e, None))?;
+        // メタデータは検証に失敗しうるので先に適用する
+        let metadata_updated = metadata.is_some();
+        if let Some(changes) = metadata {
+            self.process_manager
+                .update_process_metadata(id.clone(), changes)
+                .await
+                .map_err(|e| McpError {
+                    message: e.into(),
+                    code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                    data: None,
+                })?;
+        }
         self.process_manager
             .update_process(
                 id.clone(),
@@ -568,6 +3481,7 @@ impl VantageServer {
                 env.clone(),
                 cwd.clone(),
                 auto_start_on_restore,
+                sensitive_env.clone(),
             )
             .await
             .map_err(|e| McpError {
@@ -586,12 +3500,18 @@ impl VantageServer {
         if env.is_some() {
             updates.push("env");
         }
+        if sensitive_env.is_some() {
+            updates.push("sensitive_env");
+        }
         if cwd.is_some() {
             updates.push("cwd");
         }
         if auto_start_on_restore.is_some() {
             updates.push("auto_start_on_restore");
         }
+        if metadata_updated {
+            updates.push("metadata");
+        }
 
         let message = if updates.is_empty() {
             format!("Process '{id}' - no attributes updated")
@@ -602,40 +3522,419 @@ impl VantageServer {
         Ok(CallToolResult::success(vec![Content::text(message)]))
     }
 
-    #[tool(description = "Get smart suggestions for next actions based on learning")]
+    #[tool(
+        description = "Get smart suggestions for next actions, ranked by score across all enabled suggestion providers"
+    )]
     async fn get_suggestions(
         &self,
         Parameters(GetSuggestionsRequest { current_process }): Parameters<GetSuggestionsRequest>,
     ) -> std::result::Result<CallToolResult, McpError> {
+        let settings = self
+            .process_manager
+            .get_settings()
+            .await
+            .unwrap_or_default();
+        let context = SuggestionContext { current_process };
         let suggestions = self
-            .learning_engine
-            .get_suggestions(current_process.as_deref())
+            .suggestion_pipeline
+            .run(&context, &settings.suggestion_providers)
+            .await;
+        *self.last_suggestions.lock().await = suggestions.clone();
+
+        if suggestions.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "No suggestions available at this time.",
+            )]));
+        }
+
+        let mut result = String::from("Smart Suggestions:\n\n");
+        for (i, suggestion) in suggestions.iter().enumerate() {
+            result.push_str(&format!(
+                "{}. {}\n   Action: {:?}\n   Provider: {}\n   Score: {:.2} (confidence {:.0}%)\n   Reason: {}\n\n",
+                i + 1,
+                suggestion.message,
+                suggestion.action,
+                suggestion.provider,
+                suggestion.score,
+                suggestion.confidence * 100.0,
+                suggestion.reason
+            ));
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    /// 直近の提案を番号（1始まり）で取得
+    async fn suggestion_at(
+        &self,
+        index: usize,
+    ) -> std::result::Result<learning::Suggestion, McpError> {
+        let suggestions = self.last_suggestions.lock().await;
+        index
+            .checked_sub(1)
+            .and_then(|i| suggestions.get(i))
+            .cloned()
+            .ok_or_else(|| McpError {
+                message: format!(
+                    "No suggestion #{} in the latest get_suggestions result ({} available)",
+                    index,
+                    suggestions.len()
+                )
+                .into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })
+    }
+
+    /// 提案されたアクションを実行し、結果の説明を返す
+    async fn execute_suggested_action(
+        &self,
+        action: &learning::SuggestedAction,
+    ) -> std::result::Result<String, String> {
+        use learning::SuggestedAction;
+
+        match action {
+            SuggestedAction::StartProcess { process_id } => {
+                let pid = self
+                    .process_manager
+                    .start_process(process_id.clone())
+                    .await?;
+                Ok(format!("Started process '{process_id}' with PID {pid}"))
+            }
+            SuggestedAction::StopProcess { process_id } => {
+                self.process_manager
+                    .stop_process(process_id.clone(), None)
+                    .await?;
+                Ok(format!("Stopped process '{process_id}'"))
+            }
+            SuggestedAction::RestartProcess { process_id } => {
+                // 停止済みの場合でも起動は行う
+                let pid = self
+                    .process_manager
+                    .restart_process(process_id.clone(), None)
+                    .await?;
+                Ok(format!("Restarted process '{process_id}' with PID {pid}"))
+            }
+            SuggestedAction::CreateProcess { command, args } => {
+                let id = std::path::Path::new(command)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| command.clone());
+                self.process_manager
+                    .create_process(
+                        id.clone(),
+                        command.clone(),
+                        args.clone(),
+                        std::collections::HashMap::new(),
+                        None,
+                        false,
+                    )
+                    .await?;
+                Ok(format!("Registered process '{id}'"))
+            }
+            SuggestedAction::InvestigateProcess { process_id } => {
+                let lines = self
+                    .process_manager
+                    .get_process_output(process_id.clone(), process::OutputStream::Both, Some(50))
+                    .await?;
+                Ok(format!(
+                    "Recent output of '{}':\n{}",
+                    process_id,
+                    lines.join("\n")
+                ))
+            }
+            SuggestedAction::InvestigateCiRun { run_id } => {
+                let logs = self.ci_monitor.get_failed_logs(*run_id).await?;
+                Ok(format!("Failed logs of CI run {run_id}:\n{logs}"))
+            }
+        }
+    }
+
+    #[tool(
+        description = "Apply a suggestion from the latest get_suggestions result. Returns a preview unless confirm is true; applied suggestions raise the confidence of similar suggestions"
+    )]
+    async fn apply_suggestion(
+        &self,
+        Parameters(ApplySuggestionRequest {
+            suggestion_index,
+            confirm,
+        }): Parameters<ApplySuggestionRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let suggestion = self.suggestion_at(suggestion_index).await?;
+        if !confirm {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "Suggestion #{}: {}\nAction: {}\nCall apply_suggestion again with confirm=true to execute it.",
+                suggestion_index,
+                suggestion.message,
+                suggestion.action.describe()
+            ))]));
+        }
+
+        let result = self
+            .execute_suggested_action(&suggestion.action)
             .await
             .map_err(|e| McpError {
-                message: format!("{e}").into(),
+                message: e.into(),
                 code: rmcp::model::ErrorCode::INTERNAL_ERROR,
                 data: None,
             })?;
+        self.learning_engine
+            .record_feedback(&suggestion, true)
+            .await;
+        self.analytics.record_suggestion(&suggestion, true).await;
 
-        if suggestions.is_empty() {
-            return Ok(CallToolResult::success(vec![Content::text(
-                "No suggestions available at this time.",
-            )]));
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Dismiss a suggestion from the latest get_suggestions result; dismissed suggestions lower the confidence of similar suggestions"
+    )]
+    async fn dismiss_suggestion(
+        &self,
+        Parameters(DismissSuggestionRequest {
+            suggestion_index,
+            reason,
+        }): Parameters<DismissSuggestionRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let suggestion = self.suggestion_at(suggestion_index).await?;
+        self.learning_engine
+            .record_feedback(&suggestion, false)
+            .await;
+        self.analytics.record_suggestion(&suggestion, false).await;
+        if let Some(reason) = reason {
+            tracing::debug!("Suggestion '{}' dismissed: {}", suggestion.message, reason);
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Dismissed suggestion #{}: {}",
+            suggestion_index, suggestion.message
+        ))]))
+    }
+
+    #[tool(
+        description = "Measure which tools and suggestions lead to good outcomes: per tool, call and error counts; per suggestion provider and action, applied and dismissed counts. Process-changing calls and applied suggestions are stable when their target process did not fail, crash-loop or turn unhealthy within window_minutes afterwards, failed when it did, and pending until the window has passed"
+    )]
+    async fn get_assistant_analytics(
+        &self,
+        Parameters(GetAssistantAnalyticsRequest { window_minutes }): Parameters<
+            GetAssistantAnalyticsRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let window_minutes = window_minutes.unwrap_or(learning::analytics::DEFAULT_WINDOW_MINUTES);
+        if window_minutes <= 0 {
+            return Err(McpError::invalid_params(
+                "window_minutes must be greater than 0",
+                None,
+            ));
+        }
+        let failures = self
+            .process_manager
+            .list_notifications(false, None, None)
+            .await
+            .map_err(|e| McpError::internal_error(e, None))?;
+        let analytics = self
+            .analytics
+            .summarize(
+                &failures,
+                chrono::Duration::minutes(window_minutes),
+                chrono::Utc::now(),
+            )
+            .await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&analytics).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Subscribe to new suggestions instead of polling get_suggestions. After a process fails, stops, goes idle or crash-loops (and every minute for time-of-day patterns) the suggestion providers run, and each suggestion that was not there before and meets min_confidence is pushed as a logging notification (logger 'vantage.suggestions'). Returns a watch_id for unwatch_suggestions"
+    )]
+    async fn watch_suggestions(
+        &self,
+        Parameters(WatchSuggestionsRequest {
+            min_confidence,
+            providers,
+        }): Parameters<WatchSuggestionsRequest>,
+        peer: Peer<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let min_confidence = min_confidence.unwrap_or(0.7);
+        if !(0.0..=1.0).contains(&min_confidence) {
+            return Err(McpError::invalid_params(
+                "min_confidence must be between 0.0 and 1.0",
+                None,
+            ));
+        }
+        if let Some(providers) = &providers {
+            let known = self.suggestion_pipeline.provider_names();
+            if let Some(unknown) = providers.iter().find(|p| !known.contains(&p.as_str())) {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "Unknown suggestion provider '{}'. Available: {}",
+                        unknown,
+                        known.join(", ")
+                    ),
+                    None,
+                ));
+            }
+        }
+
+        let watch = SuggestionWatch {
+            watch_id: vantage_persistence::generate_id(),
+            min_confidence,
+            providers,
+            created_at: chrono::Utc::now(),
+        };
+        let mut receiver = self.suggestion_watcher.subscribe();
+        let task = tokio::spawn({
+            let watch = watch.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(notification) if watch.accepts(&notification.suggestion) => {
+                            let sent = peer
+                                .notify_logging_message(LoggingMessageNotificationParam {
+                                    level: LoggingLevel::Notice,
+                                    logger: Some("vantage.suggestions".to_string()),
+                                    data: serde_json::to_value(&notification).unwrap_or_default(),
+                                })
+                                .await;
+                            // クライアントが切断したら購読をやめる
+                            if sent.is_err() {
+                                break;
+                            }
+                        }
+                        Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            }
+        });
+        self.suggestion_watcher
+            .register(watch.clone(), task.abort_handle())
+            .await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&watch).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Stop a suggestion subscription created by watch_suggestions")]
+    async fn unwatch_suggestions(
+        &self,
+        Parameters(UnwatchSuggestionsRequest { watch_id }): Parameters<UnwatchSuggestionsRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        self.suggestion_watcher
+            .unregister(&watch_id)
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Stopped suggestion watch '{watch_id}'"
+        ))]))
+    }
+
+    #[tool(description = "List active suggestion subscriptions with their confidence thresholds")]
+    async fn list_suggestion_watches(&self) -> std::result::Result<CallToolResult, McpError> {
+        let watches = self.suggestion_watcher.list().await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&watches).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "List suggestion providers with their enabled state and weight")]
+    async fn list_suggestion_providers(&self) -> std::result::Result<CallToolResult, McpError> {
+        let settings = self
+            .process_manager
+            .get_settings()
+            .await
+            .unwrap_or_default();
+
+        let providers: Vec<serde_json::Value> = self
+            .suggestion_pipeline
+            .provider_names()
+            .into_iter()
+            .map(|name| {
+                let config = settings
+                    .suggestion_providers
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_default();
+                serde_json::json!({
+                    "name": name,
+                    "enabled": config.enabled,
+                    "weight": config.weight,
+                })
+            })
+            .collect();
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&providers).unwrap_or_default(),
+        )]))
+    }
+
+    #[tool(description = "Enable, disable or re-weight a suggestion provider")]
+    async fn configure_suggestion_provider(
+        &self,
+        Parameters(ConfigureSuggestionProviderRequest {
+            provider,
+            enabled,
+            weight,
+        }): Parameters<ConfigureSuggestionProviderRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let known = self.suggestion_pipeline.provider_names();
+        if !known.contains(&provider.as_str()) {
+            return Err(McpError {
+                message: format!(
+                    "Unknown suggestion provider '{}'. Available: {}",
+                    provider,
+                    known.join(", ")
+                )
+                .into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            });
+        }
+        if let Some(weight) = weight
+            && !(weight.is_finite() && weight >= 0.0)
+        {
+            return Err(McpError {
+                message: "weight must be a non-negative number".into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            });
+        }
+
+        let to_mcp_error = |e: String| McpError {
+            message: e.into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        };
+        let mut settings = self
+            .process_manager
+            .get_settings()
+            .await
+            .map_err(to_mcp_error)?;
+        let config = settings
+            .suggestion_providers
+            .entry(provider.clone())
+            .or_default();
+        if let Some(enabled) = enabled {
+            config.enabled = enabled;
         }
-
-        let mut result = String::from("Smart Suggestions:\n\n");
-        for (i, suggestion) in suggestions.iter().enumerate() {
-            result.push_str(&format!(
-                "{}. {}\n   Action: {:?}\n   Confidence: {:.0}%\n   Reason: {}\n\n",
-                i + 1,
-                suggestion.message,
-                suggestion.action,
-                suggestion.confidence * 100.0,
-                suggestion.reason
-            ));
+        if let Some(weight) = weight {
+            config.weight = weight;
         }
+        let message = format!(
+            "Suggestion provider '{}': enabled={}, weight={}",
+            provider, config.enabled, config.weight
+        );
+        self.process_manager
+            .save_settings(settings)
+            .await
+            .map_err(to_mcp_error)?;
 
-        Ok(CallToolResult::success(vec![Content::text(result)]))
+        Ok(CallToolResult::success(vec![Content::text(message)]))
     }
 
     // CI監視ツール
@@ -762,6 +4061,9 @@ impl VantageServer {
             .await
         {
             Ok(run) => {
+                if let Some(notification) = process::notifications::ci_failed(&run) {
+                    self.process_manager.notify(notification).await;
+                }
                 let response = CiRunResponse {
                     id: run.id,
                     name: run.name,
@@ -807,8 +4109,20 @@ impl VantageServer {
             CiMonitor::new(None, Some(request.poll_interval))
         };
 
+        let (failures, mut failed_runs) = tokio::sync::mpsc::unbounded_channel();
+        let ci_monitor = ci_monitor.with_failure_sink(failures);
         ci_monitor.start_monitoring().await;
 
+        // 失敗した実行を通知の受信箱に残す
+        let process_manager = self.process_manager.clone();
+        tokio::spawn(async move {
+            while let Some(run) = failed_runs.recv().await {
+                if let Some(notification) = process::notifications::ci_failed(&run) {
+                    process_manager.notify(notification).await;
+                }
+            }
+        });
+
         Ok(CallToolResult::success(vec![Content::text(format!(
             "CI monitoring started with {}s polling interval",
             request.poll_interval
@@ -904,6 +4218,142 @@ impl VantageServer {
         Ok(CallToolResult::success(vec![Content::text(json)]))
     }
 
+    // セッションコンテキスト関連ツール
+
+    #[tool(
+        description = "Save a named session context: running processes, workspace, git branch, recent clipboard items and notes"
+    )]
+    async fn save_context(
+        &self,
+        Parameters(SaveContextRequest {
+            name,
+            workspace,
+            notes,
+        }): Parameters<SaveContextRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let context = self
+            .process_manager
+            .save_context(name, workspace.map(std::path::PathBuf::from), notes)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Saved context '{}': {} running process(es), branch {}, {} clipboard item(s)",
+            context.name,
+            context.running_processes.len(),
+            context.git_branch.as_deref().unwrap_or("(none)"),
+            context.clipboard.len()
+        ))]))
+    }
+
+    #[tool(
+        description = "Restore a saved session context: start its processes, restore clipboard items and optionally check out its git branch"
+    )]
+    async fn restore_context(
+        &self,
+        Parameters(RestoreContextRequest {
+            name,
+            stop_others,
+            checkout_branch,
+        }): Parameters<RestoreContextRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if let Some(pending) = self
+            .pending_approval(
+                ApprovalOperation::RestoreContext {
+                    name: name.clone(),
+                    stop_others,
+                    checkout_branch,
+                },
+                &context,
+            )
+            .await?
+        {
+            return Ok(pending);
+        }
+        let report = self
+            .process_manager
+            .restore_context(&name, stop_others, checkout_branch)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        let json = serde_json::to_string_pretty(&report).map_err(|e| McpError {
+            message: format!("Failed to serialize response: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(description = "List saved session contexts, newest first")]
+    async fn list_contexts(&self) -> std::result::Result<CallToolResult, McpError> {
+        let contexts = self
+            .process_manager
+            .list_contexts()
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        let summary: Vec<serde_json::Value> = contexts
+            .iter()
+            .map(|c| {
+                serde_json::json!({
+                    "name": c.name,
+                    "saved_at": c.saved_at.to_rfc3339(),
+                    "workspace": c.workspace,
+                    "git_branch": c.git_branch,
+                    "running_processes": c.running_processes,
+                    "notes": c.notes,
+                })
+            })
+            .collect();
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&summary).unwrap_or_default(),
+        )]))
+    }
+
+    #[tool(description = "Delete a saved session context")]
+    async fn delete_context(
+        &self,
+        Parameters(DeleteContextRequest { name }): Parameters<DeleteContextRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if let Some(pending) = self
+            .pending_approval(
+                ApprovalOperation::DeleteContext { name: name.clone() },
+                &context,
+            )
+            .await?
+        {
+            return Ok(pending);
+        }
+        self.process_manager
+            .delete_context(&name)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Deleted context '{name}'"
+        ))]))
+    }
+
     // ========================================
     // Template Management Tools
     // ========================================
@@ -968,6 +4418,11 @@ impl VantageServer {
             code: rmcp::model::ErrorCode::INTERNAL_ERROR,
             data: None,
         })?;
+        if let Some(key) = template_key(&created) {
+            self.process_manager
+                .record_change(ChangeTarget::SharedTemplate, &key, None, Some(&created))
+                .await;
+        }
 
         let response = serde_json::json!({
             "success": true,
@@ -1041,11 +4496,37 @@ impl VantageServer {
         )]))
     }
 
-    #[tool(description = "Get detailed information about a specific template by ID or name")]
+    #[tool(
+        description = "Get detailed information about a specific template by ID or name. Templates with variables include `parameters_schema`, a JSON Schema of the variables (types, enums, patterns, defaults) for rendering an input form"
+    )]
     async fn get_template(
         &self,
         Parameters(request): Parameters<messages::template::GetTemplateRequest>,
     ) -> std::result::Result<CallToolResult, McpError> {
+        // 変数を持つテンプレートを優先し、見つからなければデータベースのテンプレートを探す
+        let saved = match (&request.id, &request.name) {
+            (Some(id), _) => self.process_manager.get_template(id).await,
+            (None, Some(name)) => self
+                .process_manager
+                .load_all_templates()
+                .await
+                .map(|templates| templates.into_iter().find(|t| &t.name == name)),
+            (None, None) => Ok(None),
+        }
+        .map_err(|e| McpError {
+            message: format!("Failed to get template: {e}").into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        })?;
+        if let Some(template) = saved {
+            let mut response = mask_secrets(Some(&self.permissions), &template);
+            response["parameters_schema"] =
+                vantage_persistence::template_schema::parameters_schema(&template.variables);
+            return Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&response).unwrap(),
+            )]));
+        }
+
         let db = self.db_connection.as_ref().ok_or_else(|| McpError {
             message: "Database connection not available".into(),
             code: rmcp::model::ErrorCode::INTERNAL_ERROR,
@@ -1128,6 +4609,8 @@ impl VantageServer {
                 data: None,
             })?;
 
+        let before = template.clone();
+
         // 更新
         if let Some(name) = request.name {
             template.name = name;
@@ -1168,6 +4651,14 @@ impl VantageServer {
                 code: rmcp::model::ErrorCode::INTERNAL_ERROR,
                 data: None,
             })?;
+        self.process_manager
+            .record_change(
+                ChangeTarget::SharedTemplate,
+                &request.id,
+                Some(&before),
+                Some(&updated),
+            )
+            .await;
 
         let response = serde_json::json!({
             "success": true,
@@ -1231,11 +4722,16 @@ impl VantageServer {
             });
         };
 
+        let key = id.trim_start_matches("template:").to_string();
+        let before = repo.get(&key).await.ok().flatten();
         repo.delete(&id).await.map_err(|e| McpError {
             message: format!("Failed to delete template: {}", e).into(),
             code: rmcp::model::ErrorCode::INTERNAL_ERROR,
             data: None,
         })?;
+        self.process_manager
+            .record_change(ChangeTarget::SharedTemplate, &key, before.as_ref(), None)
+            .await;
 
         let response = serde_json::json!({
             "success": true,
@@ -1338,6 +4834,181 @@ impl VantageServer {
         )]))
     }
 
+    #[tool(
+        description = "Dry-run a template: return the command, args, env and cwd a process would be created with after substituting variables and applying overrides, without creating it. Secret references stay masked as {{NAME}} placeholders; missing required variables and unresolved placeholders are reported"
+    )]
+    async fn render_template(
+        &self,
+        Parameters(request): Parameters<messages::template::RenderTemplateRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let rendered = self
+            .process_manager
+            .render_template(
+                &request.template_id,
+                request.values,
+                RenderOverrides {
+                    args: request.override_args,
+                    env: request.override_env,
+                    cwd: request.override_cwd,
+                },
+            )
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+        let rendered = mask_secrets(Some(&self.permissions), &rendered);
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&rendered).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Create one process per combination of a parameter matrix (e.g. PORT 3001..3005 x NODE_VERSION [18, 20]) from a template. Returns the parameters used for each generated process ID; nothing is created if any combination is invalid"
+    )]
+    async fn create_processes_from_template_matrix(
+        &self,
+        Parameters(request): Parameters<
+            messages::template::CreateProcessesFromTemplateMatrixRequest,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let instances = self
+            .process_manager
+            .create_processes_from_template_matrix(
+                &request.template_id,
+                request.matrix,
+                request.values,
+                request.id_prefix,
+                request.start,
+            )
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INVALID_PARAMS,
+                data: None,
+            })?;
+
+        let response = serde_json::json!({
+            "success": true,
+            "template_id": request.template_id,
+            "processes": instances,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Capture a process (including its runtime environment and listening ports) into a reusable template. Secrets are replaced by required variables instead of literal values"
+    )]
+    async fn capture_template(
+        &self,
+        Parameters(request): Parameters<messages::template::CaptureTemplateRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let captured = self
+            .process_manager
+            .capture_template(request.process_id, request.template_id, request.name)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        let response = serde_json::json!({
+            "success": true,
+            "template_id": captured.template.template_id,
+            "secret_references": captured.secrets,
+            "detected_ports": captured.ports,
+            "template": captured.template,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Import tasks from a VS Code tasks.json as processes or templates. Re-importing updates previously imported entries instead of duplicating them"
+    )]
+    async fn import_vscode_tasks(
+        &self,
+        Parameters(request): Parameters<messages::template::ImportVscodeTasksRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let path = request
+            .path
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| std::path::PathBuf::from(".vscode/tasks.json"));
+        let report = self
+            .process_manager
+            .import_vscode_tasks(&path, request.as_templates, request.labels)
+            .await
+            .map_err(|e| McpError {
+                message: e.into(),
+                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+                data: None,
+            })?;
+
+        let response = serde_json::json!({
+            "success": true,
+            "kind": if request.as_templates { "templates" } else { "processes" },
+            "created": report.created,
+            "updated": report.updated,
+            "skipped": report.skipped,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )]))
+    }
+
+    #[tool(
+        description = "Scan a repository's README, Makefile and package.json scripts and propose candidate processes with commands and descriptions. Set import to create them as processes or templates"
+    )]
+    async fn discover_tasks(
+        &self,
+        Parameters(request): Parameters<messages::template::DiscoverTasksRequest>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let path = request
+            .path
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| std::path::PathBuf::from("."));
+        let to_mcp_error = |e: String| McpError {
+            message: e.into(),
+            code: rmcp::model::ErrorCode::INTERNAL_ERROR,
+            data: None,
+        };
+
+        let response = if request.import {
+            let report = self
+                .process_manager
+                .import_discovered_tasks(&path, request.as_templates, request.ids)
+                .await
+                .map_err(to_mcp_error)?;
+            serde_json::json!({
+                "success": true,
+                "kind": if request.as_templates { "templates" } else { "processes" },
+                "created": report.created,
+                "updated": report.updated,
+                "skipped": report.skipped,
+            })
+        } else {
+            let report = self
+                .process_manager
+                .discover_tasks(&path)
+                .await
+                .map_err(to_mcp_error)?;
+            serde_json::to_value(&report).unwrap()
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )]))
+    }
+
     #[tool(description = "Open the Vantage web console in your browser")]
     async fn open_web_console(
         &self,
@@ -1384,13 +5055,163 @@ impl VantageServer {
     }
 }
 
-#[tool_handler]
 impl ServerHandler for VantageServer {
+    async fn call_tool(
+        &self,
+        mut request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if let Some(denied) = permissions::tool_denied(&self.permissions, &request.name) {
+            return Err(McpError::invalid_request(denied, None));
+        }
+        let budget_override = response_budget::BudgetOverride::take(&mut request.arguments)
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        // スナップショット・復元の間は、プロセスを変更するツールを待たせる
+        let mutates = permissions::tool_requirements(&request.name)
+            .is_some_and(permissions::mutates_processes);
+        let name = request.name.clone();
+        let target = target_process(request.arguments.as_ref());
+        let tcc = ToolCallContext::new(self, request, context);
+        let result = if mutates {
+            self.process_manager
+                .fence()
+                .scope(&name, self.tool_router.call(tcc))
+                .await
+                .map_err(|e| McpError::invalid_request(e, None))
+                .and_then(|result| result)
+        } else {
+            self.tool_router.call(tcc).await
+        };
+        let succeeded = result
+            .as_ref()
+            .is_ok_and(|result| result.is_error != Some(true));
+        self.analytics
+            .record_tool_call(&name, target, succeeded, mutates)
+            .await;
+        let mut result = result?;
+
+        // 設定の上限にツール呼び出しごとの指定を重ねて応答を縮める
+        let budget = self
+            .process_manager
+            .get_settings()
+            .await
+            .map(|settings| settings.response_budget)
+            .unwrap_or_default();
+        let budget = budget_override.map_or(budget, |o| o.apply(budget));
+        response_budget::shape_result(&mut result, &budget);
+        Ok(result)
+    }
+
+    /// 切断したクライアントが再接続した場合は、プロセスの状態の要約を `instructions` に加える
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> std::result::Result<InitializeResult, McpError> {
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
+        let mut info = self.get_info();
+        let readable =
+            permissions::missing(&self.permissions, &[(Capability::Processes, Access::Read)])
+                .is_none();
+        if let Some(disconnected_at) = mcp_session::last_disconnect().filter(|_| readable) {
+            let summary =
+                mcp_session::reconnect_summary(&self.process_manager, disconnected_at).await;
+            tracing::info!("MCP client reconnected; replaying the state summary");
+            info.instructions = Some(match info.instructions {
+                Some(instructions) => format!("{instructions}\n\n{summary}"),
+                None => summary,
+            });
+        }
+        Ok(info)
+    }
+
+    /// アクセス権が足りないツールは一覧に含めない
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> std::result::Result<ListToolsResult, McpError> {
+        let tools = self
+            .tool_router
+            .list_all()
+            .into_iter()
+            .filter(|tool| permissions::tool_denied(&self.permissions, &tool.name).is_none())
+            .collect();
+        Ok(ListToolsResult::with_all_items(tools))
+    }
+
+    /// 手順書のあるプロセスごとに、手順書をリソースとして公開する
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> std::result::Result<ListResourcesResult, McpError> {
+        if permissions::missing(&self.permissions, &[(Capability::Processes, Access::Read)])
+            .is_some()
+        {
+            return Ok(ListResourcesResult::default());
+        }
+        let resources = self
+            .process_manager
+            .processes_with_runbooks()
+            .await
+            .into_iter()
+            .map(|id| {
+                let mut resource = RawResource::new(runbook_uri(&id), format!("{id} runbook"));
+                resource.description = Some(format!("Recovery steps for process '{id}'"));
+                resource.mime_type = Some("text/markdown".to_string());
+                resource.no_annotation()
+            })
+            .collect();
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn read_resource(
+        &self,
+        ReadResourceRequestParam { uri }: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> std::result::Result<ReadResourceResult, McpError> {
+        if let Some(requirement) =
+            permissions::missing(&self.permissions, &[(Capability::Processes, Access::Read)])
+        {
+            return Err(McpError::invalid_request(
+                permissions::denied_message(requirement),
+                None,
+            ));
+        }
+        let id = uri
+            .strip_prefix("vantage://processes/")
+            .and_then(|rest| rest.strip_suffix("/runbook"))
+            .ok_or_else(|| McpError::resource_not_found(format!("Unknown resource {uri}"), None))?;
+        let content = self
+            .process_manager
+            .get_runbook(id)
+            .await
+            .map_err(|e| McpError::resource_not_found(e, None))?
+            .ok_or_else(|| {
+                McpError::resource_not_found(format!("Process '{id}' has no runbook"), None)
+            })?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri,
+                mime_type: Some("text/markdown".to_string()),
+                text: content.markdown,
+                meta: None,
+            }],
+        })
+    }
+
     fn get_info(&self) -> ServerInfo {
         tracing::info!("MCP client requesting server info");
         let info = ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_logging()
+                .enable_tools()
+                .enable_resources()
+                .build(),
             server_info: Implementation {
                 name: "vantage-mcp".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
@@ -1399,7 +5220,10 @@ impl ServerHandler for VantageServer {
                 icons: None,
             },
             instructions: Some(
-                "Vantage MCP - A powerful process management server for Claude Code via MCP."
+                "Vantage MCP - A powerful process management server for Claude Code via MCP. \
+                 Every tool accepts an optional `response_budget` argument \
+                 ({\"max_chars\": N, \"max_items\": N}) that summarizes large responses \
+                 (top-N items with omitted counts, truncated text) to save context."
                     .to_string(),
             ),
         };
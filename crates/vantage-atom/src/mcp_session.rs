@@ -0,0 +1,360 @@
+//! MCPクライアントの切断をまたいだセッション
+//!
+//! `--keep-alive` で起動したサーバーは、stdioのクライアントが切断してもプロセスを止めずに
+//! 動き続け、データディレクトリのUnixソケット（`mcp.sock`）で次のMCPセッションを受け付けます。
+//! 同じ指定で新しく起動したvantageは、ソケットが応答すれば自分ではサーバーを起動せず、
+//! 標準入出力をソケットへ中継します。切断したクライアントが再接続すると、初期化の応答の
+//! `instructions` にプロセスの状態と切断中の通知をまとめた要約を加えます。
+
+use crate::process::{ProcessInfo, ProcessManager, ProcessState};
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use vantage_persistence::Notification;
+
+/// 要約に名前を並べるプロセス・通知の数
+pub const MAX_LISTED: usize = 10;
+
+/// セッションの開始と切断の記録
+static SESSIONS: LazyLock<Mutex<SessionLog>> = LazyLock::new(|| Mutex::new(SessionLog::default()));
+
+#[derive(Debug, Default)]
+struct SessionLog {
+    /// 最後にクライアントが切断した時刻
+    disconnected_at: Option<DateTime<Utc>>,
+}
+
+/// セッションを受け付けるソケット
+pub fn socket_path() -> PathBuf {
+    vantage_persistence::paths::get().data_dir.join("mcp.sock")
+}
+
+/// 以前のクライアントが切断していれば（新しいセッションが再接続なら）その時刻
+pub fn last_disconnect() -> Option<DateTime<Utc>> {
+    SESSIONS.lock().unwrap().disconnected_at
+}
+
+/// クライアントの切断を記録
+pub fn end() {
+    SESSIONS.lock().unwrap().disconnected_at = Some(Utc::now());
+}
+
+/// 再接続したクライアントに返す要約
+pub async fn reconnect_summary(manager: &ProcessManager, disconnected_at: DateTime<Utc>) -> String {
+    let processes = manager.list_processes(None).await;
+    let notifications = manager
+        .list_notifications(true, None, None)
+        .await
+        .unwrap_or_default();
+    summarize(&processes, &notifications, disconnected_at)
+}
+
+/// プロセスの状態と未読の通知から要約を作る
+pub fn summarize(
+    processes: &[ProcessInfo],
+    unread: &[Notification],
+    disconnected_at: DateTime<Utc>,
+) -> String {
+    let mut running = Vec::new();
+    let mut paused = Vec::new();
+    let mut failed = Vec::new();
+    let mut stopped = 0;
+    let mut not_started = 0;
+    for process in processes {
+        match &process.state {
+            ProcessState::Running { .. } => running.push(process.id.as_str()),
+            ProcessState::Paused { .. } => paused.push(process.id.as_str()),
+            ProcessState::Failed { .. } => failed.push(process.id.as_str()),
+            ProcessState::Stopped { .. } => stopped += 1,
+            ProcessState::NotStarted => not_started += 1,
+        }
+    }
+
+    let mut counts = Vec::new();
+    for (label, ids) in [
+        ("running", &mut running),
+        ("paused", &mut paused),
+        ("failed", &mut failed),
+    ] {
+        if !ids.is_empty() {
+            ids.sort_unstable();
+            counts.push(format!("{} {}: {}", ids.len(), label, listed(ids)));
+        }
+    }
+    if stopped > 0 {
+        counts.push(format!("{stopped} stopped"));
+    }
+    if not_started > 0 {
+        counts.push(format!("{not_started} not started"));
+    }
+
+    let mut lines = vec![
+        format!(
+            "Reconnected to a running Vantage server. Processes kept running while no client \
+             was connected (the previous client disconnected at {}).",
+            disconnected_at.to_rfc3339()
+        ),
+        if counts.is_empty() {
+            format!("Processes: {}.", processes.len())
+        } else {
+            format!("Processes: {} ({}).", processes.len(), counts.join("; "))
+        },
+    ];
+
+    let since: Vec<&Notification> = unread
+        .iter()
+        .filter(|notification| notification.created_at > disconnected_at)
+        .collect();
+    if !since.is_empty() {
+        let titles: Vec<&str> = since.iter().map(|n| n.title.as_str()).collect();
+        lines.push(format!(
+            "Since the disconnect: {} notification(s): {}.",
+            since.len(),
+            listed(&titles)
+        ));
+    }
+    if !unread.is_empty() {
+        lines.push(format!(
+            "Unread notifications: {} (see list_notifications).",
+            unread.len()
+        ));
+    }
+    lines.join("\n")
+}
+
+/// 先頭の `MAX_LISTED` 件を並べ、残りは件数だけ示す
+fn listed(items: &[&str]) -> String {
+    let mut text = items
+        .iter()
+        .take(MAX_LISTED)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if items.len() > MAX_LISTED {
+        text.push_str(&format!(" (+{} more)", items.len() - MAX_LISTED));
+    }
+    text
+}
+
+#[cfg(unix)]
+pub use unix::{accept, bind, relay_stdio};
+
+#[cfg(unix)]
+mod unix {
+    use super::end;
+    use crate::VantageServer;
+    use rmcp::ServiceExt;
+    use std::fs::{self, Permissions};
+    use std::io;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{UnixListener, UnixStream};
+    use tracing::{info, warn};
+
+    /// ソケットで待ち受ける
+    ///
+    /// ソケットが既にあれば接続してみて、接続を拒否されたとき（前回のサーバーの残り）だけ消す。
+    /// 応答するサーバーがあれば、そのソケットを奪わずに `AddrInUse` を返す。
+    pub fn bind(path: &Path) -> io::Result<UnixListener> {
+        match std::os::unix::net::UnixStream::connect(path) {
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "another server is accepting MCP sessions on this socket",
+                ));
+            }
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => fs::remove_file(path)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    /// ソケットに届いたセッションを順に受け付ける
+    pub async fn accept(listener: UnixListener, server: VantageServer) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve(server.clone(), stream));
+                }
+                Err(e) => {
+                    warn!("Failed to accept an MCP session: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    }
+
+    async fn serve(server: VantageServer, stream: UnixStream) {
+        match server.serve(stream).await {
+            Ok(service) => {
+                info!("MCP client connected over the session socket");
+                if let Err(e) = service.waiting().await {
+                    warn!("MCP session ended with an error: {:?}", e);
+                }
+                end();
+                info!("MCP client disconnected; processes keep running");
+            }
+            Err(e) => warn!("Failed to start an MCP session: {:?}", e),
+        }
+    }
+
+    /// 動いているサーバーがあれば標準入出力を中継する（サーバーがなければfalse）
+    ///
+    /// クライアントが標準入力を閉じるか、サーバーが接続を閉じると終わる。標準入力の読み込みは
+    /// 終わらないことがあるため、呼び出し側は戻ったらすぐにプロセスを終了する。
+    pub async fn relay_stdio(path: &Path) -> io::Result<bool> {
+        let stream = match UnixStream::connect(path).await {
+            Ok(stream) => stream,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+                ) =>
+            {
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        };
+        let (mut reader, mut writer) = stream.into_split();
+        tokio::spawn(async move {
+            let mut stdin = tokio::io::stdin();
+            if tokio::io::copy(&mut stdin, &mut writer).await.is_ok() {
+                let _ = writer.shutdown().await;
+            }
+        });
+        let mut stdout = tokio::io::stdout();
+        tokio::io::copy(&mut reader, &mut stdout).await?;
+        stdout.flush().await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use vantage_persistence::NotificationKind;
+
+    fn process(id: &str, state: ProcessState) -> ProcessInfo {
+        ProcessInfo {
+            id: id.to_string(),
+            command: "sleep".to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: None,
+            state,
+            auto_start_on_restore: false,
+            tags: Vec::new(),
+            idle_detection: None,
+            health_check: None,
+            tmux: None,
+            repl: None,
+            log_sources: Vec::new(),
+            run_as: None,
+            resource_limits: None,
+            container: None,
+            source: None,
+            log_sinks: Vec::new(),
+            redaction: None,
+            metadata: HashMap::new(),
+            runbook: None,
+            startup: None,
+            lazy_start: None,
+            env_loader: None,
+            output_recording: None,
+            assertions: Vec::new(),
+            restart_policy: None,
+            depends_on: Vec::new(),
+            parameters: Vec::new(),
+            keep_stdin_open: false,
+            group: None,
+            health: None,
+            sensitive_env: HashMap::new(),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_bind_replaces_only_stale_sockets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mcp.sock");
+
+        let listener = bind(&path).unwrap();
+        // 動いているサーバーのソケットは消さない
+        let error = bind(&path).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AddrInUse);
+        assert!(tokio::net::UnixStream::connect(&path).await.is_ok());
+
+        // 接続を拒否する古いソケットは置き換える
+        drop(listener);
+        let _listener = bind(&path).unwrap();
+        assert!(tokio::net::UnixStream::connect(&path).await.is_ok());
+    }
+
+    #[test]
+    fn test_summary_lists_processes_and_new_notifications() {
+        let disconnected_at = Utc::now() - chrono::Duration::minutes(5);
+        let processes = vec![
+            process(
+                "web",
+                ProcessState::Running {
+                    pid: 2,
+                    started_at: disconnected_at,
+                },
+            ),
+            process(
+                "api",
+                ProcessState::Running {
+                    pid: 1,
+                    started_at: disconnected_at,
+                },
+            ),
+            process(
+                "worker",
+                ProcessState::Failed {
+                    error: "exit 1".to_string(),
+                    failed_at: Utc::now(),
+                },
+            ),
+            process("docs", ProcessState::NotStarted),
+        ];
+        let notification = |title: &str, created_at| Notification {
+            notification_id: title.to_string(),
+            kind: NotificationKind::ProcessFailed,
+            title: title.to_string(),
+            message: String::new(),
+            process_id: None,
+            link: None,
+            created_at,
+            read: false,
+            read_at: None,
+            route: None,
+        };
+        let unread = vec![
+            notification("worker failed", Utc::now()),
+            notification("old failure", disconnected_at - chrono::Duration::hours(1)),
+        ];
+
+        let summary = summarize(&processes, &unread, disconnected_at);
+        assert!(summary.starts_with("Reconnected to a running Vantage server"));
+        assert!(
+            summary
+                .contains("Processes: 4 (2 running: api, web; 1 failed: worker; 1 not started)."),
+            "{summary}"
+        );
+        assert!(summary.contains("Since the disconnect: 1 notification(s): worker failed."));
+        assert!(summary.contains("Unread notifications: 2"));
+
+        let ids: Vec<String> = (0..12).map(|i| format!("p{i:02}")).collect();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        assert!(listed(&ids).ends_with("p09 (+2 more)"));
+    }
+}
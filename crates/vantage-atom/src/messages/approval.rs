@@ -0,0 +1,11 @@
+//! 承認ワークフロー関連のメッセージ型定義
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// 承認リクエストの状態取得リクエスト
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetApprovalRequest {
+    /// Approval ID returned by a tool that requires approval
+    pub approval_id: String,
+}
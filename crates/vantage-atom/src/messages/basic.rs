@@ -4,3 +4,11 @@ use rmcp::schemars;
 pub struct EchoRequest {
     pub message: String,
 }
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateDebugBundleRequest {
+    /// Only include logs and events from the last N minutes (default: everything retained)
+    pub since_minutes: Option<u64>,
+    /// Where to write the tar.gz (default: <state dir>/debug-bundles/vantage-debug-<timestamp>.tar.gz; see show_paths)
+    pub output_path: Option<String>,
+}
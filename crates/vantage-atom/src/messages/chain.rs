@@ -0,0 +1,34 @@
+//! プロセスの連鎖関連のメッセージ型定義
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use vantage_persistence::ChainAction;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateChainRequest {
+    /// Unique chain ID (default: "<after>-then-<process_id>")
+    pub chain_id: Option<String>,
+    /// Process whose successful exit (exit code 0) triggers the chain
+    pub after: String,
+    /// What to do next: {"type": "start_process", "process_id": "..."} or {"type": "run_template", "template_id": "...", "process_id": "...", "values": {...}}
+    pub action: ChainAction,
+    /// Whether the chain is active (default: true)
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChainIdRequest {
+    pub chain_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetChainEnabledRequest {
+    pub chain_id: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListChainsRequest {
+    /// Only list chains that start from or lead to this process
+    pub process_id: Option<String>,
+}
@@ -0,0 +1,41 @@
+//! カオステスト関連のメッセージ型定義
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChaosKillRequest {
+    /// Target process ID (a random running process is picked when omitted)
+    pub id: Option<String>,
+    /// Only pick among running processes with this tag
+    pub tag: Option<String>,
+    /// Start the process again after this many seconds
+    pub restart_after_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChaosPauseRequest {
+    /// Target process ID (a random running process is picked when omitted)
+    pub id: Option<String>,
+    /// Only pick among running processes with this tag
+    pub tag: Option<String>,
+    /// How long to keep the process stopped before sending SIGCONT
+    pub duration_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChaosSchedulePausesRequest {
+    /// Target process ID (a random running process is picked on each tick when omitted)
+    pub id: Option<String>,
+    /// Only pick among running processes with this tag
+    pub tag: Option<String>,
+    /// Seconds between pauses
+    pub interval_secs: u64,
+    /// How long each pause lasts (must be shorter than the interval)
+    pub pause_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChaosCancelScheduleRequest {
+    pub schedule_id: String,
+}
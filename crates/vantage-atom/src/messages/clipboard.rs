@@ -1,8 +1,9 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 /// Set clipboard content (text)
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct SetClipboardTextRequest {
     pub content: String,
     #[serde(default)]
@@ -10,7 +11,7 @@ pub struct SetClipboardTextRequest {
 }
 
 /// Set clipboard content (file)
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct SetClipboardFileRequest {
     pub content: String,
     pub filename: String,
@@ -32,7 +33,8 @@ pub struct GetClipboardHistoryRequest {
 }
 
 /// Search clipboard items
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchClipboardRequest {
     pub query: String,
     #[serde(default)]
@@ -46,7 +48,7 @@ pub struct ClearClipboardRequest {
 }
 
 /// Response for clipboard operations
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct ClipboardResponse {
     pub id: String,
     pub content: String,
@@ -58,7 +60,7 @@ pub struct ClipboardResponse {
 }
 
 /// Response for clipboard history
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct ClipboardHistoryResponse {
     pub total_count: usize,
     pub items: Vec<ClipboardResponse>,
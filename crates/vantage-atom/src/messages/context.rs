@@ -0,0 +1,33 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Request to save the current session context
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SaveContextRequest {
+    /// Context name (letters, digits, '-', '_' or '.'), e.g. "feature-x"
+    pub name: String,
+    /// Workspace directory. Defaults to the server's working directory
+    #[serde(default)]
+    pub workspace: Option<String>,
+    /// Free-form notes about what you were working on
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// Request to restore a saved session context
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RestoreContextRequest {
+    pub name: String,
+    /// Stop running processes that are not part of the context
+    #[serde(default)]
+    pub stop_others: bool,
+    /// Check out the git branch that was active when the context was saved
+    #[serde(default)]
+    pub checkout_branch: bool,
+}
+
+/// Request to delete a saved session context
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeleteContextRequest {
+    pub name: String,
+}
@@ -0,0 +1,29 @@
+//! Kubernetes関連のメッセージ型定義
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use vantage_persistence::{KubernetesPortForward, KubernetesWorkloadKind};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RegisterKubernetesWorkloadRequest {
+    /// Unique workload ID; also used as the ID for get_process_output
+    pub workload_id: String,
+    /// pod or deployment
+    pub kind: KubernetesWorkloadKind,
+    /// Name of the pod or deployment
+    pub name: String,
+    /// kubeconfig context (default: the current context)
+    pub context: Option<String>,
+    /// Namespace (default: the context's namespace)
+    pub namespace: Option<String>,
+    /// Container to read logs from (default: the pod's default container)
+    pub container: Option<String>,
+    /// Forward local ports (bound on 127.0.0.1) to a ready pod of the workload
+    pub port_forwards: Option<Vec<KubernetesPortForward>>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct KubernetesWorkloadIdRequest {
+    pub workload_id: String,
+}
@@ -0,0 +1,29 @@
+//! メンテナンスウィンドウ関連のメッセージ型定義
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use vantage_persistence::MaintenanceSchedule;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateMaintenanceWindowRequest {
+    /// Unique window name (an existing window with the same name is replaced)
+    pub name: String,
+    /// When the window is active: {"type": "once", "start": "<RFC 3339>", "end": "<RFC 3339>"} or {"type": "recurring", "cron": "0 2 * * sun", "duration_minutes": 60} (cron uses server local time)
+    pub schedule: MaintenanceSchedule,
+    /// Process IDs covered by the window
+    #[serde(default)]
+    pub processes: Vec<String>,
+    /// Processes with any of these tags are covered
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Processes whose working directory is under any of these absolute paths are covered
+    #[serde(default)]
+    pub workspaces: Vec<String>,
+    /// Why the window exists (shown in status and events)
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MaintenanceWindowNameRequest {
+    pub name: String,
+}
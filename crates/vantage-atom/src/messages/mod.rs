@@ -1,15 +1,35 @@
+pub mod approval;
 pub mod basic;
+pub mod chain;
+pub mod chaos;
 pub mod ci;
 pub mod clipboard;
+pub mod context;
+pub mod kubernetes;
+pub mod maintenance;
+pub mod notification;
 pub mod process;
+pub mod schedule;
+pub mod settings;
 pub mod snapshot;
 pub mod suggestions;
 pub mod template;
+pub mod tunnel;
 
+pub use approval::*;
 pub use basic::*;
+pub use chain::*;
+pub use chaos::*;
 pub use ci::*;
 pub use clipboard::*;
+pub use context::*;
+pub use kubernetes::*;
+pub use maintenance::*;
+pub use notification::*;
 pub use process::*;
+pub use schedule::*;
+pub use settings::*;
 pub use snapshot::*;
 pub use suggestions::*;
 pub use template::*;
+pub use tunnel::*;
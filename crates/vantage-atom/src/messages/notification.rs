@@ -0,0 +1,49 @@
+//! 通知の受信箱関連のメッセージ型定義
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use vantage_persistence::{NotificationKind, NotificationRoute, NotificationSeverity};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListNotificationsRequest {
+    /// Only return notifications that have not been marked as read
+    #[serde(default)]
+    pub unread_only: bool,
+    /// Only return notifications of this kind (process_failed, crash_loop, process_unhealthy, ci_failed, approval_pending)
+    #[serde(default)]
+    pub kind: Option<NotificationKind>,
+    /// Maximum number of notifications to return, newest first (default 50)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MarkReadRequest {
+    /// Notification IDs to mark as read
+    #[serde(default)]
+    pub ids: Vec<String>,
+    /// Mark every notification as read (ids is ignored)
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TestNotificationRouteRequest {
+    /// Kind of the sample notification (process_failed, crash_loop, process_unhealthy, ci_failed, approval_pending)
+    pub kind: NotificationKind,
+    /// Severity of the sample notification (info, warning, error, critical; defaults to the kind's severity)
+    #[serde(default)]
+    pub severity: Option<NotificationSeverity>,
+    /// Use the tags and working directory of this process
+    #[serde(default)]
+    pub process_id: Option<String>,
+    /// Tags of the process the notification is about
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Working directory of the process the notification is about
+    #[serde(default)]
+    pub workspace: Option<String>,
+    /// Rules to try instead of the saved notification_routes setting
+    #[serde(default)]
+    pub routes: Option<Vec<NotificationRoute>>,
+}
@@ -1,17 +1,96 @@
-use crate::process::{OutputStream, ProcessFilter};
+use crate::process::history_export::ExportFormat;
+use crate::process::recording::RecordedStream;
+use crate::process::{
+    ContainerConfig, EnvLoader, HealthCheckConfig, IdleDetectionConfig, InputEncoding,
+    LogSinkConfig, LogSource, OutputRecordingConfig, OutputStream, ProcessAssertion,
+    ProcessDependency, ProcessFilter, ProcessParameter, RedactionOverride, RedactionRule,
+    ReplConfig, ResourceLimits, RestartPolicy, RunAsConfig, Runbook, StartupOrder, TmuxConfig,
+};
 use rmcp::schemars;
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, utoipa::ToSchema)]
 pub struct CreateProcessRequest {
     pub id: String,
     pub command: String,
     #[serde(default)]
     pub args: Vec<String>,
+    /// Environment variables. Values like op://vault/item/field (1Password) or vault://path#field
+    /// (HashiCorp Vault) are resolved when the process is spawned
     #[serde(default)]
     pub env: std::collections::HashMap<String, String>,
+    /// Names of `env` entries that are secrets: their values are encrypted at rest, masked in every response and decrypted only when the process is spawned
+    #[serde(default)]
+    pub sensitive_env: Vec<String>,
+    /// Working directory (`~` and `$VAR` are expanded)
     pub cwd: Option<String>,
     #[serde(default)]
     pub auto_start_on_restore: bool, // サーバー起動時に自動起動
+    /// Tags for grouping (e.g. for chaos testing targets)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Flag the process as idle when it produces no output and stays below the CPU threshold
+    #[serde(default)]
+    pub idle_detection: Option<IdleDetectionConfig>,
+    /// Flag the process as unhealthy when error lines per minute or the stderr share of its output exceed a threshold (see get_process_metrics), or when a `probe` (`http` GET, `tcp` connect or `exec` command) fails `failure_threshold` times in a row, run every `interval_secs` with `timeout_secs`
+    #[serde(default)]
+    pub health_check: Option<HealthCheckConfig>,
+    /// Launch inside a tmux session/window so it can be attached to from a terminal
+    #[serde(default)]
+    pub tmux: Option<TmuxConfig>,
+    /// Treat the process as a REPL (python -i, node -i, a shell...) so code can be sent with execute_in_repl
+    #[serde(default)]
+    pub repl: Option<ReplConfig>,
+    /// Extra log files or FIFOs to tail into the output, labeled per source
+    #[serde(default)]
+    pub log_sources: Vec<LogSource>,
+    /// Forward this process's output to syslog, Loki or a JSONL file (in addition to the sinks in settings)
+    #[serde(default)]
+    pub log_sinks: Vec<LogSinkConfig>,
+    /// Disable global redaction rules by name or add rules that mask matching output of this process before it is stored
+    #[serde(default)]
+    pub redaction: Option<RedactionOverride>,
+    /// Arbitrary key/value metadata such as owner, ticket or runbook URL; returned in list/status responses and filterable as `metadata.<key>`
+    #[serde(default)]
+    pub metadata: std::collections::HashMap<String, String>,
+    /// Recovery steps shown next to the process: `{"path": "docs/RUNBOOK.md"}` (relative to cwd, read on each view) or `{"inline": "markdown"}`
+    #[serde(default)]
+    pub runbook: Option<Runbook>,
+    /// Start order for auto-start on restore and start_processes: lower `priority` starts first (default 0), processes with the same priority and `group` start in parallel, and `ready_port` makes the next ones wait until it accepts connections (`ready_timeout_secs`, default 30)
+    #[serde(default)]
+    pub startup: Option<StartupOrder>,
+    /// Load the project's environment (`auto`, `mise`, `direnv` or `asdf`) from cwd each time the process starts, so it sees the same PATH and tool versions as your shell; `env` still takes precedence
+    #[serde(default)]
+    pub env_loader: Option<EnvLoader>,
+    /// Record the timestamped stdout/stderr of every run into a replayable file (`include_stdin` also records code sent to a REPL, `keep` recordings are kept, default 10); play it back with replay_output
+    #[serde(default)]
+    pub output_recording: Option<OutputRecordingConfig>,
+    /// Expected behavior checked by run_checks: `ready_within` (port opens or pattern appears), `no_output_match` (pattern never appears), `exit_code` (exits with the code)
+    #[serde(default)]
+    pub assertions: Vec<ProcessAssertion>,
+    /// Restart the process automatically when it exits: `mode` is `never` (default), `on_failure` (non-zero exit) or `always` (any exit not requested with stop_process); waits `backoff_ms` (default 1000, doubling per retry up to `max_backoff_ms`, default 60000) and gives up after `max_retries` consecutive restarts (default unlimited)
+    #[serde(default)]
+    pub restart_policy: Option<RestartPolicy>,
+    /// Named group (e.g. `stack`) so start_group/stop_group bring the processes up in `startup` order and tear them down in reverse
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Processes to start first, e.g. `[{"process_id": "db", "ready": {"type": "log_pattern", "pattern": "ready to accept connections"}}]`. `ready` is `started` (default), `healthy` (the dependency needs a health_check) or `log_pattern`; `timeout_secs` defaults to 30. start_process and auto-start on restore start missing dependencies and wait for them; cycles are rejected
+    #[serde(default)]
+    pub depends_on: Vec<ProcessDependency>,
+    /// Per-run parameters for `{{name}}` placeholders in args, e.g. args `["test", "{{filter}}"]` with `[{"name": "filter", "description": "Test name filter"}]`. Values are given to start_process_with_params for that run only; `default` is used otherwise (an argument that is just an empty placeholder is dropped), `required` parameters must always be given and `pattern` is a regex the whole value must match
+    #[serde(default)]
+    pub parameters: Vec<ProcessParameter>,
+    /// Keep stdin open as a pipe so input can be sent with send_process_input (not with tmux or repl)
+    #[serde(default)]
+    pub keep_stdin_open: bool,
+    /// Run the process as another local user (and optionally group). Unix only; switching to a different user requires the server to run as root
+    #[serde(default)]
+    pub run_as: Option<RunAsConfig>,
+    /// Hard memory/CPU/pid limits enforced through a per-process cgroup (Linux with cgroup v2 only)
+    #[serde(default)]
+    pub resource_limits: Option<ResourceLimits>,
+    /// Run the command inside a docker/podman container (empty command = the image's CMD), optionally building the image before every start
+    #[serde(default)]
+    pub container: Option<ContainerConfig>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -20,6 +99,14 @@ pub struct StartProcessRequest {
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct StartProcessWithParamsRequest {
+    pub id: String,
+    /// Values for the process's declared parameters, e.g. {"filter": "parser::"}
+    #[serde(default)]
+    pub params: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, utoipa::ToSchema)]
 pub struct StopProcessRequest {
     pub grace_period_ms: Option<u64>,
 }
@@ -31,21 +118,472 @@ pub struct McpStopProcessRequest {
     pub grace_period_ms: Option<u64>,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RestartProcessRequest {
+    pub id: String,
+    /// How long to wait after SIGTERM before SIGKILL (default 5000)
+    pub grace_period_ms: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetProcessAssertionsRequest {
+    pub id: String,
+    /// Expected behavior: `{"type":"ready_within","within_secs":10,"port":3000}`, `{"type":"no_output_match","pattern":"ERROR","within_secs":30}`, `{"type":"exit_code","code":0}`; empty removes them
+    #[serde(default)]
+    pub assertions: Vec<ProcessAssertion>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RunChecksRequest {
+    /// Processes to check (default: every process with assertions)
+    #[serde(default)]
+    pub process_ids: Vec<String>,
+    /// Upper bound for each process in seconds (default 300)
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Leave the processes running after the checks instead of stopping them
+    #[serde(default)]
+    pub keep_running: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RollingRestartRequest {
+    /// Process to restart (pass the original id, not the `-green` instance)
+    pub id: String,
+    /// Port the reverse proxy listens on; required on the first rolling restart of a process, which puts the proxy in front of it
+    #[serde(default)]
+    pub proxy_port: Option<u16>,
+    /// Address the proxy listens on (default: 127.0.0.1; only used when the proxy is created)
+    #[serde(default)]
+    pub proxy_host: Option<String>,
+    /// Environment variable that tells the process which port to listen on (default: PORT; only used when the proxy is created)
+    #[serde(default)]
+    pub port_env: Option<String>,
+    /// Port for the new instance (default: the port that instance used last time, otherwise a free port)
+    #[serde(default)]
+    pub standby_port: Option<u16>,
+    /// How long to wait for the new instance to accept connections (default: 30)
+    #[serde(default)]
+    pub ready_timeout_secs: Option<u64>,
+    /// How long the old instance keeps serving connections accepted before the switch (default: 5)
+    #[serde(default)]
+    pub drain_secs: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetLazyStartRequest {
+    pub id: String,
+    /// Port Vantage listens on in place of the process; clients connect here. Omit to turn lazy start off
+    #[serde(default)]
+    pub listen_port: Option<u16>,
+    /// Address to listen on (default: 127.0.0.1)
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Port the process itself listens on (default: a free port, kept in the definition)
+    #[serde(default)]
+    pub backend_port: Option<u16>,
+    /// Environment variable that tells the process which port to listen on (default: PORT)
+    #[serde(default)]
+    pub port_env: Option<String>,
+    /// How long to wait for the process to accept connections after starting it (default: 30)
+    #[serde(default)]
+    pub ready_timeout_secs: Option<u64>,
+    /// Stop the process again after this many seconds without connections (default: keep it running)
+    #[serde(default)]
+    pub idle_stop_secs: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct PauseProcessRequest {
+    pub id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ResumeProcessRequest {
+    pub id: String,
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetProcessStatusRequest {
     pub id: String,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetProcessMetricsRequest {
+    pub id: String,
+    /// Length of the window in seconds, compared with the window right before it (default 300; a multiple of 10 up to 1800)
+    #[serde(default)]
+    pub window_secs: Option<u64>,
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetProcessOutputRequest {
     pub id: String,
     pub stream: OutputStream,
     pub lines: Option<u32>,
+    /// Prefix each line with its capture timestamp (RFC 3339)
+    #[serde(default)]
+    pub timestamps: bool,
+    /// Only output after this bookmark (returns the first `lines` lines after it)
+    #[serde(default)]
+    pub from_bookmark: Option<String>,
+    /// Only output up to this bookmark (with no from_bookmark, returns the last `lines` lines before it)
+    #[serde(default)]
+    pub to_bookmark: Option<String>,
+    /// Follow mode: return JSON with a cursor to pass back for the next lines. Without cursor or since, starts with the last `lines` lines
+    #[serde(default)]
+    pub follow: bool,
+    /// Follow mode: only lines after this cursor (from the previous response), oldest first
+    #[serde(default)]
+    pub cursor: Option<u64>,
+    /// Follow mode: only lines captured after this time (RFC 3339), oldest first
+    #[serde(default)]
+    pub since: Option<String>,
+    /// Return JSON lines with seq, timestamp, stream (stdout/stderr) and text instead of plain text. With stream Both, stdout and stderr are interleaved in the order they were written
+    #[serde(default)]
+    pub structured: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AddOutputBookmarkRequest {
+    pub id: String,
+    /// Bookmark name, e.g. "before migration" (an existing bookmark with the same name is moved)
+    pub name: String,
+    /// Optional note shown when listing bookmarks
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListOutputBookmarksRequest {
+    pub id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RemoveOutputBookmarkRequest {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExecuteInReplRequest {
+    pub id: String,
+    /// Code to send to the REPL's stdin
+    pub code: String,
+    /// How long to wait for the execution to finish (default: 30000)
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SendProcessInputRequest {
+    pub id: String,
+    /// Input to write to the process's stdin
+    #[serde(default)]
+    pub input: String,
+    /// `text` (UTF-8, default) or `base64` for arbitrary bytes such as control characters
+    #[serde(default)]
+    pub encoding: InputEncoding,
+    /// Append a newline if the input does not end with one (default: true for text, false for base64)
+    pub newline: Option<bool>,
+    /// Close stdin after writing so the process reads EOF
+    #[serde(default)]
+    pub close: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetOutputSamplingRequest {
+    pub id: String,
+    /// Keep one of every N lines (at least 2). Omit to turn sampling off
+    pub every_n: Option<u32>,
+    /// Regex patterns for lines that are always kept (default: error/warning/panic/exception patterns)
+    #[serde(default)]
+    pub keep_patterns: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetLogSinksRequest {
+    /// Sinks that receive the output of every process (replaces the current list; empty turns global forwarding off)
+    #[serde(default)]
+    pub sinks: Vec<LogSinkConfig>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetRedactionRulesRequest {
+    /// Process whose own override is replaced; omit to replace the global rules applied to every process
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Rules masking matching output before it is stored, forwarded or returned (replaces the current list)
+    #[serde(default)]
+    pub rules: Vec<RedactionRule>,
+    /// Names of global rules not applied to the process (only with `id`)
+    #[serde(default)]
+    pub disable: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetRunbookRequest {
+    pub id: String,
+    /// Markdown file with the steps (`~` and `$VAR` are expanded; relative paths are resolved from the process's cwd)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Markdown written directly (use either path or text; omit both to remove the runbook)
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetEnvLoaderRequest {
+    pub id: String,
+    /// `auto` (detect mise.toml, .envrc or .tool-versions from cwd upwards), `mise`, `direnv` or `asdf`; omit to stop loading the project environment
+    #[serde(default)]
+    pub loader: Option<EnvLoader>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetOutputRecordingRequest {
+    pub id: String,
+    /// `include_stdin` also records code sent to a REPL process; `keep` is the number of recordings kept (default 10). Omit to stop recording
+    #[serde(default)]
+    pub recording: Option<OutputRecordingConfig>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListOutputRecordingsRequest {
+    pub id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ReplayOutputRequest {
+    pub id: String,
+    /// Recording name from list_output_recordings (default: the latest)
+    #[serde(default)]
+    pub recording: Option<String>,
+    /// Playback speed: 1 keeps the original timing, 10 plays ten times faster, 0 returns everything at once (default 1)
+    #[serde(default)]
+    pub speed: Option<f64>,
+    /// Only replay these streams (`stdout`, `stderr`, `stdin`; default all)
+    #[serde(default)]
+    pub streams: Vec<RecordedStream>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetEnergyCostRequest {
+    /// Power drawn by one fully busy CPU core in watts (omit to keep the current value)
+    pub watts_per_core: Option<f64>,
+    /// Electricity price per kWh (omit to keep the current value)
+    pub price_per_kwh: Option<f64>,
+    /// Currency label for estimated costs, e.g. USD or JPY (omit to keep the current value)
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ScanExternalProcessesRequest {
+    /// Definitions to match (default: every process that is not running)
+    #[serde(default)]
+    pub process_ids: Vec<String>,
+    /// Adopt the matching processes instead of only reporting them (default: false)
+    #[serde(default)]
+    pub adopt: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetAutoAdoptRequest {
+    /// Absolute path of the workspace; processes whose cwd is inside it are adopted automatically
+    pub workspace: String,
+    /// true to adopt matching processes automatically, false to stop doing so
+    pub enabled: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DiagnoseProcessRequest {
+    pub id: String,
+    /// When no rule matches, ask the client's LLM via MCP sampling to classify the redacted stderr excerpt and store its answer as a rule pending confirmation (default: false; requires client sampling support)
+    pub use_sampling: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ConfirmDiagnosisRuleRequest {
+    pub rule_id: String,
+    /// true to use the rule in future diagnoses, false to reject it
+    pub accept: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetProcessPhasesRequest {
+    pub id: String,
+    /// Regex patterns marking the start of each phase (e.g. "Compiling", "Listening on")
+    pub markers: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetProcessNetworkRequest {
+    pub id: String,
+    /// Sample I/O for this many milliseconds to estimate throughput (max 5000)
+    pub sample_ms: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DiffProcessEnvRequest {
+    pub id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExplainRunEnvironmentRequest {
+    pub id: String,
+    /// Run number to explain (default: the latest run)
+    pub run: Option<u64>,
+    /// Run number to compare with (default: the run before `run`)
+    pub compare_with: Option<u64>,
+    /// List every environment variable, not only PATH, locale and the changes (default: true)
+    pub include_env: Option<bool>,
+}
+
+/// 出力を比べる期間（RFC 3339、省略した端は無制限）
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct OutputWindowRequest {
+    /// Start of the window (inclusive), e.g. "2024-05-01T10:00:00Z"
+    pub from: Option<String>,
+    /// End of the window (exclusive)
+    pub to: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExportHistoryRequest {
+    /// Start of the time range (inclusive, RFC 3339); omit to export everything retained
+    pub from: Option<String>,
+    /// End of the time range (exclusive, RFC 3339)
+    pub to: Option<String>,
+    /// csv (default) or parquet (requires a server built with the parquet-export feature)
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Directory to write runs, events and metrics files into (default: <state dir>/history-exports/<timestamp>; see show_paths)
+    pub output_dir: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetProcessTimelineRequest {
+    pub id: String,
+    /// Only include entries starting at or after this time (RFC 3339)
+    pub from: Option<String>,
+    /// Only include entries starting before this time (RFC 3339)
+    pub to: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DiffOutputWindowsRequest {
+    pub id: String,
+    /// Earlier window; give both windows, or neither to compare the runs around a restart
+    pub before: Option<OutputWindowRequest>,
+    /// Later window
+    pub after: Option<OutputWindowRequest>,
+    /// Which restart to compare around when no windows are given (1 = most recent, the default)
+    pub restart: Option<usize>,
+    /// Maximum number of messages in each list (default: 20)
+    pub limit: Option<usize>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct ListProcessesRequest {
     pub filter: Option<ProcessFilter>,
+    /// Filter expression, e.g. `state == running && tag in ["backend"] && started_at > now()-2h`.
+    /// Fields: id, command, args, cwd, state, tag/tags, pid, started_at, stopped_at, exit_code, error, auto_start, metadata.<key>
+    pub expression: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetEventsRequest {
+    /// Filter expression, e.g. `type in [process_error, crash_loop_detected] && timestamp > now()-1h`.
+    /// Fields: type, process_id, timestamp
+    pub expression: Option<String>,
+    /// Maximum number of events, newest first (default: 100)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct StartProcessesRequest {
+    /// Filter expression selecting the processes (same syntax as list_processes)
+    pub expression: String,
+    /// Only report which processes match without starting them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct StopProcessesRequest {
+    /// Filter expression selecting the processes (same syntax as list_processes)
+    pub expression: String,
+    pub grace_period_ms: Option<u64>,
+    /// Only report which processes match without stopping them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct StartGroupRequest {
+    /// Group name set with `group` on create_process or update_process_config
+    pub group: String,
+    /// Only report which processes are in the group without starting them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct StopGroupRequest {
+    /// Group name set with `group` on create_process or update_process_config
+    pub group: String,
+    pub grace_period_ms: Option<u64>,
+    /// Only report which processes are in the group without stopping them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetGroupStatusRequest {
+    pub group: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RemoveProcessesRequest {
+    /// Filter expression selecting the processes (same syntax as list_processes)
+    pub expression: String,
+    /// Only report which processes match without removing them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CleanupProcessesRequest {
+    /// Only processes stopped at least this long ago (e.g. "30m", "12h", "7d")
+    #[serde(default)]
+    pub older_than: Option<String>,
+    /// Only processes whose id matches this glob pattern (`*` and `?` wildcards)
+    #[serde(default)]
+    pub id_pattern: Option<String>,
+    /// Only processes with this tag
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Also clean up failed processes, not just stopped ones
+    #[serde(default)]
+    pub include_failed: bool,
+    /// Also clean up processes marked to auto-start on restore
+    #[serde(default)]
+    pub include_auto_start: bool,
+    /// Only preview which processes match without moving them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RestoreFromRecycleBinRequest {
+    /// Id of the process to restore (the most recently recycled one if there are several)
+    pub id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct EmptyRecycleBinRequest {
+    /// Only permanently delete the recycled definitions of this process (all when omitted)
+    #[serde(default)]
+    pub id: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -55,11 +593,13 @@ pub struct RemoveProcessRequest {
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct ExportProcessesRequest {
+    /// Optional file path (`~` and `$VAR` are expanded; must be under the home, temp or current directory)
     pub file_path: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct ImportProcessesRequest {
+    /// File path (`~` and `$VAR` are expanded; must be under the home, temp or current directory)
     pub file_path: String,
 }
 
@@ -67,10 +607,14 @@ pub struct ImportProcessesRequest {
 pub struct UpdateProcessConfigRequest {
     pub id: String,
     pub auto_start_on_restore: Option<bool>,
+    /// Start order for auto-start on restore and start_processes (priority, parallel group, ready_port, ready_timeout_secs)
+    pub startup: Option<StartupOrder>,
+    /// Group used by start_group/stop_group (an empty string removes the process from its group)
+    pub group: Option<String>,
 }
 
 /// Request to update process attributes
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema, utoipa::ToSchema)]
 pub struct UpdateProcessRequest {
     pub id: String,
     /// Optional: Update command
@@ -79,10 +623,14 @@ pub struct UpdateProcessRequest {
     pub args: Option<Vec<String>>,
     /// Optional: Update environment variables
     pub env: Option<std::collections::HashMap<String, String>>,
-    /// Optional: Update working directory
+    /// Optional: Names to encrypt, from the new `env` or the current one (sensitive variables not in a new `env` are kept; a name given in a new `env` becomes plain unless listed here)
+    pub sensitive_env: Option<Vec<String>>,
+    /// Optional: Update working directory (`~` and `$VAR` are expanded)
     pub cwd: Option<String>,
     /// Optional: Update auto_start_on_restore flag
     pub auto_start_on_restore: Option<bool>,
+    /// Optional: Set metadata keys; a null value removes the key and unspecified keys are kept
+    pub metadata: Option<std::collections::HashMap<String, Option<String>>>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
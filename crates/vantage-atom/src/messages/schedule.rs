@@ -0,0 +1,36 @@
+//! プロセスの定期実行関連のメッセージ型定義
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateScheduleRequest {
+    /// Unique schedule ID (default: "<process_id>-schedule")
+    pub schedule_id: Option<String>,
+    /// Process to start
+    pub process_id: String,
+    /// Cron expression in the server's local time: "minute hour day month weekday" (e.g. "0 * * * *" for every hour)
+    pub cron: String,
+    /// Whether the schedule is active (default: true)
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateScheduleRequest {
+    pub schedule_id: String,
+    /// New cron expression
+    pub cron: Option<String>,
+    /// Turn the schedule off or on
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleIdRequest {
+    pub schedule_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListSchedulesRequest {
+    /// Only list schedules that start this process
+    pub process_id: Option<String>,
+}
@@ -0,0 +1,18 @@
+//! 設定関連のメッセージ型定義
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetSettingRequest {
+    /// Dotted setting key such as mdns.enabled (omit to get every key)
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetSettingRequest {
+    /// Dotted setting key such as mdns.enabled (see get_settings_schema)
+    pub key: String,
+    /// New value of the key's type; null clears a nullable setting
+    pub value: serde_json::Value,
+}
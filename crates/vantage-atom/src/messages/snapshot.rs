@@ -1,44 +1,92 @@
+use crate::process::ConflictPolicy;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use vantage_persistence::ConfigFormat;
 
-/// Request to export processes to YAML format
+/// Request to export processes to YAML (or KDL) format
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ExportYamlRequest {
-    /// Optional file path. If not provided, uses default location
+    /// Optional file path (`~` and `$VAR` are expanded; must be under the home, temp or current directory). If not provided, uses default location
     pub file_path: Option<String>,
     /// Export only processes with auto_start_on_restore flag set to true
     pub only_auto_start: bool,
+    /// File format (yaml or kdl). Defaults to the file extension: .kdl is KDL, anything else YAML
+    pub format: Option<ConfigFormat>,
 }
 
-/// Request to import processes from YAML format
+/// Request to import processes from YAML (or KDL) format
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ImportYamlRequest {
-    /// File path to import from
+    /// File path to import from (`~` and `$VAR` are expanded; must be under the home, temp or current directory)
     pub file_path: String,
+    /// File format (yaml or kdl). Defaults to the file extension: .kdl is KDL, anything else YAML
+    pub format: Option<ConfigFormat>,
 }
 
 /// Request to create a snapshot
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CreateSnapshotRequest {
-    /// Optional file path. If not provided, uses default location
+    /// Optional file path (`~` and `$VAR` are expanded; must be under the home, temp or current directory). If not provided, uses default location
     pub file_path: Option<String>,
-    /// Snapshot format (yaml or surql)
+    /// Snapshot format (yaml, kdl or surql)
     pub format: SnapshotFormat,
 }
 
 /// Request to restore from a snapshot
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct RestoreSnapshotRequest {
-    /// Optional file path. If not provided, uses default location
+    /// Optional file path (`~` and `$VAR` are expanded; must be under the home, temp or current directory). If not provided, uses default location
     pub file_path: Option<String>,
-    /// Snapshot format (yaml or surql)
+    /// Snapshot format (yaml, kdl or surql)
     pub format: SnapshotFormat,
 }
 
+/// Request to restore processes skipped by a safe-mode startup
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SafeModeRestoreRequest {
+    /// Processes to restore from the skipped snapshot (default: all of them)
+    #[serde(default)]
+    pub process_ids: Vec<String>,
+    /// Start the restored processes that are marked auto_start_on_restore (default: false)
+    #[serde(default)]
+    pub start: bool,
+}
+
+/// Request to export the workspace as a portable bundle
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ExportWorkspaceRequest {
+    /// Bundle file path (`~` and `$VAR` are expanded; must be under the home, temp or current directory). Defaults to workspace.vantage.yaml in the export directory
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// Workspace root; working directories under it are written relative to it (default: the server's working directory)
+    #[serde(default)]
+    pub root: Option<String>,
+}
+
+/// Request to import a workspace bundle
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ImportWorkspaceRequest {
+    /// Bundle file path (`~` and `$VAR` are expanded; must be under the home, temp or current directory)
+    pub file_path: String,
+    /// What to do when a process, template, chain or context with the same id exists: fail (default, changes nothing), skip or overwrite
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+    /// Replace the current settings with the bundle's settings (default: false)
+    #[serde(default)]
+    pub import_settings: bool,
+    /// Workspace root that relative working directories are resolved against (default: the server's working directory)
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Report what would be created, replaced or skipped and the validation issues without changing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
 /// Snapshot format
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum SnapshotFormat {
     Yaml,
+    Kdl,
     Surql,
 }
@@ -9,5 +9,50 @@ pub struct GetSuggestionsRequest {
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ApplySuggestionRequest {
+    /// Number of the suggestion in the latest get_suggestions result (1-based)
     pub suggestion_index: usize,
+    /// Execute the action; without it only a preview is returned
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DismissSuggestionRequest {
+    /// Number of the suggestion in the latest get_suggestions result (1-based)
+    pub suggestion_index: usize,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConfigureSuggestionProviderRequest {
+    /// Provider name as listed by list_suggestion_providers
+    pub provider: String,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Multiplier applied to each suggestion's confidence (>= 0)
+    #[serde(default)]
+    pub weight: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WatchSuggestionsRequest {
+    /// Only notify suggestions with at least this confidence, 0.0 to 1.0 (default: 0.7)
+    #[serde(default)]
+    pub min_confidence: Option<f64>,
+    /// Only notify suggestions from these providers (default: all)
+    #[serde(default)]
+    pub providers: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnwatchSuggestionsRequest {
+    pub watch_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetAssistantAnalyticsRequest {
+    /// Minutes after an action within which a failure of its target process counts against it (default: 10)
+    #[serde(default)]
+    pub window_minutes: Option<i64>,
 }
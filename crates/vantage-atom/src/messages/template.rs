@@ -61,3 +61,77 @@ pub struct CreateProcessFromTemplateRequest {
     pub override_cwd: Option<String>,
     pub auto_start: Option<bool>,
 }
+
+/// テンプレートの試し描画リクエスト
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RenderTemplateRequest {
+    pub template_id: String,
+    /// Values for the template variables (defaults are used for the rest). Numbers and booleans are accepted for typed variables; values are checked against each variable's schema
+    #[serde(
+        default,
+        deserialize_with = "vantage_persistence::template_schema::deserialize_values"
+    )]
+    #[schemars(with = "HashMap<String, serde_json::Value>")]
+    pub values: HashMap<String, String>,
+    pub override_args: Option<Vec<String>>,
+    pub override_env: Option<HashMap<String, String>>,
+    pub override_cwd: Option<String>,
+}
+
+/// パラメータ行列によるテンプレートからの一括作成リクエスト
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateProcessesFromTemplateMatrixRequest {
+    pub template_id: String,
+    /// Values for each variable; every combination becomes a process. Integer ranges like "3001..3005" (inclusive) are expanded. Variables the template does not use are passed as environment variables
+    pub matrix: HashMap<String, Vec<String>>,
+    /// Values shared by every combination
+    #[serde(
+        default,
+        deserialize_with = "vantage_persistence::template_schema::deserialize_values"
+    )]
+    #[schemars(with = "HashMap<String, serde_json::Value>")]
+    pub values: HashMap<String, String>,
+    /// Prefix of the generated process IDs (defaults to the template name)
+    pub id_prefix: Option<String>,
+    /// Start the processes after creating them
+    #[serde(default)]
+    pub start: bool,
+}
+
+/// VS Codeのtasks.json取り込みリクエスト
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImportVscodeTasksRequest {
+    /// Path to tasks.json (defaults to .vscode/tasks.json in the current directory)
+    pub path: Option<String>,
+    /// Import as templates instead of processes
+    #[serde(default)]
+    pub as_templates: bool,
+    /// Only import tasks with these labels
+    pub labels: Option<Vec<String>>,
+}
+
+/// リポジトリからのタスク検出リクエスト
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DiscoverTasksRequest {
+    /// Workspace directory to scan (defaults to the current directory)
+    pub path: Option<String>,
+    /// Create the candidates instead of only proposing them
+    #[serde(default)]
+    pub import: bool,
+    /// Create templates instead of processes when importing
+    #[serde(default)]
+    pub as_templates: bool,
+    /// Only import candidates with these IDs (defaults to all candidates not registered yet)
+    pub ids: Option<Vec<String>>,
+}
+
+/// 実行中プロセスからのテンプレート取り込みリクエスト
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CaptureTemplateRequest {
+    /// Process to capture
+    pub process_id: String,
+    /// Template ID (defaults to the process ID)
+    pub template_id: Option<String>,
+    /// Template display name (defaults to the process ID)
+    pub name: Option<String>,
+}
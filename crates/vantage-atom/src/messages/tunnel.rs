@@ -0,0 +1,36 @@
+//! トンネル関連のメッセージ型定義
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use vantage_persistence::TunnelProvider;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateTunnelRequest {
+    /// Unique tunnel ID
+    pub tunnel_id: String,
+    /// ssh_local (`ssh -L`), ssh_remote (`ssh -R`) or cloudflared (quick tunnel)
+    pub provider: TunnelProvider,
+    /// Local port: the listening port for ssh_local, the forwarded port for ssh_remote and cloudflared
+    pub local_port: u16,
+    /// SSH destination such as user@host (ssh providers only)
+    pub ssh_host: Option<String>,
+    /// Forwarding target as host:port for ssh_local, or the port to listen on ([bind:]port) for ssh_remote
+    pub remote: Option<String>,
+    /// Process to link the tunnel to; its status will show the tunnel and public URL
+    pub process_id: Option<String>,
+    /// Public URL to display (cloudflared URLs are detected automatically)
+    pub public_url: Option<String>,
+    /// Start the tunnel right away (default: true)
+    pub start: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TunnelIdRequest {
+    pub tunnel_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListTunnelsRequest {
+    /// Only list tunnels linked to this process
+    pub process_id: Option<String>,
+}
@@ -0,0 +1,266 @@
+//! 外部で起動したプロセスの検出と引き取り
+//!
+//! ターミナルで手動で起動した `npm run dev` などのうち、コマンドと作業ディレクトリが登録済みの
+//! 定義と一致するものを見つけ、その定義の実行中のプロセスとして引き取ります。引き取った
+//! プロセスはPIDで追跡するため、メトリクス・待ち受けポート・終了の検知・停止は使えますが、
+//! 標準入出力は取り込めません。自動で引き取るワークスペースを設定すると、定期的な確認
+//! （30秒ごと）のたびに、そのワークスペースで一致したプロセスを引き取ります。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+use super::reaper::{self, PidStatus};
+
+/// 照合する定義（実行中でないもの）
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    /// 作業ディレクトリ（未指定の定義はサーバーの作業ディレクトリ）
+    pub cwd: PathBuf,
+}
+
+/// 定義と一致した外部のプロセス
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExternalProcess {
+    /// 一致した定義のプロセスID
+    pub process_id: String,
+    pub pid: u32,
+    pub command_line: String,
+    pub cwd: String,
+    pub started_at: DateTime<Utc>,
+    /// 自動で引き取るワークスペースにあるか
+    pub auto_adopt: bool,
+}
+
+/// 検出と引き取りの結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AdoptionReport {
+    pub found: Vec<ExternalProcess>,
+    pub adopted: Vec<String>,
+    /// 引き取らなかったプロセスとその理由
+    pub skipped: Vec<(String, String)>,
+}
+
+/// コマンドラインが定義のコマンドと引数に一致するか
+///
+/// `node /usr/bin/npm run dev` のようにインタープリター経由で起動されたものも一致とみなす
+/// （コマンドのファイル名が一致する要素より後ろが引数と完全に一致すること）。
+pub fn matches_command(command: &str, args: &[String], command_line: &[String]) -> bool {
+    let Some(name) = file_name(command) else {
+        return false;
+    };
+    // process.titleを書き換えるプログラム（npmなど）はコマンドラインが1つの文字列になる
+    let split: Vec<String>;
+    let command_line = match command_line {
+        [single] if single.contains(' ') => {
+            split = single.split_whitespace().map(str::to_string).collect();
+            split.as_slice()
+        }
+        _ => command_line,
+    };
+    command_line
+        .iter()
+        .position(|token| file_name(token) == Some(name))
+        .is_some_and(|i| command_line[i + 1..] == *args)
+}
+
+fn file_name(path: &str) -> Option<&str> {
+    Path::new(path).file_name()?.to_str()
+}
+
+/// 設定を検証
+pub fn validate(workspaces: &[String]) -> Result<(), String> {
+    for (i, workspace) in workspaces.iter().enumerate() {
+        if !Path::new(workspace).is_absolute() {
+            return Err(format!(
+                "auto_adopt_workspaces[{i}] must be an absolute path: '{workspace}'"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 作業ディレクトリが自動で引き取るワークスペースのいずれかの中にあるか
+pub fn in_workspaces(cwd: &Path, workspaces: &[String]) -> bool {
+    workspaces
+        .iter()
+        .any(|workspace| cwd.starts_with(workspace))
+}
+
+/// 定義と一致する外部のプロセスを探す
+///
+/// `managed` はVantageが起動したプロセスのPID。その子孫とサーバー自身の子孫は対象外にする。
+/// 親子がともに一致する場合（フォークしたワーカーなど）は親だけを返す。
+pub fn scan(definitions: &[Definition], managed: &HashSet<u32>) -> Vec<ExternalProcess> {
+    if definitions.is_empty() {
+        return Vec::new();
+    }
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing()
+            .with_cmd(UpdateKind::Always)
+            .with_cwd(UpdateKind::Always),
+    );
+    let cwds: Vec<PathBuf> = definitions
+        .iter()
+        .map(|d| d.cwd.canonicalize().unwrap_or_else(|_| d.cwd.clone()))
+        .collect();
+    let own = std::process::id();
+    let excluded = |pid: Pid| {
+        let mut current = Some(pid);
+        while let Some(pid) = current {
+            if pid.as_u32() == own || managed.contains(&pid.as_u32()) {
+                return true;
+            }
+            current = system.process(pid).and_then(|p| p.parent());
+        }
+        false
+    };
+
+    let mut matched: HashMap<Pid, usize> = HashMap::new();
+    for (pid, process) in system.processes() {
+        let Some(cwd) = process.cwd() else {
+            continue;
+        };
+        let command_line: Vec<String> = process
+            .cmd()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        let found = definitions.iter().zip(&cwds).position(|(d, def_cwd)| {
+            cwd == def_cwd && matches_command(&d.command, &d.args, &command_line)
+        });
+        if let Some(index) = found
+            && !excluded(*pid)
+        {
+            matched.insert(*pid, index);
+        }
+    }
+
+    let mut found: Vec<ExternalProcess> = matched
+        .iter()
+        .filter(|(pid, index)| {
+            let parent = system.process(**pid).and_then(|p| p.parent());
+            parent.and_then(|parent| matched.get(&parent)) != Some(*index)
+        })
+        .filter_map(|(pid, index)| {
+            let process = system.process(*pid)?;
+            Some(ExternalProcess {
+                process_id: definitions[*index].id.clone(),
+                pid: pid.as_u32(),
+                command_line: process
+                    .cmd()
+                    .iter()
+                    .map(|arg| arg.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                cwd: process.cwd()?.display().to_string(),
+                started_at: DateTime::from_timestamp(process.start_time() as i64, 0)?,
+                auto_adopt: false,
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| a.process_id.cmp(&b.process_id).then(a.pid.cmp(&b.pid)));
+    found
+}
+
+/// 引き取ったプロセスを停止する（SIGTERMを送り、猶予を過ぎたらSIGKILL）
+///
+/// 引き取ったプロセスのプロセスグループは起動したターミナルのものなので、PIDだけに送る。
+#[cfg(unix)]
+pub async fn terminate(pid: u32, started_at: DateTime<Utc>, grace: Duration) -> Result<(), String> {
+    use nix::sys::signal::{Signal, kill};
+
+    let target = nix::unistd::Pid::from_raw(pid as i32);
+    let gone = || {
+        matches!(
+            reaper::verify(pid, started_at),
+            PidStatus::Gone | PidStatus::Reused
+        )
+    };
+    if gone() {
+        return Ok(());
+    }
+    kill(target, Signal::SIGTERM).map_err(|e| format!("Failed to send SIGTERM to {pid}: {e}"))?;
+    let deadline = tokio::time::Instant::now() + grace;
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        if gone() || reaper::verify(pid, started_at) == PidStatus::Zombie {
+            return Ok(());
+        }
+    }
+    kill(target, Signal::SIGKILL).map_err(|e| format!("Failed to send SIGKILL to {pid}: {e}"))
+}
+
+#[cfg(not(unix))]
+pub async fn terminate(
+    _pid: u32,
+    _started_at: DateTime<Utc>,
+    _grace: Duration,
+) -> Result<(), String> {
+    Err("Stopping an adopted process is only supported on Unix".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_command_lines_match_through_interpreters_and_titles() {
+        let args = strings(&["run", "dev"]);
+        assert!(matches_command(
+            "npm",
+            &args,
+            &strings(&["npm", "run", "dev"])
+        ));
+        assert!(matches_command(
+            "npm",
+            &args,
+            &strings(&["node", "/usr/local/bin/npm", "run", "dev"])
+        ));
+        assert!(matches_command("npm", &args, &strings(&["npm run dev"])));
+        assert!(!matches_command(
+            "npm",
+            &args,
+            &strings(&["npm", "run", "build"])
+        ));
+        assert!(!matches_command(
+            "npm",
+            &args,
+            &strings(&["npm", "run", "dev", "--", "--port", "4000"])
+        ));
+        assert!(matches_command(
+            "/usr/bin/python3",
+            &strings(&["app.py"]),
+            &strings(&["python3", "app.py"])
+        ));
+        assert!(!matches_command("", &[], &strings(&["sh"])));
+    }
+
+    #[test]
+    fn test_workspaces_match_by_path_prefix() {
+        let workspaces = strings(&["/home/alice/project"]);
+        assert!(in_workspaces(Path::new("/home/alice/project"), &workspaces));
+        assert!(in_workspaces(
+            Path::new("/home/alice/project/web"),
+            &workspaces
+        ));
+        assert!(!in_workspaces(
+            Path::new("/home/alice/project-old"),
+            &workspaces
+        ));
+        assert!(!in_workspaces(Path::new("/home/alice/project"), &[]));
+    }
+}
@@ -0,0 +1,270 @@
+//! 承認ワークフロー
+//!
+//! 設定（`approvals.tools`）で指定した操作は、エージェントから要求されてもすぐには実行せず、
+//! 承認待ちのリクエストとして登録します。Webコンソールで承認されると実行し、要求・判断・実行結果を
+//! 承認リクエストに記録して監査に使えるようにします。
+//!
+//! 確認が必要なツール（`approvals.confirm_tools`）は、承認待ちとしてすぐに返さず、Webコンソールで
+//! 判断されるかタイムアウトするまでMCPの呼び出しを止めます。待っている間は進捗通知を送り、
+//! 承認されればその実行結果を、却下・タイムアウトならその旨をツールの結果として返します。
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use vantage_persistence::{ApprovalSettings, ConfigFormat};
+
+/// 承認の対象にできる操作
+///
+/// シリアライズ結果の `tool` / `arguments` がそのまま承認リクエストに記録される。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "tool", content = "arguments", rename_all = "snake_case")]
+pub enum ApprovalOperation {
+    RemoveProcess {
+        id: String,
+    },
+    RemoveProcesses {
+        expression: String,
+    },
+    EmptyRecycleBin {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+    ImportProcesses {
+        file_path: String,
+    },
+    ImportYaml {
+        file_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        format: Option<ConfigFormat>,
+    },
+    RestoreSnapshot {},
+    RestoreContext {
+        name: String,
+        stop_others: bool,
+        checkout_branch: bool,
+    },
+    DeleteContext {
+        name: String,
+    },
+}
+
+/// 確認を待つ秒数の既定値
+pub const DEFAULT_CONFIRM_TIMEOUT_SECS: u64 = 300;
+/// 確認を待つ秒数の上限
+pub const MAX_CONFIRM_TIMEOUT_SECS: u64 = 3600;
+/// 確認を待つ間、進捗通知を送る間隔（秒）
+pub const CONFIRM_PROGRESS_SECS: u64 = 5;
+
+/// 承認の受け方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalMode {
+    /// 承認待ちとしてすぐに返し、承認されたらWebコンソールから実行する
+    Deferred,
+    /// 判断が出るまで呼び出しを止める
+    Confirm { timeout: Duration },
+}
+
+/// `remove_process` に承認が必要なら、指定がなくても同じ承認を求める削除の操作
+///
+/// 一括削除や完全削除で、1件ずつの削除に求めた承認を回避できないようにする。
+const REMOVAL_TOOLS: &[&str] = &["remove_processes", "empty_recycle_bin"];
+
+/// ツールに必要な承認（不要ならNone）
+///
+/// 両方に挙げられている場合は確認を待つ。
+pub fn mode(settings: &ApprovalSettings, tool: &str) -> Option<ApprovalMode> {
+    listed_mode(settings, tool).or_else(|| {
+        REMOVAL_TOOLS
+            .contains(&tool)
+            .then(|| listed_mode(settings, "remove_process"))
+            .flatten()
+    })
+}
+
+/// 設定に挙げられたツールの承認
+fn listed_mode(settings: &ApprovalSettings, tool: &str) -> Option<ApprovalMode> {
+    if settings.confirm_tools.iter().any(|t| t == tool) {
+        let secs = settings
+            .confirm_timeout_secs
+            .unwrap_or(DEFAULT_CONFIRM_TIMEOUT_SECS);
+        return Some(ApprovalMode::Confirm {
+            timeout: Duration::from_secs(secs),
+        });
+    }
+    settings
+        .tools
+        .iter()
+        .any(|t| t == tool)
+        .then_some(ApprovalMode::Deferred)
+}
+
+/// 承認を必要にできるツール
+pub const APPROVABLE_TOOLS: &[&str] = &[
+    "remove_process",
+    "remove_processes",
+    "empty_recycle_bin",
+    "import_processes",
+    "import_yaml",
+    "restore_snapshot",
+    "restore_context",
+    "delete_context",
+];
+
+impl ApprovalOperation {
+    pub fn tool(&self) -> &'static str {
+        match self {
+            Self::RemoveProcess { .. } => "remove_process",
+            Self::RemoveProcesses { .. } => "remove_processes",
+            Self::EmptyRecycleBin { .. } => "empty_recycle_bin",
+            Self::ImportProcesses { .. } => "import_processes",
+            Self::ImportYaml { .. } => "import_yaml",
+            Self::RestoreSnapshot {} => "restore_snapshot",
+            Self::RestoreContext { .. } => "restore_context",
+            Self::DeleteContext { .. } => "delete_context",
+        }
+    }
+
+    /// 承認者に表示する説明
+    pub fn summary(&self) -> String {
+        match self {
+            Self::RemoveProcess { id } => format!("Remove process '{id}'"),
+            Self::RemoveProcesses { expression } => {
+                format!("Remove every process matching '{expression}'")
+            }
+            Self::EmptyRecycleBin { id: Some(id) } => {
+                format!("Permanently delete the recycled definitions of '{id}'")
+            }
+            Self::EmptyRecycleBin { id: None } => {
+                "Permanently delete everything in the recycle bin".to_string()
+            }
+            Self::ImportProcesses { file_path } => format!("Import processes from {file_path}"),
+            Self::ImportYaml { file_path, .. } => {
+                format!("Import processes from {file_path}")
+            }
+            Self::RestoreSnapshot {} => "Restore the database from the latest snapshot".to_string(),
+            Self::RestoreContext {
+                name,
+                stop_others,
+                checkout_branch,
+            } => {
+                let mut summary = format!("Restore context '{name}'");
+                if *stop_others {
+                    summary.push_str(", stopping processes outside it");
+                }
+                if *checkout_branch {
+                    summary.push_str(", checking out its git branch");
+                }
+                summary
+            }
+            Self::DeleteContext { name } => format!("Delete context '{name}'"),
+        }
+    }
+
+    /// 記録する引数
+    pub fn arguments(&self) -> serde_json::Value {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|mut value| value.get_mut("arguments").map(serde_json::Value::take))
+            .unwrap_or_default()
+    }
+
+    /// 承認リクエストに記録したツール名と引数から操作を復元
+    pub fn from_request(tool: &str, arguments: &serde_json::Value) -> Result<Self, String> {
+        serde_json::from_value(serde_json::json!({ "tool": tool, "arguments": arguments }))
+            .map_err(|e| format!("Invalid approval request for '{tool}': {e}"))
+    }
+}
+
+/// 設定を検証
+pub fn validate(settings: &ApprovalSettings) -> Result<(), String> {
+    for tool in settings.tools.iter().chain(&settings.confirm_tools) {
+        if !APPROVABLE_TOOLS.contains(&tool.as_str()) {
+            return Err(format!(
+                "Tool '{tool}' cannot require approval (supported: {})",
+                APPROVABLE_TOOLS.join(", ")
+            ));
+        }
+    }
+    if let Some(secs) = settings.confirm_timeout_secs
+        && !(1..=MAX_CONFIRM_TIMEOUT_SECS).contains(&secs)
+    {
+        return Err(format!(
+            "confirm_timeout_secs must be between 1 and {MAX_CONFIRM_TIMEOUT_SECS}"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_round_trip() {
+        let operations = [
+            ApprovalOperation::RemoveProcess {
+                id: "web".to_string(),
+            },
+            ApprovalOperation::RestoreSnapshot {},
+            ApprovalOperation::RemoveProcesses {
+                expression: "tag == 'tmp'".to_string(),
+            },
+            ApprovalOperation::EmptyRecycleBin { id: None },
+            ApprovalOperation::RestoreContext {
+                name: "feature-x".to_string(),
+                stop_others: true,
+                checkout_branch: false,
+            },
+        ];
+        for operation in operations {
+            assert!(APPROVABLE_TOOLS.contains(&operation.tool()));
+            let restored =
+                ApprovalOperation::from_request(operation.tool(), &operation.arguments()).unwrap();
+            assert_eq!(restored, operation);
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let settings = |tools: &[&str]| ApprovalSettings {
+            tools: tools.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+        assert!(validate(&settings(&["remove_process", "import_yaml"])).is_ok());
+        assert!(validate(&settings(&["start_process"])).is_err());
+
+        let confirm = ApprovalSettings {
+            tools: vec!["remove_process".to_string()],
+            confirm_tools: vec!["remove_process".to_string()],
+            confirm_timeout_secs: Some(30),
+        };
+        assert!(validate(&confirm).is_ok());
+        assert_eq!(
+            mode(&confirm, "remove_process"),
+            Some(ApprovalMode::Confirm {
+                timeout: Duration::from_secs(30)
+            })
+        );
+        assert_eq!(
+            mode(&settings(&["import_yaml"]), "import_yaml"),
+            Some(ApprovalMode::Deferred)
+        );
+        assert_eq!(mode(&confirm, "import_yaml"), None);
+        // 一括削除・完全削除はremove_processの承認に従う
+        assert_eq!(
+            mode(&settings(&["remove_process"]), "remove_processes"),
+            Some(ApprovalMode::Deferred)
+        );
+        assert_eq!(
+            mode(&confirm, "empty_recycle_bin"),
+            mode(&confirm, "remove_process")
+        );
+        assert_eq!(mode(&settings(&["import_yaml"]), "empty_recycle_bin"), None);
+        assert!(
+            validate(&ApprovalSettings {
+                confirm_timeout_secs: Some(0),
+                ..confirm
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! ブルーグリーン方式の無停止再起動
+//!
+//! ポートで待ち受けるサービスの前段にVantageがTCPのリバースプロキシを置き、転送先のポートを
+//! 切り替えることで、サービスを止めずに再起動します。インスタンスは元のプロセスと
+//! `<id>-green` の2つで、`rolling_restart` のたびに稼働していない側を別のポートで起動し、
+//! ポートに接続できるようになってから転送先を切り替えて、それまでの側を停止します。
+//! 切り替えの前に受け付けた接続は元のインスタンスにつながったままなので、停止までの猶予の間に終えられます。
+//! 経路はサーバーのメモリ上にあり、サーバーを再起動すると失われます。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// 2つ目のインスタンスのIDに付ける接尾辞
+pub const GREEN_SUFFIX: &str = "-green";
+/// ポートを渡す環境変数の既定値
+pub const DEFAULT_PORT_ENV: &str = "PORT";
+/// プロキシが待ち受けるアドレスの既定値
+pub const DEFAULT_PROXY_HOST: &str = "127.0.0.1";
+/// 準備完了を待つ時間の既定値（秒）
+pub const DEFAULT_READY_TIMEOUT_SECS: u64 = 30;
+/// 切り替えてから元のインスタンスを停止するまでの既定の猶予（秒）
+pub const DEFAULT_DRAIN_SECS: u64 = 5;
+/// 準備完了を確かめる間隔
+pub const READY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// 準備完了の確認の接続タイムアウト
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// `rolling_restart` の指定
+#[derive(Debug, Clone, Default)]
+pub struct RollingRestartOptions {
+    /// プロキシが待ち受けるポート（経路を作る最初の1回のみ必須）
+    pub proxy_port: Option<u16>,
+    /// プロキシが待ち受けるアドレス（経路を作るときのみ）
+    pub proxy_host: Option<String>,
+    /// ポートを渡す環境変数（経路を作るときのみ）
+    pub port_env: Option<String>,
+    /// 新しいインスタンスのポート（省略時は前回のポート、無ければ空いているポート）
+    pub standby_port: Option<u16>,
+    pub ready_timeout_secs: Option<u64>,
+    pub drain_secs: Option<u64>,
+}
+
+/// インスタンスとそのポート
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Instance {
+    pub process_id: String,
+    pub port: u16,
+}
+
+/// `rolling_restart` の結果
+#[derive(Debug, Clone, Serialize)]
+pub struct RollingRestartReport {
+    pub process_id: String,
+    /// プロキシが待ち受けるアドレス
+    pub listen: String,
+    pub previous: Instance,
+    pub current: Instance,
+    /// 起動してからポートに接続できるまでの時間
+    pub ready_after_ms: u64,
+    /// 元のインスタンスを停止できなかった場合、その理由
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_error: Option<String>,
+}
+
+/// 経路の状態
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyRouteStatus {
+    pub process_id: String,
+    pub listen: String,
+    pub port_env: String,
+    /// 転送先のインスタンス
+    pub live: Instance,
+    /// 最後に切り替えた時刻
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub switched_at: Option<DateTime<Utc>>,
+    /// 受け付けた接続の数
+    pub connections: u64,
+    /// 転送中の接続の数
+    pub active_connections: u64,
+}
+
+struct ProxyRoute {
+    listen: SocketAddr,
+    port_env: String,
+    live_process_id: String,
+    /// 転送先のポート（切り替えは新しい接続から反映される）
+    backend: Arc<AtomicU16>,
+    switched_at: Option<DateTime<Utc>>,
+    connections: Arc<AtomicU64>,
+    active: Arc<AtomicU64>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ProxyRoute {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl ProxyRoute {
+    fn status(&self, process_id: &str) -> ProxyRouteStatus {
+        ProxyRouteStatus {
+            process_id: process_id.to_string(),
+            listen: self.listen.to_string(),
+            port_env: self.port_env.clone(),
+            live: Instance {
+                process_id: self.live_process_id.clone(),
+                port: self.backend.load(Ordering::SeqCst),
+            },
+            switched_at: self.switched_at,
+            connections: self.connections.load(Ordering::SeqCst),
+            active_connections: self.active.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// プロセスごとの経路（キーは元のプロセスのID）
+#[derive(Default)]
+pub struct ProxyRoutes {
+    routes: RwLock<HashMap<String, ProxyRoute>>,
+}
+
+impl ProxyRoutes {
+    /// 経路を作り、プロキシの待ち受けを始める
+    pub async fn open(
+        &self,
+        process_id: &str,
+        host: &str,
+        listen_port: u16,
+        port_env: &str,
+        live: Instance,
+    ) -> Result<ProxyRouteStatus, String> {
+        let mut routes = self.routes.write().await;
+        if routes.contains_key(process_id) {
+            return Err(format!("Process '{process_id}' already has a proxy route"));
+        }
+        let listener = TcpListener::bind((host, listen_port))
+            .await
+            .map_err(|e| format!("Failed to listen on {host}:{listen_port}: {e}"))?;
+        let listen = listener.local_addr().map_err(|e| e.to_string())?;
+        let backend = Arc::new(AtomicU16::new(live.port));
+        let connections = Arc::new(AtomicU64::new(0));
+        let active = Arc::new(AtomicU64::new(0));
+        let task = tokio::spawn(serve(
+            listener,
+            backend.clone(),
+            connections.clone(),
+            active.clone(),
+        ));
+        let route = ProxyRoute {
+            listen,
+            port_env: port_env.to_string(),
+            live_process_id: live.process_id,
+            backend,
+            switched_at: None,
+            connections,
+            active,
+            task,
+        };
+        let status = route.status(process_id);
+        routes.insert(process_id.to_string(), route);
+        Ok(status)
+    }
+
+    /// 転送先を切り替える
+    pub async fn switch(&self, process_id: &str, live: Instance) -> Result<(), String> {
+        let mut routes = self.routes.write().await;
+        let route = routes
+            .get_mut(process_id)
+            .ok_or_else(|| format!("Process '{process_id}' has no proxy route"))?;
+        route.backend.store(live.port, Ordering::SeqCst);
+        route.live_process_id = live.process_id;
+        route.switched_at = Some(Utc::now());
+        Ok(())
+    }
+
+    /// 経路を削除し、プロキシの待ち受けをやめる
+    pub async fn remove(&self, process_id: &str) -> bool {
+        self.routes.write().await.remove(process_id).is_some()
+    }
+
+    pub async fn status(&self, process_id: &str) -> Option<ProxyRouteStatus> {
+        let routes = self.routes.read().await;
+        routes.get(process_id).map(|route| route.status(process_id))
+    }
+}
+
+/// 2つ目のインスタンスのID
+pub fn green_id(process_id: &str) -> String {
+    format!("{process_id}{GREEN_SUFFIX}")
+}
+
+/// 環境変数からポートを読む
+pub fn port_from_env(env: &HashMap<String, String>, port_env: &str) -> Result<u16, String> {
+    let value = env
+        .get(port_env)
+        .ok_or_else(|| format!("Environment variable {port_env} is not set"))?;
+    value
+        .trim()
+        .parse::<u16>()
+        .ok()
+        .filter(|port| *port > 0)
+        .ok_or_else(|| format!("{port_env}={value} is not a valid port"))
+}
+
+/// 空いているポートを1つ選ぶ
+pub async fn free_port() -> Result<u16, String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .map_err(|e| format!("Failed to find a free port: {e}"))?;
+    listener
+        .local_addr()
+        .map(|addr| addr.port())
+        .map_err(|e| e.to_string())
+}
+
+/// ポートに接続できるか
+pub async fn accepts_connections(port: u16) -> bool {
+    matches!(
+        tokio::time::timeout(
+            CONNECT_TIMEOUT,
+            TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+/// 受け付けた接続を、その時点の転送先に中継する
+async fn serve(
+    listener: TcpListener,
+    backend: Arc<AtomicU16>,
+    connections: Arc<AtomicU64>,
+    active: Arc<AtomicU64>,
+) {
+    loop {
+        let mut inbound = match listener.accept().await {
+            Ok((socket, _)) => socket,
+            Err(e) => {
+                warn!("Failed to accept proxy connection: {}", e);
+                continue;
+            }
+        };
+        let port = backend.load(Ordering::SeqCst);
+        connections.fetch_add(1, Ordering::SeqCst);
+        active.fetch_add(1, Ordering::SeqCst);
+        let active = active.clone();
+        tokio::spawn(async move {
+            match TcpStream::connect((Ipv4Addr::LOCALHOST, port)).await {
+                Ok(mut outbound) => {
+                    if let Err(e) = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await
+                    {
+                        debug!("Proxy connection to port {} ended: {}", port, e);
+                    }
+                }
+                Err(e) => warn!("Failed to connect to backend port {}: {}", port, e),
+            }
+            active.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 接続ごとに `reply` を返して閉じるサーバー
+    async fn backend(reply: &'static str) -> u16 {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let _ = socket.write_all(reply.as_bytes()).await;
+            }
+        });
+        port
+    }
+
+    async fn fetch(port: u16) -> String {
+        let mut socket = TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+            .await
+            .unwrap();
+        let mut reply = String::new();
+        socket.read_to_string(&mut reply).await.unwrap();
+        reply
+    }
+
+    #[tokio::test]
+    async fn test_switch_sends_new_connections_to_the_new_backend() {
+        let blue = backend("blue").await;
+        let green = backend("green").await;
+        let routes = ProxyRoutes::default();
+        let status = routes
+            .open(
+                "web",
+                DEFAULT_PROXY_HOST,
+                0,
+                DEFAULT_PORT_ENV,
+                Instance {
+                    process_id: "web".to_string(),
+                    port: blue,
+                },
+            )
+            .await
+            .unwrap();
+        let listen: SocketAddr = status.listen.parse().unwrap();
+        assert_eq!(fetch(listen.port()).await, "blue");
+
+        routes
+            .switch(
+                "web",
+                Instance {
+                    process_id: green_id("web"),
+                    port: green,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(fetch(listen.port()).await, "green");
+
+        let status = routes.status("web").await.unwrap();
+        assert_eq!(status.live.process_id, "web-green");
+        assert_eq!(status.connections, 2);
+        assert!(status.switched_at.is_some());
+
+        assert!(routes.remove("web").await);
+        assert!(routes.status("web").await.is_none());
+    }
+
+    #[test]
+    fn test_port_from_env() {
+        let env = HashMap::from([("PORT".to_string(), "8080".to_string())]);
+        assert_eq!(port_from_env(&env, "PORT"), Ok(8080));
+        assert!(port_from_env(&env, "HTTP_PORT").is_err());
+        let env = HashMap::from([("PORT".to_string(), "0".to_string())]);
+        assert!(port_from_env(&env, "PORT").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! 出力のブックマーク
+//!
+//! その時点の出力の末尾に「マイグレーション前」「設定変更後」のような名前を付けておき、
+//! 後からブックマークより後・前の出力や、2つのブックマークの間の出力を取り出します。
+//! 位置は記録時の単調時計で表すため、stdoutとstderrをまとめて扱えます。
+
+use super::buffer::LogLine;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Instant;
+use utoipa::ToSchema;
+
+/// プロセスごとに残すブックマークの数（超えると古いものから消す）
+pub const MAX_BOOKMARKS: usize = 100;
+
+/// 出力の位置に付けた名前
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OutputBookmark {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// 付けた時点までにstdout・stderrに記録されていた行数（バッファに残っている分）
+    pub stdout_lines: usize,
+    pub stderr_lines: usize,
+    #[serde(skip)]
+    pub instant: Instant,
+}
+
+/// ブックマークを基準に取り出した出力
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BookmarkedOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<OutputBookmark>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<OutputBookmark>,
+    pub lines: Vec<String>,
+    /// 範囲内に、行数の上限で返さなかった行がある
+    pub more: bool,
+    /// `from` の後の行の一部が、バッファからあふれて失われている
+    pub truncated: bool,
+}
+
+/// ブックマークの名前を確かめる
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Bookmark name must not be empty".to_string());
+    }
+    if name.len() > 200 {
+        return Err("Bookmark name must be at most 200 bytes".to_string());
+    }
+    Ok(())
+}
+
+/// 記録時刻の順に並んだ行から、`from` より後・`to` 以前の行を取り出す
+///
+/// `from` があれば範囲の先頭から、`to` だけなら末尾から `limit` 行を返す。
+/// 2つ目の値は上限を超えて返さなかった行があるか。
+pub fn select(
+    lines: Vec<LogLine>,
+    from: Option<&OutputBookmark>,
+    to: Option<&OutputBookmark>,
+    limit: usize,
+) -> (Vec<LogLine>, bool) {
+    let in_range: Vec<LogLine> = lines
+        .into_iter()
+        .filter(|line| from.is_none_or(|from| line.instant > from.instant))
+        .filter(|line| to.is_none_or(|to| line.instant <= to.instant))
+        .collect();
+    let more = in_range.len() > limit;
+    let selected = if from.is_some() {
+        in_range.into_iter().take(limit).collect()
+    } else {
+        let skip = in_range.len().saturating_sub(limit);
+        in_range.into_iter().skip(skip).collect()
+    };
+    (selected, more)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(name: &str) -> OutputBookmark {
+        OutputBookmark {
+            name: name.to_string(),
+            created_at: Utc::now(),
+            note: None,
+            stdout_lines: 0,
+            stderr_lines: 0,
+            instant: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_select_between_and_around_bookmarks() {
+        let mut lines = vec![LogLine::new("boot".to_string())];
+        let before = bookmark("before migration");
+        lines.push(LogLine::new("migrating 1".to_string()));
+        lines.push(LogLine::new("migrating 2".to_string()));
+        let after = bookmark("after migration");
+        lines.push(LogLine::new("serving".to_string()));
+
+        let texts = |(lines, more): (Vec<LogLine>, bool)| {
+            (lines.into_iter().map(|l| l.text).collect::<Vec<_>>(), more)
+        };
+        assert_eq!(
+            texts(select(lines.clone(), Some(&before), Some(&after), 10)),
+            (
+                vec!["migrating 1".to_string(), "migrating 2".to_string()],
+                false
+            )
+        );
+        assert_eq!(
+            texts(select(lines.clone(), Some(&before), None, 1)),
+            (vec!["migrating 1".to_string()], true)
+        );
+        assert_eq!(
+            texts(select(lines, None, Some(&after), 1)),
+            (vec!["migrating 2".to_string()], true)
+        );
+        assert!(validate_name("  ").is_err());
+    }
+}
@@ -1,47 +1,389 @@
+use super::log_sinks::LogTap;
+use super::output_rates::{LineCount, LineRates};
+use super::recording::Recorder;
+use super::redaction::Redactor;
+use super::sampling::{Sampler, SamplingStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+/// 1行あたりの本文以外のメモリ（`LogLine` とキューの領域の目安）
+const LINE_OVERHEAD_BYTES: usize = 64;
+
+/// 行を出力したストリーム
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    #[default]
+    Stdout,
+    Stderr,
+}
+
+/// バッファに記録される1行分のログ
+///
+/// 返却テキストには含めず、メタデータとしてタイムスタンプを保持する。
+/// `instant` は単調時計で、フェーズ間の所要時間計算に使用する。
+/// `seq` はバッファに追加した順の連番（1から）で、続きを読むためのカーソルに使う。
+/// 標準出力と標準エラーで連番を共有するため、`seq` の順に並べると出力された順になる。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LogLine {
+    pub text: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub instant: Instant,
+    pub seq: u64,
+    pub stream: LogStream,
+}
+
+impl LogLine {
+    /// 連番とストリームはバッファに追加するときに決める
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            timestamp: Utc::now(),
+            instant: Instant::now(),
+            seq: 0,
+            stream: LogStream::Stdout,
+        }
+    }
+
+    /// タイムスタンプ付きのテキスト表現
+    pub fn with_timestamp(&self) -> String {
+        format!(
+            "[{}] {}",
+            self.timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            self.text
+        )
+    }
+
+    /// メモリ上の大きさの目安（本文と行ごとの管理領域）
+    pub fn memory_bytes(&self) -> u64 {
+        (self.text.len() + LINE_OVERHEAD_BYTES) as u64
+    }
+}
+
+/// 標準出力と標準エラーの最新の `n` 行（Noneならすべて）を出力された順に並べる
+pub async fn interleave(
+    stdout: &CircularBuffer,
+    stderr: &CircularBuffer,
+    n: Option<usize>,
+) -> Vec<LogLine> {
+    let mut lines = match n {
+        Some(n) => {
+            let mut lines = stdout.get_last_n_lines(n).await;
+            lines.extend(stderr.get_last_n_lines(n).await);
+            lines
+        }
+        None => {
+            let mut lines = stdout.get_all_lines().await;
+            lines.extend(stderr.get_all_lines().await);
+            lines
+        }
+    };
+    lines.sort_by_key(|l| l.seq);
+    if let Some(n) = n {
+        let excess = lines.len().saturating_sub(n);
+        lines.drain(..excess);
+    }
+    lines
+}
+
+/// カーソルより後の出力
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FollowedOutput {
+    pub lines: Vec<String>,
+    /// 続きを読むときに渡すカーソル（最後に返した行の連番）
+    pub cursor: u64,
+    /// 行数の上限で返さなかった行がある（すぐに続きを読める）
+    pub more: bool,
+    /// カーソルの後の行の一部が、読まれる前にバッファからあふれて失われている
+    pub truncated: bool,
+}
+
+/// カーソルより後の出力（時刻・ストリーム・連番付き）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FollowedLines {
+    /// 出力された順（連番の順）の行
+    pub lines: Vec<LogLine>,
+    /// 続きを読むときに渡すカーソル（最後に返した行の連番）
+    pub cursor: u64,
+    /// 行数の上限で返さなかった行がある（すぐに続きを読める）
+    pub more: bool,
+    /// カーソルの後の行の一部が、読まれる前にバッファからあふれて失われている
+    pub truncated: bool,
+}
 
 /// 循環バッファでログを管理
 #[derive(Debug, Clone)]
 pub struct CircularBuffer {
-    buffer: Arc<RwLock<VecDeque<String>>>,
-    capacity: usize,
+    buffer: Arc<RwLock<VecDeque<LogLine>>>,
+    /// 作成時の行数の上限
+    base_capacity: usize,
+    /// 現在の行数の上限（メモリが逼迫すると縮める）
+    capacity: Arc<AtomicUsize>,
+    /// 保持している行のメモリの目安
+    memory: Arc<AtomicU64>,
+    /// 出力を伏せるルール（記録・転送の前に適用する）
+    redactor: Arc<Mutex<Option<Redactor>>>,
+    /// 伏せた箇所の数（ルールを切り替えても数え続ける）
+    redactions: Arc<AtomicU64>,
+    /// 出力のサンプリング（有効な場合は間引いた行を記録しない）
+    sampler: Arc<Mutex<Option<Sampler>>>,
+    /// 外部のログシンクへの転送（サンプリングで間引く行も転送する）
+    tap: Arc<Mutex<Option<LogTap>>>,
+    /// 出力の記録（サンプリングで間引く行も記録する）
+    recorder: Arc<Mutex<Option<Recorder>>>,
+    /// 行数とエラーの行数の推移（サンプリングで間引く行やあふれた行も数える）
+    rates: Arc<Mutex<LineRates>>,
+    /// 最後に振った連番（同じプロセスの標準出力と標準エラーで共有する）
+    sequence: Arc<AtomicU64>,
+    /// あふれて捨てた行の最大の連番
+    evicted_through: Arc<AtomicU64>,
+    /// 記録する行のストリーム
+    stream: LogStream,
 }
 
 impl CircularBuffer {
     pub fn new(capacity: usize) -> Self {
         Self {
             buffer: Arc::new(RwLock::new(VecDeque::with_capacity(capacity))),
-            capacity,
+            base_capacity: capacity,
+            capacity: Arc::new(AtomicUsize::new(capacity)),
+            memory: Arc::new(AtomicU64::new(0)),
+            redactor: Arc::new(Mutex::new(None)),
+            redactions: Arc::new(AtomicU64::new(0)),
+            sampler: Arc::new(Mutex::new(None)),
+            tap: Arc::new(Mutex::new(None)),
+            recorder: Arc::new(Mutex::new(None)),
+            rates: Arc::new(Mutex::new(LineRates::default())),
+            sequence: Arc::new(AtomicU64::new(0)),
+            evicted_through: Arc::new(AtomicU64::new(0)),
+            stream: LogStream::Stdout,
+        }
+    }
+
+    /// 標準出力のバッファと連番を共有する標準エラーのバッファを作る
+    /// （標準出力と標準エラーの行を1つのカーソルで追い、出力された順に並べられるようにする）
+    pub fn paired(&self, capacity: usize) -> Self {
+        Self {
+            sequence: self.sequence.clone(),
+            stream: LogStream::Stderr,
+            ..Self::new(capacity)
         }
     }
 
+    /// 記録する行のストリーム
+    pub fn stream(&self) -> LogStream {
+        self.stream
+    }
+
     /// 新しい行を追加
     pub async fn push(&self, line: String) {
+        let line = match self.redactor.lock().unwrap().as_ref() {
+            Some(redactor) => {
+                let (line, count) = redactor.apply(line);
+                if count > 0 {
+                    self.redactions.fetch_add(count, Ordering::Relaxed);
+                }
+                line
+            }
+            None => line,
+        };
+        if let Some(tap) = self.tap.lock().unwrap().as_ref() {
+            tap.send(&line);
+        }
+        if let Some(recorder) = self.recorder.lock().unwrap().as_ref() {
+            recorder.record(&line);
+        }
+        self.rates
+            .lock()
+            .unwrap()
+            .record(Utc::now().timestamp(), &line);
+        if let Some(sampler) = self.sampler.lock().unwrap().as_mut()
+            && !sampler.keep(&line)
+        {
+            return;
+        }
+        let mut line = LogLine::new(line);
+        line.stream = self.stream;
         let mut buffer = self.buffer.write().await;
-        if buffer.len() >= self.capacity {
-            buffer.pop_front();
+        line.seq = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        while buffer.len() >= capacity {
+            match buffer.pop_front() {
+                Some(dropped) => {
+                    self.memory
+                        .fetch_sub(dropped.memory_bytes(), Ordering::Relaxed);
+                    self.evicted_through
+                        .fetch_max(dropped.seq, Ordering::Relaxed);
+                }
+                None => break,
+            }
         }
+        self.memory
+            .fetch_add(line.memory_bytes(), Ordering::Relaxed);
         buffer.push_back(line);
     }
 
+    /// 行数の上限を `lines` まで縮め、あふれた古い行を返す
+    pub async fn shrink(&self, lines: usize) -> Vec<LogLine> {
+        let lines = lines.clamp(1, self.base_capacity);
+        let mut buffer = self.buffer.write().await;
+        self.capacity.store(lines, Ordering::Relaxed);
+        let excess = buffer.len().saturating_sub(lines);
+        let dropped: Vec<LogLine> = buffer.drain(..excess).collect();
+        let freed: u64 = dropped.iter().map(LogLine::memory_bytes).sum();
+        self.memory.fetch_sub(freed, Ordering::Relaxed);
+        if let Some(last) = dropped.last() {
+            self.evicted_through.fetch_max(last.seq, Ordering::Relaxed);
+        }
+        dropped
+    }
+
+    /// 行数の上限を作成時の大きさに戻す
+    pub fn restore_capacity(&self) {
+        self.capacity.store(self.base_capacity, Ordering::Relaxed);
+    }
+
+    /// 現在の行数の上限
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// 作成時の行数の上限
+    pub fn base_capacity(&self) -> usize {
+        self.base_capacity
+    }
+
+    /// 保持している行のメモリの目安（バイト）
+    pub fn memory_bytes(&self) -> u64 {
+        self.memory.load(Ordering::Relaxed)
+    }
+
     /// 最新のN行を取得
     pub async fn get_last_n(&self, n: usize) -> Vec<String> {
+        let buffer = self.buffer.read().await;
+        buffer
+            .iter()
+            .rev()
+            .take(n)
+            .rev()
+            .map(|l| l.text.clone())
+            .collect()
+    }
+
+    /// 最新のN行をメタデータ付きで取得
+    pub async fn get_last_n_lines(&self, n: usize) -> Vec<LogLine> {
         let buffer = self.buffer.read().await;
         buffer.iter().rev().take(n).rev().cloned().collect()
     }
 
     /// すべての行を取得
     pub async fn get_all(&self) -> Vec<String> {
+        let buffer = self.buffer.read().await;
+        buffer.iter().map(|l| l.text.clone()).collect()
+    }
+
+    /// すべての行をメタデータ付きで取得
+    pub async fn get_all_lines(&self) -> Vec<LogLine> {
         let buffer = self.buffer.read().await;
         buffer.iter().cloned().collect()
     }
 
+    /// 連番が `cursor` より後の行を古い順に最大 `limit` 件取得
+    pub async fn lines_after(&self, cursor: u64, limit: usize) -> Vec<LogLine> {
+        let buffer = self.buffer.read().await;
+        let start = buffer.partition_point(|l| l.seq <= cursor);
+        buffer.range(start..).take(limit).cloned().collect()
+    }
+
+    /// 時刻が `since` より後の行を古い順に最大 `limit` 件取得
+    pub async fn lines_since(&self, since: DateTime<Utc>, limit: usize) -> Vec<LogLine> {
+        let buffer = self.buffer.read().await;
+        let start = buffer.partition_point(|l| l.timestamp <= since);
+        buffer.range(start..).take(limit).cloned().collect()
+    }
+
+    /// 連番が `cursor` より後の行のうち、読まれる前にあふれて捨てた行があるか
+    pub fn evicted_after(&self, cursor: u64) -> bool {
+        self.evicted_through.load(Ordering::Relaxed) > cursor
+    }
+
+    /// 最後に振った連番（まだ1行もなければ0）
+    pub fn last_seq(&self) -> u64 {
+        self.sequence.load(Ordering::Relaxed)
+    }
+
+    /// 最後に記録された行の時刻
+    pub async fn last_timestamp(&self) -> Option<DateTime<Utc>> {
+        let buffer = self.buffer.read().await;
+        buffer.back().map(|l| l.timestamp)
+    }
+
+    /// あふれて古い行を捨てている場合、残っている最も古い行の時刻
+    pub async fn retained_since(&self) -> Option<DateTime<Utc>> {
+        let buffer = self.buffer.read().await;
+        if buffer.len() < self.capacity() {
+            return None;
+        }
+        buffer.front().map(|l| l.timestamp)
+    }
+
+    /// `from` から `to` まで（UNIX時刻の秒）に記録した行数
+    pub fn count_lines(&self, from: i64, to: i64) -> LineCount {
+        self.rates.lock().unwrap().count(from, to)
+    }
+
+    /// `from` から `to` まで（UNIX時刻の秒）の区間ごとの行数
+    pub fn line_samples(&self, from: i64, to: i64) -> Vec<(i64, LineCount)> {
+        self.rates.lock().unwrap().samples(from, to)
+    }
+
+    /// サンプリングを切り替える（Noneで無効化）
+    pub fn set_sampler(&self, sampler: Option<Sampler>) {
+        *self.sampler.lock().unwrap() = sampler;
+    }
+
+    /// 出力を伏せるルールを切り替える（Noneで無効化）
+    pub fn set_redactor(&self, redactor: Option<Redactor>) {
+        *self.redactor.lock().unwrap() = redactor;
+    }
+
+    /// これまでに伏せた箇所の数
+    pub fn redaction_count(&self) -> u64 {
+        self.redactions.load(Ordering::Relaxed)
+    }
+
+    /// ログシンクへの転送を切り替える（Noneで無効化）
+    pub fn set_tap(&self, tap: Option<LogTap>) {
+        *self.tap.lock().unwrap() = tap;
+    }
+
+    /// 出力の記録先を設定（Noneで記録しない）
+    pub fn set_recorder(&self, recorder: Option<Recorder>) {
+        *self.recorder.lock().unwrap() = recorder;
+    }
+
+    /// サンプリングの状態
+    pub fn sampling_status(&self) -> Option<SamplingStatus> {
+        self.sampler.lock().unwrap().as_ref().map(Sampler::status)
+    }
+
     /// バッファをクリア
     pub async fn clear(&self) {
         let mut buffer = self.buffer.write().await;
+        if let Some(last) = buffer.back() {
+            self.evicted_through.fetch_max(last.seq, Ordering::Relaxed);
+        }
         buffer.clear();
+        self.memory.store(0, Ordering::Relaxed);
     }
 
     /// バッファのサイズを取得
@@ -0,0 +1,220 @@
+//! 出力バッファのメモリ管理
+//!
+//! すべてのプロセスの出力バッファの合計を監視し、設定の上限を超えたら停止中のプロセスと
+//! 出力の途絶えたプロセスのバッファから順に縮めます。縮めるときに捨てる行は、設定により
+//! ログディレクトリ（`<log_dir>/<process_id>/spill-<stream>.log`）に書き出します。
+//! 合計が上限の3/4を下回ると、縮めたバッファの上限を元に戻します（行は戻りません）。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use utoipa::ToSchema;
+use vantage_persistence::OutputMemorySettings;
+
+use super::buffer::LogLine;
+
+/// 使用量の確認間隔
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 設定を検証
+pub fn validate(settings: &OutputMemorySettings) -> Result<(), String> {
+    if settings.min_buffer_lines == 0 {
+        return Err("output_memory.min_buffer_lines must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+/// 1プロセスの出力バッファ（stdoutとstderrの合計）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BufferAllocation {
+    pub process_id: String,
+    pub running: bool,
+    /// 最後に出力した時刻
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_output: Option<DateTime<Utc>>,
+    pub lines: usize,
+    /// 現在の行数の上限
+    pub capacity: usize,
+    /// 本来の行数の上限
+    pub base_capacity: usize,
+    /// 保持している行のメモリの目安（バイト）
+    pub bytes: u64,
+    /// メモリの逼迫で縮めている
+    pub shrunk: bool,
+}
+
+/// 出力バッファのメモリの割り当て
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BufferMemoryReport {
+    /// 上限（0は無制限）
+    pub max_total_bytes: u64,
+    pub total_bytes: u64,
+    /// 縮めているプロセスの数
+    pub shrunk_count: usize,
+    /// 使用量の多い順
+    pub processes: Vec<BufferAllocation>,
+}
+
+impl BufferMemoryReport {
+    pub fn new(settings: &OutputMemorySettings, mut processes: Vec<BufferAllocation>) -> Self {
+        processes.sort_by(|a, b| {
+            b.bytes
+                .cmp(&a.bytes)
+                .then_with(|| a.process_id.cmp(&b.process_id))
+        });
+        Self {
+            max_total_bytes: settings.max_total_bytes,
+            total_bytes: processes.iter().map(|p| p.bytes).sum(),
+            shrunk_count: processes.iter().filter(|p| p.shrunk).count(),
+            processes,
+        }
+    }
+}
+
+/// 使用量に応じて行うこと
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// 縮めるプロセス（縮める順）
+    pub shrink: Vec<String>,
+    /// 縮めたバッファを元の大きさに戻す
+    pub restore: bool,
+}
+
+/// 縮めるプロセスと、元に戻すかどうかを決める
+///
+/// 停止中のプロセスを先に、次に `idle_after_secs` 以上出力していない実行中のプロセスを
+/// 最後の出力の古い順に、合計が上限に収まる見込みになるまで選ぶ。
+pub fn plan(
+    allocations: &[BufferAllocation],
+    settings: &OutputMemorySettings,
+    now: DateTime<Utc>,
+) -> Plan {
+    let total: u64 = allocations.iter().map(|a| a.bytes).sum();
+    let limit = settings.max_total_bytes;
+    if limit == 0 || total <= limit / 4 * 3 {
+        return Plan {
+            shrink: Vec::new(),
+            restore: allocations.iter().any(|a| a.shrunk),
+        };
+    }
+    if total <= limit {
+        return Plan::default();
+    }
+
+    let idle_after = chrono::Duration::seconds(settings.idle_after_secs as i64);
+    let mut candidates: Vec<&BufferAllocation> = allocations
+        .iter()
+        .filter(|a| !a.shrunk && a.lines > settings.min_buffer_lines)
+        .filter(|a| !a.running || a.last_output.is_none_or(|t| now - t >= idle_after))
+        .collect();
+    candidates.sort_by_key(|a| (a.running, a.last_output));
+
+    let mut remaining = total;
+    let mut shrink = Vec::new();
+    for candidate in candidates {
+        if remaining <= limit {
+            break;
+        }
+        // 行の大きさがそろっているとみなして、stdoutとstderrに残す行の分だけ差し引く
+        let kept_lines = (settings.min_buffer_lines * 2).min(candidate.lines);
+        let kept = candidate.bytes * kept_lines as u64 / candidate.lines as u64;
+        remaining -= candidate.bytes - kept;
+        shrink.push(candidate.process_id.clone());
+    }
+    Plan {
+        shrink,
+        restore: false,
+    }
+}
+
+/// 書き出し先（`<log_dir>/<process_id>/spill-<stream>.log`）
+pub fn spill_path(log_dir: &Path, process_id: &str, stream: &str) -> PathBuf {
+    log_dir.join(process_id).join(format!("spill-{stream}.log"))
+}
+
+/// 捨てる行をタイムスタンプ付きで追記する
+pub fn spill(path: &Path, lines: &[LogLine]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut writer = std::io::BufWriter::new(file);
+    for line in lines {
+        writeln!(writer, "{}", line.with_timestamp())?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocation(id: &str, running: bool, idle_secs: Option<i64>, bytes: u64) -> BufferAllocation {
+        BufferAllocation {
+            process_id: id.to_string(),
+            running,
+            last_output: idle_secs.map(|secs| Utc::now() - chrono::Duration::seconds(secs)),
+            lines: 1000,
+            capacity: 2000,
+            base_capacity: 2000,
+            bytes,
+            shrunk: false,
+        }
+    }
+
+    fn settings(max_total_bytes: u64) -> OutputMemorySettings {
+        OutputMemorySettings {
+            max_total_bytes,
+            min_buffer_lines: 100,
+            idle_after_secs: 60,
+            spill_to_disk: false,
+        }
+    }
+
+    #[test]
+    fn test_plan_shrinks_stopped_then_idle_processes() {
+        let allocations = vec![
+            allocation("busy", true, Some(1), 4000),
+            allocation("quiet", true, Some(600), 3000),
+            allocation("quieter", true, Some(1200), 3000),
+            allocation("stopped", false, Some(10), 2000),
+        ];
+        // 12000 → stoppedで1600、quieterで2400減らすと8000で上限に収まる
+        let plan = plan(&allocations, &settings(8000), Utc::now());
+        assert_eq!(plan.shrink, vec!["stopped", "quieter"]);
+        assert!(!plan.restore);
+
+        // 実行中で出力の続いているプロセスは縮めない
+        let plan = super::plan(&allocations[..1], &settings(1000), Utc::now());
+        assert!(plan.shrink.is_empty());
+    }
+
+    #[test]
+    fn test_plan_restores_below_low_water_mark() {
+        let mut shrunk = allocation("stopped", false, None, 500);
+        shrunk.shrunk = true;
+        let allocations = vec![shrunk, allocation("busy", true, Some(1), 1000)];
+
+        // 上限の3/4以下なら元に戻す
+        assert!(plan(&allocations, &settings(2000), Utc::now()).restore);
+        // 上限の3/4と上限の間では何もしない
+        assert_eq!(
+            plan(&allocations, &settings(1600), Utc::now()),
+            Plan::default()
+        );
+        // 無制限なら元に戻す
+        assert!(plan(&allocations, &settings(0), Utc::now()).restore);
+        assert!(
+            validate(&OutputMemorySettings {
+                min_buffer_lines: 0,
+                ..OutputMemorySettings::default()
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! 実行中プロセスからのテンプレート生成
+//!
+//! 実行時の環境変数と待ち受けポートを取り込み、ポートはデフォルト値付きの変数に、
+//! 秘密情報は値を持たない必須変数（シークレット参照）に置き換えたテンプレートを作ります。
+
+use super::env_docs::{self, DotenvFile, EnvVarDoc};
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use vantage_persistence::{ProcessTemplate, TemplateVariable};
+
+/// シークレットとみなす環境変数名・フラグ名に含まれる語
+const SECRET_KEY_MARKERS: &[&str] = &[
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "APIKEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+    "AUTH",
+];
+
+/// シークレットとみなす値の接頭辞（既知のトークン形式）
+const SECRET_VALUE_PREFIXES: &[&str] = &[
+    "ghp_",
+    "gho_",
+    "ghs_",
+    "github_pat_",
+    "sk-",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+    "glpat-",
+];
+
+/// テンプレート変数の型ヒント
+pub const SECRET_VAR_TYPE: &str = "secret";
+pub const PORT_VAR_TYPE: &str = "port";
+
+/// テンプレート化する実行時の状態
+#[derive(Debug, Clone, Default)]
+pub struct CaptureSource {
+    pub command: String,
+    pub args: Vec<String>,
+    /// 実行時に解決された環境変数
+    pub env: HashMap<String, String>,
+    pub cwd: Option<String>,
+    /// 待ち受けているポート
+    pub ports: Vec<u16>,
+    /// 作業ディレクトリの `.env` 系のファイル
+    pub dotenv_files: Vec<DotenvFile>,
+}
+
+/// 生成されたテンプレートと置き換えの内訳
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CapturedTemplate {
+    pub template: ProcessTemplate,
+    /// シークレット参照に置き換えた変数名
+    pub secrets: Vec<String>,
+    /// 検出したポート
+    pub ports: Vec<u16>,
+    /// 参照している・`.env` 系のファイルにある環境変数から作った変数と、その説明
+    pub env_variables: Vec<EnvVarDoc>,
+}
+
+/// 名前からシークレットかどうかを判定
+pub fn is_secret_key(key: &str) -> bool {
+    let upper = key.to_ascii_uppercase().replace('-', "_");
+    SECRET_KEY_MARKERS.iter().any(|m| upper.contains(m))
+}
+
+/// 値の形式からシークレットかどうかを判定
+pub fn looks_like_secret_value(value: &str) -> bool {
+    SECRET_VALUE_PREFIXES.iter().any(|p| value.starts_with(p))
+}
+
+/// 実行時の状態からテンプレートを生成
+pub fn build_template(
+    template_id: String,
+    name: String,
+    source: CaptureSource,
+) -> CapturedTemplate {
+    let mut variables: Vec<TemplateVariable> = Vec::new();
+    let mut secrets = Vec::new();
+    let mut args = source.args;
+    let mut env = source.env;
+
+    // 環境変数のシークレット
+    let mut keys: Vec<String> = env.keys().cloned().collect();
+    keys.sort();
+    for key in keys {
+        let value = &env[&key];
+        if is_secret_key(&key) || looks_like_secret_value(value) {
+            let var = variable_name(&key);
+            env.insert(key.clone(), placeholder(&var));
+            push_secret(&mut variables, &mut secrets, var, format!("env {key}"));
+        }
+    }
+
+    // 引数のシークレット（--token=xxx / --token xxx）
+    let mut secret_flag: Option<String> = None;
+    for arg in args.iter_mut() {
+        if let Some(flag) = secret_flag.take() {
+            let var = variable_name(&flag);
+            *arg = placeholder(&var);
+            push_secret(
+                &mut variables,
+                &mut secrets,
+                var,
+                format!("argument {flag}"),
+            );
+            continue;
+        }
+        if let Some((flag, value)) = arg.split_once('=')
+            && flag.starts_with('-')
+            && (is_secret_key(flag) || looks_like_secret_value(value))
+        {
+            let flag = flag.to_string();
+            let var = variable_name(&flag);
+            *arg = format!("{flag}={}", placeholder(&var));
+            push_secret(
+                &mut variables,
+                &mut secrets,
+                var,
+                format!("argument {flag}"),
+            );
+        } else if arg.starts_with("--") && !arg.contains('=') && is_secret_key(arg) {
+            secret_flag = Some(arg.clone());
+        } else if looks_like_secret_value(arg) {
+            let var = format!("SECRET_{}", secrets.len() + 1);
+            *arg = placeholder(&var);
+            push_secret(&mut variables, &mut secrets, var, "argument".to_string());
+        }
+    }
+
+    // ポート
+    let mut ports = source.ports;
+    ports.sort_unstable();
+    ports.dedup();
+    let mut unreferenced_ports = Vec::new();
+    for (i, port) in ports.iter().enumerate() {
+        let var = if i == 0 {
+            "PORT".to_string()
+        } else {
+            format!("PORT_{}", i + 1)
+        };
+        let needle = port.to_string();
+        let mut referenced = false;
+        for arg in args.iter_mut() {
+            if let Some(replaced) = replace_number(arg, &needle, &placeholder(&var)) {
+                *arg = replaced;
+                referenced = true;
+            }
+        }
+        for value in env.values_mut() {
+            if let Some(replaced) = replace_number(value, &needle, &placeholder(&var)) {
+                *value = replaced;
+                referenced = true;
+            }
+        }
+        if referenced {
+            variables.push(TemplateVariable {
+                name: var,
+                description: Some("Listening port detected at capture time".to_string()),
+                default_value: Some(needle),
+                required: false,
+                var_type: Some(PORT_VAR_TYPE.to_string()),
+                example: None,
+                schema: None,
+            });
+        } else {
+            unreferenced_ports.push(needle);
+        }
+    }
+
+    let mut template = ProcessTemplate::new(name, source.command);
+    template.template_id = template_id;
+    template.args = args;
+    template.env = env;
+    template.default_cwd = source.cwd;
+    template.variables = variables;
+    template.description = (!unreferenced_ports.is_empty()).then(|| {
+        format!(
+            "Captured from a running process (also listening on port {})",
+            unreferenced_ports.join(", ")
+        )
+    });
+    template.tags = vec!["captured".to_string()];
+    let env_variables = env_docs::document(&mut template, &source.dotenv_files);
+
+    CapturedTemplate {
+        template,
+        secrets,
+        ports,
+        env_variables,
+    }
+}
+
+fn placeholder(var: &str) -> String {
+    format!("{{{{{var}}}}}")
+}
+
+/// 環境変数名・フラグ名から変数名を作る（例: --api-token → API_TOKEN）
+fn variable_name(key: &str) -> String {
+    key.trim_start_matches('-')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn push_secret(
+    variables: &mut Vec<TemplateVariable>,
+    secrets: &mut Vec<String>,
+    name: String,
+    source: String,
+) {
+    if secrets.contains(&name) {
+        return;
+    }
+    variables.push(TemplateVariable {
+        name: name.clone(),
+        description: Some(format!("Secret reference (captured from {source})")),
+        default_value: None,
+        required: true,
+        var_type: Some(SECRET_VAR_TYPE.to_string()),
+        example: None,
+        schema: None,
+    });
+    secrets.push(name);
+}
+
+/// 前後が数字でない位置にある数値を置き換える（置き換えがなければNone）
+fn replace_number(haystack: &str, needle: &str, replacement: &str) -> Option<String> {
+    let bytes = haystack.as_bytes();
+    let mut result = String::with_capacity(haystack.len());
+    let mut last = 0;
+    let mut replaced = false;
+    for (start, _) in haystack.match_indices(needle) {
+        let end = start + needle.len();
+        let digit_before = start > 0 && bytes[start - 1].is_ascii_digit();
+        let digit_after = end < bytes.len() && bytes[end].is_ascii_digit();
+        if digit_before || digit_after || start < last {
+            continue;
+        }
+        result.push_str(&haystack[last..start]);
+        result.push_str(replacement);
+        last = end;
+        replaced = true;
+    }
+    if !replaced {
+        return None;
+    }
+    result.push_str(&haystack[last..]);
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_become_required_variables() {
+        let source = CaptureSource {
+            command: "node".to_string(),
+            args: vec![
+                "server.js".to_string(),
+                "--api-token=abc123".to_string(),
+                "--password".to_string(),
+                "hunter2".to_string(),
+            ],
+            env: HashMap::from([
+                ("DATABASE_PASSWORD".to_string(), "p@ss".to_string()),
+                ("GH".to_string(), "ghp_xxxxxxxx".to_string()),
+                ("NODE_ENV".to_string(), "development".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let captured = build_template("web".to_string(), "web".to_string(), source);
+        let template = &captured.template;
+
+        assert_eq!(template.env["DATABASE_PASSWORD"], "{{DATABASE_PASSWORD}}");
+        assert_eq!(template.env["GH"], "{{GH}}");
+        assert_eq!(template.env["NODE_ENV"], "development");
+        assert_eq!(template.args[1], "--api-token={{API_TOKEN}}");
+        assert_eq!(template.args[3], "{{PASSWORD}}");
+        assert_eq!(captured.secrets.len(), 4);
+        assert!(
+            template
+                .variables
+                .iter()
+                .all(|v| v.required && v.default_value.is_none())
+        );
+        // 値がテンプレートに残っていない
+        let serialized = serde_json::to_string(template).unwrap();
+        assert!(!serialized.contains("hunter2") && !serialized.contains("abc123"));
+    }
+
+    #[test]
+    fn test_ports_become_variables_with_defaults() {
+        let source = CaptureSource {
+            command: "python".to_string(),
+            args: vec![
+                "-m".to_string(),
+                "http.server".to_string(),
+                "8000".to_string(),
+            ],
+            env: HashMap::from([("URL".to_string(), "http://localhost:8000/80001".to_string())]),
+            ports: vec![8000, 9229],
+            ..Default::default()
+        };
+        let captured = build_template("http".to_string(), "http".to_string(), source);
+        let template = &captured.template;
+
+        assert_eq!(template.args[2], "{{PORT}}");
+        assert_eq!(template.env["URL"], "http://localhost:{{PORT}}/80001");
+        let port = &template.variables[0];
+        assert_eq!(port.default_value.as_deref(), Some("8000"));
+        assert!(!port.required);
+        assert!(template.description.as_deref().unwrap().contains("9229"));
+
+        let instantiated = template
+            .instantiate("http-2".to_string(), HashMap::new())
+            .unwrap();
+        assert_eq!(instantiated.args[2], "8000");
+    }
+}
@@ -0,0 +1,340 @@
+//! cgroup v2によるリソース管理（Linux）
+//!
+//! cgroup v2が使える環境では、管理するプロセスをそれぞれ専用のcgroup（`<ルート>/<プロセスID>`）に
+//! 入れて起動します。子プロセスも含めたCPU時間・メモリ使用量を正確に集計でき、`resource_limits`
+//! の上限を強制し、停止時にはcgroupごとプロセスツリー全体を確実に終了させられます。
+//!
+//! ルートは環境変数 `VANTAGE_CGROUP_ROOT` で指定できます（例: systemdの `Delegate=yes` で委譲された
+//! ディレクトリ）。未指定の場合はサーバー自身のcgroupの下の `vantage` を使います。
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+use vantage_persistence::ResourceLimits;
+
+/// cgroupのルートを指定する環境変数
+pub const ROOT_ENV: &str = "VANTAGE_CGROUP_ROOT";
+/// cgroup v2のマウントポイント
+const MOUNT_POINT: &str = "/sys/fs/cgroup";
+/// 子cgroupで有効にするコントローラー
+const CONTROLLERS: &[&str] = &["cpu", "memory", "pids"];
+/// `cpu.max` の周期（マイクロ秒）
+const CPU_PERIOD_USEC: u64 = 100_000;
+
+/// cgroupから読み取ったリソース使用状況
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CgroupStats {
+    /// cgroupのパス
+    pub path: String,
+    /// 累積CPU時間（マイクロ秒）
+    pub cpu_usage_usec: Option<u64>,
+    /// 現在のメモリ使用量（バイト）
+    pub memory_current: Option<u64>,
+    /// メモリ使用量の最大値（バイト）
+    pub memory_peak: Option<u64>,
+    /// メモリ使用量の上限（バイト。無制限ならNone）
+    pub memory_max: Option<u64>,
+    /// 現在のプロセス（スレッド）数
+    pub pids_current: Option<u64>,
+    /// OOM killerで強制終了された回数
+    pub oom_kills: Option<u64>,
+}
+
+/// プロセス専用のcgroup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    /// `root` の下にプロセス用のcgroupを作成し、リソース制限を書き込む
+    pub fn create(
+        root: &Path,
+        process_id: &str,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<Self, String> {
+        fs::create_dir_all(root)
+            .map_err(|e| format!("Failed to create cgroup {}: {e}", root.display()))?;
+        // 親がコントローラーを委譲していない場合は集計のみになる（上限の設定時にエラーにする）
+        if let Some(parent) = root.parent() {
+            enable_controllers(parent);
+        }
+        enable_controllers(root);
+
+        let cgroup = Self {
+            path: root.join(dir_name(process_id)),
+        };
+        if let Err(e) = fs::create_dir(&cgroup.path)
+            && e.kind() != std::io::ErrorKind::AlreadyExists
+        {
+            return Err(format!(
+                "Failed to create cgroup {}: {e}",
+                cgroup.path.display()
+            ));
+        }
+        if let Some(limits) = limits {
+            cgroup.apply_limits(limits)?;
+        }
+        Ok(cgroup)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn apply_limits(&self, limits: &ResourceLimits) -> Result<(), String> {
+        let mut values = Vec::new();
+        if let Some(bytes) = limits.memory_max_bytes {
+            values.push(("memory", "memory.max", bytes.to_string()));
+        }
+        if let Some(percent) = limits.cpu_max_percent {
+            values.push(("cpu", "cpu.max", cpu_max_value(percent)));
+        }
+        if let Some(pids) = limits.pids_max {
+            values.push(("pids", "pids.max", pids.to_string()));
+        }
+        for (controller, file, value) in values {
+            let path = self.path.join(file);
+            if !path.exists() {
+                return Err(format!(
+                    "Cannot enforce resource_limits: the {controller} controller is not enabled for {} (delegate it or set {ROOT_ENV})",
+                    self.path.display()
+                ));
+            }
+            fs::write(&path, &value)
+                .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// 起動する子プロセス自身が書き込んでcgroupに入るための `cgroup.procs`
+    pub fn procs_file(&self) -> Result<File, String> {
+        let path = self.path.join("cgroup.procs");
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {e}", path.display()))
+    }
+
+    /// cgroup内のプロセスID
+    pub fn pids(&self) -> Vec<u32> {
+        read(&self.path, "cgroup.procs")
+            .map(|procs| {
+                procs
+                    .lines()
+                    .filter_map(|l| l.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn stats(&self) -> CgroupStats {
+        CgroupStats {
+            path: self.path.display().to_string(),
+            cpu_usage_usec: read(&self.path, "cpu.stat")
+                .and_then(|stat| keyed_value(&stat, "usage_usec")),
+            memory_current: read_u64(&self.path, "memory.current"),
+            memory_peak: read_u64(&self.path, "memory.peak"),
+            memory_max: read_u64(&self.path, "memory.max"),
+            pids_current: read_u64(&self.path, "pids.current"),
+            oom_kills: read(&self.path, "memory.events")
+                .and_then(|events| keyed_value(&events, "oom_kill")),
+        }
+    }
+
+    /// cgroup内のすべてのプロセスを強制終了する
+    ///
+    /// `cgroup.kill`（Linux 5.14以降）を使い、無い場合は各プロセスにSIGKILLを送る。
+    pub fn kill(&self) -> Result<(), String> {
+        let kill_file = self.path.join("cgroup.kill");
+        if kill_file.exists() {
+            return fs::write(&kill_file, "1")
+                .map_err(|e| format!("Failed to write {}: {e}", kill_file.display()));
+        }
+        #[cfg(unix)]
+        for pid in self.pids() {
+            use nix::sys::signal::{Signal, kill};
+            use nix::unistd::Pid;
+            let _ = kill(Pid::from_raw(pid as i32), Signal::SIGKILL);
+        }
+        Ok(())
+    }
+
+    /// 空になったcgroupを削除する
+    pub fn remove(&self) -> Result<(), String> {
+        fs::remove_dir(&self.path)
+            .map_err(|e| format!("Failed to remove cgroup {}: {e}", self.path.display()))
+    }
+}
+
+/// プロセス用cgroupのルートを決める（cgroup v2が使えない場合はNone）
+pub fn detect_root() -> Option<PathBuf> {
+    if let Ok(root) = std::env::var(ROOT_ENV) {
+        return (!root.trim().is_empty()).then(|| PathBuf::from(root));
+    }
+    let mount = Path::new(MOUNT_POINT);
+    if !mount.join("cgroup.controllers").exists() {
+        return None;
+    }
+    let proc_cgroup = fs::read_to_string("/proc/self/cgroup").ok()?;
+    let own = own_cgroup(&proc_cgroup)?;
+    Some(mount.join(own.trim_start_matches('/')).join("vantage"))
+}
+
+/// リソース制限の値を検証
+pub fn validate(limits: &ResourceLimits) -> Result<(), String> {
+    if limits.memory_max_bytes == Some(0) {
+        return Err("resource_limits.memory_max_bytes must be greater than 0".to_string());
+    }
+    if limits.cpu_max_percent == Some(0) {
+        return Err("resource_limits.cpu_max_percent must be greater than 0".to_string());
+    }
+    if limits.pids_max == Some(0) {
+        return Err("resource_limits.pids_max must be greater than 0".to_string());
+    }
+    Ok(())
+}
+
+/// `/proc/self/cgroup` からcgroup v2での所属を取り出す（`0::/path` の行）
+fn own_cgroup(proc_cgroup: &str) -> Option<&str> {
+    proc_cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(str::trim)
+}
+
+/// 子cgroupでコントローラーを有効にする（失敗しても続行する）
+fn enable_controllers(dir: &Path) {
+    let Some(available) = read(dir, "cgroup.controllers") else {
+        return;
+    };
+    let subtree = dir.join("cgroup.subtree_control");
+    for controller in CONTROLLERS {
+        if available.split_whitespace().any(|c| c == *controller)
+            && let Err(e) = fs::write(&subtree, format!("+{controller}"))
+        {
+            tracing::debug!(
+                "Failed to enable the {} controller in {}: {}",
+                controller,
+                subtree.display(),
+                e
+            );
+        }
+    }
+}
+
+/// `cpu.max` に書き込む値（例: 150% → `150000 100000`）
+fn cpu_max_value(percent: u32) -> String {
+    format!(
+        "{} {CPU_PERIOD_USEC}",
+        CPU_PERIOD_USEC * u64::from(percent) / 100
+    )
+}
+
+/// cgroup名に使えない文字を置き換える
+fn dir_name(process_id: &str) -> String {
+    let name: String = process_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("proc-{name}")
+}
+
+fn read(dir: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(dir.join(file)).ok()
+}
+
+fn read_u64(dir: &Path, file: &str) -> Option<u64> {
+    read(dir, file)?.trim().parse().ok()
+}
+
+/// `key value` 形式のファイルから値を取り出す
+fn keyed_value(content: &str, key: &str) -> Option<u64> {
+    content.lines().find_map(|line| {
+        let (k, v) = line.split_once(' ')?;
+        (k == key).then(|| v.trim().parse().ok()).flatten()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_own_cgroup() {
+        let proc_cgroup = "12:pids:/user.slice\n0::/user.slice/user-1000.slice/session-2.scope\n";
+        assert_eq!(
+            own_cgroup(proc_cgroup),
+            Some("/user.slice/user-1000.slice/session-2.scope")
+        );
+        assert_eq!(own_cgroup("4:memory:/docker/abc\n"), None);
+    }
+
+    #[test]
+    fn test_limit_values() {
+        assert_eq!(cpu_max_value(150), "150000 100000");
+        assert_eq!(dir_name("web/api dev"), "proc-web_api_dev");
+        assert!(
+            validate(&ResourceLimits {
+                pids_max: Some(0),
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_create_applies_limits_and_reads_stats() {
+        let root = tempfile::tempdir().unwrap();
+        // 実際のcgroupfsではmkdir時にカーネルが作成するファイル
+        let dir = root.path().join("proc-api");
+        fs::create_dir(&dir).unwrap();
+        for (file, content) in [
+            ("memory.max", "max\n"),
+            ("cpu.max", "max 100000\n"),
+            ("memory.current", "4096\n"),
+            ("cpu.stat", "usage_usec 1500\nuser_usec 1000\n"),
+            ("memory.events", "low 0\noom 1\noom_kill 1\n"),
+        ] {
+            fs::write(dir.join(file), content).unwrap();
+        }
+
+        let limits = ResourceLimits {
+            memory_max_bytes: Some(64 * 1024 * 1024),
+            cpu_max_percent: Some(50),
+            pids_max: None,
+        };
+        let cgroup = Cgroup::create(root.path(), "api", Some(&limits)).unwrap();
+        assert_eq!(cgroup.path(), dir);
+        assert_eq!(
+            fs::read_to_string(dir.join("cpu.max")).unwrap(),
+            "50000 100000"
+        );
+
+        let stats = cgroup.stats();
+        assert_eq!(stats.memory_max, Some(64 * 1024 * 1024));
+        assert_eq!(stats.memory_current, Some(4096));
+        assert_eq!(stats.cpu_usage_usec, Some(1500));
+        assert_eq!(stats.oom_kills, Some(1));
+        assert_eq!(stats.pids_current, None);
+
+        // pidsコントローラーが無いcgroupでは上限を設定できない
+        let err = Cgroup::create(
+            root.path(),
+            "api",
+            Some(&ResourceLimits {
+                pids_max: Some(10),
+                ..Default::default()
+            }),
+        )
+        .unwrap_err();
+        assert!(err.contains("pids controller"), "{err}");
+    }
+}
@@ -0,0 +1,319 @@
+//! プロセスの連鎖（「Xが正常終了したらYを起動」）
+//!
+//! 連鎖の定義と実行履歴を管理します。連鎖の実行は `ProcessManager` がプロセスの終了イベントを
+//! 受けて行います。
+//!
+//! 無限ループを防ぐため、連鎖が循環する定義（A→B→A）は作成時に拒否します。加えて、
+//! 短時間に繰り返し実行された連鎖は一時的に抑止します（自動再起動との組み合わせなど、
+//! 定義からは分からないループへの備え）。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+use utoipa::ToSchema;
+use vantage_persistence::{PersistenceManager, ProcessChain};
+
+/// 実行回数を数える期間
+pub const RUN_WINDOW: chrono::Duration = chrono::Duration::seconds(60);
+/// `RUN_WINDOW` の間に連鎖を実行できる回数
+pub const MAX_RUNS_PER_WINDOW: usize = 5;
+
+/// 連鎖の実行結果
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum ChainOutcome {
+    /// プロセスを起動した
+    Started { pid: u32 },
+    /// 起動済みなどの理由で何もしなかった
+    Skipped { reason: String },
+    /// 起動に失敗した
+    Failed { error: String },
+    /// 短時間に繰り返し実行されたため抑止した
+    Suppressed,
+}
+
+/// 連鎖の実行記録
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ChainRun {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub outcome: ChainOutcome,
+}
+
+/// 連鎖の状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ChainStatus {
+    #[serde(flatten)]
+    pub config: ProcessChain,
+    /// 実行した回数（抑止したものは含まない）
+    pub run_count: u64,
+    pub last_run: Option<ChainRun>,
+}
+
+struct ManagedChain {
+    config: ProcessChain,
+    run_count: u64,
+    last_run: Option<ChainRun>,
+    /// `RUN_WINDOW` 内の実行時刻
+    recent_runs: VecDeque<DateTime<Utc>>,
+}
+
+impl ManagedChain {
+    fn status(&self) -> ChainStatus {
+        ChainStatus {
+            config: self.config.clone(),
+            run_count: self.run_count,
+            last_run: self.last_run.clone(),
+        }
+    }
+}
+
+/// 連鎖の管理
+pub struct ChainManager {
+    chains: RwLock<HashMap<String, ManagedChain>>,
+    persistence: Arc<PersistenceManager>,
+}
+
+impl ChainManager {
+    pub fn new(persistence: Arc<PersistenceManager>) -> Self {
+        Self {
+            chains: RwLock::new(HashMap::new()),
+            persistence,
+        }
+    }
+
+    /// 連鎖を登録する
+    pub async fn create(&self, config: ProcessChain) -> Result<ChainStatus, String> {
+        validate(&config)?;
+        let mut chains = self.chains.write().await;
+        if chains.contains_key(&config.chain_id) {
+            return Err(format!(
+                "Chain with id '{}' already exists",
+                config.chain_id
+            ));
+        }
+        let edges: Vec<(&str, &str)> = chains
+            .values()
+            .map(|chain| {
+                (
+                    chain.config.after.as_str(),
+                    chain.config.action.process_id(),
+                )
+            })
+            .collect();
+        if let Some(cycle) = find_cycle(&edges, &config.after, config.action.process_id()) {
+            return Err(format!("Chain would create a loop: {}", cycle.join(" -> ")));
+        }
+        self.persistence.save_chain(&config).await?;
+        info!(
+            "Created chain '{}': after '{}' succeeds, start '{}'",
+            config.chain_id,
+            config.after,
+            config.action.process_id()
+        );
+        let chain = ManagedChain {
+            config,
+            run_count: 0,
+            last_run: None,
+            recent_runs: VecDeque::new(),
+        };
+        let status = chain.status();
+        chains.insert(status.config.chain_id.clone(), chain);
+        Ok(status)
+    }
+
+    /// 連鎖を削除する
+    pub async fn remove(&self, chain_id: &str) -> Result<(), String> {
+        if self.chains.write().await.remove(chain_id).is_none() {
+            return Err(format!("Chain '{chain_id}' not found"));
+        }
+        self.persistence.delete_chain(chain_id).await?;
+        info!("Removed chain '{}'", chain_id);
+        Ok(())
+    }
+
+    /// 連鎖を有効・無効にする
+    pub async fn set_enabled(&self, chain_id: &str, enabled: bool) -> Result<ChainStatus, String> {
+        let mut chains = self.chains.write().await;
+        let chain = chains
+            .get_mut(chain_id)
+            .ok_or_else(|| format!("Chain '{chain_id}' not found"))?;
+        chain.config.enabled = enabled;
+        self.persistence.save_chain(&chain.config).await?;
+        Ok(chain.status())
+    }
+
+    /// 連鎖の一覧（`process_id` を指定すると、そのプロセスが起点または対象の連鎖のみ）
+    pub async fn list(&self, process_id: Option<&str>) -> Vec<ChainStatus> {
+        let chains = self.chains.read().await;
+        let mut list: Vec<ChainStatus> = chains
+            .values()
+            .filter(|chain| {
+                process_id.is_none_or(|id| {
+                    chain.config.after == id || chain.config.action.process_id() == id
+                })
+            })
+            .map(ManagedChain::status)
+            .collect();
+        list.sort_by(|a, b| {
+            a.config
+                .after
+                .cmp(&b.config.after)
+                .then(a.config.chain_id.cmp(&b.config.chain_id))
+        });
+        list
+    }
+
+    /// プロセスの正常終了で実行する連鎖（実行回数の上限を超えたものは抑止として記録し除く）
+    pub async fn begin_runs(&self, after: &str, now: DateTime<Utc>) -> Vec<ProcessChain> {
+        let mut chains = self.chains.write().await;
+        let mut runnable = Vec::new();
+        for chain in chains.values_mut() {
+            if !chain.config.enabled || chain.config.after != after {
+                continue;
+            }
+            while chain
+                .recent_runs
+                .front()
+                .is_some_and(|at| *at < now - RUN_WINDOW)
+            {
+                chain.recent_runs.pop_front();
+            }
+            if chain.recent_runs.len() >= MAX_RUNS_PER_WINDOW {
+                chain.last_run = Some(ChainRun {
+                    at: now,
+                    outcome: ChainOutcome::Suppressed,
+                });
+                continue;
+            }
+            chain.recent_runs.push_back(now);
+            chain.run_count += 1;
+            runnable.push(chain.config.clone());
+        }
+        runnable.sort_by(|a, b| a.chain_id.cmp(&b.chain_id));
+        runnable
+    }
+
+    /// 連鎖の実行結果を記録する
+    pub async fn record_run(&self, chain_id: &str, outcome: ChainOutcome) {
+        if let Some(chain) = self.chains.write().await.get_mut(chain_id) {
+            chain.last_run = Some(ChainRun {
+                at: Utc::now(),
+                outcome,
+            });
+        }
+    }
+}
+
+/// 連鎖の定義を検証
+pub fn validate(config: &ProcessChain) -> Result<(), String> {
+    if config.chain_id.trim().is_empty() {
+        return Err("chain_id must not be empty".to_string());
+    }
+    if config.after.trim().is_empty() {
+        return Err("after must name the process to watch".to_string());
+    }
+    let target = config.action.process_id();
+    if target.trim().is_empty() {
+        return Err("The chain action requires a process_id".to_string());
+    }
+    if target == config.after {
+        return Err(format!(
+            "Chain would create a loop: '{target}' would restart itself"
+        ));
+    }
+    Ok(())
+}
+
+/// 既存の連鎖に `from -> to` を加えたときにできる循環（なければNone）
+///
+/// 循環は `from` から始まり `from` で終わるプロセスIDの列で返す。
+pub fn find_cycle(edges: &[(&str, &str)], from: &str, to: &str) -> Option<Vec<String>> {
+    // `to` から既存の連鎖をたどって `from` に戻れるかを深さ優先で調べる
+    let mut stack = vec![vec![to.to_string()]];
+    let mut visited = vec![to.to_string()];
+    while let Some(path) = stack.pop() {
+        let last = path.last().unwrap();
+        if last == from {
+            return Some(std::iter::once(from.to_string()).chain(path).collect());
+        }
+        for (_, target) in edges.iter().filter(|(after, _)| after == last) {
+            if !visited.iter().any(|v| v == target) {
+                visited.push(target.to_string());
+                let mut next = path.clone();
+                next.push(target.to_string());
+                stack.push(next);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vantage_persistence::ChainAction;
+
+    fn chain(chain_id: &str, after: &str, target: &str) -> ProcessChain {
+        ProcessChain {
+            chain_id: chain_id.to_string(),
+            after: after.to_string(),
+            action: ChainAction::StartProcess {
+                process_id: target.to_string(),
+            },
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let edges = [("build", "test"), ("test", "serve")];
+        assert_eq!(find_cycle(&edges, "migrate", "seed"), None);
+        assert_eq!(find_cycle(&edges, "build", "serve"), None);
+        assert_eq!(
+            find_cycle(&edges, "serve", "build"),
+            Some(vec![
+                "serve".to_string(),
+                "build".to_string(),
+                "test".to_string(),
+                "serve".to_string()
+            ])
+        );
+        assert!(validate(&chain("loop", "build", "build")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_loops_and_suppresses_repeated_runs() {
+        let persistence = Arc::new(PersistenceManager::new().await.unwrap());
+        let manager = ChainManager::new(persistence);
+        manager
+            .create(chain("build-serve", "build", "serve"))
+            .await
+            .unwrap();
+        let err = manager
+            .create(chain("serve-build", "serve", "build"))
+            .await
+            .unwrap_err();
+        assert!(err.contains("serve -> build -> serve"), "{err}");
+
+        let now = Utc::now();
+        for _ in 0..MAX_RUNS_PER_WINDOW {
+            assert_eq!(manager.begin_runs("build", now).await.len(), 1);
+        }
+        assert!(manager.begin_runs("build", now).await.is_empty());
+        let status = &manager.list(Some("serve")).await[0];
+        assert_eq!(status.run_count, MAX_RUNS_PER_WINDOW as u64);
+        assert_eq!(
+            status.last_run.as_ref().unwrap().outcome,
+            ChainOutcome::Suppressed
+        );
+
+        // 期間が過ぎれば再び実行できる
+        let later = now + RUN_WINDOW + chrono::Duration::seconds(1);
+        assert_eq!(manager.begin_runs("build", later).await.len(), 1);
+    }
+}
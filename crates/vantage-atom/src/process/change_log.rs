@@ -0,0 +1,87 @@
+//! 定義の変更の取り消し・やり直し
+//!
+//! プロセスの作成・更新・削除とテンプレートの編集を、変更前後の定義とともに記録します。
+//! `undo_last_change` で直前の変更を取り消し、`redo_change` で取り消した変更をやり直せます。
+//! 新しい変更を記録するとやり直しの履歴は消えます。インポートなどの一括操作はここでは記録せず、
+//! 操作前の自動スナップショット（[`super::pre_op`]）で戻します。
+
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use vantage_persistence::{ChangeAction, ChangeTarget, DefinitionChange, generate_id};
+
+/// 取り消せる変更の数
+pub const MAX_HISTORY: usize = 100;
+
+/// 比較のときに無視する、実行状態や使用状況のフィールド
+const VOLATILE_FIELDS: &[&str] = &[
+    "id",
+    "status",
+    "created_at",
+    "updated_at",
+    "use_count",
+    "last_used_at",
+];
+
+/// 変更前後の定義から記録を作る（内容が変わっていなければ `None`）
+pub fn new_change<T: Serialize>(
+    target: ChangeTarget,
+    target_id: &str,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Option<DefinitionChange> {
+    let before = before.and_then(|b| serde_json::to_value(b).ok());
+    let after = after.and_then(|a| serde_json::to_value(a).ok());
+    let action = match (&before, &after) {
+        (None, None) => return None,
+        (None, Some(_)) => ChangeAction::Created,
+        (Some(_), None) => ChangeAction::Removed,
+        (Some(b), Some(a)) if definition(b) == definition(a) => return None,
+        (Some(_), Some(_)) => ChangeAction::Updated,
+    };
+    Some(DefinitionChange {
+        change_id: generate_id(),
+        target,
+        target_id: target_id.to_string(),
+        action,
+        before,
+        after,
+        changed_at: Utc::now(),
+    })
+}
+
+/// 実行状態などを除いた定義
+fn definition(value: &Value) -> Value {
+    let mut value = value.clone();
+    if let Some(map) = value.as_object_mut() {
+        for key in VOLATILE_FIELDS {
+            map.remove(*key);
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_new_change_detects_action_and_ignores_noops() {
+        let before = json!({"command": "node", "status": {"state": "Running"}});
+        let same = json!({"command": "node", "status": {"state": "Stopped"}});
+        let after = json!({"command": "bun", "status": {"state": "Running"}});
+
+        assert!(new_change(ChangeTarget::Process, "api", Some(&before), Some(&same)).is_none());
+        assert!(new_change::<Value>(ChangeTarget::Process, "api", None, None).is_none());
+
+        let change = new_change(ChangeTarget::Process, "api", Some(&before), Some(&after)).unwrap();
+        assert_eq!(change.action, ChangeAction::Updated);
+        assert_eq!(change.before, Some(before.clone()));
+
+        let change = new_change(ChangeTarget::Template, "web", None, Some(&after)).unwrap();
+        assert_eq!(change.action, ChangeAction::Created);
+        let change = new_change(ChangeTarget::Template, "web", Some(&before), None).unwrap();
+        assert_eq!(change.action, ChangeAction::Removed);
+    }
+}
@@ -0,0 +1,206 @@
+//! 障害注入（カオステスト）
+//!
+//! 復旧性の確認のため、管理下のプロセスを意図的に強制終了したり、SIGSTOP/SIGCONTで一時停止させます。
+//! 設定（`chaos.enabled`）が有効な場合のみ実行でき、実行した操作はすべてカオス操作として
+//! 履歴とイベントに記録されます。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// 保持する操作履歴の件数
+const HISTORY_LIMIT: usize = 100;
+
+/// 障害注入の対象
+#[derive(Debug, Clone, Default)]
+pub struct ChaosTarget {
+    /// 対象のプロセスID（指定しない場合は候補からランダムに選ぶ）
+    pub process_id: Option<String>,
+    /// 候補をこのタグを持つプロセスに限定する
+    pub tag: Option<String>,
+}
+
+/// 実行したカオス操作
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ChaosAction {
+    /// SIGKILLで強制終了
+    Kill {
+        pid: u32,
+        /// 指定時間後に再起動する
+        restart_after_secs: Option<u64>,
+    },
+    /// 強制終了後の再起動
+    Restart,
+    /// SIGSTOPで一時停止
+    Pause { pid: u32, duration_secs: u64 },
+    /// SIGCONTで再開
+    Resume { pid: u32 },
+}
+
+impl ChaosAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Kill { .. } => "kill",
+            Self::Restart => "restart",
+            Self::Pause { .. } => "pause",
+            Self::Resume { .. } => "resume",
+        }
+    }
+}
+
+/// カオス操作の記録
+#[derive(Debug, Clone, Serialize)]
+pub struct ChaosRecord {
+    pub at: DateTime<Utc>,
+    pub process_id: String,
+    #[serde(flatten)]
+    pub action: ChaosAction,
+    /// スケジュールによる操作の場合、そのID
+    pub schedule_id: Option<String>,
+}
+
+/// 定期的な一時停止のスケジュール
+#[derive(Debug, Clone, Serialize)]
+pub struct ChaosSchedule {
+    pub schedule_id: String,
+    pub process_id: Option<String>,
+    pub tag: Option<String>,
+    pub interval_secs: u64,
+    pub pause_secs: u64,
+    pub started_at: DateTime<Utc>,
+}
+
+/// カオステストの状態
+#[derive(Debug, Clone, Serialize)]
+pub struct ChaosStatus {
+    pub enabled: bool,
+    pub schedules: Vec<ChaosSchedule>,
+    /// 新しい順の操作履歴
+    pub recent_actions: Vec<ChaosRecord>,
+}
+
+/// 操作履歴と実行中のスケジュール
+#[derive(Default)]
+pub struct ChaosState {
+    history: Mutex<VecDeque<ChaosRecord>>,
+    schedules: Mutex<HashMap<String, (ChaosSchedule, JoinHandle<()>)>>,
+}
+
+impl ChaosState {
+    pub async fn record(&self, record: ChaosRecord) {
+        let mut history = self.history.lock().await;
+        if history.len() >= HISTORY_LIMIT {
+            history.pop_front();
+        }
+        history.push_back(record);
+    }
+
+    pub async fn recent(&self) -> Vec<ChaosRecord> {
+        self.history.lock().await.iter().rev().cloned().collect()
+    }
+
+    pub async fn add_schedule(&self, schedule: ChaosSchedule, handle: JoinHandle<()>) {
+        self.schedules
+            .lock()
+            .await
+            .insert(schedule.schedule_id.clone(), (schedule, handle));
+    }
+
+    /// スケジュールを止める（実行中の一時停止は予定どおり再開される）
+    pub async fn cancel_schedule(&self, schedule_id: &str) -> Result<(), String> {
+        let (_, handle) = self
+            .schedules
+            .lock()
+            .await
+            .remove(schedule_id)
+            .ok_or_else(|| format!("Chaos schedule '{schedule_id}' not found"))?;
+        handle.abort();
+        Ok(())
+    }
+
+    pub async fn schedules(&self) -> Vec<ChaosSchedule> {
+        let mut schedules: Vec<ChaosSchedule> = self
+            .schedules
+            .lock()
+            .await
+            .values()
+            .map(|(schedule, _)| schedule.clone())
+            .collect();
+        schedules.sort_by_key(|s| s.started_at);
+        schedules
+    }
+}
+
+/// 候補からランダムに1つ選ぶ
+pub fn pick_random<T>(items: &[T]) -> Option<&T> {
+    if items.is_empty() {
+        return None;
+    }
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(Utc::now().timestamp_nanos_opt().unwrap_or_default() as u128);
+    items.get(hasher.finish() as usize % items.len())
+}
+
+/// 障害注入に使うシグナル
+#[derive(Debug, Clone, Copy)]
+pub enum ChaosSignal {
+    Kill,
+    Stop,
+    Cont,
+}
+
+/// プロセスグループ（失敗した場合はプロセス単体）にシグナルを送る
+#[cfg(unix)]
+pub fn send_signal(pid: u32, signal: ChaosSignal) -> Result<(), String> {
+    use nix::sys::signal::{Signal, kill};
+    use nix::unistd::Pid;
+
+    let signal = match signal {
+        ChaosSignal::Kill => Signal::SIGKILL,
+        ChaosSignal::Stop => Signal::SIGSTOP,
+        ChaosSignal::Cont => Signal::SIGCONT,
+    };
+    kill(Pid::from_raw(-(pid as i32)), signal)
+        .or_else(|_| kill(Pid::from_raw(pid as i32), signal))
+        .map_err(|e| format!("Failed to send {signal} to process {pid}: {e}"))
+}
+
+#[cfg(not(unix))]
+pub fn send_signal(_pid: u32, _signal: ChaosSignal) -> Result<(), String> {
+    Err("Chaos testing is only supported on Unix".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_random() {
+        assert_eq!(pick_random::<u32>(&[]), None);
+        assert_eq!(pick_random(&["only"]), Some(&"only"));
+        let items = [1, 2, 3];
+        assert!(items.contains(pick_random(&items).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_history_is_bounded_and_newest_first() {
+        let state = ChaosState::default();
+        for i in 0..(HISTORY_LIMIT + 5) {
+            state
+                .record(ChaosRecord {
+                    at: Utc::now(),
+                    process_id: format!("p{i}"),
+                    action: ChaosAction::Restart,
+                    schedule_id: None,
+                })
+                .await;
+        }
+        let recent = state.recent().await;
+        assert_eq!(recent.len(), HISTORY_LIMIT);
+        assert_eq!(recent[0].process_id, format!("p{}", HISTORY_LIMIT + 4));
+    }
+}
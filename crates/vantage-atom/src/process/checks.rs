@@ -0,0 +1,438 @@
+//! プロセスの振る舞いの確認（ローカルのスモークテスト）
+//!
+//! プロセスに設定した確認項目（期限内に準備完了になる、最初の数分にERRORを出力しない、
+//! 終了コード0で終了するなど）を、起動からの経過時間と出力・終了の様子で評価します。
+//! 評価は観測を受け取るたびに進め、すべての項目の合否が決まるか期限を過ぎると終わります。
+
+use regex::Regex;
+use serde::Serialize;
+use std::time::Duration;
+use utoipa::ToSchema;
+use vantage_persistence::ProcessAssertion;
+
+/// 評価の既定の上限（終了コードの項目に期限がない場合もここで打ち切る）
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+/// 観測の間隔
+pub const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 確認項目を検証
+pub fn validate(assertions: &[ProcessAssertion]) -> Result<(), String> {
+    for assertion in assertions {
+        match assertion {
+            ProcessAssertion::ReadyWithin {
+                within_secs,
+                pattern,
+                ..
+            } => {
+                if *within_secs == 0 {
+                    return Err("ready_within.within_secs must be at least 1".to_string());
+                }
+                if let Some(pattern) = pattern {
+                    compile(pattern)?;
+                }
+            }
+            ProcessAssertion::NoOutputMatch {
+                pattern,
+                within_secs,
+                ..
+            } => {
+                if *within_secs == 0 {
+                    return Err("no_output_match.within_secs must be at least 1".to_string());
+                }
+                compile(pattern)?;
+            }
+            ProcessAssertion::ExitCode { within_secs, .. } => {
+                if *within_secs == Some(0) {
+                    return Err("exit_code.within_secs must be at least 1".to_string());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid assertion pattern '{pattern}': {e}"))
+}
+
+/// 1項目の結果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AssertionResult {
+    pub assertion: ProcessAssertion,
+    pub passed: bool,
+    /// 合否の理由（一致した行、終了コードなど）
+    pub detail: String,
+    /// 合否が決まった時点の起動からの経過時間（ミリ秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decided_after_ms: Option<u64>,
+}
+
+/// 1プロセスの結果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProcessCheckReport {
+    pub process_id: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// 起動できなかった場合などのエラー
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub assertions: Vec<AssertionResult>,
+}
+
+/// `run_checks` の結果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CheckReport {
+    pub passed: bool,
+    pub passed_count: usize,
+    pub failed_count: usize,
+    pub duration_ms: u64,
+    pub processes: Vec<ProcessCheckReport>,
+}
+
+impl CheckReport {
+    pub fn new(processes: Vec<ProcessCheckReport>, duration: Duration) -> Self {
+        let passed_count = processes.iter().filter(|p| p.passed).count();
+        Self {
+            passed: passed_count == processes.len(),
+            passed_count,
+            failed_count: processes.len() - passed_count,
+            duration_ms: duration.as_millis() as u64,
+            processes,
+        }
+    }
+}
+
+/// 起動してからの出力の1行
+#[derive(Debug, Clone)]
+pub struct ObservedLine {
+    /// 起動からの経過時間
+    pub offset: Duration,
+    pub stderr: bool,
+    pub text: String,
+}
+
+/// ある時点までの観測
+#[derive(Debug, Default)]
+pub struct Observation {
+    /// 起動からの経過時間
+    pub elapsed: Duration,
+    /// 起動してからの行（バッファに残っている分、前回の観測の行を含んでよい）
+    pub lines: Vec<ObservedLine>,
+    /// 終了していれば終了コード（シグナルなどで取得できなければNone）
+    pub exited: Option<Option<i32>>,
+    /// 接続を受け付けたポート
+    pub open_ports: Vec<u16>,
+}
+
+struct Pending {
+    assertion: ProcessAssertion,
+    pattern: Option<Regex>,
+    result: Option<(bool, String, Duration)>,
+}
+
+/// 確認項目の評価
+pub struct Evaluator {
+    items: Vec<Pending>,
+    timeout: Duration,
+}
+
+impl Evaluator {
+    /// 項目を検証してから評価を始める（`timeout` で打ち切る）
+    pub fn new(assertions: &[ProcessAssertion], timeout: Duration) -> Result<Self, String> {
+        validate(assertions)?;
+        let items = assertions
+            .iter()
+            .map(|assertion| {
+                let pattern = match assertion {
+                    ProcessAssertion::ReadyWithin { pattern, .. } => pattern.as_deref(),
+                    ProcessAssertion::NoOutputMatch { pattern, .. } => Some(pattern.as_str()),
+                    ProcessAssertion::ExitCode { .. } => None,
+                };
+                Pending {
+                    assertion: assertion.clone(),
+                    pattern: pattern.map(|p| compile(p).expect("validated")),
+                    result: None,
+                }
+            })
+            .collect();
+        Ok(Self { items, timeout })
+    }
+
+    /// 評価を終える時点（どの項目もこれより後には決まらない）
+    pub fn deadline(&self) -> Duration {
+        let longest = self
+            .items
+            .iter()
+            .map(|item| match &item.assertion {
+                ProcessAssertion::ReadyWithin { within_secs, .. }
+                | ProcessAssertion::NoOutputMatch { within_secs, .. } => {
+                    Duration::from_secs(*within_secs)
+                }
+                ProcessAssertion::ExitCode { within_secs, .. } => {
+                    within_secs.map(Duration::from_secs).unwrap_or(self.timeout)
+                }
+            })
+            .max()
+            .unwrap_or_default();
+        longest.min(self.timeout)
+    }
+
+    /// まだ決まっていない準備完了の項目で確かめるポート
+    pub fn ports_to_probe(&self) -> Vec<u16> {
+        self.items
+            .iter()
+            .filter(|item| item.result.is_none())
+            .filter_map(|item| match &item.assertion {
+                ProcessAssertion::ReadyWithin { port, .. } => *port,
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// すべての項目の合否が決まったか
+    pub fn is_done(&self) -> bool {
+        self.items.iter().all(|item| item.result.is_some())
+    }
+
+    /// 観測を反映する
+    pub fn observe(&mut self, observation: &Observation) {
+        let elapsed = observation.elapsed;
+        for item in self.items.iter_mut().filter(|item| item.result.is_none()) {
+            item.result = match &item.assertion {
+                ProcessAssertion::ReadyWithin {
+                    within_secs,
+                    port,
+                    pattern: _,
+                } => {
+                    let within = Duration::from_secs(*within_secs);
+                    let matched = item.pattern.as_ref().and_then(|pattern| {
+                        observation
+                            .lines
+                            .iter()
+                            .find(|line| line.offset <= within && pattern.is_match(&line.text))
+                    });
+                    let port_open = port.filter(|port| observation.open_ports.contains(port));
+                    if let Some(line) = matched {
+                        Some((true, format!("Ready: '{}'", line.text), line.offset))
+                    } else if let Some(port) = port_open.filter(|_| elapsed <= within) {
+                        Some((
+                            true,
+                            format!("Ready: port {port} accepts connections"),
+                            elapsed,
+                        ))
+                    } else if port.is_none()
+                        && item.pattern.is_none()
+                        && observation.exited.is_none()
+                        && elapsed <= within
+                    {
+                        Some((true, "Running".to_string(), elapsed))
+                    } else if let Some(code) = observation.exited {
+                        Some((
+                            false,
+                            format!("Exited ({}) before becoming ready", describe_exit(code)),
+                            elapsed,
+                        ))
+                    } else if elapsed > within {
+                        Some((false, format!("Not ready within {within_secs}s"), elapsed))
+                    } else {
+                        None
+                    }
+                }
+                ProcessAssertion::NoOutputMatch {
+                    within_secs,
+                    stderr_only,
+                    ..
+                } => {
+                    let within = Duration::from_secs(*within_secs);
+                    let pattern = item.pattern.as_ref().expect("compiled");
+                    let matched = observation.lines.iter().find(|line| {
+                        line.offset <= within
+                            && (line.stderr || !stderr_only)
+                            && pattern.is_match(&line.text)
+                    });
+                    if let Some(line) = matched {
+                        Some((false, format!("Matched: '{}'", line.text), line.offset))
+                    } else if elapsed >= within {
+                        Some((true, format!("No match in {within_secs}s"), within))
+                    } else if observation.exited.is_some() {
+                        Some((true, "No match before exiting".to_string(), elapsed))
+                    } else {
+                        None
+                    }
+                }
+                ProcessAssertion::ExitCode { code, within_secs } => {
+                    let within = within_secs.map(Duration::from_secs);
+                    match observation.exited {
+                        Some(exit) if within.is_some_and(|within| elapsed > within) => Some((
+                            false,
+                            format!("Exited ({}) after the limit", describe_exit(exit)),
+                            elapsed,
+                        )),
+                        Some(exit) => Some((
+                            exit == Some(*code),
+                            format!("Exited ({})", describe_exit(exit)),
+                            elapsed,
+                        )),
+                        None if within.is_some_and(|within| elapsed > within) => Some((
+                            false,
+                            format!("Still running after {}s", within_secs.unwrap_or_default()),
+                            elapsed,
+                        )),
+                        None => None,
+                    }
+                }
+            };
+        }
+    }
+
+    /// 評価を終え、決まっていない項目を不合格にして結果を返す
+    pub fn finish(self, elapsed: Duration) -> Vec<AssertionResult> {
+        self.items
+            .into_iter()
+            .map(|item| {
+                let (passed, detail, at) = item.result.unwrap_or_else(|| {
+                    (
+                        false,
+                        format!(
+                            "Undecided when the checks stopped after {}s",
+                            elapsed.as_secs()
+                        ),
+                        elapsed,
+                    )
+                });
+                AssertionResult {
+                    assertion: item.assertion,
+                    passed,
+                    detail,
+                    decided_after_ms: Some(at.as_millis() as u64),
+                }
+            })
+            .collect()
+    }
+}
+
+fn describe_exit(code: Option<i32>) -> String {
+    match code {
+        Some(code) => format!("exit code {code}"),
+        None => "killed by a signal".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(ms: u64, stderr: bool, text: &str) -> ObservedLine {
+        ObservedLine {
+            offset: Duration::from_millis(ms),
+            stderr,
+            text: text.to_string(),
+        }
+    }
+
+    fn observe(
+        elapsed_ms: u64,
+        lines: Vec<ObservedLine>,
+        exited: Option<Option<i32>>,
+    ) -> Observation {
+        Observation {
+            elapsed: Duration::from_millis(elapsed_ms),
+            lines,
+            exited,
+            open_ports: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_server_checks() {
+        let assertions = vec![
+            ProcessAssertion::ReadyWithin {
+                within_secs: 30,
+                port: Some(8080),
+                pattern: None,
+            },
+            ProcessAssertion::NoOutputMatch {
+                pattern: "ERROR".to_string(),
+                within_secs: 5,
+                stderr_only: false,
+            },
+        ];
+        let mut evaluator = Evaluator::new(&assertions, Duration::from_secs(60)).unwrap();
+        assert_eq!(evaluator.deadline(), Duration::from_secs(30));
+        assert_eq!(evaluator.ports_to_probe(), vec![8080]);
+
+        evaluator.observe(&observe(1000, vec![line(500, false, "booting")], None));
+        assert!(!evaluator.is_done());
+        let mut ready = observe(2000, vec![], None);
+        ready.open_ports = vec![8080];
+        evaluator.observe(&ready);
+        assert!(evaluator.ports_to_probe().is_empty());
+        evaluator.observe(&observe(5000, vec![line(6000, true, "ERROR later")], None));
+        assert!(evaluator.is_done());
+
+        let results = evaluator.finish(Duration::from_secs(5));
+        assert!(results.iter().all(|r| r.passed), "{results:?}");
+        assert_eq!(results[0].decided_after_ms, Some(2000));
+    }
+
+    #[test]
+    fn test_failures_are_reported_with_details() {
+        let assertions = vec![
+            ProcessAssertion::ReadyWithin {
+                within_secs: 10,
+                port: None,
+                pattern: Some("listening".to_string()),
+            },
+            ProcessAssertion::NoOutputMatch {
+                pattern: "(?i)error".to_string(),
+                within_secs: 60,
+                stderr_only: true,
+            },
+            ProcessAssertion::ExitCode {
+                code: 0,
+                within_secs: None,
+            },
+        ];
+        let mut evaluator = Evaluator::new(&assertions, Duration::from_secs(60)).unwrap();
+        evaluator.observe(&observe(
+            800,
+            vec![
+                line(100, false, "error in stdout is ignored"),
+                line(700, true, "Error: config missing"),
+            ],
+            Some(Some(1)),
+        ));
+        assert!(evaluator.is_done());
+
+        let results = evaluator.finish(Duration::from_millis(800));
+        assert!(results.iter().all(|r| !r.passed), "{results:?}");
+        assert_eq!(
+            results[0].detail,
+            "Exited (exit code 1) before becoming ready"
+        );
+        assert_eq!(results[1].detail, "Matched: 'Error: config missing'");
+        assert_eq!(results[2].detail, "Exited (exit code 1)");
+    }
+
+    #[test]
+    fn test_undecided_and_invalid_assertions() {
+        let assertions = vec![ProcessAssertion::ExitCode {
+            code: 0,
+            within_secs: None,
+        }];
+        let mut evaluator = Evaluator::new(&assertions, Duration::from_secs(2)).unwrap();
+        assert_eq!(evaluator.deadline(), Duration::from_secs(2));
+        evaluator.observe(&observe(2000, vec![], None));
+        let results = evaluator.finish(Duration::from_secs(2));
+        assert!(!results[0].passed);
+
+        let invalid = [ProcessAssertion::NoOutputMatch {
+            pattern: "(".to_string(),
+            within_secs: 5,
+            stderr_only: false,
+        }];
+        assert!(validate(&invalid).is_err());
+    }
+}
@@ -0,0 +1,333 @@
+//! コンテナバックエンド
+//!
+//! `container` を指定したプロセスは `<engine> run --rm --name vantage-<ID>` で起動します。
+//! `build` を指定した場合は起動のたびに先にイメージをビルドし（ビルドの出力は `[build]` を付けて
+//! プロセスの出力に取り込む）、ビルドに失敗した場合は起動しません。ビルド前後のイメージIDを比べて
+//! キャッシュだけで済んだかを判定し、`rebuild_on_change` ではビルドコンテキストの変更を検知して
+//! 再ビルド・再起動します。
+
+use super::buffer::CircularBuffer;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use utoipa::ToSchema;
+use vantage_persistence::{ContainerConfig, ContainerEngine, ImageBuildConfig};
+
+/// ビルドの出力に付ける接頭辞
+const BUILD_PREFIX: &str = "[build] ";
+/// 変更検知で無視するディレクトリ
+const IGNORED_DIRS: &[&str] = &[".git", "node_modules", "target"];
+
+/// 最後のイメージのビルドの結果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImageBuildStatus {
+    pub image: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    /// ビルド前後でイメージIDが変わらなかった（すべてキャッシュから作られた）か
+    pub cache_hit: bool,
+    /// キャッシュを使ったステップ数
+    pub cached_steps: u32,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// ビルド時のビルドコンテキストのフィンガープリント（変更検知用）
+    #[serde(skip)]
+    pub context_fingerprint: u64,
+}
+
+/// 設定を検証
+pub fn validate(config: &ContainerConfig) -> Result<(), String> {
+    if config.image.trim().is_empty() || config.image.contains(char::is_whitespace) {
+        return Err("container.image must be a non-empty image tag".to_string());
+    }
+    if let Some(build) = &config.build
+        && build.context.trim().is_empty()
+    {
+        return Err("container.build.context must not be empty".to_string());
+    }
+    crate::security::validate_args(&config.run_args)
+}
+
+pub fn program(engine: ContainerEngine) -> &'static str {
+    match engine {
+        ContainerEngine::Docker => "docker",
+        ContainerEngine::Podman => "podman",
+    }
+}
+
+/// プロセスのコンテナ名
+pub fn container_name(process_id: &str) -> String {
+    let name: String = process_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("vantage-{name}")
+}
+
+/// `run` の引数
+///
+/// 環境変数は値を引数に含めず、`-e KEY` でエンジンのクライアントの環境から渡す。
+pub fn run_args(
+    config: &ContainerConfig,
+    process_id: &str,
+    env: &HashMap<String, String>,
+    command: &str,
+    args: &[String],
+) -> Vec<String> {
+    let mut run = vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "--name".to_string(),
+        container_name(process_id),
+    ];
+    let mut keys: Vec<&String> = env.keys().collect();
+    keys.sort();
+    for key in keys {
+        run.push("-e".to_string());
+        run.push(key.clone());
+    }
+    run.extend(config.run_args.iter().cloned());
+    run.push(config.image.clone());
+    if !command.is_empty() {
+        run.push(command.to_string());
+        run.extend(args.iter().cloned());
+    }
+    run
+}
+
+/// `build` の引数
+fn build_args(build: &ImageBuildConfig, image: &str) -> Vec<String> {
+    let mut args = vec!["build".to_string(), "-t".to_string(), image.to_string()];
+    if let Some(dockerfile) = &build.dockerfile {
+        args.push("-f".to_string());
+        args.push(dockerfile.clone());
+    }
+    let mut build_args: Vec<_> = build.build_args.iter().collect();
+    build_args.sort();
+    for (key, value) in build_args {
+        args.push("--build-arg".to_string());
+        args.push(format!("{key}={value}"));
+    }
+    args.push(build.context.clone());
+    args
+}
+
+/// ビルドコンテキストのディレクトリ
+pub fn context_dir(build: &ImageBuildConfig, cwd: Option<&Path>) -> PathBuf {
+    let context = Path::new(&build.context);
+    match cwd {
+        Some(cwd) if context.is_relative() => cwd.join(context),
+        _ => context.to_path_buf(),
+    }
+}
+
+/// ビルドコンテキストのフィンガープリント（ファイルのパス・サイズ・更新時刻から計算）
+pub fn fingerprint(dir: &Path) -> u64 {
+    let mut entries = Vec::new();
+    collect_entries(dir, &mut entries);
+    entries.sort();
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn collect_entries(dir: &Path, entries: &mut Vec<(PathBuf, u64, Option<std::time::SystemTime>)>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if !IGNORED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                collect_entries(&path, entries);
+            }
+        } else {
+            entries.push((path, metadata.len(), metadata.modified().ok()));
+        }
+    }
+}
+
+/// キャッシュを使ったステップの出力か（BuildKitの `#5 CACHED`、従来のビルダー・podmanの `Using cache`）
+fn is_cached_step(line: &str) -> bool {
+    line.ends_with(" CACHED") || line.contains("Using cache")
+}
+
+/// イメージをビルドし、出力をバッファに取り込む
+///
+/// エンジンを起動できない場合はエラー、ビルドが失敗した場合は `success: false` の結果を返す。
+pub async fn build(
+    config: &ContainerConfig,
+    build: &ImageBuildConfig,
+    cwd: Option<&Path>,
+    stdout_buffer: &CircularBuffer,
+    stderr_buffer: &CircularBuffer,
+) -> Result<ImageBuildStatus, String> {
+    let context = context_dir(build, cwd);
+    let context_fingerprint = {
+        let context = context.clone();
+        tokio::task::spawn_blocking(move || fingerprint(&context))
+            .await
+            .unwrap_or_default()
+    };
+    let engine = program(config.engine);
+    let before = image_id(engine, &config.image).await;
+
+    let started_at = Utc::now();
+    let started = Instant::now();
+    let mut cmd = Command::new(engine);
+    cmd.args(build_args(build, &config.image))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(cwd) = cwd {
+        cmd.current_dir(cwd);
+    }
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to run `{engine} build`: {e}"))?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let (cached_out, cached_err) = tokio::join!(
+        stream_lines(stdout, stdout_buffer),
+        stream_lines(stderr, stderr_buffer)
+    );
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for `{engine} build`: {e}"))?;
+
+    let after = if status.success() {
+        image_id(engine, &config.image).await
+    } else {
+        None
+    };
+    Ok(ImageBuildStatus {
+        image: config.image.clone(),
+        success: status.success(),
+        exit_code: status.code(),
+        cache_hit: before.is_some() && before == after,
+        cached_steps: cached_out + cached_err,
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        context_fingerprint,
+    })
+}
+
+/// 出力を接頭辞付きでバッファに取り込み、キャッシュを使ったステップ数を返す
+async fn stream_lines(reader: Option<impl AsyncRead + Unpin>, buffer: &CircularBuffer) -> u32 {
+    let Some(reader) = reader else {
+        return 0;
+    };
+    let mut cached = 0;
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if is_cached_step(line.trim_end()) {
+            cached += 1;
+        }
+        buffer.push(format!("{BUILD_PREFIX}{line}")).await;
+    }
+    cached
+}
+
+async fn image_id(engine: &str, image: &str) -> Option<String> {
+    let output = Command::new(engine)
+        .args(["image", "inspect", "--format", "{{.Id}}", image])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !id.is_empty()).then_some(id)
+}
+
+/// 残っているコンテナを削除する（クライアントが強制終了された場合などの後始末）
+pub async fn remove_container(engine: ContainerEngine, process_id: &str) {
+    let _ = Command::new(program(engine))
+        .args(["rm", "-f", &container_name(process_id)])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ContainerConfig {
+        ContainerConfig {
+            engine: ContainerEngine::Podman,
+            image: "myapp:dev".to_string(),
+            build: Some(ImageBuildConfig {
+                context: "app".to_string(),
+                dockerfile: Some("app/Dockerfile.dev".to_string()),
+                build_args: HashMap::from([("NODE_ENV".to_string(), "development".to_string())]),
+                rebuild_on_change: true,
+            }),
+            run_args: vec!["-p".to_string(), "8080:80".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_command_lines() {
+        let config = config();
+        let env = HashMap::from([("TOKEN".to_string(), "secret".to_string())]);
+        assert_eq!(
+            run_args(&config, "web api", &env, "npm", &["start".to_string()]).join(" "),
+            "run --rm --name vantage-web_api -e TOKEN -p 8080:80 myapp:dev npm start"
+        );
+        assert_eq!(
+            run_args(&config, "web", &HashMap::new(), "", &[]).join(" "),
+            "run --rm --name vantage-web -p 8080:80 myapp:dev"
+        );
+        assert_eq!(
+            build_args(config.build.as_ref().unwrap(), &config.image).join(" "),
+            "build -t myapp:dev -f app/Dockerfile.dev --build-arg NODE_ENV=development app"
+        );
+        assert!(
+            validate(&ContainerConfig {
+                image: "my app".to_string(),
+                ..config
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_cached_step_detection() {
+        assert!(is_cached_step("#6 [2/4] RUN npm ci CACHED"));
+        assert!(is_cached_step(" ---> Using cache"));
+        assert!(!is_cached_step("#7 [3/4] COPY . . 0.3s"));
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_context() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Dockerfile"), "FROM scratch\n").unwrap();
+        std::fs::create_dir(dir.path().join("node_modules")).unwrap();
+        let initial = fingerprint(dir.path());
+
+        std::fs::write(dir.path().join("node_modules/ignored.js"), "x").unwrap();
+        assert_eq!(fingerprint(dir.path()), initial);
+
+        std::fs::write(dir.path().join("main.py"), "print('hi')\n").unwrap();
+        assert_ne!(fingerprint(dir.path()), initial);
+    }
+}
@@ -0,0 +1,162 @@
+//! クラッシュループ検知
+//!
+//! 短時間に異常終了を繰り返すプロセスを検知し、自動再起動を抑止します。
+//! 検知前の自動再起動には、直近の失敗回数に応じた指数バックオフを適用します。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// この回数の異常終了が検知ウィンドウ内に発生したらクラッシュループとみなす
+pub const CRASH_LOOP_THRESHOLD: usize = 5;
+/// 検知ウィンドウ（秒）
+pub const CRASH_LOOP_WINDOW_SECS: i64 = 60;
+/// 自動再起動バックオフの上限
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// 異常終了1回分の記録
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExitRecord {
+    pub at: DateTime<Utc>,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+/// 検知されたクラッシュループの情報
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrashLoopInfo {
+    pub detected_at: DateTime<Utc>,
+    /// ウィンドウ内の異常終了（古い順）
+    pub recent_exits: Vec<ExitRecord>,
+    pub suggestion: String,
+}
+
+/// プロセスごとの異常終了履歴
+#[derive(Debug, Clone, Default)]
+pub struct CrashTracker {
+    exits: VecDeque<ExitRecord>,
+    crash_loop: Option<CrashLoopInfo>,
+}
+
+impl CrashTracker {
+    /// 異常終了を記録し、新たにクラッシュループを検知した場合はその情報を返す
+    pub fn record_failure(&mut self, record: ExitRecord) -> Option<CrashLoopInfo> {
+        let now = record.at;
+        self.exits.push_back(record);
+        self.prune(now);
+
+        if self.crash_loop.is_some() || self.exits.len() < CRASH_LOOP_THRESHOLD {
+            return None;
+        }
+
+        let info = CrashLoopInfo {
+            detected_at: now,
+            recent_exits: self.exits.iter().cloned().collect(),
+            suggestion: format!(
+                "Process failed {} times within {}s. Automatic restarts are suspended; \
+                 inspect the output with get_process_output before restarting it.",
+                self.exits.len(),
+                CRASH_LOOP_WINDOW_SECS
+            ),
+        };
+        self.crash_loop = Some(info.clone());
+        Some(info)
+    }
+
+    /// 現在のクラッシュループ情報
+    pub fn crash_loop(&self) -> Option<&CrashLoopInfo> {
+        self.crash_loop.as_ref()
+    }
+
+    /// クラッシュループ状態を解除（手動起動時）
+    ///
+    /// 履歴は残すため、ウィンドウ内で再度失敗するとすぐに再検知される。
+    pub fn acknowledge(&mut self) {
+        self.crash_loop = None;
+    }
+
+    /// 次の自動再起動までに待つべき時間
+    pub fn backoff(&self, now: DateTime<Utc>) -> Duration {
+        let window_start = now - chrono::Duration::seconds(CRASH_LOOP_WINDOW_SECS);
+        let recent: Vec<&ExitRecord> = self.exits.iter().filter(|e| e.at >= window_start).collect();
+        let Some(last) = recent.last() else {
+            return Duration::ZERO;
+        };
+
+        let exponent = (recent.len() - 1).min(6) as u32;
+        let delay = Duration::from_secs(1 << exponent).min(MAX_BACKOFF);
+        let elapsed = (now - last.at).to_std().unwrap_or_default();
+        delay.saturating_sub(elapsed)
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let window_start = now - chrono::Duration::seconds(CRASH_LOOP_WINDOW_SECS);
+        while self.exits.front().is_some_and(|e| e.at < window_start) {
+            self.exits.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure_at(at: DateTime<Utc>) -> ExitRecord {
+        ExitRecord {
+            at,
+            exit_code: Some(1),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_detects_crash_loop_once() {
+        let mut tracker = CrashTracker::default();
+        let base = Utc::now();
+
+        for i in 0..CRASH_LOOP_THRESHOLD - 1 {
+            let at = base + chrono::Duration::seconds(i as i64);
+            assert!(tracker.record_failure(failure_at(at)).is_none());
+        }
+        let info = tracker
+            .record_failure(failure_at(base + chrono::Duration::seconds(10)))
+            .expect("crash loop should be detected");
+        assert_eq!(info.recent_exits.len(), CRASH_LOOP_THRESHOLD);
+
+        // 検知済みの間は再通知しない
+        assert!(
+            tracker
+                .record_failure(failure_at(base + chrono::Duration::seconds(11)))
+                .is_none()
+        );
+        assert!(tracker.crash_loop().is_some());
+    }
+
+    #[test]
+    fn test_failures_outside_window_are_ignored() {
+        let mut tracker = CrashTracker::default();
+        let base = Utc::now();
+
+        for i in 0..CRASH_LOOP_THRESHOLD {
+            let at = base + chrono::Duration::seconds(i as i64 * CRASH_LOOP_WINDOW_SECS);
+            assert!(tracker.record_failure(failure_at(at)).is_none());
+        }
+        assert!(tracker.crash_loop().is_none());
+    }
+
+    #[test]
+    fn test_backoff_grows_with_failures() {
+        let mut tracker = CrashTracker::default();
+        let base = Utc::now();
+        assert_eq!(tracker.backoff(base), Duration::ZERO);
+
+        tracker.record_failure(failure_at(base));
+        assert_eq!(tracker.backoff(base), Duration::from_secs(1));
+
+        tracker.record_failure(failure_at(base));
+        tracker.record_failure(failure_at(base));
+        assert_eq!(tracker.backoff(base), Duration::from_secs(4));
+    }
+}
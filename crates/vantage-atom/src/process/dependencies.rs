@@ -0,0 +1,229 @@
+//! プロセス間の依存関係（`depends_on`）
+//!
+//! `depends_on` を指定したプロセスを起動すると、実行中でない依存先を先に（依存先の依存先から）
+//! 起動し、それぞれの `ready` の条件（起動した時点・ヘルスチェックが正常・出力の行が正規表現に
+//! 一致）を満たしてから起動します。復元時の自動起動・一括起動では、依存先を依存元より前の組で
+//! 起動します。循環する依存関係はプロセスの作成時に拒否します。
+
+use super::startup;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use vantage_persistence::{DependencyReady, ProcessDependency, StartupOrder};
+
+/// 準備完了を待つ既定の秒数
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// プロセスID → 依存先
+pub type DependencyGraph = HashMap<String, Vec<ProcessDependency>>;
+
+/// 依存関係を検証（自分自身・重複・空のID・不正な正規表現を拒否する）
+pub fn validate(id: &str, dependencies: &[ProcessDependency]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for dependency in dependencies {
+        let process_id = dependency.process_id.as_str();
+        if process_id.trim().is_empty() {
+            return Err("depends_on.process_id must not be empty".to_string());
+        }
+        if process_id == id {
+            return Err(format!("Process '{id}' cannot depend on itself"));
+        }
+        if !seen.insert(process_id) {
+            return Err(format!("Duplicate dependency '{process_id}'"));
+        }
+        if dependency.timeout_secs == Some(0) {
+            return Err("depends_on.timeout_secs must be greater than 0".to_string());
+        }
+        if let DependencyReady::LogPattern { pattern } = &dependency.ready {
+            compile(pattern)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid dependency pattern '{pattern}': {e}"))
+}
+
+/// 循環する依存関係を探す（見つかれば `a -> b -> a` の形の経路を返す）
+pub fn find_cycle(graph: &DependencyGraph) -> Option<String> {
+    fn visit<'a>(
+        id: &'a str,
+        graph: &'a DependencyGraph,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Option<String> {
+        if let Some(start) = path.iter().position(|visiting| *visiting == id) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(id);
+            return Some(cycle.join(" -> "));
+        }
+        if done.contains(id) {
+            return None;
+        }
+        path.push(id);
+        for dependency in graph.get(id).into_iter().flatten() {
+            if let Some(cycle) = visit(&dependency.process_id, graph, path, done) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        done.insert(id);
+        None
+    }
+
+    let mut ids: Vec<&String> = graph.keys().collect();
+    ids.sort();
+    let mut done = HashSet::new();
+    ids.into_iter()
+        .find_map(|id| visit(id, graph, &mut Vec::new(), &mut done))
+}
+
+/// `id` を起動するために起動する順（依存先が先で、最後が `id`）
+pub fn start_order(id: &str, graph: &DependencyGraph) -> Result<Vec<String>, String> {
+    fn visit(
+        id: &str,
+        graph: &DependencyGraph,
+        visiting: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), String> {
+        if order.iter().any(|done| done == id) {
+            return Ok(());
+        }
+        if visiting.iter().any(|v| v == id) {
+            return Err(format!(
+                "Dependency cycle: {} -> {id}",
+                visiting.join(" -> ")
+            ));
+        }
+        let dependencies = graph.get(id).ok_or_else(|| match visiting.last() {
+            Some(dependent) => format!("Dependency '{id}' of '{dependent}' not found"),
+            None => format!("Process '{id}' not found"),
+        })?;
+        visiting.push(id.to_string());
+        for dependency in dependencies {
+            visit(&dependency.process_id, graph, visiting, order)?;
+        }
+        visiting.pop();
+        order.push(id.to_string());
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    visit(id, graph, &mut Vec::new(), &mut order)?;
+    Ok(order)
+}
+
+/// 依存関係の深さ（依存先のないプロセスは0、ほかは依存先の最大の深さ+1）
+fn depth(id: &str, graph: &DependencyGraph, memo: &mut HashMap<String, usize>) -> usize {
+    if let Some(depth) = memo.get(id) {
+        return *depth;
+    }
+    // 循環していても止まるよう、計算中は0とみなす
+    memo.insert(id.to_string(), 0);
+    let depth = graph
+        .get(id)
+        .into_iter()
+        .flatten()
+        .map(|dependency| depth(&dependency.process_id, graph, memo) + 1)
+        .max()
+        .unwrap_or(0);
+    memo.insert(id.to_string(), depth);
+    depth
+}
+
+/// 起動順序に依存関係を加えて、まとめて起動する単位に分ける（起動する順に並べる）
+///
+/// 依存関係の深さごとに `startup::plan` で分けるため、依存先は常に依存元より前の組になる。
+/// 依存関係がなければ `startup::plan` と同じ。
+pub fn plan(
+    members: Vec<(String, StartupOrder)>,
+    graph: &DependencyGraph,
+) -> Vec<Vec<(String, StartupOrder)>> {
+    let mut memo = HashMap::new();
+    let mut levels: Vec<Vec<(String, StartupOrder)>> = Vec::new();
+    for member in members {
+        let level = depth(&member.0, graph, &mut memo);
+        if levels.len() <= level {
+            levels.resize_with(level + 1, Vec::new);
+        }
+        levels[level].push(member);
+    }
+    levels.into_iter().flat_map(startup::plan).collect()
+}
+
+/// 条件の説明（エラーとログ用）
+pub fn describe(ready: &DependencyReady) -> String {
+    match ready {
+        DependencyReady::Started => "running".to_string(),
+        DependencyReady::Healthy => "healthy".to_string(),
+        DependencyReady::LogPattern { pattern } => format!("output matching '{pattern}'"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &[&str])]) -> DependencyGraph {
+        edges
+            .iter()
+            .map(|(id, dependencies)| {
+                (
+                    id.to_string(),
+                    dependencies
+                        .iter()
+                        .map(|process_id| ProcessDependency {
+                            process_id: process_id.to_string(),
+                            ready: DependencyReady::Started,
+                            timeout_secs: None,
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_start_order_puts_dependencies_first() {
+        let graph = graph(&[
+            ("web", &["api"]),
+            ("api", &["db", "cache"]),
+            ("worker", &["db"]),
+            ("db", &[]),
+            ("cache", &[]),
+        ]);
+        assert_eq!(
+            start_order("web", &graph).unwrap(),
+            vec!["db", "cache", "api", "web"]
+        );
+        assert!(find_cycle(&graph).is_none());
+
+        let ids = |batches: Vec<Vec<(String, StartupOrder)>>| -> Vec<Vec<String>> {
+            batches
+                .into_iter()
+                .map(|batch| batch.into_iter().map(|(id, _)| id).collect())
+                .collect()
+        };
+        let members = ["web", "worker", "db", "api"]
+            .iter()
+            .map(|id| (id.to_string(), StartupOrder::default()))
+            .collect();
+        assert_eq!(
+            ids(plan(members, &graph)),
+            vec![vec!["db"], vec!["api"], vec!["worker"], vec!["web"]]
+        );
+    }
+
+    #[test]
+    fn test_cycles_and_missing_dependencies_are_reported() {
+        let cyclic = graph(&[("a", &["b"]), ("b", &["c"]), ("c", &["a"])]);
+        assert_eq!(find_cycle(&cyclic).unwrap(), "a -> b -> c -> a");
+        assert!(start_order("a", &cyclic).is_err());
+
+        let missing = graph(&[("api", &["db"])]);
+        assert_eq!(
+            start_order("api", &missing).unwrap_err(),
+            "Dependency 'db' of 'api' not found"
+        );
+    }
+}
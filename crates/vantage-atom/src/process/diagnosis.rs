@@ -0,0 +1,354 @@
+//! エラー出力の診断とMCPサンプリングによるルールの提案
+//!
+//! プロセスのstderrの抜粋を診断ルール（正規表現のシグネチャと分類・対処）に照らします。
+//! どのルールにも一致しない未知のシグネチャは、クライアントが許可していればMCPの
+//! サンプリングで接続中のLLMに分類と対処を尋ね、その回答を確認待ちのルール候補として
+//! 保存します。候補はユーザーが承認するまで診断に使いません。
+//!
+//! LLMに送る抜粋はシークレットを伏せ字にしたもので、行数と文字数にも上限があります。
+
+use super::debug_bundle::redact_text;
+use super::port_owner::PortOwner;
+use chrono::{DateTime, Utc};
+use regex::{NoExpand, Regex};
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use vantage_persistence::{DiagnosisRule, DiagnosisRuleSource, DiagnosisRuleStatus};
+
+/// 抜粋に含める最大行数
+const EXCERPT_MAX_LINES: usize = 20;
+/// 抜粋の最大文字数
+const EXCERPT_MAX_CHARS: usize = 4000;
+/// 未知のシグネチャから作るパターンの元にする最大文字数
+const SIGNATURE_MAX_CHARS: usize = 120;
+/// LLMが提案したパターンの最大文字数
+const PATTERN_MAX_CHARS: usize = 300;
+
+static DIGITS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+").unwrap());
+static ERROR_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(error|exception|fatal|panic|failed)\b").unwrap());
+
+/// 組み込みのルール: (ID, パターン, 分類, 原因, 対処)
+const BUILT_IN_RULES: &[(&str, &str, &str, &str, &str)] = &[
+    (
+        "builtin-port-in-use",
+        r"(?i)EADDRINUSE|address already in use|port \d+ is (already )?in use",
+        "port_in_use",
+        "The port the process listens on is already taken by another process",
+        "Stop the process holding the port (see port_owners for who it is and how to stop it) or change the port in the process's args/env",
+    ),
+    (
+        "builtin-command-not-found",
+        r"(?i)command not found|No such file or directory \(os error 2\)|ENOENT.*spawn",
+        "command_not_found",
+        "The command or a binary it runs could not be found",
+        "Check the command path and that the tool is installed and on PATH",
+    ),
+    (
+        "builtin-missing-module",
+        r"Cannot find module|ModuleNotFoundError|ImportError: |cannot load such file",
+        "missing_dependency",
+        "A dependency module is not installed",
+        "Install the project's dependencies (e.g. npm install, pip install -r requirements.txt, bundle install)",
+    ),
+    (
+        "builtin-permission-denied",
+        r"(?i)permission denied|EACCES|Operation not permitted",
+        "permission_denied",
+        "The process lacks permission for a file, directory or port",
+        "Check file ownership and modes, or use a port above 1024",
+    ),
+    (
+        "builtin-connection-refused",
+        r"(?i)ECONNREFUSED|connection refused",
+        "dependency_unavailable",
+        "A service the process connects to is not accepting connections",
+        "Start the dependency first (e.g. with a chain or depends_on) and check its host and port",
+    ),
+    (
+        "builtin-out-of-memory",
+        r"(?i)out of memory|JavaScript heap out of memory|MemoryError|Cannot allocate memory",
+        "out_of_memory",
+        "The process ran out of memory",
+        "Raise the memory limit or reduce the workload (e.g. NODE_OPTIONS=--max-old-space-size)",
+    ),
+    (
+        "builtin-missing-env",
+        r"(?i)(environment variable|env var)\S* .*(not set|missing|required)|KeyError: '[A-Z_]+'",
+        "missing_configuration",
+        "A required environment variable is not set",
+        "Set the variable in the process's env (see diff_process_env)",
+    ),
+];
+
+/// 組み込みのルール
+pub fn built_in_rules() -> Vec<DiagnosisRule> {
+    BUILT_IN_RULES
+        .iter()
+        .map(|(rule_id, pattern, category, summary, fix)| DiagnosisRule {
+            rule_id: rule_id.to_string(),
+            pattern: pattern.to_string(),
+            category: category.to_string(),
+            summary: summary.to_string(),
+            fix: fix.to_string(),
+            source: DiagnosisRuleSource::BuiltIn,
+            status: DiagnosisRuleStatus::Active,
+            example: None,
+            model: None,
+            created_at: DateTime::<Utc>::UNIX_EPOCH,
+        })
+        .collect()
+}
+
+/// エラー出力の抜粋を作る（末尾の空でない行を、シークレットを伏せ字にして返す）
+pub fn error_excerpt(lines: &[String]) -> Vec<String> {
+    let mut excerpt: Vec<String> = lines
+        .iter()
+        .rev()
+        .filter(|line| !line.trim().is_empty())
+        .take(EXCERPT_MAX_LINES)
+        .map(|line| redact_text(line))
+        .collect();
+    excerpt.reverse();
+
+    // 文字数の上限を超えたら古い行から落とす
+    let mut total: usize = excerpt.iter().map(|l| l.chars().count() + 1).sum();
+    while total > EXCERPT_MAX_CHARS && excerpt.len() > 1 {
+        total -= excerpt.remove(0).chars().count() + 1;
+    }
+    if let Some(line) = excerpt.first_mut()
+        && line.chars().count() > EXCERPT_MAX_CHARS
+    {
+        *line = line.chars().take(EXCERPT_MAX_CHARS).collect();
+    }
+    excerpt
+}
+
+/// 一致したルール
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosisMatch {
+    pub rule_id: String,
+    pub category: String,
+    pub summary: String,
+    pub fix: String,
+    pub source: DiagnosisRuleSource,
+    /// 一致した行
+    pub line: String,
+}
+
+/// 有効なルールを抜粋に照らす（ルールごとに最初に一致した行）
+pub fn diagnose(excerpt: &[String], rules: &[DiagnosisRule]) -> Vec<DiagnosisMatch> {
+    rules
+        .iter()
+        .filter(|rule| rule.status == DiagnosisRuleStatus::Active)
+        .filter_map(|rule| {
+            let regex = Regex::new(&rule.pattern).ok()?;
+            let line = excerpt.iter().find(|line| regex.is_match(line))?;
+            Some(DiagnosisMatch {
+                rule_id: rule.rule_id.clone(),
+                category: rule.category.clone(),
+                summary: rule.summary.clone(),
+                fix: rule.fix.clone(),
+                source: rule.source,
+                line: line.clone(),
+            })
+        })
+        .collect()
+}
+
+/// プロセスの診断結果
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosisReport {
+    pub process_id: String,
+    /// 診断したstderrの抜粋（シークレットは伏せ字）
+    pub excerpt: Vec<String>,
+    pub matches: Vec<DiagnosisMatch>,
+    /// エラー出力があるのに、どのルールにも一致しない
+    pub unknown_signature: bool,
+    /// 競合したポートを使用しているもの（ポートが使用中の場合）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub port_owners: Vec<PortOwner>,
+    /// サンプリングで作った確認待ちのルール候補
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidate: Option<DiagnosisRule>,
+    /// サンプリングを行わなかった・失敗した理由
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling_note: Option<String>,
+}
+
+impl DiagnosisReport {
+    pub fn new(process_id: String, excerpt: Vec<String>, rules: &[DiagnosisRule]) -> Self {
+        let matches = diagnose(&excerpt, rules);
+        Self {
+            unknown_signature: !excerpt.is_empty() && matches.is_empty(),
+            process_id,
+            excerpt,
+            matches,
+            port_owners: Vec::new(),
+            candidate: None,
+            sampling_note: None,
+        }
+    }
+
+    /// ポートの競合を示すルールに一致した行から、競合したポート
+    pub fn conflicting_ports(&self) -> Vec<u16> {
+        let mut ports = Vec::new();
+        for m in self.matches.iter().filter(|m| m.category == "port_in_use") {
+            for port in super::port_owner::conflict_ports(&m.line) {
+                if !ports.contains(&port) {
+                    ports.push(port);
+                }
+            }
+        }
+        ports
+    }
+}
+
+/// サンプリングで送るシステムプロンプト
+pub const SAMPLING_SYSTEM_PROMPT: &str = "You classify error output from local development processes. \
+Reply with a single JSON object and nothing else: \
+{\"category\": \"short_snake_case_category\", \"summary\": \"one sentence root cause\", \
+\"fix\": \"one or two sentences describing the fix\", \
+\"pattern\": \"a Rust regex matching the distinctive error line, without volatile values such as ports, paths or timestamps\"}";
+
+/// サンプリングで送るメッセージ
+pub fn sampling_prompt(process_id: &str, command: Option<&str>, excerpt: &[String]) -> String {
+    let mut prompt = format!("Process '{process_id}'");
+    if let Some(command) = command {
+        prompt.push_str(&format!(" (command: {})", redact_text(command)));
+    }
+    prompt.push_str(" failed with this stderr excerpt:\n\n");
+    prompt.push_str(&excerpt.join("\n"));
+    prompt
+}
+
+/// LLMの回答
+#[derive(Debug, Clone, Deserialize)]
+struct SampledClassification {
+    category: String,
+    summary: String,
+    fix: String,
+    #[serde(default)]
+    pattern: Option<String>,
+}
+
+/// LLMの回答を読み、確認待ちのルール候補を作る
+///
+/// 提案されたパターンが不正・長すぎる・抜粋に一致しない場合は、抜粋のエラー行から
+/// 数字を除いたシグネチャを代わりに使う。
+pub fn candidate_from_reply(
+    reply: &str,
+    excerpt: &[String],
+    model: Option<String>,
+) -> Result<DiagnosisRule, String> {
+    let start = reply.find('{');
+    let end = reply.rfind('}');
+    let json = match (start, end) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => return Err("The sampled reply did not contain a JSON object".to_string()),
+    };
+    let classification: SampledClassification = serde_json::from_str(json)
+        .map_err(|e| format!("Failed to parse the sampled reply: {e}"))?;
+    if classification.summary.trim().is_empty() || classification.fix.trim().is_empty() {
+        return Err("The sampled reply is missing a summary or fix".to_string());
+    }
+
+    let pattern = classification
+        .pattern
+        .filter(|pattern| pattern.chars().count() <= PATTERN_MAX_CHARS)
+        .filter(|pattern| {
+            Regex::new(pattern).is_ok_and(|regex| excerpt.iter().any(|line| regex.is_match(line)))
+        })
+        .or_else(|| signature_pattern(excerpt))
+        .ok_or_else(|| "Cannot build a pattern from an empty excerpt".to_string())?;
+
+    Ok(DiagnosisRule {
+        rule_id: format!("sampled-{}", vantage_persistence::generate_id()),
+        pattern,
+        category: normalize_category(&classification.category),
+        summary: classification.summary.trim().to_string(),
+        fix: classification.fix.trim().to_string(),
+        source: DiagnosisRuleSource::Sampling,
+        status: DiagnosisRuleStatus::Pending,
+        example: Some(excerpt.join("\n")),
+        model,
+        created_at: Utc::now(),
+    })
+}
+
+/// 抜粋のエラー行（なければ最後の行）から、数字を除いたシグネチャのパターンを作る
+fn signature_pattern(excerpt: &[String]) -> Option<String> {
+    let line = excerpt
+        .iter()
+        .find(|line| ERROR_LINE.is_match(line))
+        .or_else(|| excerpt.last())?;
+    let line: String = line.trim().chars().take(SIGNATURE_MAX_CHARS).collect();
+    Some(
+        DIGITS
+            .replace_all(&regex::escape(&line), NoExpand(r"\d+"))
+            .into_owned(),
+    )
+}
+
+fn normalize_category(category: &str) -> String {
+    let normalized: String = category
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .take(40)
+        .collect();
+    let normalized = normalized.trim_matches('_').to_string();
+    if normalized.is_empty() {
+        "unclassified".to_string()
+    } else {
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_signature_becomes_pending_candidate() {
+        let lines: Vec<String> = vec![
+            "starting worker 3".to_string(),
+            "".to_string(),
+            "Error: listen EADDRINUSE: address already in use :::3000".to_string(),
+        ];
+        let report =
+            DiagnosisReport::new("web".to_string(), error_excerpt(&lines), &built_in_rules());
+        assert_eq!(report.excerpt.len(), 2);
+        assert_eq!(report.matches[0].category, "port_in_use");
+        assert_eq!(report.conflicting_ports(), vec![3000]);
+        assert!(!report.unknown_signature);
+
+        let excerpt = error_excerpt(&[
+            "fatal: quota 42 exceeded for bucket token=abc123secretvalue".to_string(),
+        ]);
+        assert!(!excerpt[0].contains("abc123secretvalue"), "{excerpt:?}");
+        let report = DiagnosisReport::new("sync".to_string(), excerpt.clone(), &built_in_rules());
+        assert!(report.unknown_signature);
+
+        // 一致しないパターンはシグネチャに置き換える
+        let reply = "Sure:\n{\"category\": \"Quota Exceeded\", \"summary\": \"Bucket quota hit\", \"fix\": \"Raise the quota\", \"pattern\": \"nothing like this\"}";
+        let candidate = candidate_from_reply(reply, &excerpt, Some("model-x".to_string())).unwrap();
+        assert_eq!(candidate.status, DiagnosisRuleStatus::Pending);
+        assert_eq!(candidate.category, "quota_exceeded");
+        let regex = Regex::new(&candidate.pattern).unwrap();
+        assert!(
+            regex.is_match(&excerpt[0].replace("42", "7")),
+            "{}",
+            candidate.pattern
+        );
+
+        // 確認待ちのルールは診断に使わず、承認すると使う
+        let mut rules = vec![candidate];
+        assert!(diagnose(&excerpt, &rules).is_empty());
+        rules[0].status = DiagnosisRuleStatus::Active;
+        assert_eq!(diagnose(&excerpt, &rules).len(), 1);
+
+        assert!(candidate_from_reply("no json here", &excerpt, None).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+//! リポジトリからのタスクの検出
+//!
+//! `package.json` のscripts、`Makefile` のターゲット、READMEのシェルのコードブロックに書かれた
+//! 起動コマンドを読み取り、プロセス・テンプレートの候補として提案します。
+//! 同じコマンドが複数の場所に書かれている場合は、package.json、Makefile、READMEの順に優先します。
+
+use super::vscode_tasks::{slug, unique_id};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+/// 取り込み元の種類（`ImportSource::kind`）
+pub const SOURCE_KIND: &str = "discovered";
+
+/// 読み取るREADMEのファイル名
+const README_FILES: &[&str] = &["README.md", "README.markdown", "README", "readme.md"];
+/// READMEのコマンドのうち、起動・ビルド・テストに使うものとみなす接頭辞
+const README_RUNNERS: &[&str] = &[
+    "npm run ",
+    "npm start",
+    "npm test",
+    "pnpm ",
+    "yarn ",
+    "bun run ",
+    "cargo run",
+    "cargo watch",
+    "cargo test",
+    "make ",
+    "just ",
+    "docker compose up",
+    "docker-compose up",
+    "python -m ",
+    "python3 -m ",
+    "uvicorn ",
+    "flask run",
+    "go run ",
+    "deno task ",
+    "bundle exec ",
+    "rails server",
+];
+/// 起動したまま動き続けるコマンドとみなす語
+const LONG_RUNNING_HINTS: &[&str] = &["dev", "serve", "server", "start", "watch", "up", "preview"];
+
+/// 検出した候補
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiscoveredTask {
+    /// プロセスID・テンプレートIDの候補
+    pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub cwd: String,
+    pub description: String,
+    /// 検出元（例: `package.json#scripts.dev`、`Makefile:12`）
+    pub source: String,
+    /// サーバーやウォッチャーなど、起動したまま動き続けるコマンドか
+    pub long_running: bool,
+    /// 同じコマンドと作業ディレクトリのプロセスがすでに登録されているか
+    pub already_registered: bool,
+}
+
+/// 検出結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiscoveryReport {
+    pub workspace: String,
+    /// 読み取ったファイル
+    pub scanned: Vec<String>,
+    pub candidates: Vec<DiscoveredTask>,
+}
+
+/// コマンドと作業ディレクトリ以外が決まっていない候補
+struct Candidate {
+    name: String,
+    command: String,
+    args: Vec<String>,
+    description: String,
+    source: String,
+}
+
+/// ワークスペースのファイルを読み取って候補を作る
+pub fn discover(workspace: &Path) -> DiscoveryReport {
+    let cwd = workspace.to_string_lossy().to_string();
+    let mut report = DiscoveryReport {
+        workspace: cwd.clone(),
+        ..Default::default()
+    };
+    let mut candidates = Vec::new();
+
+    if let Ok(contents) = std::fs::read_to_string(workspace.join("package.json")) {
+        report.scanned.push("package.json".to_string());
+        candidates.extend(package_scripts(&contents, package_manager(workspace)));
+    }
+    for makefile in ["Makefile", "makefile", "GNUmakefile"] {
+        if let Ok(contents) = std::fs::read_to_string(workspace.join(makefile)) {
+            report.scanned.push(makefile.to_string());
+            candidates.extend(make_targets(&contents, makefile));
+            break;
+        }
+    }
+    for readme in README_FILES {
+        if let Ok(contents) = std::fs::read_to_string(workspace.join(readme)) {
+            report.scanned.push(readme.to_string());
+            candidates.extend(readme_commands(&contents, readme));
+            break;
+        }
+    }
+
+    let mut taken = Vec::new();
+    let mut seen = Vec::new();
+    for candidate in candidates {
+        let line = command_line(&candidate.command, &candidate.args);
+        if seen.contains(&line) {
+            continue;
+        }
+        let id = unique_id(&slug(&candidate.name), &taken);
+        taken.push(id.clone());
+        report.candidates.push(DiscoveredTask {
+            id,
+            long_running: is_long_running(&candidate.name, &line),
+            command: candidate.command,
+            args: candidate.args,
+            cwd: cwd.clone(),
+            description: candidate.description,
+            source: candidate.source,
+            already_registered: false,
+        });
+        seen.push(line);
+    }
+    report
+}
+
+/// ロックファイルからパッケージマネージャーを決める
+fn package_manager(workspace: &Path) -> &'static str {
+    [
+        ("pnpm-lock.yaml", "pnpm"),
+        ("yarn.lock", "yarn"),
+        ("bun.lockb", "bun"),
+        ("bun.lock", "bun"),
+    ]
+    .into_iter()
+    .find(|(lockfile, _)| workspace.join(lockfile).exists())
+    .map_or("npm", |(_, manager)| manager)
+}
+
+/// `package.json` のscripts（`pre`/`post` フックは除く、名前順）
+///
+/// serde_jsonの `preserve_order` が有効かどうかでキーの順序が変わるため、名前で並べ直す。
+fn package_scripts(contents: &str, manager: &str) -> Vec<Candidate> {
+    let Ok(package) = serde_json::from_str::<Value>(contents) else {
+        return Vec::new();
+    };
+    let Some(scripts) = package.get("scripts").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let names: Vec<&String> = scripts.keys().collect();
+    let mut candidates = scripts
+        .iter()
+        .filter(|(name, _)| {
+            !["pre", "post"].iter().any(|hook| {
+                name.strip_prefix(hook)
+                    .is_some_and(|target| names.iter().any(|n| n.as_str() == target))
+            })
+        })
+        .filter_map(|(name, script)| {
+            Some(Candidate {
+                name: name.clone(),
+                command: manager.to_string(),
+                args: vec!["run".to_string(), name.clone()],
+                description: script.as_str()?.to_string(),
+                source: format!("package.json#scripts.{name}"),
+            })
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| a.name.cmp(&b.name));
+    candidates
+}
+
+/// `Makefile` のターゲット
+///
+/// `target: ## 説明` 形式のコメント、またはターゲットの直前のコメント行を説明にする。
+fn make_targets(contents: &str, file_name: &str) -> Vec<Candidate> {
+    let mut targets = Vec::new();
+    let mut comment: Option<String> = None;
+    for (index, line) in contents.lines().enumerate() {
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim_start_matches('#').trim().to_string());
+            continue;
+        }
+        let Some((names, rest)) = line.split_once(':') else {
+            comment = None;
+            continue;
+        };
+        // 変数の代入、レシピ行、特殊ターゲット・パターンルールは除く
+        if rest.starts_with('=') || names.contains('=') || line.starts_with(char::is_whitespace) {
+            comment = None;
+            continue;
+        }
+        let inline = rest
+            .split_once("##")
+            .map(|(_, text)| text.trim().to_string());
+        for name in names.split_whitespace() {
+            if name.starts_with('.') || name.contains(['%', '$', '/']) {
+                continue;
+            }
+            targets.push(Candidate {
+                name: name.to_string(),
+                command: "make".to_string(),
+                args: vec![name.to_string()],
+                description: inline
+                    .clone()
+                    .or_else(|| comment.clone())
+                    .unwrap_or_else(|| format!("make {name}")),
+                source: format!("{file_name}:{}", index + 1),
+            });
+        }
+        comment = None;
+    }
+    targets
+}
+
+/// READMEのシェルのコードブロックに書かれた起動コマンド
+///
+/// 直前の見出しを説明にする。パイプやリダイレクト、クォートを含むコマンドは対象外。
+fn readme_commands(contents: &str, file_name: &str) -> Vec<Candidate> {
+    let mut commands = Vec::new();
+    let mut heading = String::new();
+    let mut in_shell_block = None;
+    for (index, line) in contents.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some(fence) = trimmed.strip_prefix("```") {
+            in_shell_block = match in_shell_block {
+                Some(_) => None,
+                None => Some(matches!(
+                    fence.trim(),
+                    "" | "sh" | "bash" | "shell" | "console" | "zsh"
+                )),
+            };
+            continue;
+        }
+        match in_shell_block {
+            None => {
+                if let Some(text) = trimmed.strip_prefix('#') {
+                    heading = text.trim_start_matches('#').trim().to_string();
+                }
+            }
+            Some(false) => {}
+            Some(true) => {
+                let command = trimmed.trim_start_matches("$ ");
+                let command = command.split(" #").next().unwrap_or(command).trim();
+                if !README_RUNNERS
+                    .iter()
+                    .any(|runner| command.starts_with(runner))
+                    || command.contains(['|', '&', ';', '>', '<', '`', '"', '\''])
+                {
+                    continue;
+                }
+                let mut words = command.split_whitespace().map(str::to_string);
+                let Some(program) = words.next() else {
+                    continue;
+                };
+                commands.push(Candidate {
+                    name: command.to_string(),
+                    command: program,
+                    args: words.collect(),
+                    description: if heading.is_empty() {
+                        command.to_string()
+                    } else {
+                        heading.clone()
+                    },
+                    source: format!("{file_name}:{}", index + 1),
+                });
+            }
+        }
+    }
+    commands
+}
+
+fn command_line(command: &str, args: &[String]) -> String {
+    std::iter::once(command)
+        .chain(args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_long_running(name: &str, line: &str) -> bool {
+    let words = |s: &str| {
+        s.split(|c: char| !c.is_ascii_alphanumeric())
+            .map(str::to_ascii_lowercase)
+            .collect::<Vec<_>>()
+    };
+    let name_words = words(name);
+    LONG_RUNNING_HINTS
+        .iter()
+        .any(|hint| name_words.iter().any(|w| w == hint))
+        || line.contains("--watch")
+        || line.starts_with("cargo run")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_scripts() {
+        let package = r#"{
+            "scripts": {
+                "dev": "vite --port 5173",
+                "build": "vite build",
+                "prebuild": "rm -rf dist",
+                "lint": "eslint ."
+            }
+        }"#;
+        let scripts = package_scripts(package, "pnpm");
+        let names: Vec<_> = scripts.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "dev", "lint"]);
+        let dev = &scripts[1];
+        assert_eq!(dev.command, "pnpm");
+        assert_eq!(dev.args, vec!["run", "dev"]);
+        assert_eq!(dev.description, "vite --port 5173");
+        assert_eq!(dev.source, "package.json#scripts.dev");
+    }
+
+    #[test]
+    fn test_make_targets() {
+        let makefile = "\
+CARGO := cargo
+.PHONY: test serve
+
+# Run the test suite
+test:
+\t$(CARGO) test
+
+serve: build ## Start the dev server
+\t./bin/serve
+
+%.o: %.c
+\tcc -c $<
+";
+        let targets = make_targets(makefile, "Makefile");
+        let summary: Vec<_> = targets
+            .iter()
+            .map(|c| (c.name.as_str(), c.description.as_str(), c.source.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("test", "Run the test suite", "Makefile:5"),
+                ("serve", "Start the dev server", "Makefile:8"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_readme_commands() {
+        let readme = "\
+# App
+
+## Development
+
+```bash
+npm install
+$ npm run dev -- --host  # starts on :5173
+cargo build && cargo run
+```
+
+```python
+python -m http.server
+```
+";
+        let commands = readme_commands(readme, "README.md");
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].command, "npm");
+        assert_eq!(commands[0].args, vec!["run", "dev", "--", "--host"]);
+        assert_eq!(commands[0].description, "Development");
+        assert_eq!(commands[0].source, "README.md:7");
+    }
+
+    #[test]
+    fn test_discover_deduplicates_and_marks_long_running() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"scripts": {"dev": "vite", "test": "vitest run"}}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("yarn.lock"), "").unwrap();
+        std::fs::write(
+            dir.path().join("README.md"),
+            "## Run\n\n```sh\nyarn run dev\nmake test\n```\n",
+        )
+        .unwrap();
+
+        let report = discover(dir.path());
+        assert_eq!(report.scanned, vec!["package.json", "README.md"]);
+        let summary: Vec<_> = report
+            .candidates
+            .iter()
+            .map(|c| (c.id.as_str(), c.long_running))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("dev", true), ("test", false), ("make-test", false)]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! CPU時間の積算と消費電力量・電気代の見積もり
+//!
+//! 実行中のプロセスが使ったCPU時間を定期的に記録し、再起動をまたいで積算します。
+//! 積算したCPU秒に設定の係数（[`EnergyCostSettings`]）を掛けて消費電力量と電気代を
+//! 見積もり、常時起動しているプロセスを止める判断材料にします。計測の対象は起動した
+//! プロセス自身のCPU時間で、積算はサーバーの起動中のみ保持します。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use vantage_persistence::EnergyCostSettings;
+
+/// 1kWhあたりのワット秒
+const WATT_SECONDS_PER_KWH: f64 = 3_600_000.0;
+
+/// プロセスのCPU時間の積算
+#[derive(Debug, Clone, Default)]
+pub struct CpuAccounting {
+    /// 終了した実行のCPU時間の合計（ミリ秒）
+    completed_ms: u64,
+    /// 実行中のプロセスのPIDと、最後に記録したCPU時間（ミリ秒）
+    current: Option<(u32, u64)>,
+    /// 最初に記録した時刻
+    since: Option<DateTime<Utc>>,
+}
+
+impl CpuAccounting {
+    /// 実行中のプロセスの累積CPU時間を記録する
+    ///
+    /// PIDが変わった場合は再起動とみなし、前回の実行の分を合計に移す。
+    pub fn record(&mut self, pid: u32, cpu_ms: u64, now: DateTime<Utc>) {
+        match &mut self.current {
+            Some((current_pid, last_ms)) if *current_pid == pid => {
+                *last_ms = (*last_ms).max(cpu_ms);
+            }
+            current => {
+                if let Some((_, last_ms)) = current.take() {
+                    self.completed_ms += last_ms;
+                }
+                *current = Some((pid, cpu_ms));
+            }
+        }
+        self.since.get_or_insert(now);
+    }
+
+    /// 積算したCPU秒
+    pub fn cpu_seconds(&self) -> f64 {
+        let current_ms = self.current.map(|(_, ms)| ms).unwrap_or(0);
+        (self.completed_ms + current_ms) as f64 / 1000.0
+    }
+
+    /// 一度も記録していなければ `None`
+    pub fn usage(&self, settings: &EnergyCostSettings) -> Option<ResourceUsage> {
+        let since = self.since?;
+        Some(ResourceUsage::estimate(self.cpu_seconds(), since, settings))
+    }
+}
+
+/// CPU時間と見積もった消費電力量・電気代
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResourceUsage {
+    /// 積算したCPU秒（複数コアを使うと経過時間より大きくなる）
+    pub cpu_seconds: f64,
+    /// 見積もった消費電力量（Wh）
+    pub energy_wh: f64,
+    /// 見積もった電気代
+    pub estimated_cost: f64,
+    pub currency: String,
+    /// 積算を始めた時刻
+    pub tracked_since: DateTime<Utc>,
+}
+
+impl ResourceUsage {
+    pub fn estimate(
+        cpu_seconds: f64,
+        tracked_since: DateTime<Utc>,
+        settings: &EnergyCostSettings,
+    ) -> Self {
+        let energy_kwh = cpu_seconds * settings.watts_per_core / WATT_SECONDS_PER_KWH;
+        Self {
+            cpu_seconds,
+            energy_wh: energy_kwh * 1000.0,
+            estimated_cost: energy_kwh * settings.price_per_kwh,
+            currency: settings.currency.clone(),
+            tracked_since,
+        }
+    }
+}
+
+/// プロセスごとの使用量
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessResourceUsage {
+    pub process_id: String,
+    pub running: bool,
+    #[serde(flatten)]
+    pub usage: ResourceUsage,
+}
+
+/// すべてのプロセスの使用量と合計
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceOverview {
+    /// 使用量の多い順
+    pub processes: Vec<ProcessResourceUsage>,
+    pub total: Option<ResourceUsage>,
+    /// 見積もりに使った係数
+    pub coefficients: EnergyCostSettings,
+}
+
+impl ResourceOverview {
+    pub fn new(mut processes: Vec<ProcessResourceUsage>, coefficients: EnergyCostSettings) -> Self {
+        processes.sort_by(|a, b| {
+            b.usage
+                .cpu_seconds
+                .total_cmp(&a.usage.cpu_seconds)
+                .then_with(|| a.process_id.cmp(&b.process_id))
+        });
+        let total = processes
+            .iter()
+            .map(|p| p.usage.tracked_since)
+            .min()
+            .map(|since| {
+                let cpu_seconds = processes.iter().map(|p| p.usage.cpu_seconds).sum();
+                ResourceUsage::estimate(cpu_seconds, since, &coefficients)
+            });
+        Self {
+            processes,
+            total,
+            coefficients,
+        }
+    }
+}
+
+/// 係数を検証する
+pub fn validate(settings: &EnergyCostSettings) -> Result<(), String> {
+    for (name, value) in [
+        ("watts_per_core", settings.watts_per_core),
+        ("price_per_kwh", settings.price_per_kwh),
+    ] {
+        if !value.is_finite() || value < 0.0 {
+            return Err(format!("energy_cost.{name} must be a non-negative number"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounting_survives_restarts_and_estimates_cost() {
+        let now = Utc::now();
+        let mut accounting = CpuAccounting::default();
+        assert!(accounting.usage(&EnergyCostSettings::default()).is_none());
+
+        accounting.record(100, 1_000_000, now);
+        accounting.record(100, 1_800_000, now);
+        // 再起動で新しいPIDになる
+        accounting.record(200, 1_800_000, now);
+        assert_eq!(accounting.cpu_seconds(), 3600.0);
+
+        let settings = EnergyCostSettings {
+            watts_per_core: 20.0,
+            price_per_kwh: 0.5,
+            currency: "EUR".to_string(),
+        };
+        let usage = accounting.usage(&settings).unwrap();
+        assert!((usage.energy_wh - 20.0).abs() < 1e-9);
+        assert!((usage.estimated_cost - 0.01).abs() < 1e-9);
+        assert_eq!(usage.tracked_since, now);
+
+        assert!(validate(&settings).is_ok());
+        assert!(
+            validate(&EnergyCostSettings {
+                watts_per_core: -1.0,
+                ..settings
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! 定義と実行中プロセスの環境変数の差分
+//!
+//! プロセスの起動時に子プロセスへ渡した環境変数を記録しておき、その後に編集された定義と比べます。
+//! 値はシークレットとみなせるものを伏せて返します。
+
+use super::capture::{is_secret_key, looks_like_secret_value};
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// 伏せた値の表示
+const MASKED_VALUE: &str = "********";
+
+/// 値が異なる環境変数
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct EnvChange {
+    pub key: String,
+    /// 定義の値
+    pub definition: String,
+    /// 起動時に渡した値
+    pub running: String,
+}
+
+/// 定義と起動時の環境変数の差分
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct EnvDiff {
+    pub process_id: String,
+    /// 実行中か
+    pub running: bool,
+    /// 定義にあり、起動時には渡していない変数（再起動で追加される）
+    pub added: HashMap<String, String>,
+    /// 起動時に渡し、定義からは削除された変数（再起動で削除される）
+    pub removed: HashMap<String, String>,
+    /// 値が変わった変数
+    pub changed: Vec<EnvChange>,
+    /// 実行中のプロセスが定義と異なる環境で動いているか
+    pub drifted: bool,
+    pub advice: String,
+}
+
+/// 定義の環境変数と起動時に渡した環境変数（実行中でなければNone）を比べる
+///
+/// `injected` は定義に無くても起動時に補った変数（`run_as` の `HOME` など）で、
+/// 定義から削除されたものとは扱わない。
+pub fn diff(
+    process_id: &str,
+    definition: &HashMap<String, String>,
+    spawned: Option<&HashMap<String, String>>,
+    injected: &[String],
+) -> EnvDiff {
+    let Some(spawned) = spawned else {
+        return EnvDiff {
+            process_id: process_id.to_string(),
+            running: false,
+            added: HashMap::new(),
+            removed: HashMap::new(),
+            changed: Vec::new(),
+            drifted: false,
+            advice: format!(
+                "Process '{process_id}' is not running; it will start with the current definition"
+            ),
+        };
+    };
+
+    let added: HashMap<_, _> = definition
+        .iter()
+        .filter(|(key, _)| !spawned.contains_key(*key))
+        .map(|(key, value)| (key.clone(), mask(key, value)))
+        .collect();
+    let removed: HashMap<_, _> = spawned
+        .iter()
+        .filter(|(key, _)| !definition.contains_key(*key) && !injected.contains(key))
+        .map(|(key, value)| (key.clone(), mask(key, value)))
+        .collect();
+    let mut changed: Vec<_> = definition
+        .iter()
+        .filter_map(|(key, value)| {
+            let running = spawned.get(key).filter(|running| *running != value)?;
+            Some(EnvChange {
+                key: key.clone(),
+                definition: mask(key, value),
+                running: mask(key, running),
+            })
+        })
+        .collect();
+    changed.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let drifted = !added.is_empty() || !removed.is_empty() || !changed.is_empty();
+    let advice = if drifted {
+        format!(
+            "Process '{process_id}' is running with a stale environment \
+             ({} added, {} removed, {} changed); restart it to apply the current definition",
+            added.len(),
+            removed.len(),
+            changed.len()
+        )
+    } else {
+        format!("Process '{process_id}' is running with the current definition")
+    };
+    EnvDiff {
+        process_id: process_id.to_string(),
+        running: true,
+        added,
+        removed,
+        changed,
+        drifted,
+        advice,
+    }
+}
+
+/// シークレットとみなせる値を伏せる
+pub(crate) fn mask(key: &str, value: &str) -> String {
+    if is_secret_key(key) || looks_like_secret_value(value) {
+        MASKED_VALUE.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_reports_added_removed_and_changed() {
+        let definition = env(&[("PORT", "4000"), ("MODE", "dev"), ("API_TOKEN", "new")]);
+        let spawned = env(&[
+            ("PORT", "3000"),
+            ("DEBUG", "1"),
+            ("API_TOKEN", "old"),
+            ("HOME", "/home/app"),
+        ]);
+        let diff = diff("web", &definition, Some(&spawned), &["HOME".to_string()]);
+
+        assert!(diff.running);
+        assert!(diff.drifted);
+        assert_eq!(diff.added, env(&[("MODE", "dev")]));
+        assert_eq!(diff.removed, env(&[("DEBUG", "1")]));
+        assert_eq!(
+            diff.changed,
+            vec![
+                EnvChange {
+                    key: "API_TOKEN".to_string(),
+                    definition: MASKED_VALUE.to_string(),
+                    running: MASKED_VALUE.to_string(),
+                },
+                EnvChange {
+                    key: "PORT".to_string(),
+                    definition: "4000".to_string(),
+                    running: "3000".to_string(),
+                },
+            ]
+        );
+        assert!(diff.advice.contains("restart"));
+    }
+
+    #[test]
+    fn test_diff_without_drift_or_running_process() {
+        let definition = env(&[("PORT", "3000")]);
+        let clean = diff("web", &definition, Some(&definition.clone()), &[]);
+        assert!(clean.running);
+        assert!(!clean.drifted);
+
+        let stopped = diff("web", &definition, None, &[]);
+        assert!(!stopped.running);
+        assert!(!stopped.drifted);
+        assert!(stopped.added.is_empty());
+    }
+}
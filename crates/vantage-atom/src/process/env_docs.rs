@@ -0,0 +1,396 @@
+//! テンプレートで使う環境変数の説明の抽出
+//!
+//! コマンド・引数・環境変数の値で参照している変数（`$VAR`、`${VAR}`、`${VAR:-既定値}`）と、
+//! 作業ディレクトリの `.env` 系のファイルに並んでいる変数を集め、説明・既定値・例の付いた
+//! テンプレート変数にします。説明は `.env.example` などのコメント、よく使われる変数の説明、
+//! 見つかった場所の順に選びます。シークレットらしい変数は値を残さない必須の変数にします。
+
+use super::capture::{SECRET_VAR_TYPE, is_secret_key, looks_like_secret_value};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use utoipa::ToSchema;
+use vantage_persistence::{ProcessTemplate, TemplateVariable};
+
+/// 読み込む `.env` 系のファイル（先にあるものの説明・例を優先する）
+pub const DOTENV_FILES: &[&str] = &[".env.example", ".env.sample", ".env.template", ".env"];
+
+/// テンプレート変数の型ヒント
+pub const ENV_VAR_TYPE: &str = "env";
+
+/// シェルやOSが設定する変数（参照していても変数にしない）
+const SYSTEM_VARS: &[&str] = &[
+    "HOME", "PATH", "PWD", "OLDPWD", "USER", "LOGNAME", "SHELL", "TERM", "LANG", "TMPDIR",
+    "HOSTNAME",
+];
+
+/// よく使われる変数の説明
+const WELL_KNOWN: &[(&str, &str)] = &[
+    ("PORT", "Port the server listens on"),
+    ("HOST", "Address the server binds to"),
+    (
+        "NODE_ENV",
+        "Node.js environment (development, production, test)",
+    ),
+    (
+        "RAILS_ENV",
+        "Rails environment (development, production, test)",
+    ),
+    ("APP_ENV", "Application environment"),
+    ("DATABASE_URL", "Database connection URL"),
+    ("REDIS_URL", "Redis connection URL"),
+    ("LOG_LEVEL", "Log verbosity"),
+    ("RUST_LOG", "Rust log filter (e.g. info, my_crate=debug)"),
+    (
+        "RUST_BACKTRACE",
+        "Print Rust backtraces on panic (1 or full)",
+    ),
+    ("PYTHONUNBUFFERED", "Disable Python output buffering (1)"),
+    ("DEBUG", "Enable debug output"),
+];
+
+/// `.env` 系のファイルの中身
+#[derive(Debug, Clone)]
+pub struct DotenvFile {
+    pub name: String,
+    pub contents: String,
+}
+
+/// 説明を付けた環境変数
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct EnvVarDoc {
+    pub name: String,
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub example: Option<String>,
+    pub required: bool,
+    pub secret: bool,
+    /// 見つかった場所（`args`、`env DATABASE_URL`、`.env.example` など）
+    pub sources: Vec<String>,
+}
+
+/// `.env` の1行分
+#[derive(Debug, Clone, PartialEq)]
+pub struct DotenvEntry {
+    pub key: String,
+    pub value: String,
+    /// 直前のコメント行
+    pub comment: Option<String>,
+}
+
+/// ディレクトリにある `.env` 系のファイルを読む（ないものは飛ばす）
+pub async fn read_dotenv_files(dir: &Path) -> Vec<DotenvFile> {
+    let mut files = Vec::new();
+    for name in DOTENV_FILES {
+        if let Ok(contents) = tokio::fs::read_to_string(dir.join(name)).await {
+            files.push(DotenvFile {
+                name: name.to_string(),
+                contents,
+            });
+        }
+    }
+    files
+}
+
+/// 文字列の中で参照している変数と、`${VAR:-既定値}` の既定値
+pub fn referenced_vars(text: &str) -> Vec<(String, Option<String>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '$' {
+            i += 1;
+            continue;
+        }
+        if chars.get(i + 1) == Some(&'{') {
+            let Some(close) = chars[i + 2..].iter().position(|c| *c == '}') else {
+                break;
+            };
+            let inner: String = chars[i + 2..i + 2 + close].iter().collect();
+            let (name, default) = match inner.find([':', '-']) {
+                Some(at) => {
+                    let rest = inner[at..].trim_start_matches(':');
+                    let default = rest.strip_prefix('-').map(str::to_string);
+                    (inner[..at].to_string(), default)
+                }
+                None => (inner, None),
+            };
+            if is_var_name(&name) {
+                found.push((name, default.filter(|d| !d.is_empty())));
+            }
+            i += close + 3;
+        } else {
+            let name: String = chars[i + 1..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .collect();
+            i += name.len() + 1;
+            if is_var_name(&name) {
+                found.push((name, None));
+            }
+        }
+    }
+    found
+}
+
+fn is_var_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `.env` の形式（`KEY=value`、`export KEY=value`、`#` のコメント）を読む
+pub fn parse_dotenv(contents: &str) -> Vec<DotenvEntry> {
+    let mut entries = Vec::new();
+    let mut comment: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            comment.clear();
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            let text = text.trim();
+            if !text.is_empty() {
+                comment.push(text.to_string());
+            }
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            comment.clear();
+            continue;
+        };
+        let key = key.trim();
+        if !is_var_name(key) {
+            comment.clear();
+            continue;
+        }
+        let value = value.trim();
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or_default(),
+            _ => value.split(" #").next().unwrap_or_default().trim_end(),
+        };
+        entries.push(DotenvEntry {
+            key: key.to_string(),
+            value: value.to_string(),
+            comment: (!comment.is_empty()).then(|| comment.join(" ")),
+        });
+        comment.clear();
+    }
+    entries
+}
+
+/// テンプレートが参照する環境変数を集めて説明を付け、テンプレート変数として加える
+///
+/// すでにテンプレート変数になっているもの（ポート・シークレットなど）と、値にプレースホルダー
+/// を含むものは対象外。加えた変数は環境変数の値を `{{NAME}}` にし、元の値を既定値にする
+/// （シークレットらしいものは既定値を持たない必須の変数にする）。
+pub fn document(template: &mut ProcessTemplate, files: &[DotenvFile]) -> Vec<EnvVarDoc> {
+    let mut docs: BTreeMap<String, EnvVarDoc> = BTreeMap::new();
+    let mut note = |name: &str, source: &str, default: Option<String>| {
+        let doc = docs.entry(name.to_string()).or_insert_with(|| EnvVarDoc {
+            name: name.to_string(),
+            ..Default::default()
+        });
+        if !doc.sources.iter().any(|s| s == source) {
+            doc.sources.push(source.to_string());
+        }
+        if doc.default_value.is_none() {
+            doc.default_value = default;
+        }
+    };
+
+    for (name, default) in referenced_vars(&template.command) {
+        note(&name, "command", default);
+    }
+    for arg in &template.args {
+        for (name, default) in referenced_vars(arg) {
+            note(&name, "args", default);
+        }
+    }
+    let mut env_keys: Vec<&String> = template.env.keys().collect();
+    env_keys.sort();
+    for key in env_keys {
+        for (name, default) in referenced_vars(&template.env[key]) {
+            note(&name, &format!("env {key}"), default);
+        }
+    }
+    let mut comments: BTreeMap<String, String> = BTreeMap::new();
+    let mut examples: BTreeMap<String, String> = BTreeMap::new();
+    let mut local_values: BTreeMap<String, String> = BTreeMap::new();
+    for file in files {
+        for entry in parse_dotenv(&file.contents) {
+            note(&entry.key, &file.name, None);
+            if let Some(comment) = entry.comment {
+                comments.entry(entry.key.clone()).or_insert(comment);
+            }
+            if entry.value.is_empty() {
+                continue;
+            }
+            if file.name == ".env" {
+                local_values.entry(entry.key).or_insert(entry.value);
+            } else {
+                examples.entry(entry.key).or_insert(entry.value);
+            }
+        }
+    }
+
+    let existing: Vec<String> = template.variables.iter().map(|v| v.name.clone()).collect();
+    let mut added = Vec::new();
+    for (name, mut doc) in docs {
+        if SYSTEM_VARS.contains(&name.as_str()) {
+            continue;
+        }
+        if existing.contains(&name) {
+            // 取り込み直しで値が戻った場合も、既にある変数を参照させる
+            if let Some(value) = template.env.get_mut(&name)
+                && !value.contains("{{")
+            {
+                *value = format!("{{{{{name}}}}}");
+            }
+            continue;
+        }
+        let current = template.env.get(&name);
+        if current.is_some_and(|value| value.contains("{{")) {
+            continue;
+        }
+        let value = current
+            .cloned()
+            .or_else(|| local_values.get(&name).cloned())
+            .or(doc.default_value.take());
+        doc.secret = is_secret_key(&name)
+            || value.as_deref().is_some_and(looks_like_secret_value)
+            || examples
+                .get(&name)
+                .is_some_and(|example| looks_like_secret_value(example));
+        if doc.secret {
+            doc.default_value = None;
+        } else {
+            doc.default_value = value;
+            doc.example = examples
+                .get(&name)
+                .filter(|example| Some(*example) != doc.default_value.as_ref())
+                .cloned();
+        }
+        doc.required = doc.default_value.is_none();
+        doc.description = comments
+            .get(&name)
+            .cloned()
+            .or_else(|| {
+                WELL_KNOWN
+                    .iter()
+                    .find(|(known, _)| *known == name)
+                    .map(|(_, description)| description.to_string())
+            })
+            .unwrap_or_else(|| format!("Environment variable (from {})", doc.sources.join(", ")));
+
+        template.env.insert(name.clone(), format!("{{{{{name}}}}}"));
+        template.variables.push(TemplateVariable {
+            name: name.clone(),
+            description: Some(doc.description.clone()),
+            default_value: doc.default_value.clone(),
+            required: doc.required,
+            var_type: Some(
+                if doc.secret {
+                    SECRET_VAR_TYPE
+                } else {
+                    ENV_VAR_TYPE
+                }
+                .to_string(),
+            ),
+            example: doc.example.clone(),
+            schema: None,
+        });
+        added.push(doc);
+    }
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_referenced_vars_and_dotenv_parsing() {
+        assert_eq!(
+            referenced_vars("--db=$DATABASE_URL ${LOG_LEVEL:-info} ${HOME} $1 $"),
+            vec![
+                ("DATABASE_URL".to_string(), None),
+                ("LOG_LEVEL".to_string(), Some("info".to_string())),
+                ("HOME".to_string(), None),
+            ]
+        );
+
+        let entries = parse_dotenv(
+            "# Stripe secret key\n# (test mode)\nSTRIPE_KEY=\n\nexport WORKERS=\"4\" \nCACHE=redis # inline\n",
+        );
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[0].comment.as_deref(),
+            Some("Stripe secret key (test mode)")
+        );
+        assert_eq!(entries[1].value, "4");
+        assert_eq!(entries[1].comment, None);
+        assert_eq!(entries[2].value, "redis");
+    }
+
+    #[test]
+    fn test_document_adds_described_variables() {
+        let mut template = ProcessTemplate::new("api".to_string(), "node".to_string());
+        template.args = vec![
+            "server.js".to_string(),
+            "--log=${LOG_LEVEL:-info}".to_string(),
+            "--home=$HOME".to_string(),
+        ];
+        template.env = HashMap::from([
+            ("NODE_ENV".to_string(), "development".to_string()),
+            ("API_URL".to_string(), "http://$API_HOST/v1".to_string()),
+        ]);
+        let files = vec![
+            DotenvFile {
+                name: ".env.example".to_string(),
+                contents: "# Number of worker threads\nWORKERS=4\n# Stripe API key\nSTRIPE_SECRET=sk-test\n"
+                    .to_string(),
+            },
+            DotenvFile {
+                name: ".env".to_string(),
+                contents: "WORKERS=8\nSTRIPE_SECRET=sk-live-xxx\n".to_string(),
+            },
+        ];
+
+        let docs = document(&mut template, &files);
+        let names: Vec<&str> = docs.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["API_HOST", "LOG_LEVEL", "STRIPE_SECRET", "WORKERS"]
+        );
+        let by_name = |name: &str| docs.iter().find(|d| d.name == name).unwrap();
+        assert!(by_name("API_HOST").required);
+        assert_eq!(by_name("LOG_LEVEL").default_value.as_deref(), Some("info"));
+        let workers = by_name("WORKERS");
+        assert_eq!(workers.description, "Number of worker threads");
+        assert_eq!(workers.default_value.as_deref(), Some("8"));
+        assert_eq!(workers.example.as_deref(), Some("4"));
+        let stripe = by_name("STRIPE_SECRET");
+        assert!(stripe.secret && stripe.required && stripe.default_value.is_none());
+        assert_eq!(template.env["WORKERS"], "{{WORKERS}}");
+
+        let serialized = serde_json::to_string(&template).unwrap();
+        assert!(!serialized.contains("sk-live-xxx"));
+        let instance = template
+            .instantiate(
+                "api-1".to_string(),
+                HashMap::from([
+                    ("API_HOST".to_string(), "localhost:4000".to_string()),
+                    ("STRIPE_SECRET".to_string(), "sk-test-1".to_string()),
+                ]),
+            )
+            .unwrap();
+        assert_eq!(instance.env["WORKERS"], "8");
+    }
+}
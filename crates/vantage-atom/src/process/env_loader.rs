@@ -0,0 +1,318 @@
+//! プロジェクトの環境の読み込み（mise・direnv・asdf）
+//!
+//! ツールのバージョンをプロジェクトごとに切り替えている場合、サーバーから起動したプロセスには
+//! シェルで有効になっているPATHが渡らず、別のバージョンのnodeやpythonで動いてしまいます。
+//! `env_loader` を指定したプロセスは、起動のたびに作業ディレクトリでそのツールの環境を読み込み、
+//! 定義の環境変数より優先度の低い環境変数として渡します。解決したツールのバージョンは
+//! 実行環境の記録（`explain_run_environment`）に残ります。
+
+use super::run_environment::which;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use vantage_persistence::EnvLoader;
+
+/// ツールの環境の読み込みを待つ時間
+const LOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// miseの設定ファイル（ディレクトリごとにこの順で探す）
+const MISE_FILES: &[&str] = &[
+    "mise.local.toml",
+    "mise.toml",
+    ".mise.toml",
+    ".config/mise.toml",
+    ".mise/config.toml",
+];
+const DIRENV_FILE: &str = ".envrc";
+const TOOL_VERSIONS_FILE: &str = ".tool-versions";
+
+/// 読み込みに使ったツールと、解決したツールチェーンのバージョン
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Toolchain {
+    pub loader: EnvLoader,
+    /// 見つけた設定ファイル
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_file: Option<String>,
+    /// ツールとバージョン（miseは解決したバージョン、それ以外は .tool-versions の指定）
+    pub versions: BTreeMap<String, String>,
+}
+
+/// 読み込んだプロジェクトの環境
+#[derive(Debug, Clone)]
+pub struct ProjectEnvironment {
+    pub toolchain: Toolchain,
+    /// 子プロセスに渡す環境変数（サーバーの環境から変わったもののみ）
+    pub env: HashMap<String, String>,
+}
+
+/// 作業ディレクトリとその親から、インストールされているツールの設定ファイルを探す
+pub fn detect(cwd: &Path) -> Option<(EnvLoader, PathBuf)> {
+    let path = std::env::var("PATH").ok();
+    detect_with(cwd, |tool| which(tool, path.as_deref(), None).is_some())
+}
+
+fn detect_with(cwd: &Path, installed: impl Fn(&str) -> bool) -> Option<(EnvLoader, PathBuf)> {
+    for dir in cwd.ancestors() {
+        if installed("mise")
+            && let Some(file) = MISE_FILES
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|file| file.is_file())
+        {
+            return Some((EnvLoader::Mise, file));
+        }
+        let envrc = dir.join(DIRENV_FILE);
+        if envrc.is_file() && installed("direnv") {
+            return Some((EnvLoader::Direnv, envrc));
+        }
+        let tool_versions = dir.join(TOOL_VERSIONS_FILE);
+        if tool_versions.is_file() {
+            // miseも .tool-versions を読む
+            if installed("mise") {
+                return Some((EnvLoader::Mise, tool_versions));
+            }
+            if installed("asdf") {
+                return Some((EnvLoader::Asdf, tool_versions));
+            }
+        }
+    }
+    None
+}
+
+/// 起動する前にプロジェクトの環境を読み込む
+///
+/// `Auto` で設定ファイルが見つからない場合はNone。指定したツールが無い・読み込めない場合はエラー。
+pub async fn load(
+    loader: EnvLoader,
+    cwd: Option<&Path>,
+) -> Result<Option<ProjectEnvironment>, String> {
+    let cwd = match cwd {
+        Some(cwd) => cwd.to_path_buf(),
+        None => std::env::current_dir().map_err(|e| e.to_string())?,
+    };
+    let (loader, config_file) = match loader {
+        EnvLoader::Auto => match detect(&cwd) {
+            Some((loader, file)) => (loader, Some(file)),
+            None => return Ok(None),
+        },
+        EnvLoader::Mise => (loader, find_upwards(&cwd, MISE_FILES)),
+        EnvLoader::Direnv => (loader, find_upwards(&cwd, &[DIRENV_FILE])),
+        EnvLoader::Asdf => (loader, find_upwards(&cwd, &[TOOL_VERSIONS_FILE])),
+    };
+
+    let (env, versions) = match loader {
+        EnvLoader::Mise => {
+            let env = parse_env_json(&run("mise", &["env", "--json"], &cwd).await?)?;
+            // バージョンが取れなくても起動は続ける
+            let versions = match run("mise", &["ls", "--current", "--json"], &cwd).await {
+                Ok(output) => parse_mise_versions(&output),
+                Err(e) => {
+                    tracing::debug!("Failed to list mise tool versions: {}", e);
+                    BTreeMap::new()
+                }
+            };
+            (env, versions)
+        }
+        EnvLoader::Direnv => {
+            let output = run("direnv", &["export", "json"], &cwd).await?;
+            let env = if output.trim().is_empty() {
+                HashMap::new()
+            } else {
+                parse_env_json(&output)?
+            };
+            (env, tool_versions(&cwd))
+        }
+        EnvLoader::Asdf => {
+            let path = std::env::var("PATH").ok();
+            if which("asdf", path.as_deref(), None).is_none() {
+                return Err("asdf is not installed or not in PATH".to_string());
+            }
+            (asdf_env(path.as_deref()), tool_versions(&cwd))
+        }
+        EnvLoader::Auto => unreachable!("auto is resolved above"),
+    };
+
+    Ok(Some(ProjectEnvironment {
+        toolchain: Toolchain {
+            loader,
+            config_file: config_file.map(|file| file.display().to_string()),
+            versions,
+        },
+        env,
+    }))
+}
+
+/// 読み込みに使うツールの名前
+pub fn name(loader: EnvLoader) -> &'static str {
+    match loader {
+        EnvLoader::Auto => "auto",
+        EnvLoader::Mise => "mise",
+        EnvLoader::Direnv => "direnv",
+        EnvLoader::Asdf => "asdf",
+    }
+}
+
+fn find_upwards(cwd: &Path, names: &[&str]) -> Option<PathBuf> {
+    cwd.ancestors()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|file| file.is_file())
+}
+
+/// ツールを実行して標準出力を返す（失敗した場合は標準エラー出力の末尾をエラーにする）
+async fn run(program: &str, args: &[&str], cwd: &Path) -> Result<String, String> {
+    let path = std::env::var("PATH").ok();
+    let resolved = which(program, path.as_deref(), None)
+        .ok_or_else(|| format!("{program} is not installed or not in PATH"))?;
+    let mut cmd = tokio::process::Command::new(resolved);
+    cmd.args(args)
+        .current_dir(cwd)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(LOAD_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| {
+            format!(
+                "{program} {} timed out after {}s",
+                args.join(" "),
+                LOAD_TIMEOUT.as_secs()
+            )
+        })?
+        .map_err(|e| format!("Failed to run {program}: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .rev()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or("no error output");
+        return Err(format!(
+            "{program} {} failed in {}: {reason}",
+            args.join(" "),
+            cwd.display()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `mise env --json`・`direnv export json` の出力を読む
+///
+/// direnvは外す環境変数をnullで返すが、サーバーの環境から起動するため読み飛ばす。
+fn parse_env_json(output: &str) -> Result<HashMap<String, String>, String> {
+    let values: HashMap<String, Option<String>> = serde_json::from_str(output)
+        .map_err(|e| format!("Failed to parse the loaded environment: {e}"))?;
+    Ok(values
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect())
+}
+
+/// `mise ls --current --json` の出力からツールごとのバージョンを読む
+fn parse_mise_versions(output: &str) -> BTreeMap<String, String> {
+    let Ok(tools) = serde_json::from_str::<BTreeMap<String, Vec<serde_json::Value>>>(output) else {
+        return BTreeMap::new();
+    };
+    tools
+        .into_iter()
+        .filter_map(|(tool, installs)| {
+            let versions: Vec<&str> = installs
+                .iter()
+                .filter_map(|install| install.get("version")?.as_str())
+                .collect();
+            (!versions.is_empty()).then(|| (tool, versions.join(" ")))
+        })
+        .collect()
+}
+
+/// 最も近い .tool-versions の指定
+fn tool_versions(cwd: &Path) -> BTreeMap<String, String> {
+    find_upwards(cwd, &[TOOL_VERSIONS_FILE])
+        .and_then(|file| std::fs::read_to_string(file).ok())
+        .map(|content| parse_tool_versions(&content))
+        .unwrap_or_default()
+}
+
+fn parse_tool_versions(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let tool = words.next()?;
+            let versions: Vec<&str> = words.collect();
+            (!versions.is_empty()).then(|| (tool.to_string(), versions.join(" ")))
+        })
+        .collect()
+}
+
+/// asdfのshimを先頭に置いたPATH（shimが作業ディレクトリの .tool-versions からバージョンを選ぶ）
+fn asdf_env(path: Option<&str>) -> HashMap<String, String> {
+    let data_dir = std::env::var_os("ASDF_DATA_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".asdf")));
+    let Some(shims) = data_dir.map(|dir| dir.join("shims")) else {
+        return HashMap::new();
+    };
+    let dirs = std::iter::once(shims).chain(
+        path.map(|path| std::env::split_paths(path).collect::<Vec<_>>())
+            .unwrap_or_default(),
+    );
+    match std::env::join_paths(dirs) {
+        Ok(joined) => HashMap::from([("PATH".to_string(), joined.to_string_lossy().to_string())]),
+        Err(_) => HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_prefers_the_nearest_installed_loader() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("app");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(root.path().join(".envrc"), "use mise\n").unwrap();
+        std::fs::write(project.join(".tool-versions"), "nodejs 20.11.0\n").unwrap();
+        let cwd = project.join("src");
+
+        let only = |tools: &'static [&'static str]| move |tool: &str| tools.contains(&tool);
+        assert_eq!(
+            detect_with(&cwd, only(&["mise", "direnv"])),
+            Some((EnvLoader::Mise, project.join(".tool-versions")))
+        );
+        assert_eq!(
+            detect_with(&cwd, only(&["asdf"])),
+            Some((EnvLoader::Asdf, project.join(".tool-versions")))
+        );
+        // .tool-versions を読めるツールが無ければ親の .envrc を使う
+        assert_eq!(
+            detect_with(&cwd, only(&["direnv"])),
+            Some((EnvLoader::Direnv, root.path().join(".envrc")))
+        );
+        assert_eq!(detect_with(&cwd, only(&[])), None);
+    }
+
+    #[test]
+    fn test_parse_loader_output() {
+        let env = parse_env_json(r#"{"PATH": "/mise/node/bin:/usr/bin", "OLD": null}"#).unwrap();
+        assert_eq!(
+            env,
+            HashMap::from([("PATH".to_string(), "/mise/node/bin:/usr/bin".to_string())])
+        );
+        assert!(parse_env_json("not json").is_err());
+
+        let versions = parse_mise_versions(
+            r#"{"node": [{"version": "20.11.0", "active": true}], "python": []}"#,
+        );
+        assert_eq!(
+            versions,
+            BTreeMap::from([("node".to_string(), "20.11.0".to_string())])
+        );
+
+        let versions = parse_tool_versions("# pinned\nnodejs 20.11.0\npython 3.12.1 3.11.7\n\n");
+        assert_eq!(versions["nodejs"], "20.11.0");
+        assert_eq!(versions["python"], "3.12.1 3.11.7");
+    }
+}
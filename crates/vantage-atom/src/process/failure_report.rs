@@ -0,0 +1,114 @@
+//! 異常終了したときの状況の記録
+//!
+//! プロセスが異常終了（0以外の終了コード、シグナルによる終了、終了の待機の失敗）すると、
+//! stderrの末尾、終了コードとシグナル、直前のリソースと出力の状況を1つのレポートにまとめて
+//! 実行の記録に残します。停止要求やメンテナンス中の終了は対象にしません。
+
+use super::cgroup::CgroupStats;
+use super::crash_loop::ExitRecord;
+use super::output_rates::WindowRates;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// レポートに含めるstderrの行数
+pub const STDERR_TAIL_LINES: usize = 20;
+/// レポートに含める出力の頻度を数える期間（秒）
+pub const OUTPUT_WINDOW_SECS: u64 = 60;
+
+/// 異常終了したときの状況
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FailureReport {
+    /// 実行の通し番号（explain_run_environment の run と同じ）
+    pub run: u64,
+    pub failed_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// シグナルで終了した場合、その番号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    /// シグナルの名前（`SIGKILL` など）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal_name: Option<String>,
+    /// 終了を待てなかった場合、その理由
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 起動してから終了するまでの秒数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime_secs: Option<u64>,
+    /// stderrの末尾（tmuxではstdoutとまとめて出力されるためstdoutの末尾）
+    pub stderr_tail: Vec<String>,
+    /// 終了する直前の出力の頻度
+    pub output_rates: WindowRates,
+    /// プロセス専用のcgroupに入っていた場合、終了した時点のCPU時間・メモリの最大値・OOMの回数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<CgroupStats>,
+}
+
+impl FailureReport {
+    pub fn new(
+        run: u64,
+        record: &ExitRecord,
+        signal: Option<i32>,
+        started_at: Option<DateTime<Utc>>,
+        stderr_tail: Vec<String>,
+        output_rates: WindowRates,
+        resources: Option<CgroupStats>,
+    ) -> Self {
+        Self {
+            run,
+            failed_at: record.at,
+            exit_code: record.exit_code,
+            signal,
+            signal_name: signal.and_then(signal_name),
+            error: record.error.clone(),
+            uptime_secs: started_at.map(|at| (record.at - at).num_seconds().max(0) as u64),
+            stderr_tail,
+            output_rates,
+            resources,
+        }
+    }
+}
+
+/// シグナルの番号から名前を求める
+#[cfg(unix)]
+fn signal_name(signal: i32) -> Option<String> {
+    nix::sys::signal::Signal::try_from(signal)
+        .ok()
+        .map(|s| s.as_str().to_string())
+}
+
+#[cfg(not(unix))]
+fn signal_name(_signal: i32) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_report_names_signal_and_measures_uptime() {
+        let now = Utc::now();
+        let record = ExitRecord {
+            at: now,
+            exit_code: None,
+            error: None,
+        };
+        let report = FailureReport::new(
+            3,
+            &record,
+            Some(9),
+            Some(now - Duration::seconds(42)),
+            vec!["panic".to_string()],
+            WindowRates::default(),
+            None,
+        );
+        assert_eq!(report.run, 3);
+        assert_eq!(report.uptime_secs, Some(42));
+        #[cfg(unix)]
+        assert_eq!(report.signal_name.as_deref(), Some("SIGKILL"));
+        assert_eq!(report.stderr_tail, vec!["panic"]);
+    }
+}
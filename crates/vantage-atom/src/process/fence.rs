@@ -0,0 +1,243 @@
+//! スナップショット・復元と変更操作の排他
+//!
+//! スナップショットの書き出し・復元・インポートなど、プロセス定義全体を読み書きする操作の
+//! 途中に、別のツール呼び出しやWeb APIからの変更が割り込むと、書き出した内容や復元後の
+//! 状態が壊れます。こうした操作は [`OperationFence::begin`] で排他を取り、その間の変更操作は
+//! [`OperationFence::scope`] で待たせます。一定時間待っても終わらなければ、進捗を添えて
+//! 拒否します。
+//!
+//! 変更操作の中から排他を取る操作を呼んだ場合（承認したインポートの実行など）は、自分の
+//! 待ち合わせを外してから排他を取るので、互いに待ち続けることはありません。人の確認を待つ
+//! 変更操作も、待つ前に [`leave_scope`] で待ち合わせを外します。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+use tracing::{debug, info};
+
+/// 変更操作が排他の解除を待つ最大時間
+const QUEUE_WAIT: Duration = Duration::from_secs(30);
+/// 排他を取る操作が、実行中の変更操作の完了を待つ最大時間
+const BEGIN_WAIT: Duration = Duration::from_secs(30);
+
+tokio::task_local! {
+    /// 実行中の変更操作が持っている待ち合わせ
+    static ENTRY: Arc<Mutex<Option<OwnedRwLockReadGuard<()>>>>;
+}
+
+/// 排他を取っている操作とその進捗
+#[derive(Debug, Clone, Serialize)]
+pub struct FencedOperation {
+    pub operation: String,
+    pub detail: Option<String>,
+    pub started_at: DateTime<Utc>,
+    /// 実行中の段階（例: `Starting auto-start processes`）
+    pub step: Option<String>,
+    pub completed: usize,
+    pub total: Option<usize>,
+}
+
+impl FencedOperation {
+    /// 拒否のメッセージなどに添える進捗の説明
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "'{}' (running for {}s",
+            self.operation,
+            (Utc::now() - self.started_at).num_seconds().max(0)
+        );
+        if let Some(step) = &self.step {
+            description.push_str(&format!(", {step}"));
+        }
+        if let Some(total) = self.total {
+            description.push_str(&format!(", {}/{total}", self.completed));
+        }
+        description.push(')');
+        description
+    }
+}
+
+/// スナップショット・復元と変更操作の排他
+#[derive(Default)]
+pub struct OperationFence {
+    lock: Arc<RwLock<()>>,
+    current: Arc<Mutex<Option<FencedOperation>>>,
+}
+
+impl OperationFence {
+    /// 排他を取っている操作（なければ `None`）
+    pub fn status(&self) -> Option<FencedOperation> {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// 排他を取る。実行中の変更操作が終わるのを待ち、その後の変更操作は解除まで待たせる
+    ///
+    /// すでに別の操作が排他を取っている場合はすぐに失敗する。
+    pub async fn begin(
+        &self,
+        operation: &str,
+        detail: Option<String>,
+    ) -> Result<FenceGuard, String> {
+        if let Some(running) = self.status() {
+            return Err(format!(
+                "Cannot start '{operation}' while {} is in progress",
+                running.describe()
+            ));
+        }
+        // 変更操作の中から呼ばれた場合は、自分の待ち合わせを外す
+        leave_scope();
+
+        let guard = tokio::time::timeout(BEGIN_WAIT, self.lock.clone().write_owned())
+            .await
+            .map_err(|_| {
+                format!("Timed out waiting for in-flight changes to finish before '{operation}'")
+            })?;
+        {
+            let mut current = self.current.lock().unwrap();
+            if let Some(running) = current.as_ref() {
+                return Err(format!(
+                    "Cannot start '{operation}' while {} is in progress",
+                    running.describe()
+                ));
+            }
+            *current = Some(FencedOperation {
+                operation: operation.to_string(),
+                detail,
+                started_at: Utc::now(),
+                step: None,
+                completed: 0,
+                total: None,
+            });
+        }
+        info!("Fenced changes for '{}'", operation);
+        Ok(FenceGuard {
+            _guard: guard,
+            current: self.current.clone(),
+        })
+    }
+
+    /// 変更操作を実行する。排他中は解除まで待ち、待ちきれなければ進捗を添えて失敗する
+    pub async fn scope<F: Future>(&self, name: &str, future: F) -> Result<F::Output, String> {
+        let entry = match self.lock.clone().try_read_owned() {
+            Ok(entry) => entry,
+            Err(_) => {
+                debug!("Queueing '{}' until the running operation finishes", name);
+                tokio::time::timeout(QUEUE_WAIT, self.lock.clone().read_owned())
+                    .await
+                    .map_err(|_| {
+                        let running = self
+                            .status()
+                            .map(|op| op.describe())
+                            .unwrap_or_else(|| "another operation".to_string());
+                        format!(
+                            "'{name}' was rejected because {running} is still in progress; try again after it completes"
+                        )
+                    })?
+            }
+        };
+        Ok(ENTRY.scope(Arc::new(Mutex::new(Some(entry))), future).await)
+    }
+}
+
+/// 実行中の変更操作の待ち合わせを外す（変更操作の外から呼んだ場合は何もしない）
+///
+/// 確認を待つ間に待ち合わせを持ち続けると、承認された操作を別のタスクで実行するときの
+/// 排他が取れなくなる。外した後のこのタスクでは、プロセスを変更しないこと。
+pub fn leave_scope() {
+    let _ = ENTRY.try_with(|entry| entry.lock().unwrap().take());
+}
+
+/// 排他（破棄すると解除する）
+pub struct FenceGuard {
+    _guard: OwnedRwLockWriteGuard<()>,
+    current: Arc<Mutex<Option<FencedOperation>>>,
+}
+
+impl FenceGuard {
+    /// 進捗を更新する
+    pub fn progress(&self, step: &str, completed: usize, total: Option<usize>) {
+        if let Some(operation) = self.current.lock().unwrap().as_mut() {
+            operation.step = Some(step.to_string());
+            operation.completed = completed;
+            operation.total = total;
+        }
+    }
+}
+
+impl Drop for FenceGuard {
+    fn drop(&mut self) {
+        if let Some(operation) = self.current.lock().unwrap().take() {
+            info!(
+                "Lifted fence for '{}' after {}s",
+                operation.operation,
+                (Utc::now() - operation.started_at).num_seconds().max(0)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_changes_wait_for_fence_and_nested_begin_does_not_deadlock() {
+        let fence = Arc::new(OperationFence::default());
+        let guard = fence.begin("restore_snapshot", None).await.unwrap();
+        guard.progress("Reloading processes", 1, Some(3));
+        assert!(fence.begin("import_yaml", None).await.is_err());
+        assert!(
+            fence
+                .status()
+                .unwrap()
+                .describe()
+                .contains("Reloading processes, 1/3"),
+            "{:?}",
+            fence.status()
+        );
+
+        // 排他中の変更操作は解除まで待つ
+        let waiting = tokio::spawn({
+            let fence = fence.clone();
+            async move { fence.scope("create_process", async { 42 }).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+        drop(guard);
+        assert_eq!(waiting.await.unwrap(), Ok(42));
+        assert!(fence.status().is_none());
+
+        // 変更操作の中から排他を取っても止まらない
+        let nested = fence
+            .scope("approve", async {
+                let guard = fence.begin("import_yaml", None).await?;
+                drop(guard);
+                Ok::<_, String>(())
+            })
+            .await
+            .unwrap();
+        assert!(nested.is_ok());
+
+        // 待ち合わせを外した変更操作の実行中は、別のタスクでも排他を取れる
+        let (left, leave) = tokio::sync::oneshot::channel();
+        let (done, finish) = tokio::sync::oneshot::channel::<()>();
+        let waiting = tokio::spawn({
+            let fence = fence.clone();
+            async move {
+                fence
+                    .scope("import_yaml", async {
+                        leave_scope();
+                        let _ = left.send(());
+                        let _ = finish.await;
+                    })
+                    .await
+            }
+        });
+        leave.await.unwrap();
+        drop(fence.begin("import_yaml", None).await.unwrap());
+        let _ = done.send(());
+        assert!(waiting.await.unwrap().is_ok());
+    }
+}
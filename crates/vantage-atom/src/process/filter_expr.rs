@@ -0,0 +1,1006 @@
+//! プロセスやイベントを絞り込むフィルター式
+//!
+//! `state == running && tag in ["backend"] && started_at > now()-2h` のような式をサーバー側で
+//! 解釈し、`list_processes`・`get_events`・一括操作で共通に使います。
+//!
+//! - 比較: `==` `!=` `<` `<=` `>` `>=` `in` `contains` `~`（正規表現）`!~`
+//! - 結合: `&&` / `and`、`||` / `or`、`!` / `not`、括弧
+//! - 値: 文字列（`"..."` / `'...'`）、数値、識別子（`running` などはそのまま文字列）、
+//!   リスト `[...]`、`true` / `false`、`null`、時刻（`now()`、`now()-2h`、RFC 3339の文字列）
+//!
+//! 使えないフィールドや型の合わない比較は、評価前に位置付きのエラーにします。
+
+use crate::events::{EventType, ProcessEvent};
+use crate::process::types::{ProcessInfo, ProcessState};
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use std::fmt;
+
+/// フィールドの型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldType {
+    Text,
+    Number,
+    Time,
+    Bool,
+    /// 文字列のリスト（いずれかの要素が条件を満たせば一致）
+    List,
+    /// 決まった値のいずれか
+    Enum(&'static [&'static str]),
+}
+
+/// 式で使えるフィールド（`.` で終わる名前は `metadata.owner` のような前置きを表す）
+pub type Fields = &'static [(&'static str, FieldType)];
+
+const PROCESS_STATES: &[&str] = &["not_started", "running", "paused", "stopped", "failed"];
+
+/// プロセスの絞り込みで使えるフィールド
+pub const PROCESS_FIELDS: Fields = &[
+    ("id", FieldType::Text),
+    ("command", FieldType::Text),
+    ("args", FieldType::List),
+    ("cwd", FieldType::Text),
+    ("state", FieldType::Enum(PROCESS_STATES)),
+    ("tag", FieldType::List),
+    ("tags", FieldType::List),
+    ("group", FieldType::Text),
+    ("pid", FieldType::Number),
+    ("started_at", FieldType::Time),
+    ("stopped_at", FieldType::Time),
+    ("exit_code", FieldType::Number),
+    ("error", FieldType::Text),
+    ("auto_start", FieldType::Bool),
+    ("healthy", FieldType::Bool),
+    ("metadata.", FieldType::Text),
+];
+
+const EVENT_TYPES: &[&str] = &[
+    "process_started",
+    "process_stopped",
+    "process_error",
+    "process_recovered",
+    "process_created",
+    "process_removed",
+    "process_idle",
+    "process_unhealthy",
+    "process_paused",
+    "process_resumed",
+    "crash_loop_detected",
+    "restart_scheduled",
+    "process_restarted",
+    "restart_limit_reached",
+    "process_state_corrected",
+    "chaos_injected",
+    "maintenance_started",
+    "maintenance_ended",
+];
+
+/// イベントの絞り込みで使えるフィールド
+pub const EVENT_FIELDS: Fields = &[
+    ("type", FieldType::Enum(EVENT_TYPES)),
+    ("process_id", FieldType::Text),
+    ("timestamp", FieldType::Time),
+];
+
+/// 評価時のフィールドの値
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(f64),
+    Time(DateTime<Utc>),
+    Bool(bool),
+    List(Vec<String>),
+    Null,
+}
+
+/// フィルター式で絞り込める対象
+pub trait Filterable {
+    /// フィールドの値（値のないものは `Value::Null`）
+    fn field(&self, name: &str) -> Value;
+}
+
+/// 式の解釈エラー
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    pub message: String,
+    /// 式の中の位置（文字単位、0始まり）
+    pub position: usize,
+    pub expression: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid filter: {} at position {}\n  {}\n  {}^",
+            self.message,
+            self.position + 1,
+            self.expression,
+            " ".repeat(self.position)
+        )
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Duration(Duration),
+    Op(&'static str),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{name}'"),
+            Token::Str(text) => write!(f, "string \"{text}\""),
+            Token::Num(n) => write!(f, "number {n}"),
+            Token::Duration(_) => write!(f, "duration"),
+            Token::Op(op) => write!(f, "'{op}'"),
+            Token::End => write!(f, "end of expression"),
+        }
+    }
+}
+
+/// 長いものから順に照合する記号
+const SYMBOLS: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "!~", "<", ">", "!", "~", "(", ")", "[", "]", ",", "+", "-",
+];
+
+/// 字句に分ける（位置は文字単位）
+fn tokenize(expression: &str) -> Result<Vec<(Token, usize)>, FilterError> {
+    let chars: Vec<char> = expression.chars().collect();
+    let error = |message: String, position: usize| FilterError {
+        message,
+        position,
+        expression: expression.to_string(),
+    };
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(error("unterminated string".to_string(), start)),
+                    Some('\\') if i + 1 < chars.len() => {
+                        text.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&q) if q == c => {
+                        i += 1;
+                        break;
+                    }
+                    Some(&other) => {
+                        text.push(other);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push((Token::Str(text), start));
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            let number: f64 = digits
+                .parse()
+                .map_err(|_| error(format!("invalid number '{digits}'"), start))?;
+            let unit_start = i;
+            while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            if unit_start == i {
+                tokens.push((Token::Num(number), start));
+                continue;
+            }
+            let unit: String = chars[unit_start..i].iter().collect();
+            let millis = match unit.as_str() {
+                "ms" => 1.0,
+                "s" => 1_000.0,
+                "m" => 60_000.0,
+                "h" => 3_600_000.0,
+                "d" => 86_400_000.0,
+                "w" => 604_800_000.0,
+                _ => {
+                    return Err(error(
+                        format!("unknown duration unit '{unit}' (use ms, s, m, h, d or w)"),
+                        unit_start,
+                    ));
+                }
+            };
+            tokens.push((
+                Token::Duration(Duration::milliseconds((number * millis) as i64)),
+                start,
+            ));
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '-'))
+            {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), start));
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| {
+            symbol
+                .chars()
+                .enumerate()
+                .all(|(offset, s)| chars.get(i + offset) == Some(&s))
+        }) {
+            i += symbol.chars().count();
+            tokens.push((Token::Op(symbol), start));
+        } else {
+            return Err(error(format!("unexpected character '{c}'"), start));
+        }
+    }
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    Contains,
+    Matches,
+    NotMatches,
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Text(String),
+    Number(f64),
+    Time(DateTime<Utc>),
+    Bool(bool),
+    Null,
+    List(Vec<String>),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Compare {
+        field: String,
+        op: Op,
+        value: Literal,
+    },
+}
+
+/// 解釈済みのフィルター式
+#[derive(Debug, Clone)]
+pub struct FilterExpr {
+    root: Node,
+    source: String,
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    fields: Fields,
+    now: DateTime<Utc>,
+    expression: &'a str,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.index].0
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.index].1
+    }
+
+    fn next(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.index].clone();
+        if self.index + 1 < self.tokens.len() {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn error_at<T>(&self, message: impl Into<String>, position: usize) -> Result<T, FilterError> {
+        Err(FilterError {
+            message: message.into(),
+            position,
+            expression: self.expression.to_string(),
+        })
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Ident(name) if name == keyword)
+    }
+
+    fn expect(&mut self, op: &'static str) -> Result<(), FilterError> {
+        if self.peek() == &Token::Op(op) {
+            self.next();
+            Ok(())
+        } else {
+            self.error_at(
+                format!("expected '{op}' but found {}", self.peek()),
+                self.position(),
+            )
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Node, FilterError> {
+        let mut node = self.parse_and()?;
+        while self.peek() == &Token::Op("||") || self.is_keyword("or") {
+            self.next();
+            node = Node::Or(Box::new(node), Box::new(self.parse_and()?));
+        }
+        Ok(node)
+    }
+
+    fn parse_and(&mut self) -> Result<Node, FilterError> {
+        let mut node = self.parse_unary()?;
+        while self.peek() == &Token::Op("&&") || self.is_keyword("and") {
+            self.next();
+            node = Node::And(Box::new(node), Box::new(self.parse_unary()?));
+        }
+        Ok(node)
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, FilterError> {
+        if self.peek() == &Token::Op("!") || self.is_keyword("not") {
+            self.next();
+            return Ok(Node::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.peek() == &Token::Op("(") {
+            self.next();
+            let node = self.parse_or()?;
+            self.expect(")")?;
+            return Ok(node);
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Node, FilterError> {
+        let (token, position) = self.next();
+        let Token::Ident(name) = token else {
+            return self.error_at(format!("expected a field name but found {token}"), position);
+        };
+        let Some(&(_, field_type)) = self.fields.iter().find(|(field, _)| match field {
+            prefix if prefix.ends_with('.') => {
+                name.len() > prefix.len() && name.starts_with(prefix)
+            }
+            field => *field == name,
+        }) else {
+            let known: Vec<String> = self
+                .fields
+                .iter()
+                .map(|(field, _)| match field {
+                    prefix if prefix.ends_with('.') => format!("{prefix}<key>"),
+                    field => field.to_string(),
+                })
+                .collect();
+            return self.error_at(
+                format!("unknown field '{name}' (available: {})", known.join(", ")),
+                position,
+            );
+        };
+
+        let (token, op_position) = self.next();
+        let op = match token {
+            Token::Op("==") => Op::Eq,
+            Token::Op("!=") => Op::Ne,
+            Token::Op("<") => Op::Lt,
+            Token::Op("<=") => Op::Le,
+            Token::Op(">") => Op::Gt,
+            Token::Op(">=") => Op::Ge,
+            Token::Op("~") => Op::Matches,
+            Token::Op("!~") => Op::NotMatches,
+            Token::Ident(ref word) if word == "in" => Op::In,
+            Token::Ident(ref word) if word == "contains" => Op::Contains,
+            other => {
+                return self.error_at(
+                    format!(
+                        "expected an operator (==, !=, <, <=, >, >=, in, contains, ~, !~) after '{name}' but found {other}"
+                    ),
+                    op_position,
+                );
+            }
+        };
+
+        let value_position = self.position();
+        let value = self.parse_value(&name, field_type, op)?;
+        self.check(&name, field_type, op, &value, op_position, value_position)?;
+        Ok(Node::Compare {
+            field: name,
+            op,
+            value,
+        })
+    }
+
+    fn parse_value(
+        &mut self,
+        field: &str,
+        field_type: FieldType,
+        op: Op,
+    ) -> Result<Literal, FilterError> {
+        let (token, position) = self.next();
+        let literal = match token {
+            Token::Op("[") => {
+                let mut items = Vec::new();
+                if self.peek() != &Token::Op("]") {
+                    loop {
+                        let (item, item_position) = self.next();
+                        match item {
+                            Token::Str(text) | Token::Ident(text) => items.push(text),
+                            Token::Num(n) => items.push(n.to_string()),
+                            other => {
+                                return self.error_at(
+                                    format!("expected a list item but found {other}"),
+                                    item_position,
+                                );
+                            }
+                        }
+                        if self.peek() == &Token::Op(",") {
+                            self.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.expect("]")?;
+                Literal::List(items)
+            }
+            Token::Ident(ref name) if name == "now" => {
+                self.expect("(")?;
+                self.expect(")")?;
+                let mut time = self.now;
+                while let Token::Op(sign @ ("+" | "-")) = *self.peek() {
+                    self.next();
+                    let (offset, offset_position) = self.next();
+                    let Token::Duration(duration) = offset else {
+                        return self.error_at(
+                            format!("expected a duration such as 2h or 30m but found {offset}"),
+                            offset_position,
+                        );
+                    };
+                    time = if sign == "+" {
+                        time + duration
+                    } else {
+                        time - duration
+                    };
+                }
+                Literal::Time(time)
+            }
+            Token::Ident(ref name) if name == "true" => Literal::Bool(true),
+            Token::Ident(ref name) if name == "false" => Literal::Bool(false),
+            Token::Ident(ref name) if name == "null" => Literal::Null,
+            Token::Op("-") => match self.next() {
+                (Token::Num(n), _) => Literal::Number(-n),
+                (other, other_position) => {
+                    return self.error_at(
+                        format!("expected a number after '-' but found {other}"),
+                        other_position,
+                    );
+                }
+            },
+            Token::Num(n) => Literal::Number(n),
+            Token::Str(text) | Token::Ident(text) => {
+                if matches!(op, Op::Matches | Op::NotMatches) {
+                    match Regex::new(&text) {
+                        Ok(regex) => Literal::Regex(regex),
+                        Err(e) => {
+                            return self.error_at(
+                                format!("invalid regular expression for '{field}': {e}"),
+                                position,
+                            );
+                        }
+                    }
+                } else if field_type == FieldType::Time {
+                    match DateTime::parse_from_rfc3339(&text) {
+                        Ok(time) => Literal::Time(time.with_timezone(&Utc)),
+                        Err(_) => {
+                            return self.error_at(
+                                format!(
+                                    "'{field}' is a time; use now(), now()-2h or an RFC 3339 timestamp"
+                                ),
+                                position,
+                            );
+                        }
+                    }
+                } else {
+                    Literal::Text(text)
+                }
+            }
+            other => {
+                return self.error_at(format!("expected a value but found {other}"), position);
+            }
+        };
+        Ok(literal)
+    }
+
+    /// フィールドの型と演算子・値の組み合わせを確かめる
+    fn check(
+        &self,
+        field: &str,
+        field_type: FieldType,
+        op: Op,
+        value: &Literal,
+        op_position: usize,
+        value_position: usize,
+    ) -> Result<(), FilterError> {
+        let type_name = match field_type {
+            FieldType::Text => "text",
+            FieldType::Number => "a number",
+            FieldType::Time => "a time",
+            FieldType::Bool => "a boolean",
+            FieldType::List => "a list",
+            FieldType::Enum(_) => "one of fixed values",
+        };
+        let ordered = matches!(
+            field_type,
+            FieldType::Text | FieldType::Number | FieldType::Time
+        );
+        match op {
+            Op::Lt | Op::Le | Op::Gt | Op::Ge if !ordered => {
+                return self.error_at(
+                    format!("'{field}' is {type_name} and cannot be compared with <, <=, > or >="),
+                    op_position,
+                );
+            }
+            Op::Contains if !matches!(field_type, FieldType::Text | FieldType::List) => {
+                return self.error_at(
+                    format!("'contains' needs a text or list field, but '{field}' is {type_name}"),
+                    op_position,
+                );
+            }
+            Op::Matches | Op::NotMatches
+                if !matches!(
+                    field_type,
+                    FieldType::Text | FieldType::List | FieldType::Enum(_)
+                ) =>
+            {
+                return self.error_at(
+                    format!("'~' needs a text field, but '{field}' is {type_name}"),
+                    op_position,
+                );
+            }
+            Op::In if !matches!(value, Literal::List(_)) => {
+                return self.error_at("'in' needs a list such as [\"a\", \"b\"]", value_position);
+            }
+            _ => {}
+        }
+        if matches!(value, Literal::Null) && !matches!(op, Op::Eq | Op::Ne) {
+            return self.error_at("null can only be compared with == or !=", value_position);
+        }
+
+        let compatible = matches!(
+            (field_type, value),
+            (_, Literal::Null | Literal::Regex(_) | Literal::List(_))
+                | (FieldType::Number, Literal::Number(_))
+                | (FieldType::Time, Literal::Time(_))
+                | (FieldType::Bool, Literal::Bool(_))
+                | (
+                    FieldType::Text | FieldType::List | FieldType::Enum(_),
+                    Literal::Text(_)
+                )
+                | (FieldType::Text | FieldType::List, Literal::Number(_))
+        );
+        if !compatible {
+            return self.error_at(
+                format!("'{field}' is {type_name} and cannot be compared with this value"),
+                value_position,
+            );
+        }
+        if field_type == FieldType::Number
+            && let Literal::List(items) = value
+            && let Some(item) = items.iter().find(|item| item.parse::<f64>().is_err())
+        {
+            return self.error_at(
+                format!("'{field}' is a number but the list contains '{item}'"),
+                value_position,
+            );
+        }
+
+        if let FieldType::Enum(allowed) = field_type {
+            let values: Vec<&str> = match value {
+                Literal::Text(text) => vec![text.as_str()],
+                Literal::List(items) => items.iter().map(String::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if let Some(unknown) = values.iter().find(|v| !allowed.contains(v)) {
+                return self.error_at(
+                    format!(
+                        "unknown {field} '{unknown}' (expected one of: {})",
+                        allowed.join(", ")
+                    ),
+                    value_position,
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FilterExpr {
+    /// 式を解釈する（`now()` は現在時刻）
+    pub fn parse(expression: &str, fields: Fields) -> Result<Self, FilterError> {
+        Self::parse_at(expression, fields, Utc::now())
+    }
+
+    /// `now()` を指定した時刻として式を解釈する
+    pub fn parse_at(
+        expression: &str,
+        fields: Fields,
+        now: DateTime<Utc>,
+    ) -> Result<Self, FilterError> {
+        let mut parser = Parser {
+            tokens: tokenize(expression)?,
+            index: 0,
+            fields,
+            now,
+            expression,
+        };
+        if parser.peek() == &Token::End {
+            return parser.error_at("empty filter expression", 0);
+        }
+        let root = parser.parse_or()?;
+        if parser.peek() != &Token::End {
+            return parser.error_at(
+                format!(
+                    "unexpected {} (combine conditions with && or ||)",
+                    parser.peek()
+                ),
+                parser.position(),
+            );
+        }
+        Ok(Self {
+            root,
+            source: expression.to_string(),
+        })
+    }
+
+    /// 元の式
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// 対象が式に一致するか
+    pub fn matches(&self, item: &impl Filterable) -> bool {
+        eval(&self.root, item)
+    }
+}
+
+fn eval(node: &Node, item: &impl Filterable) -> bool {
+    match node {
+        Node::And(left, right) => eval(left, item) && eval(right, item),
+        Node::Or(left, right) => eval(left, item) || eval(right, item),
+        Node::Not(inner) => !eval(inner, item),
+        Node::Compare { field, op, value } => compare(&item.field(field), *op, value),
+    }
+}
+
+fn compare(actual: &Value, op: Op, expected: &Literal) -> bool {
+    match (actual, expected) {
+        (Value::Null, Literal::Null) => op == Op::Eq,
+        (_, Literal::Null) => op == Op::Ne,
+        (Value::Null, _) => matches!(op, Op::Ne | Op::NotMatches),
+        (Value::List(items), _) => {
+            let any = |op| {
+                items
+                    .iter()
+                    .any(|item| compare(&Value::Text(item.clone()), op, expected))
+            };
+            match op {
+                Op::Ne => !any(Op::Eq),
+                Op::NotMatches => !any(Op::Matches),
+                Op::Contains => any(Op::Eq),
+                _ => any(op),
+            }
+        }
+        (_, Literal::List(items)) => {
+            let found = items.iter().any(|item| match actual {
+                Value::Number(n) => item.parse::<f64>().is_ok_and(|item| item == *n),
+                Value::Text(text) => text == item,
+                _ => false,
+            });
+            match op {
+                Op::In => found,
+                Op::Ne => !found,
+                _ => false,
+            }
+        }
+        (Value::Text(text), Literal::Regex(regex)) => match op {
+            Op::Matches => regex.is_match(text),
+            Op::NotMatches => !regex.is_match(text),
+            _ => false,
+        },
+        (Value::Text(text), Literal::Text(expected)) if op == Op::Contains => {
+            text.contains(expected.as_str())
+        }
+        (Value::Text(text), Literal::Number(n)) => ordering(op, text.cmp(&n.to_string())),
+        (Value::Text(text), Literal::Text(expected)) => ordering(op, text.cmp(expected)),
+        (Value::Number(n), Literal::Number(expected)) => n
+            .partial_cmp(expected)
+            .is_some_and(|order| ordering(op, order)),
+        (Value::Time(time), Literal::Time(expected)) => ordering(op, time.cmp(expected)),
+        (Value::Bool(b), Literal::Bool(expected)) => ordering(op, b.cmp(expected)),
+        _ => false,
+    }
+}
+
+fn ordering(op: Op, order: std::cmp::Ordering) -> bool {
+    use std::cmp::Ordering::*;
+    match op {
+        Op::Eq => order == Equal,
+        Op::Ne => order != Equal,
+        Op::Lt => order == Less,
+        Op::Le => order != Greater,
+        Op::Gt => order == Greater,
+        Op::Ge => order != Less,
+        _ => false,
+    }
+}
+
+/// プロセス用の式を解釈する
+pub fn parse_process_filter(expression: &str) -> Result<FilterExpr, FilterError> {
+    FilterExpr::parse(expression, PROCESS_FIELDS)
+}
+
+/// イベント用の式を解釈する
+pub fn parse_event_filter(expression: &str) -> Result<FilterExpr, FilterError> {
+    FilterExpr::parse(expression, EVENT_FIELDS)
+}
+
+fn optional<T>(value: Option<T>, wrap: impl FnOnce(T) -> Value) -> Value {
+    value.map(wrap).unwrap_or(Value::Null)
+}
+
+impl Filterable for ProcessInfo {
+    fn field(&self, name: &str) -> Value {
+        let state = &self.state;
+        match name {
+            "id" => Value::Text(self.id.clone()),
+            "command" => Value::Text(self.command.clone()),
+            "args" => Value::List(self.args.clone()),
+            "cwd" => optional(self.cwd.as_ref(), |cwd| {
+                Value::Text(cwd.display().to_string())
+            }),
+            "state" => Value::Text(
+                match state {
+                    ProcessState::NotStarted => "not_started",
+                    ProcessState::Running { .. } => "running",
+                    ProcessState::Paused { .. } => "paused",
+                    ProcessState::Stopped { .. } => "stopped",
+                    ProcessState::Failed { .. } => "failed",
+                }
+                .to_string(),
+            ),
+            "tag" | "tags" => Value::List(self.tags.clone()),
+            "group" => optional(self.group.as_ref(), |group| Value::Text(group.clone())),
+            "pid" => match state {
+                ProcessState::Running { pid, .. } | ProcessState::Paused { pid, .. } => {
+                    Value::Number(*pid as f64)
+                }
+                _ => Value::Null,
+            },
+            "started_at" => match state {
+                ProcessState::Running { started_at, .. }
+                | ProcessState::Paused { started_at, .. } => Value::Time(*started_at),
+                _ => Value::Null,
+            },
+            "stopped_at" => match state {
+                ProcessState::Stopped { stopped_at, .. } => Value::Time(*stopped_at),
+                ProcessState::Failed { failed_at, .. } => Value::Time(*failed_at),
+                _ => Value::Null,
+            },
+            "exit_code" => match state {
+                ProcessState::Stopped { exit_code, .. } => {
+                    optional(*exit_code, |code| Value::Number(code as f64))
+                }
+                _ => Value::Null,
+            },
+            "error" => match state {
+                ProcessState::Failed { error, .. } => Value::Text(error.clone()),
+                _ => Value::Null,
+            },
+            "auto_start" => Value::Bool(self.auto_start_on_restore),
+            "healthy" => optional(self.health.as_ref(), |health| Value::Bool(health.healthy)),
+            _ => match name.strip_prefix("metadata.") {
+                Some(key) => optional(self.metadata.get(key), |value| Value::Text(value.clone())),
+                None => Value::Null,
+            },
+        }
+    }
+}
+
+impl Filterable for ProcessEvent {
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "type" => Value::Text(
+                match self.event_type {
+                    EventType::ProcessStarted => "process_started",
+                    EventType::ProcessStopped => "process_stopped",
+                    EventType::ProcessError => "process_error",
+                    EventType::ProcessRecovered => "process_recovered",
+                    EventType::ProcessCreated => "process_created",
+                    EventType::ProcessRemoved => "process_removed",
+                    EventType::ProcessIdle => "process_idle",
+                    EventType::ProcessUnhealthy => "process_unhealthy",
+                    EventType::ProcessPaused => "process_paused",
+                    EventType::ProcessResumed => "process_resumed",
+                    EventType::CrashLoopDetected => "crash_loop_detected",
+                    EventType::RestartScheduled => "restart_scheduled",
+                    EventType::ProcessRestarted => "process_restarted",
+                    EventType::RestartLimitReached => "restart_limit_reached",
+                    EventType::ProcessStateCorrected => "process_state_corrected",
+                    EventType::ChaosInjected => "chaos_injected",
+                    EventType::MaintenanceStarted => "maintenance_started",
+                    EventType::MaintenanceEnded => "maintenance_ended",
+                    EventType::SensitiveEnvAccessed => "sensitive_env_accessed",
+                    EventType::ProcessAdopted => "process_adopted",
+                    EventType::SnapshotRecovered => "snapshot_recovered",
+                    EventType::ScheduledRun => "scheduled_run",
+                }
+                .to_string(),
+            ),
+            "process_id" => Value::Text(self.process_id.clone()),
+            "timestamp" => Value::Time(self.timestamp),
+            _ => Value::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn process(id: &str, state: ProcessState, tags: &[&str]) -> ProcessInfo {
+        ProcessInfo {
+            id: id.to_string(),
+            command: "node".to_string(),
+            args: vec!["server.js".to_string()],
+            env: HashMap::new(),
+            cwd: None,
+            state,
+            auto_start_on_restore: false,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            idle_detection: None,
+            health_check: None,
+            tmux: None,
+            repl: None,
+            log_sources: Vec::new(),
+            run_as: None,
+            resource_limits: None,
+            container: None,
+            source: None,
+            log_sinks: Vec::new(),
+            redaction: None,
+            metadata: HashMap::new(),
+            runbook: None,
+            startup: None,
+            lazy_start: None,
+            env_loader: None,
+            output_recording: None,
+            assertions: Vec::new(),
+            restart_policy: None,
+            depends_on: Vec::new(),
+            parameters: Vec::new(),
+            keep_stdin_open: false,
+            group: None,
+            health: None,
+            sensitive_env: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_process_filter_matches() {
+        let now = Utc::now();
+        let api = process(
+            "api",
+            ProcessState::Running {
+                pid: 42,
+                started_at: now - Duration::minutes(30),
+            },
+            &["backend"],
+        );
+        let old = process(
+            "worker",
+            ProcessState::Running {
+                pid: 43,
+                started_at: now - Duration::hours(5),
+            },
+            &["backend", "jobs"],
+        );
+        let web = process(
+            "web",
+            ProcessState::Stopped {
+                exit_code: Some(1),
+                stopped_at: now,
+            },
+            &["frontend"],
+        );
+
+        let expr = FilterExpr::parse_at(
+            r#"state == running && tag in ["backend"] && started_at > now()-2h"#,
+            PROCESS_FIELDS,
+            now,
+        )
+        .unwrap();
+        assert!(expr.matches(&api));
+        assert!(!expr.matches(&old));
+        assert!(!expr.matches(&web));
+
+        let expr = FilterExpr::parse_at(
+            "(exit_code != 0 || tags contains jobs) and not id ~ '^a'",
+            PROCESS_FIELDS,
+            now,
+        )
+        .unwrap();
+        assert!(!expr.matches(&api));
+        assert!(expr.matches(&old));
+        assert!(expr.matches(&web));
+
+        let mut owned = web.clone();
+        owned
+            .metadata
+            .insert("owner".to_string(), "team-web".to_string());
+        let expr = parse_process_filter("metadata.owner == team-web").unwrap();
+        assert!(expr.matches(&owned));
+        assert!(!expr.matches(&web));
+
+        let expr = parse_process_filter("pid == null").unwrap();
+        assert!(expr.matches(&web));
+        assert!(!expr.matches(&api));
+    }
+
+    #[test]
+    fn test_parse_errors_point_at_problem() {
+        let error = parse_process_filter("state == runing").unwrap_err();
+        assert_eq!(error.position, 9);
+        assert!(error.message.contains("unknown state 'runing'"));
+        assert!(error.to_string().ends_with("\n           ^"));
+
+        let error = parse_process_filter("status == running").unwrap_err();
+        assert!(error.message.starts_with("unknown field 'status'"));
+
+        let error = parse_process_filter("tag in backend").unwrap_err();
+        assert!(error.message.contains("'in' needs a list"));
+
+        let error = parse_process_filter("started_at > now()-2x").unwrap_err();
+        assert!(error.message.contains("unknown duration unit 'x'"));
+
+        let error = parse_process_filter("state == running state == stopped").unwrap_err();
+        assert_eq!(error.position, 17);
+
+        assert!(parse_process_filter("(state == running").is_err());
+        assert!(parse_process_filter("pid > \"abc\"").is_err());
+        assert!(parse_process_filter("").is_err());
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let event = ProcessEvent::new(EventType::ProcessError, "api".to_string(), None, None);
+        let expr = parse_event_filter(
+            "type in [process_error, crash_loop_detected] && timestamp > now()-1m",
+        )
+        .unwrap();
+        assert!(expr.matches(&event));
+        assert!(
+            !parse_event_filter("process_id != api")
+                .unwrap()
+                .matches(&event)
+        );
+        assert!(parse_event_filter("type == exploded").is_err());
+    }
+}
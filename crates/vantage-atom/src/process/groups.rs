@@ -0,0 +1,158 @@
+//! まとめて起動・停止するプロセスのグループ
+//!
+//! `group` を指定したプロセスは `start_group` で起動順序（`startup` の優先度と並列グループ）に
+//! 従ってまとめて起動し、`stop_group` でその逆順に停止します。フロントエンド・バックエンド・
+//! データベースのように一緒に使うプロセスを1回の呼び出しで立ち上げ・片付けられます。
+
+use super::dependencies::{self, DependencyGraph};
+use super::types::{ProcessInfo, ProcessState};
+use serde::Serialize;
+use utoipa::ToSchema;
+use vantage_persistence::StartupOrder;
+
+/// グループ名の最大の長さ
+pub const MAX_NAME_LEN: usize = 64;
+
+/// グループ名を検証
+pub fn validate(group: &str) -> Result<(), String> {
+    if group.trim().is_empty() {
+        return Err("group must not be empty".to_string());
+    }
+    if group.len() > MAX_NAME_LEN {
+        return Err(format!("group must be at most {MAX_NAME_LEN} characters"));
+    }
+    if group.chars().any(char::is_control) {
+        return Err("group must not contain control characters".to_string());
+    }
+    Ok(())
+}
+
+/// 停止する順に並べる（起動する順の逆。同時に起動するプロセスは同時に停止する）
+pub fn stop_order(
+    members: Vec<(String, StartupOrder)>,
+    graph: &DependencyGraph,
+) -> Vec<Vec<String>> {
+    dependencies::plan(members, graph)
+        .into_iter()
+        .rev()
+        .map(|batch| batch.into_iter().map(|(id, _)| id).collect())
+        .collect()
+}
+
+/// グループのプロセスごとの状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GroupMember {
+    pub process_id: String,
+    /// `not_started`・`running`・`paused`・`stopped`・`failed`
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// ヘルスチェックを設定している場合、その判定
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub healthy: Option<bool>,
+    /// 何番目にまとめて起動するか（0始まり）
+    pub batch: usize,
+}
+
+/// グループの状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GroupStatus {
+    pub group: String,
+    /// 起動する順
+    pub processes: Vec<GroupMember>,
+    /// 実行中（一時停止を含む）のプロセスの数
+    pub running: usize,
+    /// すべてのプロセスが実行中で、ヘルスチェックが異常なものがないか
+    pub all_running: bool,
+}
+
+impl GroupStatus {
+    pub fn new(group: &str, members: &[ProcessInfo], graph: &DependencyGraph) -> Self {
+        let plan = dependencies::plan(
+            members
+                .iter()
+                .map(|info| (info.id.clone(), info.startup.clone().unwrap_or_default()))
+                .collect(),
+            graph,
+        );
+        let mut processes = Vec::new();
+        for (batch, ids) in plan.into_iter().enumerate() {
+            for (id, _) in ids {
+                let Some(info) = members.iter().find(|info| info.id == id) else {
+                    continue;
+                };
+                let (state, pid) = match &info.state {
+                    ProcessState::NotStarted => ("not_started", None),
+                    ProcessState::Running { pid, .. } => ("running", Some(*pid)),
+                    ProcessState::Paused { pid, .. } => ("paused", Some(*pid)),
+                    ProcessState::Stopped { .. } => ("stopped", None),
+                    ProcessState::Failed { .. } => ("failed", None),
+                };
+                processes.push(GroupMember {
+                    process_id: id,
+                    state: state.to_string(),
+                    pid,
+                    healthy: info.health.as_ref().map(|health| health.healthy),
+                    batch,
+                });
+            }
+        }
+        let running = processes
+            .iter()
+            .filter(|member| member.pid.is_some())
+            .count();
+        let all_running = processes
+            .iter()
+            .all(|member| member.state == "running" && member.healthy != Some(false));
+        Self {
+            group: group.to_string(),
+            processes,
+            running,
+            all_running,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: &str, priority: i32, group: Option<&str>) -> (String, StartupOrder) {
+        (
+            id.to_string(),
+            StartupOrder {
+                priority,
+                group: group.map(str::to_string),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_stop_order_reverses_the_startup_order() {
+        let order = stop_order(
+            vec![
+                member("frontend", 20, None),
+                member("db", 0, None),
+                member("api", 10, Some("backend")),
+                member("worker", 10, Some("backend")),
+            ],
+            &DependencyGraph::new(),
+        );
+        assert_eq!(
+            order,
+            vec![
+                vec!["frontend".to_string()],
+                vec!["api".to_string(), "worker".to_string()],
+                vec!["db".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_and_long_names() {
+        assert!(validate("stack").is_ok());
+        assert!(validate(" ").is_err());
+        assert!(validate(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! ヘルスチェックのプローブ
+//!
+//! HTTPのGET・TCPの接続・コマンドの実行で、PIDが生きていても応答しなくなったプロセスを
+//! 検知します。プローブはヘルスチェックの監視タスクから `interval_secs` ごとに別のタスクで
+//! 実行し、結果を `ProbeTracker` に記録します。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use utoipa::ToSchema;
+use vantage_persistence::{HealthCheckConfig, HealthProbe};
+
+/// 失敗の詳細に含める出力の長さの上限
+const MAX_DETAIL_CHARS: usize = 200;
+
+/// プローブの設定を検証
+pub fn validate(config: &HealthCheckConfig) -> Result<(), String> {
+    let Some(probe) = &config.probe else {
+        return Ok(());
+    };
+    if config.interval_secs == 0 {
+        return Err("health_check.interval_secs must be at least 1".to_string());
+    }
+    if config.timeout_secs == 0 {
+        return Err("health_check.timeout_secs must be at least 1".to_string());
+    }
+    if config.failure_threshold == 0 {
+        return Err("health_check.failure_threshold must be at least 1".to_string());
+    }
+    match probe {
+        HealthProbe::Http {
+            url,
+            expected_status,
+        } => {
+            let parsed = reqwest::Url::parse(url)
+                .map_err(|e| format!("health_check.probe.url is invalid: {e}"))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err("health_check.probe.url must be an http or https URL".to_string());
+            }
+            if expected_status.is_some_and(|status| !(100..=599).contains(&status)) {
+                return Err(
+                    "health_check.probe.expected_status must be between 100 and 599".to_string(),
+                );
+            }
+        }
+        HealthProbe::Tcp { port, .. } => {
+            if *port == 0 {
+                return Err("health_check.probe.port must not be 0".to_string());
+            }
+        }
+        HealthProbe::Exec { command, .. } => {
+            if command.trim().is_empty() {
+                return Err("health_check.probe.command must not be empty".to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// プローブ1回の結果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ProbeOutcome {
+    pub success: bool,
+    /// ステータスコードやエラーの内容
+    pub detail: String,
+    pub duration_ms: u64,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// プローブの状態
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ProbeStatus {
+    /// 続けて失敗した回数
+    pub consecutive_failures: u32,
+    pub last: ProbeOutcome,
+    /// 最後に成功した時刻
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_success_at: Option<DateTime<Utc>>,
+}
+
+/// 実行中のプロセスのプローブの実行状況
+#[derive(Debug, Default)]
+pub struct ProbeTracker {
+    /// 実行中のプローブの対象のPID
+    in_flight: Option<u32>,
+    last_started: Option<Instant>,
+    status: Option<ProbeStatus>,
+}
+
+impl ProbeTracker {
+    /// 間隔が空いていて実行中でなければ、`pid` を対象に始める
+    pub fn try_begin(&mut self, pid: u32, interval: Duration) -> bool {
+        if self.in_flight.is_some()
+            || self
+                .last_started
+                .is_some_and(|started| started.elapsed() < interval)
+        {
+            return false;
+        }
+        self.in_flight = Some(pid);
+        self.last_started = Some(Instant::now());
+        true
+    }
+
+    /// 結果を記録する（起動し直した後に届いた前の実行の結果は捨てる）
+    pub fn finish(&mut self, pid: u32, outcome: ProbeOutcome) {
+        if self.in_flight != Some(pid) {
+            return;
+        }
+        self.in_flight = None;
+        let previous = self.status.take();
+        let last_success_at = if outcome.success {
+            Some(outcome.checked_at)
+        } else {
+            previous.as_ref().and_then(|status| status.last_success_at)
+        };
+        let consecutive_failures = if outcome.success {
+            0
+        } else {
+            previous.map_or(0, |status| status.consecutive_failures) + 1
+        };
+        self.status = Some(ProbeStatus {
+            consecutive_failures,
+            last: outcome,
+            last_success_at,
+        });
+    }
+
+    /// 起動・停止のたびに結果を捨てる
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn status(&self) -> Option<&ProbeStatus> {
+        self.status.as_ref()
+    }
+
+    /// しきい値を超えて失敗している場合、その理由
+    pub fn failure(&self, config: &HealthCheckConfig) -> Option<String> {
+        let probe = config.probe.as_ref()?;
+        let status = self.status.as_ref()?;
+        if status.consecutive_failures < config.failure_threshold {
+            return None;
+        }
+        Some(format!(
+            "{} probe failed {} time(s) in a row: {}",
+            kind(probe),
+            status.consecutive_failures,
+            status.last.detail
+        ))
+    }
+}
+
+fn kind(probe: &HealthProbe) -> &'static str {
+    match probe {
+        HealthProbe::Http { .. } => "HTTP",
+        HealthProbe::Tcp { .. } => "TCP",
+        HealthProbe::Exec { .. } => "Exec",
+    }
+}
+
+/// プローブを1回実行する
+pub async fn run(probe: &HealthProbe, cwd: Option<&Path>, timeout: Duration) -> ProbeOutcome {
+    let checked_at = Utc::now();
+    let started = Instant::now();
+    let result = match tokio::time::timeout(timeout, execute(probe, cwd, timeout)).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("Timed out after {}s", timeout.as_secs())),
+    };
+    let (success, detail) = match result {
+        Ok(detail) => (true, detail),
+        Err(detail) => (false, detail),
+    };
+    ProbeOutcome {
+        success,
+        detail,
+        duration_ms: started.elapsed().as_millis() as u64,
+        checked_at,
+    }
+}
+
+async fn execute(
+    probe: &HealthProbe,
+    cwd: Option<&Path>,
+    timeout: Duration,
+) -> Result<String, String> {
+    match probe {
+        HealthProbe::Http {
+            url,
+            expected_status,
+        } => {
+            let client = reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
+            let response = client
+                .get(url)
+                .send()
+                .await
+                .map_err(|e| format!("GET {url} failed: {e}"))?;
+            let status = response.status();
+            let ok = match expected_status {
+                Some(expected) => status.as_u16() == *expected,
+                None => status.is_success() || status.is_redirection(),
+            };
+            if ok {
+                Ok(format!("HTTP {}", status.as_u16()))
+            } else {
+                Err(format!("GET {url} returned HTTP {}", status.as_u16()))
+            }
+        }
+        HealthProbe::Tcp { port, host } => {
+            let host = host.as_deref().unwrap_or("127.0.0.1");
+            TcpStream::connect((host, *port))
+                .await
+                .map(|_| format!("Connected to {host}:{port}"))
+                .map_err(|e| format!("Failed to connect to {host}:{port}: {e}"))
+        }
+        HealthProbe::Exec { command, args } => {
+            let mut cmd = tokio::process::Command::new(command);
+            cmd.args(args).kill_on_drop(true);
+            if let Some(cwd) = cwd {
+                cmd.current_dir(cwd);
+            }
+            let output = cmd
+                .output()
+                .await
+                .map_err(|e| format!("Failed to run '{command}': {e}"))?;
+            let text = String::from_utf8_lossy(if output.stderr.is_empty() {
+                &output.stdout
+            } else {
+                &output.stderr
+            })
+            .trim()
+            .chars()
+            .take(MAX_DETAIL_CHARS)
+            .collect::<String>();
+            match output.status.code() {
+                Some(0) => Ok("Exited with code 0".to_string()),
+                code => Err(format!(
+                    "'{command}' exited with {}{}",
+                    code.map_or("a signal".to_string(), |code| format!("code {code}")),
+                    if text.is_empty() {
+                        String::new()
+                    } else {
+                        format!(": {text}")
+                    }
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(probe: HealthProbe) -> HealthCheckConfig {
+        HealthCheckConfig {
+            max_errors_per_minute: None,
+            max_stderr_ratio: None,
+            window_secs: 300,
+            probe: Some(probe),
+            interval_secs: 10,
+            timeout_secs: 5,
+            failure_threshold: 2,
+            action: Default::default(),
+        }
+    }
+
+    fn outcome(success: bool) -> ProbeOutcome {
+        ProbeOutcome {
+            success,
+            detail: if success { "HTTP 200" } else { "HTTP 503" }.to_string(),
+            duration_ms: 1,
+            checked_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_tracker_counts_consecutive_failures() {
+        let config = config(HealthProbe::Http {
+            url: "http://127.0.0.1:8080/health".to_string(),
+            expected_status: None,
+        });
+        let mut tracker = ProbeTracker::default();
+        assert!(tracker.try_begin(100, Duration::from_secs(10)));
+        // 実行中や間隔が空いていない間は始めない
+        assert!(!tracker.try_begin(100, Duration::ZERO));
+        tracker.finish(100, outcome(false));
+        assert!(tracker.failure(&config).is_none());
+        assert!(!tracker.try_begin(100, Duration::from_secs(10)));
+
+        assert!(tracker.try_begin(100, Duration::ZERO));
+        tracker.finish(100, outcome(false));
+        assert_eq!(
+            tracker.failure(&config).as_deref(),
+            Some("HTTP probe failed 2 time(s) in a row: HTTP 503")
+        );
+
+        assert!(tracker.try_begin(100, Duration::ZERO));
+        tracker.finish(100, outcome(true));
+        assert!(tracker.failure(&config).is_none());
+        assert!(tracker.status().unwrap().last_success_at.is_some());
+
+        // 前の実行のプローブの結果は記録しない
+        assert!(tracker.try_begin(100, Duration::ZERO));
+        tracker.reset();
+        tracker.finish(100, outcome(false));
+        assert!(tracker.status().is_none());
+    }
+
+    #[test]
+    fn test_validate_probes() {
+        assert!(
+            validate(&config(HealthProbe::Http {
+                url: "ftp://example.com".to_string(),
+                expected_status: None,
+            }))
+            .is_err()
+        );
+        assert!(
+            validate(&config(HealthProbe::Exec {
+                command: " ".to_string(),
+                args: Vec::new(),
+            }))
+            .is_err()
+        );
+        let mut tcp = config(HealthProbe::Tcp {
+            port: 5432,
+            host: None,
+        });
+        assert!(validate(&tcp).is_ok());
+        tcp.failure_threshold = 0;
+        assert!(validate(&tcp).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exec_and_tcp_probes() {
+        let timeout = Duration::from_secs(5);
+        let ok = run(
+            &HealthProbe::Exec {
+                command: "sh".to_string(),
+                args: vec!["-c".to_string(), "exit 0".to_string()],
+            },
+            None,
+            timeout,
+        )
+        .await;
+        assert!(ok.success);
+        let failed = run(
+            &HealthProbe::Exec {
+                command: "sh".to_string(),
+                args: vec!["-c".to_string(), "echo not ready >&2; exit 2".to_string()],
+            },
+            None,
+            timeout,
+        )
+        .await;
+        assert_eq!(failed.detail, "'sh' exited with code 2: not ready");
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let probe = HealthProbe::Tcp { port, host: None };
+        assert!(run(&probe, None, timeout).await.success);
+        drop(listener);
+        assert!(!run(&probe, None, timeout).await.success);
+    }
+}
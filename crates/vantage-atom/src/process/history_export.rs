@@ -0,0 +1,305 @@
+//! 実行履歴の書き出し
+//!
+//! 実行（起動から終了まで）、イベント、出力の行数の推移を表の形にまとめ、CSVまたはParquetの
+//! ファイルに書き出します。ノートブックなどで安定性の推移を分析するためのもので、
+//! SurrealDBを直接参照せずに済みます。Parquetは `parquet-export` フィーチャーで有効になります。
+//! 元になる記録はサーバーのメモリ上にあるため、書き出せるのは保持している範囲（実行はプロセスごとに
+//! 直近の数回、イベントは直近の1000件、行数は直近1時間）に限られます。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const PARQUET_UNAVAILABLE: &str =
+    "Parquet export is not available: the server was built without the parquet-export feature";
+
+/// 書き出す形式
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+/// 列の型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    /// UTCの時刻（CSVではRFC 3339、Parquetではミリ秒のタイムスタンプ）
+    Timestamp,
+    Integer,
+    Float,
+    Text,
+}
+
+/// 表の1つの値
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Timestamp(DateTime<Utc>),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Null,
+}
+
+impl From<Option<DateTime<Utc>>> for Value {
+    fn from(value: Option<DateTime<Utc>>) -> Self {
+        value.map_or(Self::Null, Self::Timestamp)
+    }
+}
+
+impl From<Option<i64>> for Value {
+    fn from(value: Option<i64>) -> Self {
+        value.map_or(Self::Null, Self::Integer)
+    }
+}
+
+impl From<Option<f64>> for Value {
+    fn from(value: Option<f64>) -> Self {
+        value.map_or(Self::Null, Self::Float)
+    }
+}
+
+impl From<Option<String>> for Value {
+    fn from(value: Option<String>) -> Self {
+        value.map_or(Self::Null, Self::Text)
+    }
+}
+
+/// 書き出す表
+#[derive(Debug, Clone)]
+pub struct Table {
+    /// ファイル名（拡張子なし）
+    pub name: &'static str,
+    pub columns: Vec<(&'static str, ColumnType)>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(name: &'static str, columns: Vec<(&'static str, ColumnType)>) -> Self {
+        Self {
+            name,
+            columns,
+            rows: Vec::new(),
+        }
+    }
+}
+
+/// 書き出したファイル
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedTable {
+    pub table: String,
+    pub path: String,
+    pub rows: usize,
+}
+
+/// 書き出しの結果
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryExport {
+    pub format: ExportFormat,
+    pub directory: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    pub tables: Vec<ExportedTable>,
+}
+
+/// 既定の出力先（書き出した時刻ごとのディレクトリ）
+pub fn default_dir(now: DateTime<Utc>) -> PathBuf {
+    vantage_persistence::paths::get()
+        .history_export_dir()
+        .join(now.format("%Y%m%d-%H%M%S").to_string())
+}
+
+/// 表をそれぞれ `<dir>/<name>.<ext>` に書き出す
+pub fn write_tables(
+    tables: &[Table],
+    dir: &Path,
+    format: ExportFormat,
+) -> Result<Vec<ExportedTable>, String> {
+    if format == ExportFormat::Parquet && !cfg!(feature = "parquet-export") {
+        return Err(PARQUET_UNAVAILABLE.to_string());
+    }
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create directory {}: {e}", dir.display()))?;
+    let mut exported = Vec::with_capacity(tables.len());
+    for table in tables {
+        let path = dir.join(format!("{}.{}", table.name, format.extension()));
+        let result = match format {
+            ExportFormat::Csv => write_csv(table, &path).map_err(|e| e.to_string()),
+            ExportFormat::Parquet => write_parquet(table, &path),
+        };
+        result.map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        exported.push(ExportedTable {
+            table: table.name.to_string(),
+            path: path.display().to_string(),
+            rows: table.rows.len(),
+        });
+    }
+    Ok(exported)
+}
+
+/// CSVの1つの値（区切り文字・引用符・改行を含む場合は引用符で囲む）
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Timestamp(at) => at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        Value::Integer(n) => n.to_string(),
+        Value::Float(n) => n.to_string(),
+        Value::Text(text) => text.clone(),
+        Value::Null => String::new(),
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+fn write_csv(table: &Table, path: &Path) -> io::Result<()> {
+    let mut out = io::BufWriter::new(fs::File::create(path)?);
+    let header: Vec<&str> = table.columns.iter().map(|(name, _)| *name).collect();
+    writeln!(out, "{}", header.join(","))?;
+    for row in &table.rows {
+        let fields: Vec<String> = row.iter().map(csv_field).collect();
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    out.flush()
+}
+
+#[cfg(feature = "parquet-export")]
+fn write_parquet(table: &Table, path: &Path) -> Result<(), String> {
+    use arrow_array::{
+        ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, TimestampMillisecondArray,
+    };
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    let fields: Vec<Field> = table
+        .columns
+        .iter()
+        .map(|(name, column_type)| {
+            let data_type = match column_type {
+                ColumnType::Timestamp => {
+                    DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+                }
+                ColumnType::Integer => DataType::Int64,
+                ColumnType::Float => DataType::Float64,
+                ColumnType::Text => DataType::Utf8,
+            };
+            Field::new(*name, data_type, true)
+        })
+        .collect();
+    let schema = Arc::new(Schema::new(fields));
+
+    let columns: Vec<ArrayRef> = table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(index, (_, column_type))| {
+            let cells = table.rows.iter().map(|row| row.get(index));
+            let array: ArrayRef = match column_type {
+                ColumnType::Timestamp => Arc::new(
+                    cells
+                        .map(|value| match value {
+                            Some(Value::Timestamp(at)) => Some(at.timestamp_millis()),
+                            _ => None,
+                        })
+                        .collect::<TimestampMillisecondArray>()
+                        .with_timezone("UTC"),
+                ),
+                ColumnType::Integer => Arc::new(
+                    cells
+                        .map(|value| match value {
+                            Some(Value::Integer(n)) => Some(*n),
+                            _ => None,
+                        })
+                        .collect::<Int64Array>(),
+                ),
+                ColumnType::Float => Arc::new(
+                    cells
+                        .map(|value| match value {
+                            Some(Value::Float(n)) => Some(*n),
+                            Some(Value::Integer(n)) => Some(*n as f64),
+                            _ => None,
+                        })
+                        .collect::<Float64Array>(),
+                ),
+                ColumnType::Text => Arc::new(
+                    cells
+                        .map(|value| match value {
+                            Some(Value::Text(text)) => Some(text.as_str()),
+                            _ => None,
+                        })
+                        .collect::<StringArray>(),
+                ),
+            };
+            array
+        })
+        .collect();
+
+    let batch = RecordBatch::try_new(schema.clone(), columns).map_err(|e| e.to_string())?;
+    let file = fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut writer = ArrowWriter::try_new(file, schema, None).map_err(|e| e.to_string())?;
+    writer.write(&batch).map_err(|e| e.to_string())?;
+    writer.close().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet-export"))]
+fn write_parquet(_table: &Table, _path: &Path) -> Result<(), String> {
+    Err(PARQUET_UNAVAILABLE.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_escapes_fields_and_leaves_nulls_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = Table::new(
+            "events",
+            vec![
+                ("timestamp", ColumnType::Timestamp),
+                ("exit_code", ColumnType::Integer),
+                ("context", ColumnType::Text),
+            ],
+        );
+        let at = DateTime::parse_from_rfc3339("2026-01-02T03:04:05Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        table.rows.push(vec![
+            Value::Timestamp(at),
+            Value::Integer(1),
+            Value::Text(r#"say "hi", then exit"#.to_string()),
+        ]);
+        table
+            .rows
+            .push(vec![Value::Timestamp(at), Value::Null, Value::Null]);
+
+        let exported = write_tables(&[table], dir.path(), ExportFormat::Csv).unwrap();
+        assert_eq!(exported[0].rows, 2);
+        let csv = fs::read_to_string(&exported[0].path).unwrap();
+        assert_eq!(
+            csv,
+            "timestamp,exit_code,context\n\
+             2026-01-02T03:04:05.000Z,1,\"say \"\"hi\"\", then exit\"\n\
+             2026-01-02T03:04:05.000Z,,\n"
+        );
+    }
+}
@@ -0,0 +1,656 @@
+//! Kubernetesのワークロードの取り込み
+//!
+//! ローカルと開発クラスタにまたがる構成向けに、クラスタで動いているPod・Deploymentを読み取り専用の
+//! 外部プロセスとして登録します。状態はクラスタから定期的に取得し、ログは通常のプロセスと同じ
+//! バッファに取り込むので `get_process_output` で読めます。ローカルのポートからPodへの転送も
+//! 管理します。クラスタへの接続には `kubernetes` フィーチャーが必要です。
+
+use super::buffer::CircularBuffer;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::info;
+use utoipa::ToSchema;
+use vantage_persistence::{KubernetesPortForward, KubernetesWorkload, PersistenceManager};
+
+/// ログのバッファに残す行数
+const OUTPUT_CAPACITY: usize = 1000;
+/// 1つのワークロードに設定できるポート転送の数
+const MAX_PORT_FORWARDS: usize = 16;
+
+/// Podの状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PodSummary {
+    pub name: String,
+    /// Podのフェーズ（Pending・Running・Succeeded・Failed・Unknown）
+    pub phase: String,
+    /// すべてのコンテナが準備完了か
+    pub ready: bool,
+    /// コンテナの再起動回数の合計
+    pub restarts: i32,
+    pub node: Option<String>,
+}
+
+/// ポート転送の状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PortForwardStatus {
+    #[serde(flatten)]
+    pub forward: KubernetesPortForward,
+    /// 転送した接続の数
+    pub connections: u64,
+    /// 転送中の接続の数
+    pub active_connections: u64,
+    pub last_error: Option<String>,
+}
+
+/// ワークロードの状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WorkloadStatus {
+    #[serde(flatten)]
+    pub config: KubernetesWorkload,
+    /// 実際に使っている名前空間
+    pub resolved_namespace: Option<String>,
+    pub pods: Vec<PodSummary>,
+    /// 準備完了のPodの数
+    pub ready_pods: usize,
+    /// ログを取り込んでいるPod
+    pub following: Vec<String>,
+    pub port_forwards: Vec<PortForwardStatus>,
+    /// 直近のクラスタへの接続エラー
+    pub last_error: Option<String>,
+    /// 最後に状態を取得した時刻
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// 監視タスクと共有する状態
+struct Shared {
+    stdout: CircularBuffer,
+    stderr: CircularBuffer,
+    state: RwLock<WorkloadState>,
+}
+
+#[derive(Default)]
+struct WorkloadState {
+    namespace: Option<String>,
+    pods: Vec<PodSummary>,
+    following: HashSet<String>,
+    port_forwards: Vec<PortForwardStatus>,
+    last_error: Option<String>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "kubernetes")]
+impl Shared {
+    /// 接続エラーなどを記録し、stderrのバッファにも残す（同じエラーは繰り返さない）
+    async fn report_error(&self, error: String) {
+        let mut state = self.state.write().await;
+        if state.last_error.as_ref() != Some(&error) {
+            self.stderr.push(format!("[vantage] {error}")).await;
+            state.last_error = Some(error);
+        }
+    }
+}
+
+struct ManagedWorkload {
+    config: KubernetesWorkload,
+    shared: Arc<Shared>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for ManagedWorkload {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Kubernetesのワークロードの管理
+pub struct KubernetesManager {
+    workloads: RwLock<HashMap<String, ManagedWorkload>>,
+    persistence: Arc<PersistenceManager>,
+}
+
+impl KubernetesManager {
+    pub fn new(persistence: Arc<PersistenceManager>) -> Self {
+        Self {
+            workloads: RwLock::new(HashMap::new()),
+            persistence,
+        }
+    }
+
+    /// ワークロードを登録し、状態の取得・ログの取り込み・ポート転送を始める
+    pub async fn register(&self, config: KubernetesWorkload) -> Result<WorkloadStatus, String> {
+        validate(&config)?;
+        if self
+            .workloads
+            .read()
+            .await
+            .contains_key(&config.workload_id)
+        {
+            return Err(format!(
+                "Kubernetes workload '{}' already exists",
+                config.workload_id
+            ));
+        }
+
+        let stdout = CircularBuffer::new(OUTPUT_CAPACITY);
+        let stderr = stdout.paired(OUTPUT_CAPACITY);
+        let shared = Arc::new(Shared {
+            stdout,
+            stderr,
+            state: RwLock::new(WorkloadState {
+                port_forwards: config
+                    .port_forwards
+                    .iter()
+                    .map(|forward| PortForwardStatus {
+                        forward: *forward,
+                        connections: 0,
+                        active_connections: 0,
+                        last_error: None,
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+        });
+        let tasks = attach(&config, shared.clone()).await?;
+
+        let mut workloads = self.workloads.write().await;
+        if workloads.contains_key(&config.workload_id) {
+            return Err(format!(
+                "Kubernetes workload '{}' already exists",
+                config.workload_id
+            ));
+        }
+        self.persistence.save_kubernetes_workload(&config).await?;
+        info!(
+            "Registered Kubernetes workload '{}' ({})",
+            config.workload_id,
+            describe(&config)
+        );
+        let workload = ManagedWorkload {
+            config,
+            shared,
+            tasks,
+        };
+        let status = status_of(&workload).await;
+        workloads.insert(workload.config.workload_id.clone(), workload);
+        Ok(status)
+    }
+
+    /// 登録を解除する（クラスタ側のリソースには触れない）
+    pub async fn remove(&self, workload_id: &str) -> Result<(), String> {
+        self.workloads
+            .write()
+            .await
+            .remove(workload_id)
+            .ok_or_else(|| format!("Kubernetes workload '{workload_id}' not found"))?;
+        self.persistence
+            .delete_kubernetes_workload(workload_id)
+            .await?;
+        info!("Removed Kubernetes workload '{}'", workload_id);
+        Ok(())
+    }
+
+    /// ワークロードの状態
+    pub async fn status(&self, workload_id: &str) -> Result<WorkloadStatus, String> {
+        let workloads = self.workloads.read().await;
+        let workload = workloads
+            .get(workload_id)
+            .ok_or_else(|| format!("Kubernetes workload '{workload_id}' not found"))?;
+        Ok(status_of(workload).await)
+    }
+
+    /// すべてのワークロードの状態（ID順）
+    pub async fn list(&self) -> Vec<WorkloadStatus> {
+        let workloads = self.workloads.read().await;
+        let mut statuses = Vec::with_capacity(workloads.len());
+        for workload in workloads.values() {
+            statuses.push(status_of(workload).await);
+        }
+        statuses.sort_by(|a, b| a.config.workload_id.cmp(&b.config.workload_id));
+        statuses
+    }
+
+    /// ログのバッファ（stdoutにPodのログ、stderrに接続エラー）
+    pub async fn output_buffers(
+        &self,
+        workload_id: &str,
+    ) -> Option<(CircularBuffer, CircularBuffer)> {
+        let workloads = self.workloads.read().await;
+        let shared = &workloads.get(workload_id)?.shared;
+        Some((shared.stdout.clone(), shared.stderr.clone()))
+    }
+}
+
+async fn status_of(workload: &ManagedWorkload) -> WorkloadStatus {
+    let state = workload.shared.state.read().await;
+    let mut following: Vec<String> = state.following.iter().cloned().collect();
+    following.sort();
+    WorkloadStatus {
+        config: workload.config.clone(),
+        resolved_namespace: state.namespace.clone(),
+        ready_pods: state.pods.iter().filter(|pod| pod.ready).count(),
+        pods: state.pods.clone(),
+        following,
+        port_forwards: state.port_forwards.clone(),
+        last_error: state.last_error.clone(),
+        updated_at: state.updated_at,
+    }
+}
+
+/// ワークロードの定義を検証する
+pub fn validate(config: &KubernetesWorkload) -> Result<(), String> {
+    if config.workload_id.trim().is_empty() {
+        return Err("workload_id must not be empty".to_string());
+    }
+    if config.name.trim().is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    if config.port_forwards.len() > MAX_PORT_FORWARDS {
+        return Err(format!(
+            "A workload can have at most {MAX_PORT_FORWARDS} port forwards"
+        ));
+    }
+    let mut local_ports = HashSet::new();
+    for forward in &config.port_forwards {
+        if forward.local_port == 0 || forward.remote_port == 0 {
+            return Err("Port forwards require non-zero local_port and remote_port".to_string());
+        }
+        if !local_ports.insert(forward.local_port) {
+            return Err(format!(
+                "Local port {} is forwarded more than once",
+                forward.local_port
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 表示用の説明（例: `deployment/api in dev (context kind-dev)`）
+pub fn describe(config: &KubernetesWorkload) -> String {
+    let kind = match config.kind {
+        vantage_persistence::KubernetesWorkloadKind::Pod => "pod",
+        vantage_persistence::KubernetesWorkloadKind::Deployment => "deployment",
+    };
+    let mut description = format!("{kind}/{}", config.name);
+    if let Some(namespace) = &config.namespace {
+        description.push_str(&format!(" in {namespace}"));
+    }
+    if let Some(context) = &config.context {
+        description.push_str(&format!(" (context {context})"));
+    }
+    description
+}
+
+#[cfg(not(feature = "kubernetes"))]
+async fn attach(
+    _config: &KubernetesWorkload,
+    _shared: Arc<Shared>,
+) -> Result<Vec<JoinHandle<()>>, String> {
+    Err(
+        "Kubernetes integration is not supported in this build (requires the `kubernetes` feature)"
+            .to_string(),
+    )
+}
+
+#[cfg(feature = "kubernetes")]
+use cluster::attach;
+
+#[cfg(feature = "kubernetes")]
+mod cluster {
+    use super::{PodSummary, Shared};
+    use crate::process::port_owner;
+    use futures::{AsyncBufReadExt, StreamExt};
+    use k8s_openapi::api::apps::v1::Deployment;
+    use k8s_openapi::api::core::v1::Pod;
+    use kube::api::{Api, ListParams, LogParams};
+    use kube::config::KubeConfigOptions;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+    use tracing::{debug, warn};
+    use vantage_persistence::{KubernetesWorkload, KubernetesWorkloadKind};
+
+    /// クラスタから状態を取得する間隔
+    const POLL_INTERVAL: Duration = Duration::from_secs(5);
+    /// 最初にログを追い始めるときに取り込む過去の行数
+    const INITIAL_TAIL_LINES: i64 = 100;
+
+    /// クラスタに接続し、監視とポート転送のタスクを起動する
+    pub(super) async fn attach(
+        config: &KubernetesWorkload,
+        shared: Arc<Shared>,
+    ) -> Result<Vec<JoinHandle<()>>, String> {
+        let options = KubeConfigOptions {
+            context: config.context.clone(),
+            ..Default::default()
+        };
+        let kube_config = kube::Config::from_kubeconfig(&options)
+            .await
+            .map_err(|e| format!("Failed to load kubeconfig: {e}"))?;
+        let namespace = config
+            .namespace
+            .clone()
+            .unwrap_or_else(|| kube_config.default_namespace.clone());
+        let client = kube::Client::try_from(kube_config)
+            .map_err(|e| format!("Failed to create Kubernetes client: {e}"))?;
+        let pods: Api<Pod> = Api::namespaced(client.clone(), &namespace);
+        let deployments: Api<Deployment> = Api::namespaced(client, &namespace);
+
+        // 最初の取得に失敗した場合は登録しない（名前やアクセス権の誤りをすぐに返す）
+        let initial = list_pods(config, &pods, &deployments).await?;
+        shared.state.write().await.namespace = Some(namespace);
+
+        // ポートが使用中なら登録しない
+        let mut listeners = Vec::new();
+        for forward in &config.port_forwards {
+            let listener = match TcpListener::bind(("127.0.0.1", forward.local_port)).await {
+                Ok(listener) => listener,
+                Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+                    let owner = port_owner::find(forward.local_port, &HashMap::new()).await;
+                    return Err(format!(
+                        "Failed to listen on port {}: {e}. {}",
+                        forward.local_port,
+                        owner.describe()
+                    ));
+                }
+                Err(e) => {
+                    return Err(format!(
+                        "Failed to listen on port {}: {e}",
+                        forward.local_port
+                    ));
+                }
+            };
+            listeners.push((listener, forward.remote_port));
+        }
+
+        let mut tasks = vec![tokio::spawn(watch(
+            config.clone(),
+            pods.clone(),
+            deployments,
+            shared.clone(),
+            initial,
+        ))];
+        for (index, (listener, remote_port)) in listeners.into_iter().enumerate() {
+            tasks.push(tokio::spawn(forward_port(
+                listener,
+                remote_port,
+                index,
+                pods.clone(),
+                shared.clone(),
+            )));
+        }
+        Ok(tasks)
+    }
+
+    /// ワークロードに属するPod（名前順）
+    async fn list_pods(
+        config: &KubernetesWorkload,
+        pods: &Api<Pod>,
+        deployments: &Api<Deployment>,
+    ) -> Result<Vec<Pod>, String> {
+        let mut list = match config.kind {
+            KubernetesWorkloadKind::Pod => pods
+                .get_opt(&config.name)
+                .await
+                .map_err(|e| format!("Failed to get pod '{}': {e}", config.name))?
+                .into_iter()
+                .collect(),
+            KubernetesWorkloadKind::Deployment => {
+                let deployment = deployments
+                    .get(&config.name)
+                    .await
+                    .map_err(|e| format!("Failed to get deployment '{}': {e}", config.name))?;
+                let labels = deployment
+                    .spec
+                    .and_then(|spec| spec.selector.match_labels)
+                    .unwrap_or_default();
+                if labels.is_empty() {
+                    return Err(format!(
+                        "Deployment '{}' has no matchLabels selector",
+                        config.name
+                    ));
+                }
+                let selector = labels
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                pods.list(&ListParams::default().labels(&selector))
+                    .await
+                    .map_err(|e| format!("Failed to list pods of '{}': {e}", config.name))?
+                    .items
+            }
+        };
+        list.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+        Ok(list)
+    }
+
+    fn summarize(pod: &Pod) -> PodSummary {
+        let status = pod.status.as_ref();
+        let containers = status
+            .and_then(|s| s.container_statuses.clone())
+            .unwrap_or_default();
+        PodSummary {
+            name: pod.metadata.name.clone().unwrap_or_default(),
+            phase: status
+                .and_then(|s| s.phase.clone())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            ready: !containers.is_empty() && containers.iter().all(|c| c.ready),
+            restarts: containers.iter().map(|c| c.restart_count).sum(),
+            node: pod.spec.as_ref().and_then(|spec| spec.node_name.clone()),
+        }
+    }
+
+    /// 状態を定期的に取得し、実行中のPodのログを追う
+    async fn watch(
+        config: KubernetesWorkload,
+        pods: Api<Pod>,
+        deployments: Api<Deployment>,
+        shared: Arc<Shared>,
+        initial: Vec<Pod>,
+    ) {
+        let mut followers: HashMap<String, JoinHandle<()>> = HashMap::new();
+        let mut listed = Ok(initial);
+        loop {
+            match listed {
+                Ok(list) => {
+                    let summaries: Vec<PodSummary> = list.iter().map(summarize).collect();
+                    followers.retain(|_, handle| !handle.is_finished());
+                    for pod in summaries.iter().filter(|pod| pod.phase == "Running") {
+                        if !followers.contains_key(&pod.name) {
+                            let prefix = (config.kind == KubernetesWorkloadKind::Deployment)
+                                .then(|| format!("[{}] ", pod.name));
+                            followers.insert(
+                                pod.name.clone(),
+                                tokio::spawn(follow_logs(
+                                    pods.clone(),
+                                    pod.name.clone(),
+                                    config.container.clone(),
+                                    prefix,
+                                    shared.clone(),
+                                )),
+                            );
+                        }
+                    }
+                    let mut state = shared.state.write().await;
+                    state.following = followers.keys().cloned().collect();
+                    state.pods = summaries;
+                    state.last_error = None;
+                    state.updated_at = Some(chrono::Utc::now());
+                }
+                Err(error) => {
+                    warn!("Kubernetes workload '{}': {}", config.workload_id, error);
+                    shared.report_error(error).await;
+                }
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+            listed = list_pods(&config, &pods, &deployments).await;
+        }
+    }
+
+    /// Podのログを追って、バッファに取り込む
+    ///
+    /// ストリームが切れた場合は、次の状態の取得で実行中ならそこから追い直す。
+    async fn follow_logs(
+        pods: Api<Pod>,
+        pod: String,
+        container: Option<String>,
+        prefix: Option<String>,
+        shared: Arc<Shared>,
+    ) {
+        let started = chrono::Utc::now();
+        // 同じPodを追い直す場合は、前回の取り込み以降のログだけを取り込む
+        let since_time = shared.stdout.last_timestamp().await;
+        let params = LogParams {
+            follow: true,
+            container,
+            tail_lines: since_time.is_none().then_some(INITIAL_TAIL_LINES),
+            since_time,
+            ..Default::default()
+        };
+        let stream = match pods.log_stream(&pod, &params).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                shared
+                    .report_error(format!("Failed to stream logs of pod '{pod}': {e}"))
+                    .await;
+                return;
+            }
+        };
+        let mut lines = stream.lines();
+        while let Some(line) = lines.next().await {
+            match line {
+                Ok(line) => {
+                    let line = match &prefix {
+                        Some(prefix) => format!("{prefix}{line}"),
+                        None => line,
+                    };
+                    shared.stdout.push(line).await;
+                }
+                Err(e) => {
+                    debug!("Log stream of pod '{}' failed: {}", pod, e);
+                    break;
+                }
+            }
+        }
+        debug!(
+            "Log stream of pod '{}' ended after {}s",
+            pod,
+            (chrono::Utc::now() - started).num_seconds()
+        );
+    }
+
+    /// ローカルのポートへの接続を、その時点で準備完了のPodに転送する
+    async fn forward_port(
+        listener: TcpListener,
+        remote_port: u16,
+        index: usize,
+        pods: Api<Pod>,
+        shared: Arc<Shared>,
+    ) {
+        let active = Arc::new(AtomicU64::new(0));
+        loop {
+            let mut socket = match listener.accept().await {
+                Ok((socket, _)) => socket,
+                Err(e) => {
+                    warn!("Failed to accept port-forward connection: {}", e);
+                    continue;
+                }
+            };
+            let target = {
+                let state = shared.state.read().await;
+                state
+                    .pods
+                    .iter()
+                    .find(|pod| pod.phase == "Running" && pod.ready)
+                    .map(|pod| pod.name.clone())
+            };
+            let Some(target) = target else {
+                set_forward_error(&shared, index, "No ready pod to forward to".to_string()).await;
+                continue;
+            };
+
+            {
+                let mut state = shared.state.write().await;
+                let status = &mut state.port_forwards[index];
+                status.connections += 1;
+                status.active_connections = active.fetch_add(1, Ordering::SeqCst) + 1;
+            }
+            let pods = pods.clone();
+            let shared = shared.clone();
+            let active = active.clone();
+            tokio::spawn(async move {
+                let result = async {
+                    let mut forwarder = pods
+                        .portforward(&target, &[remote_port])
+                        .await
+                        .map_err(|e| format!("Failed to forward to pod '{target}': {e}"))?;
+                    let mut upstream = forwarder
+                        .take_stream(remote_port)
+                        .ok_or_else(|| format!("Pod '{target}' did not open port {remote_port}"))?;
+                    tokio::io::copy_bidirectional(&mut socket, &mut upstream)
+                        .await
+                        .map_err(|e| format!("Port-forward connection failed: {e}"))?;
+                    drop(upstream);
+                    forwarder
+                        .join()
+                        .await
+                        .map_err(|e| format!("Port-forward to pod '{target}' failed: {e}"))
+                }
+                .await;
+                let remaining = active.fetch_sub(1, Ordering::SeqCst) - 1;
+                if let Err(error) = result {
+                    set_forward_error(&shared, index, error).await;
+                }
+                shared.state.write().await.port_forwards[index].active_connections = remaining;
+            });
+        }
+    }
+
+    async fn set_forward_error(shared: &Shared, index: usize, error: String) {
+        shared.stderr.push(format!("[vantage] {error}")).await;
+        shared.state.write().await.port_forwards[index].last_error = Some(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vantage_persistence::KubernetesWorkloadKind;
+
+    fn workload(port_forwards: Vec<KubernetesPortForward>) -> KubernetesWorkload {
+        KubernetesWorkload {
+            workload_id: "api".to_string(),
+            context: Some("kind-dev".to_string()),
+            namespace: Some("dev".to_string()),
+            kind: KubernetesWorkloadKind::Deployment,
+            name: "api".to_string(),
+            container: None,
+            port_forwards,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_validate_port_forwards() {
+        let forward = |local_port, remote_port| KubernetesPortForward {
+            local_port,
+            remote_port,
+        };
+        assert!(validate(&workload(vec![forward(8080, 80), forward(5432, 5432)])).is_ok());
+        assert!(validate(&workload(vec![forward(8080, 80), forward(8080, 81)])).is_err());
+        assert!(validate(&workload(vec![forward(0, 80)])).is_err());
+        assert_eq!(
+            describe(&workload(Vec::new())),
+            "deployment/api in dev (context kind-dev)"
+        );
+    }
+}
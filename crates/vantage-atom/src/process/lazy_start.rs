@@ -0,0 +1,436 @@
+//! 最初の接続での遅延起動（ソケットアクティベーション）
+//!
+//! あまり使わない開発用のサービスのために、Vantageがプロセスの代わりに `listen_port` で待ち受け、
+//! 最初の接続が来たらプロセスを起動し、`backend_port` で接続を受け付けるようになってから中継します。
+//! 使わない間はプロセスを止めておけますが、クライアントが使うURLは常に有効なままです。
+//! `idle_stop_secs` を指定すると、遅延起動で起動したプロセスを接続がなくなってから停止します。
+//!
+//! 起動はマネージャーの遅延起動タスクに依頼し、同じプロセスへの依頼は1つずつ処理します
+//! （起動を待っている間に来た接続は、起動が終わってからまとめて中継されます）。
+
+use super::blue_green::DEFAULT_PORT_ENV;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{RwLock, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+use vantage_persistence::LazyStartConfig;
+
+/// 待ち受けるアドレスの既定値
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+/// 起動してから接続を受け付けるまで待つ時間の既定値（秒）
+pub const DEFAULT_READY_TIMEOUT_SECS: u64 = 30;
+/// 接続のなくなったプロセスを停止するか確かめる間隔
+pub const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// プロセスの起動の依頼（マネージャーの遅延起動タスクが処理する）
+pub struct Activation {
+    pub process_id: String,
+    pub backend_port: u16,
+    pub ready_timeout: Duration,
+    /// 接続を受け付けるようになったら、この依頼でプロセスを起動したかを返す
+    pub reply: oneshot::Sender<Result<bool, String>>,
+}
+
+/// 遅延起動の待ち受けの状態
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LazyStartStatus {
+    /// Vantageが待ち受けるアドレス
+    pub listen: String,
+    /// 中継先（プロセスが待ち受けるポート）
+    pub backend_port: u16,
+    /// 受け付けた接続の数
+    pub connections: u64,
+    /// 中継中の接続の数
+    pub active_connections: u64,
+    /// 接続をきっかけにプロセスを起動した回数
+    pub activations: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_activated_at: Option<DateTime<Utc>>,
+    /// 接続がない状態がこの秒数続いたら停止する
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_stop_secs: Option<u64>,
+    /// 最後に起動に失敗した理由（その後に起動できればクリアされる）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// 設定を確かめる
+pub fn validate(config: &LazyStartConfig) -> Result<(), String> {
+    if config.listen_port == 0 {
+        return Err("listen_port must not be 0".to_string());
+    }
+    if config.backend_port == Some(config.listen_port) {
+        return Err(format!(
+            "backend_port must differ from listen_port ({})",
+            config.listen_port
+        ));
+    }
+    if config
+        .port_env
+        .as_deref()
+        .is_some_and(|name| name.trim().is_empty())
+    {
+        return Err("port_env must not be empty".to_string());
+    }
+    if config.ready_timeout_secs == Some(0) {
+        return Err("ready_timeout_secs must be greater than 0".to_string());
+    }
+    if config.idle_stop_secs == Some(0) {
+        return Err("idle_stop_secs must be greater than 0".to_string());
+    }
+    Ok(())
+}
+
+/// `backend_port` を渡す環境変数
+pub fn port_env(config: &LazyStartConfig) -> &str {
+    config.port_env.as_deref().unwrap_or(DEFAULT_PORT_ENV)
+}
+
+#[derive(Default)]
+struct ActivityState {
+    last_activated_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    /// 最後に接続を受け付けた・接続が終わった時刻
+    last_activity: Option<Instant>,
+}
+
+/// 待ち受けのタスクと接続のタスクで共有する状態
+struct Shared {
+    process_id: String,
+    backend_port: u16,
+    ready_timeout: Duration,
+    connections: AtomicU64,
+    active: AtomicU64,
+    activations: AtomicU64,
+    /// 遅延起動で起動し、まだ接続がなくなったことによる停止をしていない
+    started: AtomicBool,
+    activity: Mutex<ActivityState>,
+    /// 同じプロセスの起動を1つずつにする
+    activation: tokio::sync::Mutex<()>,
+    requests: mpsc::UnboundedSender<Activation>,
+}
+
+impl Shared {
+    fn touch(&self) {
+        self.activity.lock().unwrap().last_activity = Some(Instant::now());
+    }
+
+    /// 中継先に接続する（接続できなければプロセスの起動を依頼してから接続する）
+    async fn connect_backend(&self) -> Result<TcpStream, String> {
+        if let Ok(outbound) = TcpStream::connect((Ipv4Addr::LOCALHOST, self.backend_port)).await {
+            return Ok(outbound);
+        }
+        let _guard = self.activation.lock().await;
+        // 待っている間に他の接続が起動を終えていることがある
+        if let Ok(outbound) = TcpStream::connect((Ipv4Addr::LOCALHOST, self.backend_port)).await {
+            return Ok(outbound);
+        }
+
+        let (reply, response) = oneshot::channel();
+        self.requests
+            .send(Activation {
+                process_id: self.process_id.clone(),
+                backend_port: self.backend_port,
+                ready_timeout: self.ready_timeout,
+                reply,
+            })
+            .map_err(|_| "The process manager is shutting down".to_string())?;
+        let result = response
+            .await
+            .unwrap_or_else(|_| Err("The activation was cancelled".to_string()));
+        {
+            let mut activity = self.activity.lock().unwrap();
+            match result {
+                Ok(started) => {
+                    if started {
+                        self.activations.fetch_add(1, Ordering::SeqCst);
+                        self.started.store(true, Ordering::SeqCst);
+                        activity.last_activated_at = Some(Utc::now());
+                    }
+                    activity.last_error = None;
+                }
+                Err(e) => {
+                    activity.last_error = Some(e.clone());
+                    return Err(e);
+                }
+            }
+        }
+
+        TcpStream::connect((Ipv4Addr::LOCALHOST, self.backend_port))
+            .await
+            .map_err(|e| {
+                format!(
+                    "Failed to connect to backend port {}: {e}",
+                    self.backend_port
+                )
+            })
+    }
+}
+
+struct LazyListener {
+    listen: SocketAddr,
+    idle_stop_secs: Option<u64>,
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for LazyListener {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl LazyListener {
+    fn status(&self) -> LazyStartStatus {
+        let activity = self.shared.activity.lock().unwrap();
+        LazyStartStatus {
+            listen: self.listen.to_string(),
+            backend_port: self.shared.backend_port,
+            connections: self.shared.connections.load(Ordering::SeqCst),
+            active_connections: self.shared.active.load(Ordering::SeqCst),
+            activations: self.shared.activations.load(Ordering::SeqCst),
+            last_activated_at: activity.last_activated_at,
+            idle_stop_secs: self.idle_stop_secs,
+            last_error: activity.last_error.clone(),
+        }
+    }
+
+    /// 遅延起動で起動したプロセスで、接続がないまま `idle_stop_secs` が過ぎたか
+    fn is_idle(&self, now: Instant) -> bool {
+        let Some(idle_stop_secs) = self.idle_stop_secs else {
+            return false;
+        };
+        if !self.shared.started.load(Ordering::SeqCst)
+            || self.shared.active.load(Ordering::SeqCst) > 0
+        {
+            return false;
+        }
+        let activity = self.shared.activity.lock().unwrap();
+        activity
+            .last_activity
+            .is_none_or(|last| now.duration_since(last) >= Duration::from_secs(idle_stop_secs))
+    }
+}
+
+/// プロセスごとの遅延起動の待ち受け（キーはプロセスのID）
+pub struct LazyListeners {
+    listeners: RwLock<HashMap<String, LazyListener>>,
+    requests: mpsc::UnboundedSender<Activation>,
+}
+
+impl LazyListeners {
+    /// 待ち受けの一覧と、起動の依頼を受け取る側を作る
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<Activation>) {
+        let (requests, activations) = mpsc::unbounded_channel();
+        let listeners = Self {
+            listeners: RwLock::new(HashMap::new()),
+            requests,
+        };
+        (listeners, activations)
+    }
+
+    /// 待ち受けを始める（既にある場合は設定を置き換える）
+    ///
+    /// `backend_port` は呼び出し側で決めておく。
+    pub async fn open(
+        &self,
+        process_id: &str,
+        config: &LazyStartConfig,
+    ) -> Result<LazyStartStatus, String> {
+        let backend_port = config
+            .backend_port
+            .ok_or_else(|| "backend_port is not set".to_string())?;
+        let host = config.host.as_deref().unwrap_or(DEFAULT_HOST);
+        let mut listeners = self.listeners.write().await;
+        // 同じポートで待ち受け直せるよう、先に閉じる
+        let previous = listeners.remove(process_id);
+        drop(previous);
+        let listener = TcpListener::bind((host, config.listen_port))
+            .await
+            .map_err(|e| format!("Failed to listen on {host}:{}: {e}", config.listen_port))?;
+        let listen = listener.local_addr().map_err(|e| e.to_string())?;
+        let shared = Arc::new(Shared {
+            process_id: process_id.to_string(),
+            backend_port,
+            ready_timeout: Duration::from_secs(
+                config
+                    .ready_timeout_secs
+                    .unwrap_or(DEFAULT_READY_TIMEOUT_SECS),
+            ),
+            connections: AtomicU64::new(0),
+            active: AtomicU64::new(0),
+            activations: AtomicU64::new(0),
+            started: AtomicBool::new(false),
+            activity: Mutex::new(ActivityState::default()),
+            activation: tokio::sync::Mutex::new(()),
+            requests: self.requests.clone(),
+        });
+        let task = tokio::spawn(serve(listener, shared.clone()));
+        let lazy_listener = LazyListener {
+            listen,
+            idle_stop_secs: config.idle_stop_secs,
+            shared,
+            task,
+        };
+        let status = lazy_listener.status();
+        info!(
+            "Lazy start of '{}': listening on {} for backend port {}",
+            process_id, listen, backend_port
+        );
+        listeners.insert(process_id.to_string(), lazy_listener);
+        Ok(status)
+    }
+
+    /// 待ち受けをやめる
+    pub async fn close(&self, process_id: &str) -> bool {
+        self.listeners.write().await.remove(process_id).is_some()
+    }
+
+    pub async fn is_open(&self, process_id: &str) -> bool {
+        self.listeners.read().await.contains_key(process_id)
+    }
+
+    pub async fn status(&self, process_id: &str) -> Option<LazyStartStatus> {
+        let listeners = self.listeners.read().await;
+        listeners.get(process_id).map(LazyListener::status)
+    }
+
+    /// 接続がなくなって停止するプロセスを取り出す（次に起動するまで再び返さない）
+    pub async fn take_idle(&self) -> Vec<String> {
+        let now = Instant::now();
+        let listeners = self.listeners.read().await;
+        let mut idle: Vec<String> = listeners
+            .iter()
+            .filter(|(_, listener)| listener.is_idle(now))
+            .map(|(id, listener)| {
+                listener.shared.started.store(false, Ordering::SeqCst);
+                id.clone()
+            })
+            .collect();
+        idle.sort();
+        idle
+    }
+}
+
+/// 受け付けた接続を、必要ならプロセスを起動してから中継する
+async fn serve(listener: TcpListener, shared: Arc<Shared>) {
+    loop {
+        let mut inbound = match listener.accept().await {
+            Ok((socket, _)) => socket,
+            Err(e) => {
+                warn!("Failed to accept lazy start connection: {}", e);
+                continue;
+            }
+        };
+        shared.connections.fetch_add(1, Ordering::SeqCst);
+        shared.active.fetch_add(1, Ordering::SeqCst);
+        shared.touch();
+        let shared = shared.clone();
+        tokio::spawn(async move {
+            match shared.connect_backend().await {
+                Ok(mut outbound) => {
+                    if let Err(e) = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await
+                    {
+                        debug!(
+                            "Lazy start connection to port {} ended: {}",
+                            shared.backend_port, e
+                        );
+                    }
+                }
+                Err(e) => warn!(
+                    "Lazy start of '{}' failed; dropping the connection: {}",
+                    shared.process_id, e
+                ),
+            }
+            shared.active.fetch_sub(1, Ordering::SeqCst);
+            shared.touch();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn fetch(port: u16) -> String {
+        let mut socket = TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+            .await
+            .unwrap();
+        let mut reply = String::new();
+        socket.read_to_string(&mut reply).await.unwrap();
+        reply
+    }
+
+    #[tokio::test]
+    async fn test_first_connection_activates_the_backend() {
+        let backend = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let backend_port = backend.local_addr().unwrap().port();
+        // 起動するまではポートを閉じておく
+        drop(backend);
+
+        let (listeners, mut activations) = LazyListeners::new();
+        tokio::spawn(async move {
+            while let Some(activation) = activations.recv().await {
+                let backend = TcpListener::bind((Ipv4Addr::LOCALHOST, activation.backend_port))
+                    .await
+                    .unwrap();
+                tokio::spawn(async move {
+                    while let Ok((mut socket, _)) = backend.accept().await {
+                        let _ = socket.write_all(b"hello").await;
+                    }
+                });
+                let _ = activation.reply.send(Ok(true));
+            }
+        });
+
+        // テストでは空いているポートで待ち受ける
+        let config = LazyStartConfig {
+            listen_port: 0,
+            backend_port: Some(backend_port),
+            idle_stop_secs: Some(1),
+            ..Default::default()
+        };
+        let status = listeners.open("docs", &config).await.unwrap();
+        let listen: SocketAddr = status.listen.parse().unwrap();
+
+        assert_eq!(fetch(listen.port()).await, "hello");
+        assert_eq!(fetch(listen.port()).await, "hello");
+        let status = listeners.status("docs").await.unwrap();
+        assert_eq!(status.connections, 2);
+        assert_eq!(status.activations, 1);
+        assert!(status.last_activated_at.is_some());
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(listeners.take_idle().await, vec!["docs".to_string()]);
+        assert!(listeners.take_idle().await.is_empty());
+
+        assert!(listeners.close("docs").await);
+        assert!(listeners.status("docs").await.is_none());
+    }
+
+    #[test]
+    fn test_validate_rejects_conflicting_ports() {
+        let config = LazyStartConfig {
+            listen_port: 8080,
+            backend_port: Some(8080),
+            ..Default::default()
+        };
+        assert!(validate(&config).is_err());
+        assert!(
+            validate(&LazyStartConfig {
+                listen_port: 0,
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! ログディレクトリのディスク使用量クォータ
+//!
+//! ログは `<log_dir>/<process_id>/` 以下に保存される前提で、プロセスごとの上限と
+//! ディレクトリ全体の上限を超えた場合に更新日時の古いファイルから削除します。
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use vantage_persistence::LogQuotaSettings;
+
+/// ログディレクトリ直下に置かれたファイルの集計先
+const SHARED_LOGS: &str = "_shared";
+
+/// デフォルトのログディレクトリ（状態ディレクトリの `logs`）
+pub fn default_log_dir() -> PathBuf {
+    vantage_persistence::paths::get().log_dir()
+}
+
+/// プロセスごとのログ使用量
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessLogUsage {
+    pub process_id: String,
+    pub bytes: u64,
+    pub files: usize,
+}
+
+/// ログディレクトリ全体の使用量
+#[derive(Debug, Clone, Serialize)]
+pub struct LogDiskUsage {
+    pub log_dir: PathBuf,
+    pub total_bytes: u64,
+    pub quota: LogQuotaSettings,
+    pub processes: Vec<ProcessLogUsage>,
+}
+
+/// 削除結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneReport {
+    pub removed_files: Vec<PathBuf>,
+    pub freed_bytes: u64,
+}
+
+struct LogFile {
+    path: PathBuf,
+    process_id: String,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+fn collect_files(dir: &Path) -> io::Result<Vec<LogFile>> {
+    let mut files = Vec::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+    collect_recursive(dir, dir, &mut files)?;
+    Ok(files)
+}
+
+fn collect_recursive(root: &Path, dir: &Path, files: &mut Vec<LogFile>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let path = entry.path();
+        if metadata.is_dir() {
+            collect_recursive(root, &path, files)?;
+            continue;
+        }
+
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let process_id = match relative.components().count() {
+            1 => SHARED_LOGS.to_string(),
+            _ => relative
+                .components()
+                .next()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .unwrap_or_else(|| SHARED_LOGS.to_string()),
+        };
+        files.push(LogFile {
+            path,
+            process_id,
+            bytes: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    Ok(())
+}
+
+/// ログディレクトリの使用量を集計
+pub fn usage(dir: &Path, quota: &LogQuotaSettings) -> io::Result<LogDiskUsage> {
+    let files = collect_files(dir)?;
+    let mut per_process: BTreeMap<String, ProcessLogUsage> = BTreeMap::new();
+    for file in &files {
+        let entry = per_process
+            .entry(file.process_id.clone())
+            .or_insert_with(|| ProcessLogUsage {
+                process_id: file.process_id.clone(),
+                bytes: 0,
+                files: 0,
+            });
+        entry.bytes += file.bytes;
+        entry.files += 1;
+    }
+
+    Ok(LogDiskUsage {
+        log_dir: dir.to_path_buf(),
+        total_bytes: files.iter().map(|f| f.bytes).sum(),
+        quota: quota.clone(),
+        processes: per_process.into_values().collect(),
+    })
+}
+
+/// クォータを超えた分を古いファイルから削除
+pub fn enforce(dir: &Path, quota: &LogQuotaSettings) -> io::Result<PruneReport> {
+    let mut files = collect_files(dir)?;
+    files.sort_by_key(|f| f.modified);
+
+    let mut report = PruneReport::default();
+    let mut removed = vec![false; files.len()];
+
+    // プロセスごとの上限
+    if quota.max_per_process_bytes > 0 {
+        let mut per_process: BTreeMap<&str, u64> = BTreeMap::new();
+        for file in &files {
+            *per_process.entry(&file.process_id).or_default() += file.bytes;
+        }
+        for (i, file) in files.iter().enumerate() {
+            let used = per_process.get_mut(file.process_id.as_str()).unwrap();
+            if *used > quota.max_per_process_bytes {
+                remove_file(file, &mut report)?;
+                *used -= file.bytes;
+                removed[i] = true;
+            }
+        }
+    }
+
+    // ディレクトリ全体の上限
+    if quota.max_total_bytes > 0 {
+        let mut total: u64 = files
+            .iter()
+            .zip(&removed)
+            .filter(|(_, removed)| !**removed)
+            .map(|(f, _)| f.bytes)
+            .sum();
+        for (file, removed) in files.iter().zip(&removed) {
+            if total <= quota.max_total_bytes {
+                break;
+            }
+            if *removed {
+                continue;
+            }
+            remove_file(file, &mut report)?;
+            total -= file.bytes;
+        }
+    }
+
+    Ok(report)
+}
+
+fn remove_file(file: &LogFile, report: &mut PruneReport) -> io::Result<()> {
+    match std::fs::remove_file(&file.path) {
+        Ok(()) => {}
+        // 書き込み側で既にローテーションされている場合は無視
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    }
+    report.freed_bytes += file.bytes;
+    report.removed_files.push(file.path.clone());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write_log(dir: &Path, relative: &str, bytes: usize, age_secs: u64) {
+        let path = dir.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, vec![b'x'; bytes]).unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age_secs);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_usage_groups_by_process() {
+        let dir = tempfile::tempdir().unwrap();
+        write_log(dir.path(), "web/1.log", 100, 10);
+        write_log(dir.path(), "web/2.log", 50, 5);
+        write_log(dir.path(), "db/1.log", 30, 5);
+
+        let usage = usage(dir.path(), &LogQuotaSettings::default()).unwrap();
+        assert_eq!(usage.total_bytes, 180);
+        assert_eq!(usage.processes.len(), 2);
+        let web = usage
+            .processes
+            .iter()
+            .find(|p| p.process_id == "web")
+            .unwrap();
+        assert_eq!((web.bytes, web.files), (150, 2));
+    }
+
+    #[test]
+    fn test_enforce_removes_oldest_files_first() {
+        let dir = tempfile::tempdir().unwrap();
+        write_log(dir.path(), "web/old.log", 100, 30);
+        write_log(dir.path(), "web/new.log", 100, 1);
+        write_log(dir.path(), "db/old.log", 100, 20);
+        write_log(dir.path(), "db/new.log", 100, 2);
+
+        let quota = LogQuotaSettings {
+            max_total_bytes: 250,
+            max_per_process_bytes: 150,
+        };
+        let report = enforce(dir.path(), &quota).unwrap();
+
+        // プロセスごとの上限でそれぞれ古い方が削除され、全体は上限内に収まる
+        assert_eq!(report.freed_bytes, 200);
+        assert!(!dir.path().join("web/old.log").exists());
+        assert!(!dir.path().join("db/old.log").exists());
+        assert!(dir.path().join("web/new.log").exists());
+        assert!(dir.path().join("db/new.log").exists());
+
+        let quota = LogQuotaSettings {
+            max_total_bytes: 100,
+            max_per_process_bytes: 0,
+        };
+        enforce(dir.path(), &quota).unwrap();
+        assert!(dir.path().join("web/new.log").exists());
+        assert!(!dir.path().join("db/new.log").exists());
+    }
+}
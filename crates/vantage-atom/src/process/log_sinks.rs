@@ -0,0 +1,619 @@
+//! 取り込んだ出力の外部への転送
+//!
+//! 標準出力・標準エラー出力の各行を、syslog（UDP）、Grafana Lokiのpush API、JSONLファイルに
+//! 送ります。転送先ごとに上限付きのキューと送信タスクを持ち、送信が追いつかずキューが
+//! あふれた行は捨てて数えます（出力の読み取りやプロセスを止めないため）。
+//! 転送先の設定はプロセスの起動時に決まり、変更は次の起動から反映されます。
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::warn;
+use utoipa::ToSchema;
+use vantage_persistence::{LogSinkConfig, LogSinkKind};
+
+/// 転送先ごとのキューの長さ（行数）
+pub const QUEUE_CAPACITY: usize = 10_000;
+/// 一度に送る最大の行数
+const BATCH_SIZE: usize = 500;
+/// Lokiへの送信のタイムアウト
+const LOKI_TIMEOUT: Duration = Duration::from_secs(10);
+/// Lokiへの送信に失敗した場合に再送するまでの待ち時間
+const LOKI_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// syslogのメッセージの最大長（UDPのデータグラムに収めるため、超えた分は切り捨てる）
+const SYSLOG_MAX_LEN: usize = 8192;
+/// syslogのファシリティ（local0）
+const SYSLOG_FACILITY: u8 = 16;
+/// syslogの構造化データのID（RFC 5424の例示用のPEN 32473を使う）
+const SYSLOG_SD_ID: &str = "vantage@32473";
+
+/// 転送する1行
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Utc>,
+    pub process_id: Arc<str>,
+    pub workspace: Option<Arc<str>>,
+    /// `stdout` または `stderr`
+    pub stream: &'static str,
+    pub line: String,
+}
+
+/// 転送先の状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LogSinkHealth {
+    pub name: String,
+    pub kind: LogSinkKind,
+    pub target: String,
+    /// 送信できた行数
+    pub forwarded: u64,
+    /// キューがあふれて捨てた行数
+    pub dropped: u64,
+    /// 送信に失敗した行数
+    pub failed: u64,
+    /// 送信待ちの行数
+    pub queued: usize,
+    /// 直近の送信が成功しているか
+    pub healthy: bool,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+/// 転送先の設定を検証
+pub fn validate(sinks: &[LogSinkConfig]) -> Result<(), String> {
+    for (index, sink) in sinks.iter().enumerate() {
+        if sink.name.trim().is_empty() {
+            return Err("log_sinks.name must not be empty".to_string());
+        }
+        if sinks[..index].iter().any(|other| other.name == sink.name) {
+            return Err(format!("Duplicate log sink name '{}'", sink.name));
+        }
+        let target = sink.target.trim();
+        if target.is_empty() {
+            return Err(format!("Log sink '{}' requires a target", sink.name));
+        }
+        match sink.kind {
+            LogSinkKind::Syslog => {
+                let valid = target
+                    .rsplit_once(':')
+                    .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+                if !valid {
+                    return Err(format!(
+                        "Log sink '{}': syslog target must be host:port, got '{target}'",
+                        sink.name
+                    ));
+                }
+            }
+            LogSinkKind::Loki => {
+                if !target.starts_with("http://") && !target.starts_with("https://") {
+                    return Err(format!(
+                        "Log sink '{}': loki target must be an http(s) URL of the push API",
+                        sink.name
+                    ));
+                }
+            }
+            LogSinkKind::Jsonl => {}
+        }
+        for key in sink.labels.keys() {
+            let mut chars = key.chars();
+            let valid = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(format!(
+                    "Log sink '{}': invalid label name '{key}' (use letters, digits and '_')",
+                    sink.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 送信タスクと共有する統計
+#[derive(Debug, Default)]
+struct SinkStats {
+    forwarded: AtomicU64,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+    consecutive_failures: AtomicU64,
+    last_error: Mutex<Option<(String, DateTime<Utc>)>>,
+}
+
+impl SinkStats {
+    fn succeeded(&self, lines: u64) {
+        self.forwarded.fetch_add(lines, Ordering::Relaxed);
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+    }
+
+    fn failed(&self, lines: u64, error: String) {
+        self.failed.fetch_add(lines, Ordering::Relaxed);
+        if self.consecutive_failures.fetch_add(1, Ordering::Relaxed) == 0 {
+            warn!("Log sink failed: {}", error);
+        }
+        *self.last_error.lock().unwrap() = Some((error, Utc::now()));
+    }
+}
+
+/// 転送先（すべての参照がなくなると送信タスクも終了する）
+#[derive(Debug)]
+struct Sink {
+    config: LogSinkConfig,
+    tx: mpsc::Sender<LogRecord>,
+    stats: Arc<SinkStats>,
+}
+
+impl Sink {
+    fn spawn(config: LogSinkConfig) -> Arc<Self> {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let stats = Arc::new(SinkStats::default());
+        tokio::spawn(run(config.clone(), rx, stats.clone()));
+        Arc::new(Self { config, tx, stats })
+    }
+
+    fn health(&self) -> LogSinkHealth {
+        let last_error = self.stats.last_error.lock().unwrap().clone();
+        LogSinkHealth {
+            name: self.config.name.clone(),
+            kind: self.config.kind,
+            target: self.config.target.clone(),
+            forwarded: self.stats.forwarded.load(Ordering::Relaxed),
+            dropped: self.stats.dropped.load(Ordering::Relaxed),
+            failed: self.stats.failed.load(Ordering::Relaxed),
+            queued: QUEUE_CAPACITY - self.tx.capacity(),
+            healthy: self.stats.consecutive_failures.load(Ordering::Relaxed) == 0,
+            last_error_at: last_error.as_ref().map(|(_, at)| *at),
+            last_error: last_error.map(|(error, _)| error),
+        }
+    }
+}
+
+/// 出力バッファから転送先への送り口（プロセスの起動ごと、ストリームごとに作る）
+#[derive(Debug, Clone)]
+pub struct LogTap {
+    sinks: Vec<Arc<Sink>>,
+    process_id: Arc<str>,
+    workspace: Option<Arc<str>>,
+    stream: &'static str,
+}
+
+impl LogTap {
+    /// 行をキューに入れる（キューがあふれている転送先には送らず、捨てた行として数える）
+    pub fn send(&self, line: &str) {
+        let timestamp = Utc::now();
+        for sink in &self.sinks {
+            let record = LogRecord {
+                timestamp,
+                process_id: self.process_id.clone(),
+                workspace: self.workspace.clone(),
+                stream: self.stream,
+                line: line.to_string(),
+            };
+            match sink.tx.try_send(record) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    sink.stats.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(TrySendError::Closed(_)) => {
+                    sink.stats.failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+/// 転送先の共有と状態の集計
+///
+/// 同じ設定の転送先は、複数のプロセスで1つのキューと送信タスクを共有する。
+#[derive(Debug, Default)]
+pub struct LogForwarder {
+    sinks: Mutex<HashMap<String, Arc<Sink>>>,
+}
+
+impl LogForwarder {
+    /// プロセスの出力ストリームの送り口（転送先がなければNone）
+    pub fn tap(
+        &self,
+        configs: &[LogSinkConfig],
+        process_id: &str,
+        workspace: Option<&Path>,
+        stream: &'static str,
+    ) -> Option<LogTap> {
+        if configs.is_empty() {
+            return None;
+        }
+        let mut sinks = self.sinks.lock().unwrap();
+        let sinks = configs
+            .iter()
+            .map(|config| {
+                sinks
+                    .entry(key(config))
+                    .or_insert_with(|| Sink::spawn(config.clone()))
+                    .clone()
+            })
+            .collect();
+        Some(LogTap {
+            sinks,
+            process_id: process_id.into(),
+            workspace: workspace.map(|w| w.to_string_lossy().into()),
+            stream,
+        })
+    }
+
+    /// 転送先の状態
+    ///
+    /// `configured` に含まれず、実行中のプロセスからも使われていない転送先はここで破棄する。
+    pub fn health(&self, configured: &[LogSinkConfig]) -> Vec<LogSinkHealth> {
+        let mut sinks = self.sinks.lock().unwrap();
+        for config in configured {
+            sinks
+                .entry(key(config))
+                .or_insert_with(|| Sink::spawn(config.clone()));
+        }
+        let configured: Vec<String> = configured.iter().map(key).collect();
+        sinks.retain(|key, sink| Arc::strong_count(sink) > 1 || configured.contains(key));
+        let mut health: Vec<_> = sinks.values().map(|sink| sink.health()).collect();
+        health.sort_by(|a, b| a.name.cmp(&b.name).then(a.target.cmp(&b.target)));
+        health
+    }
+}
+
+fn key(config: &LogSinkConfig) -> String {
+    serde_json::to_string(config).unwrap_or_else(|_| config.name.clone())
+}
+
+/// 行に付けるラベル（転送先の設定のラベルが優先）
+fn labels(config: &LogSinkConfig, record: &LogRecord) -> BTreeMap<String, String> {
+    let mut labels = BTreeMap::from([
+        ("process_id".to_string(), record.process_id.to_string()),
+        ("stream".to_string(), record.stream.to_string()),
+    ]);
+    if let Some(workspace) = &record.workspace {
+        labels.insert("workspace".to_string(), workspace.to_string());
+    }
+    labels.extend(config.labels.clone());
+    labels
+}
+
+/// 送信先との接続
+enum Writer {
+    Syslog { socket: UdpSocket, hostname: String },
+    Loki(reqwest::Client),
+    Jsonl(tokio::fs::File),
+}
+
+impl Writer {
+    async fn open(config: &LogSinkConfig) -> Result<Self, String> {
+        let target = config.target.trim();
+        match config.kind {
+            LogSinkKind::Syslog => {
+                let socket = UdpSocket::bind(if target.starts_with('[') {
+                    "[::]:0"
+                } else {
+                    "0.0.0.0:0"
+                })
+                .await
+                .map_err(|e| format!("Failed to open a UDP socket: {e}"))?;
+                socket
+                    .connect(target)
+                    .await
+                    .map_err(|e| format!("Failed to resolve syslog target {target}: {e}"))?;
+                let hostname = sysinfo::System::host_name()
+                    .filter(|h| !h.is_empty())
+                    .unwrap_or_else(|| "-".to_string());
+                Ok(Self::Syslog { socket, hostname })
+            }
+            LogSinkKind::Loki => reqwest::Client::builder()
+                .timeout(LOKI_TIMEOUT)
+                .build()
+                .map(Self::Loki)
+                .map_err(|e| format!("Failed to create HTTP client: {e}")),
+            LogSinkKind::Jsonl => {
+                let path = Path::new(target);
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+                }
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map(Self::Jsonl)
+                    .map_err(|e| format!("Failed to open {target}: {e}"))
+            }
+        }
+    }
+
+    async fn write(&mut self, config: &LogSinkConfig, batch: &[LogRecord]) -> Result<(), String> {
+        match self {
+            Self::Syslog { socket, hostname } => {
+                for record in batch {
+                    let message = syslog_message(&labels(config, record), record, hostname);
+                    socket
+                        .send(message.as_bytes())
+                        .await
+                        .map_err(|e| format!("Failed to send syslog message: {e}"))?;
+                }
+                Ok(())
+            }
+            Self::Loki(client) => {
+                let payload = loki_payload(config, batch);
+                let mut result = push_to_loki(client, &config.target, &payload).await;
+                if result.is_err() {
+                    tokio::time::sleep(LOKI_RETRY_DELAY).await;
+                    result = push_to_loki(client, &config.target, &payload).await;
+                }
+                result
+            }
+            Self::Jsonl(file) => {
+                let mut data = String::new();
+                for record in batch {
+                    data.push_str(&jsonl_line(config, record));
+                    data.push('\n');
+                }
+                file.write_all(data.as_bytes())
+                    .await
+                    .map_err(|e| format!("Failed to write {}: {e}", config.target))?;
+                file.flush()
+                    .await
+                    .map_err(|e| format!("Failed to write {}: {e}", config.target))
+            }
+        }
+    }
+}
+
+/// 送信タスク（キューの送り手がすべて破棄されると終了する）
+async fn run(config: LogSinkConfig, mut rx: mpsc::Receiver<LogRecord>, stats: Arc<SinkStats>) {
+    let mut writer: Option<Writer> = None;
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while rx.recv_many(&mut batch, BATCH_SIZE).await > 0 {
+        let lines = batch.len() as u64;
+        let result = match &mut writer {
+            Some(writer) => writer.write(&config, &batch).await,
+            None => match Writer::open(&config).await {
+                Ok(opened) => writer.insert(opened).write(&config, &batch).await,
+                Err(e) => Err(e),
+            },
+        };
+        match result {
+            Ok(()) => stats.succeeded(lines),
+            Err(e) => {
+                // 次のバッチで接続し直す
+                writer = None;
+                stats.failed(lines, format!("{}: {e}", config.name));
+            }
+        }
+        batch.clear();
+    }
+}
+
+async fn push_to_loki(
+    client: &reqwest::Client,
+    url: &str,
+    payload: &serde_json::Value,
+) -> Result<(), String> {
+    let response = client
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to push to Loki: {e}"))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("Loki returned {status}: {}", body.trim()))
+}
+
+/// Lokiのpush APIのリクエスト（ラベルの組み合わせごとにストリームを分ける）
+fn loki_payload(config: &LogSinkConfig, batch: &[LogRecord]) -> serde_json::Value {
+    let mut streams: BTreeMap<BTreeMap<String, String>, Vec<[String; 2]>> = BTreeMap::new();
+    for record in batch {
+        let nanos = record
+            .timestamp
+            .timestamp_nanos_opt()
+            .unwrap_or_default()
+            .to_string();
+        streams
+            .entry(labels(config, record))
+            .or_default()
+            .push([nanos, record.line.clone()]);
+    }
+    serde_json::json!({
+        "streams": streams
+            .into_iter()
+            .map(|(stream, values)| serde_json::json!({ "stream": stream, "values": values }))
+            .collect::<Vec<_>>()
+    })
+}
+
+/// RFC 5424形式のsyslogメッセージ（標準エラー出力はerr、標準出力はinfoの重大度）
+fn syslog_message(labels: &BTreeMap<String, String>, record: &LogRecord, hostname: &str) -> String {
+    let severity = if record.stream == "stderr" { 3 } else { 6 };
+    let app_name: String = record
+        .process_id
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(48)
+        .collect();
+    let params: String = labels
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace(']', "\\]");
+            format!(" {key}=\"{value}\"")
+        })
+        .collect();
+    let mut message = format!(
+        "<{}>1 {} {} {} - {} [{SYSLOG_SD_ID}{params}] {}",
+        SYSLOG_FACILITY * 8 + severity,
+        record
+            .timestamp
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+        hostname,
+        app_name,
+        record.stream,
+        record.line
+    );
+    if message.len() > SYSLOG_MAX_LEN {
+        let mut end = SYSLOG_MAX_LEN;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+    }
+    message
+}
+
+fn jsonl_line(config: &LogSinkConfig, record: &LogRecord) -> String {
+    serde_json::json!({
+        "timestamp": record.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "labels": labels(config, record),
+        "line": record.line,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sink(kind: LogSinkKind, target: &str) -> LogSinkConfig {
+        LogSinkConfig {
+            name: "test".to_string(),
+            kind,
+            target: target.to_string(),
+            labels: HashMap::from([("env".to_string(), "dev".to_string())]),
+        }
+    }
+
+    fn record(stream: &'static str, line: &str) -> LogRecord {
+        LogRecord {
+            timestamp: DateTime::parse_from_rfc3339("2025-01-02T03:04:05.678Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            process_id: "web".into(),
+            workspace: Some("/work/app".into()),
+            stream,
+            line: line.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&[sink(LogSinkKind::Syslog, "localhost:514")]).is_ok());
+        assert!(validate(&[sink(LogSinkKind::Syslog, "localhost")]).is_err());
+        assert!(validate(&[sink(LogSinkKind::Loki, "localhost:3100")]).is_err());
+        assert!(validate(&[sink(LogSinkKind::Jsonl, "")]).is_err());
+        assert!(
+            validate(&[
+                sink(LogSinkKind::Jsonl, "a.jsonl"),
+                sink(LogSinkKind::Jsonl, "b.jsonl")
+            ])
+            .is_err()
+        );
+        let mut bad_label = sink(LogSinkKind::Jsonl, "a.jsonl");
+        bad_label
+            .labels
+            .insert("app-name".to_string(), "x".to_string());
+        assert!(validate(&[bad_label]).is_err());
+    }
+
+    #[test]
+    fn test_loki_payload_groups_streams_by_labels() {
+        let config = sink(LogSinkKind::Loki, "http://localhost:3100/loki/api/v1/push");
+        let payload = loki_payload(
+            &config,
+            &[
+                record("stdout", "one"),
+                record("stderr", "oops"),
+                record("stdout", "two"),
+            ],
+        );
+        let streams = payload["streams"].as_array().unwrap();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(
+            streams[1]["stream"],
+            serde_json::json!({
+                "env": "dev",
+                "process_id": "web",
+                "stream": "stdout",
+                "workspace": "/work/app"
+            })
+        );
+        assert_eq!(
+            streams[1]["values"],
+            serde_json::json!([
+                ["1735787045678000000", "one"],
+                ["1735787045678000000", "two"]
+            ])
+        );
+    }
+
+    #[test]
+    fn test_syslog_message_format() {
+        let config = sink(LogSinkKind::Syslog, "localhost:514");
+        let record = record("stderr", "failed to bind \"0.0.0.0\"");
+        let message = syslog_message(&labels(&config, &record), &record, "host1");
+        assert_eq!(
+            message,
+            "<131>1 2025-01-02T03:04:05.678Z host1 web - stderr [vantage@32473 env=\"dev\" \
+             process_id=\"web\" stream=\"stderr\" workspace=\"/work/app\"] failed to bind \"0.0.0.0\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_sink_writes_lines_and_reports_health() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs/out.jsonl");
+        let config = sink(LogSinkKind::Jsonl, path.to_str().unwrap());
+        let forwarder = LogForwarder::default();
+        let tap = forwarder
+            .tap(
+                std::slice::from_ref(&config),
+                "web",
+                Some(Path::new("/work/app")),
+                "stdout",
+            )
+            .unwrap();
+        tap.send("hello");
+        tap.send("world");
+
+        for _ in 0..50 {
+            if forwarder.health(&[])[0].forwarded == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let health = forwarder.health(&[]);
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].forwarded, 2);
+        assert!(health[0].healthy);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["line"], "hello");
+        assert_eq!(lines[0]["labels"]["process_id"], "web");
+        assert_eq!(lines[0]["labels"]["env"], "dev");
+
+        // 使われなくなった転送先は破棄される
+        drop(tap);
+        assert!(forwarder.health(&[]).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+//! 追加のログソース
+//!
+//! 標準出力ではなくファイルにログを書き出すデーモン向けに、ファイルや名前付きパイプ（FIFO）を
+//! 追従して出力バッファに取り込みます。取り込んだ行には `[ラベル] ` を付けて出所を区別します。
+//! ファイルはローテーション（リネームによる置き換えと切り詰め）を検知して開き直します。
+
+use super::buffer::CircularBuffer;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
+use tokio::task::JoinHandle;
+use vantage_persistence::LogSource;
+
+/// 追従間隔（ファイルの出現・追記・ローテーションの確認）
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// ログソースのパス（相対パスは作業ディレクトリ基準）
+pub fn resolve_path(source: &LogSource, cwd: Option<&Path>) -> PathBuf {
+    let path = Path::new(&source.path);
+    match cwd {
+        Some(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// 出力行に付けるラベル（省略時はファイル名）
+pub fn label(source: &LogSource) -> String {
+    source.label.clone().unwrap_or_else(|| {
+        Path::new(&source.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| source.path.clone())
+    })
+}
+
+/// 設定を検証
+pub fn validate(sources: &[LogSource]) -> Result<(), String> {
+    for source in sources {
+        if source.path.trim().is_empty() {
+            return Err("log_sources.path must not be empty".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// 現在のファイルサイズ（プロセス起動前に記録し、それ以降に書かれた行だけを取り込むために使う）
+pub fn current_len(source: &LogSource, cwd: Option<&Path>) -> u64 {
+    std::fs::metadata(resolve_path(source, cwd))
+        .ok()
+        .filter(|meta| meta.is_file())
+        .map_or(0, |meta| meta.len())
+}
+
+/// ログソースの追従を `offset` バイト目から開始（タスクの中断まで続く）
+pub fn spawn(
+    source: &LogSource,
+    cwd: Option<&Path>,
+    offset: u64,
+    buffer: CircularBuffer,
+) -> JoinHandle<()> {
+    let path = resolve_path(source, cwd);
+    let label = label(source);
+    tokio::spawn(follow(path, label, buffer, offset))
+}
+
+async fn follow(path: PathBuf, label: String, buffer: CircularBuffer, mut offset: u64) {
+    loop {
+        match tokio::fs::metadata(&path).await {
+            Ok(meta) if is_fifo(&meta) => follow_fifo(&path, &label, &buffer).await,
+            Ok(_) => follow_file(&path, &label, &buffer, offset).await,
+            Err(_) => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+        // 開き直したファイルは新しいファイルなので先頭から読む
+        offset = 0;
+    }
+}
+
+/// ファイルを追従し、ローテーションや削除を検知したら戻る
+async fn follow_file(path: &Path, label: &str, buffer: &CircularBuffer, offset: u64) {
+    let Ok(file) = tokio::fs::File::open(path).await else {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        return;
+    };
+    let identity = match file.metadata().await {
+        Ok(meta) => file_identity(&meta),
+        Err(_) => return,
+    };
+    let mut reader = BufReader::new(file);
+    let Ok(mut position) = reader.seek(SeekFrom::Start(offset)).await else {
+        return;
+    };
+
+    let mut rotated = false;
+    let mut line = String::new();
+    loop {
+        match reader.read_line(&mut line).await {
+            Ok(0) => {
+                // 置き換え前のファイルを読み切ってから開き直す
+                if rotated {
+                    return;
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+                match tokio::fs::metadata(path).await {
+                    Ok(meta) if file_identity(&meta) != identity => rotated = true,
+                    // 切り詰められた
+                    Ok(meta) if meta.len() < position => return,
+                    Ok(_) => {}
+                    Err(_) => rotated = true,
+                }
+            }
+            Ok(n) => {
+                position += n as u64;
+                if line.ends_with('\n') {
+                    buffer.push(labeled(label, &line)).await;
+                    line.clear();
+                } else if !rotated {
+                    // 行の途中まで書き込まれている場合は続きを待つ
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+/// 名前付きパイプを読み、書き込み側がすべて閉じたら戻る
+#[cfg(unix)]
+async fn follow_fifo(path: &Path, label: &str, buffer: &CircularBuffer) {
+    let Ok(receiver) = tokio::net::unix::pipe::OpenOptions::new().open_receiver(path) else {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        return;
+    };
+    let mut lines = BufReader::new(receiver).lines();
+    let mut received = false;
+    while let Ok(Some(line)) = lines.next_line().await {
+        buffer.push(labeled(label, &line)).await;
+        received = true;
+    }
+    if !received {
+        // 書き込み側がいない間は開き直しを繰り返さない
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(not(unix))]
+async fn follow_fifo(_path: &Path, _label: &str, _buffer: &CircularBuffer) {
+    tokio::time::sleep(POLL_INTERVAL).await;
+}
+
+#[cfg(unix)]
+fn is_fifo(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    meta.file_type().is_fifo()
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_meta: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// ローテーションの検知に使うファイルの識別子
+#[cfg(unix)]
+fn file_identity(meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+fn labeled(label: &str, line: &str) -> String {
+    format!("[{label}] {}", line.trim_end_matches(['\r', '\n']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(path: &str, label: Option<&str>) -> LogSource {
+        LogSource {
+            path: path.to_string(),
+            label: label.map(str::to_string),
+            stderr: false,
+        }
+    }
+
+    #[test]
+    fn test_resolve_path_and_label() {
+        let relative = source("logs/app.log", None);
+        assert_eq!(
+            resolve_path(&relative, Some(Path::new("/srv"))),
+            PathBuf::from("/srv/logs/app.log")
+        );
+        assert_eq!(label(&relative), "app.log");
+        assert_eq!(label(&source("/var/log/x.log", Some("nginx"))), "nginx");
+    }
+
+    #[tokio::test]
+    async fn test_follow_file_across_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "old line\n").unwrap();
+
+        let buffer = CircularBuffer::new(100);
+        let log = source(path.to_str().unwrap(), None);
+        let offset = current_len(&log, None);
+        let handle = spawn(&log, None, offset, buffer.clone());
+
+        use std::io::Write;
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        writeln!(file, "before rotation").unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        std::fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        writeln!(file, "late write").unwrap();
+        std::fs::write(&path, "after rotation\n").unwrap();
+
+        let expected = [
+            "[app.log] before rotation",
+            "[app.log] late write",
+            "[app.log] after rotation",
+        ];
+        let lines = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let lines = buffer.get_all().await;
+                if lines.len() >= expected.len() {
+                    break lines;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("rotated log was not followed");
+        handle.abort();
+        assert_eq!(lines, expected);
+    }
+}
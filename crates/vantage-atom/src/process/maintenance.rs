@@ -0,0 +1,424 @@
+//! メンテナンスウィンドウ
+//!
+//! デプロイ中などに、対象のプロセスの自動再起動・アイドル検知の通知と処置・クラッシュループの
+//! 検知を止めます。1回限りの期間か、cron形式（分 時 日 月 曜日、サーバーのローカル時刻）で
+//! 始まり一定時間続く繰り返しの期間を設定でき、開始と終了はイベントとして通知されます。
+
+use super::types::ProcessInfo;
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+use utoipa::ToSchema;
+use vantage_persistence::{MaintenanceSchedule, MaintenanceWindow};
+
+/// ウィンドウの開始・終了を確認する間隔
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// 繰り返しのウィンドウの最大の長さ（7日）
+pub const MAX_DURATION_MINUTES: u32 = 7 * 24 * 60;
+/// 次の開始時刻を探す範囲（分）
+const LOOKAHEAD_MINUTES: i64 = 366 * 24 * 60;
+
+/// プロセスに適用中のメンテナンスウィンドウ
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ActiveMaintenance {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// メンテナンスウィンドウの状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MaintenanceWindowStatus {
+    #[serde(flatten)]
+    pub window: MaintenanceWindow,
+    /// 現在の期間（期間外ならNone）
+    pub active: Option<ActiveMaintenance>,
+    /// 次に始まる時刻（1年以内に始まらない場合はNone）
+    pub next_start: Option<DateTime<Utc>>,
+    /// 現在対象になっているプロセス
+    pub processes_in_scope: Vec<String>,
+}
+
+/// cron形式の時刻指定（分 時 日 月 曜日）
+///
+/// 各フィールドは `*`、数値、範囲 `a-b`、刻み `*/n` / `a-b/n` とそれらのカンマ区切り。
+/// 月と曜日は `jan` / `mon` などの英語名も使え、曜日の7は日曜日を表す。
+/// 日と曜日の両方を指定した場合は、どちらかに一致すれば一致とみなす（cronと同じ）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// 1つのフィールドを、一致する値のビット集合に変換する
+fn parse_field(
+    field: &str,
+    label: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    name_offset: u32,
+) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let lower = text.to_ascii_lowercase();
+        if let Some(index) = names.iter().position(|name| *name == lower) {
+            return Ok(index as u32 + name_offset);
+        }
+        text.parse::<u32>()
+            .ok()
+            .filter(|v| (min..=max).contains(v))
+            .ok_or_else(|| format!("invalid {label} '{text}' (expected {min}-{max})"))
+    };
+
+    let mut bits = 0u64;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("invalid step '{step}' in {label}"))?;
+                (range, step)
+            }
+            None => (item, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (value(start)?, value(end)?)
+        } else {
+            let single = value(range)?;
+            // `5/15` は5から最大値まで15刻み
+            (single, if item.contains('/') { max } else { single })
+        };
+        if start > end {
+            return Err(format!("invalid {label} range '{range}'"));
+        }
+        for v in (start..=end).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(format!(
+                "Invalid cron expression '{expression}': expected 5 fields (minute hour day month weekday)"
+            ));
+        };
+        let invalid = |e: String| format!("Invalid cron expression '{expression}': {e}");
+        let mut weekdays =
+            parse_field(weekday, "weekday", 0, 7, &WEEKDAY_NAMES, 0).map_err(invalid)?;
+        // 7は日曜日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, "minute", 0, 59, &[], 0).map_err(invalid)?,
+            hours: parse_field(hour, "hour", 0, 23, &[], 0).map_err(invalid)?,
+            days: parse_field(day, "day", 1, 31, &[], 0).map_err(invalid)?,
+            months: parse_field(month, "month", 1, 12, &MONTH_NAMES, 1).map_err(invalid)?,
+            weekdays,
+            any_day: *day == "*",
+            any_weekday: *weekday == "*",
+        })
+    }
+
+    /// 分単位の時刻が一致するか
+    pub fn matches(&self, at: &NaiveDateTime) -> bool {
+        let bit = |set: u64, v: u32| set & (1 << v) != 0;
+        if !bit(self.minutes, at.minute())
+            || !bit(self.hours, at.hour())
+            || !bit(self.months, at.month())
+        {
+            return false;
+        }
+        let day = bit(self.days, at.day());
+        let weekday = bit(self.weekdays, at.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// `now` を含む期間（`duration_minutes` 以内に始まったもの）の開始時刻
+    pub fn current_start(
+        &self,
+        now: NaiveDateTime,
+        duration_minutes: u32,
+    ) -> Option<NaiveDateTime> {
+        let minute = now.with_second(0)?.with_nanosecond(0)?;
+        (0..duration_minutes as i64)
+            .map(|back| minute - chrono::Duration::minutes(back))
+            .find(|start| self.matches(start))
+    }
+
+    /// `now` より後の最初の開始時刻
+    pub fn next_start(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let minute = now.with_second(0)?.with_nanosecond(0)?;
+        (1..=LOOKAHEAD_MINUTES)
+            .map(|ahead| minute + chrono::Duration::minutes(ahead))
+            .find(|start| self.matches(start))
+    }
+}
+
+/// ローカル時刻をUTCに変換する（夏時間の切り替えで存在しない時刻は少し後ろにずらす）
+pub fn local_to_utc(at: NaiveDateTime) -> DateTime<Utc> {
+    match Local.from_local_datetime(&at).earliest() {
+        Some(local) => local.with_timezone(&Utc),
+        None => local_to_utc(at + chrono::Duration::hours(1)),
+    }
+}
+
+/// ウィンドウ設定を検証する
+pub fn validate(windows: &[MaintenanceWindow]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for window in windows {
+        if window.name.trim().is_empty() {
+            return Err("maintenance_windows[].name must not be empty".to_string());
+        }
+        if !names.insert(window.name.as_str()) {
+            return Err(format!(
+                "Duplicate maintenance window name '{}'",
+                window.name
+            ));
+        }
+        if let Some(path) = window
+            .workspaces
+            .iter()
+            .find(|p| !Path::new(p).is_absolute())
+        {
+            return Err(format!(
+                "Maintenance window '{}': workspace must be an absolute path: '{path}'",
+                window.name
+            ));
+        }
+        match &window.schedule {
+            MaintenanceSchedule::Once { start, end } => {
+                if end <= start {
+                    return Err(format!(
+                        "Maintenance window '{}': end must be after start",
+                        window.name
+                    ));
+                }
+            }
+            MaintenanceSchedule::Recurring {
+                cron,
+                duration_minutes,
+            } => {
+                CronSchedule::parse(cron)
+                    .map_err(|e| format!("Maintenance window '{}': {e}", window.name))?;
+                if !(1..=MAX_DURATION_MINUTES).contains(duration_minutes) {
+                    return Err(format!(
+                        "Maintenance window '{}': duration_minutes must be between 1 and {MAX_DURATION_MINUTES}",
+                        window.name
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `now` を含む期間（期間外ならNone）
+pub fn current_period(window: &MaintenanceWindow, now: DateTime<Utc>) -> Option<ActiveMaintenance> {
+    let (started_at, ends_at) = match &window.schedule {
+        MaintenanceSchedule::Once { start, end } => {
+            (*start <= now && now < *end).then_some((*start, *end))?
+        }
+        MaintenanceSchedule::Recurring {
+            cron,
+            duration_minutes,
+        } => {
+            let schedule = CronSchedule::parse(cron).ok()?;
+            let start = schedule
+                .current_start(now.with_timezone(&Local).naive_local(), *duration_minutes)?;
+            let started_at = local_to_utc(start);
+            let ends_at = started_at + chrono::Duration::minutes(*duration_minutes as i64);
+            (now < ends_at).then_some((started_at, ends_at))?
+        }
+    };
+    Some(ActiveMaintenance {
+        name: window.name.clone(),
+        reason: window.reason.clone(),
+        started_at,
+        ends_at,
+    })
+}
+
+/// `now` より後に始まる最初の時刻
+pub fn next_start(window: &MaintenanceWindow, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match &window.schedule {
+        MaintenanceSchedule::Once { start, .. } => (*start > now).then_some(*start),
+        MaintenanceSchedule::Recurring { cron, .. } => CronSchedule::parse(cron)
+            .ok()?
+            .next_start(now.with_timezone(&Local).naive_local())
+            .map(local_to_utc),
+    }
+}
+
+/// ウィンドウがプロセスを対象にしているか
+pub fn applies_to(window: &MaintenanceWindow, info: &ProcessInfo) -> bool {
+    if window.processes.is_empty() && window.tags.is_empty() && window.workspaces.is_empty() {
+        return true;
+    }
+    window.processes.contains(&info.id)
+        || window.tags.iter().any(|tag| info.tags.contains(tag))
+        || info.cwd.as_deref().is_some_and(|cwd| {
+            window
+                .workspaces
+                .iter()
+                .any(|workspace| cwd.starts_with(workspace))
+        })
+}
+
+/// プロセスに適用中のウィンドウ（複数ある場合は最も遅く終わるもの）
+pub fn active_for(
+    windows: &[MaintenanceWindow],
+    info: &ProcessInfo,
+    now: DateTime<Utc>,
+) -> Option<ActiveMaintenance> {
+    windows
+        .iter()
+        .filter(|window| applies_to(window, info))
+        .filter_map(|window| current_period(window, now))
+        .max_by_key(|active| active.ends_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::types::ProcessState;
+    use chrono::NaiveDate;
+    use std::collections::HashMap;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cron_parse_and_match() {
+        // 平日の2:30
+        let cron = CronSchedule::parse("30 2 * * mon-fri").unwrap();
+        assert!(cron.matches(&at(2024, 5, 6, 2, 30))); // 月曜日
+        assert!(!cron.matches(&at(2024, 5, 5, 2, 30))); // 日曜日
+        assert!(!cron.matches(&at(2024, 5, 6, 2, 31)));
+
+        let cron = CronSchedule::parse("*/15 0-6 1,15 * 7").unwrap();
+        assert!(cron.matches(&at(2024, 5, 15, 6, 45)));
+        // 日と曜日はどちらかに一致すればよい（2024-05-05は日曜日）
+        assert!(cron.matches(&at(2024, 5, 5, 0, 0)));
+        assert!(!cron.matches(&at(2024, 5, 6, 0, 0)));
+
+        assert!(CronSchedule::parse("* * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 * foo *").is_err());
+    }
+
+    #[test]
+    fn test_current_and_next_start() {
+        let cron = CronSchedule::parse("0 2 * * *").unwrap();
+        assert_eq!(
+            cron.current_start(at(2024, 5, 6, 2, 45), 60),
+            Some(at(2024, 5, 6, 2, 0))
+        );
+        assert_eq!(cron.current_start(at(2024, 5, 6, 3, 0), 60), None);
+        assert_eq!(
+            cron.next_start(at(2024, 5, 6, 2, 0)),
+            Some(at(2024, 5, 7, 2, 0))
+        );
+    }
+
+    #[test]
+    fn test_validate_and_scope() {
+        let now = Utc::now();
+        let window = MaintenanceWindow {
+            name: "deploy".to_string(),
+            schedule: MaintenanceSchedule::Once {
+                start: now - chrono::Duration::minutes(5),
+                end: now + chrono::Duration::minutes(5),
+            },
+            processes: Vec::new(),
+            tags: vec!["backend".to_string()],
+            workspaces: vec!["/srv/app".to_string()],
+            reason: Some("release 1.2".to_string()),
+        };
+        assert!(validate(std::slice::from_ref(&window)).is_ok());
+        assert!(validate(&[window.clone(), window.clone()]).is_err());
+
+        let mut info = ProcessInfo {
+            id: "api".to_string(),
+            command: "cargo".to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: Some("/srv/app/api".into()),
+            state: ProcessState::NotStarted,
+            auto_start_on_restore: false,
+            tags: Vec::new(),
+            idle_detection: None,
+            health_check: None,
+            tmux: None,
+            repl: None,
+            log_sources: Vec::new(),
+            run_as: None,
+            resource_limits: None,
+            container: None,
+            source: None,
+            log_sinks: Vec::new(),
+            redaction: None,
+            metadata: HashMap::new(),
+            runbook: None,
+            startup: None,
+            lazy_start: None,
+            env_loader: None,
+            output_recording: None,
+            assertions: Vec::new(),
+            restart_policy: None,
+            depends_on: Vec::new(),
+            parameters: Vec::new(),
+            keep_stdin_open: false,
+            group: None,
+            health: None,
+            sensitive_env: HashMap::new(),
+        };
+        let active = active_for(std::slice::from_ref(&window), &info, now).unwrap();
+        assert_eq!(active.reason.as_deref(), Some("release 1.2"));
+
+        info.cwd = Some("/home/me".into());
+        assert!(active_for(std::slice::from_ref(&window), &info, now).is_none());
+        info.tags = vec!["backend".to_string()];
+        assert!(active_for(std::slice::from_ref(&window), &info, now).is_some());
+        assert!(
+            active_for(
+                std::slice::from_ref(&window),
+                &info,
+                now + chrono::Duration::minutes(10)
+            )
+            .is_none()
+        );
+    }
+}
@@ -1,16 +1,108 @@
-use super::buffer::CircularBuffer;
+use super::adoption::{self, AdoptionReport, ExternalProcess};
+use super::approval::{self, ApprovalMode, ApprovalOperation};
+use super::blue_green::{
+    self, Instance, ProxyRouteStatus, ProxyRoutes, RollingRestartOptions, RollingRestartReport,
+};
+use super::bookmarks::{self, BookmarkedOutput, OutputBookmark};
+use super::buffer::{self, CircularBuffer, FollowedLines, FollowedOutput, LogLine};
+use super::buffer_memory::{self, BufferAllocation, BufferMemoryReport};
+use super::capture::{self, CaptureSource, CapturedTemplate};
+use super::cgroup::{self, Cgroup};
+use super::chain::{ChainManager, ChainOutcome, ChainStatus};
+use super::change_log;
+use super::chaos::{
+    self, ChaosAction, ChaosRecord, ChaosSchedule, ChaosSignal, ChaosState, ChaosStatus,
+    ChaosTarget,
+};
+use super::checks::{self, CheckReport, Evaluator, Observation, ObservedLine, ProcessCheckReport};
+use super::container::{self, ImageBuildStatus};
+use super::crash_loop::{CrashLoopInfo, CrashTracker, ExitRecord};
+use super::debug_bundle::{self, DebugBundle};
+use super::dependencies::{self, DependencyGraph};
+use super::diagnosis::{self, DiagnosisReport};
+use super::discovery::{self, DiscoveryReport};
+use super::energy::{self, CpuAccounting, ProcessResourceUsage, ResourceOverview};
+use super::env_diff::{self, EnvDiff};
+use super::env_docs;
+use super::env_loader::{self, Toolchain};
+use super::failure_report::{self, FailureReport};
+use super::fence::{FencedOperation, OperationFence};
+use super::filter_expr::{self, FilterExpr};
+use super::groups::{self, GroupStatus};
+use super::health_probe::{self, ProbeTracker};
+use super::history_export::{
+    self, ColumnType, ExportFormat, HistoryExport, Table, Value as HistoryValue,
+};
+use super::kubernetes::{KubernetesManager, WorkloadStatus};
+use super::lazy_start::{self, Activation, LazyListeners, LazyStartStatus};
+use super::log_quota::{self, LogDiskUsage, PruneReport};
+use super::log_sinks::{self, LogForwarder, LogSinkHealth};
+use super::log_sources;
+use super::maintenance::{self, ActiveMaintenance, MaintenanceWindowStatus};
+use super::metadata;
+use super::network::ProcessNetworkInfo;
+use super::notification_routing::{self, RouteSubject, RouteTest};
+use super::notifications;
+use super::output_diff::{self, OutputDiff, TimeWindow};
+use super::output_rates::{self, HealthStatus, OutputMetrics, Trend, WindowRates};
+use super::parameters;
+use super::pause;
+use super::phases::{PhaseDuration, compile_markers, compute_phase_durations};
+use super::port_owner;
+use super::pre_op::{self, UndoReport};
+use super::reaper::{self, PidStatus, ReapReport, Reaper, StateCorrection};
+use super::recording::{
+    self, RecordedStream, Recorder, Recording, RecordingHeader, RecordingSummary,
+};
+use super::recycle_bin::{self, CleanupCounts, CleanupFilter, CleanupReport};
+use super::redaction;
+use super::repl::{self, ReplExecution, ReplSession};
+use super::resource_samples::ResourceSamples;
+use super::restart_policy::{self, Decision, RestartTracker};
+use super::run_as;
+use super::run_environment::{self, RunEnvironment};
+use super::runbook::{self, RunbookContent};
+use super::safe_mode::{SafeMode, SafeModeRestoreReport, SafeModeStatus};
+use super::sampling::{OutputSampling, Sampler, SamplingStatus};
+use super::schedule::{self, ScheduleManager, ScheduleOutcome, ScheduleStatus};
+use super::session_context::{self, ContextRestoreReport, GitBranchRestore, SessionContext};
+use super::startup::{self, StartupReport, StartupResult};
+use super::stdin::ProcessStdin;
+use super::template_matrix::{self, MatrixInstance};
+use super::template_render::{self, RenderOverrides, RenderedTemplate};
+use super::timeline::{self, ProcessTimeline};
+use super::tmux::{self, TmuxPane};
+use super::tunnel::{TunnelManager, TunnelStatus};
 use super::types::*;
-use chrono::Utc;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use super::vscode_tasks::{self, ImportedTask, TaskImportReport};
+use super::workspace_bundle::{
+    self, ConflictPolicy, WorkspaceBundle, WorkspaceExportReport, WorkspaceImportOptions,
+    WorkspaceImportReport,
+};
+use super::workspace_quota::{self, QuotaMember, WorkspaceUsage};
+use crate::events::{EventSystem, EventType, ProcessEvent};
+use crate::security::{paths, secret_refs, sensitive_env};
+use crate::server_admin::{self, HandedOffProcess, OutputPipes, RestartHandoff};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::{Child, Command};
-use tokio::sync::RwLock;
+use tokio::process::Command;
+use tokio::sync::{RwLock, broadcast, mpsc, watch};
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
-use vantage_persistence::{PersistenceManager, ProcessTemplate, Settings};
+use vantage_persistence::template_schema;
+use vantage_persistence::{
+    ApprovalRequest, ApprovalStatus, ChainAction, ChangeHistory, ChangeTarget, ConfigFormat,
+    DefinitionChange, DiagnosisRule, DiagnosisRuleStatus, LazyStartConfig, MaintenanceWindow,
+    Notification, NotificationKind, NotificationRoute, NotificationSeverity, PersistenceManager,
+    PreOpSnapshot, ProcessChain, ProcessSchedule, ProcessTemplate, RecycledProcess,
+    RedactionOverride, RedactionRule, Runbook, Settings, WorkspaceQuota,
+};
 use vantage_persistence::{
     ProcessInfo as DbProcessInfo, ProcessState as DbProcessState, ProcessStatus as DbProcessStatus,
 };
@@ -20,8 +112,93 @@ pub struct ManagedProcess {
     pub info: ProcessInfo,
     pub stdout_buffer: CircularBuffer,
     pub stderr_buffer: CircularBuffer,
-    pub child: Option<Child>,
     pub output_handles: Option<(JoinHandle<()>, JoinHandle<()>)>,
+    /// アイドルと判定された時刻（出力が再開されるとクリアされる）
+    pub idle_since: Option<DateTime<Utc>>,
+    /// ヘルスチェックで異常と判定された時刻（しきい値を下回るとクリアされる）
+    unhealthy_since: Option<DateTime<Utc>>,
+    /// ヘルスチェックのプローブの実行状況と最後の結果
+    probe: ProbeTracker,
+    /// 異常終了の履歴とクラッシュループ状態
+    pub crash_tracker: CrashTracker,
+    /// カオステストで一時停止している場合、再開予定時刻
+    chaos_paused_until: Option<DateTime<Utc>>,
+    /// `resume_process` で最後に再開した時刻（一時停止中の無出力をアイドルとみなさないため）
+    resumed_at: Option<DateTime<Utc>>,
+    /// 追加のログソースの追従タスク
+    log_source_handles: Vec<JoinHandle<()>>,
+    /// REPLプロセスの標準入力（REPLとして起動した場合のみ）
+    repl_session: Option<Arc<ReplSession>>,
+    /// 標準入力のパイプ（`keep_stdin_open` で起動した場合のみ）
+    process_stdin: Option<Arc<ProcessStdin>>,
+    /// 実行中のプロセスが入っているcgroup（cgroup v2が使える場合のみ）
+    cgroup: Option<Cgroup>,
+    /// コンテナのイメージの最後のビルドの結果
+    image_build: Option<ImageBuildStatus>,
+    /// 起動時に子プロセスへ渡した環境変数と、そのうち定義に無く補ったものの名前
+    spawned_env: Option<(HashMap<String, String>, Vec<String>)>,
+    /// 起動した時刻（古い順、再起動前後の出力の比較に使う）
+    start_history: VecDeque<DateTime<Utc>>,
+    /// 起動ごとの実行環境（古い順）
+    run_history: VecDeque<RunEnvironment>,
+    /// これまでに起動した回数（実行環境の通し番号）
+    run_count: u64,
+    /// CPU時間の積算（再起動をまたぐ）
+    cpu_accounting: CpuAccounting,
+    /// 直近のCPU使用率・RSSの標本（再起動すると記録し直す）
+    resource_samples: ResourceSamples,
+    control: Option<ProcessControl>,
+    stop_requested: bool,
+    /// 外部で起動したプロセスを引き取った時刻（PIDで追跡し、出力は取り込まない）
+    adopted_at: Option<DateTime<Utc>>,
+    /// 出力のパイプの複製（サーバーの再起動で引き継ぐ）
+    output_pipes: Option<OutputPipes>,
+    /// 出力に付けたブックマーク（古い順）
+    bookmarks: Vec<OutputBookmark>,
+    /// 実行中の出力の記録（`output_recording` を設定している場合のみ）
+    recorder: Option<Recorder>,
+    /// `restart_process` で再起動している間（自動再起動はしない）
+    restarting: bool,
+    /// 再起動ポリシーによる再起動の回数と予定
+    restart: RestartTracker,
+}
+
+/// 起動したプロセスの実体（終了監視タスクが所有する）
+enum LaunchedProcess {
+    Child(tokio::process::Child),
+    Tmux(TmuxPane),
+}
+
+impl LaunchedProcess {
+    /// 終了を待ち、終了コードと終了させたシグナルを返す（取得できない場合はNone）
+    async fn wait(&mut self) -> std::io::Result<(Option<i32>, Option<i32>)> {
+        match self {
+            Self::Child(child) => child.wait().await.map(|status| {
+                #[cfg(unix)]
+                let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+                #[cfg(not(unix))]
+                let signal = None;
+                (status.code(), signal)
+            }),
+            Self::Tmux(pane) => Ok((tmux::wait_for_exit(pane).await, None)),
+        }
+    }
+
+    /// 強制終了
+    async fn kill(&mut self) -> Result<(), String> {
+        match self {
+            Self::Child(child) => child.kill().await.map_err(|e| e.to_string()),
+            Self::Tmux(pane) => tmux::kill_pane(pane).await,
+        }
+    }
+}
+
+/// 実行中プロセスの終了監視タスクとのやり取り
+struct ProcessControl {
+    /// 強制終了の要求
+    kill_tx: mpsc::Sender<()>,
+    /// 終了監視タスクが状態を更新し終えると `true` になる
+    exit_rx: watch::Receiver<bool>,
 }
 
 impl ManagedProcess {
@@ -32,29 +209,156 @@ impl ManagedProcess {
         env: HashMap<String, String>,
         cwd: Option<PathBuf>,
     ) -> Self {
+        Self::from_info(ProcessInfo {
+            id,
+            command,
+            args,
+            env,
+            cwd,
+            state: ProcessState::NotStarted,
+            auto_start_on_restore: false,
+            tags: Vec::new(),
+            idle_detection: None,
+            health_check: None,
+            tmux: None,
+            repl: None,
+            log_sources: Vec::new(),
+            run_as: None,
+            resource_limits: None,
+            container: None,
+            source: None,
+            log_sinks: Vec::new(),
+            redaction: None,
+            metadata: HashMap::new(),
+            runbook: None,
+            startup: None,
+            lazy_start: None,
+            env_loader: None,
+            output_recording: None,
+            assertions: Vec::new(),
+            restart_policy: None,
+            group: None,
+            depends_on: Vec::new(),
+            parameters: Vec::new(),
+            keep_stdin_open: false,
+            health: None,
+            sensitive_env: HashMap::new(),
+        })
+    }
+
+    /// 既存のプロセス情報から作成（永続化からの復元用）
+    pub fn from_info(info: ProcessInfo) -> Self {
+        let stdout_buffer = CircularBuffer::new(1000);
+        let stderr_buffer = stdout_buffer.paired(1000);
         Self {
-            info: ProcessInfo {
-                id,
-                command,
-                args,
-                env,
-                cwd,
-                state: ProcessState::NotStarted,
-                auto_start_on_restore: false,
-            },
-            stdout_buffer: CircularBuffer::new(1000),
-            stderr_buffer: CircularBuffer::new(1000),
-            child: None,
+            info,
+            stdout_buffer,
+            stderr_buffer,
             output_handles: None,
+            idle_since: None,
+            unhealthy_since: None,
+            probe: ProbeTracker::default(),
+            crash_tracker: CrashTracker::default(),
+            chaos_paused_until: None,
+            resumed_at: None,
+            log_source_handles: Vec::new(),
+            repl_session: None,
+            process_stdin: None,
+            cgroup: None,
+            image_build: None,
+            spawned_env: None,
+            start_history: VecDeque::new(),
+            run_history: VecDeque::new(),
+            run_count: 0,
+            cpu_accounting: CpuAccounting::default(),
+            resource_samples: ResourceSamples::default(),
+            control: None,
+            stop_requested: false,
+            adopted_at: None,
+            output_pipes: None,
+            bookmarks: Vec::new(),
+            recorder: None,
+            restarting: false,
+            restart: RestartTracker::default(),
+        }
+    }
+
+    /// 最後に出力があった時刻（出力がない場合は起動時刻）
+    async fn last_activity(&self) -> Option<DateTime<Utc>> {
+        let started_at = match &self.info.state {
+            ProcessState::Running { started_at, .. } => *started_at,
+            _ => return None,
+        };
+        let stdout = self.stdout_buffer.last_timestamp().await;
+        let stderr = self.stderr_buffer.last_timestamp().await;
+        [stdout, stderr, self.resumed_at]
+            .into_iter()
+            .flatten()
+            .filter(|t| *t >= started_at)
+            .max()
+            .or(Some(started_at))
+    }
+
+    /// `from` 以降 `to` より前（UNIX時刻の秒）の出力の頻度（`window_secs` で割る）
+    fn output_rates(&self, from: i64, to: i64, window_secs: u64) -> WindowRates {
+        WindowRates::new(
+            self.stdout_buffer.count_lines(from, to),
+            self.stderr_buffer.count_lines(from, to),
+            window_secs,
+        )
+    }
+
+    /// ヘルスチェックの判定（再起動の直後に前回の出力で異常としないよう、現在の起動以降に限る）
+    fn health(&self, config: &HealthCheckConfig, now: DateTime<Utc>) -> HealthStatus {
+        let to = output_rates::window_end(now);
+        let mut from = to - config.window_secs as i64;
+        if let ProcessState::Running { started_at, .. } | ProcessState::Paused { started_at, .. } =
+            &self.info.state
+        {
+            from = from.max(output_rates::bucket_start(started_at.timestamp()));
+        }
+        let rates = self.output_rates(from, to, config.window_secs);
+        let mut failures = output_rates::evaluate(config, &rates);
+        failures.extend(self.probe.failure(config));
+        HealthStatus {
+            healthy: failures.is_empty(),
+            failures,
+            rates,
+            unhealthy_since: self.unhealthy_since,
+            probe: self.probe.status().cloned(),
         }
     }
 }
 
+/// アイドル検知の確認間隔
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// ログクォータの適用間隔
+const LOG_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+/// コンテナのビルドコンテキストの変更確認間隔
+const IMAGE_WATCH_INTERVAL: Duration = Duration::from_secs(3);
+/// 確認を待つ間、判断を確かめる間隔
+const APPROVAL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// 終了したプロセスの出力を取り込み終えるまで待つ時間
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
 /// プロセスマネージャー
 #[derive(Clone)]
 pub struct ProcessManager {
     processes: Arc<RwLock<HashMap<String, Arc<RwLock<ManagedProcess>>>>>,
     persistence: Arc<PersistenceManager>,
+    event_system: Arc<EventSystem>,
+    chaos: Arc<ChaosState>,
+    tunnels: Arc<TunnelManager>,
+    kubernetes: Arc<KubernetesManager>,
+    fence: Arc<OperationFence>,
+    log_forwarder: Arc<LogForwarder>,
+    chains: Arc<ChainManager>,
+    schedules: Arc<ScheduleManager>,
+    proxy_routes: Arc<ProxyRoutes>,
+    lazy_listeners: Arc<LazyListeners>,
+    safe_mode: Arc<SafeMode>,
+    /// 最後に行った復元時の自動起動の結果
+    last_startup: Arc<RwLock<Option<StartupReport>>>,
 }
 
 // 型変換ヘルパー関数
@@ -71,12 +375,17 @@ impl ProcessManager {
             status: DbProcessStatus {
                 state: match &info.state {
                     ProcessState::NotStarted => DbProcessState::NotStarted,
-                    ProcessState::Running { .. } => DbProcessState::Running,
+                    // 一時停止はサーバーの再起動をまたいで保持できないため、実行中として保存する
+                    ProcessState::Running { .. } | ProcessState::Paused { .. } => {
+                        DbProcessState::Running
+                    }
                     ProcessState::Stopped { .. } => DbProcessState::Stopped,
                     ProcessState::Failed { .. } => DbProcessState::Failed,
                 },
                 pid: match &info.state {
-                    ProcessState::Running { pid, .. } => Some(*pid),
+                    ProcessState::Running { pid, .. } | ProcessState::Paused { pid, .. } => {
+                        Some(*pid)
+                    }
                     _ => None,
                 },
                 exit_code: match &info.state {
@@ -84,7 +393,8 @@ impl ProcessManager {
                     _ => None,
                 },
                 started_at: match &info.state {
-                    ProcessState::Running { started_at, .. } => Some(*started_at),
+                    ProcessState::Running { started_at, .. }
+                    | ProcessState::Paused { started_at, .. } => Some(*started_at),
                     _ => None,
                 },
                 stopped_at: match &info.state {
@@ -99,8 +409,32 @@ impl ProcessManager {
             },
             created_at: Utc::now(),
             updated_at: Utc::now(),
-            tags: vec![],
+            tags: info.tags.clone(),
             auto_start_on_restore: info.auto_start_on_restore,
+            idle_detection: info.idle_detection.clone(),
+            health_check: info.health_check.clone(),
+            tmux: info.tmux.clone(),
+            repl: info.repl.clone(),
+            log_sources: info.log_sources.clone(),
+            run_as: info.run_as.clone(),
+            resource_limits: info.resource_limits.clone(),
+            container: info.container.clone(),
+            source: info.source.clone(),
+            log_sinks: info.log_sinks.clone(),
+            redaction: info.redaction.clone(),
+            metadata: info.metadata.clone(),
+            runbook: info.runbook.clone(),
+            startup: info.startup.clone(),
+            lazy_start: info.lazy_start.clone(),
+            env_loader: info.env_loader,
+            output_recording: info.output_recording.clone(),
+            assertions: info.assertions.clone(),
+            restart_policy: info.restart_policy.clone(),
+            group: info.group.clone(),
+            depends_on: info.depends_on.clone(),
+            parameters: info.parameters.clone(),
+            keep_stdin_open: info.keep_stdin_open,
+            sensitive_env: info.sensitive_env.clone(),
         }
     }
 
@@ -138,6 +472,32 @@ impl ProcessManager {
                 _ => ProcessState::NotStarted, // Default fallback
             },
             auto_start_on_restore: db_info.auto_start_on_restore,
+            tags: db_info.tags,
+            idle_detection: db_info.idle_detection,
+            health_check: db_info.health_check,
+            tmux: db_info.tmux,
+            repl: db_info.repl,
+            log_sources: db_info.log_sources,
+            run_as: db_info.run_as,
+            resource_limits: db_info.resource_limits,
+            container: db_info.container,
+            source: db_info.source,
+            log_sinks: db_info.log_sinks,
+            redaction: db_info.redaction,
+            metadata: db_info.metadata,
+            runbook: db_info.runbook,
+            startup: db_info.startup,
+            lazy_start: db_info.lazy_start,
+            env_loader: db_info.env_loader,
+            output_recording: db_info.output_recording,
+            assertions: db_info.assertions,
+            restart_policy: db_info.restart_policy,
+            group: db_info.group,
+            depends_on: db_info.depends_on,
+            parameters: db_info.parameters,
+            keep_stdin_open: db_info.keep_stdin_open,
+            health: None,
+            sensitive_env: db_info.sensitive_env,
         }
     }
 }
@@ -152,10 +512,34 @@ impl ProcessManager {
             }
         };
 
-        Self {
+        let (lazy_listeners, activations) = LazyListeners::new();
+        let manager = Self {
             processes: Arc::new(RwLock::new(HashMap::new())),
+            tunnels: Arc::new(TunnelManager::new(persistence.clone())),
+            kubernetes: Arc::new(KubernetesManager::new(persistence.clone())),
+            fence: Arc::new(OperationFence::default()),
+            chains: Arc::new(ChainManager::new(persistence.clone())),
+            schedules: Arc::new(ScheduleManager::new(persistence.clone())),
             persistence,
-        }
+            event_system: Arc::new(EventSystem::new()),
+            chaos: Arc::new(ChaosState::default()),
+            log_forwarder: Arc::new(LogForwarder::default()),
+            proxy_routes: Arc::new(ProxyRoutes::default()),
+            lazy_listeners: Arc::new(lazy_listeners),
+            safe_mode: Arc::new(SafeMode::default()),
+            last_startup: Arc::new(RwLock::new(None)),
+        };
+        manager.spawn_idle_monitor();
+        manager.spawn_log_pruner();
+        manager.spawn_image_watcher();
+        manager.spawn_chain_monitor();
+        manager.spawn_schedule_monitor();
+        manager.spawn_reaper();
+        manager.spawn_maintenance_monitor();
+        manager.spawn_buffer_governor();
+        manager.spawn_restart_monitor();
+        manager.spawn_lazy_starter(activations);
+        manager
     }
 
     /// Get the persistence manager instance
@@ -163,889 +547,8361 @@ impl ProcessManager {
         self.persistence.clone()
     }
 
-    async fn load_persisted_processes(&self) -> Result<(), String> {
-        let loaded_processes = self.persistence.load_all_processes().await?;
-        let mut processes = self.processes.write().await;
-        let mut auto_start_processes = Vec::new();
-
-        for (id, db_info) in loaded_processes {
-            // Check if this process should be auto-started on restore
-            if db_info.auto_start_on_restore {
-                auto_start_processes.push(id.clone());
-            }
+    /// プロセスのライフサイクルイベントを発行するイベントシステム
+    pub fn event_system(&self) -> Arc<EventSystem> {
+        self.event_system.clone()
+    }
 
-            let info = Self::from_db_process_info(db_info);
-            let managed = ManagedProcess {
-                info,
-                stdout_buffer: CircularBuffer::new(1000),
-                stderr_buffer: CircularBuffer::new(1000),
-                child: None,
-                output_handles: None,
-            };
-            processes.insert(id, Arc::new(RwLock::new(managed)));
-        }
+    /// アイドル検知・ヘルスチェック・CPU時間の積算のタスクを起動
+    ///
+    /// プロセス表への弱参照のみを保持し、マネージャーが破棄されると終了する。
+    fn spawn_idle_monitor(&self) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
 
-        let loaded_count = processes.len();
-        tracing::info!("Loaded {} persisted processes", loaded_count);
+        tokio::spawn(async move {
+            let mut system = System::new();
+            // CPU使用率の差分に影響しないよう、積算には別のSystemを使う
+            let mut accounting_system = System::new();
+            let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: Arc::new(ChaosState::default()),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                manager.check_idle_processes(&mut system).await;
+                manager.check_process_health().await;
+                manager.sample_resources(&mut accounting_system).await;
+            }
+        });
+    }
 
-        // Release the write lock before starting processes
-        drop(processes);
+    /// 連鎖の実行タスクを起動
+    ///
+    /// プロセスの終了イベントを受け、正常終了したプロセスに続く連鎖を実行する。
+    fn spawn_chain_monitor(&self) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let chaos = self.chaos.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
+        let mut receiver = self.event_system.subscribe();
 
-        // Start auto-start processes
-        if !auto_start_processes.is_empty() {
-            tracing::info!(
-                "Starting {} processes with auto_start_on_restore enabled",
-                auto_start_processes.len()
-            );
-            for process_id in auto_start_processes {
-                match self.start_process(process_id.clone()).await {
-                    Ok(pid) => {
-                        tracing::info!(
-                            "Auto-started process '{}' with PID {} on restore",
-                            process_id,
-                            pid
-                        );
-                    }
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to auto-start process '{}' on restore: {}",
-                            process_id,
-                            e
-                        );
+        tokio::spawn(async move {
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Chain monitor missed {} process events", skipped);
+                        continue;
                     }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let context = event.context.unwrap_or_default();
+                let succeeded = matches!(event.event_type, EventType::ProcessStopped)
+                    && context["exit_code"] == 0
+                    && context["stop_requested"] != true;
+                if !succeeded {
+                    continue;
                 }
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: chaos.clone(),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                manager.run_chains(&event.process_id).await;
             }
-        }
+        });
+    }
 
-        Ok(())
+    /// 定期実行のタスクを起動
+    ///
+    /// 一定の間隔で時刻になった定期実行を確かめ、対象のプロセスを起動する。
+    fn spawn_schedule_monitor(&self) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let chaos = self.chaos.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(schedule::CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: chaos.clone(),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                manager.run_due_schedules().await;
+            }
+        });
     }
 
-    /// プロセスを作成・登録
-    #[allow(clippy::too_many_arguments)]
-    pub async fn create_process(
-        &self,
-        id: String,
-        command: String,
-        args: Vec<String>,
-        env: HashMap<String, String>,
-        cwd: Option<PathBuf>,
-        auto_start_on_restore: bool,
-    ) -> Result<(), String> {
-        // セキュリティ検証
-        crate::security::validate_process_inputs(&command, &args, &env, &cwd)?;
+    /// 終了済みの子プロセスの回収と状態の検証を定期的に行うタスクを起動
+    fn spawn_reaper(&self) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
 
-        info!(
-            "Creating process '{}': {} {:?} (auto_start_on_restore: {})",
-            id, command, args, auto_start_on_restore
-        );
-        let mut processes = self.processes.write().await;
+        tokio::spawn(async move {
+            let mut reaper = Reaper::new();
+            let mut interval = tokio::time::interval(reaper::REAP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: Arc::new(ChaosState::default()),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                manager.reap_processes(&mut reaper).await;
+                manager.auto_adopt_external_processes().await;
+            }
+        });
+    }
 
-        if processes.contains_key(&id) {
-            return Err(format!("Process with id '{id}' already exists"));
-        }
+    /// ログクォータを定期的に適用するタスクを起動
+    fn spawn_log_pruner(&self) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
 
-        let mut process = ManagedProcess::new(id.clone(), command, args, env, cwd);
-        process.info.auto_start_on_restore = auto_start_on_restore;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(LOG_PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: Arc::new(ChaosState::default()),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                match manager.prune_logs().await {
+                    Ok(report) if !report.removed_files.is_empty() => info!(
+                        "Pruned {} log file(s), freed {} bytes",
+                        report.removed_files.len(),
+                        report.freed_bytes
+                    ),
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to enforce log quota: {}", e),
+                }
+            }
+        });
+    }
 
-        let process_info = process.info.clone();
-        let process_arc = Arc::new(RwLock::new(process));
-        processes.insert(id.clone(), process_arc.clone());
+    /// メンテナンスウィンドウの開始・終了を監視するタスクを起動
+    ///
+    /// 対象のプロセスごとに、期間に入ったときと抜けたときにイベントを発行する。
+    fn spawn_maintenance_monitor(&self) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
 
-        // Release the write lock before persistence and auto-start
-        drop(processes);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(maintenance::CHECK_INTERVAL);
+            let mut active = HashSet::new();
+            loop {
+                interval.tick().await;
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: Arc::new(ChaosState::default()),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                manager.check_maintenance_windows(&mut active).await;
+            }
+        });
+    }
 
-        // Persist the process
-        let db_process_info = Self::to_db_process_info(&process_info);
-        match self.persistence.save_process(&db_process_info).await {
-            Ok(_) => tracing::debug!("Process {} persisted successfully", id),
-            Err(e) => tracing::warn!("Failed to persist process {}: {}", id, e),
-        }
+    /// 出力バッファのメモリ使用量を監視するタスクを起動
+    fn spawn_buffer_governor(&self) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
 
-        Ok(())
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(buffer_memory::CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: Arc::new(ChaosState::default()),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                if let Err(e) = manager.rebalance_output_buffers().await {
+                    warn!("Failed to rebalance output buffers: {}", e);
+                }
+            }
+        });
     }
 
-    /// プロセスを起動
-    pub async fn start_process(&self, id: String) -> Result<u32, String> {
-        info!("Starting process '{}'...", id);
-        let processes = self.processes.read().await;
-        let process_arc = processes
-            .get(&id)
-            .ok_or_else(|| format!("Process '{id}' not found"))?
-            .clone();
-        drop(processes);
+    /// 再起動ポリシーによる再起動のタスクを起動
+    ///
+    /// 終了監視タスクが予定した再起動のイベントを受け、待ち時間が過ぎてから起動し直す。
+    fn spawn_restart_monitor(&self) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let chaos = self.chaos.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
+        let mut receiver = self.event_system.subscribe();
 
-        let mut process = process_arc.write().await;
+        tokio::spawn(async move {
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Restart monitor missed {} process events", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if !matches!(event.event_type, EventType::RestartScheduled) {
+                    continue;
+                }
+                let delay = event
+                    .context
+                    .as_ref()
+                    .and_then(|context| context["delay_ms"].as_u64())
+                    .map(Duration::from_millis)
+                    .unwrap_or_default();
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: chaos.clone(),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    manager.run_scheduled_restart(event.process_id).await;
+                });
+            }
+        });
+    }
 
-        // すでに実行中の場合はエラー
-        if matches!(process.info.state, ProcessState::Running { .. }) {
-            return Err(format!("Process '{id}' is already running"));
-        }
+    /// 遅延起動のタスクを起動
+    ///
+    /// 待ち受けから依頼を受けてプロセスを起動し、接続がなくなったプロセスを停止する。
+    fn spawn_lazy_starter(&self, mut activations: mpsc::UnboundedReceiver<Activation>) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
 
-        // コマンドを構築
-        let mut cmd = Command::new(&process.info.command);
-        cmd.args(&process.info.args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .stdin(Stdio::null());
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(lazy_start::IDLE_CHECK_INTERVAL);
+            loop {
+                let activation = tokio::select! {
+                    activation = activations.recv() => match activation {
+                        Some(activation) => Some(activation),
+                        None => break,
+                    },
+                    _ = interval.tick() => None,
+                };
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: Arc::new(ChaosState::default()),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                match activation {
+                    // 起動を待つ間も他のプロセスの依頼を受けられるよう、別のタスクで起動する
+                    Some(activation) => {
+                        tokio::spawn(async move {
+                            let result = manager
+                                .activate_lazy_process(
+                                    &activation.process_id,
+                                    activation.backend_port,
+                                    activation.ready_timeout,
+                                )
+                                .await;
+                            let _ = activation.reply.send(result);
+                        });
+                    }
+                    None => manager.stop_idle_lazy_processes().await,
+                }
+            }
+        });
+    }
 
-        // プロセスグループを設定（Unix系システムのみ）
-        // これにより、子プロセス（Dockerコンテナなど）も含めてシグナルを送信できる
-        #[cfg(unix)]
-        {
-            #[allow(unused_imports)]
-            use std::os::unix::process::CommandExt;
-            cmd.process_group(0); // 新しいプロセスグループを作成
-        }
+    /// 期間に入った・抜けた（プロセス, ウィンドウ）の組についてイベントを発行する
+    async fn check_maintenance_windows(&self, active: &mut HashSet<(String, String)>) {
+        let windows = match self.get_settings().await {
+            Ok(settings) => settings.maintenance_windows,
+            Err(e) => {
+                warn!("Failed to load maintenance windows: {}", e);
+                return;
+            }
+        };
+        let now = Utc::now();
+        let periods: Vec<(&MaintenanceWindow, ActiveMaintenance)> = windows
+            .iter()
+            .filter_map(|window| Some((window, maintenance::current_period(window, now)?)))
+            .collect();
 
-        // 環境変数を設定
-        for (key, value) in &process.info.env {
-            cmd.env(key, value);
+        let mut current = HashMap::new();
+        for info in self.list_processes(None).await {
+            for (window, period) in &periods {
+                if maintenance::applies_to(window, &info) {
+                    current.insert((info.id.clone(), period.name.clone()), period.clone());
+                }
+            }
         }
 
-        // 作業ディレクトリを設定
-        if let Some(cwd) = &process.info.cwd {
-            cmd.current_dir(cwd);
+        for (key, period) in &current {
+            if active.contains(key) {
+                continue;
+            }
+            info!(
+                "Maintenance window '{}' started for process '{}'",
+                period.name, key.0
+            );
+            if let Err(e) = self
+                .event_system
+                .emit_maintenance_started(key.0.clone(), period)
+                .await
+            {
+                warn!("Failed to emit maintenance event: {}", e);
+            }
         }
+        for (process_id, name) in active.iter().filter(|key| !current.contains_key(*key)) {
+            info!(
+                "Maintenance window '{}' ended for process '{}'",
+                name, process_id
+            );
+            if let Err(e) = self
+                .event_system
+                .emit_maintenance_ended(process_id.clone(), name.clone())
+                .await
+            {
+                warn!("Failed to emit maintenance event: {}", e);
+            }
+        }
+        *active = current.into_keys().collect();
+    }
 
-        // プロセスを起動
-        let mut child = cmd
-            .spawn()
-            .map_err(|e| format!("Failed to start process: {e}"))?;
-
-        let pid = child
-            .id()
-            .ok_or_else(|| "Failed to get process ID".to_string())?;
+    /// ログディレクトリの使用量を取得
+    pub async fn log_disk_usage(&self) -> Result<LogDiskUsage, String> {
+        let quota = self.get_settings().await?.log_quota;
+        let dir = log_quota::default_log_dir();
+        tokio::task::spawn_blocking(move || log_quota::usage(&dir, &quota))
+            .await
+            .map_err(|e| format!("Log usage task failed: {e}"))?
+            .map_err(|e| format!("Failed to read log directory: {e}"))
+    }
 
-        // 標準出力と標準エラー出力を処理
-        let stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| "Failed to capture stdout".to_string())?;
-        let stderr = child
-            .stderr
-            .take()
-            .ok_or_else(|| "Failed to capture stderr".to_string())?;
+    /// ワークスペースごとの使用量を取得
+    pub async fn workspace_usage(&self) -> Result<Vec<WorkspaceUsage>, String> {
+        let quotas = self.get_settings().await?.workspace_quotas;
+        self.collect_workspace_usage(&quotas).await
+    }
 
-        let stdout_buffer = process.stdout_buffer.clone();
-        let stderr_buffer = process.stderr_buffer.clone();
+    /// サーバーの診断情報（プロセス数、ログとワークスペースの使用量、ログシンクとシークレットの取り出し元の状態）
+    pub async fn diagnostics(&self) -> Result<serde_json::Value, String> {
+        let processes = self.list_processes(None).await;
+        let running = processes
+            .iter()
+            .filter(|p| matches!(p.state, ProcessState::Running { .. }))
+            .count();
+        let paused = processes
+            .iter()
+            .filter(|p| matches!(p.state, ProcessState::Paused { .. }))
+            .count();
+        Ok(serde_json::json!({
+            "process_count": processes.len(),
+            "running_count": running,
+            "paused_count": paused,
+            "log_disk_usage": self.log_disk_usage().await?,
+            "workspace_usage": self.workspace_usage().await?,
+            "log_sinks": self.log_sink_health().await,
+            "buffer_memory": self.buffer_memory().await?,
+            "secret_providers": secret_refs::resolver().health(),
+        }))
+    }
 
-        // 出力を非同期で読み取る
-        let stdout_handle = tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                stdout_buffer.push(line).await;
-            }
-        });
+    /// 出力バッファのメモリの割り当て
+    pub async fn buffer_memory(&self) -> Result<BufferMemoryReport, String> {
+        let settings = self.get_settings().await?.output_memory;
+        Ok(BufferMemoryReport::new(
+            &settings,
+            self.buffer_allocations().await,
+        ))
+    }
 
-        let stderr_handle = tokio::spawn(async move {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                stderr_buffer.push(line).await;
+    async fn buffer_allocations(&self) -> Vec<BufferAllocation> {
+        let processes: Vec<_> = self.processes.read().await.values().cloned().collect();
+        let mut allocations = Vec::with_capacity(processes.len());
+        for process_arc in processes {
+            let process = process_arc.read().await;
+            let buffers = [&process.stdout_buffer, &process.stderr_buffer];
+            let mut last_output = None;
+            let mut lines = 0;
+            for buffer in buffers {
+                last_output = last_output.max(buffer.last_timestamp().await);
+                lines += buffer.len().await;
             }
-        });
+            allocations.push(BufferAllocation {
+                process_id: process.info.id.clone(),
+                running: matches!(
+                    process.info.state,
+                    ProcessState::Running { .. } | ProcessState::Paused { .. }
+                ),
+                last_output,
+                lines,
+                capacity: buffers.iter().map(|b| b.capacity()).sum(),
+                base_capacity: buffers.iter().map(|b| b.base_capacity()).sum(),
+                bytes: buffers.iter().map(|b| b.memory_bytes()).sum(),
+                shrunk: buffers.iter().any(|b| b.capacity() < b.base_capacity()),
+            });
+        }
+        allocations
+    }
 
-        // プロセス情報を更新
-        process.info.state = ProcessState::Running {
-            pid,
-            started_at: chrono::Utc::now(),
-        };
-        process.child = Some(child);
-        process.output_handles = Some((stdout_handle, stderr_handle));
+    /// 出力バッファの合計が上限を超えていれば縮め、十分に下回っていれば元に戻す
+    ///
+    /// 縮めたプロセスのIDを返す。
+    pub async fn rebalance_output_buffers(&self) -> Result<Vec<String>, String> {
+        let settings = self.get_settings().await?.output_memory;
+        let allocations = self.buffer_allocations().await;
+        let plan = buffer_memory::plan(&allocations, &settings, Utc::now());
 
-        // Persist the updated state
-        let db_info = Self::to_db_process_info(&process.info);
-        if let Err(e) = self.persistence.update_process(&db_info).await {
-            tracing::warn!("Failed to persist process state: {}", e);
+        if plan.restore {
+            for process_arc in self.processes.read().await.values() {
+                let process = process_arc.read().await;
+                process.stdout_buffer.restore_capacity();
+                process.stderr_buffer.restore_capacity();
+            }
+            debug!("Restored the output buffer capacities");
         }
-
-        // プロセスの終了を監視するタスクを起動
-        let process_id = id.clone();
-        let process_arc_clone = process_arc.clone();
-        let persistence_clone = self.persistence.clone();
-        tokio::spawn(async move {
-            // childプロセスへの参照を取得
-            let child_opt = {
-                let mut process = process_arc_clone.write().await;
-                process.child.take()
+        for id in &plan.shrink {
+            let Some(process_arc) = self.processes.read().await.get(id).cloned() else {
+                continue;
             };
-
-            if let Some(mut child) = child_opt {
-                // プロセスの終了を待つ
-                match child.wait().await {
-                    Ok(status) => {
-                        let exit_code = status.code();
-                        debug!("Process '{}' exited with code: {:?}", process_id, exit_code);
-
-                        // プロセス状態を更新
-                        let mut process = process_arc_clone.write().await;
-                        process.info.state = ProcessState::Stopped {
-                            exit_code,
-                            stopped_at: chrono::Utc::now(),
-                        };
-
-                        // 永続化
-                        let db_info = ProcessManager::to_db_process_info(&process.info);
-                        if let Err(e) = persistence_clone.update_process(&db_info).await {
-                            tracing::warn!("Failed to persist stopped process state: {}", e);
-                        }
-
-                        info!(
-                            "Process '{}' stopped with exit code: {:?}",
-                            process_id, exit_code
-                        );
-                    }
-                    Err(e) => {
-                        error!("Failed to wait for process '{}': {}", process_id, e);
-
-                        // エラー状態を設定
-                        let mut process = process_arc_clone.write().await;
-                        process.info.state = ProcessState::Failed {
-                            error: format!("Process wait failed: {e}"),
-                            failed_at: chrono::Utc::now(),
-                        };
-
-                        // 永続化
-                        let db_info = ProcessManager::to_db_process_info(&process.info);
-                        if let Err(e) = persistence_clone.update_process(&db_info).await {
-                            tracing::warn!("Failed to persist failed process state: {}", e);
-                        }
-                    }
+            let (stdout_buffer, stderr_buffer) = {
+                let process = process_arc.read().await;
+                (process.stdout_buffer.clone(), process.stderr_buffer.clone())
+            };
+            let mut freed = 0;
+            for (buffer, stream) in [(stdout_buffer, "stdout"), (stderr_buffer, "stderr")] {
+                let dropped = buffer.shrink(settings.min_buffer_lines).await;
+                freed += dropped.iter().map(LogLine::memory_bytes).sum::<u64>();
+                if !settings.spill_to_disk || dropped.is_empty() {
+                    continue;
+                }
+                let path = buffer_memory::spill_path(&log_quota::default_log_dir(), id, stream);
+                let result =
+                    tokio::task::spawn_blocking(move || buffer_memory::spill(&path, &dropped))
+                        .await;
+                if !matches!(result, Ok(Ok(()))) {
+                    warn!("Failed to spill the {} buffer of '{}' to disk", stream, id);
                 }
             }
-        });
+            info!(
+                "Shrunk the output buffers of '{}' to {} lines under memory pressure ({} bytes freed)",
+                id, settings.min_buffer_lines, freed
+            );
+        }
+        Ok(plan.shrink)
+    }
 
-        info!("Started process '{}' with PID {}", id, pid);
-        Ok(pid)
+    /// 終了済みの子プロセスを回収し、実体と一致しなくなった状態を訂正する
+    ///
+    /// 実行中・一時停止中のプロセスのPIDが存在しない、または別のプロセスに再利用されている
+    /// 場合は停止状態に訂正し、`process_state_corrected` イベントを発行する。終了監視タスクが
+    /// あるプロセスは終了直後にタスク自身が状態を更新するため、続けて2回一致しなかった場合のみ
+    /// 訂正する。
+    pub async fn reap_processes(&self, reaper: &mut Reaper) -> ReapReport {
+        let candidates: Vec<(String, Arc<RwLock<ManagedProcess>>)> = {
+            let processes = self.processes.read().await;
+            processes
+                .iter()
+                .map(|(id, p)| (id.clone(), p.clone()))
+                .collect()
+        };
+        reaper.refresh();
+
+        let mut report = ReapReport::default();
+        let mut tracked = HashSet::new();
+        for (id, process_arc) in candidates {
+            let mut process = process_arc.write().await;
+            let (pid, started_at, previous_state) = match process.info.state {
+                ProcessState::Running { pid, started_at } => (pid, started_at, "running"),
+                ProcessState::Paused {
+                    pid, started_at, ..
+                } => (pid, started_at, "paused"),
+                _ => {
+                    reaper.clear_mismatched(&id);
+                    continue;
+                }
+            };
+            let watched = process.control.is_some();
+            if watched {
+                tracked.insert(pid);
+            }
+            let status = reaper.check(pid, started_at);
+            // 監視タスクのあるプロセスのゾンビはタスクが回収する
+            let stale = match status {
+                PidStatus::Alive => false,
+                PidStatus::Zombie => !watched,
+                PidStatus::Gone | PidStatus::Reused => true,
+            };
+            if !stale {
+                reaper.clear_mismatched(&id);
+                continue;
+            }
+            if watched && !reaper.mark_mismatched(&id) {
+                continue;
+            }
+            reaper.clear_mismatched(&id);
+
+            process.info.state = ProcessState::Stopped {
+                exit_code: None,
+                stopped_at: Utc::now(),
+            };
+            process.chaos_paused_until = None;
+            process.idle_since = None;
+            process.adopted_at = None;
+            process.info.health = None;
+            let db_info = Self::to_db_process_info(&process.info);
+            if let Err(e) = self.persistence.update_process(&db_info).await {
+                warn!("Failed to persist corrected process state: {}", e);
+            }
+            warn!(
+                "Process '{}' was {} but PID {} is {:?}; marked as stopped",
+                id, previous_state, pid, status
+            );
+            report.corrected.push(StateCorrection {
+                process_id: id,
+                pid,
+                previous_state: previous_state.to_string(),
+                status,
+            });
+        }
+
+        report.reaped = reaper.reap_zombies(&tracked);
+        for child in &report.reaped {
+            info!(
+                "Reaped zombie child process {} (exit code: {:?})",
+                child.pid, child.exit_code
+            );
+        }
+        for correction in &report.corrected {
+            if let Err(e) = self
+                .event_system
+                .emit_process_state_corrected(correction)
+                .await
+            {
+                debug!("Failed to emit state correction event: {}", e);
+            }
+        }
+        report
     }
 
-    /// プロセスを停止
-    pub async fn stop_process(
+    /// 登録済みの定義と一致する、外部で起動したプロセスを探す
+    ///
+    /// `process_ids` を指定するとその定義だけを照合する（空の場合は実行中でないすべての定義）。
+    /// `adopt` が真であれば見つけたプロセスを引き取る。同じ定義に複数が一致した場合は
+    /// 最初の1つだけを引き取る。
+    pub async fn scan_external_processes(
         &self,
-        id: String,
-        grace_period_ms: Option<u64>,
-    ) -> Result<(), String> {
-        info!("Stopping process '{}'...", id);
+        process_ids: Vec<String>,
+        adopt: bool,
+    ) -> Result<AdoptionReport, String> {
         let processes = self.processes.read().await;
-        let process_arc = processes
-            .get(&id)
-            .ok_or_else(|| format!("Process '{id}' not found"))?
-            .clone();
+        if let Some(unknown) = process_ids.iter().find(|id| !processes.contains_key(*id)) {
+            return Err(format!("Process '{unknown}' not found"));
+        }
         drop(processes);
+        let mut report = self.find_external_processes(&process_ids).await?;
+        if adopt {
+            let found = report.found.clone();
+            self.adopt_external_processes(&found, false, &mut report)
+                .await;
+        }
+        Ok(report)
+    }
 
-        let mut process = process_arc.write().await;
+    /// 自動で引き取るワークスペースで一致したプロセスを引き取る（定期的な確認から呼ぶ）
+    ///
+    /// セーフモードの間は自動起動と同様に行わない。
+    pub async fn auto_adopt_external_processes(&self) -> AdoptionReport {
+        let no_workspaces = self
+            .get_settings()
+            .await
+            .map_or(true, |settings| settings.auto_adopt_workspaces.is_empty());
+        if no_workspaces || self.safe_mode.is_enabled() {
+            return AdoptionReport::default();
+        }
+        let mut report = match self.find_external_processes(&[]).await {
+            Ok(report) => report,
+            Err(e) => {
+                debug!("Failed to scan external processes: {}", e);
+                return AdoptionReport::default();
+            }
+        };
+        let candidates: Vec<ExternalProcess> = report
+            .found
+            .iter()
+            .filter(|external| external.auto_adopt)
+            .cloned()
+            .collect();
+        self.adopt_external_processes(&candidates, true, &mut report)
+            .await;
+        report
+    }
 
-        // 実行中でない場合はエラー
-        if !matches!(process.info.state, ProcessState::Running { .. }) {
-            return Err(format!("Process '{id}' is not running"));
+    async fn find_external_processes(
+        &self,
+        process_ids: &[String],
+    ) -> Result<AdoptionReport, String> {
+        let workspaces = self.get_settings().await?.auto_adopt_workspaces;
+        let server_cwd = std::env::current_dir().map_err(|e| e.to_string())?;
+        let mut definitions = Vec::new();
+        let mut managed = HashSet::new();
+        for (id, process_arc) in self.processes.read().await.iter() {
+            let process = process_arc.read().await;
+            match process.info.state {
+                ProcessState::Running { pid, .. } | ProcessState::Paused { pid, .. } => {
+                    managed.insert(pid);
+                }
+                // コンテナとtmuxのプロセスはホストのコマンドとして照合できない
+                _ if process.info.container.is_some() || process.info.tmux.is_some() => {}
+                _ if process_ids.is_empty() || process_ids.contains(id) => {
+                    definitions.push(adoption::Definition {
+                        id: id.clone(),
+                        command: process.info.command.clone(),
+                        args: process.info.args.clone(),
+                        cwd: process
+                            .info
+                            .cwd
+                            .clone()
+                            .unwrap_or_else(|| server_cwd.clone()),
+                    });
+                }
+                _ => {}
+            }
+        }
+        if definitions.is_empty() {
+            return Ok(AdoptionReport::default());
         }
 
-        if let Some(mut child) = process.child.take() {
-            // デフォルトのグレースピリオドは5秒
-            let grace_ms = grace_period_ms.unwrap_or(5000);
+        let mut found = tokio::task::spawn_blocking(move || adoption::scan(&definitions, &managed))
+            .await
+            .map_err(|e| e.to_string())?;
+        for external in &mut found {
+            external.auto_adopt = adoption::in_workspaces(Path::new(&external.cwd), &workspaces);
+        }
+        Ok(AdoptionReport {
+            found,
+            ..Default::default()
+        })
+    }
 
-            // まずSIGTERMを送信してグレースフルシャットダウンを試みる
-            #[cfg(unix)]
-            {
-                use nix::sys::signal::{self, Signal};
-                use nix::unistd::Pid;
-
-                if let Some(pid) = child.id() {
-                    let pid = Pid::from_raw(pid as i32);
-
-                    // まずプロセスグループ全体にSIGTERMを送信（Dockerなどの子プロセス対策）
-                    let pgid = Pid::from_raw(-(pid.as_raw()));
-                    if let Err(e) = signal::kill(pgid, Signal::SIGTERM) {
-                        tracing::debug!("Failed to send SIGTERM to process group {}: {}", id, e);
-                        // プロセスグループ送信が失敗した場合、個別のプロセスに送信
-                        if let Err(e) = signal::kill(pid, Signal::SIGTERM) {
-                            tracing::warn!("Failed to send SIGTERM to process {}: {}", id, e);
-                        }
+    async fn adopt_external_processes(
+        &self,
+        candidates: &[ExternalProcess],
+        auto: bool,
+        report: &mut AdoptionReport,
+    ) {
+        for external in candidates {
+            match self.adopt_external_process(external).await {
+                Ok(()) => {
+                    info!(
+                        "Adopted external process {} as '{}'{}",
+                        external.pid,
+                        external.process_id,
+                        if auto { " (auto)" } else { "" }
+                    );
+                    if let Err(e) = self.event_system.emit_process_adopted(external, auto).await {
+                        debug!("Failed to emit adoption event: {}", e);
                     }
+                    report.adopted.push(external.process_id.clone());
+                }
+                Err(e) => report.skipped.push((external.process_id.clone(), e)),
+            }
+        }
+    }
 
-                    // SIGTERM送信に成功した場合の処理
-                    {
-                        info!(
-                            "Sent SIGTERM to process '{}', waiting up to {}ms for graceful shutdown",
-                            id, grace_ms
-                        );
+    /// 外部のプロセスを定義の実行中のプロセスとして記録する（出力は取り込まない）
+    async fn adopt_external_process(&self, external: &ExternalProcess) -> Result<(), String> {
+        let id = &external.process_id;
+        let process_arc = self
+            .processes
+            .read()
+            .await
+            .get(id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?
+            .clone();
+        let mut process = process_arc.write().await;
+        if matches!(
+            process.info.state,
+            ProcessState::Running { .. } | ProcessState::Paused { .. }
+        ) {
+            return Err(format!("Process '{id}' is already running"));
+        }
+        // 見つけてから引き取るまでの間に終了・再利用されていないか確かめる
+        if reaper::verify(external.pid, external.started_at) != PidStatus::Alive {
+            return Err(format!("PID {} is no longer running", external.pid));
+        }
+        process.info.state = ProcessState::Running {
+            pid: external.pid,
+            started_at: external.started_at,
+        };
+        process.adopted_at = Some(Utc::now());
+        process.idle_since = None;
+        process.unhealthy_since = None;
+        process.probe.reset();
+        process.info.health = None;
+        process.stop_requested = false;
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            warn!("Failed to persist adopted process state: {}", e);
+        }
+        Ok(())
+    }
+
+    /// 引き取ったプロセスを停止する（PIDにだけシグナルを送り、状態は自分で更新する）
+    async fn stop_adopted_process(
+        &self,
+        id: &str,
+        process_arc: &Arc<RwLock<ManagedProcess>>,
+        grace_ms: u64,
+    ) -> Result<(), String> {
+        let (pid, started_at, paused) = {
+            let mut process = process_arc.write().await;
+            process.stop_requested = true;
+            match process.info.state {
+                ProcessState::Running { pid, started_at } => (pid, started_at, false),
+                ProcessState::Paused {
+                    pid, started_at, ..
+                } => (pid, started_at, true),
+                _ => return Err(format!("Process '{id}' is not running")),
+            }
+        };
+        if paused && reaper::verify(pid, started_at) == PidStatus::Alive {
+            let _ = pause::resume(id, pid, None).await;
+        }
+        adoption::terminate(pid, started_at, Duration::from_millis(grace_ms)).await?;
+
+        let mut process = process_arc.write().await;
+        process.info.state = ProcessState::Stopped {
+            exit_code: None,
+            stopped_at: Utc::now(),
+        };
+        process.adopted_at = None;
+        process.idle_since = None;
+        process.chaos_paused_until = None;
+        process.info.health = None;
+        let db_info = Self::to_db_process_info(&process.info);
+        drop(process);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            warn!("Failed to persist process state: {}", e);
+        }
+        info!("Stopped adopted process '{}' (PID {})", id, pid);
+        if let Err(e) = self
+            .event_system
+            .emit_process_stopped(id.to_string(), None, true, None)
+            .await
+        {
+            debug!("Failed to emit stop event: {}", e);
+        }
+        Ok(())
+    }
+
+    /// サーバーの再起動で引き継ぐ実行中のプロセス（引き継がないものは理由付き）
+    pub async fn prepare_handoff(&self) -> (Vec<HandedOffProcess>, Vec<(String, String)>) {
+        let candidates: Vec<(String, Arc<RwLock<ManagedProcess>>)> = {
+            let processes = self.processes.read().await;
+            processes
+                .iter()
+                .map(|(id, p)| (id.clone(), p.clone()))
+                .collect()
+        };
+        let mut handed_off = Vec::new();
+        let mut skipped = Vec::new();
+        for (id, process_arc) in candidates {
+            let process = process_arc.read().await;
+            let (pid, started_at, paused_at) = match process.info.state {
+                ProcessState::Running { pid, started_at } => (pid, started_at, None),
+                ProcessState::Paused {
+                    pid,
+                    started_at,
+                    paused_at,
+                } => (pid, started_at, Some(paused_at)),
+                _ => continue,
+            };
+            if reaper::verify(pid, started_at) != PidStatus::Alive {
+                skipped.push((id, format!("PID {pid} is no longer running")));
+                continue;
+            }
+            let (stdout_fd, stderr_fd) = process
+                .output_pipes
+                .as_ref()
+                .map(OutputPipes::raw_fds)
+                .unwrap_or_default();
+            handed_off.push(HandedOffProcess {
+                process_id: id,
+                pid,
+                started_at,
+                paused_at,
+                stdout_fd,
+                stderr_fd,
+            });
+        }
+        (handed_off, skipped)
+    }
 
-                        // グレースピリオド内での終了を待つ
-                        let timeout = tokio::time::Duration::from_millis(grace_ms);
-                        match tokio::time::timeout(timeout, child.wait()).await {
-                            Ok(Ok(status)) => {
-                                // グレースフルに終了した
-                                info!(
-                                    "Process '{}' terminated gracefully with status: {:?}",
-                                    id, status
-                                );
-
-                                // 出力ハンドルをクリーンアップ
-                                if let Some((stdout_handle, stderr_handle)) =
-                                    process.output_handles.take()
-                                {
-                                    stdout_handle.abort();
-                                    stderr_handle.abort();
-                                }
-
-                                // 状態を更新
-                                process.info.state = ProcessState::Stopped {
-                                    exit_code: status.code(),
-                                    stopped_at: chrono::Utc::now(),
-                                };
-
-                                // Persist the updated state
-                                let db_info = Self::to_db_process_info(&process.info);
-                                if let Err(e) = self.persistence.update_process(&db_info).await {
-                                    tracing::warn!("Failed to persist process state: {}", e);
-                                }
-
-                                info!("Process '{}' stopped gracefully", id);
-                                return Ok(());
-                            }
-                            Ok(Err(e)) => {
-                                tracing::warn!("Error waiting for process {}: {}", id, e);
-                            }
-                            Err(_) => {
-                                // タイムアウト - SIGKILLで強制終了
-                                info!(
-                                    "Process '{}' did not terminate within grace period, sending SIGKILL",
-                                    id
-                                );
-                                // プロセスグループ全体にSIGKILLを送信
-                                let pgid = Pid::from_raw(-(pid.as_raw()));
-                                if let Err(e) = signal::kill(pgid, Signal::SIGKILL) {
-                                    tracing::debug!(
-                                        "Failed to send SIGKILL to process group {}: {}",
-                                        id,
-                                        e
-                                    );
-                                    // プロセスグループ送信が失敗した場合、個別のプロセスに送信
-                                    if let Err(e) = signal::kill(pid, Signal::SIGKILL) {
-                                        tracing::warn!(
-                                            "Failed to send SIGKILL to process {}: {}",
-                                            id,
-                                            e
-                                        );
-                                    }
-                                }
-                            }
+    /// 再起動前のサーバーから引き継いだプロセスを引き取り、出力の取り込みを再開する
+    ///
+    /// 定義はスナップショットから復元済みであること。引き取ったプロセスは外部で起動した
+    /// プロセスと同じくPIDで追跡する。引き取ったプロセスのIDを返す。
+    pub async fn resume_handed_off_processes(&self, handoff: RestartHandoff) -> Vec<String> {
+        let mut resumed = Vec::new();
+        for handed_off in handoff.processes {
+            let id = handed_off.process_id.clone();
+            let fds = [handed_off.stdout_fd, handed_off.stderr_fd];
+            let process_arc = self.processes.read().await.get(&id).cloned();
+            let alive = reaper::verify(handed_off.pid, handed_off.started_at) == PidStatus::Alive;
+            let Some(process_arc) = process_arc.filter(|_| alive) else {
+                warn!(
+                    "Could not take over process '{}' (PID {}) after restart",
+                    id, handed_off.pid
+                );
+                fds.into_iter().flatten().for_each(server_admin::close_fd);
+                continue;
+            };
+            let mut process = process_arc.write().await;
+            process.info.state = match handed_off.paused_at {
+                Some(paused_at) => ProcessState::Paused {
+                    pid: handed_off.pid,
+                    started_at: handed_off.started_at,
+                    paused_at,
+                },
+                None => ProcessState::Running {
+                    pid: handed_off.pid,
+                    started_at: handed_off.started_at,
+                },
+            };
+            process.adopted_at = Some(Utc::now());
+            process.stop_requested = false;
+            if let [Some(stdout_fd), Some(stderr_fd)] = fds {
+                let stdout =
+                    server_admin::read_handed_off_pipe(stdout_fd, process.stdout_buffer.clone());
+                let stderr =
+                    server_admin::read_handed_off_pipe(stderr_fd, process.stderr_buffer.clone());
+                match (stdout, stderr) {
+                    (Ok(stdout), Ok(stderr)) => process.output_handles = Some((stdout, stderr)),
+                    (stdout, stderr) => {
+                        for e in [stdout.err(), stderr.err()].into_iter().flatten() {
+                            warn!("Failed to resume reading output of '{}': {}", id, e);
                         }
                     }
                 }
             }
+            let db_info = Self::to_db_process_info(&process.info);
+            drop(process);
+            if let Err(e) = self.persistence.update_process(&db_info).await {
+                warn!("Failed to persist handed-off process state: {}", e);
+            }
+            info!(
+                "Took over process '{}' (PID {}) from the previous server",
+                id, handed_off.pid
+            );
+            resumed.push(id);
+        }
+        resumed
+    }
+
+    /// 自動起動しないプロセスも含めて、すべての定義をスナップショットに書き出す
+    /// （サーバーの再起動で定義を引き継ぐ）
+    pub async fn create_full_snapshot(&self) -> Result<String, String> {
+        self.persistence.export_snapshot(None, false).await
+    }
+
+    /// 不具合報告用のデバッグバンドル（tar.gz）を作成
+    ///
+    /// `since_minutes` を指定すると、ログとイベントはその期間のものだけを含める。
+    /// `output_path` を省略した場合は 状態ディレクトリの `debug-bundles` に作成する。
+    pub async fn create_debug_bundle(
+        &self,
+        since_minutes: Option<u64>,
+        output_path: Option<PathBuf>,
+    ) -> Result<DebugBundle, String> {
+        let created_at = Utc::now();
+        let since = since_minutes.map(|m| created_at - chrono::Duration::minutes(m as i64));
+        let to_json = |mut value: serde_json::Value| {
+            debug_bundle::redact_json(&mut value);
+            serde_json::to_vec_pretty(&value).map_err(|e| e.to_string())
+        };
+
+        let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+        let command_line: Vec<String> = std::env::args().collect();
+        files.push((
+            "version.json".to_string(),
+            to_json(serde_json::json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "os": std::env::consts::OS,
+                "arch": std::env::consts::ARCH,
+                "pid": std::process::id(),
+                "command_line": command_line.join(" "),
+                "created_at": created_at,
+                "since": since,
+            }))?,
+        ));
+        let settings = self.get_settings().await?;
+        files.push((
+            "settings.json".to_string(),
+            to_json(serde_json::to_value(&settings).map_err(|e| e.to_string())?)?,
+        ));
+
+        let ids: Vec<String> = self
+            .list_processes(None)
+            .await
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        let mut statuses = Vec::with_capacity(ids.len());
+        let mut logs = Vec::new();
+        for id in &ids {
+            let Ok(status) = self.get_process_status(id.clone()).await else {
+                continue;
+            };
+            statuses.push(status);
+            let processes = self.processes.read().await;
+            let Some(process_arc) = processes.get(id) else {
+                continue;
+            };
+            let process = process_arc.read().await;
+            let mut lines: Vec<(&str, LogLine)> = process
+                .stdout_buffer
+                .get_all_lines()
+                .await
+                .into_iter()
+                .map(|line| ("stdout", line))
+                .collect();
+            lines.extend(
+                process
+                    .stderr_buffer
+                    .get_all_lines()
+                    .await
+                    .into_iter()
+                    .map(|line| ("stderr", line)),
+            );
+            lines.retain(|(_, line)| since.is_none_or(|since| line.timestamp >= since));
+            lines.sort_by_key(|(_, line)| line.instant);
+            let text: String = lines
+                .iter()
+                .map(|(stream, line)| {
+                    format!(
+                        "[{}] [{}] {}\n",
+                        line.timestamp.to_rfc3339(),
+                        stream,
+                        debug_bundle::redact_text(&line.text)
+                    )
+                })
+                .collect();
+            logs.push((
+                format!("logs/{}.log", debug_bundle::file_name(id)),
+                text.into_bytes(),
+            ));
+        }
+        files.push((
+            "processes.json".to_string(),
+            to_json(serde_json::to_value(&statuses).map_err(|e| e.to_string())?)?,
+        ));
+        files.push((
+            "diagnostics.json".to_string(),
+            to_json(self.diagnostics().await?)?,
+        ));
+        let events = self.event_system.recent_events(since);
+        files.push((
+            "events.json".to_string(),
+            to_json(serde_json::to_value(&events).map_err(|e| e.to_string())?)?,
+        ));
+        let server_log: String = debug_bundle::server_log(since)
+            .iter()
+            .map(|line| debug_bundle::redact_text(line) + "\n")
+            .collect();
+        files.push(("server.log".to_string(), server_log.into_bytes()));
+        files.extend(logs);
+
+        let root = format!("vantage-debug-{}", created_at.format("%Y%m%d-%H%M%S"));
+        let path = match output_path {
+            Some(path) => paths::resolve_file_path(&path.to_string_lossy())?,
+            None => debug_bundle::default_dir().join(format!("{root}.tar.gz")),
+        };
+        let names: Vec<String> = files.iter().map(|(name, _)| name.clone()).collect();
+        let archive_path = path.clone();
+        let size_bytes = tokio::task::spawn_blocking(move || {
+            debug_bundle::write_archive(&archive_path, &root, &files)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to write debug bundle '{}': {e}", path.display()))?;
+
+        info!(
+            "Created debug bundle '{}' ({} bytes)",
+            path.display(),
+            size_bytes
+        );
+        Ok(DebugBundle {
+            path: path.display().to_string(),
+            size_bytes,
+            created_at,
+            since,
+            files: names,
+        })
+    }
+
+    async fn collect_workspace_usage(
+        &self,
+        quotas: &[WorkspaceQuota],
+    ) -> Result<Vec<WorkspaceUsage>, String> {
+        if quotas.is_empty() {
+            return Ok(Vec::new());
+        }
+        let members = {
+            let processes = self.processes.read().await;
+            let mut members = Vec::with_capacity(processes.len());
+            for (id, process_arc) in processes.iter() {
+                let process = process_arc.read().await;
+                members.push(QuotaMember {
+                    id: id.clone(),
+                    cwd: process.info.cwd.clone(),
+                    running: matches!(
+                        process.info.state,
+                        ProcessState::Running { .. } | ProcessState::Paused { .. }
+                    ),
+                });
+            }
+            members
+        };
+        let log_bytes: HashMap<String, u64> = self
+            .log_disk_usage()
+            .await?
+            .processes
+            .into_iter()
+            .map(|p| (p.process_id, p.bytes))
+            .collect();
+        Ok(workspace_quota::usage(quotas, &members, &log_bytes))
+    }
+
+    /// 作業ディレクトリが属するワークスペースのクォータを確認（`starting` なら起動時の上限）
+    async fn check_workspace_quota(
+        &self,
+        cwd: Option<&std::path::Path>,
+        starting: bool,
+    ) -> Result<(), String> {
+        let quotas = self.get_settings().await?.workspace_quotas;
+        let Some(quota) = workspace_quota::workspace_of(&quotas, cwd) else {
+            return Ok(());
+        };
+        let usage = self
+            .collect_workspace_usage(std::slice::from_ref(quota))
+            .await?;
+        let Some(usage) = usage.first() else {
+            return Ok(());
+        };
+        if starting {
+            usage.check_start()
+        } else {
+            usage.check_create()
+        }
+    }
+
+    /// ログクォータを超えた古いログファイルを削除
+    pub async fn prune_logs(&self) -> Result<PruneReport, String> {
+        let quota = self.get_settings().await?.log_quota;
+        let dir = log_quota::default_log_dir();
+        tokio::task::spawn_blocking(move || log_quota::enforce(&dir, &quota))
+            .await
+            .map_err(|e| format!("Log prune task failed: {e}"))?
+            .map_err(|e| format!("Failed to prune log directory: {e}"))
+    }
+
+    /// `rebuild_on_change` のコンテナのビルドコンテキストを監視するタスクを起動
+    fn spawn_image_watcher(&self) {
+        let processes = Arc::downgrade(&self.processes);
+        let persistence = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        let tunnels = self.tunnels.clone();
+        let kubernetes = self.kubernetes.clone();
+        let fence = self.fence.clone();
+        let log_forwarder = self.log_forwarder.clone();
+        let chains = self.chains.clone();
+        let schedules = self.schedules.clone();
+        let proxy_routes = self.proxy_routes.clone();
+        let lazy_listeners = self.lazy_listeners.clone();
+        let safe_mode = self.safe_mode.clone();
+        let last_startup = self.last_startup.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(IMAGE_WATCH_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(processes) = processes.upgrade() else {
+                    break;
+                };
+                let manager = ProcessManager {
+                    processes,
+                    persistence: persistence.clone(),
+                    event_system: event_system.clone(),
+                    chaos: Arc::new(ChaosState::default()),
+                    tunnels: tunnels.clone(),
+                    kubernetes: kubernetes.clone(),
+                    fence: fence.clone(),
+                    log_forwarder: log_forwarder.clone(),
+                    chains: chains.clone(),
+                    schedules: schedules.clone(),
+                    proxy_routes: proxy_routes.clone(),
+                    lazy_listeners: lazy_listeners.clone(),
+                    safe_mode: safe_mode.clone(),
+                    last_startup: last_startup.clone(),
+                };
+                manager.rebuild_changed_images().await;
+            }
+        });
+    }
+
+    /// ビルドコンテキストが前回のビルドから変わったコンテナを再ビルドして再起動する
+    ///
+    /// 実行中のプロセスに加え、前回のビルドが失敗して起動できなかったプロセスも対象にする。
+    async fn rebuild_changed_images(&self) {
+        let candidates: Vec<(String, Arc<RwLock<ManagedProcess>>)> = {
+            let processes = self.processes.read().await;
+            processes
+                .iter()
+                .map(|(id, p)| (id.clone(), p.clone()))
+                .collect()
+        };
+
+        for (id, process_arc) in candidates {
+            let (context, last_fingerprint, running) = {
+                let process = process_arc.read().await;
+                let Some(build) = process
+                    .info
+                    .container
+                    .as_ref()
+                    .and_then(|c| c.build.as_ref())
+                    .filter(|b| b.rebuild_on_change)
+                else {
+                    continue;
+                };
+                let Some(last) = &process.image_build else {
+                    continue;
+                };
+                let running = matches!(process.info.state, ProcessState::Running { .. });
+                if !running && last.success {
+                    continue;
+                }
+                if self.active_maintenance(&process.info).await.is_some() {
+                    continue;
+                }
+                (
+                    container::context_dir(build, process.info.cwd.as_deref()),
+                    last.context_fingerprint,
+                    running,
+                )
+            };
+
+            let fingerprint = tokio::task::spawn_blocking(move || container::fingerprint(&context))
+                .await
+                .unwrap_or(last_fingerprint);
+            if fingerprint == last_fingerprint {
+                continue;
+            }
+
+            info!("Build context of '{}' changed; rebuilding the image", id);
+            if running && let Err(e) = self.stop_process(id.clone(), None).await {
+                warn!("Failed to stop process '{}' for a rebuild: {}", id, e);
+                continue;
+            }
+            if let Err(e) = self.start_process(id.clone()).await {
+                warn!("Failed to restart process '{}' after a rebuild: {}", id, e);
+            }
+        }
+    }
+
+    /// アイドル検知が有効な実行中プロセスを確認し、必要に応じてアクションを実行
+    async fn check_idle_processes(&self, system: &mut System) {
+        let candidates: Vec<(String, Arc<RwLock<ManagedProcess>>)> = {
+            let processes = self.processes.read().await;
+            processes
+                .iter()
+                .map(|(id, p)| (id.clone(), p.clone()))
+                .collect()
+        };
+
+        let mut targets = Vec::new();
+        for (id, process_arc) in candidates {
+            let process = process_arc.read().await;
+            if let (Some(config), ProcessState::Running { pid, .. }) =
+                (&process.info.idle_detection, &process.info.state)
+            {
+                targets.push((id, process_arc.clone(), config.clone(), *pid));
+            }
+        }
+        if targets.is_empty() {
+            return;
+        }
+
+        // CPU使用率は前回の更新との差分で算出されるため、毎回対象すべてを更新する
+        let pids: Vec<Pid> = targets
+            .iter()
+            .map(|(_, _, _, pid)| Pid::from_u32(*pid))
+            .collect();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&pids),
+            true,
+            ProcessRefreshKind::nothing().with_cpu(),
+        );
+
+        let now = Utc::now();
+        for (id, process_arc, config, pid) in targets {
+            let mut process = process_arc.write().await;
+            let Some(last_activity) = process.last_activity().await else {
+                continue;
+            };
+
+            let idle_for = (now - last_activity).num_seconds().max(0) as u64;
+            if idle_for < config.timeout_secs {
+                if process.idle_since.take().is_some() {
+                    debug!("Process '{}' is no longer idle", id);
+                }
+                continue;
+            }
+            if process.idle_since.is_some() {
+                continue;
+            }
+            if self.active_maintenance(&process.info).await.is_some() {
+                continue;
+            }
+
+            let cpu_usage = system
+                .process(Pid::from_u32(pid))
+                .map(|p| p.cpu_usage())
+                .unwrap_or(0.0);
+            if cpu_usage >= config.cpu_threshold_percent {
+                continue;
+            }
 
-            // Windows または SIGTERM/SIGKILL失敗時の最終手段としてkill()を使用
-            child
-                .kill()
+            info!(
+                "Process '{}' has been idle for {}s (cpu: {:.1}%)",
+                id, idle_for, cpu_usage
+            );
+            process.idle_since = Some(now);
+            drop(process);
+
+            if let Err(e) = self
+                .event_system
+                .emit_process_idle(id.clone(), idle_for, config.action)
                 .await
-                .map_err(|e| format!("Failed to kill process: {e}"))?;
+            {
+                warn!("Failed to emit idle event for '{}': {}", id, e);
+            }
 
-            // プロセスの終了を待つ（タイムアウト付き）
-            let wait_timeout = tokio::time::Duration::from_secs(10);
-            match tokio::time::timeout(wait_timeout, child.wait()).await {
-                Ok(Ok(status)) => {
-                    info!("Process '{}' terminated with status: {:?}", id, status);
+            if config.action == IdleAction::Restart {
+                info!("Restarting idle process '{}'", id);
+                if let Err(e) = self.stop_process(id.clone(), None).await {
+                    warn!("Failed to stop idle process '{}': {}", id, e);
+                    continue;
                 }
-                Ok(Err(e)) => {
-                    return Err(format!(
-                        "Error waiting for process '{}' to terminate: {}",
-                        id, e
-                    ));
+                if let Err(e) = self.restart_automatically(id.clone()).await {
+                    warn!("Failed to restart idle process '{}': {}", id, e);
                 }
-                Err(_) => {
-                    return Err(format!(
-                        "Timeout waiting for process '{}' to terminate after kill signal",
-                        id
-                    ));
+            }
+        }
+    }
+
+    /// ヘルスチェックを設定した実行中プロセスの出力とプローブの結果を確認し、必要に応じてアクションを実行
+    ///
+    /// 間隔の来たプローブは別のタスクで実行し、結果は次の確認から判定に使う。
+    async fn check_process_health(&self) {
+        let candidates: Vec<(String, Arc<RwLock<ManagedProcess>>)> = {
+            let processes = self.processes.read().await;
+            processes
+                .iter()
+                .map(|(id, p)| (id.clone(), p.clone()))
+                .collect()
+        };
+
+        let now = Utc::now();
+        for (id, process_arc) in candidates {
+            let mut process = process_arc.write().await;
+            let Some(config) = process.info.health_check.clone() else {
+                continue;
+            };
+            let ProcessState::Running { pid, .. } = process.info.state else {
+                if process.info.health.take().is_some() {
+                    process.probe.reset();
+                }
+                continue;
+            };
+            if let Some(probe) = config.probe.clone()
+                && process
+                    .probe
+                    .try_begin(pid, Duration::from_secs(config.interval_secs))
+            {
+                let cwd = process.info.cwd.clone();
+                let timeout = Duration::from_secs(config.timeout_secs);
+                let process_arc = process_arc.clone();
+                tokio::spawn(async move {
+                    let outcome = health_probe::run(&probe, cwd.as_deref(), timeout).await;
+                    process_arc.write().await.probe.finish(pid, outcome);
+                });
+            }
+
+            let health = process.health(&config, now);
+            process.info.health = Some(HealthState {
+                healthy: health.healthy,
+                checked_at: now,
+                failures: health.failures.clone(),
+                unhealthy_since: (!health.healthy).then(|| process.unhealthy_since.unwrap_or(now)),
+                probe: health.probe.clone(),
+            });
+            if health.healthy {
+                if let Some(since) = process.unhealthy_since.take() {
+                    info!("Process '{}' is healthy again", id);
+                    let unhealthy_seconds = (now - since).num_seconds().max(0) as u64;
+                    if let Err(e) = self
+                        .event_system
+                        .emit_process_recovered(id.clone(), unhealthy_seconds)
+                        .await
+                    {
+                        warn!("Failed to emit recovered event for '{}': {}", id, e);
+                    }
+                }
+                continue;
+            }
+            if process.unhealthy_since.is_some() {
+                continue;
+            }
+            if self.active_maintenance(&process.info).await.is_some() {
+                continue;
+            }
+
+            warn!(
+                "Process '{}' is unhealthy: {}",
+                id,
+                health.failures.join("; ")
+            );
+            process.unhealthy_since = Some(now);
+            drop(process);
+            notifications::record(
+                &self.persistence,
+                notifications::process_unhealthy(&id, &health.failures),
+            )
+            .await;
+
+            if let Err(e) = self
+                .event_system
+                .emit_process_unhealthy(id.clone(), health.failures, config.action)
+                .await
+            {
+                warn!("Failed to emit unhealthy event for '{}': {}", id, e);
+            }
+
+            if config.action == HealthAction::Restart {
+                info!("Restarting unhealthy process '{}'", id);
+                if let Err(e) = self.stop_process(id.clone(), None).await {
+                    warn!("Failed to stop unhealthy process '{}': {}", id, e);
+                    continue;
+                }
+                if let Err(e) = self.restart_automatically(id.clone()).await {
+                    warn!("Failed to restart unhealthy process '{}': {}", id, e);
                 }
             }
+        }
+    }
+
+    /// 実行中のプロセスの累積CPU時間を記録する
+    async fn sample_resources(&self, system: &mut System) {
+        let candidates: Vec<Arc<RwLock<ManagedProcess>>> =
+            self.processes.read().await.values().cloned().collect();
+        let mut targets = Vec::new();
+        for process_arc in candidates {
+            let pid = match &process_arc.read().await.info.state {
+                ProcessState::Running { pid, .. } | ProcessState::Paused { pid, .. } => *pid,
+                _ => continue,
+            };
+            targets.push((process_arc, pid));
+        }
+        if targets.is_empty() {
+            return;
+        }
+
+        let pids: Vec<Pid> = targets.iter().map(|(_, pid)| Pid::from_u32(*pid)).collect();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&pids),
+            true,
+            ProcessRefreshKind::nothing().with_cpu().with_memory(),
+        );
+
+        let now = Utc::now();
+        for (process_arc, pid) in targets {
+            if let Some(sys_process) = system.process(Pid::from_u32(pid)) {
+                let mut process = process_arc.write().await;
+                process
+                    .cpu_accounting
+                    .record(pid, sys_process.accumulated_cpu_time(), now);
+                process.resource_samples.record(
+                    pid,
+                    sys_process.cpu_usage(),
+                    sys_process.memory(),
+                    now,
+                );
+            }
+        }
+    }
+
+    async fn load_persisted_processes(&self) -> Result<(), String> {
+        let loaded_processes = self.persistence.load_all_processes().await?;
+        let mut processes = self.processes.write().await;
+        let mut auto_start_processes = Vec::new();
+
+        for (id, db_info) in loaded_processes {
+            // Check if this process should be auto-started on restore
+            if db_info.auto_start_on_restore {
+                auto_start_processes.push(id.clone());
+            }
+
+            let info = Self::from_db_process_info(db_info);
+            let managed = ManagedProcess::from_info(info);
+            processes.insert(id, Arc::new(RwLock::new(managed)));
+        }
+
+        let loaded_count = processes.len();
+        tracing::info!("Loaded {} persisted processes", loaded_count);
+
+        // Release the write lock before starting processes
+        drop(processes);
+
+        // Start auto-start processes
+        if !auto_start_processes.is_empty() {
+            tracing::info!(
+                "Starting {} processes with auto_start_on_restore enabled",
+                auto_start_processes.len()
+            );
+            let report = self.start_in_order(auto_start_processes).await;
+            for (process_id, e) in report.failed() {
+                tracing::warn!(
+                    "Failed to auto-start process '{}' on restore: {}",
+                    process_id,
+                    e
+                );
+            }
+            *self.last_startup.write().await = Some(report);
+        }
+
+        Ok(())
+    }
+
+    /// プロセスを作成・登録
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_process(
+        &self,
+        id: String,
+        command: String,
+        args: Vec<String>,
+        env: HashMap<String, String>,
+        cwd: Option<PathBuf>,
+        auto_start_on_restore: bool,
+    ) -> Result<(), String> {
+        self.create_process_with_options(
+            id,
+            command,
+            args,
+            env,
+            cwd,
+            ProcessOptions {
+                auto_start_on_restore,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// オプションを指定してプロセスを作成・登録
+    pub async fn create_process_with_options(
+        &self,
+        id: String,
+        command: String,
+        args: Vec<String>,
+        mut env: HashMap<String, String>,
+        cwd: Option<PathBuf>,
+        options: ProcessOptions,
+    ) -> Result<(), String> {
+        let cwd = cwd.map(|cwd| paths::expand_path(&cwd)).transpose()?;
+        // セキュリティ検証（コンテナではcommandを省略してイメージのCMDを使える）
+        if options.container.is_some() && command.is_empty() {
+            if !args.is_empty() {
+                return Err("args require a command".to_string());
+            }
+            crate::security::validate_env_vars(&env)?;
+            crate::security::validate_working_directory(&cwd)?;
+        } else {
+            crate::security::validate_process_inputs(&command, &args, &env, &cwd)?;
+        }
+
+        if let Some(idle) = &options.idle_detection
+            && idle.timeout_secs == 0
+        {
+            return Err("idle_detection.timeout_secs must be greater than 0".to_string());
+        }
+        if let Some(config) = &options.health_check {
+            output_rates::validate(config)?;
+        }
+        if let Some(tmux) = &options.tmux
+            && tmux.session.trim().is_empty()
+        {
+            return Err("tmux.session must not be empty".to_string());
+        }
+        if let Some(repl) = &options.repl {
+            if options.tmux.is_some() {
+                return Err("repl cannot be combined with tmux".to_string());
+            }
+            repl::validate(repl)?;
+        }
+        if options.keep_stdin_open {
+            if options.tmux.is_some() {
+                return Err("keep_stdin_open cannot be combined with tmux".to_string());
+            }
+            if options.repl.is_some() {
+                return Err(
+                    "keep_stdin_open cannot be combined with repl; use execute_in_repl to send code"
+                        .to_string(),
+                );
+            }
+        }
+        secret_refs::validate(&env)?;
+        log_sources::validate(&options.log_sources)?;
+        log_sinks::validate(&options.log_sinks)?;
+        if let Some(redaction) = &options.redaction {
+            redaction::validate_override(redaction)?;
+        }
+        metadata::validate(&options.metadata)?;
+        if let Some(runbook) = &options.runbook {
+            runbook::validate(runbook)?;
+        }
+        if let Some(order) = &options.startup {
+            startup::validate(order)?;
+        }
+        if let Some(config) = &options.run_as {
+            if options.tmux.is_some() {
+                return Err("run_as cannot be combined with tmux".to_string());
+            }
+            run_as::validate(config)?;
+        }
+        if let Some(config) = &options.container {
+            for (field, set) in [
+                ("tmux", options.tmux.is_some()),
+                ("repl", options.repl.is_some()),
+                ("run_as", options.run_as.is_some()),
+                ("resource_limits", options.resource_limits.is_some()),
+            ] {
+                if set {
+                    return Err(format!("container cannot be combined with {field}"));
+                }
+            }
+            container::validate(config)?;
+        }
+        if options.container.is_some() && options.env_loader.is_some() {
+            return Err("container cannot be combined with env_loader".to_string());
+        }
+        if options
+            .output_recording
+            .as_ref()
+            .is_some_and(|c| c.keep == Some(0))
+        {
+            return Err("output_recording.keep must be at least 1".to_string());
+        }
+        checks::validate(&options.assertions)?;
+        if let Some(policy) = &options.restart_policy {
+            restart_policy::validate(policy)?;
+        }
+        if let Some(group) = &options.group {
+            groups::validate(group)?;
+        }
+        dependencies::validate(&id, &options.depends_on)?;
+        parameters::validate(&options.parameters)?;
+        if let Some(limits) = &options.resource_limits {
+            if options.tmux.is_some() {
+                return Err("resource_limits cannot be combined with tmux".to_string());
+            }
+            cgroup::validate(limits)?;
+        }
+        self.check_workspace_quota(cwd.as_deref(), false).await?;
+        let mut sealed_env = HashMap::new();
+        if !options.sensitive_env.is_empty() {
+            sensitive_env::seal(
+                sensitive_env::cipher()?,
+                &mut env,
+                &options.sensitive_env,
+                &mut sealed_env,
+            )?;
+        }
+
+        info!(
+            "Creating process '{}': {} {:?} (auto_start_on_restore: {})",
+            id, command, args, options.auto_start_on_restore
+        );
+        let mut processes = self.processes.write().await;
+
+        if processes.contains_key(&id) {
+            return Err(format!("Process with id '{id}' already exists"));
+        }
+        if !options.depends_on.is_empty() {
+            let mut graph = DependencyGraph::new();
+            for (other, process) in processes.iter() {
+                graph.insert(other.clone(), process.read().await.info.depends_on.clone());
+            }
+            graph.insert(id.clone(), options.depends_on.clone());
+            if let Some(cycle) = dependencies::find_cycle(&graph) {
+                return Err(format!("Dependency cycle: {cycle}"));
+            }
+        }
+
+        let mut process = ManagedProcess::new(id.clone(), command, args, env, cwd);
+        process.info.auto_start_on_restore = options.auto_start_on_restore;
+        process.info.tags = options.tags;
+        process.info.idle_detection = options.idle_detection;
+        process.info.health_check = options.health_check;
+        process.info.tmux = options.tmux;
+        process.info.repl = options.repl;
+        process.info.log_sources = options.log_sources;
+        process.info.log_sinks = options.log_sinks;
+        process.info.redaction = options.redaction;
+        process.info.metadata = options.metadata;
+        process.info.runbook = options.runbook;
+        process.info.startup = options.startup;
+        process.info.env_loader = options.env_loader;
+        process.info.output_recording = options.output_recording;
+        process.info.assertions = options.assertions;
+        process.info.restart_policy = options.restart_policy;
+        process.info.group = options.group;
+        process.info.depends_on = options.depends_on;
+        process.info.parameters = options.parameters;
+        process.info.keep_stdin_open = options.keep_stdin_open;
+        process.info.run_as = options.run_as;
+        process.info.resource_limits = options.resource_limits;
+        process.info.container = options.container;
+        process.info.source = options.source;
+        process.info.sensitive_env = sealed_env;
+
+        let process_info = process.info.clone();
+        let process_arc = Arc::new(RwLock::new(process));
+        processes.insert(id.clone(), process_arc.clone());
+
+        // Release the write lock before persistence and auto-start
+        drop(processes);
+
+        // Persist the process
+        let db_process_info = Self::to_db_process_info(&process_info);
+        match self.persistence.save_process(&db_process_info).await {
+            Ok(_) => tracing::debug!("Process {} persisted successfully", id),
+            Err(e) => tracing::warn!("Failed to persist process {}: {}", id, e),
+        }
+        self.record_change(ChangeTarget::Process, &id, None, Some(&db_process_info))
+            .await;
+
+        Ok(())
+    }
+
+    /// プロセスを起動
+    ///
+    /// 明示的な起動として扱い、検知済みのクラッシュループ状態を解除する。`depends_on` を
+    /// 指定している場合は、先に依存先を起動して準備完了を待つ。
+    pub async fn start_process(&self, id: String) -> Result<u32, String> {
+        self.start_process_with_params(id, HashMap::new()).await
+    }
+
+    /// パラメータの値を指定してプロセスを起動
+    ///
+    /// 値はこの回の起動の引数だけに置換し、実行履歴に記録する。
+    pub async fn start_process_with_params(
+        &self,
+        id: String,
+        params: HashMap<String, String>,
+    ) -> Result<u32, String> {
+        self.start_dependencies(&id).await?;
+        {
+            let processes = self.processes.read().await;
+            if let Some(process_arc) = processes.get(&id) {
+                let mut process = process_arc.write().await;
+                if process.crash_tracker.crash_loop().is_some() {
+                    warn!(
+                        "Starting process '{}' manually while a crash loop is detected",
+                        id
+                    );
+                    process.crash_tracker.acknowledge();
+                }
+                // 手動の起動で、予定している自動再起動を取り消して回数を数え直す
+                process.restart.reset();
+            }
+        }
+        self.spawn_process_with(id, &params).await
+    }
+
+    /// プロセスID → 依存先
+    async fn dependency_graph(&self) -> DependencyGraph {
+        let mut graph = DependencyGraph::new();
+        for (id, process) in self.processes.read().await.iter() {
+            graph.insert(id.clone(), process.read().await.info.depends_on.clone());
+        }
+        graph
+    }
+
+    /// `id` の依存先を依存先の依存先から順に起動し、それぞれの条件を満たすまで待つ
+    async fn start_dependencies(&self, id: &str) -> Result<(), String> {
+        let graph = self.dependency_graph().await;
+        if graph.get(id).is_none_or(Vec::is_empty) {
+            return Ok(());
+        }
+        for process_id in dependencies::start_order(id, &graph)? {
+            for dependency in &graph[&process_id] {
+                self.wait_for_dependency(&process_id, dependency).await?;
+            }
+            if process_id == id {
+                break;
+            }
+            let running = match self.processes.read().await.get(&process_id) {
+                Some(process) => matches!(
+                    process.read().await.info.state,
+                    ProcessState::Running { .. } | ProcessState::Paused { .. }
+                ),
+                None => false,
+            };
+            if !running {
+                info!("Starting dependency '{}' of '{}'", process_id, id);
+                self.spawn_process(process_id.clone())
+                    .await
+                    .map_err(|e| format!("Failed to start dependency '{process_id}': {e}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 依存先が条件を満たすまで待つ
+    async fn wait_for_dependency(
+        &self,
+        dependent: &str,
+        dependency: &ProcessDependency,
+    ) -> Result<(), String> {
+        let id = &dependency.process_id;
+        let timeout = dependency
+            .timeout_secs
+            .unwrap_or(dependencies::DEFAULT_TIMEOUT_SECS);
+        let pattern = match &dependency.ready {
+            DependencyReady::LogPattern { pattern } => Some(dependencies::compile(pattern)?),
+            _ => None,
+        };
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout);
+        let mut cursor = None;
+        loop {
+            let info = self.get_process_status(id.clone()).await?.info;
+            let ProcessState::Running { started_at, .. } = info.state else {
+                return Err(format!("Dependency '{id}' of '{dependent}' is not running"));
+            };
+            let ready = match &pattern {
+                Some(pattern) => {
+                    // 今回の起動の出力だけを、前回の続きから読む
+                    let followed = self
+                        .follow_process_lines(
+                            id,
+                            OutputStream::Both,
+                            cursor,
+                            Some(started_at),
+                            Some(1000),
+                        )
+                        .await?;
+                    cursor = Some(followed.cursor);
+                    followed
+                        .lines
+                        .iter()
+                        .any(|line| pattern.is_match(&line.text))
+                }
+                None if dependency.ready == DependencyReady::Healthy => {
+                    if info.health_check.is_none() {
+                        return Err(format!(
+                            "Dependency '{id}' of '{dependent}' has no health_check to wait for"
+                        ));
+                    }
+                    info.health.is_some_and(|health| health.healthy)
+                }
+                None => true,
+            };
+            if ready {
+                info!(
+                    "Dependency '{}' of '{}' is {}",
+                    id,
+                    dependent,
+                    dependencies::describe(&dependency.ready)
+                );
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!(
+                    "Dependency '{id}' of '{dependent}' was not {} within {timeout}s",
+                    dependencies::describe(&dependency.ready)
+                ));
+            }
+            tokio::time::sleep(startup::READY_POLL_INTERVAL).await;
+        }
+    }
+
+    /// プロセスを停止し、完全に終了するのを待ってから起動し直す（新しいPIDを返す）
+    ///
+    /// 停止の手順は `stop_process` と同じ（`grace_period_ms` の間SIGTERMで終了を待ち、
+    /// 過ぎればSIGKILL）。再起動の間は自動再起動をしないため、監視から先に起動されることはない。
+    /// 実行中でなければそのまま起動する。
+    pub async fn restart_process(
+        &self,
+        id: String,
+        grace_period_ms: Option<u64>,
+    ) -> Result<u32, String> {
+        let (process_arc, running) = {
+            let processes = self.processes.read().await;
+            let process_arc = processes
+                .get(&id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?
+                .clone();
+            let mut process = process_arc.write().await;
+            if process.restarting {
+                return Err(format!("Process '{id}' is already being restarted"));
+            }
+            process.restarting = true;
+            let running = matches!(
+                process.info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            );
+            drop(process);
+            (process_arc, running)
+        };
+
+        let result = async {
+            if running {
+                self.stop_process(id.clone(), grace_period_ms).await?;
+            }
+            self.start_process(id.clone()).await
+        }
+        .await;
+        process_arc.write().await.restarting = false;
+        if let Ok(pid) = &result {
+            info!("Restarted process '{}' with PID {}", id, pid);
+        }
+        result
+    }
+
+    /// プロセスの振る舞いの確認項目を設定する（空で削除）
+    pub async fn set_process_assertions(
+        &self,
+        id: String,
+        assertions: Vec<ProcessAssertion>,
+    ) -> Result<(), String> {
+        checks::validate(&assertions)?;
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let mut process = process_arc.write().await;
+        let before = Self::to_db_process_info(&process.info);
+
+        info!(
+            "Updated process '{}' assertions ({} item(s))",
+            id,
+            assertions.len()
+        );
+        process.info.assertions = assertions;
+
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            return Err(format!("Failed to persist process update: {e}"));
+        }
+        self.record_change(ChangeTarget::Process, &id, Some(&before), Some(&db_info))
+            .await;
+        Ok(())
+    }
+
+    /// 確認項目を設定したプロセスを起動し直して評価する
+    ///
+    /// `process_ids` が空なら確認項目のあるすべてのプロセスを対象にし、並列に評価する。
+    /// `keep_running` でなければ、評価を終えた時点で実行中のプロセスは停止する。
+    pub async fn run_checks(
+        &self,
+        process_ids: Vec<String>,
+        timeout_secs: Option<u64>,
+        keep_running: bool,
+    ) -> Result<CheckReport, String> {
+        let targets: Vec<(String, Vec<ProcessAssertion>)> = {
+            let processes = self.processes.read().await;
+            let mut targets = Vec::new();
+            if process_ids.is_empty() {
+                for (id, process_arc) in processes.iter() {
+                    let process = process_arc.read().await;
+                    if !process.info.assertions.is_empty() {
+                        targets.push((id.clone(), process.info.assertions.clone()));
+                    }
+                }
+                targets.sort_by(|a, b| a.0.cmp(&b.0));
+            } else {
+                for id in process_ids {
+                    let process = processes
+                        .get(&id)
+                        .ok_or_else(|| format!("Process '{id}' not found"))?
+                        .read()
+                        .await;
+                    if process.info.assertions.is_empty() {
+                        return Err(format!("Process '{id}' has no assertions"));
+                    }
+                    targets.push((id.clone(), process.info.assertions.clone()));
+                }
+            }
+            targets
+        };
+        if targets.is_empty() {
+            return Err(
+                "No process has assertions; add them with set_process_assertions".to_string(),
+            );
+        }
+
+        let timeout =
+            Duration::from_secs(timeout_secs.unwrap_or(checks::DEFAULT_TIMEOUT_SECS).max(1));
+        let started = std::time::Instant::now();
+        let reports = futures::future::join_all(
+            targets
+                .into_iter()
+                .map(|(id, assertions)| self.check_process(id, assertions, timeout, keep_running)),
+        )
+        .await;
+        let report = CheckReport::new(reports, started.elapsed());
+        info!(
+            "Checks finished: {} passed, {} failed",
+            report.passed_count, report.failed_count
+        );
+        Ok(report)
+    }
+
+    /// 1つのプロセスを起動し直し、確認項目の合否が決まるまで観測する
+    async fn check_process(
+        &self,
+        id: String,
+        assertions: Vec<ProcessAssertion>,
+        timeout: Duration,
+        keep_running: bool,
+    ) -> ProcessCheckReport {
+        let started = std::time::Instant::now();
+        let failed = |error: String, assertions: Vec<checks::AssertionResult>| ProcessCheckReport {
+            process_id: id.clone(),
+            passed: false,
+            pid: None,
+            error: Some(error),
+            duration_ms: started.elapsed().as_millis() as u64,
+            assertions,
+        };
+        let mut evaluator = match Evaluator::new(&assertions, timeout) {
+            Ok(evaluator) => evaluator,
+            Err(e) => return failed(e, Vec::new()),
+        };
+        // 前回の実行の出力を含めないよう、最初から観測する
+        let pid = match self.restart_process(id.clone(), None).await {
+            Ok(pid) => pid,
+            Err(e) => return failed(format!("Failed to start: {e}"), Vec::new()),
+        };
+        let process_arc = match self.processes.read().await.get(&id) {
+            Some(process_arc) => process_arc.clone(),
+            None => return failed(format!("Process '{id}' was removed"), Vec::new()),
+        };
+        let (stdout_buffer, stderr_buffer, started_at) = {
+            let process = process_arc.read().await;
+            let started_at = match process.info.state {
+                ProcessState::Running { started_at, .. } => started_at,
+                _ => Utc::now(),
+            };
+            (
+                process.stdout_buffer.clone(),
+                process.stderr_buffer.clone(),
+                started_at,
+            )
+        };
+
+        let deadline = evaluator.deadline();
+        let elapsed = loop {
+            // 終了した場合は、終了間際の出力を取り込み終えてから状態が変わる
+            let exited = match &process_arc.read().await.info.state {
+                ProcessState::Stopped { exit_code, .. } => Some(*exit_code),
+                ProcessState::Failed { .. } => Some(None),
+                _ => None,
+            };
+            let elapsed = (Utc::now() - started_at).to_std().unwrap_or_default();
+            let mut lines = Vec::new();
+            for (buffer, stderr) in [(&stdout_buffer, false), (&stderr_buffer, true)] {
+                lines.extend(
+                    buffer
+                        .get_all_lines()
+                        .await
+                        .into_iter()
+                        .filter(|line| line.timestamp >= started_at)
+                        .map(|line| ObservedLine {
+                            offset: (line.timestamp - started_at).to_std().unwrap_or_default(),
+                            stderr,
+                            text: line.text,
+                        }),
+                );
+            }
+            lines.sort_by_key(|line| line.offset);
+            let mut open_ports = Vec::new();
+            for port in evaluator.ports_to_probe() {
+                if blue_green::accepts_connections(port).await {
+                    open_ports.push(port);
+                }
+            }
+            evaluator.observe(&Observation {
+                elapsed,
+                lines,
+                exited,
+                open_ports,
+            });
+            if evaluator.is_done() || exited.is_some() || elapsed >= deadline {
+                break elapsed;
+            }
+            tokio::time::sleep(checks::POLL_INTERVAL).await;
+        };
+
+        let running = matches!(
+            process_arc.read().await.info.state,
+            ProcessState::Running { .. } | ProcessState::Paused { .. }
+        );
+        let mut error = None;
+        if running
+            && !keep_running
+            && let Err(e) = self.stop_process(id.clone(), None).await
+        {
+            error = Some(format!("Failed to stop after the checks: {e}"));
+        }
+        let results = evaluator.finish(elapsed);
+        ProcessCheckReport {
+            passed: error.is_none() && results.iter().all(|result| result.passed),
+            process_id: id,
+            pid: Some(pid),
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+            assertions: results,
+        }
+    }
+
+    /// 自動再起動（クラッシュループ中は抑止し、直近の失敗に応じてバックオフする）
+    pub async fn restart_automatically(&self, id: String) -> Result<u32, String> {
+        let (backoff, info) = {
+            let processes = self.processes.read().await;
+            let process_arc = processes
+                .get(&id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?;
+            let process = process_arc.read().await;
+            if process.restarting {
+                return Err(format!(
+                    "Automatic restart of '{id}' is skipped because restart_process is restarting it"
+                ));
+            }
+            if process.crash_tracker.crash_loop().is_some() {
+                return Err(format!(
+                    "Automatic restart of '{id}' is suppressed because a crash loop was detected"
+                ));
+            }
+            (
+                process.crash_tracker.backoff(Utc::now()),
+                process.info.clone(),
+            )
+        };
+        if let Some(active) = self.active_maintenance(&info).await {
+            return Err(format!(
+                "Automatic restart of '{id}' is suppressed during maintenance window '{}'",
+                active.name
+            ));
+        }
+
+        if !backoff.is_zero() {
+            info!(
+                "Delaying automatic restart of '{}' by {}ms",
+                id,
+                backoff.as_millis()
+            );
+            tokio::time::sleep(backoff).await;
+        }
+        self.spawn_process(id).await
+    }
+
+    /// 再起動ポリシーで予定した再起動を行う
+    ///
+    /// 待っている間に手動で起動・停止して予定が取り消された場合や、後の終了で予定が
+    /// 置き換わった場合は何もしない。
+    async fn run_scheduled_restart(&self, id: String) {
+        let Some(process_arc) = self.processes.read().await.get(&id).cloned() else {
+            return;
+        };
+        let Some(attempt) = process_arc.write().await.restart.take_due(Utc::now()) else {
+            return;
+        };
+        match self.restart_automatically(id.clone()).await {
+            Ok(pid) => {
+                info!(
+                    "Restarted process '{}' by its restart policy (attempt {}, PID {})",
+                    id, attempt, pid
+                );
+                if let Err(e) = self
+                    .event_system
+                    .emit_process_restarted(id, attempt, pid)
+                    .await
+                {
+                    warn!("Failed to emit restart event: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to restart process '{}': {}", id, e),
+        }
+    }
+
+    /// 終了したプロセスのcgroupを削除する
+    ///
+    /// 停止要求による終了では残っている子孫も強制終了する。それ以外で子孫が残っている場合は
+    /// そのままにし、次回の起動時に同じcgroupを使う。
+    async fn release_cgroup(id: String, cgroup: Cgroup, kill_remaining: bool) {
+        if kill_remaining
+            && !cgroup.pids().is_empty()
+            && let Err(e) = cgroup.kill()
+        {
+            warn!("Failed to kill remaining processes of '{}': {}", id, e);
+        }
+        for _ in 0..10 {
+            if cgroup.remove().is_ok() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        debug!(
+            "Keeping cgroup {} of process '{}' because it is not empty",
+            cgroup.path().display(),
+            id
+        );
+    }
+
+    /// 検知済みのクラッシュループを一覧
+    pub async fn crash_loops(&self) -> Vec<(String, CrashLoopInfo)> {
+        let processes = self.processes.read().await;
+        let mut result = Vec::new();
+        for (id, process_arc) in processes.iter() {
+            let process = process_arc.read().await;
+            if let Some(info) = process.crash_tracker.crash_loop() {
+                result.push((id.clone(), info.clone()));
+            }
+        }
+        result
+    }
+
+    /// アイドル状態のプロセスと、アイドルになった時刻を一覧
+    pub async fn idle_processes(&self) -> Vec<(String, DateTime<Utc>)> {
+        let processes = self.processes.read().await;
+        let mut result = Vec::new();
+        for (id, process_arc) in processes.iter() {
+            let process = process_arc.read().await;
+            if let Some(since) = process.idle_since {
+                result.push((id.clone(), since));
+            }
+        }
+        result
+    }
+
+    async fn spawn_process(&self, id: String) -> Result<u32, String> {
+        self.spawn_process_with(id, &HashMap::new()).await
+    }
+
+    /// `params` をこの回の引数に置換して起動する
+    async fn spawn_process_with(
+        &self,
+        id: String,
+        params: &HashMap<String, String>,
+    ) -> Result<u32, String> {
+        info!("Starting process '{}'...", id);
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?
+            .clone();
+        drop(processes);
+
+        let (cwd, running, loader, args, parameter_values) = {
+            let process = process_arc.read().await;
+            let running = matches!(
+                process.info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            );
+            let (args, parameter_values) =
+                parameters::resolve(&process.info.parameters, &process.info.args, params)?;
+            (
+                process.info.cwd.clone(),
+                running,
+                process.info.env_loader,
+                args,
+                parameter_values,
+            )
+        };
+        let settings = self.get_settings().await?;
+        let mut project_env = None;
+        if !running {
+            self.check_workspace_quota(cwd.as_deref(), true).await?;
+            // イメージのビルドの出力も伏せるため、ビルドより前に設定する
+            Self::apply_redaction(&*process_arc.read().await, &settings.redaction_rules);
+            self.build_container_image(&id, &process_arc).await?;
+            // ツールの実行に時間がかかることがあるため、ロックを保持せずに読み込む
+            if let Some(loader) = loader {
+                project_env = env_loader::load(loader, cwd.as_deref())
+                    .await
+                    .map_err(|e| format!("Failed to load the project environment: {e}"))?;
+            }
+        }
+        let global_log_sinks = settings.log_sinks;
+
+        let mut process = process_arc.write().await;
+
+        // すでに実行中の場合はエラー
+        match process.info.state {
+            ProcessState::Running { .. } => {
+                return Err(format!("Process '{id}' is already running"));
+            }
+            ProcessState::Paused { .. } => {
+                return Err(format!(
+                    "Process '{id}' is paused; use resume_process instead"
+                ));
+            }
+            _ => {}
+        }
+
+        let stdout_buffer = process.stdout_buffer.clone();
+        let stderr_buffer = process.stderr_buffer.clone();
+        // 全体設定とプロセス固有のログシンクへの転送
+        let sinks: Vec<LogSinkConfig> = global_log_sinks
+            .into_iter()
+            .chain(process.info.log_sinks.iter().cloned())
+            .collect();
+        let workspace = process.info.cwd.as_deref();
+        stdout_buffer.set_tap(self.log_forwarder.tap(&sinks, &id, workspace, "stdout"));
+        stderr_buffer.set_tap(self.log_forwarder.tap(&sinks, &id, workspace, "stderr"));
+        // 記録できなくても起動は止めない
+        let recorder = process.info.output_recording.as_ref().and_then(|config| {
+            let header = RecordingHeader {
+                process_id: id.clone(),
+                run: process.run_count + 1,
+                command: process.info.command.clone(),
+                args: args.clone(),
+                started_at: Utc::now(),
+                include_stdin: config.include_stdin,
+            };
+            Recorder::start(&recording::default_recordings_dir(), config, header)
+                .inspect_err(|e| warn!("Not recording the output of '{}': {}", id, e))
+                .ok()
+        });
+        stdout_buffer.set_recorder(
+            recorder
+                .as_ref()
+                .map(|r| r.for_stream(RecordedStream::Stdout)),
+        );
+        stderr_buffer.set_recorder(
+            recorder
+                .as_ref()
+                .map(|r| r.for_stream(RecordedStream::Stderr)),
+        );
+
+        // 起動後に書かれた行だけを取り込むよう、起動前のファイルサイズを記録しておく
+        let log_offsets: Vec<u64> = process
+            .info
+            .log_sources
+            .iter()
+            .map(|source| log_sources::current_len(source, process.info.cwd.as_deref()))
+            .collect();
+        let mut repl_session = None;
+        let mut process_stdin = None;
+        let mut output_pipes = None;
+        let mut process_cgroup = None;
+        let mut spawned_env = process.info.env.clone();
+        let mut injected_env = Vec::new();
+        // 機密の環境変数は起動するときだけメモリ上で復号する（実行環境の記録には含めない）
+        let mut launch_env = process.info.env.clone();
+        if !process.info.sensitive_env.is_empty() {
+            launch_env.extend(sensitive_env::open(
+                sensitive_env::cipher()?,
+                &process.info.sensitive_env,
+            )?);
+            let mut names: Vec<String> = process.info.sensitive_env.keys().cloned().collect();
+            names.sort();
+            info!("Decrypted sensitive env {:?} to start '{}'", names, id);
+            if let Err(e) = self
+                .event_system
+                .emit_sensitive_env_accessed(id.clone(), names)
+                .await
+            {
+                debug!("Failed to emit sensitive env event: {}", e);
+            }
+        }
+        // 外部のシークレット管理への参照は起動するときに取り出す（定義と実行環境の記録は参照のまま）
+        let resolved = secret_refs::resolver().resolve_env(&mut launch_env).await?;
+        if !resolved.is_empty() {
+            info!(
+                "Resolved secret references {:?} to start '{}'",
+                resolved, id
+            );
+            if let Err(e) = self
+                .event_system
+                .emit_sensitive_env_accessed(id.clone(), resolved)
+                .await
+            {
+                debug!("Failed to emit sensitive env event: {}", e);
+            }
+        }
+        // 定義の環境変数を優先し、無いものをプロジェクトの環境で補う
+        if let Some(project_env) = &project_env {
+            for (key, value) in &project_env.env {
+                if !launch_env.contains_key(key) {
+                    launch_env.insert(key.clone(), value.clone());
+                    spawned_env.insert(key.clone(), value.clone());
+                    injected_env.push(key.clone());
+                }
+            }
+        }
+        let (launched, pid, stdout_handle, stderr_handle) = match process.info.tmux.clone() {
+            Some(tmux_config) => {
+                // tmuxのウィンドウ内で起動し、ペインの出力ログを取り込む
+                let log_dir = log_quota::default_log_dir().join(&id);
+                let pane = tmux::launch(
+                    &tmux_config,
+                    &id,
+                    &process.info.command,
+                    &args,
+                    &launch_env,
+                    process.info.cwd.as_deref(),
+                    &log_dir,
+                )
+                .await
+                .map_err(|e| format!("Failed to start process in tmux: {e}"))?;
+                let pid = pane.pid;
+                let stdout_handle =
+                    tokio::spawn(tmux::tail_output(pane.log_path.clone(), stdout_buffer));
+                // ペインではstderrもstdoutにまとめて出力される
+                let stderr_handle = tokio::spawn(async {});
+                (
+                    LaunchedProcess::Tmux(pane),
+                    pid,
+                    stdout_handle,
+                    stderr_handle,
+                )
+            }
+            None => {
+                // 権限はサーバーの再起動などで変わりうるため、起動のたびに確認する
+                let identity = process
+                    .info
+                    .run_as
+                    .as_ref()
+                    .map(run_as::validate)
+                    .transpose()?;
+
+                // コマンドを構築
+                let mut cmd = match &process.info.container {
+                    Some(config) => {
+                        // 前回強制終了されたクライアントのコンテナが残っていると同じ名前で起動できない
+                        container::remove_container(config.engine, &id).await;
+                        let mut cmd = Command::new(container::program(config.engine));
+                        let mut run_args = container::run_args(
+                            config,
+                            &id,
+                            &launch_env,
+                            &process.info.command,
+                            &args,
+                        );
+                        if process.info.keep_stdin_open {
+                            // 標準入力をコンテナ内のプロセスに渡す
+                            run_args.insert(1, "--interactive".to_string());
+                        }
+                        cmd.args(run_args);
+                        cmd
+                    }
+                    None => {
+                        let mut cmd = Command::new(&process.info.command);
+                        cmd.args(&args);
+                        cmd
+                    }
+                };
+                cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).stdin(
+                    if process.info.repl.is_some() || process.info.keep_stdin_open {
+                        Stdio::piped()
+                    } else {
+                        Stdio::null()
+                    },
+                );
+
+                // プロセスグループを設定（Unix系システムのみ）
+                // これにより、子プロセス（Dockerコンテナなど）も含めてシグナルを送信できる
+                #[cfg(unix)]
+                {
+                    #[allow(unused_imports)]
+                    use std::os::unix::process::CommandExt;
+                    cmd.process_group(0); // 新しいプロセスグループを作成
+                }
+
+                // 環境変数を設定
+                for (key, value) in &launch_env {
+                    cmd.env(key, value);
+                }
+
+                // 作業ディレクトリを設定
+                if let Some(cwd) = &process.info.cwd {
+                    cmd.current_dir(cwd);
+                }
+
+                // 専用のcgroupに入れて起動する（制限の指定がなければcgroup v2が無くても起動できる）
+                let limits = process.info.resource_limits.as_ref();
+                process_cgroup = match cgroup::detect_root() {
+                    Some(root) => match Cgroup::create(&root, &id, limits) {
+                        Ok(cgroup) => Some(cgroup),
+                        Err(e) if limits.is_some() => return Err(e),
+                        Err(e) => {
+                            debug!("Starting process '{}' without a cgroup: {}", id, e);
+                            None
+                        }
+                    },
+                    None if limits.is_some() => {
+                        return Err(
+                            "resource_limits require Linux with cgroup v2, which is not available"
+                                .to_string(),
+                        );
+                    }
+                    None => None,
+                };
+                #[cfg(unix)]
+                if let Some(cgroup) = &process_cgroup {
+                    use std::io::Write;
+                    let procs = cgroup.procs_file()?;
+                    // 起動直後に生成される子プロセスも含めるため、exec前の子プロセス自身がcgroupに入る
+                    // SAFETY: fork後の子プロセスでは、開いておいたファイルへのwrite(2)のみを行う
+                    // （メモリ確保やロックを伴わない）
+                    unsafe {
+                        cmd.pre_exec(move || (&procs).write_all(b"0"));
+                    }
+                }
+
+                // 別のユーザー・グループで起動
+                #[cfg(unix)]
+                if let Some(identity) = &identity {
+                    for (key, value) in run_as::default_env(identity) {
+                        if !launch_env.contains_key(key) {
+                            cmd.env(key, &value);
+                            spawned_env.insert(key.to_string(), value);
+                            injected_env.push(key.to_string());
+                        }
+                    }
+                    cmd.uid(identity.uid).gid(identity.gid);
+                }
+
+                // プロセスを起動
+                let mut child = cmd.spawn().map_err(|e| match &identity {
+                    Some(identity) => format!(
+                        "Failed to start process as {}: {e}",
+                        run_as::describe(identity)
+                    ),
+                    None => format!("Failed to start process: {e}"),
+                })?;
+
+                let pid = child
+                    .id()
+                    .ok_or_else(|| "Failed to get process ID".to_string())?;
+
+                if let Some(identity) = &identity {
+                    info!(
+                        target: run_as::AUDIT_TARGET,
+                        process_id = %id,
+                        pid,
+                        user = %identity.user,
+                        uid = identity.uid,
+                        group = %identity.group,
+                        gid = identity.gid,
+                        "Started process '{}' as {}",
+                        id,
+                        run_as::describe(identity)
+                    );
+                }
+
+                // 標準出力と標準エラー出力を処理
+                let stdout = child
+                    .stdout
+                    .take()
+                    .ok_or_else(|| "Failed to capture stdout".to_string())?;
+                let stderr = child
+                    .stderr
+                    .take()
+                    .ok_or_else(|| "Failed to capture stderr".to_string())?;
+                output_pipes = OutputPipes::duplicate(&stdout, &stderr);
+                // REPLはexecute_in_replから、それ以外はsend_process_inputから標準入力に送る
+                if let Some(stdin) = child.stdin.take() {
+                    if process.info.repl.is_some() {
+                        repl_session = Some(Arc::new(ReplSession::new(stdin)));
+                    } else {
+                        process_stdin = Some(Arc::new(ProcessStdin::new(stdin)));
+                    }
+                }
+
+                // 出力を非同期で読み取る
+                let stdout_handle = tokio::spawn(async move {
+                    let reader = BufReader::new(stdout);
+                    let mut lines = reader.lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        stdout_buffer.push(line).await;
+                    }
+                });
+
+                let stderr_handle = tokio::spawn(async move {
+                    let reader = BufReader::new(stderr);
+                    let mut lines = reader.lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        stderr_buffer.push(line).await;
+                    }
+                });
+
+                (
+                    LaunchedProcess::Child(child),
+                    pid,
+                    stdout_handle,
+                    stderr_handle,
+                )
+            }
+        };
+
+        // プロセス情報を更新
+        let (kill_tx, mut kill_rx) = mpsc::channel(1);
+        let (exit_tx, exit_rx) = watch::channel(false);
+        let started_at = Utc::now();
+        process.info.state = ProcessState::Running { pid, started_at };
+        if process.start_history.len() >= output_diff::START_HISTORY_CAPACITY {
+            process.start_history.pop_front();
+        }
+        process.start_history.push_back(started_at);
+        process.output_handles = Some((stdout_handle, stderr_handle));
+        process.repl_session = repl_session;
+        process.process_stdin = process_stdin;
+        process.output_pipes = output_pipes;
+        process.cgroup = process_cgroup.clone();
+        process.recorder = recorder;
+        process.run_count += 1;
+        let runtime = if process.info.tmux.is_some() {
+            "tmux"
+        } else if process.info.container.is_some() {
+            "container"
+        } else {
+            "host"
+        };
+        let mut run = RunEnvironment::capture(
+            process.run_count,
+            &process.info,
+            runtime,
+            &spawned_env,
+            process.info.run_as.as_ref().map(|r| r.user.clone()),
+        );
+        run.toolchain = project_env.map(|project_env| project_env.toolchain);
+        run.args = args.clone();
+        run.parameters = parameter_values;
+        // コンテナ内のツールはホストからは調べられない
+        let tools = if runtime == "container" {
+            Vec::new()
+        } else {
+            run_environment::tools_to_probe(&process.info.command, &args)
+        };
+        if process.run_history.len() >= run_environment::RUN_HISTORY_CAPACITY {
+            process.run_history.pop_front();
+        }
+        process.run_history.push_back(run.clone());
+        // ツールのバージョンは起動を待たせないよう後から埋める
+        let run_process = process_arc.clone();
+        tokio::spawn(async move {
+            let versions = run_environment::probe_tool_versions(&run, &tools).await;
+            let mut process = run_process.write().await;
+            if let Some(entry) = process.run_history.iter_mut().find(|r| r.run == run.run) {
+                entry.tool_versions = Some(versions);
+            }
+        });
+        process.spawned_env = Some((spawned_env, injected_env));
+        process.log_source_handles = process
+            .info
+            .log_sources
+            .iter()
+            .zip(log_offsets)
+            .map(|(source, offset)| {
+                let buffer = if source.stderr {
+                    process.stderr_buffer.clone()
+                } else {
+                    process.stdout_buffer.clone()
+                };
+                log_sources::spawn(source, process.info.cwd.as_deref(), offset, buffer)
+            })
+            .collect();
+        process.idle_since = None;
+        process.unhealthy_since = None;
+        process.probe.reset();
+        process.info.health = None;
+        process.control = Some(ProcessControl { kill_tx, exit_rx });
+        process.stop_requested = false;
+
+        // Persist the updated state
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            tracing::warn!("Failed to persist process state: {}", e);
+        }
+
+        // プロセスの終了を監視するタスクを起動
+        // 起動したプロセスはこのタスクが所有し、停止要求はkill_tx経由で受け取る
+        let process_id = id.clone();
+        let run_number = process.run_count;
+        let container_engine = process.info.container.as_ref().map(|c| c.engine);
+        let process_arc_clone = process_arc.clone();
+        let persistence_clone = self.persistence.clone();
+        let event_system = self.event_system.clone();
+        tokio::spawn(async move {
+            let mut launched = launched;
+            let result = tokio::select! {
+                result = launched.wait() => result,
+                _ = kill_rx.recv() => {
+                    if let Err(e) = launched.kill().await {
+                        warn!("Failed to kill process '{}': {}", process_id, e);
+                    }
+                    if let Some(cgroup) = &process_cgroup
+                        && let Err(e) = cgroup.kill()
+                    {
+                        warn!("Failed to kill cgroup of process '{}': {}", process_id, e);
+                    }
+                    launched.wait().await
+                }
+            };
+
+            if let Some(engine) = container_engine {
+                // クライアントが強制終了された場合でもコンテナを残さない
+                container::remove_container(engine, &process_id).await;
+            }
+
+            if matches!(launched, LaunchedProcess::Tmux(_)) {
+                // ログの残りを取り込んでから追従を止める
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                if let Some((stdout_handle, stderr_handle)) =
+                    process_arc_clone.write().await.output_handles.take()
+                {
+                    stdout_handle.abort();
+                    stderr_handle.abort();
+                }
+            } else {
+                // 異常終了のレポートに含めるよう、終了間際の出力を取り込み終えるまで待つ
+                // （孫プロセスがパイプを開いたままの場合は打ち切る）
+                let handles = {
+                    let mut process = process_arc_clone.write().await;
+                    if process.stop_requested {
+                        None
+                    } else {
+                        process.output_handles.take()
+                    }
+                };
+                if let Some((mut stdout_handle, mut stderr_handle)) = handles {
+                    let drained = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, async {
+                        let _ = (&mut stdout_handle).await;
+                        let _ = (&mut stderr_handle).await;
+                    })
+                    .await;
+                    if drained.is_err() {
+                        stdout_handle.abort();
+                        stderr_handle.abort();
+                    }
+                }
+            }
+
+            let mut process = process_arc_clone.write().await;
+            let log_source_handles = std::mem::take(&mut process.log_source_handles);
+            if !log_source_handles.is_empty() {
+                // 終了間際に書かれたログを取り込んでから追従を止める
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                    for handle in log_source_handles {
+                        handle.abort();
+                    }
+                });
+            }
+            let stop_requested = std::mem::take(&mut process.stop_requested);
+            process.chaos_paused_until = None;
+            process.cgroup = None;
+            process.output_pipes = None;
+            // 記録を閉じる（書き込みタスクは残りの行を書いて終了する）
+            process.recorder = None;
+            process.stdout_buffer.set_recorder(None);
+            process.stderr_buffer.set_recorder(None);
+            // cgroupを片付ける前に、終了した時点の使用状況を控えておく
+            let resources = process_cgroup.as_ref().map(Cgroup::stats);
+            if let Some(cgroup) = process_cgroup {
+                tokio::spawn(Self::release_cgroup(
+                    process_id.clone(),
+                    cgroup,
+                    stop_requested,
+                ));
+            }
+            let now = chrono::Utc::now();
+            process.info.health = None;
+            process.probe.reset();
+
+            let mut exited_with = None;
+            let mut exit_signal = None;
+            let failure = match result {
+                Ok((exit_code, signal)) => {
+                    exit_signal = signal;
+                    exited_with = Some(exit_code);
+                    debug!("Process '{}' exited with code: {:?}", process_id, exit_code);
+
+                    process.info.state = ProcessState::Stopped {
+                        exit_code,
+                        stopped_at: now,
+                    };
+                    info!(
+                        "Process '{}' stopped with exit code: {:?}",
+                        process_id, exit_code
+                    );
+
+                    (exit_code != Some(0)).then_some(ExitRecord {
+                        at: now,
+                        exit_code,
+                        error: None,
+                    })
+                }
+                Err(e) => {
+                    error!("Failed to wait for process '{}': {}", process_id, e);
+
+                    let error = format!("Process wait failed: {e}");
+                    process.info.state = ProcessState::Failed {
+                        error: error.clone(),
+                        failed_at: now,
+                    };
+
+                    Some(ExitRecord {
+                        at: now,
+                        exit_code: None,
+                        error: Some(error),
+                    })
+                }
+            };
+
+            // 停止要求による終了とメンテナンス中の終了は異常終了として数えない
+            let in_maintenance = match persistence_clone.get_settings().await {
+                Ok(settings) => {
+                    maintenance::active_for(&settings.maintenance_windows, &process.info, now)
+                        .is_some()
+                }
+                Err(_) => false,
+            };
+            let failure = failure.filter(|_| !stop_requested && !in_maintenance);
+            let failure_report = match &failure {
+                Some(record) => {
+                    let mut stderr_tail = process
+                        .stderr_buffer
+                        .get_last_n(failure_report::STDERR_TAIL_LINES)
+                        .await;
+                    if stderr_tail.is_empty() {
+                        stderr_tail = process
+                            .stdout_buffer
+                            .get_last_n(failure_report::STDERR_TAIL_LINES)
+                            .await;
+                    }
+                    let window = failure_report::OUTPUT_WINDOW_SECS;
+                    let to = output_rates::window_end(now);
+                    let report = FailureReport::new(
+                        run_number,
+                        record,
+                        exit_signal,
+                        Some(started_at),
+                        stderr_tail,
+                        process.output_rates(to - window as i64, to, window),
+                        resources,
+                    );
+                    if let Some(entry) =
+                        process.run_history.iter_mut().find(|r| r.run == run_number)
+                    {
+                        entry.failure = Some(report.clone());
+                    }
+                    Some(report)
+                }
+                None => None,
+            };
+            let failed_notification = failure
+                .as_ref()
+                .map(|record| notifications::process_failed(&process_id, record));
+            let crash_loop =
+                failure.and_then(|record| process.crash_tracker.record_failure(record));
+
+            // 再起動ポリシー（停止要求による終了とメンテナンス中の終了では再起動しない）
+            let exit_code = exited_with.flatten();
+            let restart = match process.info.restart_policy.clone() {
+                Some(_) if stop_requested || in_maintenance => {
+                    process.restart.reset();
+                    None
+                }
+                Some(policy) => {
+                    let failed = !matches!(
+                        process.info.state,
+                        ProcessState::Stopped {
+                            exit_code: Some(0),
+                            ..
+                        }
+                    );
+                    let ran_for = (now - started_at).to_std().unwrap_or_default();
+                    let decision = process.restart.on_exit(&policy, failed, ran_for, now);
+                    Some((decision, policy.max_retries))
+                }
+                None => None,
+            };
+
+            // 永続化
+            let db_info = ProcessManager::to_db_process_info(&process.info);
+            if let Err(e) = persistence_clone.update_process(&db_info).await {
+                tracing::warn!("Failed to persist stopped process state: {}", e);
+            }
+            drop(process);
+            let _ = exit_tx.send(true);
+
+            // イベントを受けた側から通知が見えるよう、発行より先に記録する
+            if let Some(notification) = failed_notification {
+                notifications::record(&persistence_clone, notification).await;
+            }
+
+            if let Some(exit_code) = exited_with
+                && let Err(e) = event_system
+                    .emit_process_stopped(
+                        process_id.clone(),
+                        exit_code,
+                        stop_requested,
+                        failure_report.as_ref(),
+                    )
+                    .await
+            {
+                debug!("Failed to emit stop event: {}", e);
+            }
+
+            if let Some(info) = crash_loop {
+                warn!(
+                    "Crash loop detected for process '{}' ({} failures); automatic restarts are suppressed",
+                    process_id,
+                    info.recent_exits.len()
+                );
+                notifications::record(
+                    &persistence_clone,
+                    notifications::crash_loop(&process_id, &info),
+                )
+                .await;
+                if let Err(e) = event_system
+                    .emit_crash_loop_detected(process_id.clone(), &info)
+                    .await
+                {
+                    warn!("Failed to emit crash loop event: {}", e);
+                }
+            }
+
+            // 予定した再起動は再起動の監視タスクが待ってから行う
+            match restart {
+                Some((Decision::Restart { attempt, delay }, max_retries)) => {
+                    info!(
+                        "Restarting process '{}' in {}ms (attempt {})",
+                        process_id,
+                        delay.as_millis(),
+                        attempt
+                    );
+                    if let Err(e) = event_system
+                        .emit_restart_scheduled(
+                            process_id.clone(),
+                            attempt,
+                            max_retries,
+                            delay,
+                            exit_code,
+                        )
+                        .await
+                    {
+                        warn!("Failed to emit restart event: {}", e);
+                    }
+                }
+                Some((Decision::GiveUp { attempts }, _)) => {
+                    warn!(
+                        "Process '{}' exited after {} automatic restart(s); giving up",
+                        process_id, attempts
+                    );
+                    if let Err(e) = event_system
+                        .emit_restart_limit_reached(process_id.clone(), attempts, exit_code)
+                        .await
+                    {
+                        warn!("Failed to emit restart limit event: {}", e);
+                    }
+                }
+                Some((Decision::None, _)) | None => {}
+            }
+        });
+
+        info!("Started process '{}' with PID {}", id, pid);
+        Ok(pid)
+    }
+
+    /// 実行中のプロセスを一時停止する（SIGSTOP、コンテナは `pause`）
+    ///
+    /// 一時停止中のプロセスはアイドル検知やカオステストの対象にならない。
+    pub async fn pause_process(&self, id: String) -> Result<ProcessInfo, String> {
+        let process_arc = self
+            .processes
+            .read()
+            .await
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?
+            .clone();
+        let mut process = process_arc.write().await;
+        let (pid, started_at) = match process.info.state {
+            ProcessState::Running { pid, started_at } => (pid, started_at),
+            ProcessState::Paused { .. } => return Err(format!("Process '{id}' is already paused")),
+            _ => return Err(format!("Process '{id}' is not running")),
+        };
+        reaper::ensure_same_process(&id, pid, started_at)?;
+        pause::suspend(&id, pid, process.info.container.as_ref()).await?;
+        // カオステストの一時停止中であれば、予定時刻の自動再開を取り消して引き継ぐ
+        process.chaos_paused_until = None;
+        process.idle_since = None;
+        process.info.state = ProcessState::Paused {
+            pid,
+            started_at,
+            paused_at: Utc::now(),
+        };
+        let info = process.info.clone();
+        drop(process);
+
+        info!("Paused process '{}' (PID {})", id, pid);
+        if let Err(e) = self.event_system.emit_process_paused(id, pid).await {
+            debug!("Failed to emit pause event: {}", e);
+        }
+        Ok(info)
+    }
+
+    /// 一時停止したプロセスを再開する
+    pub async fn resume_process(&self, id: String) -> Result<ProcessInfo, String> {
+        let process_arc = self
+            .processes
+            .read()
+            .await
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?
+            .clone();
+        let mut process = process_arc.write().await;
+        let ProcessState::Paused {
+            pid,
+            started_at,
+            paused_at,
+        } = process.info.state
+        else {
+            return Err(format!("Process '{id}' is not paused"));
+        };
+        reaper::ensure_same_process(&id, pid, started_at)?;
+        pause::resume(&id, pid, process.info.container.as_ref()).await?;
+        let now = Utc::now();
+        process.resumed_at = Some(now);
+        process.info.state = ProcessState::Running { pid, started_at };
+        let info = process.info.clone();
+        drop(process);
+
+        let paused_seconds = (now - paused_at).num_seconds().max(0) as u64;
+        info!(
+            "Resumed process '{}' (PID {}) after {}s",
+            id, pid, paused_seconds
+        );
+        if let Err(e) = self
+            .event_system
+            .emit_process_resumed(id, pid, paused_seconds)
+            .await
+        {
+            debug!("Failed to emit resume event: {}", e);
+        }
+        Ok(info)
+    }
+
+    /// プロセスを停止
+    pub async fn stop_process(
+        &self,
+        id: String,
+        grace_period_ms: Option<u64>,
+    ) -> Result<(), String> {
+        info!("Stopping process '{}'...", id);
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?
+            .clone();
+        drop(processes);
+
+        // 引き取ったプロセスには終了監視タスクがない
+        let adopted = {
+            let process = process_arc.read().await;
+            process.adopted_at.is_some() && process.control.is_none()
+        };
+        if adopted {
+            return self
+                .stop_adopted_process(&id, &process_arc, grace_period_ms.unwrap_or(5000))
+                .await;
+        }
+
+        // 終了監視タスクが状態を更新できるよう、ロックは待機前に解放する
+        let (pid, signal_safe, kill_tx, mut exit_rx, process_cgroup) = {
+            let mut process = process_arc.write().await;
+
+            // 自動再起動を待っている間の停止は、予定の取り消しとして扱う
+            if !matches!(
+                process.info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            ) && process.restart.cancel()
+            {
+                info!("Cancelled the scheduled restart of process '{}'", id);
+                return Ok(());
+            }
+
+            // 実行中でない場合はエラー
+            let (pid, started_at, paused) = match process.info.state {
+                ProcessState::Running { pid, started_at } => (pid, started_at, false),
+                ProcessState::Paused {
+                    pid, started_at, ..
+                } => (pid, started_at, true),
+                _ => return Err(format!("Process '{id}' is not running")),
+            };
+            let control = process
+                .control
+                .as_ref()
+                .ok_or_else(|| format!("Process '{id}' is not running"))?;
+            let handles = (control.kill_tx.clone(), control.exit_rx.clone());
+            process.stop_requested = true;
+            // PIDが別のプロセスに再利用されていればシグナルは送らず、終了監視タスクに任せる
+            let signal_safe = match reaper::ensure_same_process(&id, pid, started_at) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("{}; stopping without signals", e);
+                    false
+                }
+            };
+            // カオステストで一時停止中のプロセスはSIGTERMを処理できないため先に再開する
+            if process.chaos_paused_until.take().is_some() && signal_safe {
+                let _ = chaos::send_signal(pid, ChaosSignal::Cont);
+            }
+            if paused
+                && signal_safe
+                && let Err(e) = pause::resume(&id, pid, process.info.container.as_ref()).await
+            {
+                warn!(
+                    "Failed to resume paused process '{}' before stopping: {}",
+                    id, e
+                );
+            }
+            (
+                pid,
+                signal_safe,
+                handles.0,
+                handles.1,
+                process.cgroup.clone(),
+            )
+        };
+
+        // デフォルトのグレースピリオドは5秒
+        let grace_ms = grace_period_ms.unwrap_or(5000);
+
+        // まずSIGTERMを送信してグレースフルシャットダウンを試みる
+        #[cfg(unix)]
+        if signal_safe {
+            use nix::sys::signal::{self, Signal};
+            use nix::unistd::Pid;
+
+            let pid = Pid::from_raw(pid as i32);
+
+            // まずプロセスグループ全体にSIGTERMを送信（Dockerなどの子プロセス対策）
+            let pgid = Pid::from_raw(-(pid.as_raw()));
+            if let Err(e) = signal::kill(pgid, Signal::SIGTERM) {
+                tracing::debug!("Failed to send SIGTERM to process group {}: {}", id, e);
+                // プロセスグループ送信が失敗した場合、個別のプロセスに送信
+                if let Err(e) = signal::kill(pid, Signal::SIGTERM) {
+                    tracing::warn!("Failed to send SIGTERM to process {}: {}", id, e);
+                }
+            }
+            // プロセスグループを抜けた子孫（setsidしたデーモンなど）にもcgroup経由で送信
+            for member in process_cgroup.iter().flat_map(Cgroup::pids) {
+                let _ = signal::kill(Pid::from_raw(member as i32), Signal::SIGTERM);
+            }
+
+            info!(
+                "Sent SIGTERM to process '{}', waiting up to {}ms for graceful shutdown",
+                id, grace_ms
+            );
+
+            // グレースピリオド内での終了を待つ
+            let timeout = tokio::time::Duration::from_millis(grace_ms);
+            if tokio::time::timeout(timeout, exit_rx.wait_for(|exited| *exited))
+                .await
+                .is_ok()
+            {
+                Self::cleanup_output_handles(&process_arc).await;
+                info!("Process '{}' stopped gracefully", id);
+                return Ok(());
+            }
+
+            // タイムアウト - SIGKILLで強制終了
+            info!(
+                "Process '{}' did not terminate within grace period, sending SIGKILL",
+                id
+            );
+            // プロセスグループ全体にSIGKILLを送信
+            if let Err(e) = signal::kill(pgid, Signal::SIGKILL) {
+                tracing::debug!("Failed to send SIGKILL to process group {}: {}", id, e);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (pid, signal_safe, grace_ms, process_cgroup);
+
+        // Windows または SIGTERM/SIGKILL失敗時の最終手段としてkill()を使用
+        let _ = kill_tx.try_send(());
+
+        // プロセスの終了を待つ（タイムアウト付き）
+        let wait_timeout = tokio::time::Duration::from_secs(10);
+        match tokio::time::timeout(wait_timeout, exit_rx.wait_for(|exited| *exited)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(_)) => {
+                return Err(format!(
+                    "Error waiting for process '{id}' to terminate: monitor task ended"
+                ));
+            }
+            Err(_) => {
+                return Err(format!(
+                    "Timeout waiting for process '{id}' to terminate after kill signal"
+                ));
+            }
+        }
+
+        Self::cleanup_output_handles(&process_arc).await;
+        info!("Stopped process '{}'", id);
+
+        Ok(())
+    }
+
+    /// 出力読み取りタスクを終了
+    async fn cleanup_output_handles(process_arc: &Arc<RwLock<ManagedProcess>>) {
+        let mut process = process_arc.write().await;
+        if let Some((stdout_handle, stderr_handle)) = process.output_handles.take() {
+            stdout_handle.abort();
+            stderr_handle.abort();
+        }
+        process.control = None;
+    }
+
+    /// 全ての実行中プロセスを停止（stop_on_shutdownフラグに基づく）
+    pub async fn stop_all_processes(&self) -> Result<Vec<String>, String> {
+        info!("Stopping all running processes...");
+
+        let processes = self.processes.read().await;
+        let mut stopped_processes = Vec::new();
+        let mut errors = Vec::new();
+
+        for (id, process_arc) in processes.iter() {
+            let process = process_arc.read().await;
+
+            // 実行中（一時停止中を含む）のプロセスのみ対象
+            // 引き取ったプロセスは起動したターミナルのものなので残す
+            if matches!(
+                process.info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            ) && process.adopted_at.is_none()
+            {
+                let id_clone = id.clone();
+                drop(process); // ロックを解放
+
+                // プロセスを停止（5秒の猶予期間）
+                match self.stop_process(id_clone.clone(), Some(5000)).await {
+                    Ok(_) => {
+                        info!("Successfully stopped process '{}'", id_clone);
+                        stopped_processes.push(id_clone);
+                    }
+                    Err(e) => {
+                        error!("Failed to stop process '{}': {}", id_clone, e);
+                        errors.push(format!("{id_clone}: {e}"));
+                    }
+                }
+            }
+        }
+
+        drop(processes);
+
+        if !errors.is_empty() {
+            warn!("Some processes failed to stop: {:?}", errors);
+        }
+
+        info!("Stopped {} running process(es)", stopped_processes.len());
+
+        Ok(stopped_processes)
+    }
+
+    /// トンネルを登録し、`start` が指定されていれば起動する
+    pub async fn create_tunnel(
+        &self,
+        config: vantage_persistence::TunnelConfig,
+        start: bool,
+    ) -> Result<TunnelStatus, String> {
+        if let Some(process_id) = &config.process_id
+            && !self.processes.read().await.contains_key(process_id)
+        {
+            return Err(format!("Process '{process_id}' not found"));
+        }
+        let tunnel_id = config.tunnel_id.clone();
+        self.tunnels.create(config).await?;
+        if start {
+            self.tunnels.start(&tunnel_id).await?;
+        }
+        self.tunnels.status(&tunnel_id).await
+    }
+
+    pub async fn start_tunnel(&self, tunnel_id: &str) -> Result<TunnelStatus, String> {
+        self.tunnels.start(tunnel_id).await?;
+        self.tunnels.status(tunnel_id).await
+    }
+
+    pub async fn stop_tunnel(&self, tunnel_id: &str) -> Result<TunnelStatus, String> {
+        self.tunnels.stop(tunnel_id).await?;
+        self.tunnels.status(tunnel_id).await
+    }
+
+    pub async fn remove_tunnel(&self, tunnel_id: &str) -> Result<(), String> {
+        self.tunnels.remove(tunnel_id).await
+    }
+
+    /// トンネルを一覧（`process_id` を指定するとそのプロセスに紐付いたもののみ）
+    pub async fn list_tunnels(&self, process_id: Option<&str>) -> Vec<TunnelStatus> {
+        self.tunnels.list(process_id).await
+    }
+
+    /// ブルーグリーン方式で無停止再起動する
+    ///
+    /// 稼働していない側のインスタンスを稼働中の側の定義から別のポートで作って起動し、
+    /// ポートに接続できるようになってからプロキシの転送先を切り替え、`drain_secs` の後に
+    /// それまでの側を停止する。準備完了にならなければ新しい側を停止し、転送先は変えない。
+    pub async fn rolling_restart(
+        &self,
+        id: String,
+        options: RollingRestartOptions,
+    ) -> Result<RollingRestartReport, String> {
+        let route = match self.proxy_routes.status(&id).await {
+            Some(route) => route,
+            None => self.open_proxy_route(&id, &options).await?,
+        };
+        let previous = route.live.clone();
+        let live = self
+            .process_definition(&previous.process_id)
+            .await
+            .ok_or_else(|| format!("Process '{}' not found", previous.process_id))?;
+        if live.status.state != DbProcessState::Running {
+            return Err(format!("Process '{}' is not running", previous.process_id));
+        }
+
+        let standby_id = if previous.process_id == id {
+            blue_green::green_id(&id)
+        } else {
+            id.clone()
+        };
+        let existing = self.process_definition(&standby_id).await;
+        if existing
+            .as_ref()
+            .is_some_and(|info| info.status.state == DbProcessState::Running)
+        {
+            return Err(format!(
+                "Standby instance '{standby_id}' is already running"
+            ));
+        }
+        let listen_port = route
+            .listen
+            .parse::<std::net::SocketAddr>()
+            .map(|addr| addr.port())
+            .ok();
+        let in_use = |port: u16| port == previous.port || Some(port) == listen_port;
+        let standby_port = match options.standby_port {
+            Some(port) if in_use(port) => {
+                return Err(format!(
+                    "standby_port {port} is used by the live instance or the proxy"
+                ));
+            }
+            Some(port) => port,
+            // 前回使ったポートがあれば使い回す
+            None => match existing
+                .as_ref()
+                .and_then(|info| blue_green::port_from_env(&info.env, &route.port_env).ok())
+                .filter(|port| !in_use(*port))
+            {
+                Some(port) => port,
+                None => blue_green::free_port().await?,
+            },
+        };
+
+        let before = existing;
+        let mut definition = live;
+        definition.process_id = standby_id.clone();
+        definition.name = standby_id.clone();
+        definition
+            .env
+            .insert(route.port_env.clone(), standby_port.to_string());
+        self.put_process_definition(definition).await?;
+        let after = self.process_definition(&standby_id).await;
+        self.record_change(
+            ChangeTarget::Process,
+            &standby_id,
+            before.as_ref(),
+            after.as_ref(),
+        )
+        .await;
+
+        info!(
+            "Rolling restart of '{}': starting '{}' on port {}",
+            id, standby_id, standby_port
+        );
+        let started = std::time::Instant::now();
+        self.start_process(standby_id.clone()).await?;
+        let timeout = Duration::from_secs(
+            options
+                .ready_timeout_secs
+                .unwrap_or(blue_green::DEFAULT_READY_TIMEOUT_SECS),
+        );
+        if let Err(e) = self
+            .wait_until_listening(&standby_id, standby_port, timeout)
+            .await
+        {
+            let _ = self.stop_process(standby_id.clone(), None).await;
+            return Err(e);
+        }
+        let ready_after_ms = started.elapsed().as_millis() as u64;
+
+        let current = Instance {
+            process_id: standby_id,
+            port: standby_port,
+        };
+        self.proxy_routes.switch(&id, current.clone()).await?;
+        info!(
+            "Rolling restart of '{}': {} now forwards to '{}' on port {}",
+            id, route.listen, current.process_id, current.port
+        );
+
+        // 切り替え前に受け付けた接続が終わるのを待ってから停止する
+        let drain = options.drain_secs.unwrap_or(blue_green::DEFAULT_DRAIN_SECS);
+        tokio::time::sleep(Duration::from_secs(drain)).await;
+        let stop_error = self
+            .stop_process(previous.process_id.clone(), None)
+            .await
+            .err();
+        if let Some(e) = &stop_error {
+            warn!(
+                "Rolling restart of '{}': failed to stop '{}': {}",
+                id, previous.process_id, e
+            );
+        }
+
+        Ok(RollingRestartReport {
+            process_id: id,
+            listen: route.listen,
+            previous,
+            current,
+            ready_after_ms,
+            stop_error,
+        })
+    }
+
+    /// 最初の `rolling_restart` で、実行中のプロセスの前段にプロキシを置く
+    async fn open_proxy_route(
+        &self,
+        id: &str,
+        options: &RollingRestartOptions,
+    ) -> Result<ProxyRouteStatus, String> {
+        if let Some(base) = id.strip_suffix(blue_green::GREEN_SUFFIX)
+            && self.proxy_routes.status(base).await.is_some()
+        {
+            return Err(format!(
+                "'{id}' is the standby instance of '{base}'; restart '{base}' instead"
+            ));
+        }
+        let info = self
+            .process_definition(id)
+            .await
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let proxy_port = options
+            .proxy_port
+            .ok_or_else(|| format!("Process '{id}' has no proxy route yet; specify proxy_port"))?;
+        let port_env = options
+            .port_env
+            .as_deref()
+            .unwrap_or(blue_green::DEFAULT_PORT_ENV);
+        let port = blue_green::port_from_env(&info.env, port_env)?;
+        if port == proxy_port {
+            return Err(format!(
+                "proxy_port must differ from the port the process listens on ({port})"
+            ));
+        }
+        let host = options
+            .proxy_host
+            .as_deref()
+            .unwrap_or(blue_green::DEFAULT_PROXY_HOST);
+        let live = Instance {
+            process_id: id.to_string(),
+            port,
+        };
+        self.proxy_routes
+            .open(id, host, proxy_port, port_env, live)
+            .await
+    }
+
+    /// プロセスがポートで接続を受け付けるまで待つ
+    async fn wait_until_listening(
+        &self,
+        id: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<(), String> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if blue_green::accepts_connections(port).await {
+                return Ok(());
+            }
+            let running = self
+                .process_definition(id)
+                .await
+                .is_some_and(|info| info.status.state == DbProcessState::Running);
+            if !running {
+                return Err(format!(
+                    "Process '{id}' exited before accepting connections on port {port}"
+                ));
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!(
+                    "Process '{id}' did not accept connections on port {port} within {}s",
+                    timeout.as_secs()
+                ));
+            }
+            tokio::time::sleep(blue_green::READY_POLL_INTERVAL).await;
+        }
+    }
+
+    /// プロキシの経路（`rolling_restart` で作ったもの）
+    pub async fn proxy_route(&self, id: &str) -> Option<ProxyRouteStatus> {
+        self.proxy_routes.status(id).await
+    }
+
+    /// 最初の接続で起動するように設定する（`None` で解除する）
+    ///
+    /// Vantageが `listen_port` で待ち受け、プロセスには `backend_port` を環境変数で渡す。
+    /// `backend_port` を省略すると空いているポートを選んで設定に残す。
+    /// 環境変数の変更は次にプロセスを起動したときから反映される。
+    pub async fn set_lazy_start(
+        &self,
+        id: String,
+        config: Option<LazyStartConfig>,
+    ) -> Result<Option<LazyStartStatus>, String> {
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let mut process = process_arc.write().await;
+        let before = Self::to_db_process_info(&process.info);
+
+        let status = match config {
+            Some(mut config) => {
+                lazy_start::validate(&config)?;
+                if config.backend_port.is_none() {
+                    config.backend_port = Some(blue_green::free_port().await?);
+                }
+                let status = self.lazy_listeners.open(&id, &config).await?;
+                process.info.env.insert(
+                    lazy_start::port_env(&config).to_string(),
+                    status.backend_port.to_string(),
+                );
+                process.info.lazy_start = Some(config);
+                Some(status)
+            }
+            None => {
+                if self.lazy_listeners.close(&id).await {
+                    info!("Closed the lazy start listener of '{}'", id);
+                }
+                process.info.lazy_start = None;
+                None
+            }
+        };
+
+        let db_info = Self::to_db_process_info(&process.info);
+        self.persistence
+            .update_process(&db_info)
+            .await
+            .map_err(|e| format!("Failed to persist lazy start config: {e}"))?;
+        self.record_change(ChangeTarget::Process, &id, Some(&before), Some(&db_info))
+            .await;
+        Ok(status)
+    }
+
+    /// 遅延起動を設定したプロセスの待ち受けを始める（既に待ち受けているものはそのまま）
+    async fn open_lazy_listeners(&self) {
+        let mut configs = Vec::new();
+        for (id, process_arc) in self.processes.read().await.iter() {
+            if let Some(config) = &process_arc.read().await.info.lazy_start {
+                configs.push((id.clone(), config.clone()));
+            }
+        }
+        for (id, config) in configs {
+            if self.lazy_listeners.is_open(&id).await {
+                continue;
+            }
+            if let Err(e) = self.lazy_listeners.open(&id, &config).await {
+                warn!("Failed to open the lazy start listener of '{}': {}", id, e);
+            }
+        }
+    }
+
+    /// 遅延起動の待ち受けに来た接続のためにプロセスを起動し、接続を受け付けるまで待つ
+    ///
+    /// 既に実行中の場合は起動しない。この呼び出しで起動したかを返す。
+    async fn activate_lazy_process(
+        &self,
+        id: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<bool, String> {
+        let info = self
+            .process_definition(id)
+            .await
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let started = info.status.state != DbProcessState::Running;
+        if started {
+            info!("Lazy start of '{}': starting on first connection", id);
+            self.start_process(id.to_string()).await?;
+        }
+        if let Err(e) = self.wait_until_listening(id, port, timeout).await {
+            if started {
+                let _ = self.stop_process(id.to_string(), None).await;
+            }
+            return Err(e);
+        }
+        Ok(started)
+    }
+
+    /// 遅延起動で起動し、接続がないまま `idle_stop_secs` が過ぎたプロセスを停止する
+    async fn stop_idle_lazy_processes(&self) {
+        for id in self.lazy_listeners.take_idle().await {
+            info!("Lazy start of '{}': stopping after no connections", id);
+            if let Err(e) = self.stop_process(id.clone(), None).await {
+                debug!("Failed to stop idle lazy-started process '{}': {}", id, e);
+            }
+        }
+    }
+
+    /// Kubernetesのワークロードを登録する
+    ///
+    /// プロセスと同じIDは使えない（出力は `get_process_output` でIDを指定して読む）。
+    pub async fn register_kubernetes_workload(
+        &self,
+        config: vantage_persistence::KubernetesWorkload,
+    ) -> Result<WorkloadStatus, String> {
+        if self
+            .processes
+            .read()
+            .await
+            .contains_key(&config.workload_id)
+        {
+            return Err(format!(
+                "Process '{}' already exists; choose a different workload_id",
+                config.workload_id
+            ));
+        }
+        self.kubernetes.register(config).await
+    }
+
+    pub async fn remove_kubernetes_workload(&self, workload_id: &str) -> Result<(), String> {
+        self.kubernetes.remove(workload_id).await
+    }
+
+    /// Kubernetesのワークロードを一覧
+    pub async fn list_kubernetes_workloads(&self) -> Vec<WorkloadStatus> {
+        self.kubernetes.list().await
+    }
+
+    /// 出力の頻度とエラー率を、直前の同じ長さの期間と比べて取得
+    pub async fn get_process_metrics(
+        &self,
+        id: &str,
+        window_secs: Option<u64>,
+    ) -> Result<OutputMetrics, String> {
+        let window_secs = window_secs.unwrap_or(output_rates::DEFAULT_WINDOW_SECS);
+        output_rates::validate_window(window_secs)?;
+        let process_arc = self
+            .processes
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let process = process_arc.read().await;
+
+        let now = Utc::now();
+        let to = output_rates::window_end(now);
+        let window = window_secs as i64;
+        let current = process.output_rates(to - window, to, window_secs);
+        let previous = process.output_rates(to - 2 * window, to - window, window_secs);
+        Ok(OutputMetrics {
+            process_id: id.to_string(),
+            window_secs,
+            error_trend: Trend::between(
+                previous.errors_per_minute,
+                current.errors_per_minute,
+                window_secs,
+            ),
+            stderr_trend: Trend::between(
+                previous.stderr_per_minute,
+                current.stderr_per_minute,
+                window_secs,
+            ),
+            current,
+            previous,
+            health: process
+                .info
+                .health_check
+                .as_ref()
+                .map(|config| process.health(config, now)),
+        })
+    }
+
+    /// プロセスのステータスを取得
+    pub async fn get_process_status(&self, id: String) -> Result<ProcessStatus, String> {
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+
+        let process = process_arc.read().await;
+
+        let (uptime_seconds, pid) = match &process.info.state {
+            ProcessState::Running {
+                started_at, pid, ..
+            }
+            | ProcessState::Paused {
+                started_at, pid, ..
+            } => (
+                Some((chrono::Utc::now() - *started_at).num_seconds() as u64),
+                Some(*pid),
+            ),
+            _ => (None, None),
+        };
+        let cgroup = process.cgroup.as_ref().map(Cgroup::stats);
+        let latest = pid.and_then(|pid| process.resource_samples.latest(pid));
+        let maintenance = self.active_maintenance(&process.info).await;
+        let energy_cost = self.get_settings().await.unwrap_or_default().energy_cost;
+
+        Ok(ProcessStatus {
+            info: process.info.clone(),
+            cpu_usage: latest.and_then(|(cpu, _)| cpu),
+            memory_usage: cgroup
+                .as_ref()
+                .and_then(|stats| stats.memory_current)
+                .or(latest.map(|(_, memory)| memory)),
+            uptime_seconds,
+            idle: process.idle_since.is_some(),
+            idle_since: process.idle_since,
+            unhealthy_since: process.unhealthy_since,
+            crash_loop: process.crash_tracker.crash_loop().cloned(),
+            attach_instructions: process
+                .info
+                .tmux
+                .as_ref()
+                .map(|config| tmux::attach_instructions(config, &process.info.id)),
+            repl: process
+                .repl_session
+                .as_ref()
+                .filter(|_| uptime_seconds.is_some())
+                .map(|session| session.status()),
+            chaos_paused_until: process.chaos_paused_until,
+            output_sampling: process.stdout_buffer.sampling_status().map(|mut status| {
+                if let Some(stderr) = process.stderr_buffer.sampling_status() {
+                    status.kept_lines += stderr.kept_lines;
+                    status.dropped_lines += stderr.dropped_lines;
+                }
+                status
+            }),
+            cgroup,
+            image_build: process.image_build.clone(),
+            tunnels: self.tunnels.list(Some(&id)).await,
+            maintenance,
+            resource_usage: process.cpu_accounting.usage(&energy_cost),
+            resource_history: pid.and_then(|pid| process.resource_samples.history(pid, Utc::now())),
+            redactions: process.stdout_buffer.redaction_count()
+                + process.stderr_buffer.redaction_count(),
+            adopted_at: process.adopted_at,
+            last_failure: process
+                .run_history
+                .iter()
+                .rev()
+                .find_map(|run| run.failure.clone()),
+            lazy_start: self.lazy_listeners.status(&id).await,
+            restart: process
+                .info
+                .restart_policy
+                .as_ref()
+                .map(|policy| process.restart.status(policy)),
+        })
+    }
+
+    /// コンテナのイメージをビルドする（ビルドの設定がない場合は何もしない）
+    ///
+    /// ビルドは時間がかかるため、プロセスのロックを保持せずに行う。
+    async fn build_container_image(
+        &self,
+        id: &str,
+        process_arc: &Arc<RwLock<ManagedProcess>>,
+    ) -> Result<(), String> {
+        let (config, build, cwd, stdout_buffer, stderr_buffer) = {
+            let process = process_arc.read().await;
+            let Some(config) = process.info.container.clone() else {
+                return Ok(());
+            };
+            let Some(build) = config.build.clone() else {
+                return Ok(());
+            };
+            (
+                config,
+                build,
+                process.info.cwd.clone(),
+                process.stdout_buffer.clone(),
+                process.stderr_buffer.clone(),
+            )
+        };
+
+        info!("Building image '{}' for process '{}'", config.image, id);
+        let status = container::build(
+            &config,
+            &build,
+            cwd.as_deref(),
+            &stdout_buffer,
+            &stderr_buffer,
+        )
+        .await?;
+        info!(
+            "Built image '{}' for process '{}' in {}ms (success: {}, cache hit: {}, cached steps: {})",
+            status.image,
+            id,
+            status.duration_ms,
+            status.success,
+            status.cache_hit,
+            status.cached_steps
+        );
+        let result = if status.success {
+            Ok(())
+        } else {
+            Err(format!(
+                "Image build for '{id}' failed with exit code {:?}; see the process output for the build log",
+                status.exit_code
+            ))
+        };
+        process_arc.write().await.image_build = Some(status);
+        result
+    }
+
+    /// 出力のサンプリングを切り替える（Noneで無効化）
+    ///
+    /// 設定は実行中のプロセスにもすぐに反映され、再起動後も維持される（サーバーの再起動では失われる）。
+    pub async fn set_output_sampling(
+        &self,
+        id: String,
+        sampling: Option<OutputSampling>,
+    ) -> Result<Option<SamplingStatus>, String> {
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let process = process_arc.read().await;
+
+        let Some(config) = sampling else {
+            process.stdout_buffer.set_sampler(None);
+            process.stderr_buffer.set_sampler(None);
+            tracing::info!("Disabled output sampling for process '{}'", id);
+            return Ok(None);
+        };
+        if process.info.repl.is_some() {
+            // 間引くとREPLの完了マーカーを取りこぼす
+            return Err(format!(
+                "Output sampling is not supported for REPL process '{id}'"
+            ));
+        }
+        let sampler = Sampler::new(config.clone())?;
+        process.stdout_buffer.set_sampler(Some(sampler.clone()));
+        process.stderr_buffer.set_sampler(Some(sampler));
+        tracing::info!(
+            "Enabled output sampling for process '{}' (every {} lines)",
+            id,
+            config.every_n
+        );
+        Ok(process.stdout_buffer.sampling_status())
+    }
+
+    /// REPLプロセスにコードを送って実行し、その実行の出力を返す
+    ///
+    /// タイムアウトした場合はそれまでの出力を `completed: false` として返す。
+    pub async fn execute_in_repl(
+        &self,
+        id: String,
+        code: String,
+        timeout: Duration,
+    ) -> Result<ReplExecution, String> {
+        let (config, session, stdout_buffer, stderr_buffer, recorder) = {
+            let processes = self.processes.read().await;
+            let process_arc = processes
+                .get(&id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?;
+            let process = process_arc.read().await;
+            let config = process
+                .info
+                .repl
+                .clone()
+                .ok_or_else(|| format!("Process '{id}' is not a REPL process"))?;
+            let session = match (&process.info.state, &process.repl_session) {
+                (ProcessState::Running { .. }, Some(session)) => session.clone(),
+                (ProcessState::Paused { .. }, _) => {
+                    return Err(format!("Process '{id}' is paused"));
+                }
+                _ => return Err(format!("Process '{id}' is not running")),
+            };
+            (
+                config,
+                session,
+                process.stdout_buffer.clone(),
+                process.stderr_buffer.clone(),
+                process.recorder.clone(),
+            )
+        };
+
+        if let Some(recorder) = recorder {
+            recorder.record_stdin(&code);
+        }
+        let result = session
+            .execute(&config, &code, &stdout_buffer, &stderr_buffer, timeout)
+            .await?;
+        debug!(
+            "REPL '{}' execution #{} finished in {}ms (completed: {})",
+            id, result.execution, result.duration_ms, result.completed
+        );
+        Ok(result)
+    }
+
+    /// 実行中のプロセスの標準入力に送る
+    ///
+    /// `close` なら書き込んだ後に標準入力を閉じ、プロセスにEOFを送る。書き込んだバイト数を返す。
+    pub async fn send_process_input(
+        &self,
+        id: String,
+        input: Vec<u8>,
+        close: bool,
+    ) -> Result<usize, String> {
+        let (stdin, recorder) = {
+            let processes = self.processes.read().await;
+            let process_arc = processes
+                .get(&id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?;
+            let process = process_arc.read().await;
+            if process.info.repl.is_some() {
+                return Err(format!(
+                    "Process '{id}' is a REPL process; use execute_in_repl instead"
+                ));
+            }
+            if !process.info.keep_stdin_open {
+                return Err(format!(
+                    "Process '{id}' does not keep its stdin open; create it with keep_stdin_open"
+                ));
+            }
+            let stdin = match (&process.info.state, &process.process_stdin) {
+                (ProcessState::Running { .. }, Some(stdin)) => stdin.clone(),
+                (ProcessState::Paused { .. }, _) => {
+                    return Err(format!("Process '{id}' is paused"));
+                }
+                (ProcessState::Running { .. }, None) => {
+                    return Err(format!(
+                        "Process '{id}' has no stdin pipe; restart it to open one"
+                    ));
+                }
+                _ => return Err(format!("Process '{id}' is not running")),
+            };
+            (stdin, process.recorder.clone())
+        };
+
+        stdin
+            .send(&input, close)
+            .await
+            .map_err(|e| format!("Failed to write to the stdin of '{id}': {e}"))?;
+        // プロセスに届いた入力だけを記録する
+        if let Some(recorder) = recorder {
+            recorder.record_stdin_bytes(&input);
+        }
+        debug!(
+            "Sent {} bytes to the stdin of '{}'{}",
+            input.len(),
+            id,
+            if close { " and closed it" } else { "" }
+        );
+        Ok(input.len())
+    }
+
+    /// カオステストが設定で有効になっているか確認
+    async fn ensure_chaos_enabled(&self) -> Result<(), String> {
+        if self.get_settings().await?.chaos.enabled {
+            Ok(())
+        } else {
+            Err(
+                "Chaos mode is disabled; enable it in the settings (chaos.enabled) first"
+                    .to_string(),
+            )
+        }
+    }
+
+    /// 障害注入の対象を選ぶ（実行中のプロセスのみ、IDの指定がなければランダム）
+    async fn pick_chaos_target(
+        &self,
+        target: &ChaosTarget,
+    ) -> Result<(String, u32, Arc<RwLock<ManagedProcess>>), String> {
+        let processes = self.processes.read().await;
+        let mut candidates = Vec::new();
+        for (id, process_arc) in processes.iter() {
+            if target.process_id.as_ref().is_some_and(|want| want != id) {
+                continue;
+            }
+            let process = process_arc.read().await;
+            if target
+                .tag
+                .as_ref()
+                .is_some_and(|tag| !process.info.tags.contains(tag))
+            {
+                continue;
+            }
+            // PIDが再利用されたプロセスは対象にしない
+            if let ProcessState::Running { pid, started_at } = process.info.state
+                && reaper::verify(pid, started_at) == PidStatus::Alive
+            {
+                candidates.push((id.clone(), pid, process_arc.clone()));
+            }
+        }
+        candidates.sort_by(|a, b| a.0.cmp(&b.0));
+
+        chaos::pick_random(&candidates).cloned().ok_or_else(|| {
+            match (&target.process_id, &target.tag) {
+                (Some(id), _) => format!("Process '{id}' is not running or does not match"),
+                (None, Some(tag)) => format!("No running process with tag '{tag}'"),
+                (None, None) => "No running process to target".to_string(),
+            }
+        })
+    }
+
+    /// カオス操作を履歴とイベントに記録
+    async fn record_chaos(
+        &self,
+        process_id: &str,
+        action: ChaosAction,
+        schedule_id: Option<&str>,
+    ) -> ChaosRecord {
+        warn!("[chaos] {} process '{}'", action.name(), process_id);
+        let record = ChaosRecord {
+            at: Utc::now(),
+            process_id: process_id.to_string(),
+            action,
+            schedule_id: schedule_id.map(str::to_string),
+        };
+        if let Err(e) = self.event_system.emit_chaos_action(&record).await {
+            debug!("Failed to emit chaos event: {}", e);
+        }
+        self.chaos.record(record.clone()).await;
+        record
+    }
+
+    /// プロセスをSIGKILLで強制終了し、指定があれば一定時間後に再起動する
+    ///
+    /// 停止要求を伴わない終了として扱うため、異常終了として記録され自動再起動の対象になる。
+    pub async fn chaos_kill(
+        &self,
+        target: ChaosTarget,
+        restart_after: Option<Duration>,
+    ) -> Result<ChaosRecord, String> {
+        self.ensure_chaos_enabled().await?;
+        let (id, pid, process_arc) = self.pick_chaos_target(&target).await?;
+
+        if process_arc
+            .write()
+            .await
+            .chaos_paused_until
+            .take()
+            .is_some()
+        {
+            let _ = chaos::send_signal(pid, ChaosSignal::Cont);
+        }
+        chaos::send_signal(pid, ChaosSignal::Kill)?;
+        let action = ChaosAction::Kill {
+            pid,
+            restart_after_secs: restart_after.map(|d| d.as_secs()),
+        };
+        let record = self.record_chaos(&id, action, None).await;
+
+        if let Some(delay) = restart_after {
+            let manager = self.clone();
+            let id = id.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                // 終了の反映を待ってから起動する（自動再起動で既に起動していれば何もしない）
+                let stopped = match manager.get_process_status(id.clone()).await {
+                    Ok(status) => !matches!(
+                        status.info.state,
+                        ProcessState::Running { .. } | ProcessState::Paused { .. }
+                    ),
+                    Err(_) => false,
+                };
+                if stopped {
+                    match manager.start_process(id.clone()).await {
+                        Ok(_) => {
+                            manager.record_chaos(&id, ChaosAction::Restart, None).await;
+                        }
+                        Err(e) => warn!("[chaos] Failed to restart process '{}': {}", id, e),
+                    }
+                }
+            });
+        }
+
+        Ok(record)
+    }
+
+    /// プロセスをSIGSTOPで一時停止し、指定時間後にSIGCONTで再開する
+    ///
+    /// 再開は途中でカオスモードが無効になっても必ず行う。
+    pub async fn chaos_pause(
+        &self,
+        target: ChaosTarget,
+        duration: Duration,
+    ) -> Result<ChaosRecord, String> {
+        self.ensure_chaos_enabled().await?;
+        self.pause_target(&target, duration, None).await
+    }
+
+    async fn pause_target(
+        &self,
+        target: &ChaosTarget,
+        duration: Duration,
+        schedule_id: Option<&str>,
+    ) -> Result<ChaosRecord, String> {
+        let (id, pid, process_arc) = self.pick_chaos_target(target).await?;
+        {
+            let mut process = process_arc.write().await;
+            if process.chaos_paused_until.is_some() {
+                return Err(format!("Process '{id}' is already paused"));
+            }
+            chaos::send_signal(pid, ChaosSignal::Stop)?;
+            process.chaos_paused_until =
+                Some(Utc::now() + chrono::Duration::from_std(duration).unwrap_or_default());
+        }
+        let action = ChaosAction::Pause {
+            pid,
+            duration_secs: duration.as_secs(),
+        };
+        let record = self.record_chaos(&id, action, schedule_id).await;
+
+        let manager = self.clone();
+        let resume_id = id.clone();
+        let schedule = schedule_id.map(str::to_string);
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            // 停止や強制終了で既に解除されていれば何もしない
+            if process_arc
+                .write()
+                .await
+                .chaos_paused_until
+                .take()
+                .is_none()
+            {
+                return;
+            }
+            if let Err(e) = chaos::send_signal(pid, ChaosSignal::Cont) {
+                warn!("[chaos] {}", e);
+                return;
+            }
+            manager
+                .record_chaos(&resume_id, ChaosAction::Resume { pid }, schedule.as_deref())
+                .await;
+        });
+
+        Ok(record)
+    }
+
+    /// 一定間隔で対象をランダムに選んで一時停止するスケジュールを開始
+    pub async fn chaos_schedule_pauses(
+        &self,
+        target: ChaosTarget,
+        interval: Duration,
+        pause: Duration,
+    ) -> Result<ChaosSchedule, String> {
+        self.ensure_chaos_enabled().await?;
+        if interval.is_zero() {
+            return Err("interval must be greater than zero".to_string());
+        }
+        if pause >= interval {
+            return Err("pause duration must be shorter than the interval".to_string());
+        }
+
+        let schedule = ChaosSchedule {
+            schedule_id: format!("chaos-{}", Utc::now().timestamp_millis()),
+            process_id: target.process_id.clone(),
+            tag: target.tag.clone(),
+            interval_secs: interval.as_secs(),
+            pause_secs: pause.as_secs(),
+            started_at: Utc::now(),
+        };
+
+        let manager = self.clone();
+        let schedule_id = schedule.schedule_id.clone();
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                // 無効化された後は何もしない（スケジュール自体は取り消すまで残る）
+                if manager.ensure_chaos_enabled().await.is_err() {
+                    continue;
+                }
+                if let Err(e) = manager
+                    .pause_target(&target, pause, Some(&schedule_id))
+                    .await
+                {
+                    debug!("[chaos] Scheduled pause skipped: {}", e);
+                }
+            }
+        });
+        self.chaos.add_schedule(schedule.clone(), handle).await;
+        info!("[chaos] Started pause schedule '{}'", schedule.schedule_id);
+        Ok(schedule)
+    }
+
+    /// 一時停止のスケジュールを取り消す
+    pub async fn chaos_cancel_schedule(&self, schedule_id: &str) -> Result<(), String> {
+        self.chaos.cancel_schedule(schedule_id).await?;
+        info!("[chaos] Cancelled pause schedule '{}'", schedule_id);
+        Ok(())
+    }
+
+    /// カオステストの設定・スケジュール・操作履歴
+    pub async fn chaos_status(&self) -> Result<ChaosStatus, String> {
+        Ok(ChaosStatus {
+            enabled: self.get_settings().await?.chaos.enabled,
+            schedules: self.chaos.schedules().await,
+            recent_actions: self.chaos.recent().await,
+        })
+    }
+
+    /// 出力のバッファ（プロセスになければKubernetesのワークロードを探す）
+    async fn output_buffers(&self, id: &str) -> Result<(CircularBuffer, CircularBuffer), String> {
+        if let Some(process_arc) = self.processes.read().await.get(id).cloned() {
+            let process = process_arc.read().await;
+            return Ok((process.stdout_buffer.clone(), process.stderr_buffer.clone()));
+        }
+        self.kubernetes
+            .output_buffers(id)
+            .await
+            .ok_or_else(|| format!("Process '{id}' not found"))
+    }
+
+    /// すべてのプロセスのCPU時間と、見積もった消費電力量・電気代
+    ///
+    /// サーバーの起動後に一度も実行されていないプロセスは含めない。
+    pub async fn get_resource_overview(&self) -> Result<ResourceOverview, String> {
+        let coefficients = self.get_settings().await?.energy_cost;
+        let processes: Vec<Arc<RwLock<ManagedProcess>>> =
+            self.processes.read().await.values().cloned().collect();
+        let mut usages = Vec::new();
+        for process_arc in processes {
+            let process = process_arc.read().await;
+            if let Some(usage) = process.cpu_accounting.usage(&coefficients) {
+                usages.push(ProcessResourceUsage {
+                    process_id: process.info.id.clone(),
+                    running: matches!(
+                        process.info.state,
+                        ProcessState::Running { .. } | ProcessState::Paused { .. }
+                    ),
+                    usage,
+                });
+            }
+        }
+        Ok(ResourceOverview::new(usages, coefficients))
+    }
+
+    /// プロセスのstderrを診断ルールに照らす
+    ///
+    /// 確認待ち・却下したルールは使わない。失敗したプロセスはエラーの内容も抜粋に含める。
+    pub async fn diagnose_process(&self, id: &str) -> Result<DiagnosisReport, String> {
+        let (_, stderr) = self.output_buffers(id).await?;
+        let mut lines = stderr.get_all().await;
+        if let Some(process_arc) = self.processes.read().await.get(id).cloned()
+            && let ProcessState::Failed { error, .. } = &process_arc.read().await.info.state
+        {
+            lines.push(error.clone());
+        }
+        let rules = self.list_diagnosis_rules().await?;
+        let mut report =
+            DiagnosisReport::new(id.to_string(), diagnosis::error_excerpt(&lines), &rules);
+        let ports = report.conflicting_ports();
+        if !ports.is_empty() {
+            let managed = self.managed_pids().await;
+            for port in ports {
+                report
+                    .port_owners
+                    .push(port_owner::find(port, &managed).await);
+            }
+        }
+        Ok(report)
+    }
+
+    /// 実行中の管理プロセスのPIDとID
+    async fn managed_pids(&self) -> HashMap<u32, String> {
+        let candidates: Vec<Arc<RwLock<ManagedProcess>>> =
+            self.processes.read().await.values().cloned().collect();
+        let mut pids = HashMap::new();
+        for process_arc in candidates {
+            let process = process_arc.read().await;
+            if let ProcessState::Running { pid, .. } | ProcessState::Paused { pid, .. } =
+                &process.info.state
+            {
+                pids.insert(*pid, process.info.id.clone());
+            }
+        }
+        pids
+    }
+
+    /// 組み込みと保存済みの診断ルール
+    pub async fn list_diagnosis_rules(&self) -> Result<Vec<DiagnosisRule>, String> {
+        let mut stored = self.persistence.load_all_diagnosis_rules().await?;
+        stored.sort_by_key(|rule| rule.created_at);
+        let mut rules = diagnosis::built_in_rules();
+        rules.extend(stored);
+        Ok(rules)
+    }
+
+    /// サンプリングで作った診断ルールの候補を確認待ちとして保存する
+    pub async fn add_diagnosis_candidate(&self, rule: &DiagnosisRule) -> Result<(), String> {
+        self.persistence.save_diagnosis_rule(rule).await?;
+        info!(
+            "Stored diagnosis rule candidate '{}' ({}) pending confirmation",
+            rule.rule_id, rule.category
+        );
+        Ok(())
+    }
+
+    /// 確認待ちの診断ルールを承認（以降の診断に使う）または却下する
+    pub async fn confirm_diagnosis_rule(
+        &self,
+        rule_id: &str,
+        accept: bool,
+    ) -> Result<DiagnosisRule, String> {
+        let mut rule = self
+            .persistence
+            .load_all_diagnosis_rules()
+            .await?
+            .into_iter()
+            .find(|rule| rule.rule_id == rule_id)
+            .ok_or_else(|| format!("Diagnosis rule '{rule_id}' not found or built in"))?;
+        if rule.status != DiagnosisRuleStatus::Pending {
+            return Err(format!(
+                "Diagnosis rule '{rule_id}' is not pending confirmation"
+            ));
+        }
+        rule.status = if accept {
+            DiagnosisRuleStatus::Active
+        } else {
+            DiagnosisRuleStatus::Rejected
+        };
+        self.persistence.save_diagnosis_rule(&rule).await?;
+        Ok(rule)
+    }
+
+    /// プロセスの出力を取得
+    pub async fn get_process_output(
+        &self,
+        id: String,
+        stream: OutputStream,
+        lines: Option<u32>,
+    ) -> Result<Vec<String>, String> {
+        let (stdout, stderr) = self.output_buffers(&id).await?;
+
+        let n = lines.unwrap_or(100) as usize;
+
+        let output = match stream {
+            OutputStream::Stdout => stdout.get_last_n(n).await,
+            OutputStream::Stderr => stderr.get_last_n(n).await,
+            OutputStream::Both => buffer::interleave(&stdout, &stderr, Some(n))
+                .await
+                .into_iter()
+                .map(|l| l.text)
+                .collect(),
+        };
+
+        Ok(output)
+    }
+
+    /// プロセスの出力をタイムスタンプ等のメタデータ付きで取得
+    ///
+    /// `Both` の場合は stdout/stderr を合わせた最新の行を出力された順（連番の順）に並べて返す。
+    pub async fn get_process_output_lines(
+        &self,
+        id: String,
+        stream: OutputStream,
+        lines: Option<u32>,
+    ) -> Result<Vec<LogLine>, String> {
+        let (stdout, stderr) = self.output_buffers(&id).await?;
+
+        let n = lines.unwrap_or(100) as usize;
+
+        let output = match stream {
+            OutputStream::Stdout => stdout.get_last_n_lines(n).await,
+            OutputStream::Stderr => stderr.get_last_n_lines(n).await,
+            OutputStream::Both => buffer::interleave(&stdout, &stderr, Some(n)).await,
+        };
+
+        Ok(output)
+    }
+
+    /// カーソル（行の連番）または時刻より後の出力を、古い順に最大 `lines` 行取得する
+    ///
+    /// どちらも指定しなければ最新の `lines` 行を返す。返したカーソルを次に渡すと、
+    /// 同じ行を読み直さずに続きだけを取得できる。
+    pub async fn follow_process_output(
+        &self,
+        id: &str,
+        stream: OutputStream,
+        cursor: Option<u64>,
+        since: Option<DateTime<Utc>>,
+        lines: Option<u32>,
+        timestamps: bool,
+    ) -> Result<FollowedOutput, String> {
+        let followed = self
+            .follow_process_lines(id, stream, cursor, since, lines)
+            .await?;
+        Ok(FollowedOutput {
+            lines: followed
+                .lines
+                .iter()
+                .map(|l| {
+                    if timestamps {
+                        l.with_timestamp()
+                    } else {
+                        l.text.clone()
+                    }
+                })
+                .collect(),
+            cursor: followed.cursor,
+            more: followed.more,
+            truncated: followed.truncated,
+        })
+    }
+
+    /// `follow_process_output` と同じ行を、時刻・ストリーム・連番付きで取得する
+    pub async fn follow_process_lines(
+        &self,
+        id: &str,
+        stream: OutputStream,
+        cursor: Option<u64>,
+        since: Option<DateTime<Utc>>,
+        lines: Option<u32>,
+    ) -> Result<FollowedLines, String> {
+        let (stdout, stderr) = self.output_buffers(id).await?;
+        let buffers: Vec<&CircularBuffer> = match stream {
+            OutputStream::Stdout => vec![&stdout],
+            OutputStream::Stderr => vec![&stderr],
+            OutputStream::Both => vec![&stdout, &stderr],
+        };
+        let limit = lines.unwrap_or(100) as usize;
+        // 読む前の末尾（読んでいる間に増えた行は次の呼び出しで返す）
+        let head = buffers.iter().map(|b| b.last_seq()).max().unwrap_or(0);
+        let mut all = Vec::new();
+        let mut truncated = false;
+        for buffer in &buffers {
+            match (cursor, since) {
+                (Some(cursor), _) => {
+                    all.extend(buffer.lines_after(cursor, limit + 1).await);
+                    truncated |= buffer.evicted_after(cursor);
+                }
+                (None, Some(since)) => {
+                    all.extend(buffer.lines_since(since, limit + 1).await);
+                    truncated |= buffer
+                        .retained_since()
+                        .await
+                        .is_some_and(|retained| retained > since);
+                }
+                (None, None) => all.extend(buffer.get_last_n_lines(limit).await),
+            }
+        }
+        all.sort_by_key(|l| l.seq);
+        let more = if cursor.is_none() && since.is_none() {
+            let excess = all.len().saturating_sub(limit);
+            all.drain(..excess);
+            false
+        } else {
+            let more = all.len() > limit;
+            all.truncate(limit);
+            more
+        };
+        let last = all.last().map_or(0, |l| l.seq);
+        Ok(FollowedLines {
+            cursor: if more {
+                last
+            } else {
+                last.max(head).max(cursor.unwrap_or(0))
+            },
+            lines: all,
+            more,
+            truncated,
+        })
+    }
+
+    /// 出力の現在の末尾にブックマークを付ける（同じ名前があれば付け直す）
+    pub async fn add_output_bookmark(
+        &self,
+        id: &str,
+        name: String,
+        note: Option<String>,
+    ) -> Result<OutputBookmark, String> {
+        bookmarks::validate_name(&name)?;
+        let processes = self.processes.read().await;
+        let mut process = processes
+            .get(id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?
+            .write()
+            .await;
+        let bookmark = OutputBookmark {
+            name,
+            created_at: Utc::now(),
+            note,
+            stdout_lines: process.stdout_buffer.len().await,
+            stderr_lines: process.stderr_buffer.len().await,
+            instant: std::time::Instant::now(),
+        };
+        process.bookmarks.retain(|b| b.name != bookmark.name);
+        if process.bookmarks.len() >= bookmarks::MAX_BOOKMARKS {
+            process.bookmarks.remove(0);
+        }
+        process.bookmarks.push(bookmark.clone());
+        debug!("Bookmarked output of '{}' as '{}'", id, bookmark.name);
+        Ok(bookmark)
+    }
+
+    /// 出力に付けたブックマーク（古い順）
+    pub async fn list_output_bookmarks(&self, id: &str) -> Result<Vec<OutputBookmark>, String> {
+        let processes = self.processes.read().await;
+        let process = processes
+            .get(id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?
+            .read()
+            .await;
+        Ok(process.bookmarks.clone())
+    }
+
+    /// ブックマークを削除
+    pub async fn remove_output_bookmark(&self, id: &str, name: &str) -> Result<(), String> {
+        let processes = self.processes.read().await;
+        let mut process = processes
+            .get(id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?
+            .write()
+            .await;
+        let before = process.bookmarks.len();
+        process.bookmarks.retain(|b| b.name != name);
+        if process.bookmarks.len() == before {
+            return Err(format!("Bookmark '{name}' not found for process '{id}'"));
+        }
+        Ok(())
+    }
+
+    /// ブックマークより後・前、または2つのブックマークの間の出力を取得
+    ///
+    /// `from` があれば範囲の先頭から、`to` だけなら末尾から `lines` 行（既定100行）を返す。
+    pub async fn get_output_between_bookmarks(
+        &self,
+        id: &str,
+        stream: OutputStream,
+        from: Option<&str>,
+        to: Option<&str>,
+        lines: Option<u32>,
+        timestamps: bool,
+    ) -> Result<BookmarkedOutput, String> {
+        let (saved, stdout, stderr) = {
+            let processes = self.processes.read().await;
+            let process = processes
+                .get(id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?
+                .read()
+                .await;
+            (
+                process.bookmarks.clone(),
+                process.stdout_buffer.clone(),
+                process.stderr_buffer.clone(),
+            )
+        };
+        let find = |name: Option<&str>| -> Result<Option<OutputBookmark>, String> {
+            name.map(|name| {
+                saved
+                    .iter()
+                    .find(|b| b.name == name)
+                    .cloned()
+                    .ok_or_else(|| format!("Bookmark '{name}' not found for process '{id}'"))
+            })
+            .transpose()
+        };
+        let (from, to) = (find(from)?, find(to)?);
+        if let (Some(from), Some(to)) = (&from, &to)
+            && from.instant > to.instant
+        {
+            return Err(format!(
+                "Bookmark '{}' was set after '{}'; swap from and to",
+                from.name, to.name
+            ));
+        }
+
+        let buffers: Vec<&CircularBuffer> = match stream {
+            OutputStream::Stdout => vec![&stdout],
+            OutputStream::Stderr => vec![&stderr],
+            OutputStream::Both => vec![&stdout, &stderr],
+        };
+        let mut all = Vec::new();
+        let mut truncated = false;
+        for buffer in buffers {
+            all.extend(buffer.get_all_lines().await);
+            if let (Some(from), Some(since)) = (&from, buffer.retained_since().await) {
+                truncated |= since > from.created_at;
+            }
+        }
+        all.sort_by_key(|l| l.seq);
+        let limit = lines.unwrap_or(100) as usize;
+        let (selected, more) = bookmarks::select(all, from.as_ref(), to.as_ref(), limit);
+        Ok(BookmarkedOutput {
+            from,
+            to,
+            lines: selected
+                .iter()
+                .map(|l| {
+                    if timestamps {
+                        l.with_timestamp()
+                    } else {
+                        l.text.clone()
+                    }
+                })
+                .collect(),
+            more,
+            truncated,
+        })
+    }
+
+    /// マーカーに一致した行の間のフェーズ所要時間を計算
+    pub async fn get_process_phases(
+        &self,
+        id: String,
+        markers: Vec<String>,
+    ) -> Result<Vec<PhaseDuration>, String> {
+        if markers.is_empty() {
+            return Err("At least one marker pattern is required".to_string());
+        }
+        let markers = compile_markers(&markers)?;
+
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+
+        let process = process_arc.read().await;
+        let lines = buffer::interleave(&process.stdout_buffer, &process.stderr_buffer, None).await;
+
+        Ok(compute_phase_durations(&lines, &markers))
+    }
+
+    /// プロセス（と子孫）のネットワーク接続を取得
+    ///
+    /// `sample_ms` を指定すると、その期間のI/Oレートも計測する。
+    pub async fn get_process_network(
+        &self,
+        id: String,
+        sample_ms: Option<u64>,
+    ) -> Result<ProcessNetworkInfo, String> {
+        let pid = {
+            let processes = self.processes.read().await;
+            let process_arc = processes
+                .get(&id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?;
+            let process = process_arc.read().await;
+            match process.info.state {
+                ProcessState::Running { pid, .. } | ProcessState::Paused { pid, .. } => pid,
+                _ => return Err(format!("Process '{id}' is not running")),
+            }
+        };
+
+        super::network::inspect(id, pid, sample_ms).await
+    }
+
+    /// プロセスの実行時の状態からテンプレートを作成して保存
+    ///
+    /// 実行中であれば実行時の環境変数と待ち受けポートも取り込む。
+    /// シークレットは値を残さず必須の変数に置き換える。
+    pub async fn capture_template(
+        &self,
+        id: String,
+        template_id: Option<String>,
+        name: Option<String>,
+    ) -> Result<CapturedTemplate, String> {
+        let info = {
+            let processes = self.processes.read().await;
+            let process_arc = processes
+                .get(&id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?;
+            process_arc.read().await.info.clone()
+        };
+
+        let mut env = info.env.clone();
+        let mut ports = Vec::new();
+        if let ProcessState::Running { pid, .. } | ProcessState::Paused { pid, .. } = info.state {
+            env.extend(Self::runtime_environment(pid).await);
+            match super::network::inspect(id.clone(), pid, None).await {
+                Ok(network) => {
+                    ports = network
+                        .connections
+                        .iter()
+                        .filter(|c| c.state.as_deref() == Some("LISTEN"))
+                        .filter_map(|c| c.local_address.rsplit_once(':')?.1.parse().ok())
+                        .collect();
+                }
+                Err(e) => debug!("Skipping port detection for '{}': {}", id, e),
+            }
+        }
+
+        let dotenv_files = match &info.cwd {
+            Some(cwd) => env_docs::read_dotenv_files(cwd).await,
+            None => Vec::new(),
+        };
+        let source = CaptureSource {
+            command: info.command,
+            args: info.args,
+            env,
+            cwd: info.cwd.map(|c| c.to_string_lossy().to_string()),
+            ports,
+            dotenv_files,
+        };
+        let captured = capture::build_template(
+            template_id.unwrap_or_else(|| id.clone()),
+            name.unwrap_or_else(|| id.clone()),
+            source,
+        );
+        self.save_template(captured.template.clone()).await?;
+        info!(
+            "Captured template '{}' from process '{}' ({} secret(s), {} port(s), {} env variable(s))",
+            captured.template.template_id,
+            id,
+            captured.secrets.len(),
+            captured.ports.len(),
+            captured.env_variables.len()
+        );
+        Ok(captured)
+    }
+
+    /// VS Codeの `tasks.json` からプロセス（またはテンプレート）を取り込む
+    ///
+    /// 取り込み元（ファイルとラベル）を記録しておき、再取り込み時は同じタスクから
+    /// 作ったエントリを更新する。実行中のプロセスへの変更は次回の起動から反映される。
+    pub async fn import_vscode_tasks(
+        &self,
+        path: &std::path::Path,
+        as_templates: bool,
+        labels: Option<Vec<String>>,
+    ) -> Result<TaskImportReport, String> {
+        let path = paths::resolve_file_path(&path.to_string_lossy())?;
+        let path = std::fs::canonicalize(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let contents = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let mut parsed =
+            vscode_tasks::parse_tasks(&contents, &vscode_tasks::workspace_folder(&path))?;
+        if let Some(labels) = &labels {
+            parsed.tasks.retain(|t| labels.contains(&t.label));
+            parsed.skipped.retain(|t| labels.contains(&t.label));
+        }
+
+        let source_path = path.to_string_lossy().to_string();
+        let fence = if as_templates {
+            None
+        } else {
+            let fence = self
+                .fence
+                .begin("import_vscode_tasks", Some(source_path.clone()))
+                .await?;
+            self.take_pre_op_snapshot("import_vscode_tasks", Some(source_path.clone()))
+                .await?;
+            Some(fence)
+        };
+        let mut report = TaskImportReport {
+            skipped: parsed.skipped,
+            ..Default::default()
+        };
+        let total = parsed.tasks.len();
+        for (index, task) in parsed.tasks.into_iter().enumerate() {
+            if let Some(fence) = &fence {
+                fence.progress("Importing tasks", index, Some(total));
+            }
+            let source = ImportSource {
+                kind: vscode_tasks::SOURCE_KIND.to_string(),
+                path: source_path.clone(),
+                key: task.label.clone(),
+            };
+            let label = task.label.clone();
+            let result = if as_templates {
+                let description = format!("Imported from VS Code task '{}'", task.label);
+                self.import_task_as_template(task, source, description, "vscode-task")
+                    .await
+            } else {
+                self.import_task_as_process(task, source).await
+            };
+            match result {
+                Ok((id, true)) => report.created.push(id),
+                Ok((id, false)) => report.updated.push(id),
+                Err(reason) => report
+                    .skipped
+                    .push(vscode_tasks::SkippedTask { label, reason }),
+            }
+        }
+
+        info!(
+            "Imported {} from {}: {} created, {} updated, {} skipped",
+            if as_templates {
+                "templates"
+            } else {
+                "processes"
+            },
+            source_path,
+            report.created.len(),
+            report.updated.len(),
+            report.skipped.len()
+        );
+        Ok(report)
+    }
+
+    /// ワークスペースのREADME・Makefile・package.jsonからプロセスの候補を検出する
+    pub async fn discover_tasks(&self, path: &std::path::Path) -> Result<DiscoveryReport, String> {
+        let path = paths::expand_path(path)?;
+        let workspace = std::fs::canonicalize(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        if !workspace.is_dir() {
+            return Err(format!("{} is not a directory", workspace.display()));
+        }
+        let mut report = tokio::task::spawn_blocking(move || discovery::discover(&workspace))
+            .await
+            .map_err(|e| format!("Task discovery failed: {e}"))?;
+
+        let registered: Vec<(String, Vec<String>, Option<PathBuf>)> = {
+            let processes = self.processes.read().await;
+            let mut registered = Vec::with_capacity(processes.len());
+            for process_arc in processes.values() {
+                let process = process_arc.read().await;
+                registered.push((
+                    process.info.command.clone(),
+                    process.info.args.clone(),
+                    process.info.cwd.clone(),
+                ));
+            }
+            registered
+        };
+        for candidate in &mut report.candidates {
+            let cwd = PathBuf::from(&candidate.cwd);
+            candidate.already_registered = registered.iter().any(|(command, args, process_cwd)| {
+                *command == candidate.command
+                    && *args == candidate.args
+                    && process_cwd.as_ref() == Some(&cwd)
+            });
+        }
+        Ok(report)
+    }
+
+    /// 検出した候補をプロセス（またはテンプレート）として取り込む
+    ///
+    /// `ids` を省略した場合は、まだ登録されていないすべての候補を取り込む。
+    /// VS Codeのタスクと同様に取り込み元を記録し、再取り込み時は同じ候補から作ったエントリを更新する。
+    pub async fn import_discovered_tasks(
+        &self,
+        path: &std::path::Path,
+        as_templates: bool,
+        ids: Option<Vec<String>>,
+    ) -> Result<TaskImportReport, String> {
+        let report = self.discover_tasks(path).await?;
+        let mut import = TaskImportReport::default();
+        for candidate in report.candidates {
+            let selected = match &ids {
+                Some(ids) => ids.contains(&candidate.id),
+                None => !candidate.already_registered,
+            };
+            if !selected {
+                continue;
+            }
+            let source = ImportSource {
+                kind: discovery::SOURCE_KIND.to_string(),
+                path: report.workspace.clone(),
+                key: candidate.source.clone(),
+            };
+            let task = ImportedTask {
+                label: candidate.id.clone(),
+                id: candidate.id.clone(),
+                command: candidate.command,
+                args: candidate.args,
+                env: HashMap::new(),
+                cwd: Some(candidate.cwd),
+            };
+            let result = if as_templates {
+                self.import_task_as_template(task, source, candidate.description, "discovered")
+                    .await
+            } else {
+                self.import_task_as_process(task, source).await
+            };
+            match result {
+                Ok((id, true)) => import.created.push(id),
+                Ok((id, false)) => import.updated.push(id),
+                Err(reason) => import.skipped.push(vscode_tasks::SkippedTask {
+                    label: candidate.id,
+                    reason,
+                }),
+            }
+        }
+
+        info!(
+            "Imported discovered tasks from {}: {} created, {} updated, {} skipped",
+            report.workspace,
+            import.created.len(),
+            import.updated.len(),
+            import.skipped.len()
+        );
+        Ok(import)
+    }
+
+    /// タスクをプロセスとして取り込む（戻り値は (プロセスID, 新規作成したか)）
+    async fn import_task_as_process(
+        &self,
+        task: ImportedTask,
+        source: ImportSource,
+    ) -> Result<(String, bool), String> {
+        let cwd = task.cwd.map(PathBuf::from);
+        crate::security::validate_process_inputs(&task.command, &task.args, &task.env, &cwd)?;
+
+        let (existing, taken) = {
+            let processes = self.processes.read().await;
+            let mut existing = None;
+            for process_arc in processes.values() {
+                if process_arc.read().await.info.source.as_ref() == Some(&source) {
+                    existing = Some(process_arc.clone());
+                    break;
+                }
+            }
+            let taken: Vec<String> = processes.keys().cloned().collect();
+            (existing, taken)
+        };
+
+        if let Some(process_arc) = existing {
+            let mut process = process_arc.write().await;
+            process.info.command = task.command;
+            process.info.args = task.args;
+            process.info.env = task.env;
+            process.info.cwd = cwd;
+            let db_info = Self::to_db_process_info(&process.info);
+            self.persistence
+                .update_process(&db_info)
+                .await
+                .map_err(|e| format!("Failed to persist process update: {e}"))?;
+            return Ok((process.info.id.clone(), false));
+        }
+
+        let id = vscode_tasks::unique_id(&task.id, &taken);
+        self.create_process_with_options(
+            id.clone(),
+            task.command,
+            task.args,
+            task.env,
+            cwd,
+            ProcessOptions {
+                source: Some(source),
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok((id, true))
+    }
+
+    /// タスクをテンプレートとして取り込む（戻り値は (テンプレートID, 新規作成したか)）
+    async fn import_task_as_template(
+        &self,
+        task: ImportedTask,
+        source: ImportSource,
+        description: String,
+        tag: &str,
+    ) -> Result<(String, bool), String> {
+        let templates = self.persistence.list_templates().await?;
+        let (mut template, created) = match templates
+            .iter()
+            .find(|t| t.source.as_ref() == Some(&source))
+        {
+            Some(existing) => (existing.clone(), false),
+            None => {
+                let taken: Vec<String> = templates.iter().map(|t| t.template_id.clone()).collect();
+                let mut template = ProcessTemplate::new(task.label.clone(), String::new());
+                template.template_id = vscode_tasks::unique_id(&task.id, &taken);
+                template.description = Some(description);
+                template.tags = vec![tag.to_string()];
+                template.source = Some(source);
+                (template, true)
+            }
+        };
+        template.command = task.command;
+        template.args = task.args;
+        template.env = task.env;
+        template.default_cwd = task.cwd;
+        let dotenv_files = match &template.default_cwd {
+            Some(cwd) => env_docs::read_dotenv_files(Path::new(cwd)).await,
+            None => Vec::new(),
+        };
+        env_docs::document(&mut template, &dotenv_files);
+        template.updated_at = Utc::now();
+        let template_id = template.template_id.clone();
+        self.save_template(template).await?;
+        Ok((template_id, created))
+    }
+
+    /// 実行中プロセスの環境変数のうち、サーバー自身の環境と異なるもの
+    async fn runtime_environment(pid: u32) -> HashMap<String, String> {
+        tokio::task::spawn_blocking(move || {
+            let mut system = System::new();
+            let pid = Pid::from_u32(pid);
+            system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&[pid]),
+                true,
+                ProcessRefreshKind::nothing().with_environ(sysinfo::UpdateKind::Always),
+            );
+            let Some(process) = system.process(pid) else {
+                return HashMap::new();
+            };
+            process
+                .environ()
+                .iter()
+                .filter_map(|entry| {
+                    let (key, value) = entry.to_str()?.split_once('=')?;
+                    (std::env::var(key).ok().as_deref() != Some(value))
+                        .then(|| (key.to_string(), value.to_string()))
+                })
+                .collect()
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    /// すべてのプロセスをリスト
+    pub async fn list_processes(&self, filter: Option<ProcessFilter>) -> Vec<ProcessInfo> {
+        let processes = self.processes.read().await;
+        let mut result = Vec::new();
+
+        for process_arc in processes.values() {
+            let process = process_arc.read().await;
+            let info = &process.info;
+
+            // フィルタリング
+            if let Some(ref f) = filter {
+                // 状態フィルタ
+                if let Some(ref state_filter) = f.state {
+                    let matches = match state_filter {
+                        ProcessStateFilter::Running => {
+                            matches!(info.state, ProcessState::Running { .. })
+                        }
+                        ProcessStateFilter::Paused => {
+                            matches!(info.state, ProcessState::Paused { .. })
+                        }
+                        ProcessStateFilter::Stopped => {
+                            matches!(info.state, ProcessState::Stopped { .. })
+                        }
+                        ProcessStateFilter::Failed => {
+                            matches!(info.state, ProcessState::Failed { .. })
+                        }
+                        ProcessStateFilter::All => true,
+                    };
+                    if !matches {
+                        continue;
+                    }
+                }
+
+                // 名前パターンフィルタ
+                if let Some(ref pattern) = f.name_pattern
+                    && !info.id.contains(pattern)
+                    && !info.command.contains(pattern)
+                {
+                    continue;
+                }
+            }
+
+            result.push(info.clone());
+        }
+
+        result
+    }
+
+    /// フィルター式も使ってプロセスをリスト
+    pub async fn list_processes_matching(
+        &self,
+        filter: Option<ProcessFilter>,
+        expression: Option<&str>,
+    ) -> Result<Vec<ProcessInfo>, String> {
+        let expr = expression
+            .map(filter_expr::parse_process_filter)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let mut processes = self.list_processes(filter).await;
+        if let Some(expr) = expr {
+            processes.retain(|info| expr.matches(info));
+        }
+        processes.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(processes)
+    }
+
+    /// 最近のイベントをフィルター式で絞り込む（新しい順、最大 `limit` 件）
+    pub fn get_events(
+        &self,
+        expression: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ProcessEvent>, String> {
+        let expr = expression
+            .map(filter_expr::parse_event_filter)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        Ok(self
+            .event_system
+            .recent_events(None)
+            .into_iter()
+            .rev()
+            .filter(|event| expr.as_ref().is_none_or(|expr| expr.matches(event)))
+            .take(limit)
+            .collect())
+    }
+
+    /// フィルター式に一致するプロセスのID（ID順）
+    async fn select_processes(&self, expr: &FilterExpr) -> Vec<ProcessInfo> {
+        let mut matched: Vec<ProcessInfo> = self
+            .list_processes(None)
+            .await
+            .into_iter()
+            .filter(|info| expr.matches(info))
+            .collect();
+        matched.sort_by(|a, b| a.id.cmp(&b.id));
+        matched
+    }
+
+    /// フィルター式に一致する停止中のプロセスをまとめて起動
+    pub async fn start_matching_processes(
+        &self,
+        expression: &str,
+        dry_run: bool,
+    ) -> Result<BulkOperationReport, String> {
+        let expr = filter_expr::parse_process_filter(expression).map_err(|e| e.to_string())?;
+        let matched = self.select_processes(&expr).await;
+        let mut report = BulkOperationReport {
+            matched: matched.iter().map(|info| info.id.clone()).collect(),
+            dry_run,
+            ..Default::default()
+        };
+        let mut to_start = Vec::new();
+        for info in matched {
+            if matches!(
+                info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            ) {
+                report.skipped.push(info.id);
+            } else if !dry_run {
+                to_start.push(info.id);
+            }
+        }
+        if !to_start.is_empty() {
+            let startup = self.start_in_order(to_start).await;
+            report.succeeded = startup.ready();
+            report.failed = startup
+                .failed()
+                .into_iter()
+                .map(|(id, error)| BulkOperationFailure { id, error })
+                .collect();
+            report.startup = Some(startup);
+        }
+        info!(
+            "Bulk start '{}': {} matched, {} started, {} failed",
+            expression,
+            report.matched.len(),
+            report.succeeded.len(),
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// フィルター式に一致する実行中のプロセスをまとめて停止
+    pub async fn stop_matching_processes(
+        &self,
+        expression: &str,
+        grace_period_ms: Option<u64>,
+        dry_run: bool,
+    ) -> Result<BulkOperationReport, String> {
+        let expr = filter_expr::parse_process_filter(expression).map_err(|e| e.to_string())?;
+        let matched = self.select_processes(&expr).await;
+        let mut report = BulkOperationReport {
+            matched: matched.iter().map(|info| info.id.clone()).collect(),
+            dry_run,
+            ..Default::default()
+        };
+        for info in matched {
+            if !matches!(
+                info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            ) {
+                report.skipped.push(info.id);
+            } else if !dry_run {
+                match self.stop_process(info.id.clone(), grace_period_ms).await {
+                    Ok(()) => report.succeeded.push(info.id),
+                    Err(error) => report
+                        .failed
+                        .push(BulkOperationFailure { id: info.id, error }),
+                }
+            }
+        }
+        info!(
+            "Bulk stop '{}': {} matched, {} stopped, {} failed",
+            expression,
+            report.matched.len(),
+            report.succeeded.len(),
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// フィルター式に一致するプロセスをまとめて削除（実行中のものは停止してから削除する）
+    pub async fn remove_matching_processes(
+        &self,
+        expression: &str,
+        dry_run: bool,
+    ) -> Result<BulkOperationReport, String> {
+        let expr = filter_expr::parse_process_filter(expression).map_err(|e| e.to_string())?;
+        let matched = self.select_processes(&expr).await;
+        let mut report = BulkOperationReport {
+            matched: matched.iter().map(|info| info.id.clone()).collect(),
+            dry_run,
+            ..Default::default()
+        };
+        if dry_run || matched.is_empty() {
+            return Ok(report);
+        }
+        let fence = self
+            .fence
+            .begin("remove_processes", Some(expression.to_string()))
+            .await?;
+        self.take_pre_op_snapshot("remove_processes", Some(expression.to_string()))
+            .await?;
+        let total = matched.len();
+        for (index, info) in matched.into_iter().enumerate() {
+            fence.progress("Removing processes", index, Some(total));
+            match self.remove_process(info.id.clone()).await {
+                Ok(()) => report.succeeded.push(info.id),
+                Err(error) => report
+                    .failed
+                    .push(BulkOperationFailure { id: info.id, error }),
+            }
+        }
+        info!(
+            "Bulk remove '{}': {} matched, {} removed, {} failed",
+            expression,
+            report.matched.len(),
+            report.succeeded.len(),
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// グループのプロセス（IDの順）
+    async fn group_members(&self, group: &str) -> Result<Vec<ProcessInfo>, String> {
+        let processes = self.list_processes(None).await;
+        let mut members: Vec<ProcessInfo> = processes
+            .iter()
+            .filter(|info| info.group.as_deref() == Some(group))
+            .cloned()
+            .collect();
+        if members.is_empty() {
+            let mut known: Vec<&str> = processes
+                .iter()
+                .filter_map(|info| info.group.as_deref())
+                .collect();
+            known.sort_unstable();
+            known.dedup();
+            return Err(if known.is_empty() {
+                format!("Group '{group}' not found (no process has a group)")
+            } else {
+                format!(
+                    "Group '{group}' not found (known groups: {})",
+                    known.join(", ")
+                )
+            });
+        }
+        members.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(members)
+    }
+
+    /// グループのプロセスの状態
+    pub async fn get_group_status(&self, group: &str) -> Result<GroupStatus, String> {
+        let members = self.group_members(group).await?;
+        Ok(GroupStatus::new(
+            group,
+            &members,
+            &self.dependency_graph().await,
+        ))
+    }
+
+    /// グループの停止中のプロセスを起動順序に従ってまとめて起動
+    pub async fn start_group(
+        &self,
+        group: &str,
+        dry_run: bool,
+    ) -> Result<BulkOperationReport, String> {
+        let members = self.group_members(group).await?;
+        let mut report = BulkOperationReport {
+            matched: members.iter().map(|info| info.id.clone()).collect(),
+            dry_run,
+            ..Default::default()
+        };
+        let mut to_start = Vec::new();
+        for info in members {
+            if matches!(
+                info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            ) {
+                report.skipped.push(info.id);
+            } else if !dry_run {
+                to_start.push(info.id);
+            }
+        }
+        if !to_start.is_empty() {
+            let startup = self.start_in_order(to_start).await;
+            report.succeeded = startup.ready();
+            report.failed = startup
+                .failed()
+                .into_iter()
+                .map(|(id, error)| BulkOperationFailure { id, error })
+                .collect();
+            report.startup = Some(startup);
+        }
+        info!(
+            "Group start '{}': {} process(es), {} started, {} failed",
+            group,
+            report.matched.len(),
+            report.succeeded.len(),
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// グループの実行中のプロセスを起動順序の逆順にまとめて停止
+    ///
+    /// 同時に起動するプロセスは同時に停止し、すべて停止してから次の組を停止する。
+    pub async fn stop_group(
+        &self,
+        group: &str,
+        grace_period_ms: Option<u64>,
+        dry_run: bool,
+    ) -> Result<BulkOperationReport, String> {
+        let members = self.group_members(group).await?;
+        let mut report = BulkOperationReport {
+            matched: members.iter().map(|info| info.id.clone()).collect(),
+            dry_run,
+            ..Default::default()
+        };
+        let mut to_stop = Vec::new();
+        for info in members {
+            if !matches!(
+                info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            ) {
+                report.skipped.push(info.id);
+            } else if !dry_run {
+                to_stop.push((info.id, info.startup.unwrap_or_default()));
+            }
+        }
+        let graph = self.dependency_graph().await;
+        for batch in groups::stop_order(to_stop, &graph) {
+            let results = futures::future::join_all(batch.into_iter().map(|id| async move {
+                (id.clone(), self.stop_process(id, grace_period_ms).await)
+            }))
+            .await;
+            for (id, result) in results {
+                match result {
+                    Ok(()) => report.succeeded.push(id),
+                    Err(error) => report.failed.push(BulkOperationFailure { id, error }),
+                }
+            }
+        }
+        info!(
+            "Group stop '{}': {} process(es), {} stopped, {} failed",
+            group,
+            report.matched.len(),
+            report.succeeded.len(),
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// 条件に一致する停止したプロセスを、完全には削除せずにごみ箱へ移す
+    pub async fn cleanup_processes(
+        &self,
+        filter: &CleanupFilter,
+        dry_run: bool,
+    ) -> Result<CleanupReport, String> {
+        let expression = filter.to_expression()?;
+        let expr = filter_expr::parse_process_filter(&expression).map_err(|e| e.to_string())?;
+        let matched = self.select_processes(&expr).await;
+        let mut report = CleanupReport {
+            expression: expression.clone(),
+            matched: matched.iter().map(|info| info.id.clone()).collect(),
+            dry_run,
+            ..Default::default()
+        };
+        if !dry_run && !matched.is_empty() {
+            let fence = self
+                .fence
+                .begin("cleanup_processes", Some(expression.clone()))
+                .await?;
+            let total = matched.len();
+            for (index, info) in matched.into_iter().enumerate() {
+                fence.progress("Moving processes to the recycle bin", index, Some(total));
+                match self.recycle_process(&info.id, &expr).await {
+                    Ok(purged) => {
+                        report.recycled.push(info.id);
+                        report.purged.extend(purged);
+                    }
+                    Err(error) => report
+                        .failed
+                        .push(BulkOperationFailure { id: info.id, error }),
+                }
+            }
+        }
+        report.counts = CleanupCounts {
+            matched: report.matched.len(),
+            recycled: report.recycled.len(),
+            failed: report.failed.len(),
+            in_recycle_bin: self.persistence.list_recycled_processes().await?.len(),
+        };
+        info!(
+            "Cleanup '{}': {} matched, {} recycled, {} failed",
+            expression, report.counts.matched, report.counts.recycled, report.counts.failed
+        );
+        Ok(report)
+    }
+
+    /// プロセスをごみ箱へ移し、あふれて完全に削除された定義のIDを返す
+    ///
+    /// 一致を確かめてから移すまでに起動されたプロセスは移さない。
+    async fn recycle_process(&self, id: &str, expr: &FilterExpr) -> Result<Vec<String>, String> {
+        let info = {
+            let processes = self.processes.read().await;
+            let process = processes
+                .get(id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?
+                .read()
+                .await;
+            process.info.clone()
+        };
+        if !expr.matches(&info) {
+            return Err(format!(
+                "Process '{id}' no longer matches the cleanup filter"
+            ));
+        }
+        let definition = Self::to_db_process_info(&info);
+        self.remove_process(id.to_string()).await?;
+        let item = RecycledProcess {
+            process: definition,
+            recycled_at: Utc::now(),
+            operation: "cleanup_processes".to_string(),
+            detail: Some(expr.as_str().to_string()),
+        };
+        let purged = self
+            .persistence
+            .push_recycled_process(&item, recycle_bin::MAX_ITEMS)
+            .await?;
+        Ok(purged
+            .into_iter()
+            .map(|item| item.process.process_id)
+            .collect())
+    }
+
+    /// ごみ箱の中身（新しい順）
+    pub async fn list_recycle_bin(&self) -> Result<Vec<RecycledProcess>, String> {
+        self.persistence.list_recycled_processes().await
+    }
+
+    /// ごみ箱のプロセスを停止した状態で元に戻す（同じIDが複数あれば最も新しいもの）
+    pub async fn restore_from_recycle_bin(&self, id: &str) -> Result<RecycledProcess, String> {
+        let mut processes = self.processes.write().await;
+        if processes.contains_key(id) {
+            return Err(format!(
+                "Process '{id}' already exists; remove or rename it before restoring"
+            ));
+        }
+        let item = self
+            .persistence
+            .take_recycled_process(id)
+            .await?
+            .ok_or_else(|| format!("Process '{id}' is not in the recycle bin"))?;
+        let mut db_info = item.process.clone();
+        db_info.status = Default::default();
+        self.persistence.save_process(&db_info).await?;
+        processes.insert(
+            id.to_string(),
+            Arc::new(RwLock::new(ManagedProcess::from_info(
+                Self::from_db_process_info(db_info.clone()),
+            ))),
+        );
+        drop(processes);
+        self.record_change(ChangeTarget::Process, id, None, Some(&db_info))
+            .await;
+        info!("Restored process '{}' from the recycle bin", id);
+        Ok(item)
+    }
+
+    /// ごみ箱の定義を完全に削除し、削除した数を返す（IDを指定しなければすべて）
+    pub async fn empty_recycle_bin(&self, id: Option<&str>) -> Result<usize, String> {
+        let purged = self.persistence.purge_recycled_processes(id).await?;
+        info!("Purged {} definition(s) from the recycle bin", purged);
+        Ok(purged)
+    }
+
+    /// プロセスを削除
+    pub async fn remove_process(&self, id: String) -> Result<(), String> {
+        let before = self.process_definition(&id).await;
+        self.remove_process_definition(&id).await?;
+        if self.proxy_routes.remove(&id).await {
+            info!("Closed the proxy route of removed process '{}'", id);
+        }
+        if self.lazy_listeners.close(&id).await {
+            info!("Closed the lazy start listener of removed process '{}'", id);
+        }
+        self.record_change(ChangeTarget::Process, &id, before.as_ref(), None)
+            .await;
+        Ok(())
+    }
+
+    /// 変更を記録せずにプロセスを削除
+    async fn remove_process_definition(&self, id: &str) -> Result<(), String> {
+        // まず停止を試みる
+        let _ = self.stop_process(id.to_string(), Some(5000)).await;
+
+        let mut processes = self.processes.write().await;
+        processes
+            .remove(id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+
+        // Delete from persistence
+        if let Err(e) = self.persistence.delete_process(id).await {
+            tracing::warn!("Failed to delete persisted process: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// 保存用のプロセス定義
+    async fn process_definition(&self, id: &str) -> Option<DbProcessInfo> {
+        let processes = self.processes.read().await;
+        let process = processes.get(id)?.read().await;
+        Some(Self::to_db_process_info(&process.info))
+    }
+
+    /// Export processes to JSON file
+    pub async fn export_processes(&self, file_path: Option<String>) -> Result<String, String> {
+        let path = match file_path {
+            Some(p) => paths::resolve_file_path(&p)?.to_string_lossy().to_string(),
+            None => vantage_persistence::paths::get()
+                .export_dir()
+                .join("processes.json")
+                .to_string_lossy()
+                .to_string(),
+        };
+
+        let _fence = self
+            .fence
+            .begin("export_processes", Some(path.clone()))
+            .await?;
+        // Export to JSON file
+        self.persistence.export_to_file(&path).await?;
+
+        Ok(path)
+    }
+
+    /// Export processes to a YAML or KDL file
+    ///
+    /// `format` を省略した場合はファイルの拡張子（`.kdl` ならKDL、それ以外はYAML）で決める。
+    pub async fn export_yaml(
+        &self,
+        file_path: Option<String>,
+        only_auto_start: bool,
+        format: Option<ConfigFormat>,
+    ) -> Result<String, String> {
+        let path = match file_path {
+            Some(p) => paths::resolve_file_path(&p)?.to_string_lossy().to_string(),
+            None => {
+                let extension = format.unwrap_or_default().extension();
+                vantage_persistence::paths::get()
+                    .export_dir()
+                    .join(format!("snapshot.{extension}"))
+                    .to_string_lossy()
+                    .to_string()
+            }
+        };
+
+        let _fence = self.fence.begin("export_yaml", Some(path.clone())).await?;
+        self.persistence
+            .export_snapshot_as(Some(&path), only_auto_start, format)
+            .await
+    }
+
+    /// Import processes from a YAML or KDL file
+    ///
+    /// 取り込む前の定義を自動でスナップショットに退避する。
+    pub async fn import_yaml(
+        &self,
+        file_path: &str,
+        format: Option<ConfigFormat>,
+    ) -> Result<(), String> {
+        let file_path = &paths::resolve_file_path(file_path)?
+            .to_string_lossy()
+            .to_string();
+        let fence = self
+            .fence
+            .begin("import_yaml", Some(file_path.to_string()))
+            .await?;
+        fence.progress("Taking pre-op snapshot", 0, None);
+        self.take_pre_op_snapshot("import_yaml", Some(file_path.to_string()))
+            .await?;
+        fence.progress("Importing processes", 0, None);
+        self.import_config_file(file_path, format).await
+    }
+
+    /// YAMLまたはKDLのファイルからプロセスを取り込む
+    async fn import_config_file(
+        &self,
+        file_path: &str,
+        format: Option<ConfigFormat>,
+    ) -> Result<(), String> {
+        let imported = self
+            .persistence
+            .import_snapshot_as(Some(file_path), format)
+            .await?;
+        self.cache_imported(imported).await;
+        Ok(())
+    }
+
+    /// 取り込んだ定義をプロセスのキャッシュに加える
+    async fn cache_imported(&self, imported: HashMap<String, DbProcessInfo>) {
+        let mut processes = self.processes.write().await;
+        for (id, info) in imported {
+            let process_info = crate::process::types::ProcessInfo {
+                id: info.process_id.clone(),
+                command: info.command,
+                args: info.args,
+                env: info.env,
+                cwd: info.cwd.map(std::path::PathBuf::from),
+                state: crate::process::types::ProcessState::NotStarted,
+                auto_start_on_restore: info.auto_start_on_restore,
+                tags: info.tags,
+                idle_detection: info.idle_detection,
+                health_check: info.health_check,
+                tmux: info.tmux,
+                repl: info.repl,
+                log_sources: info.log_sources,
+                run_as: info.run_as,
+                resource_limits: info.resource_limits,
+                container: info.container,
+                source: info.source,
+                log_sinks: info.log_sinks,
+                redaction: info.redaction,
+                metadata: info.metadata,
+                runbook: info.runbook,
+                startup: info.startup,
+                lazy_start: info.lazy_start,
+                env_loader: info.env_loader,
+                output_recording: info.output_recording,
+                assertions: info.assertions,
+                restart_policy: info.restart_policy,
+                group: info.group,
+                depends_on: info.depends_on,
+                parameters: info.parameters,
+                keep_stdin_open: info.keep_stdin_open,
+                health: None,
+                sensitive_env: info.sensitive_env,
+            };
+
+            let process = ManagedProcess::from_info(process_info);
+
+            processes.insert(id, Arc::new(RwLock::new(process)));
+        }
+    }
+
+    /// ワークスペース（プロセス・テンプレート・連鎖・コンテキスト・設定・手順書）をバンドルに書き出す
+    ///
+    /// シークレットらしい値は `<secret>` に置き換え、`root` の下の作業ディレクトリは相対パスにする。
+    pub async fn export_workspace(
+        &self,
+        file_path: Option<String>,
+        root: Option<PathBuf>,
+    ) -> Result<WorkspaceExportReport, String> {
+        let path = match file_path {
+            Some(p) => paths::resolve_file_path(&p)?,
+            None => vantage_persistence::paths::get()
+                .export_dir()
+                .join(workspace_bundle::DEFAULT_FILE_NAME),
+        };
+        let root = match root {
+            Some(root) => paths::expand_path(&root)?,
+            None => std::env::current_dir().map_err(|e| e.to_string())?,
+        };
+        let _fence = self
+            .fence
+            .begin("export_workspace", Some(path.display().to_string()))
+            .await?;
+
+        let mut secrets = Vec::new();
+        let mut runbooks = BTreeMap::new();
+        let mut processes: Vec<DbProcessInfo> = self
+            .persistence
+            .load_all_processes()
+            .await?
+            .into_values()
+            .collect();
+        processes.sort_by(|a, b| a.process_id.cmp(&b.process_id));
+        for info in &mut processes {
+            let location = format!("process {}", info.process_id);
+            workspace_bundle::scrub_env(&mut info.env, &location, &mut secrets);
+            workspace_bundle::scrub_sensitive_env(
+                &mut info.env,
+                &mut info.sensitive_env,
+                &location,
+                &mut secrets,
+            );
+            workspace_bundle::scrub_args(&mut info.args, &location, &mut secrets);
+            if let Some(runbook @ Runbook::Path(_)) = &info.runbook
+                && let Ok(content) =
+                    runbook::load(runbook, info.cwd.as_deref().map(std::path::Path::new))
+            {
+                runbooks.insert(info.process_id.clone(), content.markdown);
+            }
+            info.cwd = info
+                .cwd
+                .as_deref()
+                .map(|cwd| workspace_bundle::relativize(cwd, &root));
+            info.status = Default::default();
+        }
+
+        let mut templates = self.persistence.list_templates().await?;
+        templates.sort_by(|a, b| a.template_id.cmp(&b.template_id));
+        for template in &mut templates {
+            let location = format!("template {}", template.template_id);
+            workspace_bundle::scrub_env(&mut template.env, &location, &mut secrets);
+            workspace_bundle::scrub_args(&mut template.args, &location, &mut secrets);
+        }
+
+        let mut chains: Vec<ProcessChain> = self
+            .chains
+            .list(None)
+            .await
+            .into_iter()
+            .map(|status| status.config)
+            .collect();
+        chains.sort_by(|a, b| a.chain_id.cmp(&b.chain_id));
+
+        let mut contexts = self.persistence.list_contexts().await?;
+        contexts.sort_by(|a, b| a.name.cmp(&b.name));
+        for context in &mut contexts {
+            context.clipboard.clear();
+            context.workspace = context
+                .workspace
+                .as_deref()
+                .map(|workspace| workspace_bundle::relativize(workspace, &root));
+        }
+
+        let mut settings = self.persistence.get_settings().await?;
+        workspace_bundle::scrub_env(&mut settings.env_variables, "settings", &mut secrets);
+        for sink in &mut settings.log_sinks {
+            let location = format!("log sink {}", sink.name);
+            workspace_bundle::scrub_url(&mut sink.target, &location, &mut secrets);
+        }
+
+        let bundle = WorkspaceBundle {
+            version: workspace_bundle::BUNDLE_VERSION,
+            exported_at: Utc::now(),
+            vantage_version: env!("CARGO_PKG_VERSION").to_string(),
+            root: Some(root.display().to_string()),
+            processes,
+            templates,
+            chains,
+            contexts,
+            settings: Some(settings),
+            runbooks,
+            secrets,
+        };
+        let yaml = serde_yaml::to_string(&bundle)
+            .map_err(|e| format!("Failed to serialize workspace bundle: {e}"))?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+        }
+        tokio::fs::write(&path, yaml)
+            .await
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        info!(
+            "Exported workspace ({} processes, {} templates) to {}",
+            bundle.processes.len(),
+            bundle.templates.len(),
+            path.display()
+        );
+
+        Ok(WorkspaceExportReport {
+            path: path.display().to_string(),
+            version: bundle.version,
+            processes: bundle.processes.len(),
+            templates: bundle.templates.len(),
+            chains: bundle.chains.len(),
+            contexts: bundle.contexts.len(),
+            runbooks: bundle.runbooks.len(),
+            secrets: bundle.secrets,
+        })
+    }
+
+    /// ワークスペースのバンドルを取り込み、取り込んだ定義を検証する
+    ///
+    /// 同じIDの定義があるときは `on_conflict` に従う（既定では何も変更せずにエラー）。
+    /// 取り込む前のプロセス定義は自動でスナップショットに退避する。
+    pub async fn import_workspace(
+        &self,
+        file_path: &str,
+        options: WorkspaceImportOptions,
+    ) -> Result<WorkspaceImportReport, String> {
+        let path = paths::resolve_file_path(file_path)?;
+        let contents = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let mut bundle = workspace_bundle::parse(&contents)?;
+        let root = match options.root {
+            Some(root) => paths::expand_path(&root)?,
+            None => std::env::current_dir().map_err(|e| e.to_string())?,
+        };
+        for info in &mut bundle.processes {
+            info.cwd = info
+                .cwd
+                .as_deref()
+                .map(|cwd| workspace_bundle::resolve(cwd, &root));
+            info.status = Default::default();
+        }
+        for context in &mut bundle.contexts {
+            context.workspace = context
+                .workspace
+                .as_deref()
+                .map(|workspace| workspace_bundle::resolve(workspace, &root));
+        }
+
+        // 衝突を調べる
+        let policy = options.on_conflict;
+        let mut report = WorkspaceImportReport {
+            version: bundle.version,
+            exported_at: Some(bundle.exported_at),
+            dry_run: options.dry_run,
+            ..Default::default()
+        };
+        let existing_processes: HashSet<String> =
+            self.processes.read().await.keys().cloned().collect();
+        let existing_templates: HashSet<String> = self
+            .persistence
+            .list_templates()
+            .await?
+            .into_iter()
+            .map(|template| template.template_id)
+            .collect();
+        let existing_chains: HashSet<String> = self
+            .chains
+            .list(None)
+            .await
+            .into_iter()
+            .map(|status| status.config.chain_id)
+            .collect();
+        let existing_contexts: HashSet<String> = self
+            .persistence
+            .list_contexts()
+            .await?
+            .into_iter()
+            .map(|context| context.name)
+            .collect();
+        let mut processes = Vec::new();
+        for info in bundle.processes {
+            let exists = existing_processes.contains(&info.process_id);
+            if report.processes.decide(&info.process_id, exists, policy) {
+                processes.push(info);
+            }
+        }
+        let mut templates = Vec::new();
+        for template in bundle.templates {
+            let exists = existing_templates.contains(&template.template_id);
+            if report
+                .templates
+                .decide(&template.template_id, exists, policy)
+            {
+                templates.push(template);
+            }
+        }
+        let mut chains = Vec::new();
+        for chain in bundle.chains {
+            let exists = existing_chains.contains(&chain.chain_id);
+            if report.chains.decide(&chain.chain_id, exists, policy) {
+                chains.push((chain, exists));
+            }
+        }
+        let mut contexts = Vec::new();
+        for context in bundle.contexts {
+            let exists = existing_contexts.contains(&context.name);
+            if report.contexts.decide(&context.name, exists, policy) {
+                contexts.push(context);
+            }
+        }
+        if policy == ConflictPolicy::Fail {
+            let conflicts: Vec<String> = [
+                ("process", &report.processes.replaced),
+                ("template", &report.templates.replaced),
+                ("chain", &report.chains.replaced),
+                ("context", &report.contexts.replaced),
+            ]
+            .into_iter()
+            .flat_map(|(kind, ids)| ids.iter().map(move |id| format!("{kind} {id}")))
+            .collect();
+            if !conflicts.is_empty() {
+                return Err(format!(
+                    "Workspace bundle conflicts with existing definitions ({}); pass on_conflict=skip or overwrite",
+                    conflicts.join(", ")
+                ));
+            }
+        }
+
+        // ファイルの手順書が見つからなければ同梱の中身を使う
+        for info in &mut processes {
+            if let Some(runbook @ Runbook::Path(_)) = &info.runbook
+                && runbook::load(runbook, info.cwd.as_deref().map(std::path::Path::new)).is_err()
+                && let Some(markdown) = bundle.runbooks.remove(&info.process_id)
+            {
+                report.issues.push(workspace_bundle::ValidationIssue::new(
+                    format!("process {}", info.process_id),
+                    "Runbook file was not found; using the copy from the bundle",
+                ));
+                info.runbook = Some(Runbook::Inline(markdown));
+            }
+        }
+
+        // 検証する（取り込まない定義は対象外）
+        for info in &processes {
+            report
+                .issues
+                .extend(workspace_bundle::validate_process(info));
+        }
+        let known: HashSet<&str> = existing_processes
+            .iter()
+            .map(String::as_str)
+            .chain(processes.iter().map(|info| info.process_id.as_str()))
+            .collect();
+        for (chain, _) in &chains {
+            for id in [chain.after.as_str(), chain.action.process_id()] {
+                let created_by_template = matches!(chain.action, ChainAction::RunTemplate { .. })
+                    && id == chain.action.process_id();
+                if !known.contains(id) && !created_by_template {
+                    report.issues.push(workspace_bundle::ValidationIssue::new(
+                        format!("chain {}", chain.chain_id),
+                        format!("Process '{id}' does not exist"),
+                    ));
+                }
+            }
+        }
+        for context in &contexts {
+            for id in &context.running_processes {
+                if !known.contains(id.as_str()) {
+                    report.issues.push(workspace_bundle::ValidationIssue::new(
+                        format!("context {}", context.name),
+                        format!("Process '{id}' does not exist"),
+                    ));
+                }
+            }
+        }
+        if options.dry_run {
+            report.settings_imported = options.import_settings && bundle.settings.is_some();
+            return Ok(report);
+        }
+
+        let source = path.display().to_string();
+        let fence = self
+            .fence
+            .begin("import_workspace", Some(source.clone()))
+            .await?;
+        fence.progress("Taking pre-op snapshot", 0, None);
+        self.take_pre_op_snapshot("import_workspace", Some(source.clone()))
+            .await?;
+
+        let total = processes.len();
+        for (index, info) in processes.into_iter().enumerate() {
+            fence.progress("Importing processes", index, Some(total));
+            let id = info.process_id.clone();
+            let before = self.process_definition(&id).await;
+            match self.put_process_definition(info).await {
+                Ok(()) => {
+                    let after = self.process_definition(&id).await;
+                    self.record_change(ChangeTarget::Process, &id, before.as_ref(), after.as_ref())
+                        .await;
+                }
+                Err(e) => report.failed.push(workspace_bundle::ValidationIssue::new(
+                    format!("process {id}"),
+                    e,
+                )),
+            }
+        }
+        fence.progress("Importing templates", 0, None);
+        for template in templates {
+            let id = template.template_id.clone();
+            if let Err(e) = self.save_template(template).await {
+                report.failed.push(workspace_bundle::ValidationIssue::new(
+                    format!("template {id}"),
+                    e,
+                ));
+            }
+        }
+        fence.progress("Importing chains", 0, None);
+        for (chain, exists) in chains {
+            let id = chain.chain_id.clone();
+            if exists {
+                let _ = self.chains.remove(&id).await;
+            }
+            if let Err(e) = self.chains.create(chain).await {
+                report.failed.push(workspace_bundle::ValidationIssue::new(
+                    format!("chain {id}"),
+                    e,
+                ));
+            }
+        }
+        fence.progress("Importing contexts", 0, None);
+        for context in contexts {
+            if let Err(e) = self.persistence.save_context(&context).await {
+                report.failed.push(workspace_bundle::ValidationIssue::new(
+                    format!("context {}", context.name),
+                    e,
+                ));
+            }
+        }
+        if options.import_settings
+            && let Some(mut settings) = bundle.settings
+        {
+            settings.updated_at = Utc::now();
+            match self.save_settings(settings).await {
+                Ok(()) => report.settings_imported = true,
+                Err(e) => report
+                    .failed
+                    .push(workspace_bundle::ValidationIssue::new("settings", e)),
+            }
+        }
+        info!(
+            "Imported workspace bundle {} ({} created, {} replaced, {} issue(s))",
+            source,
+            report.processes.created.len(),
+            report.processes.replaced.len(),
+            report.issues.len()
+        );
+        Ok(report)
+    }
+
+    /// Create auto-start snapshot on shutdown
+    pub async fn create_auto_start_snapshot(&self) -> Result<String, String> {
+        self.persistence.create_auto_start_snapshot(None).await
+    }
+
+    /// Create YAML snapshot on shutdown
+    pub async fn create_yaml_snapshot_on_shutdown(&self) -> Result<(), String> {
+        self.persistence.create_auto_start_snapshot(None).await?;
+        Ok(())
+    }
+
+    /// Restore from YAML snapshot on startup
+    pub async fn restore_yaml_snapshot(&self) -> Result<(), String> {
+        let snapshot_path = vantage_persistence::paths::get()
+            .snapshot_file()
+            .to_string_lossy()
+            .to_string();
+
+        let path = std::path::Path::new(&snapshot_path);
+        if !path.exists()
+            && !vantage_persistence::persistence::atomic_file::backup_path(path).exists()
+        {
+            tracing::debug!("No YAML snapshot found at {}", snapshot_path);
+            return Ok(());
+        }
+
+        // 起動時の復元は取り消す対象がないので退避しない。
+        // 読み込めなければ前の世代（`.bak`）から復元する
+        match self
+            .persistence
+            .import_snapshot_with_fallback(Some(&snapshot_path), None)
+            .await
+        {
+            Ok((imported, fallback)) => {
+                self.cache_imported(imported).await;
+                if let Some(fallback) = fallback
+                    && let Err(e) = self.event_system.emit_snapshot_recovered(&fallback).await
+                {
+                    tracing::warn!("Failed to emit snapshot recovered event: {}", e);
+                }
+                tracing::info!("Successfully restored from YAML snapshot");
+                Ok(())
+            }
+            Err(e) => {
+                tracing::warn!("Failed to restore YAML snapshot: {}", e);
+                // Don't fail startup if snapshot restore fails
+                Ok(())
+            }
+        }
+    }
+
+    /// Auto-start processes marked with auto_start_on_restore flag
+    ///
+    /// 起動順序（`startup`）に従って起動し、プロセスごとの準備完了までの時間を含む結果を返す。
+    /// 結果は `last_startup_report` でも参照できる。
+    pub async fn start_auto_start_processes(&self) -> Result<StartupReport, String> {
+        self.open_lazy_listeners().await;
+
+        // auto_start_on_restore が true で未起動のプロセスを収集
+        let mut auto_start_ids = Vec::new();
+        for (id, process_arc) in self.processes.read().await.iter() {
+            let process = process_arc.read().await;
+            if process.info.auto_start_on_restore
+                && matches!(process.info.state, ProcessState::NotStarted)
+            {
+                auto_start_ids.push(id.clone());
+            }
+        }
+
+        let report = self.start_in_order(auto_start_ids).await;
+        let failed = report.failed();
+        if !failed.is_empty() {
+            tracing::warn!(
+                "Some processes failed to auto-start ({} failures): {:?}",
+                failed.len(),
+                failed
+            );
+        }
+        *self.last_startup.write().await = Some(report.clone());
+
+        Ok(report)
+    }
+
+    /// 最後に行った復元時の自動起動の結果
+    pub async fn last_startup_report(&self) -> Option<StartupReport> {
+        self.last_startup.read().await.clone()
+    }
+
+    /// 起動順序に従ってプロセスを起動する
+    ///
+    /// 同じ優先度・同じグループのプロセスは並列に起動し、そのすべてが準備完了になるか
+    /// 失敗してから次のプロセスを起動する。依存先は優先度によらず依存元より先に起動する。
+    pub async fn start_in_order(&self, ids: Vec<String>) -> StartupReport {
+        let started_at = Utc::now();
+        let clock = std::time::Instant::now();
+        let mut members = Vec::new();
+        for id in ids {
+            let order = match self.processes.read().await.get(&id) {
+                Some(process) => process.read().await.info.startup.clone(),
+                None => None,
+            };
+            members.push((id, order.unwrap_or_default()));
+        }
+
+        let graph = self.dependency_graph().await;
+        let mut processes = Vec::new();
+        for (batch, members) in dependencies::plan(members, &graph).into_iter().enumerate() {
+            let results = futures::future::join_all(
+                members
+                    .into_iter()
+                    .map(|(id, order)| self.start_until_ready(id, order, batch, clock)),
+            )
+            .await;
+            processes.extend(results);
+        }
+
+        let report = StartupReport {
+            started_at,
+            total_ms: clock.elapsed().as_millis() as u64,
+            processes,
+        };
+        info!(
+            "Started {} of {} process(es) in order in {}ms",
+            report.ready().len(),
+            report.processes.len(),
+            report.total_ms
+        );
+        report
+    }
+
+    async fn start_until_ready(
+        &self,
+        id: String,
+        order: StartupOrder,
+        batch: usize,
+        clock: std::time::Instant,
+    ) -> StartupResult {
+        let mut result = StartupResult {
+            process_id: id.clone(),
+            priority: order.priority,
+            group: order.group.clone(),
+            batch,
+            pid: None,
+            started_after_ms: None,
+            ready_after_ms: None,
+            ready: false,
+            error: None,
+        };
+        match self.start_process(id.clone()).await {
+            Ok(pid) => {
+                result.pid = Some(pid);
+                result.started_after_ms = Some(clock.elapsed().as_millis() as u64);
+            }
+            Err(e) => {
+                result.error = Some(e);
+                return result;
+            }
+        }
+        if let Some(port) = order.ready_port {
+            let timeout = Duration::from_secs(
+                order
+                    .ready_timeout_secs
+                    .unwrap_or(startup::DEFAULT_READY_TIMEOUT_SECS),
+            );
+            if let Err(e) = self.wait_until_ready(&id, port, timeout).await {
+                result.error = Some(e);
+                return result;
+            }
+        }
+        result.ready = true;
+        result.ready_after_ms = Some(clock.elapsed().as_millis() as u64);
+        result
+    }
+
+    /// プロセスがポートで接続を受け付けるまで待つ
+    async fn wait_until_ready(&self, id: &str, port: u16, timeout: Duration) -> Result<(), String> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if blue_green::accepts_connections(port).await {
+                return Ok(());
+            }
+            let running = self
+                .process_definition(id)
+                .await
+                .is_some_and(|info| info.status.state == DbProcessState::Running);
+            if !running {
+                return Err(format!(
+                    "Process '{id}' exited before accepting connections on port {port}"
+                ));
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!(
+                    "Process '{id}' did not accept connections on port {port} within {}s",
+                    timeout.as_secs()
+                ));
+            }
+            tokio::time::sleep(startup::READY_POLL_INTERVAL).await;
+        }
+    }
+
+    /// セーフモードにする（起動時の復元と自動起動を行わなかったときに呼ぶ）
+    ///
+    /// `snapshot` は復元しなかったスナップショットで、`safe_mode_restore` で読み込む。
+    pub fn enter_safe_mode(&self, snapshot: Option<PathBuf>) {
+        self.safe_mode.enable(snapshot);
+    }
+
+    pub fn is_safe_mode(&self) -> bool {
+        self.safe_mode.is_enabled()
+    }
+
+    /// セーフモードの状態（セーフモードでなければ `None`）
+    pub async fn safe_mode_status(&self) -> Option<SafeModeStatus> {
+        if !self.safe_mode.is_enabled() {
+            return None;
+        }
+        let pending = match self.safe_mode_snapshot().await {
+            Ok(saved) => {
+                let processes = self.processes.read().await;
+                saved
+                    .into_iter()
+                    .map(|info| info.process_id)
+                    .filter(|id| !processes.contains_key(id))
+                    .collect()
+            }
+            Err(e) => {
+                debug!("Failed to read the skipped snapshot: {}", e);
+                Vec::new()
+            }
+        };
+        self.safe_mode.status(pending)
+    }
+
+    /// セーフモードで復元しなかったスナップショットの中身
+    async fn safe_mode_snapshot(&self) -> Result<Vec<DbProcessInfo>, String> {
+        let path = self
+            .safe_mode
+            .snapshot()
+            .ok_or("No snapshot was skipped at startup")?;
+        PersistenceManager::read_snapshot(&path, ConfigFormat::from_path(&path)).await
+    }
+
+    /// セーフモードで起動したときに復元しなかったプロセスを選んで復元する
+    ///
+    /// `process_ids` が空ならすべてを復元する。すでに登録されているプロセスはそのままにする。
+    /// `start` を指定すると、復元したプロセスのうち自動起動の対象を起動する。
+    pub async fn safe_mode_restore(
+        &self,
+        process_ids: Vec<String>,
+        start: bool,
+    ) -> Result<SafeModeRestoreReport, String> {
+        if !self.safe_mode.is_enabled() {
+            return Err("The server is not running in safe mode".to_string());
+        }
+        let saved = self.safe_mode_snapshot().await?;
+        let mut report = SafeModeRestoreReport {
+            not_found: process_ids
+                .iter()
+                .filter(|id| !saved.iter().any(|info| &info.process_id == *id))
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut to_start = Vec::new();
+        for db_info in saved {
+            let id = db_info.process_id.clone();
+            if !process_ids.is_empty() && !process_ids.contains(&id) {
+                continue;
+            }
+            if self.processes.read().await.contains_key(&id) {
+                report.skipped.push(id);
+                continue;
+            }
+            let auto_start = db_info.auto_start_on_restore;
+            if let Err(e) = self.put_process_definition(db_info).await {
+                report.failed.push((id, e));
+                continue;
+            }
+            let after = self.process_definition(&id).await;
+            self.record_change(ChangeTarget::Process, &id, None, after.as_ref())
+                .await;
+            if start && auto_start {
+                to_start.push(id.clone());
+            }
+            report.restored.push(id);
+        }
+        self.safe_mode.record_restored(&report.restored);
+
+        if !to_start.is_empty() {
+            let startup = self.start_in_order(to_start).await;
+            report.started = startup.ready();
+            report.failed.extend(startup.failed());
+            report.startup = Some(startup);
+        }
+
+        report.pending = self
+            .safe_mode_status()
+            .await
+            .map(|status| status.pending)
+            .unwrap_or_default();
+        info!(
+            "Restored {} process(es) from safe mode ({} still pending)",
+            report.restored.len(),
+            report.pending.len()
+        );
+        Ok(report)
+    }
+
+    /// Import processes from JSON file
+    pub async fn import_processes(&self, file_path: &str) -> Result<(), String> {
+        let file_path = &paths::resolve_file_path(file_path)?
+            .to_string_lossy()
+            .to_string();
+        let fence = self
+            .fence
+            .begin("import_processes", Some(file_path.to_string()))
+            .await?;
+        fence.progress("Taking pre-op snapshot", 0, None);
+        self.take_pre_op_snapshot("import_processes", Some(file_path.to_string()))
+            .await?;
+        // Import from JSON file
+        fence.progress("Importing processes", 0, None);
+        self.persistence.import_from_file(file_path).await?;
+
+        // Reload processes into memory
+        fence.progress("Reloading processes", 0, None);
+        self.load_persisted_processes().await?;
+
+        Ok(())
+    }
+
+    /// Create a snapshot (YAML format)
+    pub async fn create_snapshot(&self) -> Result<String, String> {
+        let _fence = self.fence.begin("create_snapshot", None).await?;
+        self.persistence.create_auto_start_snapshot(None).await
+    }
+
+    /// Restore from the latest snapshot
+    ///
+    /// 復元が終わるまで、ほかの変更操作は待たされる（進捗は `operation_status` で見られる）。
+    pub async fn restore_snapshot(&self) -> Result<(), String> {
+        let fence = self.fence.begin("restore_snapshot", None).await?;
+        fence.progress("Taking pre-op snapshot", 0, None);
+        self.take_pre_op_snapshot("restore_snapshot", None).await?;
+        fence.progress("Restoring snapshot", 0, None);
+        let restored = self.persistence.restore_yaml_snapshot(None).await?;
+
+        // Reload processes into memory
+        fence.progress("Reloading processes", 0, None);
+        self.load_persisted_processes().await?;
+        self.open_lazy_listeners().await;
+
+        // Start auto-start processes
+        let total = restored.len();
+        for (index, (id, info)) in restored.into_iter().enumerate() {
+            fence.progress("Starting auto-start processes", index, Some(total));
+            if info.auto_start_on_restore
+                && let Err(e) = self.start_process(id.clone()).await
+            {
+                tracing::warn!("Failed to auto-start process {}: {}", id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 危険な操作の前に、現在のプロセス定義をスナップショットに退避する
+    ///
+    /// 退避できなければ操作を中止する。履歴は `pre_op::MAX_HISTORY` 件まで残し、
+    /// 古いものはファイルごと削除する。
+    async fn take_pre_op_snapshot(
+        &self,
+        operation: &str,
+        detail: Option<String>,
+    ) -> Result<PreOpSnapshot, String> {
+        let snapshot_id = format!(
+            "{}-{}",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            vantage_persistence::generate_id()
+        );
+        let path = pre_op::snapshot_path(&snapshot_id);
+        let process_count = self.persistence.load_all_processes().await?.len();
+        let path = self
+            .persistence
+            .export_snapshot_as(
+                Some(&path.to_string_lossy()),
+                false,
+                Some(ConfigFormat::Yaml),
+            )
+            .await
+            .map_err(|e| format!("Failed to take pre-op snapshot before {operation}: {e}"))?;
+        let snapshot = PreOpSnapshot {
+            snapshot_id,
+            operation: operation.to_string(),
+            detail,
+            created_at: Utc::now(),
+            path,
+            process_count,
+        };
+        let dropped = self
+            .persistence
+            .push_pre_op_snapshot(&snapshot, pre_op::MAX_HISTORY)
+            .await?;
+        for old in dropped {
+            if let Err(e) = tokio::fs::remove_file(&old.path).await {
+                debug!("Failed to remove pre-op snapshot {}: {}", old.path, e);
+            }
+        }
+        info!(
+            "Took pre-op snapshot '{}' of {} process(es) before {}",
+            snapshot.snapshot_id, process_count, operation
+        );
+        Ok(snapshot)
+    }
+
+    /// 自動で取ったスナップショットの一覧（新しい順）
+    pub async fn list_pre_op_snapshots(&self) -> Result<Vec<PreOpSnapshot>, String> {
+        self.persistence.list_pre_op_snapshots().await
+    }
+
+    /// 直前の危険な操作を取り消し、プロセス定義をその前のスナップショットに戻す
+    ///
+    /// 操作で追加されたプロセスは停止して削除し、変更・削除されたプロセスは停止した
+    /// 状態で作り直す。戻したスナップショットは履歴から外す。
+    pub async fn undo_last_operation(&self) -> Result<UndoReport, String> {
+        let fence = self.fence.begin("undo_last_operation", None).await?;
+        let snapshot = self
+            .persistence
+            .list_pre_op_snapshots()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or("No pre-op snapshot to undo")?;
+        let saved = PersistenceManager::read_snapshot(
+            std::path::Path::new(&snapshot.path),
+            ConfigFormat::Yaml,
+        )
+        .await?;
+        let current = self.persistence.load_all_processes().await?;
+        let plan = pre_op::plan(&current, saved);
+
+        let mut report = UndoReport {
+            snapshot: snapshot.clone(),
+            restored: Vec::new(),
+            removed: Vec::new(),
+            unchanged: plan.unchanged,
+            failed: Vec::new(),
+        };
+        let total = plan.remove.len() + plan.restore.len();
+        for (index, id) in plan.remove.into_iter().enumerate() {
+            fence.progress("Removing added processes", index, Some(total));
+            match self.remove_process_definition(&id).await {
+                Ok(()) => report.removed.push(id),
+                Err(error) => report.failed.push(BulkOperationFailure { id, error }),
+            }
+        }
+        let removed = total - plan.restore.len();
+        for (index, mut db_info) in plan.restore.into_iter().enumerate() {
+            fence.progress("Restoring processes", removed + index, Some(total));
+            let id = db_info.process_id.clone();
+            db_info.status = Default::default();
+            let _ = self.stop_process(id.clone(), Some(5000)).await;
+            if let Err(error) = self.persistence.save_process(&db_info).await {
+                report.failed.push(BulkOperationFailure { id, error });
+                continue;
+            }
+            let info = Self::from_db_process_info(db_info);
+            self.processes.write().await.insert(
+                id.clone(),
+                Arc::new(RwLock::new(ManagedProcess::from_info(info))),
+            );
+            report.restored.push(id);
+        }
+
+        self.persistence
+            .delete_pre_op_snapshot(&snapshot.snapshot_id)
+            .await?;
+        if let Err(e) = tokio::fs::remove_file(&snapshot.path).await {
+            debug!("Failed to remove pre-op snapshot {}: {}", snapshot.path, e);
+        }
+        info!(
+            "Undid {} using pre-op snapshot '{}': {} restored, {} removed",
+            snapshot.operation,
+            snapshot.snapshot_id,
+            report.restored.len(),
+            report.removed.len()
+        );
+        Ok(report)
+    }
+
+    /// 定義の変更を記録する（内容が変わっていなければ何もしない）
+    pub async fn record_change<T: serde::Serialize>(
+        &self,
+        target: ChangeTarget,
+        target_id: &str,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) {
+        let Some(change) = change_log::new_change(target, target_id, before, after) else {
+            return;
+        };
+        if let Err(e) = self
+            .persistence
+            .record_change(&change, change_log::MAX_HISTORY)
+            .await
+        {
+            warn!("Failed to record change to '{}': {}", target_id, e);
+        }
+    }
+
+    /// 取り消し・やり直しできる変更の一覧
+    pub async fn list_changes(&self) -> Result<ChangeHistory, String> {
+        self.persistence.change_history().await
+    }
+
+    /// 直前の定義の変更を取り消す
+    ///
+    /// テンプレート用データベースのテンプレートへの変更を取り消すには `shared_templates` が必要。
+    pub async fn undo_last_change(
+        &self,
+        shared_templates: Option<&vantage_persistence::TemplateRepository<'_>>,
+    ) -> Result<DefinitionChange, String> {
+        let history = self.persistence.change_history().await?;
+        let change = history.undo.into_iter().next().ok_or("No change to undo")?;
+        self.apply_definition(&change, change.before.clone(), shared_templates)
+            .await?;
+        self.persistence
+            .move_change(&change.change_id, true)
+            .await?;
+        info!(
+            "Undid {:?} of {:?} '{}'",
+            change.action, change.target, change.target_id
+        );
+        Ok(change)
+    }
+
+    /// 取り消した変更をやり直す
+    pub async fn redo_change(
+        &self,
+        shared_templates: Option<&vantage_persistence::TemplateRepository<'_>>,
+    ) -> Result<DefinitionChange, String> {
+        let history = self.persistence.change_history().await?;
+        let change = history.redo.into_iter().next().ok_or("No change to redo")?;
+        self.apply_definition(&change, change.after.clone(), shared_templates)
+            .await?;
+        self.persistence
+            .move_change(&change.change_id, false)
+            .await?;
+        info!(
+            "Redid {:?} of {:?} '{}'",
+            change.action, change.target, change.target_id
+        );
+        Ok(change)
+    }
+
+    /// 変更の対象を指定した定義にする（`None` なら削除する）。変更は記録しない
+    async fn apply_definition(
+        &self,
+        change: &DefinitionChange,
+        definition: Option<serde_json::Value>,
+        shared_templates: Option<&vantage_persistence::TemplateRepository<'_>>,
+    ) -> Result<(), String> {
+        let id = change.target_id.as_str();
+        let invalid =
+            |e: serde_json::Error| format!("Recorded definition of '{id}' is invalid: {e}");
+        match change.target {
+            ChangeTarget::Process => match definition {
+                None => self.remove_process_definition(id).await,
+                Some(value) => {
+                    let db_info: DbProcessInfo = serde_json::from_value(value).map_err(invalid)?;
+                    self.put_process_definition(db_info).await
+                }
+            },
+            ChangeTarget::Template => match definition {
+                None => self.persistence.delete_template(id).await,
+                Some(value) => {
+                    let template: ProcessTemplate =
+                        serde_json::from_value(value).map_err(invalid)?;
+                    self.persistence.save_template(&template).await
+                }
+            },
+            ChangeTarget::SharedTemplate => {
+                let repo = shared_templates
+                    .ok_or("Database connection not available for shared template changes")?;
+                match definition {
+                    None => repo.delete(id).await,
+                    Some(value) => {
+                        let template: vantage_persistence::Template =
+                            serde_json::from_value(value).map_err(invalid)?;
+                        repo.restore(id, template).await.map(|_| ())
+                    }
+                }
+                .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// プロセスの定義を置き換える（なければ停止した状態で作る）
+    ///
+    /// 実行中のプロセスは状態を保ち、変更は次回の起動から反映される。
+    async fn put_process_definition(&self, mut db_info: DbProcessInfo) -> Result<(), String> {
+        let id = db_info.process_id.clone();
+        let mut processes = self.processes.write().await;
+        if let Some(process_arc) = processes.get(&id) {
+            let mut process = process_arc.write().await;
+            let state = process.info.state.clone();
+            process.info = Self::from_db_process_info(db_info);
+            process.info.state = state;
+            self.persistence
+                .update_process(&Self::to_db_process_info(&process.info))
+                .await
+        } else {
+            db_info.status = Default::default();
+            self.persistence.save_process(&db_info).await?;
+            let info = Self::from_db_process_info(db_info);
+            processes.insert(id, Arc::new(RwLock::new(ManagedProcess::from_info(info))));
+            Ok(())
+        }
+    }
+
+    /// Update process configuration (auto_start flags and startup order)
+    pub async fn update_process_config(
+        &self,
+        id: String,
+        auto_start_on_restore: Option<bool>,
+        startup: Option<StartupOrder>,
+        group: Option<String>,
+    ) -> Result<(), String> {
+        if let Some(order) = &startup {
+            startup::validate(order)?;
+        }
+        if let Some(group) = group.as_deref().filter(|group| !group.is_empty()) {
+            groups::validate(group)?;
+        }
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+
+        let mut process = process_arc.write().await;
+        let before = Self::to_db_process_info(&process.info);
+
+        if let Some(value) = auto_start_on_restore {
+            process.info.auto_start_on_restore = value;
+            info!(
+                "Updated process '{}' auto_start_on_restore to {}",
+                id, value
+            );
+        }
+
+        if let Some(order) = startup {
+            info!("Updated process '{}' startup order to {:?}", id, order);
+            process.info.startup = Some(order);
+        }
+
+        // 空文字列はグループから外す
+        if let Some(group) = group {
+            info!("Updated process '{}' group to '{}'", id, group);
+            process.info.group = Some(group).filter(|group| !group.is_empty());
+        }
+
+        // Persist the updated configuration
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            return Err(format!("Failed to persist process config update: {e}"));
+        }
+        self.record_change(ChangeTarget::Process, &id, Some(&before), Some(&db_info))
+            .await;
+
+        Ok(())
+    }
+
+    /// Update process attributes (command, args, env, cwd, and flags)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_process(
+        &self,
+        id: String,
+        command: Option<String>,
+        args: Option<Vec<String>>,
+        env: Option<HashMap<String, String>>,
+        cwd: Option<String>,
+        auto_start_on_restore: Option<bool>,
+        sensitive_env: Option<Vec<String>>,
+    ) -> Result<(), String> {
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+
+        let mut process = process_arc.write().await;
+        let before = Self::to_db_process_info(&process.info);
+
+        // 新しいenvに同じ名前があれば平文の値で置き換え、指定した名前は暗号化する
+        let env_changed = env.is_some() || sensitive_env.is_some();
+        let mut new_sealed = process.info.sensitive_env.clone();
+        if let Some(environment) = &env {
+            new_sealed.retain(|name, _| !environment.contains_key(name));
+        }
+        let mut new_env = env.unwrap_or_else(|| process.info.env.clone());
+        if let Some(names) = &sensitive_env {
+            sensitive_env::seal(
+                sensitive_env::cipher()?,
+                &mut new_env,
+                names,
+                &mut new_sealed,
+            )?;
+        }
+        secret_refs::validate(&new_env)?;
+
+        // Update command if provided
+        if let Some(cmd) = command {
+            process.info.command = cmd.clone();
+            info!("Updated process '{}' command to '{}'", id, cmd);
+        }
+
+        // Update args if provided
+        if let Some(arguments) = args {
+            process.info.args = arguments.clone();
+            info!("Updated process '{}' args to {:?}", id, arguments);
+        }
+
+        // Update env if provided
+        if env_changed {
+            process.info.env = new_env;
+            process.info.sensitive_env = new_sealed;
+            info!("Updated process '{}' env variables", id);
+        }
+
+        // Update cwd if provided
+        if let Some(working_dir) = cwd {
+            let working_dir = paths::expand(&working_dir)?;
+            info!(
+                "Updated process '{}' cwd to '{}'",
+                id,
+                working_dir.display()
+            );
+            process.info.cwd = Some(working_dir);
+        }
+
+        // Update auto_start flags if provided
+        if let Some(value) = auto_start_on_restore {
+            process.info.auto_start_on_restore = value;
+            info!(
+                "Updated process '{}' auto_start_on_restore to {}",
+                id, value
+            );
+        }
+
+        // Persist the updated configuration
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            return Err(format!("Failed to persist process update: {e}"));
+        }
+        self.record_change(ChangeTarget::Process, &id, Some(&before), Some(&db_info))
+            .await;
+
+        Ok(())
+    }
+
+    /// プロセスのメタデータを変更する（値が `None` のキーは削除）
+    pub async fn update_process_metadata(
+        &self,
+        id: String,
+        changes: HashMap<String, Option<String>>,
+    ) -> Result<HashMap<String, String>, String> {
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let mut process = process_arc.write().await;
+        let before = Self::to_db_process_info(&process.info);
+
+        process.info.metadata = metadata::apply(&process.info.metadata, changes)?;
+        info!("Updated process '{}' metadata", id);
+
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            return Err(format!("Failed to persist process update: {e}"));
+        }
+        self.record_change(ChangeTarget::Process, &id, Some(&before), Some(&db_info))
+            .await;
+        Ok(process.info.metadata.clone())
+    }
+
+    /// すべてのプロセスの出力を伏せるルールを置き換え、実行中のプロセスにもすぐに適用する
+    pub async fn set_redaction_rules(&self, rules: Vec<RedactionRule>) -> Result<(), String> {
+        let mut settings = self.get_settings().await?;
+        settings.redaction_rules = rules;
+        let rules = settings.redaction_rules.clone();
+        self.save_settings(settings).await?;
+
+        let processes = self.processes.read().await;
+        for process_arc in processes.values() {
+            let process = process_arc.read().await;
+            Self::apply_redaction(&process, &rules);
+        }
+        info!("Updated global redaction rules ({} rules)", rules.len());
+        Ok(())
+    }
+
+    /// プロセスの手順書を置き換える（Noneで外す）
+    pub async fn set_runbook(&self, id: String, runbook: Option<Runbook>) -> Result<(), String> {
+        if let Some(runbook) = &runbook {
+            runbook::validate(runbook)?;
+        }
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let mut process = process_arc.write().await;
+        let before = Self::to_db_process_info(&process.info);
+
+        process.info.runbook = runbook;
+        info!("Updated process '{}' runbook", id);
+
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            return Err(format!("Failed to persist process update: {e}"));
+        }
+        self.record_change(ChangeTarget::Process, &id, Some(&before), Some(&db_info))
+            .await;
+        Ok(())
+    }
 
-            // 出力ハンドルをクリーンアップ
-            if let Some((stdout_handle, stderr_handle)) = process.output_handles.take() {
-                stdout_handle.abort();
-                stderr_handle.abort();
+    /// 起動するときに読み込むプロジェクトの環境を設定する（Noneで読み込まない）
+    ///
+    /// 設定する前に作業ディレクトリで一度読み込み、解決したツールチェーンを返す。
+    /// 読み込めなければ設定しない。`auto` で設定ファイルが見つからない場合はNoneを返す。
+    pub async fn set_env_loader(
+        &self,
+        id: String,
+        loader: Option<EnvLoader>,
+    ) -> Result<Option<Toolchain>, String> {
+        let (cwd, container) = {
+            let processes = self.processes.read().await;
+            let process = processes
+                .get(&id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?
+                .read()
+                .await;
+            (process.info.cwd.clone(), process.info.container.is_some())
+        };
+        let toolchain = match loader {
+            Some(_) if container => {
+                return Err("container cannot be combined with env_loader".to_string());
             }
+            Some(loader) => env_loader::load(loader, cwd.as_deref())
+                .await?
+                .map(|project_env| project_env.toolchain),
+            None => None,
+        };
 
-            // 状態を更新
-            process.info.state = ProcessState::Stopped {
-                exit_code: None,
-                stopped_at: chrono::Utc::now(),
-            };
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let mut process = process_arc.write().await;
+        let before = Self::to_db_process_info(&process.info);
 
-            // Persist the updated state
-            let db_info = Self::to_db_process_info(&process.info);
-            if let Err(e) = self.persistence.update_process(&db_info).await {
-                tracing::warn!("Failed to persist process state: {}", e);
-            }
+        process.info.env_loader = loader;
+        info!("Updated process '{}' env loader to {:?}", id, loader);
 
-            info!("Stopped process '{}'", id);
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            return Err(format!("Failed to persist process update: {e}"));
+        }
+        self.record_change(ChangeTarget::Process, &id, Some(&before), Some(&db_info))
+            .await;
+        Ok(toolchain)
+    }
+
+    /// 起動のたびに出力を記録するかを設定する（Noneで記録しない、次の起動から反映）
+    pub async fn set_output_recording(
+        &self,
+        id: String,
+        config: Option<OutputRecordingConfig>,
+    ) -> Result<(), String> {
+        if config.as_ref().is_some_and(|c| c.keep == Some(0)) {
+            return Err("output_recording.keep must be at least 1".to_string());
         }
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let mut process = process_arc.write().await;
+        let before = Self::to_db_process_info(&process.info);
 
+        info!("Updated process '{}' output recording to {:?}", id, config);
+        process.info.output_recording = config;
+
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            return Err(format!("Failed to persist process update: {e}"));
+        }
+        self.record_change(ChangeTarget::Process, &id, Some(&before), Some(&db_info))
+            .await;
         Ok(())
     }
 
-    /// 全ての実行中プロセスを停止（stop_on_shutdownフラグに基づく）
-    pub async fn stop_all_processes(&self) -> Result<Vec<String>, String> {
-        info!("Stopping all running processes...");
+    /// プロセスの出力の記録の一覧（新しい順）
+    pub async fn list_output_recordings(&self, id: &str) -> Result<Vec<RecordingSummary>, String> {
+        if !self.processes.read().await.contains_key(id) {
+            return Err(format!("Process '{id}' not found"));
+        }
+        Ok(recording::list(&recording::default_recordings_dir(), id))
+    }
 
-        let processes = self.processes.read().await;
-        let mut stopped_processes = Vec::new();
-        let mut errors = Vec::new();
+    /// 出力の記録を読み込む（名前を省略すると最新の記録）
+    pub async fn load_output_recording(
+        &self,
+        id: &str,
+        name: Option<&str>,
+    ) -> Result<Recording, String> {
+        if !self.processes.read().await.contains_key(id) {
+            return Err(format!("Process '{id}' not found"));
+        }
+        recording::load(&recording::default_recordings_dir(), id, name)
+    }
 
-        for (id, process_arc) in processes.iter() {
-            let process = process_arc.read().await;
+    /// 出力の記録のファイルの場所（Web APIでのダウンロード用）
+    pub async fn output_recording_path(
+        &self,
+        id: &str,
+        name: Option<&str>,
+    ) -> Result<PathBuf, String> {
+        if !self.processes.read().await.contains_key(id) {
+            return Err(format!("Process '{id}' not found"));
+        }
+        recording::path(&recording::default_recordings_dir(), id, name)
+    }
 
-            // 実行中のプロセスのみ対象
-            if matches!(process.info.state, ProcessState::Running { .. }) {
-                let id_clone = id.clone();
-                drop(process); // ロックを解放
+    /// プロセスの手順書を読み込む（手順書が無ければNone）
+    pub async fn get_runbook(&self, id: &str) -> Result<Option<RunbookContent>, String> {
+        let (runbook, cwd) = {
+            let processes = self.processes.read().await;
+            let process = processes
+                .get(id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?
+                .read()
+                .await;
+            (process.info.runbook.clone(), process.info.cwd.clone())
+        };
+        runbook
+            .map(|runbook| runbook::load(&runbook, cwd.as_deref()))
+            .transpose()
+    }
 
-                // プロセスを停止（5秒の猶予期間）
-                match self.stop_process(id_clone.clone(), Some(5000)).await {
-                    Ok(_) => {
-                        info!("Successfully stopped process '{}'", id_clone);
-                        stopped_processes.push(id_clone);
-                    }
-                    Err(e) => {
-                        error!("Failed to stop process '{}': {}", id_clone, e);
-                        errors.push(format!("{id_clone}: {e}"));
-                    }
-                }
+    /// 手順書のあるプロセスのID
+    pub async fn processes_with_runbooks(&self) -> Vec<String> {
+        let processes = self.processes.read().await;
+        let mut ids = Vec::new();
+        for (id, process) in processes.iter() {
+            if process.read().await.info.runbook.is_some() {
+                ids.push(id.clone());
             }
         }
+        ids.sort();
+        ids
+    }
 
-        drop(processes);
-
-        if !errors.is_empty() {
-            warn!("Some processes failed to stop: {:?}", errors);
+    /// プロセスごとの出力を伏せるルールの上書きを置き換える（Noneで設定のルールのみにする）
+    pub async fn set_process_redaction(
+        &self,
+        id: String,
+        redaction: Option<RedactionOverride>,
+    ) -> Result<(), String> {
+        if let Some(redaction) = &redaction {
+            redaction::validate_override(redaction)?;
         }
+        let global = self.get_settings().await?.redaction_rules;
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let mut process = process_arc.write().await;
+        let before = Self::to_db_process_info(&process.info);
 
-        info!("Stopped {} running process(es)", stopped_processes.len());
+        process.info.redaction = redaction;
+        Self::apply_redaction(&process, &global);
+        info!("Updated process '{}' redaction rules", id);
 
-        Ok(stopped_processes)
+        let db_info = Self::to_db_process_info(&process.info);
+        if let Err(e) = self.persistence.update_process(&db_info).await {
+            return Err(format!("Failed to persist process update: {e}"));
+        }
+        self.record_change(ChangeTarget::Process, &id, Some(&before), Some(&db_info))
+            .await;
+        Ok(())
     }
 
-    /// プロセスのステータスを取得
-    pub async fn get_process_status(&self, id: String) -> Result<ProcessStatus, String> {
+    /// 設定のルールとプロセスの上書きをまとめ、出力のバッファに設定する
+    fn apply_redaction(process: &ManagedProcess, global: &[RedactionRule]) {
+        let redactor = redaction::compile(global, process.info.redaction.as_ref());
+        process.stdout_buffer.set_redactor(redactor.clone());
+        process.stderr_buffer.set_redactor(redactor);
+    }
+
+    /// 定義の環境変数と、実行中のプロセスを起動したときの環境変数を比べる
+    pub async fn diff_process_env(&self, id: String) -> Result<EnvDiff, String> {
         let processes = self.processes.read().await;
         let process_arc = processes
             .get(&id)
             .ok_or_else(|| format!("Process '{id}' not found"))?;
-
         let process = process_arc.read().await;
 
-        let uptime_seconds = match &process.info.state {
-            ProcessState::Running { started_at, .. } => {
-                Some((chrono::Utc::now() - *started_at).num_seconds() as u64)
-            }
-            _ => None,
+        let spawned = process.spawned_env.as_ref().filter(|_| {
+            matches!(
+                process.info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            )
+        });
+        Ok(env_diff::diff(
+            &id,
+            &process.info.env,
+            spawned.map(|(env, _)| env),
+            spawned.map_or(&[], |(_, injected)| injected.as_slice()),
+        ))
+    }
+
+    /// 起動時の実行環境を説明する
+    ///
+    /// `run` を省略すると直近の実行。`compare_with` を省略すると、その1つ前の実行と比べる。
+    pub async fn explain_run_environment(
+        &self,
+        id: String,
+        run: Option<u64>,
+        compare_with: Option<u64>,
+        include_env: bool,
+    ) -> Result<String, String> {
+        let processes = self.processes.read().await;
+        let process_arc = processes
+            .get(&id)
+            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        let process = process_arc.read().await;
+        let history = &process.run_history;
+        let available = || {
+            history
+                .iter()
+                .map(|r| r.run.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let find = |number: u64| {
+            history.iter().find(|r| r.run == number).ok_or_else(|| {
+                format!(
+                    "Run {number} of '{id}' is not recorded (recorded runs: {})",
+                    available()
+                )
+            })
         };
 
-        Ok(ProcessStatus {
-            info: process.info.clone(),
-            cpu_usage: None,    // TODO: 実装
-            memory_usage: None, // TODO: 実装
-            uptime_seconds,
-        })
+        let target = match run {
+            Some(number) => find(number)?,
+            None => history.back().ok_or_else(|| {
+                format!("Process '{id}' has not been started since the server started")
+            })?,
+        };
+        let compare = match compare_with {
+            Some(number) => Some(find(number)?),
+            None => history.iter().rev().find(|r| r.run < target.run),
+        };
+        Ok(run_environment::render(target, compare, include_env))
     }
 
-    /// プロセスの出力を取得
-    pub async fn get_process_output(
+    /// 同じプロセスの2つの期間の出力を比べる
+    ///
+    /// 期間を指定しなければ、`restart` 回前（既定は直近）の再起動の前後の実行を比べる。
+    pub async fn diff_output_windows(
         &self,
         id: String,
-        stream: OutputStream,
-        lines: Option<u32>,
-    ) -> Result<Vec<String>, String> {
+        windows: Option<(TimeWindow, TimeWindow)>,
+        restart: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<OutputDiff, String> {
         let processes = self.processes.read().await;
         let process_arc = processes
             .get(&id)
             .ok_or_else(|| format!("Process '{id}' not found"))?;
-
         let process = process_arc.read().await;
 
-        let n = lines.unwrap_or(100) as usize;
-
-        let output = match stream {
-            OutputStream::Stdout => process.stdout_buffer.get_last_n(n).await,
-            OutputStream::Stderr => process.stderr_buffer.get_last_n(n).await,
-            OutputStream::Both => {
-                let mut combined = process.stdout_buffer.get_last_n(n / 2).await;
-                combined.extend(process.stderr_buffer.get_last_n(n / 2).await);
-                combined
+        let (before, after) = match windows {
+            Some(windows) => windows,
+            None => {
+                let starts: Vec<_> = process.start_history.iter().copied().collect();
+                output_diff::restart_windows(&starts, restart.unwrap_or(1))?
             }
         };
+        let lines = buffer::interleave(&process.stdout_buffer, &process.stderr_buffer, None).await;
+        let retained_since = process
+            .stdout_buffer
+            .retained_since()
+            .await
+            .max(process.stderr_buffer.retained_since().await);
+
+        Ok(output_diff::diff(
+            &id,
+            &lines,
+            retained_since,
+            before,
+            after,
+            limit.unwrap_or(output_diff::DEFAULT_LIMIT),
+        ))
+    }
 
-        Ok(output)
+    /// スナップショット・復元と変更操作の排他（Web APIの変更操作もこれで待たせる）
+    pub fn fence(&self) -> Arc<OperationFence> {
+        self.fence.clone()
     }
 
-    /// すべてのプロセスをリスト
-    pub async fn list_processes(&self, filter: Option<ProcessFilter>) -> Vec<ProcessInfo> {
-        let processes = self.processes.read().await;
-        let mut result = Vec::new();
+    /// 排他を取っている操作とその進捗
+    pub fn operation_status(&self) -> Option<FencedOperation> {
+        self.fence.status()
+    }
 
-        for process_arc in processes.values() {
-            let process = process_arc.read().await;
-            let info = &process.info;
+    // Settings management methods
+    pub async fn get_settings(&self) -> Result<Settings, String> {
+        self.persistence.get_settings().await
+    }
 
-            // フィルタリング
-            if let Some(ref f) = filter {
-                // 状態フィルタ
-                if let Some(ref state_filter) = f.state {
-                    let matches = match state_filter {
-                        ProcessStateFilter::Running => {
-                            matches!(info.state, ProcessState::Running { .. })
-                        }
-                        ProcessStateFilter::Stopped => {
-                            matches!(info.state, ProcessState::Stopped { .. })
-                        }
-                        ProcessStateFilter::Failed => {
-                            matches!(info.state, ProcessState::Failed { .. })
-                        }
-                        ProcessStateFilter::All => true,
-                    };
-                    if !matches {
-                        continue;
-                    }
-                }
+    pub async fn save_settings(&self, settings: Settings) -> Result<(), String> {
+        workspace_quota::validate(&settings.workspace_quotas)?;
+        approval::validate(&settings.approvals)?;
+        log_sinks::validate(&settings.log_sinks)?;
+        redaction::validate(&settings.redaction_rules)?;
+        maintenance::validate(&settings.maintenance_windows)?;
+        energy::validate(&settings.energy_cost)?;
+        adoption::validate(&settings.auto_adopt_workspaces)?;
+        buffer_memory::validate(&settings.output_memory)?;
+        notification_routing::validate(&settings.notification_routes)?;
+        let before = self.get_settings().await.unwrap_or_default();
+        let changes = crate::settings::changes(&before, &settings);
+        self.persistence.update_settings(settings).await?;
+        self.event_system.emit_setting_changes(changes);
+        Ok(())
+    }
 
-                // 名前パターンフィルタ
-                if let Some(ref pattern) = f.name_pattern
-                    && !info.id.contains(pattern)
-                    && !info.command.contains(pattern)
-                {
-                    continue;
+    /// 設定のキーを1つ変更する（型・範囲・選択肢を検証し、変更前後の値を返す）
+    pub async fn set_setting(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<crate::settings::SettingChange, String> {
+        let settings = self.get_settings().await?;
+        let old_value = crate::settings::get(&settings, key)?;
+        let updated = crate::settings::set(&settings, key, value)?;
+        let new_value = crate::settings::get(&updated, key)?;
+        self.save_settings(updated).await?;
+        Ok(crate::settings::SettingChange {
+            key: key.to_string(),
+            old_value,
+            new_value,
+            changed_at: Utc::now(),
+        })
+    }
+
+    // Maintenance windows
+
+    /// プロセスに適用中のメンテナンスウィンドウ
+    async fn active_maintenance(&self, info: &ProcessInfo) -> Option<ActiveMaintenance> {
+        let windows = self.get_settings().await.ok()?.maintenance_windows;
+        maintenance::active_for(&windows, info, Utc::now())
+    }
+
+    /// メンテナンスウィンドウを一覧（現在の期間・次の開始時刻・対象のプロセス付き）
+    pub async fn list_maintenance_windows(&self) -> Result<Vec<MaintenanceWindowStatus>, String> {
+        let windows = self.get_settings().await?.maintenance_windows;
+        let processes = self.list_processes(None).await;
+        let now = Utc::now();
+        Ok(windows
+            .into_iter()
+            .map(|window| {
+                let mut processes_in_scope: Vec<String> = processes
+                    .iter()
+                    .filter(|info| maintenance::applies_to(&window, info))
+                    .map(|info| info.id.clone())
+                    .collect();
+                processes_in_scope.sort();
+                MaintenanceWindowStatus {
+                    active: maintenance::current_period(&window, now),
+                    next_start: maintenance::next_start(&window, now),
+                    processes_in_scope,
+                    window,
                 }
-            }
+            })
+            .collect())
+    }
 
-            result.push(info.clone());
+    /// メンテナンスウィンドウを追加する（同じ名前のものがあれば置き換える）
+    pub async fn set_maintenance_window(
+        &self,
+        window: MaintenanceWindow,
+    ) -> Result<MaintenanceWindowStatus, String> {
+        let name = window.name.clone();
+        let mut settings = self.get_settings().await?;
+        match settings
+            .maintenance_windows
+            .iter_mut()
+            .find(|existing| existing.name == name)
+        {
+            Some(existing) => *existing = window,
+            None => settings.maintenance_windows.push(window),
         }
+        self.save_settings(settings).await?;
+        self.list_maintenance_windows()
+            .await?
+            .into_iter()
+            .find(|status| status.window.name == name)
+            .ok_or_else(|| format!("Maintenance window '{name}' not found"))
+    }
 
-        result
+    /// メンテナンスウィンドウを削除する
+    pub async fn remove_maintenance_window(&self, name: &str) -> Result<(), String> {
+        let mut settings = self.get_settings().await?;
+        let before = settings.maintenance_windows.len();
+        settings
+            .maintenance_windows
+            .retain(|window| window.name != name);
+        if settings.maintenance_windows.len() == before {
+            return Err(format!("Maintenance window '{name}' not found"));
+        }
+        self.save_settings(settings).await
     }
 
-    /// プロセスを削除
-    pub async fn remove_process(&self, id: String) -> Result<(), String> {
-        // まず停止を試みる
-        let _ = self.stop_process(id.clone(), Some(5000)).await;
+    /// ログシンクの転送状況（全体設定と実行中のプロセスが使っている転送先）
+    pub async fn log_sink_health(&self) -> Vec<LogSinkHealth> {
+        let configured = self
+            .get_settings()
+            .await
+            .map(|settings| settings.log_sinks)
+            .unwrap_or_default();
+        self.log_forwarder.health(&configured)
+    }
 
-        let mut processes = self.processes.write().await;
-        processes
-            .remove(&id)
-            .ok_or_else(|| format!("Process '{id}' not found"))?;
+    // History export
 
-        // Delete from persistence
-        if let Err(e) = self.persistence.delete_process(&id).await {
-            tracing::warn!("Failed to delete persisted process: {}", e);
+    /// 実行・イベント・出力の行数の推移を、期間を指定してCSVまたはParquetで書き出す
+    pub async fn export_history(
+        &self,
+        window: TimeWindow,
+        format: ExportFormat,
+        output_dir: Option<PathBuf>,
+    ) -> Result<HistoryExport, String> {
+        let events = self.event_system.recent_events(None);
+        let mut runs = Table::new(
+            "runs",
+            vec![
+                ("process_id", ColumnType::Text),
+                ("run", ColumnType::Integer),
+                ("started_at", ColumnType::Timestamp),
+                ("ended_at", ColumnType::Timestamp),
+                ("duration_secs", ColumnType::Float),
+                ("exit_code", ColumnType::Integer),
+                ("outcome", ColumnType::Text),
+                ("runtime", ColumnType::Text),
+                ("command", ColumnType::Text),
+            ],
+        );
+        let mut metrics = Table::new(
+            "metrics",
+            vec![
+                ("process_id", ColumnType::Text),
+                ("stream", ColumnType::Text),
+                ("bucket_start", ColumnType::Timestamp),
+                ("lines", ColumnType::Integer),
+                ("error_lines", ColumnType::Integer),
+            ],
+        );
+        let from = window.from.map_or(i64::MIN, |at| at.timestamp());
+        let to = window.to.map_or(i64::MAX, |at| at.timestamp());
+
+        let processes = self.processes.read().await;
+        let mut ids: Vec<&String> = processes.keys().collect();
+        ids.sort();
+        for id in ids {
+            let process = processes[id].read().await;
+            for run in process
+                .run_history
+                .iter()
+                .filter(|run| window.contains(run.started_at))
+            {
+                // 起動後の最初の終了がその実行の終了
+                let stopped = timeline::run_end(&events, id, run.started_at);
+                let exit_code = timeline::exit_code(stopped);
+                let outcome = timeline::run_outcome(stopped);
+                let ended_at = stopped.map(|event| event.timestamp);
+                let duration =
+                    ended_at.map(|end| (end - run.started_at).num_milliseconds() as f64 / 1000.0);
+                runs.rows.push(vec![
+                    HistoryValue::Text(id.clone()),
+                    HistoryValue::Integer(run.run as i64),
+                    HistoryValue::Timestamp(run.started_at),
+                    ended_at.into(),
+                    duration.into(),
+                    exit_code.into(),
+                    HistoryValue::Text(outcome.to_string()),
+                    HistoryValue::Text(run.runtime.clone()),
+                    HistoryValue::Text(
+                        std::iter::once(run.command.as_str())
+                            .chain(run.args.iter().map(String::as_str))
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    ),
+                ]);
+            }
+            for (stream, buffer) in [
+                ("stdout", &process.stdout_buffer),
+                ("stderr", &process.stderr_buffer),
+            ] {
+                for (start, count) in buffer.line_samples(from, to) {
+                    metrics.rows.push(vec![
+                        HistoryValue::Text(id.clone()),
+                        HistoryValue::Text(stream.to_string()),
+                        DateTime::from_timestamp(start, 0).into(),
+                        HistoryValue::Integer(count.lines as i64),
+                        HistoryValue::Integer(count.errors as i64),
+                    ]);
+                }
+            }
         }
+        drop(processes);
 
-        Ok(())
+        let mut event_table = Table::new(
+            "events",
+            vec![
+                ("timestamp", ColumnType::Timestamp),
+                ("process_id", ColumnType::Text),
+                ("event_type", ColumnType::Text),
+                ("context", ColumnType::Text),
+            ],
+        );
+        for event in events
+            .iter()
+            .filter(|event| window.contains(event.timestamp))
+        {
+            let event_type = serde_json::to_value(&event.event_type)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string));
+            event_table.rows.push(vec![
+                HistoryValue::Timestamp(event.timestamp),
+                HistoryValue::Text(event.process_id.clone()),
+                event_type.into(),
+                event.context.as_ref().map(|c| c.to_string()).into(),
+            ]);
+        }
+
+        let now = Utc::now();
+        let dir = output_dir.unwrap_or_else(|| history_export::default_dir(now));
+        let tables = history_export::write_tables(&[runs, event_table, metrics], &dir, format)?;
+        info!("Exported run history as {:?} to {}", format, dir.display());
+        Ok(HistoryExport {
+            format,
+            directory: dir.display().to_string(),
+            from: window.from,
+            to: window.to,
+            tables,
+        })
     }
 
-    /// Export processes to JSON file
-    pub async fn export_processes(&self, file_path: Option<String>) -> Result<String, String> {
-        let path = match file_path {
-            Some(p) => p,
-            None => {
-                let snapshot_dir = std::env::var("HOME")
-                    .map(|home| format!("{home}/.vantage"))
-                    .unwrap_or_else(|_| ".vantage".to_string());
-                format!("{snapshot_dir}/processes.json")
-            }
+    /// プロセスのタイムライン（期間内に始まった項目のみ）
+    pub async fn get_process_timeline(
+        &self,
+        id: &str,
+        window: TimeWindow,
+    ) -> Result<ProcessTimeline, String> {
+        let runs: Vec<RunEnvironment> = {
+            let processes = self.processes.read().await;
+            let process = processes
+                .get(id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?
+                .read()
+                .await;
+            process.run_history.iter().cloned().collect()
         };
+        let events = self.event_system.recent_events(None);
+        let changes = self.persistence.change_history().await?;
+        let ci_failures = self
+            .list_notifications(false, Some(NotificationKind::CiFailed), None)
+            .await?;
+        Ok(timeline::build(
+            id,
+            &runs,
+            &events,
+            &changes.undo,
+            &ci_failures,
+            window,
+            Utc::now(),
+        ))
+    }
 
-        // Export to JSON file
-        self.persistence.export_to_file(&path).await?;
+    // Notifications inbox
 
-        Ok(path)
+    /// 受信箱に通知を追加する
+    pub async fn notify(&self, notification: Notification) {
+        notifications::record(&self.persistence, notification).await;
     }
 
-    /// Export processes to YAML file
-    pub async fn export_yaml(
+    /// 通知がどのルールに一致するかを調べる
+    ///
+    /// `process_id` を指定した場合はそのプロセスのタグと作業ディレクトリを使う。`routes` を
+    /// 指定した場合は設定のルールの代わりに使う（保存する前のルールを試せる）。
+    pub async fn test_notification_route(
         &self,
-        file_path: Option<String>,
-        only_auto_start: bool,
-    ) -> Result<String, String> {
-        let path = match file_path {
-            Some(p) => p,
-            None => {
-                let snapshot_dir = std::env::var("HOME")
-                    .map(|home| format!("{home}/.vantage"))
-                    .unwrap_or_else(|_| ".vantage".to_string());
-                format!("{snapshot_dir}/snapshot.yaml")
+        kind: NotificationKind,
+        severity: Option<NotificationSeverity>,
+        process_id: Option<String>,
+        mut tags: Vec<String>,
+        mut workspace: Option<String>,
+        routes: Option<Vec<NotificationRoute>>,
+    ) -> Result<RouteTest, String> {
+        let routes = match routes {
+            Some(routes) => {
+                notification_routing::validate(&routes)?;
+                routes
             }
+            None => self.get_settings().await?.notification_routes,
         };
+        if let Some(id) = process_id {
+            let processes = self.processes.read().await;
+            let process = processes
+                .get(&id)
+                .ok_or_else(|| format!("Process '{id}' not found"))?
+                .read()
+                .await;
+            for tag in &process.info.tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+            if workspace.is_none() {
+                workspace = process
+                    .info
+                    .cwd
+                    .as_ref()
+                    .map(|cwd| cwd.to_string_lossy().to_string());
+            }
+        }
+        let mut subject = RouteSubject::new(kind, tags, workspace);
+        if let Some(severity) = severity {
+            subject.severity = severity;
+        }
+        Ok(notification_routing::test(&routes, subject))
+    }
 
-        self.persistence
-            .export_to_yaml(&path, only_auto_start)
-            .await?;
+    /// 通知の一覧（新しい順、`unread_only` なら未読のみ、最大 `limit` 件）
+    pub async fn list_notifications(
+        &self,
+        unread_only: bool,
+        kind: Option<NotificationKind>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Notification>, String> {
+        let notifications = self.persistence.list_notifications(unread_only).await?;
+        Ok(notifications
+            .into_iter()
+            .filter(|n| kind.is_none_or(|kind| n.kind == kind))
+            .take(limit.unwrap_or(usize::MAX))
+            .collect())
+    }
 
-        Ok(path)
+    /// 通知を既読にする（`ids` がNoneならすべて）。既読にした件数を返す
+    pub async fn mark_notifications_read(&self, ids: Option<&[String]>) -> Result<usize, String> {
+        self.persistence.mark_notifications_read(ids).await
     }
 
-    /// Import processes from YAML file
-    pub async fn import_yaml(&self, file_path: &str) -> Result<(), String> {
-        let imported = self.persistence.import_from_yaml(file_path).await?;
+    /// 未読の通知の件数
+    pub async fn unread_notification_count(&self) -> Result<usize, String> {
+        self.persistence.count_unread_notifications().await
+    }
 
-        // Update local process cache
-        let mut processes = self.processes.write().await;
-        for (id, info) in imported {
-            let process_info = crate::process::types::ProcessInfo {
-                id: info.process_id.clone(),
-                command: info.command,
-                args: info.args,
-                env: info.env,
-                cwd: info.cwd.map(std::path::PathBuf::from),
-                state: crate::process::types::ProcessState::NotStarted,
-                auto_start_on_restore: info.auto_start_on_restore,
-            };
+    // Approval workflow
+
+    /// 操作に承認が必要なら承認待ちとして登録して返す（不要な場合はNone）
+    pub async fn request_approval(
+        &self,
+        operation: ApprovalOperation,
+    ) -> Result<Option<ApprovalRequest>, String> {
+        let settings = self.get_settings().await?;
+        let Some(mode) = approval::mode(&settings.approvals, operation.tool()) else {
+            return Ok(None);
+        };
+        let now = Utc::now();
+        let expires_at = match mode {
+            ApprovalMode::Deferred => None,
+            ApprovalMode::Confirm { timeout } => {
+                Some(now + chrono::Duration::from_std(timeout).unwrap_or_default())
+            }
+        };
+
+        let request = ApprovalRequest {
+            approval_id: vantage_persistence::generate_id(),
+            tool: operation.tool().to_string(),
+            arguments: operation.arguments(),
+            summary: operation.summary(),
+            status: ApprovalStatus::Pending,
+            requested_at: now,
+            decided_at: None,
+            decided_by: None,
+            reason: None,
+            executed_at: None,
+            result: None,
+            waiting: expires_at.is_some(),
+            expires_at,
+        };
+        self.persistence.save_approval(&request).await?;
+        notifications::record(&self.persistence, notifications::approval_pending(&request)).await;
+        info!(
+            "[approval] '{}' is waiting for approval: {}",
+            request.approval_id, request.summary
+        );
+        Ok(Some(request))
+    }
 
-            let process = ManagedProcess {
-                info: process_info,
-                stdout_buffer: CircularBuffer::new(1000),
-                stderr_buffer: CircularBuffer::new(1000),
-                child: None,
-                output_handles: None,
+    /// 確認が必要なリクエストの判断を待つ
+    ///
+    /// 承認された場合は実行が終わるまで待ち、判断と結果を記録したリクエストを返す。期限を過ぎるか
+    /// `cancelled` が完了した場合は `expired` にして返す。待っている間、`on_wait` に経過時間を渡す。
+    pub async fn wait_for_approval(
+        &self,
+        approval_id: &str,
+        cancelled: impl std::future::Future<Output = ()>,
+        mut on_wait: impl FnMut(&ApprovalRequest, Duration),
+    ) -> Result<ApprovalRequest, String> {
+        let started = std::time::Instant::now();
+        let mut cancelled = std::pin::pin!(cancelled);
+        let mut was_cancelled = false;
+        loop {
+            let request = self.get_approval(approval_id).await?;
+            match request.status {
+                ApprovalStatus::Pending => {}
+                // 承認済みで実行中
+                ApprovalStatus::Approved => {}
+                _ => return Ok(request),
+            }
+            let reason = if was_cancelled {
+                Some("The agent cancelled the call".to_string())
+            } else {
+                request
+                    .expires_at
+                    .filter(|expires_at| Utc::now() >= *expires_at)
+                    .map(|_| "No decision was made before the confirmation timed out".to_string())
             };
+            if let Some(reason) = reason.filter(|_| request.status == ApprovalStatus::Pending) {
+                // 期限と同時に判断された場合はその判断を待つ
+                if let Ok(expired) = self
+                    .persistence
+                    .transition_approval(approval_id, ApprovalStatus::Pending, |request| {
+                        request.status = ApprovalStatus::Expired;
+                        request.decided_at = Some(Utc::now());
+                        request.reason = Some(reason);
+                        request.waiting = false;
+                    })
+                    .await
+                {
+                    warn!(
+                        "[approval] '{}' expired: {}",
+                        approval_id,
+                        expired.reason.as_deref().unwrap_or_default()
+                    );
+                    return Ok(expired);
+                }
+                continue;
+            }
 
-            processes.insert(id, Arc::new(RwLock::new(process)));
+            on_wait(&request, started.elapsed());
+            tokio::select! {
+                _ = &mut cancelled, if !was_cancelled => was_cancelled = true,
+                _ = tokio::time::sleep(APPROVAL_POLL_INTERVAL) => {}
+            }
         }
-
-        Ok(())
     }
 
-    /// Create auto-start snapshot on shutdown
-    pub async fn create_auto_start_snapshot(&self) -> Result<String, String> {
-        self.persistence.create_auto_start_snapshot(None).await
+    /// 承認リクエストを取得
+    pub async fn get_approval(&self, approval_id: &str) -> Result<ApprovalRequest, String> {
+        self.persistence
+            .get_approval(approval_id)
+            .await?
+            .ok_or_else(|| format!("Approval '{approval_id}' not found"))
     }
 
-    /// Create YAML snapshot on shutdown
-    pub async fn create_yaml_snapshot_on_shutdown(&self) -> Result<(), String> {
-        self.persistence.create_auto_start_snapshot(None).await?;
-        Ok(())
+    /// 承認リクエストを一覧（新しい順）
+    pub async fn list_approvals(
+        &self,
+        status: Option<ApprovalStatus>,
+    ) -> Result<Vec<ApprovalRequest>, String> {
+        self.persistence.list_approvals(status).await
     }
 
-    /// Restore from YAML snapshot on startup
-    pub async fn restore_yaml_snapshot(&self) -> Result<(), String> {
-        let snapshot_dir = std::env::var("HOME")
-            .map(|home| format!("{home}/.vantage"))
-            .unwrap_or_else(|_| ".vantage".to_string());
-        let snapshot_path = format!("{snapshot_dir}/snapshot.yaml");
+    /// 承認待ちのリクエストを承認または却下する
+    ///
+    /// 承認した場合はその場で操作を実行し、結果を記録したリクエストを返す。
+    pub async fn decide_approval(
+        &self,
+        approval_id: &str,
+        approve: bool,
+        decided_by: String,
+        reason: Option<String>,
+    ) -> Result<ApprovalRequest, String> {
+        let status = if approve {
+            ApprovalStatus::Approved
+        } else {
+            ApprovalStatus::Denied
+        };
+        let request = self
+            .persistence
+            .transition_approval(approval_id, ApprovalStatus::Pending, |request| {
+                request.status = status;
+                request.decided_at = Some(Utc::now());
+                request.decided_by = Some(decided_by);
+                request.reason = reason;
+                request.waiting = false;
+            })
+            .await?;
+        warn!(
+            "[approval] '{}' {} by {}: {}",
+            approval_id,
+            status.as_str(),
+            request.decided_by.as_deref().unwrap_or_default(),
+            request.summary
+        );
+        if !approve {
+            return Ok(request);
+        }
 
-        if !std::path::Path::new(&snapshot_path).exists() {
-            tracing::debug!("No YAML snapshot found at {}", snapshot_path);
-            return Ok(());
+        let outcome = match ApprovalOperation::from_request(&request.tool, &request.arguments) {
+            Ok(operation) => self.execute_approved(operation).await,
+            Err(e) => Err(e),
+        };
+        match &outcome {
+            Ok(_) => info!("[approval] '{}' executed", approval_id),
+            Err(e) => warn!("[approval] '{}' failed: {}", approval_id, e),
         }
+        self.persistence
+            .transition_approval(approval_id, ApprovalStatus::Approved, |request| {
+                request.executed_at = Some(Utc::now());
+                (request.status, request.result) = match outcome {
+                    Ok(result) => (ApprovalStatus::Executed, Some(result)),
+                    Err(e) => (ApprovalStatus::Failed, Some(e)),
+                };
+            })
+            .await
+    }
 
-        match self.import_yaml(&snapshot_path).await {
-            Ok(_) => {
-                tracing::info!("Successfully restored from YAML snapshot");
-                Ok(())
+    /// 承認された操作を実行し、結果のメッセージを返す
+    async fn execute_approved(&self, operation: ApprovalOperation) -> Result<String, String> {
+        match operation {
+            ApprovalOperation::RemoveProcess { id } => {
+                self.remove_process(id.clone()).await?;
+                Ok(format!("Process '{id}' removed successfully"))
             }
-            Err(e) => {
-                tracing::warn!("Failed to restore YAML snapshot: {}", e);
-                // Don't fail startup if snapshot restore fails
-                Ok(())
+            ApprovalOperation::RemoveProcesses { expression } => {
+                let report = self.remove_matching_processes(&expression, false).await?;
+                serde_json::to_string_pretty(&report)
+                    .map_err(|e| format!("Failed to serialize response: {e}"))
+            }
+            ApprovalOperation::EmptyRecycleBin { id } => {
+                let purged = self.empty_recycle_bin(id.as_deref()).await?;
+                Ok(serde_json::json!({ "purged": purged }).to_string())
+            }
+            ApprovalOperation::ImportProcesses { file_path } => {
+                self.import_processes(&file_path).await?;
+                Ok(format!("Processes imported successfully from {file_path}"))
+            }
+            ApprovalOperation::ImportYaml { file_path, format } => {
+                self.import_yaml(&file_path, format).await?;
+                Ok(format!("Processes imported successfully from {file_path}"))
+            }
+            ApprovalOperation::RestoreSnapshot {} => {
+                self.restore_snapshot().await?;
+                Ok("Snapshot restored successfully".to_string())
+            }
+            ApprovalOperation::RestoreContext {
+                name,
+                stop_others,
+                checkout_branch,
+            } => {
+                let report = self
+                    .restore_context(&name, stop_others, checkout_branch)
+                    .await?;
+                serde_json::to_string_pretty(&report)
+                    .map_err(|e| format!("Failed to serialize response: {e}"))
+            }
+            ApprovalOperation::DeleteContext { name } => {
+                self.delete_context(&name).await?;
+                Ok(format!("Deleted context '{name}'"))
             }
         }
     }
 
-    /// Auto-start processes marked with auto_start_on_restore flag
-    /// Returns a list of successfully started process IDs
-    pub async fn start_auto_start_processes(&self) -> Result<Vec<String>, String> {
-        // 1. auto_start_on_restore が true のプロセスIDを収集
-        let processes = self.processes.read().await;
-        let auto_start_ids: Vec<String> = processes.keys().cloned().collect();
-        drop(processes); // 早めにロック解放
+    // Session context methods
 
-        let mut started = Vec::new();
-        let mut errors = Vec::new();
+    /// 現在の作業状態をセッションコンテキストとして保存
+    ///
+    /// `workspace` を省略した場合はサーバーの作業ディレクトリを使う。
+    pub async fn save_context(
+        &self,
+        name: String,
+        workspace: Option<PathBuf>,
+        notes: Option<String>,
+    ) -> Result<SessionContext, String> {
+        let workspace = match workspace {
+            Some(workspace) => Some(paths::expand_path(&workspace)?),
+            None => std::env::current_dir().ok(),
+        };
+        let git_branch = match &workspace {
+            Some(dir) => session_context::current_git_branch(dir).await,
+            None => None,
+        };
 
-        // 2. 各プロセスをチェックして起動
-        for id in auto_start_ids {
-            let processes = self.processes.read().await;
-            if let Some(process_arc) = processes.get(&id) {
-                let process = process_arc.read().await;
-                let should_start = process.info.auto_start_on_restore;
-                let state_is_not_started = matches!(process.info.state, ProcessState::NotStarted);
-                drop(process);
-                drop(processes);
-
-                if should_start && state_is_not_started {
-                    match self.start_process(id.clone()).await {
-                        Ok(pid) => {
-                            tracing::info!("Auto-started process '{}' with PID {}", id, pid);
-                            started.push(id);
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to auto-start process '{}': {}", id, e);
-                            errors.push(format!("{}: {}", id, e));
-                        }
+        let mut running_processes: Vec<String> = self
+            .list_processes(None)
+            .await
+            .into_iter()
+            .filter(|p| {
+                matches!(
+                    p.state,
+                    ProcessState::Running { .. } | ProcessState::Paused { .. }
+                )
+            })
+            .map(|p| p.id)
+            .collect();
+        running_processes.sort();
+
+        let context = SessionContext {
+            name,
+            saved_at: Utc::now(),
+            workspace: workspace.map(|w| w.to_string_lossy().to_string()),
+            git_branch,
+            running_processes,
+            clipboard: self
+                .persistence
+                .get_clipboard_history(Some(session_context::CONTEXT_CLIPBOARD_ITEMS))
+                .await?,
+            notes,
+        };
+        self.persistence.save_context(&context).await?;
+        Ok(context)
+    }
+
+    /// セッションコンテキストを復元
+    ///
+    /// 保存時に実行中だったプロセスを起動し、クリップボードを戻す。
+    /// `stop_others` が指定されるとコンテキストに含まれない実行中プロセスを停止し、
+    /// `checkout_branch` が指定されると保存時のGitブランチをチェックアウトする。
+    pub async fn restore_context(
+        &self,
+        name: &str,
+        stop_others: bool,
+        checkout_branch: bool,
+    ) -> Result<ContextRestoreReport, String> {
+        let context = self.persistence.load_context(name).await?;
+        let mut report = ContextRestoreReport {
+            name: context.name.clone(),
+            workspace: context.workspace.clone(),
+            notes: context.notes.clone(),
+            ..Default::default()
+        };
+
+        if let (Some(workspace), Some(saved_branch)) = (&context.workspace, &context.git_branch) {
+            let dir = PathBuf::from(workspace);
+            let current_branch = session_context::current_git_branch(&dir).await;
+            let mut restore = GitBranchRestore {
+                saved_branch: saved_branch.clone(),
+                current_branch: current_branch.clone(),
+                checked_out: false,
+                error: None,
+            };
+            if checkout_branch && current_branch.as_ref() != Some(saved_branch) {
+                match session_context::checkout_git_branch(&dir, saved_branch).await {
+                    Ok(()) => restore.checked_out = true,
+                    Err(e) => restore.error = Some(e),
+                }
+            }
+            report.git = Some(restore);
+        }
+
+        let processes = self.list_processes(None).await;
+        if stop_others {
+            for info in &processes {
+                if matches!(
+                    info.state,
+                    ProcessState::Running { .. } | ProcessState::Paused { .. }
+                ) && !context.running_processes.contains(&info.id)
+                {
+                    match self.stop_process(info.id.clone(), None).await {
+                        Ok(()) => report.stopped.push(info.id.clone()),
+                        Err(e) => report.failed.push((info.id.clone(), e)),
                     }
                 }
             }
         }
 
-        if !errors.is_empty() {
-            tracing::warn!(
-                "Some processes failed to auto-start ({} failures): {:?}",
-                errors.len(),
-                errors
-            );
+        for id in &context.running_processes {
+            let Some(info) = processes.iter().find(|p| &p.id == id) else {
+                report.missing.push(id.clone());
+                continue;
+            };
+            if matches!(
+                info.state,
+                ProcessState::Running { .. } | ProcessState::Paused { .. }
+            ) {
+                report.already_running.push(id.clone());
+                continue;
+            }
+            match self.start_process(id.clone()).await {
+                Ok(_) => report.started.push(id.clone()),
+                Err(e) => report.failed.push((id.clone(), e)),
+            }
         }
 
-        Ok(started)
+        // 履歴は新しい順に保存されているため、古いものから戻す
+        let existing = self
+            .persistence
+            .get_clipboard_history(Some(usize::MAX))
+            .await?;
+        for item in context.clipboard.iter().rev() {
+            if existing.iter().any(|e| e.clipboard_id == item.clipboard_id) {
+                continue;
+            }
+            self.persistence.save_clipboard_item(item).await?;
+            report.restored_clipboard_items += 1;
+        }
+
+        Ok(report)
     }
 
-    /// Import processes from JSON file
-    pub async fn import_processes(&self, file_path: &str) -> Result<(), String> {
-        // Import from JSON file
-        self.persistence.import_from_file(file_path).await?;
+    pub async fn list_contexts(&self) -> Result<Vec<SessionContext>, String> {
+        self.persistence.list_contexts().await
+    }
 
-        // Reload processes into memory
-        self.load_persisted_processes().await?;
+    pub async fn delete_context(&self, name: &str) -> Result<(), String> {
+        self.persistence.delete_context(name).await
+    }
 
+    // Template management methods
+    pub async fn save_template(&self, template: ProcessTemplate) -> Result<(), String> {
+        template_schema::validate_definition(&template.variables)?;
+        let before = self.persistence.get_template(&template.template_id).await?;
+        self.persistence.save_template(&template).await?;
+        self.record_change(
+            ChangeTarget::Template,
+            &template.template_id,
+            before.as_ref(),
+            Some(&template),
+        )
+        .await;
         Ok(())
     }
 
-    /// Create a snapshot (YAML format)
-    pub async fn create_snapshot(&self) -> Result<String, String> {
-        self.persistence.create_auto_start_snapshot(None).await
+    pub async fn delete_template(&self, template_id: &str) -> Result<(), String> {
+        let before = self.persistence.get_template(template_id).await?;
+        self.persistence.delete_template(template_id).await?;
+        self.record_change(ChangeTarget::Template, template_id, before.as_ref(), None)
+            .await;
+        Ok(())
     }
 
-    /// Restore from the latest snapshot
-    pub async fn restore_snapshot(&self) -> Result<(), String> {
-        let restored = self.persistence.restore_yaml_snapshot(None).await?;
-
-        // Reload processes into memory
-        self.load_persisted_processes().await?;
-
-        // Start auto-start processes
-        for (id, info) in restored {
-            if info.auto_start_on_restore
-                && let Err(e) = self.start_process(id.clone()).await
-            {
-                tracing::warn!("Failed to auto-start process {}: {}", id, e);
-            }
-        }
-
-        Ok(())
+    pub async fn load_all_templates(&self) -> Result<Vec<ProcessTemplate>, String> {
+        self.persistence.list_templates().await
     }
 
-    /// Update process configuration (auto_start flags)
-    pub async fn update_process_config(
-        &self,
-        id: String,
-        auto_start_on_restore: Option<bool>,
-    ) -> Result<(), String> {
-        let processes = self.processes.read().await;
-        let process_arc = processes
-            .get(&id)
-            .ok_or_else(|| format!("Process '{id}' not found"))?;
+    pub async fn get_template(&self, template_id: &str) -> Result<Option<ProcessTemplate>, String> {
+        self.persistence.get_template(template_id).await
+    }
 
-        let mut process = process_arc.write().await;
+    // Process chains
 
-        if let Some(value) = auto_start_on_restore {
-            process.info.auto_start_on_restore = value;
-            info!(
-                "Updated process '{}' auto_start_on_restore to {}",
-                id, value
-            );
+    /// 連鎖を登録する（`after` が正常終了したら `action` を実行する）
+    pub async fn create_chain(
+        &self,
+        chain_id: Option<String>,
+        after: String,
+        action: ChainAction,
+        enabled: bool,
+    ) -> Result<ChainStatus, String> {
+        {
+            let processes = self.processes.read().await;
+            if !processes.contains_key(&after) {
+                return Err(format!("Process '{after}' not found"));
+            }
+            if let ChainAction::StartProcess { process_id } = &action
+                && !processes.contains_key(process_id)
+            {
+                return Err(format!("Process '{process_id}' not found"));
+            }
         }
-
-        // Persist the updated configuration
-        let db_info = Self::to_db_process_info(&process.info);
-        if let Err(e) = self.persistence.update_process(&db_info).await {
-            return Err(format!("Failed to persist process config update: {e}"));
+        if let ChainAction::RunTemplate { template_id, .. } = &action
+            && self.get_template(template_id).await?.is_none()
+        {
+            return Err(format!("Template '{template_id}' not found"));
         }
+        let chain_id =
+            chain_id.unwrap_or_else(|| format!("{}-then-{}", after, action.process_id()));
+        self.chains
+            .create(ProcessChain {
+                chain_id,
+                after,
+                action,
+                enabled,
+                created_at: Utc::now(),
+            })
+            .await
+    }
 
-        Ok(())
+    pub async fn remove_chain(&self, chain_id: &str) -> Result<(), String> {
+        self.chains.remove(chain_id).await
     }
 
-    /// Update process attributes (command, args, env, cwd, and flags)
-    pub async fn update_process(
+    pub async fn set_chain_enabled(
         &self,
-        id: String,
-        command: Option<String>,
-        args: Option<Vec<String>>,
-        env: Option<HashMap<String, String>>,
-        cwd: Option<String>,
-        auto_start_on_restore: Option<bool>,
-    ) -> Result<(), String> {
-        let processes = self.processes.read().await;
-        let process_arc = processes
-            .get(&id)
-            .ok_or_else(|| format!("Process '{id}' not found"))?;
+        chain_id: &str,
+        enabled: bool,
+    ) -> Result<ChainStatus, String> {
+        self.chains.set_enabled(chain_id, enabled).await
+    }
 
-        let mut process = process_arc.write().await;
+    pub async fn list_chains(&self, process_id: Option<&str>) -> Vec<ChainStatus> {
+        self.chains.list(process_id).await
+    }
 
-        // Update command if provided
-        if let Some(cmd) = command {
-            process.info.command = cmd.clone();
-            info!("Updated process '{}' command to '{}'", id, cmd);
+    /// 正常終了したプロセスに続く連鎖を実行する
+    async fn run_chains(&self, after: &str) {
+        for chain in self.chains.begin_runs(after, Utc::now()).await {
+            let outcome = self.run_chain_action(&chain.action).await;
+            match &outcome {
+                ChainOutcome::Failed { error } => warn!(
+                    "Chain '{}' failed to start '{}': {}",
+                    chain.chain_id,
+                    chain.action.process_id(),
+                    error
+                ),
+                _ => info!(
+                    "Chain '{}' ran after '{}' succeeded: {:?}",
+                    chain.chain_id, after, outcome
+                ),
+            }
+            self.chains.record_run(&chain.chain_id, outcome).await;
         }
+    }
 
-        // Update args if provided
-        if let Some(arguments) = args {
-            process.info.args = arguments.clone();
-            info!("Updated process '{}' args to {:?}", id, arguments);
+    async fn run_chain_action(&self, action: &ChainAction) -> ChainOutcome {
+        let id = action.process_id().to_string();
+        if let ChainAction::RunTemplate {
+            template_id,
+            values,
+            ..
+        } = action
+            && !self.processes.read().await.contains_key(&id)
+        {
+            let created = async {
+                let template = self
+                    .get_template(template_id)
+                    .await?
+                    .ok_or_else(|| format!("Template '{template_id}' not found"))?;
+                let info = template.instantiate(id.clone(), values.clone())?;
+                self.create_process(
+                    id.clone(),
+                    info.command,
+                    info.args,
+                    info.env,
+                    info.cwd.map(PathBuf::from),
+                    info.auto_start_on_restore,
+                )
+                .await
+            }
+            .await;
+            if let Err(error) = created {
+                return ChainOutcome::Failed { error };
+            }
         }
 
-        // Update env if provided
-        if let Some(environment) = env {
-            process.info.env = environment.clone();
-            info!("Updated process '{}' env variables", id);
+        match self.get_process_status(id.clone()).await {
+            Ok(status)
+                if matches!(
+                    status.info.state,
+                    ProcessState::Running { .. } | ProcessState::Paused { .. }
+                ) =>
+            {
+                return ChainOutcome::Skipped {
+                    reason: format!("Process '{id}' is already running"),
+                };
+            }
+            Ok(_) => {}
+            Err(error) => return ChainOutcome::Failed { error },
         }
-
-        // Update cwd if provided
-        if let Some(working_dir) = cwd {
-            process.info.cwd = Some(PathBuf::from(&working_dir));
-            info!("Updated process '{}' cwd to '{}'", id, working_dir);
+        match self.start_process(id).await {
+            Ok(pid) => ChainOutcome::Started { pid },
+            Err(error) => ChainOutcome::Failed { error },
         }
+    }
 
-        // Update auto_start flags if provided
-        if let Some(value) = auto_start_on_restore {
-            process.info.auto_start_on_restore = value;
-            info!(
-                "Updated process '{}' auto_start_on_restore to {}",
-                id, value
-            );
-        }
+    // Process schedules
 
-        // Persist the updated configuration
-        let db_info = Self::to_db_process_info(&process.info);
-        if let Err(e) = self.persistence.update_process(&db_info).await {
-            return Err(format!("Failed to persist process update: {e}"));
+    /// 定期実行を登録する（cron形式の時刻に `process_id` を起動する）
+    pub async fn create_schedule(
+        &self,
+        schedule_id: Option<String>,
+        process_id: String,
+        cron: String,
+        enabled: bool,
+    ) -> Result<ScheduleStatus, String> {
+        if !self.processes.read().await.contains_key(&process_id) {
+            return Err(format!("Process '{process_id}' not found"));
         }
+        let schedule_id = schedule_id.unwrap_or_else(|| format!("{process_id}-schedule"));
+        self.schedules
+            .create(ProcessSchedule {
+                schedule_id,
+                process_id,
+                cron,
+                enabled,
+                created_at: Utc::now(),
+            })
+            .await
+    }
 
-        Ok(())
+    pub async fn update_schedule(
+        &self,
+        schedule_id: &str,
+        cron: Option<String>,
+        enabled: Option<bool>,
+    ) -> Result<ScheduleStatus, String> {
+        self.schedules.update(schedule_id, cron, enabled).await
     }
 
-    // Settings management methods
-    pub async fn get_settings(&self) -> Result<Settings, String> {
-        self.persistence.get_settings().await
+    pub async fn remove_schedule(&self, schedule_id: &str) -> Result<(), String> {
+        self.schedules.remove(schedule_id).await
     }
 
-    pub async fn save_settings(&self, settings: Settings) -> Result<(), String> {
-        self.persistence.update_settings(settings).await
+    pub async fn list_schedules(&self, process_id: Option<&str>) -> Vec<ScheduleStatus> {
+        self.schedules.list(process_id).await
     }
 
-    // Template management methods
-    pub async fn save_template(&self, template: ProcessTemplate) -> Result<(), String> {
-        self.persistence.save_template(&template).await
+    /// 時刻になった定期実行のプロセスを起動する
+    async fn run_due_schedules(&self) {
+        for schedule in self.schedules.begin_runs(Utc::now()).await {
+            let outcome = self.run_schedule(&schedule.process_id).await;
+            match &outcome {
+                ScheduleOutcome::Failed { error } => warn!(
+                    "Schedule '{}' failed to start '{}': {}",
+                    schedule.schedule_id, schedule.process_id, error
+                ),
+                _ => info!(
+                    "Schedule '{}' ran at '{}': {:?}",
+                    schedule.schedule_id, schedule.cron, outcome
+                ),
+            }
+            if let Err(e) = self
+                .event_system
+                .emit_scheduled_run(schedule.process_id.clone(), &schedule.schedule_id, &outcome)
+                .await
+            {
+                warn!("Failed to emit scheduled run event: {}", e);
+            }
+            self.schedules
+                .record_run(&schedule.schedule_id, outcome)
+                .await;
+        }
     }
 
-    pub async fn delete_template(&self, template_id: &str) -> Result<(), String> {
-        self.persistence.delete_template(template_id).await
+    async fn run_schedule(&self, process_id: &str) -> ScheduleOutcome {
+        match self.get_process_status(process_id.to_string()).await {
+            Ok(status)
+                if matches!(
+                    status.info.state,
+                    ProcessState::Running { .. } | ProcessState::Paused { .. }
+                ) =>
+            {
+                return ScheduleOutcome::Skipped {
+                    reason: format!("Process '{process_id}' is still running"),
+                };
+            }
+            Ok(_) => {}
+            Err(error) => return ScheduleOutcome::Failed { error },
+        }
+        match self.start_process(process_id.to_string()).await {
+            Ok(pid) => ScheduleOutcome::Started { pid },
+            Err(error) => ScheduleOutcome::Failed { error },
+        }
     }
 
-    pub async fn load_all_templates(&self) -> Result<Vec<ProcessTemplate>, String> {
-        self.persistence.list_templates().await
+    /// テンプレートを試し描画する（プロセスは作らない）
+    pub async fn render_template(
+        &self,
+        template_id: &str,
+        values: HashMap<String, String>,
+        overrides: RenderOverrides,
+    ) -> Result<RenderedTemplate, String> {
+        let template = self
+            .get_template(template_id)
+            .await?
+            .ok_or_else(|| format!("Template '{template_id}' not found"))?;
+        Ok(template_render::render(&template, &values, overrides))
     }
 
-    pub async fn get_template(&self, template_id: &str) -> Result<Option<ProcessTemplate>, String> {
-        self.persistence.get_template(template_id).await
+    /// テンプレートをパラメータ行列で展開し、組み合わせごとにプロセスを作成
+    ///
+    /// すべての組み合わせを検証してから作成し、途中で失敗した場合は作成したプロセスを削除する。
+    /// テンプレートが変数として扱わない行列の値は環境変数として渡す。
+    pub async fn create_processes_from_template_matrix(
+        &self,
+        template_id: &str,
+        matrix: HashMap<String, Vec<String>>,
+        values: HashMap<String, String>,
+        id_prefix: Option<String>,
+        start: bool,
+    ) -> Result<Vec<MatrixInstance>, String> {
+        let template = self
+            .get_template(template_id)
+            .await?
+            .ok_or_else(|| format!("Template '{template_id}' not found"))?;
+        let combinations = template_matrix::expand(&matrix)?;
+        let prefix = id_prefix.unwrap_or_else(|| vscode_tasks::slug(&template.name));
+
+        let mut taken: Vec<String> = self.processes.read().await.keys().cloned().collect();
+        let mut planned = Vec::new();
+        for parameters in combinations {
+            let id =
+                vscode_tasks::unique_id(&template_matrix::process_id(&prefix, &parameters), &taken);
+            taken.push(id.clone());
+            let mut resolved = values.clone();
+            resolved.extend(parameters.clone());
+            let mut info = template.instantiate(id, resolved)?;
+            for (name, value) in &parameters {
+                if !template_matrix::uses_variable(&template, name) {
+                    info.env.insert(name.clone(), value.clone());
+                }
+            }
+            planned.push((parameters, info));
+        }
+
+        let mut created: Vec<MatrixInstance> = Vec::new();
+        for (parameters, info) in planned {
+            let process_id = info.process_id.clone();
+            if let Err(e) = self
+                .create_process(
+                    process_id.clone(),
+                    info.command,
+                    info.args,
+                    info.env,
+                    info.cwd.map(PathBuf::from),
+                    info.auto_start_on_restore,
+                )
+                .await
+            {
+                for instance in &created {
+                    if let Err(e) = self.remove_process(instance.process_id.clone()).await {
+                        warn!(
+                            "Failed to roll back process '{}': {}",
+                            instance.process_id, e
+                        );
+                    }
+                }
+                return Err(format!("Failed to create process '{process_id}': {e}"));
+            }
+            created.push(MatrixInstance {
+                process_id,
+                parameters,
+                start_error: None,
+            });
+        }
+
+        if start {
+            for instance in &mut created {
+                if let Err(e) = self.start_process(instance.process_id.clone()).await {
+                    instance.start_error = Some(e);
+                }
+            }
+        }
+        info!(
+            "Created {} process(es) from template '{}' with a parameter matrix",
+            created.len(),
+            template_id
+        );
+        Ok(created)
     }
 
     pub async fn search_templates(
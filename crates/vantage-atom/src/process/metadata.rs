@@ -0,0 +1,99 @@
+//! プロセスの任意のメタデータ
+//!
+//! 担当者・チケットURL・手順書へのリンクなどをキーと値で付けておき、一覧やステータスで返します。
+//! フィルター式では `metadata.<キー>` で参照でき、Webコンソールではバッジ（URLはリンク）で表示します。
+
+use std::collections::HashMap;
+
+/// キーの最大長
+pub const MAX_KEY_LEN: usize = 64;
+/// 値の最大長
+pub const MAX_VALUE_LEN: usize = 2048;
+/// 1つのプロセスに付けられる数
+pub const MAX_ENTRIES: usize = 32;
+
+/// メタデータの形式を確かめる
+pub fn validate(metadata: &HashMap<String, String>) -> Result<(), String> {
+    if metadata.len() > MAX_ENTRIES {
+        return Err(format!(
+            "A process can have at most {MAX_ENTRIES} metadata entries (got {})",
+            metadata.len()
+        ));
+    }
+    for (key, value) in metadata {
+        if key.is_empty() || key.len() > MAX_KEY_LEN {
+            return Err(format!(
+                "Metadata key '{key}' must be 1 to {MAX_KEY_LEN} characters"
+            ));
+        }
+        if !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            return Err(format!(
+                "Metadata key '{key}' may only contain letters, digits, '_', '-' and '.'"
+            ));
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(format!(
+                "Metadata value for '{key}' is longer than {MAX_VALUE_LEN} bytes"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 変更を適用する（値が `None` のキーは削除し、指定のないキーはそのまま残す）
+pub fn apply(
+    current: &HashMap<String, String>,
+    changes: HashMap<String, Option<String>>,
+) -> Result<HashMap<String, String>, String> {
+    let mut updated = current.clone();
+    for (key, value) in changes {
+        match value {
+            Some(value) => {
+                updated.insert(key, value);
+            }
+            None => {
+                updated.remove(&key);
+            }
+        }
+    }
+    validate(&updated)?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_merges_and_removes() {
+        let current = HashMap::from([
+            ("owner".to_string(), "alice".to_string()),
+            ("ticket".to_string(), "OPS-1".to_string()),
+        ]);
+        let updated = apply(
+            &current,
+            HashMap::from([
+                ("ticket".to_string(), None),
+                (
+                    "runbook".to_string(),
+                    Some("https://wiki.example.com/api".to_string()),
+                ),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(updated.len(), 2);
+        assert_eq!(updated["owner"], "alice");
+        assert!(!updated.contains_key("ticket"));
+
+        let error = apply(
+            &current,
+            HashMap::from([("bad key".to_string(), Some("x".to_string()))]),
+        )
+        .unwrap_err();
+        assert!(error.contains("'bad key'"));
+        assert!(validate(&HashMap::from([(String::new(), "x".to_string())])).is_err());
+    }
+}
@@ -1,11 +1,132 @@
+pub mod adoption;
+pub mod approval;
+pub mod blue_green;
+pub mod bookmarks;
 pub mod buffer;
+pub mod buffer_memory;
+pub mod capture;
+pub mod cgroup;
+pub mod chain;
+pub mod change_log;
+pub mod chaos;
+pub mod checks;
+pub mod container;
+pub mod crash_loop;
+pub mod debug_bundle;
+pub mod dependencies;
+pub mod diagnosis;
+pub mod discovery;
+pub mod energy;
+pub mod env_diff;
+pub mod env_docs;
+pub mod env_loader;
+pub mod failure_report;
+pub mod fence;
+pub mod filter_expr;
+pub mod groups;
+pub mod health_probe;
+pub mod history_export;
+pub mod kubernetes;
+pub mod lazy_start;
+pub mod log_quota;
+pub mod log_sinks;
+pub mod log_sources;
+pub mod maintenance;
 pub mod manager;
+pub mod metadata;
+pub mod network;
+pub mod notification_routing;
+pub mod notifications;
+pub mod output_diff;
+pub mod output_rates;
+pub mod parameters;
+pub mod pause;
+pub mod phases;
+pub mod port_owner;
+pub mod pre_op;
 pub mod protocol;
+pub mod reaper;
+pub mod recording;
+pub mod recycle_bin;
+pub mod redaction;
+pub mod repl;
+pub mod resource_samples;
+pub mod restart_policy;
+pub mod run_as;
+pub mod run_environment;
+pub mod runbook;
+pub mod safe_mode;
+pub mod sampling;
+pub mod schedule;
+pub mod session_context;
 pub mod shell;
+pub mod startup;
+pub mod stdin;
+pub mod template_matrix;
+pub mod template_render;
+pub mod timeline;
+pub mod tmux;
+pub mod tunnel;
 pub mod types;
+pub mod vscode_tasks;
+pub mod workspace_bundle;
+pub mod workspace_quota;
 
-pub use buffer::CircularBuffer;
+pub use adoption::{AdoptionReport, ExternalProcess};
+pub use approval::ApprovalOperation;
+pub use blue_green::{Instance, ProxyRouteStatus, RollingRestartOptions, RollingRestartReport};
+pub use buffer::{CircularBuffer, FollowedLines, FollowedOutput, LogLine, LogStream};
+pub use capture::CapturedTemplate;
+pub use cgroup::CgroupStats;
+pub use chain::{ChainOutcome, ChainRun, ChainStatus};
+pub use chaos::{ChaosAction, ChaosRecord, ChaosSchedule, ChaosStatus, ChaosTarget};
+pub use container::ImageBuildStatus;
+pub use crash_loop::{CrashLoopInfo, ExitRecord};
+pub use debug_bundle::DebugBundle;
+pub use diagnosis::{DiagnosisMatch, DiagnosisReport};
+pub use discovery::{DiscoveredTask, DiscoveryReport};
+pub use energy::{ProcessResourceUsage, ResourceOverview, ResourceUsage};
+pub use env_diff::{EnvChange, EnvDiff};
+pub use env_loader::Toolchain;
+pub use failure_report::FailureReport;
+pub use fence::{FencedOperation, OperationFence};
+pub use filter_expr::{FilterError, FilterExpr};
+pub use groups::{GroupMember, GroupStatus};
+pub use health_probe::{ProbeOutcome, ProbeStatus};
+pub use kubernetes::{PodSummary, PortForwardStatus, WorkloadStatus};
+pub use lazy_start::LazyStartStatus;
+pub use log_quota::{LogDiskUsage, PruneReport};
+pub use log_sinks::LogSinkHealth;
+pub use maintenance::{ActiveMaintenance, MaintenanceWindowStatus};
 pub use manager::{ManagedProcess, ProcessManager};
+pub use network::{NetworkConnection, ProcessNetworkInfo};
+pub use output_diff::{OutputDiff, TimeWindow};
+pub use output_rates::{HealthStatus, OutputMetrics, Trend, WindowRates};
+pub use phases::PhaseDuration;
+pub use port_owner::{PortOwner, ServiceManager, ServiceUnit};
 pub use protocol::{Process, ProcessBuilder};
+pub use reaper::{PidStatus, ReapReport, ReapedChild, StateCorrection};
+pub use recording::{RecordedLine, RecordedStream, RecordingSummary};
+pub use repl::{ReplExecution, ReplStatus};
+pub use resource_samples::{ResourceHistory, UsageSummary};
+pub use restart_policy::RestartStatus;
+pub use run_environment::{RunEnvironment, RunEnvironmentDiff};
+pub use runbook::RunbookContent;
+pub use safe_mode::{SafeModeRestoreReport, SafeModeStatus};
+pub use sampling::{OutputSampling, SamplingStatus};
+pub use schedule::{ScheduleOutcome, ScheduleRun, ScheduleStatus};
+pub use session_context::{ContextRestoreReport, GitBranchRestore, SessionContext};
 pub use shell::{ShellProcess, ShellProcessBuilder};
+pub use startup::{StartupReport, StartupResult};
+pub use stdin::InputEncoding;
+pub use template_matrix::MatrixInstance;
+pub use template_render::RenderedTemplate;
+pub use timeline::{ProcessTimeline, TimelineEntry, TimelineLane, TimelineSummary};
+pub use tunnel::{TunnelState, TunnelStatus};
 pub use types::*;
+pub use vscode_tasks::{SkippedTask, TaskImportReport};
+pub use workspace_bundle::{
+    ConflictPolicy, WorkspaceBundle, WorkspaceExportReport, WorkspaceImportOptions,
+    WorkspaceImportReport,
+};
+pub use workspace_quota::WorkspaceUsage;
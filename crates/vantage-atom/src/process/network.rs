@@ -0,0 +1,298 @@
+//! プロセスのネットワーク使用状況
+//!
+//! 管理プロセスとその子孫プロセスが開いているソケットを列挙します。
+//! プラットフォーム依存の実装は `network-inspect` フィーチャー（Linuxのみ）で有効になります。
+
+use serde::Serialize;
+
+/// 1つのソケット接続
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct NetworkConnection {
+    /// ソケットを保持しているPID
+    pub pid: u32,
+    /// tcp / tcp6 / udp / udp6
+    pub protocol: String,
+    pub local_address: String,
+    /// 未接続（LISTEN等）の場合はNone
+    pub remote_address: Option<String>,
+    /// TCPの状態（LISTEN, ESTABLISHED など）
+    pub state: Option<String>,
+}
+
+/// サンプリング期間中のI/Oレート
+///
+/// ソケット以外の読み書きも含むため、通信量の上限の目安として扱う。
+#[derive(Debug, Clone, Serialize)]
+pub struct IoRateEstimate {
+    pub sample_ms: u64,
+    pub read_bytes_per_sec: u64,
+    pub write_bytes_per_sec: u64,
+    pub note: String,
+}
+
+/// プロセスのネットワーク使用状況
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessNetworkInfo {
+    pub process_id: String,
+    /// 対象としたPID（ルートプロセスと子孫）
+    pub pids: Vec<u32>,
+    pub connections: Vec<NetworkConnection>,
+    pub io_rate: Option<IoRateEstimate>,
+}
+
+/// ルートPIDとその子孫のネットワーク使用状況を取得
+#[cfg(all(feature = "network-inspect", target_os = "linux"))]
+pub async fn inspect(
+    process_id: String,
+    root_pid: u32,
+    sample_ms: Option<u64>,
+) -> Result<ProcessNetworkInfo, String> {
+    let (pids, connections) = tokio::task::spawn_blocking(move || {
+        let pids = linux::descendants(root_pid);
+        let connections = linux::connections(&pids);
+        (pids, connections)
+    })
+    .await
+    .map_err(|e| format!("Network inspection task failed: {e}"))?;
+
+    let io_rate = match sample_ms {
+        Some(ms) if ms > 0 => Some(linux::sample_io_rate(&pids, ms).await),
+        _ => None,
+    };
+
+    Ok(ProcessNetworkInfo {
+        process_id,
+        pids,
+        connections,
+        io_rate,
+    })
+}
+
+/// ルートPIDとその子孫のネットワーク使用状況を取得
+#[cfg(not(all(feature = "network-inspect", target_os = "linux")))]
+pub async fn inspect(
+    _process_id: String,
+    _root_pid: u32,
+    _sample_ms: Option<u64>,
+) -> Result<ProcessNetworkInfo, String> {
+    Err(
+        "Network inspection is not supported in this build (requires the `network-inspect` feature on Linux)"
+            .to_string(),
+    )
+}
+
+#[cfg(all(feature = "network-inspect", target_os = "linux"))]
+mod linux {
+    use super::{IoRateEstimate, NetworkConnection};
+    use std::collections::{HashMap, HashSet};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    /// ルートPIDと、/proc/*/stat の親子関係から求めた子孫PID
+    pub fn descendants(root_pid: u32) -> Vec<u32> {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        if let Ok(entries) = std::fs::read_dir("/proc") {
+            for entry in entries.flatten() {
+                let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse().ok()) else {
+                    continue;
+                };
+                if let Some(ppid) = read_ppid(pid) {
+                    children.entry(ppid).or_default().push(pid);
+                }
+            }
+        }
+
+        let mut result = vec![root_pid];
+        let mut i = 0;
+        while i < result.len() {
+            if let Some(kids) = children.get(&result[i]) {
+                result.extend(kids);
+            }
+            i += 1;
+        }
+        result
+    }
+
+    fn read_ppid(pid: u32) -> Option<u32> {
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // comm は括弧内に空白を含みうるため、最後の ')' 以降を解析する
+        let rest = &stat[stat.rfind(')')? + 1..];
+        rest.split_whitespace().nth(1)?.parse().ok()
+    }
+
+    fn socket_inodes(pid: u32) -> HashSet<u64> {
+        let mut inodes = HashSet::new();
+        if let Ok(entries) = std::fs::read_dir(format!("/proc/{pid}/fd")) {
+            for entry in entries.flatten() {
+                if let Ok(target) = std::fs::read_link(entry.path())
+                    && let Some(inode) = target
+                        .to_str()
+                        .and_then(|t| t.strip_prefix("socket:["))
+                        .and_then(|t| t.strip_suffix(']'))
+                        .and_then(|t| t.parse().ok())
+                {
+                    inodes.insert(inode);
+                }
+            }
+        }
+        inodes
+    }
+
+    pub fn connections(pids: &[u32]) -> Vec<NetworkConnection> {
+        let mut result = Vec::new();
+        let mut seen = HashSet::new();
+        for &pid in pids {
+            let inodes = socket_inodes(pid);
+            if inodes.is_empty() {
+                continue;
+            }
+            for protocol in ["tcp", "tcp6", "udp", "udp6"] {
+                let Ok(table) = std::fs::read_to_string(format!("/proc/{pid}/net/{protocol}"))
+                else {
+                    continue;
+                };
+                for (inode, conn) in parse_table(&table, protocol, pid) {
+                    if inodes.contains(&inode) && seen.insert(inode) {
+                        result.push(conn);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// /proc/net/{tcp,udp}[6] の内容を解析
+    pub fn parse_table(table: &str, protocol: &str, pid: u32) -> Vec<(u64, NetworkConnection)> {
+        table
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let local = parse_address(fields.get(1)?)?;
+                let remote = parse_address(fields.get(2)?)?;
+                let state = u8::from_str_radix(fields.get(3)?, 16).ok()?;
+                let inode = fields.get(9)?.parse().ok()?;
+
+                let is_tcp = protocol.starts_with("tcp");
+                let connected = !remote.ends_with(":0");
+                Some((
+                    inode,
+                    NetworkConnection {
+                        pid,
+                        protocol: protocol.to_string(),
+                        local_address: local,
+                        remote_address: connected.then_some(remote),
+                        state: is_tcp.then(|| tcp_state(state).to_string()),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// "0100007F:1F90" 形式（リトルエンディアンの16進）をアドレス表記に変換
+    fn parse_address(field: &str) -> Option<String> {
+        let (ip, port) = field.split_once(':')?;
+        let port = u16::from_str_radix(port, 16).ok()?;
+        match ip.len() {
+            8 => {
+                let raw = u32::from_str_radix(ip, 16).ok()?;
+                Some(format!("{}:{port}", Ipv4Addr::from(raw.swap_bytes())))
+            }
+            32 => {
+                let mut bytes = [0u8; 16];
+                for (i, chunk) in bytes.chunks_mut(4).enumerate() {
+                    let word = u32::from_str_radix(&ip[i * 8..i * 8 + 8], 16).ok()?;
+                    chunk.copy_from_slice(&word.to_le_bytes());
+                }
+                Some(format!("[{}]:{port}", Ipv6Addr::from(bytes)))
+            }
+            _ => None,
+        }
+    }
+
+    fn tcp_state(state: u8) -> &'static str {
+        match state {
+            0x01 => "ESTABLISHED",
+            0x02 => "SYN_SENT",
+            0x03 => "SYN_RECV",
+            0x04 => "FIN_WAIT1",
+            0x05 => "FIN_WAIT2",
+            0x06 => "TIME_WAIT",
+            0x07 => "CLOSE",
+            0x08 => "CLOSE_WAIT",
+            0x09 => "LAST_ACK",
+            0x0A => "LISTEN",
+            0x0B => "CLOSING",
+            _ => "UNKNOWN",
+        }
+    }
+
+    /// /proc/<pid>/io の rchar/wchar 合計
+    fn io_counters(pids: &[u32]) -> (u64, u64) {
+        let mut read = 0;
+        let mut write = 0;
+        for pid in pids {
+            let Ok(io) = std::fs::read_to_string(format!("/proc/{pid}/io")) else {
+                continue;
+            };
+            for line in io.lines() {
+                if let Some(v) = line.strip_prefix("rchar: ") {
+                    read += v.trim().parse::<u64>().unwrap_or(0);
+                } else if let Some(v) = line.strip_prefix("wchar: ") {
+                    write += v.trim().parse::<u64>().unwrap_or(0);
+                }
+            }
+        }
+        (read, write)
+    }
+
+    pub async fn sample_io_rate(pids: &[u32], sample_ms: u64) -> IoRateEstimate {
+        let (read_before, write_before) = io_counters(pids);
+        tokio::time::sleep(std::time::Duration::from_millis(sample_ms)).await;
+        let (read_after, write_after) = io_counters(pids);
+
+        let per_sec = |delta: u64| delta.saturating_mul(1000) / sample_ms;
+        IoRateEstimate {
+            sample_ms,
+            read_bytes_per_sec: per_sec(read_after.saturating_sub(read_before)),
+            write_bytes_per_sec: per_sec(write_after.saturating_sub(write_before)),
+            note: "Upper bound: includes file and pipe I/O as well as sockets".to_string(),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_parse_tcp_table() {
+            let table = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 12345 1 0 100 0 0 10 0
+   1: 0100007F:C350 0100007F:1538 01 00000000:00000000 00:00000000 00000000  1000        0 67890 1 0 20 4 30 10 -1
+";
+            let parsed = parse_table(table, "tcp", 42);
+            assert_eq!(parsed.len(), 2);
+
+            let (inode, listen) = &parsed[0];
+            assert_eq!(*inode, 12345);
+            assert_eq!(listen.local_address, "127.0.0.1:8080");
+            assert_eq!(listen.remote_address, None);
+            assert_eq!(listen.state.as_deref(), Some("LISTEN"));
+
+            let (_, established) = &parsed[1];
+            assert_eq!(
+                established.remote_address.as_deref(),
+                Some("127.0.0.1:5432")
+            );
+            assert_eq!(established.state.as_deref(), Some("ESTABLISHED"));
+        }
+
+        #[test]
+        fn test_parse_ipv6_address() {
+            assert_eq!(
+                parse_address("00000000000000000000000001000000:0050").as_deref(),
+                Some("[::1]:80")
+            );
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! 通知の送り先のルーティング
+//!
+//! 設定の `notification_routes` を上から順に評価し、最初に一致したルールの送り先
+//! （Slack・メール・デスクトップ通知）に通知を送ります。どのルールにも一致しない通知は
+//! 受信箱に残るだけです。条件はタグ・ワークスペース・通知の種類・重大度で、
+//! 分類の間はすべてを満たし、同じ分類の中はいずれかに一致すれば満たします。
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use vantage_persistence::{
+    Notification, NotificationChannel, NotificationKind, NotificationRoute, NotificationSeverity,
+};
+
+/// 外部への送信を待つ時間
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// ルールと照らし合わせる通知の属性
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RouteSubject {
+    pub kind: NotificationKind,
+    pub severity: NotificationSeverity,
+    /// 通知の対象のプロセスのタグ
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 通知の対象のプロセスの作業ディレクトリ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+}
+
+impl RouteSubject {
+    /// 種類の重大度を使う
+    pub fn new(kind: NotificationKind, tags: Vec<String>, cwd: Option<String>) -> Self {
+        Self {
+            kind,
+            severity: kind.severity(),
+            tags,
+            cwd,
+        }
+    }
+}
+
+/// 1つのルールの評価結果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RouteEvaluation {
+    pub name: String,
+    pub matched: bool,
+    /// 一致しなかった条件
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mismatches: Vec<String>,
+}
+
+/// 通知がどのルールに一致するかの確認結果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RouteTest {
+    pub subject: RouteSubject,
+    /// 一致したルールの名前（どれにも一致しなければNone）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+    /// 送り先（どれにも一致しなければ受信箱にだけ残る）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<NotificationChannel>,
+    /// 一致したルールまでの評価結果（上から順）
+    pub evaluations: Vec<RouteEvaluation>,
+}
+
+/// ルールを検証する
+pub fn validate(routes: &[NotificationRoute]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for route in routes {
+        if route.name.trim().is_empty() {
+            return Err("notification_routes[].name must not be empty".to_string());
+        }
+        if !names.insert(route.name.as_str()) {
+            return Err(format!(
+                "Duplicate notification route name '{}'",
+                route.name
+            ));
+        }
+        if let Some(path) = route
+            .workspaces
+            .iter()
+            .find(|p| !Path::new(p).is_absolute())
+        {
+            return Err(format!(
+                "Notification route '{}': workspace must be an absolute path: '{path}'",
+                route.name
+            ));
+        }
+        match &route.channel {
+            NotificationChannel::Slack { webhook_url } => {
+                if !webhook_url.starts_with("https://") && !webhook_url.starts_with("http://") {
+                    return Err(format!(
+                        "Notification route '{}': webhook_url must be an http(s) URL",
+                        route.name
+                    ));
+                }
+            }
+            NotificationChannel::Email { to, .. } => {
+                if to.is_empty() {
+                    return Err(format!(
+                        "Notification route '{}': email needs at least one recipient",
+                        route.name
+                    ));
+                }
+                if let Some(address) = to.iter().find(|a| !a.contains('@')) {
+                    return Err(format!(
+                        "Notification route '{}': invalid email address '{address}'",
+                        route.name
+                    ));
+                }
+            }
+            NotificationChannel::Desktop | NotificationChannel::Inbox => {}
+        }
+    }
+    Ok(())
+}
+
+/// ルールの条件のうち、通知が満たさないもの（空なら一致）
+pub fn mismatches(route: &NotificationRoute, subject: &RouteSubject) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if !route.tags.is_empty() && !route.tags.iter().any(|tag| subject.tags.contains(tag)) {
+        mismatches.push(format!("tags: none of {:?}", route.tags));
+    }
+    if !route.workspaces.is_empty()
+        && !subject.cwd.as_deref().is_some_and(|cwd| {
+            route
+                .workspaces
+                .iter()
+                .any(|workspace| Path::new(cwd).starts_with(workspace))
+        })
+    {
+        mismatches.push(format!("workspace: not under {:?}", route.workspaces));
+    }
+    if !route.kinds.is_empty() && !route.kinds.contains(&subject.kind) {
+        mismatches.push(format!(
+            "kind: {:?} is not one of {:?}",
+            subject.kind, route.kinds
+        ));
+    }
+    if let Some(min) = route.min_severity
+        && subject.severity < min
+    {
+        mismatches.push(format!(
+            "severity: {:?} is below {:?}",
+            subject.severity, min
+        ));
+    }
+    mismatches
+}
+
+/// 最初に一致したルール
+pub fn select<'a>(
+    routes: &'a [NotificationRoute],
+    subject: &RouteSubject,
+) -> Option<&'a NotificationRoute> {
+    routes
+        .iter()
+        .find(|route| mismatches(route, subject).is_empty())
+}
+
+/// 通知がどのルールに一致するかを、評価の過程とともに調べる
+pub fn test(routes: &[NotificationRoute], subject: RouteSubject) -> RouteTest {
+    let mut evaluations = Vec::new();
+    let mut matched = None;
+    for route in routes {
+        let mismatches = mismatches(route, &subject);
+        let is_match = mismatches.is_empty();
+        evaluations.push(RouteEvaluation {
+            name: route.name.clone(),
+            matched: is_match,
+            mismatches,
+        });
+        if is_match {
+            matched = Some(route);
+            break;
+        }
+    }
+    RouteTest {
+        route: matched.map(|route| route.name.clone()),
+        channel: matched.map(|route| route.channel.clone()),
+        subject,
+        evaluations,
+    }
+}
+
+/// 通知を送り先に送る
+pub async fn deliver(
+    channel: &NotificationChannel,
+    notification: &Notification,
+) -> Result<(), String> {
+    match channel {
+        NotificationChannel::Slack { webhook_url } => send_slack(webhook_url, notification).await,
+        NotificationChannel::Email { to, from } => {
+            send_email(to, from.as_deref(), notification).await
+        }
+        NotificationChannel::Desktop => send_desktop(notification).await,
+        NotificationChannel::Inbox => Ok(()),
+    }
+}
+
+/// 本文（メッセージとリンク）
+fn body(notification: &Notification) -> String {
+    match &notification.link {
+        Some(link) => format!("{}\n{link}", notification.message),
+        None => notification.message.clone(),
+    }
+}
+
+async fn send_slack(webhook_url: &str, notification: &Notification) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
+    let payload = serde_json::json!({
+        "text": format!("*{}*\n{}", notification.title, body(notification)),
+    });
+    let response = client
+        .post(webhook_url)
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to post to Slack: {e}"))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("Slack returned {status}: {}", body.trim()))
+}
+
+/// ヘッダーに改行が入らないようにする
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+async fn send_email(
+    to: &[String],
+    from: Option<&str>,
+    notification: &Notification,
+) -> Result<(), String> {
+    let mut message = format!("To: {}\n", header_value(&to.join(", ")));
+    if let Some(from) = from {
+        message.push_str(&format!("From: {}\n", header_value(from)));
+    }
+    message.push_str(&format!(
+        "Subject: [vantage] {}\n\n{}\n",
+        header_value(&notification.title),
+        body(notification)
+    ));
+
+    let mut child = Command::new("sendmail")
+        .arg("-t")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run sendmail: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(message.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write to sendmail: {e}"))?;
+    }
+    run_to_completion(child, "sendmail").await
+}
+
+async fn send_desktop(notification: &Notification) -> Result<(), String> {
+    let (program, child) = if cfg!(target_os = "macos") {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            escape(&notification.message),
+            escape(&notification.title)
+        );
+        (
+            "osascript",
+            Command::new("osascript").arg("-e").arg(script).spawn(),
+        )
+    } else {
+        (
+            "notify-send",
+            Command::new("notify-send")
+                .arg(&notification.title)
+                .arg(&notification.message)
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .spawn(),
+        )
+    };
+    let child = child.map_err(|e| format!("Failed to run {program}: {e}"))?;
+    run_to_completion(child, program).await
+}
+
+async fn run_to_completion(child: tokio::process::Child, program: &str) -> Result<(), String> {
+    let output = tokio::time::timeout(DELIVERY_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("{program} did not finish within {DELIVERY_TIMEOUT:?}"))?
+        .map_err(|e| format!("Failed to wait for {program}: {e}"))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(format!(
+        "{program} exited with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(name: &str, channel: NotificationChannel) -> NotificationRoute {
+        NotificationRoute {
+            name: name.to_string(),
+            tags: Vec::new(),
+            workspaces: Vec::new(),
+            kinds: Vec::new(),
+            min_severity: None,
+            channel,
+        }
+    }
+
+    fn routes() -> Vec<NotificationRoute> {
+        vec![
+            NotificationRoute {
+                tags: vec!["backend".to_string()],
+                min_severity: Some(NotificationSeverity::Error),
+                ..route(
+                    "backend",
+                    NotificationChannel::Slack {
+                        webhook_url: "https://hooks.slack.com/services/T/B/X".to_string(),
+                    },
+                )
+            },
+            NotificationRoute {
+                kinds: vec![NotificationKind::CiFailed],
+                ..route(
+                    "ci",
+                    NotificationChannel::Email {
+                        to: vec!["team@example.com".to_string()],
+                        from: None,
+                    },
+                )
+            },
+            route("everything else", NotificationChannel::Desktop),
+        ]
+    }
+
+    #[test]
+    fn test_first_matching_route_wins() {
+        let routes = routes();
+        validate(&routes).unwrap();
+
+        let backend_failure = RouteSubject::new(
+            NotificationKind::ProcessFailed,
+            vec!["backend".to_string()],
+            None,
+        );
+        assert_eq!(select(&routes, &backend_failure).unwrap().name, "backend");
+
+        // 重大度が足りなければ次のルールへ
+        let backend_unhealthy = RouteSubject::new(
+            NotificationKind::ProcessUnhealthy,
+            vec!["backend".to_string()],
+            None,
+        );
+        let result = test(&routes, backend_unhealthy);
+        assert_eq!(result.route.as_deref(), Some("everything else"));
+        assert_eq!(result.evaluations.len(), 3);
+        assert!(result.evaluations[0].mismatches[0].starts_with("severity"));
+
+        let ci = RouteSubject::new(NotificationKind::CiFailed, Vec::new(), None);
+        assert_eq!(select(&routes, &ci).unwrap().name, "ci");
+        assert!(
+            select(
+                &routes[..2],
+                &RouteSubject::new(NotificationKind::CrashLoop, Vec::new(), None)
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_workspace_matches_by_path_prefix() {
+        let route = NotificationRoute {
+            workspaces: vec!["/srv/app".to_string()],
+            ..route("app", NotificationChannel::Inbox)
+        };
+        let subject = |cwd: &str| {
+            RouteSubject::new(
+                NotificationKind::ProcessFailed,
+                Vec::new(),
+                Some(cwd.to_string()),
+            )
+        };
+        assert!(mismatches(&route, &subject("/srv/app/api")).is_empty());
+        // 名前の前方一致ではなくディレクトリとして比べる
+        assert!(!mismatches(&route, &subject("/srv/application")).is_empty());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_routes() {
+        let mut duplicated = routes();
+        duplicated[2].name = "backend".to_string();
+        assert!(validate(&duplicated).unwrap_err().contains("Duplicate"));
+
+        let relative = NotificationRoute {
+            workspaces: vec!["app".to_string()],
+            ..route("relative", NotificationChannel::Desktop)
+        };
+        assert!(validate(&[relative]).is_err());
+
+        let no_recipients = route(
+            "mail",
+            NotificationChannel::Email {
+                to: Vec::new(),
+                from: None,
+            },
+        );
+        assert!(validate(&[no_recipients]).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! 通知の受信箱
+//!
+//! イベントは発行されると流れて消えるため、利用者が後から確認すべきもの（プロセスの失敗・
+//! クラッシュループ・ヘルスチェックの異常・CIの失敗・承認待ち）を既読・未読の状態とともに残します。
+//! 受信箱はサーバーのメモリ上にあり、古いものから `MAX_NOTIFICATIONS` 件を超えた分を捨てます。
+//! 設定の `notification_routes` に一致した通知は、受信箱に加えてルールの送り先にも送ります。
+
+use super::crash_loop::{CrashLoopInfo, ExitRecord};
+use super::notification_routing::{self, RouteSubject};
+use crate::ci::{CiRun, CiRunConclusion};
+use chrono::Utc;
+use tracing::warn;
+use vantage_persistence::{
+    ApprovalRequest, Notification, NotificationKind, PersistenceManager, generate_id,
+};
+
+/// 受信箱に残す通知の件数
+pub const MAX_NOTIFICATIONS: usize = 500;
+
+fn notification(
+    kind: NotificationKind,
+    title: String,
+    message: String,
+    process_id: Option<&str>,
+    link: Option<String>,
+) -> Notification {
+    Notification {
+        notification_id: generate_id(),
+        kind,
+        title,
+        message,
+        process_id: process_id.map(str::to_string),
+        link,
+        created_at: Utc::now(),
+        read: false,
+        read_at: None,
+        route: None,
+    }
+}
+
+/// 異常終了したプロセスの通知
+pub fn process_failed(process_id: &str, record: &ExitRecord) -> Notification {
+    let message = match (&record.error, record.exit_code) {
+        (Some(error), _) => error.clone(),
+        (None, Some(code)) => format!("Exited with code {code}"),
+        (None, None) => "Terminated by a signal".to_string(),
+    };
+    notification(
+        NotificationKind::ProcessFailed,
+        format!("Process '{process_id}' failed"),
+        message,
+        Some(process_id),
+        None,
+    )
+}
+
+/// クラッシュループを検知したプロセスの通知
+pub fn crash_loop(process_id: &str, info: &CrashLoopInfo) -> Notification {
+    notification(
+        NotificationKind::CrashLoop,
+        format!("Process '{process_id}' is in a crash loop"),
+        info.suggestion.clone(),
+        Some(process_id),
+        None,
+    )
+}
+
+/// ヘルスチェックで異常と判定されたプロセスの通知
+pub fn process_unhealthy(process_id: &str, failures: &[String]) -> Notification {
+    notification(
+        NotificationKind::ProcessUnhealthy,
+        format!("Process '{process_id}' is unhealthy"),
+        failures.join("; "),
+        Some(process_id),
+        None,
+    )
+}
+
+/// 失敗したCIの実行の通知（失敗・タイムアウト以外はNone）
+pub fn ci_failed(run: &CiRun) -> Option<Notification> {
+    let outcome = match run.conclusion {
+        Some(CiRunConclusion::Failure) => "failed",
+        Some(CiRunConclusion::TimedOut) => "timed out",
+        _ => return None,
+    };
+    Some(notification(
+        NotificationKind::CiFailed,
+        format!("CI run '{}' {outcome}", run.workflow_name),
+        format!("{} on {} ({})", run.name, run.branch, run.event),
+        None,
+        Some(run.url.clone()),
+    ))
+}
+
+/// 承認を待っている操作の通知（リンクは承認リクエストのID）
+pub fn approval_pending(request: &ApprovalRequest) -> Notification {
+    notification(
+        NotificationKind::ApprovalPending,
+        format!("'{}' is waiting for approval", request.tool),
+        request.summary.clone(),
+        None,
+        Some(request.approval_id.clone()),
+    )
+}
+
+/// 通知を受信箱に追加し、一致したルールの送り先に送る（失敗しても呼び出し元の処理は続ける）
+pub async fn record(persistence: &PersistenceManager, mut notification: Notification) {
+    let routes = persistence
+        .get_settings()
+        .await
+        .map(|settings| settings.notification_routes)
+        .unwrap_or_default();
+    let channel = if routes.is_empty() {
+        None
+    } else {
+        let process = match &notification.process_id {
+            Some(id) => persistence.get_process(id).await.ok().flatten(),
+            None => None,
+        };
+        let subject = RouteSubject::new(
+            notification.kind,
+            process.as_ref().map(|p| p.tags.clone()).unwrap_or_default(),
+            process.and_then(|p| p.cwd),
+        );
+        notification_routing::select(&routes, &subject).map(|route| {
+            notification.route = Some(route.name.clone());
+            route.channel.clone()
+        })
+    };
+
+    if let Err(e) = persistence
+        .push_notification(&notification, MAX_NOTIFICATIONS)
+        .await
+    {
+        warn!(
+            "Failed to record notification '{}': {}",
+            notification.title, e
+        );
+    }
+    if let Some(channel) = channel {
+        // 外部への送信で呼び出し元を待たせない
+        tokio::spawn(async move {
+            if let Err(e) = notification_routing::deliver(&channel, &notification).await {
+                warn!(
+                    "Failed to deliver notification '{}' via route '{}': {}",
+                    notification.title,
+                    notification.route.as_deref().unwrap_or_default(),
+                    e
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_inbox_read_state() {
+        let persistence = PersistenceManager::new().await.unwrap();
+        let record = ExitRecord {
+            at: Utc::now(),
+            exit_code: Some(2),
+            error: None,
+        };
+        let first = process_failed("api", &record);
+        assert_eq!(first.message, "Exited with code 2");
+        super::record(&persistence, first.clone()).await;
+        super::record(
+            &persistence,
+            process_unhealthy("api", &["too many errors".into()]),
+        )
+        .await;
+
+        assert_eq!(persistence.count_unread_notifications().await.unwrap(), 2);
+        let ids = [first.notification_id.clone()];
+        assert_eq!(
+            persistence
+                .mark_notifications_read(Some(&ids))
+                .await
+                .unwrap(),
+            1
+        );
+        // 既読のものは数えない
+        assert_eq!(
+            persistence
+                .mark_notifications_read(Some(&ids))
+                .await
+                .unwrap(),
+            0
+        );
+
+        let unread = persistence.list_notifications(true).await.unwrap();
+        assert_eq!(unread.len(), 1);
+        assert_eq!(unread[0].kind, NotificationKind::ProcessUnhealthy);
+        assert_eq!(persistence.mark_notifications_read(None).await.unwrap(), 1);
+        assert_eq!(persistence.count_unread_notifications().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,357 @@
+//! 同じプロセスの2つの期間の出力の比較
+//!
+//! 設定変更や再起動の前後で出力がどう変わったかを、ログ全体ではなく要約で返します。
+//! 数値・16進数・UUID・時刻を伏せた「メッセージの形」で行をまとめ、片方の期間にだけ
+//! 現れた形を、エラーとそれ以外に分けて件数の多い順に並べます。
+
+use super::buffer::LogLine;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use utoipa::ToSchema;
+
+/// 各一覧に含める件数の既定値
+pub const DEFAULT_LIMIT: usize = 20;
+/// 記録しておく起動時刻の数
+pub const START_HISTORY_CAPACITY: usize = 20;
+
+/// メッセージの形を作るときに伏せる部分（先に書いたものから置き換える）
+static VOLATILE: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    [
+        (r"\x1b\[[0-9;]*[A-Za-z]", ""),
+        (
+            r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?",
+            "<time>",
+        ),
+        (
+            r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b",
+            "<uuid>",
+        ),
+        (r"(?i)\b0x[0-9a-f]+\b", "<hex>"),
+        (r"(?i)\b[0-9a-f]{12,}\b", "<hex>"),
+        (r"\d+(\.\d+)?", "<n>"),
+        (r"\s+", " "),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+    .collect()
+});
+
+/// エラーとみなす行
+static ERROR_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(error|err|panic|panicked|exception|fatal|failed|failure|traceback|critical)\b",
+    )
+    .unwrap()
+});
+
+/// 比較する期間（両端のないものは無制限）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, ToSchema)]
+pub struct TimeWindow {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl TimeWindow {
+    /// 時刻が期間内か（`from` を含み `to` を含まない）
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.from.is_none_or(|from| at >= from) && self.to.is_none_or(|to| at < to)
+    }
+}
+
+/// 片方の期間にだけ現れたメッセージ
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct MessageCount {
+    /// 実際の行の例（最初に現れたもの）
+    pub example: String,
+    /// その期間に現れた回数
+    pub count: usize,
+}
+
+/// 期間ごとの行数
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct WindowSummary {
+    pub window: TimeWindow,
+    pub lines: usize,
+    pub error_lines: usize,
+}
+
+/// 2つの期間の出力の差分
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct OutputDiff {
+    pub process_id: String,
+    pub before: WindowSummary,
+    pub after: WindowSummary,
+    /// 後の期間にだけ現れたエラー
+    pub new_errors: Vec<MessageCount>,
+    /// 前の期間にだけ現れたエラー（解消したもの）
+    pub resolved_errors: Vec<MessageCount>,
+    /// 後の期間にだけ現れたエラー以外のメッセージ
+    pub new_messages: Vec<MessageCount>,
+    /// 前の期間にだけ現れ、後の期間には出なくなったメッセージ
+    pub disappeared_messages: Vec<MessageCount>,
+    /// 件数の上限で省いたものがあるか
+    pub truncated: bool,
+    /// 比較の精度に関する注意（バッファから消えた期間など）
+    pub warnings: Vec<String>,
+    pub summary: String,
+}
+
+/// 行からメッセージの形を作る（数値やIDの違いを無視するため）
+pub fn signature(text: &str) -> String {
+    let mut text = text.to_string();
+    for (pattern, replacement) in VOLATILE.iter() {
+        text = pattern.replace_all(&text, *replacement).into_owned();
+    }
+    text.trim().to_string()
+}
+
+/// エラーの行か
+pub fn is_error_line(text: &str) -> bool {
+    ERROR_LINE.is_match(text)
+}
+
+/// 形ごとの例と回数（最初に現れた順）
+#[derive(Default)]
+struct Tally {
+    order: Vec<String>,
+    messages: HashMap<String, MessageCount>,
+    lines: usize,
+    error_lines: usize,
+}
+
+impl Tally {
+    fn collect<'a>(lines: impl IntoIterator<Item = &'a LogLine>) -> Self {
+        let mut tally = Self::default();
+        for line in lines {
+            let key = signature(&line.text);
+            if key.is_empty() {
+                continue;
+            }
+            tally.lines += 1;
+            if is_error_line(&line.text) {
+                tally.error_lines += 1;
+            }
+            tally
+                .messages
+                .entry(key.clone())
+                .and_modify(|m| m.count += 1)
+                .or_insert_with(|| {
+                    tally.order.push(key);
+                    MessageCount {
+                        example: line.text.clone(),
+                        count: 1,
+                    }
+                });
+        }
+        tally
+    }
+
+    /// `other` に現れない形を、エラーとそれ以外に分けて返す
+    fn only_in(&self, other: &Self) -> (Vec<MessageCount>, Vec<MessageCount>) {
+        let (errors, others): (Vec<_>, Vec<_>) = self
+            .order
+            .iter()
+            .filter(|key| !other.messages.contains_key(*key))
+            .map(|key| self.messages[key].clone())
+            .partition(|m| is_error_line(&m.example));
+        (errors, others)
+    }
+}
+
+/// 件数の多い順に並べ、上限で切る（切った場合はtrue）
+fn top(mut messages: Vec<MessageCount>, limit: usize) -> (Vec<MessageCount>, bool) {
+    messages.sort_by_key(|m| std::cmp::Reverse(m.count));
+    let truncated = messages.len() > limit;
+    messages.truncate(limit);
+    (messages, truncated)
+}
+
+/// 2つの期間の出力を比べる
+///
+/// `lines` はプロセスの記録している出力（stdout/stderrをまとめたもの）、
+/// `retained_since` はバッファがあふれて古い行を捨てている場合の、残っている最も古い時刻。
+pub fn diff(
+    process_id: &str,
+    lines: &[LogLine],
+    retained_since: Option<DateTime<Utc>>,
+    before: TimeWindow,
+    after: TimeWindow,
+    limit: usize,
+) -> OutputDiff {
+    let before_tally = Tally::collect(lines.iter().filter(|l| before.contains(l.timestamp)));
+    let after_tally = Tally::collect(lines.iter().filter(|l| after.contains(l.timestamp)));
+
+    let (new_errors, new_messages) = after_tally.only_in(&before_tally);
+    let (resolved_errors, disappeared_messages) = before_tally.only_in(&after_tally);
+    let counts = [
+        new_errors.len(),
+        resolved_errors.len(),
+        new_messages.len(),
+        disappeared_messages.len(),
+    ];
+    let (new_errors, t1) = top(new_errors, limit);
+    let (resolved_errors, t2) = top(resolved_errors, limit);
+    let (new_messages, t3) = top(new_messages, limit);
+    let (disappeared_messages, t4) = top(disappeared_messages, limit);
+
+    let mut warnings = Vec::new();
+    if let Some(oldest) = retained_since
+        && before.from.is_none_or(|from| oldest > from)
+    {
+        warnings.push(format!(
+            "Output before {} is no longer retained; the earlier window is incomplete",
+            oldest.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        ));
+    }
+    for (name, tally) in [("earlier", &before_tally), ("later", &after_tally)] {
+        if tally.lines == 0 {
+            warnings.push(format!("The {name} window contains no output"));
+        }
+    }
+
+    let summary = format!(
+        "{} new error message(s), {} resolved, {} new other message(s), {} disappeared ({} -> {} lines, {} -> {} error lines)",
+        counts[0],
+        counts[1],
+        counts[2],
+        counts[3],
+        before_tally.lines,
+        after_tally.lines,
+        before_tally.error_lines,
+        after_tally.error_lines,
+    );
+
+    OutputDiff {
+        process_id: process_id.to_string(),
+        before: WindowSummary {
+            window: before,
+            lines: before_tally.lines,
+            error_lines: before_tally.error_lines,
+        },
+        after: WindowSummary {
+            window: after,
+            lines: after_tally.lines,
+            error_lines: after_tally.error_lines,
+        },
+        new_errors,
+        resolved_errors,
+        new_messages,
+        disappeared_messages,
+        truncated: t1 || t2 || t3 || t4,
+        warnings,
+        summary,
+    }
+}
+
+/// 起動時刻の履歴から、`restart` 回前の再起動の前後の期間を求める（1が直近の再起動）
+pub fn restart_windows(
+    starts: &[DateTime<Utc>],
+    restart: usize,
+) -> Result<(TimeWindow, TimeWindow), String> {
+    if restart == 0 {
+        return Err("restart must be 1 or greater (1 = the most recent restart)".to_string());
+    }
+    let restarts = starts.len().saturating_sub(1);
+    if restart > restarts {
+        return Err(format!(
+            "Only {restarts} restart(s) are recorded for this process; specify the windows explicitly"
+        ));
+    }
+    let index = starts.len() - restart;
+    let before = TimeWindow {
+        from: Some(starts[index - 1]),
+        to: Some(starts[index]),
+    };
+    let after = TimeWindow {
+        from: Some(starts[index]),
+        to: starts.get(index + 1).copied(),
+    };
+    Ok((before, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::buffer::LogStream;
+    use super::*;
+    use chrono::Duration;
+    use std::time::Instant;
+
+    fn line(text: &str, at: DateTime<Utc>) -> LogLine {
+        LogLine {
+            text: text.to_string(),
+            timestamp: at,
+            instant: Instant::now(),
+            seq: 0,
+            stream: LogStream::Stdout,
+        }
+    }
+
+    #[test]
+    fn test_signature_ignores_volatile_parts() {
+        assert_eq!(
+            signature("2024-05-01T10:00:00Z GET /users/42 took 12.5ms"),
+            signature("2024-05-01T10:00:07.123Z GET /users/7 took 3ms")
+        );
+        assert_eq!(
+            signature("request 550e8400-e29b-41d4-a716-446655440000 ok"),
+            "request <uuid> ok"
+        );
+        assert!(is_error_line("Error: connection refused"));
+        assert!(!is_error_line("listening on :3000"));
+    }
+
+    #[test]
+    fn test_diff_reports_new_and_disappeared_messages() {
+        let t0 = Utc::now() - Duration::minutes(10);
+        let t1 = t0 + Duration::minutes(5);
+        let lines = vec![
+            line("listening on :3000", t0),
+            line("GET /health 200", t0 + Duration::seconds(1)),
+            line("cache warmed in 120ms", t0 + Duration::seconds(2)),
+            line("listening on :3001", t1),
+            line("GET /health 500", t1 + Duration::seconds(1)),
+            line(
+                "Error: database timeout after 5000ms",
+                t1 + Duration::seconds(2),
+            ),
+            line(
+                "Error: database timeout after 5001ms",
+                t1 + Duration::seconds(3),
+            ),
+        ];
+        let (before, after) = restart_windows(&[t0, t1], 1).unwrap();
+        let diff = diff("web", &lines, None, before, after, DEFAULT_LIMIT);
+
+        assert_eq!(diff.before.lines, 3);
+        assert_eq!(diff.after.error_lines, 2);
+        assert_eq!(
+            diff.new_errors,
+            vec![MessageCount {
+                example: "Error: database timeout after 5000ms".to_string(),
+                count: 2,
+            }]
+        );
+        assert_eq!(
+            diff.disappeared_messages[0].example,
+            "cache warmed in 120ms"
+        );
+        assert!(diff.new_messages.is_empty());
+        assert!(diff.resolved_errors.is_empty());
+        assert!(diff.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_restart_windows() {
+        let t0 = Utc::now();
+        let t1 = t0 + Duration::minutes(1);
+        let t2 = t0 + Duration::minutes(2);
+        let (before, after) = restart_windows(&[t0, t1, t2], 2).unwrap();
+        assert_eq!(before.from, Some(t0));
+        assert_eq!(after.to, Some(t2));
+        assert!(restart_windows(&[t0], 1).is_err());
+        assert!(restart_windows(&[t0, t1], 0).is_err());
+    }
+}
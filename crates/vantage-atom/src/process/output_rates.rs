@@ -0,0 +1,314 @@
+//! 出力の頻度とエラー率の推移
+//!
+//! stdout・stderrそれぞれの行数と、そのうちエラーの行の数を10秒ごとの区間で数えます。
+//! バッファからあふれた行やサンプリングで間引いた行も数えるため、直近1時間の頻度を求められます。
+//! 集計した頻度はヘルスチェックの判定にも使い、クラッシュせずに劣化していくサービスを検知します。
+
+use super::health_probe::{self, ProbeStatus};
+use super::output_diff::is_error_line;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use utoipa::ToSchema;
+use vantage_persistence::HealthCheckConfig;
+
+/// 区間の長さ（秒）
+const BUCKET_SECS: i64 = 10;
+/// 保持する区間の数（1時間分）
+const BUCKET_CAPACITY: usize = 360;
+/// 集計期間の既定値（秒）
+pub const DEFAULT_WINDOW_SECS: u64 = 300;
+/// 集計期間の上限（直前の同じ長さの期間と比べるため、保持期間の半分）
+pub const MAX_WINDOW_SECS: u64 = (BUCKET_SECS as u64 * BUCKET_CAPACITY as u64) / 2;
+/// stderrの割合を判定に使うのに必要な行数（数行だけの出力で異常としないため）
+const MIN_LINES_FOR_RATIO: u64 = 20;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: i64,
+    lines: u64,
+    errors: u64,
+}
+
+/// 1つのストリームの行数の記録
+#[derive(Debug, Default)]
+pub struct LineRates {
+    buckets: VecDeque<Bucket>,
+}
+
+/// ある期間の行数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineCount {
+    pub lines: u64,
+    pub errors: u64,
+}
+
+impl LineRates {
+    /// 1行を記録する（`at` はUNIX時刻の秒）
+    pub fn record(&mut self, at: i64, text: &str) {
+        let start = bucket_start(at);
+        let error = is_error_line(text) as u64;
+        match self.buckets.back_mut() {
+            Some(bucket) if bucket.start >= start => {
+                bucket.lines += 1;
+                bucket.errors += error;
+            }
+            _ => {
+                if self.buckets.len() >= BUCKET_CAPACITY {
+                    self.buckets.pop_front();
+                }
+                self.buckets.push_back(Bucket {
+                    start,
+                    lines: 1,
+                    errors: error,
+                });
+            }
+        }
+    }
+
+    /// `from` 以降 `to` より前（UNIX時刻の秒）に始まった区間ごとの開始時刻と行数（古い順）
+    pub fn samples(&self, from: i64, to: i64) -> Vec<(i64, LineCount)> {
+        self.buckets
+            .iter()
+            .filter(|b| b.start >= from && b.start < to)
+            .map(|b| {
+                (
+                    b.start,
+                    LineCount {
+                        lines: b.lines,
+                        errors: b.errors,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// `from` 以降 `to` より前（UNIX時刻の秒）に始まった区間の行数
+    pub fn count(&self, from: i64, to: i64) -> LineCount {
+        self.buckets
+            .iter()
+            .filter(|b| b.start >= from && b.start < to)
+            .fold(LineCount::default(), |acc, b| LineCount {
+                lines: acc.lines + b.lines,
+                errors: acc.errors + b.errors,
+            })
+    }
+}
+
+/// `at`（UNIX時刻の秒）を含む区間の開始時刻
+pub fn bucket_start(at: i64) -> i64 {
+    at - at.rem_euclid(BUCKET_SECS)
+}
+
+/// `now` を含む区間の終わり（直近の期間の終端）
+pub fn window_end(now: DateTime<Utc>) -> i64 {
+    bucket_start(now.timestamp()) + BUCKET_SECS
+}
+
+/// 1つの期間の頻度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WindowRates {
+    pub stdout_lines: u64,
+    pub stderr_lines: u64,
+    /// エラーの行（stdout・stderrの両方）
+    pub error_lines: u64,
+    pub stdout_per_minute: f64,
+    pub stderr_per_minute: f64,
+    pub errors_per_minute: f64,
+    /// 出力の行のうちstderrの割合（出力がない場合はNone）
+    pub stderr_ratio: Option<f64>,
+}
+
+impl WindowRates {
+    /// 期間の長さで割って頻度を求める
+    pub fn new(stdout: LineCount, stderr: LineCount, window_secs: u64) -> Self {
+        let minutes = window_secs.max(1) as f64 / 60.0;
+        let total = stdout.lines + stderr.lines;
+        Self {
+            stdout_lines: stdout.lines,
+            stderr_lines: stderr.lines,
+            error_lines: stdout.errors + stderr.errors,
+            stdout_per_minute: round(stdout.lines as f64 / minutes),
+            stderr_per_minute: round(stderr.lines as f64 / minutes),
+            errors_per_minute: round((stdout.errors + stderr.errors) as f64 / minutes),
+            stderr_ratio: (total > 0).then(|| round(stderr.lines as f64 / total as f64)),
+        }
+    }
+}
+
+/// 直前の期間と比べたエラー率の傾向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Trend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+impl Trend {
+    /// 期間の中で1行分の差、または直前の値の25%を超える変化を増減とみなす
+    pub fn between(previous: f64, current: f64, window_secs: u64) -> Self {
+        let margin = (previous * 0.25).max(60.0 / window_secs.max(1) as f64);
+        if current - previous > margin {
+            Self::Rising
+        } else if previous - current > margin {
+            Self::Falling
+        } else {
+            Self::Steady
+        }
+    }
+}
+
+/// ヘルスチェックの結果
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct HealthStatus {
+    pub healthy: bool,
+    /// しきい値を超えた条件
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<String>,
+    /// 判定に使った期間の頻度（現在の起動以降に限る）
+    pub rates: WindowRates,
+    /// 異常と判定された時刻（監視タスクが判定した場合）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unhealthy_since: Option<DateTime<Utc>>,
+    /// プローブを設定している場合、その最後の結果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe: Option<ProbeStatus>,
+}
+
+/// プロセスの出力の頻度と、直前の同じ長さの期間からの推移
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OutputMetrics {
+    pub process_id: String,
+    pub window_secs: u64,
+    /// 直近の期間
+    pub current: WindowRates,
+    /// その直前の同じ長さの期間
+    pub previous: WindowRates,
+    /// エラーの行の頻度の傾向
+    pub error_trend: Trend,
+    /// stderrの行の頻度の傾向
+    pub stderr_trend: Trend,
+    /// ヘルスチェックを設定している場合、その判定
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthStatus>,
+}
+
+/// 頻度がヘルスチェックのしきい値を超えているか判定する
+pub fn evaluate(config: &HealthCheckConfig, rates: &WindowRates) -> Vec<String> {
+    let mut failures = Vec::new();
+    if let Some(max) = config.max_errors_per_minute
+        && rates.errors_per_minute > max
+    {
+        failures.push(format!(
+            "{} error lines per minute over the last {}s exceeds {}",
+            rates.errors_per_minute, config.window_secs, max
+        ));
+    }
+    if let (Some(max), Some(ratio)) = (config.max_stderr_ratio, rates.stderr_ratio)
+        && rates.stdout_lines + rates.stderr_lines >= MIN_LINES_FOR_RATIO
+        && ratio > max
+    {
+        failures.push(format!(
+            "stderr is {:.0}% of the output over the last {}s, above {:.0}%",
+            ratio * 100.0,
+            config.window_secs,
+            max * 100.0
+        ));
+    }
+    failures
+}
+
+/// ヘルスチェックの設定を検証する
+pub fn validate(config: &HealthCheckConfig) -> Result<(), String> {
+    if config.max_errors_per_minute.is_none()
+        && config.max_stderr_ratio.is_none()
+        && config.probe.is_none()
+    {
+        return Err(
+            "health_check needs max_errors_per_minute, max_stderr_ratio and/or probe".to_string(),
+        );
+    }
+    health_probe::validate(config)?;
+    if config.max_errors_per_minute.is_some_and(|max| max < 0.0) {
+        return Err("health_check.max_errors_per_minute must not be negative".to_string());
+    }
+    if config
+        .max_stderr_ratio
+        .is_some_and(|max| !(0.0..=1.0).contains(&max))
+    {
+        return Err("health_check.max_stderr_ratio must be between 0 and 1".to_string());
+    }
+    validate_window(config.window_secs).map_err(|e| format!("health_check.{e}"))
+}
+
+/// 集計期間を検証する（区間の境界で区切るため、区間の長さの倍数に限る）
+pub fn validate_window(window_secs: u64) -> Result<(), String> {
+    if !(BUCKET_SECS as u64..=MAX_WINDOW_SECS).contains(&window_secs)
+        || !window_secs.is_multiple_of(BUCKET_SECS as u64)
+    {
+        return Err(format!(
+            "window_secs must be a multiple of {BUCKET_SECS} between {BUCKET_SECS} and {MAX_WINDOW_SECS}"
+        ));
+    }
+    Ok(())
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rates_trend_and_health() {
+        let mut stdout = LineRates::default();
+        let mut stderr = LineRates::default();
+        // 前の5分はエラーが1件、直近の5分は12件
+        stderr.record(1_000, "ERROR: connection reset");
+        for i in 0..30 {
+            stdout.record(1_300 + i * 5, "GET /health 200");
+        }
+        for i in 0..12 {
+            stderr.record(1_300 + i * 20, "error: upstream timed out");
+        }
+
+        let previous =
+            WindowRates::new(stdout.count(1_000, 1_300), stderr.count(1_000, 1_300), 300);
+        let current = WindowRates::new(stdout.count(1_300, 1_600), stderr.count(1_300, 1_600), 300);
+        assert_eq!(previous.error_lines, 1);
+        assert_eq!(current.stdout_lines, 30);
+        assert_eq!(current.error_lines, 12);
+        assert_eq!(current.errors_per_minute, 2.4);
+        assert_eq!(current.stderr_ratio, Some(0.29));
+        assert_eq!(
+            Trend::between(previous.errors_per_minute, current.errors_per_minute, 300),
+            Trend::Rising
+        );
+        assert_eq!(Trend::between(2.4, 2.3, 300), Trend::Steady);
+
+        let config = HealthCheckConfig {
+            max_errors_per_minute: Some(2.0),
+            max_stderr_ratio: Some(0.5),
+            window_secs: 300,
+            probe: None,
+            interval_secs: 10,
+            timeout_secs: 5,
+            failure_threshold: 3,
+            action: Default::default(),
+        };
+        assert!(validate(&config).is_ok());
+        assert_eq!(evaluate(&config, &current).len(), 1);
+        assert!(evaluate(&config, &previous).is_empty());
+        assert!(
+            validate(&HealthCheckConfig {
+                max_errors_per_minute: None,
+                max_stderr_ratio: None,
+                ..config
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! プロセスの引数のパラメータ（`cargo test {{filter}}`）
+//!
+//! プロセスの定義で宣言したパラメータを引数の `{{name}}` として書いておき、
+//! `start_process_with_params` で指定した値をその回の起動だけに置換します。
+//! 指定しなかったパラメータはデフォルト値（なければ空文字列）になり、引数全体が
+//! `{{name}}` で空になったものは引数から取り除きます。宣言していないプレースホルダーは
+//! そのまま残します。
+
+use super::template_render::PLACEHOLDER;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use vantage_persistence::ProcessParameter;
+
+/// パラメータの宣言を検証
+pub fn validate(parameters: &[ProcessParameter]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for parameter in parameters {
+        if parameter.name.is_empty()
+            || !parameter
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        {
+            return Err(format!(
+                "Invalid parameter name '{}': use letters, digits, '_', '.' or '-'",
+                parameter.name
+            ));
+        }
+        if !names.insert(parameter.name.as_str()) {
+            return Err(format!("Duplicate parameter '{}'", parameter.name));
+        }
+        if let Some(pattern) = &parameter.pattern {
+            let regex = full_match(pattern)
+                .map_err(|e| format!("Invalid pattern for parameter '{}': {e}", parameter.name))?;
+            if let Some(default) = &parameter.default
+                && !regex.is_match(default)
+            {
+                return Err(format!(
+                    "Default value '{default}' of parameter '{}' does not match pattern '{pattern}'",
+                    parameter.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 値を検証して引数に置換する
+///
+/// 置換した引数と、この回に使ったパラメータの値（デフォルト値を含む）を返す。
+pub fn resolve(
+    parameters: &[ProcessParameter],
+    args: &[String],
+    values: &HashMap<String, String>,
+) -> Result<(Vec<String>, BTreeMap<String, String>), String> {
+    let mut unknown: Vec<&str> = values
+        .keys()
+        .filter(|name| !parameters.iter().any(|p| &p.name == *name))
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        unknown.sort();
+        let declared: Vec<&str> = parameters.iter().map(|p| p.name.as_str()).collect();
+        return Err(format!(
+            "Unknown parameter(s): {} (declared: {})",
+            unknown.join(", "),
+            if declared.is_empty() {
+                "none".to_string()
+            } else {
+                declared.join(", ")
+            }
+        ));
+    }
+
+    let mut resolved = BTreeMap::new();
+    for parameter in parameters {
+        let value = match values.get(&parameter.name).or(parameter.default.as_ref()) {
+            Some(value) => value.clone(),
+            None if parameter.required => {
+                return Err(format!(
+                    "Parameter '{}' is required; pass it with start_process_with_params",
+                    parameter.name
+                ));
+            }
+            None => String::new(),
+        };
+        if let Some(pattern) = &parameter.pattern {
+            let regex = full_match(pattern)
+                .map_err(|e| format!("Invalid pattern for parameter '{}': {e}", parameter.name))?;
+            if !regex.is_match(&value) {
+                return Err(format!(
+                    "Value '{value}' of parameter '{}' does not match pattern '{pattern}'",
+                    parameter.name
+                ));
+            }
+        }
+        resolved.insert(parameter.name.clone(), value);
+    }
+
+    let args = args
+        .iter()
+        .filter(|arg| {
+            // 引数全体が空になるパラメータは引数ごと取り除く
+            PLACEHOLDER
+                .captures(arg)
+                .filter(|caps| caps[0].len() == arg.len())
+                .and_then(|caps| resolved.get(&caps[1]))
+                .is_none_or(|value| !value.is_empty())
+        })
+        .map(|arg| {
+            PLACEHOLDER
+                .replace_all(arg, |caps: &regex::Captures| {
+                    resolved
+                        .get(&caps[1])
+                        .cloned()
+                        .unwrap_or_else(|| caps[0].to_string())
+                })
+                .into_owned()
+        })
+        .collect();
+    Ok((args, resolved))
+}
+
+/// 値全体に一致させるパターン
+fn full_match(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{pattern})$"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameter(name: &str, default: Option<&str>, required: bool) -> ProcessParameter {
+        ProcessParameter {
+            name: name.to_string(),
+            description: None,
+            default: default.map(str::to_string),
+            required,
+            pattern: None,
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_substitutes_values_for_one_run() {
+        let mut features = parameter("features", Some("default"), false);
+        features.pattern = Some("[a-z,]+".to_string());
+        let parameters = vec![parameter("filter", None, false), features];
+        let template = args(&["test", "--features={{features}}", "{{filter}}", "{{other}}"]);
+
+        // 指定しなければデフォルト値、空になる引数は取り除く
+        let (resolved, values) = resolve(&parameters, &template, &HashMap::new()).unwrap();
+        assert_eq!(resolved, args(&["test", "--features=default", "{{other}}"]));
+        assert_eq!(values["filter"], "");
+
+        let given = HashMap::from([("filter".to_string(), "parser::".to_string())]);
+        let (resolved, _) = resolve(&parameters, &template, &given).unwrap();
+        assert_eq!(
+            resolved,
+            args(&["test", "--features=default", "parser::", "{{other}}"])
+        );
+
+        let bad = HashMap::from([("features".to_string(), "A B".to_string())]);
+        assert!(resolve(&parameters, &template, &bad).is_err());
+        let unknown = HashMap::from([("filtre".to_string(), "x".to_string())]);
+        let error = resolve(&parameters, &template, &unknown).unwrap_err();
+        assert!(error.contains("declared: filter, features"));
+    }
+
+    #[test]
+    fn test_required_and_declarations() {
+        let parameters = vec![parameter("target", None, true)];
+        assert!(resolve(&parameters, &args(&["{{target}}"]), &HashMap::new()).is_err());
+
+        assert!(validate(&[parameter("a b", None, false)]).is_err());
+        assert!(validate(&[parameter("x", None, false), parameter("x", None, false)]).is_err());
+        let mut mismatched = parameter("level", Some("loud"), false);
+        mismatched.pattern = Some("debug|info".to_string());
+        assert!(validate(&[mismatched]).is_err());
+    }
+}
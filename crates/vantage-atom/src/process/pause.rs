@@ -0,0 +1,74 @@
+//! プロセスの一時停止と再開
+//!
+//! Unix系ではプロセスグループにSIGSTOP/SIGCONTを送ります。メモリ上の状態は保たれたまま、
+//! CPUを使わなくなります。コンテナで起動したプロセスは、クライアントではなくコンテナ自体を
+//! `pause`/`unpause` します。
+//!
+//! WindowsにはSIGSTOPに相当するシグナルがないため、一時停止はエラーになります。
+//! 状態を保つ必要がなければ、代わりに `stop_process` と `start_process` を使ってください。
+
+use super::chaos::ChaosSignal;
+use super::container;
+use std::process::Stdio;
+use tokio::process::Command;
+use vantage_persistence::ContainerConfig;
+
+/// プロセスを一時停止する
+pub async fn suspend(
+    process_id: &str,
+    pid: u32,
+    container: Option<&ContainerConfig>,
+) -> Result<(), String> {
+    match container {
+        Some(config) => container_command(config, "pause", process_id).await,
+        None => send(pid, ChaosSignal::Stop),
+    }
+}
+
+/// 一時停止したプロセスを再開する
+pub async fn resume(
+    process_id: &str,
+    pid: u32,
+    container: Option<&ContainerConfig>,
+) -> Result<(), String> {
+    match container {
+        Some(config) => container_command(config, "unpause", process_id).await,
+        None => send(pid, ChaosSignal::Cont),
+    }
+}
+
+#[cfg(unix)]
+fn send(pid: u32, signal: ChaosSignal) -> Result<(), String> {
+    super::chaos::send_signal(pid, signal)
+}
+
+#[cfg(not(unix))]
+fn send(_pid: u32, _signal: ChaosSignal) -> Result<(), String> {
+    Err(
+        "Pausing processes requires SIGSTOP/SIGCONT, which are not available on this platform; \
+         use stop_process and start_process instead"
+            .to_string(),
+    )
+}
+
+async fn container_command(
+    config: &ContainerConfig,
+    action: &str,
+    process_id: &str,
+) -> Result<(), String> {
+    let program = container::program(config.engine);
+    let output = Command::new(program)
+        .args([action, &container::container_name(process_id)])
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {program} {action}: {e}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{program} {action} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
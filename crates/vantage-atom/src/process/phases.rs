@@ -0,0 +1,100 @@
+//! ログマーカー間のフェーズ所要時間計算
+//!
+//! 出力行のうちマーカー（正規表現）に一致した行をフェーズの開始点とみなし、
+//! 次のマーカーまで（最後のフェーズは最終行まで）の所要時間を単調時計で算出します。
+
+use super::buffer::LogLine;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+
+/// 1フェーズ分の所要時間
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseDuration {
+    /// 一致したマーカーパターン
+    pub marker: String,
+    /// マーカーに一致した行
+    pub line: String,
+    pub started_at: DateTime<Utc>,
+    /// 次のフェーズ開始（最後のフェーズは最終行）までの時間
+    pub duration_ms: u64,
+}
+
+/// マーカー文字列を正規表現にコンパイル
+pub fn compile_markers(markers: &[String]) -> Result<Vec<Regex>, String> {
+    markers
+        .iter()
+        .map(|m| Regex::new(m).map_err(|e| format!("Invalid marker pattern '{m}': {e}")))
+        .collect()
+}
+
+/// 時系列順の行からフェーズ所要時間を計算
+pub fn compute_phase_durations(lines: &[LogLine], markers: &[Regex]) -> Vec<PhaseDuration> {
+    let mut starts: Vec<(&Regex, &LogLine)> = Vec::new();
+    for line in lines {
+        if let Some(marker) = markers.iter().find(|m| m.is_match(&line.text)) {
+            starts.push((marker, line));
+        }
+    }
+
+    let last_instant = lines.last().map(|l| l.instant);
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, (marker, line))| {
+            let end = starts
+                .get(i + 1)
+                .map(|(_, next)| next.instant)
+                .or(last_instant)
+                .unwrap_or(line.instant);
+            PhaseDuration {
+                marker: marker.as_str().to_string(),
+                line: line.text.clone(),
+                started_at: line.timestamp,
+                duration_ms: end.saturating_duration_since(line.instant).as_millis() as u64,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::buffer::LogStream;
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn line_at(text: &str, base: Instant, offset_ms: u64) -> LogLine {
+        LogLine {
+            text: text.to_string(),
+            timestamp: Utc::now(),
+            instant: base + Duration::from_millis(offset_ms),
+            seq: 0,
+            stream: LogStream::Stdout,
+        }
+    }
+
+    #[test]
+    fn test_phase_durations_between_markers() {
+        let base = Instant::now();
+        let lines = vec![
+            line_at("Compiling app", base, 0),
+            line_at("warning: unused", base, 100),
+            line_at("Finished build", base, 1500),
+            line_at("Listening on :3000", base, 2000),
+        ];
+        let markers =
+            compile_markers(&["^Compiling".to_string(), "Listening".to_string()]).unwrap();
+
+        let phases = compute_phase_durations(&lines, &markers);
+        assert_eq!(phases.len(), 2);
+        assert_eq!(phases[0].duration_ms, 2000);
+        assert_eq!(phases[1].line, "Listening on :3000");
+        assert_eq!(phases[1].duration_ms, 0);
+    }
+
+    #[test]
+    fn test_invalid_marker_is_rejected() {
+        assert!(compile_markers(&["(".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+//! ポートを使用しているプロセスとサービスの特定
+//!
+//! ポートの競合（EADDRINUSE）が起きたとき、そのポートで待ち受けているPIDと、それが
+//! systemd・launchdのサービスであればユニット名を調べ、止めるためのコマンドを提案します。
+//! PIDは `lsof`（Linuxでは `ss` も）で調べるため、ほかのユーザーのプロセスは権限によって
+//! 見えないことがあります。
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::LazyLock;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// 外部コマンドの待ち時間
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 時刻（12:34:56 など）。ポート番号と取り違えないよう先に取り除く
+static TIME_OF_DAY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(^|[^\d:])\d{1,2}:\d{2}(:\d{2})?([.,]\d+)?([^\d:]|$)").unwrap());
+static PORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bport\s+(\d{1,5})\b|:(\d{1,5})\b").unwrap());
+static SS_PID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"pid=(\d+)").unwrap());
+
+/// サービスを管理しているもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceManager {
+    /// systemdのシステムサービス
+    Systemd,
+    /// systemdのユーザーサービス（`systemctl --user`）
+    SystemdUser,
+    Launchd,
+}
+
+/// ポートを使用しているサービス
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceUnit {
+    pub manager: ServiceManager,
+    /// ユニット名（launchdではラベル）
+    pub name: String,
+}
+
+/// ポートを使用しているもの
+#[derive(Debug, Clone, Serialize)]
+pub struct PortOwner {
+    pub port: u16,
+    /// 待ち受けているPID（権限で見えない場合はNone）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// 管理プロセス（またはその子孫）の場合、そのID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub managed_process_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<ServiceUnit>,
+    pub summary: String,
+    /// 止めるためのコマンド
+    pub suggestions: Vec<String>,
+}
+
+impl PortOwner {
+    /// エラーメッセージに添える説明（要約と最初の提案）
+    pub fn describe(&self) -> String {
+        match self.suggestions.first() {
+            Some(suggestion) => format!("{}; try `{suggestion}`", self.summary),
+            None => self.summary.clone(),
+        }
+    }
+}
+
+/// ポートの競合を示す行から、ポート番号を取り出す
+pub fn conflict_ports(line: &str) -> Vec<u16> {
+    let line = TIME_OF_DAY.replace_all(line, "$1 $4");
+    let mut ports = Vec::new();
+    for captures in PORT.captures_iter(&line) {
+        let Some(port) = captures
+            .get(1)
+            .or_else(|| captures.get(2))
+            .and_then(|m| m.as_str().parse::<u16>().ok())
+            .filter(|port| *port > 0)
+        else {
+            continue;
+        };
+        if !ports.contains(&port) {
+            ports.push(port);
+        }
+    }
+    ports
+}
+
+/// ポートを使用しているものを調べる
+///
+/// `managed` は管理プロセスのルートPIDとID。待ち受けているPIDがその子孫なら管理プロセスとみなす。
+pub async fn find(port: u16, managed: &HashMap<u32, String>) -> PortOwner {
+    let pids = listening_pids(port).await;
+    let managed = managed.clone();
+    let details = tokio::task::spawn_blocking(move || process_details(&pids, &managed))
+        .await
+        .ok()
+        .flatten();
+    let (pid, command, managed_process_id) = match details {
+        Some((pid, command, managed_process_id)) => (Some(pid), command, managed_process_id),
+        None => (None, None, None),
+    };
+    let service = match pid {
+        Some(pid) if managed_process_id.is_none() => service_of(pid).await,
+        _ => None,
+    };
+    build(port, pid, command, managed_process_id, service)
+}
+
+fn build(
+    port: u16,
+    pid: Option<u32>,
+    command: Option<String>,
+    managed_process_id: Option<String>,
+    service: Option<ServiceUnit>,
+) -> PortOwner {
+    let holder = match (pid, &command) {
+        (Some(pid), Some(command)) => format!("PID {pid} ({command})"),
+        (Some(pid), None) => format!("PID {pid}"),
+        _ => String::new(),
+    };
+    let mut suggestions = Vec::new();
+    let summary = if let Some(id) = &managed_process_id {
+        suggestions.push(format!("stop_process(id=\"{id}\")"));
+        format!("Port {port} is held by {holder}, started by the managed process '{id}'")
+    } else if let Some(service) = &service {
+        let name = &service.name;
+        match service.manager {
+            ServiceManager::Systemd => {
+                suggestions.push(format!("sudo systemctl stop {name}"));
+                suggestions.push(format!("sudo systemctl disable --now {name}"));
+            }
+            ServiceManager::SystemdUser => {
+                suggestions.push(format!("systemctl --user stop {name}"));
+                suggestions.push(format!("systemctl --user disable --now {name}"));
+            }
+            ServiceManager::Launchd => {
+                suggestions.push(format!("launchctl bootout gui/$(id -u)/{name}"));
+            }
+        }
+        let manager = match service.manager {
+            ServiceManager::Systemd => "systemd service",
+            ServiceManager::SystemdUser => "systemd user service",
+            ServiceManager::Launchd => "launchd job",
+        };
+        format!("Port {port} is held by {holder}, which belongs to the {manager} {name}")
+    } else if pid == Some(1) {
+        // systemdのソケットアクティベーションではPID 1が待ち受ける
+        suggestions.push(format!("systemctl list-sockets --all | grep ':{port}'"));
+        suggestions.push("sudo systemctl stop <unit>.socket".to_string());
+        format!("Port {port} is held by {holder}, likely a socket-activated systemd unit")
+    } else if let Some(pid) = pid {
+        if command
+            .as_deref()
+            .is_some_and(|c| c.starts_with("docker-proxy") || c == "com.docker.backend")
+        {
+            suggestions.push(format!("docker ps --filter publish={port}"));
+        }
+        suggestions.push(format!("kill {pid}"));
+        format!("Port {port} is held by {holder}, which is not a managed process")
+    } else {
+        suggestions.push(format!("sudo lsof -nP -iTCP:{port} -sTCP:LISTEN"));
+        format!("Port {port} is in use, but the owning process is not visible to this user")
+    };
+    PortOwner {
+        port,
+        pid,
+        command,
+        managed_process_id,
+        service,
+        summary,
+        suggestions,
+    }
+}
+
+/// 待ち受けているPIDのうち代表のもの（ワーカーの親）と、コマンド名・管理プロセスのID
+fn process_details(
+    pids: &[u32],
+    managed: &HashMap<u32, String>,
+) -> Option<(u32, Option<String>, Option<String>)> {
+    if pids.is_empty() {
+        return None;
+    }
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+    let parent_of = |pid: u32| {
+        system
+            .process(Pid::from_u32(pid))
+            .and_then(|p| p.parent())
+            .map(|p| p.as_u32())
+    };
+    // 同じソケットを共有するワーカーより、その親を代表にする
+    let pid = pids
+        .iter()
+        .copied()
+        .find(|pid| parent_of(*pid).is_none_or(|parent| !pids.contains(&parent)))
+        .unwrap_or(pids[0]);
+    let command = system
+        .process(Pid::from_u32(pid))
+        .map(|p| p.name().to_string_lossy().to_string());
+
+    let mut current = Some(pid);
+    let mut managed_process_id = None;
+    while let Some(ancestor) = current.filter(|p| *p > 1) {
+        if let Some(id) = managed.get(&ancestor) {
+            managed_process_id = Some(id.clone());
+            break;
+        }
+        current = parent_of(ancestor);
+    }
+    Some((pid, command, managed_process_id))
+}
+
+/// ポートを待ち受けているPID
+async fn listening_pids(port: u16) -> Vec<u32> {
+    let lsof_port = format!("-iTCP:{port}");
+    if let Some(output) = run("lsof", &["-nP", &lsof_port, "-sTCP:LISTEN", "-t"]).await {
+        let pids: Vec<u32> = output
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect();
+        if !pids.is_empty() {
+            return pids;
+        }
+    }
+    if cfg!(target_os = "linux") {
+        let ss_port = format!(":{port}");
+        if let Some(output) = run("ss", &["-Hltnp", "sport", "=", &ss_port]).await {
+            return parse_ss_pids(&output);
+        }
+    }
+    Vec::new()
+}
+
+fn parse_ss_pids(output: &str) -> Vec<u32> {
+    let mut pids = Vec::new();
+    for captures in SS_PID.captures_iter(output) {
+        if let Ok(pid) = captures[1].parse()
+            && !pids.contains(&pid)
+        {
+            pids.push(pid);
+        }
+    }
+    pids
+}
+
+/// PIDが属するサービス
+async fn service_of(pid: u32) -> Option<ServiceUnit> {
+    if cfg!(target_os = "linux") {
+        let cgroup = tokio::fs::read_to_string(format!("/proc/{pid}/cgroup"))
+            .await
+            .ok()?;
+        systemd_unit(&cgroup)
+    } else if cfg!(target_os = "macos") {
+        let output = run("launchctl", &["list"]).await?;
+        launchd_label(&output, pid).map(|name| ServiceUnit {
+            manager: ServiceManager::Launchd,
+            name,
+        })
+    } else {
+        None
+    }
+}
+
+/// /proc/<pid>/cgroup の内容からsystemdのサービスを求める
+///
+/// シェルから起動したプロセス（session-N.scope など）はサービスとみなさない。
+pub fn systemd_unit(cgroup: &str) -> Option<ServiceUnit> {
+    let path = cgroup.lines().find_map(|line| {
+        let mut fields = line.splitn(3, ':');
+        let (_, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+        (controllers.is_empty() || controllers == "name=systemd").then_some(path)
+    })?;
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    let user_manager = components
+        .iter()
+        .position(|c| c.starts_with("user@") && c.ends_with(".service"));
+    let (index, name) = components
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(_, c)| c.ends_with(".service"))?;
+    let manager = match user_manager {
+        // ユーザーマネージャー自身はサービスとして止める対象ではない
+        Some(position) if position == index => return None,
+        Some(_) => ServiceManager::SystemdUser,
+        None => ServiceManager::Systemd,
+    };
+    Some(ServiceUnit {
+        manager,
+        name: name.to_string(),
+    })
+}
+
+/// `launchctl list` の出力からPIDのラベルを求める
+pub fn launchd_label(output: &str, pid: u32) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let listed = fields.next()?.parse::<u32>().ok()?;
+        let label = fields.nth(1)?;
+        (listed == pid).then(|| label.to_string())
+    })
+}
+
+async fn run(program: &str, args: &[&str]) -> Option<String> {
+    let mut cmd = tokio::process::Command::new(program);
+    cmd.args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, cmd.output())
+        .await
+        .ok()?
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conflict_ports_and_service_units() {
+        assert_eq!(
+            conflict_ports(
+                "2024-05-01T12:34:56Z Error: listen EADDRINUSE: address already in use :::3000"
+            ),
+            vec![3000]
+        );
+        assert_eq!(
+            conflict_ports("bind 127.0.0.1:5432 failed: port 5432 is already in use"),
+            vec![5432]
+        );
+        assert!(conflict_ports("OSError: [Errno 98] Address already in use").is_empty());
+
+        assert_eq!(
+            systemd_unit("0::/system.slice/postgresql@16-main.service\n"),
+            Some(ServiceUnit {
+                manager: ServiceManager::Systemd,
+                name: "postgresql@16-main.service".to_string(),
+            })
+        );
+        assert_eq!(
+            systemd_unit(
+                "0::/user.slice/user-1000.slice/user@1000.service/app.slice/redis.service\n"
+            ),
+            Some(ServiceUnit {
+                manager: ServiceManager::SystemdUser,
+                name: "redis.service".to_string(),
+            })
+        );
+        assert_eq!(
+            systemd_unit("0::/user.slice/user-1000.slice/session-2.scope\n"),
+            None
+        );
+        assert_eq!(
+            launchd_label(
+                "PID\tStatus\tLabel\n-\t0\tcom.apple.x\n812\t0\thomebrew.mxcl.postgresql@16\n",
+                812
+            ),
+            Some("homebrew.mxcl.postgresql@16".to_string())
+        );
+        assert_eq!(
+            parse_ss_pids(
+                "LISTEN 0 244 127.0.0.1:5432 0.0.0.0:* users:((\"postgres\",pid=812,fd=7))"
+            ),
+            vec![812]
+        );
+
+        let owner = build(
+            5432,
+            Some(812),
+            Some("postgres".to_string()),
+            None,
+            systemd_unit("0::/system.slice/postgresql.service"),
+        );
+        assert_eq!(
+            owner.suggestions[0],
+            "sudo systemctl stop postgresql.service"
+        );
+        assert!(owner.summary.contains("systemd service postgresql.service"));
+        assert!(
+            build(8080, None, None, None, None)
+                .summary
+                .contains("not visible")
+        );
+    }
+}
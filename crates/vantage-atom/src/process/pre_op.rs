@@ -0,0 +1,130 @@
+//! 危険な操作の前の自動スナップショット
+//!
+//! インポート・スナップショットからの復元・一括削除など、多くのプロセス定義を書き換える操作の
+//! 前に、その時点の定義を データディレクトリの `pre-op/` に自動で退避します。失敗した操作は
+//! `undo_last_operation` で直前の状態に戻せます。履歴は直近の数件だけを残します。
+
+use super::types::BulkOperationFailure;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use vantage_persistence::{PreOpSnapshot, ProcessInfo as DbProcessInfo};
+
+/// 残すスナップショットの数
+pub const MAX_HISTORY: usize = 10;
+
+/// スナップショットの保存先（データディレクトリの `pre-op`）
+pub fn snapshot_dir() -> PathBuf {
+    vantage_persistence::paths::get().pre_op_dir()
+}
+
+/// スナップショットのファイル
+pub fn snapshot_path(snapshot_id: &str) -> PathBuf {
+    snapshot_dir().join(format!("{snapshot_id}.yaml"))
+}
+
+/// 操作の取り消しの結果
+#[derive(Debug, Clone, Serialize)]
+pub struct UndoReport {
+    /// 戻したスナップショット
+    pub snapshot: PreOpSnapshot,
+    /// 定義を戻した（再作成した）プロセス。停止した状態で戻す
+    pub restored: Vec<String>,
+    /// 操作で追加されたため削除したプロセス
+    pub removed: Vec<String>,
+    /// 変わっていなかったプロセス
+    pub unchanged: Vec<String>,
+    pub failed: Vec<BulkOperationFailure>,
+}
+
+/// スナップショットの状態に戻すための変更
+#[derive(Debug, Default)]
+pub struct UndoPlan {
+    /// 作り直す定義（ID順）
+    pub restore: Vec<DbProcessInfo>,
+    /// 削除するプロセス（ID順）
+    pub remove: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+/// 現在の定義とスナップショットを比べて、戻すための変更を求める
+pub fn plan(current: &HashMap<String, DbProcessInfo>, snapshot: Vec<DbProcessInfo>) -> UndoPlan {
+    let mut plan = UndoPlan::default();
+    let kept: HashSet<&str> = snapshot.iter().map(|p| p.process_id.as_str()).collect();
+    plan.remove = current
+        .keys()
+        .filter(|id| !kept.contains(id.as_str()))
+        .cloned()
+        .collect();
+    plan.remove.sort();
+
+    for info in snapshot {
+        let same = current
+            .get(&info.process_id)
+            .is_some_and(|existing| same_definition(existing, &info));
+        if same {
+            plan.unchanged.push(info.process_id);
+        } else {
+            plan.restore.push(info);
+        }
+    }
+    plan.restore.sort_by(|a, b| a.process_id.cmp(&b.process_id));
+    plan.unchanged.sort();
+    plan
+}
+
+/// 実行状態を除いて定義が同じか
+fn same_definition(a: &DbProcessInfo, b: &DbProcessInfo) -> bool {
+    let definition = |info: &DbProcessInfo| {
+        let mut value = serde_json::to_value(info).unwrap_or_default();
+        if let Some(map) = value.as_object_mut() {
+            for key in ["id", "status", "created_at", "updated_at"] {
+                map.remove(key);
+            }
+        }
+        value
+    };
+    definition(a) == definition(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(id: &str, command: &str) -> DbProcessInfo {
+        serde_json::from_value(serde_json::json!({
+            "process_id": id,
+            "name": id,
+            "command": command,
+            "args": [],
+            "env": {},
+            "cwd": null,
+            "status": vantage_persistence::ProcessStatus::default(),
+            "tags": [],
+            "auto_start_on_restore": false,
+            "created_at": chrono::Utc::now(),
+            "updated_at": chrono::Utc::now(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plan_restores_changed_and_removes_added() {
+        let current = HashMap::from([
+            ("api".to_string(), info("api", "node")),
+            ("web".to_string(), info("web", "vite")),
+            ("added".to_string(), info("added", "sleep")),
+        ]);
+        let snapshot = vec![
+            info("api", "node"),
+            info("web", "npm"),
+            info("removed", "redis-server"),
+        ];
+
+        let plan = plan(&current, snapshot);
+        assert_eq!(plan.unchanged, vec!["api"]);
+        assert_eq!(plan.remove, vec!["added"]);
+        let restored: Vec<&str> = plan.restore.iter().map(|p| p.process_id.as_str()).collect();
+        assert_eq!(restored, vec!["removed", "web"]);
+    }
+}
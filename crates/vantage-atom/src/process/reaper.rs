@@ -0,0 +1,226 @@
+//! 終了済みの子プロセスの回収と、記録したPIDの検証
+//!
+//! 二重forkする子プロセスや、起動後に待たれなくなった子プロセスは、終了してもゾンビとして
+//! 残り続けます。定期的にこれを回収（wait）し、あわせて記録しているPIDが今も同じプロセスを
+//! 指しているかを確かめます。
+//!
+//! PIDは終了後に再利用されるため、プロセスの起動時刻が記録した起動時刻と一致するかで
+//! 同じプロセスかどうかを判定します。シグナルを送る前にも同じ確認を行い、無関係の
+//! プロセスにシグナルを送らないようにします。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System};
+use utoipa::ToSchema;
+
+/// 回収と検証の間隔
+pub const REAP_INTERVAL: Duration = Duration::from_secs(30);
+/// 記録した起動時刻とOSの起動時刻の許容誤差（秒）
+const START_TIME_TOLERANCE_SECS: i64 = 5;
+
+/// 記録したPIDの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PidStatus {
+    /// 記録したプロセスが実行中
+    Alive,
+    /// 終了しているが回収されていない
+    Zombie,
+    /// プロセスが存在しない
+    Gone,
+    /// 別のプロセスがPIDを使っている
+    Reused,
+}
+
+/// 回収した子プロセス
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ReapedChild {
+    pub pid: u32,
+    /// 終了コード（シグナルで終了した場合はNone）
+    pub exit_code: Option<i32>,
+}
+
+/// 実体に合わせて訂正したプロセスの状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StateCorrection {
+    pub process_id: String,
+    pub pid: u32,
+    /// 訂正前の状態（`running` または `paused`）
+    pub previous_state: String,
+    pub status: PidStatus,
+}
+
+/// 1回の回収と検証の結果
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ReapReport {
+    pub reaped: Vec<ReapedChild>,
+    pub corrected: Vec<StateCorrection>,
+}
+
+/// 回収と検証の状態（前回の確認結果を覚えておく）
+#[derive(Default)]
+pub struct Reaper {
+    system: System,
+    /// 前回の確認で見つけたゾンビ
+    zombies: HashSet<u32>,
+    /// 前回の確認で実体と一致しなかったプロセスのID
+    mismatched: HashSet<String>,
+}
+
+impl Reaper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// プロセス一覧を更新する
+    pub fn refresh(&mut self) {
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing(),
+        );
+    }
+
+    /// 記録したPIDを検証する（`refresh` の時点の一覧を使う）
+    pub fn check(&self, pid: u32, started_at: DateTime<Utc>) -> PidStatus {
+        status_of(&self.system, pid, started_at)
+    }
+
+    /// 自身の子プロセスのうち、前回の確認から続けてゾンビのものを回収する
+    ///
+    /// `tracked` は終了監視タスクが待っているPID。終了直後のプロセスは待っている側が
+    /// 回収するため、2回続けてゾンビだったものだけを対象にする。
+    pub fn reap_zombies(&mut self, tracked: &HashSet<u32>) -> Vec<ReapedChild> {
+        let own = Pid::from_u32(std::process::id());
+        let zombies: HashSet<u32> = self
+            .system
+            .processes()
+            .values()
+            .filter(|p| p.parent() == Some(own) && p.status() == ProcessStatus::Zombie)
+            .map(|p| p.pid().as_u32())
+            .filter(|pid| !tracked.contains(pid))
+            .collect();
+        let previous = std::mem::replace(&mut self.zombies, zombies);
+        let mut reaped: Vec<ReapedChild> = self
+            .zombies
+            .intersection(&previous)
+            .filter_map(|&pid| reap(pid).map(|exit_code| ReapedChild { pid, exit_code }))
+            .collect();
+        self.zombies
+            .retain(|pid| !reaped.iter().any(|r| r.pid == *pid));
+        reaped.sort_by_key(|r| r.pid);
+        reaped
+    }
+
+    /// 実体と一致しないプロセスを記録し、前回も一致しなかったかを返す
+    pub fn mark_mismatched(&mut self, id: &str) -> bool {
+        !self.mismatched.insert(id.to_string())
+    }
+
+    /// 実体と一致したプロセスの記録を消す
+    pub fn clear_mismatched(&mut self, id: &str) {
+        self.mismatched.remove(id);
+    }
+}
+
+/// 記録したPIDを検証する（シグナルを送る前の確認用）
+pub fn verify(pid: u32, started_at: DateTime<Utc>) -> PidStatus {
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+    status_of(&system, pid, started_at)
+}
+
+/// シグナルを送ってよいかを確認する
+pub fn ensure_same_process(id: &str, pid: u32, started_at: DateTime<Utc>) -> Result<(), String> {
+    match verify(pid, started_at) {
+        PidStatus::Alive => Ok(()),
+        PidStatus::Zombie | PidStatus::Gone => {
+            Err(format!("Process '{id}' (PID {pid}) is no longer running"))
+        }
+        PidStatus::Reused => Err(format!(
+            "PID {pid} of process '{id}' now belongs to another process; refusing to send a signal"
+        )),
+    }
+}
+
+fn status_of(system: &System, pid: u32, started_at: DateTime<Utc>) -> PidStatus {
+    let Some(process) = system.process(Pid::from_u32(pid)) else {
+        return PidStatus::Gone;
+    };
+    let start_time = process.start_time() as i64;
+    if (start_time - started_at.timestamp()).abs() > START_TIME_TOLERANCE_SECS {
+        return PidStatus::Reused;
+    }
+    if process.status() == ProcessStatus::Zombie {
+        PidStatus::Zombie
+    } else {
+        PidStatus::Alive
+    }
+}
+
+/// ゾンビを回収し、終了コードを返す（自身の子でない場合などはNone）
+#[cfg(unix)]
+fn reap(pid: u32) -> Option<Option<i32>> {
+    use nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
+
+    match waitpid(
+        nix::unistd::Pid::from_raw(pid as i32),
+        Some(WaitPidFlag::WNOHANG),
+    ) {
+        Ok(WaitStatus::Exited(_, code)) => Some(Some(code)),
+        Ok(WaitStatus::Signaled(..)) => Some(None),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn reap(_pid: u32) -> Option<Option<i32>> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_detects_reuse_and_exit() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        let now = Utc::now();
+        assert_eq!(verify(pid, now), PidStatus::Alive);
+        assert_eq!(
+            verify(pid, now - chrono::Duration::hours(1)),
+            PidStatus::Reused
+        );
+        assert!(ensure_same_process("sleeper", pid, now).is_ok());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(verify(pid, now), PidStatus::Gone);
+        assert!(ensure_same_process("sleeper", pid, now).is_err());
+    }
+
+    // 待たずに残したゾンビを `reap` で回収する
+    #[test]
+    #[allow(clippy::zombie_processes)]
+    fn test_reap_collects_exit_code() {
+        let child = std::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(verify(pid, Utc::now()), PidStatus::Zombie);
+        assert_eq!(reap(pid), Some(Some(3)));
+        assert_eq!(verify(pid, Utc::now()), PidStatus::Gone);
+    }
+}
@@ -0,0 +1,458 @@
+//! 出力の記録と再生
+//!
+//! `output_recording` を設定したプロセスは、起動のたびにstdout・stderr（設定すればREPLに送った
+//! 標準入力も）の各行を、起動からの経過時間付きでJSONLファイルに書き出します。1行目は記録の情報、
+//! 2行目以降が出力の行です。出力を伏せるルールを適用した後、サンプリングで間引く前の行を記録します。
+//! 記録は `<state_dir>/recordings/<process_id>/` に置き、設定した数を超えると古いものから削除します。
+
+use super::stdin::InputEncoding;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::warn;
+use utoipa::ToSchema;
+use vantage_persistence::OutputRecordingConfig;
+
+/// 残す記録の数の既定値
+pub const DEFAULT_KEEP: usize = 10;
+/// 記録ファイルの拡張子
+const EXTENSION: &str = "jsonl";
+
+/// 記録の置き場所（状態ディレクトリの `recordings`）
+pub fn default_recordings_dir() -> PathBuf {
+    vantage_persistence::paths::get().recordings_dir()
+}
+
+/// 記録した行のストリーム
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedStream {
+    Stdout,
+    Stderr,
+    /// REPLプロセスに送ったコード
+    Stdin,
+}
+
+/// 記録ファイルの1行目
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RecordingHeader {
+    pub process_id: String,
+    /// 起動の通し番号（サーバーを再起動すると1から数え直す）
+    pub run: u64,
+    pub command: String,
+    pub args: Vec<String>,
+    pub started_at: DateTime<Utc>,
+    pub include_stdin: bool,
+}
+
+/// 記録した1行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RecordedLine {
+    /// 起動からの経過時間（ミリ秒）
+    pub offset_ms: u64,
+    pub stream: RecordedStream,
+    pub line: String,
+    /// `base64` なら標準入力に送ったUTF-8でないバイト列を `line` にBase64で記録している
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<InputEncoding>,
+}
+
+/// 記録の一覧の項目
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RecordingSummary {
+    /// 記録の名前（`replay_output` の `recording` に指定する）
+    pub name: String,
+    pub run: u64,
+    pub started_at: DateTime<Utc>,
+    pub lines: usize,
+    /// 最後の行までの経過時間（ミリ秒）
+    pub duration_ms: u64,
+    pub bytes: u64,
+}
+
+/// 読み込んだ記録
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Recording {
+    pub name: String,
+    pub header: RecordingHeader,
+    pub lines: Vec<RecordedLine>,
+}
+
+/// 起動ごとの記録先（ストリームごとに複製して使い、すべてなくなると書き込みタスクも終了する）
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    tx: mpsc::UnboundedSender<RecordedLine>,
+    started: Instant,
+    stream: RecordedStream,
+    include_stdin: bool,
+}
+
+impl Recorder {
+    /// 記録ファイルを作り、書き込みタスクを起動する（古い記録はここで削除する）
+    pub fn start(
+        dir: &Path,
+        config: &OutputRecordingConfig,
+        header: RecordingHeader,
+    ) -> Result<Self, String> {
+        let dir = dir.join(&header.process_id);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        let name = format!(
+            "{}-run{}",
+            header.started_at.format("%Y%m%dT%H%M%S%3fZ"),
+            header.run
+        );
+        let path = dir.join(format!("{name}.{EXTENSION}"));
+        let mut first = serde_json::to_string(&header).map_err(|e| e.to_string())?;
+        first.push('\n');
+        std::fs::write(&path, first)
+            .map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+        prune(&dir, config.keep.unwrap_or(DEFAULT_KEEP).max(1));
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(write_lines(path, rx));
+        Ok(Self {
+            tx,
+            started: Instant::now(),
+            stream: RecordedStream::Stdout,
+            include_stdin: config.include_stdin,
+        })
+    }
+
+    /// 指定したストリームの行を記録する複製
+    pub fn for_stream(&self, stream: RecordedStream) -> Self {
+        Self {
+            stream,
+            ..self.clone()
+        }
+    }
+
+    /// 行を記録する
+    pub fn record(&self, line: &str) {
+        self.send(line.to_string(), None);
+    }
+
+    fn send(&self, line: String, encoding: Option<InputEncoding>) {
+        let _ = self.tx.send(RecordedLine {
+            offset_ms: self.started.elapsed().as_millis() as u64,
+            stream: self.stream,
+            line,
+            encoding,
+        });
+    }
+
+    /// REPLや標準入力に送った内容を記録する（`include_stdin` のときだけ）
+    pub fn record_stdin(&self, code: &str) {
+        if !self.include_stdin {
+            return;
+        }
+        let stdin = self.for_stream(RecordedStream::Stdin);
+        for line in code.lines() {
+            stdin.record(line);
+        }
+    }
+
+    /// 標準入力に書き込んだバイト列を記録する（UTF-8でなければまとめてBase64で1行にする）
+    pub fn record_stdin_bytes(&self, input: &[u8]) {
+        match std::str::from_utf8(input) {
+            Ok(text) => self.record_stdin(text),
+            Err(_) if self.include_stdin => self
+                .for_stream(RecordedStream::Stdin)
+                .send(STANDARD.encode(input), Some(InputEncoding::Base64)),
+            Err(_) => {}
+        }
+    }
+}
+
+/// 受け取った行をファイルに追記する（届いた分をまとめて書き、都度フラッシュする）
+async fn write_lines(path: PathBuf, mut rx: mpsc::UnboundedReceiver<RecordedLine>) {
+    let file = match tokio::fs::OpenOptions::new().append(true).open(&path).await {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("Failed to open recording {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let mut writer = tokio::io::BufWriter::new(file);
+    while let Some(line) = rx.recv().await {
+        let mut batch = vec![line];
+        while let Ok(line) = rx.try_recv() {
+            batch.push(line);
+        }
+        let mut text = String::new();
+        for line in &batch {
+            if let Ok(json) = serde_json::to_string(line) {
+                text.push_str(&json);
+                text.push('\n');
+            }
+        }
+        let written = match writer.write_all(text.as_bytes()).await {
+            Ok(()) => writer.flush().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            warn!("Failed to write recording {}: {}", path.display(), e);
+            return;
+        }
+    }
+}
+
+/// 記録ファイルを新しい順に並べる
+fn recording_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect();
+    // 名前は起動時刻から始まるため、名前の順が時刻の順になる
+    files.sort();
+    files.reverse();
+    files
+}
+
+/// 新しいものから `keep` 個を残して削除する
+fn prune(dir: &Path, keep: usize) {
+    for path in recording_files(dir).into_iter().skip(keep) {
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!("Failed to remove old recording {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn name_of(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// 記録ファイルを読み込む（壊れた行は読み飛ばす）
+fn parse(name: String, text: &str) -> Result<Recording, String> {
+    let mut rows = text.lines().filter(|row| !row.trim().is_empty());
+    let header = rows
+        .next()
+        .ok_or_else(|| format!("Recording '{name}' is empty"))?;
+    let header: RecordingHeader = serde_json::from_str(header)
+        .map_err(|e| format!("Recording '{name}' has an invalid header: {e}"))?;
+    let lines = rows
+        .filter_map(|row| serde_json::from_str(row).ok())
+        .collect();
+    Ok(Recording {
+        name,
+        header,
+        lines,
+    })
+}
+
+/// プロセスの記録の一覧（新しい順）
+pub fn list(dir: &Path, process_id: &str) -> Vec<RecordingSummary> {
+    recording_files(&dir.join(process_id))
+        .into_iter()
+        .filter_map(|path| {
+            let text = std::fs::read_to_string(&path).ok()?;
+            let recording = parse(name_of(&path), &text).ok()?;
+            Some(RecordingSummary {
+                name: recording.name,
+                run: recording.header.run,
+                started_at: recording.header.started_at,
+                lines: recording.lines.len(),
+                duration_ms: recording.lines.last().map_or(0, |line| line.offset_ms),
+                bytes: text.len() as u64,
+            })
+        })
+        .collect()
+}
+
+/// 記録のファイルの場所（名前を省略すると最新の記録）
+pub fn path(dir: &Path, process_id: &str, name: Option<&str>) -> Result<PathBuf, String> {
+    let dir = dir.join(process_id);
+    match name {
+        Some(name) => {
+            if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+                return Err(format!("Invalid recording name '{name}'"));
+            }
+            let path = dir.join(format!("{name}.{EXTENSION}"));
+            if !path.is_file() {
+                return Err(format!(
+                    "Recording '{name}' not found for process '{process_id}'"
+                ));
+            }
+            Ok(path)
+        }
+        None => recording_files(&dir)
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Process '{process_id}' has no output recordings")),
+    }
+}
+
+/// 記録を読み込む（名前を省略すると最新の記録）
+pub fn load(dir: &Path, process_id: &str, name: Option<&str>) -> Result<Recording, String> {
+    let path = path(dir, process_id, name)?;
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    parse(name_of(&path), &text)
+}
+
+/// 記録の開始から、再生の速さ `speed` で行を出すまでの時間（0以下なら待たない）
+pub fn replay_delay(offset_ms: u64, speed: f64) -> Duration {
+    if speed <= 0.0 || !speed.is_finite() {
+        return Duration::ZERO;
+    }
+    Duration::from_secs_f64(offset_ms as f64 / 1000.0 / speed)
+}
+
+/// 記録した時間の間隔を保って（`speed` 倍で）行を順に渡す
+pub async fn replay(lines: &[RecordedLine], speed: f64, mut on_line: impl FnMut(&RecordedLine)) {
+    let started = tokio::time::Instant::now();
+    for line in lines {
+        tokio::time::sleep_until(started + replay_delay(line.offset_ms, speed)).await;
+        on_line(line);
+    }
+}
+
+/// 再生の表示用の1行（経過時間とストリームを付ける）
+pub fn format_line(line: &RecordedLine) -> String {
+    let stream = match line.stream {
+        RecordedStream::Stdout => "stdout",
+        RecordedStream::Stderr => "stderr",
+        RecordedStream::Stdin => "stdin ",
+    };
+    let encoding = match line.encoding {
+        Some(InputEncoding::Base64) => "(base64) ",
+        _ => "",
+    };
+    format!(
+        "[+{}.{:03}s] {stream} | {encoding}{}",
+        line.offset_ms / 1000,
+        line.offset_ms % 1000,
+        line.line
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(run: u64, started_at: DateTime<Utc>) -> RecordingHeader {
+        RecordingHeader {
+            process_id: "web".to_string(),
+            run,
+            command: "npm".to_string(),
+            args: vec!["run".to_string(), "dev".to_string()],
+            started_at,
+            include_stdin: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_list_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = OutputRecordingConfig {
+            include_stdin: true,
+            keep: Some(2),
+        };
+        let base = Utc::now();
+        for run in 1..=3 {
+            let started_at = base + chrono::Duration::seconds(run as i64);
+            let recorder = Recorder::start(dir.path(), &config, header(run, started_at)).unwrap();
+            recorder.record("ready");
+            recorder
+                .for_stream(RecordedStream::Stderr)
+                .record(&format!("warning {run}"));
+            recorder.record_stdin("1 + 1\nprint(2)");
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // 新しい2つだけが残る
+        let recordings = list(dir.path(), "web");
+        assert_eq!(
+            recordings.iter().map(|r| r.run).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+        assert_eq!(recordings[0].lines, 4);
+
+        let latest = load(dir.path(), "web", None).unwrap();
+        assert_eq!(latest.header.run, 3);
+        let streams: Vec<_> = latest.lines.iter().map(|l| l.stream).collect();
+        assert_eq!(
+            streams,
+            vec![
+                RecordedStream::Stdout,
+                RecordedStream::Stderr,
+                RecordedStream::Stdin,
+                RecordedStream::Stdin
+            ]
+        );
+        assert_eq!(latest.lines[1].line, "warning 3");
+
+        let older = load(dir.path(), "web", Some(&recordings[1].name)).unwrap();
+        assert_eq!(older.header.run, 2);
+        assert!(load(dir.path(), "web", Some("../web")).is_err());
+        assert!(load(dir.path(), "api", None).is_err());
+    }
+
+    #[test]
+    fn test_stdin_is_recorded_only_when_enabled() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let recorder = Recorder {
+            tx,
+            started: Instant::now(),
+            stream: RecordedStream::Stdout,
+            include_stdin: false,
+        };
+        recorder.record_stdin("secret()");
+        recorder.record_stdin_bytes(&[0xff, 0x04]);
+        recorder.record("out");
+        assert_eq!(rx.try_recv().unwrap().line, "out");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_binary_stdin_is_recorded_as_base64() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let recorder = Recorder {
+            tx,
+            started: Instant::now(),
+            stream: RecordedStream::Stdout,
+            include_stdin: true,
+        };
+        recorder.record_stdin_bytes("yes\n".as_bytes());
+        recorder.record_stdin_bytes(&[0xff, 0x04]);
+
+        let text = rx.try_recv().unwrap();
+        assert_eq!((text.line.as_str(), text.encoding), ("yes", None));
+        let binary = rx.try_recv().unwrap();
+        assert_eq!(binary.stream, RecordedStream::Stdin);
+        assert_eq!(binary.line, "/wQ=");
+        assert_eq!(binary.encoding, Some(InputEncoding::Base64));
+        assert_eq!(
+            format_line(&binary),
+            format!("[+0.{:03}s] stdin  | (base64) /wQ=", binary.offset_ms)
+        );
+    }
+
+    #[test]
+    fn test_replay_delay_and_format() {
+        assert_eq!(replay_delay(1500, 1.0), Duration::from_millis(1500));
+        assert_eq!(replay_delay(1500, 3.0), Duration::from_millis(500));
+        assert_eq!(replay_delay(1500, 0.0), Duration::ZERO);
+
+        let line = RecordedLine {
+            offset_ms: 12_034,
+            stream: RecordedStream::Stderr,
+            line: "boom".to_string(),
+            encoding: None,
+        };
+        assert_eq!(format_line(&line), "[+12.034s] stderr | boom");
+    }
+}
@@ -0,0 +1,142 @@
+//! 停止したプロセスのまとめての片付けとごみ箱
+//!
+//! `cleanup_processes` は停止してから一定の時間が経った・IDがパターンに一致する・タグが付いた
+//! プロセスを選び、完全には削除せずにごみ箱へ移します。移したプロセスは
+//! `restore_from_recycle_bin` で停止した状態のまま元に戻せます。ごみ箱には直近の件数だけを残します。
+//!
+//! 条件はフィルター式（`state == stopped && stopped_at < now()-7d && ...`）に組み立てて評価するため、
+//! `list_processes` に同じ式を渡せば一致するプロセスを確かめられます。
+
+use super::types::BulkOperationFailure;
+use serde::Serialize;
+
+/// ごみ箱に残す定義の数
+pub const MAX_ITEMS: usize = 100;
+
+/// 片付けるプロセスの条件（指定したものをすべて満たすプロセスが対象）
+#[derive(Debug, Clone, Default)]
+pub struct CleanupFilter {
+    /// 異常終了（failed）したプロセスも対象にする
+    pub include_failed: bool,
+    /// 停止してから経った時間（`30m`、`7d` など）
+    pub older_than: Option<String>,
+    /// IDのパターン（`*` と `?` が使えるglob）
+    pub id_pattern: Option<String>,
+    pub tag: Option<String>,
+    /// サーバー起動時に自動起動するプロセスも対象にする
+    pub include_auto_start: bool,
+}
+
+impl CleanupFilter {
+    /// 条件をプロセスのフィルター式にする
+    pub fn to_expression(&self) -> Result<String, String> {
+        let mut clauses = vec![if self.include_failed {
+            "state in [stopped, failed]".to_string()
+        } else {
+            "state == stopped".to_string()
+        }];
+        if let Some(age) = &self.older_than {
+            if !is_duration(age) {
+                return Err(format!(
+                    "Invalid older_than '{age}': use a number with ms, s, m, h, d or w (e.g. 7d)"
+                ));
+            }
+            clauses.push(format!("stopped_at < now()-{age}"));
+        }
+        if let Some(pattern) = &self.id_pattern {
+            clauses.push(format!("id ~ {}", quote(&glob_to_regex(pattern))));
+        }
+        if let Some(tag) = &self.tag {
+            clauses.push(format!("tag in [{}]", quote(tag)));
+        }
+        if !self.include_auto_start {
+            clauses.push("auto_start == false".to_string());
+        }
+        Ok(clauses.join(" && "))
+    }
+}
+
+/// 片付けた件数
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanupCounts {
+    pub matched: usize,
+    pub recycled: usize,
+    pub failed: usize,
+    /// 片付けた後にごみ箱に入っている定義の数
+    pub in_recycle_bin: usize,
+}
+
+/// 片付けの結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanupReport {
+    /// 条件から組み立てたフィルター式
+    pub expression: String,
+    /// 条件に一致したプロセス（ID順）
+    pub matched: Vec<String>,
+    /// ごみ箱に移したプロセス
+    pub recycled: Vec<String>,
+    pub failed: Vec<BulkOperationFailure>,
+    /// ごみ箱からあふれて完全に削除された定義
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub purged: Vec<String>,
+    pub counts: CleanupCounts,
+    /// 一致を確かめただけで片付けていないか
+    pub dry_run: bool,
+}
+
+/// `30m` のような時間の指定か（フィルター式に埋め込む前に確かめる）
+fn is_duration(value: &str) -> bool {
+    let digits = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = &value[digits.len()..];
+    !digits.is_empty()
+        && digits.parse::<f64>().is_ok_and(|n| n >= 0.0)
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && matches!(unit, "ms" | "s" | "m" | "h" | "d" | "w")
+}
+
+/// globを全体に一致する正規表現にする
+fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// フィルター式の文字列にする
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::filter_expr::parse_process_filter;
+
+    #[test]
+    fn test_filter_builds_a_valid_expression() {
+        let filter = CleanupFilter {
+            older_than: Some("7d".to_string()),
+            id_pattern: Some("job-*.it's".to_string()),
+            tag: Some("one-shot".to_string()),
+            ..Default::default()
+        };
+        let expression = filter.to_expression().unwrap();
+        assert_eq!(
+            expression,
+            r#"state == stopped && stopped_at < now()-7d && id ~ '^job\\-.*\\.it\'s$' && tag in ['one-shot'] && auto_start == false"#
+        );
+        parse_process_filter(&expression).unwrap();
+
+        let injected = CleanupFilter {
+            older_than: Some("1h || true".to_string()),
+            ..Default::default()
+        };
+        assert!(injected.to_expression().is_err());
+    }
+}
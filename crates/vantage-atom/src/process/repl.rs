@@ -0,0 +1,278 @@
+//! REPLプロセス
+//!
+//! 標準入力からコードを受け付けるインタプリタに対して、コードの前後で完了マーカーを出力させ、
+//! マーカーの間の出力をその実行の結果として切り出します。マーカーが出力されるまでを実行中、
+//! それ以外を待機中とみなします。
+
+use super::buffer::{CircularBuffer, LogLine};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::ChildStdin;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+use vantage_persistence::{ReplConfig, ReplLanguage};
+
+/// 出力の確認間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// 完了マーカーの確認後、標準エラー出力の読み取りが追いつくのを待つ時間
+///
+/// 標準エラー出力は別のタスクで読み取るため、完了マーカーより後にバッファに届くことがある。
+const STDERR_SETTLE: Duration = Duration::from_millis(100);
+
+/// REPLの状態
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReplStatus {
+    /// コードを実行中か
+    pub busy: bool,
+    /// これまでの実行回数
+    pub executions: u64,
+}
+
+/// 1回の実行の結果
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplExecution {
+    /// 実行番号（1から連番）
+    pub execution: u64,
+    pub stdout: Vec<String>,
+    pub stderr: Vec<String>,
+    /// 完了マーカーを確認できたか（falseならタイムアウト）
+    pub completed: bool,
+    pub duration_ms: u64,
+}
+
+/// 実行中のREPLプロセスとの対話
+pub struct ReplSession {
+    stdin: Mutex<ChildStdin>,
+    executions: AtomicU64,
+}
+
+impl ReplSession {
+    pub fn new(stdin: ChildStdin) -> Self {
+        Self {
+            stdin: Mutex::new(stdin),
+            executions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn status(&self) -> ReplStatus {
+        ReplStatus {
+            busy: self.stdin.try_lock().is_err(),
+            executions: self.executions.load(Ordering::SeqCst),
+        }
+    }
+
+    /// コードを送信し、完了マーカーが出力されるまで待って出力を返す
+    ///
+    /// 実行は1つずつ行い、他の実行中の場合は完了を待つ（待ち時間もタイムアウトに含む）。
+    pub async fn execute(
+        &self,
+        config: &ReplConfig,
+        code: &str,
+        stdout: &CircularBuffer,
+        stderr: &CircularBuffer,
+        timeout: Duration,
+    ) -> Result<ReplExecution, String> {
+        let deadline = Instant::now() + timeout;
+        let mut stdin = tokio::time::timeout(timeout, self.stdin.lock())
+            .await
+            .map_err(|_| "REPL is still busy with a previous execution".to_string())?;
+
+        let execution = self.executions.fetch_add(1, Ordering::SeqCst) + 1;
+        let start_marker = format!("__vantage_start_{execution}__");
+        let end_marker = format!("__vantage_done_{execution}__");
+        let payload = build_payload(config, code, &start_marker, &end_marker)?;
+
+        let started = Instant::now();
+        stdin
+            .write_all(payload.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write to REPL stdin: {e}"))?;
+        stdin
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to write to REPL stdin: {e}"))?;
+
+        let prompts = prompts(config);
+        loop {
+            let lines = lines_since(stdout, started).await;
+            let end = lines.iter().position(|l| l.text.contains(&end_marker));
+            if end.is_some() || Instant::now() >= deadline {
+                let end_at = end.map(|i| lines[i].instant);
+                let end = end.unwrap_or(lines.len());
+                let begin = lines[..end]
+                    .iter()
+                    .position(|l| l.text.contains(&start_marker))
+                    .map_or(0, |i| i + 1);
+                let mut out: Vec<String> = lines[begin..end]
+                    .iter()
+                    .filter_map(|l| strip_prompts(&l.text, &prompts))
+                    .collect();
+                if let Some(echo) = marker_echo(config.language)
+                    && out.first().map(String::as_str) == Some(echo)
+                {
+                    out.remove(0);
+                }
+                if end_at.is_some() {
+                    tokio::time::sleep(STDERR_SETTLE).await;
+                }
+                let err = lines_since(stderr, started)
+                    .await
+                    .into_iter()
+                    .filter(|l| end_at.is_none_or(|at| l.instant <= at + STDERR_SETTLE))
+                    .filter_map(|l| strip_prompts(&l.text, &prompts))
+                    .collect();
+
+                return Ok(ReplExecution {
+                    execution,
+                    stdout: out,
+                    stderr: err,
+                    completed: end_at.is_some(),
+                    duration_ms: started.elapsed().as_millis() as u64,
+                });
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// 設定を検証
+pub fn validate(config: &ReplConfig) -> Result<(), String> {
+    match (&config.language, &config.marker_command) {
+        (ReplLanguage::Custom, None) => {
+            Err("repl.marker_command is required for the custom language".to_string())
+        }
+        (_, Some(command)) if !command.contains("{marker}") => {
+            Err("repl.marker_command must contain '{marker}'".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 開始マーカー、コード、完了マーカーの順に送る入力を作る
+fn build_payload(
+    config: &ReplConfig,
+    code: &str,
+    start_marker: &str,
+    end_marker: &str,
+) -> Result<String, String> {
+    let mut payload = marker_statement(config, start_marker)?;
+    payload.push('\n');
+    payload.push_str(code.trim_end());
+    payload.push('\n');
+    if config.language == ReplLanguage::Python {
+        // 複合文（for / def など）を空行で閉じる
+        payload.push('\n');
+    }
+    payload.push_str(&marker_statement(config, end_marker)?);
+    payload.push('\n');
+    Ok(payload)
+}
+
+fn marker_statement(config: &ReplConfig, marker: &str) -> Result<String, String> {
+    if let Some(command) = &config.marker_command {
+        return Ok(command.replace("{marker}", marker));
+    }
+    Ok(match config.language {
+        ReplLanguage::Python => format!("print('{marker}', flush=True)"),
+        ReplLanguage::Node => format!("console.log('{marker}')"),
+        ReplLanguage::Shell => format!("echo {marker}"),
+        ReplLanguage::Custom => {
+            return Err("repl.marker_command is required for the custom language".to_string());
+        }
+    })
+}
+
+/// マーカーを出力する文の評価結果としてREPLが表示する行
+fn marker_echo(language: ReplLanguage) -> Option<&'static str> {
+    match language {
+        ReplLanguage::Node => Some("undefined"),
+        _ => None,
+    }
+}
+
+fn prompts(config: &ReplConfig) -> Vec<String> {
+    if !config.prompts.is_empty() {
+        return config.prompts.clone();
+    }
+    let defaults: &[&str] = match config.language {
+        ReplLanguage::Python => &[">>> ", "... "],
+        ReplLanguage::Node => &["> ", "... "],
+        ReplLanguage::Shell | ReplLanguage::Custom => &[],
+    };
+    defaults.iter().map(|p| p.to_string()).collect()
+}
+
+/// 行頭のプロンプトを取り除く（プロンプトのみの行はNone）
+fn strip_prompts(line: &str, prompts: &[String]) -> Option<String> {
+    let mut rest = line;
+    let mut stripped = false;
+    while let Some(p) = prompts.iter().find(|p| rest.starts_with(p.as_str())) {
+        rest = &rest[p.len()..];
+        stripped = true;
+    }
+    if stripped && rest.trim().is_empty() {
+        None
+    } else {
+        Some(rest.to_string())
+    }
+}
+
+async fn lines_since(buffer: &CircularBuffer, since: Instant) -> Vec<LogLine> {
+    buffer
+        .get_all_lines()
+        .await
+        .into_iter()
+        .filter(|l| l.instant >= since)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(language: ReplLanguage) -> ReplConfig {
+        ReplConfig {
+            language,
+            marker_command: None,
+            prompts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_build_payload() {
+        let payload = build_payload(
+            &config(ReplLanguage::Python),
+            "for i in range(2):\n    print(i)\n",
+            "S",
+            "E",
+        )
+        .unwrap();
+        assert_eq!(
+            payload,
+            "print('S', flush=True)\nfor i in range(2):\n    print(i)\n\nprint('E', flush=True)\n"
+        );
+
+        let custom = ReplConfig {
+            marker_command: Some("puts '{marker}'".to_string()),
+            ..config(ReplLanguage::Custom)
+        };
+        assert_eq!(
+            build_payload(&custom, "1 + 1", "S", "E").unwrap(),
+            "puts 'S'\n1 + 1\nputs 'E'\n"
+        );
+        assert!(validate(&config(ReplLanguage::Custom)).is_err());
+    }
+
+    #[test]
+    fn test_strip_prompts() {
+        let prompts = prompts(&config(ReplLanguage::Python));
+        assert_eq!(
+            strip_prompts(">>> >>> Traceback (most recent call last):", &prompts).as_deref(),
+            Some("Traceback (most recent call last):")
+        );
+        assert_eq!(strip_prompts(">>> ... ", &prompts), None);
+        assert_eq!(strip_prompts("", &prompts).as_deref(), Some(""));
+    }
+}
@@ -0,0 +1,172 @@
+//! CPU使用率・メモリ（RSS）の標本と直近の統計
+//!
+//! アイドル検知のタスクが実行中のプロセスのCPU使用率とRSSを定期的に記録し、
+//! `get_process_status` は最新の値と直近 [`HISTORY_WINDOW_SECS`] 秒の最小・最大・平均を返します。
+//! 計測の対象は起動したプロセス自身（子プロセスは含まない）で、再起動すると記録し直します。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use utoipa::ToSchema;
+
+/// 統計に含める期間（秒）
+pub const HISTORY_WINDOW_SECS: i64 = 600;
+
+/// 1回の標本
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: DateTime<Utc>,
+    /// CPU使用率（%）。差分が無い最初の標本では `None`
+    cpu_percent: Option<f32>,
+    /// RSS（バイト）
+    memory_bytes: u64,
+}
+
+/// 実行中のプロセスの標本（古い順）
+#[derive(Debug, Clone, Default)]
+pub struct ResourceSamples {
+    pid: Option<u32>,
+    samples: VecDeque<Sample>,
+}
+
+impl ResourceSamples {
+    /// 標本を記録する
+    ///
+    /// PIDが変わった場合は再起動とみなして記録し直す。CPU使用率は前回の更新との差分で
+    /// 算出されるため、そのPIDの最初の標本では記録しない。
+    pub fn record(&mut self, pid: u32, cpu_percent: f32, memory_bytes: u64, now: DateTime<Utc>) {
+        let first = self.pid != Some(pid);
+        if first {
+            self.pid = Some(pid);
+            self.samples.clear();
+        }
+        self.samples.push_back(Sample {
+            at: now,
+            cpu_percent: (!first).then_some(cpu_percent),
+            memory_bytes,
+        });
+        let cutoff = now - chrono::Duration::seconds(HISTORY_WINDOW_SECS);
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| sample.at < cutoff)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// 最新の標本のCPU使用率とRSS（`pid` の標本が無ければ `None`）
+    pub fn latest(&self, pid: u32) -> Option<(Option<f32>, u64)> {
+        if self.pid != Some(pid) {
+            return None;
+        }
+        self.samples
+            .back()
+            .map(|sample| (sample.cpu_percent, sample.memory_bytes))
+    }
+
+    /// `pid` の直近の統計（標本が無ければ `None`）
+    pub fn history(&self, pid: u32, now: DateTime<Utc>) -> Option<ResourceHistory> {
+        if self.pid != Some(pid) {
+            return None;
+        }
+        let cutoff = now - chrono::Duration::seconds(HISTORY_WINDOW_SECS);
+        let recent: Vec<&Sample> = self
+            .samples
+            .iter()
+            .filter(|sample| sample.at >= cutoff)
+            .collect();
+        let first = recent.first()?;
+        let cpu: Vec<f64> = recent
+            .iter()
+            .filter_map(|sample| sample.cpu_percent)
+            .map(f64::from)
+            .collect();
+        let memory: Vec<f64> = recent
+            .iter()
+            .map(|sample| sample.memory_bytes as f64)
+            .collect();
+        Some(ResourceHistory {
+            window_secs: HISTORY_WINDOW_SECS as u64,
+            since: first.at,
+            samples: recent.len(),
+            cpu_percent: UsageSummary::of(&cpu),
+            memory_bytes: UsageSummary::of(&memory),
+        })
+    }
+}
+
+/// 直近の期間のCPU使用率・RSSの統計
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResourceHistory {
+    /// 統計に含める期間（秒）
+    pub window_secs: u64,
+    /// 期間内で最も古い標本の時刻（起動して間もない場合は期間より短い）
+    pub since: DateTime<Utc>,
+    /// 標本の数
+    pub samples: usize,
+    /// CPU使用率（%、1コアで100%）
+    pub cpu_percent: Option<UsageSummary>,
+    /// RSS（バイト）
+    pub memory_bytes: Option<UsageSummary>,
+}
+
+/// 最小・最大・平均
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UsageSummary {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+impl UsageSummary {
+    fn of(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let avg = values.iter().sum::<f64>() / values.len() as f64;
+        Some(Self { min, max, avg })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_covers_the_window_of_the_current_pid() {
+        let start = Utc::now() - chrono::Duration::seconds(HISTORY_WINDOW_SECS + 60);
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+        let mut samples = ResourceSamples::default();
+
+        // 期間より古い標本は統計から外れる
+        samples.record(10, 0.0, 1000, at(0));
+        samples.record(10, 90.0, 9000, at(30));
+        samples.record(10, 10.0, 2000, at(120));
+        samples.record(10, 30.0, 4000, at(180));
+        let now = at(HISTORY_WINDOW_SECS + 60);
+        let history = samples.history(10, now).unwrap();
+        assert_eq!(history.samples, 2);
+        assert_eq!(history.since, at(120));
+        assert_eq!(
+            history.cpu_percent,
+            Some(UsageSummary {
+                min: 10.0,
+                max: 30.0,
+                avg: 20.0
+            })
+        );
+        assert_eq!(history.memory_bytes.unwrap().max, 4000.0);
+        assert_eq!(samples.latest(10), Some((Some(30.0), 4000)));
+
+        // 再起動すると記録し直し、最初の標本にはCPU使用率が無い
+        samples.record(11, 50.0, 500, now);
+        assert!(samples.history(10, now).is_none());
+        let history = samples.history(11, now).unwrap();
+        assert_eq!(history.samples, 1);
+        assert!(history.cpu_percent.is_none());
+        assert_eq!(samples.latest(11), Some((None, 500)));
+    }
+}
@@ -0,0 +1,249 @@
+//! 再起動ポリシー
+//!
+//! 終了したプロセスを `restart_policy` に従って自動で再起動します。終了監視タスクが
+//! 終了の種類とそれまでに続けて再起動した回数から、再起動するかどうかと待ち時間を決めます。
+//! `STABLE_RUN` 以上動いてから終了した場合は回数を数え直します。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use utoipa::ToSchema;
+use vantage_persistence::{RestartMode, RestartPolicy};
+
+/// この時間以上動いてから終了した場合は、続けて再起動した回数を数え直す
+pub const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// ポリシーを検証
+pub fn validate(policy: &RestartPolicy) -> Result<(), String> {
+    if policy.max_retries == Some(0) {
+        return Err(
+            "restart_policy.max_retries must be at least 1 (use mode \"never\" to disable restarts)"
+                .to_string(),
+        );
+    }
+    if policy.backoff_ms > policy.max_backoff_ms {
+        return Err(
+            "restart_policy.backoff_ms must not be greater than max_backoff_ms".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// `attempt` 回目（1から）の再起動までの待ち時間
+pub fn delay(policy: &RestartPolicy, attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(32);
+    let delay = policy.backoff_ms.saturating_mul(1u64 << exponent);
+    Duration::from_millis(delay.min(policy.max_backoff_ms))
+}
+
+/// 終了したときに行うこと
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// 再起動しない
+    None,
+    /// `delay` 待ってから `attempt` 回目の再起動をする
+    Restart { attempt: u32, delay: Duration },
+    /// 上限の回数まで再起動したのでやめる
+    GiveUp { attempts: u32 },
+}
+
+/// 自動再起動の状態
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RestartStatus {
+    /// 続けて再起動した回数
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// 再起動を待っている場合、その予定時刻
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_restart_at: Option<DateTime<Utc>>,
+    /// 上限の回数に達して再起動をやめた時刻
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gave_up_at: Option<DateTime<Utc>>,
+}
+
+/// プロセスごとの自動再起動の回数と予定
+#[derive(Debug, Default)]
+pub struct RestartTracker {
+    attempts: u32,
+    /// 予定している再起動（回数と時刻）
+    pending: Option<(u32, DateTime<Utc>)>,
+    gave_up_at: Option<DateTime<Utc>>,
+}
+
+impl RestartTracker {
+    /// 停止要求によらない終了で、再起動するかどうかを決めて予定する
+    ///
+    /// `failed` は終了コードが0以外か、終了を待てなかったか。`ran_for` は起動から終了までの時間。
+    pub fn on_exit(
+        &mut self,
+        policy: &RestartPolicy,
+        failed: bool,
+        ran_for: Duration,
+        now: DateTime<Utc>,
+    ) -> Decision {
+        self.pending = None;
+        let wanted = match policy.mode {
+            RestartMode::Never => false,
+            RestartMode::OnFailure => failed,
+            RestartMode::Always => true,
+        };
+        if !wanted {
+            self.reset();
+            return Decision::None;
+        }
+        if ran_for >= STABLE_RUN {
+            self.attempts = 0;
+        }
+        if policy
+            .max_retries
+            .is_some_and(|max_retries| self.attempts >= max_retries)
+        {
+            self.gave_up_at = Some(now);
+            return Decision::GiveUp {
+                attempts: self.attempts,
+            };
+        }
+        self.attempts += 1;
+        self.gave_up_at = None;
+        let delay = delay(policy, self.attempts);
+        let due = now + chrono::Duration::from_std(delay).unwrap_or_default();
+        self.pending = Some((self.attempts, due));
+        Decision::Restart {
+            attempt: self.attempts,
+            delay,
+        }
+    }
+
+    /// 予定の時刻が来ていれば予定を外し、何回目の再起動かを返す
+    ///
+    /// 取り消されたか、後から別の予定に置き換わった場合はNone。
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Option<u32> {
+        let (attempt, due) = self.pending?;
+        if due > now {
+            return None;
+        }
+        self.pending = None;
+        Some(attempt)
+    }
+
+    /// 予定している再起動を取り消す（予定があった場合はtrue）
+    pub fn cancel(&mut self) -> bool {
+        self.pending.take().is_some()
+    }
+
+    /// 手動の起動・停止で、予定を取り消して回数を数え直す
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn status(&self, policy: &RestartPolicy) -> RestartStatus {
+        RestartStatus {
+            attempts: self.attempts,
+            max_retries: policy.max_retries,
+            next_restart_at: self.pending.map(|(_, due)| due),
+            gave_up_at: self.gave_up_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(mode: RestartMode, max_retries: Option<u32>) -> RestartPolicy {
+        RestartPolicy {
+            mode,
+            max_retries,
+            backoff_ms: 1000,
+            max_backoff_ms: 5000,
+        }
+    }
+
+    #[test]
+    fn test_on_failure_retries_until_the_limit() {
+        let policy = policy(RestartMode::OnFailure, Some(3));
+        let mut tracker = RestartTracker::default();
+        let now = Utc::now();
+        let short = Duration::from_secs(1);
+
+        // 正常終了では再起動しない
+        assert_eq!(tracker.on_exit(&policy, false, short, now), Decision::None);
+
+        for (attempt, secs) in [(1, 1), (2, 2), (3, 4)] {
+            assert_eq!(
+                tracker.on_exit(&policy, true, short, now),
+                Decision::Restart {
+                    attempt,
+                    delay: Duration::from_secs(secs),
+                }
+            );
+        }
+        assert_eq!(
+            tracker.on_exit(&policy, true, short, now),
+            Decision::GiveUp { attempts: 3 }
+        );
+        assert!(tracker.status(&policy).gave_up_at.is_some());
+
+        // 安定して動いた後の終了では数え直す
+        assert_eq!(
+            tracker.on_exit(&policy, true, STABLE_RUN, now),
+            Decision::Restart {
+                attempt: 1,
+                delay: Duration::from_secs(1),
+            }
+        );
+    }
+
+    #[test]
+    fn test_always_caps_the_backoff() {
+        let policy = policy(RestartMode::Always, None);
+        let mut tracker = RestartTracker::default();
+        let now = Utc::now();
+        let mut last = Decision::None;
+        for _ in 0..10 {
+            last = tracker.on_exit(&policy, false, Duration::ZERO, now);
+        }
+        assert_eq!(
+            last,
+            Decision::Restart {
+                attempt: 10,
+                delay: Duration::from_secs(5),
+            }
+        );
+
+        let never = RestartPolicy {
+            mode: RestartMode::Never,
+            ..policy
+        };
+        assert_eq!(
+            RestartTracker::default().on_exit(&never, true, Duration::ZERO, now),
+            Decision::None
+        );
+    }
+
+    #[test]
+    fn test_pending_restart_is_taken_once() {
+        let policy = policy(RestartMode::OnFailure, None);
+        let mut tracker = RestartTracker::default();
+        let now = Utc::now();
+        tracker.on_exit(&policy, true, Duration::ZERO, now);
+        assert_eq!(tracker.take_due(now), None);
+        let due = now + chrono::Duration::seconds(1);
+        assert_eq!(tracker.take_due(due), Some(1));
+        assert_eq!(tracker.take_due(due), None);
+
+        // 取り消した予定は実行しない
+        tracker.on_exit(&policy, true, Duration::ZERO, now);
+        assert!(tracker.cancel());
+        assert_eq!(tracker.take_due(now + chrono::Duration::seconds(10)), None);
+
+        assert!(
+            validate(&RestartPolicy {
+                backoff_ms: 10_000,
+                ..policy.clone()
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! 別のローカルアカウントでの起動
+//!
+//! `run_as` で指定したユーザー・グループを名前またはIDから解決し、起動時に setgid / setuid で
+//! 切り替えます。現在のユーザー以外に切り替えるにはサーバーをrootで実行している必要があり、
+//! 権限が足りない場合は起動前にエラーにします。切り替えたプロセスの実効ユーザーは監査ログ
+//! （`vantage::audit` ターゲット）に記録します。
+
+use vantage_persistence::RunAsConfig;
+
+/// 監査ログのtracingターゲット
+pub const AUDIT_TARGET: &str = "vantage::audit";
+
+/// 解決済みのユーザー・グループ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub user: String,
+    pub uid: u32,
+    pub group: String,
+    pub gid: u32,
+    pub home: Option<String>,
+}
+
+/// ユーザー・グループを解決する
+#[cfg(unix)]
+pub fn resolve(config: &RunAsConfig) -> Result<Identity, String> {
+    use nix::unistd::{Gid, Group, Uid, User};
+
+    let user_spec = config.user.trim();
+    if user_spec.is_empty() {
+        return Err("run_as.user must not be empty".to_string());
+    }
+    let user = match user_spec.parse::<u32>() {
+        Ok(uid) => User::from_uid(Uid::from_raw(uid)),
+        Err(_) => User::from_name(user_spec),
+    }
+    .map_err(|e| format!("Failed to look up user '{user_spec}': {e}"))?
+    .ok_or_else(|| format!("Unknown user '{user_spec}' in run_as.user"))?;
+
+    let group = match config.group.as_deref().map(str::trim) {
+        Some("") => return Err("run_as.group must not be empty".to_string()),
+        Some(group_spec) => match group_spec.parse::<u32>() {
+            Ok(gid) => Group::from_gid(Gid::from_raw(gid)),
+            Err(_) => Group::from_name(group_spec),
+        }
+        .map_err(|e| format!("Failed to look up group '{group_spec}': {e}"))?
+        .ok_or_else(|| format!("Unknown group '{group_spec}' in run_as.group"))?,
+        None => Group::from_gid(user.gid)
+            .map_err(|e| format!("Failed to look up group of user '{}': {e}", user.name))?
+            .ok_or_else(|| {
+                format!(
+                    "Primary group {} of user '{}' does not exist",
+                    user.gid, user.name
+                )
+            })?,
+    };
+
+    Ok(Identity {
+        user: user.name,
+        uid: user.uid.as_raw(),
+        group: group.name,
+        gid: group.gid.as_raw(),
+        home: user.dir.to_str().map(str::to_string),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn resolve(_config: &RunAsConfig) -> Result<Identity, String> {
+    Err("run_as is only supported on Unix".to_string())
+}
+
+/// サーバーの権限でそのユーザー・グループに切り替えられるか確認する
+#[cfg(unix)]
+pub fn check_privileges(identity: &Identity) -> Result<(), String> {
+    use nix::unistd::{getegid, geteuid};
+
+    let euid = geteuid();
+    if euid.is_root() || (identity.uid == euid.as_raw() && identity.gid == getegid().as_raw()) {
+        return Ok(());
+    }
+    Err(format!(
+        "Insufficient privileges to run as {}: the server runs as uid {} and must run as root to switch users",
+        describe(identity),
+        euid.as_raw()
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn check_privileges(_identity: &Identity) -> Result<(), String> {
+    Err("run_as is only supported on Unix".to_string())
+}
+
+/// 設定を検証し、切り替え先を解決する
+pub fn validate(config: &RunAsConfig) -> Result<Identity, String> {
+    let identity = resolve(config)?;
+    check_privileges(&identity)?;
+    Ok(identity)
+}
+
+/// 切り替え先のユーザーに合わせる環境変数（プロセスの env で指定されていない場合のみ）
+pub fn default_env(identity: &Identity) -> Vec<(&'static str, String)> {
+    let mut env = vec![
+        ("USER", identity.user.clone()),
+        ("LOGNAME", identity.user.clone()),
+    ];
+    if let Some(home) = &identity.home {
+        env.push(("HOME", home.clone()));
+    }
+    env
+}
+
+/// ログ・エラー用の表記（例: `deploy(1001):www-data(33)`）
+pub fn describe(identity: &Identity) -> String {
+    format!(
+        "{}({}):{}({})",
+        identity.user, identity.uid, identity.group, identity.gid
+    )
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use nix::unistd::{Uid, User, getegid, getuid};
+
+    fn config(user: &str, group: Option<&str>) -> RunAsConfig {
+        RunAsConfig {
+            user: user.to_string(),
+            group: group.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_resolve_by_name_and_id() {
+        let me = User::from_uid(getuid()).unwrap().unwrap();
+        let by_name = resolve(&config(&me.name, None)).unwrap();
+        assert_eq!(by_name.uid, me.uid.as_raw());
+        assert_eq!(by_name.gid, me.gid.as_raw());
+
+        let by_id = resolve(&config(
+            &me.uid.to_string(),
+            Some(&me.gid.as_raw().to_string()),
+        ))
+        .unwrap();
+        assert_eq!(by_id, by_name);
+    }
+
+    #[test]
+    fn test_resolve_rejects_unknown_and_empty() {
+        assert!(resolve(&config("", None)).is_err());
+        let err = resolve(&config("vantage-no-such-user", None)).unwrap_err();
+        assert!(err.contains("Unknown user"), "{err}");
+        let err = resolve(&config("0", Some("vantage-no-such-group"))).unwrap_err();
+        assert!(err.contains("Unknown group"), "{err}");
+    }
+
+    #[test]
+    fn test_check_privileges() {
+        let me = User::from_uid(getuid()).unwrap().unwrap();
+        let identity = Identity {
+            user: me.name.clone(),
+            uid: getuid().as_raw(),
+            group: String::new(),
+            gid: getegid().as_raw(),
+            home: None,
+        };
+        assert!(check_privileges(&identity).is_ok());
+
+        if !Uid::effective().is_root() {
+            let other = Identity {
+                uid: identity.uid + 1,
+                ..identity
+            };
+            let err = check_privileges(&other).unwrap_err();
+            assert!(err.contains("must run as root"), "{err}");
+        }
+    }
+}
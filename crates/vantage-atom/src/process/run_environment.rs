@@ -0,0 +1,641 @@
+//! 起動ごとの実行環境の記録
+//!
+//! プロセスを起動するたびに、子プロセスが受け取った環境変数（サーバーから引き継いだものを含む）、
+//! PATH、ロケール、OS・ホストなどの情報と、使っているツールのバージョンを記録します。
+//! 「自分の環境では動く」の原因を探すときに、実行どうしや、チームメンバーの出力と比べられます。
+//! 値はシークレットとみなせるものを伏せて保存します。
+
+use super::env_diff::mask;
+use super::env_loader::{self, Toolchain};
+use super::failure_report::FailureReport;
+use super::types::ProcessInfo;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 記録しておく実行の数（プロセスごと）
+pub const RUN_HISTORY_CAPACITY: usize = 10;
+/// ツールのバージョンの取得を待つ時間
+const VERSION_TIMEOUT: Duration = Duration::from_secs(3);
+/// バージョンを調べるツールの数の上限
+const MAX_TOOLS: usize = 6;
+
+/// コマンドや引数に現れたらバージョンを調べるツール
+const KNOWN_TOOLS: &[&str] = &[
+    "node", "npm", "npx", "yarn", "pnpm", "bun", "deno", "python", "python3", "pip", "pip3", "uv",
+    "poetry", "ruby", "bundle", "cargo", "rustc", "go", "java", "mvn", "gradle", "docker",
+    "podman", "make",
+];
+
+/// ロケールとみなす環境変数
+const LOCALE_KEYS: &[&str] = &["LANG", "LANGUAGE", "TZ"];
+
+/// 実行したOSとホストの情報
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SystemFacts {
+    pub os: String,
+    pub kernel: Option<String>,
+    pub arch: String,
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+    pub vantage_version: String,
+}
+
+impl SystemFacts {
+    pub fn current(user: Option<String>) -> Self {
+        Self {
+            os: sysinfo::System::long_os_version()
+                .unwrap_or_else(|| std::env::consts::OS.to_string()),
+            kernel: sysinfo::System::kernel_version(),
+            arch: std::env::consts::ARCH.to_string(),
+            hostname: sysinfo::System::host_name(),
+            user,
+            vantage_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// 1回の起動の実行環境
+#[derive(Debug, Clone, Serialize)]
+pub struct RunEnvironment {
+    /// プロセスごとの通し番号（サーバーの起動から数える）
+    pub run: u64,
+    pub process_id: String,
+    pub started_at: DateTime<Utc>,
+    pub command: String,
+    /// 起動した引数（パラメータを置換した後）
+    pub args: Vec<String>,
+    /// この回に指定したパラメータの値（デフォルト値を含む）
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, String>,
+    /// PATHから解決したコマンドの場所
+    pub resolved_command: Option<String>,
+    pub cwd: Option<String>,
+    /// 起動方法（`host`、`tmux`、`container`）
+    pub runtime: String,
+    pub system: SystemFacts,
+    /// 子プロセスが受け取った環境変数（シークレットは伏せる）
+    pub env: BTreeMap<String, String>,
+    /// PATHの各ディレクトリ（先頭ほど優先）
+    pub path: Vec<String>,
+    /// LANG・LC_*・TZ
+    pub locale: BTreeMap<String, String>,
+    /// ツールのバージョン（取得中はNone）
+    pub tool_versions: Option<BTreeMap<String, String>>,
+    /// プロジェクトの環境（mise・direnv・asdf）を読み込んで起動した場合、そのツールチェーン
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<Toolchain>,
+    /// この実行が異常終了した場合、その状況
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureReport>,
+}
+
+impl RunEnvironment {
+    /// 起動した時点の環境を記録する
+    ///
+    /// `spawned` は定義から子プロセスに渡した環境変数。コンテナ以外ではサーバーの環境変数を引き継ぐ。
+    pub fn capture(
+        run: u64,
+        info: &ProcessInfo,
+        runtime: &str,
+        spawned: &HashMap<String, String>,
+        user: Option<String>,
+    ) -> Self {
+        let (command, cwd) = (info.command.as_str(), info.cwd.as_deref());
+        let mut resolved: BTreeMap<String, String> = if runtime == "container" {
+            BTreeMap::new()
+        } else {
+            std::env::vars().collect()
+        };
+        resolved.extend(spawned.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        let path = resolved
+            .get("PATH")
+            .map(|p| {
+                std::env::split_paths(p)
+                    .map(|d| d.display().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let locale = resolved
+            .iter()
+            .filter(|(k, _)| LOCALE_KEYS.contains(&k.as_str()) || k.starts_with("LC_"))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let resolved_command = (runtime != "container")
+            .then(|| which(command, resolved.get("PATH").map(String::as_str), cwd))
+            .flatten()
+            .map(|p| p.display().to_string());
+        let user = user.or_else(|| {
+            resolved
+                .get("USER")
+                .or_else(|| resolved.get("USERNAME"))
+                .cloned()
+        });
+
+        Self {
+            run,
+            process_id: info.id.clone(),
+            started_at: Utc::now(),
+            command: command.to_string(),
+            args: info.args.clone(),
+            parameters: BTreeMap::new(),
+            resolved_command,
+            cwd: cwd.map(|c| c.display().to_string()),
+            runtime: runtime.to_string(),
+            system: SystemFacts::current(user),
+            env: resolved
+                .iter()
+                .map(|(k, v)| (k.clone(), mask(k, v)))
+                .collect(),
+            path,
+            locale,
+            tool_versions: None,
+            toolchain: None,
+            failure: None,
+        }
+    }
+
+    /// PATHの文字列（バージョンの取得に使う）
+    fn path_var(&self) -> Option<String> {
+        std::env::join_paths(&self.path)
+            .ok()
+            .map(|p| p.to_string_lossy().to_string())
+    }
+}
+
+/// バージョンを調べるツール（コマンドと引数に現れた既知のツール）
+///
+/// 任意のコマンドに `--version` を付けて実行すると、そのまま起動してしまうことがあるので、
+/// 既知のツールだけを対象にする。
+pub fn tools_to_probe(command: &str, args: &[String]) -> Vec<String> {
+    let mut tools = Vec::new();
+    let words = std::iter::once(command)
+        .chain(args.iter().map(String::as_str))
+        .flat_map(|a| a.split(|c: char| c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(')))
+        .filter_map(|w| {
+            Path::new(w)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+        });
+    for word in words {
+        if KNOWN_TOOLS.contains(&word.as_str()) {
+            tools.push(word);
+        }
+    }
+    // パッケージマネージャーは実行系のバージョンも合わせて見る
+    let runtimes: Vec<&str> = tools
+        .iter()
+        .filter_map(|tool| match tool.as_str() {
+            "npm" | "npx" | "yarn" | "pnpm" => Some("node"),
+            "pip" | "pip3" | "uv" | "poetry" => Some("python3"),
+            "cargo" => Some("rustc"),
+            "mvn" | "gradle" => Some("java"),
+            _ => None,
+        })
+        .collect();
+    tools.extend(runtimes.into_iter().map(str::to_string));
+
+    let mut unique = Vec::new();
+    for tool in tools {
+        if !unique.contains(&tool) {
+            unique.push(tool);
+        }
+    }
+    unique.truncate(MAX_TOOLS);
+    unique
+}
+
+/// ツールのバージョンを調べる（見つからない・答えないツールはその旨を値にする）
+pub async fn probe_tool_versions(
+    run: &RunEnvironment,
+    tools: &[String],
+) -> BTreeMap<String, String> {
+    let path = run.path_var();
+    let cwd = run.cwd.as_ref().map(PathBuf::from);
+    let probes = tools.iter().map(|tool| {
+        let path = path.clone();
+        let cwd = cwd.clone();
+        async move {
+            let version = probe(tool, path.as_deref(), cwd.as_deref()).await;
+            (tool.clone(), version)
+        }
+    });
+    futures::future::join_all(probes)
+        .await
+        .into_iter()
+        .collect()
+}
+
+async fn probe(tool: &str, path: Option<&str>, cwd: Option<&Path>) -> String {
+    let Some(program) = which(tool, path, cwd) else {
+        return "not found in PATH".to_string();
+    };
+    let args: &[&str] = match tool {
+        "go" => &["version"],
+        "java" => &["-version"],
+        _ => &["--version"],
+    };
+    let mut cmd = tokio::process::Command::new(&program);
+    cmd.args(args)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true);
+    if let Some(path) = path {
+        cmd.env("PATH", path);
+    }
+    if let Some(cwd) = cwd.filter(|c| c.is_dir()) {
+        cmd.current_dir(cwd);
+    }
+    match tokio::time::timeout(VERSION_TIMEOUT, cmd.output()).await {
+        Ok(Ok(output)) => {
+            // javaなどはバージョンを標準エラー出力に書く
+            let text = [output.stdout, output.stderr]
+                .iter()
+                .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            text.lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(|line| line.chars().take(200).collect())
+                .unwrap_or_else(|| "no version output".to_string())
+        }
+        Ok(Err(e)) => format!("failed to run: {e}"),
+        Err(_) => "timed out".to_string(),
+    }
+}
+
+/// PATHからコマンドを探す（パスを含むコマンドは作業ディレクトリから解決する）
+pub(crate) fn which(command: &str, path: Option<&str>, cwd: Option<&Path>) -> Option<PathBuf> {
+    let command_path = Path::new(command);
+    if command_path.components().count() > 1 {
+        let resolved = match cwd {
+            Some(cwd) if command_path.is_relative() => cwd.join(command_path),
+            _ => command_path.to_path_buf(),
+        };
+        return resolved.is_file().then_some(resolved);
+    }
+    std::env::split_paths(path?)
+        .map(|dir| dir.join(command))
+        .find(|candidate| candidate.is_file())
+}
+
+/// 2つの実行の環境の差分
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunEnvironmentDiff {
+    pub base_run: u64,
+    pub added: BTreeMap<String, String>,
+    pub removed: BTreeMap<String, String>,
+    /// 値が変わった環境変数（変更前, 変更後）
+    pub changed: BTreeMap<String, (String, String)>,
+    /// 変わったツールのバージョン（変更前, 変更後）
+    pub tools: BTreeMap<String, (String, String)>,
+    /// 変わったツールチェーンのバージョン（変更前, 変更後、無い側は空）
+    pub toolchain: BTreeMap<String, (String, String)>,
+    /// 変わったOS・ホストなどの情報（変更前, 変更後）
+    pub system: BTreeMap<String, (String, String)>,
+    pub command_changed: bool,
+}
+
+impl RunEnvironmentDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.tools.is_empty()
+            && self.toolchain.is_empty()
+            && self.system.is_empty()
+            && !self.command_changed
+    }
+}
+
+/// `base` から `run` への変化
+pub fn diff(base: &RunEnvironment, run: &RunEnvironment) -> RunEnvironmentDiff {
+    let added = run
+        .env
+        .iter()
+        .filter(|(k, _)| !base.env.contains_key(*k))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let removed = base
+        .env
+        .iter()
+        .filter(|(k, _)| !run.env.contains_key(*k))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let changed = run
+        .env
+        .iter()
+        .filter_map(|(k, v)| {
+            let before = base.env.get(k).filter(|before| *before != v)?;
+            Some((k.clone(), (before.clone(), v.clone())))
+        })
+        .collect();
+
+    let empty = BTreeMap::new();
+    let before_tools = base.tool_versions.as_ref().unwrap_or(&empty);
+    let tools = run
+        .tool_versions
+        .as_ref()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|(tool, version)| {
+            let before = before_tools.get(tool).filter(|before| *before != version)?;
+            Some((tool.clone(), (before.clone(), version.clone())))
+        })
+        .collect();
+
+    let versions = |run: &RunEnvironment| {
+        run.toolchain
+            .as_ref()
+            .map(|toolchain| toolchain.versions.clone())
+            .unwrap_or_default()
+    };
+    let (before_toolchain, after_toolchain) = (versions(base), versions(run));
+    let toolchain = before_toolchain
+        .keys()
+        .chain(after_toolchain.keys())
+        .filter_map(|tool| {
+            let before = before_toolchain.get(tool).cloned().unwrap_or_default();
+            let after = after_toolchain.get(tool).cloned().unwrap_or_default();
+            (before != after).then(|| (tool.clone(), (before, after)))
+        })
+        .collect();
+
+    let facts = |facts: &SystemFacts| {
+        [
+            ("os", Some(facts.os.clone())),
+            ("kernel", facts.kernel.clone()),
+            ("arch", Some(facts.arch.clone())),
+            ("hostname", facts.hostname.clone()),
+            ("user", facts.user.clone()),
+            ("vantage_version", Some(facts.vantage_version.clone())),
+        ]
+    };
+    let system = facts(&base.system)
+        .into_iter()
+        .zip(facts(&run.system))
+        .filter(|((_, before), (_, after))| before != after)
+        .map(|((name, before), (_, after))| {
+            (
+                name.to_string(),
+                (before.unwrap_or_default(), after.unwrap_or_default()),
+            )
+        })
+        .collect();
+
+    RunEnvironmentDiff {
+        base_run: base.run,
+        added,
+        removed,
+        changed,
+        tools,
+        toolchain,
+        system,
+        command_changed: base.command != run.command
+            || base.args != run.args
+            || base.resolved_command != run.resolved_command
+            || base.cwd != run.cwd,
+    }
+}
+
+/// 人が読める形にする（`compare` があればその実行からの変化も書く）
+pub fn render(run: &RunEnvironment, compare: Option<&RunEnvironment>, include_env: bool) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Run {} of '{}' started at {} ({})",
+        run.run,
+        run.process_id,
+        run.started_at.to_rfc3339(),
+        run.runtime
+    );
+    let _ = writeln!(out, "Command: {} {}", run.command, shell_words(&run.args));
+    if !run.parameters.is_empty() {
+        let parameters: Vec<String> = run
+            .parameters
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        let _ = writeln!(out, "Parameters: {}", parameters.join(", "));
+    }
+    if let Some(resolved) = &run.resolved_command {
+        let _ = writeln!(out, "Resolved to: {resolved}");
+    }
+    let _ = writeln!(
+        out,
+        "Working directory: {}",
+        run.cwd.as_deref().unwrap_or("(server's working directory)")
+    );
+    if let Some(failure) = &run.failure {
+        let reason = match (&failure.error, failure.exit_code, &failure.signal_name) {
+            (Some(error), _, _) => error.clone(),
+            (None, Some(code), _) => format!("exit code {code}"),
+            (None, None, Some(signal)) => format!("signal {signal}"),
+            (None, None, None) => "terminated by a signal".to_string(),
+        };
+        let _ = writeln!(out, "Failed: {} ({reason})", failure.failed_at.to_rfc3339());
+    }
+
+    let system = &run.system;
+    let _ = writeln!(out, "\nSystem:");
+    let _ = writeln!(out, "  OS: {} ({})", system.os, system.arch);
+    if let Some(kernel) = &system.kernel {
+        let _ = writeln!(out, "  Kernel: {kernel}");
+    }
+    if let Some(hostname) = &system.hostname {
+        let _ = writeln!(out, "  Host: {hostname}");
+    }
+    if let Some(user) = &system.user {
+        let _ = writeln!(out, "  User: {user}");
+    }
+    let _ = writeln!(out, "  Vantage: {}", system.vantage_version);
+
+    let _ = writeln!(out, "\nLocale:");
+    if run.locale.is_empty() {
+        let _ = writeln!(out, "  (not set)");
+    }
+    for (key, value) in &run.locale {
+        let _ = writeln!(out, "  {key}={value}");
+    }
+
+    let _ = writeln!(out, "\nTool versions:");
+    match &run.tool_versions {
+        None => {
+            let _ = writeln!(out, "  (still being collected)");
+        }
+        Some(versions) => {
+            for (tool, version) in versions {
+                let _ = writeln!(out, "  {tool}: {version}");
+            }
+        }
+    }
+
+    if let Some(toolchain) = &run.toolchain {
+        let source = match &toolchain.config_file {
+            Some(file) => format!("{}, {file}", env_loader::name(toolchain.loader)),
+            None => env_loader::name(toolchain.loader).to_string(),
+        };
+        let _ = writeln!(out, "\nToolchain ({source}):");
+        if toolchain.versions.is_empty() {
+            let _ = writeln!(out, "  (no versions reported)");
+        }
+        for (tool, version) in &toolchain.versions {
+            let _ = writeln!(out, "  {tool}: {version}");
+        }
+    }
+
+    let _ = writeln!(out, "\nPATH (first match wins):");
+    for dir in &run.path {
+        let _ = writeln!(out, "  {dir}");
+    }
+
+    if include_env {
+        let _ = writeln!(
+            out,
+            "\nEnvironment ({} variables, secrets masked):",
+            run.env.len()
+        );
+        for (key, value) in &run.env {
+            let _ = writeln!(out, "  {key}={value}");
+        }
+    }
+
+    if let Some(base) = compare {
+        let changes = diff(base, run);
+        let _ = writeln!(out, "\nChanges since run {}:", base.run);
+        if changes.is_empty() {
+            let _ = writeln!(out, "  (none)");
+        }
+        if changes.command_changed {
+            let _ = writeln!(
+                out,
+                "  command: {} {} -> {} {}",
+                base.command,
+                shell_words(&base.args),
+                run.command,
+                shell_words(&run.args)
+            );
+        }
+        for (name, (before, after)) in &changes.system {
+            let _ = writeln!(out, "  {name}: {before} -> {after}");
+        }
+        for (tool, (before, after)) in &changes.tools {
+            let _ = writeln!(out, "  {tool}: {before} -> {after}");
+        }
+        for (tool, (before, after)) in &changes.toolchain {
+            let or_none = |version: &str| {
+                if version.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    version.to_string()
+                }
+            };
+            let _ = writeln!(
+                out,
+                "  toolchain {tool}: {} -> {}",
+                or_none(before),
+                or_none(after)
+            );
+        }
+        for (key, value) in &changes.added {
+            let _ = writeln!(out, "  + {key}={value}");
+        }
+        for (key, value) in &changes.removed {
+            let _ = writeln!(out, "  - {key}={value}");
+        }
+        for (key, (before, after)) in &changes.changed {
+            let _ = writeln!(out, "  ~ {key}: {before} -> {after}");
+        }
+    }
+    out
+}
+
+fn shell_words(args: &[String]) -> String {
+    args.iter()
+        .map(|a| {
+            if a.is_empty() || a.contains(char::is_whitespace) {
+                format!("'{a}'")
+            } else {
+                a.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::ManagedProcess;
+
+    #[test]
+    fn test_capture_and_diff_runs() {
+        let spawned: HashMap<String, String> = [
+            ("PATH", "/opt/node/bin:/usr/bin"),
+            ("LANG", "ja_JP.UTF-8"),
+            ("API_TOKEN", "ghp_secret"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let info = ManagedProcess::new(
+            "web".to_string(),
+            "npm".to_string(),
+            vec!["run".to_string(), "dev".to_string()],
+            HashMap::new(),
+            None,
+        )
+        .info;
+        let mut first = RunEnvironment::capture(1, &info, "container", &spawned, None);
+        assert_eq!(first.path, vec!["/opt/node/bin", "/usr/bin"]);
+        assert_eq!(first.locale.get("LANG").unwrap(), "ja_JP.UTF-8");
+        assert_eq!(first.env.get("API_TOKEN").unwrap(), "********");
+        assert_eq!(
+            tools_to_probe("npm", &["run".to_string(), "dev".to_string()]),
+            vec!["npm", "node"]
+        );
+        assert_eq!(
+            tools_to_probe(
+                "/bin/sh",
+                &["-c".to_string(), "cargo run && make".to_string()]
+            ),
+            vec!["cargo", "make", "rustc"]
+        );
+        first.tool_versions = Some([("node".to_string(), "v20.1.0".to_string())].into());
+
+        let mut second = first.clone();
+        second.run = 2;
+        second.env.insert("LANG".to_string(), "C".to_string());
+        second.env.insert("DEBUG".to_string(), "1".to_string());
+        second.tool_versions = Some([("node".to_string(), "v22.0.0".to_string())].into());
+        second.toolchain = Some(Toolchain {
+            loader: vantage_persistence::EnvLoader::Mise,
+            config_file: Some("/app/mise.toml".to_string()),
+            versions: [("node".to_string(), "22.0.0".to_string())].into(),
+        });
+
+        let changes = diff(&first, &second);
+        assert_eq!(changes.base_run, 1);
+        assert_eq!(
+            changes.changed.get("LANG").unwrap(),
+            &("ja_JP.UTF-8".to_string(), "C".to_string())
+        );
+        assert!(changes.added.contains_key("DEBUG"));
+        assert_eq!(
+            changes.tools.get("node").unwrap(),
+            &("v20.1.0".to_string(), "v22.0.0".to_string())
+        );
+        assert!(!changes.command_changed);
+
+        let text = render(&second, Some(&first), false);
+        assert!(text.contains("Changes since run 1:"), "{text}");
+        assert!(text.contains("node: v20.1.0 -> v22.0.0"), "{text}");
+        assert!(text.contains("Toolchain (mise, /app/mise.toml):"), "{text}");
+        assert!(text.contains("toolchain node: (none) -> 22.0.0"), "{text}");
+        assert!(!text.contains("ghp_secret"));
+    }
+}
@@ -0,0 +1,103 @@
+//! プロセスの手順書
+//!
+//! 障害時の復旧手順などのMarkdownをプロセスに付け、ステータス・MCPのリソース・Webコンソールで
+//! プロセスの隣に表示します。ファイルを指定した場合は表示のたびに読み込むため、
+//! リポジトリのREADMEやRUNBOOK.mdを編集すればそのまま反映されます。
+
+use crate::security::paths;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+use vantage_persistence::Runbook;
+
+/// 手順書の大きさの上限（バイト）
+pub const MAX_RUNBOOK_BYTES: usize = 256 * 1024;
+
+/// 読み込んだ手順書
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RunbookContent {
+    /// 読み込んだファイル（直接書いた場合はNone）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub markdown: String,
+}
+
+/// 手順書の指定を検証する（ファイルの有無は表示するときに確かめる）
+pub fn validate(runbook: &Runbook) -> Result<(), String> {
+    match runbook {
+        Runbook::Path(path) if path.trim().is_empty() => {
+            Err("runbook.path must not be empty".to_string())
+        }
+        Runbook::Path(path) => paths::expand_path(Path::new(path)).map(|_| ()),
+        Runbook::Inline(text) if text.trim().is_empty() => {
+            Err("runbook.inline must not be empty".to_string())
+        }
+        Runbook::Inline(text) if text.len() > MAX_RUNBOOK_BYTES => Err(format!(
+            "runbook.inline must be at most {MAX_RUNBOOK_BYTES} bytes"
+        )),
+        Runbook::Inline(_) => Ok(()),
+    }
+}
+
+/// ファイルのパス（相対パスはプロセスの作業ディレクトリから解決する）
+fn resolve(path: &str, cwd: Option<&Path>) -> Result<PathBuf, String> {
+    let path = paths::expand_path(Path::new(path))?;
+    Ok(match cwd {
+        Some(cwd) if path.is_relative() => cwd.join(path),
+        _ => path,
+    })
+}
+
+/// 手順書を読み込む
+pub fn load(runbook: &Runbook, cwd: Option<&Path>) -> Result<RunbookContent, String> {
+    match runbook {
+        Runbook::Inline(text) => Ok(RunbookContent {
+            path: None,
+            markdown: text.clone(),
+        }),
+        Runbook::Path(path) => {
+            let path = resolve(path, cwd)?;
+            let size = std::fs::metadata(&path)
+                .map_err(|e| format!("Failed to read runbook {}: {e}", path.display()))?
+                .len();
+            if size > MAX_RUNBOOK_BYTES as u64 {
+                return Err(format!(
+                    "Runbook {} is larger than {MAX_RUNBOOK_BYTES} bytes",
+                    path.display()
+                ));
+            }
+            let markdown = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read runbook {}: {e}", path.display()))?;
+            Ok(RunbookContent {
+                path: Some(path.display().to_string()),
+                markdown,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_paths_resolve_from_the_working_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/RUNBOOK.md"), "# Recover\n1. restart").unwrap();
+
+        let runbook = Runbook::Path("docs/RUNBOOK.md".to_string());
+        assert!(validate(&runbook).is_ok());
+        let content = load(&runbook, Some(dir.path())).unwrap();
+        assert_eq!(content.markdown, "# Recover\n1. restart");
+        assert!(content.path.unwrap().ends_with("docs/RUNBOOK.md"));
+
+        let missing = Runbook::Path("docs/MISSING.md".to_string());
+        assert!(load(&missing, Some(dir.path())).is_err());
+
+        let inline = Runbook::Inline("Run `make reset`".to_string());
+        assert_eq!(load(&inline, None).unwrap().path, None);
+        assert!(validate(&Runbook::Inline("  ".to_string())).is_err());
+        assert!(validate(&Runbook::Path(String::new())).is_err());
+    }
+}
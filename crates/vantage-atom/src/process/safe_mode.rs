@@ -0,0 +1,143 @@
+//! セーフモード
+//!
+//! `--safe-mode`（または環境変数 `VANTAGE_SAFE_MODE`）で起動すると、スナップショットからの
+//! 復元と自動起動を行わずにサーバーだけが立ち上がります。復元したプロセスが起動のたびに
+//! 問題を起こす場合に、原因を調べて直してから `safe_mode_restore` で選んだプロセスだけを
+//! 復元します。復元しなかったスナップショットを上書きしないよう、セーフモードの間は終了時の
+//! スナップショットとエクスポートも書き出しません。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use utoipa::ToSchema;
+
+/// セーフモードで起動する環境変数
+pub const ENV_VAR: &str = "VANTAGE_SAFE_MODE";
+
+/// 環境変数でセーフモードが指定されているか
+pub fn env_enabled() -> bool {
+    std::env::var(ENV_VAR).is_ok_and(|value| {
+        matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    })
+}
+
+/// セーフモードの状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SafeModeStatus {
+    /// セーフモードで起動した時刻
+    pub since: DateTime<Utc>,
+    /// 復元しなかったスナップショット
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    /// まだ復元していないプロセス
+    pub pending: Vec<String>,
+    /// `safe_mode_restore` で復元したプロセス
+    pub restored: Vec<String>,
+}
+
+/// `safe_mode_restore` の結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SafeModeRestoreReport {
+    pub restored: Vec<String>,
+    /// 復元して起動したプロセス（`start` 指定時、自動起動の対象のみ）
+    pub started: Vec<String>,
+    /// すでに登録されていたため復元しなかったプロセス
+    pub skipped: Vec<String>,
+    /// スナップショットに無いプロセス
+    pub not_found: Vec<String>,
+    /// 復元または起動に失敗したプロセスとその理由
+    pub failed: Vec<(String, String)>,
+    /// まだ復元していないプロセス
+    pub pending: Vec<String>,
+    /// 起動順序に従った起動の結果（`start` 指定時）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub startup: Option<super::startup::StartupReport>,
+}
+
+struct Session {
+    since: DateTime<Utc>,
+    snapshot: Option<PathBuf>,
+    restored: Vec<String>,
+}
+
+/// セーフモードのセッション（起動時に一度だけ有効にする）
+#[derive(Default)]
+pub struct SafeMode {
+    session: RwLock<Option<Session>>,
+}
+
+impl SafeMode {
+    /// セーフモードにする。`snapshot` は復元しなかったスナップショット
+    pub fn enable(&self, snapshot: Option<PathBuf>) {
+        *self.session.write().unwrap() = Some(Session {
+            since: Utc::now(),
+            snapshot,
+            restored: Vec::new(),
+        });
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.session.read().unwrap().is_some()
+    }
+
+    /// 復元しなかったスナップショット
+    pub fn snapshot(&self) -> Option<PathBuf> {
+        self.session
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|session| session.snapshot.clone())
+    }
+
+    pub fn record_restored(&self, ids: &[String]) {
+        if let Some(session) = self.session.write().unwrap().as_mut() {
+            for id in ids {
+                if !session.restored.contains(id) {
+                    session.restored.push(id.clone());
+                }
+            }
+        }
+    }
+
+    /// 状態（`pending` は呼び出し側で埋める）
+    pub fn status(&self, pending: Vec<String>) -> Option<SafeModeStatus> {
+        self.session
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|session| SafeModeStatus {
+                since: session.since,
+                snapshot: session
+                    .snapshot
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+                pending,
+                restored: session.restored.clone(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restored_processes_are_recorded_once() {
+        let safe_mode = SafeMode::default();
+        assert!(!safe_mode.is_enabled());
+        assert!(safe_mode.status(Vec::new()).is_none());
+        safe_mode.record_restored(&["api".to_string()]);
+
+        safe_mode.enable(Some(PathBuf::from("/tmp/snapshot.yaml")));
+        safe_mode.record_restored(&["api".to_string(), "web".to_string()]);
+        safe_mode.record_restored(&["api".to_string()]);
+        let status = safe_mode.status(vec!["worker".to_string()]).unwrap();
+        assert_eq!(status.restored, vec!["api", "web"]);
+        assert_eq!(status.pending, vec!["worker"]);
+        assert_eq!(status.snapshot.as_deref(), Some("/tmp/snapshot.yaml"));
+    }
+}
@@ -0,0 +1,149 @@
+//! 出力のサンプリング
+//!
+//! 毎秒数千行を出力するプロセスでもバッファや下流への配信が使えるように、N行ごとに1行だけを
+//! 残します。エラーなどのパターンに一致する行は間引かずにすべて残します。実行中に切り替えられます。
+
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// パターンを指定しない場合に常に残す行
+pub const DEFAULT_KEEP_PATTERNS: &[&str] = &[
+    r"(?i)\berror\b",
+    r"(?i)\bwarn(ing)?\b",
+    r"(?i)\bfatal\b",
+    r"(?i)\bpanic(ked)?\b",
+    r"(?i)exception",
+    r"(?i)traceback",
+];
+
+/// サンプリングの設定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct OutputSampling {
+    /// N行ごとに1行を残す
+    pub every_n: u32,
+    /// 間引かずに残す行の正規表現（空の場合はエラー・警告などの既定パターン）
+    #[serde(default)]
+    pub keep_patterns: Vec<String>,
+}
+
+/// サンプリングの状態
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SamplingStatus {
+    #[serde(flatten)]
+    pub config: OutputSampling,
+    /// 残した行数
+    pub kept_lines: u64,
+    /// 間引いた行数
+    pub dropped_lines: u64,
+}
+
+/// ストリームごとのサンプラー
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    config: OutputSampling,
+    patterns: Vec<Regex>,
+    seen: u64,
+    kept: u64,
+    dropped: u64,
+}
+
+impl Sampler {
+    pub fn new(config: OutputSampling) -> Result<Self, String> {
+        if config.every_n < 2 {
+            return Err("every_n must be at least 2".to_string());
+        }
+        let patterns = if config.keep_patterns.is_empty() {
+            DEFAULT_KEEP_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect()
+        } else {
+            config.keep_patterns.clone()
+        };
+        let patterns = patterns
+            .iter()
+            .map(|p| Regex::new(p).map_err(|e| format!("Invalid keep pattern '{p}': {e}")))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            config,
+            patterns,
+            seen: 0,
+            kept: 0,
+            dropped: 0,
+        })
+    }
+
+    /// 行を残すかどうか
+    pub fn keep(&mut self, line: &str) -> bool {
+        let nth = self.seen.is_multiple_of(self.config.every_n as u64);
+        self.seen += 1;
+        if nth || self.patterns.iter().any(|p| p.is_match(line)) {
+            self.kept += 1;
+            true
+        } else {
+            self.dropped += 1;
+            false
+        }
+    }
+
+    pub fn status(&self) -> SamplingStatus {
+        SamplingStatus {
+            config: self.config.clone(),
+            kept_lines: self.kept,
+            dropped_lines: self.dropped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampler(every_n: u32, keep_patterns: &[&str]) -> Sampler {
+        Sampler::new(OutputSampling {
+            every_n,
+            keep_patterns: keep_patterns.iter().map(|p| p.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_keeps_every_nth_line_and_matching_lines() {
+        let mut sampler = sampler(3, &[]);
+        let kept: Vec<String> = (0..7)
+            .map(|i| {
+                if i == 4 {
+                    "ERROR: disk full".to_string()
+                } else {
+                    format!("line {i}")
+                }
+            })
+            .filter(|line| sampler.keep(line))
+            .collect();
+        assert_eq!(kept, vec!["line 0", "line 3", "ERROR: disk full", "line 6"]);
+
+        let status = sampler.status();
+        assert_eq!(status.kept_lines, 4);
+        assert_eq!(status.dropped_lines, 3);
+    }
+
+    #[test]
+    fn test_custom_patterns_replace_defaults() {
+        let mut sampler = sampler(100, &["^GET /health"]);
+        assert!(sampler.keep("first line is always kept"));
+        assert!(sampler.keep("GET /health 200"));
+        assert!(!sampler.keep("error: not matched by custom patterns"));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = |every_n, pattern: &str| OutputSampling {
+            every_n,
+            keep_patterns: vec![pattern.to_string()],
+        };
+        assert!(Sampler::new(config(1, "ok")).is_err());
+        assert!(Sampler::new(config(10, "(unclosed")).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! プロセスの定期実行（「毎時0分にバックアップを起動」）
+//!
+//! cron形式（分 時 日 月 曜日、サーバーのローカル時刻）の時刻になったプロセスを起動します。
+//! 時刻の判定は `ProcessManager` が一定の間隔で行い、実行した結果は定期実行ごとに記録して
+//! `scheduled_run` イベントとして通知します。時刻になってもプロセスが実行中であれば、その回は
+//! 実行しません。スリープからの復帰などで確認が遅れて過ぎてしまった時刻は、
+//! `MISSED_RUN_WINDOW_MINUTES` 以内であれば1回だけ実行します。
+
+use super::maintenance::{self, CronSchedule};
+use chrono::{DateTime, Local, NaiveDateTime, Timelike, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::info;
+use utoipa::ToSchema;
+use vantage_persistence::{PersistenceManager, ProcessSchedule};
+
+/// 時刻になった定期実行を確認する間隔
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// 過ぎてしまった時刻を実行する範囲（分）
+pub const MISSED_RUN_WINDOW_MINUTES: i64 = 60;
+
+/// 定期実行の結果
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum ScheduleOutcome {
+    /// プロセスを起動した
+    Started { pid: u32 },
+    /// 実行中などの理由で何もしなかった
+    Skipped { reason: String },
+    /// 起動に失敗した
+    Failed { error: String },
+}
+
+/// 定期実行の記録
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScheduleRun {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub outcome: ScheduleOutcome,
+}
+
+/// 定期実行の状態
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScheduleStatus {
+    #[serde(flatten)]
+    pub config: ProcessSchedule,
+    /// 時刻になった回数（実行しなかった回を含む）
+    pub run_count: u64,
+    pub last_run: Option<ScheduleRun>,
+    /// 次に実行する時刻（無効にしている場合や1年以内に来ない場合はNone）
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+struct ManagedSchedule {
+    config: ProcessSchedule,
+    cron: CronSchedule,
+    run_count: u64,
+    last_run: Option<ScheduleRun>,
+    /// 時刻の判定を済ませた最後の分（ローカル時刻）
+    checked_until: NaiveDateTime,
+}
+
+impl ManagedSchedule {
+    fn new(config: ProcessSchedule, now: DateTime<Utc>) -> Result<Self, String> {
+        Ok(Self {
+            cron: CronSchedule::parse(&config.cron)?,
+            config,
+            run_count: 0,
+            last_run: None,
+            // 登録した分はすでに始まっているので実行しない
+            checked_until: local_minute(now),
+        })
+    }
+
+    fn status(&self, now: DateTime<Utc>) -> ScheduleStatus {
+        ScheduleStatus {
+            config: self.config.clone(),
+            run_count: self.run_count,
+            last_run: self.last_run.clone(),
+            next_run: self
+                .config
+                .enabled
+                .then(|| {
+                    self.cron
+                        .next_start(now.with_timezone(&Local).naive_local())
+                })
+                .flatten()
+                .map(maintenance::local_to_utc),
+        }
+    }
+}
+
+/// 分の始まりに切り捨てたローカル時刻
+fn local_minute(now: DateTime<Utc>) -> NaiveDateTime {
+    let local = now.with_timezone(&Local).naive_local();
+    local
+        .with_second(0)
+        .and_then(|minute| minute.with_nanosecond(0))
+        .unwrap_or(local)
+}
+
+/// 定期実行の管理
+pub struct ScheduleManager {
+    schedules: RwLock<HashMap<String, ManagedSchedule>>,
+    persistence: Arc<PersistenceManager>,
+}
+
+impl ScheduleManager {
+    pub fn new(persistence: Arc<PersistenceManager>) -> Self {
+        Self {
+            schedules: RwLock::new(HashMap::new()),
+            persistence,
+        }
+    }
+
+    /// 定期実行を登録する
+    pub async fn create(&self, config: ProcessSchedule) -> Result<ScheduleStatus, String> {
+        validate(&config)?;
+        let now = Utc::now();
+        let mut schedules = self.schedules.write().await;
+        if schedules.contains_key(&config.schedule_id) {
+            return Err(format!(
+                "Schedule with id '{}' already exists",
+                config.schedule_id
+            ));
+        }
+        let schedule = ManagedSchedule::new(config, now)?;
+        self.persistence.save_schedule(&schedule.config).await?;
+        info!(
+            "Created schedule '{}': start '{}' at '{}'",
+            schedule.config.schedule_id, schedule.config.process_id, schedule.config.cron
+        );
+        let status = schedule.status(now);
+        schedules.insert(status.config.schedule_id.clone(), schedule);
+        Ok(status)
+    }
+
+    /// cron形式の時刻・有効かどうかを変更する
+    pub async fn update(
+        &self,
+        schedule_id: &str,
+        cron: Option<String>,
+        enabled: Option<bool>,
+    ) -> Result<ScheduleStatus, String> {
+        let now = Utc::now();
+        let mut schedules = self.schedules.write().await;
+        let schedule = schedules
+            .get_mut(schedule_id)
+            .ok_or_else(|| format!("Schedule '{schedule_id}' not found"))?;
+        if let Some(cron) = cron {
+            schedule.cron = CronSchedule::parse(&cron)?;
+            schedule.config.cron = cron;
+            schedule.checked_until = local_minute(now);
+        }
+        if let Some(enabled) = enabled {
+            if enabled && !schedule.config.enabled {
+                // 無効にしていた間の時刻はまとめて実行しない
+                schedule.checked_until = local_minute(now);
+            }
+            schedule.config.enabled = enabled;
+        }
+        self.persistence.save_schedule(&schedule.config).await?;
+        Ok(schedule.status(now))
+    }
+
+    /// 定期実行を削除する
+    pub async fn remove(&self, schedule_id: &str) -> Result<(), String> {
+        if self.schedules.write().await.remove(schedule_id).is_none() {
+            return Err(format!("Schedule '{schedule_id}' not found"));
+        }
+        self.persistence.delete_schedule(schedule_id).await?;
+        info!("Removed schedule '{}'", schedule_id);
+        Ok(())
+    }
+
+    /// 定期実行の一覧（`process_id` を指定すると、そのプロセスを起動するもののみ）
+    pub async fn list(&self, process_id: Option<&str>) -> Vec<ScheduleStatus> {
+        let now = Utc::now();
+        let schedules = self.schedules.read().await;
+        let mut list: Vec<ScheduleStatus> = schedules
+            .values()
+            .filter(|schedule| process_id.is_none_or(|id| schedule.config.process_id == id))
+            .map(|schedule| schedule.status(now))
+            .collect();
+        list.sort_by(|a, b| {
+            a.config
+                .process_id
+                .cmp(&b.config.process_id)
+                .then(a.config.schedule_id.cmp(&b.config.schedule_id))
+        });
+        list
+    }
+
+    /// `now` までに時刻になった定期実行（同じ定期実行の複数の時刻が過ぎていても1回とする）
+    pub async fn begin_runs(&self, now: DateTime<Utc>) -> Vec<ProcessSchedule> {
+        let current = local_minute(now);
+        let mut schedules = self.schedules.write().await;
+        let earliest = current - chrono::Duration::minutes(MISSED_RUN_WINDOW_MINUTES - 1);
+        let mut due = Vec::new();
+        for schedule in schedules.values_mut() {
+            let from = (schedule.checked_until + chrono::Duration::minutes(1)).max(earliest);
+            let checked_until = std::mem::replace(&mut schedule.checked_until, current);
+            if !schedule.config.enabled || checked_until >= current {
+                continue;
+            }
+            let minutes = (current - from).num_minutes();
+            if (0..=minutes)
+                .map(|offset| from + chrono::Duration::minutes(offset))
+                .any(|minute| schedule.cron.matches(&minute))
+            {
+                schedule.run_count += 1;
+                due.push(schedule.config.clone());
+            }
+        }
+        due.sort_by(|a, b| a.schedule_id.cmp(&b.schedule_id));
+        due
+    }
+
+    /// 定期実行の結果を記録する
+    pub async fn record_run(&self, schedule_id: &str, outcome: ScheduleOutcome) {
+        if let Some(schedule) = self.schedules.write().await.get_mut(schedule_id) {
+            schedule.last_run = Some(ScheduleRun {
+                at: Utc::now(),
+                outcome,
+            });
+        }
+    }
+}
+
+/// 定期実行の定義を検証
+pub fn validate(config: &ProcessSchedule) -> Result<(), String> {
+    if config.schedule_id.trim().is_empty() {
+        return Err("schedule_id must not be empty".to_string());
+    }
+    if config.process_id.trim().is_empty() {
+        return Err("The schedule requires a process_id".to_string());
+    }
+    CronSchedule::parse(&config.cron)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(schedule_id: &str, cron: &str) -> ProcessSchedule {
+        ProcessSchedule {
+            schedule_id: schedule_id.to_string(),
+            process_id: "backup".to_string(),
+            cron: cron.to_string(),
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runs_once_per_due_time() {
+        let persistence = Arc::new(PersistenceManager::new().await.unwrap());
+        let manager = ScheduleManager::new(persistence);
+        assert!(manager.create(schedule("bad", "* * *")).await.is_err());
+        manager
+            .create(schedule("every-minute", "* * * * *"))
+            .await
+            .unwrap();
+        manager
+            .create(schedule("leap-day", "0 0 29 2 *"))
+            .await
+            .unwrap();
+
+        // 登録した分は実行しない
+        let now = Utc::now();
+        assert!(manager.begin_runs(now).await.is_empty());
+
+        // 次の分になれば1回だけ実行する
+        let next = now + chrono::Duration::minutes(1);
+        let due = manager.begin_runs(next).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].schedule_id, "every-minute");
+        assert!(manager.begin_runs(next).await.is_empty());
+
+        // 数分過ぎていても1回とする
+        let later = next + chrono::Duration::minutes(5);
+        assert_eq!(manager.begin_runs(later).await.len(), 1);
+
+        // 無効にしている間は実行しない
+        manager
+            .update("every-minute", None, Some(false))
+            .await
+            .unwrap();
+        let disabled = later + chrono::Duration::minutes(1);
+        assert!(manager.begin_runs(disabled).await.is_empty());
+
+        let status = &manager.list(Some("backup")).await;
+        assert_eq!(status.len(), 2);
+        let every_minute = status
+            .iter()
+            .find(|s| s.config.schedule_id == "every-minute")
+            .unwrap();
+        assert_eq!(every_minute.run_count, 2);
+        assert!(every_minute.next_run.is_none());
+    }
+}
@@ -0,0 +1,74 @@
+//! セッションコンテキスト
+//!
+//! 実行中のプロセス、作業ディレクトリ、Gitブランチ、クリップボード、メモを名前付きで
+//! 保存し、作業の切り替え時にまとめて復元します。
+
+use serde::Serialize;
+use std::path::Path;
+use tokio::process::Command;
+
+pub use vantage_persistence::SessionContext;
+
+/// 保存するクリップボードアイテムの件数
+pub const CONTEXT_CLIPBOARD_ITEMS: usize = 10;
+
+/// コンテキスト復元の結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContextRestoreReport {
+    pub name: String,
+    pub workspace: Option<String>,
+    pub notes: Option<String>,
+    /// 起動したプロセス
+    pub started: Vec<String>,
+    /// すでに実行中だったプロセス
+    pub already_running: Vec<String>,
+    /// コンテキストに含まれないため停止したプロセス
+    pub stopped: Vec<String>,
+    /// 登録されていないプロセス
+    pub missing: Vec<String>,
+    /// 起動・停止に失敗したプロセスとエラー
+    pub failed: Vec<(String, String)>,
+    pub restored_clipboard_items: usize,
+    pub git: Option<GitBranchRestore>,
+}
+
+/// Gitブランチの復元結果
+#[derive(Debug, Clone, Serialize)]
+pub struct GitBranchRestore {
+    pub saved_branch: String,
+    pub current_branch: Option<String>,
+    pub checked_out: bool,
+    pub error: Option<String>,
+}
+
+/// 作業ディレクトリの現在のGitブランチ（Gitリポジトリでない場合はNone）
+pub async fn current_git_branch(workspace: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(workspace)
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!branch.is_empty()).then_some(branch)
+}
+
+/// 指定したブランチをチェックアウト
+pub async fn checkout_git_branch(workspace: &Path, branch: &str) -> Result<(), String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(workspace)
+        .args(["checkout", branch])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
@@ -9,7 +9,7 @@ use tokio::process::{Child, Command};
 use tokio::task::JoinHandle;
 use tracing::{info, warn};
 
-use super::buffer::CircularBuffer;
+use super::buffer::{self, CircularBuffer};
 use super::protocol::Process;
 use super::types::{OutputStream, ProcessInfo, ProcessState};
 
@@ -31,6 +31,8 @@ impl ShellProcess {
         env: HashMap<String, String>,
         cwd: Option<PathBuf>,
     ) -> Self {
+        let stdout_buffer = CircularBuffer::new(1000);
+        let stderr_buffer = stdout_buffer.paired(1000);
         Self {
             info: ProcessInfo {
                 id,
@@ -40,9 +42,35 @@ impl ShellProcess {
                 cwd,
                 state: ProcessState::NotStarted,
                 auto_start_on_restore: false,
+                tags: Vec::new(),
+                idle_detection: None,
+                health_check: None,
+                tmux: None,
+                repl: None,
+                log_sources: Vec::new(),
+                run_as: None,
+                resource_limits: None,
+                container: None,
+                source: None,
+                log_sinks: Vec::new(),
+                redaction: None,
+                metadata: HashMap::new(),
+                runbook: None,
+                startup: None,
+                lazy_start: None,
+                env_loader: None,
+                output_recording: None,
+                assertions: Vec::new(),
+                restart_policy: None,
+                depends_on: Vec::new(),
+                parameters: Vec::new(),
+                keep_stdin_open: false,
+                group: None,
+                health: None,
+                sensitive_env: HashMap::new(),
             },
-            stdout_buffer: CircularBuffer::new(1000),
-            stderr_buffer: CircularBuffer::new(1000),
+            stdout_buffer,
+            stderr_buffer,
             child: None,
             output_handles: None,
         }
@@ -252,17 +280,11 @@ impl ShellProcess {
                 }
             }
             OutputStream::Both => {
-                let stdout = self.stdout_buffer.get_all().await;
-                let stderr = self.stderr_buffer.get_all().await;
-                let mut combined = Vec::new();
-                combined.extend(stdout);
-                combined.extend(stderr);
-
-                if let Some(n) = lines {
-                    combined.into_iter().rev().take(n).rev().collect()
-                } else {
-                    combined
-                }
+                buffer::interleave(&self.stdout_buffer, &self.stderr_buffer, lines)
+                    .await
+                    .into_iter()
+                    .map(|l| l.text)
+                    .collect()
             }
         }
     }
@@ -383,3 +405,26 @@ impl ShellProcessBuilder {
         Ok(process)
     }
 }
+
+/// シェルのコマンドラインに埋め込めるように引用する（記号を含まない語はそのまま）
+pub(crate) fn shell_quote(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c))
+    {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("npm"), "npm");
+        assert_eq!(shell_quote("hello world"), "'hello world'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
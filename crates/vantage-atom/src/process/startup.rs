@@ -0,0 +1,179 @@
+//! 自動起動・一括起動の起動順序
+//!
+//! プロセスごとの `startup.priority` の小さい順に起動します。同じ優先度で同じ `startup.group`
+//! のプロセスはまとめて並列に起動し、グループのないプロセスは1つずつ起動します。まとめて
+//! 起動したプロセスがすべて準備完了になる（`startup.ready_port` を指定した場合はそのポートで
+//! 接続を受け付ける）まで、次のプロセスは起動しません。`depends_on` の依存先は優先度によらず
+//! 依存元より先に起動します（[`super::dependencies`]）。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+use utoipa::ToSchema;
+use vantage_persistence::StartupOrder;
+
+/// 準備完了を待つ既定の秒数
+pub const DEFAULT_READY_TIMEOUT_SECS: u64 = 30;
+/// 準備完了を確認する間隔
+pub const READY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 起動順序を検証
+pub fn validate(order: &StartupOrder) -> Result<(), String> {
+    if order.group.as_ref().is_some_and(|g| g.trim().is_empty()) {
+        return Err("startup.group must not be empty".to_string());
+    }
+    if order.ready_port == Some(0) {
+        return Err("startup.ready_port must be between 1 and 65535".to_string());
+    }
+    if order.ready_timeout_secs == Some(0) {
+        return Err("startup.ready_timeout_secs must be greater than 0".to_string());
+    }
+    Ok(())
+}
+
+/// 起動するプロセスをまとめて起動する単位に分ける（起動する順に並べる）
+///
+/// 優先度の小さい順に並べ、同じ優先度ではIDの順に並べる。同じ優先度で同じグループの
+/// プロセスは、そのグループで最初のプロセスの位置にまとめる。
+pub fn plan(mut members: Vec<(String, StartupOrder)>) -> Vec<Vec<(String, StartupOrder)>> {
+    members.sort_by(|(a_id, a), (b_id, b)| a.priority.cmp(&b.priority).then(a_id.cmp(b_id)));
+    let mut batches: Vec<Vec<(String, StartupOrder)>> = Vec::new();
+    for member in members {
+        let order = &member.1;
+        let existing = order.group.as_ref().and_then(|group| {
+            batches.iter_mut().find(|batch| {
+                batch[0].1.priority == order.priority && batch[0].1.group.as_ref() == Some(group)
+            })
+        });
+        match existing {
+            Some(batch) => batch.push(member),
+            None => batches.push(vec![member]),
+        }
+    }
+    batches
+}
+
+/// プロセスごとの起動の結果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StartupResult {
+    pub process_id: String,
+    pub priority: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// 何番目にまとめて起動したか（0始まり）
+    pub batch: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// 全体の開始から起動するまでの時間
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_after_ms: Option<u64>,
+    /// 全体の開始から準備完了になるまでの時間
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ready_after_ms: Option<u64>,
+    pub ready: bool,
+    /// 起動または準備完了の確認に失敗した理由
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 順序に従った起動の結果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StartupReport {
+    pub started_at: DateTime<Utc>,
+    /// すべてのプロセスが準備完了になる（または失敗する）までの時間
+    pub total_ms: u64,
+    pub processes: Vec<StartupResult>,
+}
+
+impl StartupReport {
+    /// 準備完了になったプロセス
+    pub fn ready(&self) -> Vec<String> {
+        self.processes
+            .iter()
+            .filter(|result| result.ready)
+            .map(|result| result.process_id.clone())
+            .collect()
+    }
+
+    /// 起動または準備完了の確認に失敗したプロセスとその理由
+    pub fn failed(&self) -> Vec<(String, String)> {
+        self.processes
+            .iter()
+            .filter_map(|result| {
+                let error = result.error.clone()?;
+                Some((result.process_id.clone(), error))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: &str, priority: i32, group: Option<&str>) -> (String, StartupOrder) {
+        (
+            id.to_string(),
+            StartupOrder {
+                priority,
+                group: group.map(str::to_string),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn ids(batches: &[Vec<(String, StartupOrder)>]) -> Vec<Vec<&str>> {
+        batches
+            .iter()
+            .map(|batch| batch.iter().map(|(id, _)| id.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_orders_by_priority_and_groups_parallel_starts() {
+        let batches = plan(vec![
+            member("web", 10, Some("frontend")),
+            member("db", 0, None),
+            member("worker", 5, Some("backend")),
+            member("api", 5, Some("backend")),
+            member("cache", 0, None),
+            member("docs", 10, Some("frontend")),
+            member("admin", 10, None),
+        ]);
+        assert_eq!(
+            ids(&batches),
+            vec![
+                vec!["cache"],
+                vec!["db"],
+                vec!["api", "worker"],
+                vec!["admin"],
+                vec!["docs", "web"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_group_at_another_priority_is_a_separate_batch() {
+        let batches = plan(vec![
+            member("a", 0, Some("g")),
+            member("b", 1, Some("g")),
+            member("c", 0, Some("g")),
+        ]);
+        assert_eq!(ids(&batches), vec![vec!["a", "c"], vec!["b"]]);
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_group_and_zero_port() {
+        assert!(validate(&StartupOrder::default()).is_ok());
+        let order = StartupOrder {
+            group: Some(" ".to_string()),
+            ..Default::default()
+        };
+        assert!(validate(&order).is_err());
+        let order = StartupOrder {
+            ready_port: Some(0),
+            ..Default::default()
+        };
+        assert!(validate(&order).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! 実行中のプロセスの標準入力
+//!
+//! `keep_stdin_open` を指定したプロセスは標準入力をパイプとして開いたまま起動し、
+//! `send_process_input` で送った入力をそのまま書き込みます。入力はUTF-8のテキストか、
+//! 任意のバイト列をBase64で指定します。標準入力を閉じると、プロセスはEOFを受け取ります。
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::ChildStdin;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+/// 書き込みを待つ時間（プロセスが標準入力を読まずにパイプが埋まった場合）
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 入力の指定方法
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum InputEncoding {
+    /// UTF-8のテキスト
+    #[default]
+    Text,
+    /// Base64で表した任意のバイト列
+    Base64,
+}
+
+/// 入力を書き込むバイト列にする
+///
+/// `newline` を省略すると、テキストは改行で終わっていなければ改行を付け、Base64はそのまま送る。
+pub fn encode_input(
+    input: &str,
+    encoding: InputEncoding,
+    newline: Option<bool>,
+) -> Result<Vec<u8>, String> {
+    let mut bytes = match encoding {
+        InputEncoding::Text => input.as_bytes().to_vec(),
+        InputEncoding::Base64 => STANDARD
+            .decode(input.trim())
+            .map_err(|e| format!("Input is not valid base64: {e}"))?,
+    };
+    if newline.unwrap_or(encoding == InputEncoding::Text) && !bytes.ends_with(b"\n") {
+        bytes.push(b'\n');
+    }
+    Ok(bytes)
+}
+
+/// 実行中のプロセスの標準入力のパイプ
+pub struct ProcessStdin {
+    /// 閉じた後はNone
+    pipe: Mutex<Option<ChildStdin>>,
+}
+
+impl ProcessStdin {
+    pub fn new(stdin: ChildStdin) -> Self {
+        Self {
+            pipe: Mutex::new(Some(stdin)),
+        }
+    }
+
+    /// 入力を書き込む（`close` なら書き込んだ後に閉じる）
+    ///
+    /// 書き込みは1つずつ行うため、同時に送った入力が混ざることはない。
+    pub async fn send(&self, input: &[u8], close: bool) -> Result<(), String> {
+        let mut pipe = self.pipe.lock().await;
+        let stdin = pipe
+            .as_mut()
+            .ok_or_else(|| "stdin has already been closed".to_string())?;
+        if !input.is_empty() {
+            let write = async {
+                stdin.write_all(input).await?;
+                stdin.flush().await
+            };
+            match tokio::time::timeout(WRITE_TIMEOUT, write).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                    *pipe = None;
+                    return Err("the process has closed its stdin".to_string());
+                }
+                Ok(Err(e)) => return Err(e.to_string()),
+                Err(_) => {
+                    return Err(format!(
+                        "timed out after {}s; the process is not reading its stdin",
+                        WRITE_TIMEOUT.as_secs()
+                    ));
+                }
+            }
+        }
+        if close {
+            // 書き込み側を閉じるとプロセスはEOFを受け取る
+            *pipe = None;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_input() {
+        assert_eq!(
+            encode_input("yes", InputEncoding::Text, None).unwrap(),
+            b"yes\n"
+        );
+        assert_eq!(
+            encode_input("yes\n", InputEncoding::Text, None).unwrap(),
+            b"yes\n"
+        );
+        assert_eq!(
+            encode_input("q", InputEncoding::Text, Some(false)).unwrap(),
+            b"q"
+        );
+        assert_eq!(
+            encode_input("héllo", InputEncoding::Text, Some(false)).unwrap(),
+            "héllo".as_bytes()
+        );
+        // Ctrl-D（0x04）などの制御文字はBase64で送る
+        assert_eq!(
+            encode_input("BA==", InputEncoding::Base64, None).unwrap(),
+            vec![4]
+        );
+        assert_eq!(
+            encode_input("BA==", InputEncoding::Base64, Some(true)).unwrap(),
+            vec![4, b'\n']
+        );
+        assert!(encode_input("not base64!", InputEncoding::Base64, None).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! テンプレートのパラメータ行列による一括作成
+//!
+//! 変数ごとの値の一覧の直積を取り、組み合わせごとにテンプレートからプロセスを作ります。
+//! 値には `3001..3005` のような整数の範囲（両端を含む）も書けます。
+
+use super::vscode_tasks::slug;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use utoipa::ToSchema;
+use vantage_persistence::ProcessTemplate;
+
+/// 一度に作成できる組み合わせの上限
+pub const MAX_COMBINATIONS: usize = 100;
+
+/// 行列から作成したプロセス
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct MatrixInstance {
+    pub process_id: String,
+    /// このプロセスに使った行列の値
+    pub parameters: BTreeMap<String, String>,
+    /// 起動に失敗した場合のエラー
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_error: Option<String>,
+}
+
+/// 行列を展開し、すべての組み合わせを返す（変数名の順、値は指定した順）
+pub fn expand(
+    matrix: &HashMap<String, Vec<String>>,
+) -> Result<Vec<BTreeMap<String, String>>, String> {
+    if matrix.is_empty() {
+        return Err("Matrix must contain at least one variable".to_string());
+    }
+    let mut axes = BTreeMap::new();
+    for (name, values) in matrix {
+        let mut expanded = Vec::new();
+        for value in values {
+            match parse_range(value)? {
+                Some((start, end)) => expanded.extend((start..=end).map(|n| n.to_string())),
+                None => expanded.push(value.clone()),
+            }
+        }
+        if expanded.is_empty() {
+            return Err(format!("Matrix variable '{name}' has no values"));
+        }
+        axes.insert(name.clone(), expanded);
+    }
+
+    let total = axes
+        .values()
+        .try_fold(1usize, |total, values| total.checked_mul(values.len()));
+    if total.is_none_or(|total| total > MAX_COMBINATIONS) {
+        return Err(format!(
+            "Matrix expands to more than {MAX_COMBINATIONS} combinations"
+        ));
+    }
+
+    let mut combinations = vec![BTreeMap::new()];
+    for (name, values) in &axes {
+        combinations = combinations
+            .iter()
+            .flat_map(|combination| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.insert(name.clone(), value.clone());
+                    combination
+                })
+            })
+            .collect();
+    }
+    Ok(combinations)
+}
+
+/// 組み合わせのプロセスIDの候補（例: `web-node_version-18-port-3001`）
+pub fn process_id(prefix: &str, parameters: &BTreeMap<String, String>) -> String {
+    std::iter::once(prefix.to_string())
+        .chain(
+            parameters
+                .iter()
+                .map(|(name, value)| format!("{}-{}", slug(name), slug(value))),
+        )
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// テンプレートが変数として扱う名前か（宣言されているか、`{{NAME}}` として参照されている）
+///
+/// 扱わない名前の値は、環境変数としてプロセスに渡す。
+pub fn uses_variable(template: &ProcessTemplate, name: &str) -> bool {
+    let placeholder = format!("{{{{{name}}}}}");
+    template.variables.iter().any(|var| var.name == name)
+        || template.command.contains(&placeholder)
+        || template.args.iter().any(|arg| arg.contains(&placeholder))
+        || template
+            .env
+            .values()
+            .any(|value| value.contains(&placeholder))
+}
+
+/// `start..end` 形式の整数の範囲（それ以外の値はNone）
+fn parse_range(value: &str) -> Result<Option<(i64, i64)>, String> {
+    let Some((start, end)) = value.split_once("..") else {
+        return Ok(None);
+    };
+    let end = end.strip_prefix('=').unwrap_or(end);
+    let (Ok(start), Ok(end)) = (start.trim().parse::<i64>(), end.trim().parse::<i64>()) else {
+        return Ok(None);
+    };
+    if start > end {
+        return Err(format!(
+            "Invalid range '{value}': start is greater than end"
+        ));
+    }
+    if end.abs_diff(start) >= MAX_COMBINATIONS as u64 {
+        return Err(format!(
+            "Range '{value}' has more than {MAX_COMBINATIONS} values"
+        ));
+    }
+    Ok(Some((start, end)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_ranges_and_lists() {
+        let matrix = HashMap::from([
+            ("PORT".to_string(), vec!["3001..3003".to_string()]),
+            (
+                "NODE_VERSION".to_string(),
+                vec!["18".to_string(), "20".to_string()],
+            ),
+        ]);
+        let combinations = expand(&matrix).unwrap();
+        let summary: Vec<_> = combinations
+            .iter()
+            .map(|c| format!("{}/{}", c["NODE_VERSION"], c["PORT"]))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "18/3001", "18/3002", "18/3003", "20/3001", "20/3002", "20/3003"
+            ]
+        );
+        assert_eq!(
+            process_id("web", &combinations[0]),
+            "web-node_version-18-port-3001"
+        );
+    }
+
+    #[test]
+    fn test_expand_rejects_invalid_matrices() {
+        assert!(expand(&HashMap::new()).is_err());
+        assert!(expand(&HashMap::from([("A".to_string(), vec![])])).is_err());
+        assert!(
+            expand(&HashMap::from([(
+                "A".to_string(),
+                vec!["5..1".to_string()]
+            )]))
+            .is_err()
+        );
+        assert!(
+            expand(&HashMap::from([(
+                "A".to_string(),
+                vec!["1..1000".to_string()]
+            )]))
+            .is_err()
+        );
+        // 整数でない範囲は値そのものとして扱う
+        assert_eq!(
+            expand(&HashMap::from([(
+                "A".to_string(),
+                vec!["a..b".to_string()]
+            )]))
+            .unwrap()[0]["A"],
+            "a..b"
+        );
+    }
+}
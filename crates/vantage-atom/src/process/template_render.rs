@@ -0,0 +1,238 @@
+//! テンプレートの試し描画（ドライラン）
+//!
+//! 変数の置換と上書きを適用した、起動されるコマンド・引数・環境変数・作業ディレクトリを
+//! プロセスを作らずに返します。シークレット参照の変数は値が指定されていても置換せず、
+//! `{{NAME}}` のまま残します。
+
+use super::capture::SECRET_VAR_TYPE;
+use super::template_matrix::uses_variable;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::LazyLock;
+use utoipa::ToSchema;
+use vantage_persistence::{ProcessTemplate, SchemaError, template_schema};
+
+/// `{{NAME}}` 形式のプレースホルダー
+pub(crate) static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{([A-Za-z0-9_.-]+)\}\}").unwrap());
+
+/// テンプレートの値の上書き
+#[derive(Debug, Clone, Default)]
+pub struct RenderOverrides {
+    pub args: Option<Vec<String>>,
+    pub env: Option<HashMap<String, String>>,
+    pub cwd: Option<String>,
+}
+
+/// 描画した起動内容
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct RenderedTemplate {
+    pub template_id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub cwd: Option<String>,
+    pub auto_start_on_restore: bool,
+    /// 値を伏せたシークレット参照の変数
+    pub secret_references: Vec<String>,
+    /// 値が指定されていない必須変数（このままでは作成できない）
+    pub missing_variables: Vec<String>,
+    /// 置換されずに残ったプレースホルダー（シークレット参照を除く）
+    pub unresolved_placeholders: Vec<String>,
+    /// 指定されたがテンプレートで使われていない値
+    pub unused_values: Vec<String>,
+    /// 変数のスキーマに合わない値（このままでは作成できない）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub schema_errors: Vec<SchemaError>,
+}
+
+/// テンプレートを描画する
+///
+/// 上書きした引数・環境変数・作業ディレクトリにも変数を置換する。
+pub fn render(
+    template: &ProcessTemplate,
+    values: &HashMap<String, String>,
+    overrides: RenderOverrides,
+) -> RenderedTemplate {
+    let secrets: BTreeSet<String> = template
+        .variables
+        .iter()
+        .filter(|var| var.var_type.as_deref() == Some(SECRET_VAR_TYPE))
+        .map(|var| var.name.clone())
+        .collect();
+
+    // 指定されなかった変数にはデフォルト値を使う
+    let mut resolved: HashMap<&str, &str> = HashMap::new();
+    for var in &template.variables {
+        if let Some(default) = &var.default_value {
+            resolved.insert(&var.name, default);
+        }
+    }
+    for (name, value) in values {
+        resolved.insert(name, value);
+    }
+
+    let substitute = |text: &str| -> String {
+        PLACEHOLDER
+            .replace_all(text, |caps: &regex::Captures| {
+                let name = &caps[1];
+                match resolved.get(name) {
+                    Some(value) if !secrets.contains(name) => value.to_string(),
+                    _ => caps[0].to_string(),
+                }
+            })
+            .into_owned()
+    };
+
+    let command = substitute(&template.command);
+    let args: Vec<String> = overrides
+        .args
+        .unwrap_or_else(|| template.args.clone())
+        .iter()
+        .map(|arg| substitute(arg))
+        .collect();
+    let env: BTreeMap<String, String> = overrides
+        .env
+        .unwrap_or_else(|| template.env.clone())
+        .iter()
+        .map(|(key, value)| (key.clone(), substitute(value)))
+        .collect();
+    let cwd = overrides
+        .cwd
+        .or_else(|| template.default_cwd.clone())
+        .map(|cwd| substitute(&cwd));
+
+    let mut unresolved = BTreeSet::new();
+    for text in std::iter::once(&command)
+        .chain(&args)
+        .chain(env.values())
+        .chain(&cwd)
+    {
+        for caps in PLACEHOLDER.captures_iter(text) {
+            if !secrets.contains(&caps[1]) {
+                unresolved.insert(caps[1].to_string());
+            }
+        }
+    }
+
+    let missing_variables = template
+        .variables
+        .iter()
+        .filter(|var| var.required && !values.contains_key(&var.name))
+        .map(|var| var.name.clone())
+        .collect();
+    let mut unused_values: Vec<String> = values
+        .keys()
+        .filter(|name| !uses_variable(template, name))
+        .cloned()
+        .collect();
+    unused_values.sort();
+
+    RenderedTemplate {
+        template_id: template.template_id.clone(),
+        command,
+        args,
+        env,
+        cwd,
+        auto_start_on_restore: template.default_auto_start,
+        secret_references: secrets.into_iter().collect(),
+        missing_variables,
+        unresolved_placeholders: unresolved.into_iter().collect(),
+        unused_values,
+        schema_errors: template_schema::validate_values(&template.variables, values),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use vantage_persistence::TemplateVariable;
+
+    fn variable(
+        name: &str,
+        default: Option<&str>,
+        required: bool,
+        secret: bool,
+    ) -> TemplateVariable {
+        TemplateVariable {
+            name: name.to_string(),
+            description: None,
+            default_value: default.map(str::to_string),
+            required,
+            var_type: secret.then(|| SECRET_VAR_TYPE.to_string()),
+            example: None,
+            schema: None,
+        }
+    }
+
+    fn template() -> ProcessTemplate {
+        ProcessTemplate {
+            id: None,
+            template_id: "web".to_string(),
+            name: "web".to_string(),
+            description: None,
+            category: None,
+            command: "node".to_string(),
+            args: vec![
+                "server.js".to_string(),
+                "--port={{PORT}}".to_string(),
+                "--token={{API_TOKEN}}".to_string(),
+            ],
+            env: HashMap::from([
+                ("NODE_ENV".to_string(), "{{MODE}}".to_string()),
+                ("API_TOKEN".to_string(), "{{API_TOKEN}}".to_string()),
+            ]),
+            default_cwd: Some("/srv/{{APP}}".to_string()),
+            default_auto_start: false,
+            variables: vec![
+                variable("PORT", Some("3000"), false, false),
+                variable("APP", None, true, false),
+                variable("API_TOKEN", None, true, true),
+            ],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            tags: Vec::new(),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_render_substitutes_and_masks_secrets() {
+        let values = HashMap::from([
+            ("APP".to_string(), "shop".to_string()),
+            ("API_TOKEN".to_string(), "ghp_real".to_string()),
+            ("EXTRA".to_string(), "1".to_string()),
+        ]);
+        let rendered = render(&template(), &values, RenderOverrides::default());
+        assert_eq!(
+            rendered.args,
+            vec!["server.js", "--port=3000", "--token={{API_TOKEN}}"]
+        );
+        assert_eq!(rendered.env["API_TOKEN"], "{{API_TOKEN}}");
+        assert_eq!(rendered.cwd.as_deref(), Some("/srv/shop"));
+        assert_eq!(rendered.secret_references, vec!["API_TOKEN"]);
+        assert!(rendered.missing_variables.is_empty());
+        assert_eq!(rendered.unresolved_placeholders, vec!["MODE"]);
+        assert_eq!(rendered.unused_values, vec!["EXTRA"]);
+    }
+
+    #[test]
+    fn test_render_applies_overrides() {
+        let overrides = RenderOverrides {
+            args: Some(vec!["--port={{PORT}}".to_string()]),
+            env: Some(HashMap::from([("DEBUG".to_string(), "1".to_string())])),
+            cwd: Some("/tmp".to_string()),
+        };
+        let values = HashMap::from([("PORT".to_string(), "4000".to_string())]);
+        let rendered = render(&template(), &values, overrides);
+        assert_eq!(rendered.args, vec!["--port=4000"]);
+        assert_eq!(
+            rendered.env,
+            BTreeMap::from([("DEBUG".to_string(), "1".to_string())])
+        );
+        assert_eq!(rendered.cwd.as_deref(), Some("/tmp"));
+        assert_eq!(rendered.missing_variables, vec!["APP", "API_TOKEN"]);
+    }
+}
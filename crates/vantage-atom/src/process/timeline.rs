@@ -0,0 +1,479 @@
+//! プロセスの実行のタイムライン
+//!
+//! 実行（起動から終了まで）、ヘルスチェックの異常、一時停止、メンテナンスウィンドウを期間として、
+//! 再起動・エラーなどの出来事、定義の変更、同じ時間帯に失敗したCIを時点として、開始時刻の順に並べます。
+//! Webコンソールでガントチャート風に描くことと、エージェントが経緯を要約することを想定した形です。
+//! 元になる記録はサーバーのメモリ上にあるため、並ぶのは保持している範囲（実行はプロセスごとに
+//! 直近の数回、イベントは直近の1000件）に限られます。
+
+use super::output_diff::TimeWindow;
+use super::run_environment::RunEnvironment;
+use crate::events::{EventType, ProcessEvent};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use vantage_persistence::{ChangeTarget, DefinitionChange, Notification, NotificationKind};
+
+/// タイムラインの行
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineLane {
+    /// 起動から終了までの実行
+    Run,
+    /// 再起動（前の実行の終了から次の起動まで）
+    Restart,
+    /// ヘルスチェックで異常と判定されていた期間
+    Health,
+    /// 一時停止していた期間
+    Pause,
+    /// メンテナンスウィンドウ
+    Maintenance,
+    /// エラー・クラッシュループ・アイドル・カオステストなどの出来事
+    Event,
+    /// 定義の変更
+    Annotation,
+    /// 実行中に失敗したCI
+    Ci,
+}
+
+/// タイムラインの1つの項目
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TimelineEntry {
+    pub lane: TimelineLane,
+    /// 表示用の短い説明
+    pub label: String,
+    pub start: DateTime<Utc>,
+    /// 期間の終わり（時点の項目と継続中の期間はNone）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Utc>>,
+    /// 期間の長さ（継続中の期間は現在まで、時点の項目はNone）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    /// 継続中の期間か
+    #[serde(default)]
+    pub ongoing: bool,
+    /// 結果（実行は running・succeeded・failed・stopped）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// 項目が属する実行の通し番号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run: Option<u64>,
+    /// 元になった記録の内容
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+/// タイムラインの集計
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct TimelineSummary {
+    pub runs: usize,
+    pub restarts: usize,
+    /// 異常終了した実行
+    pub failed_runs: usize,
+    /// 実行していた時間の合計
+    pub running_ms: i64,
+    /// ヘルスチェックで異常だった時間の合計
+    pub unhealthy_ms: i64,
+    pub ci_failures: usize,
+}
+
+/// プロセスのタイムライン
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProcessTimeline {
+    pub process_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    pub generated_at: DateTime<Utc>,
+    /// 開始時刻の順
+    pub entries: Vec<TimelineEntry>,
+    pub summary: TimelineSummary,
+}
+
+/// 実行を終わらせた終了イベント（起動後の最初の終了）
+pub fn run_end<'a>(
+    events: &'a [ProcessEvent],
+    process_id: &str,
+    started_at: DateTime<Utc>,
+) -> Option<&'a ProcessEvent> {
+    events.iter().find(|event| {
+        matches!(event.event_type, EventType::ProcessStopped)
+            && event.process_id == process_id
+            && event.timestamp >= started_at
+    })
+}
+
+/// 終了イベントの終了コード
+pub fn exit_code(stopped: Option<&ProcessEvent>) -> Option<i64> {
+    stopped
+        .and_then(|event| event.context.as_ref())
+        .and_then(|c| c.get("exit_code"))
+        .and_then(|code| code.as_i64())
+}
+
+/// 実行の結果（running・stopped・succeeded・failed）
+pub fn run_outcome(stopped: Option<&ProcessEvent>) -> &'static str {
+    let stop_requested = stopped
+        .and_then(|event| event.context.as_ref())
+        .and_then(|c| c.get("stop_requested"))
+        .and_then(|flag| flag.as_bool())
+        .unwrap_or(false);
+    match (stopped, exit_code(stopped)) {
+        (None, _) => "running",
+        (Some(_), _) if stop_requested => "stopped",
+        (Some(_), Some(0)) => "succeeded",
+        (Some(_), _) => "failed",
+    }
+}
+
+fn span(
+    lane: TimelineLane,
+    label: String,
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> TimelineEntry {
+    TimelineEntry {
+        lane,
+        label,
+        start,
+        end,
+        duration_ms: Some((end.unwrap_or(now) - start).num_milliseconds()),
+        ongoing: end.is_none(),
+        status: None,
+        run: None,
+        details: None,
+    }
+}
+
+fn point(lane: TimelineLane, label: String, at: DateTime<Utc>) -> TimelineEntry {
+    TimelineEntry {
+        lane,
+        label,
+        start: at,
+        end: None,
+        duration_ms: None,
+        ongoing: false,
+        status: None,
+        run: None,
+        details: None,
+    }
+}
+
+/// `start` の後に最初に現れる、期間を終わらせるイベントの時刻
+fn closed_by(
+    events: &[&ProcessEvent],
+    start: DateTime<Utc>,
+    closing: impl Fn(&EventType) -> bool,
+) -> Option<DateTime<Utc>> {
+    events
+        .iter()
+        .find(|event| event.timestamp >= start && closing(&event.event_type))
+        .map(|event| event.timestamp)
+}
+
+/// 記録からタイムラインを組み立てる
+///
+/// `events` は古い順。`ci_failures` は全体の通知で、このプロセスの実行中に失敗したものだけを並べる。
+pub fn build(
+    process_id: &str,
+    runs: &[RunEnvironment],
+    events: &[ProcessEvent],
+    changes: &[DefinitionChange],
+    ci_failures: &[Notification],
+    window: TimeWindow,
+    now: DateTime<Utc>,
+) -> ProcessTimeline {
+    let own: Vec<&ProcessEvent> = events
+        .iter()
+        .filter(|event| event.process_id == process_id)
+        .collect();
+    let mut entries = Vec::new();
+    let mut summary = TimelineSummary::default();
+
+    // 実行と再起動
+    let mut run_spans: Vec<(u64, DateTime<Utc>, Option<DateTime<Utc>>)> = Vec::new();
+    let mut previous: Option<(DateTime<Utc>, &'static str)> = None;
+    for run in runs {
+        let stopped = run_end(events, process_id, run.started_at);
+        let outcome = run_outcome(stopped);
+        let ended_at = stopped.map(|event| event.timestamp);
+        run_spans.push((run.run, run.started_at, ended_at));
+        if let Some((previous_end, previous_outcome)) = previous
+            && window.contains(run.started_at)
+        {
+            let mut entry = span(
+                TimelineLane::Restart,
+                format!("Restarted after {previous_outcome} run"),
+                previous_end,
+                Some(run.started_at),
+                now,
+            );
+            entry.run = Some(run.run);
+            entry.status = Some(previous_outcome.to_string());
+            entries.push(entry);
+            summary.restarts += 1;
+        }
+        previous = ended_at.map(|end| (end, outcome));
+        if !window.contains(run.started_at) {
+            continue;
+        }
+        let mut entry = span(
+            TimelineLane::Run,
+            format!("Run #{}", run.run),
+            run.started_at,
+            ended_at,
+            now,
+        );
+        entry.run = Some(run.run);
+        entry.status = Some(outcome.to_string());
+        entry.details = Some(serde_json::json!({
+            "exit_code": exit_code(stopped),
+            "runtime": run.runtime,
+            "command": std::iter::once(run.command.as_str())
+                .chain(run.args.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }));
+        summary.runs += 1;
+        summary.running_ms += entry.duration_ms.unwrap_or_default();
+        if outcome == "failed" {
+            summary.failed_runs += 1;
+        }
+        entries.push(entry);
+    }
+    let run_at = |at: DateTime<Utc>| {
+        run_spans
+            .iter()
+            .rev()
+            .find(|(_, start, end)| *start <= at && end.is_none_or(|end| at <= end))
+            .map(|(run, _, _)| *run)
+    };
+
+    // 期間と時点の出来事
+    for event in own.iter().filter(|event| window.contains(event.timestamp)) {
+        let at = event.timestamp;
+        let stopped = |t: &EventType| matches!(t, EventType::ProcessStopped);
+        let mut entry = match event.event_type {
+            EventType::ProcessUnhealthy => {
+                let end = closed_by(&own, at, |t| {
+                    stopped(t) || matches!(t, EventType::ProcessRecovered)
+                });
+                let entry = span(TimelineLane::Health, "Unhealthy".to_string(), at, end, now);
+                summary.unhealthy_ms += entry.duration_ms.unwrap_or_default();
+                entry
+            }
+            EventType::ProcessPaused => {
+                let end = closed_by(&own, at, |t| {
+                    stopped(t) || matches!(t, EventType::ProcessResumed)
+                });
+                span(TimelineLane::Pause, "Paused".to_string(), at, end, now)
+            }
+            EventType::MaintenanceStarted => {
+                let end = closed_by(&own, at, |t| matches!(t, EventType::MaintenanceEnded));
+                let name = event
+                    .context
+                    .as_ref()
+                    .and_then(|c| c.get("name"))
+                    .and_then(|name| name.as_str())
+                    .unwrap_or("maintenance");
+                span(
+                    TimelineLane::Maintenance,
+                    format!("Maintenance '{name}'"),
+                    at,
+                    end,
+                    now,
+                )
+            }
+            EventType::ProcessError => point(TimelineLane::Event, "Error".to_string(), at),
+            EventType::CrashLoopDetected => {
+                point(TimelineLane::Event, "Crash loop detected".to_string(), at)
+            }
+            EventType::ProcessIdle => point(TimelineLane::Event, "Idle".to_string(), at),
+            EventType::ProcessRestarted => {
+                point(TimelineLane::Event, "Restarted by policy".to_string(), at)
+            }
+            EventType::RestartLimitReached => {
+                point(TimelineLane::Event, "Restart limit reached".to_string(), at)
+            }
+            EventType::ChaosInjected => {
+                point(TimelineLane::Event, "Chaos injected".to_string(), at)
+            }
+            EventType::ProcessStateCorrected => {
+                point(TimelineLane::Event, "State corrected".to_string(), at)
+            }
+            EventType::ProcessAdopted => point(
+                TimelineLane::Event,
+                "Adopted external process".to_string(),
+                at,
+            ),
+            EventType::ScheduledRun => point(TimelineLane::Event, "Scheduled run".to_string(), at),
+            // 実行・期間の境界として使うもの
+            _ => continue,
+        };
+        entry.run = run_at(at);
+        entry.details = event.context.clone();
+        entries.push(entry);
+    }
+
+    // 定義の変更
+    for change in changes.iter().filter(|change| {
+        change.target == ChangeTarget::Process
+            && change.target_id == process_id
+            && window.contains(change.changed_at)
+    }) {
+        let action = serde_json::to_value(change.action)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let mut entry = point(
+            TimelineLane::Annotation,
+            format!("Definition {action}"),
+            change.changed_at,
+        );
+        entry.run = run_at(change.changed_at);
+        entry.details = Some(serde_json::json!({ "change_id": change.change_id }));
+        entries.push(entry);
+    }
+
+    // 実行中に失敗したCI
+    for notification in ci_failures
+        .iter()
+        .filter(|n| n.kind == NotificationKind::CiFailed && window.contains(n.created_at))
+    {
+        let Some(run) = run_at(notification.created_at) else {
+            continue;
+        };
+        let mut entry = point(
+            TimelineLane::Ci,
+            notification.title.clone(),
+            notification.created_at,
+        );
+        entry.run = Some(run);
+        entry.details = Some(serde_json::json!({
+            "message": notification.message,
+            "url": notification.link,
+        }));
+        summary.ci_failures += 1;
+        entries.push(entry);
+    }
+
+    entries.sort_by_key(|entry| entry.start);
+    ProcessTimeline {
+        process_id: process_id.to_string(),
+        from: window.from,
+        to: window.to,
+        generated_at: now,
+        entries,
+        summary,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::run_environment::SystemFacts;
+    use chrono::Duration;
+    use std::collections::BTreeMap;
+
+    fn run(run: u64, started_at: DateTime<Utc>) -> RunEnvironment {
+        RunEnvironment {
+            run,
+            process_id: "api".to_string(),
+            started_at,
+            command: "server".to_string(),
+            args: Vec::new(),
+            parameters: BTreeMap::new(),
+            resolved_command: None,
+            cwd: None,
+            runtime: "host".to_string(),
+            system: SystemFacts::current(None),
+            env: BTreeMap::new(),
+            path: Vec::new(),
+            locale: BTreeMap::new(),
+            tool_versions: None,
+            toolchain: None,
+            failure: None,
+        }
+    }
+
+    fn event(event_type: EventType, at: DateTime<Utc>, context: serde_json::Value) -> ProcessEvent {
+        ProcessEvent {
+            event_type,
+            process_id: "api".to_string(),
+            timestamp: at,
+            context: Some(context),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_build_orders_runs_restarts_health_and_ci() {
+        let now = Utc::now();
+        let t = |minutes: i64| now - Duration::minutes(60 - minutes);
+        let runs = [run(1, t(0)), run(2, t(20))];
+        let events = [
+            event(
+                EventType::ProcessUnhealthy,
+                t(5),
+                serde_json::json!({ "failures": ["error rate"] }),
+            ),
+            event(EventType::ProcessRecovered, t(8), serde_json::json!({})),
+            event(
+                EventType::ProcessStopped,
+                t(10),
+                serde_json::json!({ "exit_code": 1, "stop_requested": false }),
+            ),
+            event(EventType::CrashLoopDetected, t(15), serde_json::json!({})),
+        ];
+        let ci = Notification {
+            notification_id: "n1".to_string(),
+            kind: NotificationKind::CiFailed,
+            title: "CI run 'test' failed".to_string(),
+            message: String::new(),
+            process_id: None,
+            link: Some("https://example.com/run/1".to_string()),
+            created_at: t(30),
+            read: false,
+            read_at: None,
+            route: None,
+        };
+
+        let timeline = build(
+            "api",
+            &runs,
+            &events,
+            &[],
+            &[ci],
+            TimeWindow {
+                from: None,
+                to: None,
+            },
+            now,
+        );
+        let lanes: Vec<TimelineLane> = timeline.entries.iter().map(|e| e.lane).collect();
+        assert_eq!(
+            lanes,
+            vec![
+                TimelineLane::Run,
+                TimelineLane::Health,
+                TimelineLane::Restart,
+                TimelineLane::Event,
+                TimelineLane::Run,
+                TimelineLane::Ci,
+            ]
+        );
+        let first = &timeline.entries[0];
+        assert_eq!(first.status.as_deref(), Some("failed"));
+        assert_eq!(first.duration_ms, Some(10 * 60 * 1000));
+        assert_eq!(timeline.entries[1].duration_ms, Some(3 * 60 * 1000));
+        // 2回目の実行は続いている
+        assert!(timeline.entries[4].ongoing);
+        assert_eq!(timeline.entries[5].run, Some(2));
+        assert_eq!(timeline.summary.runs, 2);
+        assert_eq!(timeline.summary.restarts, 1);
+        assert_eq!(timeline.summary.failed_runs, 1);
+        assert_eq!(timeline.summary.ci_failures, 1);
+    }
+}
@@ -0,0 +1,256 @@
+//! tmuxバックエンド
+//!
+//! プロセスを指定したtmuxセッションのウィンドウ内で起動します。
+//! ペインの出力は `pipe-pane` でログファイルに書き出し、それを読み取ってバッファに取り込むため、
+//! ターミナルから接続して操作しながらでも出力の取得やアイドル検知が機能します。
+
+use super::buffer::CircularBuffer;
+use super::shell::shell_quote;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use vantage_persistence::TmuxConfig;
+
+/// ペインの終了確認間隔
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// 出力ログの追従間隔
+const TAIL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 起動したtmuxペイン
+#[derive(Debug, Clone)]
+pub struct TmuxPane {
+    /// ペインID（例: %3）
+    pub pane_id: String,
+    /// ペインで実行中のプロセスのPID
+    pub pid: u32,
+    /// ペイン出力のログファイル
+    pub log_path: PathBuf,
+}
+
+/// 使用するウィンドウ名（未指定ならプロセスID）
+pub fn window_name<'a>(config: &'a TmuxConfig, process_id: &'a str) -> &'a str {
+    config.window.as_deref().unwrap_or(process_id)
+}
+
+/// ターミナルからセッションに接続するためのコマンド
+pub fn attach_instructions(config: &TmuxConfig, process_id: &str) -> String {
+    let window = window_name(config, process_id);
+    format!(
+        "tmux attach-session -t {session} \\; select-window -t {session}:{window}",
+        session = shell_quote(&config.session),
+        window = shell_quote(window)
+    )
+}
+
+/// tmuxのウィンドウでコマンドを起動
+///
+/// 出力の取りこぼしを防ぐため、ペインは `tmux wait-for` で待機させておき、
+/// `pipe-pane` を設定してから実行を開始する。
+pub async fn launch(
+    config: &TmuxConfig,
+    process_id: &str,
+    command: &str,
+    args: &[String],
+    env: &HashMap<String, String>,
+    cwd: Option<&Path>,
+    log_dir: &Path,
+) -> Result<TmuxPane, String> {
+    let session = &config.session;
+    let window = window_name(config, process_id);
+
+    if tmux(&["has-session", "-t", &format!("={session}")])
+        .await
+        .is_err()
+    {
+        tmux(&["new-session", "-d", "-s", session]).await?;
+    }
+    release_window(session, window).await?;
+
+    let channel = format!(
+        "vantage-{}-{}",
+        process_id,
+        chrono::Utc::now().timestamp_micros()
+    );
+    let mut script = format!("tmux wait-for {} && exec", shell_quote(&channel));
+    for part in std::iter::once(command).chain(args.iter().map(String::as_str)) {
+        script.push(' ');
+        script.push_str(&shell_quote(part));
+    }
+
+    let mut new_window: Vec<String> = vec![
+        "new-window".into(),
+        "-d".into(),
+        "-P".into(),
+        "-F".into(),
+        "#{pane_id} #{pane_pid}".into(),
+        "-t".into(),
+        format!("={session}:"),
+        "-n".into(),
+        window.to_string(),
+    ];
+    if let Some(cwd) = cwd {
+        new_window.push("-c".into());
+        new_window.push(cwd.to_string_lossy().to_string());
+    }
+    for (key, value) in env {
+        new_window.push("-e".into());
+        new_window.push(format!("{key}={value}"));
+    }
+    new_window.push(script);
+
+    let args: Vec<&str> = new_window.iter().map(String::as_str).collect();
+    let output = tmux(&args).await?;
+    let (pane_id, pid) = output
+        .trim()
+        .split_once(' ')
+        .and_then(|(pane, pid)| Some((pane.to_string(), pid.parse::<u32>().ok()?)))
+        .ok_or_else(|| format!("Unexpected tmux output: {output}"))?;
+
+    // 終了コードを取得できるよう、終了後もペインを残す
+    tmux(&["set-option", "-w", "-t", &pane_id, "remain-on-exit", "on"]).await?;
+
+    std::fs::create_dir_all(log_dir).map_err(|e| format!("Failed to create log directory: {e}"))?;
+    let log_path = log_dir.join(format!(
+        "tmux-{}.log",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    std::fs::File::create(&log_path).map_err(|e| format!("Failed to create pane log: {e}"))?;
+    tmux(&[
+        "pipe-pane",
+        "-O",
+        "-t",
+        &pane_id,
+        &format!("cat >> {}", shell_quote(&log_path.to_string_lossy())),
+    ])
+    .await?;
+
+    tmux(&["wait-for", "-S", &channel]).await?;
+
+    Ok(TmuxPane {
+        pane_id,
+        pid,
+        log_path,
+    })
+}
+
+/// ペインの終了を待ち、終了コードを返す（ペインが破棄された場合はNone）
+pub async fn wait_for_exit(pane: &TmuxPane) -> Option<i32> {
+    loop {
+        match tmux(&[
+            "display-message",
+            "-p",
+            "-t",
+            &pane.pane_id,
+            "#{pane_dead} #{pane_dead_status}",
+        ])
+        .await
+        {
+            Ok(output) => {
+                let mut fields = output.split_whitespace();
+                if fields.next() == Some("1") {
+                    return fields.next().and_then(|s| s.parse().ok());
+                }
+            }
+            Err(_) => return None,
+        }
+        tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+    }
+}
+
+/// ペインを破棄
+pub async fn kill_pane(pane: &TmuxPane) -> Result<(), String> {
+    tmux(&["kill-pane", "-t", &pane.pane_id]).await.map(|_| ())
+}
+
+/// ペインの出力ログを追従してバッファに取り込む（タスクの中断まで続く）
+pub async fn tail_output(log_path: PathBuf, buffer: CircularBuffer) {
+    let Ok(file) = tokio::fs::File::open(&log_path).await else {
+        return;
+    };
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    loop {
+        match reader.read_line(&mut line).await {
+            Ok(0) => tokio::time::sleep(TAIL_POLL_INTERVAL).await,
+            Ok(_) if line.ends_with('\n') => {
+                buffer.push(strip_terminal_codes(&line)).await;
+                line.clear();
+            }
+            // 行の途中まで書き込まれている場合は続きを待つ
+            Ok(_) => tokio::time::sleep(TAIL_POLL_INTERVAL).await,
+            Err(_) => return,
+        }
+    }
+}
+
+/// 同名のウィンドウが終了済みなら破棄し、実行中ならエラーにする
+async fn release_window(session: &str, window: &str) -> Result<(), String> {
+    let target = format!("={session}:={window}");
+    let Ok(output) = tmux(&["list-panes", "-t", &target, "-F", "#{pane_dead}"]).await else {
+        return Ok(());
+    };
+    if output.lines().any(|dead| dead.trim() == "0") {
+        return Err(format!(
+            "tmux window '{session}:{window}' is already running a command"
+        ));
+    }
+    tmux(&["kill-window", "-t", &target]).await.map(|_| ())
+}
+
+async fn tmux(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("tmux")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run tmux: {e}"))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(format!(
+            "tmux {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// 端末の制御シーケンスと行末のCRを取り除く
+fn strip_terminal_codes(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{1b}' => {
+                // CSI: ESC [ ... 終端文字(0x40-0x7e)
+                if chars.peek() == Some(&'[') {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if ('\u{40}'..='\u{7e}').contains(&c) {
+                            break;
+                        }
+                    }
+                } else {
+                    chars.next();
+                }
+            }
+            '\r' | '\n' => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_terminal_codes() {
+        assert_eq!(
+            strip_terminal_codes("\u{1b}[32mready\u{1b}[0m on :3000\r\n"),
+            "ready on :3000"
+        );
+    }
+}
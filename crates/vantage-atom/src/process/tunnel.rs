@@ -0,0 +1,487 @@
+//! トンネル（ポートフォワード）の管理
+//!
+//! `ssh -L` / `ssh -R` / cloudflaredのトンネルを子プロセスとして起動・停止し、状態を管理します。
+//! cloudflaredのQuick Tunnelは出力から公開URL（`*.trycloudflare.com`）を取得します。
+//! `process_id` で紐付けたプロセスの `get_process_status` にトンネルの状態と公開URLを表示します。
+
+use super::buffer::CircularBuffer;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::{RwLock, mpsc};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use vantage_persistence::{PersistenceManager, TunnelConfig, TunnelProvider};
+
+/// 停止要求からSIGKILLまでの猶予
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(3);
+/// ヘルスチェックの接続タイムアウト
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_millis(500);
+/// ステータスに含める最近の出力の行数
+const RECENT_OUTPUT_LINES: usize = 5;
+
+/// cloudflaredのQuick Tunnelの公開URL
+static QUICK_TUNNEL_URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"https://[a-z0-9-]+\.trycloudflare\.com").unwrap());
+
+/// トンネルの状態
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum TunnelState {
+    Stopped,
+    Running {
+        pid: u32,
+        started_at: DateTime<Utc>,
+    },
+    Failed {
+        error: String,
+        failed_at: DateTime<Utc>,
+    },
+}
+
+/// トンネルのステータス
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TunnelStatus {
+    #[serde(flatten)]
+    pub config: TunnelConfig,
+    pub state: TunnelState,
+    /// 公開URL（設定値、またはcloudflaredの出力から取得したもの）
+    pub public_url: Option<String>,
+    /// ヘルスチェックの結果（実行中の場合のみ）
+    ///
+    /// ssh_localは待ち受けポートに接続できるか、cloudflaredは公開URLを取得できたかで判定する。
+    pub healthy: Option<bool>,
+    /// 最近の出力（接続エラーの確認用）
+    pub recent_output: Vec<String>,
+}
+
+struct ManagedTunnel {
+    config: TunnelConfig,
+    state: TunnelState,
+    /// 出力から取得した公開URL
+    detected_url: Option<String>,
+    output: CircularBuffer,
+    stop_tx: Option<mpsc::Sender<()>>,
+}
+
+/// トンネルの管理
+pub struct TunnelManager {
+    tunnels: RwLock<HashMap<String, Arc<RwLock<ManagedTunnel>>>>,
+    persistence: Arc<PersistenceManager>,
+}
+
+impl TunnelManager {
+    pub fn new(persistence: Arc<PersistenceManager>) -> Self {
+        Self {
+            tunnels: RwLock::new(HashMap::new()),
+            persistence,
+        }
+    }
+
+    /// トンネルを登録する（起動はしない）
+    pub async fn create(&self, config: TunnelConfig) -> Result<(), String> {
+        validate(&config)?;
+        let mut tunnels = self.tunnels.write().await;
+        if tunnels.contains_key(&config.tunnel_id) {
+            return Err(format!(
+                "Tunnel with id '{}' already exists",
+                config.tunnel_id
+            ));
+        }
+        self.persistence.save_tunnel(&config).await?;
+        info!(
+            "Created tunnel '{}' ({})",
+            config.tunnel_id,
+            describe(&config)
+        );
+        tunnels.insert(
+            config.tunnel_id.clone(),
+            Arc::new(RwLock::new(ManagedTunnel {
+                config,
+                state: TunnelState::Stopped,
+                detected_url: None,
+                output: CircularBuffer::new(100),
+                stop_tx: None,
+            })),
+        );
+        Ok(())
+    }
+
+    /// トンネルを削除する（実行中なら停止する）
+    pub async fn remove(&self, tunnel_id: &str) -> Result<(), String> {
+        let tunnel = self.get(tunnel_id).await?;
+        if matches!(tunnel.read().await.state, TunnelState::Running { .. }) {
+            self.stop(tunnel_id).await?;
+        }
+        self.tunnels.write().await.remove(tunnel_id);
+        self.persistence.delete_tunnel(tunnel_id).await?;
+        info!("Removed tunnel '{}'", tunnel_id);
+        Ok(())
+    }
+
+    /// トンネルを起動し、PIDを返す
+    pub async fn start(&self, tunnel_id: &str) -> Result<u32, String> {
+        let tunnel_arc = self.get(tunnel_id).await?;
+        let mut tunnel = tunnel_arc.write().await;
+        if matches!(tunnel.state, TunnelState::Running { .. }) {
+            return Err(format!("Tunnel '{tunnel_id}' is already running"));
+        }
+
+        let (program, args) = command_line(&tunnel.config);
+        let mut child = Command::new(program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start tunnel '{tunnel_id}' ({program}): {e}"))?;
+        let pid = child
+            .id()
+            .ok_or_else(|| "Failed to get tunnel process ID".to_string())?;
+
+        tunnel.detected_url = None;
+        tunnel.output.clear().await;
+        let readers = [
+            tokio::spawn(capture_output(child.stdout.take(), tunnel_arc.clone())),
+            tokio::spawn(capture_output(child.stderr.take(), tunnel_arc.clone())),
+        ];
+
+        let (stop_tx, mut stop_rx) = mpsc::channel(1);
+        tunnel.stop_tx = Some(stop_tx);
+        tunnel.state = TunnelState::Running {
+            pid,
+            started_at: Utc::now(),
+        };
+        info!(
+            "Started tunnel '{}' (pid {}): {} {}",
+            tunnel_id,
+            pid,
+            program,
+            args.join(" ")
+        );
+
+        let id = tunnel_id.to_string();
+        let watched = tunnel_arc.clone();
+        tokio::spawn(async move {
+            let (status, stopped) = tokio::select! {
+                status = child.wait() => (status, false),
+                _ = stop_rx.recv() => {
+                    terminate(&mut child, pid).await;
+                    (child.wait().await, true)
+                }
+            };
+            for reader in readers {
+                let _ = reader.await;
+            }
+
+            let mut tunnel = watched.write().await;
+            tunnel.stop_tx = None;
+            tunnel.state = if stopped {
+                info!("Stopped tunnel '{}'", id);
+                TunnelState::Stopped
+            } else {
+                let last_line = tunnel.output.get_last_n(1).await.pop();
+                let error = match (status, last_line) {
+                    (Ok(status), Some(line)) => format!("Tunnel exited ({status}): {line}"),
+                    (Ok(status), None) => format!("Tunnel exited ({status})"),
+                    (Err(e), _) => format!("Failed to wait for tunnel: {e}"),
+                };
+                warn!("Tunnel '{}' stopped unexpectedly: {}", id, error);
+                TunnelState::Failed {
+                    error,
+                    failed_at: Utc::now(),
+                }
+            };
+        });
+        Ok(pid)
+    }
+
+    /// トンネルを停止する
+    pub async fn stop(&self, tunnel_id: &str) -> Result<(), String> {
+        let tunnel_arc = self.get(tunnel_id).await?;
+        let stop_tx = tunnel_arc
+            .read()
+            .await
+            .stop_tx
+            .clone()
+            .ok_or_else(|| format!("Tunnel '{tunnel_id}' is not running"))?;
+        let _ = stop_tx.send(()).await;
+
+        // 終了監視タスクが状態を更新するまで待つ
+        let deadline = tokio::time::Instant::now() + STOP_GRACE_PERIOD * 2;
+        while tokio::time::Instant::now() < deadline {
+            if tunnel_arc.read().await.stop_tx.is_none() {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Err(format!(
+            "Timed out waiting for tunnel '{tunnel_id}' to stop"
+        ))
+    }
+
+    pub async fn status(&self, tunnel_id: &str) -> Result<TunnelStatus, String> {
+        let tunnel = self.get(tunnel_id).await?;
+        Ok(status_of(&*tunnel.read().await).await)
+    }
+
+    /// トンネルを一覧する（`process_id` を指定するとそのプロセスに紐付いたもののみ）
+    pub async fn list(&self, process_id: Option<&str>) -> Vec<TunnelStatus> {
+        let tunnels: Vec<_> = self.tunnels.read().await.values().cloned().collect();
+        let mut statuses = Vec::new();
+        for tunnel in tunnels {
+            let tunnel = tunnel.read().await;
+            if process_id.is_none_or(|id| tunnel.config.process_id.as_deref() == Some(id)) {
+                statuses.push(status_of(&tunnel).await);
+            }
+        }
+        statuses.sort_by(|a, b| a.config.tunnel_id.cmp(&b.config.tunnel_id));
+        statuses
+    }
+
+    async fn get(&self, tunnel_id: &str) -> Result<Arc<RwLock<ManagedTunnel>>, String> {
+        self.tunnels
+            .read()
+            .await
+            .get(tunnel_id)
+            .cloned()
+            .ok_or_else(|| format!("Tunnel '{tunnel_id}' not found"))
+    }
+}
+
+/// 定義を検証
+pub fn validate(config: &TunnelConfig) -> Result<(), String> {
+    if config.tunnel_id.trim().is_empty() {
+        return Err("tunnel_id must not be empty".to_string());
+    }
+    if config.local_port == 0 {
+        return Err("local_port must be greater than 0".to_string());
+    }
+    if config.provider == TunnelProvider::Cloudflared {
+        return Ok(());
+    }
+    let ssh_host = config.ssh_host.as_deref().unwrap_or_default();
+    if ssh_host.trim().is_empty()
+        || ssh_host.starts_with('-')
+        || ssh_host.contains(char::is_whitespace)
+    {
+        return Err("ssh_host is required for ssh tunnels (e.g. user@host)".to_string());
+    }
+    let remote = config.remote.as_deref().unwrap_or_default();
+    let valid = match config.provider {
+        TunnelProvider::SshLocal => remote
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok()),
+        _ => remote
+            .rsplit(':')
+            .next()
+            .is_some_and(|port| port.parse::<u16>().is_ok()),
+    };
+    if !valid || remote.starts_with('-') || remote.contains(char::is_whitespace) {
+        return Err(match config.provider {
+            TunnelProvider::SshLocal => {
+                "remote must be the forwarding target as host:port for ssh_local".to_string()
+            }
+            _ => "remote must be the port to listen on ([bind:]port) for ssh_remote".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// 起動するコマンドと引数
+fn command_line(config: &TunnelConfig) -> (&'static str, Vec<String>) {
+    let ssh = |flag: &str, spec: String| {
+        let mut args: Vec<String> = [
+            "-N",
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ExitOnForwardFailure=yes",
+            "-o",
+            "ServerAliveInterval=30",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+        args.push(flag.to_string());
+        args.push(spec);
+        args.push(config.ssh_host.clone().unwrap_or_default());
+        ("ssh", args)
+    };
+    let remote = config.remote.clone().unwrap_or_default();
+    match config.provider {
+        TunnelProvider::SshLocal => ssh("-L", format!("{}:{remote}", config.local_port)),
+        TunnelProvider::SshRemote => ssh("-R", format!("{remote}:localhost:{}", config.local_port)),
+        TunnelProvider::Cloudflared => (
+            "cloudflared",
+            vec![
+                "tunnel".to_string(),
+                "--no-autoupdate".to_string(),
+                "--url".to_string(),
+                format!("http://localhost:{}", config.local_port),
+            ],
+        ),
+    }
+}
+
+/// ログ用の表記
+fn describe(config: &TunnelConfig) -> String {
+    let (program, args) = command_line(config);
+    format!("{program} {}", args.join(" "))
+}
+
+/// 出力から公開URLを取り出す
+fn detect_public_url(provider: TunnelProvider, line: &str) -> Option<String> {
+    match provider {
+        TunnelProvider::Cloudflared => QUICK_TUNNEL_URL.find(line).map(|m| m.as_str().to_string()),
+        _ => None,
+    }
+}
+
+async fn capture_output(
+    reader: Option<impl AsyncRead + Unpin>,
+    tunnel: Arc<RwLock<ManagedTunnel>>,
+) {
+    let Some(reader) = reader else {
+        return;
+    };
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let mut tunnel = tunnel.write().await;
+        if tunnel.detected_url.is_none()
+            && let Some(url) = detect_public_url(tunnel.config.provider, &line)
+        {
+            info!(
+                "Tunnel '{}' is available at {}",
+                tunnel.config.tunnel_id, url
+            );
+            tunnel.detected_url = Some(url);
+        }
+        tunnel.output.push(line).await;
+    }
+}
+
+/// SIGTERMで終了を促し、猶予内に終了しなければ強制終了する
+async fn terminate(child: &mut tokio::process::Child, pid: u32) {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{Signal, kill};
+        use nix::unistd::Pid;
+        if kill(Pid::from_raw(pid as i32), Signal::SIGTERM).is_ok()
+            && tokio::time::timeout(STOP_GRACE_PERIOD, child.wait())
+                .await
+                .is_ok()
+        {
+            return;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = pid;
+    let _ = child.kill().await;
+}
+
+async fn status_of(tunnel: &ManagedTunnel) -> TunnelStatus {
+    let public_url = tunnel
+        .config
+        .public_url
+        .clone()
+        .or_else(|| tunnel.detected_url.clone());
+    let healthy = match tunnel.state {
+        TunnelState::Running { .. } => Some(match tunnel.config.provider {
+            TunnelProvider::SshLocal => port_is_open(tunnel.config.local_port).await,
+            TunnelProvider::SshRemote => true,
+            TunnelProvider::Cloudflared => public_url.is_some(),
+        }),
+        _ => None,
+    };
+    TunnelStatus {
+        config: tunnel.config.clone(),
+        state: tunnel.state.clone(),
+        public_url,
+        healthy,
+        recent_output: tunnel.output.get_last_n(RECENT_OUTPUT_LINES).await,
+    }
+}
+
+async fn port_is_open(port: u16) -> bool {
+    matches!(
+        tokio::time::timeout(
+            HEALTH_CHECK_TIMEOUT,
+            tokio::net::TcpStream::connect(("127.0.0.1", port))
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(provider: TunnelProvider, remote: Option<&str>) -> TunnelConfig {
+        TunnelConfig {
+            tunnel_id: "db".to_string(),
+            provider,
+            local_port: 15432,
+            ssh_host: Some("me@bastion".to_string()),
+            remote: remote.map(str::to_string),
+            process_id: None,
+            public_url: None,
+        }
+    }
+
+    #[test]
+    fn test_command_line() {
+        let (program, args) = command_line(&config(TunnelProvider::SshLocal, Some("db:5432")));
+        assert_eq!(program, "ssh");
+        assert_eq!(
+            args[args.len() - 3..],
+            ["-L", "15432:db:5432", "me@bastion"]
+        );
+
+        let (_, args) = command_line(&config(TunnelProvider::SshRemote, Some("0.0.0.0:8080")));
+        assert_eq!(
+            args[args.len() - 3..],
+            ["-R", "0.0.0.0:8080:localhost:15432", "me@bastion"]
+        );
+
+        let (program, args) = command_line(&config(TunnelProvider::Cloudflared, None));
+        assert_eq!(program, "cloudflared");
+        assert_eq!(args.last().unwrap(), "http://localhost:15432");
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&config(TunnelProvider::SshLocal, Some("db:5432"))).is_ok());
+        assert!(validate(&config(TunnelProvider::SshLocal, Some("5432"))).is_err());
+        assert!(validate(&config(TunnelProvider::SshRemote, Some("8080"))).is_ok());
+        assert!(validate(&config(TunnelProvider::SshRemote, None)).is_err());
+        assert!(validate(&config(TunnelProvider::Cloudflared, None)).is_ok());
+
+        let mut injected = config(TunnelProvider::SshLocal, Some("db:5432"));
+        injected.ssh_host = Some("-oProxyCommand=evil".to_string());
+        assert!(validate(&injected).is_err());
+    }
+
+    #[test]
+    fn test_detect_public_url() {
+        let line = "2024-05-01T00:00:00Z INF |  https://quiet-river-1234.trycloudflare.com  |";
+        assert_eq!(
+            detect_public_url(TunnelProvider::Cloudflared, line).as_deref(),
+            Some("https://quiet-river-1234.trycloudflare.com")
+        );
+        assert_eq!(
+            detect_public_url(
+                TunnelProvider::Cloudflared,
+                "INF Visit https://www.cloudflare.com/website-terms/"
+            ),
+            None
+        );
+    }
+}
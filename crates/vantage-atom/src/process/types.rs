@@ -3,15 +3,45 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use utoipa::ToSchema;
+
+use super::cgroup::CgroupStats;
+use super::container::ImageBuildStatus;
+use super::crash_loop::CrashLoopInfo;
+use super::energy::ResourceUsage;
+use super::failure_report::FailureReport;
+use super::health_probe::ProbeStatus;
+use super::lazy_start::LazyStartStatus;
+use super::maintenance::ActiveMaintenance;
+use super::repl::ReplStatus;
+use super::resource_samples::ResourceHistory;
+use super::restart_policy::RestartStatus;
+use super::sampling::SamplingStatus;
+use super::tunnel::TunnelStatus;
+pub use vantage_persistence::{
+    ApprovalRequest, ApprovalStatus, ChainAction, ConfigFormat, ContainerConfig, ContainerEngine,
+    DependencyReady, EnvLoader, HealthAction, HealthCheckConfig, HealthProbe, IdleAction,
+    IdleDetectionConfig, ImageBuildConfig, ImportSource, LazyStartConfig, LogSinkConfig,
+    LogSinkKind, LogSource, OutputRecordingConfig, ProcessAssertion, ProcessDependency,
+    ProcessParameter, RedactionOverride, RedactionRule, ReplConfig, ReplLanguage, ResourceLimits,
+    RestartMode, RestartPolicy, RunAsConfig, Runbook, StartupOrder, TmuxConfig, TunnelConfig,
+    TunnelProvider, WorkspaceQuota,
+};
 
 /// プロセスの状態
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub enum ProcessState {
     NotStarted,
     Running {
         pid: u32,
         started_at: DateTime<Utc>,
     },
+    /// SIGSTOPで一時停止中（`resume_process` で再開する）
+    Paused {
+        pid: u32,
+        started_at: DateTime<Utc>,
+        paused_at: DateTime<Utc>,
+    },
     Stopped {
         exit_code: Option<i32>,
         stopped_at: DateTime<Utc>,
@@ -23,25 +53,225 @@ pub enum ProcessState {
 }
 
 /// プロセスの基本情報
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ProcessInfo {
     pub id: String,
     pub command: String,
     pub args: Vec<String>,
     pub env: HashMap<String, String>,
+    /// 暗号化して保存する機密の環境変数（出力では値を伏せる）
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "crate::security::sensitive_env::serialize_masked"
+    )]
+    pub sensitive_env: HashMap<String, String>,
+    #[schema(value_type = Option<String>)]
     pub cwd: Option<PathBuf>,
     pub state: ProcessState,
     #[serde(default)]
     pub auto_start_on_restore: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_detection: Option<IdleDetectionConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<HealthCheckConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmux: Option<TmuxConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repl: Option<ReplConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_sources: Vec<LogSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as: Option<RunAsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_limits: Option<ResourceLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ImportSource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_sinks: Vec<LogSinkConfig>,
+    /// 出力を伏せるルールの上書き（設定のルールの一部を無効にする・独自のルールを追加する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<RedactionOverride>,
+    /// 担当者・チケットURL・手順書へのリンクなどの任意のキーと値
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// 障害時の復旧手順などの手順書（Markdownファイルまたは直接書いたテキスト）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runbook: Option<Runbook>,
+    /// 自動起動・一括起動での起動順序（優先度・並列に起動するグループ・準備完了の確認）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup: Option<StartupOrder>,
+    /// 最初の接続で起動する設定（Vantageがポートで待ち受け、接続が来たら起動して中継する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lazy_start: Option<LazyStartConfig>,
+    /// 起動するときに作業ディレクトリで読み込むプロジェクトの環境（mise・direnv・asdf）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_loader: Option<EnvLoader>,
+    /// 起動のたびに出力を時刻付きで記録する設定（`replay_output` で再生する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_recording: Option<OutputRecordingConfig>,
+    /// `run_checks` で評価する振る舞いの確認項目
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<ProcessAssertion>,
+    /// 終了したときに自動で再起動する方針
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_policy: Option<RestartPolicy>,
+    /// `start_group` / `stop_group` でまとめて起動・停止するグループ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// 起動する前に準備完了を待つプロセス
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<ProcessDependency>,
+    /// 起動ごとに値を指定する引数のパラメーター
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<ProcessParameter>,
+    /// 標準入力をパイプとして開いたままにする
+    #[serde(default)]
+    pub keep_stdin_open: bool,
+    /// ヘルスチェックの現在の判定（実行中のみ、保存しない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthState>,
+}
+
+/// ヘルスチェックの現在の判定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct HealthState {
+    pub healthy: bool,
+    pub checked_at: DateTime<Utc>,
+    /// 異常とみなした理由（出力のしきい値・プローブの失敗）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<String>,
+    /// 異常と判定された時刻
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unhealthy_since: Option<DateTime<Utc>>,
+    /// プローブを設定している場合、その最後の結果と続けて失敗した回数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe: Option<ProbeStatus>,
+}
+
+/// プロセス作成時のオプション
+#[derive(Debug, Clone, Default)]
+pub struct ProcessOptions {
+    /// サーバー起動時に自動起動する
+    pub auto_start_on_restore: bool,
+    /// 分類・一括操作用のタグ
+    pub tags: Vec<String>,
+    /// アイドル検知設定
+    pub idle_detection: Option<IdleDetectionConfig>,
+    /// 出力のエラー率・stderrの割合によるヘルスチェック
+    pub health_check: Option<HealthCheckConfig>,
+    /// tmuxセッション内で起動する
+    pub tmux: Option<TmuxConfig>,
+    /// REPLとして扱い、execute_in_replでコードを実行できるようにする
+    pub repl: Option<ReplConfig>,
+    /// 追従して出力に取り込むログファイル・FIFO
+    pub log_sources: Vec<LogSource>,
+    /// 別のユーザー・グループで起動する（Unixのみ）
+    pub run_as: Option<RunAsConfig>,
+    /// cgroupで課すリソース制限（Linuxのcgroup v2のみ）
+    pub resource_limits: Option<ResourceLimits>,
+    /// コンテナで実行する（起動前にイメージをビルドできる）
+    pub container: Option<ContainerConfig>,
+    /// 取り込み元（tasks.json等から取り込んだ場合）
+    pub source: Option<ImportSource>,
+    /// 出力の転送先（設定の転送先に追加される）
+    pub log_sinks: Vec<LogSinkConfig>,
+    /// 出力を伏せるルールの上書き
+    pub redaction: Option<RedactionOverride>,
+    /// 任意のメタデータ（担当者・チケットURLなど）
+    pub metadata: HashMap<String, String>,
+    /// 手順書
+    pub runbook: Option<Runbook>,
+    /// 自動起動・一括起動での起動順序
+    pub startup: Option<StartupOrder>,
+    /// 起動するときに読み込むプロジェクトの環境
+    pub env_loader: Option<EnvLoader>,
+    /// 起動のたびに出力を記録する
+    pub output_recording: Option<OutputRecordingConfig>,
+    /// `run_checks` で評価する振る舞いの確認項目
+    pub assertions: Vec<ProcessAssertion>,
+    /// 終了したときに自動で再起動する方針
+    pub restart_policy: Option<RestartPolicy>,
+    /// まとめて起動・停止するグループ
+    pub group: Option<String>,
+    /// 起動する前に準備完了を待つプロセス
+    pub depends_on: Vec<ProcessDependency>,
+    /// 起動ごとに値を指定する引数のパラメーター
+    pub parameters: Vec<ProcessParameter>,
+    /// 標準入力をパイプとして開いたままにし、send_process_inputで入力を送れるようにする
+    pub keep_stdin_open: bool,
+    /// 暗号化して保存する環境変数の名前（`env` の中から指定する）
+    pub sensitive_env: Vec<String>,
 }
 
 /// プロセスの詳細ステータス
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ProcessStatus {
     pub info: ProcessInfo,
+    /// 直近のCPU使用率（%、1コアで100%）
     pub cpu_usage: Option<f32>,
+    /// メモリの使用量（バイト、cgroupに入っていれば子プロセスを含め、それ以外はRSS）
     pub memory_usage: Option<u64>,
     pub uptime_seconds: Option<u64>,
+    /// アイドル検知でアイドルと判定されているか
+    #[serde(default)]
+    pub idle: bool,
+    /// アイドルと判定された時刻
+    pub idle_since: Option<DateTime<Utc>>,
+    /// ヘルスチェックで異常と判定された時刻（詳細は get_process_metrics）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unhealthy_since: Option<DateTime<Utc>>,
+    /// 検知されたクラッシュループ（自動再起動は抑止される）
+    pub crash_loop: Option<CrashLoopInfo>,
+    /// tmuxバックエンドの場合、ターミナルからセッションに接続するコマンド
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attach_instructions: Option<String>,
+    /// REPLプロセスの場合、実行中か待機中か
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repl: Option<ReplStatus>,
+    /// カオステストで一時停止（SIGSTOP）している場合、再開予定時刻
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chaos_paused_until: Option<DateTime<Utc>>,
+    /// 出力のサンプリングが有効な場合、その設定と間引いた行数（stdout/stderrの合計）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_sampling: Option<SamplingStatus>,
+    /// プロセス専用のcgroupに入っている場合、子プロセスを含めたCPU・メモリの使用状況
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<CgroupStats>,
+    /// コンテナのイメージをビルドした場合、最後のビルドの結果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_build: Option<ImageBuildStatus>,
+    /// このプロセスに紐付けたトンネルの状態と公開URL
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tunnels: Vec<TunnelStatus>,
+    /// メンテナンスウィンドウの期間中の場合、そのウィンドウ（自動再起動や通知は止まる）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<ActiveMaintenance>,
+    /// 積算したCPU時間と、見積もった消費電力量・電気代（実行したことがある場合のみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_usage: Option<ResourceUsage>,
+    /// 実行中の場合、直近のCPU使用率・RSSの最小・最大・平均
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_history: Option<ResourceHistory>,
+    /// 出力を伏せるルールで置き換えた箇所の数（stdout/stderrの合計、再起動をまたいで数える）
+    #[serde(default)]
+    pub redactions: u64,
+    /// 外部で起動したプロセスを引き取った場合、その時刻（出力は取り込まない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adopted_at: Option<DateTime<Utc>>,
+    /// 最後に異常終了したときのstderrの末尾・終了コードとシグナル・リソースの状況
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<FailureReport>,
+    /// 最初の接続で起動するように設定している場合、その待ち受けの状態
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lazy_start: Option<LazyStartStatus>,
+    /// 再起動ポリシーを設定している場合、続けて再起動した回数と次の再起動の予定
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<RestartStatus>,
 }
 
 /// 出力ストリームの種類
@@ -59,18 +289,35 @@ pub struct ProcessFilter {
     pub name_pattern: Option<String>,
 }
 
+/// フィルター式で選んだプロセスへの一括操作の結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BulkOperationReport {
+    /// 式に一致したプロセス
+    pub matched: Vec<String>,
+    /// 操作に成功したプロセス
+    pub succeeded: Vec<String>,
+    /// 操作の必要がなかったプロセス（すでに起動中・停止中など）
+    pub skipped: Vec<String>,
+    /// 操作に失敗したプロセスとエラー
+    pub failed: Vec<BulkOperationFailure>,
+    /// 一致を確かめただけで操作していないか
+    pub dry_run: bool,
+    /// 起動順序に従った起動の結果（一括起動のみ）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub startup: Option<super::startup::StartupReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkOperationFailure {
+    pub id: String,
+    pub error: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum ProcessStateFilter {
     Running,
+    Paused,
     Stopped,
     Failed,
     All,
 }
-
-/// 再起動ポリシー
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum RestartPolicy {
-    Never,
-    Always,
-    OnFailure { max_retries: u32 },
-}
@@ -0,0 +1,456 @@
+//! VS Codeの `tasks.json` からの取り込み
+//!
+//! タスクのコマンド・引数・作業ディレクトリ・環境変数をプロセス定義に変換します。
+//! `problemMatcher` や `group`、`presentation` など実行に関係しない項目は無視します。
+
+use super::shell::shell_quote;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 取り込み元の種類（`ImportSource::kind`）
+pub const SOURCE_KIND: &str = "vscode_tasks";
+
+/// プロセス定義に変換したタスク
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedTask {
+    /// タスクのラベル
+    pub label: String,
+    /// ラベルから作ったID候補
+    pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub cwd: Option<String>,
+}
+
+/// 取り込めなかったタスク
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkippedTask {
+    pub label: String,
+    pub reason: String,
+}
+
+/// 取り込み結果（作成・更新したプロセスIDまたはテンプレートID）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskImportReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub skipped: Vec<SkippedTask>,
+}
+
+/// `tasks.json` の解析結果
+#[derive(Debug, Clone, Default)]
+pub struct ParsedTasks {
+    pub tasks: Vec<ImportedTask>,
+    pub skipped: Vec<SkippedTask>,
+}
+
+/// `tasks.json` のパスからワークスペースフォルダを求める（`.vscode` の親ディレクトリ）
+pub fn workspace_folder(tasks_path: &Path) -> PathBuf {
+    let dir = tasks_path.parent().unwrap_or(Path::new("."));
+    if dir.file_name().is_some_and(|name| name == ".vscode") {
+        dir.parent().unwrap_or(dir).to_path_buf()
+    } else {
+        dir.to_path_buf()
+    }
+}
+
+/// `tasks.json` の内容を解析する
+pub fn parse_tasks(contents: &str, workspace_folder: &Path) -> Result<ParsedTasks, String> {
+    let root: Value = serde_json::from_str(&strip_jsonc(contents))
+        .map_err(|e| format!("Invalid tasks.json: {e}"))?;
+    let root = platform_merged(
+        root.as_object()
+            .ok_or("Invalid tasks.json: expected an object")?,
+    );
+    let tasks = root
+        .get("tasks")
+        .and_then(Value::as_array)
+        .ok_or("Invalid tasks.json: missing 'tasks' array")?;
+
+    let vars = Variables::new(workspace_folder);
+    let global_options = root.get("options").and_then(Value::as_object);
+    let mut parsed = ParsedTasks::default();
+    for (index, task) in tasks.iter().enumerate() {
+        let Some(task) = task.as_object() else {
+            continue;
+        };
+        let task = platform_merged(task);
+        let label = task
+            .get("label")
+            .or_else(|| task.get("taskName"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("task-{}", index + 1));
+        match convert_task(&task, global_options, &vars) {
+            Ok((command, args, env, cwd)) => parsed.tasks.push(ImportedTask {
+                id: slug(&label),
+                label,
+                command,
+                args,
+                env,
+                cwd,
+            }),
+            Err(reason) => parsed.skipped.push(SkippedTask { label, reason }),
+        }
+    }
+    Ok(parsed)
+}
+
+type ConvertedTask = (String, Vec<String>, HashMap<String, String>, Option<String>);
+
+fn convert_task(
+    task: &Map<String, Value>,
+    global_options: Option<&Map<String, Value>>,
+    vars: &Variables,
+) -> Result<ConvertedTask, String> {
+    let options = task.get("options").and_then(Value::as_object);
+    let option = |key: &str| {
+        options
+            .and_then(|o| o.get(key))
+            .or_else(|| global_options.and_then(|o| o.get(key)))
+    };
+
+    let mut env = HashMap::new();
+    for source in [global_options, options].into_iter().flatten() {
+        if let Some(vars_map) = source.get("env").and_then(Value::as_object) {
+            for (key, value) in vars_map {
+                if let Some(value) = value.as_str() {
+                    env.insert(key.clone(), vars.substitute(value)?);
+                }
+            }
+        }
+    }
+    let mut cwd = match option("cwd").and_then(Value::as_str) {
+        Some(cwd) => vars.substitute(cwd)?,
+        None => vars.workspace_folder.clone(),
+    };
+
+    let task_type = task
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("process");
+    let args = task
+        .get("args")
+        .and_then(Value::as_array)
+        .map(|args| {
+            args.iter()
+                .filter_map(quoted_value)
+                .map(|arg| vars.substitute(&arg))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let (command, args) = match task_type {
+        "npm" => {
+            let script = task
+                .get("script")
+                .and_then(Value::as_str)
+                .ok_or("npm task has no 'script'")?;
+            if let Some(path) = task.get("path").and_then(Value::as_str) {
+                cwd = Path::new(&cwd)
+                    .join(vars.substitute(path)?)
+                    .to_string_lossy()
+                    .to_string();
+            }
+            let mut npm_args = vec!["run".to_string(), script.to_string()];
+            if !args.is_empty() {
+                npm_args.push("--".to_string());
+                npm_args.extend(args);
+            }
+            ("npm".to_string(), npm_args)
+        }
+        "shell" | "process" => {
+            let command = task
+                .get("command")
+                .and_then(quoted_value)
+                .ok_or("task has no command")?;
+            let command = vars.substitute(&command)?;
+            if task_type == "process" {
+                (command, args)
+            } else {
+                let shell = option("shell").and_then(Value::as_object);
+                let executable = shell
+                    .and_then(|s| s.get("executable"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("sh")
+                    .to_string();
+                let mut shell_args: Vec<String> = shell
+                    .and_then(|s| s.get("args"))
+                    .and_then(Value::as_array)
+                    .map(|a| a.iter().filter_map(quoted_value).collect())
+                    .unwrap_or_else(|| vec!["-c".to_string()]);
+                // シェルタスクの引数はコマンドラインに連結される
+                let line = std::iter::once(command)
+                    .chain(args.iter().map(|arg| shell_quote(arg)))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                shell_args.push(line);
+                (executable, shell_args)
+            }
+        }
+        other => return Err(format!("unsupported task type '{other}'")),
+    };
+
+    Ok((command, args, env, Some(cwd)))
+}
+
+/// 実行中のプラットフォーム向けの上書き設定（linux / osx / windows）をマージする
+fn platform_merged(object: &Map<String, Value>) -> Map<String, Value> {
+    let platform = if cfg!(target_os = "macos") {
+        "osx"
+    } else if cfg!(windows) {
+        "windows"
+    } else {
+        "linux"
+    };
+    let mut merged = object.clone();
+    if let Some(Value::Object(overrides)) = object.get(platform) {
+        for (key, value) in overrides {
+            match (merged.get_mut(key), value) {
+                (Some(Value::Object(base)), Value::Object(patch)) => {
+                    for (k, v) in patch {
+                        base.insert(k.clone(), v.clone());
+                    }
+                }
+                _ => {
+                    merged.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// 文字列、または `{ "value": ..., "quoting": ... }` 形式の値
+fn quoted_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Object(o) => match o.get("value")? {
+            Value::String(s) => Some(s.clone()),
+            Value::Array(parts) => Some(
+                parts
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `${workspaceFolder}` などの変数の置換
+struct Variables {
+    workspace_folder: String,
+    basename: String,
+}
+
+impl Variables {
+    fn new(workspace_folder: &Path) -> Self {
+        Self {
+            workspace_folder: workspace_folder.to_string_lossy().to_string(),
+            basename: workspace_folder
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// 変数を置換する（起動時に決まらない変数を含む場合はエラー）
+    fn substitute(&self, input: &str) -> Result<String, String> {
+        let mut result = String::with_capacity(input.len());
+        let mut rest = input;
+        while let Some(start) = rest.find("${") {
+            result.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("unterminated variable in '{input}'"))?;
+            let name = &after[..end];
+            let value = match name {
+                "workspaceFolder" | "workspaceRoot" => self.workspace_folder.clone(),
+                "workspaceFolderBasename" => self.basename.clone(),
+                "pathSeparator" | "/" => std::path::MAIN_SEPARATOR.to_string(),
+                _ => match name.strip_prefix("env:") {
+                    Some(var) => std::env::var(var).unwrap_or_default(),
+                    None => return Err(format!("unsupported variable '${{{name}}}'")),
+                },
+            };
+            result.push_str(&value);
+            rest = &after[end + 1..];
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+}
+
+/// ラベルからプロセスID・テンプレートIDに使える文字列を作る
+pub fn slug(label: &str) -> String {
+    let mut slug = String::with_capacity(label.len());
+    for c in label.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "task".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// 既存のIDと重ならないよう、必要に応じて連番を付ける
+pub fn unique_id(base: &str, taken: &[String]) -> String {
+    if !taken.iter().any(|id| id == base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{base}-{n}"))
+        .find(|id| !taken.contains(id))
+        .expect("an unused id always exists")
+}
+
+/// JSONCのコメントと末尾のカンマを取り除く
+fn strip_jsonc(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        out.push(escaped);
+                    }
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            ']' | '}' => {
+                // 直前の空白を挟んだカンマを取り除く
+                let trimmed = out.trim_end().len();
+                if out[..trimmed].ends_with(',') {
+                    out.truncate(trimmed - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASKS: &str = r#"{
+        // See https://go.microsoft.com/fwlink/?LinkId=733558
+        "version": "2.0.0",
+        "options": { "env": { "RUST_LOG": "info" } },
+        "tasks": [
+            {
+                "label": "Dev Server",
+                "type": "shell",
+                "command": "npm run dev",
+                "args": ["--port", "3000"],
+                "problemMatcher": ["$tsc-watch"],
+                "options": { "cwd": "${workspaceFolder}/web" },
+            },
+            {
+                "label": "cargo watch",
+                "type": "process",
+                "command": "cargo",
+                "args": ["watch", { "value": "-x check", "quoting": "strong" }],
+                "linux": { "options": { "env": { "CARGO_TARGET_DIR": "/tmp/target" } } }
+            },
+            /* npm script */
+            { "type": "npm", "script": "lint", "path": "web", "label": "npm: lint" },
+            { "label": "all", "dependsOn": ["Dev Server", "cargo watch"] },
+            { "label": "pick", "type": "shell", "command": "echo ${input:name}" }
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_tasks() {
+        let parsed = parse_tasks(TASKS, Path::new("/work/app")).unwrap();
+        assert_eq!(parsed.tasks.len(), 3);
+
+        let dev = &parsed.tasks[0];
+        assert_eq!(dev.id, "dev-server");
+        assert_eq!(dev.command, "sh");
+        assert_eq!(dev.args, vec!["-c", "npm run dev --port 3000"]);
+        assert_eq!(dev.cwd.as_deref(), Some("/work/app/web"));
+        assert_eq!(dev.env["RUST_LOG"], "info");
+
+        let watch = &parsed.tasks[1];
+        assert_eq!(watch.command, "cargo");
+        assert_eq!(watch.args, vec!["watch", "-x check"]);
+        assert_eq!(watch.cwd.as_deref(), Some("/work/app"));
+        #[cfg(target_os = "linux")]
+        assert_eq!(watch.env["CARGO_TARGET_DIR"], "/tmp/target");
+
+        let lint = &parsed.tasks[2];
+        assert_eq!(lint.id, "npm-lint");
+        assert_eq!(lint.command, "npm");
+        assert_eq!(lint.args, vec!["run", "lint"]);
+        assert_eq!(lint.cwd.as_deref(), Some("/work/app/web"));
+
+        let skipped: Vec<_> = parsed.skipped.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(skipped, vec!["all", "pick"]);
+        assert!(parsed.skipped[1].reason.contains("input:name"));
+    }
+
+    #[test]
+    fn test_strip_jsonc_keeps_strings() {
+        let stripped = strip_jsonc(r#"{"url": "http://x//y", /* c */ "a": [1, 2,], }"#);
+        let value: Value = serde_json::from_str(&stripped).unwrap();
+        assert_eq!(value["url"], "http://x//y");
+        assert_eq!(value["a"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn test_workspace_folder() {
+        assert_eq!(
+            workspace_folder(Path::new("/work/app/.vscode/tasks.json")),
+            PathBuf::from("/work/app")
+        );
+        assert_eq!(slug("  Build & Test!"), "build-test");
+        let taken = vec!["build".to_string(), "build-2".to_string()];
+        assert_eq!(unique_id("build", &taken), "build-3");
+    }
+}
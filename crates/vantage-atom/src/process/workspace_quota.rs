@@ -0,0 +1,207 @@
+//! ワークスペースごとのクォータ
+//!
+//! 共有の開発サーバーで複数人が同じインスタンスを使う場合に備えて、作業ディレクトリ単位で
+//! 登録数・同時実行数・ログのディスク使用量を制限します。プロセスは作業ディレクトリを含む
+//! もっとも深いワークスペースに属し、どのワークスペースにも属さないプロセスは制限しません。
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use vantage_persistence::WorkspaceQuota;
+
+/// ワークスペースの使用量
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceUsage {
+    pub workspace: String,
+    pub quota: WorkspaceQuota,
+    pub processes: usize,
+    pub running: usize,
+    pub log_bytes: u64,
+    pub process_ids: Vec<String>,
+}
+
+/// 使用量の集計に使うプロセスの情報
+pub struct QuotaMember {
+    pub id: String,
+    pub cwd: Option<PathBuf>,
+    pub running: bool,
+}
+
+/// 設定を検証
+pub fn validate(quotas: &[WorkspaceQuota]) -> Result<(), String> {
+    for (i, quota) in quotas.iter().enumerate() {
+        if !Path::new(&quota.path).is_absolute() {
+            return Err(format!(
+                "workspace_quotas[{i}].path must be an absolute path: '{}'",
+                quota.path
+            ));
+        }
+        if quotas[..i].iter().any(|q| same_path(&q.path, &quota.path)) {
+            return Err(format!("Duplicate workspace quota for '{}'", quota.path));
+        }
+    }
+    Ok(())
+}
+
+/// プロセスが属するワークスペース（作業ディレクトリ未指定の場合はサーバーの作業ディレクトリ）
+pub fn workspace_of<'a>(
+    quotas: &'a [WorkspaceQuota],
+    cwd: Option<&Path>,
+) -> Option<&'a WorkspaceQuota> {
+    let current = std::env::current_dir().ok();
+    let cwd = match (cwd, &current) {
+        (Some(cwd), Some(current)) if cwd.is_relative() => current.join(cwd),
+        (Some(cwd), _) => cwd.to_path_buf(),
+        (None, Some(current)) => current.clone(),
+        (None, None) => return None,
+    };
+    quotas
+        .iter()
+        .filter(|q| cwd.starts_with(&q.path))
+        .max_by_key(|q| Path::new(&q.path).components().count())
+}
+
+/// ワークスペースごとの使用量を集計
+///
+/// `log_bytes` はプロセスIDごとのログのディスク使用量。
+pub fn usage(
+    quotas: &[WorkspaceQuota],
+    members: &[QuotaMember],
+    log_bytes: &HashMap<String, u64>,
+) -> Vec<WorkspaceUsage> {
+    let mut usages: Vec<WorkspaceUsage> = quotas
+        .iter()
+        .map(|quota| WorkspaceUsage {
+            workspace: quota.path.clone(),
+            quota: quota.clone(),
+            processes: 0,
+            running: 0,
+            log_bytes: 0,
+            process_ids: Vec::new(),
+        })
+        .collect();
+
+    for member in members {
+        let Some(quota) = workspace_of(quotas, member.cwd.as_deref()) else {
+            continue;
+        };
+        let Some(usage) = usages.iter_mut().find(|u| u.workspace == quota.path) else {
+            continue;
+        };
+        usage.processes += 1;
+        if member.running {
+            usage.running += 1;
+        }
+        usage.log_bytes += log_bytes.get(&member.id).copied().unwrap_or(0);
+        usage.process_ids.push(member.id.clone());
+    }
+    for usage in &mut usages {
+        usage.process_ids.sort();
+    }
+    usages
+}
+
+impl WorkspaceUsage {
+    /// プロセスを1つ追加で登録できるか
+    pub fn check_create(&self) -> Result<(), String> {
+        let max = self.quota.max_processes;
+        if max > 0 && self.processes >= max {
+            return Err(self.exceeded(format!(
+                "{} of {max} processes registered (max_processes)",
+                self.processes
+            )));
+        }
+        Ok(())
+    }
+
+    /// プロセスを1つ追加で起動できるか
+    pub fn check_start(&self) -> Result<(), String> {
+        let max = self.quota.max_running;
+        if max > 0 && self.running >= max {
+            return Err(self.exceeded(format!(
+                "{} of {max} processes running (max_running)",
+                self.running
+            )));
+        }
+        let max = self.quota.max_log_bytes;
+        if max > 0 && self.log_bytes >= max {
+            return Err(self.exceeded(format!(
+                "logs use {} of {max} bytes (max_log_bytes); prune or remove logs first",
+                self.log_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    fn exceeded(&self, detail: String) -> String {
+        format!(
+            "Workspace quota exceeded for '{}': {detail}",
+            self.workspace
+        )
+    }
+}
+
+fn same_path(a: &str, b: &str) -> bool {
+    Path::new(a) == Path::new(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(path: &str, max_processes: usize, max_running: usize) -> WorkspaceQuota {
+        WorkspaceQuota {
+            path: path.to_string(),
+            max_processes,
+            max_running,
+            max_log_bytes: 0,
+        }
+    }
+
+    fn member(id: &str, cwd: &str, running: bool) -> QuotaMember {
+        QuotaMember {
+            id: id.to_string(),
+            cwd: Some(PathBuf::from(cwd)),
+            running,
+        }
+    }
+
+    #[test]
+    fn test_deepest_workspace_wins() {
+        let quotas = [quota("/srv", 0, 0), quota("/srv/alice", 0, 0)];
+        let of = |cwd: &str| workspace_of(&quotas, Some(Path::new(cwd))).map(|q| q.path.as_str());
+        assert_eq!(of("/srv/alice/app"), Some("/srv/alice"));
+        assert_eq!(of("/srv/bob"), Some("/srv"));
+        // パスの途中が一致するだけでは属さない
+        assert_eq!(of("/srv/alice2"), Some("/srv"));
+        assert_eq!(of("/home/carol"), None);
+    }
+
+    #[test]
+    fn test_usage_and_checks() {
+        let quotas = [quota("/srv/alice", 2, 1)];
+        let members = [
+            member("web", "/srv/alice/web", true),
+            member("db", "/srv/alice", false),
+            member("other", "/srv/bob", true),
+        ];
+        let log_bytes = HashMap::from([("web".to_string(), 10), ("other".to_string(), 99)]);
+
+        let usage = usage(&quotas, &members, &log_bytes).remove(0);
+        assert_eq!(usage.processes, 2);
+        assert_eq!(usage.running, 1);
+        assert_eq!(usage.log_bytes, 10);
+        assert_eq!(usage.process_ids, vec!["db", "web"]);
+
+        let err = usage.check_create().unwrap_err();
+        assert!(err.contains("max_processes"), "{err}");
+        assert!(usage.check_start().unwrap_err().contains("max_running"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&[quota("relative/path", 1, 1)]).is_err());
+        assert!(validate(&[quota("/srv/a", 1, 1), quota("/srv/a/", 1, 1)]).is_err());
+        assert!(validate(&[quota("/srv/a", 1, 1), quota("/srv/b", 1, 1)]).is_ok());
+    }
+}
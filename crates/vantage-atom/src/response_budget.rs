@@ -0,0 +1,214 @@
+//! MCPツールの応答の大きさの調整
+//!
+//! コンテキストの小さいエージェントでも使えるように、すべてのツールの応答を上限に収めます。
+//! JSONの応答は配列を先頭の要素と件数の印に縮め、長い文字列を切り詰めます。それでも収まらない
+//! 場合やJSONでない応答は、先頭と末尾を残して中間を省きます。上限はサーバーの設定
+//! （`response_budget`）と、ツール呼び出しごとの引数 `response_budget` で指定できます。
+
+use rmcp::model::{CallToolResult, RawContent};
+use serde::Deserialize;
+use serde_json::Value;
+use vantage_persistence::ResponseBudget;
+
+/// ツール呼び出しごとに上限を指定する予約済みの引数名
+pub const ARGUMENT: &str = "response_budget";
+/// 切り詰めた文字列に残す最小の文字数
+const MIN_STRING_CHARS: usize = 80;
+
+/// ツール呼び出しごとの上限（指定のないものは設定の値を使う）
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct BudgetOverride {
+    pub max_chars: Option<usize>,
+    pub max_items: Option<usize>,
+}
+
+impl BudgetOverride {
+    /// 引数から予約済みの引数を取り除いて読み取る
+    pub fn take(
+        arguments: &mut Option<serde_json::Map<String, Value>>,
+    ) -> Result<Option<Self>, String> {
+        let Some(value) = arguments.as_mut().and_then(|args| args.remove(ARGUMENT)) else {
+            return Ok(None);
+        };
+        serde_json::from_value(value)
+            .map(Some)
+            .map_err(|e| format!("Invalid {ARGUMENT}: {e}"))
+    }
+
+    /// 設定の上限に重ねる
+    pub fn apply(self, budget: ResponseBudget) -> ResponseBudget {
+        ResponseBudget {
+            max_chars: self.max_chars.unwrap_or(budget.max_chars),
+            max_items: self.max_items.unwrap_or(budget.max_items),
+        }
+    }
+}
+
+/// 上限がないか
+pub fn is_unlimited(budget: &ResponseBudget) -> bool {
+    budget.max_chars == 0 && budget.max_items == 0
+}
+
+/// ツールの結果のテキストを上限に収める
+pub fn shape_result(result: &mut CallToolResult, budget: &ResponseBudget) {
+    if is_unlimited(budget) {
+        return;
+    }
+    for content in &mut result.content {
+        if let RawContent::Text(text) = &mut content.raw {
+            text.text = shape(&text.text, budget);
+        }
+    }
+}
+
+/// テキストを上限に収める
+pub fn shape(text: &str, budget: &ResponseBudget) -> String {
+    if is_unlimited(budget) {
+        return text.to_string();
+    }
+    let Ok(value) = serde_json::from_str::<Value>(text) else {
+        return truncate_text(text, budget.max_chars);
+    };
+    let fits =
+        |rendered: &str| budget.max_chars == 0 || rendered.chars().count() <= budget.max_chars;
+    if budget.max_items == 0 && fits(text) {
+        return text.to_string();
+    }
+
+    // 配列の要素数、文字列の長さの順に減らし、収まった時点で返す
+    let mut max_items = match budget.max_items {
+        0 => usize::MAX,
+        n => n,
+    };
+    let mut max_string = usize::MAX;
+    loop {
+        let rendered = serde_json::to_string_pretty(&summarize(&value, max_items, max_string))
+            .unwrap_or_default();
+        if fits(&rendered) {
+            return rendered;
+        }
+        if max_items > 1 {
+            max_items = (max_items.min(longest_array(&value)) / 2).max(1);
+        } else if max_string > MIN_STRING_CHARS {
+            max_string = (max_string.min(budget.max_chars) / 2).max(MIN_STRING_CHARS);
+        } else {
+            return truncate_text(&rendered, budget.max_chars);
+        }
+    }
+}
+
+/// 配列を先頭の `max_items` 件と省いた件数の印に、文字列を `max_string` 文字に縮める
+fn summarize(value: &Value, max_items: usize, max_string: usize) -> Value {
+    match value {
+        Value::Array(items) => {
+            let mut shaped: Vec<Value> = items
+                .iter()
+                .take(max_items)
+                .map(|item| summarize(item, max_items, max_string))
+                .collect();
+            if items.len() > max_items {
+                shaped.push(Value::String(format!(
+                    "... {} more item(s) omitted ({} total)",
+                    items.len() - max_items,
+                    items.len()
+                )));
+            }
+            Value::Array(shaped)
+        }
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), summarize(value, max_items, max_string)))
+                .collect(),
+        ),
+        Value::String(text) if text.chars().count() > max_string => {
+            let kept: String = text.chars().take(max_string).collect();
+            Value::String(format!(
+                "{kept}... [{} chars omitted]",
+                text.chars().count() - max_string
+            ))
+        }
+        other => other.clone(),
+    }
+}
+
+/// 最も長い配列の要素数
+fn longest_array(value: &Value) -> usize {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(longest_array)
+            .max()
+            .unwrap_or(0)
+            .max(items.len()),
+        Value::Object(map) => map.values().map(longest_array).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// 先頭と末尾を残して中間を省く
+fn truncate_text(text: &str, max_chars: usize) -> String {
+    let total = text.chars().count();
+    if max_chars == 0 || total <= max_chars {
+        return text.to_string();
+    }
+    let marker = |omitted: usize| {
+        format!("\n... [{omitted} chars omitted to fit the {max_chars}-char response budget] ...\n")
+    };
+    let available = max_chars.saturating_sub(marker(total).chars().count());
+    let head = available * 2 / 3;
+    let tail = available - head;
+    let start: String = text.chars().take(head).collect();
+    let end: String = text.chars().skip(total - tail).collect();
+    format!("{start}{}{end}", marker(total - head - tail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn budget(max_chars: usize, max_items: usize) -> ResponseBudget {
+        ResponseBudget {
+            max_chars,
+            max_items,
+        }
+    }
+
+    #[test]
+    fn test_json_arrays_are_summarized() {
+        let text = serde_json::to_string_pretty(&json!({
+            "processes": (0..50).map(|i| json!({"id": format!("p{i}")})).collect::<Vec<_>>(),
+        }))
+        .unwrap();
+
+        let shaped: Value = serde_json::from_str(&shape(&text, &budget(0, 3))).unwrap();
+        let processes = shaped["processes"].as_array().unwrap();
+        assert_eq!(processes.len(), 4);
+        assert_eq!(processes[3], "... 47 more item(s) omitted (50 total)");
+
+        let shaped = shape(&text, &budget(400, 0));
+        assert!(shaped.chars().count() <= 400, "{shaped}");
+        assert!(serde_json::from_str::<Value>(&shaped).is_ok());
+        assert!(shaped.contains("more item(s) omitted (50 total)"));
+
+        assert_eq!(shape(&text, &budget(0, 0)), text);
+    }
+
+    #[test]
+    fn test_plain_text_keeps_head_and_tail() {
+        let text = format!("first line\n{}\nlast line", "x".repeat(5000));
+        let shaped = shape(&text, &budget(300, 0));
+        assert!(shaped.chars().count() <= 300);
+        assert!(shaped.starts_with("first line"));
+        assert!(shaped.ends_with("last line"));
+        assert!(shaped.contains("chars omitted"));
+
+        let mut arguments = Some(serde_json::Map::from_iter([
+            ("id".to_string(), json!("web")),
+            (ARGUMENT.to_string(), json!({"max_chars": 1000})),
+        ]));
+        let request = BudgetOverride::take(&mut arguments).unwrap().unwrap();
+        assert!(!arguments.unwrap().contains_key(ARGUMENT));
+        assert_eq!(request.apply(budget(0, 20)), budget(1000, 20));
+    }
+}
@@ -1,3 +1,9 @@
+pub mod paths;
+pub mod permissions;
+pub mod private_file;
+pub mod secret_refs;
+pub mod sensitive_env;
+
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
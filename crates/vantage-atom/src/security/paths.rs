@@ -0,0 +1,261 @@
+//! パス入力の展開と書き出し・読み込み先の制限
+//!
+//! ツールやWeb APIで受け取ったパスの先頭の `~` と、`$VAR` / `${VAR}` の環境変数を展開します。
+//! シェルは通さないので、コマンド置換（`$(...)`、`` `...` ``）やワイルドカードは展開されません。
+//!
+//! エクスポート・インポート・スナップショットなど、サーバーが読み書きするファイルは
+//! [`resolve_file_path`] で許可された場所に限ります:
+//!
+//! - ホームディレクトリ（`~/.ssh` などの認証情報のディレクトリを除く）
+//! - 一時ディレクトリ
+//! - サーバーのカレントディレクトリ
+//! - 環境変数 `VANTAGE_ALLOWED_PATHS` にパス区切り（Unixでは `:`）で並べたディレクトリ
+//!
+//! シンボリックリンクは解決してから判定するので、許可された場所のリンク経由で外に出ることはできません。
+
+use std::path::{Component, Path, PathBuf};
+
+/// 読み書きを許可するディレクトリを追加する環境変数
+pub const ALLOWED_PATHS_ENV: &str = "VANTAGE_ALLOWED_PATHS";
+
+/// ホームディレクトリの中でも読み書きを許可しないディレクトリ
+const DENIED_HOME_DIRS: &[&str] = &[".ssh", ".gnupg", ".aws", ".kube", ".docker"];
+
+/// 先頭の `~` と `$VAR` / `${VAR}` を展開する
+///
+/// `~user` の形式と、未設定の環境変数はエラーにする。
+pub fn expand(input: &str) -> Result<PathBuf, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Path must not be empty".to_string());
+    }
+    if input.contains("$(") || input.contains('`') {
+        return Err(format!(
+            "Command substitution is not allowed in paths: '{input}'"
+        ));
+    }
+
+    let rest = match input.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\') => {
+            let home = dirs::home_dir()
+                .ok_or_else(|| "Cannot expand '~': the home directory is unknown".to_string())?;
+            format!("{}{rest}", home.display())
+        }
+        Some(_) => {
+            return Err(format!(
+                "'~user' paths are not supported: '{input}'; use an absolute path"
+            ));
+        }
+        None => input.to_string(),
+    };
+
+    let mut expanded = String::with_capacity(rest.len());
+    let mut chars = rest.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+        let (name, braced) = match chars.peek() {
+            Some((_, '{')) => {
+                let end = rest[i + 2..]
+                    .find('}')
+                    .ok_or_else(|| format!("Unclosed '${{' in path: '{input}'"))?;
+                (&rest[i + 2..i + 2 + end], true)
+            }
+            _ => {
+                let len = rest[i + 1..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len() - i - 1);
+                (&rest[i + 1..i + 1 + len], false)
+            }
+        };
+        if name.is_empty() && !braced {
+            // 変数名が続かない `$` はそのまま
+            expanded.push('$');
+            continue;
+        }
+        if !is_variable_name(name) {
+            return Err(format!(
+                "Invalid environment variable name '{name}' in path: '{input}'"
+            ));
+        }
+        let value = std::env::var(name)
+            .map_err(|_| format!("Environment variable '{name}' in path '{input}' is not set"))?;
+        expanded.push_str(&value);
+        let consumed = name.len() + if braced { 2 } else { 0 };
+        for _ in 0..consumed {
+            chars.next();
+        }
+    }
+    Ok(PathBuf::from(expanded))
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// パスを展開する（UTF-8でないパスはそのまま）
+pub fn expand_path(path: &Path) -> Result<PathBuf, String> {
+    match path.to_str() {
+        Some(input) => expand(input),
+        None => Ok(path.to_path_buf()),
+    }
+}
+
+/// サーバーが読み書きするファイルのパスを展開し、許可された場所か確かめる
+///
+/// 相対パスはサーバーのカレントディレクトリを基準にする。返すパスは絶対パス。
+pub fn resolve_file_path(input: &str) -> Result<PathBuf, String> {
+    let expanded = expand(input)?;
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        std::env::current_dir()
+            .map_err(|e| format!("Failed to resolve '{input}': {e}"))?
+            .join(expanded)
+    };
+    let resolved = resolve_symlinks(&normalize(&absolute));
+
+    let home = dirs::home_dir().map(|home| canonical(&home));
+    if let Some(home) = &home
+        && let Ok(relative) = resolved.strip_prefix(home)
+        && let Some(Component::Normal(first)) = relative.components().next()
+        && DENIED_HOME_DIRS.iter().any(|d| first == *d)
+    {
+        return Err(format!(
+            "Access to '{}' is not allowed: it holds credentials",
+            resolved.display()
+        ));
+    }
+
+    let allowed = allowed_roots(home);
+    if allowed.iter().any(|root| resolved.starts_with(root)) {
+        return Ok(resolved);
+    }
+    Err(format!(
+        "Access to '{}' is not allowed. Files can be read and written under: {} (add directories with {ALLOWED_PATHS_ENV})",
+        resolved.display(),
+        allowed
+            .iter()
+            .map(|root| root.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+/// 読み書きを許可するディレクトリ
+fn allowed_roots(home: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = home.into_iter().collect();
+    roots.push(canonical(&std::env::temp_dir()));
+    roots.push(canonical(Path::new("/tmp")));
+    if let Ok(current) = std::env::current_dir() {
+        roots.push(canonical(&current));
+    }
+    if let Some(extra) = std::env::var_os(ALLOWED_PATHS_ENV) {
+        roots.extend(
+            std::env::split_paths(&extra)
+                .filter(|p| p.is_absolute())
+                .map(|p| canonical(&p)),
+        );
+    }
+    // ルート自体を許可するとすべてを許可してしまう
+    roots.retain(|root| root.parent().is_some());
+    roots.dedup();
+    roots
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// `.` と `..` を取り除く（ファイルシステムは参照しない）
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// 存在する最も深い祖先のシンボリックリンクを解決し、残りを付け足す
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return rest.iter().rev().fold(resolved, |acc, part| acc.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_tilde_and_environment_variables() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand("~").unwrap(), home);
+        assert_eq!(
+            expand("~/exports/a.yaml").unwrap(),
+            home.join("exports/a.yaml")
+        );
+        assert_eq!(
+            expand("$HOME/a.yaml").unwrap(),
+            PathBuf::from(format!("{}/a.yaml", std::env::var("HOME").unwrap()))
+        );
+        assert_eq!(
+            expand("${HOME}x/a").unwrap(),
+            PathBuf::from(format!("{}x/a", std::env::var("HOME").unwrap()))
+        );
+        assert_eq!(expand("/tmp/cost$").unwrap(), PathBuf::from("/tmp/cost$"));
+
+        assert!(expand("~root/a").is_err());
+        assert!(expand("$VANTAGE_SURELY_UNSET_VARIABLE/a").is_err());
+        assert!(expand("/tmp/$(whoami)").is_err());
+        assert!(expand("${HOME").is_err());
+        assert!(expand("  ").is_err());
+    }
+
+    #[test]
+    fn test_resolve_file_path_allow_rules() {
+        let temp = canonical(&std::env::temp_dir());
+        assert_eq!(
+            resolve_file_path(&format!("{}/exports/../snap.yaml", temp.display())).unwrap(),
+            temp.join("snap.yaml")
+        );
+        assert!(resolve_file_path("~/.vantage/snapshot.yaml").is_ok());
+
+        let err = resolve_file_path("/etc/vantage.yaml").unwrap_err();
+        assert!(err.contains(ALLOWED_PATHS_ENV), "{err}");
+        assert!(resolve_file_path(&format!("{}/../../etc/passwd", temp.display())).is_err());
+        assert!(resolve_file_path("~/.ssh/id_ed25519").is_err());
+
+        // 許可された場所のリンク経由で外に出られない
+        #[cfg(unix)]
+        {
+            let dir = tempfile::tempdir().unwrap();
+            let link = dir.path().join("etc-link");
+            std::os::unix::fs::symlink("/etc", &link).unwrap();
+            assert!(resolve_file_path(&link.join("hosts").to_string_lossy()).is_err());
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! 機能ごとのアクセス権の判定
+//!
+//! Webコンソールはログインしたユーザーのロールから、MCPクライアントは `mcp_permissions` から
+//! アクセス権を決めます（どちらも設定ディレクトリの auth.yaml で上書きできます）。
+//! 必要なアクセス権は、Web APIではメソッドとパスから、MCPではツール名から引きます。
+
+use crate::process::capture::{is_secret_key, looks_like_secret_value};
+use crate::process::debug_bundle::redact_json;
+use axum::http::Method;
+use serde::Serialize;
+use std::collections::HashMap;
+use vantage_persistence::{Access, Capability, Permissions};
+
+/// 必要なアクセス権
+pub type Requirement = (Capability, Access);
+
+const CLIPBOARD_READ: Requirement = (Capability::Clipboard, Access::Read);
+const CLIPBOARD_WRITE: Requirement = (Capability::Clipboard, Access::Write);
+const TEMPLATES_READ: Requirement = (Capability::Templates, Access::Read);
+const TEMPLATES_WRITE: Requirement = (Capability::Templates, Access::Write);
+const PROCESSES_READ: Requirement = (Capability::Processes, Access::Read);
+const PROCESSES_WRITE: Requirement = (Capability::Processes, Access::Write);
+const SECRETS_READ: Requirement = (Capability::Secrets, Access::Read);
+const SERVER_WRITE: Requirement = (Capability::Server, Access::Write);
+
+/// Web APIのリクエストに必要なアクセス権
+///
+/// 機能に属さないパス（設定・承認など）は空とし、ロールで判定する。ここに載っていない `/api/`
+/// のパスはNoneとし、リクエストを拒否する（新しいAPIの登録漏れで誰でも呼べるようにならないため）。
+pub fn route_requirements(method: &Method, path: &str) -> Option<Vec<Requirement>> {
+    let Some(path) = path.strip_prefix("/api/") else {
+        return Some(Vec::new());
+    };
+    let access = if method == Method::GET {
+        Access::Read
+    } else {
+        Access::Write
+    };
+    let first = path.split('/').next().unwrap_or_default();
+    Some(match first {
+        "clipboard" => vec![(Capability::Clipboard, access)],
+        "templates" if path.ends_with("/instantiate") => vec![TEMPLATES_READ, PROCESSES_WRITE],
+        "templates" => vec![(Capability::Templates, access)],
+        "processes" if path.ends_with("/template") && method != Method::GET => {
+            vec![PROCESSES_READ, TEMPLATES_WRITE]
+        }
+        // 定期実行はプロセスを起動するため、プロセスと同じアクセス権で判定する
+        "processes" | "dashboard" | "chains" | "schedules" => {
+            vec![(Capability::Processes, access)]
+        }
+        // プロセスの数や出力・ログの使用量を含む（MCPの get_status・get_diagnostics と同じ）
+        "status" | "diagnostics" => vec![PROCESSES_READ],
+        // 認証はWebhookごとのトークンで行い、ここではスナップショットの間に待たせるためだけに使う
+        "hooks" => vec![PROCESSES_WRITE],
+        // 既読にする操作も閲覧の一部として扱う
+        "notifications" => vec![PROCESSES_READ],
+        // ロールで判定する（ゲートはゲートごとのトークンで認証する）
+        "settings" | "approvals" | "auth" | "gate" | "openapi.json" => Vec::new(),
+        _ => return None,
+    })
+}
+
+/// Web APIのリクエストを拒否する理由（アクセス権で判定するならNone）
+pub fn route_denied(permissions: &Permissions, method: &Method, path: &str) -> Option<String> {
+    match route_requirements(method, path) {
+        Some(requirements) => missing(permissions, &requirements).map(denied_message),
+        None => Some(format!(
+            "Permission denied: no access rule is defined for {method} {path}"
+        )),
+    }
+}
+
+/// MCPツールに必要なアクセス権
+///
+/// ここに載っていないツールはNoneとし、呼び出しを拒否する（新しいツールの登録漏れで
+/// 誰でも呼べるようにならないため）。
+pub fn tool_requirements(name: &str) -> Option<&'static [Requirement]> {
+    Some(match name {
+        // 接続の確認のみで、機能に属さない
+        "ping" | "echo" => &[],
+        "get_clipboard" => &[CLIPBOARD_READ],
+        "set_clipboard_text" => &[CLIPBOARD_WRITE],
+        "list_templates" | "get_template" | "render_template" => &[TEMPLATES_READ],
+        "create_template" | "update_template" | "delete_template" => &[TEMPLATES_WRITE],
+        "capture_template" => &[PROCESSES_READ, TEMPLATES_WRITE],
+        "create_process_from_template" | "create_processes_from_template_matrix" => {
+            &[TEMPLATES_READ, PROCESSES_WRITE]
+        }
+        "get_process_status"
+        | "get_group_status"
+        | "get_process_metrics"
+        | "get_process_output"
+        | "list_output_bookmarks"
+        | "list_output_recordings"
+        | "replay_output"
+        | "get_process_phases"
+        | "get_process_timeline"
+        | "get_process_network"
+        | "diff_process_env"
+        | "explain_run_environment"
+        | "diff_output_windows"
+        | "list_processes"
+        | "get_events"
+        | "list_tunnels"
+        | "list_kubernetes_workloads"
+        | "get_resource_overview"
+        | "get_settings_schema"
+        | "get_setting"
+        | "get_operation_status"
+        | "diagnose_process"
+        | "list_diagnosis_rules"
+        | "list_chains"
+        | "list_schedules"
+        | "chaos_status"
+        | "list_maintenance_windows"
+        | "list_pre_op_snapshots"
+        | "list_recycle_bin"
+        | "list_changes"
+        | "list_contexts"
+        | "list_notifications"
+        | "mark_read"
+        | "test_notification_route"
+        | "get_assistant_analytics"
+        | "export_history"
+        | "get_approval"
+        | "get_status"
+        | "get_startup_report"
+        | "show_paths"
+        | "open_web_console"
+        | "discover_tasks"
+        | "get_suggestions"
+        | "dismiss_suggestion"
+        | "watch_suggestions"
+        | "unwatch_suggestions"
+        | "list_suggestion_watches"
+        | "list_suggestion_providers"
+        | "list_ci_runs"
+        | "get_ci_run_details"
+        | "get_ci_failed_logs"
+        | "wait_for_ci_completion"
+        | "start_ci_monitoring" => &[PROCESSES_READ],
+        // すべてのプロセスの出力・イベントを含む（シークレットらしき値は伏せる）
+        "get_diagnostics" | "create_debug_bundle" => &[PROCESSES_READ],
+        // 書き出したファイルには環境変数の値がそのまま含まれる
+        "export_processes" | "create_snapshot" | "export_yaml" | "create_formatted_snapshot" => {
+            &[PROCESSES_READ, SECRETS_READ]
+        }
+        // シークレットは書き出さない
+        "export_workspace" => &[PROCESSES_READ, TEMPLATES_READ],
+        "import_vscode_tasks" | "import_workspace" => &[PROCESSES_WRITE, TEMPLATES_WRITE],
+        // 取り消す変更はプロセスとテンプレートのどちらにもなりうる
+        "undo_last_change" | "redo_change" => &[PROCESSES_WRITE, TEMPLATES_WRITE],
+        "create_process"
+        | "start_process"
+        | "start_process_with_params"
+        | "stop_process"
+        | "restart_process"
+        | "start_processes"
+        | "stop_processes"
+        | "start_group"
+        | "stop_group"
+        | "remove_processes"
+        | "cleanup_processes"
+        | "restore_from_recycle_bin"
+        | "empty_recycle_bin"
+        | "pause_process"
+        | "resume_process"
+        | "rolling_restart"
+        | "set_lazy_start"
+        | "remove_process"
+        | "update_process"
+        | "update_process_config"
+        | "execute_in_repl"
+        | "send_process_input"
+        | "set_output_sampling"
+        | "add_output_bookmark"
+        | "remove_output_bookmark"
+        | "set_log_sinks"
+        | "set_redaction_rules"
+        | "set_runbook"
+        | "set_env_loader"
+        | "set_output_recording"
+        | "set_process_assertions"
+        | "run_checks"
+        | "set_energy_cost"
+        | "set_setting"
+        | "confirm_diagnosis_rule"
+        | "chaos_kill"
+        | "chaos_pause"
+        | "chaos_schedule_pauses"
+        | "chaos_cancel_schedule"
+        | "create_tunnel"
+        | "start_tunnel"
+        | "stop_tunnel"
+        | "remove_tunnel"
+        | "register_kubernetes_workload"
+        | "remove_kubernetes_workload"
+        | "create_chain"
+        | "set_chain_enabled"
+        | "remove_chain"
+        | "create_schedule"
+        | "update_schedule"
+        | "remove_schedule"
+        | "create_maintenance_window"
+        | "remove_maintenance_window"
+        | "import_processes"
+        | "restore_snapshot"
+        | "import_yaml"
+        | "undo_last_operation"
+        | "safe_mode_restore"
+        | "scan_external_processes"
+        | "set_auto_adopt"
+        | "save_context"
+        | "restore_context"
+        | "delete_context"
+        | "execute_suggested_action"
+        | "apply_suggestion"
+        | "configure_suggestion_provider" => &[PROCESSES_WRITE],
+        "restart_web_server" | "rotate_server_log" | "clear_server_caches" | "restart_server" => {
+            &[SERVER_WRITE]
+        }
+        _ => return None,
+    })
+}
+
+/// ツールを呼べない理由（呼べればNone）
+pub fn tool_denied(permissions: &Permissions, name: &str) -> Option<String> {
+    match tool_requirements(name) {
+        Some(requirements) => missing(permissions, requirements).map(denied_message),
+        None => Some(format!(
+            "Permission denied: no access rule is defined for tool '{name}'"
+        )),
+    }
+}
+
+/// プロセスを変更する操作か（スナップショット・復元の間は待たせる）
+pub fn mutates_processes(requirements: &[Requirement]) -> bool {
+    requirements.contains(&PROCESSES_WRITE)
+}
+
+/// 満たしていないアクセス権（すべて満たしていればNone）
+pub fn missing(permissions: &Permissions, requirements: &[Requirement]) -> Option<Requirement> {
+    requirements
+        .iter()
+        .copied()
+        .find(|(capability, access)| !permissions.allows(*capability, *access))
+}
+
+/// アクセス権が不足しているときのメッセージ
+pub fn denied_message((capability, access): Requirement) -> String {
+    let access = match access {
+        Access::None => "no",
+        Access::Read => "read",
+        Access::Write => "write",
+    };
+    let capability = match capability {
+        Capability::Clipboard => "clipboard",
+        Capability::Templates => "templates",
+        Capability::Processes => "processes",
+        Capability::Secrets => "secrets",
+        Capability::Server => "server",
+    };
+    format!("Permission denied: requires {access} access to {capability}")
+}
+
+/// JSONに変換し、シークレットの閲覧権がなければシークレットらしき値を伏せる
+///
+/// `permissions` がNone（認証なし）の場合は伏せない。
+pub fn mask_secrets<T: Serialize>(
+    permissions: Option<&Permissions>,
+    value: &T,
+) -> serde_json::Value {
+    let mut value = serde_json::to_value(value).unwrap_or_default();
+    if permissions.is_some_and(|p| !p.allows(Capability::Secrets, Access::Read)) {
+        redact_json(&mut value);
+    }
+    value
+}
+
+/// シークレットを含む環境変数を設定できるかを確認（機密に指定した名前があればシークレットとみなす）
+pub fn check_secret_env(
+    permissions: &Permissions,
+    env: &HashMap<String, String>,
+    sensitive: &[String],
+) -> Result<(), String> {
+    let has_secret = !sensitive.is_empty()
+        || env
+            .iter()
+            .any(|(key, value)| is_secret_key(key) || looks_like_secret_value(value));
+    if has_secret && !permissions.allows(Capability::Secrets, Access::Write) {
+        return Err(denied_message((Capability::Secrets, Access::Write)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vantage_persistence::Role;
+
+    #[test]
+    fn test_route_requirements() {
+        let route_requirements =
+            |method: &Method, path: &str| super::route_requirements(method, path).unwrap();
+        assert_eq!(
+            route_requirements(&Method::GET, "/api/clipboard/history"),
+            vec![CLIPBOARD_READ]
+        );
+        assert_eq!(
+            route_requirements(&Method::POST, "/api/clipboard/text"),
+            vec![CLIPBOARD_WRITE]
+        );
+        assert_eq!(
+            route_requirements(&Method::POST, "/api/templates/web/instantiate"),
+            vec![TEMPLATES_READ, PROCESSES_WRITE]
+        );
+        assert_eq!(
+            route_requirements(&Method::POST, "/api/processes/web/template"),
+            vec![PROCESSES_READ, TEMPLATES_WRITE]
+        );
+        assert_eq!(
+            route_requirements(&Method::PATCH, "/api/schedules/nightly"),
+            vec![PROCESSES_WRITE]
+        );
+        assert!(mutates_processes(&route_requirements(
+            &Method::DELETE,
+            "/api/schedules/nightly"
+        )));
+        assert!(route_requirements(&Method::PUT, "/api/settings").is_empty());
+        assert_eq!(
+            route_requirements(&Method::GET, "/api/diagnostics"),
+            vec![PROCESSES_READ]
+        );
+    }
+
+    #[test]
+    fn test_every_api_route_has_an_access_rule() {
+        use utoipa::OpenApi;
+        let unlisted: Vec<_> = crate::web::openapi::ApiDoc::openapi()
+            .paths
+            .paths
+            .keys()
+            .map(|path| format!("/api{path}"))
+            .filter(|path| super::route_requirements(&Method::GET, path).is_none())
+            .collect();
+        assert!(
+            unlisted.is_empty(),
+            "API routes without an access rule: {unlisted:?}"
+        );
+
+        let all = Permissions::all(Access::Write);
+        assert!(route_denied(&all, &Method::GET, "/api/unknown").is_some());
+        assert!(route_denied(&all, &Method::GET, "/api/openapi.json").is_none());
+        assert!(route_denied(&all, &Method::GET, "/").is_none());
+    }
+
+    #[test]
+    fn test_every_tool_has_an_access_rule() {
+        let unlisted: Vec<_> = crate::VantageServer::tool_router()
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name)
+            .filter(|name| tool_requirements(name).is_none())
+            .collect();
+        assert!(
+            unlisted.is_empty(),
+            "Tools without an access rule: {unlisted:?}"
+        );
+
+        let none = Permissions::all(Access::None);
+        assert!(tool_denied(&none, "create_debug_bundle").is_some());
+    }
+
+    #[test]
+    fn test_role_defaults() {
+        let viewer = Permissions::for_role(Role::Viewer);
+        assert_eq!(missing(&viewer, &[CLIPBOARD_READ]), None);
+        assert_eq!(missing(&viewer, &[CLIPBOARD_WRITE]), Some(CLIPBOARD_WRITE));
+        assert_eq!(
+            missing(&viewer, tool_requirements("export_yaml").unwrap()),
+            Some(SECRETS_READ)
+        );
+        assert!(tool_denied(&Permissions::all(Access::Write), "unknown_tool").is_some());
+
+        let env = HashMap::from([("API_TOKEN".to_string(), "abc".to_string())]);
+        assert!(check_secret_env(&viewer, &env, &[]).is_err());
+        assert!(check_secret_env(&Permissions::for_role(Role::Operator), &env, &[]).is_ok());
+        let plain = HashMap::from([("MODE".to_string(), "dev".to_string())]);
+        assert!(check_secret_env(&viewer, &plain, &[]).is_ok());
+        assert!(check_secret_env(&viewer, &plain, &["MODE".to_string()]).is_err());
+
+        let masked = mask_secrets(Some(&viewer), &env);
+        assert_eq!(masked["API_TOKEN"], "********");
+        assert_eq!(mask_secrets(None, &env)["API_TOKEN"], "abc");
+    }
+}
@@ -0,0 +1,59 @@
+//! 所有者だけが読み書きできるファイルの書き出し
+//!
+//! 秘密鍵や暗号化の鍵は、書き出してから権限を変えると、その間だけ他のユーザーから読める
+//! 状態になります。新しいファイルは最初から0600で作り、既存のファイルは中身を書く前に
+//! 権限を0600に戻します。
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// `contents` を所有者だけが読み書きできるファイルとして書き出す（Unix以外では通常の書き出し）
+pub fn write(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // 作成時の権限は既存のファイルには効かないため、書く前に戻す
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+fn open(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_restricts_new_and_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key.pem");
+        std::fs::write(&path, "old").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        }
+
+        write(&path, b"secret").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "secret");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
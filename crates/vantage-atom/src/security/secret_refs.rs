@@ -0,0 +1,403 @@
+//! 外部のシークレット管理にある環境変数の値
+//!
+//! 環境変数の値に `op://<vault>/<item>/<field>`（1Password CLI）や `vault://<path>#<field>`
+//! （HashiCorp Vault）を書くと、プロセスを起動するときにそれぞれのCLIで値を取り出して置き換えます。
+//! 取り出した値はメモリ上に `CACHE_TTL` の間だけ保持し、定義・実行環境の記録・スナップショットには
+//! 参照のまま残します。
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use utoipa::ToSchema;
+
+/// 取り出した値をキャッシュする時間
+pub const CACHE_TTL: Duration = Duration::from_secs(60);
+/// CLIを待つ時間
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+static RESOLVER: LazyLock<SecretResolver> = LazyLock::new(SecretResolver::new);
+
+/// プロセス間で共有するリゾルバー（キャッシュと状態はサーバーのメモリ上にある）
+pub fn resolver() -> &'static SecretResolver {
+    &RESOLVER
+}
+
+/// シークレットの取り出し元
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretProvider {
+    OnePassword,
+    Vault,
+}
+
+impl SecretProvider {
+    pub const ALL: [Self; 2] = [Self::OnePassword, Self::Vault];
+
+    /// 値を取り出すCLI
+    pub fn cli(self) -> &'static str {
+        match self {
+            Self::OnePassword => "op",
+            Self::Vault => "vault",
+        }
+    }
+}
+
+/// 環境変数の値に書かれたシークレットの参照
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretRef {
+    /// `op://<vault>/<item>/[<section>/]<field>`
+    OnePassword { uri: String },
+    /// `vault://<path>#<field>`（KVシークレットのフィールド）
+    Vault { path: String, field: String },
+}
+
+impl SecretRef {
+    /// 値が参照ならパースする（参照でなければNone、参照の形が誤っていればエラー）
+    pub fn parse(value: &str) -> Option<Result<Self, String>> {
+        if let Some(rest) = value.strip_prefix("op://") {
+            let segments: Vec<&str> = rest.split('/').collect();
+            if !(3..=4).contains(&segments.len()) || segments.iter().any(|s| s.trim().is_empty()) {
+                return Some(Err(format!(
+                    "'{value}' must look like op://<vault>/<item>/<field>"
+                )));
+            }
+            return Some(Ok(Self::OnePassword {
+                uri: value.to_string(),
+            }));
+        }
+        let rest = value.strip_prefix("vault://")?;
+        Some(match rest.split_once('#') {
+            Some((path, field)) if !path.trim().is_empty() && !field.trim().is_empty() => {
+                Ok(Self::Vault {
+                    path: path.to_string(),
+                    field: field.to_string(),
+                })
+            }
+            _ => Err(format!("'{value}' must look like vault://<path>#<field>")),
+        })
+    }
+
+    pub fn provider(&self) -> SecretProvider {
+        match self {
+            Self::OnePassword { .. } => SecretProvider::OnePassword,
+            Self::Vault { .. } => SecretProvider::Vault,
+        }
+    }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            Self::OnePassword { uri } => {
+                vec!["read".to_string(), "--no-newline".to_string(), uri.clone()]
+            }
+            Self::Vault { path, field } => vec![
+                "kv".to_string(),
+                "get".to_string(),
+                format!("-field={field}"),
+                path.clone(),
+            ],
+        }
+    }
+}
+
+/// 環境変数に書かれた参照の形を検証する
+pub fn validate(env: &HashMap<String, String>) -> Result<(), String> {
+    for (name, value) in env {
+        if let Some(Err(e)) = SecretRef::parse(value) {
+            return Err(format!("env {name}: {e}"));
+        }
+    }
+    Ok(())
+}
+
+/// 取り出し元の状態（診断情報に表示する）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SecretProviderHealth {
+    pub provider: SecretProvider,
+    pub cli: String,
+    /// CLIがPATHにあるか
+    pub installed: bool,
+    /// CLIで取り出した回数
+    pub resolved: u64,
+    /// キャッシュから返した回数
+    pub cache_hits: u64,
+    pub failures: u64,
+    /// キャッシュしている値の数
+    pub cached: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+struct Cached {
+    provider: SecretProvider,
+    value: String,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct Stats {
+    resolved: u64,
+    cache_hits: u64,
+    failures: u64,
+    last_success_at: Option<DateTime<Utc>>,
+    last_error: Option<(String, DateTime<Utc>)>,
+}
+
+/// 参照をCLIで値に置き換え、短い間キャッシュする
+pub struct SecretResolver {
+    programs: HashMap<SecretProvider, String>,
+    cache: Mutex<HashMap<String, Cached>>,
+    stats: Mutex<HashMap<SecretProvider, Stats>>,
+}
+
+impl Default for SecretResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretResolver {
+    pub fn new() -> Self {
+        Self::with_programs(
+            SecretProvider::ALL
+                .into_iter()
+                .map(|provider| (provider, provider.cli().to_string()))
+                .collect(),
+        )
+    }
+
+    /// 取り出し元ごとに実行するプログラムを指定する
+    pub fn with_programs(programs: HashMap<SecretProvider, String>) -> Self {
+        Self {
+            programs,
+            cache: Mutex::new(HashMap::new()),
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 参照になっている値を取り出した値に置き換え、置き換えた変数名を返す
+    pub async fn resolve_env(
+        &self,
+        env: &mut HashMap<String, String>,
+    ) -> Result<Vec<String>, String> {
+        let mut names = Vec::new();
+        for (name, value) in env.iter_mut() {
+            let Some(reference) = SecretRef::parse(value) else {
+                continue;
+            };
+            let reference = reference.map_err(|e| format!("env {name}: {e}"))?;
+            *value = self
+                .resolve(value, &reference)
+                .await
+                .map_err(|e| format!("Failed to resolve env {name}: {e}"))?;
+            names.push(name.clone());
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    async fn resolve(&self, key: &str, reference: &SecretRef) -> Result<String, String> {
+        let provider = reference.provider();
+        let cached = {
+            let mut cache = self.cache.lock().unwrap();
+            cache.retain(|_, cached| cached.expires_at > Instant::now());
+            cache.get(key).map(|cached| cached.value.clone())
+        };
+        if let Some(value) = cached {
+            self.stats
+                .lock()
+                .unwrap()
+                .entry(provider)
+                .or_default()
+                .cache_hits += 1;
+            return Ok(value);
+        }
+
+        let result = self.fetch(reference).await;
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats.entry(provider).or_default();
+        match &result {
+            Ok(value) => {
+                stats.resolved += 1;
+                stats.last_success_at = Some(Utc::now());
+                self.cache.lock().unwrap().insert(
+                    key.to_string(),
+                    Cached {
+                        provider,
+                        value: value.clone(),
+                        expires_at: Instant::now() + CACHE_TTL,
+                    },
+                );
+            }
+            Err(e) => {
+                stats.failures += 1;
+                stats.last_error = Some((e.clone(), Utc::now()));
+            }
+        }
+        result
+    }
+
+    async fn fetch(&self, reference: &SecretRef) -> Result<String, String> {
+        let provider = reference.provider();
+        let program = self
+            .programs
+            .get(&provider)
+            .map(String::as_str)
+            .unwrap_or(provider.cli());
+        let output = Command::new(program)
+            .args(reference.args())
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = tokio::time::timeout(FETCH_TIMEOUT, output)
+            .await
+            .map_err(|_| format!("{program} did not finish within {FETCH_TIMEOUT:?}"))?
+            .map_err(|e| format!("Failed to run {program}: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "{program} exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let mut value = String::from_utf8(output.stdout)
+            .map_err(|_| format!("{program} returned a value that is not UTF-8"))?;
+        if value.ends_with('\n') {
+            value.pop();
+            if value.ends_with('\r') {
+                value.pop();
+            }
+        }
+        Ok(value)
+    }
+
+    /// 取り出し元ごとの状態
+    pub fn health(&self) -> Vec<SecretProviderHealth> {
+        let path = std::env::var("PATH").ok();
+        let now = Instant::now();
+        let stats = self.stats.lock().unwrap();
+        let cache = self.cache.lock().unwrap();
+        SecretProvider::ALL
+            .into_iter()
+            .map(|provider| {
+                let cli = self
+                    .programs
+                    .get(&provider)
+                    .cloned()
+                    .unwrap_or_else(|| provider.cli().to_string());
+                let stats = stats.get(&provider);
+                SecretProviderHealth {
+                    provider,
+                    installed: crate::process::run_environment::which(&cli, path.as_deref(), None)
+                        .is_some(),
+                    cli,
+                    resolved: stats.map_or(0, |s| s.resolved),
+                    cache_hits: stats.map_or(0, |s| s.cache_hits),
+                    failures: stats.map_or(0, |s| s.failures),
+                    cached: cache
+                        .values()
+                        .filter(|cached| cached.provider == provider && cached.expires_at > now)
+                        .count(),
+                    last_success_at: stats.and_then(|s| s.last_success_at),
+                    last_error: stats.and_then(|s| s.last_error.as_ref().map(|(e, _)| e.clone())),
+                    last_error_at: stats.and_then(|s| s.last_error.as_ref().map(|(_, at)| *at)),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_references() {
+        assert_eq!(
+            SecretRef::parse("op://dev/stripe/secret key"),
+            Some(Ok(SecretRef::OnePassword {
+                uri: "op://dev/stripe/secret key".to_string()
+            }))
+        );
+        assert_eq!(
+            SecretRef::parse("vault://secret/app#db_password"),
+            Some(Ok(SecretRef::Vault {
+                path: "secret/app".to_string(),
+                field: "db_password".to_string(),
+            }))
+        );
+        assert_eq!(SecretRef::parse("postgres://localhost/app"), None);
+        assert!(matches!(SecretRef::parse("op://dev/stripe"), Some(Err(_))));
+        assert!(matches!(
+            SecretRef::parse("vault://secret/app"),
+            Some(Err(_))
+        ));
+        assert!(
+            validate(&HashMap::from([(
+                "TOKEN".to_string(),
+                "op://dev//token".to_string()
+            )]))
+            .is_err()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_resolve_env_caches_values() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // 呼ばれた回数を記録して固定の値を返す偽のCLI
+        let dir = tempfile::tempdir().unwrap();
+        let calls = dir.path().join("calls");
+        let script = dir.path().join("fake-op");
+        std::fs::write(
+            &script,
+            format!("#!/bin/sh\necho x >> {}\necho s3cret\n", calls.display()),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let resolver = SecretResolver::with_programs(HashMap::from([
+            (
+                SecretProvider::OnePassword,
+                script.to_string_lossy().to_string(),
+            ),
+            (
+                SecretProvider::Vault,
+                dir.path().join("missing").to_string_lossy().to_string(),
+            ),
+        ]));
+
+        let mut env = HashMap::from([
+            ("TOKEN".to_string(), "op://dev/api/token".to_string()),
+            ("PORT".to_string(), "3000".to_string()),
+        ]);
+        let names = resolver.resolve_env(&mut env.clone()).await.unwrap();
+        assert_eq!(names, vec!["TOKEN"]);
+        resolver.resolve_env(&mut env).await.unwrap();
+        assert_eq!(env["TOKEN"], "s3cret");
+        assert_eq!(env["PORT"], "3000");
+        // 2回目はキャッシュから返す
+        assert_eq!(std::fs::read_to_string(&calls).unwrap().lines().count(), 1);
+
+        let mut env = HashMap::from([(
+            "DB_PASSWORD".to_string(),
+            "vault://secret/app#password".to_string(),
+        )]);
+        let error = resolver.resolve_env(&mut env).await.unwrap_err();
+        assert!(error.contains("DB_PASSWORD"), "{error}");
+
+        let health = resolver.health();
+        let op = &health[0];
+        assert_eq!((op.resolved, op.cache_hits, op.cached), (1, 1, 1));
+        let vault = &health[1];
+        assert_eq!(vault.failures, 1);
+        assert!(!vault.installed && vault.last_error.is_some());
+    }
+}
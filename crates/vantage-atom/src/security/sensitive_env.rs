@@ -0,0 +1,267 @@
+//! 機密の環境変数の暗号化
+//!
+//! 機密に指定した環境変数の値はChaCha20-Poly1305で暗号化して保存し、プロセスを起動する
+//! ときにだけメモリ上で復号します。一覧・ステータスなどの応答では値を伏せます。
+//! 暗号化の鍵は `VANTAGE_ENV_KEY`（Base64の32バイト）、OSのキーチェーン（macOSのキーチェーン・
+//! Windowsの資格情報マネージャー）、設定ディレクトリの `env.key`（所有者のみ読み書き可）の順に探し、
+//! 見つからなければ作ります。鍵を失うと暗号化した値は復号できません。
+
+use crate::security::private_file;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serializer;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// 鍵を直接指定する環境変数
+pub const KEY_ENV_VAR: &str = "VANTAGE_ENV_KEY";
+/// 応答で値の代わりに表示する文字列
+pub const MASK: &str = "[REDACTED]";
+/// 暗号文の形式のバージョン
+const PREFIX: &str = "v1:";
+const KEY_LEN: usize = 32;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const KEYCHAIN_SERVICE: &str = "vantage";
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const KEYCHAIN_USER: &str = "env-encryption-key";
+
+static CIPHER: OnceLock<Result<EnvCipher, String>> = OnceLock::new();
+
+/// 環境変数の値の暗号化と復号
+pub struct EnvCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl EnvCipher {
+    pub fn from_key(key: &[u8]) -> Result<Self, String> {
+        if key.len() != KEY_LEN {
+            return Err(format!(
+                "The env encryption key must be {KEY_LEN} bytes, got {}",
+                key.len()
+            ));
+        }
+        let key = UnboundKey::new(&CHACHA20_POLY1305, key)
+            .map_err(|_| "Invalid env encryption key".to_string())?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// 値を暗号化する（名前を関連データにして、別の変数への付け替えを防ぐ）
+    pub fn encrypt(&self, name: &str, value: &str) -> Result<String, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| "Failed to generate a nonce".to_string())?;
+        let mut sealed = value.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(name.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| format!("Failed to encrypt {name}"))?;
+        let mut bytes = nonce.to_vec();
+        bytes.extend_from_slice(&sealed);
+        Ok(format!("{PREFIX}{}", STANDARD.encode(bytes)))
+    }
+
+    pub fn decrypt(&self, name: &str, sealed: &str) -> Result<String, String> {
+        let invalid = || format!("Failed to decrypt sensitive env {name}");
+        let encoded = sealed.strip_prefix(PREFIX).ok_or_else(invalid)?;
+        let mut bytes = STANDARD.decode(encoded).map_err(|_| invalid())?;
+        if bytes.len() < NONCE_LEN {
+            return Err(invalid());
+        }
+        let mut sealed = bytes.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&bytes).map_err(|_| invalid())?;
+        let plain = self
+            .key
+            .open_in_place(nonce, Aad::from(name.as_bytes()), &mut sealed)
+            .map_err(|_| invalid())?;
+        String::from_utf8(plain.to_vec()).map_err(|_| invalid())
+    }
+}
+
+/// 鍵を読み込んだ暗号化器（初回に鍵を探し、無ければ作る）
+pub fn cipher() -> Result<&'static EnvCipher, String> {
+    CIPHER
+        .get_or_init(|| EnvCipher::from_key(&load_key()?))
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+fn load_key() -> Result<Vec<u8>, String> {
+    if let Ok(encoded) = std::env::var(KEY_ENV_VAR) {
+        return STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("{KEY_ENV_VAR} is not valid Base64: {e}"));
+    }
+    if let Some(key) = keychain_key() {
+        return Ok(key);
+    }
+    file_key(&vantage_persistence::paths::get().env_key_file())
+}
+
+fn generate_key() -> Result<Vec<u8>, String> {
+    let mut key = vec![0u8; KEY_LEN];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| "Failed to generate an env encryption key".to_string())?;
+    Ok(key)
+}
+
+/// OSのキーチェーンの鍵（使えなければNone）
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn keychain_key() -> Option<Vec<u8>> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_USER).ok()?;
+    match entry.get_password() {
+        Ok(encoded) => STANDARD.decode(encoded.trim()).ok(),
+        Err(keyring::Error::NoEntry) => {
+            let key = generate_key().ok()?;
+            entry.set_password(&STANDARD.encode(&key)).ok()?;
+            Some(key)
+        }
+        Err(e) => {
+            tracing::warn!("OS keychain is not available, using the key file: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn keychain_key() -> Option<Vec<u8>> {
+    None
+}
+
+/// 鍵ファイルの鍵（無ければ所有者のみ読み書きできる権限で作る）
+fn file_key(path: &Path) -> Result<Vec<u8>, String> {
+    if path.exists() {
+        let encoded = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        return STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("{} is not valid Base64: {e}", path.display()));
+    }
+    let key = generate_key()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+    }
+    private_file::write(path, STANDARD.encode(&key).as_bytes())
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    tracing::info!("Created env encryption key at {}", path.display());
+    Ok(key)
+}
+
+/// 指定した名前の環境変数を `env` から取り出し、暗号化して `sealed` に移す
+pub fn seal(
+    cipher: &EnvCipher,
+    env: &mut HashMap<String, String>,
+    names: &[String],
+    sealed: &mut HashMap<String, String>,
+) -> Result<(), String> {
+    for name in names {
+        match env.remove(name) {
+            Some(value) => {
+                sealed.insert(name.clone(), cipher.encrypt(name, &value)?);
+            }
+            None if sealed.contains_key(name) => {}
+            None => return Err(format!("sensitive_env names '{name}', which is not in env")),
+        }
+    }
+    Ok(())
+}
+
+/// 暗号化した環境変数を復号する（起動するときのみ呼ぶ）
+pub fn open(
+    cipher: &EnvCipher,
+    sealed: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    sealed
+        .iter()
+        .map(|(name, value)| Ok((name.clone(), cipher.decrypt(name, value)?)))
+        .collect()
+}
+
+/// 値を伏せて名前だけを出力する（`#[serde(serialize_with)]` 用）
+pub fn serialize_masked<S: Serializer>(
+    sealed: &HashMap<String, String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let masked: BTreeMap<&str, &str> = sealed.keys().map(|name| (name.as_str(), MASK)).collect();
+    serializer.collect_map(masked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_cipher() -> EnvCipher {
+        EnvCipher::from_key(&[7u8; KEY_LEN]).unwrap()
+    }
+
+    #[test]
+    fn test_values_round_trip_only_under_the_same_name() {
+        let cipher = test_cipher();
+        let sealed = cipher.encrypt("DB_PASSWORD", "hunter2").unwrap();
+        assert!(sealed.starts_with(PREFIX));
+        assert!(!sealed.contains("hunter2"));
+        // 同じ値でも毎回違う暗号文になる
+        assert_ne!(sealed, cipher.encrypt("DB_PASSWORD", "hunter2").unwrap());
+
+        assert_eq!(cipher.decrypt("DB_PASSWORD", &sealed).unwrap(), "hunter2");
+        assert!(cipher.decrypt("API_TOKEN", &sealed).is_err());
+        let other = EnvCipher::from_key(&[8u8; KEY_LEN]).unwrap();
+        assert!(other.decrypt("DB_PASSWORD", &sealed).is_err());
+    }
+
+    #[test]
+    fn test_seal_moves_named_values_out_of_the_plain_env() {
+        let cipher = test_cipher();
+        let mut env = HashMap::from([
+            ("DB_PASSWORD".to_string(), "hunter2".to_string()),
+            ("PORT".to_string(), "3000".to_string()),
+        ]);
+        let mut sealed = HashMap::new();
+        seal(&cipher, &mut env, &["DB_PASSWORD".to_string()], &mut sealed).unwrap();
+        assert_eq!(env.keys().collect::<Vec<_>>(), ["PORT"]);
+        assert_eq!(open(&cipher, &sealed).unwrap()["DB_PASSWORD"], "hunter2");
+
+        // 暗号化済みの名前はもう一度指定してもよい
+        seal(&cipher, &mut env, &["DB_PASSWORD".to_string()], &mut sealed).unwrap();
+        let err = seal(&cipher, &mut env, &["MISSING".to_string()], &mut sealed).unwrap_err();
+        assert!(err.contains("MISSING"), "{err}");
+
+        let json = serde_json::to_value(Masked(&sealed)).unwrap();
+        assert_eq!(json, serde_json::json!({ "DB_PASSWORD": MASK }));
+    }
+
+    struct Masked<'a>(&'a HashMap<String, String>);
+
+    impl serde::Serialize for Masked<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_masked(self.0, serializer)
+        }
+    }
+
+    #[test]
+    fn test_key_file_is_created_once_with_private_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config/env.key");
+        let key = file_key(&path).unwrap();
+        assert_eq!(key.len(), KEY_LEN);
+        assert_eq!(file_key(&path).unwrap(), key);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,528 @@
+//! サーバー自身の運用
+//!
+//! `server_admin` のツール群から、Webサーバーの再起動（auth.yaml・Webhook・証明書と、
+//! デバッグビルドではWebコンソールのアセットを読み直す）、サーバーのログファイルの
+//! ローテーション、キャッシュの消去、サーバー自身の再起動を行います。どの操作も
+//! 監査ログ（`vantage::audit` ターゲット）に記録します。
+//!
+//! サーバー自身の再起動では、同じPIDのまま実行ファイルを読み込み直し（exec）、実行中の
+//! プロセスを止めずに引き継ぎます。引き継ぐプロセスと出力のパイプを引き継ぎファイルに書き、
+//! 再起動したサーバーは外部で起動したプロセスと同じように引き取って出力の取り込みを再開します。
+
+use crate::process::ProcessManager;
+use crate::process::run_as::AUDIT_TARGET;
+use crate::web::WebServerOptions;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::info;
+use vantage_persistence::PersistenceManager;
+
+/// 残しておくローテーション済みのログファイルの数
+pub const ROTATED_LOG_FILES: usize = 5;
+/// 自己再起動の応答を返してから再起動するまでの時間
+pub const RESTART_DELAY: Duration = Duration::from_millis(500);
+
+/// `--log-file` で指定したサーバーのログファイル
+static LOG_FILE: LazyLock<Mutex<Option<LogFile>>> = LazyLock::new(|| Mutex::new(None));
+/// 起動中のWebサーバー
+static WEB_SERVER: LazyLock<Mutex<Option<RunningWebServer>>> = LazyLock::new(|| Mutex::new(None));
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+}
+
+/// Webサーバーを起動し直すのに必要なものと、止めるタスク
+struct RunningWebServer {
+    process_manager: ProcessManager,
+    persistence_manager: Arc<PersistenceManager>,
+    options: WebServerOptions,
+    port: u16,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+/// 監査ログに記録する
+fn audit(action: &str, detail: &str) {
+    info!(
+        target: AUDIT_TARGET,
+        action,
+        "Server admin: {} ({})",
+        action,
+        detail
+    );
+}
+
+/// サーバーのログファイルを開く（以降のログは `LogFileWriter` で追記する）
+pub fn open_log_file(path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = open_append(path)?;
+    *LOG_FILE.lock().unwrap() = Some(LogFile {
+        path: path.to_path_buf(),
+        file,
+    });
+    Ok(())
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// サーバーのログファイルに書き込むwriter（ローテーション後は新しいファイルに書く）
+///
+/// `tracing_subscriber::fmt().with_writer(std::io::stderr.and(LogFileWriter::new))` のように使う。
+pub struct LogFileWriter;
+
+impl LogFileWriter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for LogFileWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match LOG_FILE.lock().unwrap().as_mut() {
+            Some(log) => log.file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match LOG_FILE.lock().unwrap().as_mut() {
+            Some(log) => log.file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// ログファイルのローテーションの結果
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRotation {
+    pub path: String,
+    /// それまでのログを移したファイル
+    pub rotated_to: String,
+    /// 古くなって削除したファイル
+    pub removed: Vec<String>,
+}
+
+/// ログファイルを日時付きの名前に移して新しいファイルに切り替え、古いものを削除する
+pub fn rotate_log_file() -> Result<LogRotation, String> {
+    let mut guard = LOG_FILE.lock().unwrap();
+    let log = guard
+        .as_mut()
+        .ok_or("The server is not writing a log file (start it with --log-file)")?;
+    let file_name = log
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or("Invalid log file path")?;
+    let rotated_to = log.path.with_file_name(format!(
+        "{file_name}.{}",
+        Utc::now().format("%Y%m%d-%H%M%S%.3f")
+    ));
+    let _ = log.file.flush();
+    fs::rename(&log.path, &rotated_to)
+        .map_err(|e| format!("Failed to rotate {}: {e}", log.path.display()))?;
+    log.file = open_append(&log.path)
+        .map_err(|e| format!("Failed to reopen {}: {e}", log.path.display()))?;
+    let path = log.path.clone();
+    drop(guard);
+
+    let removed = remove_old_rotations(&path, &file_name);
+    let rotation = LogRotation {
+        path: path.display().to_string(),
+        rotated_to: rotated_to.display().to_string(),
+        removed,
+    };
+    audit("rotate_server_log", &rotation.rotated_to);
+    Ok(rotation)
+}
+
+/// ローテーション済みのファイルを新しい順に `ROTATED_LOG_FILES` だけ残す
+fn remove_old_rotations(path: &Path, file_name: &str) -> Vec<String> {
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    let prefix = format!("{file_name}.");
+    let mut rotated: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|p| {
+                    p.file_name()
+                        .is_some_and(|n| n.to_string_lossy().starts_with(&prefix))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // 日時の部分は辞書順で古い順に並ぶ
+    rotated.sort();
+    let excess = rotated.len().saturating_sub(ROTATED_LOG_FILES);
+    rotated
+        .into_iter()
+        .take(excess)
+        .filter(|p| fs::remove_file(p).is_ok())
+        .map(|p| p.display().to_string())
+        .collect()
+}
+
+/// 起動したWebサーバーを登録する（後から登録したものに置き換える）
+pub(crate) fn register_web_server(
+    (process_manager, persistence_manager, options): (
+        ProcessManager,
+        Arc<PersistenceManager>,
+        WebServerOptions,
+    ),
+    port: u16,
+    tasks: Vec<JoinHandle<()>>,
+) {
+    *WEB_SERVER.lock().unwrap() = Some(RunningWebServer {
+        process_manager,
+        persistence_manager,
+        options,
+        port,
+        tasks,
+    });
+}
+
+/// Webサーバーの再起動の結果
+#[derive(Debug, Clone, Serialize)]
+pub struct WebServerRestart {
+    pub previous_port: u16,
+    /// 同じポートで待ち受けられなかった場合は別のポートになる
+    pub port: u16,
+}
+
+/// Webサーバーを止めて、同じオプションで起動し直す
+///
+/// auth.yaml（認証・Webhook）と証明書を読み直す。同じポートで待ち受け直すので、
+/// mDNSの告知はそのまま続ける。
+pub async fn restart_web_server() -> Result<WebServerRestart, String> {
+    let running = WEB_SERVER
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or("The web server is not running in this process")?;
+    for task in &running.tasks {
+        task.abort();
+    }
+    for task in running.tasks {
+        let _ = task.await;
+    }
+
+    let mut options = running.options;
+    match &mut options.tls {
+        Some(tls) => tls.https_port = running.port,
+        None => options.port = running.port,
+    }
+    let port = crate::web::server::serve(
+        running.process_manager,
+        running.persistence_manager,
+        options,
+    )
+    .await
+    .map_err(|e| format!("Failed to restart the web server: {e}"))?;
+    if port != running.port {
+        tracing::warn!(
+            "Web server moved from port {} to {}; mDNS still advertises the old port",
+            running.port,
+            port
+        );
+    }
+    audit(
+        "restart_web_server",
+        &format!("port {} -> {port}", running.port),
+    );
+    Ok(WebServerRestart {
+        previous_port: running.port,
+        port,
+    })
+}
+
+/// 消去したキャッシュと件数
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClearedCaches {
+    /// get_suggestions の直近の結果（番号での指定に使う）
+    pub suggestions: usize,
+    /// CIの実行の一覧
+    pub ci_runs: usize,
+}
+
+/// キャッシュの消去を監査ログに記録する
+pub fn record_cleared_caches(cleared: &ClearedCaches) {
+    audit(
+        "clear_server_caches",
+        &format!(
+            "{} suggestion(s), {} CI run(s)",
+            cleared.suggestions, cleared.ci_runs
+        ),
+    );
+}
+
+/// 再起動の前後で引き継ぐプロセス
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandedOffProcess {
+    pub process_id: String,
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    /// 一時停止中の場合、一時停止した時刻
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused_at: Option<DateTime<Utc>>,
+    /// 出力を読むパイプ（再起動後も開いたままのファイルディスクリプタ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout_fd: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr_fd: Option<i32>,
+}
+
+/// 子プロセスの出力のパイプの複製（execした後もパイプを読めるよう、閉じずに持っておく）
+pub(crate) struct OutputPipes {
+    #[cfg(unix)]
+    stdout: std::os::fd::OwnedFd,
+    #[cfg(unix)]
+    stderr: std::os::fd::OwnedFd,
+}
+
+impl OutputPipes {
+    #[cfg(unix)]
+    pub(crate) fn duplicate(
+        stdout: &tokio::process::ChildStdout,
+        stderr: &tokio::process::ChildStderr,
+    ) -> Option<Self> {
+        use std::os::fd::AsFd;
+        Some(Self {
+            stdout: stdout.as_fd().try_clone_to_owned().ok()?,
+            stderr: stderr.as_fd().try_clone_to_owned().ok()?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn duplicate(
+        _stdout: &tokio::process::ChildStdout,
+        _stderr: &tokio::process::ChildStderr,
+    ) -> Option<Self> {
+        None
+    }
+
+    /// 引き継ぎファイルに書くファイルディスクリプタ
+    pub(crate) fn raw_fds(&self) -> (Option<i32>, Option<i32>) {
+        #[cfg(unix)]
+        {
+            use std::os::fd::AsRawFd;
+            (Some(self.stdout.as_raw_fd()), Some(self.stderr.as_raw_fd()))
+        }
+        #[cfg(not(unix))]
+        (None, None)
+    }
+}
+
+/// 引き継いだパイプから出力を読み、行ごとに `buffer` に追加するタスクを起動する
+#[cfg(unix)]
+pub(crate) fn read_handed_off_pipe(
+    fd: i32,
+    buffer: crate::process::CircularBuffer,
+) -> Result<JoinHandle<()>, String> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    // 再起動前のサーバーが閉じずに残したディスクリプタで、ほかに持ち主はいない
+    let owned = unsafe { OwnedFd::from_raw_fd(fd) };
+    let pipe = tokio::net::unix::pipe::Receiver::from_owned_fd(owned)
+        .map_err(|e| format!("fd {fd} is not a readable pipe: {e}"))?;
+    Ok(tokio::spawn(async move {
+        let mut lines = BufReader::new(pipe).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            buffer.push(line).await;
+        }
+    }))
+}
+
+/// 引き取らなかったプロセスのパイプを閉じる
+#[cfg(unix)]
+pub(crate) fn close_fd(fd: i32) {
+    use std::os::fd::{FromRawFd, OwnedFd};
+    // 再起動前のサーバーが閉じずに残したディスクリプタで、ほかに持ち主はいない
+    drop(unsafe { OwnedFd::from_raw_fd(fd) });
+}
+
+#[cfg(not(unix))]
+pub(crate) fn close_fd(_fd: i32) {}
+
+#[cfg(not(unix))]
+pub(crate) fn read_handed_off_pipe(
+    fd: i32,
+    _buffer: crate::process::CircularBuffer,
+) -> Result<JoinHandle<()>, String> {
+    Err(format!("fd {fd} can't be read on this platform"))
+}
+
+/// 引き継ぎファイルの内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestartHandoff {
+    /// 再起動したサーバーのPID（execではPIDは変わらない）
+    pub server_pid: u32,
+    pub requested_at: DateTime<Utc>,
+    pub processes: Vec<HandedOffProcess>,
+}
+
+/// 自己再起動の予定
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerRestart {
+    /// 止めずに引き継ぐプロセス
+    pub handed_off: Vec<String>,
+    /// 引き継がないプロセス（理由付き）
+    pub skipped: Vec<(String, String)>,
+    pub restart_in_ms: u64,
+}
+
+fn handoff_file() -> PathBuf {
+    vantage_persistence::paths::get().restart_handoff_file()
+}
+
+/// 再起動前のサーバーが書いた引き継ぎファイルを読み、削除する
+///
+/// 別のPIDのサーバーが書いたもの（execでない起動）は、ファイルディスクリプタが
+/// 無効なので使わない。
+pub fn take_handoff() -> Option<RestartHandoff> {
+    let path = handoff_file();
+    let content = fs::read_to_string(&path).ok()?;
+    let _ = fs::remove_file(&path);
+    let handoff: RestartHandoff = match serde_json::from_str(&content) {
+        Ok(handoff) => handoff,
+        Err(e) => {
+            tracing::warn!("Ignoring invalid restart handoff {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    (handoff.server_pid == std::process::id()).then_some(handoff)
+}
+
+/// 実行中のプロセスを引き継いでサーバー自身を再起動する
+///
+/// 応答を返せるよう、定義のスナップショットと引き継ぎファイルを書いてから `RESTART_DELAY`
+/// 後に同じ引数で実行ファイルを読み込み直す。MCPのセッションは終わるので、クライアントは
+/// 接続し直す必要がある。
+pub async fn restart_server(process_manager: &ProcessManager) -> Result<ServerRestart, String> {
+    if !cfg!(unix) {
+        return Err("Self-restart is only supported on Unix".to_string());
+    }
+    if process_manager.is_safe_mode() {
+        return Err(
+            "Self-restart is not available in safe mode; restart the server manually".to_string(),
+        );
+    }
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate the server: {e}"))?;
+
+    // 再起動後に定義を復元できるよう、自動起動しないプロセスも含めて書き出す
+    process_manager.create_full_snapshot().await?;
+    let (processes, skipped) = process_manager.prepare_handoff().await;
+    let handoff = RestartHandoff {
+        server_pid: std::process::id(),
+        requested_at: Utc::now(),
+        processes,
+    };
+    let path = handoff_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+    }
+    let json = serde_json::to_string_pretty(&handoff).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+
+    let handed_off: Vec<String> = handoff
+        .processes
+        .iter()
+        .map(|p| p.process_id.clone())
+        .collect();
+    audit(
+        "restart_server",
+        &format!("handing off {} process(es)", handed_off.len()),
+    );
+
+    tokio::spawn(async move {
+        tokio::time::sleep(RESTART_DELAY).await;
+        let error = exec(&exe, &handoff);
+        tracing::error!("Failed to restart the server: {}", error);
+        let _ = fs::remove_file(handoff_file());
+    });
+
+    Ok(ServerRestart {
+        handed_off,
+        skipped,
+        restart_in_ms: RESTART_DELAY.as_millis() as u64,
+    })
+}
+
+/// 出力のパイプを閉じないようにして、同じ引数で実行ファイルを読み込み直す（失敗した場合だけ戻る）
+#[cfg(unix)]
+fn exec(exe: &Path, handoff: &RestartHandoff) -> io::Error {
+    use nix::fcntl::{FcntlArg, FdFlag, fcntl};
+    use std::os::unix::process::CommandExt;
+
+    for fd in handoff
+        .processes
+        .iter()
+        .flat_map(|p| [p.stdout_fd, p.stderr_fd])
+        .flatten()
+    {
+        if let Err(e) = fcntl(fd, FcntlArg::F_SETFD(FdFlag::empty())) {
+            tracing::warn!("Failed to keep fd {} open across restart: {}", fd, e);
+        }
+    }
+    std::process::Command::new(exe)
+        .args(std::env::args_os().skip(1))
+        .exec()
+}
+
+#[cfg(not(unix))]
+fn exec(_exe: &Path, _handoff: &RestartHandoff) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "exec is not supported")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_old_rotations_keeps_the_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.log");
+        fs::write(&path, "current").unwrap();
+        for i in 0..ROTATED_LOG_FILES + 2 {
+            fs::write(
+                dir.path().join(format!("server.log.2026010{i}-000000.000")),
+                "",
+            )
+            .unwrap();
+        }
+        fs::write(dir.path().join("other.log.20260101-000000.000"), "").unwrap();
+
+        let removed = remove_old_rotations(&path, "server.log");
+        assert_eq!(removed.len(), 2);
+        assert!(removed[0].ends_with("server.log.20260100-000000.000"));
+        assert!(path.exists());
+        assert!(dir.path().join("other.log.20260101-000000.000").exists());
+        assert!(
+            dir.path()
+                .join(format!(
+                    "server.log.2026010{}-000000.000",
+                    ROTATED_LOG_FILES + 1
+                ))
+                .exists()
+        );
+    }
+}
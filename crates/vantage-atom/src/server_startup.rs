@@ -0,0 +1,243 @@
+//! サーバー自身の起動にかかった時間
+//!
+//! 起動の段階（データディレクトリの準備、プロセスマネージャーの作成、スナップショットの復元、
+//! 自動起動、Webサーバー、DBへの接続とスキーマの適用など）ごとの時間を記録し、起動が終わると
+//! 合計と時間のかかった段階をinfoレベルで記録します。`get_startup_report` は今回の内訳と、
+//! データディレクトリに残した直近の起動（バージョンごとの合計）を返すため、バージョンを
+//! 上げて起動が遅くなったことに気付けます。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// 残しておく過去の起動の数
+pub const MAX_HISTORY: usize = 20;
+/// 起動の内訳で名前を挙げる時間のかかった段階の数
+const SLOWEST_LISTED: usize = 3;
+
+/// 今回の起動の記録
+static PROFILE: LazyLock<Mutex<Profile>> = LazyLock::new(|| Mutex::new(Profile::new()));
+
+struct Profile {
+    clock: Instant,
+    started_at: DateTime<Utc>,
+    phases: Vec<StartupPhase>,
+    total_ms: Option<u64>,
+}
+
+impl Profile {
+    fn new() -> Self {
+        Self {
+            clock: Instant::now(),
+            started_at: Utc::now(),
+            phases: Vec::new(),
+            total_ms: None,
+        }
+    }
+}
+
+/// 起動の段階
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupPhase {
+    pub name: String,
+    /// 起動の開始からこの段階を始めるまでの時間
+    pub started_after_ms: u64,
+    pub duration_ms: u64,
+    /// 件数や失敗の理由など
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// 過去の起動の合計
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupSummary {
+    pub version: String,
+    pub started_at: DateTime<Utc>,
+    pub total_ms: u64,
+    /// 最も時間のかかった段階
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slowest_phase: Option<String>,
+}
+
+/// サーバーの起動の内訳
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStartupReport {
+    pub version: String,
+    pub started_at: DateTime<Utc>,
+    /// 起動の開始から要求を受け付けられるようになるまでの時間（起動中は `None`）
+    pub total_ms: Option<u64>,
+    /// 始めた順
+    pub phases: Vec<StartupPhase>,
+    /// 直近の起動（新しい順、今回を含む）
+    pub history: Vec<StartupSummary>,
+}
+
+/// 起動の開始を記録する（最初に呼ぶ。呼ばなければ最初に記録した時点を開始とする）
+pub fn begin() {
+    LazyLock::force(&PROFILE);
+}
+
+/// 段階の時間を記録する
+pub fn record(name: &str, started: Instant, detail: Option<String>) {
+    let duration = started.elapsed();
+    let mut profile = PROFILE.lock().unwrap();
+    let started_after = started.saturating_duration_since(profile.clock);
+    info!(
+        "Startup phase '{}' took {}ms{}",
+        name,
+        duration.as_millis(),
+        detail
+            .as_ref()
+            .map(|detail| format!(" ({detail})"))
+            .unwrap_or_default()
+    );
+    profile.phases.push(StartupPhase {
+        name: name.to_string(),
+        started_after_ms: millis(started_after),
+        duration_ms: millis(duration),
+        detail,
+    });
+}
+
+/// 非同期の段階を実行して時間を記録する
+pub async fn measure<F: Future>(name: &str, future: F) -> F::Output {
+    let started = Instant::now();
+    let output = future.await;
+    record(name, started, None);
+    output
+}
+
+/// 起動の終わりを記録し、合計と時間のかかった段階を記録して直近の起動に加える
+pub fn finish() {
+    let summary = {
+        let mut profile = PROFILE.lock().unwrap();
+        if profile.total_ms.is_some() {
+            return;
+        }
+        let total_ms = millis(profile.clock.elapsed());
+        profile.total_ms = Some(total_ms);
+        let slowest = slowest(&profile.phases);
+        info!(
+            "Server started in {}ms{}",
+            total_ms,
+            if slowest.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " (slowest: {})",
+                    slowest
+                        .iter()
+                        .map(|phase| format!("{} {}ms", phase.name, phase.duration_ms))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        );
+        StartupSummary {
+            version: version().to_string(),
+            started_at: profile.started_at,
+            total_ms,
+            slowest_phase: slowest.first().map(|phase| phase.name.clone()),
+        }
+    };
+
+    let mut history = load_history();
+    if let Some(previous) = history.first()
+        && previous.version != summary.version
+    {
+        info!(
+            "Startup took {}ms with {} ({}ms with {})",
+            summary.total_ms, summary.version, previous.total_ms, previous.version
+        );
+    }
+    history.insert(0, summary);
+    history.truncate(MAX_HISTORY);
+    if let Err(e) = save_history(&history) {
+        warn!("Failed to save the startup history: {}", e);
+    }
+}
+
+/// 今回の起動の内訳と直近の起動
+pub fn report() -> ServerStartupReport {
+    let (started_at, total_ms, phases) = {
+        let profile = PROFILE.lock().unwrap();
+        (profile.started_at, profile.total_ms, profile.phases.clone())
+    };
+    ServerStartupReport {
+        version: version().to_string(),
+        started_at,
+        total_ms,
+        phases,
+        history: load_history(),
+    }
+}
+
+fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// 時間のかかった順の段階（先頭の数件）
+fn slowest(phases: &[StartupPhase]) -> Vec<&StartupPhase> {
+    let mut sorted: Vec<&StartupPhase> = phases.iter().collect();
+    sorted.sort_by_key(|phase| std::cmp::Reverse(phase.duration_ms));
+    sorted.truncate(SLOWEST_LISTED);
+    sorted
+}
+
+fn history_path() -> PathBuf {
+    vantage_persistence::paths::get()
+        .data_dir
+        .join("startup-history.json")
+}
+
+fn load_history() -> Vec<StartupSummary> {
+    std::fs::read_to_string(history_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_history(history: &[StartupSummary]) -> std::io::Result<()> {
+    let path = history_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(history)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(name: &str, duration_ms: u64) -> StartupPhase {
+        StartupPhase {
+            name: name.to_string(),
+            started_after_ms: 0,
+            duration_ms,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn test_slowest_phases_come_first() {
+        let phases = vec![
+            phase("data_dir", 2),
+            phase("db_connect", 1200),
+            phase("snapshot_restore", 40),
+            phase("auto_start", 300),
+        ];
+        let names: Vec<&str> = slowest(&phases)
+            .iter()
+            .map(|phase| phase.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["db_connect", "auto_start", "snapshot_restore"]);
+    }
+}
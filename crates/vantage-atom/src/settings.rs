@@ -0,0 +1,492 @@
+//! 型付きの設定
+//!
+//! 設定はまとめて1つの値として保存していますが、ここでは `mdns.enabled` のような
+//! ドット区切りのキーごとに型・範囲・選択肢・既定値を定め、キーごとの取得と変更に使います。
+//! 一覧は `GET /api/settings/schema` と `get_settings_schema` で機械的に読める形で返します。
+//! 設定が変わると、変わったキーごとに変更イベント（`SettingChange`）を送ります。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use vantage_persistence::Settings;
+
+/// 設定の値の型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingType {
+    Boolean,
+    /// 0以上の整数
+    Integer,
+    Number,
+    String,
+    /// `values` のいずれかの文字列
+    Enum,
+    /// 要素の検証は設定ごとの検証に任せる
+    Array,
+    Object,
+}
+
+/// 設定のキーの定義
+struct SettingSpec {
+    key: &'static str,
+    kind: SettingType,
+    nullable: bool,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    values: &'static [&'static str],
+    description: &'static str,
+}
+
+const fn spec(key: &'static str, kind: SettingType, description: &'static str) -> SettingSpec {
+    SettingSpec {
+        key,
+        kind,
+        nullable: false,
+        minimum: None,
+        maximum: None,
+        values: &[],
+        description,
+    }
+}
+
+impl SettingSpec {
+    const fn nullable(self) -> Self {
+        Self {
+            nullable: true,
+            ..self
+        }
+    }
+
+    const fn range(self, minimum: f64, maximum: Option<f64>) -> Self {
+        Self {
+            minimum: Some(minimum),
+            maximum,
+            ..self
+        }
+    }
+
+    const fn values(self, values: &'static [&'static str]) -> Self {
+        Self { values, ..self }
+    }
+}
+
+/// キーごとに変更できる設定（`Settings` のフィールドをドットでつないだもの）
+const SETTINGS: &[SettingSpec] = &[
+    spec("theme", SettingType::Enum, "Color theme of the web console").values(&["dark", "light"]),
+    spec(
+        "auto_save_interval",
+        SettingType::Integer,
+        "Refresh interval used by the web console",
+    )
+    .nullable()
+    .range(1.0, None),
+    spec(
+        "max_log_lines",
+        SettingType::Integer,
+        "Maximum number of output lines to show",
+    )
+    .nullable()
+    .range(1.0, None),
+    spec(
+        "enable_auto_restart",
+        SettingType::Boolean,
+        "Automatically refresh the web console",
+    ),
+    spec(
+        "default_shell",
+        SettingType::String,
+        "Shell used when a process does not name one",
+    )
+    .nullable(),
+    spec(
+        "env_variables",
+        SettingType::Object,
+        "Environment variables shared by the workspace (name to value)",
+    ),
+    spec(
+        "log_quota.max_total_bytes",
+        SettingType::Integer,
+        "Disk quota for the whole log directory in bytes (0 = unlimited)",
+    )
+    .range(0.0, None),
+    spec(
+        "log_quota.max_per_process_bytes",
+        SettingType::Integer,
+        "Disk quota per process in bytes (0 = unlimited)",
+    )
+    .range(0.0, None),
+    spec(
+        "suggestion_providers",
+        SettingType::Object,
+        "Suggestion providers by name with {enabled, weight}",
+    ),
+    spec(
+        "mdns.enabled",
+        SettingType::Boolean,
+        "Advertise the web console on the LAN via mDNS",
+    ),
+    spec(
+        "mdns.instance_name",
+        SettingType::String,
+        "mDNS instance name (default: Vantage on <hostname>)",
+    )
+    .nullable(),
+    spec(
+        "chaos.enabled",
+        SettingType::Boolean,
+        "Allow chaos testing tools to kill and pause processes",
+    ),
+    spec(
+        "workspace_quotas",
+        SettingType::Array,
+        "Resource quotas per workspace directory",
+    ),
+    spec(
+        "approvals.tools",
+        SettingType::Array,
+        "MCP tools that wait for approval in the web console",
+    ),
+    spec(
+        "approvals.confirm_tools",
+        SettingType::Array,
+        "MCP tools whose call blocks until confirmed in the web console",
+    ),
+    spec(
+        "approvals.confirm_timeout_secs",
+        SettingType::Integer,
+        "Seconds to wait for a confirmation (default: 300)",
+    )
+    .nullable()
+    .range(
+        1.0,
+        Some(crate::process::approval::MAX_CONFIRM_TIMEOUT_SECS as f64),
+    ),
+    spec(
+        "log_sinks",
+        SettingType::Array,
+        "Destinations every process's output is forwarded to",
+    ),
+    spec(
+        "redaction_rules",
+        SettingType::Array,
+        "Patterns masked in every process's output",
+    ),
+    spec(
+        "maintenance_windows",
+        SettingType::Array,
+        "Periods that suppress automatic restarts and health notifications",
+    ),
+    spec(
+        "response_budget.max_chars",
+        SettingType::Integer,
+        "Maximum characters per MCP tool response (0 = unlimited)",
+    )
+    .range(0.0, None),
+    spec(
+        "response_budget.max_items",
+        SettingType::Integer,
+        "Maximum array items per MCP tool response (0 = unlimited)",
+    )
+    .range(0.0, None),
+    spec(
+        "energy_cost.watts_per_core",
+        SettingType::Number,
+        "Power drawn by one fully busy CPU core in watts",
+    )
+    .range(0.0, None),
+    spec(
+        "energy_cost.price_per_kwh",
+        SettingType::Number,
+        "Electricity price per kWh",
+    )
+    .range(0.0, None),
+    spec(
+        "energy_cost.currency",
+        SettingType::String,
+        "Currency label for estimated costs",
+    ),
+    spec(
+        "auto_adopt_workspaces",
+        SettingType::Array,
+        "Workspaces whose externally started processes are adopted automatically",
+    ),
+    spec(
+        "output_memory.max_total_bytes",
+        SettingType::Integer,
+        "Memory limit for all output buffers in bytes before idle ones are shrunk (0 = unlimited)",
+    )
+    .range(0.0, None),
+    spec(
+        "output_memory.min_buffer_lines",
+        SettingType::Integer,
+        "Lines kept per stream when an output buffer is shrunk",
+    )
+    .range(1.0, None),
+    spec(
+        "output_memory.idle_after_secs",
+        SettingType::Integer,
+        "Seconds without output before a running process's buffers may be shrunk",
+    )
+    .range(0.0, None),
+    spec(
+        "output_memory.spill_to_disk",
+        SettingType::Boolean,
+        "Write lines dropped by shrinking to the log directory",
+    ),
+    spec(
+        "notification_routes",
+        SettingType::Array,
+        "Ordered rules choosing where each notification is delivered",
+    ),
+];
+
+/// 設定のキーの型・範囲・既定値
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SettingSchema {
+    pub key: String,
+    #[serde(rename = "type")]
+    pub kind: SettingType,
+    /// nullを指定できる（未設定を表す）
+    pub nullable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    /// `enum` で選べる値
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    #[schema(value_type = Object)]
+    pub default: serde_json::Value,
+    pub description: String,
+}
+
+/// キーの現在の値と既定値
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SettingValue {
+    pub key: String,
+    #[schema(value_type = Object)]
+    pub value: serde_json::Value,
+    #[schema(value_type = Object)]
+    pub default: serde_json::Value,
+}
+
+/// 設定の変更（変わったキーごと）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SettingChange {
+    pub key: String,
+    #[schema(value_type = Object)]
+    pub old_value: serde_json::Value,
+    #[schema(value_type = Object)]
+    pub new_value: serde_json::Value,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// すべてのキーの定義（既定値付き）
+pub fn schema() -> Vec<SettingSchema> {
+    let defaults = to_json(&Settings::default());
+    SETTINGS
+        .iter()
+        .map(|spec| SettingSchema {
+            key: spec.key.to_string(),
+            kind: spec.kind,
+            nullable: spec.nullable,
+            minimum: spec.minimum,
+            maximum: spec.maximum,
+            values: spec.values.iter().map(|v| v.to_string()).collect(),
+            default: lookup(&defaults, spec.key).cloned().unwrap_or_default(),
+            description: spec.description.to_string(),
+        })
+        .collect()
+}
+
+fn find(key: &str) -> Result<&'static SettingSpec, String> {
+    SETTINGS.iter().find(|spec| spec.key == key).ok_or_else(|| {
+        format!("Unknown setting '{key}' (see get_settings_schema for the available keys)")
+    })
+}
+
+fn to_json(settings: &Settings) -> serde_json::Value {
+    serde_json::to_value(settings).unwrap_or_default()
+}
+
+fn lookup<'a>(value: &'a serde_json::Value, key: &str) -> Option<&'a serde_json::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
+/// キーの現在の値
+pub fn get(settings: &Settings, key: &str) -> Result<serde_json::Value, String> {
+    find(key)?;
+    Ok(lookup(&to_json(settings), key).cloned().unwrap_or_default())
+}
+
+/// キーの現在の値を既定値と合わせて返す
+pub fn get_with_default(settings: &Settings, key: &str) -> Result<SettingValue, String> {
+    Ok(SettingValue {
+        key: key.to_string(),
+        value: get(settings, key)?,
+        default: default_value(key)?,
+    })
+}
+
+/// キーごとの現在の値（定義の順）
+pub fn get_all(settings: &Settings) -> serde_json::Map<String, serde_json::Value> {
+    let json = to_json(settings);
+    SETTINGS
+        .iter()
+        .map(|spec| {
+            let value = lookup(&json, spec.key).cloned().unwrap_or_default();
+            (spec.key.to_string(), value)
+        })
+        .collect()
+}
+
+/// キーの既定値
+pub fn default_value(key: &str) -> Result<serde_json::Value, String> {
+    get(&Settings::default(), key)
+}
+
+/// 値をキーの型・範囲・選択肢で検証し、設定に反映したものを返す
+///
+/// 配列やオブジェクトの中身は、保存するときの設定ごとの検証で確かめる。
+pub fn set(settings: &Settings, key: &str, value: serde_json::Value) -> Result<Settings, String> {
+    let spec = find(key)?;
+    validate(spec, &value)?;
+
+    let mut json = to_json(settings);
+    let (parent, field) = match key.rsplit_once('.') {
+        Some((parent, field)) => (lookup_mut(&mut json, parent), field),
+        None => (Some(&mut json), key),
+    };
+    let parent = parent
+        .and_then(|parent| parent.as_object_mut())
+        .ok_or_else(|| format!("Setting '{key}' is not available"))?;
+    parent.insert(field.to_string(), value);
+    serde_json::from_value(json).map_err(|e| format!("Invalid value for '{key}': {e}"))
+}
+
+fn lookup_mut<'a>(
+    value: &'a mut serde_json::Value,
+    key: &str,
+) -> Option<&'a mut serde_json::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get_mut(part))
+}
+
+fn validate(spec: &SettingSpec, value: &serde_json::Value) -> Result<(), String> {
+    let key = spec.key;
+    if value.is_null() {
+        return if spec.nullable {
+            Ok(())
+        } else {
+            Err(format!("'{key}' cannot be null"))
+        };
+    }
+    let number = match spec.kind {
+        SettingType::Boolean => {
+            return expect(value.is_boolean(), key, "a boolean");
+        }
+        SettingType::String => return expect(value.is_string(), key, "a string"),
+        SettingType::Array => return expect(value.is_array(), key, "an array"),
+        SettingType::Object => return expect(value.is_object(), key, "an object"),
+        SettingType::Enum => {
+            let valid = value.as_str().is_some_and(|v| spec.values.contains(&v));
+            return expect(valid, key, &format!("one of {}", spec.values.join(", ")));
+        }
+        SettingType::Integer => value
+            .as_u64()
+            .map(|n| n as f64)
+            .ok_or_else(|| format!("'{key}' must be a non-negative integer"))?,
+        SettingType::Number => value
+            .as_f64()
+            .filter(|n| n.is_finite())
+            .ok_or_else(|| format!("'{key}' must be a number"))?,
+    };
+    if spec.minimum.is_some_and(|min| number < min) || spec.maximum.is_some_and(|max| number > max)
+    {
+        let range = match (spec.minimum, spec.maximum) {
+            (Some(min), Some(max)) => format!("between {min} and {max}"),
+            (Some(min), None) => format!("at least {min}"),
+            (None, Some(max)) => format!("at most {max}"),
+            (None, None) => unreachable!(),
+        };
+        return Err(format!("'{key}' must be {range}"));
+    }
+    Ok(())
+}
+
+fn expect(valid: bool, key: &str, expected: &str) -> Result<(), String> {
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{key}' must be {expected}"))
+    }
+}
+
+/// 変わったキーの一覧（`updated_at` は含めない）
+pub fn changes(before: &Settings, after: &Settings) -> Vec<SettingChange> {
+    let (before, after) = (to_json(before), to_json(after));
+    let now = Utc::now();
+    SETTINGS
+        .iter()
+        .filter_map(|spec| {
+            let old_value = lookup(&before, spec.key).cloned().unwrap_or_default();
+            let new_value = lookup(&after, spec.key).cloned().unwrap_or_default();
+            (old_value != new_value).then(|| SettingChange {
+                key: spec.key.to_string(),
+                old_value,
+                new_value,
+                changed_at: now,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_every_key_resolves_and_has_a_default() {
+        let json = to_json(&Settings::default());
+        for spec in SETTINGS {
+            // 未設定のときに書き出さないフィールドもある
+            let found = lookup(&json, spec.key).is_some()
+                || spec.nullable
+                    && spec
+                        .key
+                        .rsplit_once('.')
+                        .is_some_and(|(parent, _)| lookup(&json, parent).is_some());
+            assert!(found, "{} is not a field of Settings", spec.key);
+        }
+        let schema = schema();
+        let theme = schema.iter().find(|s| s.key == "theme").unwrap();
+        assert_eq!(theme.default, json!("dark"));
+        assert_eq!(theme.values, vec!["dark", "light"]);
+    }
+
+    #[test]
+    fn test_set_validates_type_range_and_enum() {
+        let settings = Settings::default();
+        let updated = set(&settings, "mdns.enabled", json!(true)).unwrap();
+        assert!(updated.mdns.enabled);
+        let updated = set(&settings, "approvals.confirm_timeout_secs", json!(60)).unwrap();
+        assert_eq!(updated.approvals.confirm_timeout_secs, Some(60));
+
+        assert!(set(&settings, "mdns.enabled", json!("yes")).is_err());
+        assert!(set(&settings, "theme", json!("blue")).is_err());
+        assert!(set(&settings, "approvals.confirm_timeout_secs", json!(0)).is_err());
+        assert!(set(&settings, "energy_cost.price_per_kwh", json!(-1.0)).is_err());
+        assert!(set(&settings, "chaos.enabled", json!(null)).is_err());
+        assert!(set(&settings, "unknown", json!(1)).is_err());
+        // 配列の中身は設定の型で確かめる
+        assert!(set(&settings, "maintenance_windows", json!([{"bogus": 1}])).is_err());
+
+        let changes = changes(&settings, &updated);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].key, "approvals.confirm_timeout_secs");
+        assert_eq!(changes[0].old_value, json!(null));
+        assert_eq!(changes[0].new_value, json!(60));
+    }
+}
@@ -10,6 +10,10 @@ pub fn create_api_routes() -> Router<AppState> {
         .route("/dashboard", get(super::handlers::get_dashboard))
         .route("/processes", get(super::handlers::list_processes))
         .route("/processes", post(super::handlers::create_process))
+        .route(
+            "/processes/changes",
+            get(super::handlers::process_list_changes),
+        )
         .route("/processes/:id", get(super::handlers::get_process))
         .route("/processes/:id", delete(super::handlers::remove_process))
         .route("/processes/:id", put(super::handlers::update_process))
@@ -23,19 +27,114 @@ pub fn create_api_routes() -> Router<AppState> {
             "/processes/:id/logs",
             get(super::handlers::get_process_logs),
         )
+        .route(
+            "/processes/:id/lines",
+            get(super::handlers::get_process_lines),
+        )
+        .route(
+            "/processes/:id/bookmarks",
+            get(super::handlers::list_output_bookmarks),
+        )
+        .route(
+            "/processes/:id/bookmarks",
+            post(super::handlers::add_output_bookmark),
+        )
+        .route(
+            "/processes/:id/bookmarks/:name",
+            delete(super::handlers::remove_output_bookmark),
+        )
+        .route(
+            "/processes/:id/recordings",
+            get(super::handlers::list_output_recordings),
+        )
+        .route(
+            "/processes/:id/recordings/:name",
+            get(super::handlers::get_output_recording),
+        )
+        .route(
+            "/processes/:id/runbook",
+            get(super::handlers::get_process_runbook),
+        )
+        .route(
+            "/processes/:id/timeline",
+            get(super::handlers::get_process_timeline),
+        )
+        .route(
+            "/processes/:id/sampling",
+            put(super::handlers::set_output_sampling),
+        )
+        .route(
+            "/processes/:id/sampling",
+            delete(super::handlers::disable_output_sampling),
+        )
         .route(
             "/processes/:id/logs/stream",
             get(super::handlers::stream_logs),
         )
+        .route(
+            "/processes/:id/template",
+            post(super::handlers::capture_template),
+        )
+        .route("/diagnostics", get(super::handlers::get_diagnostics))
+        .route("/openapi.json", get(super::openapi::get_openapi))
+        // Approval endpoints
+        .route("/approvals", get(super::handlers::list_approvals))
+        .route("/approvals/:id", get(super::handlers::get_approval))
+        .route(
+            "/approvals/:id/approve",
+            post(super::handlers::approve_approval),
+        )
+        .route("/approvals/:id/deny", post(super::handlers::deny_approval))
+        // Notification inbox endpoints
+        .route("/notifications", get(super::handlers::list_notifications))
+        .route(
+            "/notifications/count",
+            get(super::handlers::get_notification_count),
+        )
+        .route(
+            "/notifications/read",
+            post(super::handlers::mark_notifications_read),
+        )
+        // Process chain endpoints
+        .route("/chains", get(super::handlers::list_chains))
+        .route("/chains/:id", patch(super::handlers::set_chain_enabled))
+        .route("/chains/:id", delete(super::handlers::remove_chain))
+        // Process schedule endpoints
+        .route("/schedules", get(super::handlers::list_schedules))
+        .route("/schedules", post(super::handlers::create_schedule))
+        .route("/schedules/:id", patch(super::handlers::update_schedule))
+        .route("/schedules/:id", delete(super::handlers::remove_schedule))
+        .route("/auth/me", get(super::auth::current_user))
+        // Webhook endpoint (authenticated by the hook's token instead of a session)
+        .route("/hooks/:name", post(super::hooks::trigger_hook))
+        .route("/gate/:process_id", get(super::gates::check_gate))
         // Settings endpoints
         .route("/settings", get(super::handlers::get_settings))
         .route("/settings", put(super::handlers::update_settings))
+        .route(
+            "/settings/schema",
+            get(super::handlers::get_settings_schema),
+        )
+        .route(
+            "/settings/changes",
+            get(super::handlers::stream_setting_changes),
+        )
+        .route("/settings/:key", get(super::handlers::get_setting))
+        .route("/settings/:key", put(super::handlers::set_setting))
         // Template endpoints
         .route("/templates", get(super::handlers::list_templates))
+        .route(
+            "/templates/changes",
+            get(super::handlers::stream_template_changes),
+        )
         .route("/templates", post(super::handlers::create_template))
         .route("/templates/:id", get(super::handlers::get_template))
         .route("/templates/:id", put(super::handlers::update_template))
         .route("/templates/:id", delete(super::handlers::delete_template))
+        .route(
+            "/templates/:id/schema",
+            get(super::handlers::get_template_schema),
+        )
         .route(
             "/templates/:id/instantiate",
             post(super::handlers::instantiate_template),
@@ -0,0 +1,629 @@
+//! Webコンソールの認証
+//!
+//! 認証プロバイダーは `AuthProvider` トレイトで差し替え可能で、標準ではOIDCを提供します。
+//! ログイン後のセッションは永続化層に保存し、アクセストークンの更新はサーバー側で行うため、
+//! ブラウザにはセッションIDのCookieのみを渡します。
+
+use super::server::AppState;
+use crate::security::permissions;
+use axum::{
+    extract::{Query, Request, State},
+    http::{HeaderMap, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Json, Redirect, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OnceCell};
+use vantage_persistence::{
+    Access, AuthConfig, AuthSession, Capability, OidcProviderConfig, Permissions, Role, generate_id,
+};
+
+/// セッションIDを保持するCookie名
+pub const SESSION_COOKIE: &str = "vantage_session";
+/// ログイン開始からコールバックまでの有効期間
+const LOGIN_STATE_TTL_SECS: i64 = 600;
+/// 有効期限のこの秒数前になったらアクセストークンを更新する
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 60;
+
+/// プロバイダーから取得したトークン
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenSet {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// 有効期間（秒）
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+}
+
+impl TokenSet {
+    fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.expires_in
+            .map(|secs| Utc::now() + Duration::seconds(secs))
+    }
+}
+
+/// 認証プロバイダーが返すFuture
+pub type AuthFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// 認証プロバイダー
+pub trait AuthProvider: Send + Sync {
+    /// セッションに記録されるプロバイダー名
+    fn name(&self) -> &str;
+
+    /// ログイン画面のURL
+    fn authorization_url<'a>(&'a self, state: &'a str) -> AuthFuture<'a, String>;
+
+    /// 認可コードをトークンに交換
+    fn exchange_code<'a>(&'a self, code: &'a str) -> AuthFuture<'a, TokenSet>;
+
+    /// リフレッシュトークンでアクセストークンを更新
+    fn refresh<'a>(&'a self, refresh_token: &'a str) -> AuthFuture<'a, TokenSet>;
+
+    /// ユーザーのクレーム
+    fn user_claims<'a>(&'a self, access_token: &'a str) -> AuthFuture<'a, serde_json::Value>;
+
+    /// クレームからロールを決定（ログインを許可しない場合はNone）
+    fn map_role(&self, claims: &serde_json::Value) -> Option<Role>;
+}
+
+/// OIDCディスカバリーで取得するエンドポイント
+#[derive(Debug, Clone, Deserialize)]
+struct OidcDiscovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+/// OpenID Connectプロバイダー
+///
+/// クレームはアクセストークンでuserinfoエンドポイントから取得する。
+pub struct OidcProvider {
+    config: OidcProviderConfig,
+    client: reqwest::Client,
+    discovery: OnceCell<OidcDiscovery>,
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcProviderConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            discovery: OnceCell::new(),
+        }
+    }
+
+    async fn discovery(&self) -> Result<&OidcDiscovery, String> {
+        self.discovery
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.config.issuer_url.trim_end_matches('/')
+                );
+                self.client
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| format!("OIDC discovery failed: {e}"))?
+                    .json::<OidcDiscovery>()
+                    .await
+                    .map_err(|e| format!("Invalid OIDC discovery document: {e}"))
+            })
+            .await
+    }
+
+    fn client_secret(&self) -> Option<String> {
+        self.config
+            .client_secret_env
+            .as_ref()
+            .and_then(|var| std::env::var(var).ok())
+            .or_else(|| self.config.client_secret.clone())
+    }
+
+    async fn token_request(&self, params: &[(&str, &str)]) -> Result<TokenSet, String> {
+        let discovery = self.discovery().await?;
+        let secret = self.client_secret();
+        let mut form: Vec<(&str, &str)> = params.to_vec();
+        form.push(("client_id", &self.config.client_id));
+        if let Some(secret) = &secret {
+            form.push(("client_secret", secret));
+        }
+
+        let response = self
+            .client
+            .post(&discovery.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| format!("Token request failed: {e}"))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Token request failed: {}",
+                response.text().await.unwrap_or_default()
+            ));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid token response: {e}"))
+    }
+}
+
+impl AuthProvider for OidcProvider {
+    fn name(&self) -> &str {
+        "oidc"
+    }
+
+    fn authorization_url<'a>(&'a self, state: &'a str) -> AuthFuture<'a, String> {
+        Box::pin(async move {
+            let discovery = self.discovery().await?;
+            let scope = self.config.scopes.join(" ");
+            let url = reqwest::Url::parse_with_params(
+                &discovery.authorization_endpoint,
+                &[
+                    ("response_type", "code"),
+                    ("client_id", self.config.client_id.as_str()),
+                    ("redirect_uri", self.config.redirect_url.as_str()),
+                    ("scope", scope.as_str()),
+                    ("state", state),
+                ],
+            )
+            .map_err(|e| format!("Invalid authorization endpoint: {e}"))?;
+            Ok(url.to_string())
+        })
+    }
+
+    fn exchange_code<'a>(&'a self, code: &'a str) -> AuthFuture<'a, TokenSet> {
+        Box::pin(async move {
+            self.token_request(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.config.redirect_url),
+            ])
+            .await
+        })
+    }
+
+    fn refresh<'a>(&'a self, refresh_token: &'a str) -> AuthFuture<'a, TokenSet> {
+        Box::pin(async move {
+            self.token_request(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+            ])
+            .await
+        })
+    }
+
+    fn user_claims<'a>(&'a self, access_token: &'a str) -> AuthFuture<'a, serde_json::Value> {
+        Box::pin(async move {
+            let discovery = self.discovery().await?;
+            self.client
+                .get(&discovery.userinfo_endpoint)
+                .bearer_auth(access_token)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("Userinfo request failed: {e}"))?
+                .json()
+                .await
+                .map_err(|e| format!("Invalid userinfo response: {e}"))
+        })
+    }
+
+    fn map_role(&self, claims: &serde_json::Value) -> Option<Role> {
+        map_claims_to_role(
+            claims,
+            &self.config.role_claim,
+            &self.config.role_mapping,
+            self.config.default_role,
+        )
+    }
+}
+
+/// クレームの値に対応するロールのうち最も強いものを返す
+pub fn map_claims_to_role(
+    claims: &serde_json::Value,
+    role_claim: &str,
+    role_mapping: &HashMap<String, Role>,
+    default_role: Option<Role>,
+) -> Option<Role> {
+    let values: Vec<&str> = match claims.get(role_claim) {
+        Some(serde_json::Value::String(value)) => vec![value.as_str()],
+        Some(serde_json::Value::Array(values)) => {
+            values.iter().filter_map(|v| v.as_str()).collect()
+        }
+        _ => Vec::new(),
+    };
+    values
+        .iter()
+        .filter_map(|value| role_mapping.get(*value).copied())
+        .max()
+        .or(default_role)
+}
+
+/// リクエストに必要なロール（機能ごとのアクセス権で判定しないパス）
+pub fn required_role(method: &Method, path: &str) -> Role {
+    if (path.starts_with("/api/settings") || path.starts_with("/api/approvals"))
+        && method != Method::GET
+    {
+        Role::Admin
+    } else if path.starts_with("/api/") && method != Method::GET {
+        Role::Operator
+    } else {
+        Role::Viewer
+    }
+}
+
+/// 認証の状態（プロバイダーとログイン途中のstate）
+pub struct AuthState {
+    provider: Arc<dyn AuthProvider>,
+    session_ttl: Duration,
+    /// Cookieに付けるSecure属性
+    secure_cookie: bool,
+    pending_logins: Mutex<HashMap<String, DateTime<Utc>>>,
+    /// ロールごとのアクセス権の上書き
+    role_permissions: HashMap<Role, HashMap<Capability, Access>>,
+}
+
+impl AuthState {
+    pub fn new(
+        provider: Arc<dyn AuthProvider>,
+        session_ttl_secs: u64,
+        secure_cookie: bool,
+    ) -> Self {
+        Self {
+            provider,
+            session_ttl: Duration::seconds(session_ttl_secs as i64),
+            secure_cookie,
+            pending_logins: Mutex::new(HashMap::new()),
+            role_permissions: HashMap::new(),
+        }
+    }
+
+    /// ロールごとのアクセス権の上書きを設定
+    pub fn with_role_permissions(
+        mut self,
+        role_permissions: HashMap<Role, HashMap<Capability, Access>>,
+    ) -> Self {
+        self.role_permissions = role_permissions;
+        self
+    }
+
+    /// ロールのアクセス権
+    pub fn permissions_for(&self, role: Role) -> Permissions {
+        let permissions = Permissions::for_role(role);
+        match self.role_permissions.get(&role) {
+            Some(overrides) => permissions.with_overrides(overrides),
+            None => permissions,
+        }
+    }
+
+    /// 設定から認証の状態を作成（プロバイダーが未設定ならNone）
+    pub fn from_config(config: &AuthConfig) -> Option<Self> {
+        let oidc = config.oidc.clone()?;
+        let secure_cookie = oidc.redirect_url.starts_with("https://");
+        Some(
+            Self::new(
+                Arc::new(OidcProvider::new(oidc)),
+                config.session_ttl_secs,
+                secure_cookie,
+            )
+            .with_role_permissions(config.role_permissions.clone()),
+        )
+    }
+
+    fn session_cookie(&self, value: &str, max_age_secs: i64) -> String {
+        let mut cookie = format!(
+            "{SESSION_COOKIE}={value}; Path=/; HttpOnly; SameSite=Lax; Max-Age={max_age_secs}"
+        );
+        if self.secure_cookie {
+            cookie.push_str("; Secure");
+        }
+        cookie
+    }
+}
+
+/// ログイン中のユーザー
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct CurrentUser {
+    pub subject: String,
+    pub display_name: Option<String>,
+    pub role: Role,
+    pub provider: String,
+    /// 機能ごとのアクセス権
+    pub permissions: Permissions,
+}
+
+impl CurrentUser {
+    fn new(session: &AuthSession, permissions: Permissions) -> Self {
+        Self {
+            subject: session.subject.clone(),
+            display_name: session.display_name.clone(),
+            role: session.role,
+            provider: session.provider.clone(),
+            permissions,
+        }
+    }
+}
+
+fn session_id_from_headers(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+/// セッションを検証し、必要に応じてアクセストークンを更新する
+///
+/// 更新に失敗した場合はセッションを破棄する。
+async fn resolve_session(
+    state: &AppState,
+    auth: &AuthState,
+    session_id: &str,
+) -> Option<AuthSession> {
+    let persistence = &state.persistence_manager;
+    let mut session = persistence.get_auth_session(session_id).await.ok()??;
+
+    let needs_refresh = session
+        .token_expires_at
+        .is_some_and(|at| at - Duration::seconds(TOKEN_REFRESH_MARGIN_SECS) <= Utc::now());
+    if !needs_refresh {
+        return Some(session);
+    }
+
+    let refreshed = match &session.refresh_token {
+        Some(refresh_token) => auth.provider.refresh(refresh_token).await,
+        None => Err("no refresh token".to_string()),
+    };
+    match refreshed {
+        Ok(tokens) => {
+            session.token_expires_at = tokens.expires_at();
+            session.access_token = tokens.access_token;
+            if tokens.refresh_token.is_some() {
+                session.refresh_token = tokens.refresh_token;
+            }
+            let _ = persistence.save_auth_session(&session).await;
+            Some(session)
+        }
+        Err(e) => {
+            tracing::info!("Failed to refresh session for '{}': {}", session.subject, e);
+            let _ = persistence.delete_auth_session(session_id).await;
+            None
+        }
+    }
+}
+
+/// 認証ミドルウェア
+///
+/// 認証が設定されていない場合は全てのリクエストを通す。
+pub async fn require_session(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(auth) = state.auth.clone() else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path().to_string();
+    // Webhookとゲートはセッションの代わりにWebhook・ゲートごとのトークンで認証する
+    if path.starts_with("/auth/")
+        || path.starts_with("/api/hooks/")
+        || path.starts_with("/api/gate/")
+    {
+        return next.run(request).await;
+    }
+
+    let session = match session_id_from_headers(request.headers()) {
+        Some(session_id) => resolve_session(&state, &auth, &session_id).await,
+        None => None,
+    };
+    let Some(session) = session else {
+        return if path.starts_with("/api/") {
+            (StatusCode::UNAUTHORIZED, "Login required").into_response()
+        } else {
+            Redirect::to("/auth/login").into_response()
+        };
+    };
+
+    // 機能に属するパスはアクセス権で、それ以外はロールで判定する
+    let user_permissions = auth.permissions_for(session.role);
+    let requirements = permissions::route_requirements(request.method(), &path);
+    if requirements.as_ref().is_some_and(Vec::is_empty) {
+        if session.role < required_role(request.method(), &path) {
+            return (StatusCode::FORBIDDEN, "Insufficient role").into_response();
+        }
+    } else if let Some(message) =
+        permissions::route_denied(&user_permissions, request.method(), &path)
+    {
+        return (StatusCode::FORBIDDEN, message).into_response();
+    }
+
+    request
+        .extensions_mut()
+        .insert(CurrentUser::new(&session, user_permissions));
+    next.run(request).await
+}
+
+/// ログインを開始し、プロバイダーへリダイレクト
+pub async fn login(State(state): State<AppState>) -> Response {
+    let Some(auth) = state.auth.clone() else {
+        return Redirect::to("/").into_response();
+    };
+
+    let login_state = generate_id();
+    {
+        let mut pending = auth.pending_logins.lock().await;
+        let cutoff = Utc::now() - Duration::seconds(LOGIN_STATE_TTL_SECS);
+        pending.retain(|_, started_at| *started_at > cutoff);
+        pending.insert(login_state.clone(), Utc::now());
+    }
+
+    match auth.provider.authorization_url(&login_state).await {
+        Ok(url) => Redirect::to(&url).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to start login: {}", e);
+            (StatusCode::BAD_GATEWAY, e).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// プロバイダーからのコールバック
+pub async fn callback(
+    State(state): State<AppState>,
+    Query(query): Query<CallbackQuery>,
+) -> Response {
+    let Some(auth) = state.auth.clone() else {
+        return Redirect::to("/").into_response();
+    };
+    if let Some(error) = query.error {
+        return (StatusCode::UNAUTHORIZED, format!("Login failed: {error}")).into_response();
+    }
+    let (Some(code), Some(login_state)) = (query.code, query.state) else {
+        return (StatusCode::BAD_REQUEST, "Missing code or state").into_response();
+    };
+
+    let started_at = auth.pending_logins.lock().await.remove(&login_state);
+    if started_at.is_none_or(|at| at <= Utc::now() - Duration::seconds(LOGIN_STATE_TTL_SECS)) {
+        return (StatusCode::BAD_REQUEST, "Unknown or expired login state").into_response();
+    }
+
+    let tokens = match auth.provider.exchange_code(&code).await {
+        Ok(tokens) => tokens,
+        Err(e) => return (StatusCode::BAD_GATEWAY, e).into_response(),
+    };
+    let claims = match auth.provider.user_claims(&tokens.access_token).await {
+        Ok(claims) => claims,
+        Err(e) => return (StatusCode::BAD_GATEWAY, e).into_response(),
+    };
+    let Some(subject) = claims.get("sub").and_then(|v| v.as_str()) else {
+        return (StatusCode::BAD_GATEWAY, "Userinfo has no 'sub' claim").into_response();
+    };
+    let Some(role) = auth.provider.map_role(&claims) else {
+        tracing::info!("Login denied for '{}': no role mapped", subject);
+        return (StatusCode::FORBIDDEN, "No role is assigned to this account").into_response();
+    };
+
+    let now = Utc::now();
+    let session = AuthSession {
+        session_id: generate_id(),
+        provider: auth.provider.name().to_string(),
+        subject: subject.to_string(),
+        display_name: ["name", "email", "preferred_username"]
+            .iter()
+            .find_map(|key| claims.get(*key).and_then(|v| v.as_str()))
+            .map(str::to_string),
+        role,
+        token_expires_at: tokens.expires_at(),
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        created_at: now,
+        expires_at: now + auth.session_ttl,
+    };
+    let _ = state.persistence_manager.prune_auth_sessions().await;
+    if let Err(e) = state.persistence_manager.save_auth_session(&session).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+    }
+    tracing::info!("'{}' logged in as {:?}", session.subject, session.role);
+
+    let cookie = auth.session_cookie(&session.session_id, auth.session_ttl.num_seconds());
+    ([(header::SET_COOKIE, cookie)], Redirect::to("/")).into_response()
+}
+
+/// ログアウト
+pub async fn logout(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(auth) = state.auth.clone() else {
+        return Redirect::to("/").into_response();
+    };
+    if let Some(session_id) = session_id_from_headers(&headers) {
+        let _ = state
+            .persistence_manager
+            .delete_auth_session(&session_id)
+            .await;
+    }
+    (
+        [(header::SET_COOKIE, auth.session_cookie("", 0))],
+        Redirect::to("/"),
+    )
+        .into_response()
+}
+
+/// ログイン中のユーザー（認証が無効な場合は204）
+#[utoipa::path(
+    get, path = "/auth/me", tag = "auth",
+    responses((status = 200, body = CurrentUser), (status = 204))
+)]
+pub async fn current_user(request: Request) -> Response {
+    match request.extensions().get::<CurrentUser>() {
+        Some(user) => Json(user.clone()).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_map_claims_to_role_picks_strongest() {
+        let mapping = HashMap::from([
+            ("devs".to_string(), Role::Operator),
+            ("platform".to_string(), Role::Admin),
+        ]);
+        let claims = json!({"sub": "u1", "groups": ["devs", "platform", "other"]});
+        assert_eq!(
+            map_claims_to_role(&claims, "groups", &mapping, None),
+            Some(Role::Admin)
+        );
+
+        let claims = json!({"sub": "u2", "groups": "guests"});
+        assert_eq!(map_claims_to_role(&claims, "groups", &mapping, None), None);
+        assert_eq!(
+            map_claims_to_role(&claims, "groups", &mapping, Some(Role::Viewer)),
+            Some(Role::Viewer)
+        );
+    }
+
+    #[test]
+    fn test_required_role() {
+        assert_eq!(required_role(&Method::GET, "/api/processes"), Role::Viewer);
+        assert_eq!(
+            required_role(&Method::POST, "/api/processes/web/start"),
+            Role::Operator
+        );
+        assert_eq!(required_role(&Method::PUT, "/api/settings"), Role::Admin);
+        assert_eq!(
+            required_role(&Method::POST, "/api/approvals/a1/approve"),
+            Role::Admin
+        );
+        assert_eq!(required_role(&Method::GET, "/api/approvals"), Role::Viewer);
+        assert_eq!(required_role(&Method::GET, "/"), Role::Viewer);
+    }
+
+    #[test]
+    fn test_session_id_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            "theme=dark; vantage_session=abc123".parse().unwrap(),
+        );
+        assert_eq!(session_id_from_headers(&headers).as_deref(), Some("abc123"));
+    }
+}
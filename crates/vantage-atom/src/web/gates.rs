@@ -0,0 +1,204 @@
+//! 外部のツールが準備完了を待つゲート
+//!
+//! auth.yaml の `gates` に定義したプロセスの状態を `GET /api/gate/<process_id>` で返します。
+//! 準備完了（実行中で、ヘルスチェックが正常で、`startup.ready_port` が接続を受け付ける）の
+//! 場合だけ200を、それ以外は閉じている理由とともに503を返すため、gitフックやスクリプトは
+//! `curl -f` でMCPを使わずに待てます。ログインのセッションの代わりにゲートごとのトークンを
+//! `Authorization: Bearer` か `X-Vantage-Gate-Token` ヘッダーで受け取ります。
+
+use super::hooks::{token_from_headers, tokens_match};
+use crate::process::blue_green::accepts_connections;
+use crate::process::{ProcessManager, ProcessState};
+use crate::web::server::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+use vantage_persistence::GateConfig;
+
+/// トークンを受け取るヘッダー（`Authorization: Bearer` の代わり）
+pub const TOKEN_HEADER: &str = "x-vantage-gate-token";
+/// `wait` で待てる最大の秒数
+pub const MAX_WAIT_SECS: u64 = 60;
+/// 開くのを待つ間に状態を確かめる間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 設定から読み込んだゲート（プロセスID → トークン）
+#[derive(Default)]
+pub struct GateRegistry {
+    tokens: HashMap<String, String>,
+}
+
+impl GateRegistry {
+    /// 設定からゲートを読み込む（トークンの無いもの・プロセスが重複するものは警告して無視する）
+    pub fn from_config(configs: &[GateConfig]) -> Self {
+        let mut tokens = HashMap::new();
+        for config in configs {
+            let token = config
+                .token_env
+                .as_ref()
+                .and_then(|name| std::env::var(name).ok())
+                .or_else(|| config.token.clone())
+                .filter(|token| !token.is_empty());
+            let Some(token) = token else {
+                tracing::warn!("Gate '{}' has no token and is disabled", config.process_id);
+                continue;
+            };
+            if tokens.contains_key(&config.process_id) {
+                tracing::warn!("Duplicate gate '{}' is ignored", config.process_id);
+                continue;
+            }
+            tokens.insert(config.process_id.clone(), token);
+        }
+        Self { tokens }
+    }
+
+    /// 有効なゲートの数
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GateQuery {
+    /// 閉じている場合、開くまでこの秒数まで待つ（最大60秒）
+    #[serde(default)]
+    wait: Option<u64>,
+}
+
+/// ゲートの状態
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GateStatus {
+    pub process_id: String,
+    /// 準備完了か（trueなら200、falseなら503）
+    pub open: bool,
+    /// 閉じている理由
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reasons: Vec<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+fn state_name(state: &ProcessState) -> &'static str {
+    match state {
+        ProcessState::NotStarted => "not started",
+        ProcessState::Running { .. } => "running",
+        ProcessState::Paused { .. } => "paused",
+        ProcessState::Stopped { .. } => "stopped",
+        ProcessState::Failed { .. } => "failed",
+    }
+}
+
+/// プロセスが準備完了かを確かめる
+pub async fn evaluate(manager: &ProcessManager, process_id: &str) -> GateStatus {
+    let mut reasons = Vec::new();
+    match manager.get_process_status(process_id.to_string()).await {
+        Err(e) => reasons.push(e),
+        Ok(status) => {
+            let info = status.info;
+            if !matches!(info.state, ProcessState::Running { .. }) {
+                reasons.push(format!("Process is {}", state_name(&info.state)));
+            } else {
+                if let Some(health) = info.health.as_ref().filter(|health| !health.healthy) {
+                    reasons.push(if health.failures.is_empty() {
+                        "Health check is failing".to_string()
+                    } else {
+                        format!("Health check is failing: {}", health.failures.join("; "))
+                    });
+                }
+                if let Some(port) = info.startup.as_ref().and_then(|order| order.ready_port)
+                    && !accepts_connections(port).await
+                {
+                    reasons.push(format!("Port {port} is not accepting connections"));
+                }
+            }
+        }
+    }
+    GateStatus {
+        process_id: process_id.to_string(),
+        open: reasons.is_empty(),
+        reasons,
+        checked_at: Utc::now(),
+    }
+}
+
+/// ゲートの状態を返す（準備完了の場合だけ200）
+#[utoipa::path(
+    get, path = "/gate/{process_id}", tag = "gates",
+    params(("process_id" = String, Path, description = "Process ID of a gate from auth.yaml"), GateQuery),
+    responses(
+        (status = 200, body = GateStatus, description = "The process is ready"),
+        (status = 401, body = String),
+        (status = 404, body = String),
+        (status = 503, body = GateStatus, description = "The process is not ready; see reasons")
+    )
+)]
+pub async fn check_gate(
+    State(state): State<AppState>,
+    Path(process_id): Path<String>,
+    Query(query): Query<GateQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(token) = state.gates.tokens.get(&process_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Gate '{process_id}' not found"),
+        )
+            .into_response();
+    };
+    if !token_from_headers(&headers, TOKEN_HEADER).is_some_and(|given| tokens_match(given, token)) {
+        tracing::warn!("Rejected gate check for '{}': invalid token", process_id);
+        return (StatusCode::UNAUTHORIZED, "Invalid gate token").into_response();
+    }
+
+    let deadline = tokio::time::Instant::now()
+        + Duration::from_secs(query.wait.unwrap_or(0).min(MAX_WAIT_SECS));
+    let status = loop {
+        let status = evaluate(&state.process_manager, &process_id).await;
+        if status.open || tokio::time::Instant::now() + POLL_INTERVAL > deadline {
+            break status;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    };
+    let code = if status.open {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (code, [(header::CACHE_CONTROL, "no-store")], Json(status)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gate(process_id: &str, token: Option<&str>) -> GateConfig {
+        GateConfig {
+            process_id: process_id.to_string(),
+            token: token.map(str::to_string),
+            token_env: None,
+        }
+    }
+
+    #[test]
+    fn test_gates_without_a_token_are_disabled() {
+        let registry = GateRegistry::from_config(&[
+            gate("api", Some("secret")),
+            gate("api", Some("other")),
+            gate("web", None),
+            gate("worker", Some("")),
+        ]);
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry.tokens["api"], "secret");
+    }
+}
@@ -1,12 +1,23 @@
 use crate::messages::clipboard::*;
 use crate::messages::{CreateProcessRequest, StopProcessRequest, UpdateProcessRequest};
-use crate::process::{OutputStream, ProcessFilter, ProcessStateFilter};
+use crate::process::bookmarks::OutputBookmark;
+use crate::process::recording::{Recording, RecordingSummary};
+use crate::process::{
+    CapturedTemplate, ChainStatus, FollowedLines, OutputSampling, OutputStream, ProcessFilter,
+    ProcessInfo, ProcessOptions, ProcessStateFilter, ProcessStatus, ProcessTimeline,
+    RunbookContent, SafeModeStatus, SamplingStatus, ScheduleStatus, StartupReport, TimeWindow,
+};
+use crate::security::permissions::{check_secret_env, mask_secrets};
+use crate::settings::{SettingChange, SettingSchema, SettingValue};
+use crate::web::auth::CurrentUser;
+use crate::web::process_feed::{self, ProcessListMessage};
 use crate::web::server::AppState;
 use axum::{
-    extract::{Path, Query, State},
+    Extension,
+    extract::{Path, Query, State, WebSocketUpgrade},
     http::StatusCode,
-    response::Json,
     response::sse::{Event, KeepAlive, Sse},
+    response::{Json, Response},
 };
 use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
@@ -15,22 +26,51 @@ use std::convert::Infallible;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio_stream::StreamExt;
-use vantage_persistence::{ClipboardItem, ProcessTemplate, TemplateVariable};
+use utoipa::{IntoParams, ToSchema};
+use vantage_persistence::{
+    ApprovalRequest, ApprovalSettings, ApprovalStatus, ChaosSettings, ClipboardItem,
+    EnergyCostSettings, LogQuotaSettings, LogSinkConfig, MaintenanceWindow, MdnsSettings,
+    Notification, NotificationKind, NotificationRoute, OutputMemorySettings, Permissions,
+    ProcessTemplate, RedactionRule, ResponseBudget, StartupOrder, TemplateVariable, WorkspaceQuota,
+};
+
+/// ログインしているユーザーのアクセス権（認証なしの場合はNone）
+fn permissions_of(user: &Option<Extension<CurrentUser>>) -> Option<&Permissions> {
+    user.as_ref().map(|Extension(user)| &user.permissions)
+}
+
+/// 操作結果のメッセージ
+#[derive(Serialize, ToSchema)]
+pub struct MessageResponse {
+    pub message: String,
+}
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ProcessConfigUpdate {
     pub auto_start_on_restore: Option<bool>,
+    /// 自動起動・一括起動での起動順序（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub startup: Option<StartupOrder>,
+    /// まとめて起動・停止するグループ（空文字列でグループから外す、未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub group: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ServerStatus {
     status: String,
     version: String,
     uptime_seconds: u64,
     process_count: usize,
+    /// セーフモードで起動した場合のみ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    safe_mode: Option<SafeModeStatus>,
+    /// 復元時の自動起動の結果（優先度・グループ順の起動と準備完了までの時間）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    startup: Option<StartupReport>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct DashboardData {
     server: ServerInfo,
     stats: ProcessStats,
@@ -39,7 +79,7 @@ pub struct DashboardData {
     system_metrics: SystemMetrics,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ServerInfo {
     status: String,
     version: String,
@@ -47,16 +87,17 @@ pub struct ServerInfo {
     current_time: u64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ProcessStats {
     total: usize,
     running: usize,
+    paused: usize,
     stopped: usize,
     failed: usize,
     auto_start_enabled: usize,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct RecentEvent {
     timestamp: u64,
     process_id: String,
@@ -64,25 +105,58 @@ pub struct RecentEvent {
     message: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct SystemMetrics {
     cpu_usage: f32,
     memory_usage: f32,
     process_manager_memory: usize,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ListProcessesQuery {
     state: Option<String>,
     name_pattern: Option<String>,
+    /// フィルター式（例: `state == running && tag in ["backend"]`）
+    expression: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct LogsQuery {
     stream: Option<String>,
     lines: Option<u32>,
+    #[serde(default)]
+    timestamps: bool,
+    /// このブックマークより後の出力（先頭から `lines` 行）
+    from_bookmark: Option<String>,
+    /// このブックマークまでの出力（`from_bookmark` がなければ末尾から `lines` 行）
+    to_bookmark: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LinesQuery {
+    /// stdout・stderr（既定は両方を出力された順に並べる）
+    stream: Option<String>,
+    lines: Option<u32>,
+    /// この連番より後の行（前回の応答の `cursor`）
+    cursor: Option<u64>,
+    /// この時刻（RFC 3339）より後の行
+    since: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddBookmarkBody {
+    name: String,
+    #[serde(default)]
+    note: Option<String>,
 }
 
+#[utoipa::path(
+    get, path = "/status", tag = "system",
+    responses((status = 200, body = ServerStatus))
+)]
 pub async fn get_status(State(state): State<AppState>) -> Json<ServerStatus> {
     let processes = state.process_manager.list_processes(None).await;
 
@@ -91,16 +165,26 @@ pub async fn get_status(State(state): State<AppState>) -> Json<ServerStatus> {
         version: env!("CARGO_PKG_VERSION").to_string(),
         uptime_seconds: 0, // TODO: Track actual uptime
         process_count: processes.len(),
+        safe_mode: state.process_manager.safe_mode_status().await,
+        startup: state.process_manager.last_startup_report().await,
     })
 }
 
-pub async fn get_dashboard(State(state): State<AppState>) -> Json<DashboardData> {
+#[utoipa::path(
+    get, path = "/dashboard", tag = "system",
+    responses((status = 200, body = DashboardData))
+)]
+pub async fn get_dashboard(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+) -> Json<DashboardData> {
     let processes = state.process_manager.list_processes(None).await;
 
     // 統計を計算
     let mut stats = ProcessStats {
         total: processes.len(),
         running: 0,
+        paused: 0,
         stopped: 0,
         failed: 0,
         auto_start_enabled: 0,
@@ -109,6 +193,7 @@ pub async fn get_dashboard(State(state): State<AppState>) -> Json<DashboardData>
     for process in &processes {
         match &process.state {
             crate::process::types::ProcessState::Running { .. } => stats.running += 1,
+            crate::process::types::ProcessState::Paused { .. } => stats.paused += 1,
             crate::process::types::ProcessState::Stopped { .. } => stats.stopped += 1,
             crate::process::types::ProcessState::Failed { .. } => stats.failed += 1,
             crate::process::types::ProcessState::NotStarted => stats.stopped += 1,
@@ -142,8 +227,8 @@ pub async fn get_dashboard(State(state): State<AppState>) -> Json<DashboardData>
 
     // ProcessInfoをJSONに変換
     let processes_json: Vec<serde_json::Value> = processes
-        .into_iter()
-        .map(|p| serde_json::to_value(p).unwrap())
+        .iter()
+        .map(|p| mask_secrets(permissions_of(&user), p))
         .collect();
 
     Json(DashboardData {
@@ -160,14 +245,21 @@ pub async fn get_dashboard(State(state): State<AppState>) -> Json<DashboardData>
     })
 }
 
+#[utoipa::path(
+    get, path = "/processes", tag = "processes",
+    params(ListProcessesQuery),
+    responses((status = 200, body = Vec<ProcessInfo>), (status = 400, body = String))
+)]
 pub async fn list_processes(
     State(state): State<AppState>,
     Query(query): Query<ListProcessesQuery>,
-) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
+    user: Option<Extension<CurrentUser>>,
+) -> Result<Json<Vec<serde_json::Value>>, (StatusCode, String)> {
     let filter = if query.state.is_some() || query.name_pattern.is_some() {
         Some(ProcessFilter {
             state: query.state.map(|s| match s.as_str() {
                 "running" => ProcessStateFilter::Running,
+                "paused" => ProcessStateFilter::Paused,
                 "stopped" => ProcessStateFilter::Stopped,
                 "failed" => ProcessStateFilter::Failed,
                 _ => ProcessStateFilter::All,
@@ -178,57 +270,142 @@ pub async fn list_processes(
         None
     };
 
-    let processes = state.process_manager.list_processes(filter).await;
+    let processes = state
+        .process_manager
+        .list_processes_matching(filter, query.expression.as_deref())
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
     // Convert to JSON values
     let json_processes: Vec<serde_json::Value> = processes
-        .into_iter()
-        .map(|p| serde_json::to_value(p).unwrap_or(serde_json::json!({})))
+        .iter()
+        .map(|p| mask_secrets(permissions_of(&user), p))
         .collect();
 
     Ok(Json(json_processes))
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProcessChangesQuery {
+    /// 再接続のとき、最後に受け取った通し番号（それ以降の差分が残っていれば差分だけを送る）
+    since: Option<u64>,
+}
+
+#[utoipa::path(
+    get, path = "/processes/changes", tag = "processes",
+    params(ProcessChangesQuery),
+    responses((status = 101, description = "WebSocket. The server sends a `snapshot` ({type, seq, processes}) and then a `delta` ({type, seq, added, updated, removed}) whenever the list changes. Send {\"type\": \"resync\"} to get a new snapshot after missing a sequence number", body = ProcessListMessage))
+)]
+pub async fn process_list_changes(
+    State(state): State<AppState>,
+    Query(query): Query<ProcessChangesQuery>,
+    user: Option<Extension<CurrentUser>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let permissions = permissions_of(&user).cloned();
+    ws.on_upgrade(move |socket| {
+        process_feed::serve_connection(
+            socket,
+            state.process_feed,
+            state.process_manager,
+            query.since,
+            permissions,
+        )
+    })
+}
+
+#[utoipa::path(
+    post, path = "/processes", tag = "processes",
+    request_body = CreateProcessRequest,
+    responses(
+        (status = 201, body = MessageResponse),
+        (status = 400, body = String),
+        (status = 403, body = String),
+    )
+)]
 pub async fn create_process(
     State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
     Json(req): Json<CreateProcessRequest>,
-) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<MessageResponse>), (StatusCode, String)> {
+    if let Some(permissions) = permissions_of(&user) {
+        check_secret_env(permissions, &req.env, &req.sensitive_env)
+            .map_err(|e| (StatusCode::FORBIDDEN, e))?;
+    }
     let cwd = req.cwd.map(std::path::PathBuf::from);
 
     // Create process with auto_start flags
     state
         .process_manager
-        .create_process(
+        .create_process_with_options(
             req.id.clone(),
             req.command,
             req.args,
             req.env,
             cwd,
-            req.auto_start_on_restore,
+            ProcessOptions {
+                auto_start_on_restore: req.auto_start_on_restore,
+                tags: req.tags,
+                idle_detection: req.idle_detection,
+                health_check: req.health_check,
+                tmux: req.tmux,
+                repl: req.repl,
+                log_sources: req.log_sources,
+                log_sinks: req.log_sinks,
+                redaction: req.redaction,
+                metadata: req.metadata,
+                runbook: req.runbook,
+                startup: req.startup,
+                env_loader: req.env_loader,
+                output_recording: req.output_recording,
+                assertions: req.assertions,
+                restart_policy: req.restart_policy,
+                group: req.group,
+                depends_on: req.depends_on,
+                parameters: req.parameters,
+                keep_stdin_open: req.keep_stdin_open,
+                sensitive_env: req.sensitive_env,
+                run_as: req.run_as,
+                resource_limits: req.resource_limits,
+                container: req.container,
+                ..Default::default()
+            },
         )
         .await
         .map_err(|e| (StatusCode::BAD_REQUEST, e.clone()))?;
 
     Ok((
         StatusCode::CREATED,
-        Json(serde_json::json!({
-            "message": format!("Process '{}' created successfully", req.id)
-        })),
+        Json(MessageResponse {
+            message: format!("Process '{}' created successfully", req.id),
+        }),
     ))
 }
 
+#[utoipa::path(
+    get, path = "/processes/{id}", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    responses((status = 200, body = ProcessStatus), (status = 404))
+)]
 pub async fn get_process(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    user: Option<Extension<CurrentUser>>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     state
         .process_manager
         .get_process_status(id)
         .await
-        .map(|status| Json(serde_json::to_value(status).unwrap()))
+        .map(|status| Json(mask_secrets(permissions_of(&user), &status)))
         .map_err(|_| StatusCode::NOT_FOUND)
 }
 
+#[utoipa::path(
+    delete, path = "/processes/{id}", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    responses((status = 204), (status = 400, body = String))
+)]
 pub async fn remove_process(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -241,22 +418,33 @@ pub async fn remove_process(
         .map_err(|e| (StatusCode::BAD_REQUEST, e))
 }
 
+#[utoipa::path(
+    post, path = "/processes/{id}/start", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    responses((status = 200, body = MessageResponse), (status = 400, body = String))
+)]
 pub async fn start_process(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<MessageResponse>, (StatusCode, String)> {
     state
         .process_manager
         .start_process(id.clone())
         .await
         .map(|pid| {
-            Json(serde_json::json!({
-                "message": format!("Process '{}' started with PID {}", id, pid)
-            }))
+            Json(MessageResponse {
+                message: format!("Process '{}' started with PID {}", id, pid),
+            })
         })
         .map_err(|e| (StatusCode::BAD_REQUEST, e))
 }
 
+#[utoipa::path(
+    patch, path = "/processes/{id}/config", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    request_body = ProcessConfigUpdate,
+    responses((status = 200), (status = 400, body = String))
+)]
 pub async fn update_process_config(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -264,18 +452,45 @@ pub async fn update_process_config(
 ) -> Result<StatusCode, (StatusCode, String)> {
     state
         .process_manager
-        .update_process_config(id, config.auto_start_on_restore)
+        .update_process_config(
+            id,
+            config.auto_start_on_restore,
+            config.startup,
+            config.group,
+        )
         .await
         .map(|_| StatusCode::OK)
         .map_err(|e| (StatusCode::BAD_REQUEST, e))
 }
 
 /// Update process attributes
+#[utoipa::path(
+    put, path = "/processes/{id}", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    request_body = UpdateProcessRequest,
+    responses((status = 200), (status = 400, body = String), (status = 403, body = String))
+)]
 pub async fn update_process(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    user: Option<Extension<CurrentUser>>,
     Json(request): Json<UpdateProcessRequest>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    if let Some(permissions) = permissions_of(&user) {
+        check_secret_env(
+            permissions,
+            request.env.as_ref().unwrap_or(&Default::default()),
+            request.sensitive_env.as_deref().unwrap_or_default(),
+        )
+        .map_err(|e| (StatusCode::FORBIDDEN, e))?;
+    }
+    if let Some(changes) = request.metadata {
+        state
+            .process_manager
+            .update_process_metadata(id.clone(), changes)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
     state
         .process_manager
         .update_process(
@@ -285,12 +500,19 @@ pub async fn update_process(
             request.env,
             request.cwd,
             request.auto_start_on_restore,
+            request.sensitive_env,
         )
         .await
         .map(|_| StatusCode::OK)
         .map_err(|e| (StatusCode::BAD_REQUEST, e))
 }
 
+#[utoipa::path(
+    post, path = "/processes/{id}/stop", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    request_body(content = Option<StopProcessRequest>),
+    responses((status = 204), (status = 400, body = String))
+)]
 pub async fn stop_process(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -306,6 +528,100 @@ pub async fn stop_process(
         .map_err(|e| (StatusCode::BAD_REQUEST, e))
 }
 
+/// Get the runbook of a process (the file is read on each request)
+#[utoipa::path(
+    get, path = "/processes/{id}/runbook", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    responses(
+        (status = 200, body = RunbookContent),
+        (status = 404, body = String),
+        (status = 422, body = String, description = "The runbook file could not be read")
+    )
+)]
+pub async fn get_process_runbook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<RunbookContent>, (StatusCode, String)> {
+    state
+        .process_manager
+        .get_process_status(id.clone())
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e))?;
+    state
+        .process_manager
+        .get_runbook(&id)
+        .await
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?
+        .map(Json)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Process '{id}' has no runbook"),
+            )
+        })
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TimelineQuery {
+    /// この時刻以降に始まった項目のみ（RFC 3339）
+    pub from: Option<String>,
+    /// この時刻より前に始まった項目のみ（RFC 3339）
+    pub to: Option<String>,
+}
+
+/// Get the timeline of a process (runs, restarts, health, pauses, maintenance, changes and CI failures)
+#[utoipa::path(
+    get, path = "/processes/{id}/timeline", tag = "processes",
+    params(("id" = String, Path, description = "Process ID"), TimelineQuery),
+    responses(
+        (status = 200, body = ProcessTimeline),
+        (status = 400, body = String),
+        (status = 404, body = String),
+        (status = 500, body = String)
+    )
+)]
+pub async fn get_process_timeline(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<TimelineQuery>,
+) -> Result<Json<ProcessTimeline>, (StatusCode, String)> {
+    let parse = |value: Option<String>| {
+        value
+            .map(|v| {
+                chrono::DateTime::parse_from_rfc3339(&v)
+                    .map(|t| t.with_timezone(&chrono::Utc))
+                    .map_err(|e| {
+                        (
+                            StatusCode::BAD_REQUEST,
+                            format!("Invalid timestamp '{v}': {e}"),
+                        )
+                    })
+            })
+            .transpose()
+    };
+    let window = TimeWindow {
+        from: parse(query.from)?,
+        to: parse(query.to)?,
+    };
+    state
+        .process_manager
+        .get_process_status(id.clone())
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e))?;
+    state
+        .process_manager
+        .get_process_timeline(&id, window)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+#[utoipa::path(
+    get, path = "/processes/{id}/logs", tag = "processes",
+    params(("id" = String, Path, description = "Process ID"), LogsQuery),
+    responses((status = 200, body = Vec<String>), (status = 404))
+)]
 pub async fn get_process_logs(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -317,6 +633,31 @@ pub async fn get_process_logs(
         _ => OutputStream::Both,
     };
 
+    if query.from_bookmark.is_some() || query.to_bookmark.is_some() {
+        return state
+            .process_manager
+            .get_output_between_bookmarks(
+                &id,
+                stream,
+                query.from_bookmark.as_deref(),
+                query.to_bookmark.as_deref(),
+                query.lines,
+                query.timestamps,
+            )
+            .await
+            .map(|output| Json(output.lines))
+            .map_err(|_| StatusCode::NOT_FOUND);
+    }
+
+    if query.timestamps {
+        return state
+            .process_manager
+            .get_process_output_lines(id, stream, query.lines)
+            .await
+            .map(|lines| Json(lines.iter().map(|l| l.with_timestamp()).collect()))
+            .map_err(|_| StatusCode::NOT_FOUND);
+    }
+
     state
         .process_manager
         .get_process_output(id, stream, query.lines)
@@ -325,6 +666,177 @@ pub async fn get_process_logs(
         .map_err(|_| StatusCode::NOT_FOUND)
 }
 
+#[utoipa::path(
+    get, path = "/processes/{id}/lines", tag = "processes",
+    params(("id" = String, Path, description = "Process ID"), LinesQuery),
+    responses((status = 200, body = FollowedLines), (status = 400, body = String), (status = 404, body = String))
+)]
+pub async fn get_process_lines(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<LinesQuery>,
+) -> Result<Json<FollowedLines>, (StatusCode, String)> {
+    let stream = match query.stream.as_deref() {
+        Some("stdout") => OutputStream::Stdout,
+        Some("stderr") => OutputStream::Stderr,
+        _ => OutputStream::Both,
+    };
+    let since = query
+        .since
+        .map(|since| {
+            chrono::DateTime::parse_from_rfc3339(&since)
+                .map(|t| t.with_timezone(&chrono::Utc))
+                .map_err(|e| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid timestamp '{since}': {e}"),
+                    )
+                })
+        })
+        .transpose()?;
+    state
+        .process_manager
+        .follow_process_lines(&id, stream, query.cursor, since, query.lines)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+#[utoipa::path(
+    get, path = "/processes/{id}/bookmarks", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    responses((status = 200, body = Vec<OutputBookmark>), (status = 404, body = String))
+)]
+pub async fn list_output_bookmarks(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<OutputBookmark>>, (StatusCode, String)> {
+    state
+        .process_manager
+        .list_output_bookmarks(&id)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+#[utoipa::path(
+    post, path = "/processes/{id}/bookmarks", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    request_body = AddBookmarkBody,
+    responses((status = 200, body = OutputBookmark), (status = 400, body = String))
+)]
+pub async fn add_output_bookmark(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<AddBookmarkBody>,
+) -> Result<Json<OutputBookmark>, (StatusCode, String)> {
+    state
+        .process_manager
+        .add_output_bookmark(&id, body.name, body.note)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[utoipa::path(
+    delete, path = "/processes/{id}/bookmarks/{name}", tag = "processes",
+    params(
+        ("id" = String, Path, description = "Process ID"),
+        ("name" = String, Path, description = "Bookmark name"),
+    ),
+    responses((status = 204), (status = 404, body = String))
+)]
+pub async fn remove_output_bookmark(
+    State(state): State<AppState>,
+    Path((id, name)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .process_manager
+        .remove_output_bookmark(&id, &name)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+#[utoipa::path(
+    get, path = "/processes/{id}/recordings", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    responses((status = 200, body = Vec<RecordingSummary>), (status = 404, body = String))
+)]
+pub async fn list_output_recordings(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<RecordingSummary>>, (StatusCode, String)> {
+    state
+        .process_manager
+        .list_output_recordings(&id)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+#[utoipa::path(
+    get, path = "/processes/{id}/recordings/{name}", tag = "processes",
+    params(
+        ("id" = String, Path, description = "Process ID"),
+        ("name" = String, Path, description = "Recording name (`latest` for the newest)"),
+    ),
+    responses((status = 200, body = Recording), (status = 404, body = String))
+)]
+pub async fn get_output_recording(
+    State(state): State<AppState>,
+    Path((id, name)): Path<(String, String)>,
+) -> Result<Json<Recording>, (StatusCode, String)> {
+    let name = (name != "latest").then_some(name);
+    state
+        .process_manager
+        .load_output_recording(&id, name.as_deref())
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+#[utoipa::path(
+    put, path = "/processes/{id}/sampling", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    request_body = OutputSampling,
+    responses((status = 200, body = SamplingStatus), (status = 400, body = String))
+)]
+pub async fn set_output_sampling(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(sampling): Json<OutputSampling>,
+) -> Result<Json<Option<SamplingStatus>>, (StatusCode, String)> {
+    state
+        .process_manager
+        .set_output_sampling(id, Some(sampling))
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[utoipa::path(
+    delete, path = "/processes/{id}/sampling", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    responses((status = 204), (status = 400, body = String))
+)]
+pub async fn disable_output_sampling(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .process_manager
+        .set_output_sampling(id, None)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[utoipa::path(
+    get, path = "/processes/{id}/logs/stream", tag = "processes",
+    params(("id" = String, Path, description = "Process ID")),
+    responses((status = 200, description = "Server-sent events", content_type = "text/event-stream"))
+)]
 pub async fn stream_logs(
     State(_state): State<AppState>,
     Path(_id): Path<String>,
@@ -339,12 +851,71 @@ pub async fn stream_logs(
     Sse::new(stream).keep_alive(KeepAlive::default())
 }
 
+#[utoipa::path(
+    get, path = "/diagnostics", tag = "system",
+    responses((status = 200, body = Object), (status = 500, body = String))
+)]
+pub async fn get_diagnostics(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state
+        .process_manager
+        .diagnostics()
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
 // Settings handlers
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct Settings {
     pub color_mode: String,
     pub auto_refresh: bool,
     pub refresh_interval: u32,
+    #[serde(default)]
+    pub log_quota: LogQuotaSettings,
+    /// mDNSによる告知（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub mdns_enabled: Option<bool>,
+    /// 障害注入の許可（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub chaos_enabled: Option<bool>,
+    /// ワークスペースごとのクォータ（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub workspace_quotas: Option<Vec<WorkspaceQuota>>,
+    /// 承認を必要とするツール（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub approval_tools: Option<Vec<String>>,
+    /// 呼び出しを止めて確認を待つツール（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub confirm_tools: Option<Vec<String>>,
+    /// 確認を待つ秒数（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub confirm_timeout_secs: Option<u64>,
+    /// すべてのプロセスの出力の転送先（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub log_sinks: Option<Vec<LogSinkConfig>>,
+    /// すべてのプロセスの出力を伏せるルール（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub redaction_rules: Option<Vec<RedactionRule>>,
+    /// メンテナンスウィンドウ（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub maintenance_windows: Option<Vec<MaintenanceWindow>>,
+    /// MCPツールの応答の大きさの上限（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub response_budget: Option<ResponseBudget>,
+    /// 消費電力量と電気代の見積もりの係数（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub energy_cost: Option<EnergyCostSettings>,
+    /// 外部で起動したプロセスを自動で引き取るワークスペース（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub auto_adopt_workspaces: Option<Vec<String>>,
+    /// 出力バッファのメモリ使用量の上限（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub output_memory: Option<OutputMemorySettings>,
+    /// 通知の送り先のルール（未指定の場合は現在の値を維持）
+    #[serde(default)]
+    pub notification_routes: Option<Vec<NotificationRoute>>,
 }
 
 impl Default for Settings {
@@ -353,10 +924,29 @@ impl Default for Settings {
             color_mode: "dark".to_string(),
             auto_refresh: true,
             refresh_interval: 5000,
+            log_quota: LogQuotaSettings::default(),
+            mdns_enabled: Some(false),
+            chaos_enabled: Some(false),
+            workspace_quotas: Some(Vec::new()),
+            approval_tools: Some(Vec::new()),
+            confirm_tools: Some(Vec::new()),
+            confirm_timeout_secs: Some(crate::process::approval::DEFAULT_CONFIRM_TIMEOUT_SECS),
+            log_sinks: Some(Vec::new()),
+            redaction_rules: Some(Vec::new()),
+            maintenance_windows: Some(Vec::new()),
+            response_budget: Some(ResponseBudget::default()),
+            energy_cost: Some(EnergyCostSettings::default()),
+            auto_adopt_workspaces: Some(Vec::new()),
+            output_memory: Some(OutputMemorySettings::default()),
+            notification_routes: Some(Vec::new()),
         }
     }
 }
 
+#[utoipa::path(
+    get, path = "/settings", tag = "settings",
+    responses((status = 200, body = Settings))
+)]
 pub async fn get_settings(State(state): State<AppState>) -> Result<Json<Settings>, StatusCode> {
     // Persistence Managerから設定を取得
     let db_settings = state
@@ -370,25 +960,110 @@ pub async fn get_settings(State(state): State<AppState>) -> Result<Json<Settings
         color_mode: db_settings.theme,
         auto_refresh: db_settings.enable_auto_restart,
         refresh_interval: db_settings.auto_save_interval.unwrap_or(5000) as u32,
+        log_quota: db_settings.log_quota,
+        mdns_enabled: Some(db_settings.mdns.enabled),
+        chaos_enabled: Some(db_settings.chaos.enabled),
+        workspace_quotas: Some(db_settings.workspace_quotas),
+        approval_tools: Some(db_settings.approvals.tools),
+        confirm_tools: Some(db_settings.approvals.confirm_tools),
+        confirm_timeout_secs: Some(
+            db_settings
+                .approvals
+                .confirm_timeout_secs
+                .unwrap_or(crate::process::approval::DEFAULT_CONFIRM_TIMEOUT_SECS),
+        ),
+        log_sinks: Some(db_settings.log_sinks),
+        redaction_rules: Some(db_settings.redaction_rules),
+        maintenance_windows: Some(db_settings.maintenance_windows),
+        response_budget: Some(db_settings.response_budget),
+        energy_cost: Some(db_settings.energy_cost),
+        auto_adopt_workspaces: Some(db_settings.auto_adopt_workspaces),
+        output_memory: Some(db_settings.output_memory),
+        notification_routes: Some(db_settings.notification_routes),
     };
 
     Ok(Json(settings))
 }
 
+#[utoipa::path(
+    put, path = "/settings", tag = "settings",
+    request_body = Settings,
+    responses((status = 200), (status = 400), (status = 500))
+)]
 pub async fn update_settings(
     State(state): State<AppState>,
     Json(settings): Json<Settings>,
 ) -> Result<StatusCode, StatusCode> {
+    // Web UIで扱わない設定は現在の値を引き継ぐ
+    let current = state
+        .process_manager
+        .get_settings()
+        .await
+        .unwrap_or_default();
+
     // Convert to DB settings
     let db_settings = vantage_persistence::Settings {
         theme: settings.color_mode,
         auto_save_interval: Some(settings.refresh_interval as u64),
-        max_log_lines: None,
+        max_log_lines: current.max_log_lines,
         enable_auto_restart: settings.auto_refresh,
-        default_shell: None,
-        env_variables: HashMap::new(),
+        default_shell: current.default_shell,
+        env_variables: current.env_variables,
         updated_at: chrono::Utc::now(),
+        log_quota: settings.log_quota,
+        suggestion_providers: current.suggestion_providers,
+        mdns: MdnsSettings {
+            enabled: settings.mdns_enabled.unwrap_or(current.mdns.enabled),
+            ..current.mdns
+        },
+        chaos: ChaosSettings {
+            enabled: settings.chaos_enabled.unwrap_or(current.chaos.enabled),
+        },
+        workspace_quotas: settings
+            .workspace_quotas
+            .unwrap_or(current.workspace_quotas),
+        approvals: ApprovalSettings {
+            tools: settings.approval_tools.unwrap_or(current.approvals.tools),
+            confirm_tools: settings
+                .confirm_tools
+                .unwrap_or(current.approvals.confirm_tools),
+            confirm_timeout_secs: settings
+                .confirm_timeout_secs
+                .or(current.approvals.confirm_timeout_secs),
+        },
+        log_sinks: settings.log_sinks.unwrap_or(current.log_sinks),
+        redaction_rules: settings.redaction_rules.unwrap_or(current.redaction_rules),
+        maintenance_windows: settings
+            .maintenance_windows
+            .unwrap_or(current.maintenance_windows),
+        response_budget: settings.response_budget.unwrap_or(current.response_budget),
+        energy_cost: settings.energy_cost.unwrap_or(current.energy_cost),
+        auto_adopt_workspaces: settings
+            .auto_adopt_workspaces
+            .unwrap_or(current.auto_adopt_workspaces),
+        output_memory: settings.output_memory.unwrap_or(current.output_memory),
+        notification_routes: settings
+            .notification_routes
+            .unwrap_or(current.notification_routes),
     };
+    crate::process::workspace_quota::validate(&db_settings.workspace_quotas)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    crate::process::approval::validate(&db_settings.approvals)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    crate::process::log_sinks::validate(&db_settings.log_sinks)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    crate::process::redaction::validate(&db_settings.redaction_rules)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    crate::process::maintenance::validate(&db_settings.maintenance_windows)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    crate::process::energy::validate(&db_settings.energy_cost)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    crate::process::adoption::validate(&db_settings.auto_adopt_workspaces)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    crate::process::buffer_memory::validate(&db_settings.output_memory)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    crate::process::notification_routing::validate(&db_settings.notification_routes)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
 
     // Persistence Managerに設定を保存
     state
@@ -400,15 +1075,482 @@ pub async fn update_settings(
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(
+    get, path = "/settings/schema", tag = "settings",
+    responses((status = 200, body = Vec<SettingSchema>))
+)]
+pub async fn get_settings_schema() -> Json<Vec<SettingSchema>> {
+    Json(crate::settings::schema())
+}
+
+#[utoipa::path(
+    get, path = "/settings/changes", tag = "settings",
+    responses((status = 200, description = "Server-sent `setting_change` events with a JSON body of {key, old_value, new_value, changed_at}, one per changed key", content_type = "text/event-stream"))
+)]
+pub async fn stream_setting_changes(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state
+        .process_manager
+        .event_system()
+        .subscribe_setting_changes();
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(change) => {
+                    let event = Event::default()
+                        .event("setting_change")
+                        .json_data(&change)
+                        .unwrap_or_default();
+                    return Some((Ok(event), receiver));
+                }
+                // 取りこぼした変更は飛ばして続ける
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[utoipa::path(
+    get, path = "/settings/{key}", tag = "settings",
+    params(("key" = String, Path, description = "Dotted setting key, e.g. mdns.enabled")),
+    responses((status = 200, body = SettingValue), (status = 404, body = String))
+)]
+pub async fn get_setting(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Json<SettingValue>, (StatusCode, String)> {
+    let settings = state
+        .process_manager
+        .get_settings()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    crate::settings::get_with_default(&settings, &key)
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+/// 設定のキーの新しい値
+#[derive(Deserialize, ToSchema)]
+pub struct SetSettingBody {
+    /// キーの型の値（nullを指定できるキーではnullで未設定に戻す）
+    #[schema(value_type = Object)]
+    pub value: serde_json::Value,
+}
+
+#[utoipa::path(
+    put, path = "/settings/{key}", tag = "settings",
+    params(("key" = String, Path, description = "Dotted setting key, e.g. mdns.enabled")),
+    request_body = SetSettingBody,
+    responses((status = 200, body = SettingChange), (status = 400, body = String))
+)]
+pub async fn set_setting(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    Json(body): Json<SetSettingBody>,
+) -> Result<Json<SettingChange>, (StatusCode, String)> {
+    state
+        .process_manager
+        .set_setting(&key, body.value)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+// Approval handlers
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListApprovalsQuery {
+    pub status: Option<ApprovalStatus>,
+}
+
+/// 承認・却下の理由
+#[derive(Deserialize, Default, ToSchema)]
+pub struct ApprovalDecision {
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[utoipa::path(
+    get, path = "/approvals", tag = "approvals",
+    params(ListApprovalsQuery),
+    responses((status = 200, body = Vec<ApprovalRequest>), (status = 500, body = String))
+)]
+pub async fn list_approvals(
+    State(state): State<AppState>,
+    Query(query): Query<ListApprovalsQuery>,
+) -> Result<Json<Vec<ApprovalRequest>>, (StatusCode, String)> {
+    state
+        .process_manager
+        .list_approvals(query.status)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+#[utoipa::path(
+    get, path = "/approvals/{id}", tag = "approvals",
+    params(("id" = String, Path, description = "Approval ID")),
+    responses((status = 200, body = ApprovalRequest), (status = 404, body = String))
+)]
+pub async fn get_approval(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<ApprovalRequest>, (StatusCode, String)> {
+    state
+        .process_manager
+        .get_approval(&id)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+#[utoipa::path(
+    post, path = "/approvals/{id}/approve", tag = "approvals",
+    params(("id" = String, Path, description = "Approval ID")),
+    request_body(content = Option<ApprovalDecision>),
+    responses(
+        (status = 200, body = ApprovalRequest),
+        (status = 404, body = String),
+        (status = 409, body = String),
+    )
+)]
+pub async fn approve_approval(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: Option<Extension<CurrentUser>>,
+    decision: Option<Json<ApprovalDecision>>,
+) -> Result<Json<ApprovalRequest>, (StatusCode, String)> {
+    decide_approval(state, id, true, user, decision).await
+}
+
+#[utoipa::path(
+    post, path = "/approvals/{id}/deny", tag = "approvals",
+    params(("id" = String, Path, description = "Approval ID")),
+    request_body(content = Option<ApprovalDecision>),
+    responses(
+        (status = 200, body = ApprovalRequest),
+        (status = 404, body = String),
+        (status = 409, body = String),
+    )
+)]
+pub async fn deny_approval(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: Option<Extension<CurrentUser>>,
+    decision: Option<Json<ApprovalDecision>>,
+) -> Result<Json<ApprovalRequest>, (StatusCode, String)> {
+    decide_approval(state, id, false, user, decision).await
+}
+
+async fn decide_approval(
+    state: AppState,
+    id: String,
+    approve: bool,
+    user: Option<Extension<CurrentUser>>,
+    decision: Option<Json<ApprovalDecision>>,
+) -> Result<Json<ApprovalRequest>, (StatusCode, String)> {
+    // 認証なしの場合は誰が判断したか分からないため、Webコンソールからの操作として記録する
+    let decided_by = user
+        .map(|Extension(user)| user.display_name.unwrap_or(user.subject))
+        .unwrap_or_else(|| "web-console".to_string());
+    let reason = decision.and_then(|Json(d)| d.reason);
+
+    state
+        .process_manager
+        .decide_approval(&id, approve, decided_by, reason)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            let status = if e.contains("not found") {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::CONFLICT
+            };
+            (status, e)
+        })
+}
+
+// Notification inbox handlers
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListNotificationsQuery {
+    /// 未読のみ
+    #[serde(default)]
+    pub unread_only: bool,
+    pub kind: Option<NotificationKind>,
+    /// 最大件数（新しい順）
+    pub limit: Option<usize>,
+}
+
+/// 既読にする通知（`ids` を省略するとすべて）
+#[derive(Deserialize, Default, ToSchema)]
+pub struct MarkNotificationsReadRequest {
+    #[serde(default)]
+    pub ids: Option<Vec<String>>,
+}
+
+/// 未読の通知の件数（ヘッダーのバッジ用）
+#[derive(Serialize, ToSchema)]
+pub struct NotificationCount {
+    pub unread: usize,
+}
+
+/// 既読にした件数と残りの未読の件数
+#[derive(Serialize, ToSchema)]
+pub struct MarkNotificationsReadResponse {
+    pub marked: usize,
+    pub unread: usize,
+}
+
+#[utoipa::path(
+    get, path = "/notifications", tag = "notifications",
+    params(ListNotificationsQuery),
+    responses((status = 200, body = Vec<Notification>), (status = 500, body = String))
+)]
+pub async fn list_notifications(
+    State(state): State<AppState>,
+    Query(query): Query<ListNotificationsQuery>,
+) -> Result<Json<Vec<Notification>>, (StatusCode, String)> {
+    state
+        .process_manager
+        .list_notifications(query.unread_only, query.kind, query.limit)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+#[utoipa::path(
+    get, path = "/notifications/count", tag = "notifications",
+    responses((status = 200, body = NotificationCount), (status = 500, body = String))
+)]
+pub async fn get_notification_count(
+    State(state): State<AppState>,
+) -> Result<Json<NotificationCount>, (StatusCode, String)> {
+    state
+        .process_manager
+        .unread_notification_count()
+        .await
+        .map(|unread| Json(NotificationCount { unread }))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+#[utoipa::path(
+    post, path = "/notifications/read", tag = "notifications",
+    request_body = MarkNotificationsReadRequest,
+    responses((status = 200, body = MarkNotificationsReadResponse), (status = 500, body = String))
+)]
+pub async fn mark_notifications_read(
+    State(state): State<AppState>,
+    request: Option<Json<MarkNotificationsReadRequest>>,
+) -> Result<Json<MarkNotificationsReadResponse>, (StatusCode, String)> {
+    let ids = request.and_then(|Json(request)| request.ids);
+    let internal = |e| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    let marked = state
+        .process_manager
+        .mark_notifications_read(ids.as_deref())
+        .await
+        .map_err(internal)?;
+    let unread = state
+        .process_manager
+        .unread_notification_count()
+        .await
+        .map_err(internal)?;
+    Ok(Json(MarkNotificationsReadResponse { marked, unread }))
+}
+
+// Process chain handlers
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListChainsQuery {
+    /// Only list chains that start from or lead to this process
+    pub process_id: Option<String>,
+}
+
+/// 連鎖の有効・無効
+#[derive(Deserialize, ToSchema)]
+pub struct ChainEnabledRequest {
+    pub enabled: bool,
+}
+
+#[utoipa::path(
+    get, path = "/chains", tag = "chains",
+    params(ListChainsQuery),
+    responses((status = 200, body = Vec<ChainStatus>))
+)]
+pub async fn list_chains(
+    State(state): State<AppState>,
+    Query(query): Query<ListChainsQuery>,
+) -> Json<Vec<ChainStatus>> {
+    Json(
+        state
+            .process_manager
+            .list_chains(query.process_id.as_deref())
+            .await,
+    )
+}
+
+#[utoipa::path(
+    patch, path = "/chains/{id}", tag = "chains",
+    params(("id" = String, Path, description = "Chain ID")),
+    request_body = ChainEnabledRequest,
+    responses((status = 200, body = ChainStatus), (status = 404, body = String))
+)]
+pub async fn set_chain_enabled(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<ChainEnabledRequest>,
+) -> Result<Json<ChainStatus>, (StatusCode, String)> {
+    state
+        .process_manager
+        .set_chain_enabled(&id, req.enabled)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+#[utoipa::path(
+    delete, path = "/chains/{id}", tag = "chains",
+    params(("id" = String, Path, description = "Chain ID")),
+    responses((status = 204), (status = 404, body = String))
+)]
+pub async fn remove_chain(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .process_manager
+        .remove_chain(&id)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+// Process schedule handlers
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListSchedulesQuery {
+    /// Only list schedules that start this process
+    pub process_id: Option<String>,
+}
+
+/// 定期実行の登録
+#[derive(Deserialize, ToSchema)]
+pub struct CreateScheduleRequest {
+    /// Unique schedule ID (default: "<process_id>-schedule")
+    pub schedule_id: Option<String>,
+    pub process_id: String,
+    /// Cron expression in the server's local time (minute hour day month weekday)
+    pub cron: String,
+    pub enabled: Option<bool>,
+}
+
+/// 定期実行の変更
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateScheduleRequest {
+    pub cron: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+#[utoipa::path(
+    get, path = "/schedules", tag = "schedules",
+    params(ListSchedulesQuery),
+    responses((status = 200, body = Vec<ScheduleStatus>))
+)]
+pub async fn list_schedules(
+    State(state): State<AppState>,
+    Query(query): Query<ListSchedulesQuery>,
+) -> Json<Vec<ScheduleStatus>> {
+    Json(
+        state
+            .process_manager
+            .list_schedules(query.process_id.as_deref())
+            .await,
+    )
+}
+
+#[utoipa::path(
+    post, path = "/schedules", tag = "schedules",
+    request_body = CreateScheduleRequest,
+    responses((status = 201, body = ScheduleStatus), (status = 400, body = String))
+)]
+pub async fn create_schedule(
+    State(state): State<AppState>,
+    Json(req): Json<CreateScheduleRequest>,
+) -> Result<(StatusCode, Json<ScheduleStatus>), (StatusCode, String)> {
+    state
+        .process_manager
+        .create_schedule(
+            req.schedule_id,
+            req.process_id,
+            req.cron,
+            req.enabled.unwrap_or(true),
+        )
+        .await
+        .map(|status| (StatusCode::CREATED, Json(status)))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[utoipa::path(
+    patch, path = "/schedules/{id}", tag = "schedules",
+    params(("id" = String, Path, description = "Schedule ID")),
+    request_body = UpdateScheduleRequest,
+    responses((status = 200, body = ScheduleStatus), (status = 400, body = String), (status = 404, body = String))
+)]
+pub async fn update_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateScheduleRequest>,
+) -> Result<Json<ScheduleStatus>, (StatusCode, String)> {
+    state
+        .process_manager
+        .update_schedule(&id, req.cron, req.enabled)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            let status = if e.contains("not found") {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::BAD_REQUEST
+            };
+            (status, e)
+        })
+}
+
+#[utoipa::path(
+    delete, path = "/schedules/{id}", tag = "schedules",
+    params(("id" = String, Path, description = "Schedule ID")),
+    responses((status = 204), (status = 404, body = String))
+)]
+pub async fn remove_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .process_manager
+        .remove_schedule(&id)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
 // Template handlers
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ListTemplatesQuery {
     category: Option<String>,
     tags: Option<String>, // comma-separated tags
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateTemplateRequest {
     pub id: String,
     pub name: String,
@@ -423,7 +1565,7 @@ pub struct CreateTemplateRequest {
     pub tags: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct UpdateTemplateRequest {
     pub name: Option<String>,
     pub description: Option<String>,
@@ -437,12 +1579,51 @@ pub struct UpdateTemplateRequest {
     pub tags: Option<Vec<String>>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct InstantiateTemplateRequest {
     pub process_id: String,
+    /// 変数の値（型のある変数には数値・真偽値も指定できる）
+    #[serde(deserialize_with = "vantage_persistence::template_schema::deserialize_values")]
+    #[schema(value_type = HashMap<String, Object>)]
     pub values: HashMap<String, String>,
 }
 
+#[utoipa::path(
+    get, path = "/templates/changes", tag = "templates",
+    responses((status = 200, description = "Server-sent `template_change` events with a JSON body of {action, template}; only emitted when the server is built with the live-query feature and connected to SurrealDB", content_type = "text/event-stream"))
+)]
+pub async fn stream_template_changes(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state
+        .process_manager
+        .event_system()
+        .subscribe_template_changes();
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(change) => {
+                    let event = Event::default()
+                        .event("template_change")
+                        .json_data(&change)
+                        .unwrap_or_default();
+                    return Some((Ok(event), receiver));
+                }
+                // 取りこぼした変更は飛ばして続ける
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[utoipa::path(
+    get, path = "/templates", tag = "templates",
+    params(ListTemplatesQuery),
+    responses((status = 200, body = Vec<ProcessTemplate>), (status = 500))
+)]
 pub async fn list_templates(
     State(state): State<AppState>,
     Query(query): Query<ListTemplatesQuery>,
@@ -465,6 +1646,11 @@ pub async fn list_templates(
         })
 }
 
+#[utoipa::path(
+    get, path = "/templates/{id}", tag = "templates",
+    params(("id" = String, Path, description = "Template ID")),
+    responses((status = 200, body = ProcessTemplate), (status = 404), (status = 500))
+)]
 pub async fn get_template(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -481,10 +1667,42 @@ pub async fn get_template(
         .ok_or(StatusCode::NOT_FOUND)
 }
 
+#[utoipa::path(
+    get, path = "/templates/{id}/schema", tag = "templates",
+    params(("id" = String, Path, description = "Template ID")),
+    responses(
+        (status = 200, description = "JSON Schema (draft 2020-12) of the template's variables, for rendering an input form", body = Object),
+        (status = 404),
+        (status = 500)
+    )
+)]
+pub async fn get_template_schema(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let template = state
+        .process_manager
+        .get_template(&id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get template: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(
+        vantage_persistence::template_schema::parameters_schema(&template.variables),
+    ))
+}
+
+#[utoipa::path(
+    post, path = "/templates", tag = "templates",
+    request_body = CreateTemplateRequest,
+    responses((status = 201, body = MessageResponse), (status = 400, body = String))
+)]
 pub async fn create_template(
     State(state): State<AppState>,
     Json(req): Json<CreateTemplateRequest>,
-) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<MessageResponse>), (StatusCode, String)> {
     let template = ProcessTemplate {
         id: None,
         template_id: req.id.clone(),
@@ -500,6 +1718,7 @@ pub async fn create_template(
         tags: req.tags,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
+        source: None,
     };
 
     state
@@ -510,12 +1729,22 @@ pub async fn create_template(
 
     Ok((
         StatusCode::CREATED,
-        Json(serde_json::json!({
-            "message": format!("Template '{}' created successfully", req.id)
-        })),
+        Json(MessageResponse {
+            message: format!("Template '{}' created successfully", req.id),
+        }),
     ))
 }
 
+#[utoipa::path(
+    put, path = "/templates/{id}", tag = "templates",
+    params(("id" = String, Path, description = "Template ID")),
+    request_body = UpdateTemplateRequest,
+    responses(
+        (status = 200),
+        (status = 400, body = String),
+        (status = 404, body = String),
+    )
+)]
 pub async fn update_template(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -572,6 +1801,11 @@ pub async fn update_template(
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(
+    delete, path = "/templates/{id}", tag = "templates",
+    params(("id" = String, Path, description = "Template ID")),
+    responses((status = 204), (status = 400, body = String))
+)]
 pub async fn delete_template(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -585,11 +1819,46 @@ pub async fn delete_template(
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[derive(Deserialize, Default, ToSchema)]
+pub struct CaptureTemplateRequest {
+    pub template_id: Option<String>,
+    pub name: Option<String>,
+}
+
+#[utoipa::path(
+    post, path = "/processes/{id}/template", tag = "templates",
+    params(("id" = String, Path, description = "Process ID")),
+    request_body = CaptureTemplateRequest,
+    responses((status = 201, body = CapturedTemplate), (status = 400, body = String))
+)]
+pub async fn capture_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<CaptureTemplateRequest>,
+) -> Result<(StatusCode, Json<CapturedTemplate>), (StatusCode, String)> {
+    state
+        .process_manager
+        .capture_template(id, req.template_id, req.name)
+        .await
+        .map(|captured| (StatusCode::CREATED, Json(captured)))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[utoipa::path(
+    post, path = "/templates/{id}/instantiate", tag = "templates",
+    params(("id" = String, Path, description = "Template ID")),
+    request_body = InstantiateTemplateRequest,
+    responses(
+        (status = 201, body = MessageResponse),
+        (status = 400, body = String),
+        (status = 404, body = String),
+    )
+)]
 pub async fn instantiate_template(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(req): Json<InstantiateTemplateRequest>,
-) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<MessageResponse>), (StatusCode, String)> {
     // テンプレートを取得
     let template = state
         .process_manager
@@ -619,9 +1888,12 @@ pub async fn instantiate_template(
 
     Ok((
         StatusCode::CREATED,
-        Json(serde_json::json!({
-            "message": format!("Process '{}' created from template '{}'", req.process_id, id)
-        })),
+        Json(MessageResponse {
+            message: format!(
+                "Process '{}' created from template '{}'",
+                req.process_id, id
+            ),
+        }),
     ))
 }
 
@@ -630,6 +1902,10 @@ pub async fn instantiate_template(
 // ========================================
 
 /// Get the latest clipboard item
+#[utoipa::path(
+    get, path = "/clipboard", tag = "clipboard",
+    responses((status = 200, body = ClipboardResponse), (status = 404, body = String))
+)]
 pub async fn get_clipboard(
     State(state): State<AppState>,
 ) -> Result<Json<ClipboardResponse>, (StatusCode, String)> {
@@ -652,11 +1928,17 @@ pub async fn get_clipboard(
 }
 
 /// Get clipboard history
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ClipboardHistoryQuery {
     limit: Option<usize>,
 }
 
+#[utoipa::path(
+    get, path = "/clipboard/history", tag = "clipboard",
+    params(ClipboardHistoryQuery),
+    responses((status = 200, body = ClipboardHistoryResponse))
+)]
 pub async fn get_clipboard_history(
     Query(query): Query<ClipboardHistoryQuery>,
     State(state): State<AppState>,
@@ -687,6 +1969,11 @@ pub async fn get_clipboard_history(
 }
 
 /// Set clipboard text content
+#[utoipa::path(
+    post, path = "/clipboard/text", tag = "clipboard",
+    request_body = SetClipboardTextRequest,
+    responses((status = 200, body = ClipboardResponse))
+)]
 pub async fn set_clipboard_text(
     State(state): State<AppState>,
     Json(req): Json<SetClipboardTextRequest>,
@@ -726,6 +2013,11 @@ pub async fn set_clipboard_text(
 }
 
 /// Set clipboard file content
+#[utoipa::path(
+    post, path = "/clipboard/file", tag = "clipboard",
+    request_body = SetClipboardFileRequest,
+    responses((status = 200, body = ClipboardResponse))
+)]
 pub async fn set_clipboard_file(
     State(state): State<AppState>,
     Json(req): Json<SetClipboardFileRequest>,
@@ -759,6 +2051,11 @@ pub async fn set_clipboard_file(
 }
 
 /// Search clipboard items
+#[utoipa::path(
+    get, path = "/clipboard/search", tag = "clipboard",
+    params(SearchClipboardRequest),
+    responses((status = 200, body = ClipboardHistoryResponse))
+)]
 pub async fn search_clipboard(
     Query(req): Query<SearchClipboardRequest>,
     State(state): State<AppState>,
@@ -789,6 +2086,10 @@ pub async fn search_clipboard(
 }
 
 /// Clear all clipboard items
+#[utoipa::path(
+    delete, path = "/clipboard", tag = "clipboard",
+    responses((status = 204))
+)]
 pub async fn clear_clipboard(
     State(state): State<AppState>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
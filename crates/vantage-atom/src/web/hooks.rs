@@ -0,0 +1,533 @@
+//! 外部のシステムから呼び出すWebhook
+//!
+//! auth.yaml の `hooks` に定義したWebhookを `POST /api/hooks/<name>` で受け付けます。
+//! ログインのセッションの代わりにWebhookごとのトークンを `Authorization: Bearer` か
+//! `X-Vantage-Hook-Token` ヘッダーで受け取り、ペイロードをスキーマで検証してから操作を実行します。
+//! 呼び出しの回数はWebhookごとに制限し、トークンを誤った呼び出しも数えます。
+
+use crate::process::ProcessState;
+use crate::web::server::AppState;
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+use vantage_persistence::template_schema::{check_schema, value_to_string};
+use vantage_persistence::{WebhookAction, WebhookConfig, WebhookRateLimit};
+
+/// トークンを受け取るヘッダー（`Authorization: Bearer` の代わり）
+pub const TOKEN_HEADER: &str = "x-vantage-hook-token";
+
+/// 有効なWebhook（トークンを解決済み）
+struct Hook {
+    config: WebhookConfig,
+    token: String,
+}
+
+/// 設定から読み込んだWebhookと、Webhookごとの直近の呼び出し時刻
+#[derive(Default)]
+pub struct HookRegistry {
+    hooks: HashMap<String, Hook>,
+    calls: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl HookRegistry {
+    /// 設定からWebhookを読み込む（トークンの無いもの・名前が重複するものは警告して無視する）
+    pub fn from_config(configs: &[WebhookConfig]) -> Self {
+        let mut hooks = HashMap::new();
+        for config in configs {
+            let token = config
+                .token_env
+                .as_ref()
+                .and_then(|name| std::env::var(name).ok())
+                .or_else(|| config.token.clone())
+                .filter(|token| !token.is_empty());
+            let Some(token) = token else {
+                tracing::warn!("Webhook '{}' has no token and is disabled", config.name);
+                continue;
+            };
+            if config.rate_limit.max_requests == 0 || config.rate_limit.per_secs == 0 {
+                tracing::warn!(
+                    "Webhook '{}' has an empty rate limit and is disabled",
+                    config.name
+                );
+                continue;
+            }
+            if hooks.contains_key(&config.name) {
+                tracing::warn!("Duplicate webhook '{}' is ignored", config.name);
+                continue;
+            }
+            hooks.insert(
+                config.name.clone(),
+                Hook {
+                    config: config.clone(),
+                    token,
+                },
+            );
+        }
+        Self {
+            hooks,
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 有効なWebhookの数
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// 呼び出しを数え、上限を超えていれば次に呼び出せるまでの時間を返す
+    fn check_rate(
+        &self,
+        name: &str,
+        limit: WebhookRateLimit,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let period = Duration::from_secs(limit.per_secs);
+        let mut calls = self.calls.lock().unwrap();
+        let recent = calls.entry(name.to_string()).or_default();
+        while recent
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= period)
+        {
+            recent.pop_front();
+        }
+        if recent.len() >= limit.max_requests as usize {
+            let oldest = recent.front().copied().unwrap_or(now);
+            return Err(period.saturating_sub(now.duration_since(oldest)));
+        }
+        recent.push_back(now);
+        Ok(())
+    }
+}
+
+/// Webhookの呼び出しの結果
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HookResponse {
+    pub hook: String,
+    /// 実行した操作（`start_process`・`restart_process`・`instantiate_template`）
+    pub action: String,
+    pub process_id: String,
+    /// 起動したプロセスのPID（起動中で何もしなかった場合はNone）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub message: String,
+}
+
+/// リクエストのトークン（`Authorization: Bearer` または `token_header`）
+pub(crate) fn token_from_headers<'a>(
+    headers: &'a HeaderMap,
+    token_header: &str,
+) -> Option<&'a str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| {
+            headers
+                .get(token_header)
+                .and_then(|value| value.to_str().ok())
+        })
+        .map(str::trim)
+}
+
+/// 比較にかかる時間が一致した文字数によらないように比較する
+pub(crate) fn tokens_match(given: &str, expected: &str) -> bool {
+    let (given, expected) = (given.as_bytes(), expected.as_bytes());
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// ペイロードをWebhookのスキーマで検証する
+fn validate_payload(config: &WebhookConfig, payload: &Value) -> Result<(), String> {
+    let mut errors = Vec::new();
+    for (pointer, field) in &config.payload_schema {
+        match payload.pointer(pointer).filter(|value| !value.is_null()) {
+            None if field.required => errors.push(format!("{pointer}: is required")),
+            None => {}
+            Some(value) => match value_to_string(value) {
+                Some(text) => errors.extend(
+                    check_schema(&field.schema, &text)
+                        .into_iter()
+                        .map(|message| format!("{pointer}: {message}")),
+                ),
+                None => errors.push(format!(
+                    "{pointer}: must be a string, number or boolean, got {value}"
+                )),
+            },
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Payload does not match the hook's schema: {}",
+            errors.join("; ")
+        ))
+    }
+}
+
+/// プロセスIDに使えない文字を `-` にする
+fn sanitize_id_part(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// テンプレート変数の値をペイロードから取り出す（値の無い変数はテンプレートのデフォルトに任せる）
+fn template_values(
+    variables: &std::collections::BTreeMap<String, String>,
+    payload: &Value,
+) -> Result<HashMap<String, String>, String> {
+    let mut values = HashMap::new();
+    for (name, pointer) in variables {
+        let Some(value) = payload.pointer(pointer).filter(|value| !value.is_null()) else {
+            continue;
+        };
+        let text = value_to_string(value).ok_or_else(|| {
+            format!("{pointer}: must be a string, number or boolean, got {value}")
+        })?;
+        values.insert(name.clone(), text);
+    }
+    Ok(values)
+}
+
+/// `{{変数名}}` を変数の値で置き換えたプロセスID
+fn render_process_id(pattern: &str, values: &HashMap<String, String>) -> String {
+    values
+        .iter()
+        .fold(pattern.to_string(), |id, (name, value)| {
+            id.replace(&format!("{{{{{name}}}}}"), &sanitize_id_part(value))
+        })
+}
+
+/// 呼び出しを拒否・失敗したときのステータスとメッセージ
+type Rejection = (StatusCode, String);
+
+fn error(status: StatusCode, message: impl Into<String>) -> Rejection {
+    (status, message.into())
+}
+
+/// 起動中（一時停止中を含む）か（プロセスが無ければエラー）
+async fn is_running(state: &AppState, process_id: &str) -> Result<bool, Rejection> {
+    let status = state
+        .process_manager
+        .get_process_status(process_id.to_string())
+        .await
+        .map_err(|e| error(StatusCode::NOT_FOUND, e))?;
+    Ok(matches!(
+        status.info.state,
+        ProcessState::Running { .. } | ProcessState::Paused { .. }
+    ))
+}
+
+async fn start(state: &AppState, process_id: &str) -> Result<u32, Rejection> {
+    state
+        .process_manager
+        .start_process(process_id.to_string())
+        .await
+        .map_err(|e| error(StatusCode::UNPROCESSABLE_ENTITY, e))
+}
+
+async fn run_action(
+    state: &AppState,
+    action: &WebhookAction,
+    payload: &Value,
+) -> Result<(String, Option<u32>, String), Rejection> {
+    match action {
+        WebhookAction::StartProcess { process_id } => {
+            if is_running(state, process_id).await? {
+                return Ok((
+                    process_id.clone(),
+                    None,
+                    format!("Process '{process_id}' is already running"),
+                ));
+            }
+            let pid = start(state, process_id).await?;
+            Ok((
+                process_id.clone(),
+                Some(pid),
+                format!("Process '{process_id}' started with PID {pid}"),
+            ))
+        }
+        WebhookAction::RestartProcess { process_id } => {
+            // 存在しないプロセスは404にする
+            is_running(state, process_id).await?;
+            let pid = state
+                .process_manager
+                .restart_process(process_id.clone(), None)
+                .await
+                .map_err(|e| error(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+            Ok((
+                process_id.clone(),
+                Some(pid),
+                format!("Process '{process_id}' restarted with PID {pid}"),
+            ))
+        }
+        WebhookAction::InstantiateTemplate {
+            template_id,
+            process_id,
+            variables,
+        } => {
+            let values = template_values(variables, payload)
+                .map_err(|e| error(StatusCode::BAD_REQUEST, e))?;
+            let process_id = render_process_id(process_id, &values);
+            let template = state
+                .process_manager
+                .get_template(template_id)
+                .await
+                .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e))?
+                .ok_or_else(|| {
+                    error(
+                        StatusCode::NOT_FOUND,
+                        format!("Template '{template_id}' not found"),
+                    )
+                })?;
+            let info = template
+                .instantiate(process_id.clone(), values)
+                .map_err(|e| error(StatusCode::BAD_REQUEST, e))?;
+
+            // 同じIDのプロセスがあれば、新しいペイロードで作り直す
+            let replaced = match is_running(state, &process_id).await {
+                Ok(running) => {
+                    if running {
+                        state
+                            .process_manager
+                            .stop_process(process_id.clone(), None)
+                            .await
+                            .map_err(|e| error(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+                    }
+                    state
+                        .process_manager
+                        .remove_process(process_id.clone())
+                        .await
+                        .map_err(|e| error(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+                    true
+                }
+                Err(_) => false,
+            };
+            state
+                .process_manager
+                .create_process(
+                    process_id.clone(),
+                    info.command,
+                    info.args,
+                    info.env,
+                    info.cwd.map(PathBuf::from),
+                    info.auto_start_on_restore,
+                )
+                .await
+                .map_err(|e| error(StatusCode::BAD_REQUEST, e))?;
+            let pid = start(state, &process_id).await?;
+            let verb = if replaced { "recreated" } else { "created" };
+            Ok((
+                process_id.clone(),
+                Some(pid),
+                format!(
+                    "Process '{process_id}' {verb} from template '{template_id}' and started with PID {pid}"
+                ),
+            ))
+        }
+    }
+}
+
+fn action_name(action: &WebhookAction) -> &'static str {
+    match action {
+        WebhookAction::StartProcess { .. } => "start_process",
+        WebhookAction::RestartProcess { .. } => "restart_process",
+        WebhookAction::InstantiateTemplate { .. } => "instantiate_template",
+    }
+}
+
+/// Webhookを呼び出す
+#[utoipa::path(
+    post, path = "/hooks/{name}", tag = "hooks",
+    params(("name" = String, Path, description = "Hook name from auth.yaml")),
+    request_body(content = Object, description = "Payload checked against the hook's payload_schema (optional)"),
+    responses(
+        (status = 200, body = HookResponse),
+        (status = 400, body = String),
+        (status = 401, body = String),
+        (status = 404, body = String),
+        (status = 429, body = String, description = "Rate limit exceeded; see Retry-After")
+    )
+)]
+pub async fn trigger_hook(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(hook) = state.hooks.hooks.get(&name) else {
+        return error(StatusCode::NOT_FOUND, format!("Hook '{name}' not found")).into_response();
+    };
+    if let Err(retry_after) = state
+        .hooks
+        .check_rate(&name, hook.config.rate_limit, Instant::now())
+    {
+        let secs = retry_after.as_secs().max(1).to_string();
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, secs)],
+            format!("Rate limit of hook '{name}' exceeded"),
+        )
+            .into_response();
+    }
+    match call_hook(&state, &name, hook, &headers, &body).await {
+        Ok(response) => Json(response).into_response(),
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+async fn call_hook(
+    state: &AppState,
+    name: &str,
+    hook: &Hook,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<HookResponse, Rejection> {
+    if !token_from_headers(headers, TOKEN_HEADER)
+        .is_some_and(|token| tokens_match(token, &hook.token))
+    {
+        tracing::warn!("Rejected call to webhook '{}': invalid token", name);
+        return Err(error(StatusCode::UNAUTHORIZED, "Invalid hook token"));
+    }
+
+    // ボディを省略した呼び出し（cronなど）は空のオブジェクトとして扱う
+    let payload: Value = if body.iter().all(u8::is_ascii_whitespace) {
+        Value::Object(Default::default())
+    } else {
+        serde_json::from_slice(body).map_err(|e| {
+            error(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid JSON payload: {e}"),
+            )
+        })?
+    };
+    validate_payload(&hook.config, &payload).map_err(|e| error(StatusCode::BAD_REQUEST, e))?;
+
+    let (process_id, pid, message) = run_action(state, &hook.config.action, &payload).await?;
+    tracing::info!("Webhook '{}': {}", name, message);
+    Ok(HookResponse {
+        hook: name.to_string(),
+        action: action_name(&hook.config.action).to_string(),
+        process_id,
+        pid,
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vantage_persistence::{PayloadField, SchemaType, VariableSchema};
+
+    fn hook(max_requests: u32) -> WebhookConfig {
+        WebhookConfig {
+            name: "deploy".to_string(),
+            token: Some("secret".to_string()),
+            token_env: None,
+            action: WebhookAction::StartProcess {
+                process_id: "web".to_string(),
+            },
+            payload_schema: Default::default(),
+            rate_limit: WebhookRateLimit {
+                max_requests,
+                per_secs: 60,
+            },
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_tokens_and_payload_schema() {
+        let registry = HookRegistry::from_config(&[
+            hook(2),
+            WebhookConfig {
+                name: "no-token".to_string(),
+                token: None,
+                ..hook(2)
+            },
+        ]);
+        assert_eq!(registry.len(), 1);
+
+        // 期間内の上限を超えると、最も古い呼び出しが期間を外れるまで待たせる
+        let now = Instant::now();
+        let limit = hook(2).rate_limit;
+        assert!(registry.check_rate("deploy", limit, now).is_ok());
+        assert!(
+            registry
+                .check_rate("deploy", limit, now + Duration::from_secs(10))
+                .is_ok()
+        );
+        assert_eq!(
+            registry.check_rate("deploy", limit, now + Duration::from_secs(20)),
+            Err(Duration::from_secs(40))
+        );
+        assert!(
+            registry
+                .check_rate("deploy", limit, now + Duration::from_secs(60))
+                .is_ok()
+        );
+
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+
+        let mut config = hook(2);
+        config.payload_schema.insert(
+            "/ref".to_string(),
+            PayloadField {
+                required: true,
+                schema: VariableSchema {
+                    pattern: Some("^refs/heads/".to_string()),
+                    ..Default::default()
+                },
+            },
+        );
+        config.payload_schema.insert(
+            "/pr/number".to_string(),
+            PayloadField {
+                required: false,
+                schema: VariableSchema {
+                    value_type: Some(SchemaType::Integer),
+                    ..Default::default()
+                },
+            },
+        );
+        let ok = serde_json::json!({"ref": "refs/heads/main", "pr": {"number": 12}});
+        assert!(validate_payload(&config, &ok).is_ok());
+        let error =
+            validate_payload(&config, &serde_json::json!({"pr": {"number": "x"}})).unwrap_err();
+        assert!(error.contains("/ref: is required"), "{error}");
+        assert!(error.contains("/pr/number: must be an integer"), "{error}");
+
+        let values = HashMap::from([("BRANCH".to_string(), "feature/login page".to_string())]);
+        assert_eq!(
+            render_process_id("preview-{{BRANCH}}", &values),
+            "preview-feature-login-page"
+        );
+    }
+}
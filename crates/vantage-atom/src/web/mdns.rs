@@ -0,0 +1,279 @@
+//! mDNS（zeroconf）によるWebコンソールの告知と探索
+//!
+//! LAN上に `_vantage._tcp` サービスとしてインスタンス名とポートを告知し、
+//! IPアドレスを知らなくてもチームメイトやツールからコンソールを見つけられるようにします。
+
+use crate::settings::SettingChange;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use vantage_persistence::PersistenceManager;
+
+/// 告知するサービスタイプ
+pub const SERVICE_TYPE: &str = "_vantage._tcp.local.";
+/// 設定の変更を確認する間隔（変更イベントを取りこぼした場合に備える）
+const SETTINGS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 告知中のサービス
+pub struct MdnsAdvertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl MdnsAdvertisement {
+    /// サービスを告知する
+    ///
+    /// `bind` が未指定アドレス（0.0.0.0 / ::）の場合は全インターフェースのアドレスを告知する。
+    pub fn start(
+        instance_name: &str,
+        bind: IpAddr,
+        port: u16,
+        https: bool,
+    ) -> Result<Self, String> {
+        if bind.is_loopback() {
+            return Err(
+                "the web console only listens on localhost; use --bind to expose it on the LAN"
+                    .to_string(),
+            );
+        }
+
+        let daemon = ServiceDaemon::new().map_err(|e| format!("Failed to start mDNS: {e}"))?;
+        let host = format!("{}.local.", sanitize_label(&local_hostname()));
+        let properties = [
+            ("version", env!("CARGO_PKG_VERSION")),
+            ("scheme", if https { "https" } else { "http" }),
+            ("path", "/"),
+        ];
+        let info = if bind.is_unspecified() {
+            ServiceInfo::new(
+                SERVICE_TYPE,
+                instance_name,
+                &host,
+                "",
+                port,
+                &properties[..],
+            )
+            .map(ServiceInfo::enable_addr_auto)
+        } else {
+            ServiceInfo::new(
+                SERVICE_TYPE,
+                instance_name,
+                &host,
+                bind,
+                port,
+                &properties[..],
+            )
+        }
+        .map_err(|e| format!("Invalid mDNS service: {e}"))?;
+
+        let fullname = info.get_fullname().to_string();
+        daemon
+            .register(info)
+            .map_err(|e| format!("Failed to register mDNS service: {e}"))?;
+        tracing::info!("Advertising web console via mDNS as '{}'", fullname);
+
+        Ok(Self { daemon, fullname })
+    }
+
+    /// 告知を取り下げる
+    pub fn stop(self) {
+        if let Err(e) = self.daemon.unregister(&self.fullname) {
+            tracing::warn!("Failed to unregister mDNS service: {}", e);
+        }
+        let _ = self.daemon.shutdown();
+        tracing::info!("Stopped advertising '{}' via mDNS", self.fullname);
+    }
+}
+
+/// 設定（`mdns.enabled`）に従って告知を開始・停止するタスクを起動
+///
+/// `mdns.*` の変更イベントを受けるとすぐに設定を読み直す。
+pub fn spawn_advertiser(
+    persistence_manager: Arc<PersistenceManager>,
+    mut setting_changes: broadcast::Receiver<SettingChange>,
+    bind: IpAddr,
+    port: u16,
+    https: bool,
+) {
+    tokio::spawn(async move {
+        let mut current: Option<(String, MdnsAdvertisement)> = None;
+        loop {
+            let settings = persistence_manager
+                .get_settings()
+                .await
+                .unwrap_or_default()
+                .mdns;
+            let wanted = settings
+                .enabled
+                .then(|| settings.instance_name.unwrap_or_else(default_instance_name));
+
+            let changed = current.as_ref().map(|(name, _)| name) != wanted.as_ref();
+            if changed {
+                if let Some((_, advertisement)) = current.take() {
+                    advertisement.stop();
+                }
+                if let Some(name) = wanted {
+                    match MdnsAdvertisement::start(&name, bind, port, https) {
+                        Ok(advertisement) => current = Some((name, advertisement)),
+                        Err(e) => {
+                            tracing::warn!("mDNS advertisement disabled: {}", e);
+                            // 設定が変わるまで再試行しない
+                            wait_for_settings_change(
+                                &persistence_manager,
+                                &mut setting_changes,
+                                settings.enabled,
+                            )
+                            .await;
+                            continue;
+                        }
+                    }
+                }
+            }
+            wait_for_mdns_change(&mut setting_changes).await;
+        }
+    });
+}
+
+/// `mdns.*` が変わるか、確認の間隔が過ぎるまで待つ
+async fn wait_for_mdns_change(setting_changes: &mut broadcast::Receiver<SettingChange>) {
+    let changed = async {
+        loop {
+            match setting_changes.recv().await {
+                Ok(change) if change.key.starts_with("mdns.") => return,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                // 送る側がなくなったら間隔ごとの確認だけを続ける
+                Err(broadcast::error::RecvError::Closed) => std::future::pending().await,
+            }
+        }
+    };
+    tokio::select! {
+        _ = changed => {}
+        _ = tokio::time::sleep(SETTINGS_POLL_INTERVAL) => {}
+    }
+}
+
+async fn wait_for_settings_change(
+    persistence_manager: &PersistenceManager,
+    setting_changes: &mut broadcast::Receiver<SettingChange>,
+    enabled: bool,
+) {
+    loop {
+        wait_for_mdns_change(setting_changes).await;
+        let settings = persistence_manager.get_settings().await.unwrap_or_default();
+        if settings.mdns.enabled != enabled {
+            return;
+        }
+    }
+}
+
+/// LAN上で見つかったWebコンソール
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredConsole {
+    pub instance_name: String,
+    pub hostname: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+    pub version: Option<String>,
+    /// 接続先URL（IPv4アドレスを優先）
+    pub url: Option<String>,
+}
+
+/// 指定時間だけLANを探索し、見つかったWebコンソールを返す
+pub async fn discover(timeout: Duration) -> Result<Vec<DiscoveredConsole>, String> {
+    tokio::task::spawn_blocking(move || {
+        let daemon = ServiceDaemon::new().map_err(|e| format!("Failed to start mDNS: {e}"))?;
+        let receiver = daemon
+            .browse(SERVICE_TYPE)
+            .map_err(|e| format!("Failed to browse mDNS: {e}"))?;
+
+        let deadline = Instant::now() + timeout;
+        let mut consoles: Vec<DiscoveredConsole> = Vec::new();
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            let Ok(event) = receiver.recv_timeout(remaining) else {
+                break;
+            };
+            if let ServiceEvent::ServiceResolved(info) = event {
+                let console = to_discovered(&info);
+                consoles.retain(|c| c.instance_name != console.instance_name);
+                consoles.push(console);
+            }
+        }
+        let _ = daemon.shutdown();
+
+        consoles.sort_by(|a, b| a.instance_name.cmp(&b.instance_name));
+        Ok(consoles)
+    })
+    .await
+    .map_err(|e| format!("mDNS discovery task failed: {e}"))?
+}
+
+fn to_discovered(info: &ServiceInfo) -> DiscoveredConsole {
+    let instance_name = info
+        .get_fullname()
+        .strip_suffix(&format!(".{SERVICE_TYPE}"))
+        .unwrap_or(info.get_fullname())
+        .to_string();
+    let mut addresses: Vec<IpAddr> = info.get_addresses().iter().copied().collect();
+    addresses.sort_by_key(|a| (a.is_ipv6(), *a));
+    let scheme = info.get_property_val_str("scheme").unwrap_or("http");
+    let path = info.get_property_val_str("path").unwrap_or("/");
+    let url = addresses.first().map(|addr| match addr {
+        IpAddr::V4(v4) => format!("{scheme}://{v4}:{}{path}", info.get_port()),
+        IpAddr::V6(v6) => format!("{scheme}://[{v6}]:{}{path}", info.get_port()),
+    });
+
+    DiscoveredConsole {
+        instance_name,
+        hostname: info.get_hostname().trim_end_matches('.').to_string(),
+        addresses,
+        port: info.get_port(),
+        version: info.get_property_val_str("version").map(str::to_string),
+        url,
+    }
+}
+
+fn local_hostname() -> String {
+    sysinfo::System::host_name().unwrap_or_else(|| "vantage".to_string())
+}
+
+/// 既定のインスタンス名（例: "Vantage on devbox"）
+pub fn default_instance_name() -> String {
+    format!("Vantage on {}", local_hostname())
+}
+
+/// ホスト名をDNSラベルとして使える形にする
+fn sanitize_label(name: &str) -> String {
+    let label: String = name
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    if label.trim_matches('-').is_empty() {
+        "vantage".to_string()
+    } else {
+        label
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_label() {
+        assert_eq!(sanitize_label("devbox.corp.example.com"), "devbox");
+        assert_eq!(sanitize_label("my box_1"), "my-box-1");
+        assert_eq!(sanitize_label(""), "vantage");
+    }
+
+    #[test]
+    fn test_loopback_bind_is_rejected() {
+        let result = MdnsAdvertisement::start("test", "127.0.0.1".parse().unwrap(), 12700, false);
+        assert!(result.is_err());
+    }
+}
@@ -1,6 +1,13 @@
 pub mod api;
 pub mod assets;
+pub mod auth;
+pub mod gates;
 pub mod handlers;
+pub mod hooks;
+pub mod mdns;
+pub mod openapi;
+pub mod process_feed;
 pub mod server;
+pub mod tls;
 
-pub use server::start_web_server;
+pub use server::{WebServerOptions, start_web_server, start_web_server_with_options};
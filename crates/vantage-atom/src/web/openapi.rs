@@ -0,0 +1,150 @@
+//! Web APIのOpenAPIドキュメント
+//!
+//! 各ハンドラーの `#[utoipa::path]` からドキュメントを生成し、`/api/openapi.json` で公開します。
+//! Webコンソールの型付きクライアント（ui/web/src/api/generated.ts）はこのドキュメントから生成します。
+
+use super::auth;
+use super::gates;
+use super::handlers;
+use super::hooks;
+use axum::response::Json;
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Vantage Web API",
+        description = "REST API of the Vantage web console"
+    ),
+    servers((url = "/api")),
+    paths(
+        handlers::get_status,
+        handlers::get_dashboard,
+        handlers::list_processes,
+        handlers::process_list_changes,
+        handlers::create_process,
+        handlers::get_process,
+        handlers::remove_process,
+        handlers::update_process,
+        handlers::start_process,
+        handlers::stop_process,
+        handlers::update_process_config,
+        handlers::get_process_logs,
+        handlers::get_process_lines,
+        handlers::list_output_bookmarks,
+        handlers::add_output_bookmark,
+        handlers::remove_output_bookmark,
+        handlers::list_output_recordings,
+        handlers::get_output_recording,
+        handlers::get_process_runbook,
+        handlers::get_process_timeline,
+        handlers::set_output_sampling,
+        handlers::disable_output_sampling,
+        handlers::stream_logs,
+        handlers::capture_template,
+        handlers::get_diagnostics,
+        handlers::list_approvals,
+        handlers::get_approval,
+        handlers::approve_approval,
+        handlers::deny_approval,
+        handlers::list_notifications,
+        handlers::get_notification_count,
+        handlers::mark_notifications_read,
+        handlers::list_chains,
+        handlers::set_chain_enabled,
+        handlers::remove_chain,
+        handlers::list_schedules,
+        handlers::create_schedule,
+        handlers::update_schedule,
+        handlers::remove_schedule,
+        auth::current_user,
+        hooks::trigger_hook,
+        gates::check_gate,
+        handlers::get_settings,
+        handlers::update_settings,
+        handlers::get_settings_schema,
+        handlers::stream_setting_changes,
+        handlers::get_setting,
+        handlers::set_setting,
+        handlers::list_templates,
+        handlers::stream_template_changes,
+        handlers::create_template,
+        handlers::get_template,
+        handlers::get_template_schema,
+        handlers::update_template,
+        handlers::delete_template,
+        handlers::instantiate_template,
+        handlers::get_clipboard,
+        handlers::clear_clipboard,
+        handlers::get_clipboard_history,
+        handlers::set_clipboard_text,
+        handlers::set_clipboard_file,
+        handlers::search_clipboard,
+    ),
+    tags(
+        (name = "system", description = "Server status and diagnostics"),
+        (name = "processes", description = "Managed processes"),
+        (name = "approvals", description = "Approval requests for dangerous operations"),
+        (name = "notifications", description = "Inbox of notable notifications with read state"),
+        (name = "chains", description = "Process chains (start a process after another succeeds)"),
+        (name = "schedules", description = "Process schedules (start a process on a cron schedule)"),
+        (name = "auth", description = "Web console sign-in"),
+        (name = "hooks", description = "Webhooks for external systems, authenticated by per-hook tokens"),
+        (name = "gates", description = "Readiness gates for external tools, authenticated by per-gate tokens"),
+        (name = "settings", description = "Server settings"),
+        (name = "templates", description = "Process templates"),
+        (name = "clipboard", description = "Shared clipboard"),
+    )
+)]
+pub struct ApiDoc;
+
+/// OpenAPIドキュメント（JSON）
+pub fn openapi_json() -> String {
+    ApiDoc::openapi()
+        .to_pretty_json()
+        .expect("OpenAPI document should serialize")
+}
+
+pub async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_committed_openapi_json_is_up_to_date() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../ui/web/openapi.json");
+        let committed = std::fs::read_to_string(path).expect("ui/web/openapi.json should exist");
+        assert!(
+            committed.trim_end() == openapi_json(),
+            "ui/web/openapi.json is out of date; run `npm run generate:api` in ui/web"
+        );
+    }
+
+    #[test]
+    fn test_every_api_route_is_documented() {
+        let doc = ApiDoc::openapi();
+        for path in [
+            "/processes",
+            "/processes/{id}",
+            "/processes/{id}/logs",
+            "/processes/{id}/lines",
+            "/approvals/{id}/approve",
+            "/notifications/count",
+            "/chains/{id}",
+            "/schedules/{id}",
+            "/settings",
+            "/settings/{key}",
+            "/templates/{id}/instantiate",
+            "/clipboard/search",
+        ] {
+            assert!(doc.paths.paths.contains_key(path), "{path} is missing");
+        }
+        let schemas = doc.components.expect("components").schemas;
+        for schema in ["ProcessStatus", "CreateProcessRequest", "ApprovalRequest"] {
+            assert!(schemas.contains_key(schema), "{schema} is missing");
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//! Webコンソール向けのプロセス一覧の差分配信
+//!
+//! WebSocket（`GET /api/processes/changes`）で、接続したときに一覧全体（`snapshot`）を送り、
+//! 以降は追加・更新・削除されたプロセスだけ（`delta`）を通し番号（`seq`）付きで送ります。
+//! プロセスのイベントが起きるとすぐに、それ以外の変更も `FEED_POLL_INTERVAL` ごとに検出します。
+//!
+//! 番号が飛んだクライアントは `{"type": "resync"}` を送ると一覧全体を受け取り直せます。
+//! 再接続するときに `?since=<seq>` を付けると、履歴に残っている範囲の差分だけを受け取ります。
+
+use crate::process::ProcessManager;
+use crate::security::permissions::mask_secrets;
+use axum::extract::ws::{Message, WebSocket};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+use vantage_persistence::Permissions;
+
+/// イベントのない変更（定義の編集など）を確認する間隔
+pub const FEED_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// 再接続したクライアントに送り直せる差分の数
+pub const FEED_HISTORY: usize = 256;
+/// 続けて起きたイベントをまとめて1つの差分にするための待ち時間
+const EVENT_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// 前回の通し番号からの変化
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ProcessListDelta {
+    pub seq: u64,
+    /// 追加されたプロセス（`GET /api/processes` の要素と同じ形）
+    #[schema(value_type = Vec<Object>)]
+    pub added: Vec<serde_json::Value>,
+    /// 状態や定義が変わったプロセス（変更後の全体）
+    #[schema(value_type = Vec<Object>)]
+    pub updated: Vec<serde_json::Value>,
+    /// 削除されたプロセスのID
+    pub removed: Vec<String>,
+}
+
+impl ProcessListDelta {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// サーバーからクライアントへのメッセージ
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProcessListMessage {
+    /// 一覧全体（IDの順）
+    Snapshot {
+        seq: u64,
+        #[schema(value_type = Vec<Object>)]
+        processes: Vec<serde_json::Value>,
+    },
+    Delta(ProcessListDelta),
+}
+
+impl ProcessListMessage {
+    fn seq(&self) -> u64 {
+        match self {
+            Self::Snapshot { seq, .. } => *seq,
+            Self::Delta(delta) => delta.seq,
+        }
+    }
+}
+
+/// クライアントからサーバーへのメッセージ
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// 一覧全体を送り直してもらう
+    Resync,
+}
+
+#[derive(Default)]
+struct FeedState {
+    seq: u64,
+    processes: BTreeMap<String, serde_json::Value>,
+    history: VecDeque<ProcessListDelta>,
+}
+
+/// プロセス一覧の差分を記録して配信する（Webサーバーごとに1つ）
+pub struct ProcessListFeed {
+    state: Mutex<FeedState>,
+    sender: broadcast::Sender<ProcessListDelta>,
+    started: AtomicBool,
+}
+
+impl Default for ProcessListFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessListFeed {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(FEED_HISTORY);
+        Self {
+            state: Mutex::new(FeedState::default()),
+            sender,
+            started: AtomicBool::new(false),
+        }
+    }
+
+    /// 一覧を読み直し、変化があれば差分を記録して配信する
+    pub async fn refresh(&self, process_manager: &ProcessManager) -> Option<ProcessListDelta> {
+        let current: BTreeMap<String, serde_json::Value> = process_manager
+            .list_processes(None)
+            .await
+            .into_iter()
+            .map(|info| {
+                (
+                    info.id.clone(),
+                    serde_json::to_value(&info).unwrap_or_default(),
+                )
+            })
+            .collect();
+
+        let mut state = self.state.lock().unwrap();
+        let mut delta = diff(&state.processes, &current);
+        if delta.is_empty() {
+            return None;
+        }
+        state.seq += 1;
+        delta.seq = state.seq;
+        state.processes = current;
+        if state.history.len() >= FEED_HISTORY {
+            state.history.pop_front();
+        }
+        state.history.push_back(delta.clone());
+        // 番号の順に届くよう、記録と同じロックの中で送る
+        let _ = self.sender.send(delta.clone());
+        Some(delta)
+    }
+
+    /// 現在の一覧全体
+    pub fn snapshot(&self) -> ProcessListMessage {
+        let state = self.state.lock().unwrap();
+        ProcessListMessage::Snapshot {
+            seq: state.seq,
+            processes: state.processes.values().cloned().collect(),
+        }
+    }
+
+    /// 接続したクライアントに最初に送るメッセージと、以降の差分の購読を返す
+    ///
+    /// `since` より後の差分がすべて履歴に残っていればそれを、そうでなければ一覧全体を返す。
+    pub async fn connect(
+        self: &Arc<Self>,
+        process_manager: &Arc<ProcessManager>,
+        since: Option<u64>,
+    ) -> (
+        Vec<ProcessListMessage>,
+        broadcast::Receiver<ProcessListDelta>,
+    ) {
+        self.ensure_started(process_manager);
+        // 読み直す前に購読し、その間の差分を取りこぼさないようにする
+        let receiver = self.sender.subscribe();
+        self.refresh(process_manager).await;
+
+        let state = self.state.lock().unwrap();
+        let replayable = since.filter(|since| {
+            *since == state.seq
+                || *since < state.seq
+                    && state
+                        .history
+                        .front()
+                        .is_some_and(|oldest| oldest.seq <= since + 1)
+        });
+        let initial = match replayable {
+            Some(since) => state
+                .history
+                .iter()
+                .filter(|delta| delta.seq > since)
+                .cloned()
+                .map(ProcessListMessage::Delta)
+                .collect(),
+            None => {
+                drop(state);
+                vec![self.snapshot()]
+            }
+        };
+        (initial, receiver)
+    }
+
+    /// 購読しているクライアントがいる間、イベントと一定の間隔で一覧を読み直すタスクを起動する
+    fn ensure_started(self: &Arc<Self>, process_manager: &Arc<ProcessManager>) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let feed: Weak<Self> = Arc::downgrade(self);
+        let process_manager = process_manager.clone();
+        tokio::spawn(async move {
+            let mut events = process_manager.event_system().subscribe();
+            loop {
+                tokio::select! {
+                    event = events.recv() => {
+                        if let Err(broadcast::error::RecvError::Closed) = event {
+                            return;
+                        }
+                        tokio::time::sleep(EVENT_DEBOUNCE).await;
+                        // まとめて起きたイベントは1回の読み直しで済ませる
+                        while events.try_recv().is_ok() {}
+                    }
+                    _ = tokio::time::sleep(FEED_POLL_INTERVAL) => {}
+                }
+                let Some(feed) = feed.upgrade() else {
+                    return;
+                };
+                if feed.sender.receiver_count() > 0 {
+                    feed.refresh(&process_manager).await;
+                }
+            }
+        });
+    }
+}
+
+/// 2つの一覧の差分（通し番号は呼び出し側で付ける）
+fn diff(
+    before: &BTreeMap<String, serde_json::Value>,
+    after: &BTreeMap<String, serde_json::Value>,
+) -> ProcessListDelta {
+    let mut delta = ProcessListDelta::default();
+    for (id, process) in after {
+        match before.get(id) {
+            None => delta.added.push(process.clone()),
+            Some(previous) if previous != process => delta.updated.push(process.clone()),
+            Some(_) => {}
+        }
+    }
+    delta.removed = before
+        .keys()
+        .filter(|id| !after.contains_key(*id))
+        .cloned()
+        .collect();
+    delta
+}
+
+/// WebSocketの接続が閉じるまで、一覧の差分を送り続ける
+pub async fn serve_connection(
+    mut socket: WebSocket,
+    feed: Arc<ProcessListFeed>,
+    process_manager: Arc<ProcessManager>,
+    since: Option<u64>,
+    permissions: Option<Permissions>,
+) {
+    let permissions = permissions.as_ref();
+    let (initial, mut receiver) = feed.connect(&process_manager, since).await;
+    let mut last_seq = 0;
+    for message in initial {
+        last_seq = message.seq();
+        if send(&mut socket, &message, permissions).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        let message = tokio::select! {
+            delta = receiver.recv() => match delta {
+                // 最初に送った一覧・差分に含まれている
+                Ok(delta) if delta.seq <= last_seq => continue,
+                Ok(delta) => ProcessListMessage::Delta(delta),
+                // 送るのが追いつかなかった場合は一覧全体を送り直す
+                Err(broadcast::error::RecvError::Lagged(_)) => feed.snapshot(),
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Resync) => feed.snapshot(),
+                        Err(e) => {
+                            tracing::debug!("Ignoring process feed message: {}", e);
+                            continue;
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        };
+        last_seq = message.seq();
+        if send(&mut socket, &message, permissions).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// シークレットを読めないユーザーには、環境変数や引数の値を伏せて送る
+async fn send(
+    socket: &mut WebSocket,
+    message: &ProcessListMessage,
+    permissions: Option<&Permissions>,
+) -> Result<(), axum::Error> {
+    let text = mask_secrets(permissions, message).to_string();
+    socket.send(Message::Text(text)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_reports_added_updated_and_removed() {
+        let before: BTreeMap<String, serde_json::Value> = [
+            ("api".to_string(), json!({"id": "api", "state": "Stopped"})),
+            ("db".to_string(), json!({"id": "db", "state": "Stopped"})),
+        ]
+        .into();
+        let after: BTreeMap<String, serde_json::Value> = [
+            ("api".to_string(), json!({"id": "api", "state": "Running"})),
+            ("web".to_string(), json!({"id": "web", "state": "Stopped"})),
+        ]
+        .into();
+
+        let delta = diff(&before, &after);
+        assert_eq!(delta.added, vec![json!({"id": "web", "state": "Stopped"})]);
+        assert_eq!(
+            delta.updated,
+            vec![json!({"id": "api", "state": "Running"})]
+        );
+        assert_eq!(delta.removed, vec!["db"]);
+        assert!(diff(&after, &after).is_empty());
+    }
+}
@@ -1,58 +1,221 @@
+use super::auth::{self, AuthState};
+use super::gates::GateRegistry;
+use super::hooks::HookRegistry;
+use super::mdns;
+use super::process_feed::ProcessListFeed;
+use super::tls::{self, TlsCertSource, TlsOptions};
 use crate::process::ProcessManager;
+use crate::security::permissions;
+use crate::server_admin;
 use axum::{
     Router,
+    extract::{Request, State},
     http::StatusCode,
+    middleware::{self, Next},
     response::{Html, IntoResponse, Response},
+    routing::{get, post},
 };
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
 use vantage_persistence::PersistenceManager;
 
+/// Webサーバーの起動オプション
+#[derive(Debug, Clone)]
+pub struct WebServerOptions {
+    /// 待ち受けるアドレス（既定はlocalhostのみ）
+    pub bind: IpAddr,
+    /// HTTPのポート（使用中の場合は空いているポートを探す）
+    pub port: u16,
+    /// HTTPSの設定（未設定の場合はHTTPのみ）
+    pub tls: Option<TlsOptions>,
+}
+
+impl Default for WebServerOptions {
+    fn default() -> Self {
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 12700,
+            tls: None,
+        }
+    }
+}
+
+impl WebServerOptions {
+    /// ダッシュボードのURL
+    pub fn dashboard_url(&self, port: u16) -> String {
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        format!("{scheme}://localhost:{port}")
+    }
+}
+
 pub async fn start_web_server(
     process_manager: ProcessManager,
     persistence_manager: Arc<PersistenceManager>,
     port: u16,
 ) -> Result<u16, Box<dyn std::error::Error>> {
-    let app = create_app(process_manager, persistence_manager);
+    let options = WebServerOptions {
+        port,
+        ..Default::default()
+    };
+    start_web_server_with_options(process_manager, persistence_manager, options).await
+}
+
+/// オプションを指定してWebサーバーを起動し、ダッシュボードを配信するポートを返す
+///
+/// HTTPSが有効な場合はHTTPSのポートを返し、HTTPのポートではリダイレクトのみを行う。
+/// 設定でmDNSが有効になっていれば、そのポートをLANに告知する。
+pub async fn start_web_server_with_options(
+    process_manager: ProcessManager,
+    persistence_manager: Arc<PersistenceManager>,
+    options: WebServerOptions,
+) -> Result<u16, Box<dyn std::error::Error>> {
+    let https = options.tls.is_some();
+    let bind = options.bind;
+    let setting_changes = process_manager.event_system().subscribe_setting_changes();
+    let port = serve(process_manager, persistence_manager.clone(), options).await?;
+    mdns::spawn_advertiser(persistence_manager, setting_changes, bind, port, https);
+    Ok(port)
+}
 
-    // Try to bind to the specified port, or find an available one
-    let (listener, actual_port) = bind_to_available_port(port).await?;
+/// Webサーバーを起動してポートを返す（mDNSの告知は行わない）
+///
+/// 起動したタスクは `server_admin` に登録し、`restart_web_server` で止めて起動し直せるようにする。
+pub(crate) async fn serve(
+    process_manager: ProcessManager,
+    persistence_manager: Arc<PersistenceManager>,
+    options: WebServerOptions,
+) -> Result<u16, Box<dyn std::error::Error>> {
+    let registration = (
+        process_manager.clone(),
+        persistence_manager.clone(),
+        options.clone(),
+    );
+    // 認証設定（設定ディレクトリの auth.yaml）があればログインを必須にする
+    let auth_config = persistence_manager.load_auth_config().await?;
+    let auth = AuthState::from_config(&auth_config).map(Arc::new);
+    if auth.is_some() {
+        tracing::info!("Web console authentication enabled");
+    }
+    let hooks = HookRegistry::from_config(&auth_config.hooks);
+    if !hooks.is_empty() {
+        tracing::info!("{} webhook(s) enabled", hooks.len());
+    }
+    let gates = GateRegistry::from_config(&auth_config.gates);
+    if !gates.is_empty() {
+        tracing::info!("{} gate(s) enabled", gates.len());
+    }
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], actual_port));
-    tracing::info!("Web dashboard started on http://{}", addr);
+    let app = create_app(process_manager, persistence_manager, auth, hooks, gates);
 
-    // Spawn the server in a background task
-    tokio::spawn(async move {
-        if let Err(e) = axum::serve(listener, app).await {
+    let Some(tls) = options.tls else {
+        // Try to bind to the specified port, or find an available one
+        let (listener, actual_port) = bind_to_available_port(options.bind, options.port).await?;
+
+        let addr = SocketAddr::new(options.bind, actual_port);
+        tracing::info!("Web dashboard started on http://{}", addr);
+
+        // Spawn the server in a background task
+        let task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("Web server error: {}", e);
+            }
+        });
+
+        server_admin::register_web_server(registration, actual_port, vec![task]);
+        return Ok(actual_port);
+    };
+
+    let (cert_path, key_path) = match &tls.cert {
+        TlsCertSource::Files {
+            cert_path,
+            key_path,
+        } => (cert_path.clone(), key_path.clone()),
+        TlsCertSource::LocalCa { hostnames } => {
+            let dir = tls::default_tls_dir();
+            let hostnames = hostnames.clone();
+            let certs = tokio::task::spawn_blocking(move || {
+                tls::ensure_local_certificates(&dir, &hostnames)
+            })
+            .await??;
+            tracing::info!(
+                "Using certificates issued by the local CA ({})",
+                certs.ca_cert_path.display()
+            );
+            (certs.cert_path, certs.key_path)
+        }
+    };
+    let rustls_config = tls::load_rustls_config(&cert_path, &key_path).await?;
+
+    let (listener, https_port) = bind_to_available_port(options.bind, tls.https_port).await?;
+    tracing::info!(
+        "Web dashboard started on https://{}",
+        SocketAddr::new(options.bind, https_port)
+    );
+    let listener = listener.into_std()?;
+    let mut tasks = vec![tokio::spawn(async move {
+        if let Err(e) = axum_server::from_tcp_rustls(listener, rustls_config)
+            .serve(app.into_make_service())
+            .await
+        {
             tracing::error!("Web server error: {}", e);
         }
-    });
+    })];
 
-    Ok(actual_port)
+    if tls.redirect_http {
+        // リダイレクト先が変わらないよう、HTTPのポートは指定どおりにのみ待ち受ける
+        let addr = SocketAddr::new(options.bind, options.port);
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => {
+                tracing::info!("Redirecting http://{} to HTTPS", addr);
+                tasks.push(tokio::spawn(async move {
+                    if let Err(e) = axum::serve(listener, tls::redirect_app(https_port)).await {
+                        tracing::error!("HTTP redirect server error: {}", e);
+                    }
+                }));
+            }
+            Err(e) => tracing::warn!(
+                "HTTP redirect disabled: port {} unavailable: {}",
+                options.port,
+                e
+            ),
+        }
+    }
+
+    server_admin::register_web_server(registration, https_port, tasks);
+    Ok(https_port)
 }
 
 async fn bind_to_available_port(
+    ip: IpAddr,
     preferred_port: u16,
 ) -> Result<(tokio::net::TcpListener, u16), Box<dyn std::error::Error>> {
     // First try the preferred port
-    let addr = SocketAddr::from(([127, 0, 0, 1], preferred_port));
+    let addr = SocketAddr::new(ip, preferred_port);
     match tokio::net::TcpListener::bind(addr).await {
         Ok(listener) => {
             tracing::info!("Successfully bound to preferred port {}", preferred_port);
             Ok((listener, preferred_port))
         }
         Err(e) => {
+            let owner = if e.kind() == std::io::ErrorKind::AddrInUse {
+                let owner =
+                    crate::process::port_owner::find(preferred_port, &Default::default()).await;
+                format!(" ({})", owner.describe())
+            } else {
+                String::new()
+            };
             tracing::warn!(
-                "Port {} is already in use: {}. Trying to find an available port...",
+                "Port {} is already in use: {}{}. Trying to find an available port...",
                 preferred_port,
-                e
+                e,
+                owner
             );
 
             // Try a range of ports from preferred_port+1 to preferred_port+100
             for offset in 1..=100 {
                 let try_port = preferred_port + offset;
-                let addr = SocketAddr::from(([127, 0, 0, 1], try_port));
+                let addr = SocketAddr::new(ip, try_port);
 
                 match tokio::net::TcpListener::bind(addr).await {
                     Ok(listener) => {
@@ -64,7 +227,7 @@ async fn bind_to_available_port(
             }
 
             // If still no port found, let the OS assign one
-            let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+            let addr = SocketAddr::new(ip, 0);
             let listener = tokio::net::TcpListener::bind(addr).await?;
             let actual_port = listener.local_addr()?.port();
             tracing::info!("OS assigned port {}", actual_port);
@@ -73,19 +236,39 @@ async fn bind_to_available_port(
     }
 }
 
-fn create_app(
+pub fn create_app(
     process_manager: ProcessManager,
     persistence_manager: Arc<PersistenceManager>,
+    auth: Option<Arc<AuthState>>,
+    hooks: HookRegistry,
+    gates: GateRegistry,
 ) -> Router {
     let app_state = AppState {
         process_manager: Arc::new(process_manager),
         persistence_manager,
+        auth,
+        hooks: Arc::new(hooks),
+        gates: Arc::new(gates),
+        process_feed: Default::default(),
     };
 
     Router::new()
-        .route("/", axum::routing::get(index_handler))
-        .nest("/api", super::api::create_api_routes())
+        .route("/", get(index_handler))
+        .route("/auth/login", get(auth::login))
+        .route("/auth/callback", get(auth::callback))
+        .route("/auth/logout", post(auth::logout))
+        .nest(
+            "/api",
+            super::api::create_api_routes().layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                fence_mutations,
+            )),
+        )
         .fallback(static_handler)
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth::require_session,
+        ))
         .layer(CorsLayer::permissive())
         .with_state(app_state)
 }
@@ -94,6 +277,33 @@ fn create_app(
 pub struct AppState {
     pub process_manager: Arc<ProcessManager>,
     pub persistence_manager: Arc<PersistenceManager>,
+    /// Webコンソールの認証（未設定の場合は認証なし）
+    pub auth: Option<Arc<AuthState>>,
+    /// 外部のシステムから呼び出すWebhook
+    pub hooks: Arc<HookRegistry>,
+    /// 外部のツールが準備完了を待つゲート
+    pub gates: Arc<GateRegistry>,
+    /// Webコンソールに送るプロセス一覧の差分
+    pub process_feed: Arc<ProcessListFeed>,
+}
+
+/// スナップショット・復元の間は、プロセスを変更するリクエストを待たせる
+async fn fence_mutations(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let path = format!("/api{}", request.uri().path());
+    let requirements = permissions::route_requirements(request.method(), &path).unwrap_or_default();
+    if !permissions::mutates_processes(&requirements) {
+        return next.run(request).await;
+    }
+    let name = format!("{} {}", request.method(), path);
+    match state
+        .process_manager
+        .fence()
+        .scope(&name, next.run(request))
+        .await
+    {
+        Ok(response) => response,
+        Err(message) => (StatusCode::SERVICE_UNAVAILABLE, message).into_response(),
+    }
 }
 
 async fn index_handler() -> impl IntoResponse {
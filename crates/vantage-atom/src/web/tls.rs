@@ -0,0 +1,270 @@
+//! WebダッシュボードのHTTPS対応
+//!
+//! ユーザー指定の証明書、またはデータディレクトリの `tls` に作成するローカルCA（mkcert方式）で
+//! 発行したサーバー証明書を使ってHTTPSで配信します。ローカルCAの証明書（ca.pem）を
+//! ブラウザやOSに一度信頼させれば、以降は警告なしで接続できます。
+
+use crate::security::private_file;
+use axum::{
+    Router,
+    extract::Request,
+    http::{StatusCode, Uri, header},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_server::tls_rustls::RustlsConfig;
+use chrono::{Datelike, Utc};
+use rcgen::{
+    BasicConstraints, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// ローカルCAの名前
+const LOCAL_CA_NAME: &str = "Vantage Local CA";
+/// ローカルCAの有効期間（日）
+const CA_VALIDITY_DAYS: i64 = 10 * 365;
+/// サーバー証明書の有効期間（日）
+const SERVER_CERT_VALIDITY_DAYS: i64 = 365;
+
+/// サーバー証明書の取得元
+#[derive(Debug, Clone)]
+pub enum TlsCertSource {
+    /// PEM形式の証明書チェーンと秘密鍵
+    Files {
+        cert_path: PathBuf,
+        key_path: PathBuf,
+    },
+    /// ローカルCAで発行（localhostと127.0.0.1、::1に加えて指定したホスト名を含める）
+    LocalCa { hostnames: Vec<String> },
+}
+
+/// HTTPSの設定
+#[derive(Debug, Clone)]
+pub struct TlsOptions {
+    pub https_port: u16,
+    pub cert: TlsCertSource,
+    /// HTTPポートへのアクセスをHTTPSにリダイレクトする
+    pub redirect_http: bool,
+}
+
+/// 発行したローカル証明書のパス
+#[derive(Debug, Clone)]
+pub struct LocalCertificates {
+    pub ca_cert_path: PathBuf,
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+/// ローカルCAと証明書の保存先（データディレクトリの `tls`）
+pub fn default_tls_dir() -> PathBuf {
+    vantage_persistence::paths::get().tls_dir()
+}
+
+/// ローカルCAを（なければ作成して）読み込み、サーバー証明書を発行する
+///
+/// CAは一度作成したら使い回し、サーバー証明書は起動のたびに発行し直す。
+pub fn ensure_local_certificates(
+    dir: &Path,
+    hostnames: &[String],
+) -> Result<LocalCertificates, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create TLS directory: {e}"))?;
+    let ca_cert_path = dir.join("ca.pem");
+    let ca_key_path = dir.join("ca-key.pem");
+    let cert_path = dir.join("server.pem");
+    let key_path = dir.join("server-key.pem");
+
+    let ca_key = if ca_key_path.exists() && ca_cert_path.exists() {
+        let pem = std::fs::read_to_string(&ca_key_path)
+            .map_err(|e| format!("Failed to read local CA key: {e}"))?;
+        KeyPair::from_pem(&pem).map_err(|e| format!("Invalid local CA key: {e}"))?
+    } else {
+        let key = KeyPair::generate().map_err(|e| format!("Failed to generate CA key: {e}"))?;
+        let ca_cert = ca_params()?
+            .self_signed(&key)
+            .map_err(|e| format!("Failed to create local CA: {e}"))?;
+        write_private(&ca_key_path, &key.serialize_pem())?;
+        std::fs::write(&ca_cert_path, ca_cert.pem())
+            .map_err(|e| format!("Failed to write local CA certificate: {e}"))?;
+        tracing::info!(
+            "Created local CA at {} (trust it to avoid browser warnings)",
+            ca_cert_path.display()
+        );
+        key
+    };
+    // 署名にはCAの識別名と鍵のみが使われるため、同じパラメータで作り直したものを発行者とする
+    let issuer = ca_params()?
+        .self_signed(&ca_key)
+        .map_err(|e| format!("Failed to load local CA: {e}"))?;
+
+    let mut names = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+    for hostname in hostnames {
+        if !names.contains(hostname) {
+            names.push(hostname.clone());
+        }
+    }
+    let mut params =
+        CertificateParams::new(names.clone()).map_err(|e| format!("Invalid TLS hostname: {e}"))?;
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "Vantage Dashboard");
+    params.key_usages = vec![
+        KeyUsagePurpose::DigitalSignature,
+        KeyUsagePurpose::KeyEncipherment,
+    ];
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    params.use_authority_key_identifier_extension = true;
+    set_validity(&mut params, SERVER_CERT_VALIDITY_DAYS);
+
+    let key = KeyPair::generate().map_err(|e| format!("Failed to generate server key: {e}"))?;
+    let cert = params
+        .signed_by(&key, &issuer, &ca_key)
+        .map_err(|e| format!("Failed to issue server certificate: {e}"))?;
+    write_private(&key_path, &key.serialize_pem())?;
+    std::fs::write(&cert_path, cert.pem())
+        .map_err(|e| format!("Failed to write server certificate: {e}"))?;
+    tracing::debug!("Issued server certificate for {:?}", names);
+
+    Ok(LocalCertificates {
+        ca_cert_path,
+        cert_path,
+        key_path,
+    })
+}
+
+/// 証明書と秘密鍵を読み込んでrustlsの設定を作成
+pub async fn load_rustls_config(cert_path: &Path, key_path: &Path) -> Result<RustlsConfig, String> {
+    let cert_pem = tokio::fs::read(cert_path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {e}", cert_path.display()))?;
+    let key_pem = tokio::fs::read(key_path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {e}", key_path.display()))?;
+
+    let certs = rustls_pemfile::certs(&mut cert_pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid certificate: {e}"))?;
+    if certs.is_empty() {
+        return Err(format!("No certificate found in {}", cert_path.display()));
+    }
+    let key = rustls_pemfile::private_key(&mut key_pem.as_slice())
+        .map_err(|e| format!("Invalid private key: {e}"))?
+        .ok_or_else(|| format!("No private key found in {}", key_path.display()))?;
+
+    let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| format!("Failed to configure TLS: {e}"))?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .map_err(|e| format!("Failed to configure TLS: {e}"))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(RustlsConfig::from_config(Arc::new(config)))
+}
+
+/// HTTPのリクエストを全てHTTPSへリダイレクトするルーター
+pub fn redirect_app(https_port: u16) -> Router {
+    Router::new()
+        .fallback(move |request: Request| async move { redirect_to_https(&request, https_port) })
+}
+
+fn redirect_to_https(request: &Request, https_port: u16) -> Response {
+    let Some(host) = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+    else {
+        return (StatusCode::BAD_REQUEST, "Missing Host header").into_response();
+    };
+    match https_url(host, request.uri(), https_port) {
+        Some(url) => Redirect::permanent(&url).into_response(),
+        None => (StatusCode::BAD_REQUEST, "Invalid Host header").into_response(),
+    }
+}
+
+/// Hostヘッダーのポートを差し替えたHTTPSのURL
+fn https_url(host: &str, uri: &Uri, https_port: u16) -> Option<String> {
+    let authority: axum::http::uri::Authority = host.parse().ok()?;
+    let hostname = authority.host();
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    Some(if https_port == 443 {
+        format!("https://{hostname}{path}")
+    } else {
+        format!("https://{hostname}:{https_port}{path}")
+    })
+}
+
+fn ca_params() -> Result<CertificateParams, String> {
+    let mut params =
+        CertificateParams::new(Vec::new()).map_err(|e| format!("Invalid CA parameters: {e}"))?;
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, LOCAL_CA_NAME);
+    name.push(DnType::OrganizationName, "Vantage");
+    params.distinguished_name = name;
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    set_validity(&mut params, CA_VALIDITY_DAYS);
+    Ok(params)
+}
+
+fn set_validity(params: &mut CertificateParams, days: i64) {
+    let date = |at: chrono::DateTime<Utc>| {
+        rcgen::date_time_ymd(at.year(), at.month() as u8, at.day() as u8)
+    };
+    let now = Utc::now();
+    params.not_before = date(now - chrono::Duration::days(1));
+    params.not_after = date(now + chrono::Duration::days(days));
+}
+
+/// 秘密鍵を所有者のみ読み書きできる権限で書き出す
+fn write_private(path: &Path, contents: &str) -> Result<(), String> {
+    private_file::write(path, contents.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_https_url_replaces_port() {
+        let uri: Uri = "/api/processes?state=running".parse().unwrap();
+        assert_eq!(
+            https_url("devbox:12700", &uri, 12743).as_deref(),
+            Some("https://devbox:12743/api/processes?state=running")
+        );
+        assert_eq!(
+            https_url("[::1]:80", &"/".parse().unwrap(), 443).as_deref(),
+            Some("https://[::1]/")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_local_ca_issues_loadable_certificate() {
+        let dir = tempfile::tempdir().unwrap();
+        let certs = ensure_local_certificates(dir.path(), &["devbox.local".to_string()]).unwrap();
+        assert!(certs.ca_cert_path.exists());
+        let ca_pem = std::fs::read_to_string(&certs.ca_cert_path).unwrap();
+
+        // 2回目はCAを使い回す
+        let again = ensure_local_certificates(dir.path(), &[]).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&again.ca_cert_path).unwrap(),
+            ca_pem
+        );
+
+        load_rustls_config(&certs.cert_path, &certs.key_path)
+            .await
+            .unwrap();
+    }
+}
@@ -139,6 +139,10 @@ mod browser_tests {
         let app_state = AppState {
             process_manager: Arc::new(process_manager),
             persistence_manager: Arc::new(persistence_manager),
+            auth: None,
+            hooks: Default::default(),
+            gates: Default::default(),
+            process_feed: Default::default(),
         };
 
         let app = create_api_routes().with_state(app_state);
@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use tempfile::tempdir;
-use vantage_atom::{messages::CreateProcessRequest, process::ProcessManager};
+use vantage_atom::{
+    messages::CreateProcessRequest,
+    process::{ProcessManager, ProcessOptions},
+};
 
 #[tokio::test]
 async fn test_update_process_attributes() {
@@ -14,8 +17,31 @@ async fn test_update_process_attributes() {
         command: "echo".to_string(),
         args: vec!["original".to_string()],
         env: HashMap::new(),
+        sensitive_env: Vec::new(),
         cwd: None,
         auto_start_on_restore: false,
+        tags: vec![],
+        idle_detection: None,
+        health_check: None,
+        tmux: None,
+        repl: None,
+        log_sources: vec![],
+        log_sinks: vec![],
+        redaction: None,
+        metadata: HashMap::new(),
+        runbook: None,
+        startup: None,
+        env_loader: None,
+        output_recording: None,
+        assertions: Vec::new(),
+        restart_policy: None,
+        depends_on: Vec::new(),
+        parameters: Vec::new(),
+        keep_stdin_open: false,
+        group: None,
+        run_as: None,
+        resource_limits: None,
+        container: None,
     };
 
     manager
@@ -42,6 +68,7 @@ async fn test_update_process_attributes() {
             Some(new_env.clone()),
             Some("/tmp".to_string()),
             Some(true),
+            None,
         )
         .await
         .unwrap();
@@ -90,6 +117,7 @@ async fn test_update_process_persistence() {
                 None,
                 Some("/home/user".to_string()),
                 Some(true),
+                None,
             )
             .await
             .unwrap();
@@ -157,6 +185,7 @@ async fn test_partial_update() {
             None,                   // envは更新しない
             None,                   // cwdは更新しない
             Some(false),            // auto_start_on_restoreを更新
+            None,                   // sensitive_envは更新しない
         )
         .await
         .unwrap();
@@ -174,3 +203,152 @@ async fn test_partial_update() {
     assert!(!updated_process.auto_start_on_restore); // 更新された値
     assert_eq!(updated_process.env.get("INITIAL").unwrap(), "value");
 }
+
+#[tokio::test]
+async fn test_diff_process_env_detects_stale_environment() {
+    let manager = ProcessManager::new().await;
+    let env = HashMap::from([("PORT".to_string(), "3000".to_string())]);
+    manager
+        .create_process(
+            "env_drift".to_string(),
+            "sleep".to_string(),
+            vec!["30".to_string()],
+            env,
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+
+    // 起動前は定義のまま起動されるので差分はない
+    let diff = manager
+        .diff_process_env("env_drift".to_string())
+        .await
+        .unwrap();
+    assert!(!diff.running);
+    assert!(!diff.drifted);
+
+    manager
+        .start_process("env_drift".to_string())
+        .await
+        .unwrap();
+    manager
+        .update_process(
+            "env_drift".to_string(),
+            None,
+            None,
+            Some(HashMap::from([
+                ("PORT".to_string(), "4000".to_string()),
+                ("MODE".to_string(), "dev".to_string()),
+            ])),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let diff = manager
+        .diff_process_env("env_drift".to_string())
+        .await
+        .unwrap();
+    assert!(diff.running);
+    assert!(diff.drifted);
+    assert_eq!(diff.added.get("MODE").map(String::as_str), Some("dev"));
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].key, "PORT");
+    assert_eq!(diff.changed[0].definition, "4000");
+    assert_eq!(diff.changed[0].running, "3000");
+    assert!(diff.advice.contains("restart"));
+
+    // 再起動すると新しい定義で起動される
+    manager
+        .stop_process("env_drift".to_string(), Some(100))
+        .await
+        .unwrap();
+    manager
+        .start_process("env_drift".to_string())
+        .await
+        .unwrap();
+    let diff = manager
+        .diff_process_env("env_drift".to_string())
+        .await
+        .unwrap();
+    assert!(diff.running);
+    assert!(!diff.drifted);
+
+    manager
+        .stop_process("env_drift".to_string(), Some(100))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_update_process_metadata() {
+    let manager = ProcessManager::new().await;
+    manager
+        .create_process_with_options(
+            "metadata_test".to_string(),
+            "echo".to_string(),
+            vec![],
+            HashMap::new(),
+            None,
+            ProcessOptions {
+                metadata: HashMap::from([
+                    ("owner".to_string(), "alice".to_string()),
+                    ("ticket".to_string(), "OPS-12".to_string()),
+                ]),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    // 指定したキーだけを変更し、nullのキーは削除する
+    let metadata = manager
+        .update_process_metadata(
+            "metadata_test".to_string(),
+            HashMap::from([
+                ("ticket".to_string(), None),
+                (
+                    "runbook".to_string(),
+                    Some("https://wiki.example.com/runbooks/echo".to_string()),
+                ),
+            ]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(metadata.len(), 2);
+    assert_eq!(metadata["owner"], "alice");
+
+    let error = manager
+        .update_process_metadata(
+            "metadata_test".to_string(),
+            HashMap::from([("has space".to_string(), Some("x".to_string()))]),
+        )
+        .await
+        .unwrap_err();
+    assert!(error.contains("'has space'"), "{error}");
+
+    // エクスポートと読み込みで保たれる
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("processes.yaml");
+    manager
+        .export_yaml(Some(path.to_string_lossy().to_string()), false, None)
+        .await
+        .unwrap();
+    let restored = ProcessManager::new().await;
+    restored
+        .import_yaml(&path.to_string_lossy(), None)
+        .await
+        .unwrap();
+    let processes = restored
+        .list_processes_matching(None, Some("metadata.owner == alice"))
+        .await
+        .unwrap();
+    let process = processes
+        .iter()
+        .find(|p| p.id == "metadata_test")
+        .expect("metadata_test should match its owner");
+    assert_eq!(process.metadata, metadata);
+}
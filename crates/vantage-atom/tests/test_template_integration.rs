@@ -2,7 +2,7 @@
 ///
 /// このテストは実際にSurrealDBに接続し、テンプレートのCRUD操作とプロセス作成を検証します。
 use vantage_atom::VantageServer;
-use vantage_persistence::db::connection::{DbConfig, DbConnection};
+use vantage_persistence::db::connection::{AuthLevel, DbConfig, DbConnection};
 use vantage_persistence::db::schema::SchemaManager;
 use vantage_persistence::db::template_repository::{
     Template, TemplateCategory, TemplateRepository,
@@ -16,6 +16,7 @@ async fn setup_test_db() -> DbConnection {
         database: "test".to_string(),
         username: "vtg-local".to_string(),
         password: "vtg-local".to_string(),
+        auth_level: AuthLevel::Root,
     };
 
     let conn = DbConnection::new(config)
@@ -1,10 +1,16 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
-use vantage_atom::process::ProcessManager;
+use vantage_atom::process::{ProcessManager, ProcessState};
 use vantage_atom::web::api::create_api_routes;
-use vantage_atom::web::server::AppState;
-use vantage_persistence::PersistenceManager;
+use vantage_atom::web::auth::{AuthFuture, AuthProvider, AuthState, TokenSet};
+use vantage_atom::web::gates::GateRegistry;
+use vantage_atom::web::hooks::HookRegistry;
+use vantage_atom::web::server::{AppState, create_app};
+use vantage_persistence::{
+    Access, Capability, GateConfig, PersistenceManager, Role, Template, TemplateChange,
+    TemplateChangeAction, WebhookConfig,
+};
 
 // テスト用のCreateProcessRequest（Serialize追加）
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -56,6 +62,37 @@ async fn test_get_status() {
     assert!(body["process_count"].is_number());
 }
 
+#[tokio::test]
+async fn test_get_diagnostics() {
+    let app_state = create_test_app_state().await;
+    let app = create_api_routes().with_state(app_state);
+
+    let client = reqwest::Client::new();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // GET /diagnostics をテスト
+    let response = client
+        .get(format!("http://{}/diagnostics", addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["process_count"].is_number());
+    assert!(body["log_disk_usage"]["total_bytes"].is_number());
+    assert!(body["log_disk_usage"]["quota"]["max_total_bytes"].is_number());
+}
+
 #[tokio::test]
 async fn test_process_lifecycle_api() {
     let app_state = create_test_app_state().await;
@@ -142,7 +179,36 @@ async fn test_process_lifecycle_api() {
         lines
     );
 
-    // 5. プロセスを削除
+    // 5. タイムラインを取得（echoは終了済み）
+    let response = client
+        .get(format!("{}/processes/api-test-echo/timeline", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let timeline: serde_json::Value = response.json().await.unwrap();
+    let run = &timeline["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["lane"] == "run")
+        .expect("Expected a run entry")
+        .clone();
+    assert_eq!(run["status"], "succeeded");
+    assert!(run["duration_ms"].is_number());
+    assert_eq!(timeline["summary"]["runs"], 1);
+
+    let response = client
+        .get(format!(
+            "{}/processes/api-test-echo/timeline?from=yesterday",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    // 6. プロセスを削除
     let response = client
         .delete(format!("{}/processes/api-test-echo", base_url))
         .send()
@@ -279,6 +345,793 @@ async fn test_process_filtering() {
     }
 }
 
+/// テスト用の認証プロバイダー（常に同じユーザーでログインし、トークンは即座に期限切れ）
+struct FakeProvider {
+    role: Role,
+    refreshes: std::sync::atomic::AtomicUsize,
+}
+
+impl AuthProvider for FakeProvider {
+    fn name(&self) -> &str {
+        "fake"
+    }
+
+    fn authorization_url<'a>(&'a self, state: &'a str) -> AuthFuture<'a, String> {
+        Box::pin(async move { Ok(format!("https://idp.example.com/authorize?state={state}")) })
+    }
+
+    fn exchange_code<'a>(&'a self, _code: &'a str) -> AuthFuture<'a, TokenSet> {
+        Box::pin(async move {
+            Ok(TokenSet {
+                access_token: "access-1".to_string(),
+                refresh_token: Some("refresh-1".to_string()),
+                expires_in: Some(0),
+            })
+        })
+    }
+
+    fn refresh<'a>(&'a self, _refresh_token: &'a str) -> AuthFuture<'a, TokenSet> {
+        Box::pin(async move {
+            self.refreshes
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(TokenSet {
+                access_token: "access-2".to_string(),
+                refresh_token: None,
+                expires_in: Some(3600),
+            })
+        })
+    }
+
+    fn user_claims<'a>(&'a self, _access_token: &'a str) -> AuthFuture<'a, serde_json::Value> {
+        Box::pin(
+            async move { Ok(serde_json::json!({"sub": "alice", "email": "alice@example.com"})) },
+        )
+    }
+
+    fn map_role(&self, _claims: &serde_json::Value) -> Option<Role> {
+        Some(self.role)
+    }
+}
+
+#[tokio::test]
+async fn test_auth_login_flow_and_roles() {
+    let provider = Arc::new(FakeProvider {
+        role: Role::Viewer,
+        refreshes: Default::default(),
+    });
+    // 閲覧ロールからテンプレートの閲覧権を外す
+    let auth =
+        AuthState::new(provider.clone(), 3600, false).with_role_permissions(HashMap::from([(
+            Role::Viewer,
+            HashMap::from([(Capability::Templates, Access::None)]),
+        )]));
+    let persistence = Arc::new(PersistenceManager::new().await.unwrap());
+    let app = create_app(
+        ProcessManager::new().await,
+        persistence.clone(),
+        Some(Arc::new(auth)),
+        Default::default(),
+        Default::default(),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let base_url = format!("http://{}", addr);
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    // 未ログインのAPIアクセスは401
+    let response = client
+        .get(format!("{}/api/processes", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    // ログイン開始でプロバイダーへリダイレクト
+    let response = client
+        .get(format!("{}/auth/login", base_url))
+        .send()
+        .await
+        .unwrap();
+    let location = response.headers()["location"].to_str().unwrap().to_string();
+    let state = location.split("state=").nth(1).unwrap();
+
+    // コールバックでセッションCookieが発行される
+    let response = client
+        .get(format!(
+            "{}/auth/callback?code=xyz&state={}",
+            base_url, state
+        ))
+        .send()
+        .await
+        .unwrap();
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    let session = cookie.split(';').next().unwrap().to_string();
+
+    // ユーザー情報の取得時に期限切れのトークンがサーバー側で更新される
+    let response = client
+        .get(format!("{}/api/auth/me", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let me: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(me["subject"], "alice");
+    assert_eq!(me["role"], "viewer");
+    assert_eq!(me["permissions"]["clipboard"], "read");
+    assert_eq!(me["permissions"]["templates"], "none");
+    assert_eq!(
+        provider.refreshes.load(std::sync::atomic::Ordering::SeqCst),
+        1
+    );
+    let session_id = session.split_once('=').unwrap().1;
+    let stored = persistence
+        .get_auth_session(session_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.access_token, "access-2");
+    assert_eq!(stored.refresh_token.as_deref(), Some("refresh-1"));
+
+    // 閲覧ロールでは操作できない
+    let response = client
+        .post(format!("{}/api/processes/foo/start", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+
+    // クリップボードは閲覧できるが書き込めない
+    let response = client
+        .get(format!("{}/api/clipboard/history", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(response.status(), 403);
+    let response = client
+        .post(format!("{}/api/clipboard/text", base_url))
+        .header("cookie", &session)
+        .json(&serde_json::json!({ "content": "hello" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "Permission denied: requires write access to clipboard"
+    );
+
+    // 上書きで外したテンプレートは閲覧もできない
+    let response = client
+        .get(format!("{}/api/templates", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+
+    // ログアウト後はセッションが無効になる
+    client
+        .post(format!("{}/auth/logout", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    let response = client
+        .get(format!("{}/api/processes", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+/// 指定したロールでログインしたクライアント・URL・セッションCookie
+async fn login_as(
+    role: Role,
+    overrides: HashMap<Capability, Access>,
+) -> (reqwest::Client, String, String) {
+    let provider = Arc::new(FakeProvider {
+        role,
+        refreshes: Default::default(),
+    });
+    let auth = AuthState::new(provider, 3600, false)
+        .with_role_permissions(HashMap::from([(role, overrides)]));
+    let app = create_app(
+        ProcessManager::new().await,
+        Arc::new(PersistenceManager::new().await.unwrap()),
+        Some(Arc::new(auth)),
+        Default::default(),
+        Default::default(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("{}/auth/login", base_url))
+        .send()
+        .await
+        .unwrap();
+    let location = response.headers()["location"].to_str().unwrap().to_string();
+    let state = location.split("state=").nth(1).unwrap().to_string();
+    let response = client
+        .get(format!(
+            "{}/auth/callback?code=xyz&state={}",
+            base_url, state
+        ))
+        .send()
+        .await
+        .unwrap();
+    let session = response.headers()["set-cookie"]
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+    (client, base_url, session)
+}
+
+#[tokio::test]
+async fn test_schedule_routes_require_process_access() {
+    // プロセスを閲覧だけできる運用者
+    let (client, base_url, session) = login_as(
+        Role::Operator,
+        HashMap::from([(Capability::Processes, Access::Read)]),
+    )
+    .await;
+
+    let response = client
+        .get(format!("{}/api/schedules", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // 定期実行はプロセスを起動するため、登録・変更・削除にはプロセスの変更権が要る
+    let response = client
+        .post(format!("{}/api/schedules", base_url))
+        .header("cookie", &session)
+        .json(&serde_json::json!({
+            "id": "nightly",
+            "process_id": "backup",
+            "cron": "0 0 3 * * *"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "Permission denied: requires write access to processes"
+    );
+    for request in [
+        client.patch(format!("{}/api/schedules/nightly", base_url)),
+        client.delete(format!("{}/api/schedules/nightly", base_url)),
+    ] {
+        let response = request
+            .header("cookie", &session)
+            .json(&serde_json::json!({}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 403);
+    }
+
+    // プロセスの閲覧権がなければ一覧も見られない
+    let (client, base_url, session) = login_as(
+        Role::Viewer,
+        HashMap::from([(Capability::Processes, Access::None)]),
+    )
+    .await;
+    let response = client
+        .get(format!("{}/api/schedules", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+}
+
+#[tokio::test]
+async fn test_diagnostics_and_unknown_routes_are_denied_without_access() {
+    let (client, base_url, session) = login_as(
+        Role::Viewer,
+        HashMap::from([(Capability::Processes, Access::None)]),
+    )
+    .await;
+    for path in ["/api/diagnostics", "/api/status"] {
+        let response = client
+            .get(format!("{}{}", base_url, path))
+            .header("cookie", &session)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 403, "{path}");
+        assert_eq!(
+            response.text().await.unwrap(),
+            "Permission denied: requires read access to processes"
+        );
+    }
+
+    // アクセス権の定義がないAPIは管理者でも拒否する
+    let (client, base_url, session) = login_as(Role::Admin, HashMap::new()).await;
+    let response = client
+        .get(format!("{}/api/diagnostics", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = client
+        .get(format!("{}/api/unlisted", base_url))
+        .header("cookie", &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+}
+
+#[tokio::test]
+async fn test_template_changes_are_streamed() {
+    let app_state = create_test_app_state().await;
+    let sender = app_state
+        .process_manager
+        .event_system()
+        .template_change_sender();
+    let app = create_api_routes().with_state(app_state);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut response = reqwest::Client::new()
+        .get(format!("http://{}/templates/changes", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // 購読が始まってから送る
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    sender
+        .send(TemplateChange {
+            action: TemplateChangeAction::Updated,
+            template: Template::new("web".to_string(), "npm".to_string()),
+        })
+        .unwrap();
+
+    let chunk = tokio::time::timeout(Duration::from_secs(2), response.chunk())
+        .await
+        .expect("No template change was streamed")
+        .unwrap()
+        .unwrap();
+    let text = String::from_utf8_lossy(&chunk);
+    assert!(text.contains("event: template_change"), "{text}");
+    assert!(text.contains(r#""action":"updated""#), "{text}");
+}
+
+#[tokio::test]
+async fn test_settings_are_typed_per_key_and_changes_are_streamed() {
+    let app_state = create_test_app_state().await;
+    let app = create_api_routes().with_state(app_state);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let client = reqwest::Client::new();
+
+    let schema: Vec<serde_json::Value> = client
+        .get(format!("http://{}/settings/schema", addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let timeout = schema
+        .iter()
+        .find(|s| s["key"] == "approvals.confirm_timeout_secs")
+        .unwrap();
+    assert_eq!(timeout["type"], "integer");
+    assert_eq!(timeout["minimum"], 1.0);
+
+    // 範囲外・型違いの値は保存しない
+    for value in [serde_json::json!(0), serde_json::json!("soon")] {
+        let response = client
+            .put(format!(
+                "http://{}/settings/approvals.confirm_timeout_secs",
+                addr
+            ))
+            .json(&serde_json::json!({ "value": value }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+    }
+    let response = client
+        .get(format!("http://{}/settings/no.such.key", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    let mut changes = client
+        .get(format!("http://{}/settings/changes", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(changes.status(), 200);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let change: serde_json::Value = client
+        .put(format!(
+            "http://{}/settings/approvals.confirm_timeout_secs",
+            addr
+        ))
+        .json(&serde_json::json!({ "value": 120 }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(change["new_value"], 120);
+
+    let chunk = tokio::time::timeout(Duration::from_secs(2), changes.chunk())
+        .await
+        .expect("No setting change was streamed")
+        .unwrap()
+        .unwrap();
+    let text = String::from_utf8_lossy(&chunk);
+    assert!(text.contains("event: setting_change"), "{text}");
+    assert!(
+        text.contains(r#""key":"approvals.confirm_timeout_secs""#),
+        "{text}"
+    );
+
+    let current: serde_json::Value = client
+        .get(format!(
+            "http://{}/settings/approvals.confirm_timeout_secs",
+            addr
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(current["value"], 120);
+    assert_eq!(current["default"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+async fn test_process_list_changes_are_sent_as_deltas_over_websocket() {
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let app_state = create_test_app_state().await;
+    let process_manager = app_state.process_manager.clone();
+    process_manager
+        .create_process(
+            "feed-api".to_string(),
+            "sleep".to_string(),
+            vec!["30".to_string()],
+            HashMap::new(),
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+    let app = create_api_routes().with_state(app_state);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    async fn next_message(
+        socket: &mut tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    ) -> serde_json::Value {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+                .await
+                .expect("No message from the process feed")
+                .unwrap()
+                .unwrap();
+            if let Message::Text(text) = message {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(format!("ws://{}/processes/changes", addr))
+            .await
+            .unwrap();
+    let snapshot = next_message(&mut socket).await;
+    assert_eq!(snapshot["type"], "snapshot");
+    assert_eq!(snapshot["processes"][0]["id"], "feed-api");
+    let snapshot_seq = snapshot["seq"].as_u64().unwrap();
+
+    // 追加と状態の変化は差分だけが届く
+    process_manager
+        .create_process(
+            "feed-web".to_string(),
+            "sleep".to_string(),
+            vec!["30".to_string()],
+            HashMap::new(),
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+    let delta = next_message(&mut socket).await;
+    assert_eq!(delta["type"], "delta");
+    assert_eq!(delta["seq"].as_u64().unwrap(), snapshot_seq + 1);
+    assert_eq!(delta["added"][0]["id"], "feed-web");
+    assert!(delta["updated"].as_array().unwrap().is_empty());
+
+    process_manager
+        .remove_process("feed-api".to_string())
+        .await
+        .unwrap();
+    let delta = next_message(&mut socket).await;
+    assert_eq!(delta["seq"].as_u64().unwrap(), snapshot_seq + 2);
+    assert_eq!(delta["removed"][0], "feed-api");
+
+    // 番号が飛んだクライアントは一覧全体を受け取り直せる
+    socket
+        .send(Message::Text(r#"{"type":"resync"}"#.to_string()))
+        .await
+        .unwrap();
+    let resync = next_message(&mut socket).await;
+    assert_eq!(resync["type"], "snapshot");
+    assert_eq!(resync["processes"].as_array().unwrap().len(), 1);
+    drop(socket);
+
+    // 再接続では、履歴にある差分だけを送り直す
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!(
+        "ws://{}/processes/changes?since={}",
+        addr,
+        snapshot_seq + 1
+    ))
+    .await
+    .unwrap();
+    let replayed = next_message(&mut socket).await;
+    assert_eq!(replayed["type"], "delta");
+    assert_eq!(replayed["seq"].as_u64().unwrap(), snapshot_seq + 2);
+}
+
+#[tokio::test]
+async fn test_webhook_requires_token_validates_payload_and_rate_limits() {
+    let process_manager = ProcessManager::new().await;
+    process_manager
+        .create_process(
+            "hook-sleeper".to_string(),
+            "sleep".to_string(),
+            vec!["30".to_string()],
+            HashMap::new(),
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+    let hook: WebhookConfig = serde_json::from_value(serde_json::json!({
+        "name": "start-sleeper",
+        "token": "hook-secret",
+        "action": "start_process",
+        "process_id": "hook-sleeper",
+        "payload_schema": {"/ref": {"required": true, "pattern": "^refs/heads/"}},
+        "rate_limit": {"max_requests": 4, "per_secs": 60},
+    }))
+    .unwrap();
+    // ログインを必須にしていても、Webhookはトークンだけで呼び出せる
+    let auth = AuthState::new(
+        Arc::new(FakeProvider {
+            role: Role::Viewer,
+            refreshes: Default::default(),
+        }),
+        3600,
+        false,
+    );
+    let app = create_app(
+        process_manager.clone(),
+        Arc::new(PersistenceManager::new().await.unwrap()),
+        Some(Arc::new(auth)),
+        HookRegistry::from_config(&[hook]),
+        Default::default(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/api/hooks/start-sleeper", addr);
+    let payload = serde_json::json!({"ref": "refs/heads/main"});
+
+    let response = client
+        .post(format!("http://{}/api/hooks/unknown", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    let response = client.post(&url).json(&payload).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = client
+        .post(&url)
+        .bearer_auth("hook-secret")
+        .json(&serde_json::json!({"ref": "v1.0"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let message = response.text().await.unwrap();
+    assert!(message.contains("/ref: must match"), "{message}");
+
+    let response = client
+        .post(&url)
+        .header("X-Vantage-Hook-Token", "hook-secret")
+        .json(&payload)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["action"], "start_process");
+    assert!(body["pid"].as_u64().is_some(), "{body}");
+    let status = process_manager
+        .get_process_status("hook-sleeper".to_string())
+        .await
+        .unwrap();
+    assert!(matches!(status.info.state, ProcessState::Running { .. }));
+
+    // 4回目までは受け付け、5回目は待たせる
+    let response = client
+        .post(&url)
+        .bearer_auth("hook-secret")
+        .json(&payload)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body.get("pid").is_none(), "{body}");
+
+    let response = client
+        .post(&url)
+        .bearer_auth("hook-secret")
+        .json(&payload)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+
+    process_manager
+        .stop_process("hook-sleeper".to_string(), None)
+        .await
+        .ok();
+}
+
+#[tokio::test]
+async fn test_gate_opens_only_when_process_is_running() {
+    let process_manager = ProcessManager::new().await;
+    process_manager
+        .create_process(
+            "gate-sleeper".to_string(),
+            "sleep".to_string(),
+            vec!["30".to_string()],
+            HashMap::new(),
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+    let app = create_app(
+        process_manager.clone(),
+        Arc::new(PersistenceManager::new().await.unwrap()),
+        None,
+        Default::default(),
+        GateRegistry::from_config(&[GateConfig {
+            process_id: "gate-sleeper".to_string(),
+            token: Some("gate-secret".to_string()),
+            token_env: None,
+        }]),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/api/gate/gate-sleeper", addr);
+
+    let response = client
+        .get(format!("http://{}/api/gate/unknown", addr))
+        .bearer_auth("gate-secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    let response = client.get(&url).bearer_auth("wrong").send().await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = client
+        .get(&url)
+        .header("X-Vantage-Gate-Token", "gate-secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 503);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["open"], false);
+    assert_eq!(body["reasons"][0], "Process is not started");
+
+    // 閉じている間は `wait` の秒数まで待ち、途中で開けば200を返す
+    let starter = process_manager.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        starter
+            .start_process("gate-sleeper".to_string())
+            .await
+            .unwrap();
+    });
+    let response = client
+        .get(format!("{url}?wait=10"))
+        .bearer_auth("gate-secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["open"], true);
+    assert!(body.get("reasons").is_none(), "{body}");
+
+    process_manager
+        .stop_process("gate-sleeper".to_string(), None)
+        .await
+        .ok();
+}
+
 // ヘルパー関数
 async fn create_test_app_state() -> AppState {
     let process_manager = ProcessManager::new().await;
@@ -288,5 +1141,9 @@ async fn create_test_app_state() -> AppState {
     AppState {
         process_manager: Arc::new(process_manager),
         persistence_manager: Arc::new(persistence_manager),
+        auth: None,
+        hooks: Default::default(),
+        gates: Default::default(),
+        process_feed: Default::default(),
     }
 }
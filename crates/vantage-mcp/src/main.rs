@@ -1,9 +1,19 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use rmcp::{ServiceExt, transport::stdio};
 use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Instant;
+use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriterExt};
 use tracing_subscriber::{self, EnvFilter};
 use vantage::VantageServer;
+use vantage::atom::process::debug_bundle::ServerLogWriter;
+use vantage::atom::{mcp_session, server_admin, server_startup};
+use vantage::web::WebServerOptions;
+use vantage::web::tls::{TlsCertSource, TlsOptions};
+
+mod service;
 
 // メンテナビリティ向上のための定数
 const BROWSER_STARTUP_DELAY_MS: u64 = 500;
@@ -13,9 +23,162 @@ const KEEPALIVE_INTERVAL_SECS: u64 = 3600;
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Webダッシュボード用のブラウザを自動的に開かない
     #[arg(long)]
     no_open: bool,
+
+    /// WebダッシュボードのHTTPポート（使用中の場合は自動変更）
+    #[arg(long, default_value_t = 12700)]
+    port: u16,
+
+    /// Webダッシュボードを待ち受けるアドレス
+    #[arg(long, default_value = "127.0.0.1")]
+    bind: IpAddr,
+
+    /// HTTPSで配信する（証明書を指定しない場合はデータディレクトリの tls のローカルCAで発行）
+    #[arg(long)]
+    https: bool,
+
+    /// HTTPSのポート
+    #[arg(long, default_value_t = 12743)]
+    https_port: u16,
+
+    /// PEM形式の証明書チェーン（--tls-keyと併用）
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM形式の秘密鍵（--tls-certと併用）
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// ローカルCAで発行する証明書に含めるホスト名（複数指定可）
+    #[arg(long = "tls-host")]
+    tls_hosts: Vec<String>,
+
+    /// HTTPからHTTPSへのリダイレクトを行わない
+    #[arg(long)]
+    no_https_redirect: bool,
+
+    /// WebコンソールをmDNS（_vantage._tcp）でLANに告知する
+    #[arg(long)]
+    mdns: bool,
+
+    /// mDNSで告知するインスタンス名（既定は "Vantage on <ホスト名>"）
+    #[arg(long)]
+    mdns_name: Option<String>,
+
+    /// カオステスト用の障害注入ツール（chaos_kill など）を有効にする
+    #[arg(long)]
+    chaos: bool,
+
+    /// 実行前にWebコンソールでの承認を必要とするツール（複数指定可、例: remove_process）
+    #[arg(long = "require-approval")]
+    require_approval: Vec<String>,
+
+    /// 呼び出しを止めてWebコンソールでの確認を待つツール（複数指定可、例: restore_snapshot）
+    #[arg(long = "require-confirmation")]
+    require_confirmation: Vec<String>,
+
+    /// MCPをstdioで提供せず、Webコンソールだけを動かす（サービスとして起動する場合）
+    #[arg(long)]
+    headless: bool,
+
+    /// MCPクライアントが切断してもプロセスを止めずに動き続け、次のセッションをデータディレクトリの
+    /// mcp.sock で受け付ける（同じ指定で起動したvantageは標準入出力をそこへ中継する）
+    #[arg(long)]
+    keep_alive: bool,
+
+    /// ログを標準エラー出力に加えてファイルにも追記する
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// 設定・データ・ログをまとめて置くディレクトリ
+    /// （既定は VANTAGE_DATA_DIR、ワークスペースの .vantage、XDGベースディレクトリの順）
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
+
+    /// スナップショットからの復元と自動起動を行わずに起動する（VANTAGE_SAFE_MODE=1 でも可）
+    /// 復元は safe_mode_restore ツールで選んで行う
+    #[arg(long)]
+    safe_mode: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// LAN上でmDNSにより告知されているWebコンソールを探す
+    Discover {
+        /// 探索する秒数
+        #[arg(long, default_value_t = 3)]
+        timeout_secs: u64,
+    },
+    /// Web APIのOpenAPIドキュメントを標準出力に書き出す
+    Openapi,
+    /// ログイン時にサーバーを起動するユーザーサービスとして登録する
+    /// （systemd / launchd / タスクスケジューラ）
+    #[command(alias = "install_service")]
+    InstallService {
+        /// WebダッシュボードのHTTPポート
+        #[arg(long, default_value_t = 12700)]
+        port: u16,
+        /// Webダッシュボードを待ち受けるアドレス
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: IpAddr,
+        /// サーバーに追加で渡す引数（例: -- --https --mdns）
+        #[arg(last = true)]
+        server_args: Vec<String>,
+    },
+    /// 登録したサービスを停止して登録を解除する
+    #[command(alias = "uninstall_service")]
+    UninstallService,
+    /// 登録したサービスの状態を表示する
+    #[command(alias = "service_status")]
+    ServiceStatus,
+    /// テンプレート用のSurrealDBに名前空間・データベース・テーブルを作成する
+    /// （権限のあるユーザーで一度だけ実行する。接続先は VANTAGE_DB_* の環境変数）
+    #[command(alias = "init_db")]
+    InitDb {
+        /// 作成に使うユーザー（既定は VANTAGE_DB_ADMIN_USERNAME、なければ VANTAGE_DB_USERNAME）。
+        /// パスワードは VANTAGE_DB_ADMIN_PASSWORD（なければ VANTAGE_DB_PASSWORD）
+        #[arg(long)]
+        username: Option<String>,
+        /// ユーザーを定義した単位（root・namespace・database、既定は VANTAGE_DB_AUTH_LEVEL）
+        #[arg(long)]
+        auth_level: Option<vantage_persistence::AuthLevel>,
+        /// 作成せず、足りない定義とその作成に必要な単位だけを表示する
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+impl Cli {
+    /// Webサーバーの起動オプション
+    fn web_server_options(&self) -> WebServerOptions {
+        let https = self.https || self.tls_cert.is_some();
+        let tls = https.then(|| {
+            let cert = match (&self.tls_cert, &self.tls_key) {
+                (Some(cert_path), Some(key_path)) => TlsCertSource::Files {
+                    cert_path: cert_path.clone(),
+                    key_path: key_path.clone(),
+                },
+                _ => TlsCertSource::LocalCa {
+                    hostnames: self.tls_hosts.clone(),
+                },
+            };
+            TlsOptions {
+                https_port: self.https_port,
+                cert,
+                redirect_http: !self.no_https_redirect,
+            }
+        });
+        WebServerOptions {
+            bind: self.bind,
+            port: self.port,
+            tls,
+        }
+    }
 }
 
 #[tokio::main]
@@ -23,9 +186,31 @@ async fn main() -> Result<()> {
     // clapを使用してコマンドライン引数をパース
     let cli = Cli::parse();
 
+    match cli.command {
+        Some(Command::Discover { timeout_secs }) => return discover(timeout_secs).await,
+        Some(Command::Openapi) => {
+            println!("{}", vantage::atom::web::openapi::openapi_json());
+            return Ok(());
+        }
+        Some(Command::InstallService {
+            port,
+            bind,
+            ref server_args,
+        }) => return install_service(port, bind, server_args),
+        Some(Command::UninstallService) => return uninstall_service(),
+        Some(Command::ServiceStatus) => return service_status(),
+        Some(Command::InitDb {
+            ref username,
+            auth_level,
+            check,
+        }) => return init_db(username.clone(), auth_level, check).await,
+        None => {}
+    }
+
     // CLI引数から設定を導出
     let auto_open = !cli.no_open;
-    let web_port = 12700; // デフォルトポート（衝突時は自動変更）
+    let web_options = cli.web_server_options();
+    let web_port = web_options.port;
 
     // 環境に基づいてロギングをセットアップ
     let log_level = env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
@@ -48,37 +233,202 @@ async fn main() -> Result<()> {
                 .map_err(|e| anyhow::anyhow!("Invalid log level: {}", e))?,
         );
 
+    let writer = match &cli.log_file {
+        Some(path) => {
+            // rotate_server_log でローテーションできるよう server_admin 経由で書き込む
+            server_admin::open_log_file(path).map_err(|e| {
+                anyhow::anyhow!("Failed to open log file {}: {}", path.display(), e)
+            })?;
+            BoxMakeWriter::new(
+                std::io::stderr
+                    .and(ServerLogWriter::new)
+                    .and(server_admin::LogFileWriter::new),
+            )
+        }
+        None => BoxMakeWriter::new(std::io::stderr.and(ServerLogWriter::new)),
+    };
     tracing_subscriber::fmt()
         .with_env_filter(filter)
-        .with_writer(std::io::stderr)
+        .with_writer(writer)
         .with_ansi(false)
         .init();
 
     tracing::info!("Starting Vantage MCP (MCP + Web mode)");
+    server_startup::begin();
+
+    // データファイルの置き場所を決め、以前の ~/.vantage・~/.ichimi から移行する
+    let started = Instant::now();
+    let data_paths = vantage_persistence::paths::init(cli.data_dir.as_deref());
+    tracing::info!(
+        "Data directory: {} ({:?})",
+        data_paths.data_dir.display(),
+        data_paths.source
+    );
+    let migrated = vantage_persistence::paths::migrate_legacy(data_paths);
+    if !migrated.is_empty() {
+        tracing::info!("Migrated {} legacy data file(s)", migrated.len());
+    }
+    server_startup::record(
+        "data_dir",
+        started,
+        (!migrated.is_empty()).then(|| format!("migrated {} legacy file(s)", migrated.len())),
+    );
+
+    // 切断をまたいで動いているサーバーがあれば、標準入出力をそこへ中継するだけにする
+    #[cfg(unix)]
+    if cli.keep_alive && !cli.headless {
+        let socket = mcp_session::socket_path();
+        match mcp_session::relay_stdio(&socket).await {
+            Ok(true) => {
+                tracing::info!("Relayed MCP session to the running server ended");
+                std::process::exit(0);
+            }
+            Ok(false) => {}
+            // サーバーが動いているかわからないまま起動すると、そのソケットを奪ってしまう
+            Err(e) => {
+                tracing::error!(
+                    "Failed to reach the running server at {}: {}",
+                    socket.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+    }
 
     // 共有プロセスマネージャーを作成
-    let process_manager = vantage::atom::process::ProcessManager::new().await;
+    let process_manager = server_startup::measure(
+        "process_manager",
+        vantage::atom::process::ProcessManager::new(),
+    )
+    .await;
+
+    // restart_server で再起動した場合、実行中のまま引き継いだプロセス
+    let handoff = server_admin::take_handoff();
+    let restarted = handoff.is_some();
+    if let Some(handoff) = &handoff {
+        tracing::info!(
+            "Restarted in place; taking over {} running process(es)",
+            handoff.processes.len()
+        );
+    }
+
+    // mDNSによる告知を有効にする（以降は設定から切り替え可能）
+    if cli.mdns || cli.mdns_name.is_some() {
+        let mut settings = process_manager.get_settings().await.unwrap_or_default();
+        settings.mdns.enabled = true;
+        if cli.mdns_name.is_some() {
+            settings.mdns.instance_name = cli.mdns_name.clone();
+        }
+        if let Err(e) = process_manager.save_settings(settings).await {
+            tracing::warn!("Failed to enable mDNS advertisement: {}", e);
+        }
+    }
+
+    // カオスモードを有効にする（以降は設定から切り替え可能）
+    if cli.chaos {
+        let mut settings = process_manager.get_settings().await.unwrap_or_default();
+        settings.chaos.enabled = true;
+        if let Err(e) = process_manager.save_settings(settings).await {
+            tracing::warn!("Failed to enable chaos mode: {}", e);
+        } else {
+            tracing::warn!("Chaos mode is enabled; chaos tools can kill and pause processes");
+        }
+    }
+
+    // 承認が必要なツールを追加する（以降は設定から変更可能）
+    if !cli.require_approval.is_empty() || !cli.require_confirmation.is_empty() {
+        let mut settings = process_manager.get_settings().await.unwrap_or_default();
+        for tool in &cli.require_approval {
+            if !settings.approvals.tools.contains(tool) {
+                settings.approvals.tools.push(tool.clone());
+            }
+        }
+        for tool in &cli.require_confirmation {
+            if !settings.approvals.confirm_tools.contains(tool) {
+                settings.approvals.confirm_tools.push(tool.clone());
+            }
+        }
+        if let Err(e) = process_manager.save_settings(settings).await {
+            tracing::error!("Failed to configure approvals: {}", e);
+            std::process::exit(1);
+        }
+    }
 
     // 設定されている場合、起動時にプロセスを自動インポート
     // まず自動起動プロセス用のYAMLスナップショットを試行
-    let yaml_snapshot = std::env::var("HOME")
-        .map(|home| format!("{home}/.vantage/snapshot.yaml"))
-        .unwrap_or_else(|_| ".vantage/snapshot.yaml".to_string());
+    let yaml_snapshot = data_paths.snapshot_file();
+    let legacy_import_file = env::var("VANTAGE_IMPORT_FILE").unwrap_or_else(|_| {
+        data_paths
+            .export_dir()
+            .join("processes.yaml")
+            .to_string_lossy()
+            .to_string()
+    });
 
-    if std::path::Path::new(&yaml_snapshot).exists() {
-        tracing::info!("Restoring from YAML snapshot: {}", yaml_snapshot);
-        match process_manager.restore_yaml_snapshot().await {
+    if cli.safe_mode || vantage::atom::process::safe_mode::env_enabled() {
+        // セーフモード: 何も復元・自動起動せず、復元しなかったファイルだけを覚えておく
+        let skipped = if yaml_snapshot.exists() {
+            Some(yaml_snapshot.clone())
+        } else {
+            Some(PathBuf::from(&legacy_import_file)).filter(|path| path.exists())
+        };
+        match &skipped {
+            Some(path) => tracing::warn!(
+                "Safe mode: skipped restoring {} and auto-starting processes (use safe_mode_restore to restore them)",
+                path.display()
+            ),
+            None => tracing::warn!("Safe mode: no snapshot to restore, auto-start disabled"),
+        }
+        process_manager.enter_safe_mode(skipped);
+    } else if yaml_snapshot.exists() {
+        tracing::info!("Restoring from YAML snapshot: {}", yaml_snapshot.display());
+        let started = Instant::now();
+        let restored = process_manager.restore_yaml_snapshot().await;
+        server_startup::record(
+            "snapshot_restore",
+            started,
+            restored.as_ref().err().map(|e| format!("failed: {e}")),
+        );
+        match restored {
             Ok(_) => {
                 tracing::info!("Successfully restored processes from YAML snapshot");
 
+                // 引き継いだプロセスは起動し直さない
+                if let Some(handoff) = handoff {
+                    let started = Instant::now();
+                    let resumed = process_manager.resume_handed_off_processes(handoff).await;
+                    server_startup::record(
+                        "handoff",
+                        started,
+                        Some(format!("took over {} process(es)", resumed.len())),
+                    );
+                    tracing::info!("Took over {} process(es): {:?}", resumed.len(), resumed);
+                }
+
                 // auto_start_on_restoreフラグが設定されたプロセスを自動起動
-                match process_manager.start_auto_start_processes().await {
-                    Ok(started) => {
-                        if !started.is_empty() {
+                let started = Instant::now();
+                let auto_started = process_manager.start_auto_start_processes().await;
+                server_startup::record(
+                    "auto_start",
+                    started,
+                    Some(match &auto_started {
+                        Ok(report) => format!(
+                            "{} of {} process(es) ready",
+                            report.ready().len(),
+                            report.processes.len()
+                        ),
+                        Err(e) => format!("failed: {e}"),
+                    }),
+                );
+                match auto_started {
+                    Ok(report) => {
+                        if !report.processes.is_empty() {
                             tracing::info!(
-                                "Auto-started {} process(es): {:?}",
-                                started.len(),
-                                started
+                                "Auto-started {} process(es) in {}ms: {:?}",
+                                report.ready().len(),
+                                report.total_ms,
+                                report.ready()
                             );
                         } else {
                             tracing::debug!("No processes marked for auto-start");
@@ -96,18 +446,18 @@ async fn main() -> Result<()> {
         }
     } else {
         // YAMLスナップショットがない場合、レガシーインポートにフォールバック
-        let import_file = env::var("VANTAGE_IMPORT_FILE").unwrap_or_else(|_| {
-            std::env::current_dir()
-                .unwrap_or_else(|_| std::path::PathBuf::from("."))
-                .join(".vantage")
-                .join("snapshot.yaml")
-                .to_string_lossy()
-                .to_string()
-        });
+        let import_file = legacy_import_file;
 
         if std::path::Path::new(&import_file).exists() {
             tracing::info!("Auto-importing processes from: {}", import_file);
-            match process_manager.import_processes(&import_file).await {
+            let started = Instant::now();
+            let imported = process_manager.import_processes(&import_file).await;
+            server_startup::record(
+                "legacy_import",
+                started,
+                imported.as_ref().err().map(|e| format!("failed: {e}")),
+            );
+            match imported {
                 Ok(_) => {
                     tracing::info!("Successfully imported processes from {}", import_file);
                 }
@@ -149,41 +499,45 @@ async fn main() -> Result<()> {
 
         #[cfg(not(unix))]
         {
-            let _ = signal::ctrl_c().await;
+            let _ = tokio::signal::ctrl_c().await;
             tracing::info!("Received shutdown signal, exporting processes and stopping all...");
         }
 
-        // まず、自動起動プロセスのYAMLスナップショットを作成
-        match pm_for_shutdown.create_auto_start_snapshot().await {
-            Ok(path) => {
-                tracing::info!("Created auto-start snapshot at {}", path);
-            }
-            Err(e) => {
-                tracing::error!("Failed to create auto-start snapshot: {}", e);
+        if pm_for_shutdown.is_safe_mode() {
+            // 復元しなかったスナップショットを上書きしない
+            tracing::info!("Safe mode: skipping the shutdown snapshot and export");
+        } else {
+            // まず、自動起動プロセスのYAMLスナップショットを作成
+            match pm_for_shutdown.create_auto_start_snapshot().await {
+                Ok(path) => {
+                    tracing::info!("Created auto-start snapshot at {}", path);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to create auto-start snapshot: {}", e);
+                }
             }
-        }
 
-        // 完全なYAMLスナップショットもエクスポート
-        let export_file = env::var("VANTAGE_EXPORT_FILE").unwrap_or_else(|_| {
-            std::env::current_dir()
-                .unwrap_or_else(|_| std::path::PathBuf::from("."))
-                .join(".vantage")
-                .join("snapshot.yaml")
-                .to_string_lossy()
-                .to_string()
-        });
-
-        // ディレクトリが存在しない場合は作成
-        if let Some(parent) = std::path::Path::new(&export_file).parent() {
-            let _ = std::fs::create_dir_all(parent);
-        }
+            // 完全なYAMLスナップショットもエクスポート
+            let export_file = env::var("VANTAGE_EXPORT_FILE").unwrap_or_else(|_| {
+                data_paths
+                    .export_dir()
+                    .join("processes.yaml")
+                    .to_string_lossy()
+                    .to_string()
+            });
+
+            // ディレクトリが存在しない場合は作成
+            if let Some(parent) = std::path::Path::new(&export_file).parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
 
-        match pm_for_shutdown
-            .export_processes(Some(export_file.clone()))
-            .await
-        {
-            Ok(_) => tracing::info!("Successfully exported processes to {}", export_file),
-            Err(e) => tracing::error!("Failed to export processes on shutdown: {}", e),
+            match pm_for_shutdown
+                .export_processes(Some(export_file.clone()))
+                .await
+            {
+                Ok(_) => tracing::info!("Successfully exported processes to {}", export_file),
+                Err(e) => tracing::error!("Failed to export processes on shutdown: {}", e),
+            }
         }
 
         // 次にクリーンシャットダウンのため全プロセスを停止
@@ -214,21 +568,29 @@ async fn main() -> Result<()> {
     let web_persistence = process_manager.persistence_manager();
 
     // Webサーバーを起動し、実際のポートを取得
-    let actual_port =
-        match vantage::atom::web::start_web_server(web_manager, web_persistence, web_port).await {
-            Ok(port) => {
-                tracing::debug!("Web server started on actual port {}", port);
-                port
-            }
-            Err(e) => {
-                tracing::error!("Failed to start web server: {:?}", e);
-                web_port // リクエストされたポートにフォールバック
-            }
-        };
+    let actual_port = match server_startup::measure(
+        "web_server",
+        vantage::atom::web::start_web_server_with_options(
+            web_manager,
+            web_persistence,
+            web_options.clone(),
+        ),
+    )
+    .await
+    {
+        Ok(port) => {
+            tracing::debug!("Web server started on actual port {}", port);
+            port
+        }
+        Err(e) => {
+            tracing::error!("Failed to start web server: {:?}", e);
+            web_port // リクエストされたポートにフォールバック
+        }
+    };
 
-    // 実際のポートでブラウザを開く
-    if auto_open {
-        let url = format!("http://localhost:{actual_port}");
+    // 実際のポートでブラウザを開く（再起動した場合は開いたままのはず）
+    if auto_open && !restarted {
+        let url = web_options.dashboard_url(actual_port);
         tokio::spawn(async move {
             tokio::time::sleep(tokio::time::Duration::from_millis(BROWSER_STARTUP_DELAY_MS)).await;
 
@@ -240,18 +602,55 @@ async fn main() -> Result<()> {
         });
     }
 
+    // ヘッドレスではWebコンソールだけを動かし、シグナルで終了するまで待つ
+    if cli.headless {
+        tracing::info!("Running headless; MCP over stdio is disabled");
+        if cli.keep_alive {
+            let server = server_startup::measure(
+                "mcp_server",
+                VantageServer::with_process_manager(process_manager.clone()),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to initialize VantageServer: {}", e))?;
+            listen_for_sessions(server);
+        }
+        server_startup::finish();
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(KEEPALIVE_INTERVAL_SECS)).await;
+        }
+    }
+
     // MCPサーバーを起動
     tracing::info!("Starting MCP server");
-    let server = VantageServer::with_process_manager(process_manager.clone())
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to initialize VantageServer: {}", e))?;
+    let server = server_startup::measure(
+        "mcp_server",
+        VantageServer::with_process_manager(process_manager.clone()),
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("Failed to initialize VantageServer: {}", e))?;
     let server_arc = std::sync::Arc::new(server);
+    if cli.keep_alive {
+        listen_for_sessions((*server_arc).clone());
+    }
 
+    server_startup::finish();
     tracing::debug!("Serving MCP on stdio");
     match (*server_arc).clone().serve(stdio()).await {
         Ok(service) => {
             tracing::info!("MCP server ready, waiting for requests");
             service.waiting().await?;
+
+            // 切断してもプロセスを止めず、次のセッションを待つ
+            if cli.keep_alive {
+                mcp_session::end();
+                tracing::info!(
+                    "MCP client disconnected; processes keep running until the server is stopped"
+                );
+                loop {
+                    tokio::time::sleep(tokio::time::Duration::from_secs(KEEPALIVE_INTERVAL_SECS))
+                        .await;
+                }
+            }
             tracing::info!("MCP server shutting down");
 
             // MCPサーバー終了時も全プロセスを停止
@@ -288,3 +687,137 @@ async fn main() -> Result<()> {
     tracing::info!("Vantage MCP shutdown complete");
     Ok(())
 }
+
+/// 次のMCPセッションをソケットで受け付ける（--keep-alive）
+fn listen_for_sessions(server: VantageServer) {
+    #[cfg(unix)]
+    {
+        let socket = mcp_session::socket_path();
+        match mcp_session::bind(&socket) {
+            Ok(listener) => {
+                tracing::info!("Accepting MCP sessions on {}", socket.display());
+                tokio::spawn(mcp_session::accept(listener, server));
+            }
+            Err(e) => tracing::warn!(
+                "Failed to listen for MCP sessions on {}: {}",
+                socket.display(),
+                e
+            ),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = server;
+        tracing::warn!(
+            "--keep-alive cannot accept new MCP sessions on this platform; processes keep running for the web console"
+        );
+    }
+}
+
+/// mDNSで告知されているWebコンソールを一覧表示
+async fn discover(timeout_secs: u64) -> Result<()> {
+    let consoles = vantage::web::mdns::discover(std::time::Duration::from_secs(timeout_secs))
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    if consoles.is_empty() {
+        println!("No Vantage web consoles found on the local network");
+        return Ok(());
+    }
+    for console in consoles {
+        println!(
+            "{}\t{}\t{}",
+            console.instance_name,
+            console.url.as_deref().unwrap_or("-"),
+            console.version.as_deref().unwrap_or("unknown version")
+        );
+    }
+    Ok(())
+}
+
+/// テンプレート用のデータベースのスキーマを作成する（`--check` では確認だけ）
+async fn init_db(
+    username: Option<String>,
+    auth_level: Option<vantage_persistence::AuthLevel>,
+    check: bool,
+) -> Result<()> {
+    let mut config = vantage_persistence::db::connection::DbConfig::from_env();
+    if let Some(username) = username.or_else(|| env::var("VANTAGE_DB_ADMIN_USERNAME").ok()) {
+        config.username = username;
+    }
+    if let Ok(password) = env::var("VANTAGE_DB_ADMIN_PASSWORD") {
+        config.password = password;
+    }
+    if let Some(auth_level) = auth_level {
+        config.auth_level = auth_level;
+    }
+    println!(
+        "Connecting to {} ({}/{}) as {} user '{}'",
+        config.endpoint, config.namespace, config.database, config.auth_level, config.username
+    );
+    let conn = vantage_persistence::DbConnection::new(config).await?;
+    let schema = vantage_persistence::SchemaManager::new(conn.db());
+
+    if check {
+        let missing = schema.check().await?;
+        if missing.is_empty() {
+            println!("The schema is up to date");
+            return Ok(());
+        }
+        println!("{} definition(s) missing:", missing.len());
+        for statement in &missing {
+            println!(
+                "  {} (requires a {}-level user)",
+                statement
+                    .definition
+                    .as_ref()
+                    .map_or_else(|| statement.sql.clone(), ToString::to_string),
+                statement.required
+            );
+        }
+        anyhow::bail!("Run `vantagemcp init-db` without --check to create them");
+    }
+
+    let report = schema.bootstrap().await;
+    println!("Applied {} statement(s)", report.applied);
+    if !report.is_ok() {
+        println!("{} statement(s) failed:", report.failures.len());
+        for line in report.diagnostics().lines() {
+            println!("  {line}");
+        }
+        anyhow::bail!("The schema was not fully created");
+    }
+    println!("The schema is ready; the server only needs read/write access to the tables");
+    Ok(())
+}
+
+/// ホームディレクトリ
+fn home_dir() -> Result<PathBuf> {
+    dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not determine the home directory"))
+}
+
+/// ログイン時に起動するサービスとして登録し、状態を表示
+fn install_service(port: u16, bind: IpAddr, server_args: &[String]) -> Result<()> {
+    let platform = service::Platform::current()?;
+    let home = home_dir()?;
+    let config = service::ServiceConfig::from_current(&home, port, &bind.to_string(), server_args)?;
+    let status = service::install(platform, &home, &config)?;
+    println!("{status}");
+    println!("Web console: http://{bind}:{port}");
+    Ok(())
+}
+
+/// サービスの登録を解除
+fn uninstall_service() -> Result<()> {
+    let platform = service::Platform::current()?;
+    service::uninstall(platform, &home_dir()?)?;
+    println!("Removed the {platform}");
+    Ok(())
+}
+
+/// サービスの状態を表示
+fn service_status() -> Result<()> {
+    let platform = service::Platform::current()?;
+    println!("{}", service::status(platform, &home_dir()?)?);
+    Ok(())
+}
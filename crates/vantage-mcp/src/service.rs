@@ -0,0 +1,531 @@
+//! Vantageサーバー自身をログイン時に起動するサービスとして登録する
+//!
+//! Linuxはユーザー単位のsystemdユニット、macOSはlaunchdのLaunchAgent、Windowsはログオン時に
+//! 実行するタスクスケジューラのタスクとして登録します。いずれも管理者権限は不要です。
+//! サービスとして起動したサーバーは標準入力を持たないため、MCPは提供せずWebコンソールだけを
+//! 動かします（`--headless`）。
+
+use anyhow::{Context, Result, bail};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use vantage::atom::security::private_file;
+use vantage_persistence::paths::DataPaths;
+
+/// systemdのユニット名
+const SYSTEMD_UNIT: &str = "vantage.service";
+/// launchdのラベル
+const LAUNCHD_LABEL: &str = "club.chronista.vantage";
+/// タスクスケジューラのタスク名
+const TASK_NAME: &str = "Vantage";
+
+/// サービスとして起動したサーバーのログの場所
+///
+/// サービスはワークスペースの外で動くので、ユーザー単位の状態ディレクトリに置く。
+pub fn log_file() -> PathBuf {
+    DataPaths::user().log_dir().join("server.log")
+}
+
+/// サービスの登録先
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Systemd,
+    Launchd,
+    TaskScheduler,
+}
+
+impl Platform {
+    /// 実行中のOSの登録先
+    pub fn current() -> Result<Self> {
+        if cfg!(target_os = "linux") {
+            Ok(Self::Systemd)
+        } else if cfg!(target_os = "macos") {
+            Ok(Self::Launchd)
+        } else if cfg!(windows) {
+            Ok(Self::TaskScheduler)
+        } else {
+            bail!("Installing Vantage as a service is not supported on this platform")
+        }
+    }
+
+    /// サービス定義ファイルの場所
+    pub fn definition_path(self, home: &Path) -> PathBuf {
+        match self {
+            Self::Systemd => home.join(".config/systemd/user").join(SYSTEMD_UNIT),
+            Self::Launchd => home
+                .join("Library/LaunchAgents")
+                .join(format!("{LAUNCHD_LABEL}.plist")),
+            Self::TaskScheduler => home.join(".vantage").join("service-task.xml"),
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Systemd => "systemd user unit",
+            Self::Launchd => "launchd agent",
+            Self::TaskScheduler => "Task Scheduler logon task",
+        })
+    }
+}
+
+/// 登録するサーバーの起動方法
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    pub executable: PathBuf,
+    pub args: Vec<String>,
+    pub working_dir: PathBuf,
+    pub log_file: PathBuf,
+    /// サーバーに渡す環境変数（登録時の値を記録する）
+    pub env: Vec<(String, String)>,
+}
+
+impl ServiceConfig {
+    /// 実行中のバイナリと環境から作る（`server_args` は `--headless` などに追加する引数）
+    pub fn from_current(
+        home: &Path,
+        port: u16,
+        bind: &str,
+        server_args: &[String],
+    ) -> Result<Self> {
+        let executable = std::env::current_exe()
+            .and_then(|path| path.canonicalize())
+            .context("Failed to resolve the path of the vantagemcp executable")?;
+        let log_file = log_file();
+
+        let mut args = vec![
+            "--headless".to_string(),
+            "--no-open".to_string(),
+            "--port".to_string(),
+            port.to_string(),
+            "--bind".to_string(),
+            bind.to_string(),
+        ];
+        args.extend(server_args.iter().cloned());
+
+        // 管理するプロセスがコマンドを見つけられるよう、登録時のPATHを引き継ぐ
+        let mut env: Vec<(String, String)> = std::env::vars()
+            .filter(|(key, _)| {
+                matches!(key.as_str(), "PATH" | "RUST_LOG") || key.starts_with("VANTAGE_")
+            })
+            .collect();
+        env.push(("HOME".to_string(), home.display().to_string()));
+        env.sort();
+
+        Ok(Self {
+            executable,
+            args,
+            working_dir: home.to_path_buf(),
+            log_file,
+            env,
+        })
+    }
+}
+
+/// サービスの状態
+#[derive(Debug, Clone)]
+pub struct ServiceStatus {
+    pub platform: Platform,
+    pub definition: PathBuf,
+    pub installed: bool,
+    pub enabled: bool,
+    pub running: bool,
+    pub log_file: PathBuf,
+}
+
+impl fmt::Display for ServiceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        writeln!(f, "Service:    {}", self.platform)?;
+        writeln!(f, "Definition: {}", self.definition.display())?;
+        writeln!(f, "Installed:  {}", yes_no(self.installed))?;
+        writeln!(f, "Enabled:    {}", yes_no(self.enabled))?;
+        writeln!(f, "Running:    {}", yes_no(self.running))?;
+        write!(f, "Log file:   {}", self.log_file.display())
+    }
+}
+
+/// サービス定義を作る
+pub fn render(platform: Platform, config: &ServiceConfig) -> String {
+    match platform {
+        Platform::Systemd => render_systemd(config),
+        Platform::Launchd => render_launchd(config),
+        Platform::TaskScheduler => render_task(config),
+    }
+}
+
+fn render_systemd(config: &ServiceConfig) -> String {
+    let exec = std::iter::once(config.executable.display().to_string())
+        .chain(config.args.iter().cloned())
+        // コマンドラインでは `$` が変数の展開になる
+        .map(|arg| systemd_quote(&arg.replace('$', "$$")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let env: String = config
+        .env
+        .iter()
+        .map(|(key, value)| format!("Environment={}\n", systemd_quote(&format!("{key}={value}"))))
+        .collect();
+    let log = systemd_escape(&config.log_file.display().to_string());
+    format!(
+        "[Unit]
+Description=Vantage process manager
+After=network-online.target
+
+[Service]
+Type=simple
+ExecStart={exec}
+WorkingDirectory={dir}
+{env}StandardOutput=append:{log}
+StandardError=append:{log}
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy=default.target
+",
+        dir = systemd_quote(&config.working_dir.display().to_string()),
+    )
+}
+
+fn render_launchd(config: &ServiceConfig) -> String {
+    let args: String = std::iter::once(config.executable.display().to_string())
+        .chain(config.args.iter().cloned())
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(&arg)))
+        .collect();
+    let env: String = config
+        .env
+        .iter()
+        .map(|(key, value)| {
+            format!(
+                "        <key>{}</key>\n        <string>{}</string>\n",
+                xml_escape(key),
+                xml_escape(value)
+            )
+        })
+        .collect();
+    let log = xml_escape(&config.log_file.display().to_string());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCHD_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+{args}    </array>
+    <key>WorkingDirectory</key>
+    <string>{dir}</string>
+    <key>EnvironmentVariables</key>
+    <dict>
+{env}    </dict>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        dir = xml_escape(&config.working_dir.display().to_string()),
+    )
+}
+
+/// タスクスケジューラのタスク定義（出力先を指定できないため、ログはサーバー自身が `--log-file` に書く）
+fn render_task(config: &ServiceConfig) -> String {
+    let args = config
+        .args
+        .iter()
+        .cloned()
+        .chain([
+            "--log-file".to_string(),
+            config.log_file.display().to_string(),
+        ])
+        .map(|arg| windows_quote(&arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>Vantage process manager</Description>
+  </RegistrationInfo>
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+    </LogonTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <LogonType>InteractiveToken</LogonType>
+      <RunLevel>LeastPrivilege</RunLevel>
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <RestartOnFailure>
+      <Interval>PT1M</Interval>
+      <Count>3</Count>
+    </RestartOnFailure>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{command}</Command>
+      <Arguments>{args}</Arguments>
+      <WorkingDirectory>{dir}</WorkingDirectory>
+    </Exec>
+  </Actions>
+</Task>
+"#,
+        command = xml_escape(&config.executable.display().to_string()),
+        args = xml_escape(&args),
+        dir = xml_escape(&config.working_dir.display().to_string()),
+    )
+}
+
+/// サービスを登録して起動する
+pub fn install(platform: Platform, home: &Path, config: &ServiceConfig) -> Result<ServiceStatus> {
+    let path = platform.definition_path(home);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    if let Some(parent) = config.log_file.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let definition = render(platform, config);
+    let bytes = match platform {
+        // schtasksはUTF-16のタスク定義を読む
+        Platform::TaskScheduler => std::iter::once(0xfeff)
+            .chain(definition.encode_utf16())
+            .flat_map(u16::to_le_bytes)
+            .collect(),
+        _ => definition.into_bytes(),
+    };
+    write_private(&path, &bytes)?;
+
+    match platform {
+        Platform::Systemd => {
+            run("systemctl", &["--user", "daemon-reload"])?;
+            run("systemctl", &["--user", "enable", "--now", SYSTEMD_UNIT])?;
+        }
+        Platform::Launchd => {
+            let path = path.display().to_string();
+            // 登録済みの場合は読み込み直す
+            let _ = run("launchctl", &["unload", &path]);
+            run("launchctl", &["load", "-w", &path])?;
+        }
+        Platform::TaskScheduler => {
+            let path = path.display().to_string();
+            run(
+                "schtasks",
+                &["/Create", "/TN", TASK_NAME, "/XML", &path, "/F"],
+            )?;
+            run("schtasks", &["/Run", "/TN", TASK_NAME])?;
+        }
+    }
+    status(platform, home)
+}
+
+/// サービスを停止して登録を解除する
+pub fn uninstall(platform: Platform, home: &Path) -> Result<()> {
+    let path = platform.definition_path(home);
+    match platform {
+        Platform::Systemd => {
+            if path.exists() {
+                run("systemctl", &["--user", "disable", "--now", SYSTEMD_UNIT])?;
+            }
+        }
+        Platform::Launchd => {
+            if path.exists() {
+                run("launchctl", &["unload", "-w", &path.display().to_string()])?;
+            }
+        }
+        Platform::TaskScheduler => {
+            if succeeds("schtasks", &["/Query", "/TN", TASK_NAME]) {
+                let _ = run("schtasks", &["/End", "/TN", TASK_NAME]);
+                run("schtasks", &["/Delete", "/TN", TASK_NAME, "/F"])?;
+            }
+        }
+    }
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    if platform == Platform::Systemd {
+        run("systemctl", &["--user", "daemon-reload"])?;
+    }
+    Ok(())
+}
+
+/// サービスの状態を調べる
+pub fn status(platform: Platform, home: &Path) -> Result<ServiceStatus> {
+    let definition = platform.definition_path(home);
+    let (installed, enabled, running) = match platform {
+        Platform::Systemd => (
+            definition.exists(),
+            succeeds(
+                "systemctl",
+                &["--user", "is-enabled", "--quiet", SYSTEMD_UNIT],
+            ),
+            succeeds(
+                "systemctl",
+                &["--user", "is-active", "--quiet", SYSTEMD_UNIT],
+            ),
+        ),
+        Platform::Launchd => {
+            let listed = output("launchctl", &["list", LAUNCHD_LABEL]);
+            (
+                definition.exists(),
+                listed.is_some(),
+                listed.is_some_and(|out| out.contains("\"PID\"")),
+            )
+        }
+        Platform::TaskScheduler => {
+            let listed = output("schtasks", &["/Query", "/TN", TASK_NAME, "/FO", "LIST"]);
+            (
+                listed.is_some(),
+                listed.as_ref().is_some_and(|out| !out.contains("Disabled")),
+                listed.is_some_and(|out| out.contains("Running")),
+            )
+        }
+    };
+    Ok(ServiceStatus {
+        platform,
+        definition,
+        installed,
+        enabled,
+        running,
+        log_file: log_file(),
+    })
+}
+
+/// 所有者だけが読めるファイルとして書き出す（環境変数に接続情報が含まれることがあるため）
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    private_file::write(path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let out = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {program}"))?;
+    if !out.status.success() {
+        bail!(
+            "{program} {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn succeeds(program: &str, args: &[&str]) -> bool {
+    output(program, args).is_some()
+}
+
+/// 成功した場合の標準出力
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// systemdの指定子（`%`）を無効にする
+fn systemd_escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+/// systemdのコマンドライン・Environment用に引用する
+fn systemd_quote(value: &str) -> String {
+    let value = systemd_escape(value);
+    if !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';'))
+    {
+        return value;
+    }
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+/// Windowsのコマンドライン引数として引用する
+fn windows_quote(value: &str) -> String {
+    if !value.is_empty() && !value.chars().any(|c| c.is_whitespace() || c == '"') {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('"', "\\\""))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ServiceConfig {
+        ServiceConfig {
+            executable: PathBuf::from("/opt/vantage/bin/vantagemcp"),
+            args: vec![
+                "--headless".to_string(),
+                "--port".to_string(),
+                "12700".to_string(),
+                "--mdns-name".to_string(),
+                "Dev box".to_string(),
+            ],
+            working_dir: PathBuf::from("/home/dev"),
+            log_file: PathBuf::from("/home/dev/.vantage/logs/server.log"),
+            env: vec![
+                ("HOME".to_string(), "/home/dev".to_string()),
+                ("VANTAGE_DB_PASSWORD".to_string(), "50% off".to_string()),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_render_systemd_quotes_arguments() {
+        let unit = render(Platform::Systemd, &config());
+        assert!(unit.contains(
+            "ExecStart=/opt/vantage/bin/vantagemcp --headless --port 12700 --mdns-name \"Dev box\"\n"
+        ));
+        assert!(unit.contains("Environment=\"VANTAGE_DB_PASSWORD=50%% off\"\n"));
+        assert!(unit.contains("StandardError=append:/home/dev/.vantage/logs/server.log\n"));
+        assert!(unit.contains("WantedBy=default.target"));
+    }
+
+    #[test]
+    fn test_render_launchd_and_task() {
+        let plist = render(Platform::Launchd, &config());
+        assert!(plist.contains("<string>Dev box</string>"));
+        assert!(plist.contains("<key>VANTAGE_DB_PASSWORD</key>"));
+
+        let task = render(Platform::TaskScheduler, &config());
+        assert!(task.contains(
+            "<Arguments>--headless --port 12700 --mdns-name &quot;Dev box&quot; --log-file /home/dev/.vantage/logs/server.log</Arguments>"
+        ));
+        assert!(task.contains("<LogonTrigger>"));
+    }
+}
@@ -34,6 +34,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         updated_at: Utc::now(),
         tags: vec!["production".to_string(), "web".to_string()],
         auto_start_on_restore: true,
+        idle_detection: None,
+        health_check: None,
+        tmux: None,
+        repl: None,
+        log_sources: Vec::new(),
+        run_as: None,
+        resource_limits: None,
+        container: None,
+        source: None,
+        log_sinks: Vec::new(),
+        redaction: None,
+        metadata: HashMap::new(),
+        runbook: None,
+        startup: None,
+        lazy_start: None,
+        env_loader: None,
+        output_recording: None,
+        assertions: Vec::new(),
+        restart_policy: None,
+        depends_on: Vec::new(),
+        parameters: Vec::new(),
+        keep_stdin_open: false,
+        group: None,
+        sensitive_env: HashMap::new(),
     };
 
     let mut env2 = HashMap::new();
@@ -67,6 +91,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         updated_at: Utc::now(),
         tags: vec!["worker".to_string(), "background".to_string()],
         auto_start_on_restore: true,
+        idle_detection: None,
+        health_check: None,
+        tmux: None,
+        repl: None,
+        log_sources: Vec::new(),
+        run_as: None,
+        resource_limits: None,
+        container: None,
+        source: None,
+        log_sinks: Vec::new(),
+        redaction: None,
+        metadata: HashMap::new(),
+        runbook: None,
+        startup: None,
+        lazy_start: None,
+        env_loader: None,
+        output_recording: None,
+        assertions: Vec::new(),
+        restart_policy: None,
+        depends_on: Vec::new(),
+        parameters: Vec::new(),
+        keep_stdin_open: false,
+        group: None,
+        sensitive_env: HashMap::new(),
     };
 
     let monitoring = ProcessInfo {
@@ -92,6 +140,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         updated_at: Utc::now(),
         tags: vec!["monitoring".to_string(), "metrics".to_string()],
         auto_start_on_restore: false,
+        idle_detection: None,
+        health_check: None,
+        tmux: None,
+        repl: None,
+        log_sources: Vec::new(),
+        run_as: None,
+        resource_limits: None,
+        container: None,
+        source: None,
+        log_sinks: Vec::new(),
+        redaction: None,
+        metadata: HashMap::new(),
+        runbook: None,
+        startup: None,
+        lazy_start: None,
+        env_loader: None,
+        output_recording: None,
+        assertions: Vec::new(),
+        restart_policy: None,
+        depends_on: Vec::new(),
+        parameters: Vec::new(),
+        keep_stdin_open: false,
+        group: None,
+        sensitive_env: HashMap::new(),
     };
 
     // Save processes to manager
@@ -102,8 +174,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Saved 3 sample processes to manager\n");
 
     // Create snapshot directory
-    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    let snapshot_dir = std::path::Path::new(&home).join(".vantage");
+    let snapshot_dir = vantage_persistence::paths::get().export_dir();
     std::fs::create_dir_all(&snapshot_dir)?;
 
     // Export snapshot to KDL
@@ -2,9 +2,51 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use surrealdb::Surreal;
 use surrealdb::engine::remote::ws::{Client, Ws};
-use surrealdb::opt::auth::Root;
+use surrealdb::opt::auth::{Database, Namespace, Root};
 use tracing::{debug, info};
 
+/// 認証するユーザーを定義した単位
+///
+/// `Root` 以外のユーザーでも、スキーマを作成済みであれば実行時に必要なのはテーブルの読み書き
+/// だけです。スキーマの作成に必要な単位は [`crate::db::schema::SchemaStatement::required`] を
+/// 参照してください。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthLevel {
+    /// データベースのユーザー（`DEFINE USER ... ON DATABASE`）
+    Database,
+    /// 名前空間のユーザー（`DEFINE USER ... ON NAMESPACE`）
+    Namespace,
+    /// ルートユーザー
+    #[default]
+    Root,
+}
+
+impl std::str::FromStr for AuthLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "root" => Ok(Self::Root),
+            "namespace" | "ns" => Ok(Self::Namespace),
+            "database" | "db" => Ok(Self::Database),
+            other => Err(format!(
+                "Unknown auth level '{other}' (expected root, namespace or database)"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for AuthLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Root => "root",
+            Self::Namespace => "namespace",
+            Self::Database => "database",
+        })
+    }
+}
+
 /// SurrealDB接続設定
 ///
 /// # 環境変数
@@ -15,6 +57,7 @@ use tracing::{debug, info};
 /// - `VANTAGE_DB_DATABASE`: データベース名（デフォルト: "main"）
 /// - `VANTAGE_DB_USERNAME`: 認証ユーザー名（デフォルト: "vtg-local"）
 /// - `VANTAGE_DB_PASSWORD`: 認証パスワード（デフォルト: "vtg-local"）
+/// - `VANTAGE_DB_AUTH_LEVEL`: ユーザーを定義した単位（root・namespace・database、デフォルト: root）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DbConfig {
     pub endpoint: String,
@@ -22,6 +65,8 @@ pub struct DbConfig {
     pub database: String,
     pub username: String,
     pub password: String,
+    #[serde(default)]
+    pub auth_level: AuthLevel,
 }
 
 impl DbConfig {
@@ -37,6 +82,13 @@ impl DbConfig {
             database: std::env::var("VANTAGE_DB_DATABASE").unwrap_or(default.database),
             username: std::env::var("VANTAGE_DB_USERNAME").unwrap_or(default.username),
             password: std::env::var("VANTAGE_DB_PASSWORD").unwrap_or(default.password),
+            auth_level: match std::env::var("VANTAGE_DB_AUTH_LEVEL") {
+                Ok(level) => level.parse().unwrap_or_else(|e| {
+                    tracing::warn!("{}; signing in as a root user", e);
+                    AuthLevel::Root
+                }),
+                Err(_) => default.auth_level,
+            },
         }
     }
 }
@@ -49,6 +101,7 @@ impl Default for DbConfig {
             database: "main".to_string(),
             username: "vtg-local".to_string(),
             password: "vtg-local".to_string(),
+            auth_level: AuthLevel::Root,
         }
     }
 }
@@ -82,13 +135,42 @@ impl DbConnection {
             .await
             .with_context(|| format!("Failed to connect to SurrealDB at {}", config.endpoint))?;
 
-        debug!("Signing in with user: {}", config.username);
-        db.signin(Root {
-            username: &config.username,
-            password: &config.password,
-        })
-        .await
-        .with_context(|| format!("Failed to sign in as user: {}", config.username))?;
+        debug!(
+            "Signing in with {} user: {}",
+            config.auth_level, config.username
+        );
+        let signin = match config.auth_level {
+            AuthLevel::Root => {
+                db.signin(Root {
+                    username: &config.username,
+                    password: &config.password,
+                })
+                .await
+            }
+            AuthLevel::Namespace => {
+                db.signin(Namespace {
+                    namespace: &config.namespace,
+                    username: &config.username,
+                    password: &config.password,
+                })
+                .await
+            }
+            AuthLevel::Database => {
+                db.signin(Database {
+                    namespace: &config.namespace,
+                    database: &config.database,
+                    username: &config.username,
+                    password: &config.password,
+                })
+                .await
+            }
+        };
+        signin.with_context(|| {
+            format!(
+                "Failed to sign in as {} user: {}",
+                config.auth_level, config.username
+            )
+        })?;
 
         debug!(
             "Using namespace: {}, database: {}",
@@ -178,4 +260,19 @@ mod tests {
         let conn = DbConnection::new_default().await.unwrap();
         conn.test_connection().await.unwrap();
     }
+
+    #[test]
+    fn test_auth_level_parse_and_order() {
+        assert_eq!("ROOT".parse::<AuthLevel>().unwrap(), AuthLevel::Root);
+        assert_eq!("ns".parse::<AuthLevel>().unwrap(), AuthLevel::Namespace);
+        assert_eq!("db".parse::<AuthLevel>().unwrap(), AuthLevel::Database);
+        assert!("admin".parse::<AuthLevel>().is_err());
+        for level in [AuthLevel::Root, AuthLevel::Namespace, AuthLevel::Database] {
+            assert_eq!(level.to_string().parse::<AuthLevel>().unwrap(), level);
+        }
+        // 強い単位ほど大きい（スキーマの作成に必要な単位と比べる）
+        assert!(AuthLevel::Database < AuthLevel::Namespace);
+        assert!(AuthLevel::Namespace < AuthLevel::Root);
+        assert_eq!(DbConfig::default().auth_level, AuthLevel::Root);
+    }
 }
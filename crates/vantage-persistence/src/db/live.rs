@@ -0,0 +1,131 @@
+//! テンプレートの変更の購読
+//!
+//! 同じデータベースを複数のサーバーで共有している場合や、外部から直接編集した場合に、
+//! 実行中のサーバーへ変更を伝えます。`live-query` フィーチャーを有効にすると、
+//! SurrealDBの `LIVE SELECT` で `template` テーブルを購読し、接続が切れた場合は
+//! 間隔を空けながら再接続します。
+//!
+//! プロセス情報はサーバーごとのメモリ上に保持しており、データベースには保存しないため
+//! 購読の対象はテンプレートのみです。テンプレートはキャッシュせず、リクエストのたびに
+//! データベースから読むため、変更は `/api/templates/changes` のSSEでWebクライアントに伝える
+//! だけです。
+
+use super::template_repository::Template;
+use serde::Serialize;
+
+/// 変更の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateChangeAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// テンプレートの変更
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateChange {
+    pub action: TemplateChangeAction,
+    /// 変更後のテンプレート（削除の場合は削除前の内容）
+    pub template: Template,
+}
+
+#[cfg(feature = "live-query")]
+pub use subscriber::LiveTemplatesGuard;
+
+#[cfg(feature = "live-query")]
+mod subscriber {
+    use super::{TemplateChange, TemplateChangeAction};
+    use crate::db::connection::{DbConfig, DbConnection};
+    use crate::db::template_repository::Template;
+    use anyhow::{Context, Result};
+    use futures::StreamExt;
+    use std::time::Duration;
+    use surrealdb::{Action, Notification};
+    use tokio::sync::broadcast;
+    use tokio::task::JoinHandle;
+    use tracing::{debug, info, warn};
+
+    /// 再接続までの最初の待ち時間
+    const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+    /// 再接続までの最大の待ち時間
+    const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+    /// `template` テーブルの購読を続けるガード
+    ///
+    /// 持っている間だけ購読し、破棄すると購読を止める。
+    pub struct LiveTemplatesGuard {
+        task: JoinHandle<()>,
+    }
+
+    impl LiveTemplatesGuard {
+        /// 購読を始め、変更を `sender` に送る
+        ///
+        /// 購読用に専用の接続を開き、切れた場合は1秒から最大60秒まで間隔を倍にしながら
+        /// 再接続する。
+        pub fn spawn(config: DbConfig, sender: broadcast::Sender<TemplateChange>) -> Self {
+            let task = tokio::spawn(async move {
+                let mut delay = INITIAL_RECONNECT_DELAY;
+                loop {
+                    match subscribe(&config, &sender, &mut delay).await {
+                        Ok(()) => info!("Template live query ended; reconnecting"),
+                        Err(e) => warn!(
+                            "Template live query failed: {:#}; retrying in {}s",
+                            e,
+                            delay.as_secs()
+                        ),
+                    }
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                }
+            });
+            Self { task }
+        }
+    }
+
+    impl Drop for LiveTemplatesGuard {
+        fn drop(&mut self) {
+            self.task.abort();
+        }
+    }
+
+    /// 接続して購読し、通知が途切れるまで変更を送る
+    async fn subscribe(
+        config: &DbConfig,
+        sender: &broadcast::Sender<TemplateChange>,
+        delay: &mut Duration,
+    ) -> Result<()> {
+        let connection = DbConnection::new(config.clone()).await?;
+        let mut stream = connection
+            .db()
+            .select::<Vec<Template>>("template")
+            .live()
+            .await
+            .context("Failed to start LIVE SELECT on template")?;
+        info!("Subscribed to template changes");
+        // 購読できたら待ち時間を戻す
+        *delay = INITIAL_RECONNECT_DELAY;
+
+        while let Some(notification) = stream.next().await {
+            let notification: Notification<Template> =
+                notification.context("Template live query notification failed")?;
+            let action = match notification.action {
+                Action::Create => TemplateChangeAction::Created,
+                Action::Update => TemplateChangeAction::Updated,
+                Action::Delete => TemplateChangeAction::Deleted,
+                // サーバー側で購読が終了した
+                _ => return Ok(()),
+            };
+            debug!(
+                "Template '{}' changed ({:?})",
+                notification.data.name, action
+            );
+            // 受信者がいなくてもエラーにしない
+            let _ = sender.send(TemplateChange {
+                action,
+                template: notification.data,
+            });
+        }
+        Ok(())
+    }
+}
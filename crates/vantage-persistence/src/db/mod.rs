@@ -5,8 +5,9 @@
 //! # モジュール
 //!
 //! - `connection`: データベース接続の管理
-//! - `schema`: スキーマ定義の適用と管理
+//! - `schema`: スキーマ定義の適用と管理（管理者による作成と、実行時の確認）
 //! - `template_repository`: テンプレートのCRUD操作
+//! - `live`: テンプレートの変更の購読（`live-query` フィーチャー）
 //!
 //! # 使用例
 //!
@@ -33,12 +34,16 @@
 //! ```
 
 pub mod connection;
+pub mod live;
 pub mod schema;
 pub mod template_repository;
 
 #[cfg(test)]
 mod template_repository_tests;
 
-pub use connection::DbConnection;
-pub use schema::SchemaManager;
+pub use connection::{AuthLevel, DbConnection};
+#[cfg(feature = "live-query")]
+pub use live::LiveTemplatesGuard;
+pub use live::{TemplateChange, TemplateChangeAction};
+pub use schema::{SchemaManager, SchemaReport};
 pub use template_repository::TemplateRepository;
@@ -1,13 +1,212 @@
-use anyhow::{Context, Result};
+use crate::db::connection::AuthLevel;
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::HashMap;
 use surrealdb::Surreal;
 use surrealdb::engine::remote::ws::Client;
 use tracing::{debug, info};
 
+/// スキーマファイル（適用する順）
+const SCHEMA_FILES: [&str; 3] = [
+    include_str!("../../schema/00_init/namespace_and_databases.surql"),
+    include_str!("../../schema/01_tables/template.surql"),
+    include_str!("../../schema/02_indexes/template_indexes.surql"),
+];
+
+/// スキーマの文が定義するもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Definition {
+    Namespace(String),
+    Database(String),
+    Table(String),
+    Field { table: String, name: String },
+    Index { table: String, name: String },
+}
+
+impl std::fmt::Display for Definition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Namespace(name) => write!(f, "namespace {name}"),
+            Self::Database(name) => write!(f, "database {name}"),
+            Self::Table(name) => write!(f, "table {name}"),
+            Self::Field { table, name } => write!(f, "field {table}.{name}"),
+            Self::Index { table, name } => write!(f, "index {name} on {table}"),
+        }
+    }
+}
+
+/// スキーマファイルの1つの文
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaStatement {
+    pub sql: String,
+    /// 実行できるユーザーの単位（これより狭い単位のユーザーでは実行できない）
+    pub required: AuthLevel,
+    /// 定義するもの（`USE` などでは `None`）
+    pub definition: Option<Definition>,
+}
+
+impl SchemaStatement {
+    fn parse(sql: &str) -> Self {
+        let tokens: Vec<&str> = sql.split_whitespace().collect();
+        let upper: Vec<String> = tokens.iter().map(|t| t.to_ascii_uppercase()).collect();
+        let keyword = |i: usize| upper.get(i).map(String::as_str);
+        // DEFINE <種類> [IF NOT EXISTS | OVERWRITE] <名前> [ON [TABLE] <テーブル>]
+        let name_at = match (keyword(2), keyword(3), keyword(4)) {
+            (Some("IF"), Some("NOT"), Some("EXISTS")) => 5,
+            (Some("OVERWRITE"), _, _) => 3,
+            _ => 2,
+        };
+        let name = tokens.get(name_at).map(|t| t.to_string());
+        let table = upper
+            .iter()
+            .position(|t| t == "ON")
+            .map(|on| {
+                if keyword(on + 1) == Some("TABLE") {
+                    on + 2
+                } else {
+                    on + 1
+                }
+            })
+            .and_then(|i| tokens.get(i))
+            .map(|t| t.to_string());
+        let definition = match (keyword(0), keyword(1), name) {
+            (Some("DEFINE"), Some("NAMESPACE" | "NS"), Some(name)) => {
+                Some(Definition::Namespace(name))
+            }
+            (Some("DEFINE"), Some("DATABASE" | "DB"), Some(name)) => {
+                Some(Definition::Database(name))
+            }
+            (Some("DEFINE"), Some("TABLE"), Some(name)) => Some(Definition::Table(name)),
+            (Some("DEFINE"), Some("FIELD"), Some(name)) => {
+                table.map(|table| Definition::Field { table, name })
+            }
+            (Some("DEFINE"), Some("INDEX"), Some(name)) => {
+                table.map(|table| Definition::Index { table, name })
+            }
+            _ => None,
+        };
+        // 名前空間は root、データベースは名前空間のユーザーが定義でき、
+        // テーブル・フィールド・インデックスはデータベースのユーザー（OWNER・EDITOR）でも定義できる
+        let required = match definition {
+            Some(Definition::Namespace(_)) => AuthLevel::Root,
+            Some(Definition::Database(_)) => AuthLevel::Namespace,
+            _ => AuthLevel::Database,
+        };
+        Self {
+            sql: sql.to_string(),
+            required,
+            definition,
+        }
+    }
+
+    fn is_use(&self) -> bool {
+        self.sql
+            .split_whitespace()
+            .next()
+            .is_some_and(|keyword| keyword.eq_ignore_ascii_case("USE"))
+    }
+}
+
+/// SurrealQLのスクリプトを文に分ける（`--` のコメントを除く）
+pub fn statements(script: &str) -> Vec<SchemaStatement> {
+    script
+        .lines()
+        .map(|line| line.split_once("--").map_or(line, |(code, _)| code))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .split(';')
+        .map(|sql| sql.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|sql| !sql.is_empty())
+        .map(|sql| SchemaStatement::parse(&sql))
+        .collect()
+}
+
+/// 埋め込んだスキーマのすべての文（適用する順）
+pub fn all_statements() -> Vec<SchemaStatement> {
+    SCHEMA_FILES
+        .iter()
+        .flat_map(|script| statements(script))
+        .collect()
+}
+
+/// 実行に失敗した文
+#[derive(Debug, Clone)]
+pub struct StatementFailure {
+    pub statement: SchemaStatement,
+    pub error: String,
+}
+
+impl StatementFailure {
+    /// 権限が足りずに失敗したか
+    pub fn permission_denied(&self) -> bool {
+        let error = self.error.to_ascii_lowercase();
+        error.contains("permission") || error.contains("not allowed") || error.contains("iam error")
+    }
+}
+
+/// スキーマを適用した結果
+#[derive(Debug, Clone, Default)]
+pub struct SchemaReport {
+    /// 実行した文の数
+    pub applied: usize,
+    pub failures: Vec<StatementFailure>,
+}
+
+impl SchemaReport {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// 失敗した文ごとに、必要なユーザーの単位とエラーを1行ずつ
+    pub fn diagnostics(&self) -> String {
+        self.failures
+            .iter()
+            .map(|failure| {
+                let target = failure
+                    .statement
+                    .definition
+                    .as_ref()
+                    .map_or_else(|| failure.statement.sql.clone(), ToString::to_string);
+                if failure.permission_denied() {
+                    format!(
+                        "{target}: requires a {}-level user ({})",
+                        failure.statement.required, failure.error
+                    )
+                } else {
+                    format!("{target}: {}", failure.error)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// `INFO FOR DB` の結果（使う部分だけ）
+#[derive(Debug, Default, Deserialize)]
+struct DbInfo {
+    #[serde(default)]
+    tables: HashMap<String, String>,
+}
+
+/// `INFO FOR TABLE` の結果（使う部分だけ）
+#[derive(Debug, Default, Deserialize)]
+struct TableInfo {
+    #[serde(default)]
+    fields: HashMap<String, String>,
+    #[serde(default)]
+    indexes: HashMap<String, String>,
+}
+
 /// スキーママネージャー
 ///
 /// データベーススキーマの初期化と管理を行います。
 /// スキーマファイル(.surql)はコンパイル時に埋め込まれ、
-/// IDEMPOTENTな方法で適用されます（OVERWRITE戦略を使用）。
+/// IDEMPOTENTな方法で適用されます（IF NOT EXISTSを使用）。
+///
+/// 名前空間・データベースの作成には広い権限が必要なため、管理者が `vantagemcp init-db` で
+/// 一度だけ [`bootstrap`](Self::bootstrap) を実行し、サーバーは起動時に
+/// [`ensure`](Self::ensure) で足りない定義だけを確かめます。作成済みであれば読み取りの
+/// 権限しか使いません。
 pub struct SchemaManager<'a> {
     db: &'a Surreal<Client>,
 }
@@ -25,86 +224,124 @@ impl<'a> SchemaManager<'a> {
     /// 全スキーマを適用
     ///
     /// ネームスペース/データベースの初期化、テーブル定義、インデックスを順番に適用します。
-    /// OVERWRITE戦略を使用しているため、何度実行しても安全です。
+    /// IF NOT EXISTSを使用しているため、何度実行しても安全です。
     ///
     /// # エラー
     ///
-    /// スキーマの適用に失敗した場合にエラーを返します。
+    /// いずれかの文の適用に失敗した場合にエラーを返します。
     pub async fn apply_all(&self) -> Result<()> {
-        info!("Applying all schemas");
-
-        self.apply_init().await?;
-        self.apply_tables().await?;
-        self.apply_indexes().await?;
-
-        info!("All schemas applied successfully");
+        let report = self.bootstrap().await;
+        if !report.is_ok() {
+            bail!("Failed to apply schema:\n{}", report.diagnostics());
+        }
         Ok(())
     }
 
-    /// 初期化スクリプトを適用
+    /// すべての文を1つずつ適用する（管理者の権限で一度だけ実行する）
     ///
-    /// 00_init/以下の全初期化スクリプトを適用します。
-    /// ネームスペースとデータベースの作成を行います。
-    async fn apply_init(&self) -> Result<()> {
-        debug!("Applying initialization scripts");
-
-        // ネームスペースとデータベース
-        let init_script = include_str!("../../schema/00_init/namespace_and_databases.surql");
-        self.execute_schema(init_script, "namespace and databases")
-            .await?;
-
-        Ok(())
+    /// 失敗した文があっても残りの文を適用し、失敗した文を結果に含めます。
+    pub async fn bootstrap(&self) -> SchemaReport {
+        info!("Applying all schemas");
+        let report = self.apply(&all_statements()).await;
+        if report.is_ok() {
+            info!("All schemas applied successfully");
+        }
+        report
     }
 
-    /// テーブルスキーマを適用
+    /// 足りない定義を確かめ、あればそれだけを適用する（サーバーの起動時に使う）
     ///
-    /// 01_tables/以下の全テーブル定義を適用します。
-    async fn apply_tables(&self) -> Result<()> {
-        debug!("Applying table schemas");
-
-        // テンプレートテーブル
-        let template_schema = include_str!("../../schema/01_tables/template.surql");
-        self.execute_schema(template_schema, "template table")
-            .await?;
-
-        Ok(())
-    }
-
-    /// インデックスを適用
+    /// 名前空間・データベースは接続できた時点で存在するため作成しません。
     ///
-    /// 02_indexes/以下の全インデックス定義を適用します。
-    /// テーブルが存在している必要があるため、apply_tables()の後に実行されます。
-    async fn apply_indexes(&self) -> Result<()> {
-        debug!("Applying indexes");
-
-        // テンプレートインデックス
-        let template_indexes = include_str!("../../schema/02_indexes/template_indexes.surql");
-        self.execute_schema(template_indexes, "template indexes")
-            .await?;
-
-        Ok(())
+    /// # エラー
+    ///
+    /// 定義を読み取れない場合にエラーを返します。
+    pub async fn ensure(&self) -> Result<SchemaReport> {
+        let missing = self.check().await?;
+        if missing.is_empty() {
+            debug!("Database schema is up to date");
+            return Ok(SchemaReport::default());
+        }
+        info!("Applying {} missing schema definition(s)", missing.len());
+        Ok(self.apply(&missing).await)
     }
 
-    /// スキーマSQLを実行
+    /// 存在しないテーブル・フィールド・インデックスの定義（適用する順）
     ///
-    /// # 引数
-    ///
-    /// * `sql` - 実行するSurrealQLスキーマ定義
-    /// * `description` - ログ出力用の説明文
+    /// `INFO FOR DB` と `INFO FOR TABLE` だけを使うため、読み取りの権限で実行できます。
     ///
     /// # エラー
     ///
-    /// クエリ実行に失敗した場合にエラーを返します。
-    async fn execute_schema(&self, sql: &str, description: &str) -> Result<()> {
-        debug!("Executing schema: {}", description);
-
-        self.db
-            .query(sql)
+    /// 定義を読み取れない場合にエラーを返します。
+    pub async fn check(&self) -> Result<Vec<SchemaStatement>> {
+        let db_info: DbInfo = self
+            .info("INFO FOR DB")
             .await
-            .with_context(|| format!("Failed to execute schema: {}", description))?;
+            .context("Failed to read the database definitions")?;
+        let mut tables: HashMap<String, TableInfo> = HashMap::new();
+        let mut missing = Vec::new();
+        for statement in all_statements() {
+            let exists = match &statement.definition {
+                Some(Definition::Table(name)) => db_info.tables.contains_key(name),
+                Some(Definition::Field { table, name } | Definition::Index { table, name }) => {
+                    if !db_info.tables.contains_key(table) {
+                        false
+                    } else {
+                        if !tables.contains_key(table) {
+                            let info = self
+                                .info(&format!("INFO FOR TABLE {table}"))
+                                .await
+                                .with_context(|| {
+                                    format!("Failed to read the definitions of table {table}")
+                                })?;
+                            tables.insert(table.clone(), info);
+                        }
+                        let info = &tables[table];
+                        match &statement.definition {
+                            Some(Definition::Field { .. }) => info.fields.contains_key(name),
+                            _ => info.indexes.contains_key(name),
+                        }
+                    }
+                }
+                // 名前空間・データベースと `USE` は確かめない
+                _ => true,
+            };
+            if !exists {
+                missing.push(statement);
+            }
+        }
+        Ok(missing)
+    }
 
-        debug!("Successfully executed: {}", description);
-        Ok(())
+    /// 文を1つずつ実行する（`USE` は続く文の前に付けて実行する）
+    async fn apply(&self, statements: &[SchemaStatement]) -> SchemaReport {
+        let mut report = SchemaReport::default();
+        let mut prefix = String::new();
+        for statement in statements {
+            if statement.is_use() {
+                prefix = format!("{}; ", statement.sql);
+                continue;
+            }
+            debug!("Executing schema: {}", statement.sql);
+            let result = match self.db.query(format!("{prefix}{};", statement.sql)).await {
+                Ok(response) => response.check().map(|_| ()),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => report.applied += 1,
+                Err(e) => report.failures.push(StatementFailure {
+                    statement: statement.clone(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+        report
+    }
+
+    async fn info<T: serde::de::DeserializeOwned + Default>(&self, sql: &str) -> Result<T> {
+        let mut response = self.db.query(sql).await?.check()?;
+        let info: Option<T> = response.take(0)?;
+        Ok(info.unwrap_or_default())
     }
 
     /// スキーマバージョンを確認（将来の拡張用）
@@ -143,6 +380,44 @@ mod tests {
     use super::*;
     use crate::db::DbConnection;
 
+    #[test]
+    fn test_statements_record_the_required_auth_level() {
+        let statements = all_statements();
+        let init: Vec<(&str, AuthLevel)> = statements
+            .iter()
+            .take(4)
+            .map(|s| (s.sql.as_str(), s.required))
+            .collect();
+        assert_eq!(
+            init,
+            vec![
+                ("DEFINE NAMESPACE IF NOT EXISTS vantage", AuthLevel::Root),
+                ("USE NAMESPACE vantage", AuthLevel::Database),
+                ("DEFINE DATABASE IF NOT EXISTS main", AuthLevel::Namespace),
+                ("DEFINE DATABASE IF NOT EXISTS test", AuthLevel::Namespace),
+            ]
+        );
+
+        let field = statements
+            .iter()
+            .find(|s| s.sql.starts_with("DEFINE FIELD IF NOT EXISTS category"))
+            .unwrap();
+        assert_eq!(field.required, AuthLevel::Database);
+        assert_eq!(
+            field.definition,
+            Some(Definition::Field {
+                table: "template".to_string(),
+                name: "category".to_string(),
+            })
+        );
+        assert!(field.sql.ends_with("DEFAULT \"other\""));
+        assert!(statements.iter().any(|s| s.definition
+            == Some(Definition::Index {
+                table: "template".to_string(),
+                name: "template_name_idx".to_string(),
+            })));
+    }
+
     #[tokio::test]
     #[ignore] // SurrealDBサーバーが起動している必要がある
     async fn test_apply_schema() {
@@ -150,5 +425,6 @@ mod tests {
         let schema_manager = SchemaManager::new(conn.db());
 
         schema_manager.apply_all().await.unwrap();
+        assert!(schema_manager.check().await.unwrap().is_empty());
     }
 }
@@ -39,22 +39,17 @@ use surrealdb::engine::remote::ws::Client;
 use tracing::{debug, info};
 
 /// テンプレートカテゴリ
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum TemplateCategory {
     Database,
     WebServer,
     BuildTool,
     Script,
+    #[default]
     Other,
 }
 
-impl Default for TemplateCategory {
-    fn default() -> Self {
-        Self::Other
-    }
-}
-
 /// プロセステンプレート
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Template {
@@ -243,6 +238,21 @@ impl<'a> TemplateRepository<'a> {
         updated.context("Template update returned None")
     }
 
+    /// 指定したIDでテンプレートを書き戻す（なければ作成）
+    pub async fn restore(&self, id: &str, mut template: Template) -> Result<Template> {
+        info!("Restoring template: {}", id);
+        template.id = None;
+
+        let restored: Option<Template> = self
+            .db
+            .upsert(("template", id))
+            .content(template)
+            .await
+            .context("Failed to restore template")?;
+
+        restored.context("Template restore returned None")
+    }
+
     /// テンプレートを削除
     pub async fn delete(&self, id: &str) -> Result<()> {
         info!("Deleting template: {}", id);
@@ -312,8 +322,8 @@ mod tests {
         assert!(fetched.is_some());
 
         // Get by ID (RecordId経由)
-        let id = created.id.as_ref().unwrap();
-        let id_str = id.to_string().split(':').nth(1).unwrap();
+        let id = created.id.as_ref().unwrap().to_string();
+        let id_str = id.split(':').nth(1).unwrap();
         let fetched_by_id = repo.get(id_str).await.unwrap();
         assert!(fetched_by_id.is_some());
 
@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod tests {
     use super::super::template_repository::{Template, TemplateCategory, TemplateRepository};
-    use crate::db::{DbConfig, DbConnection, SchemaManager};
+    use crate::db::connection::DbConfig;
+    use crate::db::{DbConnection, SchemaManager};
 
     /// テスト用のDB接続を作成
     async fn setup_test_db() -> DbConnection {
@@ -11,6 +12,7 @@ mod tests {
             database: "test".to_string(),
             username: "vtg-local".to_string(),
             password: "vtg-local".to_string(),
+            auth_level: Default::default(),
         };
 
         let conn = DbConnection::new(config)
@@ -27,6 +29,12 @@ mod tests {
         conn
     }
 
+    /// `template:<key>` の `<key>` 部分
+    fn record_key(template: &Template) -> String {
+        let id = template.id.as_ref().unwrap().to_string();
+        id.split(':').nth(1).unwrap().to_string()
+    }
+
     /// テスト用のテンプレートを作成
     fn create_test_template(name: &str) -> Template {
         let mut template = Template::new(name.to_string(), "echo".to_string());
@@ -60,7 +68,7 @@ mod tests {
         // テンプレートを作成
         let template = create_test_template("test_get");
         let created = repo.create(template).await.unwrap();
-        let id = created.id.as_ref().unwrap().id.to_string();
+        let id = record_key(&created);
 
         // IDで取得
         let retrieved = repo.get(&id).await.unwrap();
@@ -93,7 +101,7 @@ mod tests {
         // テンプレートを作成
         let template = create_test_template("test_update");
         let created = repo.create(template).await.unwrap();
-        let id = created.id.as_ref().unwrap().id.to_string();
+        let id = record_key(&created);
 
         // 更新
         let mut updated_template = created.clone();
@@ -114,7 +122,7 @@ mod tests {
         // テンプレートを作成
         let template = create_test_template("test_delete");
         let created = repo.create(template).await.unwrap();
-        let id = created.id.as_ref().unwrap().id.to_string();
+        let id = record_key(&created);
 
         // 削除
         repo.delete(&id).await.unwrap();
@@ -142,7 +150,7 @@ mod tests {
             .unwrap();
 
         // 全件取得
-        let templates = repo.list_all().await.unwrap();
+        let templates = repo.list().await.unwrap();
         assert!(templates.len() >= 3);
     }
 
@@ -171,16 +179,16 @@ mod tests {
 
         // カテゴリ別テンプレートを作成
         let mut dev_template = create_test_template("dev_test");
-        dev_template.category = Some(TemplateCategory::Development);
+        dev_template.category = TemplateCategory::WebServer;
         repo.create(dev_template).await.unwrap();
 
         let mut monitor_template = create_test_template("monitor_test");
-        monitor_template.category = Some(TemplateCategory::Monitoring);
+        monitor_template.category = TemplateCategory::Script;
         repo.create(monitor_template).await.unwrap();
 
         // カテゴリで検索
         let dev_results = repo
-            .list_by_category(&TemplateCategory::Development)
+            .list_by_category(TemplateCategory::WebServer)
             .await
             .unwrap();
         assert!(!dev_results.is_empty());
@@ -13,6 +13,8 @@
 //! - `persistence`: インメモリストレージとYAMLエクスポート/インポート
 //! - `db`: SurrealDBベースのデータベース層（接続、スキーマ、リポジトリ）
 //! - `types`: 共通の型定義
+//! - `paths`: 設定・データ・状態のファイルの置き場所
+//! - `template_schema`: テンプレート変数のJSON Schemaと入力の検証
 //!
 //! # 使用例
 //!
@@ -22,7 +24,7 @@
 //! #[tokio::main]
 //! async fn main() -> anyhow::Result<()> {
 //!     // インメモリストレージの初期化
-//!     let persistence = PersistenceManager::new().await?;
+//!     let persistence = PersistenceManager::new().await.map_err(anyhow::Error::msg)?;
 //!
 //!     // データベース接続
 //!     let db = DbConnection::new_from_env().await?;
@@ -32,18 +34,41 @@
 //! ```
 
 pub mod db;
+pub mod paths;
 pub mod persistence;
+pub mod template_schema;
 pub mod types;
 
 // Re-export main types
-pub use persistence::manager::PersistenceManager;
+pub use persistence::manager::{PersistenceManager, SnapshotFallback};
 
 // Re-export types for convenience
 pub use types::{
-    ClipboardItem, ProcessInfo, ProcessState, ProcessStatus, ProcessTemplate, Settings,
-    TemplateVariable, generate_id,
+    Access, ApprovalRequest, ApprovalSettings, ApprovalStatus, AuthConfig, AuthSession, Capability,
+    ChainAction, ChangeAction, ChangeHistory, ChangeTarget, ChaosSettings, ClipboardItem,
+    ConfigFormat, ContainerConfig, ContainerEngine, DefinitionChange, DependencyReady,
+    DiagnosisRule, DiagnosisRuleSource, DiagnosisRuleStatus, EnergyCostSettings, EnvLoader,
+    GateConfig, HealthAction, HealthCheckConfig, HealthProbe, IdleAction, IdleDetectionConfig,
+    ImageBuildConfig, ImportSource, KubernetesPortForward, KubernetesWorkload,
+    KubernetesWorkloadKind, LazyStartConfig, LogQuotaSettings, LogSinkConfig, LogSinkKind,
+    LogSource, MaintenanceSchedule, MaintenanceWindow, MdnsSettings, Notification,
+    NotificationChannel, NotificationKind, NotificationRoute, NotificationSeverity,
+    OidcProviderConfig, OutputMemorySettings, OutputRecordingConfig, PayloadField, Permissions,
+    PreOpSnapshot, ProcessAssertion, ProcessChain, ProcessDependency, ProcessInfo,
+    ProcessParameter, ProcessSchedule, ProcessState, ProcessStatus, ProcessTemplate,
+    RecycledProcess, RedactionOverride, RedactionRule, ReplConfig, ReplLanguage, ResourceLimits,
+    ResponseBudget, RestartMode, RestartPolicy, Role, RunAsConfig, Runbook, SessionContext,
+    Settings, StartupOrder, SuggestionProviderSettings, TemplateVariable, TmuxConfig, TunnelConfig,
+    TunnelProvider, WebhookAction, WebhookConfig, WebhookRateLimit, WorkspaceQuota, generate_id,
 };
 
+pub use template_schema::{SchemaError, SchemaType, VariableSchema};
+
 // Re-export DB types
+#[cfg(feature = "live-query")]
+pub use db::LiveTemplatesGuard;
 pub use db::template_repository::{Template, TemplateCategory};
-pub use db::{DbConnection, SchemaManager, TemplateRepository};
+pub use db::{
+    AuthLevel, DbConnection, SchemaManager, SchemaReport, TemplateChange, TemplateChangeAction,
+    TemplateRepository,
+};
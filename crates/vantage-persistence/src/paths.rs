@@ -0,0 +1,347 @@
+//! データファイルの置き場所
+//!
+//! 設定・データ・状態のファイルをどこに置くかを一か所で決めます。優先順位は次のとおりです。
+//!
+//! 1. `--data-dir` フラグ
+//! 2. 環境変数 `VANTAGE_DATA_DIR`
+//! 3. カレントディレクトリ（ワークスペース）の `.vantage` ディレクトリ（存在する場合）
+//! 4. ユーザー単位のXDGベースディレクトリ
+//!    - 設定（`auth.yaml`）: `$XDG_CONFIG_HOME/vantage`（既定 `~/.config/vantage`）
+//!    - データ（スナップショット・セッションコンテキスト・自動退避・TLS）:
+//!      `$XDG_DATA_HOME/vantage`（既定 `~/.local/share/vantage`）
+//!    - 状態（ログ・デバッグバンドル・出力の記録）: `$XDG_STATE_HOME/vantage`（既定 `~/.local/state/vantage`）
+//!
+//! 1〜3では、設定・データ・状態をすべてそのディレクトリにまとめます（従来の `~/.vantage` と同じ構成）。
+//! ユーザー単位のディレクトリを使うときは、以前の `~/.vantage`・`~/.ichimi` の中身を
+//! [`migrate_legacy`] で新しい場所に移します。
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+/// データディレクトリを指定する環境変数
+pub const DATA_DIR_ENV: &str = "VANTAGE_DATA_DIR";
+/// ワークスペースのデータディレクトリ名
+pub const WORKSPACE_DIR: &str = ".vantage";
+/// XDGのディレクトリの下のアプリケーション名
+const APP_DIR: &str = "vantage";
+/// 以前のバージョンが使っていたホームディレクトリ直下のディレクトリ
+const LEGACY_DIRS: &[&str] = &[".vantage", ".ichimi"];
+
+static PATHS: OnceLock<DataPaths> = OnceLock::new();
+
+/// 置き場所を決めた根拠
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathSource {
+    /// `--data-dir` フラグ
+    Flag,
+    /// 環境変数 `VANTAGE_DATA_DIR`
+    Env,
+    /// ワークスペースの `.vantage`
+    Workspace,
+    /// ユーザー単位のXDGベースディレクトリ
+    User,
+}
+
+/// 設定・データ・状態のディレクトリ
+#[derive(Debug, Clone, Serialize)]
+pub struct DataPaths {
+    pub source: PathSource,
+    /// 認証・アクセス権などの設定
+    pub config_dir: PathBuf,
+    /// スナップショット・セッションコンテキスト・自動退避・TLS
+    pub data_dir: PathBuf,
+    /// ログ・デバッグバンドル
+    pub state_dir: PathBuf,
+}
+
+impl DataPaths {
+    /// 優先順位に従って置き場所を決める
+    pub fn resolve(flag: Option<&Path>) -> Self {
+        if let Some(dir) = flag {
+            return Self::single(absolute(dir), PathSource::Flag);
+        }
+        if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|v| !v.is_empty()) {
+            return Self::single(absolute(Path::new(&dir)), PathSource::Env);
+        }
+        if let Ok(current) = std::env::current_dir() {
+            let workspace = current.join(WORKSPACE_DIR);
+            if workspace.is_dir()
+                && Some(&workspace) != home_dir().map(|h| h.join(WORKSPACE_DIR)).as_ref()
+            {
+                return Self::single(workspace, PathSource::Workspace);
+            }
+        }
+        Self::user()
+    }
+
+    /// すべてを1つのディレクトリにまとめる
+    fn single(root: PathBuf, source: PathSource) -> Self {
+        Self {
+            source,
+            config_dir: root.clone(),
+            data_dir: root.clone(),
+            state_dir: root,
+        }
+    }
+
+    /// ユーザー単位のXDGベースディレクトリ
+    pub fn user() -> Self {
+        let home = home_dir().unwrap_or_else(|| PathBuf::from("."));
+        let xdg = |var: &str, default: &str| {
+            std::env::var_os(var)
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+                .unwrap_or_else(|| home.join(default))
+                .join(APP_DIR)
+        };
+        if cfg!(windows) {
+            let local = std::env::var_os("LOCALAPPDATA")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join("AppData").join("Local"))
+                .join(APP_DIR);
+            let roaming = std::env::var_os("APPDATA")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join("AppData").join("Roaming"))
+                .join(APP_DIR);
+            return Self {
+                source: PathSource::User,
+                config_dir: roaming,
+                data_dir: local.join("data"),
+                state_dir: local.join("state"),
+            };
+        }
+        Self {
+            source: PathSource::User,
+            config_dir: xdg("XDG_CONFIG_HOME", ".config"),
+            data_dir: xdg("XDG_DATA_HOME", ".local/share"),
+            state_dir: xdg("XDG_STATE_HOME", ".local/state"),
+        }
+    }
+
+    /// 自動起動するプロセスのスナップショット
+    pub fn snapshot_file(&self) -> PathBuf {
+        self.data_dir.join("snapshot.yaml")
+    }
+
+    /// エクスポートの既定のディレクトリ
+    pub fn export_dir(&self) -> PathBuf {
+        self.data_dir.clone()
+    }
+
+    /// Webコンソールの認証とMCPクライアントのアクセス権
+    pub fn auth_file(&self) -> PathBuf {
+        self.config_dir.join("auth.yaml")
+    }
+
+    /// 機密の環境変数を暗号化する鍵（OSのキーチェーンを使えない場合）
+    pub fn env_key_file(&self) -> PathBuf {
+        self.config_dir.join("env.key")
+    }
+
+    pub fn contexts_dir(&self) -> PathBuf {
+        self.data_dir.join("contexts")
+    }
+
+    /// 危険な操作の前に自動で取るスナップショット
+    pub fn pre_op_dir(&self) -> PathBuf {
+        self.data_dir.join("pre-op")
+    }
+
+    /// ローカルCAと証明書
+    pub fn tls_dir(&self) -> PathBuf {
+        self.data_dir.join("tls")
+    }
+
+    /// プロセスとサーバーのログ
+    pub fn log_dir(&self) -> PathBuf {
+        self.state_dir.join("logs")
+    }
+
+    pub fn debug_bundle_dir(&self) -> PathBuf {
+        self.state_dir.join("debug-bundles")
+    }
+
+    /// サーバーの再起動で実行中のプロセスを引き継ぐファイル
+    pub fn restart_handoff_file(&self) -> PathBuf {
+        self.state_dir.join("restart-handoff.json")
+    }
+
+    /// 実行履歴を書き出す既定のディレクトリ
+    pub fn history_export_dir(&self) -> PathBuf {
+        self.state_dir.join("history-exports")
+    }
+
+    /// プロセスの出力の記録（`replay_output` で再生する）
+    pub fn recordings_dir(&self) -> PathBuf {
+        self.state_dir.join("recordings")
+    }
+
+    /// 表示用の一覧（名前と場所）
+    pub fn locations(&self) -> Vec<(&'static str, PathBuf)> {
+        vec![
+            ("config_dir", self.config_dir.clone()),
+            ("data_dir", self.data_dir.clone()),
+            ("state_dir", self.state_dir.clone()),
+            ("auth_file", self.auth_file()),
+            ("env_key_file", self.env_key_file()),
+            ("snapshot_file", self.snapshot_file()),
+            ("contexts_dir", self.contexts_dir()),
+            ("pre_op_dir", self.pre_op_dir()),
+            ("tls_dir", self.tls_dir()),
+            ("log_dir", self.log_dir()),
+            ("debug_bundle_dir", self.debug_bundle_dir()),
+            ("history_export_dir", self.history_export_dir()),
+            ("recordings_dir", self.recordings_dir()),
+        ]
+    }
+}
+
+/// 起動時に置き場所を決める（`--data-dir` を反映する）
+///
+/// すでに決まっている場合はその置き場所を返す。
+pub fn init(flag: Option<&Path>) -> &'static DataPaths {
+    PATHS.get_or_init(|| DataPaths::resolve(flag))
+}
+
+/// 現在の置き場所（未初期化ならフラグなしで決める）
+pub fn get() -> &'static DataPaths {
+    init(None)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    std::env::current_dir()
+        .map(|current| current.join(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// 移行したファイル・ディレクトリ
+#[derive(Debug, Clone, Serialize)]
+pub struct MigratedEntry {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// 以前の `~/.vantage`・`~/.ichimi` の中身をユーザー単位のディレクトリに移す
+///
+/// 移動先にすでにあるものは移さず、元の場所に残す。空になった以前のディレクトリは削除する。
+/// ユーザー単位のディレクトリを使わない場合は何もしない。
+pub fn migrate_legacy(paths: &DataPaths) -> Vec<MigratedEntry> {
+    if paths.source != PathSource::User {
+        return Vec::new();
+    }
+    let Some(home) = home_dir() else {
+        return Vec::new();
+    };
+    let mut migrated = Vec::new();
+    for legacy in LEGACY_DIRS {
+        let legacy = home.join(legacy);
+        if legacy.is_dir() {
+            migrated.extend(migrate_dir(&legacy, paths));
+        }
+    }
+    migrated
+}
+
+fn migrate_dir(legacy: &Path, paths: &DataPaths) -> Vec<MigratedEntry> {
+    let entries = match std::fs::read_dir(legacy) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read {}: {}", legacy.display(), e);
+            return Vec::new();
+        }
+    };
+    let mut migrated = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let target_dir = match name.to_str() {
+            Some("auth.yaml") => &paths.config_dir,
+            Some("logs") | Some("debug-bundles") => &paths.state_dir,
+            // Windowsのサービス定義は登録したタスクが参照するので動かさない
+            Some("service-task.xml") => continue,
+            _ => &paths.data_dir,
+        };
+        let from = entry.path();
+        let to = target_dir.join(&name);
+        if to.exists() {
+            warn!(
+                "Not migrating {}: {} already exists",
+                from.display(),
+                to.display()
+            );
+            continue;
+        }
+        let moved = std::fs::create_dir_all(target_dir).and_then(|_| std::fs::rename(&from, &to));
+        match moved {
+            Ok(()) => {
+                info!("Migrated {} to {}", from.display(), to.display());
+                migrated.push(MigratedEntry { from, to });
+            }
+            Err(e) => warn!(
+                "Failed to migrate {} to {}: {}",
+                from.display(),
+                to.display(),
+                e
+            ),
+        }
+    }
+    // 空になった場合だけ消える
+    if std::fs::remove_dir(legacy).is_ok() {
+        info!("Removed the empty legacy directory {}", legacy.display());
+    }
+    migrated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_root_and_legacy_migration() {
+        let root = std::env::temp_dir().join(format!("vantage-paths-{}", nanoid::nanoid!()));
+        let paths = DataPaths::single(root.clone(), PathSource::Flag);
+        assert_eq!(paths.auth_file(), root.join("auth.yaml"));
+        assert_eq!(paths.log_dir(), root.join("logs"));
+        assert!(migrate_legacy(&paths).is_empty());
+
+        // 以前の構成を設定・データ・状態に振り分ける
+        let legacy = root.join("legacy");
+        std::fs::create_dir_all(legacy.join("logs")).unwrap();
+        std::fs::create_dir_all(legacy.join("contexts")).unwrap();
+        std::fs::write(legacy.join("auth.yaml"), "users: []").unwrap();
+        std::fs::write(legacy.join("snapshot.yaml"), "processes: {}").unwrap();
+        let user = DataPaths {
+            source: PathSource::User,
+            config_dir: root.join("config"),
+            data_dir: root.join("data"),
+            state_dir: root.join("state"),
+        };
+        std::fs::create_dir_all(&user.data_dir).unwrap();
+        std::fs::write(user.snapshot_file(), "kept").unwrap();
+
+        let migrated = migrate_dir(&legacy, &user);
+        assert_eq!(migrated.len(), 3, "{migrated:?}");
+        assert!(user.auth_file().exists());
+        assert!(user.log_dir().is_dir());
+        assert!(user.contexts_dir().is_dir());
+        // 移動先にあるものは上書きしない
+        assert_eq!(
+            std::fs::read_to_string(user.snapshot_file()).unwrap(),
+            "kept"
+        );
+        assert!(legacy.join("snapshot.yaml").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
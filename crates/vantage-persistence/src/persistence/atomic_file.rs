@@ -0,0 +1,151 @@
+//! スナップショットのクラッシュに強い書き込み
+//!
+//! 同じディレクトリの一時ファイルに書いてfsyncし、renameで置き換えるため、書き込みの途中で
+//! 止まっても前の内容か新しい内容のどちらかが残ります。置き換える前の世代は `<name>.bak` に
+//! 残し、読み込めないスナップショットはそこから復元します。
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+type Result<T> = std::result::Result<T, String>;
+
+/// 前の世代を残すファイル（`snapshot.yaml` なら `snapshot.yaml.bak`）
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling(path, "", ".bak")
+}
+
+/// 書き込み中の一時ファイル（`snapshot.yaml` なら `.snapshot.yaml.<pid>.<id>.tmp`）
+///
+/// 同じファイルへ同時に書き込んでも互いの一時ファイルを上書きしないよう、書き込みごとに
+/// 別の名前にする。
+fn temp_path(path: &Path) -> PathBuf {
+    let unique = format!(".{}.{}.tmp", std::process::id(), nanoid::nanoid!(8));
+    sibling(path, ".", &unique)
+}
+
+fn sibling(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{prefix}{name}{suffix}"))
+}
+
+/// `contents` で `path` を置き換える
+///
+/// `keep_backup` なら置き換える前のファイルを `<name>.bak` に残す（読み込めるものだけを
+/// 残すよう、呼び出し側で確かめてから指定する）。
+pub async fn write(path: &Path, contents: String, keep_backup: bool) -> Result<()> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || write_sync(&path, contents.as_bytes(), keep_backup))
+        .await
+        .map_err(|e| format!("Snapshot write task failed: {e}"))?
+}
+
+fn write_sync(path: &Path, contents: &[u8], keep_backup: bool) -> Result<()> {
+    let temp = temp_path(path);
+    let result = replace(path, &temp, contents, keep_backup);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+fn replace(path: &Path, temp: &Path, contents: &[u8], keep_backup: bool) -> Result<()> {
+    let mut file =
+        File::create(temp).map_err(|e| format!("Failed to create {}: {e}", temp.display()))?;
+    file.write_all(contents)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {e}", temp.display()))?;
+    drop(file);
+
+    if keep_backup && path.exists() {
+        let backup = backup_path(path);
+        let _ = fs::remove_file(&backup);
+        // renameの後も前の世代を参照できるようハードリンクを張る（できなければコピー）
+        fs::hard_link(path, &backup)
+            .or_else(|_| fs::copy(path, &backup).map(|_| ()))
+            .map_err(|e| format!("Failed to back up {}: {e}", path.display()))?;
+    }
+
+    fs::rename(temp, path).map_err(|e| format!("Failed to replace {}: {e}", path.display()))?;
+    sync_dir(path);
+    Ok(())
+}
+
+/// renameをディスクに反映する（ディレクトリをfsyncできないプラットフォームでは何もしない）
+fn sync_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        let _ = File::open(parent).and_then(|dir| dir.sync_all());
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_replaces_and_keeps_previous_generation() {
+        let dir = std::env::temp_dir().join(format!("vantage-atomic-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("snapshot.yaml");
+
+        write(&path, "first".to_string(), true).await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        assert!(!backup_path(&path).exists());
+
+        write(&path, "second".to_string(), true).await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "first");
+
+        // 残さない指定なら前の世代はそのまま
+        write(&path, "third".to_string(), false).await.unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "first");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    async fn write_owned(path: PathBuf, contents: String) -> Result<()> {
+        write(&path, contents, true).await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_writes_do_not_share_a_temp_file() {
+        let dir = std::env::temp_dir().join(format!("vantage-atomic-{}", nanoid::nanoid!()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("snapshot.yaml");
+
+        let contents: Vec<String> = (0..16)
+            .map(|i| format!("generation {i}\n").repeat(1000))
+            .collect();
+        let writes: Vec<_> = contents
+            .iter()
+            .map(|contents| tokio::spawn(write_owned(path.clone(), contents.clone())))
+            .collect();
+        for handle in writes {
+            handle.await.unwrap().unwrap();
+        }
+
+        // 途中で混ざらず、どれか1つの書き込みがそのまま残る
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(&written));
+        let names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert!(
+            names.iter().all(|name| !name.ends_with(".tmp")),
+            "{names:?}"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
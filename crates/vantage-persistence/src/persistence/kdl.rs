@@ -0,0 +1,1074 @@
+//! KDL形式の読み書き
+//!
+//! YAMLと同じserdeのモデルを、差分の読みやすいKDL文書として書き出し・読み込みします。
+//! 値とノードは次のように対応します。
+//!
+//! - 構造体・マップ: フィールドごとの子ノードを名前順に並べる（`command "python"`）。空のものは `env {}`
+//! - スカラーの配列: 引数を並べたノード（`args "-m" "http.server"`）。空の配列は引数のないノード
+//! - 構造体を含む配列: `-` ノードを並べた子ブロック
+//! - `None`: `#null`
+//!
+//! 一覧は `process "<ID>" { ... }` のようにIDを引数に持つノードを並べた文書になります。
+//! 読み込みはKDL v2の構文（v1の `true` / `false` / `null`、`r#"..."#` も可）に対応し、
+//! `meta` ノードは無視します。
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::{Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+const INDENT: &str = "    ";
+
+/// 一覧をKDL文書に変換する（`id_field` の値をノードの引数にする）
+pub fn to_string<T: Serialize>(
+    node_name: &str,
+    id_field: &str,
+    items: &[T],
+) -> Result<String, String> {
+    let mut out = String::from("// Vantage MCP Process Configuration\n");
+    for item in items {
+        let value =
+            serde_json::to_value(item).map_err(|e| format!("Failed to serialize to KDL: {e}"))?;
+        let Value::Object(mut fields) = value else {
+            return Err("Failed to serialize to KDL: expected a struct".to_string());
+        };
+        let id = match fields.remove(id_field) {
+            Some(Value::String(id)) => id,
+            _ => {
+                return Err(format!(
+                    "Failed to serialize to KDL: missing string field '{id_field}'"
+                ));
+            }
+        };
+        out.push('\n');
+        write_name(&mut out, node_name);
+        out.push(' ');
+        write_string(&mut out, &id);
+        write_block(&mut out, &fields, 0);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// KDL文書から一覧を読み込む
+pub fn from_str<T: DeserializeOwned>(
+    input: &str,
+    node_name: &str,
+    id_field: &str,
+) -> Result<Vec<T>, String> {
+    let nodes = Parser::new(input)
+        .document()
+        .map_err(|e| format!("Invalid KDL: {e}"))?;
+    let mut items = Vec::new();
+    for node in nodes {
+        if node.name == "meta" {
+            continue;
+        }
+        if node.name != node_name {
+            return Err(format!(
+                "Invalid KDL: unexpected node '{}' (expected '{node_name}')",
+                node.name
+            ));
+        }
+        let id = match node.args.as_slice() {
+            [Scalar::String(id)] => id.clone(),
+            _ => {
+                return Err(format!(
+                    "Invalid KDL: '{node_name}' must have exactly one string argument (its ID)"
+                ));
+            }
+        };
+        let mut children = vec![Node {
+            name: id_field.to_string(),
+            args: vec![Scalar::String(id.clone())],
+            children: None,
+        }];
+        children.extend(node.children.unwrap_or_default());
+        let fields = Node {
+            name: node.name,
+            args: Vec::new(),
+            children: Some(children),
+        };
+        items.push(
+            T::deserialize(NodeDeserializer(&fields))
+                .map_err(|e| format!("Invalid KDL in {node_name} '{id}': {e}"))?,
+        );
+    }
+    Ok(items)
+}
+
+// ---- 書き出し ----
+
+/// フィールドは名前順に書き出す（`HashMap` の順序は書き出すたびに変わるため）
+fn write_block(out: &mut String, fields: &Map<String, Value>, depth: usize) {
+    if fields.is_empty() {
+        out.push_str(" {}");
+        return;
+    }
+    out.push_str(" {\n");
+    let sorted: BTreeMap<&String, &Value> = fields.iter().collect();
+    for (key, value) in sorted {
+        write_node(out, key, value, depth + 1);
+    }
+    out.push_str(&INDENT.repeat(depth));
+    out.push('}');
+}
+
+fn write_node(out: &mut String, name: &str, value: &Value, depth: usize) {
+    out.push_str(&INDENT.repeat(depth));
+    write_name(out, name);
+    match value {
+        Value::Array(items) if items.iter().all(is_scalar) => {
+            for item in items {
+                out.push(' ');
+                write_scalar(out, item);
+            }
+        }
+        Value::Array(items) => {
+            out.push_str(" {\n");
+            for item in items {
+                write_node(out, "-", item, depth + 1);
+            }
+            out.push_str(&INDENT.repeat(depth));
+            out.push('}');
+        }
+        Value::Object(fields) => write_block(out, fields, depth),
+        scalar => {
+            out.push(' ');
+            write_scalar(out, scalar);
+        }
+    }
+    out.push('\n');
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+fn write_scalar(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("#null"),
+        Value::Bool(true) => out.push_str("#true"),
+        Value::Bool(false) => out.push_str("#false"),
+        Value::Number(n) => out.push_str(&n.to_string()),
+        Value::String(s) => write_string(out, s),
+        Value::Array(_) | Value::Object(_) => unreachable!("not a scalar"),
+    }
+}
+
+/// ノード名（識別子として書けない場合は文字列にする）
+fn write_name(out: &mut String, name: &str) {
+    if is_bare_identifier(name) {
+        out.push_str(name);
+    } else {
+        write_string(out, name);
+    }
+}
+
+fn is_bare_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    let starts_like_number = first.is_ascii_digit()
+        || (matches!(first, '-' | '+' | '.')
+            && name[1..].starts_with(|c: char| c.is_ascii_digit()));
+    (first.is_ascii_alphabetic() || matches!(first, '_' | '-' | '.' | '+'))
+        && !starts_like_number
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+'))
+        && !matches!(name, "true" | "false" | "null" | "inf" | "nan")
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// ---- 読み込み ----
+
+#[derive(Debug, Clone, PartialEq)]
+enum Scalar {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    name: String,
+    args: Vec<Scalar>,
+    /// 子ブロック（`{}` が書かれていなければNone）
+    children: Option<Vec<Node>>,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input: input.strip_prefix('\u{feff}').unwrap_or(input),
+            pos: 0,
+        }
+    }
+
+    fn document(&mut self) -> Result<Vec<Node>, String> {
+        let nodes = self.nodes()?;
+        if self.peek().is_some() {
+            return Err(self.error("unexpected '}'"));
+        }
+        Ok(nodes)
+    }
+
+    /// 文書の終わりか `}` までのノード
+    fn nodes(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_line_space()?;
+            match self.peek() {
+                None | Some('}') => return Ok(nodes),
+                _ if self.rest().starts_with("/-") => {
+                    self.pos += 2;
+                    self.skip_node_space()?;
+                    self.node()?;
+                }
+                _ => nodes.push(self.node()?),
+            }
+        }
+    }
+
+    fn node(&mut self) -> Result<Node, String> {
+        self.skip_type_annotation()?;
+        let name = self.string_or_identifier()?;
+        let mut node = Node {
+            name,
+            args: Vec::new(),
+            children: None,
+        };
+        loop {
+            let had_space = self.skip_node_space()?;
+            match self.peek() {
+                None | Some('\n' | '\r' | ';' | '}') => return Ok(node),
+                Some('{') => {
+                    self.pos += 1;
+                    let children = self.nodes()?;
+                    if self.peek() != Some('}') {
+                        return Err(self.error("unclosed children block"));
+                    }
+                    self.pos += 1;
+                    node.children = Some(children);
+                    self.skip_node_space()?;
+                    return match self.peek() {
+                        None | Some('\n' | '\r' | ';' | '}') => Ok(node),
+                        _ => Err(self.error("unexpected value after children block")),
+                    };
+                }
+                _ if !had_space => return Err(self.error("expected whitespace")),
+                _ if self.rest().starts_with("/-") => {
+                    self.pos += 2;
+                    self.skip_node_space()?;
+                    if self.peek() == Some('{') {
+                        self.pos += 1;
+                        self.nodes()?;
+                        self.pos += 1;
+                    } else {
+                        self.value()?;
+                    }
+                }
+                _ => {
+                    let value = self.value()?;
+                    if self.peek() == Some('=') {
+                        return Err(self.error("properties (key=value) are not supported"));
+                    }
+                    node.args.push(value);
+                }
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Scalar, String> {
+        self.skip_type_annotation()?;
+        let rest = self.rest();
+        if rest.starts_with('"') {
+            return self.quoted_string().map(Scalar::String);
+        }
+        if rest.starts_with("#\"")
+            || rest.starts_with("##")
+            || rest.starts_with("r#")
+            || rest.starts_with("r\"")
+        {
+            return self.raw_string().map(Scalar::String);
+        }
+        if let Some(keyword) = rest.strip_prefix('#') {
+            let word: String = keyword
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+                .collect();
+            self.pos += 1 + word.len();
+            return match word.as_str() {
+                "true" => Ok(Scalar::Bool(true)),
+                "false" => Ok(Scalar::Bool(false)),
+                "null" => Ok(Scalar::Null),
+                "inf" => Ok(Scalar::Float(f64::INFINITY)),
+                "-inf" => Ok(Scalar::Float(f64::NEG_INFINITY)),
+                "nan" => Ok(Scalar::Float(f64::NAN)),
+                _ => Err(self.error(&format!("unknown keyword '#{word}'"))),
+            };
+        }
+        let word = self.bare_word();
+        if word.is_empty() {
+            return Err(self.error("expected a value"));
+        }
+        let is_number = word.starts_with(|c: char| c.is_ascii_digit())
+            || (word.len() > 1
+                && word.starts_with(['-', '+'])
+                && word[1..].starts_with(|c: char| c.is_ascii_digit()));
+        if is_number {
+            return parse_number(&word)
+                .ok_or_else(|| self.error(&format!("invalid number '{word}'")));
+        }
+        Ok(match word.as_str() {
+            "true" => Scalar::Bool(true),
+            "false" => Scalar::Bool(false),
+            "null" => Scalar::Null,
+            _ => Scalar::String(word),
+        })
+    }
+
+    fn string_or_identifier(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some('"') => self.quoted_string(),
+            Some('#' | 'r')
+                if self.rest().starts_with("#\"")
+                    || self.rest().starts_with("##")
+                    || self.rest().starts_with("r#")
+                    || self.rest().starts_with("r\"") =>
+            {
+                self.raw_string()
+            }
+            _ => {
+                let word = self.bare_word();
+                if word.is_empty() {
+                    Err(self.error("expected a node name"))
+                } else {
+                    Ok(word)
+                }
+            }
+        }
+    }
+
+    fn bare_word(&mut self) -> String {
+        let word: String = self
+            .rest()
+            .chars()
+            .take_while(|c| {
+                !c.is_whitespace()
+                    && !matches!(
+                        c,
+                        '(' | ')' | '{' | '}' | '[' | ']' | '/' | '\\' | '"' | '#' | ';' | '='
+                    )
+            })
+            .collect();
+        self.pos += word.len();
+        word
+    }
+
+    fn quoted_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut s = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(s);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('s') => s.push(' '),
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('u') => {
+                        let hex: String = chars
+                            .by_ref()
+                            .map(|(_, c)| c)
+                            .skip_while(|c| *c == '{')
+                            .take_while(|c| *c != '}')
+                            .collect();
+                        let c = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| {
+                                self.error(&format!("invalid unicode escape '{hex}'"))
+                            })?;
+                        s.push(c);
+                    }
+                    // 空白のエスケープ（改行を含む空白をまとめて読み飛ばす）
+                    Some(c) if c.is_whitespace() => {
+                        let mut lookahead = chars.clone();
+                        while let Some((_, c)) = lookahead.next() {
+                            if !c.is_whitespace() {
+                                break;
+                            }
+                            chars = lookahead.clone();
+                        }
+                    }
+                    _ => return Err(self.error("invalid escape in string")),
+                },
+                c => s.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn raw_string(&mut self) -> Result<String, String> {
+        if self.peek() == Some('r') {
+            self.pos += 1;
+        }
+        let hashes = self.rest().chars().take_while(|c| *c == '#').count();
+        self.pos += hashes;
+        if self.peek() != Some('"') {
+            return Err(self.error("invalid raw string"));
+        }
+        self.pos += 1;
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let end = self
+            .rest()
+            .find(&terminator)
+            .ok_or_else(|| self.error("unterminated raw string"))?;
+        let s = self.rest()[..end].to_string();
+        self.pos += end + terminator.len();
+        Ok(s)
+    }
+
+    /// 型注釈（`(type)`）は読み飛ばす
+    fn skip_type_annotation(&mut self) -> Result<(), String> {
+        if self.peek() == Some('(') {
+            let end = self
+                .rest()
+                .find(')')
+                .ok_or_else(|| self.error("unclosed type annotation"))?;
+            self.pos += end + 1;
+        }
+        Ok(())
+    }
+
+    /// ノードの間の空白・改行・`;`・コメント
+    fn skip_line_space(&mut self) -> Result<(), String> {
+        loop {
+            self.skip_node_space()?;
+            match self.peek() {
+                Some('\n' | '\r' | ';') => self.pos += 1,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// ノード内の空白・コメント・行の継続（改行は含まない）。読み飛ばしたかを返す
+    fn skip_node_space(&mut self) -> Result<bool, String> {
+        let start = self.pos;
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                self.skip_block_comment()?;
+            } else if let Some(after) = rest.strip_prefix('\\') {
+                // 行の継続
+                let line_end = after.find('\n').map_or(after.len(), |i| i + 1);
+                let between = &after[..line_end];
+                let between = between.split("//").next().unwrap_or(between);
+                if !between.trim().is_empty() {
+                    return Err(self.error("unexpected '\\'"));
+                }
+                self.pos += 1 + line_end;
+            } else if let Some(c) = self.peek()
+                && c.is_whitespace()
+                && !matches!(c, '\n' | '\r')
+            {
+                self.pos += c.len_utf8();
+            } else {
+                return Ok(self.pos > start);
+            }
+        }
+    }
+
+    fn skip_block_comment(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+        while !self.rest().is_empty() {
+            let rest = self.rest();
+            if rest.starts_with("/*") {
+                depth += 1;
+                self.pos += 2;
+            } else if rest.starts_with("*/") {
+                depth -= 1;
+                self.pos += 2;
+                if depth == 0 {
+                    return Ok(());
+                }
+            } else {
+                self.pos += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        Err(self.error("unterminated block comment"))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn error(&self, message: &str) -> String {
+        let consumed = &self.input[..self.pos];
+        let line = consumed.matches('\n').count() + 1;
+        let column = consumed
+            .rsplit('\n')
+            .next()
+            .map_or(0, |l| l.chars().count())
+            + 1;
+        format!("{message} at line {line}, column {column}")
+    }
+}
+
+fn parse_number(word: &str) -> Option<Scalar> {
+    let cleaned = word.replace('_', "");
+    let (negative, digits) = match cleaned.as_bytes().first() {
+        Some(b'-') => (true, &cleaned[1..]),
+        Some(b'+') => (false, &cleaned[1..]),
+        _ => (false, cleaned.as_str()),
+    };
+    let radix = match digits.get(..2) {
+        Some("0x") => Some(16),
+        Some("0o") => Some(8),
+        Some("0b") => Some(2),
+        _ => None,
+    };
+    if let Some(radix) = radix {
+        let value = i64::from_str_radix(&digits[2..], radix).ok()?;
+        return Some(Scalar::Int(if negative { -value } else { value }));
+    }
+    if digits.contains(['.', 'e', 'E']) {
+        return cleaned.parse().ok().map(Scalar::Float);
+    }
+    cleaned
+        .parse()
+        .map(Scalar::Int)
+        .or_else(|_| cleaned.parse().map(Scalar::UInt))
+        .ok()
+}
+
+// ---- serdeとの対応 ----
+
+/// 読み込み時のエラー
+#[derive(Debug)]
+struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+struct ScalarDeserializer<'a>(&'a Scalar);
+
+impl<'de> Deserializer<'de> for ScalarDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Scalar::Null => visitor.visit_unit(),
+            Scalar::Bool(b) => visitor.visit_bool(*b),
+            Scalar::Int(i) => visitor.visit_i64(*i),
+            Scalar::UInt(u) => visitor.visit_u64(*u),
+            Scalar::Float(f) => visitor.visit_f64(*f),
+            Scalar::String(s) => visitor.visit_str(s),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Scalar::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.0 {
+            Scalar::String(s) => visitor.visit_enum(s.as_str().into_deserializer()),
+            other => Err(Error(format!(
+                "expected an enum variant name, found {other:?}"
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct NodeDeserializer<'a>(&'a Node);
+
+impl NodeDeserializer<'_> {
+    fn single_arg(&self) -> Option<&Scalar> {
+        match (self.0.args.as_slice(), &self.0.children) {
+            ([arg], None) => Some(arg),
+            _ => None,
+        }
+    }
+
+    fn type_error(&self, expected: &str) -> Error {
+        Error(format!("expected {expected} for '{}'", self.0.name))
+    }
+}
+
+impl<'de> Deserializer<'de> for NodeDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match (&self.0.children, self.0.args.len()) {
+            (Some(children), _)
+                if !children.is_empty() && children.iter().all(|c| c.name == "-") =>
+            {
+                self.deserialize_seq(visitor)
+            }
+            (Some(_), _) => self.deserialize_map(visitor),
+            (None, 1) => ScalarDeserializer(&self.0.args[0]).deserialize_any(visitor),
+            (None, _) => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.single_arg() {
+            Some(Scalar::Null) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match (self.0.args.as_slice(), &self.0.children) {
+            ([] | [Scalar::Null], None) => visitor.visit_unit(),
+            _ => Err(self.type_error("#null")),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match &self.0.children {
+            Some(children) if self.0.args.is_empty() => visitor.visit_seq(NodeSeq(children.iter())),
+            Some(_) => Err(self.type_error("either arguments or a children block")),
+            None => visitor.visit_seq(ScalarSeq(self.0.args.iter())),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match &self.0.children {
+            Some(children) if self.0.args.is_empty() => visitor.visit_map(NodeMap {
+                nodes: children.iter(),
+                value: None,
+            }),
+            _ => Err(self.type_error("a children block")),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if let Some(arg) = self.single_arg() {
+            return ScalarDeserializer(arg).deserialize_enum(name, variants, visitor);
+        }
+        match (self.0.args.as_slice(), &self.0.children) {
+            ([], Some(children)) if children.len() == 1 => {
+                visitor.visit_enum(NodeEnum(&children[0]))
+            }
+            _ => Err(self.type_error("an enum variant")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit_struct identifier ignored_any
+    }
+}
+
+struct ScalarSeq<'a>(std::slice::Iter<'a, Scalar>);
+
+impl<'de> SeqAccess<'de> for ScalarSeq<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.0
+            .next()
+            .map(|scalar| seed.deserialize(ScalarDeserializer(scalar)))
+            .transpose()
+    }
+}
+
+struct NodeSeq<'a>(std::slice::Iter<'a, Node>);
+
+impl<'de> SeqAccess<'de> for NodeSeq<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.0
+            .next()
+            .map(|node| seed.deserialize(NodeDeserializer(node)))
+            .transpose()
+    }
+}
+
+struct NodeMap<'a> {
+    nodes: std::slice::Iter<'a, Node>,
+    value: Option<&'a Node>,
+}
+
+impl<'de> MapAccess<'de> for NodeMap<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some(node) = self.nodes.next() else {
+            return Ok(None);
+        };
+        self.value = Some(node);
+        seed.deserialize(node.name.as_str().into_deserializer())
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let node = self
+            .value
+            .take()
+            .ok_or_else(|| Error("value requested before key".to_string()))?;
+        seed.deserialize(NodeDeserializer(node))
+    }
+}
+
+struct NodeEnum<'a>(&'a Node);
+
+impl<'de, 'a> EnumAccess<'de> for NodeEnum<'a> {
+    type Error = Error;
+    type Variant = NodeDeserializer<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(self.0.name.as_str().into_deserializer())?;
+        Ok((variant, NodeDeserializer(self.0)))
+    }
+}
+
+impl<'de> VariantAccess<'de> for NodeDeserializer<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        ContainerConfig, ContainerEngine, IdleDetectionConfig, ImageBuildConfig, LogSource,
+        ProcessInfo, ProcessState, ProcessStatus,
+    };
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashMap;
+
+    fn process(id: &str) -> ProcessInfo {
+        let at = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+        ProcessInfo {
+            id: None,
+            process_id: id.to_string(),
+            name: id.to_string(),
+            command: "python".to_string(),
+            args: vec!["-m".to_string(), "http.server".to_string()],
+            env: HashMap::new(),
+            cwd: Some("/srv/public".to_string()),
+            status: ProcessStatus::default(),
+            created_at: at,
+            updated_at: at,
+            tags: vec![],
+            auto_start_on_restore: false,
+            idle_detection: None,
+            health_check: None,
+            tmux: None,
+            repl: None,
+            log_sources: vec![],
+            run_as: None,
+            resource_limits: None,
+            container: None,
+            source: None,
+            log_sinks: vec![],
+            redaction: None,
+            metadata: HashMap::new(),
+            runbook: None,
+            startup: None,
+            lazy_start: None,
+            env_loader: None,
+            output_recording: None,
+            assertions: Vec::new(),
+            restart_policy: None,
+            depends_on: Vec::new(),
+            parameters: Vec::new(),
+            keep_stdin_open: false,
+            group: None,
+            sensitive_env: HashMap::new(),
+        }
+    }
+
+    fn rich_process() -> ProcessInfo {
+        let mut info = process("api server");
+        info.args = vec!["--name=\"quoted\"\n".to_string()];
+        info.env = HashMap::from([
+            (
+                "DATABASE_URL".to_string(),
+                "postgres://localhost/dev".to_string(),
+            ),
+            ("my-key".to_string(), String::new()),
+        ]);
+        info.status = ProcessStatus {
+            state: ProcessState::Running,
+            pid: Some(4242),
+            exit_code: Some(-1),
+            started_at: Some(info.created_at),
+            ..Default::default()
+        };
+        info.tags = vec!["web".to_string()];
+        info.auto_start_on_restore = true;
+        info.idle_detection = Some(IdleDetectionConfig {
+            timeout_secs: 300,
+            cpu_threshold_percent: 1.5,
+            action: crate::types::IdleAction::Restart,
+        });
+        info.log_sources = vec![LogSource {
+            path: "/var/log/app.log".to_string(),
+            label: None,
+            stderr: true,
+        }];
+        info.container = Some(ContainerConfig {
+            engine: ContainerEngine::Podman,
+            image: "myapp:dev".to_string(),
+            build: Some(ImageBuildConfig {
+                context: ".".to_string(),
+                dockerfile: None,
+                build_args: HashMap::new(),
+                rebuild_on_change: true,
+            }),
+            run_args: vec![],
+        });
+        info
+    }
+
+    fn json(items: &[ProcessInfo]) -> Vec<Value> {
+        items
+            .iter()
+            .map(|p| serde_json::to_value(p).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_matches_yaml() {
+        let processes = vec![process("web"), rich_process()];
+        let kdl = to_string("process", "process_id", &processes).unwrap();
+        let from_kdl: Vec<ProcessInfo> = from_str(&kdl, "process", "process_id").unwrap();
+
+        let yaml = serde_yaml::to_string(&processes).unwrap();
+        let from_yaml: Vec<ProcessInfo> = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(json(&from_kdl), json(&from_yaml));
+        assert_eq!(json(&from_kdl), json(&processes));
+
+        // 書き出しは安定している（差分が出ない）
+        assert_eq!(to_string("process", "process_id", &from_kdl).unwrap(), kdl);
+    }
+
+    #[test]
+    fn test_output_format() {
+        let kdl = to_string("process", "process_id", &[process("web")]).unwrap();
+        assert!(kdl.contains("process \"web\" {\n"), "{kdl}");
+        assert!(kdl.contains("    args \"-m\" \"http.server\"\n"), "{kdl}");
+        assert!(kdl.contains("    env {}\n"), "{kdl}");
+        assert!(kdl.contains("    tags\n"), "{kdl}");
+        assert!(kdl.contains("    auto_start_on_restore #false\n"), "{kdl}");
+        assert!(kdl.contains("        pid #null\n"), "{kdl}");
+
+        // マップのキーは名前順に並ぶ
+        let mut api = process("api");
+        api.env = ["PORT", "DATABASE_URL", "my-key", "API_TOKEN"]
+            .into_iter()
+            .map(|key| (key.to_string(), "x".to_string()))
+            .collect();
+        let kdl = to_string("process", "process_id", &[api]).unwrap();
+        assert!(
+            kdl.contains(
+                "    env {\n        API_TOKEN \"x\"\n        DATABASE_URL \"x\"\n        PORT \"x\"\n        my-key \"x\"\n    }\n"
+            ),
+            "{kdl}"
+        );
+    }
+
+    #[test]
+    fn test_reads_handwritten_documents() {
+        let kdl = r##"
+            // Vantage MCP Process Configuration
+            meta { version "1.0.0"; }
+
+            process "worker" {
+                name worker
+                command "/usr/local/bin/worker"
+                args "--config" \
+                    "worker.conf"  // single-element arrays and line continuations
+                env { RUST_LOG r#"info,"quoted""#; }
+                cwd "/app"
+                /-disabled "ignored"
+                status { state running; pid 0x10; exit_code #null; started_at null; stopped_at #null; error #null; }
+                created_at "2025-01-02T03:04:05Z"
+                updated_at "2025-01-02T03:04:05Z"
+                tags "batch"
+                auto_start_on_restore true /* v1 keyword */
+            }
+        "##;
+        let processes: Vec<ProcessInfo> = from_str(kdl, "process", "process_id").unwrap();
+        let worker = &processes[0];
+        assert_eq!(worker.process_id, "worker");
+        assert_eq!(worker.args, vec!["--config", "worker.conf"]);
+        assert_eq!(worker.env["RUST_LOG"], r#"info,"quoted""#);
+        assert_eq!(worker.status.state, ProcessState::Running);
+        assert_eq!(worker.status.pid, Some(16));
+        assert_eq!(worker.tags, vec!["batch"]);
+        assert!(worker.auto_start_on_restore);
+    }
+
+    #[test]
+    fn test_reports_errors_with_location() {
+        let err = from_str::<ProcessInfo>(
+            "process \"a\" {\n    command \"x\n",
+            "process",
+            "process_id",
+        )
+        .unwrap_err();
+        assert!(err.contains("unterminated string"), "{err}");
+
+        let err =
+            from_str::<ProcessInfo>("template \"a\" {}\n", "process", "process_id").unwrap_err();
+        assert!(err.contains("unexpected node 'template'"), "{err}");
+
+        let err =
+            from_str::<ProcessInfo>("process \"a\" { command \"x\" }\n", "process", "process_id")
+                .unwrap_err();
+        assert!(err.contains("missing field"), "{err}");
+    }
+}
@@ -1,4 +1,9 @@
-use crate::types::{ClipboardItem, ProcessInfo, ProcessTemplate, Settings};
+use crate::types::{
+    ApprovalRequest, ApprovalStatus, AuthConfig, AuthSession, ChangeHistory, ClipboardItem,
+    ConfigFormat, DefinitionChange, DiagnosisRule, KubernetesWorkload, Notification, PreOpSnapshot,
+    ProcessChain, ProcessInfo, ProcessSchedule, ProcessTemplate, RecycledProcess, SessionContext,
+    Settings, TunnelConfig,
+};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -6,6 +11,15 @@ use std::sync::Arc;
 // Type alias for simplified Result type
 type Result<T> = std::result::Result<T, String>;
 
+/// 読み込めないスナップショットの代わりに前の世代を読み込んだこと
+#[derive(Debug, Clone)]
+pub struct SnapshotFallback {
+    pub path: PathBuf,
+    pub backup: PathBuf,
+    /// スナップショットを読み込めなかった理由
+    pub error: String,
+}
+
 /// Persistence manager for in-memory storage with YAML snapshot support
 #[derive(Clone)]
 pub struct PersistenceManager {
@@ -17,6 +31,17 @@ pub struct PersistenceManager {
     templates: Arc<tokio::sync::RwLock<HashMap<String, ProcessTemplate>>>,
     clipboard: Arc<tokio::sync::RwLock<Vec<ClipboardItem>>>,
     settings: Arc<tokio::sync::RwLock<Settings>>,
+    auth_sessions: Arc<tokio::sync::RwLock<HashMap<String, AuthSession>>>,
+    approvals: Arc<tokio::sync::RwLock<Vec<ApprovalRequest>>>,
+    notifications: Arc<tokio::sync::RwLock<Vec<Notification>>>,
+    tunnels: Arc<tokio::sync::RwLock<HashMap<String, TunnelConfig>>>,
+    chains: Arc<tokio::sync::RwLock<HashMap<String, ProcessChain>>>,
+    schedules: Arc<tokio::sync::RwLock<HashMap<String, ProcessSchedule>>>,
+    kubernetes_workloads: Arc<tokio::sync::RwLock<HashMap<String, KubernetesWorkload>>>,
+    pre_op_snapshots: Arc<tokio::sync::RwLock<Vec<PreOpSnapshot>>>,
+    recycle_bin: Arc<tokio::sync::RwLock<Vec<RecycledProcess>>>,
+    changes: Arc<tokio::sync::RwLock<ChangeHistory>>,
+    diagnosis_rules: Arc<tokio::sync::RwLock<HashMap<String, DiagnosisRule>>>,
 }
 
 impl PersistenceManager {
@@ -28,6 +53,17 @@ impl PersistenceManager {
         let templates = Arc::new(tokio::sync::RwLock::new(HashMap::new()));
         let clipboard = Arc::new(tokio::sync::RwLock::new(Vec::new()));
         let settings = Arc::new(tokio::sync::RwLock::new(Settings::default()));
+        let auth_sessions = Arc::new(tokio::sync::RwLock::new(HashMap::new()));
+        let approvals = Arc::new(tokio::sync::RwLock::new(Vec::new()));
+        let notifications = Arc::new(tokio::sync::RwLock::new(Vec::new()));
+        let tunnels = Arc::new(tokio::sync::RwLock::new(HashMap::new()));
+        let chains = Arc::new(tokio::sync::RwLock::new(HashMap::new()));
+        let schedules = Arc::new(tokio::sync::RwLock::new(HashMap::new()));
+        let kubernetes_workloads = Arc::new(tokio::sync::RwLock::new(HashMap::new()));
+        let pre_op_snapshots = Arc::new(tokio::sync::RwLock::new(Vec::new()));
+        let recycle_bin = Arc::new(tokio::sync::RwLock::new(Vec::new()));
+        let changes = Arc::new(tokio::sync::RwLock::new(ChangeHistory::default()));
+        let diagnosis_rules = Arc::new(tokio::sync::RwLock::new(HashMap::new()));
 
         Ok(Self {
             snapshot_path,
@@ -36,13 +72,23 @@ impl PersistenceManager {
             templates,
             clipboard,
             settings,
+            auth_sessions,
+            approvals,
+            notifications,
+            tunnels,
+            chains,
+            schedules,
+            kubernetes_workloads,
+            pre_op_snapshots,
+            recycle_bin,
+            changes,
+            diagnosis_rules,
         })
     }
 
     /// Get default snapshot path
     fn default_snapshot_path() -> PathBuf {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(home).join(".vantage").join("snapshot.yaml")
+        crate::paths::get().snapshot_file()
     }
 
     /// Save or update a process
@@ -66,25 +112,43 @@ impl PersistenceManager {
         Ok(())
     }
 
+    /// Get a process by id
+    pub async fn get_process(&self, process_id: &str) -> Result<Option<ProcessInfo>> {
+        Ok(self.processes.read().await.get(process_id).cloned())
+    }
+
     /// Load all processes
     pub async fn load_all_processes(&self) -> Result<HashMap<String, ProcessInfo>> {
         let processes = self.processes.read().await;
         Ok(processes.clone())
     }
 
-    /// Export processes to YAML snapshot
+    /// Export processes to a snapshot (YAML, or KDL for `.kdl` paths)
     pub async fn export_snapshot(
         &self,
         file_path: Option<&str>,
         only_auto_start: bool,
+    ) -> Result<String> {
+        self.export_snapshot_as(file_path, only_auto_start, None)
+            .await
+    }
+
+    /// Export processes to a snapshot in the given format (defaults to the file extension)
+    pub async fn export_snapshot_as(
+        &self,
+        file_path: Option<&str>,
+        only_auto_start: bool,
+        format: Option<ConfigFormat>,
     ) -> Result<String> {
         let path = match file_path {
             Some(p) => PathBuf::from(p),
             None => self.snapshot_path.clone(),
         };
+        let format = format.unwrap_or_else(|| ConfigFormat::from_path(&path));
 
         let processes = self.load_all_processes().await?;
         let mut process_list: Vec<ProcessInfo> = processes.into_values().collect();
+        process_list.sort_by(|a, b| a.process_id.cmp(&b.process_id));
 
         if only_auto_start {
             process_list.retain(|p| p.auto_start_on_restore);
@@ -97,43 +161,95 @@ impl PersistenceManager {
                 .map_err(|e| format!("Failed to create directory: {e}"))?;
         }
 
-        let yaml = serde_yaml::to_string(&process_list)
-            .map_err(|e| format!("Failed to serialize to YAML: {e}"))?;
+        let contents = match format {
+            ConfigFormat::Yaml => serde_yaml::to_string(&process_list)
+                .map_err(|e| format!("Failed to serialize to YAML: {e}"))?,
+            ConfigFormat::Kdl => super::kdl::to_string("process", "process_id", &process_list)?,
+        };
 
-        tokio::fs::write(&path, yaml)
-            .await
-            .map_err(|e| format!("Failed to write snapshot: {e}"))?;
+        // 読み込めるスナップショットだけを前の世代として残す
+        let keep_backup = Self::read_snapshot(&path, format).await.is_ok();
+        super::atomic_file::write(&path, contents, keep_backup).await?;
 
         tracing::info!(
-            "Exported {} processes to YAML snapshot (auto_start_only: {})",
+            "Exported {} processes to {:?} snapshot (auto_start_only: {})",
             process_list.len(),
+            format,
             only_auto_start
         );
 
         Ok(path.to_string_lossy().to_string())
     }
 
-    /// Import processes from YAML snapshot
+    /// Import processes from a snapshot (YAML, or KDL for `.kdl` paths)
     pub async fn import_snapshot(
         &self,
         file_path: Option<&str>,
+    ) -> Result<HashMap<String, ProcessInfo>> {
+        self.import_snapshot_as(file_path, None).await
+    }
+
+    /// Import processes from a snapshot in the given format (defaults to the file extension)
+    pub async fn import_snapshot_as(
+        &self,
+        file_path: Option<&str>,
+        format: Option<ConfigFormat>,
     ) -> Result<HashMap<String, ProcessInfo>> {
         let path = match file_path {
             Some(p) => Path::new(p),
             None => &self.snapshot_path,
         };
+        let format = format.unwrap_or_else(|| ConfigFormat::from_path(path));
 
-        if !path.exists() {
-            return Err(format!("Snapshot file not found: {}", path.display()));
-        }
+        let process_list = Self::read_snapshot(path, format).await?;
+        Ok(self.insert_imported(process_list, format).await)
+    }
 
-        let yaml = tokio::fs::read_to_string(path)
-            .await
-            .map_err(|e| format!("Failed to read snapshot: {e}"))?;
+    /// Import processes from a snapshot, falling back to the previous generation (`.bak`)
+    /// when the snapshot is missing or cannot be read
+    pub async fn import_snapshot_with_fallback(
+        &self,
+        file_path: Option<&str>,
+        format: Option<ConfigFormat>,
+    ) -> Result<(HashMap<String, ProcessInfo>, Option<SnapshotFallback>)> {
+        let path = match file_path {
+            Some(p) => Path::new(p),
+            None => &self.snapshot_path,
+        };
+        let format = format.unwrap_or_else(|| ConfigFormat::from_path(path));
 
-        let process_list: Vec<ProcessInfo> =
-            serde_yaml::from_str(&yaml).map_err(|e| format!("Failed to deserialize YAML: {e}"))?;
+        let error = match Self::read_snapshot(path, format).await {
+            Ok(process_list) => {
+                return Ok((self.insert_imported(process_list, format).await, None));
+            }
+            Err(e) => e,
+        };
+        let backup = super::atomic_file::backup_path(path);
+        let process_list = Self::read_snapshot(&backup, format)
+            .await
+            .map_err(|backup_error| format!("{error} (backup: {backup_error})"))?;
+        tracing::warn!(
+            "Snapshot {} could not be read ({}); restored the previous generation from {}",
+            path.display(),
+            error,
+            backup.display()
+        );
+        let imported = self.insert_imported(process_list, format).await;
+        Ok((
+            imported,
+            Some(SnapshotFallback {
+                path: path.to_path_buf(),
+                backup,
+                error,
+            }),
+        ))
+    }
 
+    async fn insert_imported(
+        &self,
+        process_list: Vec<ProcessInfo>,
+        format: ConfigFormat,
+    ) -> HashMap<String, ProcessInfo> {
         let mut imported = HashMap::new();
         let mut processes = self.processes.write().await;
 
@@ -143,9 +259,30 @@ impl PersistenceManager {
             imported.insert(process_id, process_info);
         }
 
-        tracing::info!("Imported {} processes from YAML snapshot", imported.len());
+        tracing::info!(
+            "Imported {} processes from {:?} snapshot",
+            imported.len(),
+            format
+        );
+
+        imported
+    }
+
+    /// Read the processes in a snapshot without importing them
+    pub async fn read_snapshot(path: &Path, format: ConfigFormat) -> Result<Vec<ProcessInfo>> {
+        if !path.exists() {
+            return Err(format!("Snapshot file not found: {}", path.display()));
+        }
+
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("Failed to read snapshot: {e}"))?;
 
-        Ok(imported)
+        match format {
+            ConfigFormat::Yaml => serde_yaml::from_str(&contents)
+                .map_err(|e| format!("Failed to deserialize YAML: {e}")),
+            ConfigFormat::Kdl => super::kdl::from_str(&contents, "process", "process_id"),
+        }
     }
 
     /// Create an auto-start snapshot
@@ -350,6 +487,457 @@ impl PersistenceManager {
         Ok(results)
     }
 
+    // Session contexts
+
+    /// Directory where session contexts are stored
+    fn contexts_dir(&self) -> PathBuf {
+        crate::paths::get().contexts_dir()
+    }
+
+    fn context_path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && !name.starts_with('.');
+        if !valid {
+            return Err(format!(
+                "Invalid context name '{name}': use letters, digits, '-', '_' or '.'"
+            ));
+        }
+        Ok(self.contexts_dir().join(format!("{name}.yaml")))
+    }
+
+    /// Save a session context (overwrites an existing one with the same name)
+    pub async fn save_context(&self, context: &SessionContext) -> Result<PathBuf> {
+        let path = self.context_path(&context.name)?;
+        tokio::fs::create_dir_all(self.contexts_dir())
+            .await
+            .map_err(|e| format!("Failed to create directory: {e}"))?;
+
+        let yaml = serde_yaml::to_string(context)
+            .map_err(|e| format!("Failed to serialize context: {e}"))?;
+        tokio::fs::write(&path, yaml)
+            .await
+            .map_err(|e| format!("Failed to write context: {e}"))?;
+
+        tracing::info!("Saved session context '{}'", context.name);
+        Ok(path)
+    }
+
+    /// Load a session context by name
+    pub async fn load_context(&self, name: &str) -> Result<SessionContext> {
+        let path = self.context_path(name)?;
+        if !path.exists() {
+            return Err(format!("Context '{name}' not found"));
+        }
+
+        let yaml = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Failed to read context: {e}"))?;
+        serde_yaml::from_str(&yaml).map_err(|e| format!("Failed to deserialize context: {e}"))
+    }
+
+    /// List saved session contexts (newest first)
+    pub async fn list_contexts(&self) -> Result<Vec<SessionContext>> {
+        let dir = self.contexts_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = tokio::fs::read_dir(&dir)
+            .await
+            .map_err(|e| format!("Failed to read contexts directory: {e}"))?;
+        let mut contexts = Vec::new();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| format!("Failed to read contexts directory: {e}"))?
+        {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("yaml") {
+                continue;
+            }
+            match tokio::fs::read_to_string(&path).await.map(|yaml| {
+                serde_yaml::from_str::<SessionContext>(&yaml).map_err(|e| e.to_string())
+            }) {
+                Ok(Ok(context)) => contexts.push(context),
+                Ok(Err(e)) => tracing::warn!("Skipping invalid context {}: {}", path.display(), e),
+                Err(e) => tracing::warn!("Failed to read context {}: {}", path.display(), e),
+            }
+        }
+
+        contexts.sort_by_key(|c| std::cmp::Reverse(c.saved_at));
+        Ok(contexts)
+    }
+
+    /// Delete a session context
+    pub async fn delete_context(&self, name: &str) -> Result<()> {
+        let path = self.context_path(name)?;
+        tokio::fs::remove_file(&path)
+            .await
+            .map_err(|e| format!("Failed to delete context '{name}': {e}"))?;
+        tracing::info!("Deleted session context '{}'", name);
+        Ok(())
+    }
+
+    // Web console authentication
+
+    /// Path of the authentication config
+    pub fn auth_config_path(&self) -> PathBuf {
+        crate::paths::get().auth_file()
+    }
+
+    /// Load the authentication config (defaults to no authentication if the file is missing)
+    pub async fn load_auth_config(&self) -> Result<AuthConfig> {
+        let path = self.auth_config_path();
+        if !path.exists() {
+            return Ok(AuthConfig::default());
+        }
+
+        let yaml = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Failed to read auth config: {e}"))?;
+        serde_yaml::from_str(&yaml).map_err(|e| format!("Failed to parse auth config: {e}"))
+    }
+
+    /// Save or update a login session
+    pub async fn save_auth_session(&self, session: &AuthSession) -> Result<()> {
+        let mut sessions = self.auth_sessions.write().await;
+        sessions.insert(session.session_id.clone(), session.clone());
+        Ok(())
+    }
+
+    /// Get a login session (expired sessions are removed and not returned)
+    pub async fn get_auth_session(&self, session_id: &str) -> Result<Option<AuthSession>> {
+        let mut sessions = self.auth_sessions.write().await;
+        match sessions.get(session_id) {
+            Some(session) if session.expires_at <= chrono::Utc::now() => {
+                sessions.remove(session_id);
+                Ok(None)
+            }
+            session => Ok(session.cloned()),
+        }
+    }
+
+    /// Delete a login session
+    pub async fn delete_auth_session(&self, session_id: &str) -> Result<()> {
+        let mut sessions = self.auth_sessions.write().await;
+        sessions.remove(session_id);
+        Ok(())
+    }
+
+    /// Remove expired login sessions and return how many were removed
+    pub async fn prune_auth_sessions(&self) -> Result<usize> {
+        let mut sessions = self.auth_sessions.write().await;
+        let before = sessions.len();
+        let now = chrono::Utc::now();
+        sessions.retain(|_, s| s.expires_at > now);
+        Ok(before - sessions.len())
+    }
+
+    // Approval workflow
+
+    /// Record a new approval request
+    pub async fn save_approval(&self, request: &ApprovalRequest) -> Result<()> {
+        let mut approvals = self.approvals.write().await;
+        approvals.push(request.clone());
+        Ok(())
+    }
+
+    /// Get an approval request
+    pub async fn get_approval(&self, approval_id: &str) -> Result<Option<ApprovalRequest>> {
+        let approvals = self.approvals.read().await;
+        Ok(approvals
+            .iter()
+            .find(|a| a.approval_id == approval_id)
+            .cloned())
+    }
+
+    /// List approval requests (newest first), optionally only those with the given status
+    pub async fn list_approvals(
+        &self,
+        status: Option<ApprovalStatus>,
+    ) -> Result<Vec<ApprovalRequest>> {
+        let approvals = self.approvals.read().await;
+        Ok(approvals
+            .iter()
+            .rev()
+            .filter(|a| status.is_none_or(|s| a.status == s))
+            .cloned()
+            .collect())
+    }
+
+    /// Update an approval request, failing if its current status is not `expected`
+    ///
+    /// The check and the update happen under one lock, so a request is decided only once.
+    pub async fn transition_approval(
+        &self,
+        approval_id: &str,
+        expected: ApprovalStatus,
+        update: impl FnOnce(&mut ApprovalRequest),
+    ) -> Result<ApprovalRequest> {
+        let mut approvals = self.approvals.write().await;
+        let approval = approvals
+            .iter_mut()
+            .find(|a| a.approval_id == approval_id)
+            .ok_or_else(|| format!("Approval '{approval_id}' not found"))?;
+        if approval.status != expected {
+            return Err(format!(
+                "Approval '{approval_id}' is already {}",
+                approval.status.as_str()
+            ));
+        }
+        update(approval);
+        Ok(approval.clone())
+    }
+
+    // Notifications inbox
+
+    /// Record a notification, dropping the oldest ones to keep at most `max_history`
+    pub async fn push_notification(
+        &self,
+        notification: &Notification,
+        max_history: usize,
+    ) -> Result<()> {
+        let mut notifications = self.notifications.write().await;
+        notifications.push(notification.clone());
+        let excess = notifications.len().saturating_sub(max_history);
+        notifications.drain(..excess);
+        Ok(())
+    }
+
+    /// List notifications (newest first), optionally only unread ones
+    pub async fn list_notifications(&self, unread_only: bool) -> Result<Vec<Notification>> {
+        let notifications = self.notifications.read().await;
+        Ok(notifications
+            .iter()
+            .rev()
+            .filter(|n| !unread_only || !n.read)
+            .cloned()
+            .collect())
+    }
+
+    /// Mark notifications as read (all of them when `ids` is None), returning how many changed
+    pub async fn mark_notifications_read(&self, ids: Option<&[String]>) -> Result<usize> {
+        let read_at = chrono::Utc::now();
+        let mut notifications = self.notifications.write().await;
+        let mut changed = 0;
+        for notification in notifications
+            .iter_mut()
+            .filter(|n| !n.read && ids.is_none_or(|ids| ids.contains(&n.notification_id)))
+        {
+            notification.read = true;
+            notification.read_at = Some(read_at);
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    /// Count unread notifications
+    pub async fn count_unread_notifications(&self) -> Result<usize> {
+        let notifications = self.notifications.read().await;
+        Ok(notifications.iter().filter(|n| !n.read).count())
+    }
+
+    // Tunnels
+
+    /// Save or update a tunnel definition
+    pub async fn save_tunnel(&self, tunnel: &TunnelConfig) -> Result<()> {
+        let mut tunnels = self.tunnels.write().await;
+        tunnels.insert(tunnel.tunnel_id.clone(), tunnel.clone());
+        Ok(())
+    }
+
+    /// Delete a tunnel definition
+    pub async fn delete_tunnel(&self, tunnel_id: &str) -> Result<()> {
+        let mut tunnels = self.tunnels.write().await;
+        tunnels.remove(tunnel_id);
+        Ok(())
+    }
+
+    /// Load all tunnel definitions
+    pub async fn load_all_tunnels(&self) -> Result<Vec<TunnelConfig>> {
+        let tunnels = self.tunnels.read().await;
+        Ok(tunnels.values().cloned().collect())
+    }
+
+    // Process chains
+
+    /// Save or update a process chain
+    pub async fn save_chain(&self, chain: &ProcessChain) -> Result<()> {
+        let mut chains = self.chains.write().await;
+        chains.insert(chain.chain_id.clone(), chain.clone());
+        Ok(())
+    }
+
+    /// Delete a process chain
+    pub async fn delete_chain(&self, chain_id: &str) -> Result<()> {
+        let mut chains = self.chains.write().await;
+        chains.remove(chain_id);
+        Ok(())
+    }
+
+    /// Load all process chains
+    pub async fn load_all_chains(&self) -> Result<Vec<ProcessChain>> {
+        let chains = self.chains.read().await;
+        Ok(chains.values().cloned().collect())
+    }
+
+    // Process schedules
+
+    /// Save or update a process schedule
+    pub async fn save_schedule(&self, schedule: &ProcessSchedule) -> Result<()> {
+        let mut schedules = self.schedules.write().await;
+        schedules.insert(schedule.schedule_id.clone(), schedule.clone());
+        Ok(())
+    }
+
+    /// Delete a process schedule
+    pub async fn delete_schedule(&self, schedule_id: &str) -> Result<()> {
+        let mut schedules = self.schedules.write().await;
+        schedules.remove(schedule_id);
+        Ok(())
+    }
+
+    /// Load all process schedules
+    pub async fn load_all_schedules(&self) -> Result<Vec<ProcessSchedule>> {
+        let schedules = self.schedules.read().await;
+        Ok(schedules.values().cloned().collect())
+    }
+
+    // Kubernetes workloads
+
+    /// Save or update a Kubernetes workload registration
+    pub async fn save_kubernetes_workload(&self, workload: &KubernetesWorkload) -> Result<()> {
+        let mut workloads = self.kubernetes_workloads.write().await;
+        workloads.insert(workload.workload_id.clone(), workload.clone());
+        Ok(())
+    }
+
+    /// Delete a Kubernetes workload registration
+    pub async fn delete_kubernetes_workload(&self, workload_id: &str) -> Result<()> {
+        let mut workloads = self.kubernetes_workloads.write().await;
+        workloads.remove(workload_id);
+        Ok(())
+    }
+
+    /// Load all Kubernetes workload registrations
+    pub async fn load_all_kubernetes_workloads(&self) -> Result<Vec<KubernetesWorkload>> {
+        let workloads = self.kubernetes_workloads.read().await;
+        Ok(workloads.values().cloned().collect())
+    }
+
+    // Pre-operation snapshots
+
+    /// Record a pre-operation snapshot, returning the ones dropped to keep `max_history`
+    pub async fn push_pre_op_snapshot(
+        &self,
+        snapshot: &PreOpSnapshot,
+        max_history: usize,
+    ) -> Result<Vec<PreOpSnapshot>> {
+        let mut snapshots = self.pre_op_snapshots.write().await;
+        snapshots.push(snapshot.clone());
+        let excess = snapshots.len().saturating_sub(max_history);
+        Ok(snapshots.drain(..excess).collect())
+    }
+
+    /// List pre-operation snapshots (newest first)
+    pub async fn list_pre_op_snapshots(&self) -> Result<Vec<PreOpSnapshot>> {
+        let snapshots = self.pre_op_snapshots.read().await;
+        Ok(snapshots.iter().rev().cloned().collect())
+    }
+
+    /// Remove a pre-operation snapshot from the history
+    pub async fn delete_pre_op_snapshot(&self, snapshot_id: &str) -> Result<()> {
+        let mut snapshots = self.pre_op_snapshots.write().await;
+        snapshots.retain(|s| s.snapshot_id != snapshot_id);
+        Ok(())
+    }
+
+    // Recycle bin
+
+    /// Move a process definition to the recycle bin, returning the ones dropped to keep `max_items`
+    pub async fn push_recycled_process(
+        &self,
+        item: &RecycledProcess,
+        max_items: usize,
+    ) -> Result<Vec<RecycledProcess>> {
+        let mut bin = self.recycle_bin.write().await;
+        bin.push(item.clone());
+        let excess = bin.len().saturating_sub(max_items);
+        Ok(bin.drain(..excess).collect())
+    }
+
+    /// List the recycle bin (newest first)
+    pub async fn list_recycled_processes(&self) -> Result<Vec<RecycledProcess>> {
+        let bin = self.recycle_bin.read().await;
+        Ok(bin.iter().rev().cloned().collect())
+    }
+
+    /// Take the newest recycled definition of a process out of the recycle bin
+    pub async fn take_recycled_process(&self, process_id: &str) -> Result<Option<RecycledProcess>> {
+        let mut bin = self.recycle_bin.write().await;
+        let index = bin
+            .iter()
+            .rposition(|item| item.process.process_id == process_id);
+        Ok(index.map(|index| bin.remove(index)))
+    }
+
+    /// Permanently delete recycled definitions (all of them, or those of one process)
+    pub async fn purge_recycled_processes(&self, process_id: Option<&str>) -> Result<usize> {
+        let mut bin = self.recycle_bin.write().await;
+        let before = bin.len();
+        bin.retain(|item| process_id.is_some_and(|id| item.process.process_id != id));
+        Ok(before - bin.len())
+    }
+
+    // Definition change history
+
+    /// Record a definition change, clearing the redo history and keeping at most `max_history`
+    pub async fn record_change(&self, change: &DefinitionChange, max_history: usize) -> Result<()> {
+        let mut changes = self.changes.write().await;
+        changes.undo.insert(0, change.clone());
+        changes.undo.truncate(max_history);
+        changes.redo.clear();
+        Ok(())
+    }
+
+    /// Get the undo and redo history
+    pub async fn change_history(&self) -> Result<ChangeHistory> {
+        let changes = self.changes.read().await;
+        Ok(changes.clone())
+    }
+
+    /// Move the newest change between the undo and redo history
+    ///
+    /// Fails if the newest change is no longer `change_id`, so a change is applied only once.
+    pub async fn move_change(&self, change_id: &str, undone: bool) -> Result<()> {
+        let mut changes = self.changes.write().await;
+        let ChangeHistory { undo, redo } = &mut *changes;
+        let (from, to) = if undone { (undo, redo) } else { (redo, undo) };
+        if from.first().is_none_or(|c| c.change_id != change_id) {
+            return Err(format!("Change '{change_id}' is no longer the latest"));
+        }
+        to.insert(0, from.remove(0));
+        Ok(())
+    }
+
+    // Diagnosis rules
+
+    /// Save or update a diagnosis rule
+    pub async fn save_diagnosis_rule(&self, rule: &DiagnosisRule) -> Result<()> {
+        let mut rules = self.diagnosis_rules.write().await;
+        rules.insert(rule.rule_id.clone(), rule.clone());
+        Ok(())
+    }
+
+    /// Load all diagnosis rules
+    pub async fn load_all_diagnosis_rules(&self) -> Result<Vec<DiagnosisRule>> {
+        let rules = self.diagnosis_rules.read().await;
+        Ok(rules.values().cloned().collect())
+    }
+
     // Settings management
 
     /// Get settings
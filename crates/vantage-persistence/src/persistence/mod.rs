@@ -1,3 +1,5 @@
+pub mod atomic_file;
+pub mod kdl;
 pub mod manager;
 
-pub use manager::PersistenceManager;
+pub use manager::{PersistenceManager, SnapshotFallback};
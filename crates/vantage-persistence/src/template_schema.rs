@@ -0,0 +1,457 @@
+//! テンプレート変数のJSON Schema
+//!
+//! 変数ごとに型・列挙値・パターン・範囲を宣言し、インスタンス化の入力を検証します。
+//! 入力は文字列で受け取るため、`integer` などの型は文字列を解釈できるかで判定します。
+//! テンプレート全体の変数は1つのオブジェクトのスキーマとして書き出し、
+//! MCPクライアントが入力フォームを描画できるようにします。
+
+use crate::types::TemplateVariable;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::fmt;
+use utoipa::ToSchema;
+
+/// 書き出すスキーマの方言
+const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// 変数の値のスキーマ（JSON Schemaのキーワードのうち、値の検証に使うもの）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableSchema {
+    /// 値の型（省略時は `var_type` から決め、それもなければ文字列）
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<SchemaType>,
+    /// 取りうる値
+    #[serde(rename = "enum", default, skip_serializing_if = "Vec::is_empty")]
+    pub enum_values: Vec<String>,
+    /// 値が一致すべき正規表現（部分一致、全体に一致させる場合は `^...$`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// 数値の下限（`integer`・`number` のみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    /// 数値の上限（`integer`・`number` のみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    /// 文字数の下限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    /// 文字数の上限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+}
+
+/// 値の型
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaType {
+    #[default]
+    String,
+    Integer,
+    Number,
+    Boolean,
+}
+
+impl SchemaType {
+    /// 型ヒント（`var_type`）から型を決める
+    fn from_hint(hint: &str) -> Option<Self> {
+        match hint {
+            "integer" => Some(Self::Integer),
+            "number" => Some(Self::Number),
+            "boolean" => Some(Self::Boolean),
+            _ => None,
+        }
+    }
+
+    /// 文字列の値をJSONの値として解釈する（解釈できなければNone）
+    fn parse(self, value: &str) -> Option<Value> {
+        let trimmed = value.trim();
+        match self {
+            Self::String => Some(Value::String(value.to_string())),
+            Self::Integer => trimmed.parse::<i64>().ok().map(Value::from),
+            Self::Number => trimmed
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(Value::from),
+            Self::Boolean => match trimmed {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::String => "a string",
+            Self::Integer => "an integer",
+            Self::Number => "a number",
+            Self::Boolean => "true or false",
+        }
+    }
+}
+
+/// スキーマに合わない入力
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct SchemaError {
+    /// 入力のオブジェクトの中の位置（JSON Pointer、例: `/PORT`）
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.pointer, self.message)
+    }
+}
+
+impl TemplateVariable {
+    /// 検証に使う型（スキーマの型、なければ型ヒント）
+    pub fn value_type(&self) -> SchemaType {
+        self.schema
+            .as_ref()
+            .and_then(|schema| schema.value_type)
+            .or_else(|| self.var_type.as_deref().and_then(SchemaType::from_hint))
+            .unwrap_or_default()
+    }
+}
+
+/// 変数名をJSON Pointerの1要素にする
+fn pointer(name: &str) -> String {
+    format!("/{}", name.replace('~', "~0").replace('/', "~1"))
+}
+
+/// 1つの値をスキーマで検証する
+fn check_value(variable: &TemplateVariable, value: &str) -> Vec<String> {
+    check_typed(variable.value_type(), variable.schema.as_ref(), value)
+}
+
+/// 変数に属さない1つの値をスキーマで検証する（型の省略時は文字列）
+pub fn check_schema(schema: &VariableSchema, value: &str) -> Vec<String> {
+    check_typed(schema.value_type.unwrap_or_default(), Some(schema), value)
+}
+
+fn check_typed(
+    value_type: SchemaType,
+    schema: Option<&VariableSchema>,
+    value: &str,
+) -> Vec<String> {
+    // フォームの未入力（空文字列）は、型のある変数では指定なしとみなす
+    if value.is_empty() && value_type != SchemaType::String {
+        return Vec::new();
+    }
+    let Some(parsed) = value_type.parse(value) else {
+        return vec![format!(
+            "must be {}, got {:?}",
+            value_type.describe(),
+            value
+        )];
+    };
+    let Some(schema) = schema else {
+        return Vec::new();
+    };
+
+    let mut messages = Vec::new();
+    if !schema.enum_values.is_empty() && !schema.enum_values.iter().any(|v| v == value) {
+        messages.push(format!(
+            "must be one of {}, got {:?}",
+            schema.enum_values.join(", "),
+            value
+        ));
+    }
+    if let Some(pattern) = &schema.pattern
+        && let Ok(regex) = Regex::new(pattern)
+        && !regex.is_match(value)
+    {
+        messages.push(format!("must match the pattern `{pattern}`"));
+    }
+    if let Some(number) = parsed.as_f64() {
+        if let Some(minimum) = schema.minimum
+            && number < minimum
+        {
+            messages.push(format!("must be at least {minimum}, got {value}"));
+        }
+        if let Some(maximum) = schema.maximum
+            && number > maximum
+        {
+            messages.push(format!("must be at most {maximum}, got {value}"));
+        }
+    }
+    let length = value.chars().count();
+    if let Some(min) = schema.min_length
+        && length < min
+    {
+        messages.push(format!("must be at least {min} characters long"));
+    }
+    if let Some(max) = schema.max_length
+        && length > max
+    {
+        messages.push(format!("must be at most {max} characters long"));
+    }
+    messages
+}
+
+/// インスタンス化の入力を検証する
+///
+/// 指定されなかった変数はデフォルト値を検証する。必須変数が無いことは扱わない。
+pub fn validate_values(
+    variables: &[TemplateVariable],
+    values: &HashMap<String, String>,
+) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    for variable in variables {
+        let Some(value) = values
+            .get(&variable.name)
+            .or(variable.default_value.as_ref())
+        else {
+            continue;
+        };
+        errors.extend(
+            check_value(variable, value)
+                .into_iter()
+                .map(|message| SchemaError {
+                    pointer: pointer(&variable.name),
+                    message,
+                }),
+        );
+    }
+    errors
+}
+
+/// 検証の結果を1つのエラーメッセージにまとめる
+pub fn describe_errors(errors: &[SchemaError]) -> String {
+    let details: Vec<String> = errors.iter().map(SchemaError::to_string).collect();
+    format!(
+        "Template values do not match the parameter schema: {}",
+        details.join("; ")
+    )
+}
+
+/// 変数の定義を検証する（パターン・範囲・列挙値・デフォルト値）
+pub fn validate_definition(variables: &[TemplateVariable]) -> Result<(), String> {
+    for variable in variables {
+        let name = &variable.name;
+        let Some(schema) = &variable.schema else {
+            continue;
+        };
+        if let Some(pattern) = &schema.pattern {
+            Regex::new(pattern)
+                .map_err(|e| format!("Variable '{name}' has an invalid pattern: {e}"))?;
+        }
+        if let (Some(min), Some(max)) = (schema.minimum, schema.maximum)
+            && min > max
+        {
+            return Err(format!("Variable '{name}' has minimum above maximum"));
+        }
+        if let (Some(min), Some(max)) = (schema.min_length, schema.max_length)
+            && min > max
+        {
+            return Err(format!("Variable '{name}' has minLength above maxLength"));
+        }
+        let value_type = variable.value_type();
+        for value in &schema.enum_values {
+            if value_type.parse(value).is_none() {
+                return Err(format!(
+                    "Variable '{name}' lists {value:?} in enum, which is not {}",
+                    value_type.describe()
+                ));
+            }
+        }
+    }
+    let errors = validate_values(variables, &HashMap::new());
+    if !errors.is_empty() {
+        let details: Vec<String> = errors.iter().map(SchemaError::to_string).collect();
+        return Err(format!(
+            "Default values do not match the parameter schema: {}",
+            details.join("; ")
+        ));
+    }
+    Ok(())
+}
+
+/// テンプレートの変数を1つのオブジェクトのJSON Schemaとして書き出す
+pub fn parameters_schema(variables: &[TemplateVariable]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for variable in variables {
+        let value_type = variable.value_type();
+        let mut property = match &variable.schema {
+            Some(schema) => serde_json::to_value(schema).unwrap_or_else(|_| json!({})),
+            None => json!({}),
+        };
+        let property_map = property.as_object_mut().expect("schema is an object");
+        property_map.insert("type".to_string(), json!(value_type));
+        if let Some(values) = property_map.get_mut("enum") {
+            // 型に合わせて列挙値を数値・真偽値にする
+            if let Some(list) = values.as_array_mut() {
+                for value in list.iter_mut() {
+                    if let Some(parsed) = value.as_str().and_then(|v| value_type.parse(v)) {
+                        *value = parsed;
+                    }
+                }
+            }
+        }
+        if let Some(description) = &variable.description {
+            property_map.insert("description".to_string(), json!(description));
+        }
+        if let Some(default) = variable
+            .default_value
+            .as_deref()
+            .and_then(|v| value_type.parse(v))
+        {
+            property_map.insert("default".to_string(), default);
+        }
+        if let Some(example) = variable
+            .example
+            .as_deref()
+            .and_then(|v| value_type.parse(v))
+        {
+            property_map.insert("examples".to_string(), json!([example]));
+        }
+        if variable.var_type.as_deref() == Some("secret") {
+            property_map.insert("writeOnly".to_string(), json!(true));
+        }
+        properties.insert(variable.name.clone(), property);
+        if variable.required {
+            required.push(variable.name.clone());
+        }
+    }
+    json!({
+        "$schema": SCHEMA_DIALECT,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// 変数の値を文字列として受け取る（数値・真偽値はその表記の文字列にする）
+///
+/// 書き出したスキーマに従うクライアントは `integer` の変数に数値を送るため。
+pub fn deserialize_values<'de, D>(deserializer: D) -> Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = HashMap::<String, Value>::deserialize(deserializer)?;
+    raw.into_iter()
+        .map(|(name, value)| match value_to_string(&value) {
+            Some(text) => Ok((name, text)),
+            None => Err(serde::de::Error::custom(format!(
+                "value of '{name}' must be a string, number or boolean, got {value}"
+            ))),
+        })
+        .collect()
+}
+
+/// JSONの値を変数の値の文字列にする（文字列・数値・真偽値以外はNone）
+pub fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(
+        name: &str,
+        var_type: Option<&str>,
+        schema: Option<VariableSchema>,
+    ) -> TemplateVariable {
+        TemplateVariable {
+            name: name.to_string(),
+            description: None,
+            default_value: None,
+            required: false,
+            var_type: var_type.map(str::to_string),
+            example: None,
+            schema,
+        }
+    }
+
+    #[test]
+    fn test_values_are_checked_with_pointer_paths() {
+        let mut port = variable(
+            "PORT",
+            None,
+            Some(VariableSchema {
+                value_type: Some(SchemaType::Integer),
+                minimum: Some(1024.0),
+                maximum: Some(65535.0),
+                ..Default::default()
+            }),
+        );
+        port.default_value = Some("8080".to_string());
+        port.required = true;
+        let mode = variable(
+            "MODE",
+            None,
+            Some(VariableSchema {
+                enum_values: vec!["dev".to_string(), "prod".to_string()],
+                ..Default::default()
+            }),
+        );
+        let path = variable(
+            "a/b",
+            None,
+            Some(VariableSchema {
+                pattern: Some("^/".to_string()),
+                ..Default::default()
+            }),
+        );
+        let variables = vec![port, mode, path];
+        assert!(validate_definition(&variables).is_ok());
+
+        let values = HashMap::from([
+            ("PORT".to_string(), "80".to_string()),
+            ("MODE".to_string(), "staging".to_string()),
+            ("a/b".to_string(), "relative".to_string()),
+        ]);
+        let errors = validate_values(&variables, &values);
+        let pointers: Vec<&str> = errors.iter().map(|e| e.pointer.as_str()).collect();
+        assert_eq!(pointers, vec!["/PORT", "/MODE", "/a~1b"]);
+        assert!(errors[0].message.contains("at least 1024"));
+
+        let values = HashMap::from([("PORT".to_string(), "http".to_string())]);
+        assert_eq!(
+            validate_values(&variables, &values)[0].to_string(),
+            "/PORT: must be an integer, got \"http\""
+        );
+
+        // 型ヒントの数値型も検証し、書き出すスキーマでは数値になる
+        let mut workers = variable("WORKERS", Some("number"), None);
+        workers.default_value = Some("many".to_string());
+        assert!(validate_definition(&[workers.clone()]).is_err());
+        workers.default_value = Some("4".to_string());
+        let schema = parameters_schema(&[variables[0].clone(), workers]);
+        assert_eq!(schema["properties"]["PORT"]["type"], "integer");
+        assert_eq!(schema["properties"]["PORT"]["default"], 8080);
+        assert_eq!(schema["properties"]["PORT"]["maximum"], 65535.0);
+        assert_eq!(schema["properties"]["WORKERS"]["default"], 4.0);
+        assert_eq!(schema["required"], json!(["PORT"]));
+    }
+
+    #[test]
+    fn test_deserialize_scalar_values() {
+        #[derive(Deserialize)]
+        struct Request {
+            #[serde(deserialize_with = "deserialize_values")]
+            values: HashMap<String, String>,
+        }
+        let request: Request =
+            serde_json::from_value(json!({"values": {"PORT": 8080, "DEBUG": true, "NAME": "api"}}))
+                .unwrap();
+        assert_eq!(request.values["PORT"], "8080");
+        assert_eq!(request.values["DEBUG"], "true");
+        assert!(serde_json::from_value::<Request>(json!({"values": {"LIST": [1]}})).is_err());
+    }
+}
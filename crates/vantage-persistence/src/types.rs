@@ -1,12 +1,42 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use utoipa::ToSchema;
 
 /// Generate a unique ID for templates and clipboard items
 pub fn generate_id() -> String {
     nanoid::nanoid!()
 }
 
+/// プロセス定義・スナップショットのファイル形式
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigFormat {
+    #[default]
+    Yaml,
+    Kdl,
+}
+
+impl ConfigFormat {
+    /// ファイルの拡張子から形式を決める（`.kdl` 以外はYAML）
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("kdl") => Self::Kdl,
+            _ => Self::Yaml,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Kdl => "kdl",
+        }
+    }
+}
+
 /// Process state in the lifecycle
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -64,6 +94,10 @@ pub struct ProcessInfo {
     /// Environment variables
     pub env: HashMap<String, String>,
 
+    /// Encrypted environment variables (name to ciphertext, decrypted only at spawn)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub sensitive_env: HashMap<String, String>,
+
     /// Working directory
     pub cwd: Option<String>,
 
@@ -81,10 +115,794 @@ pub struct ProcessInfo {
 
     /// Whether to auto-start on restore
     pub auto_start_on_restore: bool,
+
+    /// Idle detection settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_detection: Option<IdleDetectionConfig>,
+
+    /// Health check on the error rate and stderr ratio of the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<HealthCheckConfig>,
+
+    /// Launch inside a tmux session instead of as a direct child process
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmux: Option<TmuxConfig>,
+
+    /// Treat the process as a long-running REPL that accepts code on stdin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repl: Option<ReplConfig>,
+
+    /// Additional log files or FIFOs merged into the output buffers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_sources: Vec<LogSource>,
+
+    /// Run the process as a different local user/group (Unix only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as: Option<RunAsConfig>,
+
+    /// Hard resource limits enforced through the process's cgroup (Linux cgroup v2)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_limits: Option<ResourceLimits>,
+
+    /// Run the command inside a container, optionally building the image first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerConfig>,
+
+    /// Where the process was imported from (re-import updates it in place)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ImportSource>,
+
+    /// External sinks receiving this process's output in addition to the global sinks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_sinks: Vec<LogSinkConfig>,
+
+    /// Redaction rules added or disabled for this process's output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<RedactionOverride>,
+
+    /// Arbitrary key/value metadata (owner, ticket URL, runbook link)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+
+    /// Recovery steps shown next to the process (a Markdown file or inline text)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runbook: Option<Runbook>,
+
+    /// Start priority, parallel group and readiness port used by auto-start and bulk start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup: Option<StartupOrder>,
+
+    /// Listen on a port and start the process on the first incoming connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lazy_start: Option<LazyStartConfig>,
+
+    /// Load the project's environment (mise, direnv or asdf) from the working directory at spawn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_loader: Option<EnvLoader>,
+
+    /// Record the full timestamped output of each run into a replayable file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_recording: Option<OutputRecordingConfig>,
+
+    /// Expected behavior checked by `run_checks` (ready in time, no error output, exit code)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<ProcessAssertion>,
+
+    /// Restart automatically when the process exits (never, on failure or always)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_policy: Option<RestartPolicy>,
+
+    /// Named group started and stopped together with start_group/stop_group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+
+    /// Processes started (and waited for) before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<ProcessDependency>,
+
+    /// Per-run parameters substituted into `{{name}}` placeholders in args
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<ProcessParameter>,
+
+    /// Keep stdin open as a pipe so input can be sent to the running process
+    #[serde(default)]
+    pub keep_stdin_open: bool,
+}
+
+/// アイドル検知の設定
+///
+/// 出力が途絶え、かつCPU使用率がしきい値未満の状態が `timeout_secs` 続いた場合に
+/// プロセスをアイドルとみなします。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct IdleDetectionConfig {
+    /// アイドルとみなすまでの無出力時間（秒）
+    pub timeout_secs: u64,
+    /// この値（%）未満のCPU使用率をアイドルとみなす
+    #[serde(default = "default_idle_cpu_threshold")]
+    pub cpu_threshold_percent: f32,
+    /// アイドル検知時のアクション
+    #[serde(default)]
+    pub action: IdleAction,
+}
+
+fn default_idle_cpu_threshold() -> f32 {
+    1.0
+}
+
+/// アイドル検知時のアクション
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleAction {
+    /// イベントを発行するのみ
+    #[default]
+    Notify,
+    /// プロセスを再起動する
+    Restart,
+}
+
+/// ヘルスチェックの設定
+///
+/// 直近 `window_secs` の出力を集計し、エラーの行の頻度やstderrの割合がしきい値を超えた場合に
+/// プロセスを異常とみなします。クラッシュせずに劣化していくサービスを検知するためのものです。
+/// `probe` を指定すると、`interval_secs` ごとにHTTP・TCP・コマンドで応答を確かめ、
+/// `failure_threshold` 回続けて失敗した場合も異常とみなします。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct HealthCheckConfig {
+    /// エラーの行（ERROR・panic・Traceback等）が1分あたりこの件数を超えたら異常
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_errors_per_minute: Option<f64>,
+    /// 出力の行のうちstderrの割合（0〜1）がこの値を超えたら異常
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_stderr_ratio: Option<f64>,
+    /// 集計する期間（秒、10の倍数）
+    #[serde(default = "default_health_window_secs")]
+    pub window_secs: u64,
+    /// 応答を確かめるプローブ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe: Option<HealthProbe>,
+    /// プローブの間隔（秒）
+    #[serde(default = "default_probe_interval_secs")]
+    pub interval_secs: u64,
+    /// プローブ1回のタイムアウト（秒）
+    #[serde(default = "default_probe_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 異常とみなす、プローブが続けて失敗した回数
+    #[serde(default = "default_probe_failure_threshold")]
+    pub failure_threshold: u32,
+    /// 異常と判定したときのアクション
+    #[serde(default)]
+    pub action: HealthAction,
+}
+
+fn default_health_window_secs() -> u64 {
+    300
+}
+
+fn default_probe_interval_secs() -> u64 {
+    10
+}
+
+fn default_probe_timeout_secs() -> u64 {
+    5
+}
+
+fn default_probe_failure_threshold() -> u32 {
+    3
+}
+
+/// ヘルスチェックのプローブ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HealthProbe {
+    /// URLにGETし、ステータスコードを確かめる（省略時は2xx・3xxを正常とする）
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected_status: Option<u16>,
+    },
+    /// ポートに接続できるかを確かめる（ホストの省略時は127.0.0.1）
+    Tcp {
+        port: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        host: Option<String>,
+    },
+    /// プロセスの作業ディレクトリでコマンドを実行し、終了コード0を正常とする
+    Exec {
+        command: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+    },
+}
+
+/// ヘルスチェックで異常と判定したときのアクション
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthAction {
+    /// イベントを発行するのみ
+    #[default]
+    Notify,
+    /// プロセスを再起動する
+    Restart,
+}
+
+/// tmuxバックエンドの設定
+///
+/// プロセスを指定したセッションのウィンドウ内で起動し、ターミナルから接続できるようにします。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct TmuxConfig {
+    /// セッション名（存在しなければ作成）
+    pub session: String,
+    /// ウィンドウ名（省略時はプロセスID）
+    #[serde(default)]
+    pub window: Option<String>,
+}
+
+/// 追加のログソース
+///
+/// 標準出力以外にログを書き出すプロセスのために、ファイルや名前付きパイプ（FIFO）を追従して
+/// 出力バッファに取り込みます。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct LogSource {
+    /// 追従するファイルまたはFIFOのパス（相対パスは作業ディレクトリ基準）
+    pub path: String,
+    /// 取り込んだ行に付けるラベル（省略時はファイル名）
+    #[serde(default)]
+    pub label: Option<String>,
+    /// 標準出力ではなく標準エラー出力のバッファに取り込む
+    #[serde(default)]
+    pub stderr: bool,
+}
+
+/// REPLプロセスの設定
+///
+/// 標準入力からコードを受け付けるインタプリタ（`python -i`、`node -i`、シェルなど）として扱い、
+/// 実行ごとに完了マーカーを出力させることで出力の区切りと実行中・待機中の状態を判定します。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ReplConfig {
+    /// インタプリタの種類（完了マーカーを出力する文の書き方が決まる）
+    pub language: ReplLanguage,
+    /// 完了マーカーを出力するコード（`{marker}` がマーカーに置換される。customでは必須）
+    #[serde(default)]
+    pub marker_command: Option<String>,
+    /// 出力から取り除くプロンプト（省略時は言語ごとの既定値）
+    #[serde(default)]
+    pub prompts: Vec<String>,
+}
+
+/// REPLのインタプリタの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplLanguage {
+    Python,
+    Node,
+    Shell,
+    Custom,
+}
+
+/// 別のローカルアカウントでプロセスを起動する設定（Unixのみ）
+///
+/// 起動時に setgid / setuid で指定したユーザー・グループに切り替えます。
+/// 現在のユーザー以外に切り替えるにはサーバーをrootで実行している必要があります。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct RunAsConfig {
+    /// ユーザー名またはUID
+    pub user: String,
+    /// グループ名またはGID（省略時はユーザーのプライマリグループ）
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+/// cgroupで課すリソース制限（Linuxのcgroup v2のみ）
+///
+/// プロセスごとのcgroupの `memory.max` / `cpu.max` / `pids.max` に書き込みます。
+/// 子プロセスも含めたプロセスツリー全体に適用されます。
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ResourceLimits {
+    /// メモリ使用量の上限（バイト）。超えるとOOM killerで強制終了される
+    #[serde(default)]
+    pub memory_max_bytes: Option<u64>,
+    /// CPU使用率の上限（%。100で1コア分）
+    #[serde(default)]
+    pub cpu_max_percent: Option<u32>,
+    /// プロセス（スレッド）数の上限
+    #[serde(default)]
+    pub pids_max: Option<u64>,
+}
+
+/// コンテナで実行する設定
+///
+/// `<engine> run --rm` でイメージを実行します。プロセスの `command` / `args` はコンテナ内で実行する
+/// コマンドになります（`command` が空ならイメージのCMD）。`build` を指定すると、起動のたびに
+/// 先にイメージをビルドします。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ContainerConfig {
+    /// コンテナエンジン
+    #[serde(default)]
+    pub engine: ContainerEngine,
+    /// 実行する（ビルドする場合は作成する）イメージのタグ
+    pub image: String,
+    /// 起動前のイメージのビルド
+    #[serde(default)]
+    pub build: Option<ImageBuildConfig>,
+    /// `run` に渡す追加の引数（例: `["-p", "8080:80"]`）
+    #[serde(default)]
+    pub run_args: Vec<String>,
+}
+
+/// コンテナエンジン
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerEngine {
+    #[default]
+    Docker,
+    Podman,
+}
+
+/// イメージのビルド設定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ImageBuildConfig {
+    /// ビルドコンテキスト（相対パスは作業ディレクトリ基準）
+    #[serde(default = "default_build_context")]
+    pub context: String,
+    /// Dockerfileのパス（省略時はコンテキスト内のDockerfile）
+    #[serde(default)]
+    pub dockerfile: Option<String>,
+    /// `--build-arg` に渡す値
+    #[serde(default)]
+    pub build_args: HashMap<String, String>,
+    /// ビルドコンテキストのファイルが変更されたら再ビルドして再起動する
+    #[serde(default)]
+    pub rebuild_on_change: bool,
+}
+
+fn default_build_context() -> String {
+    ".".to_string()
+}
+
+/// ログの転送先
+///
+/// 取り込んだ標準出力・標準エラー出力の各行を、プロセスID・ストリーム・ワークスペースの
+/// ラベルを付けて外部に送ります。設定の `log_sinks` はすべてのプロセスに、プロセスの
+/// `log_sinks` はそのプロセスのみに適用されます。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct LogSinkConfig {
+    /// 転送先の名前（診断情報での表示と、同じ転送先の共有に使う）
+    pub name: String,
+    /// 転送先の種類
+    pub kind: LogSinkKind,
+    /// 送り先（syslog: `host:port`、loki: push APIのURL、jsonl: ファイルのパス）
+    pub target: String,
+    /// すべての行に追加するラベル
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+/// ログの転送先の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSinkKind {
+    /// RFC 5424形式のsyslogをUDPで送る
+    Syslog,
+    /// Grafana Lokiのpush API（`/loki/api/v1/push`）に送る
+    Loki,
+    /// 1行1JSONでファイルに追記する
+    Jsonl,
+}
+
+/// 出力を伏せるルール
+///
+/// 取り込んだ出力の各行に、バッファへの記録・ログシンクへの転送より前に適用します。
+/// 設定の `redaction_rules` はすべてのプロセスに、プロセスの `redaction` はそのプロセスのみに適用されます。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct RedactionRule {
+    /// ルールの名前（プロセスごとに無効にするときに指定する）
+    pub name: String,
+    /// 伏せる部分に一致する正規表現
+    pub pattern: String,
+    /// 置き換える文字列（`$1` などでキャプチャを残せる、省略時は `[REDACTED]`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask: Option<String>,
+}
+
+/// プロセスの手順書（障害時の復旧手順などのMarkdown）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Runbook {
+    /// Markdownファイルのパス（相対パスはプロセスの作業ディレクトリから解決する）
+    Path(String),
+    /// 直接書いたMarkdown
+    Inline(String),
+}
+
+/// 自動起動・一括起動での起動順序
+///
+/// `priority` の小さい順に起動し、同じ優先度で同じ `group` のプロセスは並列に起動します。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct StartupOrder {
+    /// 起動の優先度（小さいほど先に起動する、既定は0）
+    #[serde(default)]
+    pub priority: i32,
+    /// 並列に起動するグループ（未指定の場合は1つずつ起動する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// 接続を受け付けたら準備完了とみなすポート（未指定の場合は起動した時点で準備完了）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready_port: Option<u16>,
+    /// 準備完了を待つ秒数（既定は30秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready_timeout_secs: Option<u64>,
+}
+
+/// 最初の接続で起動する（遅延起動）設定
+///
+/// Vantageが `listen_port` で待ち受け、最初の接続が来たらプロセスを起動し、
+/// `backend_port` で接続を受け付けるようになってから中継します。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct LazyStartConfig {
+    /// Vantageが待ち受けるポート（クライアントが接続するポート）
+    pub listen_port: u16,
+    /// 待ち受けるアドレス（既定は127.0.0.1）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// プロセスが実際に待ち受けるポート（未指定の場合は空いているポートを選ぶ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_port: Option<u16>,
+    /// `backend_port` をプロセスに渡す環境変数（既定はPORT）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port_env: Option<String>,
+    /// 起動してから接続を受け付けるまで待つ秒数（既定は30秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready_timeout_secs: Option<u64>,
+    /// 接続がない状態がこの秒数続いたら停止する（未指定の場合は停止しない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_stop_secs: Option<u64>,
+}
+
+/// 出力の記録の設定
+///
+/// 起動のたびに、stdout・stderr（と `include_stdin` の場合は標準入力）のすべての行を
+/// 時刻付きで記録ファイルに書き出します。記録は `replay_output` で元の速さか早送りで再生できます。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct OutputRecordingConfig {
+    /// REPLプロセスに送ったコード（標準入力）も記録する
+    #[serde(default)]
+    pub include_stdin: bool,
+    /// 残す記録の数（既定は10、古いものから削除する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep: Option<usize>,
+}
+
+/// プロセスの振る舞いについての確認項目
+///
+/// `run_checks` がプロセスを起動し、起動からの経過時間で各項目を評価します。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProcessAssertion {
+    /// `within_secs` 秒以内に準備完了になる
+    ///
+    /// `port` に接続できるか、出力のいずれかの行が `pattern`（正規表現）に一致すれば準備完了。
+    /// どちらも指定しなければ、起動して実行中であれば準備完了とみなす。
+    ReadyWithin {
+        within_secs: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        port: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pattern: Option<String>,
+    },
+    /// 起動から `within_secs` 秒の間、`pattern`（正規表現）に一致する行を出力しない
+    NoOutputMatch {
+        pattern: String,
+        within_secs: u64,
+        /// stderrだけを対象にする
+        #[serde(default)]
+        stderr_only: bool,
+    },
+    /// 終了コード `code` で終了する（`within_secs` を指定するとその秒数以内に）
+    ExitCode {
+        code: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        within_secs: Option<u64>,
+    },
+}
+
+/// 終了したプロセスを自動で再起動する方針
+///
+/// 停止要求による終了とメンテナンスウィンドウ中の終了では再起動しません。再起動の前に
+/// `backoff_ms` から再試行ごとに倍になる時間（`max_backoff_ms` まで）待ちます。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct RestartPolicy {
+    /// 再起動する終了の種類
+    #[serde(default)]
+    pub mode: RestartMode,
+    /// 続けて再起動する回数の上限（省略時は無制限）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// 最初の再起動までの待ち時間（ミリ秒）
+    #[serde(default = "default_restart_backoff_ms")]
+    pub backoff_ms: u64,
+    /// 待ち時間の上限（ミリ秒）
+    #[serde(default = "default_restart_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+fn default_restart_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_restart_max_backoff_ms() -> u64 {
+    60_000
+}
+
+/// 再起動する終了の種類
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartMode {
+    /// 再起動しない
+    #[default]
+    Never,
+    /// 終了コードが0以外か、終了を待てなかった場合
+    OnFailure,
+    /// 終了コードによらず常に
+    Always,
+}
+
+/// 起動する前に準備完了を待つ別のプロセス
+///
+/// 依存先が実行中でなければ先に起動し、`ready` の条件を満たすまで（`timeout_secs` 秒まで）
+/// 待ってから依存元を起動します。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ProcessDependency {
+    /// 先に起動するプロセスのID
+    pub process_id: String,
+    /// 準備完了とみなす条件（既定は起動した時点）
+    #[serde(default)]
+    pub ready: DependencyReady,
+    /// 準備完了を待つ秒数（既定は30秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// 起動ごとに値を指定する引数のパラメーター
+///
+/// `args` の `{{name}}` を `start_process_with_params` で指定した値（指定されなければ
+/// `default`）に置き換えます。置き換えるのはその起動だけで、定義の引数は変わりません。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ProcessParameter {
+    /// パラメーター名（`{{name}}` の形式で引数に書く）
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 指定されなかったときの値（無ければ空。引数全体が `{{name}}` なら引数ごと省く）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// 起動のたびに指定が必要か
+    #[serde(default)]
+    pub required: bool,
+    /// 値全体が一致しなければならない正規表現
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+/// 依存先を準備完了とみなす条件
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DependencyReady {
+    /// 起動して実行中になった時点
+    #[default]
+    Started,
+    /// ヘルスチェックが正常と判定した時点（依存先に `health_check` が必要）
+    Healthy,
+    /// 今回の起動の出力のいずれかの行が `pattern`（正規表現）に一致した時点
+    LogPattern { pattern: String },
+}
+
+/// プロセスを起動するときにプロジェクトの環境を読み込むツール
+///
+/// 作業ディレクトリで読み込んだ環境変数（ツールのバージョンを切り替えたPATHなど）を、
+/// 定義の環境変数より優先度の低いものとして子プロセスに渡します。
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvLoader {
+    /// 作業ディレクトリとその親から設定ファイルを探し、インストールされているツールを使う
+    #[default]
+    Auto,
+    /// `mise env` の環境（mise.toml・.tool-versions）
+    Mise,
+    /// `direnv export` の環境（.envrc、`direnv allow` 済みのもの）
+    Direnv,
+    /// asdfのshimをPATHの先頭に置く（.tool-versions）
+    Asdf,
+}
+
+/// プロセスごとの伏せるルールの上書き
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct RedactionOverride {
+    /// このプロセスでは適用しない設定のルールの名前
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
+    /// このプロセスのみに適用するルール
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RedactionRule>,
+}
+
+/// トンネル（ポートフォワード）の定義
+///
+/// `ssh -L` / `ssh -R` / cloudflaredのトンネルをプロセスと同じように起動・停止して管理します。
+/// `process_id` を指定すると、そのプロセスのステータスにトンネルの状態と公開URLが表示されます。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct TunnelConfig {
+    /// トンネルの一意識別子
+    pub tunnel_id: String,
+    /// トンネルの種類
+    pub provider: TunnelProvider,
+    /// ローカル側のポート（ssh_localでは待ち受けるポート、それ以外では公開するポート）
+    pub local_port: u16,
+    /// SSHの接続先（例: `user@bastion`。sshのみ）
+    #[serde(default)]
+    pub ssh_host: Option<String>,
+    /// リモート側（ssh_localでは転送先の `host:port`、ssh_remoteではSSHサーバーで待ち受ける `[bind:]port`）
+    #[serde(default)]
+    pub remote: Option<String>,
+    /// 状態と公開URLを表示するプロセス
+    #[serde(default)]
+    pub process_id: Option<String>,
+    /// 公開URL（cloudflaredのQuick Tunnelでは出力から自動的に取得）
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+/// トンネルの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TunnelProvider {
+    /// `ssh -L`: ローカルのポートをリモートの転送先に転送する
+    SshLocal,
+    /// `ssh -R`: ローカルのポートをSSHサーバー側で公開する
+    SshRemote,
+    /// `cloudflared tunnel --url`: ローカルのポートをCloudflare経由で公開する
+    Cloudflared,
+}
+
+/// Kubernetesのワークロード（読み取り専用の外部プロセス）
+///
+/// 開発クラスタで動いているPod・Deploymentを登録し、状態をクラスタから取得して、ログを
+/// 通常のプロセスと同じバッファに取り込みます。起動・停止はできません。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct KubernetesWorkload {
+    /// ワークロードの一意識別子（`get_process_output` でもこのIDを使う）
+    pub workload_id: String,
+    /// kubeconfigのコンテキスト（省略時は現在のコンテキスト）
+    #[serde(default)]
+    pub context: Option<String>,
+    /// 名前空間（省略時はコンテキストの名前空間）
+    #[serde(default)]
+    pub namespace: Option<String>,
+    pub kind: KubernetesWorkloadKind,
+    /// PodまたはDeploymentの名前
+    pub name: String,
+    /// ログを取り込むコンテナ（省略時はPodの既定のコンテナ）
+    #[serde(default)]
+    pub container: Option<String>,
+    /// ローカルのポートからPodへの転送
+    #[serde(default)]
+    pub port_forwards: Vec<KubernetesPortForward>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Kubernetesのワークロードの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KubernetesWorkloadKind {
+    Pod,
+    /// セレクターに一致するPodをまとめて扱う
+    Deployment,
+}
+
+/// ローカルのポートからPodのポートへの転送
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct KubernetesPortForward {
+    /// 127.0.0.1で待ち受けるポート
+    pub local_port: u16,
+    /// Pod側のポート
+    pub remote_port: u16,
+}
+
+/// プロセスの連鎖 - あるプロセスが正常終了したら次の処理を実行
+///
+/// `after` のプロセスが終了コード0で終了したときに `action` を実行します。
+/// ビルド→起動、マイグレーション→シードのような手順を一度だけ設定しておくために使います。
+/// 停止要求による終了では実行しません。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ProcessChain {
+    /// 連鎖の一意識別子
+    pub chain_id: String,
+    /// 終了を監視するプロセス
+    pub after: String,
+    /// 正常終了したときに実行する処理
+    pub action: ChainAction,
+    /// 無効にした連鎖は実行しない
+    #[serde(default = "default_chain_enabled")]
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_chain_enabled() -> bool {
+    true
+}
+
+/// 連鎖で実行する処理
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChainAction {
+    /// 登録済みのプロセスを起動する
+    StartProcess { process_id: String },
+    /// テンプレートからプロセスを作成して起動する（作成済みであれば起動のみ）
+    RunTemplate {
+        template_id: String,
+        process_id: String,
+        #[serde(default)]
+        values: HashMap<String, String>,
+    },
+}
+
+impl ChainAction {
+    /// 起動するプロセスのID
+    pub fn process_id(&self) -> &str {
+        match self {
+            ChainAction::StartProcess { process_id } => process_id,
+            ChainAction::RunTemplate { process_id, .. } => process_id,
+        }
+    }
+}
+
+/// プロセスの定期実行 - cron形式の時刻にプロセスを起動
+///
+/// バックアップやレポートの生成など、決まった時刻に終わるまで動かすプロセスのために使います。
+/// 時刻になってもプロセスが実行中であれば、その回は実行しません。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ProcessSchedule {
+    /// 定期実行の一意識別子
+    pub schedule_id: String,
+    /// 起動するプロセス
+    pub process_id: String,
+    /// cron形式（分 時 日 月 曜日、サーバーのローカル時刻）
+    pub cron: String,
+    /// 無効にした定期実行は実行しない
+    #[serde(default = "default_schedule_enabled")]
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_schedule_enabled() -> bool {
+    true
+}
+
+/// 外部ファイルから取り込んだプロセス・テンプレートの取り込み元
+///
+/// 再取り込み時に同じ取り込み元のエントリを更新するために使います。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ImportSource {
+    /// 取り込み元の種類（例: "vscode_tasks"）
+    pub kind: String,
+    /// 取り込み元ファイルのパス
+    pub path: String,
+    /// ファイル内でのエントリの識別子（例: タスクのラベル）
+    pub key: String,
 }
 
 /// プロセステンプレート - よく使うプロセス設定を保存して再利用
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ProcessTemplate {
     /// Optional unique record identifier
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -128,6 +946,10 @@ pub struct ProcessTemplate {
 
     /// タグ（検索・フィルタリング用）
     pub tags: Vec<String>,
+
+    /// 取り込み元（外部ファイルから取り込んだ場合）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ImportSource>,
 }
 
 impl ProcessTemplate {
@@ -142,8 +964,18 @@ impl ProcessTemplate {
         let mut args = self.args.clone();
         let mut env = self.env.clone();
 
+        // 指定されなかった変数にはデフォルト値を使う
+        let mut resolved = values.clone();
+        for var in &self.variables {
+            if let Some(default) = &var.default_value {
+                resolved
+                    .entry(var.name.clone())
+                    .or_insert_with(|| default.clone());
+            }
+        }
+
         // 変数置換処理
-        for (key, value) in &values {
+        for (key, value) in &resolved {
             let placeholder = format!("{{{{{key}}}}}");
 
             // コマンドの置換
@@ -167,6 +999,10 @@ impl ProcessTemplate {
                 return Err(format!("Required variable '{}' is missing", var.name));
             }
         }
+        let errors = crate::template_schema::validate_values(&self.variables, &values);
+        if !errors.is_empty() {
+            return Err(crate::template_schema::describe_errors(&errors));
+        }
 
         Ok(ProcessInfo {
             id: None,
@@ -188,6 +1024,30 @@ impl ProcessTemplate {
             updated_at: Utc::now(),
             tags: self.tags.clone(),
             auto_start_on_restore: self.default_auto_start,
+            idle_detection: None,
+            health_check: None,
+            tmux: None,
+            repl: None,
+            log_sources: Vec::new(),
+            run_as: None,
+            resource_limits: None,
+            container: None,
+            source: None,
+            log_sinks: Vec::new(),
+            redaction: None,
+            metadata: HashMap::new(),
+            runbook: None,
+            startup: None,
+            lazy_start: None,
+            env_loader: None,
+            output_recording: None,
+            assertions: Vec::new(),
+            restart_policy: None,
+            depends_on: Vec::new(),
+            parameters: Vec::new(),
+            keep_stdin_open: false,
+            group: None,
+            sensitive_env: HashMap::new(),
         })
     }
 }
@@ -211,12 +1071,13 @@ impl ProcessTemplate {
             created_at: now,
             updated_at: now,
             tags: Vec::new(),
+            source: None,
         }
     }
 }
 
 /// テンプレート変数の定義
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct TemplateVariable {
     /// 変数名（${VAR_NAME} の形式で使用）
     pub name: String,
@@ -235,10 +1096,14 @@ pub struct TemplateVariable {
 
     /// 値の例
     pub example: Option<String>,
+
+    /// 値のJSON Schema（型・列挙値・パターン・範囲）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<crate::template_schema::VariableSchema>,
 }
 
 /// クリップボードアイテム - ファイルやテキストの共有
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ClipboardItem {
     /// Optional unique record identifier
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -283,6 +1148,400 @@ impl ClipboardItem {
     }
 }
 
+/// セッションコンテキスト - 作業中の開発環境のスナップショット
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionContext {
+    /// コンテキスト名（例: "feature-x"）
+    pub name: String,
+
+    /// 保存日時
+    pub saved_at: DateTime<Utc>,
+
+    /// 作業ディレクトリ
+    pub workspace: Option<String>,
+
+    /// 保存時のGitブランチ
+    pub git_branch: Option<String>,
+
+    /// 保存時に実行中だったプロセスのID
+    pub running_processes: Vec<String>,
+
+    /// 直近のクリップボードアイテム（新しい順）
+    #[serde(default)]
+    pub clipboard: Vec<ClipboardItem>,
+
+    /// メモ
+    pub notes: Option<String>,
+}
+
+/// Webコンソールのロール（権限の弱い順）
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// 閲覧のみ
+    Viewer,
+    /// プロセスの操作が可能
+    Operator,
+    /// 設定の変更も可能
+    Admin,
+}
+
+/// 権限を分ける機能
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// クリップボード
+    Clipboard,
+    /// テンプレート
+    Templates,
+    /// プロセスの定義と操作
+    Processes,
+    /// 環境変数などに含まれるシークレットの値
+    Secrets,
+    /// サーバー自身の運用（Webサーバー・サーバーの再起動、ログのローテーション）
+    Server,
+}
+
+/// 機能へのアクセス権（弱い順）
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    None,
+    Read,
+    Write,
+}
+
+/// 機能ごとのアクセス権
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Permissions {
+    pub clipboard: Access,
+    pub templates: Access,
+    pub processes: Access,
+    /// `read` 未満ではシークレットらしき値を伏せて返し、`write` 未満ではシークレットを
+    /// 含む環境変数を設定できない
+    pub secrets: Access,
+    /// `write` でサーバー自身を再起動したり、ログをローテーションしたりできる
+    pub server: Access,
+}
+
+impl Permissions {
+    /// すべての機能に同じアクセス権
+    pub fn all(access: Access) -> Self {
+        Self {
+            clipboard: access,
+            templates: access,
+            processes: access,
+            secrets: access,
+            server: access,
+        }
+    }
+
+    /// ロールの既定のアクセス権
+    pub fn for_role(role: Role) -> Self {
+        match role {
+            Role::Viewer => Self {
+                secrets: Access::None,
+                server: Access::None,
+                ..Self::all(Access::Read)
+            },
+            Role::Operator => Self {
+                server: Access::Read,
+                ..Self::all(Access::Write)
+            },
+            Role::Admin => Self::all(Access::Write),
+        }
+    }
+
+    pub fn get(&self, capability: Capability) -> Access {
+        match capability {
+            Capability::Clipboard => self.clipboard,
+            Capability::Templates => self.templates,
+            Capability::Processes => self.processes,
+            Capability::Secrets => self.secrets,
+            Capability::Server => self.server,
+        }
+    }
+
+    pub fn set(&mut self, capability: Capability, access: Access) {
+        match capability {
+            Capability::Clipboard => self.clipboard = access,
+            Capability::Templates => self.templates = access,
+            Capability::Processes => self.processes = access,
+            Capability::Secrets => self.secrets = access,
+            Capability::Server => self.server = access,
+        }
+    }
+
+    /// 機能に `access` 以上のアクセス権があるか
+    pub fn allows(&self, capability: Capability, access: Access) -> bool {
+        self.get(capability) >= access
+    }
+
+    /// 設定による上書きを適用
+    pub fn with_overrides(mut self, overrides: &HashMap<Capability, Access>) -> Self {
+        for (capability, access) in overrides {
+            self.set(*capability, *access);
+        }
+        self
+    }
+}
+
+/// Webコンソールの認証とアクセス権の設定（設定ディレクトリの auth.yaml）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// OIDCプロバイダー（未設定の場合は認証なし）
+    #[serde(default)]
+    pub oidc: Option<OidcProviderConfig>,
+
+    /// ログインセッションの有効期間（秒）
+    #[serde(default = "default_session_ttl_secs")]
+    pub session_ttl_secs: u64,
+
+    /// ロールごとのアクセス権の上書き（例: `viewer: { clipboard: write }`）
+    #[serde(default)]
+    pub role_permissions: HashMap<Role, HashMap<Capability, Access>>,
+
+    /// MCPクライアントのアクセス権の上書き（既定はすべて `write`）
+    #[serde(default)]
+    pub mcp_permissions: HashMap<Capability, Access>,
+
+    /// 外部のシステムから `/api/hooks/<name>` で呼び出すWebhook
+    #[serde(default)]
+    pub hooks: Vec<WebhookConfig>,
+
+    /// 外部のスクリプトが `/api/gate/<process_id>` で準備完了を待つゲート
+    #[serde(default)]
+    pub gates: Vec<GateConfig>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            oidc: None,
+            session_ttl_secs: default_session_ttl_secs(),
+            role_permissions: HashMap::new(),
+            mcp_permissions: HashMap::new(),
+            hooks: Vec::new(),
+            gates: Vec::new(),
+        }
+    }
+}
+
+impl AuthConfig {
+    /// ログインしたユーザーのアクセス権
+    pub fn permissions_for_role(&self, role: Role) -> Permissions {
+        let permissions = Permissions::for_role(role);
+        match self.role_permissions.get(&role) {
+            Some(overrides) => permissions.with_overrides(overrides),
+            None => permissions,
+        }
+    }
+
+    /// MCPクライアントのアクセス権
+    pub fn mcp_permissions(&self) -> Permissions {
+        Permissions::all(Access::Write).with_overrides(&self.mcp_permissions)
+    }
+}
+
+/// ゲートの設定
+///
+/// gitフック・スクリプト・他のエージェントなど、MCPを使わない外部のツールがプロセスの
+/// 準備完了を待つためのもの。呼び出しにはゲートごとのトークンが必要で、トークンの無いゲートは無効。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateConfig {
+    /// 状態を返すプロセス（`/api/gate/<process_id>`）
+    pub process_id: String,
+
+    /// 呼び出しに必要なトークン
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// トークンを読み込む環境変数名（tokenより優先）
+    #[serde(default)]
+    pub token_env: Option<String>,
+}
+
+/// Webhookの設定
+///
+/// CI・cron・ホームオートメーションなど、ログインできない外部のシステムから決まった操作を
+/// 呼び出すためのもの。呼び出しにはWebhookごとのトークンが必要で、トークンの無いWebhookは無効。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// URLの一部になる名前（`/api/hooks/<name>`）
+    pub name: String,
+
+    /// 呼び出しに必要なトークン
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// トークンを読み込む環境変数名（tokenより優先）
+    #[serde(default)]
+    pub token_env: Option<String>,
+
+    /// 呼び出されたときの操作
+    #[serde(flatten)]
+    pub action: WebhookAction,
+
+    /// ペイロードの検証（JSON Pointer → 値のスキーマ）
+    #[serde(default)]
+    pub payload_schema: BTreeMap<String, PayloadField>,
+
+    /// 呼び出しの回数の上限
+    #[serde(default)]
+    pub rate_limit: WebhookRateLimit,
+}
+
+/// Webhookの操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum WebhookAction {
+    /// プロセスを起動する（起動中なら何もしない）
+    StartProcess { process_id: String },
+    /// プロセスを再起動する（停止中なら起動する）
+    RestartProcess { process_id: String },
+    /// テンプレートからプロセスを作成して起動する
+    InstantiateTemplate {
+        template_id: String,
+        /// 作成するプロセスのID（`{{変数名}}` を変数の値で置き換える）
+        process_id: String,
+        /// テンプレート変数 → 値を取り出すペイロードのJSON Pointer（例: `BRANCH: /ref`）
+        #[serde(default)]
+        variables: BTreeMap<String, String>,
+    },
+}
+
+/// ペイロードの1つの値のスキーマ
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PayloadField {
+    /// 値が無い（またはnull）ときに拒否する
+    #[serde(default)]
+    pub required: bool,
+
+    #[serde(flatten)]
+    pub schema: crate::template_schema::VariableSchema,
+}
+
+/// Webhookの呼び出しの回数の上限（`per_secs` 秒あたり `max_requests` 回、トークンの誤りも数える）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookRateLimit {
+    pub max_requests: u32,
+    pub per_secs: u64,
+}
+
+impl Default for WebhookRateLimit {
+    fn default() -> Self {
+        Self {
+            max_requests: 10,
+            per_secs: 60,
+        }
+    }
+}
+
+fn default_session_ttl_secs() -> u64 {
+    8 * 60 * 60 // 8 hours
+}
+
+/// OIDCプロバイダーの設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcProviderConfig {
+    /// Issuer URL（/.well-known/openid-configuration の取得元）
+    pub issuer_url: String,
+    pub client_id: String,
+
+    /// クライアントシークレット
+    #[serde(default)]
+    pub client_secret: Option<String>,
+
+    /// クライアントシークレットを読み込む環境変数名（client_secretより優先）
+    #[serde(default)]
+    pub client_secret_env: Option<String>,
+
+    /// コールバックURL（例: https://devbox.example.com/auth/callback）
+    pub redirect_url: String,
+
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>,
+
+    /// ロールの判定に使うクレーム（文字列または文字列の配列）
+    #[serde(default = "default_role_claim")]
+    pub role_claim: String,
+
+    /// クレームの値からロールへの対応
+    #[serde(default)]
+    pub role_mapping: HashMap<String, Role>,
+
+    /// どの値にも一致しない場合のロール（未設定ならログインを拒否）
+    #[serde(default)]
+    pub default_role: Option<Role>,
+}
+
+fn default_oidc_scopes() -> Vec<String> {
+    vec![
+        "openid".to_string(),
+        "profile".to_string(),
+        "email".to_string(),
+    ]
+}
+
+fn default_role_claim() -> String {
+    "groups".to_string()
+}
+
+/// Webコンソールのログインセッション
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthSession {
+    /// セッションID（Cookieの値）
+    pub session_id: String,
+
+    /// 認証したプロバイダー名
+    pub provider: String,
+
+    /// ユーザーの識別子（subクレーム）
+    pub subject: String,
+
+    /// 表示名（name / email クレーム）
+    pub display_name: Option<String>,
+
+    pub role: Role,
+
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+
+    /// アクセストークンの有効期限
+    pub token_expires_at: Option<DateTime<Utc>>,
+
+    pub created_at: DateTime<Utc>,
+
+    /// セッションの有効期限
+    pub expires_at: DateTime<Utc>,
+}
+
 /// Settings stored in database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
@@ -293,6 +1552,47 @@ pub struct Settings {
     pub default_shell: Option<String>,
     pub env_variables: HashMap<String, String>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub log_quota: LogQuotaSettings,
+    /// 提案プロバイダーごとの設定（未設定のプロバイダーは有効・重み1.0）
+    #[serde(default)]
+    pub suggestion_providers: HashMap<String, SuggestionProviderSettings>,
+    /// mDNSによるWebコンソールの告知
+    #[serde(default)]
+    pub mdns: MdnsSettings,
+    /// 障害注入（カオステスト）
+    #[serde(default)]
+    pub chaos: ChaosSettings,
+    /// ワークスペース（作業ディレクトリ）ごとのクォータ
+    #[serde(default)]
+    pub workspace_quotas: Vec<WorkspaceQuota>,
+    /// 実行前に人の承認を必要とする操作
+    #[serde(default)]
+    pub approvals: ApprovalSettings,
+    /// すべてのプロセスの出力の転送先
+    #[serde(default)]
+    pub log_sinks: Vec<LogSinkConfig>,
+    /// すべてのプロセスの出力に適用する伏せるルール
+    #[serde(default)]
+    pub redaction_rules: Vec<RedactionRule>,
+    /// 自動再起動やヘルス通知を止めるメンテナンスウィンドウ
+    #[serde(default)]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    /// MCPツールの応答の大きさの上限
+    #[serde(default)]
+    pub response_budget: ResponseBudget,
+    /// CPU時間から消費電力量と電気代を見積もる係数
+    #[serde(default)]
+    pub energy_cost: EnergyCostSettings,
+    /// 外部で起動した一致するプロセスを自動で引き取るワークスペース（絶対パス）
+    #[serde(default)]
+    pub auto_adopt_workspaces: Vec<String>,
+    /// 出力バッファのメモリ使用量の上限と、超えたときの縮小の仕方
+    #[serde(default)]
+    pub output_memory: OutputMemorySettings,
+    /// 通知の送り先のルール（上から順に評価する）
+    #[serde(default)]
+    pub notification_routes: Vec<NotificationRoute>,
 }
 
 impl Default for Settings {
@@ -305,6 +1605,548 @@ impl Default for Settings {
             default_shell: None,
             env_variables: HashMap::new(),
             updated_at: Utc::now(),
+            log_quota: LogQuotaSettings::default(),
+            suggestion_providers: HashMap::new(),
+            mdns: MdnsSettings::default(),
+            chaos: ChaosSettings::default(),
+            workspace_quotas: Vec::new(),
+            approvals: ApprovalSettings::default(),
+            log_sinks: Vec::new(),
+            redaction_rules: Vec::new(),
+            maintenance_windows: Vec::new(),
+            response_budget: ResponseBudget::default(),
+            energy_cost: EnergyCostSettings::default(),
+            auto_adopt_workspaces: Vec::new(),
+            output_memory: OutputMemorySettings::default(),
+            notification_routes: Vec::new(),
+        }
+    }
+}
+
+/// 提案プロバイダーの有効化と重み
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SuggestionProviderSettings {
+    pub enabled: bool,
+    /// 信頼度に掛ける重み
+    pub weight: f64,
+}
+
+impl Default for SuggestionProviderSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weight: 1.0,
+        }
+    }
+}
+
+/// mDNS（_vantage._tcp）によるWebコンソールの告知設定
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MdnsSettings {
+    pub enabled: bool,
+    /// 告知するインスタンス名（未設定なら "Vantage on <ホスト名>"）
+    #[serde(default)]
+    pub instance_name: Option<String>,
+}
+
+/// 障害注入（カオステスト）の設定
+///
+/// 有効にしない限り、プロセスの強制終了や一時停止などの障害注入ツールは実行できません。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ChaosSettings {
+    pub enabled: bool,
+}
+
+/// 承認ワークフローの設定
+///
+/// ここに挙げたMCPツールは呼び出されてもすぐには実行されず、Webコンソールで承認されてから実行されます。
+/// `tools` の呼び出しは承認待ちとしてすぐに返り、`confirm_tools` の呼び出しは判断が出るか
+/// タイムアウトするまで待ちます。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ApprovalSettings {
+    /// 承認を必要とするツール名
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// 呼び出しを止めてWebコンソールでの確認を待つツール名
+    #[serde(default)]
+    pub confirm_tools: Vec<String>,
+    /// 確認を待つ秒数（省略時は300秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm_timeout_secs: Option<u64>,
+}
+
+/// メンテナンスウィンドウ
+///
+/// 期間中は対象のプロセスの自動再起動・アイドル検知の通知と処置・クラッシュループの検知を止めます。
+/// 対象（`processes` / `tags` / `workspaces`）をすべて空にすると全プロセスが対象になります。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct MaintenanceWindow {
+    /// ウィンドウの名前（一意）
+    pub name: String,
+    /// 期間
+    pub schedule: MaintenanceSchedule,
+    /// 対象のプロセスID
+    #[serde(default)]
+    pub processes: Vec<String>,
+    /// 対象のタグ（いずれかを持つプロセス）
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 対象のワークスペース（作業ディレクトリがこの絶対パス以下のプロセス）
+    #[serde(default)]
+    pub workspaces: Vec<String>,
+    /// 理由（ステータスやイベントに表示する）
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// メンテナンスウィンドウの期間
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaintenanceSchedule {
+    /// 1回限り（`start` から `end` まで）
+    Once {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+    /// cron形式（分 時 日 月 曜日、サーバーのローカル時刻）の時刻に始まり、`duration_minutes` 分続く
+    Recurring { cron: String, duration_minutes: u32 },
+}
+
+/// 承認リクエストの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    /// 承認待ち
+    Pending,
+    /// 承認済み（実行中）
+    Approved,
+    /// 却下
+    Denied,
+    /// 承認後に実行が成功した
+    Executed,
+    /// 承認後に実行が失敗した
+    Failed,
+    /// 確認を待つ間に判断されなかった（またはエージェントが取り消した）
+    Expired,
+}
+
+impl ApprovalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Denied => "denied",
+            Self::Executed => "executed",
+            Self::Failed => "failed",
+            Self::Expired => "expired",
+        }
+    }
+}
+
+/// 承認リクエスト（判断と実行結果を含む監査記録）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ApprovalRequest {
+    pub approval_id: String,
+    /// 要求されたツール名
+    pub tool: String,
+    /// ツールの引数
+    pub arguments: serde_json::Value,
+    /// 承認者に表示する操作の説明
+    pub summary: String,
+    pub status: ApprovalStatus,
+    pub requested_at: DateTime<Utc>,
+    #[serde(default)]
+    pub decided_at: Option<DateTime<Utc>>,
+    /// 判断したユーザー（認証なしの場合は "web-console"）
+    #[serde(default)]
+    pub decided_by: Option<String>,
+    /// 判断の理由
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub executed_at: Option<DateTime<Utc>>,
+    /// 実行結果またはエラー
+    #[serde(default)]
+    pub result: Option<String>,
+    /// エージェントの呼び出しが判断を待っている（確認が必要なツール）
+    #[serde(default)]
+    pub waiting: bool,
+    /// 判断を待つ期限（過ぎると `expired` になる）
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 通知の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// プロセスが失敗した（起動の失敗・異常終了）
+    ProcessFailed,
+    /// クラッシュループを検知した
+    CrashLoop,
+    /// ヘルスチェックで異常と判定された
+    ProcessUnhealthy,
+    /// 監視しているCIの実行が失敗した
+    CiFailed,
+    /// 操作が承認を待っている
+    ApprovalPending,
+}
+
+impl NotificationKind {
+    /// 種類ごとの重大度
+    pub fn severity(self) -> NotificationSeverity {
+        match self {
+            Self::CrashLoop => NotificationSeverity::Critical,
+            Self::ProcessFailed | Self::CiFailed => NotificationSeverity::Error,
+            Self::ProcessUnhealthy => NotificationSeverity::Warning,
+            Self::ApprovalPending => NotificationSeverity::Info,
+        }
+    }
+}
+
+/// 通知の重大度（低い順）
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationSeverity {
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+/// 通知の送り先のルール
+///
+/// 設定のルールを上から順に評価し、最初に一致したルールの送り先に送ります。条件はすべて
+/// 満たす必要があり、空の条件は何にでも一致します（条件のないルールはそれまでに一致しなかった
+/// すべての通知を受けます）。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct NotificationRoute {
+    /// ルールの名前（一意）
+    pub name: String,
+    /// 対象のタグ（いずれかを持つプロセスの通知）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 対象のワークスペース（作業ディレクトリがこの絶対パス以下のプロセスの通知）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<String>,
+    /// 対象の通知の種類
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<NotificationKind>,
+    /// この重大度以上の通知のみ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_severity: Option<NotificationSeverity>,
+    /// 送り先
+    pub channel: NotificationChannel,
+}
+
+/// 通知の送り先
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationChannel {
+    /// SlackのIncoming Webhook
+    Slack { webhook_url: String },
+    /// ローカルの `sendmail` で送るメール
+    Email {
+        to: Vec<String>,
+        /// 差出人（省略時はsendmailの既定）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from: Option<String>,
+    },
+    /// デスクトップ通知（Linuxは `notify-send`、macOSは `osascript`）
+    Desktop,
+    /// 受信箱に残すだけで外部には送らない
+    Inbox,
+}
+
+/// 受信箱の通知
+///
+/// イベントのうち利用者が後から確認すべきものを、既読・未読の状態とともに残します。
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Notification {
+    pub notification_id: String,
+    pub kind: NotificationKind,
+    /// 一覧に表示する見出し
+    pub title: String,
+    /// 詳細（エラーの内容など）
+    #[serde(default)]
+    pub message: String,
+    /// 関係するプロセス
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process_id: Option<String>,
+    /// 詳細を確認できる場所（CIの実行のURL、承認リクエストのIDなど）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub read: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_at: Option<DateTime<Utc>>,
+    /// 送り先を決めたルールの名前（一致するルールがなかった場合はNone）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+}
+
+/// ワークスペースごとのクォータ
+///
+/// 作業ディレクトリが `path` 以下にあるプロセスに適用します。0 は無制限を表します。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct WorkspaceQuota {
+    /// ワークスペースのディレクトリ（絶対パス）
+    pub path: String,
+    /// 登録できるプロセス数の上限
+    #[serde(default)]
+    pub max_processes: usize,
+    /// 同時に実行できるプロセス数の上限
+    #[serde(default)]
+    pub max_running: usize,
+    /// ログのディスク使用量の上限（バイト）。超えている間は起動できない
+    #[serde(default)]
+    pub max_log_bytes: u64,
+}
+
+/// MCPツールの応答の大きさの上限
+///
+/// コンテキストの小さいエージェント向けに、上限を超える応答は件数と先頭の要素に要約し、
+/// 省いた部分を示す印を入れます。0 は無制限を表します（既定）。
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+pub struct ResponseBudget {
+    /// 応答1件あたりの最大文字数
+    #[serde(default)]
+    pub max_chars: usize,
+    /// 配列に残す最大要素数
+    #[serde(default)]
+    pub max_items: usize,
+}
+
+/// 出力バッファのメモリ使用量の上限
+///
+/// すべてのプロセスの出力バッファの合計が上限を超えると、停止中のプロセスと出力の途絶えた
+/// プロセスのバッファを古い順に `min_buffer_lines` 行まで縮めます。`spill_to_disk` の場合、
+/// 縮めるときに捨てる行をログディレクトリに書き出します。合計が上限の3/4を下回ると元の大きさに戻します。
+/// `max_total_bytes` の0は無制限を表します。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct OutputMemorySettings {
+    /// すべての出力バッファの合計の上限（バイト）
+    #[serde(default = "default_output_memory_max_total_bytes")]
+    pub max_total_bytes: u64,
+    /// 縮めたバッファに残す行数
+    #[serde(default = "default_min_buffer_lines")]
+    pub min_buffer_lines: usize,
+    /// 実行中のプロセスを縮める対象にする、出力が途絶えてからの秒数
+    #[serde(default = "default_output_idle_after_secs")]
+    pub idle_after_secs: u64,
+    /// 縮めるときに捨てる行を `<log_dir>/<process_id>/spill-<stream>.log` に書き出す
+    #[serde(default)]
+    pub spill_to_disk: bool,
+}
+
+fn default_output_memory_max_total_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_min_buffer_lines() -> usize {
+    100
+}
+
+fn default_output_idle_after_secs() -> u64 {
+    300
+}
+
+impl Default for OutputMemorySettings {
+    fn default() -> Self {
+        Self {
+            max_total_bytes: default_output_memory_max_total_bytes(),
+            min_buffer_lines: default_min_buffer_lines(),
+            idle_after_secs: default_output_idle_after_secs(),
+            spill_to_disk: false,
+        }
+    }
+}
+
+/// 消費電力量と電気代の見積もりの係数
+///
+/// プロセスが使ったCPU時間（CPU秒）に、CPUコア1つを使い切ったときの消費電力を掛けて
+/// 消費電力量を見積もり、電気料金の単価から費用を求めます。実測ではなく目安です。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct EnergyCostSettings {
+    /// CPUコア1つを使い切ったときの消費電力（W）
+    #[serde(default = "default_watts_per_core")]
+    pub watts_per_core: f64,
+    /// 電気料金の単価（1kWhあたり）
+    #[serde(default = "default_price_per_kwh")]
+    pub price_per_kwh: f64,
+    /// 費用の通貨（表示用）
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+fn default_watts_per_core() -> f64 {
+    10.0
+}
+
+fn default_price_per_kwh() -> f64 {
+    0.30
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+impl Default for EnergyCostSettings {
+    fn default() -> Self {
+        Self {
+            watts_per_core: default_watts_per_core(),
+            price_per_kwh: default_price_per_kwh(),
+            currency: default_currency(),
         }
     }
 }
+
+/// ログディレクトリ（状態ディレクトリの logs）のディスク使用量クォータ
+///
+/// 0 は無制限を表します。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct LogQuotaSettings {
+    /// ログディレクトリ全体の上限（バイト）
+    pub max_total_bytes: u64,
+    /// プロセスごとの上限（バイト）
+    pub max_per_process_bytes: u64,
+}
+
+impl Default for LogQuotaSettings {
+    fn default() -> Self {
+        Self {
+            max_total_bytes: 512 * 1024 * 1024,      // 512MB
+            max_per_process_bytes: 64 * 1024 * 1024, // 64MB
+        }
+    }
+}
+
+/// 危険な操作の前に自動で取ったプロセス定義のスナップショット
+///
+/// データディレクトリの `pre-op/` にYAMLで保存し、直近の数件だけを残します。
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct PreOpSnapshot {
+    pub snapshot_id: String,
+    /// スナップショットを取るきっかけになった操作（ツール名）
+    pub operation: String,
+    /// 操作の対象（ファイルパスやフィルター式）
+    #[serde(default)]
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// スナップショットのファイル
+    pub path: String,
+    /// 退避したプロセス定義の数
+    pub process_count: usize,
+}
+
+/// ごみ箱に移したプロセス定義
+///
+/// `cleanup_processes` で片付けたプロセスは完全には削除せず、ここから元に戻せます。
+/// 直近の件数だけを残し、あふれた古いものから完全に削除します。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecycledProcess {
+    pub process: ProcessInfo,
+    pub recycled_at: DateTime<Utc>,
+    /// ごみ箱に移した操作（ツール名）
+    pub operation: String,
+    /// 操作の対象（絞り込みの式など）
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+/// 変更を記録する定義の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeTarget {
+    /// プロセス定義
+    Process,
+    /// Webコンソール・取り込みで作るテンプレート
+    Template,
+    /// テンプレート用データベース（SurrealDB）のテンプレート
+    SharedTemplate,
+}
+
+/// 定義の変更の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Created,
+    Updated,
+    Removed,
+}
+
+/// 定義の変更の記録（取り消し・やり直しに使う）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct DefinitionChange {
+    pub change_id: String,
+    pub target: ChangeTarget,
+    /// プロセスID・テンプレートID
+    pub target_id: String,
+    pub action: ChangeAction,
+    /// 変更前の定義（作成の場合はなし）
+    #[serde(default)]
+    pub before: Option<serde_json::Value>,
+    /// 変更後の定義（削除の場合はなし）
+    #[serde(default)]
+    pub after: Option<serde_json::Value>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// 取り消し・やり直しできる変更
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ChangeHistory {
+    /// 取り消せる変更（新しい順）
+    pub undo: Vec<DefinitionChange>,
+    /// やり直せる変更（次にやり直すものが先頭）
+    pub redo: Vec<DefinitionChange>,
+}
+
+/// 診断ルールの出所
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosisRuleSource {
+    /// 組み込みのルール
+    BuiltIn,
+    /// MCPサンプリングでクライアントのLLMが提案したルール
+    Sampling,
+}
+
+/// 診断ルールの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosisRuleStatus {
+    /// 診断に使う
+    Active,
+    /// ユーザーの確認待ち（診断には使わない）
+    Pending,
+    /// 却下された
+    Rejected,
+}
+
+/// エラー出力のシグネチャ（正規表現）と、その分類・対処
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct DiagnosisRule {
+    pub rule_id: String,
+    /// エラー出力の行に一致させる正規表現
+    pub pattern: String,
+    /// 分類（例: `port_in_use`）
+    pub category: String,
+    /// 原因の説明
+    pub summary: String,
+    /// 対処の提案
+    pub fix: String,
+    pub source: DiagnosisRuleSource,
+    pub status: DiagnosisRuleStatus,
+    /// ルールのきっかけになったエラー出力の抜粋（シークレットは伏せ字）
+    #[serde(default)]
+    pub example: Option<String>,
+    /// ルールを提案したモデル
+    #[serde(default)]
+    pub model: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
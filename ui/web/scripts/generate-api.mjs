@@ -0,0 +1,222 @@
+#!/usr/bin/env node
+// openapi.json（`vantagemcp openapi` の出力）から型付きAPIクライアントを生成する
+//
+//   node scripts/generate-api.mjs          src/api/generated.ts を書き出す
+//   node scripts/generate-api.mjs --check  生成結果と差分があれば失敗する
+
+import { readFileSync, writeFileSync } from 'node:fs';
+import { dirname, resolve } from 'node:path';
+import { fileURLToPath } from 'node:url';
+
+const root = resolve(dirname(fileURLToPath(import.meta.url)), '..');
+const specPath = resolve(root, 'openapi.json');
+const outPath = resolve(root, 'src/api/generated.ts');
+
+const spec = JSON.parse(readFileSync(specPath, 'utf8'));
+
+function refName(ref) {
+  return ref.split('/').pop();
+}
+
+function literal(value) {
+  return JSON.stringify(value);
+}
+
+function propertyKey(name) {
+  return /^[A-Za-z_$][A-Za-z0-9_$]*$/.test(name) ? name : literal(name);
+}
+
+function union(types) {
+  const unique = [...new Set(types)];
+  return unique.length === 0 ? 'never' : unique.join(' | ');
+}
+
+function tsType(schema, indent = '') {
+  if (!schema || Object.keys(schema).length === 0) {
+    return 'unknown';
+  }
+  if (schema.$ref) {
+    return refName(schema.$ref);
+  }
+  for (const key of ['oneOf', 'anyOf']) {
+    if (schema[key]) {
+      return union(schema[key].map(s => tsType(s, indent)));
+    }
+  }
+  if (schema.allOf) {
+    return schema.allOf.map(s => tsType(s, indent)).join(' & ');
+  }
+  if (schema.enum) {
+    return union(schema.enum.map(literal));
+  }
+  if (Array.isArray(schema.type)) {
+    return union(schema.type.map(type => tsType({ ...schema, type }, indent)));
+  }
+  switch (schema.type) {
+    case 'null':
+      return 'null';
+    case 'string':
+      return 'string';
+    case 'integer':
+    case 'number':
+      return 'number';
+    case 'boolean':
+      return 'boolean';
+    case 'array': {
+      const item = tsType(schema.items, indent);
+      return /^[A-Za-z0-9_]+$/.test(item) ? `${item}[]` : `Array<${item}>`;
+    }
+    case 'object':
+      return objectType(schema, indent);
+    default:
+      return 'unknown';
+  }
+}
+
+function objectType(schema, indent) {
+  const properties = Object.entries(schema.properties ?? {});
+  if (properties.length === 0) {
+    const value = schema.additionalProperties;
+    return `Record<string, ${value && value !== true ? tsType(value, indent) : 'unknown'}>`;
+  }
+  const required = new Set(schema.required ?? []);
+  const inner = indent + '  ';
+  const lines = properties.map(([name, property]) => {
+    const doc = property.description ? docComment(property.description, inner) : '';
+    const optional = required.has(name) ? '' : '?';
+    return `${doc}${inner}${propertyKey(name)}${optional}: ${tsType(property, inner)};`;
+  });
+  return `{\n${lines.join('\n')}\n${indent}}`;
+}
+
+function docComment(text, indent = '') {
+  const lines = text.split('\n');
+  if (lines.length === 1) {
+    return `${indent}/** ${text} */\n`;
+  }
+  const body = lines.map(line => (line ? `${indent} * ${line}` : `${indent} *`)).join('\n');
+  return `${indent}/**\n${body}\n${indent} */\n`;
+}
+
+function camelCase(name) {
+  return name.replace(/_([a-z0-9])/g, (_, c) => c.toUpperCase());
+}
+
+function jsonContent(content) {
+  return content?.['application/json']?.schema;
+}
+
+function responseType(operation) {
+  for (const [status, response] of Object.entries(operation.responses ?? {})) {
+    if (status.startsWith('2')) {
+      const schema = jsonContent(response.content);
+      return schema ? tsType(schema, '  ') : 'void';
+    }
+  }
+  return 'void';
+}
+
+function generateOperation(path, method, operation) {
+  const parameters = operation.parameters ?? [];
+  const pathParams = parameters.filter(p => p.in === 'path');
+  const queryParams = parameters.filter(p => p.in === 'query');
+  const bodySchema = jsonContent(operation.requestBody?.content);
+  const bodyRequired = operation.requestBody?.required ?? false;
+
+  const args = pathParams.map(p => `${camelCase(p.name)}: ${tsType(p.schema)}`);
+  if (bodySchema) {
+    args.push(`body${bodyRequired ? '' : '?'}: ${tsType(bodySchema, '    ')}`);
+  }
+  if (queryParams.length > 0) {
+    const fields = queryParams.map(
+      p => `${propertyKey(p.name)}${p.required ? '' : '?'}: ${tsType(p.schema)}`
+    );
+    const optional = queryParams.every(p => !p.required);
+    args.push(`query${optional ? '?' : ''}: { ${fields.join('; ')} }`);
+  }
+
+  const url = path.replace(
+    /\{([^}]+)\}/g,
+    (_, name) => `\${encodeURIComponent(${camelCase(name)})}`
+  );
+  const result = responseType(operation);
+  const config = queryParams.length > 0 ? '{ params: query }' : undefined;
+  const callArgs = [`\`${url}\``];
+  if (bodySchema) {
+    callArgs.push('body');
+  } else if (config && ['post', 'put', 'patch'].includes(method)) {
+    callArgs.push('undefined');
+  }
+  if (config) {
+    callArgs.push(config);
+  }
+
+  const doc = operation.summary ? docComment(operation.summary, '    ') : '';
+  return (
+    `${doc}    async ${camelCase(operation.operationId)}(${args.join(', ')}): Promise<${result}> {\n` +
+    `      const { data } = await http.${method}<${result}>(${callArgs.join(', ')});\n` +
+    `      return data;\n` +
+    `    },`
+  );
+}
+
+function generate() {
+  const out = [
+    '// このファイルは scripts/generate-api.mjs により openapi.json から生成されています。',
+    '// 直接編集せず、`npm run generate:api` で再生成してください。',
+    '',
+    "import type { AxiosInstance } from 'axios';",
+    '',
+  ];
+
+  for (const [name, schema] of Object.entries(spec.components?.schemas ?? {})) {
+    const doc = schema.description ? docComment(schema.description) : '';
+    const type = tsType(schema);
+    if (type.startsWith('{\n')) {
+      out.push(`${doc}export interface ${name} ${type}`, '');
+    } else {
+      out.push(`${doc}export type ${name} = ${type};`, '');
+    }
+  }
+
+  const operations = [];
+  for (const [path, item] of Object.entries(spec.paths ?? {})) {
+    for (const method of ['get', 'post', 'put', 'patch', 'delete']) {
+      const operation = item[method];
+      if (!operation || operation.responses?.['200']?.content?.['text/event-stream']) {
+        continue;
+      }
+      operations.push(generateOperation(path, method, operation));
+    }
+  }
+
+  out.push(
+    `/** ${spec.info.title} ${spec.info.version} のクライアント（baseURLは \`${spec.servers?.[0]?.url ?? '/'}\`） */`,
+    'export function createApiClient(http: AxiosInstance) {',
+    '  return {',
+    ...operations,
+    '  };',
+    '}',
+    '',
+    'export type ApiClient = ReturnType<typeof createApiClient>;',
+    ''
+  );
+  return out.join('\n');
+}
+
+const generated = generate();
+if (process.argv.includes('--check')) {
+  let current = '';
+  try {
+    current = readFileSync(outPath, 'utf8');
+  } catch {
+    // 未生成の場合は差分として扱う
+  }
+  if (current !== generated) {
+    console.error('src/api/generated.ts is out of date; run `npm run generate:api`');
+    process.exit(1);
+  }
+} else {
+  writeFileSync(outPath, generated);
+  console.log(`Wrote ${outPath}`);
+}
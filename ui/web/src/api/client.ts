@@ -5,7 +5,13 @@ import type {
   ProcessTemplate, 
   Settings, 
   ClipboardItem, 
-  ClipboardHistoryResponse 
+  ClipboardHistoryResponse,
+  ApprovalRequest,
+  ApprovalStatus,
+  ChainStatus,
+  Notification,
+  RunbookContent,
+  FollowedLines
 } from '@/types';
 
 class ApiClient {
@@ -68,8 +74,23 @@ class ApiClient {
     await this.client.delete(`/processes/${encodeURIComponent(id)}`);
   }
 
-  async getProcessOutput(id: string): Promise<{ stdout: string; stderr: string }> {
-    const { data } = await this.client.get(`/processes/${encodeURIComponent(id)}/output`);
+  async getProcessRunbook(id: string): Promise<RunbookContent> {
+    const { data } = await this.client.get<RunbookContent>(
+      `/processes/${encodeURIComponent(id)}/runbook`
+    );
+    return data;
+  }
+
+  // stdout and stderr interleaved in the order they were written; pass the returned
+  // cursor back to read only the lines written since
+  async getProcessOutput(
+    id: string,
+    params: { stream?: 'stdout' | 'stderr'; lines?: number; cursor?: number } = {}
+  ): Promise<FollowedLines> {
+    const { data } = await this.client.get<FollowedLines>(
+      `/processes/${encodeURIComponent(id)}/lines`,
+      { params }
+    );
     return data;
   }
 
@@ -162,6 +183,71 @@ class ApiClient {
     await this.client.delete('/clipboard');
   }
 
+  // Approval API
+  async getApprovals(status?: ApprovalStatus): Promise<ApprovalRequest[]> {
+    const { data } = await this.client.get<ApprovalRequest[]>('/approvals', {
+      params: { status }
+    });
+    return data;
+  }
+
+  async approve(id: string, reason?: string): Promise<ApprovalRequest> {
+    const { data } = await this.client.post<ApprovalRequest>(
+      `/approvals/${encodeURIComponent(id)}/approve`,
+      { reason }
+    );
+    return data;
+  }
+
+  async deny(id: string, reason?: string): Promise<ApprovalRequest> {
+    const { data } = await this.client.post<ApprovalRequest>(
+      `/approvals/${encodeURIComponent(id)}/deny`,
+      { reason }
+    );
+    return data;
+  }
+
+  // Notification inbox API
+  async getNotifications(unreadOnly = false, limit?: number): Promise<Notification[]> {
+    const { data } = await this.client.get<Notification[]>('/notifications', {
+      params: { unread_only: unreadOnly, limit }
+    });
+    return data;
+  }
+
+  async getUnreadNotificationCount(): Promise<number> {
+    const { data } = await this.client.get<{ unread: number }>('/notifications/count');
+    return data.unread;
+  }
+
+  async markNotificationsRead(ids?: string[]): Promise<{ marked: number; unread: number }> {
+    const { data } = await this.client.post<{ marked: number; unread: number }>(
+      '/notifications/read',
+      { ids }
+    );
+    return data;
+  }
+
+  // Process chain API
+  async getChains(processId?: string): Promise<ChainStatus[]> {
+    const { data } = await this.client.get<ChainStatus[]>('/chains', {
+      params: { process_id: processId }
+    });
+    return data;
+  }
+
+  async setChainEnabled(id: string, enabled: boolean): Promise<ChainStatus> {
+    const { data } = await this.client.patch<ChainStatus>(
+      `/chains/${encodeURIComponent(id)}`,
+      { enabled }
+    );
+    return data;
+  }
+
+  async removeChain(id: string): Promise<void> {
+    await this.client.delete(`/chains/${encodeURIComponent(id)}`);
+  }
+
   // Test processes
   async addTestProcesses(): Promise<ProcessInfo[]> {
     const { data } = await this.client.post<ProcessInfo[]>('/processes/test');
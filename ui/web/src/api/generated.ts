@@ -0,0 +1,1952 @@
+// このファイルは scripts/generate-api.mjs により openapi.json から生成されています。
+// 直接編集せず、`npm run generate:api` で再生成してください。
+
+import type { AxiosInstance } from 'axios';
+
+/** 機能へのアクセス権（弱い順） */
+export type Access = "none" | "read" | "write";
+
+/** プロセスに適用中のメンテナンスウィンドウ */
+export interface ActiveMaintenance {
+  ends_at: string;
+  name: string;
+  reason?: string | null;
+  started_at: string;
+}
+
+export interface AddBookmarkBody {
+  name: string;
+  note?: string | null;
+}
+
+/** 承認・却下の理由 */
+export interface ApprovalDecision {
+  reason?: string | null;
+}
+
+/** 承認リクエスト（判断と実行結果を含む監査記録） */
+export interface ApprovalRequest {
+  approval_id: string;
+  /** ツールの引数 */
+  arguments: unknown;
+  decided_at?: string | null;
+  /** 判断したユーザー（認証なしの場合は "web-console"） */
+  decided_by?: string | null;
+  executed_at?: string | null;
+  /** 判断を待つ期限（過ぎると `expired` になる） */
+  expires_at?: string | null;
+  /** 判断の理由 */
+  reason?: string | null;
+  requested_at: string;
+  /** 実行結果またはエラー */
+  result?: string | null;
+  status: ApprovalStatus;
+  /** 承認者に表示する操作の説明 */
+  summary: string;
+  /** 要求されたツール名 */
+  tool: string;
+  /** エージェントの呼び出しが判断を待っている（確認が必要なツール） */
+  waiting?: boolean;
+}
+
+/** 承認リクエストの状態 */
+export type ApprovalStatus = "pending" | "approved" | "denied" | "executed" | "failed" | "expired";
+
+export interface CaptureTemplateRequest {
+  name?: string | null;
+  template_id?: string | null;
+}
+
+/** 生成されたテンプレートと置き換えの内訳 */
+export interface CapturedTemplate {
+  /** 参照している・`.env` 系のファイルにある環境変数から作った変数と、その説明 */
+  env_variables: EnvVarDoc[];
+  /** 検出したポート */
+  ports: number[];
+  /** シークレット参照に置き換えた変数名 */
+  secrets: string[];
+  template: ProcessTemplate;
+}
+
+/** cgroupから読み取ったリソース使用状況 */
+export interface CgroupStats {
+  /** 累積CPU時間（マイクロ秒） */
+  cpu_usage_usec?: number | null;
+  /** 現在のメモリ使用量（バイト） */
+  memory_current?: number | null;
+  /** メモリ使用量の上限（バイト。無制限ならNone） */
+  memory_max?: number | null;
+  /** メモリ使用量の最大値（バイト） */
+  memory_peak?: number | null;
+  /** OOM killerで強制終了された回数 */
+  oom_kills?: number | null;
+  /** cgroupのパス */
+  path: string;
+  /** 現在のプロセス（スレッド）数 */
+  pids_current?: number | null;
+}
+
+/** 連鎖で実行する処理 */
+export interface ChainAction {
+  process_id: string;
+  type: "start_process";
+} | {
+  process_id: string;
+  template_id: string;
+  type: "run_template";
+  values?: Record<string, string>;
+}
+
+/** 連鎖の有効・無効 */
+export interface ChainEnabledRequest {
+  enabled: boolean;
+}
+
+/** 連鎖の実行結果 */
+export interface ChainOutcome {
+  outcome: "started";
+  pid: number;
+} | {
+  outcome: "skipped";
+  reason: string;
+} | {
+  error: string;
+  outcome: "failed";
+} | {
+  outcome: "suppressed";
+}
+
+/** 連鎖の実行記録 */
+export type ChainRun = ChainOutcome & {
+  at: string;
+};
+
+/** 連鎖の状態 */
+export type ChainStatus = ProcessChain & {
+  last_run?: null | ChainRun;
+  /** 実行した回数（抑止したものは含まない） */
+  run_count: number;
+};
+
+/** Response for clipboard history */
+export interface ClipboardHistoryResponse {
+  items: ClipboardResponse[];
+  total_count: number;
+}
+
+/** Response for clipboard operations */
+export interface ClipboardResponse {
+  content: string;
+  content_type: string;
+  created_at: string;
+  filename?: string | null;
+  id: string;
+  tags: string[];
+  updated_at: string;
+}
+
+/**
+ * コンテナで実行する設定
+ *
+ * `<engine> run --rm` でイメージを実行します。プロセスの `command` / `args` はコンテナ内で実行する
+ * コマンドになります（`command` が空ならイメージのCMD）。`build` を指定すると、起動のたびに
+ * 先にイメージをビルドします。
+ */
+export interface ContainerConfig {
+  build?: null | ImageBuildConfig;
+  /** コンテナエンジン */
+  engine?: ContainerEngine;
+  /** 実行する（ビルドする場合は作成する）イメージのタグ */
+  image: string;
+  /** `run` に渡す追加の引数（例: `["-p", "8080:80"]`） */
+  run_args?: string[];
+}
+
+/** コンテナエンジン */
+export type ContainerEngine = "docker" | "podman";
+
+/** 検知されたクラッシュループの情報 */
+export interface CrashLoopInfo {
+  detected_at: string;
+  /** ウィンドウ内の異常終了（古い順） */
+  recent_exits: ExitRecord[];
+  suggestion: string;
+}
+
+export interface CreateProcessRequest {
+  args?: string[];
+  /** Expected behavior checked by run_checks: `ready_within` (port opens or pattern appears), `no_output_match` (pattern never appears), `exit_code` (exits with the code) */
+  assertions?: ProcessAssertion[];
+  auto_start_on_restore?: boolean;
+  command: string;
+  container?: null | ContainerConfig;
+  /** Working directory (`~` and `$VAR` are expanded) */
+  cwd?: string | null;
+  /** Processes to start first, e.g. `[{"process_id": "db", "ready": {"type": "log_pattern", "pattern": "ready to accept connections"}}]`. `ready` is `started` (default), `healthy` (the dependency needs a health_check) or `log_pattern`; `timeout_secs` defaults to 30. start_process and auto-start on restore start missing dependencies and wait for them; cycles are rejected */
+  depends_on?: ProcessDependency[];
+  /**
+   * Environment variables. Values like op://vault/item/field (1Password) or vault://path#field
+   * (HashiCorp Vault) are resolved when the process is spawned
+   */
+  env?: Record<string, string>;
+  env_loader?: null | EnvLoader;
+  /** Named group (e.g. `stack`) so start_group/stop_group bring the processes up in `startup` order and tear them down in reverse */
+  group?: string | null;
+  health_check?: null | HealthCheckConfig;
+  id: string;
+  idle_detection?: null | IdleDetectionConfig;
+  /** Keep stdin open as a pipe so input can be sent with send_process_input (not with tmux or repl) */
+  keep_stdin_open?: boolean;
+  /** Forward this process's output to syslog, Loki or a JSONL file (in addition to the sinks in settings) */
+  log_sinks?: LogSinkConfig[];
+  /** Extra log files or FIFOs to tail into the output, labeled per source */
+  log_sources?: LogSource[];
+  /** Arbitrary key/value metadata such as owner, ticket or runbook URL; returned in list/status responses and filterable as `metadata.<key>` */
+  metadata?: Record<string, string>;
+  output_recording?: null | OutputRecordingConfig;
+  /** Per-run parameters for `{{name}}` placeholders in args, e.g. args `["test", "{{filter}}"]` with `[{"name": "filter", "description": "Test name filter"}]`. Values are given to start_process_with_params for that run only; `default` is used otherwise (an argument that is just an empty placeholder is dropped), `required` parameters must always be given and `pattern` is a regex the whole value must match */
+  parameters?: ProcessParameter[];
+  redaction?: null | RedactionOverride;
+  repl?: null | ReplConfig;
+  resource_limits?: null | ResourceLimits;
+  restart_policy?: null | RestartPolicy;
+  run_as?: null | RunAsConfig;
+  runbook?: null | Runbook;
+  /** Names of `env` entries that are secrets: their values are encrypted at rest, masked in every response and decrypted only when the process is spawned */
+  sensitive_env?: string[];
+  startup?: null | StartupOrder;
+  /** Tags for grouping (e.g. for chaos testing targets) */
+  tags?: string[];
+  tmux?: null | TmuxConfig;
+}
+
+/** 定期実行の登録 */
+export interface CreateScheduleRequest {
+  /** Cron expression in the server's local time (minute hour day month weekday) */
+  cron: string;
+  enabled?: boolean | null;
+  process_id: string;
+  /** Unique schedule ID (default: "<process_id>-schedule") */
+  schedule_id?: string | null;
+}
+
+export interface CreateTemplateRequest {
+  args: string[];
+  category?: string | null;
+  command: string;
+  default_auto_start: boolean;
+  default_cwd?: string | null;
+  description?: string | null;
+  env: Record<string, string>;
+  id: string;
+  name: string;
+  tags: string[];
+  variables: TemplateVariable[];
+}
+
+/** ログイン中のユーザー */
+export interface CurrentUser {
+  display_name?: string | null;
+  /** 機能ごとのアクセス権 */
+  permissions: Permissions;
+  provider: string;
+  role: Role;
+  subject: string;
+}
+
+export interface DashboardData {
+  processes: unknown[];
+  recent_events: RecentEvent[];
+  server: ServerInfo;
+  stats: ProcessStats;
+  system_metrics: SystemMetrics;
+}
+
+/** 依存先を準備完了とみなす条件 */
+export interface DependencyReady {
+  type: "started";
+} | {
+  type: "healthy";
+} | {
+  pattern: string;
+  type: "log_pattern";
+}
+
+/**
+ * 消費電力量と電気代の見積もりの係数
+ *
+ * プロセスが使ったCPU時間（CPU秒）に、CPUコア1つを使い切ったときの消費電力を掛けて
+ * 消費電力量を見積もり、電気料金の単価から費用を求めます。実測ではなく目安です。
+ */
+export interface EnergyCostSettings {
+  /** 費用の通貨（表示用） */
+  currency?: string;
+  /** 電気料金の単価（1kWhあたり） */
+  price_per_kwh?: number;
+  /** CPUコア1つを使い切ったときの消費電力（W） */
+  watts_per_core?: number;
+}
+
+/**
+ * プロセスを起動するときにプロジェクトの環境を読み込むツール
+ *
+ * 作業ディレクトリで読み込んだ環境変数（ツールのバージョンを切り替えたPATHなど）を、
+ * 定義の環境変数より優先度の低いものとして子プロセスに渡します。
+ */
+export type EnvLoader = "auto" | "mise" | "direnv" | "asdf";
+
+/** 説明を付けた環境変数 */
+export interface EnvVarDoc {
+  default_value?: string | null;
+  description: string;
+  example?: string | null;
+  name: string;
+  required: boolean;
+  secret: boolean;
+  /** 見つかった場所（`args`、`env DATABASE_URL`、`.env.example` など） */
+  sources: string[];
+}
+
+/** 異常終了1回分の記録 */
+export interface ExitRecord {
+  at: string;
+  error?: string | null;
+  exit_code?: number | null;
+}
+
+/** 異常終了したときの状況 */
+export interface FailureReport {
+  /** 終了を待てなかった場合、その理由 */
+  error?: string | null;
+  exit_code?: number | null;
+  failed_at: string;
+  /** 終了する直前の出力の頻度 */
+  output_rates: WindowRates;
+  resources?: null | CgroupStats;
+  /** 実行の通し番号（explain_run_environment の run と同じ） */
+  run: number;
+  /** シグナルで終了した場合、その番号 */
+  signal?: number | null;
+  /** シグナルの名前（`SIGKILL` など） */
+  signal_name?: string | null;
+  /** stderrの末尾（tmuxではstdoutとまとめて出力されるためstdoutの末尾） */
+  stderr_tail: string[];
+  /** 起動してから終了するまでの秒数 */
+  uptime_secs?: number | null;
+}
+
+/** カーソルより後の出力（時刻・ストリーム・連番付き） */
+export interface FollowedLines {
+  /** 続きを読むときに渡すカーソル（最後に返した行の連番） */
+  cursor: number;
+  /** 出力された順（連番の順）の行 */
+  lines: LogLine[];
+  /** 行数の上限で返さなかった行がある（すぐに続きを読める） */
+  more: boolean;
+  /** カーソルの後の行の一部が、読まれる前にバッファからあふれて失われている */
+  truncated: boolean;
+}
+
+/** ゲートの状態 */
+export interface GateStatus {
+  checked_at: string;
+  /** 準備完了か（trueなら200、falseなら503） */
+  open: boolean;
+  process_id: string;
+  /** 閉じている理由 */
+  reasons?: string[];
+}
+
+/** ヘルスチェックで異常と判定したときのアクション */
+export type HealthAction = "notify" | "restart";
+
+/**
+ * ヘルスチェックの設定
+ *
+ * 直近 `window_secs` の出力を集計し、エラーの行の頻度やstderrの割合がしきい値を超えた場合に
+ * プロセスを異常とみなします。クラッシュせずに劣化していくサービスを検知するためのものです。
+ * `probe` を指定すると、`interval_secs` ごとにHTTP・TCP・コマンドで応答を確かめ、
+ * `failure_threshold` 回続けて失敗した場合も異常とみなします。
+ */
+export interface HealthCheckConfig {
+  /** 異常と判定したときのアクション */
+  action?: HealthAction;
+  /** 異常とみなす、プローブが続けて失敗した回数 */
+  failure_threshold?: number;
+  /** プローブの間隔（秒） */
+  interval_secs?: number;
+  /** エラーの行（ERROR・panic・Traceback等）が1分あたりこの件数を超えたら異常 */
+  max_errors_per_minute?: number | null;
+  /** 出力の行のうちstderrの割合（0〜1）がこの値を超えたら異常 */
+  max_stderr_ratio?: number | null;
+  probe?: null | HealthProbe;
+  /** プローブ1回のタイムアウト（秒） */
+  timeout_secs?: number;
+  /** 集計する期間（秒、10の倍数） */
+  window_secs?: number;
+}
+
+/** ヘルスチェックのプローブ */
+export interface HealthProbe {
+  expected_status?: number | null;
+  type: "http";
+  url: string;
+} | {
+  host?: string | null;
+  port: number;
+  type: "tcp";
+} | {
+  args?: string[];
+  command: string;
+  type: "exec";
+}
+
+/** ヘルスチェックの現在の判定 */
+export interface HealthState {
+  checked_at: string;
+  /** 異常とみなした理由（出力のしきい値・プローブの失敗） */
+  failures?: string[];
+  healthy: boolean;
+  probe?: null | ProbeStatus;
+  /** 異常と判定された時刻 */
+  unhealthy_since?: string | null;
+}
+
+/** Webhookの呼び出しの結果 */
+export interface HookResponse {
+  /** 実行した操作（`start_process`・`restart_process`・`instantiate_template`） */
+  action: string;
+  hook: string;
+  message: string;
+  /** 起動したプロセスのPID（起動中で何もしなかった場合はNone） */
+  pid?: number | null;
+  process_id: string;
+}
+
+/** アイドル検知時のアクション */
+export type IdleAction = "notify" | "restart";
+
+/**
+ * アイドル検知の設定
+ *
+ * 出力が途絶え、かつCPU使用率がしきい値未満の状態が `timeout_secs` 続いた場合に
+ * プロセスをアイドルとみなします。
+ */
+export interface IdleDetectionConfig {
+  /** アイドル検知時のアクション */
+  action?: IdleAction;
+  /** この値（%）未満のCPU使用率をアイドルとみなす */
+  cpu_threshold_percent?: number;
+  /** アイドルとみなすまでの無出力時間（秒） */
+  timeout_secs: number;
+}
+
+/** イメージのビルド設定 */
+export interface ImageBuildConfig {
+  /** `--build-arg` に渡す値 */
+  build_args?: Record<string, string>;
+  /** ビルドコンテキスト（相対パスは作業ディレクトリ基準） */
+  context?: string;
+  /** Dockerfileのパス（省略時はコンテキスト内のDockerfile） */
+  dockerfile?: string | null;
+  /** ビルドコンテキストのファイルが変更されたら再ビルドして再起動する */
+  rebuild_on_change?: boolean;
+}
+
+/** 最後のイメージのビルドの結果 */
+export interface ImageBuildStatus {
+  /** ビルド前後でイメージIDが変わらなかった（すべてキャッシュから作られた）か */
+  cache_hit: boolean;
+  /** キャッシュを使ったステップ数 */
+  cached_steps: number;
+  duration_ms: number;
+  exit_code?: number | null;
+  image: string;
+  started_at: string;
+  success: boolean;
+}
+
+/**
+ * 外部ファイルから取り込んだプロセス・テンプレートの取り込み元
+ *
+ * 再取り込み時に同じ取り込み元のエントリを更新するために使います。
+ */
+export interface ImportSource {
+  /** ファイル内でのエントリの識別子（例: タスクのラベル） */
+  key: string;
+  /** 取り込み元の種類（例: "vscode_tasks"） */
+  kind: string;
+  /** 取り込み元ファイルのパス */
+  path: string;
+}
+
+/** 入力の指定方法 */
+export type InputEncoding = "text" | "base64";
+
+export interface InstantiateTemplateRequest {
+  process_id: string;
+  /** 変数の値（型のある変数には数値・真偽値も指定できる） */
+  values: Record<string, Record<string, unknown>>;
+}
+
+/**
+ * 最初の接続で起動する（遅延起動）設定
+ *
+ * Vantageが `listen_port` で待ち受け、最初の接続が来たらプロセスを起動し、
+ * `backend_port` で接続を受け付けるようになってから中継します。
+ */
+export interface LazyStartConfig {
+  /** プロセスが実際に待ち受けるポート（未指定の場合は空いているポートを選ぶ） */
+  backend_port?: number | null;
+  /** 待ち受けるアドレス（既定は127.0.0.1） */
+  host?: string | null;
+  /** 接続がない状態がこの秒数続いたら停止する（未指定の場合は停止しない） */
+  idle_stop_secs?: number | null;
+  /** Vantageが待ち受けるポート（クライアントが接続するポート） */
+  listen_port: number;
+  /** `backend_port` をプロセスに渡す環境変数（既定はPORT） */
+  port_env?: string | null;
+  /** 起動してから接続を受け付けるまで待つ秒数（既定は30秒） */
+  ready_timeout_secs?: number | null;
+}
+
+/** 遅延起動の待ち受けの状態 */
+export interface LazyStartStatus {
+  /** 接続をきっかけにプロセスを起動した回数 */
+  activations: number;
+  /** 中継中の接続の数 */
+  active_connections: number;
+  /** 中継先（プロセスが待ち受けるポート） */
+  backend_port: number;
+  /** 受け付けた接続の数 */
+  connections: number;
+  /** 接続がない状態がこの秒数続いたら停止する */
+  idle_stop_secs?: number | null;
+  last_activated_at?: string | null;
+  /** 最後に起動に失敗した理由（その後に起動できればクリアされる） */
+  last_error?: string | null;
+  /** Vantageが待ち受けるアドレス */
+  listen: string;
+}
+
+/**
+ * バッファに記録される1行分のログ
+ *
+ * 返却テキストには含めず、メタデータとしてタイムスタンプを保持する。
+ * `instant` は単調時計で、フェーズ間の所要時間計算に使用する。
+ * `seq` はバッファに追加した順の連番（1から）で、続きを読むためのカーソルに使う。
+ * 標準出力と標準エラーで連番を共有するため、`seq` の順に並べると出力された順になる。
+ */
+export interface LogLine {
+  seq: number;
+  stream: LogStream;
+  text: string;
+  timestamp: string;
+}
+
+/**
+ * ログディレクトリ（状態ディレクトリの logs）のディスク使用量クォータ
+ *
+ * 0 は無制限を表します。
+ */
+export interface LogQuotaSettings {
+  /** プロセスごとの上限（バイト） */
+  max_per_process_bytes: number;
+  /** ログディレクトリ全体の上限（バイト） */
+  max_total_bytes: number;
+}
+
+/**
+ * ログの転送先
+ *
+ * 取り込んだ標準出力・標準エラー出力の各行を、プロセスID・ストリーム・ワークスペースの
+ * ラベルを付けて外部に送ります。設定の `log_sinks` はすべてのプロセスに、プロセスの
+ * `log_sinks` はそのプロセスのみに適用されます。
+ */
+export interface LogSinkConfig {
+  /** 転送先の種類 */
+  kind: LogSinkKind;
+  /** すべての行に追加するラベル */
+  labels?: Record<string, string>;
+  /** 転送先の名前（診断情報での表示と、同じ転送先の共有に使う） */
+  name: string;
+  /** 送り先（syslog: `host:port`、loki: push APIのURL、jsonl: ファイルのパス） */
+  target: string;
+}
+
+/** ログの転送先の種類 */
+export type LogSinkKind = "syslog" | "loki" | "jsonl";
+
+/**
+ * 追加のログソース
+ *
+ * 標準出力以外にログを書き出すプロセスのために、ファイルや名前付きパイプ（FIFO）を追従して
+ * 出力バッファに取り込みます。
+ */
+export interface LogSource {
+  /** 取り込んだ行に付けるラベル（省略時はファイル名） */
+  label?: string | null;
+  /** 追従するファイルまたはFIFOのパス（相対パスは作業ディレクトリ基準） */
+  path: string;
+  /** 標準出力ではなく標準エラー出力のバッファに取り込む */
+  stderr?: boolean;
+}
+
+/** 行を出力したストリーム */
+export type LogStream = "stdout" | "stderr";
+
+/** メンテナンスウィンドウの期間 */
+export interface MaintenanceSchedule {
+  end: string;
+  start: string;
+  type: "once";
+} | {
+  cron: string;
+  duration_minutes: number;
+  type: "recurring";
+}
+
+/**
+ * メンテナンスウィンドウ
+ *
+ * 期間中は対象のプロセスの自動再起動・アイドル検知の通知と処置・クラッシュループの検知を止めます。
+ * 対象（`processes` / `tags` / `workspaces`）をすべて空にすると全プロセスが対象になります。
+ */
+export interface MaintenanceWindow {
+  /** ウィンドウの名前（一意） */
+  name: string;
+  /** 対象のプロセスID */
+  processes?: string[];
+  /** 理由（ステータスやイベントに表示する） */
+  reason?: string | null;
+  /** 期間 */
+  schedule: MaintenanceSchedule;
+  /** 対象のタグ（いずれかを持つプロセス） */
+  tags?: string[];
+  /** 対象のワークスペース（作業ディレクトリがこの絶対パス以下のプロセス） */
+  workspaces?: string[];
+}
+
+/** 既読にする通知（`ids` を省略するとすべて） */
+export interface MarkNotificationsReadRequest {
+  ids?: string[] | null;
+}
+
+/** 既読にした件数と残りの未読の件数 */
+export interface MarkNotificationsReadResponse {
+  marked: number;
+  unread: number;
+}
+
+/** 操作結果のメッセージ */
+export interface MessageResponse {
+  message: string;
+}
+
+/**
+ * 受信箱の通知
+ *
+ * イベントのうち利用者が後から確認すべきものを、既読・未読の状態とともに残します。
+ */
+export interface Notification {
+  created_at: string;
+  kind: NotificationKind;
+  /** 詳細を確認できる場所（CIの実行のURL、承認リクエストのIDなど） */
+  link?: string | null;
+  /** 詳細（エラーの内容など） */
+  message?: string;
+  notification_id: string;
+  /** 関係するプロセス */
+  process_id?: string | null;
+  read?: boolean;
+  read_at?: string | null;
+  /** 送り先を決めたルールの名前（一致するルールがなかった場合はNone） */
+  route?: string | null;
+  /** 一覧に表示する見出し */
+  title: string;
+}
+
+/** 通知の送り先 */
+export interface NotificationChannel {
+  type: "slack";
+  webhook_url: string;
+} | {
+  /** 差出人（省略時はsendmailの既定） */
+  from?: string | null;
+  to: string[];
+  type: "email";
+} | {
+  type: "desktop";
+} | {
+  type: "inbox";
+}
+
+/** 未読の通知の件数（ヘッダーのバッジ用） */
+export interface NotificationCount {
+  unread: number;
+}
+
+/** 通知の種類 */
+export type NotificationKind = "process_failed" | "crash_loop" | "process_unhealthy" | "ci_failed" | "approval_pending";
+
+/**
+ * 通知の送り先のルール
+ *
+ * 設定のルールを上から順に評価し、最初に一致したルールの送り先に送ります。条件はすべて
+ * 満たす必要があり、空の条件は何にでも一致します（条件のないルールはそれまでに一致しなかった
+ * すべての通知を受けます）。
+ */
+export interface NotificationRoute {
+  /** 送り先 */
+  channel: NotificationChannel;
+  /** 対象の通知の種類 */
+  kinds?: NotificationKind[];
+  min_severity?: null | NotificationSeverity;
+  /** ルールの名前（一意） */
+  name: string;
+  /** 対象のタグ（いずれかを持つプロセスの通知） */
+  tags?: string[];
+  /** 対象のワークスペース（作業ディレクトリがこの絶対パス以下のプロセスの通知） */
+  workspaces?: string[];
+}
+
+/** 通知の重大度（低い順） */
+export type NotificationSeverity = "info" | "warning" | "error" | "critical";
+
+/** 出力の位置に付けた名前 */
+export interface OutputBookmark {
+  created_at: string;
+  name: string;
+  note?: string | null;
+  stderr_lines: number;
+  /** 付けた時点までにstdout・stderrに記録されていた行数（バッファに残っている分） */
+  stdout_lines: number;
+}
+
+/**
+ * 出力バッファのメモリ使用量の上限
+ *
+ * すべてのプロセスの出力バッファの合計が上限を超えると、停止中のプロセスと出力の途絶えた
+ * プロセスのバッファを古い順に `min_buffer_lines` 行まで縮めます。`spill_to_disk` の場合、
+ * 縮めるときに捨てる行をログディレクトリに書き出します。合計が上限の3/4を下回ると元の大きさに戻します。
+ * `max_total_bytes` の0は無制限を表します。
+ */
+export interface OutputMemorySettings {
+  /** 実行中のプロセスを縮める対象にする、出力が途絶えてからの秒数 */
+  idle_after_secs?: number;
+  /** すべての出力バッファの合計の上限（バイト） */
+  max_total_bytes?: number;
+  /** 縮めたバッファに残す行数 */
+  min_buffer_lines?: number;
+  /** 縮めるときに捨てる行を `<log_dir>/<process_id>/spill-<stream>.log` に書き出す */
+  spill_to_disk?: boolean;
+}
+
+/**
+ * 出力の記録の設定
+ *
+ * 起動のたびに、stdout・stderr（と `include_stdin` の場合は標準入力）のすべての行を
+ * 時刻付きで記録ファイルに書き出します。記録は `replay_output` で元の速さか早送りで再生できます。
+ */
+export interface OutputRecordingConfig {
+  /** REPLプロセスに送ったコード（標準入力）も記録する */
+  include_stdin?: boolean;
+  /** 残す記録の数（既定は10、古いものから削除する） */
+  keep?: number | null;
+}
+
+/** サンプリングの設定 */
+export interface OutputSampling {
+  /** N行ごとに1行を残す */
+  every_n: number;
+  /** 間引かずに残す行の正規表現（空の場合はエラー・警告などの既定パターン） */
+  keep_patterns?: string[];
+}
+
+/** 機能ごとのアクセス権 */
+export interface Permissions {
+  clipboard: Access;
+  processes: Access;
+  /**
+   * `read` 未満ではシークレットらしき値を伏せて返し、`write` 未満ではシークレットを
+   * 含む環境変数を設定できない
+   */
+  secrets: Access;
+  /** `write` でサーバー自身を再起動したり、ログをローテーションしたりできる */
+  server: Access;
+  templates: Access;
+}
+
+/** プローブ1回の結果 */
+export interface ProbeOutcome {
+  checked_at: string;
+  /** ステータスコードやエラーの内容 */
+  detail: string;
+  duration_ms: number;
+  success: boolean;
+}
+
+/** プローブの状態 */
+export interface ProbeStatus {
+  /** 続けて失敗した回数 */
+  consecutive_failures: number;
+  last: ProbeOutcome;
+  /** 最後に成功した時刻 */
+  last_success_at?: string | null;
+}
+
+/**
+ * プロセスの振る舞いについての確認項目
+ *
+ * `run_checks` がプロセスを起動し、起動からの経過時間で各項目を評価します。
+ */
+export interface ProcessAssertion {
+  pattern?: string | null;
+  port?: number | null;
+  type: "ready_within";
+  within_secs: number;
+} | {
+  pattern: string;
+  /** stderrだけを対象にする */
+  stderr_only?: boolean;
+  type: "no_output_match";
+  within_secs: number;
+} | {
+  code: number;
+  type: "exit_code";
+  within_secs?: number | null;
+}
+
+/**
+ * プロセスの連鎖 - あるプロセスが正常終了したら次の処理を実行
+ *
+ * `after` のプロセスが終了コード0で終了したときに `action` を実行します。
+ * ビルド→起動、マイグレーション→シードのような手順を一度だけ設定しておくために使います。
+ * 停止要求による終了では実行しません。
+ */
+export interface ProcessChain {
+  /** 正常終了したときに実行する処理 */
+  action: ChainAction;
+  /** 終了を監視するプロセス */
+  after: string;
+  /** 連鎖の一意識別子 */
+  chain_id: string;
+  created_at: string;
+  /** 無効にした連鎖は実行しない */
+  enabled?: boolean;
+}
+
+export interface ProcessConfigUpdate {
+  auto_start_on_restore?: boolean | null;
+  /** まとめて起動・停止するグループ（空文字列でグループから外す、未指定の場合は現在の値を維持） */
+  group?: string | null;
+  startup?: null | StartupOrder;
+}
+
+/**
+ * 起動する前に準備完了を待つ別のプロセス
+ *
+ * 依存先が実行中でなければ先に起動し、`ready` の条件を満たすまで（`timeout_secs` 秒まで）
+ * 待ってから依存元を起動します。
+ */
+export interface ProcessDependency {
+  /** 先に起動するプロセスのID */
+  process_id: string;
+  /** 準備完了とみなす条件（既定は起動した時点） */
+  ready?: DependencyReady;
+  /** 準備完了を待つ秒数（既定は30秒） */
+  timeout_secs?: number | null;
+}
+
+/** プロセスの基本情報 */
+export interface ProcessInfo {
+  args: string[];
+  /** `run_checks` で評価する振る舞いの確認項目 */
+  assertions?: ProcessAssertion[];
+  auto_start_on_restore?: boolean;
+  command: string;
+  container?: null | ContainerConfig;
+  cwd?: string | null;
+  /** 起動する前に準備完了を待つプロセス */
+  depends_on?: ProcessDependency[];
+  env: Record<string, string>;
+  env_loader?: null | EnvLoader;
+  /** `start_group` / `stop_group` でまとめて起動・停止するグループ */
+  group?: string | null;
+  health?: null | HealthState;
+  health_check?: null | HealthCheckConfig;
+  id: string;
+  idle_detection?: null | IdleDetectionConfig;
+  /** 標準入力をパイプとして開いたままにする */
+  keep_stdin_open?: boolean;
+  lazy_start?: null | LazyStartConfig;
+  log_sinks?: LogSinkConfig[];
+  log_sources?: LogSource[];
+  /** 担当者・チケットURL・手順書へのリンクなどの任意のキーと値 */
+  metadata?: Record<string, string>;
+  output_recording?: null | OutputRecordingConfig;
+  /** 起動ごとに値を指定する引数のパラメーター */
+  parameters?: ProcessParameter[];
+  redaction?: null | RedactionOverride;
+  repl?: null | ReplConfig;
+  resource_limits?: null | ResourceLimits;
+  restart_policy?: null | RestartPolicy;
+  run_as?: null | RunAsConfig;
+  runbook?: null | Runbook;
+  /** 暗号化して保存する機密の環境変数（出力では値を伏せる） */
+  sensitive_env?: Record<string, string>;
+  source?: null | ImportSource;
+  startup?: null | StartupOrder;
+  state: ProcessState;
+  tags?: string[];
+  tmux?: null | TmuxConfig;
+}
+
+/** 前回の通し番号からの変化 */
+export interface ProcessListDelta {
+  /** 追加されたプロセス（`GET /api/processes` の要素と同じ形） */
+  added: Array<Record<string, unknown>>;
+  /** 削除されたプロセスのID */
+  removed: string[];
+  seq: number;
+  /** 状態や定義が変わったプロセス（変更後の全体） */
+  updated: Array<Record<string, unknown>>;
+}
+
+/** サーバーからクライアントへのメッセージ */
+export interface ProcessListMessage {
+  processes: Array<Record<string, unknown>>;
+  seq: number;
+  type: "snapshot";
+} | ProcessListDelta & {
+  type: "delta";
+}
+
+/**
+ * 起動ごとに値を指定する引数のパラメーター
+ *
+ * `args` の `{{name}}` を `start_process_with_params` で指定した値（指定されなければ
+ * `default`）に置き換えます。置き換えるのはその起動だけで、定義の引数は変わりません。
+ */
+export interface ProcessParameter {
+  /** 指定されなかったときの値（無ければ空。引数全体が `{{name}}` なら引数ごと省く） */
+  default?: string | null;
+  description?: string | null;
+  /** パラメーター名（`{{name}}` の形式で引数に書く） */
+  name: string;
+  /** 値全体が一致しなければならない正規表現 */
+  pattern?: string | null;
+  /** 起動のたびに指定が必要か */
+  required?: boolean;
+}
+
+/**
+ * プロセスの定期実行 - cron形式の時刻にプロセスを起動
+ *
+ * バックアップやレポートの生成など、決まった時刻に終わるまで動かすプロセスのために使います。
+ * 時刻になってもプロセスが実行中であれば、その回は実行しません。
+ */
+export interface ProcessSchedule {
+  created_at: string;
+  /** cron形式（分 時 日 月 曜日、サーバーのローカル時刻） */
+  cron: string;
+  /** 無効にした定期実行は実行しない */
+  enabled?: boolean;
+  /** 起動するプロセス */
+  process_id: string;
+  /** 定期実行の一意識別子 */
+  schedule_id: string;
+}
+
+/** プロセスの状態 */
+export type ProcessState = "NotStarted" | {
+  Running: {
+    pid: number;
+    started_at: string;
+  };
+} | {
+  /** SIGSTOPで一時停止中（`resume_process` で再開する） */
+  Paused: {
+    paused_at: string;
+    pid: number;
+    started_at: string;
+  };
+} | {
+  Stopped: {
+    exit_code?: number | null;
+    stopped_at: string;
+  };
+} | {
+  Failed: {
+    error: string;
+    failed_at: string;
+  };
+};
+
+export interface ProcessStats {
+  auto_start_enabled: number;
+  failed: number;
+  paused: number;
+  running: number;
+  stopped: number;
+  total: number;
+}
+
+/** プロセスの詳細ステータス */
+export interface ProcessStatus {
+  /** 外部で起動したプロセスを引き取った場合、その時刻（出力は取り込まない） */
+  adopted_at?: string | null;
+  /** tmuxバックエンドの場合、ターミナルからセッションに接続するコマンド */
+  attach_instructions?: string | null;
+  cgroup?: null | CgroupStats;
+  /** カオステストで一時停止（SIGSTOP）している場合、再開予定時刻 */
+  chaos_paused_until?: string | null;
+  /** 直近のCPU使用率（%、1コアで100%） */
+  cpu_usage?: number | null;
+  crash_loop?: null | CrashLoopInfo;
+  /** アイドル検知でアイドルと判定されているか */
+  idle?: boolean;
+  /** アイドルと判定された時刻 */
+  idle_since?: string | null;
+  image_build?: null | ImageBuildStatus;
+  info: ProcessInfo;
+  last_failure?: null | FailureReport;
+  lazy_start?: null | LazyStartStatus;
+  maintenance?: null | ActiveMaintenance;
+  /** メモリの使用量（バイト、cgroupに入っていれば子プロセスを含め、それ以外はRSS） */
+  memory_usage?: number | null;
+  output_sampling?: null | SamplingStatus;
+  /** 出力を伏せるルールで置き換えた箇所の数（stdout/stderrの合計、再起動をまたいで数える） */
+  redactions?: number;
+  repl?: null | ReplStatus;
+  resource_history?: null | ResourceHistory;
+  resource_usage?: null | ResourceUsage;
+  restart?: null | RestartStatus;
+  /** このプロセスに紐付けたトンネルの状態と公開URL */
+  tunnels?: TunnelStatus[];
+  /** ヘルスチェックで異常と判定された時刻（詳細は get_process_metrics） */
+  unhealthy_since?: string | null;
+  uptime_seconds?: number | null;
+}
+
+/** プロセステンプレート - よく使うプロセス設定を保存して再利用 */
+export interface ProcessTemplate {
+  /** コマンド引数のテンプレート（変数置換可能） */
+  args: string[];
+  /** カテゴリ（例: "development", "monitoring", "utility"） */
+  category?: string | null;
+  /** 実行するコマンド */
+  command: string;
+  /** 作成日時 */
+  created_at: string;
+  /** 復元時に自動起動するかのデフォルト値 */
+  default_auto_start: boolean;
+  /** デフォルトの作業ディレクトリ */
+  default_cwd?: string | null;
+  /** テンプレートの説明 */
+  description?: string | null;
+  /** 環境変数のテンプレート */
+  env: Record<string, string>;
+  /** Optional unique record identifier */
+  id?: string | null;
+  /** テンプレートの表示名 */
+  name: string;
+  source?: null | ImportSource;
+  /** タグ（検索・フィルタリング用） */
+  tags: string[];
+  /** テンプレートの一意識別子 */
+  template_id: string;
+  /** 更新日時 */
+  updated_at: string;
+  /** テンプレート変数の定義 */
+  variables: TemplateVariable[];
+}
+
+/** プロセスのタイムライン */
+export interface ProcessTimeline {
+  /** 開始時刻の順 */
+  entries: TimelineEntry[];
+  from?: string | null;
+  generated_at: string;
+  process_id: string;
+  summary: TimelineSummary;
+  to?: string | null;
+}
+
+export interface RecentEvent {
+  event_type: string;
+  message: string;
+  process_id: string;
+  timestamp: number;
+}
+
+/** 記録した1行 */
+export interface RecordedLine {
+  encoding?: null | InputEncoding;
+  line: string;
+  /** 起動からの経過時間（ミリ秒） */
+  offset_ms: number;
+  stream: RecordedStream;
+}
+
+/** 記録した行のストリーム */
+export type RecordedStream = "stdout" | "stderr" | "stdin";
+
+/** 読み込んだ記録 */
+export interface Recording {
+  header: RecordingHeader;
+  lines: RecordedLine[];
+  name: string;
+}
+
+/** 記録ファイルの1行目 */
+export interface RecordingHeader {
+  args: string[];
+  command: string;
+  include_stdin: boolean;
+  process_id: string;
+  /** 起動の通し番号（サーバーを再起動すると1から数え直す） */
+  run: number;
+  started_at: string;
+}
+
+/** 記録の一覧の項目 */
+export interface RecordingSummary {
+  bytes: number;
+  /** 最後の行までの経過時間（ミリ秒） */
+  duration_ms: number;
+  lines: number;
+  /** 記録の名前（`replay_output` の `recording` に指定する） */
+  name: string;
+  run: number;
+  started_at: string;
+}
+
+/** プロセスごとの伏せるルールの上書き */
+export interface RedactionOverride {
+  /** このプロセスでは適用しない設定のルールの名前 */
+  disable?: string[];
+  /** このプロセスのみに適用するルール */
+  rules?: RedactionRule[];
+}
+
+/**
+ * 出力を伏せるルール
+ *
+ * 取り込んだ出力の各行に、バッファへの記録・ログシンクへの転送より前に適用します。
+ * 設定の `redaction_rules` はすべてのプロセスに、プロセスの `redaction` はそのプロセスのみに適用されます。
+ */
+export interface RedactionRule {
+  /** 置き換える文字列（`$1` などでキャプチャを残せる、省略時は `[REDACTED]`） */
+  mask?: string | null;
+  /** ルールの名前（プロセスごとに無効にするときに指定する） */
+  name: string;
+  /** 伏せる部分に一致する正規表現 */
+  pattern: string;
+}
+
+/**
+ * REPLプロセスの設定
+ *
+ * 標準入力からコードを受け付けるインタプリタ（`python -i`、`node -i`、シェルなど）として扱い、
+ * 実行ごとに完了マーカーを出力させることで出力の区切りと実行中・待機中の状態を判定します。
+ */
+export interface ReplConfig {
+  /** インタプリタの種類（完了マーカーを出力する文の書き方が決まる） */
+  language: ReplLanguage;
+  /** 完了マーカーを出力するコード（`{marker}` がマーカーに置換される。customでは必須） */
+  marker_command?: string | null;
+  /** 出力から取り除くプロンプト（省略時は言語ごとの既定値） */
+  prompts?: string[];
+}
+
+/** REPLのインタプリタの種類 */
+export type ReplLanguage = "python" | "node" | "shell" | "custom";
+
+/** REPLの状態 */
+export interface ReplStatus {
+  /** コードを実行中か */
+  busy: boolean;
+  /** これまでの実行回数 */
+  executions: number;
+}
+
+/** 直近の期間のCPU使用率・RSSの統計 */
+export interface ResourceHistory {
+  cpu_percent?: null | UsageSummary;
+  memory_bytes?: null | UsageSummary;
+  /** 標本の数 */
+  samples: number;
+  /** 期間内で最も古い標本の時刻（起動して間もない場合は期間より短い） */
+  since: string;
+  /** 統計に含める期間（秒） */
+  window_secs: number;
+}
+
+/**
+ * cgroupで課すリソース制限（Linuxのcgroup v2のみ）
+ *
+ * プロセスごとのcgroupの `memory.max` / `cpu.max` / `pids.max` に書き込みます。
+ * 子プロセスも含めたプロセスツリー全体に適用されます。
+ */
+export interface ResourceLimits {
+  /** CPU使用率の上限（%。100で1コア分） */
+  cpu_max_percent?: number | null;
+  /** メモリ使用量の上限（バイト）。超えるとOOM killerで強制終了される */
+  memory_max_bytes?: number | null;
+  /** プロセス（スレッド）数の上限 */
+  pids_max?: number | null;
+}
+
+/** CPU時間と見積もった消費電力量・電気代 */
+export interface ResourceUsage {
+  /** 積算したCPU秒（複数コアを使うと経過時間より大きくなる） */
+  cpu_seconds: number;
+  currency: string;
+  /** 見積もった消費電力量（Wh） */
+  energy_wh: number;
+  /** 見積もった電気代 */
+  estimated_cost: number;
+  /** 積算を始めた時刻 */
+  tracked_since: string;
+}
+
+/**
+ * MCPツールの応答の大きさの上限
+ *
+ * コンテキストの小さいエージェント向けに、上限を超える応答は件数と先頭の要素に要約し、
+ * 省いた部分を示す印を入れます。0 は無制限を表します（既定）。
+ */
+export interface ResponseBudget {
+  /** 応答1件あたりの最大文字数 */
+  max_chars?: number;
+  /** 配列に残す最大要素数 */
+  max_items?: number;
+}
+
+/** 再起動する終了の種類 */
+export type RestartMode = "never" | "on_failure" | "always";
+
+/**
+ * 終了したプロセスを自動で再起動する方針
+ *
+ * 停止要求による終了とメンテナンスウィンドウ中の終了では再起動しません。再起動の前に
+ * `backoff_ms` から再試行ごとに倍になる時間（`max_backoff_ms` まで）待ちます。
+ */
+export interface RestartPolicy {
+  /** 最初の再起動までの待ち時間（ミリ秒） */
+  backoff_ms?: number;
+  /** 待ち時間の上限（ミリ秒） */
+  max_backoff_ms?: number;
+  /** 続けて再起動する回数の上限（省略時は無制限） */
+  max_retries?: number | null;
+  /** 再起動する終了の種類 */
+  mode?: RestartMode;
+}
+
+/** 自動再起動の状態 */
+export interface RestartStatus {
+  /** 続けて再起動した回数 */
+  attempts: number;
+  /** 上限の回数に達して再起動をやめた時刻 */
+  gave_up_at?: string | null;
+  max_retries?: number | null;
+  /** 再起動を待っている場合、その予定時刻 */
+  next_restart_at?: string | null;
+}
+
+/** Webコンソールのロール（権限の弱い順） */
+export type Role = "viewer" | "operator" | "admin";
+
+/**
+ * 別のローカルアカウントでプロセスを起動する設定（Unixのみ）
+ *
+ * 起動時に setgid / setuid で指定したユーザー・グループに切り替えます。
+ * 現在のユーザー以外に切り替えるにはサーバーをrootで実行している必要があります。
+ */
+export interface RunAsConfig {
+  /** グループ名またはGID（省略時はユーザーのプライマリグループ） */
+  group?: string | null;
+  /** ユーザー名またはUID */
+  user: string;
+}
+
+/** プロセスの手順書（障害時の復旧手順などのMarkdown） */
+export interface Runbook {
+  /** Markdownファイルのパス（相対パスはプロセスの作業ディレクトリから解決する） */
+  path: string;
+} | {
+  /** 直接書いたMarkdown */
+  inline: string;
+}
+
+/** 読み込んだ手順書 */
+export interface RunbookContent {
+  markdown: string;
+  /** 読み込んだファイル（直接書いた場合はNone） */
+  path?: string | null;
+}
+
+/** セーフモードの状態 */
+export interface SafeModeStatus {
+  /** まだ復元していないプロセス */
+  pending: string[];
+  /** `safe_mode_restore` で復元したプロセス */
+  restored: string[];
+  /** セーフモードで起動した時刻 */
+  since: string;
+  /** 復元しなかったスナップショット */
+  snapshot?: string | null;
+}
+
+/** サンプリングの状態 */
+export type SamplingStatus = OutputSampling & {
+  /** 間引いた行数 */
+  dropped_lines: number;
+  /** 残した行数 */
+  kept_lines: number;
+};
+
+/** 定期実行の結果 */
+export interface ScheduleOutcome {
+  outcome: "started";
+  pid: number;
+} | {
+  outcome: "skipped";
+  reason: string;
+} | {
+  error: string;
+  outcome: "failed";
+}
+
+/** 定期実行の記録 */
+export type ScheduleRun = ScheduleOutcome & {
+  at: string;
+};
+
+/** 定期実行の状態 */
+export type ScheduleStatus = ProcessSchedule & {
+  last_run?: null | ScheduleRun;
+  /** 次に実行する時刻（無効にしている場合や1年以内に来ない場合はNone） */
+  next_run?: string | null;
+  /** 時刻になった回数（実行しなかった回を含む） */
+  run_count: number;
+};
+
+/** 値の型 */
+export type SchemaType = "string" | "integer" | "number" | "boolean";
+
+export interface ServerInfo {
+  current_time: number;
+  status: string;
+  uptime_seconds: number;
+  version: string;
+}
+
+export interface ServerStatus {
+  process_count: number;
+  safe_mode?: null | SafeModeStatus;
+  startup?: null | StartupReport;
+  status: string;
+  uptime_seconds: number;
+  version: string;
+}
+
+/** Set clipboard content (file) */
+export interface SetClipboardFileRequest {
+  content: string;
+  filename: string;
+  tags?: string[];
+}
+
+/** Set clipboard content (text) */
+export interface SetClipboardTextRequest {
+  content: string;
+  tags?: string[];
+}
+
+/** 設定のキーの新しい値 */
+export interface SetSettingBody {
+  /** キーの型の値（nullを指定できるキーではnullで未設定に戻す） */
+  value: Record<string, unknown>;
+}
+
+/** 設定の変更（変わったキーごと） */
+export interface SettingChange {
+  changed_at: string;
+  key: string;
+  new_value: Record<string, unknown>;
+  old_value: Record<string, unknown>;
+}
+
+/** 設定のキーの型・範囲・既定値 */
+export interface SettingSchema {
+  default: Record<string, unknown>;
+  description: string;
+  key: string;
+  maximum?: number | null;
+  minimum?: number | null;
+  /** nullを指定できる（未設定を表す） */
+  nullable: boolean;
+  type: SettingType;
+  /** `enum` で選べる値 */
+  values?: string[];
+}
+
+/** 設定の値の型 */
+export type SettingType = "boolean" | "integer" | "number" | "string" | "enum" | "array" | "object";
+
+/** キーの現在の値と既定値 */
+export interface SettingValue {
+  default: Record<string, unknown>;
+  key: string;
+  value: Record<string, unknown>;
+}
+
+export interface Settings {
+  /** 承認を必要とするツール（未指定の場合は現在の値を維持） */
+  approval_tools?: string[] | null;
+  /** 外部で起動したプロセスを自動で引き取るワークスペース（未指定の場合は現在の値を維持） */
+  auto_adopt_workspaces?: string[] | null;
+  auto_refresh: boolean;
+  /** 障害注入の許可（未指定の場合は現在の値を維持） */
+  chaos_enabled?: boolean | null;
+  color_mode: string;
+  /** 確認を待つ秒数（未指定の場合は現在の値を維持） */
+  confirm_timeout_secs?: number | null;
+  /** 呼び出しを止めて確認を待つツール（未指定の場合は現在の値を維持） */
+  confirm_tools?: string[] | null;
+  energy_cost?: null | EnergyCostSettings;
+  log_quota?: LogQuotaSettings;
+  /** すべてのプロセスの出力の転送先（未指定の場合は現在の値を維持） */
+  log_sinks?: LogSinkConfig[] | null;
+  /** メンテナンスウィンドウ（未指定の場合は現在の値を維持） */
+  maintenance_windows?: MaintenanceWindow[] | null;
+  /** mDNSによる告知（未指定の場合は現在の値を維持） */
+  mdns_enabled?: boolean | null;
+  /** 通知の送り先のルール（未指定の場合は現在の値を維持） */
+  notification_routes?: NotificationRoute[] | null;
+  output_memory?: null | OutputMemorySettings;
+  /** すべてのプロセスの出力を伏せるルール（未指定の場合は現在の値を維持） */
+  redaction_rules?: RedactionRule[] | null;
+  refresh_interval: number;
+  response_budget?: null | ResponseBudget;
+  /** ワークスペースごとのクォータ（未指定の場合は現在の値を維持） */
+  workspace_quotas?: WorkspaceQuota[] | null;
+}
+
+/**
+ * 自動起動・一括起動での起動順序
+ *
+ * `priority` の小さい順に起動し、同じ優先度で同じ `group` のプロセスは並列に起動します。
+ */
+export interface StartupOrder {
+  /** 並列に起動するグループ（未指定の場合は1つずつ起動する） */
+  group?: string | null;
+  /** 起動の優先度（小さいほど先に起動する、既定は0） */
+  priority?: number;
+  /** 接続を受け付けたら準備完了とみなすポート（未指定の場合は起動した時点で準備完了） */
+  ready_port?: number | null;
+  /** 準備完了を待つ秒数（既定は30秒） */
+  ready_timeout_secs?: number | null;
+}
+
+/** 順序に従った起動の結果 */
+export interface StartupReport {
+  processes: StartupResult[];
+  started_at: string;
+  /** すべてのプロセスが準備完了になる（または失敗する）までの時間 */
+  total_ms: number;
+}
+
+/** プロセスごとの起動の結果 */
+export interface StartupResult {
+  /** 何番目にまとめて起動したか（0始まり） */
+  batch: number;
+  /** 起動または準備完了の確認に失敗した理由 */
+  error?: string | null;
+  group?: string | null;
+  pid?: number | null;
+  priority: number;
+  process_id: string;
+  ready: boolean;
+  /** 全体の開始から準備完了になるまでの時間 */
+  ready_after_ms?: number | null;
+  /** 全体の開始から起動するまでの時間 */
+  started_after_ms?: number | null;
+}
+
+export interface StopProcessRequest {
+  grace_period_ms?: number | null;
+}
+
+export interface SystemMetrics {
+  cpu_usage: number;
+  memory_usage: number;
+  process_manager_memory: number;
+}
+
+/** テンプレート変数の定義 */
+export interface TemplateVariable {
+  /** デフォルト値 */
+  default_value?: string | null;
+  /** 変数の説明 */
+  description?: string | null;
+  /** 値の例 */
+  example?: string | null;
+  /** 変数名（${VAR_NAME} の形式で使用） */
+  name: string;
+  /** 必須かどうか */
+  required: boolean;
+  schema?: null | VariableSchema;
+  /** 変数の型ヒント */
+  var_type?: string | null;
+}
+
+/** タイムラインの1つの項目 */
+export interface TimelineEntry {
+  /** 元になった記録の内容 */
+  details?: unknown;
+  /** 期間の長さ（継続中の期間は現在まで、時点の項目はNone） */
+  duration_ms?: number | null;
+  /** 期間の終わり（時点の項目と継続中の期間はNone） */
+  end?: string | null;
+  /** 表示用の短い説明 */
+  label: string;
+  lane: TimelineLane;
+  /** 継続中の期間か */
+  ongoing?: boolean;
+  /** 項目が属する実行の通し番号 */
+  run?: number | null;
+  start: string;
+  /** 結果（実行は running・succeeded・failed・stopped） */
+  status?: string | null;
+}
+
+/** タイムラインの行 */
+export type TimelineLane = "run" | "restart" | "health" | "pause" | "maintenance" | "event" | "annotation" | "ci";
+
+/** タイムラインの集計 */
+export interface TimelineSummary {
+  ci_failures: number;
+  /** 異常終了した実行 */
+  failed_runs: number;
+  restarts: number;
+  /** 実行していた時間の合計 */
+  running_ms: number;
+  runs: number;
+  /** ヘルスチェックで異常だった時間の合計 */
+  unhealthy_ms: number;
+}
+
+/**
+ * tmuxバックエンドの設定
+ *
+ * プロセスを指定したセッションのウィンドウ内で起動し、ターミナルから接続できるようにします。
+ */
+export interface TmuxConfig {
+  /** セッション名（存在しなければ作成） */
+  session: string;
+  /** ウィンドウ名（省略時はプロセスID） */
+  window?: string | null;
+}
+
+/**
+ * トンネル（ポートフォワード）の定義
+ *
+ * `ssh -L` / `ssh -R` / cloudflaredのトンネルをプロセスと同じように起動・停止して管理します。
+ * `process_id` を指定すると、そのプロセスのステータスにトンネルの状態と公開URLが表示されます。
+ */
+export interface TunnelConfig {
+  /** ローカル側のポート（ssh_localでは待ち受けるポート、それ以外では公開するポート） */
+  local_port: number;
+  /** 状態と公開URLを表示するプロセス */
+  process_id?: string | null;
+  /** トンネルの種類 */
+  provider: TunnelProvider;
+  /** 公開URL（cloudflaredのQuick Tunnelでは出力から自動的に取得） */
+  public_url?: string | null;
+  /** リモート側（ssh_localでは転送先の `host:port`、ssh_remoteではSSHサーバーで待ち受ける `[bind:]port`） */
+  remote?: string | null;
+  /** SSHの接続先（例: `user@bastion`。sshのみ） */
+  ssh_host?: string | null;
+  /** トンネルの一意識別子 */
+  tunnel_id: string;
+}
+
+/** トンネルの種類 */
+export type TunnelProvider = "ssh_local" | "ssh_remote" | "cloudflared";
+
+/** トンネルの状態 */
+export type TunnelState = "Stopped" | {
+  Running: {
+    pid: number;
+    started_at: string;
+  };
+} | {
+  Failed: {
+    error: string;
+    failed_at: string;
+  };
+};
+
+/** トンネルのステータス */
+export type TunnelStatus = TunnelConfig & {
+  /**
+   * ヘルスチェックの結果（実行中の場合のみ）
+   *
+   * ssh_localは待ち受けポートに接続できるか、cloudflaredは公開URLを取得できたかで判定する。
+   */
+  healthy?: boolean | null;
+  /** 公開URL（設定値、またはcloudflaredの出力から取得したもの） */
+  public_url?: string | null;
+  /** 最近の出力（接続エラーの確認用） */
+  recent_output: string[];
+  state: TunnelState;
+};
+
+/** Request to update process attributes */
+export interface UpdateProcessRequest {
+  /** Optional: Update args */
+  args?: string[] | null;
+  /** Optional: Update auto_start_on_restore flag */
+  auto_start_on_restore?: boolean | null;
+  /** Optional: Update command */
+  command?: string | null;
+  /** Optional: Update working directory (`~` and `$VAR` are expanded) */
+  cwd?: string | null;
+  /** Optional: Update environment variables */
+  env?: Record<string, string> | null;
+  id: string;
+  /** Optional: Set metadata keys; a null value removes the key and unspecified keys are kept */
+  metadata?: Record<string, string | null> | null;
+  /** Optional: Names to encrypt, from the new `env` or the current one (sensitive variables not in a new `env` are kept; a name given in a new `env` becomes plain unless listed here) */
+  sensitive_env?: string[] | null;
+}
+
+/** 定期実行の変更 */
+export interface UpdateScheduleRequest {
+  cron?: string | null;
+  enabled?: boolean | null;
+}
+
+export interface UpdateTemplateRequest {
+  args?: string[] | null;
+  category?: string | null;
+  command?: string | null;
+  default_auto_start?: boolean | null;
+  default_cwd?: string | null;
+  description?: string | null;
+  env?: Record<string, string> | null;
+  name?: string | null;
+  tags?: string[] | null;
+  variables?: TemplateVariable[] | null;
+}
+
+/** 最小・最大・平均 */
+export interface UsageSummary {
+  avg: number;
+  max: number;
+  min: number;
+}
+
+/** 変数の値のスキーマ（JSON Schemaのキーワードのうち、値の検証に使うもの） */
+export interface VariableSchema {
+  /** 取りうる値 */
+  enum?: string[];
+  /** 文字数の上限 */
+  maxLength?: number | null;
+  /** 数値の上限（`integer`・`number` のみ） */
+  maximum?: number | null;
+  /** 文字数の下限 */
+  minLength?: number | null;
+  /** 数値の下限（`integer`・`number` のみ） */
+  minimum?: number | null;
+  /** 値が一致すべき正規表現（部分一致、全体に一致させる場合は `^...$`） */
+  pattern?: string | null;
+  type?: null | SchemaType;
+}
+
+/** 1つの期間の頻度 */
+export interface WindowRates {
+  /** エラーの行（stdout・stderrの両方） */
+  error_lines: number;
+  errors_per_minute: number;
+  stderr_lines: number;
+  stderr_per_minute: number;
+  /** 出力の行のうちstderrの割合（出力がない場合はNone） */
+  stderr_ratio?: number | null;
+  stdout_lines: number;
+  stdout_per_minute: number;
+}
+
+/**
+ * ワークスペースごとのクォータ
+ *
+ * 作業ディレクトリが `path` 以下にあるプロセスに適用します。0 は無制限を表します。
+ */
+export interface WorkspaceQuota {
+  /** ログのディスク使用量の上限（バイト）。超えている間は起動できない */
+  max_log_bytes?: number;
+  /** 登録できるプロセス数の上限 */
+  max_processes?: number;
+  /** 同時に実行できるプロセス数の上限 */
+  max_running?: number;
+  /** ワークスペースのディレクトリ（絶対パス） */
+  path: string;
+}
+
+/** Vantage Web API 0.2.3 のクライアント（baseURLは `/api`） */
+export function createApiClient(http: AxiosInstance) {
+  return {
+    async listApprovals(query?: { status?: ApprovalStatus }): Promise<ApprovalRequest[]> {
+      const { data } = await http.get<ApprovalRequest[]>(`/approvals`, { params: query });
+      return data;
+    },
+    async getApproval(id: string): Promise<ApprovalRequest> {
+      const { data } = await http.get<ApprovalRequest>(`/approvals/${encodeURIComponent(id)}`);
+      return data;
+    },
+    async approveApproval(id: string, body?: null | ApprovalDecision): Promise<ApprovalRequest> {
+      const { data } = await http.post<ApprovalRequest>(`/approvals/${encodeURIComponent(id)}/approve`, body);
+      return data;
+    },
+    async denyApproval(id: string, body?: null | ApprovalDecision): Promise<ApprovalRequest> {
+      const { data } = await http.post<ApprovalRequest>(`/approvals/${encodeURIComponent(id)}/deny`, body);
+      return data;
+    },
+    /** ログイン中のユーザー（認証が無効な場合は204） */
+    async currentUser(): Promise<CurrentUser> {
+      const { data } = await http.get<CurrentUser>(`/auth/me`);
+      return data;
+    },
+    async listChains(query?: { process_id?: string }): Promise<ChainStatus[]> {
+      const { data } = await http.get<ChainStatus[]>(`/chains`, { params: query });
+      return data;
+    },
+    async setChainEnabled(id: string, body: ChainEnabledRequest): Promise<ChainStatus> {
+      const { data } = await http.patch<ChainStatus>(`/chains/${encodeURIComponent(id)}`, body);
+      return data;
+    },
+    async removeChain(id: string): Promise<void> {
+      const { data } = await http.delete<void>(`/chains/${encodeURIComponent(id)}`);
+      return data;
+    },
+    /** Get the latest clipboard item */
+    async getClipboard(): Promise<ClipboardResponse> {
+      const { data } = await http.get<ClipboardResponse>(`/clipboard`);
+      return data;
+    },
+    /** Clear all clipboard items */
+    async clearClipboard(): Promise<void> {
+      const { data } = await http.delete<void>(`/clipboard`);
+      return data;
+    },
+    /** Set clipboard file content */
+    async setClipboardFile(body: SetClipboardFileRequest): Promise<ClipboardResponse> {
+      const { data } = await http.post<ClipboardResponse>(`/clipboard/file`, body);
+      return data;
+    },
+    async getClipboardHistory(query?: { limit?: number }): Promise<ClipboardHistoryResponse> {
+      const { data } = await http.get<ClipboardHistoryResponse>(`/clipboard/history`, { params: query });
+      return data;
+    },
+    /** Search clipboard items */
+    async searchClipboard(query: { query: string; limit?: number }): Promise<ClipboardHistoryResponse> {
+      const { data } = await http.get<ClipboardHistoryResponse>(`/clipboard/search`, { params: query });
+      return data;
+    },
+    /** Set clipboard text content */
+    async setClipboardText(body: SetClipboardTextRequest): Promise<ClipboardResponse> {
+      const { data } = await http.post<ClipboardResponse>(`/clipboard/text`, body);
+      return data;
+    },
+    async getDashboard(): Promise<DashboardData> {
+      const { data } = await http.get<DashboardData>(`/dashboard`);
+      return data;
+    },
+    async getDiagnostics(): Promise<Record<string, unknown>> {
+      const { data } = await http.get<Record<string, unknown>>(`/diagnostics`);
+      return data;
+    },
+    /** ゲートの状態を返す（準備完了の場合だけ200） */
+    async checkGate(processId: string, query?: { wait?: number }): Promise<GateStatus> {
+      const { data } = await http.get<GateStatus>(`/gate/${encodeURIComponent(processId)}`, { params: query });
+      return data;
+    },
+    /** Webhookを呼び出す */
+    async triggerHook(name: string, body: Record<string, unknown>): Promise<HookResponse> {
+      const { data } = await http.post<HookResponse>(`/hooks/${encodeURIComponent(name)}`, body);
+      return data;
+    },
+    async listNotifications(query?: { unread_only?: boolean; kind?: NotificationKind; limit?: number }): Promise<Notification[]> {
+      const { data } = await http.get<Notification[]>(`/notifications`, { params: query });
+      return data;
+    },
+    async getNotificationCount(): Promise<NotificationCount> {
+      const { data } = await http.get<NotificationCount>(`/notifications/count`);
+      return data;
+    },
+    async markNotificationsRead(body: MarkNotificationsReadRequest): Promise<MarkNotificationsReadResponse> {
+      const { data } = await http.post<MarkNotificationsReadResponse>(`/notifications/read`, body);
+      return data;
+    },
+    async listProcesses(query?: { state?: string; name_pattern?: string; expression?: string }): Promise<ProcessInfo[]> {
+      const { data } = await http.get<ProcessInfo[]>(`/processes`, { params: query });
+      return data;
+    },
+    async createProcess(body: CreateProcessRequest): Promise<MessageResponse> {
+      const { data } = await http.post<MessageResponse>(`/processes`, body);
+      return data;
+    },
+    async processListChanges(query?: { since?: number }): Promise<void> {
+      const { data } = await http.get<void>(`/processes/changes`, { params: query });
+      return data;
+    },
+    async getProcess(id: string): Promise<ProcessStatus> {
+      const { data } = await http.get<ProcessStatus>(`/processes/${encodeURIComponent(id)}`);
+      return data;
+    },
+    /** Update process attributes */
+    async updateProcess(id: string, body: UpdateProcessRequest): Promise<void> {
+      const { data } = await http.put<void>(`/processes/${encodeURIComponent(id)}`, body);
+      return data;
+    },
+    async removeProcess(id: string): Promise<void> {
+      const { data } = await http.delete<void>(`/processes/${encodeURIComponent(id)}`);
+      return data;
+    },
+    async listOutputBookmarks(id: string): Promise<OutputBookmark[]> {
+      const { data } = await http.get<OutputBookmark[]>(`/processes/${encodeURIComponent(id)}/bookmarks`);
+      return data;
+    },
+    async addOutputBookmark(id: string, body: AddBookmarkBody): Promise<OutputBookmark> {
+      const { data } = await http.post<OutputBookmark>(`/processes/${encodeURIComponent(id)}/bookmarks`, body);
+      return data;
+    },
+    async removeOutputBookmark(id: string, name: string): Promise<void> {
+      const { data } = await http.delete<void>(`/processes/${encodeURIComponent(id)}/bookmarks/${encodeURIComponent(name)}`);
+      return data;
+    },
+    async updateProcessConfig(id: string, body: ProcessConfigUpdate): Promise<void> {
+      const { data } = await http.patch<void>(`/processes/${encodeURIComponent(id)}/config`, body);
+      return data;
+    },
+    async getProcessLines(id: string, query?: { stream?: string; lines?: number; cursor?: number; since?: string }): Promise<FollowedLines> {
+      const { data } = await http.get<FollowedLines>(`/processes/${encodeURIComponent(id)}/lines`, { params: query });
+      return data;
+    },
+    async getProcessLogs(id: string, query?: { stream?: string; lines?: number; timestamps?: boolean; from_bookmark?: string; to_bookmark?: string }): Promise<string[]> {
+      const { data } = await http.get<string[]>(`/processes/${encodeURIComponent(id)}/logs`, { params: query });
+      return data;
+    },
+    async listOutputRecordings(id: string): Promise<RecordingSummary[]> {
+      const { data } = await http.get<RecordingSummary[]>(`/processes/${encodeURIComponent(id)}/recordings`);
+      return data;
+    },
+    async getOutputRecording(id: string, name: string): Promise<Recording> {
+      const { data } = await http.get<Recording>(`/processes/${encodeURIComponent(id)}/recordings/${encodeURIComponent(name)}`);
+      return data;
+    },
+    /** Get the runbook of a process (the file is read on each request) */
+    async getProcessRunbook(id: string): Promise<RunbookContent> {
+      const { data } = await http.get<RunbookContent>(`/processes/${encodeURIComponent(id)}/runbook`);
+      return data;
+    },
+    async setOutputSampling(id: string, body: OutputSampling): Promise<SamplingStatus> {
+      const { data } = await http.put<SamplingStatus>(`/processes/${encodeURIComponent(id)}/sampling`, body);
+      return data;
+    },
+    async disableOutputSampling(id: string): Promise<void> {
+      const { data } = await http.delete<void>(`/processes/${encodeURIComponent(id)}/sampling`);
+      return data;
+    },
+    async startProcess(id: string): Promise<MessageResponse> {
+      const { data } = await http.post<MessageResponse>(`/processes/${encodeURIComponent(id)}/start`);
+      return data;
+    },
+    async stopProcess(id: string, body?: null | StopProcessRequest): Promise<void> {
+      const { data } = await http.post<void>(`/processes/${encodeURIComponent(id)}/stop`, body);
+      return data;
+    },
+    async captureTemplate(id: string, body: CaptureTemplateRequest): Promise<CapturedTemplate> {
+      const { data } = await http.post<CapturedTemplate>(`/processes/${encodeURIComponent(id)}/template`, body);
+      return data;
+    },
+    /** Get the timeline of a process (runs, restarts, health, pauses, maintenance, changes and CI failures) */
+    async getProcessTimeline(id: string, query?: { from?: string; to?: string }): Promise<ProcessTimeline> {
+      const { data } = await http.get<ProcessTimeline>(`/processes/${encodeURIComponent(id)}/timeline`, { params: query });
+      return data;
+    },
+    async listSchedules(query?: { process_id?: string }): Promise<ScheduleStatus[]> {
+      const { data } = await http.get<ScheduleStatus[]>(`/schedules`, { params: query });
+      return data;
+    },
+    async createSchedule(body: CreateScheduleRequest): Promise<ScheduleStatus> {
+      const { data } = await http.post<ScheduleStatus>(`/schedules`, body);
+      return data;
+    },
+    async updateSchedule(id: string, body: UpdateScheduleRequest): Promise<ScheduleStatus> {
+      const { data } = await http.patch<ScheduleStatus>(`/schedules/${encodeURIComponent(id)}`, body);
+      return data;
+    },
+    async removeSchedule(id: string): Promise<void> {
+      const { data } = await http.delete<void>(`/schedules/${encodeURIComponent(id)}`);
+      return data;
+    },
+    async getSettings(): Promise<Settings> {
+      const { data } = await http.get<Settings>(`/settings`);
+      return data;
+    },
+    async updateSettings(body: Settings): Promise<void> {
+      const { data } = await http.put<void>(`/settings`, body);
+      return data;
+    },
+    async getSettingsSchema(): Promise<SettingSchema[]> {
+      const { data } = await http.get<SettingSchema[]>(`/settings/schema`);
+      return data;
+    },
+    async getSetting(key: string): Promise<SettingValue> {
+      const { data } = await http.get<SettingValue>(`/settings/${encodeURIComponent(key)}`);
+      return data;
+    },
+    async setSetting(key: string, body: SetSettingBody): Promise<SettingChange> {
+      const { data } = await http.put<SettingChange>(`/settings/${encodeURIComponent(key)}`, body);
+      return data;
+    },
+    async getStatus(): Promise<ServerStatus> {
+      const { data } = await http.get<ServerStatus>(`/status`);
+      return data;
+    },
+    async listTemplates(query?: { category?: string; tags?: string }): Promise<ProcessTemplate[]> {
+      const { data } = await http.get<ProcessTemplate[]>(`/templates`, { params: query });
+      return data;
+    },
+    async createTemplate(body: CreateTemplateRequest): Promise<MessageResponse> {
+      const { data } = await http.post<MessageResponse>(`/templates`, body);
+      return data;
+    },
+    async getTemplate(id: string): Promise<ProcessTemplate> {
+      const { data } = await http.get<ProcessTemplate>(`/templates/${encodeURIComponent(id)}`);
+      return data;
+    },
+    async updateTemplate(id: string, body: UpdateTemplateRequest): Promise<void> {
+      const { data } = await http.put<void>(`/templates/${encodeURIComponent(id)}`, body);
+      return data;
+    },
+    async deleteTemplate(id: string): Promise<void> {
+      const { data } = await http.delete<void>(`/templates/${encodeURIComponent(id)}`);
+      return data;
+    },
+    async instantiateTemplate(id: string, body: InstantiateTemplateRequest): Promise<MessageResponse> {
+      const { data } = await http.post<MessageResponse>(`/templates/${encodeURIComponent(id)}/instantiate`, body);
+      return data;
+    },
+    async getTemplateSchema(id: string): Promise<Record<string, unknown>> {
+      const { data } = await http.get<Record<string, unknown>>(`/templates/${encodeURIComponent(id)}/schema`);
+      return data;
+    },
+  };
+}
+
+export type ApiClient = ReturnType<typeof createApiClient>;
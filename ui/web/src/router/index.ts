@@ -26,6 +26,16 @@ const routes: RouteRecordRaw[] = [
     name: 'clipboard',
     component: () => import('@/views/ClipboardView.vue'),
   },
+  {
+    path: '/approvals',
+    name: 'approvals',
+    component: () => import('@/views/ApprovalsView.vue'),
+  },
+  {
+    path: '/chains',
+    name: 'chains',
+    component: () => import('@/views/ChainsView.vue'),
+  },
   {
     path: '/:pathMatch(.*)*',
     name: 'not-found',
@@ -0,0 +1,84 @@
+import { defineStore } from 'pinia';
+import { ref, computed } from 'vue';
+import type { ApprovalRequest } from '@/types';
+import apiClient from '@/api/client';
+
+export const useApprovalStore = defineStore('approval', () => {
+  // State
+  const approvals = ref<ApprovalRequest[]>([]);
+  const loading = ref(false);
+  const error = ref<string | null>(null);
+
+  // Auto-refresh interval
+  let refreshInterval: number | null = null;
+
+  // Computed
+  const pending = computed(() =>
+    approvals.value.filter(a => a.status === 'pending')
+  );
+
+  const decided = computed(() =>
+    approvals.value.filter(a => a.status !== 'pending')
+  );
+
+  const pendingCount = computed(() => pending.value.length);
+
+  // Actions
+  async function loadApprovals() {
+    loading.value = true;
+    error.value = null;
+    try {
+      approvals.value = await apiClient.getApprovals();
+    } catch (e: any) {
+      error.value = e.message || 'Failed to load approvals';
+      console.error('Failed to load approvals:', e);
+    } finally {
+      loading.value = false;
+    }
+  }
+
+  async function approve(id: string, reason?: string) {
+    const result = await apiClient.approve(id, reason);
+    await loadApprovals();
+    return result;
+  }
+
+  async function deny(id: string, reason?: string) {
+    const result = await apiClient.deny(id, reason);
+    await loadApprovals();
+    return result;
+  }
+
+  function startAutoRefresh(interval = 5000) {
+    stopAutoRefresh();
+    refreshInterval = window.setInterval(() => {
+      loadApprovals();
+    }, interval);
+  }
+
+  function stopAutoRefresh() {
+    if (refreshInterval !== null) {
+      window.clearInterval(refreshInterval);
+      refreshInterval = null;
+    }
+  }
+
+  return {
+    // State
+    approvals,
+    loading,
+    error,
+
+    // Computed
+    pending,
+    decided,
+    pendingCount,
+
+    // Actions
+    loadApprovals,
+    approve,
+    deny,
+    startAutoRefresh,
+    stopAutoRefresh,
+  };
+});
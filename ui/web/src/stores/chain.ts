@@ -0,0 +1,88 @@
+import { defineStore } from 'pinia';
+import { ref, computed } from 'vue';
+import type { ChainStatus } from '@/types';
+import apiClient from '@/api/client';
+
+export const useChainStore = defineStore('chain', () => {
+  // State
+  const chains = ref<ChainStatus[]>([]);
+  const loading = ref(false);
+  const error = ref<string | null>(null);
+
+  // Auto-refresh interval
+  let refreshInterval: number | null = null;
+
+  // Computed
+  // 連鎖をたどったプロセスIDの列（起点から終点まで）。連鎖は循環しないため必ず終わる
+  const flows = computed(() => {
+    const targets = new Set(chains.value.map(c => c.action.process_id));
+    const roots = [...new Set(chains.value.map(c => c.after))].filter(id => !targets.has(id));
+    const result: string[][] = [];
+    const walk = (path: string[]) => {
+      const next = chains.value.filter(c => c.after === path[path.length - 1]);
+      if (next.length === 0) {
+        result.push(path);
+        return;
+      }
+      next.forEach(c => walk([...path, c.action.process_id]));
+    };
+    roots.sort().forEach(root => walk([root]));
+    return result;
+  });
+
+  // Actions
+  async function loadChains() {
+    loading.value = true;
+    error.value = null;
+    try {
+      chains.value = await apiClient.getChains();
+    } catch (e: any) {
+      error.value = e.message || 'Failed to load chains';
+      console.error('Failed to load chains:', e);
+    } finally {
+      loading.value = false;
+    }
+  }
+
+  async function setEnabled(id: string, enabled: boolean) {
+    const result = await apiClient.setChainEnabled(id, enabled);
+    await loadChains();
+    return result;
+  }
+
+  async function removeChain(id: string) {
+    await apiClient.removeChain(id);
+    await loadChains();
+  }
+
+  function startAutoRefresh(interval = 5000) {
+    stopAutoRefresh();
+    refreshInterval = window.setInterval(() => {
+      loadChains();
+    }, interval);
+  }
+
+  function stopAutoRefresh() {
+    if (refreshInterval !== null) {
+      window.clearInterval(refreshInterval);
+      refreshInterval = null;
+    }
+  }
+
+  return {
+    // State
+    chains,
+    loading,
+    error,
+
+    // Computed
+    flows,
+
+    // Actions
+    loadChains,
+    setEnabled,
+    removeChain,
+    startAutoRefresh,
+    stopAutoRefresh,
+  };
+});
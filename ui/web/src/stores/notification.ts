@@ -0,0 +1,69 @@
+import { defineStore } from 'pinia';
+import { ref } from 'vue';
+import type { Notification } from '@/types';
+import apiClient from '@/api/client';
+
+export const useNotificationStore = defineStore('notification', () => {
+  // State
+  const notifications = ref<Notification[]>([]);
+  const unreadCount = ref(0);
+  const loading = ref(false);
+
+  // Auto-refresh interval
+  let refreshInterval: number | null = null;
+
+  // Actions
+  async function loadCount() {
+    try {
+      unreadCount.value = await apiClient.getUnreadNotificationCount();
+    } catch (e) {
+      console.error('Failed to load notification count:', e);
+    }
+  }
+
+  async function loadNotifications(limit = 20) {
+    loading.value = true;
+    try {
+      notifications.value = await apiClient.getNotifications(false, limit);
+    } catch (e) {
+      console.error('Failed to load notifications:', e);
+    } finally {
+      loading.value = false;
+    }
+  }
+
+  async function markRead(ids?: string[]) {
+    const result = await apiClient.markNotificationsRead(ids);
+    unreadCount.value = result.unread;
+    await loadNotifications();
+  }
+
+  function startAutoRefresh(interval = 10000) {
+    stopAutoRefresh();
+    loadCount();
+    refreshInterval = window.setInterval(() => {
+      loadCount();
+    }, interval);
+  }
+
+  function stopAutoRefresh() {
+    if (refreshInterval !== null) {
+      window.clearInterval(refreshInterval);
+      refreshInterval = null;
+    }
+  }
+
+  return {
+    // State
+    notifications,
+    unreadCount,
+    loading,
+
+    // Actions
+    loadCount,
+    loadNotifications,
+    markRead,
+    startAutoRefresh,
+    stopAutoRefresh,
+  };
+});
@@ -94,9 +94,9 @@ export const useProcessStore = defineStore('process', () => {
     }
   }
 
-  async function getProcessOutput(id: string) {
+  async function getProcessOutput(id: string, cursor?: number) {
     try {
-      return await apiClient.getProcessOutput(id);
+      return await apiClient.getProcessOutput(id, { cursor });
     } catch (e: any) {
       error.value = e.message || `Failed to get output for process ${id}`;
       throw e;
@@ -122,14 +122,87 @@ export const useProcessStore = defineStore('process', () => {
     error.value = null;
   }
 
+  // Live updates: the server sends a snapshot and then only the changed processes
+  // (see GET /api/processes/changes). Polling is used only while the feed is down.
+  let feed: WebSocket | null = null;
+  let feedSeq: number | null = null;
+  let reconnectTimer: number | null = null;
+
+  type FeedMessage =
+    | { type: 'snapshot'; seq: number; processes: ProcessInfo[] }
+    | { type: 'delta'; seq: number; added: ProcessInfo[]; updated: ProcessInfo[]; removed: string[] };
+
+  function applyFeedMessage(message: FeedMessage) {
+    if (message.type === 'snapshot') {
+      processes.value = message.processes;
+      feedSeq = message.seq;
+      return;
+    }
+    if (feedSeq !== null && message.seq !== feedSeq + 1) {
+      // A delta was missed; ask for the whole list again
+      feed?.send(JSON.stringify({ type: 'resync' }));
+      return;
+    }
+    const removed = new Set(message.removed);
+    const changed = new Map(
+      [...message.added, ...message.updated].map((process) => [process.id, process])
+    );
+    const next = processes.value
+      .filter((process) => !removed.has(process.id))
+      .map((process) => changed.get(process.id) ?? process);
+    for (const process of message.added) {
+      if (!next.some((p) => p.id === process.id)) {
+        next.push(process);
+      }
+    }
+    processes.value = next;
+    feedSeq = message.seq;
+  }
+
+  function connectFeed() {
+    if (feed || typeof WebSocket === 'undefined') {
+      return;
+    }
+    const protocol = window.location.protocol === 'https:' ? 'wss' : 'ws';
+    const since = feedSeq !== null ? `?since=${feedSeq}` : '';
+    const socket = new WebSocket(`${protocol}://${window.location.host}/api/processes/changes${since}`);
+    socket.onmessage = (event) => applyFeedMessage(JSON.parse(event.data) as FeedMessage);
+    socket.onclose = () => {
+      if (feed !== socket) {
+        return;
+      }
+      feed = null;
+      if (refreshInterval !== null) {
+        reconnectTimer = window.setTimeout(() => {
+          reconnectTimer = null;
+          connectFeed();
+        }, 2000);
+      }
+    };
+    feed = socket;
+  }
+
+  function disconnectFeed() {
+    if (reconnectTimer !== null) {
+      window.clearTimeout(reconnectTimer);
+      reconnectTimer = null;
+    }
+    const socket = feed;
+    feed = null;
+    socket?.close();
+  }
+
   // Auto-refresh functionality
   let refreshInterval: number | null = null;
 
   function startAutoRefresh(interval = 5000) {
     stopAutoRefresh();
     refreshInterval = window.setInterval(() => {
-      loadProcesses();
+      if (feed?.readyState !== WebSocket.OPEN) {
+        loadProcesses();
+      }
     }, interval);
+    connectFeed();
   }
 
   function stopAutoRefresh() {
@@ -137,6 +210,7 @@ export const useProcessStore = defineStore('process', () => {
       window.clearInterval(refreshInterval);
       refreshInterval = null;
     }
+    disconnectFeed();
   }
 
   return {
@@ -2,6 +2,7 @@
 export type ProcessState = 
   | 'NotStarted'
   | { Running: { pid: number; started_at: string } }
+  | { Paused: { pid: number; started_at: string; paused_at: string } }
   | { Stopped: { exit_code?: number; stopped_at: string } }
   | { Failed: { error: string; failed_at: string } };
 
@@ -15,6 +16,32 @@ export interface ProcessInfo {
   env?: Record<string, string>;
   auto_start_on_create?: boolean;
   auto_start_on_restore?: boolean;
+  tags?: string[];
+  metadata?: Record<string, string>;
+  runbook?: Runbook;
+}
+
+// Runbook attached to a process (a Markdown file or inline text)
+export type Runbook = { path: string } | { inline: string };
+
+export interface RunbookContent {
+  path?: string;
+  markdown: string;
+}
+
+// Output lines with capture metadata
+export interface LogLine {
+  text: string;
+  timestamp: string;
+  seq: number;
+  stream: 'stdout' | 'stderr';
+}
+
+export interface FollowedLines {
+  lines: LogLine[];
+  cursor: number;
+  more: boolean;
+  truncated: boolean;
 }
 
 // Template types
@@ -70,6 +97,75 @@ export interface ClipboardHistoryResponse {
   items: ClipboardItem[];
 }
 
+// Approval types
+export type ApprovalStatus = 'pending' | 'approved' | 'denied' | 'executed' | 'failed' | 'expired';
+
+export interface ApprovalRequest {
+  approval_id: string;
+  tool: string;
+  arguments: Record<string, any>;
+  summary: string;
+  status: ApprovalStatus;
+  requested_at: string;
+  decided_at?: string;
+  decided_by?: string;
+  reason?: string;
+  executed_at?: string;
+  result?: string;
+  // エージェントの呼び出しが判断を待っている
+  waiting?: boolean;
+  expires_at?: string;
+}
+
+// Notification inbox types
+export type NotificationKind =
+  | 'process_failed'
+  | 'crash_loop'
+  | 'process_unhealthy'
+  | 'ci_failed'
+  | 'approval_pending';
+
+export interface Notification {
+  notification_id: string;
+  kind: NotificationKind;
+  title: string;
+  message: string;
+  process_id?: string;
+  // CIの実行のURL、または承認リクエストのID
+  link?: string;
+  created_at: string;
+  read: boolean;
+  read_at?: string;
+}
+
+// Process chain types
+export type ChainAction =
+  | { type: 'start_process'; process_id: string }
+  | {
+      type: 'run_template';
+      template_id: string;
+      process_id: string;
+      values?: Record<string, string>;
+    };
+
+export type ChainOutcome =
+  | { outcome: 'started'; pid: number }
+  | { outcome: 'skipped'; reason: string }
+  | { outcome: 'failed'; error: string }
+  | { outcome: 'suppressed' };
+
+export type ChainRun = ChainOutcome & { at: string };
+
+export interface ChainStatus {
+  chain_id: string;
+  after: string;
+  action: ChainAction;
+  enabled: boolean;
+  created_at: string;
+  run_count: number;
+  last_run?: ChainRun;
+}
+
 // API Response types
 export interface ApiResponse<T> {
   data: T;
@@ -81,6 +177,10 @@ export function isRunning(state: ProcessState): boolean {
   return typeof state === 'object' && 'Running' in state;
 }
 
+export function isPaused(state: ProcessState): boolean {
+  return typeof state === 'object' && 'Paused' in state;
+}
+
 export function isStopped(state: ProcessState): boolean {
   return typeof state === 'object' && 'Stopped' in state;
 }
@@ -95,6 +195,7 @@ export function isNotStarted(state: ProcessState): boolean {
 
 export function getStateLabel(state: ProcessState): string {
   if (isRunning(state)) return 'Running';
+  if (isPaused(state)) return 'Paused';
   if (isStopped(state)) return 'Stopped';
   if (isFailed(state)) return 'Failed';
   if (isNotStarted(state)) return 'Not Started';
@@ -103,6 +204,7 @@ export function getStateLabel(state: ProcessState): string {
 
 export function getStateColor(state: ProcessState): string {
   if (isRunning(state)) return 'green';
+  if (isPaused(state)) return 'gray';
   if (isStopped(state)) return 'yellow';
   if (isFailed(state)) return 'red';
   if (isNotStarted(state)) return 'secondary';